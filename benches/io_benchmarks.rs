@@ -3,9 +3,10 @@
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
 use rustdb::common::types::PAGE_SIZE;
 use rustdb::storage::{
+    advanced_file_manager::AdvancedFileManager,
     database_file::{DatabaseFileType, ExtensionStrategy},
     io_optimization::{BufferedIoManager, IoBufferConfig},
-    optimized_file_manager::OptimizedFileManager,
+    optimized_file_manager::{MmapAdvice, OptimizedFileManager, ReadPath},
 };
 use tempfile::TempDir;
 
@@ -146,6 +147,61 @@ fn bench_optimized_file_manager_operations(c: &mut Criterion) {
     group.finish();
 }
 
+/// Benchmark: mmap read path vs reads through the file manager
+fn bench_mmap_read_path(c: &mut Criterion) {
+    const PAGES: u64 = 256;
+    let temp_dir = TempDir::new().unwrap();
+    let mut files = AdvancedFileManager::new(temp_dir.path()).unwrap();
+    let file_id = files
+        .create_database_file(
+            "mmap_bench.db",
+            DatabaseFileType::Data,
+            1,
+            ExtensionStrategy::Fixed,
+        )
+        .unwrap();
+    let first = files.allocate_pages(file_id, PAGES as u32).unwrap();
+    for page_id in first..first + PAGES {
+        files
+            .write_page(file_id, page_id, &vec![page_id as u8; PAGE_SIZE])
+            .unwrap();
+    }
+    files.sync_all().unwrap();
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mapped = OptimizedFileManager::with_read_path(
+        temp_dir.path(),
+        ReadPath::Mmap(MmapAdvice::Sequential),
+    )
+    .unwrap();
+    let mapped_id = runtime
+        .block_on(mapped.open_database_file("mmap_bench.db"))
+        .unwrap();
+
+    let mut group = c.benchmark_group("mmap_read_path");
+    group.throughput(Throughput::Bytes(PAGE_SIZE as u64 * PAGES));
+
+    group.bench_function("buffered_scan", |b| {
+        b.iter(|| {
+            for page_id in first..first + PAGES {
+                std::hint::black_box(files.read_page(file_id, page_id).unwrap());
+            }
+        });
+    });
+
+    group.bench_function("mmap_scan", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                for page_id in first..first + PAGES {
+                    std::hint::black_box(mapped.read_page(mapped_id, page_id).await.unwrap());
+                }
+            })
+        });
+    });
+
+    group.finish();
+}
+
 /// Benchmark: access patterns
 fn bench_access_patterns(c: &mut Criterion) {
     let mut group = c.benchmark_group("access_patterns");
@@ -239,6 +295,7 @@ criterion_group!(
     bench_basic_io_operations,
     bench_buffered_io_operations,
     bench_optimized_file_manager_operations,
+    bench_mmap_read_path,
     bench_access_patterns,
     bench_read_write_ratios
 );
@@ -31,7 +31,7 @@ fn main() -> Result<()> {
 
             if let Some(from_clause) = &select_stmt.from {
                 match &from_clause.table {
-                    rustdb::parser::TableReference::Table { name, alias } => {
+                    rustdb::parser::TableReference::Table { name, alias, .. } => {
                         println!("Table: {}", name);
                         if let Some(alias) = alias {
                             println!("Table alias: {}", alias);
@@ -40,6 +40,9 @@ fn main() -> Result<()> {
                     rustdb::parser::TableReference::Subquery { alias, .. } => {
                         println!("Subquery with alias: {}", alias);
                     }
+                    rustdb::parser::TableReference::Function { name, .. } => {
+                        println!("Table function: {}()", name);
+                    }
                 }
             }
         }
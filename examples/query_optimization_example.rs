@@ -97,6 +97,8 @@ fn demo_advanced_optimizer() -> Result<(), Box<dyn std::error::Error>> {
         enable_query_rewriting: true,
         enable_expression_simplification: true,
         enable_subquery_extraction: true,
+        enable_join_reordering: true,
+        max_join_relations: 10,
         enable_debug_logging: true,
         cost_threshold: 500.0,
     };
@@ -257,6 +259,7 @@ fn create_test_execution_plan() -> Result<ExecutionPlan, Box<dyn std::error::Err
         estimated_cost: 2100.0,
         estimated_rows: 1500,
         created_at: std::time::SystemTime::now(),
+        actual_rows: None,
         statistics: PlanStatistics {
             operator_count: 3,
             max_depth: 2,
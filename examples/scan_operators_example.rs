@@ -7,7 +7,7 @@ use rustdb::executor::{
 // removed unused imports
 use rustdb::common::Result;
 use rustdb::storage::index::BPlusTree;
-use rustdb::storage::page_manager::{PageManager, PageManagerConfig, PageManagerMutex};
+use rustdb::storage::page_manager::{PageManager, PageManagerConfig, PageManagerLock};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
@@ -15,7 +15,7 @@ fn main() -> Result<()> {
     println!("=== Example of using scan operators ===\n");
 
     // Creating a page manager
-    let page_manager = Arc::new(PageManagerMutex::new(PageManager::new(
+    let page_manager = Arc::new(PageManagerLock::new(PageManager::new(
         PathBuf::from("./data"),
         "users",
         PageManagerConfig::default(),
@@ -53,7 +53,7 @@ fn main() -> Result<()> {
 
     // Example 3: IndexScan operator
     println!("3. IndexScan operator:");
-    let index = Arc::new(Mutex::new(BPlusTree::new(4)));
+    let index = Arc::new(Mutex::new(BPlusTree::new(4).into()));
     let search_conditions = vec![IndexCondition {
         column: "id".to_string(),
         operator: IndexOperator::Equal,
@@ -120,7 +120,7 @@ fn main() -> Result<()> {
     let factory = ScanOperatorFactory::new(page_manager.clone());
 
     // Adding an index
-    let index_for_factory = Arc::new(Mutex::new(BPlusTree::new_default()));
+    let index_for_factory = Arc::new(Mutex::new(BPlusTree::new_default().into()));
     factory.add_index("users", "idx_users_id", index_for_factory);
 
     // Creating operators through a factory
@@ -0,0 +1,8 @@
+//! Lexer + parser over arbitrary bytes: any input must yield `Ok` or `Err`, never a panic.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = rustdb::parser::parse_unchecked(data);
+});
@@ -0,0 +1,11 @@
+//! WAL decoding over arbitrary bytes, as recovery would see a corrupt segment on disk.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rustdb::logging::log_record::LogRecord;
+
+fuzz_target!(|data: &[u8]| {
+    // Single record body and a full length-prefixed segment.
+    let _ = LogRecord::decode(data);
+    let _ = LogRecord::decode_stream(data);
+});
@@ -0,0 +1,87 @@
+//! One QUIC connection to a server.
+
+use crate::session::Session;
+use crate::{ClientConfig, ClientError, EngineError, EngineOutput, Result};
+use rustdb::network::client::{
+    authenticate, build_compressed_quinn_client_config, build_quinn_client_config, connect,
+    make_client_endpoint,
+};
+use rustdb::network::framing::ServerMessage;
+use rustdb::network::server::negotiated_compression;
+
+/// A connection; each [`Session`] opened on it is a separate server session.
+pub struct Client {
+    // Kept alive for as long as the connection: dropping the endpoint closes it.
+    _endpoint: quinn::Endpoint,
+    connection: quinn::Connection,
+    // Payload size from which requests are compressed, when the server agreed to compression.
+    compression_min_bytes: Option<usize>,
+}
+
+impl Client {
+    /// Dials the server, checks its certificate and authenticates when `config` has a user.
+    pub async fn connect(config: &ClientConfig) -> Result<Self> {
+        let addr = tokio::net::lookup_host(&config.address)
+            .await?
+            .next()
+            .ok_or_else(|| {
+                ClientError::Config(format!("address {} does not resolve", config.address))
+            })?;
+        let quinn_config = if config.compression {
+            build_compressed_quinn_client_config(&config.trusted_certs)?
+        } else {
+            build_quinn_client_config(&config.trusted_certs)?
+        };
+        let endpoint = make_client_endpoint(quinn_config)?;
+        let connection = connect(&endpoint, addr, &config.tls_name()).await?;
+        if let Some(user) = &config.user {
+            if let ServerMessage::Error(e) =
+                authenticate(&connection, user, config.password.as_deref()).await?
+            {
+                return Err(EngineError::new(e.code, e.message).into());
+            }
+        }
+        let compression_min_bytes =
+            negotiated_compression(&connection).then_some(config.compression_min_bytes);
+        Ok(Self {
+            _endpoint: endpoint,
+            connection,
+            compression_min_bytes,
+        })
+    }
+
+    /// [`Self::connect`] with [`ClientConfig::from_url`].
+    pub async fn connect_url(url: &str) -> Result<Self> {
+        Self::connect(&ClientConfig::from_url(url)?).await
+    }
+
+    /// Opens a new server session.
+    pub async fn session(&self) -> Result<Session> {
+        let (send, recv) = self
+            .connection
+            .open_bi()
+            .await
+            .map_err(rustdb::network::client::QuicClientError::from)?;
+        Ok(Session::new(send, recv, self.compression_min_bytes))
+    }
+
+    /// Runs one statement in a session of its own.
+    pub async fn execute(&self, sql: &str) -> Result<EngineOutput> {
+        self.session().await?.execute(sql).await
+    }
+
+    /// Whether the server agreed to compress frames on this connection.
+    pub fn is_compressed(&self) -> bool {
+        self.compression_min_bytes.is_some()
+    }
+
+    /// Whether the connection has been closed, by either side or by an idle timeout.
+    pub fn is_closed(&self) -> bool {
+        self.connection.close_reason().is_some()
+    }
+
+    /// Closes the connection; open sessions fail and the server rolls their transactions back.
+    pub fn close(&self) {
+        self.connection.close(0u32.into(), b"client closed");
+    }
+}
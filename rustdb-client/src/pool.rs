@@ -0,0 +1,129 @@
+//! Session pool over a shared connection.
+
+use crate::{Client, ClientConfig, Result, Session};
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Hands out up to [`ClientConfig::pool_size`] sessions at once and keeps returned ones open for
+/// reuse. Cloning is cheap; clones share the pool.
+#[derive(Clone)]
+pub struct Pool {
+    inner: Arc<PoolInner>,
+}
+
+struct PoolInner {
+    config: ClientConfig,
+    client: tokio::sync::Mutex<Option<Arc<Client>>>,
+    // Idle sessions, with the connection they belong to.
+    idle: Mutex<Vec<(Arc<Client>, Session)>>,
+    permits: Arc<Semaphore>,
+}
+
+impl Pool {
+    /// A pool that connects on first use.
+    pub fn new(config: ClientConfig) -> Self {
+        let permits = Arc::new(Semaphore::new(config.pool_size.max(1)));
+        Self {
+            inner: Arc::new(PoolInner {
+                config,
+                client: tokio::sync::Mutex::new(None),
+                idle: Mutex::new(Vec::new()),
+                permits,
+            }),
+        }
+    }
+
+    /// A pool for a server URL ([`ClientConfig::from_url`]), connected before it is returned
+    /// so a wrong address or certificate shows up here.
+    pub async fn connect(url: &str) -> Result<Self> {
+        let pool = Self::new(ClientConfig::from_url(url)?);
+        pool.client().await?;
+        Ok(pool)
+    }
+
+    pub fn config(&self) -> &ClientConfig {
+        &self.inner.config
+    }
+
+    /// Waits for a free slot, then reuses an idle session or opens a new one.
+    pub async fn get(&self) -> Result<PooledSession> {
+        let permit = self
+            .inner
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("the pool semaphore is never closed");
+        let client = self.client().await?;
+        let idle = {
+            let mut idle = self.inner.idle.lock().expect("pool lock");
+            idle.retain(|(owner, _)| Arc::ptr_eq(owner, &client));
+            idle.pop()
+        };
+        let session = match idle {
+            Some((_, session)) => session,
+            None => client.session().await?,
+        };
+        Ok(PooledSession {
+            pool: self.inner.clone(),
+            client,
+            session: Some(session),
+            _permit: permit,
+        })
+    }
+
+    /// Sessions kept open for reuse.
+    pub fn idle_sessions(&self) -> usize {
+        self.inner.idle.lock().expect("pool lock").len()
+    }
+
+    /// The shared connection, (re)connected when there is none or it was closed.
+    async fn client(&self) -> Result<Arc<Client>> {
+        let mut client = self.inner.client.lock().await;
+        match client.as_ref() {
+            Some(c) if !c.is_closed() => Ok(c.clone()),
+            _ => {
+                let connected = Arc::new(Client::connect(&self.inner.config).await?);
+                *client = Some(connected.clone());
+                Ok(connected)
+            }
+        }
+    }
+}
+
+/// A session borrowed from a [`Pool`]; dereferences to [`Session`]. On drop it goes back to the
+/// pool, unless it is inside a transaction or failed, in which case it is closed (the server
+/// rolls the transaction back).
+pub struct PooledSession {
+    pool: Arc<PoolInner>,
+    client: Arc<Client>,
+    session: Option<Session>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Deref for PooledSession {
+    type Target = Session;
+
+    fn deref(&self) -> &Session {
+        self.session.as_ref().expect("session present until drop")
+    }
+}
+
+impl DerefMut for PooledSession {
+    fn deref_mut(&mut self) -> &mut Session {
+        self.session.as_mut().expect("session present until drop")
+    }
+}
+
+impl Drop for PooledSession {
+    fn drop(&mut self) {
+        if let Some(session) = self.session.take() {
+            if session.reusable() && !self.client.is_closed() {
+                if let Ok(mut idle) = self.pool.idle.lock() {
+                    idle.push((self.client.clone(), session));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,358 @@
+//! Server sessions and prepared statements.
+
+use crate::{ClientError, EngineError, EngineOutput, FromRow, Result, ToValue};
+use quinn::{RecvStream, SendStream};
+use rustdb::common::types::DataType;
+use rustdb::network::client::{QuicClientError, COPY_DATA_BATCH_BYTES};
+use rustdb::network::engine::engine_error_code;
+use rustdb::network::framing::{
+    compress_frame, decode_server_frame_v1, encode_client_message_v1, ClientMessage,
+    CopyDataPayload, CopyInPayload, EncodeError, QueryPayload, QueryWithFormatPayload,
+    ResultFormat, ServerMessage, MAX_FRAME_PAYLOAD_BYTES,
+};
+use rustdb::network::query_stream::{read_application_frame, MAX_FRAMES_PER_STREAM};
+use rustdb::parser::{bind_params, placeholder_count, SqlParser};
+use rustdb::ResultRow;
+
+/// A statement parsed once, to run many times with different parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Statement {
+    sql: String,
+    params: usize,
+}
+
+impl Statement {
+    /// Checks that `sql` is one statement that parses (with its `?` placeholders as `NULL`).
+    pub fn prepare(sql: &str) -> Result<Self> {
+        let params = placeholder_count(sql)?;
+        let nulls = vec![&None::<i64> as &dyn ToValue; params];
+        SqlParser::new(&bind_params(sql, &nulls)?)?.parse()?;
+        Ok(Self {
+            sql: sql.to_string(),
+            params,
+        })
+    }
+
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    /// Number of `?` placeholders.
+    pub fn param_count(&self) -> usize {
+        self.params
+    }
+}
+
+/// Result set of [`Session::query_values`]: typed values, one per column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueRows {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<DataType>>,
+}
+
+/// One server session: a QUIC stream whose statements share transaction state.
+///
+/// Dropping a session ends it; the server rolls back its open transaction.
+pub struct Session {
+    send: SendStream,
+    recv: RecvStream,
+    frames_sent: usize,
+    in_transaction: bool,
+    broken: bool,
+    compression_min_bytes: Option<usize>,
+}
+
+impl Session {
+    pub(crate) fn new(
+        send: SendStream,
+        recv: RecvStream,
+        compression_min_bytes: Option<usize>,
+    ) -> Self {
+        Self {
+            send,
+            recv,
+            frames_sent: 0,
+            in_transaction: false,
+            broken: false,
+            compression_min_bytes,
+        }
+    }
+
+    /// Runs one statement.
+    pub async fn execute(&mut self, sql: &str) -> Result<EngineOutput> {
+        let mut outputs = self.pipeline(&[sql]).await?;
+        Ok(outputs.pop().expect("one answer per statement")?)
+    }
+
+    /// Runs one statement with its `?` placeholders bound to `params`.
+    pub async fn execute_params(
+        &mut self,
+        sql: &str,
+        params: &[&dyn ToValue],
+    ) -> Result<EngineOutput> {
+        let sql = bind_params(sql, params)?;
+        self.execute(&sql).await
+    }
+
+    /// Runs a [`Statement`] with `params`.
+    pub async fn execute_prepared(
+        &mut self,
+        statement: &Statement,
+        params: &[&dyn ToValue],
+    ) -> Result<EngineOutput> {
+        self.execute_params(&statement.sql, params).await
+    }
+
+    /// Runs a query with its rows in the binary result format, so values arrive typed instead
+    /// of as text to parse. A statement without a result set fails with
+    /// `engine_error_code::RESULT_MAPPING`.
+    pub async fn query_values(&mut self, sql: &str) -> Result<ValueRows> {
+        let request = ClientMessage::QueryWithFormat(QueryWithFormatPayload {
+            sql: sql.to_string(),
+            result_format: ResultFormat::Binary,
+        });
+        let answer = self
+            .exchange(&[sql], vec![request], 1)
+            .await?
+            .pop()
+            .expect("one answer per statement");
+        match answer {
+            ServerMessage::BinaryResultSet(p) => {
+                let rows = p.rows().map_err(QuicClientError::from)?;
+                Ok(ValueRows {
+                    columns: p.columns,
+                    rows,
+                })
+            }
+            answer => match output(answer)? {
+                EngineOutput::ResultSet { .. } => Err(EngineError::new(
+                    engine_error_code::PROTOCOL,
+                    "expected a binary result set",
+                )
+                .into()),
+                EngineOutput::ExecutionOk { .. } => Err(EngineError::new(
+                    engine_error_code::RESULT_MAPPING,
+                    "statement returned no result set",
+                )
+                .into()),
+            },
+        }
+    }
+
+    /// Runs a query and maps each result row into `T` (see [`FromRow`]); rows come in the
+    /// binary result format.
+    pub async fn query_as<T: FromRow>(&mut self, sql: &str) -> Result<Vec<T>> {
+        let result = self.query_values(sql).await?;
+        result
+            .rows
+            .into_iter()
+            .map(|values| {
+                T::from_row(&ResultRow::from_values(&result.columns, values)).map_err(|e| {
+                    EngineError::new(engine_error_code::RESULT_MAPPING, e.to_string()).into()
+                })
+            })
+            .collect()
+    }
+
+    /// Sends all of `sqls` before reading the answers, one per statement and in order, so a
+    /// batch costs one round trip. A failed statement does not stop the ones after it.
+    pub async fn pipeline(
+        &mut self,
+        sqls: &[&str],
+    ) -> Result<Vec<std::result::Result<EngineOutput, EngineError>>> {
+        let requests = sqls
+            .iter()
+            .map(|sql| {
+                ClientMessage::Query(QueryPayload {
+                    sql: sql.to_string(),
+                })
+            })
+            .collect();
+        Ok(self
+            .exchange(sqls, requests, sqls.len())
+            .await?
+            .into_iter()
+            .map(output)
+            .collect())
+    }
+
+    /// Bulk-loads `rows` into `table` with the COPY protocol and returns the rows loaded.
+    ///
+    /// `columns` names the columns each row gives values for; empty means all, in table order.
+    /// The rows travel in the binary row encoding and the server inserts them in batches, which
+    /// is much faster than an `INSERT` per row. Outside a transaction the load is atomic.
+    pub async fn copy_in(
+        &mut self,
+        table: &str,
+        columns: &[&str],
+        rows: &[Vec<DataType>],
+    ) -> Result<u64> {
+        let column_count = rows.first().map_or(columns.len(), Vec::len);
+        let requests = std::iter::once(ClientMessage::CopyIn(CopyInPayload {
+            table: table.to_string(),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+        }))
+        .chain(
+            CopyDataPayload::batches(column_count, rows, COPY_DATA_BATCH_BYTES)
+                .into_iter()
+                .map(ClientMessage::CopyData),
+        )
+        .chain(std::iter::once(ClientMessage::CopyDone))
+        .collect();
+        let answer = self
+            .exchange(&[], requests, 1)
+            .await?
+            .pop()
+            .expect("one answer per COPY");
+        match output(answer)? {
+            EngineOutput::ExecutionOk { rows_affected } => Ok(rows_affected),
+            EngineOutput::ResultSet { .. } => Err(EngineError::new(
+                engine_error_code::PROTOCOL,
+                "unexpected result set for a COPY",
+            )
+            .into()),
+        }
+    }
+
+    /// Sends `requests` (the statements `sqls`) and reads the `expected` answers to them.
+    async fn exchange(
+        &mut self,
+        sqls: &[&str],
+        requests: Vec<ClientMessage>,
+        expected: usize,
+    ) -> Result<Vec<ServerMessage>> {
+        if self.broken {
+            return Err(ClientError::Session(
+                "an earlier transport error ended this session".to_string(),
+            ));
+        }
+        // The server does not count a COPY's data frames against its per-stream cap.
+        let counted = requests
+            .iter()
+            .filter(|r| !matches!(r, ClientMessage::CopyData(_)))
+            .count();
+        if self.frames_sent + counted > MAX_FRAMES_PER_STREAM {
+            return Err(ClientError::Session(format!(
+                "a session carries at most {MAX_FRAMES_PER_STREAM} statements; open a new one"
+            )));
+        }
+        let frames = requests
+            .iter()
+            .map(|request| {
+                let frame = encode_client_message_v1(request)?;
+                Ok(self
+                    .compression_min_bytes
+                    .and_then(|min| compress_frame(&frame, min))
+                    .unwrap_or(frame))
+            })
+            .collect::<std::result::Result<Vec<_>, EncodeError>>()
+            .map_err(QuicClientError::from)?;
+        self.frames_sent += counted;
+
+        // Write and read at once: the server answers while the batch is still arriving, and
+        // unread answers would otherwise stall it once flow control fills up.
+        let send = &mut self.send;
+        let recv = &mut self.recv;
+        let write = async {
+            for frame in &frames {
+                send.write_all(frame).await.map_err(QuicClientError::from)?;
+            }
+            Ok::<_, ClientError>(())
+        };
+        let read = async {
+            let mut answers = Vec::with_capacity(expected);
+            for _ in 0..expected {
+                let frame = read_application_frame(recv, MAX_FRAME_PAYLOAD_BYTES)
+                    .await
+                    .map_err(QuicClientError::from)?;
+                answers.push(decode_server_frame_v1(&frame).map_err(QuicClientError::from)?);
+            }
+            Ok::<_, ClientError>(answers)
+        };
+        let answers = match tokio::try_join!(write, read) {
+            Ok(((), answers)) => answers,
+            Err(e) => {
+                self.broken = true;
+                return Err(e);
+            }
+        };
+
+        for (sql, answer) in sqls.iter().zip(&answers) {
+            if !matches!(answer, ServerMessage::Error(_)) {
+                if let Some(open) = transaction_effect(sql) {
+                    self.in_transaction = open;
+                }
+            }
+        }
+        Ok(answers)
+    }
+
+    /// Whether a transaction started in this session is still open, as far as the client can
+    /// tell from the statements it sent.
+    pub fn in_transaction(&self) -> bool {
+        self.in_transaction
+    }
+
+    /// Whether the session can go back to a pool: usable, outside a transaction and with room
+    /// for more statements.
+    pub(crate) fn reusable(&self) -> bool {
+        !self.broken && !self.in_transaction && self.frames_sent < MAX_FRAMES_PER_STREAM
+    }
+}
+
+fn output(answer: ServerMessage) -> std::result::Result<EngineOutput, EngineError> {
+    match answer {
+        ServerMessage::ResultSet(p) => Ok(EngineOutput::ResultSet {
+            columns: p.columns,
+            rows: p.rows,
+        }),
+        ServerMessage::ExecutionOk(p) => Ok(EngineOutput::ExecutionOk {
+            rows_affected: p.rows_affected,
+        }),
+        ServerMessage::Error(e) => Err(EngineError::new(e.code, e.message)),
+        other => Err(EngineError::new(
+            engine_error_code::PROTOCOL,
+            format!("unexpected response {:?}", other),
+        )),
+    }
+}
+
+/// `Some(true)` for a statement that opens a transaction, `Some(false)` for one that ends it.
+fn transaction_effect(sql: &str) -> Option<bool> {
+    let mut words = sql
+        .split(|c: char| c.is_whitespace() || c == ';')
+        .filter(|w| !w.is_empty())
+        .map(str::to_ascii_uppercase);
+    match words.next()?.as_str() {
+        "BEGIN" | "START" => Some(true),
+        "COMMIT" | "END" => Some(false),
+        "ROLLBACK" => match words.next().as_deref() {
+            Some("TO") => None,
+            _ => Some(false),
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statements_are_checked_when_prepared() {
+        let statement =
+            Statement::prepare("SELECT name FROM users WHERE id = ? AND age > ?").unwrap();
+        assert_eq!(statement.param_count(), 2);
+        assert!(Statement::prepare("SELEC name FROM users").is_err());
+        assert!(Statement::prepare("SELECT ?; DROP TABLE users").is_err());
+    }
+
+    #[test]
+    fn transaction_boundaries_follow_the_leading_keywords() {
+        assert_eq!(transaction_effect("begin transaction"), Some(true));
+        assert_eq!(transaction_effect("COMMIT;"), Some(false));
+        assert_eq!(transaction_effect("ROLLBACK"), Some(false));
+        assert_eq!(transaction_effect("ROLLBACK TO SAVEPOINT a"), None);
+        assert_eq!(transaction_effect("SELECT 1"), None);
+    }
+}
@@ -0,0 +1,159 @@
+//! `rustdb-client` against a QUIC server over a real [`SqlEngine`].
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use rustdb::network::engine::{engine_error_code, EngineOutput};
+use rustdb::network::server::{QuicServer, ServerConfig};
+use rustdb::network::SqlEngine;
+use rustdb::FromRow;
+use rustdb_client::{Client, ClientConfig, ClientError, DataType, Pool, Statement};
+use tempfile::TempDir;
+
+#[derive(Debug, PartialEq, FromRow)]
+struct Item {
+    id: i64,
+    name: String,
+}
+
+fn spawn_server(dir: &TempDir) -> (ClientConfig, tokio::task::JoinHandle<()>) {
+    let engine = Arc::new(SqlEngine::open(dir.path().to_path_buf()).expect("engine"));
+    let srv = Arc::new(
+        QuicServer::bind(ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 0,
+            query_timeout: Duration::from_secs(10),
+            ..Default::default()
+        })
+        .expect("bind"),
+    );
+    let config = ClientConfig::new(
+        srv.local_addr().expect("local addr").to_string(),
+        srv.pinned_certificate().clone(),
+    )
+    .with_server_name("127.0.0.1")
+    .with_pool_size(2);
+    let run_task = tokio::spawn(async move {
+        let _ = srv.run(engine).await;
+    });
+    (config, run_task)
+}
+
+fn row_count(out: EngineOutput) -> usize {
+    match out {
+        EngineOutput::ResultSet { rows, .. } => rows.len(),
+        other => panic!("{other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn pooled_sessions_run_prepared_and_pipelined_statements() {
+    let dir = TempDir::new().unwrap();
+    let (config, run_task) = spawn_server(&dir);
+    let pool = Pool::new(config);
+
+    let mut session = pool.get().await.unwrap();
+    session
+        .execute("CREATE TABLE items (id INTEGER PRIMARY KEY, name VARCHAR(32))")
+        .await
+        .unwrap();
+    let insert = Statement::prepare("INSERT INTO items (id, name) VALUES (?, ?)").unwrap();
+    for (id, name) in [(1, "pen"), (2, "o'clock")] {
+        session
+            .execute_prepared(&insert, &[&id, &name])
+            .await
+            .unwrap();
+    }
+    assert!(session.execute_prepared(&insert, &[&3]).await.is_err());
+
+    let mut items: Vec<Item> = session
+        .query_as("SELECT id, name FROM items")
+        .await
+        .unwrap();
+    items.sort_by_key(|i| i.id);
+    assert_eq!(items[1].name, "o'clock");
+    let values = session
+        .query_values("SELECT id, name FROM items WHERE id = 1")
+        .await
+        .unwrap();
+    assert_eq!(values.columns, vec!["id", "name"]);
+    assert_eq!(
+        values.rows,
+        vec![vec![DataType::Integer(1), DataType::Varchar("pen".into())]]
+    );
+
+    let answers = session
+        .pipeline(&[
+            "INSERT INTO items (id, name) VALUES (3, 'cup')",
+            "INSERT INTO items (id, name) VALUES (1, 'duplicate')",
+            "SELECT id FROM items",
+        ])
+        .await
+        .unwrap();
+    assert_eq!(answers.len(), 3);
+    assert!(answers[0].is_ok());
+    assert_eq!(
+        answers[1].as_ref().unwrap_err().code,
+        engine_error_code::CONSTRAINT_VIOLATION
+    );
+    assert_eq!(row_count(answers[2].clone().unwrap()), 3);
+    drop(session);
+    assert_eq!(pool.idle_sessions(), 1);
+
+    // A session dropped inside a transaction is closed, and the server rolls it back.
+    let mut session = pool.get().await.unwrap();
+    assert_eq!(pool.idle_sessions(), 0);
+    session.execute("BEGIN TRANSACTION").await.unwrap();
+    session
+        .execute("INSERT INTO items (id, name) VALUES (4, 'lost')")
+        .await
+        .unwrap();
+    assert!(session.in_transaction());
+    drop(session);
+    assert_eq!(pool.idle_sessions(), 0);
+
+    let mut session = pool.get().await.unwrap();
+    let mut checked = false;
+    for _ in 0..50 {
+        if row_count(session.execute("SELECT id FROM items").await.unwrap()) == 3 {
+            checked = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert!(checked, "the dropped transaction was not rolled back");
+
+    let client = Client::connect(
+        &pool
+            .config()
+            .clone()
+            .with_compression_min_bytes(16)
+            .with_compression(true),
+    )
+    .await
+    .unwrap();
+    assert!(client.is_compressed());
+    let mut session = client.session().await.unwrap();
+    let values = session.query_values("SELECT id FROM items").await.unwrap();
+    assert_eq!(values.rows.len(), 3);
+
+    session
+        .execute("CREATE TABLE bulk (n INTEGER, label TEXT)")
+        .await
+        .unwrap();
+    let rows: Vec<Vec<DataType>> = (0..2000)
+        .map(|i| vec![DataType::Integer(i), DataType::Text(format!("row {i}"))])
+        .collect();
+    assert_eq!(
+        session
+            .copy_in("bulk", &["n", "label"], &rows)
+            .await
+            .unwrap(),
+        2000
+    );
+    let values = session.query_values("SELECT n FROM bulk").await.unwrap();
+    assert_eq!(values.rows.len(), 2000);
+    let err = session.copy_in("bulk", &["n"], &rows).await.unwrap_err();
+    assert!(matches!(err, ClientError::Server(ref e) if e.code == engine_error_code::PROTOCOL));
+    run_task.abort();
+}
@@ -0,0 +1,80 @@
+//! `#[derive(FromRow)]` for `rustdb::FromRow`: each named field is read from the result column
+//! of the same name (ASCII case-insensitive) with `rustdb::FromValue`.
+//!
+//! `#[rustdb(rename = "column")]` reads a field from another column; use `Option<T>` for
+//! nullable columns.
+//!
+//! ```ignore
+//! #[derive(rustdb::FromRow)]
+//! struct User {
+//!     id: i64,
+//!     #[rustdb(rename = "user_name")]
+//!     name: String,
+//!     email: Option<String>,
+//! }
+//!
+//! let users: Vec<User> = conn.query_as("SELECT id, user_name, email FROM users")?;
+//! ```
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr};
+
+#[proc_macro_derive(FromRow, attributes(rustdb))]
+pub fn derive_from_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    from_row(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn from_row(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "FromRow needs a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "FromRow can only be derived for structs",
+            ))
+        }
+    };
+    let mut reads = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+        let column = column_name(field)?.unwrap_or_else(|| ident.to_string());
+        reads.push(quote! { #ident: row.get(#column)? });
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::rustdb::FromRow for #name #ty_generics #where_clause {
+            fn from_row(row: &::rustdb::ResultRow<'_>) -> ::rustdb::Result<Self> {
+                Ok(Self { #(#reads),* })
+            }
+        }
+    })
+}
+
+/// The column named by `#[rustdb(rename = "...")]`, if any.
+fn column_name(field: &syn::Field) -> syn::Result<Option<String>> {
+    let mut column = None;
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("rustdb")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                column = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("unknown rustdb attribute (expected `rename`)"))
+            }
+        })?;
+    }
+    Ok(column)
+}
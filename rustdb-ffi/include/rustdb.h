@@ -0,0 +1,61 @@
+/*
+ * rustdb C API (see rustdb-ffi/src/lib.rs for the full contract).
+ *
+ * Build: `cargo build -p rustdb-ffi --release` produces librustdb_ffi.{so,dylib,a}
+ * (rustdb_ffi.dll / .lib on Windows) under target/release.
+ *
+ * Handles are opaque. A rustdb_db carries one session; use it from one thread at a time.
+ * Returned strings are owned by the library: column text is valid until the next
+ * rustdb_step / rustdb_finalize on that statement, error messages until the next call on
+ * that database handle.
+ */
+#ifndef RUSTDB_H
+#define RUSTDB_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Status codes (stable). */
+#define RUSTDB_OK 0
+#define RUSTDB_ERROR 1    /* SQL / engine error: see rustdb_errmsg, rustdb_errcode */
+#define RUSTDB_MISUSE 2   /* null pointer, invalid UTF-8, bad flags, ... */
+#define RUSTDB_BUSY 3     /* rustdb_close with unfinalized statements */
+#define RUSTDB_INTERNAL 4 /* internal error (panic caught at the boundary) */
+#define RUSTDB_CANTOPEN 5 /* rustdb_open could not open the data directory */
+#define RUSTDB_ROW 100    /* rustdb_step: a row is available */
+#define RUSTDB_DONE 101   /* rustdb_step: statement finished */
+
+/* rustdb_open flags. */
+#define RUSTDB_OPEN_DEFAULT 0 /* safe-by-default: commit waits for fsync */
+#define RUSTDB_OPEN_FAST 1    /* relaxed durability */
+
+typedef struct RustdbDb rustdb_db;
+typedef struct RustdbStmt rustdb_stmt;
+
+int rustdb_open(const char *path, int flags, rustdb_db **out_db);
+int rustdb_close(rustdb_db *db);
+
+int rustdb_exec(rustdb_db *db, const char *sql, uint64_t *rows_affected);
+
+int rustdb_prepare(rustdb_db *db, const char *sql, rustdb_stmt **out_stmt);
+int rustdb_step(rustdb_stmt *stmt);
+int rustdb_column_count(const rustdb_stmt *stmt);
+const char *rustdb_column_name(const rustdb_stmt *stmt, int i);
+const char *rustdb_column_text(const rustdb_stmt *stmt, int i);
+uint64_t rustdb_changes(const rustdb_stmt *stmt);
+int rustdb_finalize(rustdb_stmt *stmt);
+
+int rustdb_errcode(const rustdb_db *db);
+const char *rustdb_errmsg(const rustdb_db *db);
+
+int rustdb_checkpoint(rustdb_db *db);
+const char *rustdb_version(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RUSTDB_H */
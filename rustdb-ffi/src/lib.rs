@@ -0,0 +1,552 @@
+//! C ABI for embedding rustdb from other languages.
+//!
+//! The API follows SQLite's shape so existing binding patterns (Python `ctypes`, Go `cgo`,
+//! C++ RAII wrappers) carry over:
+//!
+//! ```c
+//! rustdb_db *db;
+//! if (rustdb_open("./data", RUSTDB_OPEN_DEFAULT, &db) != RUSTDB_OK) { ... }
+//!
+//! rustdb_stmt *stmt;
+//! rustdb_prepare(db, "SELECT id, name FROM users", &stmt);
+//! while (rustdb_step(stmt) == RUSTDB_ROW) {
+//!     printf("%s %s\n", rustdb_column_text(stmt, 0), rustdb_column_text(stmt, 1));
+//! }
+//! rustdb_finalize(stmt);
+//! rustdb_close(db);
+//! ```
+//!
+//! # ABI contract
+//! - Every function returns one of the `RUSTDB_*` status codes (or a value documented
+//!   otherwise); the numeric values are stable. The engine's own error code (e.g. `2005`
+//!   for a constraint violation) is available from [`rustdb_errcode`].
+//! - A `rustdb_db` carries one session (transaction state). It may move between threads
+//!   but must not be used from two threads at once; open one handle per thread instead.
+//! - Strings returned by the library are owned by it: column text stays valid until the
+//!   next [`rustdb_step`] / [`rustdb_finalize`] on that statement, error messages until the
+//!   next call on that database handle.
+//! - Panics never cross the boundary; they are reported as [`RUSTDB_INTERNAL`].
+//!
+//! The matching header is `include/rustdb.h`.
+
+use rustdb::embedded::{Config, Connection, Db};
+use rustdb::network::engine::{engine_error_code, EngineError, EngineOutput};
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+/// Success
+pub const RUSTDB_OK: c_int = 0;
+/// SQL or engine error; see [`rustdb_errmsg`] / [`rustdb_errcode`]
+pub const RUSTDB_ERROR: c_int = 1;
+/// API misuse: null pointer, invalid UTF-8, out-of-range column, ...
+pub const RUSTDB_MISUSE: c_int = 2;
+/// [`rustdb_close`] called while statements are still open
+pub const RUSTDB_BUSY: c_int = 3;
+/// Internal error (a panic was caught at the boundary)
+pub const RUSTDB_INTERNAL: c_int = 4;
+/// [`rustdb_open`] failed (bad path, unreadable data directory, recovery error)
+pub const RUSTDB_CANTOPEN: c_int = 5;
+/// [`rustdb_step`] produced a row
+pub const RUSTDB_ROW: c_int = 100;
+/// [`rustdb_step`] finished executing the statement
+pub const RUSTDB_DONE: c_int = 101;
+
+/// [`rustdb_open`] flag: safe-by-default configuration (commit waits for fsync)
+pub const RUSTDB_OPEN_DEFAULT: c_int = 0;
+/// [`rustdb_open`] flag: [`Config::fast`] (relaxed durability)
+pub const RUSTDB_OPEN_FAST: c_int = 1;
+
+/// Opaque database handle (one engine + one session)
+pub struct RustdbDb {
+    db: Db,
+    conn: Connection,
+    open_statements: usize,
+    errcode: c_int,
+    errmsg: CString,
+}
+
+/// Opaque prepared statement
+pub struct RustdbStmt {
+    db: *mut RustdbDb,
+    sql: String,
+    state: StmtState,
+}
+
+enum StmtState {
+    /// Not executed yet; the first `rustdb_step` runs it
+    Pending,
+    /// Executed; rows are handed out one per step
+    Running {
+        columns: Vec<CString>,
+        rows: std::vec::IntoIter<Vec<String>>,
+        current: Vec<CString>,
+        changes: u64,
+    },
+    Done {
+        columns: Vec<CString>,
+        changes: u64,
+    },
+}
+
+/// Converts to a C string, dropping interior NULs rather than failing.
+fn to_cstring(s: &str) -> CString {
+    CString::new(s.replace('\0', "")).unwrap_or_default()
+}
+
+/// Runs an entry point, turning a panic into [`RUSTDB_INTERNAL`].
+fn guard(f: impl FnOnce() -> c_int) -> c_int {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or(RUSTDB_INTERNAL)
+}
+
+unsafe fn str_arg<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        return None;
+    }
+    CStr::from_ptr(s).to_str().ok()
+}
+
+impl RustdbDb {
+    fn clear_error(&mut self) {
+        self.errcode = 0;
+        self.errmsg = CString::default();
+    }
+
+    fn set_error(&mut self, err: &EngineError) -> c_int {
+        self.errcode = err.code as c_int;
+        self.errmsg = to_cstring(&err.message);
+        RUSTDB_ERROR
+    }
+
+    fn set_misuse(&mut self, message: &str) -> c_int {
+        self.errcode = engine_error_code::PROTOCOL as c_int;
+        self.errmsg = to_cstring(message);
+        RUSTDB_MISUSE
+    }
+}
+
+/// Opens (or creates) a database rooted at the directory `path`.
+///
+/// `flags` is [`RUSTDB_OPEN_DEFAULT`] or [`RUSTDB_OPEN_FAST`]. On success `*out_db` receives
+/// a handle to release with [`rustdb_close`]; on failure it is set to NULL.
+///
+/// # Safety
+/// `path` must be a NUL-terminated string and `out_db` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn rustdb_open(
+    path: *const c_char,
+    flags: c_int,
+    out_db: *mut *mut RustdbDb,
+) -> c_int {
+    guard(|| {
+        if out_db.is_null() {
+            return RUSTDB_MISUSE;
+        }
+        *out_db = ptr::null_mut();
+        let Some(path) = str_arg(path) else {
+            return RUSTDB_MISUSE;
+        };
+        let config = match flags {
+            RUSTDB_OPEN_DEFAULT => Config::default(),
+            RUSTDB_OPEN_FAST => Config::fast(),
+            _ => return RUSTDB_MISUSE,
+        };
+        match Db::open(path, config) {
+            Ok(db) => {
+                let conn = db.connect();
+                *out_db = Box::into_raw(Box::new(RustdbDb {
+                    db,
+                    conn,
+                    open_statements: 0,
+                    errcode: 0,
+                    errmsg: CString::default(),
+                }));
+                RUSTDB_OK
+            }
+            Err(_) => RUSTDB_CANTOPEN,
+        }
+    })
+}
+
+/// Closes a database handle. An open transaction is rolled back.
+///
+/// Returns [`RUSTDB_BUSY`] (and keeps the handle open) while statements from this handle
+/// have not been finalized. Closing NULL is a no-op.
+///
+/// # Safety
+/// `db` must be NULL or a handle returned by [`rustdb_open`] that was not closed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rustdb_close(db: *mut RustdbDb) -> c_int {
+    guard(|| {
+        if db.is_null() {
+            return RUSTDB_OK;
+        }
+        if (*db).open_statements > 0 {
+            (*db).set_misuse("unfinalized statements remain");
+            return RUSTDB_BUSY;
+        }
+        drop(Box::from_raw(db));
+        RUSTDB_OK
+    })
+}
+
+/// Executes one statement and discards any result rows.
+///
+/// `rows_affected` may be NULL; otherwise it receives the DML row count (0 for queries/DDL).
+///
+/// # Safety
+/// `db` must be a live handle and `sql` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rustdb_exec(
+    db: *mut RustdbDb,
+    sql: *const c_char,
+    rows_affected: *mut u64,
+) -> c_int {
+    guard(|| {
+        let Some(db) = db.as_mut() else {
+            return RUSTDB_MISUSE;
+        };
+        db.clear_error();
+        let Some(sql) = str_arg(sql) else {
+            return db.set_misuse("sql must be a non-null UTF-8 string");
+        };
+        match db.conn.execute(sql) {
+            Ok(out) => {
+                if !rows_affected.is_null() {
+                    *rows_affected = match out {
+                        EngineOutput::ExecutionOk { rows_affected } => rows_affected,
+                        EngineOutput::ResultSet { .. } => 0,
+                    };
+                }
+                RUSTDB_OK
+            }
+            Err(e) => db.set_error(&e),
+        }
+    })
+}
+
+/// Prepares a statement. Execution happens on the first [`rustdb_step`].
+///
+/// # Safety
+/// `db` must be a live handle, `sql` a NUL-terminated string and `out_stmt` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn rustdb_prepare(
+    db: *mut RustdbDb,
+    sql: *const c_char,
+    out_stmt: *mut *mut RustdbStmt,
+) -> c_int {
+    guard(|| {
+        let Some(db_ref) = db.as_mut() else {
+            return RUSTDB_MISUSE;
+        };
+        db_ref.clear_error();
+        if out_stmt.is_null() {
+            return db_ref.set_misuse("out_stmt must not be null");
+        }
+        *out_stmt = ptr::null_mut();
+        let Some(sql) = str_arg(sql) else {
+            return db_ref.set_misuse("sql must be a non-null UTF-8 string");
+        };
+        if sql.trim().is_empty() {
+            return db_ref.set_misuse("sql must not be empty");
+        }
+        db_ref.open_statements += 1;
+        *out_stmt = Box::into_raw(Box::new(RustdbStmt {
+            db,
+            sql: sql.to_string(),
+            state: StmtState::Pending,
+        }));
+        RUSTDB_OK
+    })
+}
+
+/// Advances a statement: [`RUSTDB_ROW`] when a row is available, [`RUSTDB_DONE`] when
+/// finished, [`RUSTDB_ERROR`] if execution failed.
+///
+/// # Safety
+/// `stmt` must be a live statement whose database handle is still open.
+#[no_mangle]
+pub unsafe extern "C" fn rustdb_step(stmt: *mut RustdbStmt) -> c_int {
+    guard(|| {
+        let Some(stmt) = stmt.as_mut() else {
+            return RUSTDB_MISUSE;
+        };
+        let db = &mut *stmt.db;
+        if matches!(stmt.state, StmtState::Pending) {
+            db.clear_error();
+            stmt.state = match db.conn.execute(&stmt.sql) {
+                Ok(EngineOutput::ResultSet { columns, rows }) => StmtState::Running {
+                    columns: columns.iter().map(|c| to_cstring(c)).collect(),
+                    rows: rows.into_iter(),
+                    current: Vec::new(),
+                    changes: 0,
+                },
+                Ok(EngineOutput::ExecutionOk { rows_affected }) => StmtState::Done {
+                    columns: Vec::new(),
+                    changes: rows_affected,
+                },
+                Err(e) => {
+                    stmt.state = StmtState::Done {
+                        columns: Vec::new(),
+                        changes: 0,
+                    };
+                    return db.set_error(&e);
+                }
+            };
+        }
+        match &mut stmt.state {
+            StmtState::Running {
+                columns,
+                rows,
+                current,
+                changes,
+            } => match rows.next() {
+                Some(row) => {
+                    *current = row.iter().map(|v| to_cstring(v)).collect();
+                    RUSTDB_ROW
+                }
+                None => {
+                    stmt.state = StmtState::Done {
+                        columns: std::mem::take(columns),
+                        changes: *changes,
+                    };
+                    RUSTDB_DONE
+                }
+            },
+            StmtState::Done { .. } => RUSTDB_DONE,
+            StmtState::Pending => RUSTDB_INTERNAL,
+        }
+    })
+}
+
+/// Number of result columns (0 for DML/DDL or before the first step).
+///
+/// # Safety
+/// `stmt` must be NULL or a live statement.
+#[no_mangle]
+pub unsafe extern "C" fn rustdb_column_count(stmt: *const RustdbStmt) -> c_int {
+    match stmt.as_ref().map(|s| &s.state) {
+        Some(StmtState::Running { columns, .. }) | Some(StmtState::Done { columns, .. }) => {
+            columns.len() as c_int
+        }
+        _ => 0,
+    }
+}
+
+/// Name of result column `i`, or NULL when out of range.
+///
+/// # Safety
+/// `stmt` must be NULL or a live statement.
+#[no_mangle]
+pub unsafe extern "C" fn rustdb_column_name(stmt: *const RustdbStmt, i: c_int) -> *const c_char {
+    match stmt.as_ref().map(|s| &s.state) {
+        Some(StmtState::Running { columns, .. }) | Some(StmtState::Done { columns, .. }) => {
+            usize::try_from(i)
+                .ok()
+                .and_then(|i| columns.get(i))
+                .map_or(ptr::null(), |c| c.as_ptr())
+        }
+        _ => ptr::null(),
+    }
+}
+
+/// Text of column `i` in the current row, or NULL when there is no current row or `i` is
+/// out of range. Cells use the engine's result-set rendering (the same text the QUIC
+/// protocol carries, e.g. `Integer(1)`).
+///
+/// # Safety
+/// `stmt` must be NULL or a live statement.
+#[no_mangle]
+pub unsafe extern "C" fn rustdb_column_text(stmt: *const RustdbStmt, i: c_int) -> *const c_char {
+    match stmt.as_ref().map(|s| &s.state) {
+        Some(StmtState::Running { current, .. }) => usize::try_from(i)
+            .ok()
+            .and_then(|i| current.get(i))
+            .map_or(ptr::null(), |c| c.as_ptr()),
+        _ => ptr::null(),
+    }
+}
+
+/// Rows affected by a finished DML statement (0 otherwise).
+///
+/// # Safety
+/// `stmt` must be NULL or a live statement.
+#[no_mangle]
+pub unsafe extern "C" fn rustdb_changes(stmt: *const RustdbStmt) -> u64 {
+    match stmt.as_ref().map(|s| &s.state) {
+        Some(StmtState::Running { changes, .. }) | Some(StmtState::Done { changes, .. }) => {
+            *changes
+        }
+        _ => 0,
+    }
+}
+
+/// Releases a statement. Finalizing NULL is a no-op.
+///
+/// # Safety
+/// `stmt` must be NULL or a live statement; it must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rustdb_finalize(stmt: *mut RustdbStmt) -> c_int {
+    guard(|| {
+        if stmt.is_null() {
+            return RUSTDB_OK;
+        }
+        let stmt = Box::from_raw(stmt);
+        if let Some(db) = stmt.db.as_mut() {
+            db.open_statements = db.open_statements.saturating_sub(1);
+        }
+        RUSTDB_OK
+    })
+}
+
+/// Engine error code of the last failed call on `db` (0 if it succeeded), e.g. `2005`
+/// for a constraint violation. See `rustdb::network::engine::engine_error_code`.
+///
+/// # Safety
+/// `db` must be NULL or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn rustdb_errcode(db: *const RustdbDb) -> c_int {
+    db.as_ref().map_or(0, |db| db.errcode)
+}
+
+/// Message for the last failed call on `db` (empty string if it succeeded).
+///
+/// # Safety
+/// `db` must be NULL or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn rustdb_errmsg(db: *const RustdbDb) -> *const c_char {
+    match db.as_ref() {
+        Some(db) => db.errmsg.as_ptr(),
+        None => c"invalid database handle".as_ptr(),
+    }
+}
+
+/// Runs a manual checkpoint on the database.
+///
+/// # Safety
+/// `db` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn rustdb_checkpoint(db: *mut RustdbDb) -> c_int {
+    guard(|| {
+        let Some(db) = db.as_mut() else {
+            return RUSTDB_MISUSE;
+        };
+        db.clear_error();
+        match db.db.checkpoint() {
+            Ok(()) => RUSTDB_OK,
+            Err(e) => db.set_error(&EngineError::new(
+                engine_error_code::INTERNAL,
+                e.to_string(),
+            )),
+        }
+    })
+}
+
+/// Library version (static string).
+#[no_mangle]
+pub extern "C" fn rustdb_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(dir: &tempfile::TempDir) -> *mut RustdbDb {
+        let path = CString::new(dir.path().to_str().unwrap()).unwrap();
+        let mut db = ptr::null_mut();
+        assert_eq!(
+            unsafe { rustdb_open(path.as_ptr(), RUSTDB_OPEN_FAST, &mut db) },
+            RUSTDB_OK
+        );
+        assert!(!db.is_null());
+        db
+    }
+
+    fn text(p: *const c_char) -> String {
+        assert!(!p.is_null());
+        unsafe { CStr::from_ptr(p) }.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_exec_prepare_step_finalize() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open(&dir);
+        unsafe {
+            let mut n = 0u64;
+            let sql = c"CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT)";
+            assert_eq!(rustdb_exec(db, sql.as_ptr(), ptr::null_mut()), RUSTDB_OK);
+            let sql = c"INSERT INTO t (id, name) VALUES (1, 'a'), (2, 'b')";
+            assert_eq!(rustdb_exec(db, sql.as_ptr(), &mut n), RUSTDB_OK);
+            assert_eq!(n, 2);
+
+            let mut stmt = ptr::null_mut();
+            let sql = c"SELECT id, name FROM t ORDER BY id";
+            assert_eq!(rustdb_prepare(db, sql.as_ptr(), &mut stmt), RUSTDB_OK);
+            assert_eq!(rustdb_column_count(stmt), 0);
+            assert_eq!(rustdb_step(stmt), RUSTDB_ROW);
+            assert_eq!(rustdb_column_count(stmt), 2);
+            assert_eq!(text(rustdb_column_name(stmt, 1)), "name");
+            // Cells use the engine's text rendering, as on the wire.
+            assert_eq!(text(rustdb_column_text(stmt, 0)), "Integer(1)");
+            assert!(rustdb_column_text(stmt, 2).is_null());
+            assert_eq!(rustdb_step(stmt), RUSTDB_ROW);
+            assert!(text(rustdb_column_text(stmt, 1)).contains('b'));
+            assert_eq!(rustdb_step(stmt), RUSTDB_DONE);
+            assert_eq!(rustdb_step(stmt), RUSTDB_DONE);
+
+            // Statements pin the handle until finalized.
+            assert_eq!(rustdb_close(db), RUSTDB_BUSY);
+            assert_eq!(rustdb_finalize(stmt), RUSTDB_OK);
+
+            let mut stmt = ptr::null_mut();
+            let sql = c"DELETE FROM t WHERE id = 1";
+            assert_eq!(rustdb_prepare(db, sql.as_ptr(), &mut stmt), RUSTDB_OK);
+            assert_eq!(rustdb_step(stmt), RUSTDB_DONE);
+            assert_eq!(rustdb_changes(stmt), 1);
+            assert_eq!(rustdb_finalize(stmt), RUSTDB_OK);
+
+            assert_eq!(rustdb_close(db), RUSTDB_OK);
+        }
+    }
+
+    #[test]
+    fn test_errors_and_misuse() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open(&dir);
+        unsafe {
+            let sql = c"CREATE TABLE t (id INTEGER PRIMARY KEY)";
+            assert_eq!(rustdb_exec(db, sql.as_ptr(), ptr::null_mut()), RUSTDB_OK);
+            let sql = c"INSERT INTO t (id) VALUES (1)";
+            assert_eq!(rustdb_exec(db, sql.as_ptr(), ptr::null_mut()), RUSTDB_OK);
+            assert_eq!(rustdb_errcode(db), 0);
+            assert_eq!(rustdb_exec(db, sql.as_ptr(), ptr::null_mut()), RUSTDB_ERROR);
+            assert_eq!(
+                rustdb_errcode(db),
+                engine_error_code::CONSTRAINT_VIOLATION as c_int
+            );
+            assert!(!text(rustdb_errmsg(db)).is_empty());
+
+            let mut stmt = ptr::null_mut();
+            let sql = c"SELEC nonsense";
+            assert_eq!(rustdb_prepare(db, sql.as_ptr(), &mut stmt), RUSTDB_OK);
+            assert_eq!(rustdb_step(stmt), RUSTDB_ERROR);
+            assert_eq!(rustdb_finalize(stmt), RUSTDB_OK);
+
+            assert_eq!(rustdb_exec(db, ptr::null(), ptr::null_mut()), RUSTDB_MISUSE);
+            assert_eq!(rustdb_step(ptr::null_mut()), RUSTDB_MISUSE);
+            assert_eq!(rustdb_finalize(ptr::null_mut()), RUSTDB_OK);
+            assert_eq!(rustdb_close(db), RUSTDB_OK);
+            assert_eq!(rustdb_close(ptr::null_mut()), RUSTDB_OK);
+        }
+
+        let mut db = ptr::null_mut();
+        let path = c"/tmp";
+        assert_eq!(
+            unsafe { rustdb_open(path.as_ptr(), 42, &mut db) },
+            RUSTDB_MISUSE
+        );
+        assert!(db.is_null());
+        assert!(!text(rustdb_version()).is_empty());
+    }
+}
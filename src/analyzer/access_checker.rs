@@ -87,6 +87,8 @@ pub enum Permission {
     DropIndex,
     /// Administrative rights
     Admin,
+    /// See masked columns unmasked
+    Unmask,
 }
 
 impl Permission {
@@ -103,6 +105,7 @@ impl Permission {
             Permission::CreateIndex => "CREATE INDEX",
             Permission::DropIndex => "DROP INDEX",
             Permission::Admin => "ADMIN",
+            Permission::Unmask => "UNMASK",
         }
     }
 }
@@ -138,6 +141,7 @@ impl Role {
         role.permissions.insert(Permission::CreateIndex);
         role.permissions.insert(Permission::DropIndex);
         role.permissions.insert(Permission::Admin);
+        role.permissions.insert(Permission::Unmask);
         role
     }
 
@@ -325,6 +329,21 @@ impl AccessChecker {
             SqlStatement::DropTable(drop) => {
                 self.check_drop_table_access(drop, username, &mut result)?;
             }
+            SqlStatement::CreateIndex(create) => {
+                result.checks_performed += 1;
+                self.check_table_access(
+                    &create.table_name,
+                    username,
+                    &Permission::CreateIndex,
+                    &mut result,
+                )?;
+            }
+            SqlStatement::DropIndex(drop) => {
+                result.checks_performed += 1;
+                // Without `ON <table>` the index name stands for the object.
+                let object = drop.table_name.as_ref().unwrap_or(&drop.index_name);
+                self.check_table_access(object, username, &Permission::DropIndex, &mut result)?;
+            }
             _ => {
                 // Transactional commands do not require special access checking
             }
@@ -430,6 +449,8 @@ impl AccessChecker {
                 TableReference::Subquery { query, .. } => {
                     self.check_select_access(query, username, result)?;
                 }
+                // Table functions read engine state, not tables.
+                TableReference::Function { .. } => {}
             }
 
             // Check access to JOIN tables
@@ -441,6 +462,7 @@ impl AccessChecker {
                     TableReference::Subquery { query, .. } => {
                         self.check_select_access(query, username, result)?;
                     }
+                    TableReference::Function { .. } => {}
                 }
             }
         }
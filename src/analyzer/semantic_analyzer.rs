@@ -348,6 +348,12 @@ impl SemanticAnalyzer {
             SqlStatement::DropTable(drop) => {
                 self.check_drop_table_objects(drop, context, result)?;
             }
+            SqlStatement::CreateIndex(create) => {
+                self.check_create_index_objects(create, context, result)?;
+            }
+            SqlStatement::DropIndex(drop) => {
+                self.check_drop_index_objects(drop, context, result)?;
+            }
             _ => {
                 // Transactional commands do not require object checking
             }
@@ -460,7 +466,7 @@ impl SemanticAnalyzer {
     ) -> Result<()> {
         // Check main table (or subquery) + populate scope.
         let table_name = match &from.table {
-            TableReference::Table { name, alias } => {
+            TableReference::Table { name, alias, .. } => {
                 scope.table_refs.insert(name.clone(), name.clone());
                 if let Some(a) = alias {
                     scope.table_refs.insert(a.clone(), name.clone());
@@ -476,6 +482,13 @@ impl SemanticAnalyzer {
                 // No schema existence check for subquery itself.
                 ""
             }
+            TableReference::Function { name, alias, .. } => {
+                scope.table_refs.insert(
+                    alias.clone().unwrap_or_else(|| name.clone()),
+                    "<function>".to_string(),
+                );
+                ""
+            }
         };
 
         if !table_name.is_empty() {
@@ -497,7 +510,7 @@ impl SemanticAnalyzer {
         // Check JOIN tables
         for join in &from.joins {
             let join_table_name = match &join.table {
-                TableReference::Table { name, alias } => {
+                TableReference::Table { name, alias, .. } => {
                     scope.table_refs.insert(name.clone(), name.clone());
                     if let Some(a) = alias {
                         scope.table_refs.insert(a.clone(), name.clone());
@@ -511,6 +524,13 @@ impl SemanticAnalyzer {
                     self.check_select_objects(query, context, result)?;
                     continue;
                 }
+                TableReference::Function { name, alias, .. } => {
+                    scope.table_refs.insert(
+                        alias.clone().unwrap_or_else(|| name.clone()),
+                        "<function>".to_string(),
+                    );
+                    continue;
+                }
             };
 
             if let Some(schema) = &context.schema {
@@ -801,6 +821,73 @@ impl SemanticAnalyzer {
         Ok(())
     }
 
+    fn check_create_index_objects(
+        &mut self,
+        create: &CreateIndexStatement,
+        context: &AnalysisContext,
+        result: &mut AnalysisResult,
+    ) -> Result<()> {
+        result.statistics.objects_checked += 1;
+
+        // Check table and key column existence
+        if let Some(schema) = &context.schema {
+            let object_result = self
+                .object_checker
+                .check_table_exists(&create.table_name, schema)?;
+            if !object_result.exists {
+                result.add_error(SemanticError {
+                    error_type: SemanticErrorType::ObjectNotFound,
+                    message: format!("Table '{}' does not exist", create.table_name),
+                    location: Some("CREATE INDEX statement".to_string()),
+                    suggested_fix: Some("Check table name or create the table first".to_string()),
+                });
+                return Ok(());
+            }
+            for column in create.key_table_columns() {
+                let column_result =
+                    self.object_checker
+                        .check_column_exists(&create.table_name, column, schema)?;
+                if !column_result.exists {
+                    result.add_error(SemanticError {
+                        error_type: SemanticErrorType::ObjectNotFound,
+                        message: format!(
+                            "Column '{}' does not exist in table '{}'",
+                            column, create.table_name
+                        ),
+                        location: Some("CREATE INDEX statement".to_string()),
+                        suggested_fix: Some("Check the indexed column names".to_string()),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_drop_index_objects(
+        &mut self,
+        drop: &DropIndexStatement,
+        context: &AnalysisContext,
+        result: &mut AnalysisResult,
+    ) -> Result<()> {
+        result.statistics.objects_checked += 1;
+
+        // Check table existence (the index itself is looked up when the statement runs)
+        if let (Some(schema), Some(table)) = (&context.schema, &drop.table_name) {
+            let object_result = self.object_checker.check_table_exists(table, schema)?;
+            if !object_result.exists && !drop.if_exists {
+                result.add_error(SemanticError {
+                    error_type: SemanticErrorType::ObjectNotFound,
+                    message: format!("Table '{}' does not exist", table),
+                    location: Some("DROP INDEX statement".to_string()),
+                    suggested_fix: Some("Use IF EXISTS clause or check table name".to_string()),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
     /// Get analyzer settings
     pub fn settings(&self) -> &SemanticAnalyzerSettings {
         &self.settings
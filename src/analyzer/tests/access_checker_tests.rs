@@ -20,6 +20,7 @@ fn test_permission_enum() {
     assert_eq!(Permission::Insert.as_str(), "INSERT");
     assert_eq!(Permission::CreateTable.as_str(), "CREATE TABLE");
     assert_eq!(Permission::Admin.as_str(), "ADMIN");
+    assert_eq!(Permission::Unmask.as_str(), "UNMASK");
 }
 
 #[test]
@@ -43,6 +44,7 @@ fn test_admin_role() {
     assert!(admin_role.has_permission(&Permission::Select));
     assert!(admin_role.has_permission(&Permission::CreateTable));
     assert!(admin_role.has_permission(&Permission::Admin));
+    assert!(admin_role.has_permission(&Permission::Unmask));
 }
 
 #[test]
@@ -531,6 +531,8 @@ impl TypeChecker {
 
         match expr {
             Expression::Literal(literal) => Ok(self.get_literal_type(literal)),
+            // Like NULL, an unbound parameter can be any type.
+            Expression::Parameter(_) => Ok(self.get_literal_type(&Literal::Null)),
             Expression::Identifier(_) | Expression::QualifiedIdentifier { .. } => {
                 // In a real implementation, column type would be retrieved from schema here
                 Ok(DataType::Text) // Placeholder
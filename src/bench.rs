@@ -0,0 +1,497 @@
+//! Built-in benchmark suite behind `rustdb bench` (TPC-B / TPC-C style workloads).
+//!
+//! [`run_in_dir`] generates a dataset in a data directory, drives a closed-loop workload from
+//! `clients` concurrent sessions against an in-process [`SqlEngine`], and returns a
+//! [`BenchReport`] with throughput and latency percentiles.
+//!
+//! Neither workload is an audited TPC implementation:
+//! - **TPC-B** follows the `pgbench` schema (`pgbench_branches` / `tellers` / `accounts` /
+//!   `history`) with [`TPCB_ACCOUNTS_PER_BRANCH`] accounts per branch instead of 100 000.
+//! - **TPC-C** reuses the minimal schema from `scripts/tpcc_seed.sql` and the transaction SQL in
+//!   [`crate::tpcc_workload`]; `scale` adds extra `stock` rows so stock-level scans grow with it.
+//!
+//! Sessions run on plain OS threads: [`SqlEngine`] may `block_on` internally (WAL), so the
+//! driver must not be called from a Tokio worker.
+
+use crate::network::engine::{EngineHandle, SessionContext};
+use crate::network::SqlEngine;
+use crate::tpcc_workload::{self, lcg_next, quantile_ms, rand_f64_0_1, Mix};
+use serde::Serialize;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Accounts generated per branch for TPC-B (`pgbench` uses 100 000; scaled down for CI).
+pub const TPCB_ACCOUNTS_PER_BRANCH: u64 = 1_000;
+/// Tellers generated per branch for TPC-B (same ratio as `pgbench`).
+pub const TPCB_TELLERS_PER_BRANCH: u64 = 10;
+/// Default TPC-C mix (roughly the spec's minimum percentages).
+pub const DEFAULT_TPCC_MIX: &str =
+    "new_order=45,payment=43,order_status=4,delivery=4,stock_level=4";
+
+/// Rows per explicit transaction while populating tables.
+const LOAD_BATCH_ROWS: u64 = 500;
+/// Extra `stock` rows per TPC-C scale step (item ids start above the seeded range).
+const TPCC_EXTRA_STOCK_PER_SCALE: u64 = 100;
+
+const TPCC_SEED_SQL: &str = include_str!("../scripts/tpcc_seed.sql");
+
+/// Workload selected with `rustdb bench --workload`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BenchWorkload {
+    /// `pgbench`-style debit/credit transaction.
+    Tpcb,
+    /// Mixed new-order / payment / order-status / delivery / stock-level transactions.
+    Tpcc,
+}
+
+impl BenchWorkload {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BenchWorkload::Tpcb => "tpcb",
+            BenchWorkload::Tpcc => "tpcc",
+        }
+    }
+}
+
+impl FromStr for BenchWorkload {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "tpcb" | "tpc-b" => Ok(BenchWorkload::Tpcb),
+            "tpcc" | "tpc-c" => Ok(BenchWorkload::Tpcc),
+            other => Err(format!("unknown workload: {other} (expected tpcb or tpcc)")),
+        }
+    }
+}
+
+impl std::fmt::Display for BenchWorkload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Benchmark run parameters.
+#[derive(Debug, Clone)]
+pub struct BenchConfig {
+    pub workload: BenchWorkload,
+    /// Dataset scale factor (TPC-B: number of branches).
+    pub scale: u32,
+    /// Concurrent sessions (one OS thread each).
+    pub clients: usize,
+    /// Total transactions across all clients (ignored when `duration` is set).
+    pub transactions: usize,
+    /// Run for a fixed wall-clock time instead of a fixed transaction count.
+    pub duration: Option<Duration>,
+    /// Skip data generation (dataset already loaded by a previous run).
+    pub skip_load: bool,
+    /// Transaction mix for [`BenchWorkload::Tpcc`].
+    pub tpcc_mix: String,
+    /// Base seed for the per-client PRNG.
+    pub seed: u64,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self {
+            workload: BenchWorkload::Tpcb,
+            scale: 1,
+            clients: 1,
+            transactions: 1_000,
+            duration: None,
+            skip_load: false,
+            tpcc_mix: DEFAULT_TPCC_MIX.to_string(),
+            seed: 0xC0FFEE,
+        }
+    }
+}
+
+/// Latency summary over per-transaction samples (milliseconds).
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LatencyStats {
+    pub count: usize,
+    pub min_ms: f64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl LatencyStats {
+    /// Builds the summary from microsecond samples (sorted in place).
+    pub fn from_micros(samples: &mut [u128]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        samples.sort_unstable();
+        let total: u128 = samples.iter().sum();
+        Self {
+            count: samples.len(),
+            min_ms: samples[0] as f64 / 1000.0,
+            mean_ms: total as f64 / samples.len() as f64 / 1000.0,
+            p50_ms: quantile_ms(samples, 0.50),
+            p95_ms: quantile_ms(samples, 0.95),
+            p99_ms: quantile_ms(samples, 0.99),
+            max_ms: samples[samples.len() - 1] as f64 / 1000.0,
+        }
+    }
+
+    /// Builds the summary from [`Duration`] measurements.
+    pub fn from_durations(measurements: &[Duration]) -> Self {
+        let mut us: Vec<u128> = measurements.iter().map(|d| d.as_micros()).collect();
+        Self::from_micros(&mut us)
+    }
+}
+
+/// Result of one benchmark run (serialized by `rustdb bench --json`).
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchReport {
+    pub workload: String,
+    pub scale: u32,
+    pub clients: usize,
+    pub load_s: f64,
+    pub elapsed_s: f64,
+    pub txn_attempts: u64,
+    pub txn_successes: u64,
+    pub errors: u64,
+    pub txns_per_s: f64,
+    pub latency: LatencyStats,
+}
+
+impl BenchReport {
+    /// Human-readable multi-line summary printed by the CLI.
+    pub fn summary(&self) -> String {
+        format!(
+            "workload: {}\nscale: {}\nclients: {}\nload time: {:.2}s\nrun time: {:.2}s\n\
+             transactions: {} ok / {} attempted ({} errors)\nthroughput: {:.1} txn/s\n\
+             latency ms: min {:.3}, mean {:.3}, p50 {:.3}, p95 {:.3}, p99 {:.3}, max {:.3}",
+            self.workload,
+            self.scale,
+            self.clients,
+            self.load_s,
+            self.elapsed_s,
+            self.txn_successes,
+            self.txn_attempts,
+            self.errors,
+            self.txns_per_s,
+            self.latency.min_ms,
+            self.latency.mean_ms,
+            self.latency.p50_ms,
+            self.latency.p95_ms,
+            self.latency.p99_ms,
+            self.latency.max_ms,
+        )
+    }
+}
+
+/// Opens an engine in `data_dir`, loads the dataset (unless `skip_load`), and runs the workload.
+pub fn run_in_dir(data_dir: &Path, config: &BenchConfig) -> Result<BenchReport, String> {
+    let engine = SqlEngine::open(data_dir.to_path_buf()).map_err(|e| e.to_string())?;
+    let load_start = Instant::now();
+    if !config.skip_load {
+        load(&engine, config)?;
+    }
+    let load_s = load_start.elapsed().as_secs_f64();
+    let mut report = run(&engine, config)?;
+    report.load_s = load_s;
+    Ok(report)
+}
+
+/// Creates the schema and generates rows for `config.workload` at `config.scale`.
+pub fn load<E: EngineHandle>(engine: &E, config: &BenchConfig) -> Result<(), String> {
+    let scale = u64::from(config.scale.max(1));
+    let mut ctx = SessionContext::default();
+    match config.workload {
+        BenchWorkload::Tpcb => {
+            for ddl in [
+                "CREATE TABLE pgbench_branches (bid INTEGER, bbalance INTEGER, filler VARCHAR(88))",
+                "CREATE TABLE pgbench_tellers (tid INTEGER, bid INTEGER, tbalance INTEGER, filler VARCHAR(84))",
+                "CREATE TABLE pgbench_accounts (aid INTEGER, bid INTEGER, abalance INTEGER, filler VARCHAR(84))",
+                "CREATE TABLE pgbench_history (tid INTEGER, bid INTEGER, aid INTEGER, delta INTEGER)",
+                "CREATE INDEX idx_pgbench_branches_bid ON pgbench_branches (bid)",
+                "CREATE INDEX idx_pgbench_tellers_tid ON pgbench_tellers (tid)",
+                "CREATE INDEX idx_pgbench_accounts_aid ON pgbench_accounts (aid)",
+            ] {
+                exec(engine, &mut ctx, ddl)?;
+            }
+            load_rows(engine, &mut ctx, 1..=scale, |bid| {
+                format!(
+                    "INSERT INTO pgbench_branches (bid, bbalance, filler) VALUES ({bid}, 0, '')"
+                )
+            })?;
+            load_rows(
+                engine,
+                &mut ctx,
+                1..=scale * TPCB_TELLERS_PER_BRANCH,
+                |tid| {
+                    let bid = (tid - 1) / TPCB_TELLERS_PER_BRANCH + 1;
+                    format!(
+                        "INSERT INTO pgbench_tellers (tid, bid, tbalance, filler) VALUES ({tid}, {bid}, 0, '')"
+                    )
+                },
+            )?;
+            load_rows(
+                engine,
+                &mut ctx,
+                1..=scale * TPCB_ACCOUNTS_PER_BRANCH,
+                |aid| {
+                    let bid = (aid - 1) / TPCB_ACCOUNTS_PER_BRANCH + 1;
+                    format!(
+                        "INSERT INTO pgbench_accounts (aid, bid, abalance, filler) VALUES ({aid}, {bid}, 0, '')"
+                    )
+                },
+            )?;
+        }
+        BenchWorkload::Tpcc => {
+            for stmt in seed_statements(TPCC_SEED_SQL) {
+                exec(engine, &mut ctx, stmt)?;
+            }
+            let extra = (scale - 1) * TPCC_EXTRA_STOCK_PER_SCALE;
+            load_rows(engine, &mut ctx, 1..=extra, |i| {
+                let i_id = 1_000 + i;
+                format!(
+                    "INSERT INTO stock (s_i_id, s_w_id, s_qty, s_ytd, s_order_cnt) VALUES ({i_id}, 1, {}, 0, 0)",
+                    10 + i % 90
+                )
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Runs the workload against an already loaded dataset.
+pub fn run<E: EngineHandle>(engine: &E, config: &BenchConfig) -> Result<BenchReport, String> {
+    let clients = config.clients.max(1);
+    let mix = match config.workload {
+        BenchWorkload::Tpcc => Some(Mix::parse(&config.tpcc_mix)?),
+        BenchWorkload::Tpcb => None,
+    };
+    let scale = u64::from(config.scale.max(1));
+    let total = config.transactions.max(1) as u64;
+    let deadline = config.duration.map(|d| Instant::now() + d);
+    let next_txn = AtomicU64::new(0);
+
+    let start = Instant::now();
+    let per_client: Vec<ClientResult> = std::thread::scope(|s| {
+        let handles: Vec<_> = (0..clients)
+            .map(|client_id| {
+                let mix = mix.as_ref();
+                let next_txn = &next_txn;
+                s.spawn(move || {
+                    let mut out = ClientResult::default();
+                    let mut ctx = SessionContext::default();
+                    let seed = config.seed ^ (client_id as u64).wrapping_mul(0xA5A5_A5A5_A5A5_A5A5);
+                    let mut rng = seed;
+                    loop {
+                        if let Some(dl) = deadline {
+                            if Instant::now() >= dl {
+                                break;
+                            }
+                        }
+                        let txn_id = next_txn.fetch_add(1, Ordering::Relaxed);
+                        if deadline.is_none() && txn_id >= total {
+                            break;
+                        }
+                        let sqls = match mix {
+                            Some(mix) => {
+                                let kind = mix.pick(rand_f64_0_1(&mut rng));
+                                tpcc_workload::txn_sql(kind, seed, txn_id)
+                            }
+                            None => tpcb_txn_sql(&mut rng, scale),
+                        };
+                        let t0 = Instant::now();
+                        let ok = run_txn(engine, &mut ctx, &sqls);
+                        out.attempts += 1;
+                        if ok {
+                            out.successes += 1;
+                            out.latencies_us.push(t0.elapsed().as_micros());
+                        }
+                    }
+                    out
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap_or_default())
+            .collect()
+    });
+    let elapsed_s = start.elapsed().as_secs_f64();
+
+    let mut latencies: Vec<u128> = Vec::new();
+    let (mut attempts, mut successes) = (0u64, 0u64);
+    for r in per_client {
+        attempts += r.attempts;
+        successes += r.successes;
+        latencies.extend(r.latencies_us);
+    }
+    Ok(BenchReport {
+        workload: config.workload.to_string(),
+        scale: config.scale.max(1),
+        clients,
+        load_s: 0.0,
+        elapsed_s,
+        txn_attempts: attempts,
+        txn_successes: successes,
+        errors: attempts - successes,
+        txns_per_s: if elapsed_s > 0.0 {
+            successes as f64 / elapsed_s
+        } else {
+            0.0
+        },
+        latency: LatencyStats::from_micros(&mut latencies),
+    })
+}
+
+#[derive(Default)]
+struct ClientResult {
+    attempts: u64,
+    successes: u64,
+    latencies_us: Vec<u128>,
+}
+
+/// One `pgbench`-style TPC-B transaction over uniformly chosen account / teller / branch ids.
+pub fn tpcb_txn_sql(rng: &mut u64, scale: u64) -> Vec<String> {
+    let scale = scale.max(1);
+    let aid = lcg_next(rng) % (scale * TPCB_ACCOUNTS_PER_BRANCH) + 1;
+    let tid = lcg_next(rng) % (scale * TPCB_TELLERS_PER_BRANCH) + 1;
+    let bid = lcg_next(rng) % scale + 1;
+    let delta = (lcg_next(rng) % 10_001) as i64 - 5_000;
+    // The parser has no unary minus on literals; spell negative deltas as `x - n` / `0 - n`.
+    let (sign, abs) = if delta < 0 {
+        ('-', -delta)
+    } else {
+        ('+', delta)
+    };
+    vec![
+        "BEGIN TRANSACTION".to_string(),
+        format!("UPDATE pgbench_accounts SET abalance = abalance {sign} {abs} WHERE aid = {aid}"),
+        format!("SELECT abalance FROM pgbench_accounts WHERE aid = {aid}"),
+        format!("UPDATE pgbench_tellers SET tbalance = tbalance {sign} {abs} WHERE tid = {tid}"),
+        format!("UPDATE pgbench_branches SET bbalance = bbalance {sign} {abs} WHERE bid = {bid}"),
+        format!(
+            "INSERT INTO pgbench_history (tid, bid, aid, delta) VALUES ({tid}, {bid}, {aid}, 0 {sign} {abs})"
+        ),
+        "COMMIT".to_string(),
+    ]
+}
+
+/// Executes one transaction's statements; rolls back the session on the first failure.
+fn run_txn<E: EngineHandle>(engine: &E, ctx: &mut SessionContext, sqls: &[String]) -> bool {
+    for sql in sqls {
+        if engine.execute_sql(sql, ctx).is_err() {
+            if ctx.transaction.is_some() {
+                let _ = engine.execute_sql("ROLLBACK", ctx);
+            }
+            return false;
+        }
+    }
+    true
+}
+
+fn exec<E: EngineHandle>(engine: &E, ctx: &mut SessionContext, sql: &str) -> Result<(), String> {
+    engine
+        .execute_sql(sql, ctx)
+        .map(|_| ())
+        .map_err(|e| format!("{sql}: {}", e.message))
+}
+
+/// Inserts one row per id, [`LOAD_BATCH_ROWS`] rows per explicit transaction.
+fn load_rows<E: EngineHandle>(
+    engine: &E,
+    ctx: &mut SessionContext,
+    ids: std::ops::RangeInclusive<u64>,
+    row_sql: impl Fn(u64) -> String,
+) -> Result<(), String> {
+    let mut in_batch = 0u64;
+    for id in ids {
+        if in_batch == 0 {
+            exec(engine, ctx, "BEGIN TRANSACTION")?;
+        }
+        exec(engine, ctx, &row_sql(id))?;
+        in_batch += 1;
+        if in_batch == LOAD_BATCH_ROWS {
+            exec(engine, ctx, "COMMIT")?;
+            in_batch = 0;
+        }
+    }
+    if in_batch > 0 {
+        exec(engine, ctx, "COMMIT")?;
+    }
+    Ok(())
+}
+
+/// Single-line statements from a seed script (`--` comments and blank lines skipped).
+fn seed_statements(script: &str) -> impl Iterator<Item = &str> {
+    script
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with("--"))
+        .map(|l| l.trim_end_matches(';').trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn workload_parse() {
+        assert_eq!("tpcb".parse::<BenchWorkload>(), Ok(BenchWorkload::Tpcb));
+        assert_eq!("TPC-C".parse::<BenchWorkload>(), Ok(BenchWorkload::Tpcc));
+        assert!("ycsb".parse::<BenchWorkload>().is_err());
+    }
+
+    #[test]
+    fn latency_stats_percentiles() {
+        let mut v: Vec<u128> = (1..=100).map(|i| i * 1000).collect();
+        let s = LatencyStats::from_micros(&mut v);
+        assert_eq!(s.count, 100);
+        assert_eq!(s.min_ms, 1.0);
+        assert_eq!(s.max_ms, 100.0);
+        assert!(s.p50_ms >= 50.0 && s.p50_ms <= 51.0);
+        assert!(s.p99_ms >= 99.0);
+        assert_eq!(LatencyStats::from_durations(&[]), LatencyStats::default());
+    }
+
+    #[test]
+    fn tpcb_txn_ids_in_range() {
+        let mut rng = 7;
+        for _ in 0..200 {
+            let sqls = tpcb_txn_sql(&mut rng, 2);
+            assert_eq!(sqls.len(), 7);
+            assert!(sqls[1].contains("pgbench_accounts"));
+            assert!(!sqls[1].contains("aid = 0"));
+            assert!(!sqls[4].contains("bid = 3"));
+        }
+    }
+
+    #[test]
+    fn seed_script_statements_are_single_line() {
+        let stmts: Vec<&str> = seed_statements(TPCC_SEED_SQL).collect();
+        assert!(stmts
+            .iter()
+            .any(|s| s.starts_with("CREATE TABLE warehouse")));
+        assert!(stmts.iter().all(|s| !s.ends_with(';')));
+    }
+
+    #[test]
+    fn tpcb_end_to_end_small() {
+        let dir = TempDir::new().unwrap();
+        let config = BenchConfig {
+            workload: BenchWorkload::Tpcb,
+            scale: 1,
+            clients: 1,
+            transactions: 20,
+            ..Default::default()
+        };
+        let report = run_in_dir(dir.path(), &config).unwrap();
+        assert_eq!(report.txn_attempts, 20);
+        assert_eq!(report.errors, 0);
+        assert_eq!(report.latency.count as u64, report.txn_successes);
+        assert!(report.summary().contains("tpcb"));
+    }
+}
@@ -498,6 +498,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                             ServerMessage::ServerReady(p) => {
                                 println!("#{i} OK ServerReady {}", p.server_version);
                             }
+                            ServerMessage::BackupChunk(_) | ServerMessage::BackupEnd(_) => {
+                                println!("#{i} OK unexpected base backup frame");
+                            }
+                            ServerMessage::ReplicatedTransaction(_) => {
+                                println!("#{i} OK unexpected replication frame");
+                            }
+                            ServerMessage::BinaryResultSet(p) => {
+                                println!(
+                                    "#{i} OK BinaryResultSet cols={} rows={}",
+                                    p.columns.len(),
+                                    p.row_count
+                                );
+                            }
                         }
                     }
                 }
@@ -3,6 +3,8 @@
 //! Usage:
 //! ```text
 //! rustdb_quic_client --addr 127.0.0.1:5432 --cert server.der "SELECT 1"
+//! rustdb_quic_client --cert server.der --binary "SELECT * FROM t"
+//! rustdb_quic_client --cert server.der --compress "SELECT * FROM big_table"
 //! ```
 //!
 //! Save the server leaf certificate in DER form (`pinned_certificate` from a test or future export) as `server.der`.
@@ -15,9 +17,11 @@ use clap::Parser;
 use rustls::pki_types::CertificateDer;
 
 use rustdb::network::client::{
-    build_quinn_client_config, connect, make_client_endpoint, query_once,
+    build_compressed_quinn_client_config, build_quinn_client_config, connect, make_client_endpoint,
+    query_once_with_format,
 };
-use rustdb::network::framing::ServerMessage;
+use rustdb::network::framing::{ResultFormat, ServerMessage};
+use rustdb::network::server::negotiated_compression;
 
 #[derive(Parser, Debug)]
 #[command(name = "rustdb_quic_client")]
@@ -31,6 +35,12 @@ struct Args {
     /// TLS server name (must match certificate SAN; use `127.0.0.1` when the server cert is for that IP).
     #[arg(long, default_value = "127.0.0.1")]
     server_name: String,
+    /// Ask for the result set in the binary row format.
+    #[arg(long)]
+    binary: bool,
+    /// Ask the server for lz4 frame compression.
+    #[arg(long)]
+    compress: bool,
     /// SQL query text.
     sql: String,
 }
@@ -41,10 +51,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let addr: SocketAddr = args.addr.parse()?;
     let der = fs::read(&args.cert)?;
     let cert = CertificateDer::from(der);
-    let client_cfg = build_quinn_client_config(std::slice::from_ref(&cert))?;
+    let client_cfg = if args.compress {
+        build_compressed_quinn_client_config(std::slice::from_ref(&cert))?
+    } else {
+        build_quinn_client_config(std::slice::from_ref(&cert))?
+    };
     let endpoint = make_client_endpoint(client_cfg)?;
     let conn = connect(&endpoint, addr, &args.server_name).await?;
-    let msg = query_once(&conn, &args.sql).await?;
+    if args.compress && !negotiated_compression(&conn) {
+        eprintln!("the server does not compress frames; continuing without compression");
+    }
+    let format = if args.binary {
+        ResultFormat::Binary
+    } else {
+        ResultFormat::Text
+    };
+    let msg = query_once_with_format(&conn, &args.sql, format).await?;
     match msg {
         ServerMessage::ResultSet(p) => {
             println!("ResultSet: columns={:?} rows={:?}", p.columns, p.rows);
@@ -58,6 +80,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         ServerMessage::ServerReady(p) => {
             println!("ServerReady: {}", p.server_version);
         }
+        ServerMessage::BackupChunk(_) | ServerMessage::BackupEnd(_) => {
+            println!("unexpected base backup frame");
+        }
+        ServerMessage::ReplicatedTransaction(_) => {
+            println!("unexpected replication frame");
+        }
+        ServerMessage::BinaryResultSet(p) => {
+            println!(
+                "BinaryResultSet: columns={:?} rows={:?}",
+                p.columns,
+                p.rows()?
+            );
+        }
     }
     Ok(())
 }
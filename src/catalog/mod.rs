@@ -8,8 +8,8 @@ pub mod statistics;
 mod tests;
 
 pub use statistics::{
-    ColumnStatistics, ColumnValue, HistogramBucket, StatisticsManager, StatisticsSettings,
-    TableStatistics, ValueDistribution,
+    ColumnStatistics, ColumnValue, HistogramBucket, MostCommonValue, StatisticsManager,
+    StatisticsSettings, TableStatistics, ValueDistribution,
 };
 
 pub use access::AccessControl;
@@ -2,7 +2,7 @@
 
 use crate::common::types::Column;
 use crate::common::{Error, Result};
-use crate::parser::ast::Expression;
+use crate::parser::ast::{Expression, IndexMethod};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
@@ -28,11 +28,46 @@ pub struct ForeignKeyConstraintDef {
     pub referenced_columns: Vec<String>,
 }
 
-/// Secondary B+tree index registered via `CREATE INDEX` (persisted in catalog for reopen).
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// Secondary index registered via `CREATE INDEX` (persisted in catalog for reopen).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SecondaryIndexDef {
     pub name: String,
+    /// B+ tree or hash
+    #[serde(default)]
+    pub method: IndexMethod,
     pub columns: Vec<String>,
+    /// `WHERE` predicate of a partial index
+    #[serde(default)]
+    pub predicate: Option<Expression>,
+    /// Computed key parts by their name in `columns` (expression indexes)
+    #[serde(default)]
+    pub expressions: Vec<(String, Expression)>,
+}
+
+/// External data behind `CREATE FOREIGN TABLE ... USING <adapter> OPTIONS (...)`; rows are
+/// read through `crate::storage::foreign` and never stored in a heap file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForeignTableDef {
+    /// Adapter name (`csv`, `parquet`, `rustdb`)
+    pub adapter: String,
+    /// `OPTIONS` pairs in declaration order; keys are lowercase
+    pub options: Vec<(String, String)>,
+}
+
+impl ForeignTableDef {
+    /// Value of option `key`
+    pub fn option(&self, key: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Whether the adapter evaluates `WHERE` predicates itself (remote tables); the planner
+    /// only hands predicates to these
+    pub fn pushes_down_filters(&self) -> bool {
+        self.adapter == "rustdb"
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,6 +82,17 @@ pub struct TableSchema {
     /// Non-primary secondary indexes; rebuilt into [`crate::storage::index_registry::IndexRegistry`] on engine open.
     #[serde(default)]
     pub secondary_indexes: Vec<SecondaryIndexDef>,
+    /// Set for foreign tables (read-only, no constraints or indexes).
+    #[serde(default)]
+    pub foreign: Option<ForeignTableDef>,
+    /// `ENCRYPT BY` column: rows are sealed with the key of their subject (see
+    /// [`crate::storage::shred`]).
+    #[serde(default)]
+    pub encrypt_by: Option<String>,
+    /// Tenant owning the table: its rows are sealed with the tenant's key, and only sessions of
+    /// the tenant (or without one) see it.
+    #[serde(default)]
+    pub tenant: Option<String>,
 }
 
 /// Registered table names and simple ordinal ids (for tests and tooling).
@@ -117,6 +163,28 @@ impl SchemaManager {
         out
     }
 
+    /// Tables with a secondary index named `index`.
+    pub fn tables_with_index(&self, index: &str) -> Vec<String> {
+        let mut out: Vec<String> = self
+            .schemas
+            .iter()
+            .filter(|(_, sch)| sch.secondary_indexes.iter().any(|i| i.name == index))
+            .map(|(tname, _)| tname.clone())
+            .collect();
+        out.sort();
+        out
+    }
+
+    /// Removes the secondary index `index` of `table`; returns whether it existed.
+    pub fn drop_secondary_index(&mut self, table: &str, index: &str) -> bool {
+        let Some(sch) = self.schemas.get_mut(table) else {
+            return false;
+        };
+        let before = sch.secondary_indexes.len();
+        sch.secondary_indexes.retain(|i| i.name != index);
+        sch.secondary_indexes.len() != before
+    }
+
     pub fn drop_table(&mut self, table: &str) {
         self.table_ids.remove(table);
         self.schemas.remove(table);
@@ -167,32 +235,15 @@ impl SchemaManager {
         let dir = data_dir.join(".rustdb");
         std::fs::create_dir_all(&dir)?;
         let path = dir.join("catalog.json");
-        let tmp_path = dir.join("catalog.json.tmp");
         let file = self.build_catalog_file()?;
         let json = serde_json::to_string_pretty(&file).map_err(Error::from)?;
-        // Atomic-ish update: write to a temp file, fsync it (best-effort), then rename.
-        // This reduces the chance of leaving a truncated `catalog.json` on crash/power loss.
-        {
-            use std::io::Write;
-            let mut f = std::fs::OpenOptions::new()
-                .create(true)
-                .truncate(true)
-                .write(true)
-                .open(&tmp_path)?;
-            f.write_all(json.as_bytes())?;
-            // If requested, make the file contents durable before renaming.
-            if fsync_on_commit {
-                let _ = f.sync_all();
-            }
-        }
-        std::fs::rename(&tmp_path, &path)?;
-        // Best-effort directory fsync on Unix so the rename is durable too.
-        #[cfg(unix)]
-        if fsync_on_commit {
-            if let Ok(d) = std::fs::File::open(&dir) {
-                let _ = d.sync_all();
-            }
-        }
+        // Write-temp + rename, so a crash never leaves a truncated `catalog.json`; with
+        // `fsync_on_commit` the contents and the rename are also synced.
+        crate::storage::atomic_file::write_atomic_with_sync(
+            &path,
+            json.as_bytes(),
+            fsync_on_commit,
+        )?;
         Ok(())
     }
 
@@ -29,6 +29,12 @@ pub struct ColumnStatistics {
     pub distinct_values: usize,
     /// Number of NULL values
     pub null_count: usize,
+    /// Fraction of the rows whose value is NULL
+    #[serde(default)]
+    pub null_frac: f64,
+    /// Values much more frequent than the others, most frequent first
+    #[serde(default)]
+    pub most_common_values: Vec<MostCommonValue>,
     /// Minimum value
     pub min_value: Option<ColumnValue>,
     /// Maximum value
@@ -39,6 +45,56 @@ pub struct ColumnStatistics {
     pub value_distribution: ValueDistribution,
 }
 
+/// A frequent value of a column
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MostCommonValue {
+    /// The value
+    pub value: ColumnValue,
+    /// Fraction of the rows with the value
+    pub frequency: f64,
+}
+
+impl ColumnStatistics {
+    /// Fraction of the rows where the column equals `value`: the frequency of a most common
+    /// value, else the rows left by the NULLs and the most common values spread evenly over
+    /// the remaining distinct values. Histograms alone put every value at that even share,
+    /// which badly underestimates the hot values of skewed columns.
+    pub fn equality_selectivity(&self, value: &ColumnValue) -> f64 {
+        if matches!(value, ColumnValue::Null) {
+            // `column = NULL` is never true.
+            return 0.0;
+        }
+        if let Some(mcv) = self
+            .most_common_values
+            .iter()
+            .find(|m| values_equal(&m.value, value))
+        {
+            return mcv.frequency;
+        }
+        let common: f64 = self.most_common_values.iter().map(|m| m.frequency).sum();
+        let rest = (1.0 - self.null_frac - common).clamp(0.0, 1.0);
+        let others = self
+            .distinct_values
+            .saturating_sub(self.most_common_values.len())
+            .max(1);
+        let selectivity = rest / others as f64;
+        // No value outside the list is as frequent as the least common one in it.
+        self.most_common_values
+            .iter()
+            .map(|m| m.frequency)
+            .fold(selectivity, f64::min)
+    }
+}
+
+/// Equality of statistics values, integers and floats compared as numbers
+fn values_equal(a: &ColumnValue, b: &ColumnValue) -> bool {
+    match (a, b) {
+        (ColumnValue::Integer(i), ColumnValue::Float(f))
+        | (ColumnValue::Float(f), ColumnValue::Integer(i)) => *i as f64 == *f,
+        _ => a == b,
+    }
+}
+
 /// Value distribution in a column
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ValueDistribution {
@@ -169,9 +225,14 @@ impl StatisticsManager {
             self.create_column_statistics("salary", 1000, 0, 30000, 150000),
         );
 
+        let total_rows = 10000;
+        for stats in column_stats.values_mut() {
+            stats.null_frac = stats.null_count as f64 / total_rows as f64;
+        }
+
         let table_stats = TableStatistics {
             table_name: table_name.to_string(),
-            total_rows: 10000,
+            total_rows,
             total_size_bytes: 1024 * 1024, // 1MB
             last_updated: SystemTime::now(),
             column_statistics: column_stats,
@@ -206,6 +267,8 @@ impl StatisticsManager {
             column_name: column_name.to_string(),
             distinct_values: distinct,
             null_count: nulls,
+            null_frac: 0.0,
+            most_common_values: Vec::new(),
             min_value: Some(ColumnValue::Integer(min_val)),
             max_value: Some(ColumnValue::Integer(max_val)),
             avg_length: None,
@@ -213,6 +276,12 @@ impl StatisticsManager {
         }
     }
 
+    /// Install statistics gathered elsewhere (e.g. by `ANALYZE`) for their table
+    pub fn set_table_statistics(&mut self, statistics: TableStatistics) {
+        self.table_statistics
+            .insert(statistics.table_name.clone(), statistics);
+    }
+
     /// Get table statistics
     pub fn get_table_statistics(&self, table_name: &str) -> Option<&TableStatistics> {
         self.table_statistics.get(table_name)
@@ -235,6 +304,23 @@ impl StatisticsManager {
         Ok(0.1)
     }
 
+    /// Estimate selectivity of `column = value`, using the column's most common values
+    pub fn estimate_equality_selectivity(
+        &self,
+        table_name: &str,
+        column_name: &str,
+        value: &ColumnValue,
+    ) -> Result<f64> {
+        if let Some(table_stats) = self.get_table_statistics(table_name) {
+            if let Some(column_stats) = table_stats.column_statistics.get(column_name) {
+                return Ok(column_stats.equality_selectivity(value));
+            }
+        }
+
+        // If statistics are unavailable, return conservative estimate
+        Ok(0.1)
+    }
+
     /// Calculate condition selectivity
     fn calculate_selectivity(
         &self,
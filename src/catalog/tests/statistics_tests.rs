@@ -1,8 +1,8 @@
 //! Tests for the statistics manager
 
 use crate::catalog::statistics::{
-    ColumnStatistics, ColumnValue, HistogramBucket, StatisticsManager, StatisticsSettings,
-    TableStatistics, ValueDistribution,
+    ColumnStatistics, ColumnValue, HistogramBucket, MostCommonValue, StatisticsManager,
+    StatisticsSettings, TableStatistics, ValueDistribution,
 };
 
 #[test]
@@ -110,6 +110,46 @@ fn test_selectivity_estimation() {
     assert_eq!(selectivity_unknown, 0.1);
 }
 
+#[test]
+fn test_equality_selectivity_with_most_common_values() {
+    let mut manager = StatisticsManager::new().unwrap();
+    let mut stats = manager.collect_table_statistics("users").unwrap();
+    assert_eq!(stats.column_statistics["name"].null_frac, 0.05);
+
+    let age = stats.column_statistics.get_mut("age").unwrap();
+    age.null_frac = 0.2;
+    age.most_common_values = vec![
+        MostCommonValue {
+            value: ColumnValue::Integer(30),
+            frequency: 0.5,
+        },
+        MostCommonValue {
+            value: ColumnValue::Integer(40),
+            frequency: 0.1,
+        },
+    ];
+    manager.set_table_statistics(stats);
+
+    let selectivity = |value: ColumnValue| {
+        manager
+            .estimate_equality_selectivity("users", "age", &value)
+            .unwrap()
+    };
+    assert_eq!(selectivity(ColumnValue::Integer(30)), 0.5);
+    // Integers and floats compare as numbers.
+    assert_eq!(selectivity(ColumnValue::Float(40.0)), 0.1);
+    // What NULLs and the common values leave, over the 98 other values.
+    let other = selectivity(ColumnValue::Integer(18));
+    assert!((other - 0.2 / 98.0).abs() < 1e-9, "{other}");
+    assert_eq!(selectivity(ColumnValue::Null), 0.0);
+    assert_eq!(
+        manager
+            .estimate_equality_selectivity("unknown", "age", &ColumnValue::Integer(1))
+            .unwrap(),
+        0.1
+    );
+}
+
 #[test]
 fn test_result_rows_estimation() {
     let mut manager = StatisticsManager::new().unwrap();
@@ -26,3 +26,21 @@ pub fn deserialize_from_reader<T: serde::de::DeserializeOwned, R: std::io::Read>
 ) -> Result<T, bincode_next::error::DecodeError> {
     bincode_next::serde::decode_from_std_read(reader, legacy())
 }
+
+/// Deserialize untrusted bytes (e.g. WAL read back from disk).
+///
+/// Collection lengths are checked against `LIMIT` before anything is allocated, and
+/// trailing bytes after the value are rejected, so corrupt input yields an error
+/// instead of a huge allocation or a silently truncated decode.
+pub fn deserialize_bounded<T: serde::de::DeserializeOwned, const LIMIT: usize>(
+    bytes: &[u8],
+) -> Result<T, bincode_next::error::DecodeError> {
+    let (value, read) =
+        bincode_next::serde::decode_from_slice(bytes, legacy().with_limit::<LIMIT>())?;
+    if read != bytes.len() {
+        return Err(bincode_next::error::DecodeError::Other(
+            "trailing bytes after encoded value",
+        ));
+    }
+    Ok(value)
+}
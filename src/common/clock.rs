@@ -0,0 +1,45 @@
+//! Clocks that also work on `wasm32-unknown-unknown`
+//!
+//! The standard clocks panic on that target (it has no time source of its own), so there
+//! [`Instant`] measures no time and [`system_now`] is the Unix epoch. Everywhere else these are
+//! the `std` clocks. Code shared with the in-memory playground (rows, planner, executor) reads
+//! time through this module.
+
+use std::time::SystemTime;
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub use std::time::Instant;
+
+/// Stand-in for [`std::time::Instant`]: every instant is the same one
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant;
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+impl Instant {
+    /// The (only) instant
+    pub fn now() -> Self {
+        Instant
+    }
+
+    /// Always zero
+    pub fn elapsed(&self) -> std::time::Duration {
+        std::time::Duration::ZERO
+    }
+}
+
+/// Current wall-clock time
+pub fn system_now() -> SystemTime {
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    return SystemTime::UNIX_EPOCH;
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    SystemTime::now()
+}
+
+/// Seconds since the Unix epoch
+pub fn unix_now_secs() -> u64 {
+    system_now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
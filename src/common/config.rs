@@ -1,10 +1,18 @@
 //! Configuration for rustdb
 //!
-//! Provides configuration structures for various system components
+//! Provides configuration structures for various system components.
+//!
+//! Every setting of [`DatabaseConfig`] is a documented [`ConfigParameter`] addressed by a dotted
+//! key (`network.port`). [`LayeredConfig`] assembles the effective configuration from layers —
+//! defaults < file < `RUSTDB_*` environment variables < command-line flags < runtime `SET` — and
+//! reports errors as [`ConfigError`]s naming the offending key. Parameters marked `runtime` take
+//! effect without a restart; the others keep their value until the server restarts.
 
 use crate::common::i18n::Language;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 /// Main database configuration
@@ -25,6 +33,33 @@ pub struct DatabaseConfig {
     /// QUIC / network listener (used by `rustdb server`; see `docs/network/`).
     #[serde(default)]
     pub network: NetworkConfig,
+    /// Primary-side replication settings (see `rustdb replica`).
+    #[serde(default)]
+    pub replication: ReplicationConfig,
+    /// Audit of reads of sensitive tables.
+    #[serde(default)]
+    pub audit: AuditConfig,
+    /// Masking of sensitive columns.
+    #[serde(default)]
+    pub masking: MaskingConfig,
+    /// Row history kept for time-travel queries.
+    #[serde(default)]
+    pub history: HistoryConfig,
+    /// Tables whose changed rows are tracked for incremental extraction.
+    #[serde(default)]
+    pub change_tracking: ChangeTrackingConfig,
+    /// Per-role resource quotas.
+    #[serde(default)]
+    pub quotas: QuotaConfig,
+    /// Migration of cold table extents to a secondary tablespace.
+    #[serde(default)]
+    pub tiering: TieringConfig,
+    /// `ANALYZE` of tables whose rows changed since their statistics were taken.
+    #[serde(default)]
+    pub auto_analyze: AutoAnalyzeConfig,
+    /// Memory of query operators.
+    #[serde(default)]
+    pub execution: ExecutionConfig,
 }
 
 impl Default for DatabaseConfig {
@@ -37,6 +72,15 @@ impl Default for DatabaseConfig {
             query_timeout: 60,
             language: Language::English,
             network: NetworkConfig::default(),
+            replication: ReplicationConfig::default(),
+            audit: AuditConfig::default(),
+            masking: MaskingConfig::default(),
+            history: HistoryConfig::default(),
+            change_tracking: ChangeTrackingConfig::default(),
+            quotas: QuotaConfig::default(),
+            tiering: TieringConfig::default(),
+            auto_analyze: AutoAnalyzeConfig::default(),
+            execution: ExecutionConfig::default(),
         }
     }
 }
@@ -99,8 +143,51 @@ pub struct NetworkConfig {
     pub host: String,
     /// Port to listen on
     pub port: u16,
+    /// TCP port of the PostgreSQL wire protocol listener (on `host`). `0`: no such listener.
+    #[serde(default)]
+    pub pg_port: u16,
     /// Maximum number of connections
     pub max_connections: usize,
+    /// Sessions each listed role may hold at once: `reporting=5, etl=2` (see [`parse_role_limits`]).
+    /// Empty: no per-role limits.
+    #[serde(default)]
+    pub max_connections_per_role: String,
+    /// Sessions idle inside an open transaction for longer are rolled back and closed (in
+    /// milliseconds). `0`: never.
+    #[serde(default)]
+    pub idle_in_transaction_timeout_ms: u64,
+    /// Requests one session may send before reading their answers; they run in order. `1`:
+    /// no pipelining.
+    #[serde(default = "default_max_pipelined_requests")]
+    pub max_pipelined_requests: usize,
+    /// Authentication rules file (see [`parse_hba_rules`]). Empty: clients are not authenticated.
+    #[serde(default)]
+    pub hba_file: String,
+    /// Frame compression offered to clients at connection startup: `lz4` or `off` (see
+    /// [`parse_compression`]). Only clients that ask for it get compressed frames.
+    #[serde(default = "default_compression")]
+    pub compression: String,
+    /// Frames with smaller payloads are sent uncompressed, even on a compressed connection.
+    #[serde(default = "default_compression_min_bytes")]
+    pub compression_min_bytes: usize,
+}
+
+/// Default `network.max_pipelined_requests`.
+pub const DEFAULT_MAX_PIPELINED_REQUESTS: usize = 32;
+
+fn default_max_pipelined_requests() -> usize {
+    DEFAULT_MAX_PIPELINED_REQUESTS
+}
+
+/// Default `network.compression_min_bytes`: below about a kilobyte lz4 saves little.
+pub const DEFAULT_COMPRESSION_MIN_BYTES: usize = 1024;
+
+fn default_compression() -> String {
+    "lz4".to_string()
+}
+
+fn default_compression_min_bytes() -> usize {
+    DEFAULT_COMPRESSION_MIN_BYTES
 }
 
 impl Default for NetworkConfig {
@@ -108,7 +195,201 @@ impl Default for NetworkConfig {
         Self {
             host: "127.0.0.1".to_string(),
             port: 5432,
+            pg_port: 0,
             max_connections: 100,
+            max_connections_per_role: String::new(),
+            idle_in_transaction_timeout_ms: 0,
+            max_pipelined_requests: default_max_pipelined_requests(),
+            hba_file: String::new(),
+            compression: default_compression(),
+            compression_min_bytes: default_compression_min_bytes(),
+        }
+    }
+}
+
+/// Replication configuration of a primary
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReplicationConfig {
+    /// Replicas (by label) whose acknowledgement a commit waits for: `ANY 2 (a, b, c)`, `2 (a, b, c)`
+    /// or a plain list for any one of them; `*` matches every replica. Empty: asynchronous.
+    pub synchronous_standby_names: String,
+    /// How long a commit waits for the synchronous quorum (in milliseconds)
+    pub synchronous_commit_timeout_ms: u64,
+    /// On timeout: `local` (report success, committed on the primary only) or `error`
+    pub synchronous_commit_timeout_policy: String,
+}
+
+impl Default for ReplicationConfig {
+    fn default() -> Self {
+        Self {
+            synchronous_standby_names: String::new(),
+            synchronous_commit_timeout_ms: 10_000,
+            synchronous_commit_timeout_policy: "local".to_string(),
+        }
+    }
+}
+
+/// Audit configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditConfig {
+    /// Tables whose reads are audited, each with an optional sampling rate:
+    /// `customers, payments=0.1`. Empty: no read auditing.
+    pub select_tables: String,
+    /// Roles whose reads are never audited (`etl, backup`)
+    pub exempt_roles: String,
+    /// Audit events written per second at most; further ones are only counted (0: no limit)
+    pub max_events_per_second: u64,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            select_tables: String::new(),
+            exempt_roles: String::new(),
+            max_events_per_second: 1000,
+        }
+    }
+}
+
+/// Masking configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaskingConfig {
+    /// Masked columns, each with an optional mask (`full` by default):
+    /// `customers.card=last4, customers.email=email, patients.ssn`. Empty: no masking.
+    pub columns: String,
+    /// Roles holding the `UNMASK` privilege, which see masked columns as stored (`admin, fraud`)
+    pub unmask_roles: String,
+}
+
+/// History configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// How far back `SELECT ... FROM t AS OF TIMESTAMP '...'` can read, in seconds (0: no time
+    /// travel)
+    pub retention_secs: u64,
+}
+
+/// Change tracking configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChangeTrackingConfig {
+    /// Tables whose committed changes are recorded in `rustdb_change_tracking`:
+    /// `orders, customers`. Empty: no change tracking.
+    pub tables: String,
+}
+
+/// Quota configuration: per-role limits as `role=limit` pairs (see [`parse_role_limits`]); roles
+/// not listed are not limited
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuotaConfig {
+    /// Statements each role's sessions may run per second, together: `reporting=50, etl=5`
+    pub queries_per_second: String,
+    /// Rows one statement of the role may read from tables and indexes
+    pub max_rows_read: String,
+    /// Bytes one statement of the role may buffer for sorts, grouping and joins
+    pub max_temp_bytes: String,
+}
+
+/// Hot/cold tiering configuration: heap file extents nobody reads move to the cold tablespace
+/// and back once read again (see `storage::tiering`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TieringConfig {
+    /// Directory of the cold tablespace, e.g. on a slower disk; relative paths are under the data
+    /// directory. Empty: no tiering
+    pub cold_tablespace: String,
+    /// Seconds between tiering runs; each run closes an access-counting window
+    pub interval_secs: u64,
+    /// Runs in a row without an access after which an extent moves to the cold tablespace
+    pub cold_after_windows: u32,
+    /// Accesses between two runs after which a cold extent moves back
+    pub promote_accesses: u64,
+    /// Extents moved per table and run
+    pub max_moves_per_run: usize,
+}
+
+impl Default for TieringConfig {
+    fn default() -> Self {
+        Self {
+            cold_tablespace: String::new(),
+            interval_secs: 60,
+            cold_after_windows: 10,
+            promote_accesses: 8,
+            max_moves_per_run: 16,
+        }
+    }
+}
+
+/// Automatic `ANALYZE`: a table is analyzed again once more than
+/// `threshold + scale_factor * rows` of its rows were inserted, updated or deleted since its last
+/// `ANALYZE` (`rows` as counted then; see `network::sql_engine::auto_analyze`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoAnalyzeConfig {
+    /// Seconds between checks for tables to analyze; 0: no automatic `ANALYZE`
+    pub interval_secs: u64,
+    /// Changed rows a table always tolerates
+    pub threshold: u64,
+    /// Fraction of a table's rows that may change on top of `threshold`
+    pub scale_factor: f64,
+}
+
+impl Default for AutoAnalyzeConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 60,
+            threshold: 50,
+            scale_factor: 0.1,
+        }
+    }
+}
+
+/// Work memory of a sort when none is configured, in bytes
+pub const DEFAULT_WORK_MEM: usize = 4 * 1024 * 1024;
+
+/// Memory of query operators
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExecutionConfig {
+    /// Bytes of rows a sort keeps in memory before it writes them to temporary runs
+    pub work_mem: usize,
+}
+
+impl Default for ExecutionConfig {
+    fn default() -> Self {
+        Self {
+            work_mem: DEFAULT_WORK_MEM,
+        }
+    }
+}
+
+/// How a masked column is shown to roles without the `UNMASK` privilege, from the least to the
+/// most strict
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColumnMask {
+    /// Only the last four letters or digits: `****-****-****-1111`
+    Last4,
+    /// First letter and domain of an e-mail address: `a****@example.com`
+    Email,
+    /// `****`; values other than strings become NULL
+    Full,
+    /// NULL
+    Null,
+}
+
+impl ColumnMask {
+    /// Name used in `masking.columns`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ColumnMask::Last4 => "last4",
+            ColumnMask::Email => "email",
+            ColumnMask::Full => "full",
+            ColumnMask::Null => "null",
         }
     }
 }
@@ -152,45 +433,32 @@ impl DatabaseConfig {
     }
 
     /// Saves configuration to a TOML file
-    pub fn to_file(&self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn to_file(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
         let content = toml::to_string_pretty(self)?;
-        std::fs::write(path, content)?;
+        crate::storage::atomic_file::write_atomic(path, content.as_bytes())?;
         Ok(())
     }
 
-    /// Loads configuration from environment variables
+    /// Loads configuration from environment variables (see [`ConfigParameter::env`])
     pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
         let mut config = DatabaseConfig::default();
-
-        if let Ok(name) = std::env::var("RUSTDB_NAME") {
-            config.name = name;
+        for param in PARAMETERS {
+            if let Ok(value) = std::env::var(param.env) {
+                param.apply(&mut config, &value, ConfigSource::Environment)?;
+            }
         }
+        Ok(config)
+    }
 
-        if let Ok(data_dir) = std::env::var("RUSTDB_DATA_DIR") {
-            config.data_directory = data_dir;
-        }
-
-        if let Ok(max_conn) = std::env::var("RUSTDB_MAX_CONNECTIONS") {
-            config.max_connections = max_conn.parse()?;
-        }
-
-        if let Ok(lang) = std::env::var("RUSTDB_LANGUAGE") {
-            config.language = lang.parse()?;
-        }
-
-        // if let Ok(host) = std::env::var("RUSTDB_HOST") {
-        //     config.network.host = host;
-        // }
-
-        // if let Ok(port) = std::env::var("RUSTDB_PORT") {
-        //     config.network.port = port.parse()?;
-        // }
-
-        // if let Ok(log_level) = std::env::var("RUSTDB_LOG_LEVEL") {
-        //     config.logging.level = log_level;
-        // }
+    /// Value of the parameter `key` in its text form (as shown by `SHOW`)
+    pub fn get(&self, key: &str) -> Result<String, ConfigError> {
+        Ok((parameter(key)?.get)(self))
+    }
 
-        Ok(config)
+    /// Sets the parameter `key` from its text form
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        let param = parameter(key)?;
+        (param.set)(self, value).map_err(|message| ConfigError::new(param.key, message))
     }
 
     /// Merges configuration with another
@@ -214,6 +482,21 @@ impl DatabaseConfig {
             self.language = other.language;
         }
         self.network = self.network.clone().merge(other.network.clone());
+        self.replication = self.replication.clone().merge(other.replication.clone());
+        self.audit = self.audit.clone().merge(other.audit.clone());
+        self.masking = self.masking.clone().merge(other.masking.clone());
+        if other.history.retention_secs != 0 {
+            self.history.retention_secs = other.history.retention_secs;
+        }
+        if !other.change_tracking.tables.is_empty() {
+            self.change_tracking.tables = other.change_tracking.tables;
+        }
+        self.quotas = self.quotas.clone().merge(other.quotas.clone());
+        self.tiering = self.tiering.clone().merge(other.tiering.clone());
+        self.auto_analyze = self.auto_analyze.clone().merge(other.auto_analyze.clone());
+        if other.execution != ExecutionConfig::default() {
+            self.execution = other.execution;
+        }
 
         // Merge nested configs
         // self.storage = self.storage.merge(other.storage);
@@ -225,31 +508,57 @@ impl DatabaseConfig {
     }
 
     /// Validates the configuration
-    pub fn validate(&self) -> Result<(), String> {
+    pub fn validate(&self) -> Result<(), ConfigError> {
         if self.name.is_empty() {
-            return Err("Database name cannot be empty".to_string());
+            return Err(ConfigError::new("name", "must not be empty"));
+        }
+        if self.data_directory.is_empty() {
+            return Err(ConfigError::new("data_directory", "must not be empty"));
         }
-
         if self.max_connections == 0 {
-            return Err("Max connections must be greater than 0".to_string());
+            return Err(ConfigError::new(
+                "max_connections",
+                "must be greater than 0",
+            ));
         }
-
-        // if self.storage.page_size == 0 {
-        //     return Err("Page size must be greater than 0".to_string());
-        // }
-
-        // if self.storage.buffer_pool_size == 0 {
-        //     return Err("Buffer pool size must be greater than 0".to_string());
-        // }
-
-        // if self.network.port == 0 {
-        //     return Err("Port must be greater than 0".to_string());
-        // }
-
-        // if self.network.max_connections == 0 {
-        //     return Err("Network max connections must be greater than 0".to_string());
-        // }
-
+        if self.connection_timeout == 0 {
+            return Err(ConfigError::new(
+                "connection_timeout",
+                "must be greater than 0",
+            ));
+        }
+        if self.query_timeout == 0 {
+            return Err(ConfigError::new("query_timeout", "must be greater than 0"));
+        }
+        if self.network.host.is_empty() {
+            return Err(ConfigError::new("network.host", "must not be empty"));
+        }
+        if self.network.max_connections == 0 {
+            return Err(ConfigError::new(
+                "network.max_connections",
+                "must be greater than 0",
+            ));
+        }
+        parse_role_limits(&self.network.max_connections_per_role)
+            .map_err(|message| ConfigError::new("network.max_connections_per_role", message))?;
+        parse_compression(&self.network.compression)
+            .map_err(|message| ConfigError::new("network.compression", message))?;
+        parse_timeout_policy(&self.replication.synchronous_commit_timeout_policy).map_err(
+            |message| ConfigError::new("replication.synchronous_commit_timeout_policy", message),
+        )?;
+        parse_audit_tables(&self.audit.select_tables)
+            .map_err(|message| ConfigError::new("audit.select_tables", message))?;
+        parse_masked_columns(&self.masking.columns)
+            .map_err(|message| ConfigError::new("masking.columns", message))?;
+        self.quotas.validate()?;
+        if self.tiering.interval_secs == 0 {
+            return Err(ConfigError::new(
+                "tiering.interval_secs",
+                "must be greater than 0",
+            ));
+        }
+        parse_scale_factor(&self.auto_analyze.scale_factor.to_string())
+            .map_err(|message| ConfigError::new("auto_analyze.scale_factor", message))?;
         Ok(())
     }
 }
@@ -303,9 +612,139 @@ impl NetworkConfig {
         if other.port != default.port {
             self.port = other.port;
         }
+        if other.pg_port != default.pg_port {
+            self.pg_port = other.pg_port;
+        }
         if other.max_connections != default.max_connections {
             self.max_connections = other.max_connections;
         }
+        if other.max_connections_per_role != default.max_connections_per_role {
+            self.max_connections_per_role = other.max_connections_per_role;
+        }
+        if other.idle_in_transaction_timeout_ms != default.idle_in_transaction_timeout_ms {
+            self.idle_in_transaction_timeout_ms = other.idle_in_transaction_timeout_ms;
+        }
+        if other.max_pipelined_requests != default.max_pipelined_requests {
+            self.max_pipelined_requests = other.max_pipelined_requests;
+        }
+        if other.hba_file != default.hba_file {
+            self.hba_file = other.hba_file;
+        }
+        if other.compression != default.compression {
+            self.compression = other.compression;
+        }
+        if other.compression_min_bytes != default.compression_min_bytes {
+            self.compression_min_bytes = other.compression_min_bytes;
+        }
+        self
+    }
+}
+
+impl ReplicationConfig {
+    fn merge(mut self, other: Self) -> Self {
+        let default = Self::default();
+        if other.synchronous_standby_names != default.synchronous_standby_names {
+            self.synchronous_standby_names = other.synchronous_standby_names;
+        }
+        if other.synchronous_commit_timeout_ms != default.synchronous_commit_timeout_ms {
+            self.synchronous_commit_timeout_ms = other.synchronous_commit_timeout_ms;
+        }
+        if other.synchronous_commit_timeout_policy != default.synchronous_commit_timeout_policy {
+            self.synchronous_commit_timeout_policy = other.synchronous_commit_timeout_policy;
+        }
+        self
+    }
+}
+
+impl AuditConfig {
+    fn merge(mut self, other: Self) -> Self {
+        let default = Self::default();
+        if other.select_tables != default.select_tables {
+            self.select_tables = other.select_tables;
+        }
+        if other.exempt_roles != default.exempt_roles {
+            self.exempt_roles = other.exempt_roles;
+        }
+        if other.max_events_per_second != default.max_events_per_second {
+            self.max_events_per_second = other.max_events_per_second;
+        }
+        self
+    }
+}
+
+impl MaskingConfig {
+    fn merge(mut self, other: Self) -> Self {
+        if !other.columns.is_empty() {
+            self.columns = other.columns;
+        }
+        if !other.unmask_roles.is_empty() {
+            self.unmask_roles = other.unmask_roles;
+        }
+        self
+    }
+}
+
+impl QuotaConfig {
+    fn merge(mut self, other: Self) -> Self {
+        if !other.queries_per_second.is_empty() {
+            self.queries_per_second = other.queries_per_second;
+        }
+        if !other.max_rows_read.is_empty() {
+            self.max_rows_read = other.max_rows_read;
+        }
+        if !other.max_temp_bytes.is_empty() {
+            self.max_temp_bytes = other.max_temp_bytes;
+        }
+        self
+    }
+
+    /// Parses every quota, naming the first malformed parameter
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for (key, value) in [
+            ("quotas.queries_per_second", &self.queries_per_second),
+            ("quotas.max_rows_read", &self.max_rows_read),
+            ("quotas.max_temp_bytes", &self.max_temp_bytes),
+        ] {
+            parse_role_limits(value).map_err(|message| ConfigError::new(key, message))?;
+        }
+        Ok(())
+    }
+}
+
+impl TieringConfig {
+    fn merge(mut self, other: Self) -> Self {
+        let defaults = Self::default();
+        if !other.cold_tablespace.is_empty() {
+            self.cold_tablespace = other.cold_tablespace;
+        }
+        if other.interval_secs != defaults.interval_secs {
+            self.interval_secs = other.interval_secs;
+        }
+        if other.cold_after_windows != defaults.cold_after_windows {
+            self.cold_after_windows = other.cold_after_windows;
+        }
+        if other.promote_accesses != defaults.promote_accesses {
+            self.promote_accesses = other.promote_accesses;
+        }
+        if other.max_moves_per_run != defaults.max_moves_per_run {
+            self.max_moves_per_run = other.max_moves_per_run;
+        }
+        self
+    }
+}
+
+impl AutoAnalyzeConfig {
+    fn merge(mut self, other: Self) -> Self {
+        let defaults = Self::default();
+        if other.interval_secs != defaults.interval_secs {
+            self.interval_secs = other.interval_secs;
+        }
+        if other.threshold != defaults.threshold {
+            self.threshold = other.threshold;
+        }
+        if other.scale_factor != defaults.scale_factor {
+            self.scale_factor = other.scale_factor;
+        }
         self
     }
 }
@@ -331,6 +770,963 @@ impl PerformanceConfig {
     }
 }
 
+/// Layer a configuration value was taken from; each layer overrides the ones before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ConfigSource {
+    /// Built-in default
+    Default,
+    /// Configuration file (`--config`, or `config.toml` when present)
+    File,
+    /// `RUSTDB_*` environment variable
+    Environment,
+    /// `--set key=value` (and dedicated flags such as `--language`)
+    CommandLine,
+    /// `SET` on a running server
+    Runtime,
+}
+
+impl ConfigSource {
+    /// Lower-case name used by `SHOW`
+    pub fn name(self) -> &'static str {
+        match self {
+            ConfigSource::Default => "default",
+            ConfigSource::File => "file",
+            ConfigSource::Environment => "environment",
+            ConfigSource::CommandLine => "command line",
+            ConfigSource::Runtime => "runtime",
+        }
+    }
+}
+
+/// Invalid configuration, naming the parameter (or file) at fault
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    /// Parameter key (`network.port`), or the file that could not be read
+    pub key: String,
+    pub message: String,
+}
+
+impl ConfigError {
+    pub fn new(key: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.key, self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Documented configuration parameter
+#[derive(Debug)]
+pub struct ConfigParameter {
+    /// Key in the configuration file, `SHOW`, `SET` and `--set` (`section.name` when nested)
+    pub key: &'static str,
+    /// Environment variable overriding the file
+    pub env: &'static str,
+    /// One-line documentation
+    pub description: &'static str,
+    /// Takes effect without a restart (`SET`, configuration reload)
+    pub runtime: bool,
+    get: fn(&DatabaseConfig) -> String,
+    set: fn(&mut DatabaseConfig, &str) -> Result<(), String>,
+}
+
+impl ConfigParameter {
+    /// Current value in `config`, in text form
+    pub fn value(&self, config: &DatabaseConfig) -> String {
+        (self.get)(config)
+    }
+
+    fn apply(
+        &self,
+        config: &mut DatabaseConfig,
+        value: &str,
+        source: ConfigSource,
+    ) -> Result<(), ConfigError> {
+        (self.set)(config, value).map_err(|message| match source {
+            ConfigSource::Environment => {
+                ConfigError::new(self.key, format!("{message} (from {})", self.env))
+            }
+            _ => ConfigError::new(self.key, message),
+        })
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String>
+where
+    T::Err: std::fmt::Display,
+{
+    value
+        .trim()
+        .parse()
+        .map_err(|e| format!("invalid value {value:?}: {e}"))
+}
+
+/// `auto_analyze.scale_factor`: a fraction, from 0 to 1.
+fn parse_scale_factor(value: &str) -> Result<f64, String> {
+    let factor: f64 = parse_number(value)?;
+    if !(0.0..=1.0).contains(&factor) {
+        return Err(format!("{factor} is not between 0 and 1"));
+    }
+    Ok(factor)
+}
+
+/// `network.compression`: whether connections may negotiate lz4 frame compression.
+pub fn parse_compression(value: &str) -> Result<bool, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "lz4" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(format!("invalid value {value:?}: expected `lz4` or `off`")),
+    }
+}
+
+fn parse_timeout_policy(value: &str) -> Result<String, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        p @ ("local" | "error") => Ok(p.to_string()),
+        _ => Err(format!(
+            "invalid value {value:?}: expected `local` or `error`"
+        )),
+    }
+}
+
+/// Per-role session limits from `role=count` pairs separated by commas (`reporting=5, etl=2`).
+/// Role names are case-insensitive and returned in lowercase.
+pub fn parse_role_limits(value: &str) -> Result<BTreeMap<String, usize>, String> {
+    let mut limits = BTreeMap::new();
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (role, count) = entry
+            .split_once('=')
+            .ok_or_else(|| format!("invalid entry {entry:?}: expected `role=count`"))?;
+        let role = role.trim().to_ascii_lowercase();
+        if role.is_empty() {
+            return Err(format!("invalid entry {entry:?}: missing role name"));
+        }
+        limits.insert(role, parse_number(count)?);
+    }
+    Ok(limits)
+}
+
+/// Audited tables and their sampling rates from `table[=rate]` entries separated by commas
+/// (`customers, payments=0.1`); the rate is in `(0, 1]` and defaults to 1. Table names are
+/// case-insensitive and returned in lowercase.
+pub fn parse_audit_tables(value: &str) -> Result<BTreeMap<String, f64>, String> {
+    let mut tables = BTreeMap::new();
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (table, rate) = match entry.split_once('=') {
+            Some((table, rate)) => (table, parse_number::<f64>(rate)?),
+            None => (entry, 1.0),
+        };
+        let table = table.trim().to_ascii_lowercase();
+        if table.is_empty() {
+            return Err(format!("invalid entry {entry:?}: missing table name"));
+        }
+        if !(rate > 0.0 && rate <= 1.0) {
+            return Err(format!(
+                "invalid entry {entry:?}: the sampling rate must be in (0, 1]"
+            ));
+        }
+        tables.insert(table, rate);
+    }
+    Ok(tables)
+}
+
+/// Masked columns and their masks from `table.column[=mask]` entries separated by commas
+/// (`customers.card=last4, patients.ssn`); the mask is `last4`, `email`, `full` (the default) or
+/// `null`. Names are case-insensitive and returned in lowercase.
+pub fn parse_masked_columns(value: &str) -> Result<BTreeMap<(String, String), ColumnMask>, String> {
+    let mut columns = BTreeMap::new();
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (column, mask) = match entry.split_once('=') {
+            Some((column, mask)) => (column, mask.trim().to_ascii_lowercase()),
+            None => (entry, "full".to_string()),
+        };
+        let mask = [
+            ColumnMask::Last4,
+            ColumnMask::Email,
+            ColumnMask::Full,
+            ColumnMask::Null,
+        ]
+        .into_iter()
+        .find(|m| m.as_str() == mask)
+        .ok_or_else(|| {
+            format!("invalid entry {entry:?}: the mask must be last4, email, full or null")
+        })?;
+        let column = column.trim().to_ascii_lowercase();
+        match column.split_once('.') {
+            Some((table, name)) if !table.is_empty() && !name.is_empty() => {
+                columns.insert((table.to_string(), name.to_string()), mask);
+            }
+            _ => return Err(format!("invalid entry {entry:?}: expected table.column")),
+        }
+    }
+    Ok(columns)
+}
+
+/// One rule of the authentication rules file (`network.hba_file`)
+///
+/// Lines read `host <roles> <address> <method> [option=value ...]`, like PostgreSQL's
+/// `pg_hba.conf` without the database column: `<roles>` is `all` or a comma-separated list,
+/// `<address>` is `all`, an IP address or a CIDR block (`10.0.0.0/8`), and the options are
+/// passed to the method. Values may be double-quoted; `#` starts a comment. The first rule
+/// matching the role and client address decides how the connection authenticates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HbaRule {
+    /// Line in the file (1-based)
+    pub line: usize,
+    /// Roles the rule applies to, in lowercase; empty for `all`
+    pub roles: Vec<String>,
+    /// Network and prefix length the client address must be in; `None` for `all`
+    pub address: Option<(IpAddr, u8)>,
+    /// Authentication method (`trust`, `reject`, `password`, `ldap`, `token` or a registered one)
+    pub method: String,
+    /// Options of the method
+    pub options: BTreeMap<String, String>,
+}
+
+impl HbaRule {
+    /// Whether the rule applies to `role` connecting from `client`
+    pub fn matches(&self, role: &str, client: IpAddr) -> bool {
+        let role_ok =
+            self.roles.is_empty() || self.roles.iter().any(|r| r.eq_ignore_ascii_case(role));
+        role_ok
+            && self.address.is_none_or(|(net, prefix)| {
+                match (net.to_canonical(), client.to_canonical()) {
+                    (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                        let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+                        u32::from(net) & mask == u32::from(ip) & mask
+                    }
+                    (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                        let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+                        u128::from(net) & mask == u128::from(ip) & mask
+                    }
+                    _ => false,
+                }
+            })
+    }
+}
+
+/// Splits a rules line into words; double quotes group words and are removed
+fn hba_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let (mut quoted, mut in_word) = (false, false);
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_word = true;
+            }
+            '#' if !quoted => break,
+            c if c.is_whitespace() && !quoted => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quoted {
+        return Err("unterminated quote".to_string());
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+fn parse_hba_address(value: &str) -> Result<Option<(IpAddr, u8)>, String> {
+    if value.eq_ignore_ascii_case("all") {
+        return Ok(None);
+    }
+    let (addr, prefix) = match value.split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix)),
+        None => (value, None),
+    };
+    let addr: IpAddr = addr
+        .parse()
+        .map_err(|_| format!("invalid address {value:?}"))?;
+    let max = if addr.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(p) => p
+            .parse::<u8>()
+            .ok()
+            .filter(|p| *p <= max)
+            .ok_or_else(|| format!("invalid prefix length in {value:?}"))?,
+        None => max,
+    };
+    Ok(Some((addr, prefix)))
+}
+
+/// Parses the authentication rules in `text`; errors name `source` and the line
+pub fn parse_hba_rules(source: &str, text: &str) -> Result<Vec<HbaRule>, ConfigError> {
+    let mut rules = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let at = |message: String| ConfigError::new(format!("{source}:{}", index + 1), message);
+        let words = hba_words(line).map_err(at)?;
+        let Some((kind, rest)) = words.split_first() else {
+            continue;
+        };
+        if !kind.eq_ignore_ascii_case("host") {
+            return Err(at(format!(
+                "unknown connection type {kind:?} (expected `host`)"
+            )));
+        }
+        let [roles, address, method, options @ ..] = rest else {
+            return Err(at(
+                "expected `host <roles> <address> <method> [option=value ...]`".to_string(),
+            ));
+        };
+        let roles = if roles.eq_ignore_ascii_case("all") {
+            Vec::new()
+        } else {
+            roles
+                .split(',')
+                .map(|r| r.trim().to_ascii_lowercase())
+                .filter(|r| !r.is_empty())
+                .collect()
+        };
+        let options = options
+            .iter()
+            .map(|o| {
+                o.split_once('=')
+                    .map(|(k, v)| (k.to_ascii_lowercase(), v.to_string()))
+                    .ok_or_else(|| at(format!("invalid option {o:?}: expected `name=value`")))
+            })
+            .collect::<Result<_, _>>()?;
+        rules.push(HbaRule {
+            line: index + 1,
+            roles,
+            address: parse_hba_address(address).map_err(at)?,
+            method: method.to_ascii_lowercase(),
+            options,
+        });
+    }
+    Ok(rules)
+}
+
+/// Reads the authentication rules file at `path` (see [`HbaRule`])
+pub fn read_hba_file(path: &Path) -> Result<Vec<HbaRule>, ConfigError> {
+    let source = path.display().to_string();
+    let text =
+        std::fs::read_to_string(path).map_err(|e| ConfigError::new(&source, e.to_string()))?;
+    parse_hba_rules(&source, &text)
+}
+
+/// Every configuration parameter, in file order
+pub static PARAMETERS: &[ConfigParameter] = &[
+    ConfigParameter {
+        key: "name",
+        env: "RUSTDB_NAME",
+        description: "Database name",
+        runtime: false,
+        get: |c| c.name.clone(),
+        set: |c, v| {
+            c.name = v.to_string();
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "data_directory",
+        env: "RUSTDB_DATA_DIR",
+        description: "Data storage directory",
+        runtime: false,
+        get: |c| c.data_directory.clone(),
+        set: |c, v| {
+            c.data_directory = v.to_string();
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "max_connections",
+        env: "RUSTDB_MAX_CONNECTIONS",
+        description: "Maximum number of connections",
+        runtime: false,
+        get: |c| c.max_connections.to_string(),
+        set: |c, v| {
+            c.max_connections = parse_number(v)?;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "connection_timeout",
+        env: "RUSTDB_CONNECTION_TIMEOUT",
+        description: "Idle connection timeout (in seconds)",
+        runtime: false,
+        get: |c| c.connection_timeout.to_string(),
+        set: |c, v| {
+            c.connection_timeout = parse_number(v)?;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "query_timeout",
+        env: "RUSTDB_QUERY_TIMEOUT",
+        description: "Query timeout (in seconds)",
+        runtime: false,
+        get: |c| c.query_timeout.to_string(),
+        set: |c, v| {
+            c.query_timeout = parse_number(v)?;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "language",
+        env: "RUSTDB_LANGUAGE",
+        description: "Interface language (en)",
+        runtime: true,
+        get: |c| c.language.to_string(),
+        set: |c, v| {
+            c.language = v.trim().parse()?;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "network.host",
+        env: "RUSTDB_HOST",
+        description: "Host the QUIC listener binds to",
+        runtime: false,
+        get: |c| c.network.host.clone(),
+        set: |c, v| {
+            c.network.host = v.trim().to_string();
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "network.port",
+        env: "RUSTDB_PORT",
+        description: "UDP port of the QUIC listener",
+        runtime: false,
+        get: |c| c.network.port.to_string(),
+        set: |c, v| {
+            c.network.port = parse_number(v)?;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "network.pg_port",
+        env: "RUSTDB_PG_PORT",
+        description: "TCP port of the PostgreSQL wire protocol listener; 0: disabled",
+        runtime: false,
+        get: |c| c.network.pg_port.to_string(),
+        set: |c, v| {
+            c.network.pg_port = parse_number(v)?;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "network.max_connections",
+        env: "RUSTDB_NETWORK_MAX_CONNECTIONS",
+        description: "Maximum number of concurrent QUIC connections",
+        runtime: false,
+        get: |c| c.network.max_connections.to_string(),
+        set: |c, v| {
+            c.network.max_connections = parse_number(v)?;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "network.max_connections_per_role",
+        env: "RUSTDB_MAX_CONNECTIONS_PER_ROLE",
+        description: "Sessions each role may hold at once (`reporting=5, etl=2`); empty: no limit",
+        runtime: true,
+        get: |c| c.network.max_connections_per_role.clone(),
+        set: |c, v| {
+            parse_role_limits(v)?;
+            c.network.max_connections_per_role = v.trim().to_string();
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "network.idle_in_transaction_timeout_ms",
+        env: "RUSTDB_IDLE_IN_TRANSACTION_TIMEOUT_MS",
+        description: "Roll back and close sessions idle in a transaction this long (ms); 0: never",
+        runtime: false,
+        get: |c| c.network.idle_in_transaction_timeout_ms.to_string(),
+        set: |c, v| {
+            c.network.idle_in_transaction_timeout_ms = parse_number(v)?;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "network.max_pipelined_requests",
+        env: "RUSTDB_MAX_PIPELINED_REQUESTS",
+        description: "Requests a session may send ahead of their answers; 1: no pipelining",
+        runtime: false,
+        get: |c| c.network.max_pipelined_requests.to_string(),
+        set: |c, v| {
+            let n: usize = parse_number(v)?;
+            if n == 0 {
+                return Err("must be at least 1".to_string());
+            }
+            c.network.max_pipelined_requests = n;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "network.hba_file",
+        env: "RUSTDB_HBA_FILE",
+        description: "Authentication rules file (pg_hba style); empty: no authentication",
+        runtime: false,
+        get: |c| c.network.hba_file.clone(),
+        set: |c, v| {
+            c.network.hba_file = v.trim().to_string();
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "network.compression",
+        env: "RUSTDB_NETWORK_COMPRESSION",
+        description: "Frame compression clients may negotiate (`lz4` or `off`)",
+        runtime: false,
+        get: |c| c.network.compression.clone(),
+        set: |c, v| {
+            parse_compression(v)?;
+            c.network.compression = v.trim().to_ascii_lowercase();
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "network.compression_min_bytes",
+        env: "RUSTDB_COMPRESSION_MIN_BYTES",
+        description: "Smallest frame payload compressed on a compressed connection (bytes)",
+        runtime: false,
+        get: |c| c.network.compression_min_bytes.to_string(),
+        set: |c, v| {
+            c.network.compression_min_bytes = parse_number(v)?;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "replication.synchronous_standby_names",
+        env: "RUSTDB_SYNCHRONOUS_STANDBY_NAMES",
+        description: "Replicas whose acknowledgement a commit waits for; empty: asynchronous",
+        runtime: true,
+        get: |c| c.replication.synchronous_standby_names.clone(),
+        set: |c, v| {
+            c.replication.synchronous_standby_names = v.trim().to_string();
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "replication.synchronous_commit_timeout_ms",
+        env: "RUSTDB_SYNCHRONOUS_COMMIT_TIMEOUT_MS",
+        description: "How long a commit waits for the synchronous quorum (in milliseconds)",
+        runtime: true,
+        get: |c| c.replication.synchronous_commit_timeout_ms.to_string(),
+        set: |c, v| {
+            c.replication.synchronous_commit_timeout_ms = parse_number(v)?;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "replication.synchronous_commit_timeout_policy",
+        env: "RUSTDB_SYNCHRONOUS_COMMIT_TIMEOUT_POLICY",
+        description: "On quorum timeout: `local` (report success) or `error`",
+        runtime: true,
+        get: |c| c.replication.synchronous_commit_timeout_policy.clone(),
+        set: |c, v| {
+            c.replication.synchronous_commit_timeout_policy = parse_timeout_policy(v)?;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "audit.select_tables",
+        env: "RUSTDB_AUDIT_SELECT_TABLES",
+        description:
+            "Tables whose reads are audited, with sampling rates: `customers, payments=0.1`",
+        runtime: true,
+        get: |c| c.audit.select_tables.clone(),
+        set: |c, v| {
+            parse_audit_tables(v)?;
+            c.audit.select_tables = v.trim().to_string();
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "audit.exempt_roles",
+        env: "RUSTDB_AUDIT_EXEMPT_ROLES",
+        description: "Roles whose reads are not audited: `etl, backup`",
+        runtime: true,
+        get: |c| c.audit.exempt_roles.clone(),
+        set: |c, v| {
+            c.audit.exempt_roles = v.trim().to_string();
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "audit.max_events_per_second",
+        env: "RUSTDB_AUDIT_MAX_EVENTS_PER_SECOND",
+        description: "Audit events written per second at most; more are only counted (0: no limit)",
+        runtime: true,
+        get: |c| c.audit.max_events_per_second.to_string(),
+        set: |c, v| {
+            c.audit.max_events_per_second = parse_number(v)?;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "masking.columns",
+        env: "RUSTDB_MASKING_COLUMNS",
+        description: "Masked columns and their masks: `customers.card=last4, patients.ssn`",
+        runtime: true,
+        get: |c| c.masking.columns.clone(),
+        set: |c, v| {
+            parse_masked_columns(v)?;
+            c.masking.columns = v.trim().to_string();
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "masking.unmask_roles",
+        env: "RUSTDB_MASKING_UNMASK_ROLES",
+        description: "Roles with the UNMASK privilege, which see masked columns: `admin, fraud`",
+        runtime: true,
+        get: |c| c.masking.unmask_roles.clone(),
+        set: |c, v| {
+            c.masking.unmask_roles = v.trim().to_string();
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "history.retention_secs",
+        env: "RUSTDB_HISTORY_RETENTION_SECS",
+        description:
+            "How far back AS OF TIMESTAMP queries can read, in seconds (0: no time travel)",
+        runtime: true,
+        get: |c| c.history.retention_secs.to_string(),
+        set: |c, v| {
+            c.history.retention_secs = parse_number(v)?;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "quotas.queries_per_second",
+        env: "RUSTDB_QUOTAS_QUERIES_PER_SECOND",
+        description: "Statements per second each role may run (`reporting=50, etl=5`); empty: no limit",
+        runtime: true,
+        get: |c| c.quotas.queries_per_second.clone(),
+        set: |c, v| {
+            parse_role_limits(v)?;
+            c.quotas.queries_per_second = v.trim().to_string();
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "quotas.max_rows_read",
+        env: "RUSTDB_QUOTAS_MAX_ROWS_READ",
+        description: "Rows one statement of each role may read (`reporting=1000000`); empty: no limit",
+        runtime: true,
+        get: |c| c.quotas.max_rows_read.clone(),
+        set: |c, v| {
+            parse_role_limits(v)?;
+            c.quotas.max_rows_read = v.trim().to_string();
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "quotas.max_temp_bytes",
+        env: "RUSTDB_QUOTAS_MAX_TEMP_BYTES",
+        description: "Bytes one statement of each role may buffer for sorts, grouping and joins; empty: no limit",
+        runtime: true,
+        get: |c| c.quotas.max_temp_bytes.clone(),
+        set: |c, v| {
+            parse_role_limits(v)?;
+            c.quotas.max_temp_bytes = v.trim().to_string();
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "tiering.cold_tablespace",
+        env: "RUSTDB_TIERING_COLD_TABLESPACE",
+        description: "Directory cold table extents move to (relative: under the data directory); empty: no tiering",
+        runtime: false,
+        get: |c| c.tiering.cold_tablespace.clone(),
+        set: |c, v| {
+            c.tiering.cold_tablespace = v.trim().to_string();
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "tiering.interval_secs",
+        env: "RUSTDB_TIERING_INTERVAL_SECS",
+        description: "Seconds between tiering runs, each closing an access-counting window",
+        runtime: false,
+        get: |c| c.tiering.interval_secs.to_string(),
+        set: |c, v| {
+            let secs: u64 = parse_number(v)?;
+            if secs == 0 {
+                return Err("must be greater than 0".to_string());
+            }
+            c.tiering.interval_secs = secs;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "tiering.cold_after_windows",
+        env: "RUSTDB_TIERING_COLD_AFTER_WINDOWS",
+        description: "Tiering runs in a row without an access after which an extent moves to the cold tablespace",
+        runtime: true,
+        get: |c| c.tiering.cold_after_windows.to_string(),
+        set: |c, v| {
+            c.tiering.cold_after_windows = parse_number(v)?;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "tiering.promote_accesses",
+        env: "RUSTDB_TIERING_PROMOTE_ACCESSES",
+        description: "Page accesses between two tiering runs after which a cold extent moves back",
+        runtime: true,
+        get: |c| c.tiering.promote_accesses.to_string(),
+        set: |c, v| {
+            c.tiering.promote_accesses = parse_number(v)?;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "tiering.max_moves_per_run",
+        env: "RUSTDB_TIERING_MAX_MOVES_PER_RUN",
+        description: "Extents moved per table and tiering run",
+        runtime: true,
+        get: |c| c.tiering.max_moves_per_run.to_string(),
+        set: |c, v| {
+            c.tiering.max_moves_per_run = parse_number(v)?;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "auto_analyze.interval_secs",
+        env: "RUSTDB_AUTO_ANALYZE_INTERVAL_SECS",
+        description: "Seconds between checks for tables whose statistics are out of date; 0: no automatic ANALYZE",
+        runtime: false,
+        get: |c| c.auto_analyze.interval_secs.to_string(),
+        set: |c, v| {
+            c.auto_analyze.interval_secs = parse_number(v)?;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "auto_analyze.threshold",
+        env: "RUSTDB_AUTO_ANALYZE_THRESHOLD",
+        description: "Changed rows after which a table is analyzed again, on top of auto_analyze.scale_factor of its rows",
+        runtime: true,
+        get: |c| c.auto_analyze.threshold.to_string(),
+        set: |c, v| {
+            c.auto_analyze.threshold = parse_number(v)?;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "auto_analyze.scale_factor",
+        env: "RUSTDB_AUTO_ANALYZE_SCALE_FACTOR",
+        description: "Fraction of a table's rows that may change before it is analyzed again (plus auto_analyze.threshold)",
+        runtime: true,
+        get: |c| c.auto_analyze.scale_factor.to_string(),
+        set: |c, v| {
+            c.auto_analyze.scale_factor = parse_scale_factor(v)?;
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "change_tracking.tables",
+        env: "RUSTDB_CHANGE_TRACKING_TABLES",
+        description: "Tables whose changed rows are recorded by commit LSN: `orders, customers`",
+        runtime: true,
+        get: |c| c.change_tracking.tables.clone(),
+        set: |c, v| {
+            c.change_tracking.tables = v.trim().to_string();
+            Ok(())
+        },
+    },
+    ConfigParameter {
+        key: "execution.work_mem",
+        env: "RUSTDB_EXECUTION_WORK_MEM",
+        description: "Bytes of rows a sort keeps in memory before it spills them to temporary files",
+        runtime: true,
+        get: |c| c.execution.work_mem.to_string(),
+        set: |c, v| {
+            let bytes: usize = parse_number(v)?;
+            if bytes == 0 {
+                return Err("must be greater than 0".to_string());
+            }
+            c.execution.work_mem = bytes;
+            Ok(())
+        },
+    },
+];
+
+/// Parameter `key` (case-insensitive)
+pub fn parameter(key: &str) -> Result<&'static ConfigParameter, ConfigError> {
+    PARAMETERS
+        .iter()
+        .find(|p| p.key.eq_ignore_ascii_case(key.trim()))
+        .ok_or_else(|| ConfigError::new(key.trim(), "unknown configuration parameter"))
+}
+
+/// Reads `path` as `(key, value)` pairs; nested tables yield dotted keys
+fn read_config_file(path: &Path) -> Result<Vec<(String, String)>, ConfigError> {
+    fn flatten(prefix: &str, table: toml::Table, out: &mut Vec<(String, String)>) {
+        for (name, value) in table {
+            let key = format!("{prefix}{name}");
+            match value {
+                toml::Value::Table(t) => flatten(&format!("{key}."), t, out),
+                toml::Value::String(s) => out.push((key, s)),
+                other => out.push((key, other.to_string())),
+            }
+        }
+    }
+    let file = path.display().to_string();
+    let content =
+        std::fs::read_to_string(path).map_err(|e| ConfigError::new(&file, e.to_string()))?;
+    let table: toml::Table =
+        toml::from_str(&content).map_err(|e| ConfigError::new(&file, e.to_string()))?;
+    let mut entries = Vec::new();
+    flatten("", table, &mut entries);
+    Ok(entries)
+}
+
+/// Outcome of [`LayeredConfig::reload`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigReload {
+    /// Runtime parameters whose value changed
+    pub applied: Vec<&'static str>,
+    /// Parameters that changed but keep their value until the server restarts
+    pub pending_restart: Vec<&'static str>,
+}
+
+/// Effective configuration and the layer each value comes from
+///
+/// The file and command-line layers are remembered, so [`Self::reload`] can re-read the file and
+/// environment while keeping flags and runtime `SET`s on top.
+#[derive(Debug, Clone, Default)]
+pub struct LayeredConfig {
+    config: DatabaseConfig,
+    sources: BTreeMap<&'static str, ConfigSource>,
+    file: Option<PathBuf>,
+    command_line: Vec<(String, String)>,
+    runtime: BTreeMap<&'static str, String>,
+}
+
+impl LayeredConfig {
+    /// Loads the defaults, `file` (when given), the environment and the `(key, value)` pairs given
+    /// on the command line, then validates the result
+    pub fn load(
+        file: Option<&Path>,
+        command_line: Vec<(String, String)>,
+    ) -> Result<Self, ConfigError> {
+        let mut layered = Self {
+            file: file.map(Path::to_path_buf),
+            command_line,
+            ..Self::default()
+        };
+        layered.rebuild()?;
+        Ok(layered)
+    }
+
+    /// Effective configuration
+    pub fn config(&self) -> &DatabaseConfig {
+        &self.config
+    }
+
+    /// Layer the value of `key` comes from
+    pub fn source(&self, key: &str) -> ConfigSource {
+        parameter(key)
+            .ok()
+            .and_then(|p| self.sources.get(p.key).copied())
+            .unwrap_or(ConfigSource::Default)
+    }
+
+    /// Configuration file, if one was loaded
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    /// Sets a runtime-changeable parameter; the value wins over every other layer, including on
+    /// reload. Nothing changes when the new configuration does not validate.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<&'static ConfigParameter, ConfigError> {
+        let param = parameter(key)?;
+        if !param.runtime {
+            return Err(ConfigError::new(
+                param.key,
+                "cannot be changed without restarting the server",
+            ));
+        }
+        let mut next = self.clone();
+        next.runtime.insert(param.key, value.to_string());
+        next.rebuild()?;
+        *self = next;
+        Ok(param)
+    }
+
+    /// Re-reads the file and the environment. Runtime parameters take their new values; the
+    /// others keep their current value until restart and are reported as pending.
+    pub fn reload(&mut self) -> Result<ConfigReload, ConfigError> {
+        let mut next = self.clone();
+        next.rebuild()?;
+        let mut reload = ConfigReload::default();
+        for param in PARAMETERS {
+            let current = param.value(&self.config);
+            if param.value(&next.config) == current {
+                continue;
+            }
+            if param.runtime {
+                reload.applied.push(param.key);
+            } else {
+                param.apply(&mut next.config, &current, ConfigSource::Default)?;
+                match self.sources.get(param.key) {
+                    Some(source) => next.sources.insert(param.key, *source),
+                    None => next.sources.remove(param.key),
+                };
+                reload.pending_restart.push(param.key);
+            }
+        }
+        *self = next;
+        Ok(reload)
+    }
+
+    fn rebuild(&mut self) -> Result<(), ConfigError> {
+        let mut config = DatabaseConfig::default();
+        let mut sources = BTreeMap::new();
+        let mut apply = |key: &str, value: &str, source: ConfigSource| {
+            let param = parameter(key)?;
+            param.apply(&mut config, value, source)?;
+            sources.insert(param.key, source);
+            Ok::<_, ConfigError>(())
+        };
+        if let Some(path) = &self.file {
+            for (key, value) in read_config_file(path)? {
+                apply(&key, &value, ConfigSource::File)?;
+            }
+        }
+        for param in PARAMETERS {
+            if let Ok(value) = std::env::var(param.env) {
+                apply(param.key, &value, ConfigSource::Environment)?;
+            }
+        }
+        for (key, value) in &self.command_line {
+            apply(key, value, ConfigSource::CommandLine)?;
+        }
+        for (key, value) in &self.runtime {
+            apply(key, value, ConfigSource::Runtime)?;
+        }
+        config.validate()?;
+        self.config = config;
+        self.sources = sources;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -361,6 +1757,101 @@ mod tests {
         assert!(config.validate().is_err());
     }
 
+    #[test]
+    fn role_limits_parse_and_reject_malformed_entries() {
+        let limits = parse_role_limits(" Reporting=5, etl = 2 ,").unwrap();
+        assert_eq!(limits.get("reporting"), Some(&5));
+        assert_eq!(limits.get("etl"), Some(&2));
+        assert!(parse_role_limits("").unwrap().is_empty());
+        assert!(parse_role_limits("reporting").is_err());
+        assert!(parse_role_limits("=3").is_err());
+        assert!(parse_role_limits("etl=many").is_err());
+
+        let mut config = DatabaseConfig::default();
+        assert!(config
+            .set("network.max_connections_per_role", "etl=x")
+            .is_err());
+        config
+            .set("network.max_connections_per_role", "etl=2")
+            .unwrap();
+        assert_eq!(config.network.max_connections_per_role, "etl=2");
+    }
+
+    #[test]
+    fn quotas_are_validated_per_parameter() {
+        let mut config = DatabaseConfig::default();
+        assert!(config.set("quotas.max_rows_read", "etl").is_err());
+        config.set("quotas.queries_per_second", "ETL=5").unwrap();
+        assert_eq!(config.quotas.queries_per_second, "ETL=5");
+
+        config.quotas.max_temp_bytes = "etl=lots".to_string();
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("quotas.max_temp_bytes"), "{err}");
+    }
+
+    #[test]
+    fn audit_tables_parse_rates_and_reject_out_of_range_ones() {
+        let tables = parse_audit_tables("Customers, payments = 0.25,").unwrap();
+        assert_eq!(tables.get("customers"), Some(&1.0));
+        assert_eq!(tables.get("payments"), Some(&0.25));
+        assert!(parse_audit_tables("").unwrap().is_empty());
+        assert!(parse_audit_tables("payments=0").is_err());
+        assert!(parse_audit_tables("payments=1.5").is_err());
+        assert!(parse_audit_tables("=0.5").is_err());
+
+        let mut config = DatabaseConfig::default();
+        assert!(config.set("audit.select_tables", "t=often").is_err());
+        config.set("audit.select_tables", "t=0.5").unwrap();
+        assert_eq!(config.audit.select_tables, "t=0.5");
+    }
+
+    #[test]
+    fn masked_columns_parse_masks_and_reject_bare_names() {
+        let columns = parse_masked_columns("Customers.Card = LAST4, patients.ssn,").unwrap();
+        let mask = |t: &str, c: &str| columns.get(&(t.to_string(), c.to_string())).copied();
+        assert_eq!(mask("customers", "card"), Some(ColumnMask::Last4));
+        assert_eq!(mask("patients", "ssn"), Some(ColumnMask::Full));
+        assert!(parse_masked_columns("card=last4").is_err());
+        assert!(parse_masked_columns("customers.card=hash").is_err());
+
+        let mut config = DatabaseConfig::default();
+        assert!(config.set("masking.columns", ".card").is_err());
+        config.set("masking.columns", "t.c=null").unwrap();
+        assert_eq!(config.masking.columns, "t.c=null");
+    }
+
+    #[test]
+    fn hba_rules_parse_and_match_roles_and_networks() {
+        let rules = parse_hba_rules(
+            "hba.conf",
+            "# local admins need nothing\n\
+             host all 127.0.0.1 trust\n\
+             host etl,Reporting 10.0.0.0/8 ldap ldapserver=ldap.internal \"ldapsuffix=,ou=people,dc=example\"\n\
+             host all ::/0 token secret=s3cret # anything else\n",
+        )
+        .unwrap();
+        assert_eq!(rules.len(), 3);
+        assert_eq!(rules[1].line, 3);
+        assert_eq!(rules[1].roles, vec!["etl", "reporting"]);
+        assert_eq!(rules[1].options["ldapsuffix"], ",ou=people,dc=example");
+
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        assert!(rules[0].matches("anyone", ip("127.0.0.1")));
+        assert!(rules[0].matches("anyone", ip("::ffff:127.0.0.1")));
+        assert!(!rules[0].matches("anyone", ip("127.0.0.2")));
+        assert!(rules[1].matches("REPORTING", ip("10.1.2.3")));
+        assert!(!rules[1].matches("admin", ip("10.1.2.3")));
+        assert!(!rules[1].matches("etl", ip("11.0.0.1")));
+        assert!(rules[2].matches("etl", ip("2001:db8::1")));
+        assert!(!rules[2].matches("etl", ip("192.0.2.1")));
+
+        let err = parse_hba_rules("hba.conf", "host all 10.0.0.0/33 trust").unwrap_err();
+        assert_eq!(err.key, "hba.conf:1");
+        assert!(parse_hba_rules("hba.conf", "local all trust").is_err());
+        assert!(parse_hba_rules("hba.conf", "host all all").is_err());
+        assert!(parse_hba_rules("hba.conf", "host all all ldap server").is_err());
+    }
+
     #[test]
     fn test_config_merge() {
         let mut config1 = DatabaseConfig::default();
@@ -465,4 +1956,126 @@ mod tests {
         assert!(!pm.enable_query_optimization);
         assert!(!pm.enable_parallel_execution);
     }
+
+    #[test]
+    fn test_layered_config_precedence_and_sources() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        let dir = TempDir::new().expect("tempdir");
+        let path = dir.path().join("rustdb.toml");
+        std::fs::write(
+            &path,
+            "name = \"filedb\"\nquery_timeout = 5\n\n[network]\nport = 6000\nhost = \"0.0.0.0\"\n",
+        )
+        .unwrap();
+        std::env::set_var("RUSTDB_PORT", "6001");
+        std::env::set_var("RUSTDB_QUERY_TIMEOUT", "7");
+        let loaded = LayeredConfig::load(
+            Some(&path),
+            vec![("Network.Port".to_string(), "6002".to_string())],
+        );
+        std::env::remove_var("RUSTDB_PORT");
+        std::env::remove_var("RUSTDB_QUERY_TIMEOUT");
+
+        let layered = loaded.expect("load");
+        let config = layered.config();
+        assert_eq!(config.name, "filedb");
+        assert_eq!(config.network.host, "0.0.0.0");
+        assert_eq!(config.query_timeout, 7);
+        assert_eq!(config.network.port, 6002);
+        assert_eq!(config.max_connections, 100);
+        assert_eq!(layered.source("name"), ConfigSource::File);
+        assert_eq!(layered.source("query_timeout"), ConfigSource::Environment);
+        assert_eq!(layered.source("network.port"), ConfigSource::CommandLine);
+        assert_eq!(layered.source("max_connections"), ConfigSource::Default);
+        assert_eq!(config.get("network.port").unwrap(), "6002");
+    }
+
+    #[test]
+    fn test_config_errors_name_the_offending_key() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        let dir = TempDir::new().expect("tempdir");
+        let path = dir.path().join("rustdb.toml");
+
+        std::fs::write(&path, "[network]\nport = \"many\"\n").unwrap();
+        let err = LayeredConfig::load(Some(&path), Vec::new()).unwrap_err();
+        assert_eq!(err.key, "network.port");
+
+        std::fs::write(&path, "[network]\nportt = 1\n").unwrap();
+        let err = LayeredConfig::load(Some(&path), Vec::new()).unwrap_err();
+        assert_eq!(err.key, "network.portt");
+        assert!(err.to_string().contains("unknown configuration parameter"));
+
+        let err = LayeredConfig::load(
+            None,
+            vec![("network.max_connections".to_string(), "0".to_string())],
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "network.max_connections: must be greater than 0"
+        );
+
+        std::env::set_var("RUSTDB_SYNCHRONOUS_COMMIT_TIMEOUT_POLICY", "retry");
+        let err = LayeredConfig::load(None, Vec::new()).unwrap_err();
+        std::env::remove_var("RUSTDB_SYNCHRONOUS_COMMIT_TIMEOUT_POLICY");
+        assert_eq!(err.key, "replication.synchronous_commit_timeout_policy");
+        assert!(err
+            .message
+            .contains("RUSTDB_SYNCHRONOUS_COMMIT_TIMEOUT_POLICY"));
+    }
+
+    #[test]
+    fn test_layered_config_runtime_set_and_reload() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        let dir = TempDir::new().expect("tempdir");
+        let path = dir.path().join("rustdb.toml");
+        std::fs::write(&path, "query_timeout = 5\n").unwrap();
+        let mut layered = LayeredConfig::load(Some(&path), Vec::new()).unwrap();
+
+        let err = layered.set("query_timeout", "9").unwrap_err();
+        assert_eq!(err.key, "query_timeout");
+        assert!(layered
+            .set("replication.synchronous_commit_timeout_ms", "soon")
+            .is_err());
+        layered
+            .set("replication.synchronous_commit_timeout_ms", "250")
+            .unwrap();
+        assert_eq!(
+            layered.config().replication.synchronous_commit_timeout_ms,
+            250
+        );
+        assert_eq!(
+            layered.source("replication.synchronous_commit_timeout_ms"),
+            ConfigSource::Runtime
+        );
+
+        std::fs::write(
+            &path,
+            "query_timeout = 9\n[replication]\nsynchronous_standby_names = \"a\"\nsynchronous_commit_timeout_ms = 1\n",
+        )
+        .unwrap();
+        let reload = layered.reload().unwrap();
+        assert_eq!(
+            reload.applied,
+            vec!["replication.synchronous_standby_names"]
+        );
+        assert_eq!(reload.pending_restart, vec!["query_timeout"]);
+        assert_eq!(layered.config().query_timeout, 5);
+        assert_eq!(layered.config().replication.synchronous_standby_names, "a");
+        assert_eq!(
+            layered.config().replication.synchronous_commit_timeout_ms,
+            250
+        );
+    }
+
+    #[test]
+    fn test_every_parameter_roundtrips_through_text() {
+        let mut config = DatabaseConfig::default();
+        for param in PARAMETERS {
+            let value = param.value(&config);
+            config.set(param.key, &value).unwrap();
+            assert_eq!(config.get(param.key).unwrap(), value);
+            assert!(!param.description.is_empty());
+        }
+    }
 }
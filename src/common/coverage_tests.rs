@@ -1,13 +1,15 @@
 //! Additional unit tests to increase coverage (errors, config, types).
 
 use crate::common::config::{
-    DatabaseConfig, LoggingConfig, NetworkConfig, PerformanceConfig, StorageConfig,
+    AuditConfig, AutoAnalyzeConfig, ChangeTrackingConfig, DatabaseConfig, ExecutionConfig,
+    HistoryConfig, LoggingConfig, MaskingConfig, NetworkConfig, PerformanceConfig, QuotaConfig,
+    ReplicationConfig, StorageConfig, TieringConfig,
 };
 use crate::common::error::Error;
 use crate::common::i18n::Language;
 use crate::common::i18n::MessageKey;
 use crate::common::types::PAGE_SIZE;
-use crate::common::types::{ColumnValue, DataType};
+use crate::common::types::{ColumnValue, DataType, Row, RowSchema};
 use crate::common::utils::{
     calculate_hash, calculate_max_record_size, calculate_optimal_page_size,
     calculate_page_header_size, calculate_pages_needed, can_fit_record_on_page, format_bytes,
@@ -16,6 +18,7 @@ use crate::common::utils::{
 };
 use crate::test_env::ENV_LOCK;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 #[test]
 fn test_error_constructors_and_display() {
@@ -83,6 +86,18 @@ fn test_database_config_file_roundtrip() -> Result<(), Box<dyn std::error::Error
         query_timeout: 22,
         language: Language::English,
         network: NetworkConfig::default(),
+        replication: ReplicationConfig {
+            synchronous_standby_names: "ANY 1 (r1, r2)".into(),
+            ..Default::default()
+        },
+        audit: AuditConfig::default(),
+        masking: MaskingConfig::default(),
+        history: HistoryConfig::default(),
+        change_tracking: ChangeTrackingConfig::default(),
+        quotas: QuotaConfig::default(),
+        tiering: TieringConfig::default(),
+        auto_analyze: AutoAnalyzeConfig::default(),
+        execution: ExecutionConfig::default(),
     };
     original.to_file(&path)?;
     let loaded = DatabaseConfig::from_file(&path)?;
@@ -93,6 +108,7 @@ fn test_database_config_file_roundtrip() -> Result<(), Box<dyn std::error::Error
     assert_eq!(loaded.query_timeout, original.query_timeout);
     assert_eq!(loaded.network.host, original.network.host);
     assert_eq!(loaded.network.port, original.network.port);
+    assert_eq!(loaded.replication, original.replication);
     Ok(())
 }
 
@@ -264,3 +280,33 @@ fn test_utils_page_and_hash_heuristics() {
     let _ = should_expand_hash_table(16, 20);
     let _ = should_shrink_hash_table(1024, 2);
 }
+
+#[test]
+fn test_row_positional_layout_shares_schema() {
+    let schema = Arc::new(RowSchema::new(["id", "name", "id"]));
+    assert_eq!(schema.columns(), ["id", "name"]);
+    assert_eq!(schema.position("name"), Some(1));
+
+    let int = |v| ColumnValue::new(DataType::Integer(v));
+    let mut a = Row::from_values(schema.clone(), vec![int(1)]);
+    let mut b = Row::with_schema(schema.clone());
+    b.set_value_at(1, ColumnValue::new(DataType::Text("x".to_string())));
+    assert!(Arc::ptr_eq(a.schema(), b.schema()));
+    assert_eq!(a.get_value("id"), Some(&int(1)));
+    assert!(!a.has_column("name"));
+    assert_eq!(b.get_value_at(1), b.get_value("name"));
+    assert_eq!(b.len(), 1);
+
+    // A column outside the schema copies the schema for this row only
+    a.set_value("extra", int(2));
+    assert_eq!(a.column_names().collect::<Vec<_>>(), ["id", "extra"]);
+    assert_eq!(schema.len(), 2);
+    assert!(!b.has_column("extra"));
+    assert_eq!(a.remove_value("id"), Some(int(1)));
+    assert_eq!(a.to_map().len(), 1);
+
+    let json = serde_json::to_string(&a).unwrap();
+    let back: Row = serde_json::from_str(&json).unwrap();
+    assert_eq!(back.to_map(), a.to_map());
+    assert_eq!(back.version, a.version);
+}
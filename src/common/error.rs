@@ -90,6 +90,10 @@ pub enum Error {
     /// Conflict error
     #[error("Conflict error: {message}")]
     Conflict { message: String },
+
+    /// A query went past a limit of the resource governor
+    #[error("Resource limit exceeded: {message}")]
+    ResourceLimit { message: String },
 }
 
 /// Result type for rustdb
@@ -141,6 +145,13 @@ impl Error {
         }
     }
 
+    /// Creates a resource limit error
+    pub fn resource_limit(message: impl Into<String>) -> Self {
+        Self::ResourceLimit {
+            message: message.into(),
+        }
+    }
+
     /// Creates a semantic analysis error
     pub fn semantic_analysis(message: impl Into<String>) -> Self {
         Self::SemanticAnalysis {
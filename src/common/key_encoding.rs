@@ -0,0 +1,398 @@
+//! Order-preserving binary encoding of column values for rustdb
+//!
+//! [`encode_value`] writes a [`ColumnValue`] so that comparing the encoded bytes
+//! (memcmp) gives the same order as [`compare_values`]. Keys of several columns are the
+//! concatenation of the encoded values, and [`encode_value_descending`] produces the reverse
+//! order for descending sort keys, so joins, sorts and indexes can compare plain byte strings.
+//!
+//! Order and layout (one tag byte, then the payload):
+//!
+//! - booleans (`false < true`);
+//! - numbers: all integer and floating point types share one domain, so `Integer(5)`,
+//!   `BigInt(5)` and `Double(5.0)` are equal. The payload is the value rounded to `f64` in
+//!   sign-flipped big-endian form followed by the integer remainder lost by the rounding, which
+//!   keeps 64-bit integers exact. `-0.0` equals `0.0` and NaN sorts after `+inf`;
+//! - character strings (`Char`, `Varchar`, `Text`), by bytes;
+//! - `Date`, `Time`, `Timestamp` (each its own domain): ISO 8601 values
+//!   (`YYYY-MM-DD`, `HH:MM:SS[.fffffffff]`, `YYYY-MM-DD HH:MM:SS[.f]` or with `T`) are ordered by
+//!   the instant they denote, values in any other format after them, by bytes;
+//! - blobs, by bytes;
+//! - NULL last.
+//!
+//! [`sql_compare`] is the SQL comparison in the same order: unknown (`None`) when a value is NULL
+//! or the values are of different domains.
+//!
+//! Variable-length payloads escape `0x00` as `0x00 0xFF` and end with `0x00 0x01`, so no
+//! encoded value is a prefix of another.
+
+use crate::common::types::{ColumnValue, DataType};
+use std::cmp::Ordering;
+
+const TAG_BOOLEAN: u8 = 0x10;
+const TAG_NUMBER: u8 = 0x20;
+const TAG_STRING: u8 = 0x30;
+const TAG_DATE: u8 = 0x40;
+const TAG_TIME: u8 = 0x41;
+const TAG_TIMESTAMP: u8 = 0x42;
+const TAG_BLOB: u8 = 0x50;
+const TAG_NULL: u8 = 0xFF;
+
+/// Appends the order-preserving encoding of `value` to `out`
+pub fn encode_value(value: &ColumnValue, out: &mut Vec<u8>) {
+    match KeyValue::of(value) {
+        KeyValue::Boolean(b) => out.extend_from_slice(&[TAG_BOOLEAN, b as u8]),
+        KeyValue::Number(rounded, remainder) => {
+            out.push(TAG_NUMBER);
+            out.extend_from_slice(&rounded.to_be_bytes());
+            out.extend_from_slice(&flip_sign(remainder).to_be_bytes());
+        }
+        KeyValue::String(bytes) => {
+            out.push(TAG_STRING);
+            encode_bytes(bytes, out);
+        }
+        KeyValue::Date(t) => encode_temporal(TAG_DATE, t, out),
+        KeyValue::Time(t) => encode_temporal(TAG_TIME, t, out),
+        KeyValue::Timestamp(t) => encode_temporal(TAG_TIMESTAMP, t, out),
+        KeyValue::Blob(bytes) => {
+            out.push(TAG_BLOB);
+            encode_bytes(bytes, out);
+        }
+        KeyValue::Null => out.push(TAG_NULL),
+    }
+}
+
+/// Appends an encoding of `value` that sorts in the reverse order of [`encode_value`]
+pub fn encode_value_descending(value: &ColumnValue, out: &mut Vec<u8>) {
+    let start = out.len();
+    encode_value(value, out);
+    for byte in &mut out[start..] {
+        *byte = !*byte;
+    }
+}
+
+/// Encodes a composite key, ordered by the first value, then the second, ...
+pub fn encode_key<'a>(values: impl IntoIterator<Item = &'a ColumnValue>) -> Vec<u8> {
+    let mut out = Vec::new();
+    for value in values {
+        encode_value(value, &mut out);
+    }
+    out
+}
+
+/// Compares two values in the order of their encodings, without encoding them
+pub fn compare_values(a: &ColumnValue, b: &ColumnValue) -> Ordering {
+    KeyValue::of(a).cmp(&KeyValue::of(b))
+}
+
+/// SQL comparison of two values: their order when both are non-NULL and of the same domain
+/// (numbers of any type compare as numbers), else unknown
+pub fn sql_compare(a: &ColumnValue, b: &ColumnValue) -> Option<Ordering> {
+    let (a, b) = (KeyValue::of(a), KeyValue::of(b));
+    if a == KeyValue::Null || std::mem::discriminant(&a) != std::mem::discriminant(&b) {
+        return None;
+    }
+    Some(a.cmp(&b))
+}
+
+/// Temporal value: the parsed instant as `(seconds, nanoseconds)`, or the raw text
+type TemporalKey<'a> = std::result::Result<(i64, u32), &'a [u8]>;
+
+/// Normalized, borrowed form of a value; the derived order is the encoding order
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum KeyValue<'a> {
+    Boolean(bool),
+    /// Order-preserving bits of the value rounded to `f64`, then the rounding remainder
+    Number(u64, i64),
+    String(&'a [u8]),
+    Date(TemporalKey<'a>),
+    Time(TemporalKey<'a>),
+    Timestamp(TemporalKey<'a>),
+    Blob(&'a [u8]),
+    Null,
+}
+
+impl<'a> KeyValue<'a> {
+    fn of(value: &'a ColumnValue) -> Self {
+        if value.is_null {
+            return KeyValue::Null;
+        }
+        match &value.data_type {
+            DataType::Null => KeyValue::Null,
+            DataType::Boolean(b) => KeyValue::Boolean(*b),
+            DataType::TinyInt(v) => integer(*v as i64),
+            DataType::SmallInt(v) => integer(*v as i64),
+            DataType::Integer(v) => integer(*v as i64),
+            DataType::BigInt(v) => integer(*v),
+            DataType::Float(v) => KeyValue::Number(float_bits(*v as f64), 0),
+            DataType::Double(v) => KeyValue::Number(float_bits(*v), 0),
+            DataType::Char(s) | DataType::Varchar(s) | DataType::Text(s) => {
+                KeyValue::String(s.as_bytes())
+            }
+            DataType::Date(s) => KeyValue::Date(parse_date(s).map(|d| (d, 0)).ok_or(s.as_bytes())),
+            DataType::Time(s) => KeyValue::Time(parse_time(s).ok_or(s.as_bytes())),
+            DataType::Timestamp(s) => KeyValue::Timestamp(parse_timestamp(s).ok_or(s.as_bytes())),
+            DataType::Blob(b) => KeyValue::Blob(b),
+        }
+    }
+}
+
+fn integer(v: i64) -> KeyValue<'static> {
+    let rounded = v as f64;
+    // Exact: `rounded` is an integer within 2^63 of `v`
+    let remainder = (v as i128 - rounded as i128) as i64;
+    KeyValue::Number(float_bits(rounded), remainder)
+}
+
+/// Maps an `f64` to bits whose unsigned order is the numeric order
+fn float_bits(v: f64) -> u64 {
+    let v = if v.is_nan() {
+        f64::NAN
+    } else if v == 0.0 {
+        0.0
+    } else {
+        v
+    };
+    let bits = v.to_bits();
+    if bits >> 63 == 1 {
+        !bits
+    } else {
+        bits | (1 << 63)
+    }
+}
+
+fn flip_sign(v: i64) -> u64 {
+    (v as u64) ^ (1 << 63)
+}
+
+fn encode_bytes(bytes: &[u8], out: &mut Vec<u8>) {
+    for &byte in bytes {
+        out.push(byte);
+        if byte == 0 {
+            out.push(0xFF);
+        }
+    }
+    out.extend_from_slice(&[0x00, 0x01]);
+}
+
+fn encode_temporal(tag: u8, value: TemporalKey<'_>, out: &mut Vec<u8>) {
+    out.push(tag);
+    match value {
+        Ok((seconds, nanos)) => {
+            out.push(0);
+            out.extend_from_slice(&flip_sign(seconds).to_be_bytes());
+            out.extend_from_slice(&nanos.to_be_bytes());
+        }
+        Err(raw) => {
+            out.push(1);
+            encode_bytes(raw, out);
+        }
+    }
+}
+
+/// `[-]YYYY-MM-DD` as a number increasing with the date (not a day count)
+pub(crate) fn parse_date(s: &str) -> Option<i64> {
+    let (negative, rest) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let mut parts = rest.splitn(3, '-');
+    let year = parse_digits(parts.next()?, 1, 6)? as i64;
+    let month = parse_digits(parts.next()?, 1, 2)?;
+    let day = parse_digits(parts.next()?, 1, 2)?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let year = if negative { -year } else { year };
+    Some(year * 512 + (month * 32 + day) as i64)
+}
+
+/// `HH:MM[:SS[.fffffffff]]` as seconds since midnight and nanoseconds
+pub(crate) fn parse_time(s: &str) -> Option<(i64, u32)> {
+    let (clock, fraction) = match s.split_once('.') {
+        Some((clock, fraction)) => (clock, Some(fraction)),
+        None => (s, None),
+    };
+    let mut parts = clock.splitn(3, ':');
+    let hours = parse_digits(parts.next()?, 1, 2)?;
+    let minutes = parse_digits(parts.next()?, 2, 2)?;
+    let seconds = match parts.next() {
+        Some(p) => parse_digits(p, 2, 2)?,
+        None if fraction.is_none() => 0,
+        None => return None,
+    };
+    if hours > 24 || minutes > 59 || seconds > 60 {
+        return None;
+    }
+    let nanos = match fraction {
+        Some(f) => {
+            let digits = parse_digits(f, 1, 9)?;
+            digits * 10u32.pow(9 - f.len() as u32)
+        }
+        None => 0,
+    };
+    Some(((hours * 3600 + minutes * 60 + seconds) as i64, nanos))
+}
+
+/// `DATE TIME` or `DATEtTIME` as a number increasing with the instant, and nanoseconds
+pub(crate) fn parse_timestamp(s: &str) -> Option<(i64, u32)> {
+    let split = s.rfind([' ', 'T'])?;
+    let date = parse_date(&s[..split])?;
+    let (seconds, nanos) = parse_time(&s[split + 1..])?;
+    Some((date * 100_000 + seconds, nanos))
+}
+
+fn parse_digits(s: &str, min_len: usize, max_len: usize) -> Option<u32> {
+    if s.len() < min_len || s.len() > max_len || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(data_type: DataType) -> ColumnValue {
+        ColumnValue::new(data_type)
+    }
+
+    fn samples() -> Vec<ColumnValue> {
+        vec![
+            ColumnValue::null(),
+            v(DataType::Boolean(false)),
+            v(DataType::Boolean(true)),
+            v(DataType::BigInt(i64::MIN)),
+            v(DataType::BigInt(i64::MIN + 1)),
+            v(DataType::Double(f64::NEG_INFINITY)),
+            v(DataType::Double(-1.5)),
+            v(DataType::Integer(-1)),
+            v(DataType::TinyInt(0)),
+            v(DataType::Double(-0.0)),
+            v(DataType::Float(0.5)),
+            v(DataType::SmallInt(2)),
+            v(DataType::Integer(10)),
+            v(DataType::BigInt(9_007_199_254_740_993)),
+            v(DataType::Double(9_007_199_254_740_992.0)),
+            v(DataType::BigInt(i64::MAX - 1)),
+            v(DataType::BigInt(i64::MAX)),
+            v(DataType::Double(f64::INFINITY)),
+            v(DataType::Double(f64::NAN)),
+            v(DataType::Varchar(String::new())),
+            v(DataType::Varchar("a".to_string())),
+            v(DataType::Text("a\0".to_string())),
+            v(DataType::Char("ab".to_string())),
+            v(DataType::Date("999-12-31".to_string())),
+            v(DataType::Date("2024-1-5".to_string())),
+            v(DataType::Date("2024-01-10".to_string())),
+            v(DataType::Date("not a date".to_string())),
+            v(DataType::Time("9:30:00".to_string())),
+            v(DataType::Time("09:30:00.5".to_string())),
+            v(DataType::Time("10:00:00".to_string())),
+            v(DataType::Timestamp("2024-01-01 23:59:59".to_string())),
+            v(DataType::Timestamp("2024-01-02T00:00:00".to_string())),
+            v(DataType::Blob(vec![0, 1])),
+            v(DataType::Blob(vec![1])),
+        ]
+    }
+
+    #[test]
+    fn encoding_order_matches_compare_values() {
+        let values = samples();
+        for a in &values {
+            for b in &values {
+                let (mut ea, mut eb) = (Vec::new(), Vec::new());
+                encode_value(a, &mut ea);
+                encode_value(b, &mut eb);
+                assert_eq!(ea.cmp(&eb), compare_values(a, b), "{a:?} vs {b:?}");
+
+                let (mut da, mut db) = (Vec::new(), Vec::new());
+                encode_value_descending(a, &mut da);
+                encode_value_descending(b, &mut db);
+                assert_eq!(da.cmp(&db), compare_values(b, a), "desc {a:?} vs {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn numbers_and_temporals_compare_by_value() {
+        let cmp = |a: DataType, b: DataType| compare_values(&v(a), &v(b));
+        assert_eq!(
+            cmp(DataType::Integer(5), DataType::BigInt(5)),
+            Ordering::Equal
+        );
+        assert_eq!(
+            cmp(DataType::Integer(5), DataType::Double(5.0)),
+            Ordering::Equal
+        );
+        assert_eq!(
+            cmp(DataType::Integer(9), DataType::Integer(10)),
+            Ordering::Less
+        );
+        assert_eq!(
+            cmp(DataType::Integer(-2), DataType::Float(-1.5)),
+            Ordering::Less
+        );
+        assert_eq!(
+            cmp(
+                DataType::BigInt(9_007_199_254_740_993),
+                DataType::Double(9_007_199_254_740_992.0)
+            ),
+            Ordering::Greater
+        );
+        assert_eq!(
+            cmp(
+                DataType::Date("2024-02-01".to_string()),
+                DataType::Date("2024-1-31".to_string())
+            ),
+            Ordering::Greater
+        );
+        assert_eq!(
+            cmp(
+                DataType::Time("12:00:00".to_string()),
+                DataType::Time("12:00:00.000".to_string())
+            ),
+            Ordering::Equal
+        );
+        assert_eq!(
+            cmp(DataType::Varchar("b".to_string()), DataType::Integer(1)),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn sql_compare_is_unknown_for_nulls_and_mixed_domains() {
+        let cmp = |a: DataType, b: DataType| sql_compare(&v(a), &v(b));
+        assert_eq!(
+            cmp(DataType::Integer(10), DataType::Double(9.5)),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            cmp(DataType::Varchar("a".into()), DataType::Text("a".into())),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(cmp(DataType::Null, DataType::Null), None);
+        assert_eq!(cmp(DataType::Integer(1), DataType::Null), None);
+        assert_eq!(
+            cmp(DataType::Varchar("1".into()), DataType::Integer(1)),
+            None
+        );
+        assert_eq!(
+            sql_compare(&ColumnValue::null(), &v(DataType::Boolean(true))),
+            None
+        );
+    }
+
+    #[test]
+    fn composite_keys_order_column_by_column() {
+        let key = |a: &str, b: i64| {
+            encode_key([
+                &v(DataType::Varchar(a.to_string())),
+                &v(DataType::BigInt(b)),
+            ])
+        };
+        assert!(key("a", 100) < key("a\0", 1));
+        assert!(key("a", 2) < key("a", 10));
+        assert!(key("ab", -5) > key("a", 7));
+        assert_eq!(key("x", 3), key("x", 3));
+    }
+}
@@ -0,0 +1,271 @@
+//! Heap accounting by subsystem
+//!
+//! [`TrackingAllocator`] wraps the system allocator and charges every allocation to the
+//! [`MemoryTag`] active on the allocating thread (see [`memory_scope`]). The tag is stored in
+//! front of the block, so a free is credited to the subsystem that allocated it, whichever thread
+//! or scope releases it. Live bytes per tag therefore answer "who holds the memory", e.g. when
+//! RSS grows: the buffer pool, cached plans, lock tables or running queries.
+//!
+//! Tracking is opt-in: the `rustdb` binary installs the allocator when built with the
+//! `memory-profiling` feature. Without it the counters stay zero and [`tracking_active`] is
+//! false. The cost is a few bytes per allocation (the block alignment) and relaxed atomic
+//! updates on every allocation and free.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Subsystem an allocation is charged to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum MemoryTag {
+    /// Allocations outside any tagged scope
+    Other = 0,
+    /// Cached pages and the page cache itself
+    BufferPool = 1,
+    /// Query plans (planning and the plan cache)
+    PlanCache = 2,
+    /// Row and transaction lock tables
+    LockTables = 3,
+    /// Query execution (intermediate and result rows)
+    Execution = 4,
+}
+
+impl MemoryTag {
+    /// Every tag, in report order
+    pub const ALL: [MemoryTag; 5] = [
+        MemoryTag::Other,
+        MemoryTag::BufferPool,
+        MemoryTag::PlanCache,
+        MemoryTag::LockTables,
+        MemoryTag::Execution,
+    ];
+
+    /// Stable name used in reports and system views
+    pub fn name(self) -> &'static str {
+        match self {
+            MemoryTag::Other => "other",
+            MemoryTag::BufferPool => "buffer_pool",
+            MemoryTag::PlanCache => "plan_cache",
+            MemoryTag::LockTables => "lock_tables",
+            MemoryTag::Execution => "execution",
+        }
+    }
+}
+
+/// Allocation counters of one tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubsystemMemory {
+    /// Subsystem
+    pub tag: MemoryTag,
+    /// Bytes allocated under this tag and not freed yet
+    pub live_bytes: u64,
+    /// Allocations made under this tag
+    pub allocations: u64,
+    /// Bytes ever allocated under this tag (growth by `realloc` included)
+    pub allocated_bytes: u64,
+}
+
+struct TagCounters {
+    live_bytes: AtomicU64,
+    allocations: AtomicU64,
+    allocated_bytes: AtomicU64,
+}
+
+impl TagCounters {
+    const fn new() -> Self {
+        Self {
+            live_bytes: AtomicU64::new(0),
+            allocations: AtomicU64::new(0),
+            allocated_bytes: AtomicU64::new(0),
+        }
+    }
+}
+
+static COUNTERS: [TagCounters; MemoryTag::ALL.len()] = [
+    TagCounters::new(),
+    TagCounters::new(),
+    TagCounters::new(),
+    TagCounters::new(),
+    TagCounters::new(),
+];
+
+thread_local! {
+    static CURRENT_TAG: Cell<u8> = const { Cell::new(MemoryTag::Other as u8) };
+}
+
+/// Charges allocations of the current thread to `tag` until the returned guard is dropped
+///
+/// Scopes nest: dropping the guard restores the enclosing tag.
+pub fn memory_scope(tag: MemoryTag) -> MemoryScope {
+    let previous = CURRENT_TAG.with(|c| c.replace(tag as u8));
+    MemoryScope {
+        previous,
+        _not_send: std::marker::PhantomData,
+    }
+}
+
+/// Guard returned by [`memory_scope`]
+pub struct MemoryScope {
+    previous: u8,
+    // The tag is per thread: the guard must be dropped where it was created.
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+impl Drop for MemoryScope {
+    fn drop(&mut self) {
+        CURRENT_TAG.with(|c| c.set(self.previous));
+    }
+}
+
+/// Counters of every tag (all zero unless [`TrackingAllocator`] is the global allocator)
+pub fn memory_by_tag() -> Vec<SubsystemMemory> {
+    MemoryTag::ALL
+        .iter()
+        .map(|&tag| {
+            let c = &COUNTERS[tag as usize];
+            SubsystemMemory {
+                tag,
+                live_bytes: c.live_bytes.load(Ordering::Relaxed),
+                allocations: c.allocations.load(Ordering::Relaxed),
+                allocated_bytes: c.allocated_bytes.load(Ordering::Relaxed),
+            }
+        })
+        .collect()
+}
+
+/// Whether [`TrackingAllocator`] has counted any allocation in this process
+pub fn tracking_active() -> bool {
+    COUNTERS
+        .iter()
+        .any(|c| c.allocations.load(Ordering::Relaxed) > 0)
+}
+
+/// Global allocator that counts heap use per [`MemoryTag`]
+///
+/// Install with `#[global_allocator] static ALLOC: TrackingAllocator = TrackingAllocator;`.
+pub struct TrackingAllocator;
+
+impl TrackingAllocator {
+    /// Layout of the block holding `layout` behind an alignment-sized header whose last byte is
+    /// the tag; `None` on overflow
+    fn outer_layout(layout: Layout) -> Option<Layout> {
+        let size = layout.size().checked_add(layout.align())?;
+        Layout::from_size_align(size, layout.align()).ok()
+    }
+
+    fn current_tag() -> u8 {
+        CURRENT_TAG
+            .try_with(Cell::get)
+            .unwrap_or(MemoryTag::Other as u8)
+    }
+
+    /// Writes the tag in front of the user block and counts the allocation
+    ///
+    /// # Safety
+    /// `outer` must be null or a block of `layout.size() + layout.align()` bytes.
+    unsafe fn finish_alloc(outer: *mut u8, layout: Layout) -> *mut u8 {
+        if outer.is_null() {
+            return outer;
+        }
+        let tag = Self::current_tag();
+        let user = outer.add(layout.align());
+        user.sub(1).write(tag);
+        let c = &COUNTERS[tag as usize];
+        c.allocations.fetch_add(1, Ordering::Relaxed);
+        c.allocated_bytes
+            .fetch_add(layout.size() as u64, Ordering::Relaxed);
+        c.live_bytes
+            .fetch_add(layout.size() as u64, Ordering::Relaxed);
+        user
+    }
+}
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        match Self::outer_layout(layout) {
+            Some(outer) => Self::finish_alloc(System.alloc(outer), layout),
+            None => std::ptr::null_mut(),
+        }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        match Self::outer_layout(layout) {
+            Some(outer) => Self::finish_alloc(System.alloc_zeroed(outer), layout),
+            None => std::ptr::null_mut(),
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let tag = ptr.sub(1).read();
+        COUNTERS[tag as usize]
+            .live_bytes
+            .fetch_sub(layout.size() as u64, Ordering::Relaxed);
+        // `alloc` succeeded with this layout, so the outer layout is valid.
+        let outer =
+            Layout::from_size_align_unchecked(layout.size() + layout.align(), layout.align());
+        System.dealloc(ptr.sub(layout.align()), outer);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let Some(new_outer_size) = new_size.checked_add(layout.align()) else {
+            return std::ptr::null_mut();
+        };
+        let tag = ptr.sub(1).read();
+        let outer =
+            Layout::from_size_align_unchecked(layout.size() + layout.align(), layout.align());
+        // The header moves with the block, so the allocation keeps its tag.
+        let moved = System.realloc(ptr.sub(layout.align()), outer, new_outer_size);
+        if moved.is_null() {
+            return moved;
+        }
+        let c = &COUNTERS[tag as usize];
+        if new_size >= layout.size() {
+            let grown = (new_size - layout.size()) as u64;
+            c.live_bytes.fetch_add(grown, Ordering::Relaxed);
+            c.allocated_bytes.fetch_add(grown, Ordering::Relaxed);
+        } else {
+            c.live_bytes
+                .fetch_sub((layout.size() - new_size) as u64, Ordering::Relaxed);
+        }
+        moved.add(layout.align())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn live(tag: MemoryTag) -> u64 {
+        memory_by_tag()
+            .into_iter()
+            .find(|m| m.tag == tag)
+            .map_or(0, |m| m.live_bytes)
+    }
+
+    #[test]
+    fn allocations_are_charged_to_the_scope_that_made_them() {
+        // Only this test allocates through the tracker, and only under `LockTables`.
+        let alloc = TrackingAllocator;
+        let layout = Layout::from_size_align(100, 16).unwrap();
+        let before = live(MemoryTag::LockTables);
+        let ptr = {
+            let _scope = memory_scope(MemoryTag::LockTables);
+            {
+                let _inner = memory_scope(MemoryTag::BufferPool);
+            }
+            unsafe { alloc.alloc(layout) }
+        };
+        assert!(!ptr.is_null());
+        assert_eq!(ptr as usize % 16, 0);
+        assert_eq!(live(MemoryTag::LockTables), before + 100);
+
+        // Growth and the free are credited to the allocating tag, outside its scope.
+        let ptr = unsafe { alloc.realloc(ptr, layout, 300) };
+        assert!(!ptr.is_null());
+        assert_eq!(live(MemoryTag::LockTables), before + 300);
+        unsafe { alloc.dealloc(ptr, Layout::from_size_align(300, 16).unwrap()) };
+        assert_eq!(live(MemoryTag::LockTables), before);
+        assert!(tracking_active());
+    }
+}
@@ -1,11 +1,16 @@
 //! Common types and utilities for rustdb
 
 pub mod bincode_io;
+pub mod clock;
 pub mod config;
+pub mod connection_url;
 pub mod constants;
 pub mod durability;
 pub mod error;
 pub mod i18n;
+pub mod key_encoding;
+pub mod memory_tracking;
+pub mod row_mapping;
 pub mod types;
 pub mod utils;
 
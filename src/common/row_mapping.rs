@@ -0,0 +1,438 @@
+//! Typed access to result rows: [`FromRow`] maps a row into a user type (usually via
+//! `#[derive(FromRow)]` from `rustdb-derive`), [`FromValue`] / [`ToValue`] convert single values.
+//!
+//! Result sets carry each cell as the `Debug` text of its [`DataType`] (`Integer(5)`,
+//! `Varchar("'ann'")`, `NULL`); [`parse_cell`] turns that text back into a value. String values
+//! lose the SQL quotes the engine keeps around them. Cells in any other shape (`SHOW`, `EXPLAIN`)
+//! are read as [`DataType::Text`].
+
+use crate::common::types::DataType;
+use crate::common::{Error, Result};
+
+/// One result row, with values looked up by column name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultRow<'a> {
+    columns: &'a [String],
+    values: Vec<DataType>,
+}
+
+impl<'a> ResultRow<'a> {
+    /// Parses the cells of one result row; `cells` line up with `columns`.
+    pub fn new(columns: &'a [String], cells: &[String]) -> Self {
+        Self {
+            columns,
+            values: cells.iter().map(|c| parse_cell(c)).collect(),
+        }
+    }
+
+    /// A row of values already typed (the binary result format); `values` line up with
+    /// `columns`.
+    pub fn from_values(columns: &'a [String], values: Vec<DataType>) -> Self {
+        Self { columns, values }
+    }
+
+    pub fn columns(&self) -> &[String] {
+        self.columns
+    }
+
+    /// Value of `column` (ASCII case-insensitive), or `None` if the row has no such column.
+    pub fn value(&self, column: &str) -> Option<&DataType> {
+        self.columns
+            .iter()
+            .position(|c| c.eq_ignore_ascii_case(column))
+            .and_then(|i| self.values.get(i))
+    }
+
+    /// Value of `column` converted to `T`.
+    pub fn get<T: FromValue>(&self, column: &str) -> Result<T> {
+        let value = self
+            .value(column)
+            .ok_or_else(|| Error::validation(format!("result has no column {column}")))?;
+        T::from_value(value).map_err(|e| match e {
+            Error::Validation { message } => {
+                Error::validation(format!("column {column}: {message}"))
+            }
+            other => other,
+        })
+    }
+}
+
+/// Builds `Self` from a result row (see [`crate::Connection::query_as`]).
+pub trait FromRow: Sized {
+    fn from_row(row: &ResultRow<'_>) -> Result<Self>;
+}
+
+/// Converts one result value into a Rust value.
+pub trait FromValue: Sized {
+    fn from_value(value: &DataType) -> Result<Self>;
+}
+
+/// Converts a Rust value into a [`DataType`]; [`ToValue::to_sql`] gives it as an SQL literal.
+pub trait ToValue {
+    fn to_value(&self) -> DataType;
+
+    fn to_sql(&self) -> String {
+        sql_literal(&self.to_value())
+    }
+}
+
+fn mismatch<T>(expected: &str, value: &DataType) -> Result<T> {
+    Err(Error::validation(format!(
+        "expected {expected}, found {value:?}"
+    )))
+}
+
+fn as_i64(value: &DataType) -> Option<i64> {
+    match value {
+        DataType::TinyInt(n) => Some(i64::from(*n)),
+        DataType::SmallInt(n) => Some(i64::from(*n)),
+        DataType::Integer(n) => Some(i64::from(*n)),
+        DataType::BigInt(n) => Some(*n),
+        _ => None,
+    }
+}
+
+fn as_str(value: &DataType) -> Option<&str> {
+    match value {
+        DataType::Char(s)
+        | DataType::Varchar(s)
+        | DataType::Text(s)
+        | DataType::Date(s)
+        | DataType::Time(s)
+        | DataType::Timestamp(s) => Some(s),
+        _ => None,
+    }
+}
+
+macro_rules! integer_values {
+    ($($ty:ty => $variant:ident),*) => {$(
+        impl FromValue for $ty {
+            fn from_value(value: &DataType) -> Result<Self> {
+                match as_i64(value).map(<$ty>::try_from) {
+                    Some(Ok(n)) => Ok(n),
+                    Some(Err(_)) => Err(Error::validation(format!(
+                        "{value:?} does not fit in {}",
+                        stringify!($ty)
+                    ))),
+                    None => mismatch(stringify!($ty), value),
+                }
+            }
+        }
+
+        impl ToValue for $ty {
+            fn to_value(&self) -> DataType {
+                DataType::$variant((*self).into())
+            }
+        }
+    )*};
+}
+
+integer_values!(i8 => TinyInt, i16 => SmallInt, i32 => Integer, i64 => BigInt, u8 => SmallInt, u16 => Integer, u32 => BigInt);
+
+impl FromValue for u64 {
+    fn from_value(value: &DataType) -> Result<Self> {
+        match as_i64(value) {
+            Some(n) => u64::try_from(n)
+                .map_err(|_| Error::validation(format!("{value:?} does not fit in u64"))),
+            None => mismatch("u64", value),
+        }
+    }
+}
+
+impl FromValue for f64 {
+    fn from_value(value: &DataType) -> Result<Self> {
+        match value {
+            DataType::Float(f) => Ok(f64::from(*f)),
+            DataType::Double(f) => Ok(*f),
+            other => match as_i64(other) {
+                Some(n) => Ok(n as f64),
+                None => mismatch("f64", value),
+            },
+        }
+    }
+}
+
+impl ToValue for f64 {
+    fn to_value(&self) -> DataType {
+        DataType::Double(*self)
+    }
+}
+
+impl FromValue for f32 {
+    fn from_value(value: &DataType) -> Result<Self> {
+        f64::from_value(value).map(|f| f as f32)
+    }
+}
+
+impl ToValue for f32 {
+    fn to_value(&self) -> DataType {
+        DataType::Float(*self)
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &DataType) -> Result<Self> {
+        match value {
+            DataType::Boolean(b) => Ok(*b),
+            _ => mismatch("bool", value),
+        }
+    }
+}
+
+impl ToValue for bool {
+    fn to_value(&self) -> DataType {
+        DataType::Boolean(*self)
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: &DataType) -> Result<Self> {
+        match as_str(value) {
+            Some(s) => Ok(s.to_string()),
+            None => mismatch("a string", value),
+        }
+    }
+}
+
+impl ToValue for String {
+    fn to_value(&self) -> DataType {
+        self.as_str().to_value()
+    }
+}
+
+impl ToValue for str {
+    fn to_value(&self) -> DataType {
+        DataType::Varchar(self.to_string())
+    }
+}
+
+impl FromValue for Vec<u8> {
+    fn from_value(value: &DataType) -> Result<Self> {
+        match value {
+            DataType::Blob(b) => Ok(b.clone()),
+            _ => mismatch("a blob", value),
+        }
+    }
+}
+
+impl FromValue for DataType {
+    fn from_value(value: &DataType) -> Result<Self> {
+        Ok(value.clone())
+    }
+}
+
+impl ToValue for DataType {
+    fn to_value(&self) -> DataType {
+        self.clone()
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: &DataType) -> Result<Self> {
+        match value {
+            DataType::Null => Ok(None),
+            other => T::from_value(other).map(Some),
+        }
+    }
+}
+
+impl<T: ToValue> ToValue for Option<T> {
+    fn to_value(&self) -> DataType {
+        self.as_ref().map_or(DataType::Null, ToValue::to_value)
+    }
+}
+
+impl<T: ToValue + ?Sized> ToValue for &T {
+    fn to_value(&self) -> DataType {
+        (**self).to_value()
+    }
+}
+
+/// `value` as an SQL literal: strings in `'` with `'` and `\` escaped by a backslash, as the
+/// lexer reads them; blobs as `X'..'`.
+pub fn sql_literal(value: &DataType) -> String {
+    match value {
+        DataType::Null => "NULL".to_string(),
+        DataType::Boolean(b) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+        DataType::Blob(bytes) => {
+            let hex: String = bytes.iter().map(|b| format!("{b:02X}")).collect();
+            format!("X'{hex}'")
+        }
+        DataType::Float(f) => f.to_string(),
+        DataType::Double(f) => f.to_string(),
+        other => match as_i64(other) {
+            Some(n) => n.to_string(),
+            None => {
+                let text = as_str(other).unwrap_or_default();
+                format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+            }
+        },
+    }
+}
+
+/// Parses a result cell (the `Debug` text of a [`DataType`]) back into the value.
+pub fn parse_cell(cell: &str) -> DataType {
+    if cell == "NULL" || cell == "Null" {
+        return DataType::Null;
+    }
+    parse_debug(cell)
+        .or_else(|| parse_plain(cell))
+        .unwrap_or_else(|| DataType::Text(cell.to_string()))
+}
+
+/// `value` as a result row holds it: string values without the SQL quotes the engine keeps
+/// around them.
+pub fn result_value(value: DataType) -> DataType {
+    let strip = |s: String| unquote(&s).unwrap_or(s);
+    match value {
+        DataType::Char(s) => DataType::Char(strip(s)),
+        DataType::Varchar(s) => DataType::Varchar(strip(s)),
+        DataType::Text(s) => DataType::Text(strip(s)),
+        DataType::Date(s) => DataType::Date(strip(s)),
+        DataType::Time(s) => DataType::Time(strip(s)),
+        DataType::Timestamp(s) => DataType::Timestamp(strip(s)),
+        other => other,
+    }
+}
+
+/// Cells of `SELECT` without `FROM`, which are plain literal text (`7`, `true`, `'x'`).
+fn parse_plain(cell: &str) -> Option<DataType> {
+    if let Ok(n) = cell.parse() {
+        return Some(DataType::BigInt(n));
+    }
+    if cell.contains(|c: char| c.is_ascii_digit()) {
+        if let Ok(f) = cell.parse() {
+            return Some(DataType::Double(f));
+        }
+    }
+    match cell {
+        "true" => Some(DataType::Boolean(true)),
+        "false" => Some(DataType::Boolean(false)),
+        _ => unquote(cell).map(DataType::Varchar),
+    }
+}
+
+/// Strips the SQL quotes the engine keeps around string values, and the backslashes of escapes
+/// inside them (the lexer keeps string literals as written).
+fn unquote(s: &str) -> Option<String> {
+    let inner = s.strip_prefix('\'')?.strip_suffix('\'')?;
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.extend(chars.next()),
+            c => out.push(c),
+        }
+    }
+    Some(out)
+}
+
+fn parse_debug(cell: &str) -> Option<DataType> {
+    let (variant, inner) = cell.strip_suffix(')')?.split_once('(')?;
+    Some(match variant {
+        "Boolean" => DataType::Boolean(inner.parse().ok()?),
+        "TinyInt" => DataType::TinyInt(inner.parse().ok()?),
+        "SmallInt" => DataType::SmallInt(inner.parse().ok()?),
+        "Integer" => DataType::Integer(inner.parse().ok()?),
+        "BigInt" => DataType::BigInt(inner.parse().ok()?),
+        "Float" => DataType::Float(inner.parse().ok()?),
+        "Double" => DataType::Double(inner.parse().ok()?),
+        "Char" => DataType::Char(parse_string(inner)?),
+        "Varchar" => DataType::Varchar(parse_string(inner)?),
+        "Text" => DataType::Text(parse_string(inner)?),
+        "Date" => DataType::Date(parse_string(inner)?),
+        "Time" => DataType::Time(parse_string(inner)?),
+        "Timestamp" => DataType::Timestamp(parse_string(inner)?),
+        "Blob" => {
+            let list = inner.strip_prefix('[')?.strip_suffix(']')?;
+            let bytes = list
+                .split(", ")
+                .filter(|b| !b.is_empty())
+                .map(|b| b.parse().ok())
+                .collect::<Option<Vec<u8>>>()?;
+            DataType::Blob(bytes)
+        }
+        _ => return None,
+    })
+}
+
+/// A `Debug`-escaped string literal, without the SQL quotes the engine stores around strings.
+fn parse_string(literal: &str) -> Option<String> {
+    let body = literal.strip_prefix('"')?.strip_suffix('"')?;
+    let mut out = String::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => out.push('\n'),
+            'r' => out.push('\r'),
+            't' => out.push('\t'),
+            '0' => out.push('\0'),
+            'u' => {
+                let code: String = chars
+                    .by_ref()
+                    .skip_while(|&c| c == '{')
+                    .take_while(|&c| c != '}')
+                    .collect();
+                out.push(char::from_u32(u32::from_str_radix(&code, 16).ok()?)?);
+            }
+            other => out.push(other),
+        }
+    }
+    Some(unquote(&out).unwrap_or(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_parse_back_into_values() {
+        assert_eq!(parse_cell("NULL"), DataType::Null);
+        assert_eq!(parse_cell("Integer(-5)"), DataType::Integer(-5));
+        assert_eq!(parse_cell("Double(1.5)"), DataType::Double(1.5));
+        assert_eq!(parse_cell("Boolean(true)"), DataType::Boolean(true));
+        assert_eq!(parse_cell("Blob([1, 255])"), DataType::Blob(vec![1, 255]));
+        assert_eq!(parse_cell("Blob([])"), DataType::Blob(vec![]));
+        assert_eq!(
+            parse_cell(r#"Varchar("'it\\'s \"x\"\n\u{e9}'")"#),
+            DataType::Varchar("it's \"x\"\né".to_string())
+        );
+        assert_eq!(parse_cell("7"), DataType::BigInt(7));
+        assert_eq!(parse_cell("2.5"), DataType::Double(2.5));
+        assert_eq!(parse_cell("'x'"), DataType::Varchar("x".to_string()));
+        assert_eq!(
+            parse_cell("Seq Scan on t"),
+            DataType::Text("Seq Scan on t".to_string())
+        );
+    }
+
+    #[test]
+    fn row_values_convert_by_column_name() {
+        let columns = vec!["id".to_string(), "name".to_string(), "note".to_string()];
+        let cells = vec![
+            "BigInt(7)".to_string(),
+            r#"Varchar("'ann'")"#.to_string(),
+            "NULL".to_string(),
+        ];
+        let row = ResultRow::new(&columns, &cells);
+        assert_eq!(row.get::<i32>("ID").unwrap(), 7);
+        assert_eq!(row.get::<String>("name").unwrap(), "ann");
+        assert_eq!(row.get::<Option<String>>("note").unwrap(), None);
+        assert!(row.get::<String>("note").is_err());
+        assert!(row.get::<i64>("missing").is_err());
+        assert!(row.get::<i8>("name").is_err());
+    }
+
+    #[test]
+    fn values_render_as_sql_literals() {
+        assert_eq!(r"it's \".to_sql(), r"'it\'s \\'");
+        assert_eq!(Some(3u8).to_sql(), "3");
+        assert_eq!(None::<i32>.to_sql(), "NULL");
+        assert_eq!(sql_literal(&DataType::Blob(vec![0, 171])), "X'00AB'");
+        assert_eq!(true.to_sql(), "TRUE");
+    }
+}
@@ -1,7 +1,11 @@
 //! Basic data types for rustdb
 
+use crate::common::clock::unix_now_secs;
+use crate::common::key_encoding;
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Page identifier
 pub type PageId = u64;
@@ -139,6 +143,18 @@ impl ColumnValue {
     pub fn is_null(&self) -> bool {
         self.is_null
     }
+
+    /// Total order of values, the order of sort and index keys: numbers of every type compare
+    /// as numbers and NULL sorts last (see [`crate::common::key_encoding`])
+    pub fn total_cmp(&self, other: &Self) -> Ordering {
+        key_encoding::compare_values(self, other)
+    }
+
+    /// SQL comparison: `None` (unknown) when either value is NULL or the two are not comparable,
+    /// such as a number and a string
+    pub fn sql_cmp(&self, other: &Self) -> Option<Ordering> {
+        key_encoding::sql_compare(self, other)
+    }
 }
 
 /// Column definition
@@ -304,11 +320,69 @@ pub enum IndexType {
     FullText,
 }
 
+/// Column layout shared by rows: column names in position order
+///
+/// Rows produced by one operator share a single `Arc<RowSchema>`, so column names are stored once
+/// instead of per row and a column can be addressed by its position.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RowSchema {
+    columns: Vec<String>,
+    positions: HashMap<String, usize>,
+}
+
+impl RowSchema {
+    /// Creates a layout of `columns`; a repeated name keeps its first position
+    pub fn new<S: Into<String>>(columns: impl IntoIterator<Item = S>) -> Self {
+        let mut schema = Self::default();
+        for column in columns {
+            schema.push(column.into());
+        }
+        schema
+    }
+
+    /// Column names in position order
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Position of `column`
+    pub fn position(&self, column: &str) -> Option<usize> {
+        self.positions.get(column).copied()
+    }
+
+    /// Number of columns
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// Whether the layout has no columns
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    /// Appends `column` (if new) and returns its position
+    fn push(&mut self, column: String) -> usize {
+        if let Some(&position) = self.positions.get(&column) {
+            return position;
+        }
+        let position = self.columns.len();
+        self.positions.insert(column.clone(), position);
+        self.columns.push(column);
+        position
+    }
+}
+
 /// Table row
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Values are stored by position in the row's [`RowSchema`]. Setting a column that is not in the
+/// schema extends the row's own copy of the schema, so rows built column by column keep working.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "RowRepr", into = "RowRepr")]
 pub struct Row {
-    /// Column values
-    pub values: HashMap<String, ColumnValue>,
+    /// Column layout, usually shared with the other rows of a result
+    schema: Arc<RowSchema>,
+    /// Values by schema position (`None` = column not set in this row)
+    values: Vec<Option<ColumnValue>>,
     /// Row version (for MVCC)
     pub version: u64,
     /// Creation time
@@ -320,17 +394,7 @@ pub struct Row {
 impl Row {
     /// Creates a new row
     pub fn new() -> Self {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-
-        Self {
-            values: HashMap::new(),
-            version: 1,
-            created_at: now,
-            updated_at: now,
-        }
+        Self::with_capacity(0)
     }
 
     /// Creates a new row with preallocated column capacity.
@@ -338,40 +402,187 @@ impl Row {
     /// Intended for hot-path operators (scan/projection) where we already know how many columns
     /// will be inserted and we don't want to update timestamps on every column insert.
     pub fn with_capacity(column_capacity: usize) -> Self {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        let now = unix_now_secs();
+        Self {
+            schema: Arc::new(RowSchema::default()),
+            values: Vec::with_capacity(column_capacity),
+            version: 1,
+            created_at: now,
+            updated_at: now,
+        }
+    }
 
+    /// Creates a row with every column of `schema` unset
+    pub fn with_schema(schema: Arc<RowSchema>) -> Self {
+        let now = unix_now_secs();
         Self {
-            values: HashMap::with_capacity(column_capacity),
+            values: vec![None; schema.len()],
+            schema,
             version: 1,
             created_at: now,
             updated_at: now,
         }
     }
 
+    /// Creates a row of `schema` from values in position order
+    pub fn from_values(schema: Arc<RowSchema>, values: Vec<ColumnValue>) -> Self {
+        let mut row = Self::with_schema(schema);
+        for (slot, value) in row.values.iter_mut().zip(values) {
+            *slot = Some(value);
+        }
+        row
+    }
+
+    /// Column layout of this row
+    pub fn schema(&self) -> &Arc<RowSchema> {
+        &self.schema
+    }
+
     /// Sets a column value
     pub fn set_value(&mut self, column: &str, value: ColumnValue) {
-        self.values.insert(column.to_string(), value);
-        self.updated_at = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        self.set_value_fast(column, value);
+        self.updated_at = unix_now_secs();
     }
 
     /// Sets a column value **without** touching timestamps.
     pub fn set_value_fast(&mut self, column: &str, value: ColumnValue) {
-        self.values.insert(column.to_string(), value);
+        let position = match self.schema.position(column) {
+            Some(position) => position,
+            None => Arc::make_mut(&mut self.schema).push(column.to_string()),
+        };
+        if position >= self.values.len() {
+            self.values.resize(position + 1, None);
+        }
+        self.values[position] = Some(value);
+    }
+
+    /// Sets the value at `position` of the schema (no-op past the end)
+    pub fn set_value_at(&mut self, position: usize, value: ColumnValue) {
+        if position < self.schema.len() {
+            if position >= self.values.len() {
+                self.values.resize(position + 1, None);
+            }
+            self.values[position] = Some(value);
+        }
     }
 
     /// Gets a column value
     pub fn get_value(&self, column: &str) -> Option<&ColumnValue> {
-        self.values.get(column)
+        self.get_value_at(self.schema.position(column)?)
+    }
+
+    /// Gets the value at `position` of the schema
+    pub fn get_value_at(&self, position: usize) -> Option<&ColumnValue> {
+        self.values.get(position)?.as_ref()
+    }
+
+    /// Gets a mutable column value
+    pub fn get_value_mut(&mut self, column: &str) -> Option<&mut ColumnValue> {
+        let position = self.schema.position(column)?;
+        self.values.get_mut(position)?.as_mut()
+    }
+
+    /// Removes a column value (the column stays in the schema)
+    pub fn remove_value(&mut self, column: &str) -> Option<ColumnValue> {
+        let position = self.schema.position(column)?;
+        self.values.get_mut(position)?.take()
     }
 
     /// Checks if the row contains a column
     pub fn has_column(&self, column: &str) -> bool {
-        self.values.contains_key(column)
+        self.get_value(column).is_some()
+    }
+
+    /// Set columns and their values, in schema order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ColumnValue)> {
+        self.schema
+            .columns
+            .iter()
+            .zip(&self.values)
+            .filter_map(|(name, value)| Some((name.as_str(), value.as_ref()?)))
+    }
+
+    /// Names of the set columns, in schema order
+    pub fn column_names(&self) -> impl Iterator<Item = &str> {
+        self.iter().map(|(name, _)| name)
+    }
+
+    /// Number of set columns
+    pub fn len(&self) -> usize {
+        self.values.iter().filter(|v| v.is_some()).count()
+    }
+
+    /// Whether no column is set
+    pub fn is_empty(&self) -> bool {
+        self.values.iter().all(|v| v.is_none())
+    }
+
+    /// Set columns and values as a map keyed by column name
+    pub fn to_map(&self) -> HashMap<String, ColumnValue> {
+        self.iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect()
+    }
+}
+
+impl Default for Row {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for Row {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        struct Values<'a>(&'a Row);
+        impl std::fmt::Debug for Values<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_map().entries(self.0.iter()).finish()
+            }
+        }
+        f.debug_struct("Row")
+            .field("values", &Values(self))
+            .field("version", &self.version)
+            .field("created_at", &self.created_at)
+            .field("updated_at", &self.updated_at)
+            .finish()
+    }
+}
+
+/// Serialized form of [`Row`]: values keyed by column name
+#[derive(Clone, Serialize, Deserialize)]
+struct RowRepr {
+    values: Vec<(String, ColumnValue)>,
+    version: u64,
+    created_at: u64,
+    updated_at: u64,
+}
+
+impl From<Row> for RowRepr {
+    fn from(row: Row) -> Self {
+        Self {
+            values: row
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect(),
+            version: row.version,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+impl From<RowRepr> for Row {
+    fn from(repr: RowRepr) -> Self {
+        let schema = Arc::new(RowSchema::new(
+            repr.values.iter().map(|(name, _)| name.as_str()),
+        ));
+        let mut row = Row::with_schema(schema);
+        for (name, value) in repr.values {
+            row.set_value_fast(&name, value);
+        }
+        row.version = repr.version;
+        row.created_at = repr.created_at;
+        row.updated_at = repr.updated_at;
+        row
     }
 }
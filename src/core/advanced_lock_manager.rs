@@ -5,11 +5,27 @@
 //! - Intention locks (IS, IX, SIX)
 //! - Improved deadlock detection
 //! - Timeouts and automatic rollback
+//! - Key-range (predicate) locks closing phantoms at SERIALIZABLE
+//!
+//! The lock table, waiting queues and per-transaction lock sets are [`DashMap`]s, sharded by the
+//! hash of their key, so transactions locking different resources do not contend on one latch.
+//! Only the wait-for graph is global; it is touched when a request has to wait. Statistics are
+//! atomic counters.
+//!
+//! Key-range locks cover the keys of a table between two bounds, the gaps between existing keys
+//! included. A scan takes one on the range it reads; a write takes a key lock on the key it
+//! writes. They conflict when held by different transactions and the key lies in the range, in
+//! either order, so a row can neither appear in nor vanish from a locked range until the scanning
+//! transaction ends. Both live in one table under a single latch, which makes each check and
+//! grant atomic; waiters poll it like record lock requests and join the same wait-for graph.
 
 use crate::common::{Error, Result};
 use crate::core::transaction::TransactionId;
+use dashmap::DashMap;
 use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
-use std::sync::{Arc, Mutex, RwLock};
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 /// Resource type for locking
@@ -47,6 +63,75 @@ impl std::fmt::Display for ResourceType {
     }
 }
 
+/// Keys `start..=end` of a table, locked by a scan
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyRange {
+    /// Table id
+    pub table: u64,
+    /// First key of the range
+    pub start: u64,
+    /// Last key of the range
+    pub end: u64,
+}
+
+impl KeyRange {
+    /// Creates the range of `keys` in `table`
+    pub fn new(table: u64, keys: RangeInclusive<u64>) -> Self {
+        Self {
+            table,
+            start: *keys.start(),
+            end: *keys.end(),
+        }
+    }
+
+    /// Checks whether `key` of `table` lies in the range
+    pub fn contains(&self, table: u64, key: u64) -> bool {
+        self.table == table && (self.start..=self.end).contains(&key)
+    }
+
+    /// Checks whether the range includes all of `other`
+    fn covers(&self, other: &KeyRange) -> bool {
+        self.table == other.table && self.start <= other.start && other.end <= self.end
+    }
+}
+
+/// Key-range locks of scans and key locks of writes, by transaction
+#[derive(Debug, Default)]
+struct KeyRangeLocks {
+    ranges: HashMap<TransactionId, Vec<KeyRange>>,
+    keys: HashMap<TransactionId, HashSet<(u64, u64)>>,
+}
+
+impl KeyRangeLocks {
+    /// Other transactions holding a key in `range`
+    fn key_owners(&self, transaction_id: TransactionId, range: &KeyRange) -> Vec<TransactionId> {
+        self.keys
+            .iter()
+            .filter(|(owner, keys)| {
+                **owner != transaction_id
+                    && keys.iter().any(|(table, key)| range.contains(*table, *key))
+            })
+            .map(|(owner, _)| *owner)
+            .collect()
+    }
+
+    /// Other transactions holding a range containing `key` of `table`
+    fn range_owners(
+        &self,
+        transaction_id: TransactionId,
+        table: u64,
+        key: u64,
+    ) -> Vec<TransactionId> {
+        self.ranges
+            .iter()
+            .filter(|(owner, ranges)| {
+                **owner != transaction_id && ranges.iter().any(|r| r.contains(table, key))
+            })
+            .map(|(owner, _)| *owner)
+            .collect()
+    }
+}
+
 /// Lock mode
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum LockMode {
@@ -268,17 +353,19 @@ impl AdvancedWaitForGraph {
 /// Advanced lock manager
 pub struct AdvancedLockManager {
     /// Active locks by resource
-    locks: Arc<RwLock<HashMap<ResourceType, Vec<AdvancedLockInfo>>>>,
+    locks: Arc<DashMap<ResourceType, Vec<AdvancedLockInfo>>>,
     /// Waiting queues by resource
-    waiting_queues: Arc<RwLock<HashMap<ResourceType, VecDeque<AdvancedLockRequest>>>>,
+    waiting_queues: Arc<DashMap<ResourceType, VecDeque<AdvancedLockRequest>>>,
     /// Wait-for graph for deadlock detection
     wait_for_graph: Arc<Mutex<AdvancedWaitForGraph>>,
     /// Transactions owning locks
-    transaction_locks: Arc<RwLock<HashMap<TransactionId, HashSet<ResourceType>>>>,
+    transaction_locks: Arc<DashMap<TransactionId, HashSet<ResourceType>>>,
+    /// Key-range locks of scans and key locks of writes
+    key_range_locks: Arc<Mutex<KeyRangeLocks>>,
     /// Configuration
     config: AdvancedLockConfig,
     /// Statistics
-    statistics: Arc<Mutex<AdvancedLockStatistics>>,
+    statistics: Arc<LockCounters>,
 }
 
 /// Advanced lock manager configuration
@@ -342,16 +429,68 @@ impl AdvancedLockStatistics {
     }
 }
 
+/// Lock-free counters behind [`AdvancedLockStatistics`]
+#[derive(Debug)]
+struct LockCounters {
+    total_locks: AtomicUsize,
+    waiting_transactions: AtomicUsize,
+    deadlocks_detected: AtomicU64,
+    lock_timeouts: AtomicU64,
+    lock_upgrades: AtomicU64,
+    /// Origin of `last_updated_us`
+    started_at: Instant,
+    /// Microseconds after `started_at` of the last update
+    last_updated_us: AtomicU64,
+}
+
+impl LockCounters {
+    fn new() -> Self {
+        Self {
+            total_locks: AtomicUsize::new(0),
+            waiting_transactions: AtomicUsize::new(0),
+            deadlocks_detected: AtomicU64::new(0),
+            lock_timeouts: AtomicU64::new(0),
+            lock_upgrades: AtomicU64::new(0),
+            started_at: Instant::now(),
+            last_updated_us: AtomicU64::new(0),
+        }
+    }
+
+    fn touch(&self) {
+        let us = self.started_at.elapsed().as_micros() as u64;
+        self.last_updated_us.fetch_max(us, Ordering::Relaxed);
+    }
+
+    fn decrement(counter: &AtomicUsize) {
+        let _ = counter.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |v| {
+            Some(v.saturating_sub(1))
+        });
+    }
+
+    fn snapshot(&self) -> AdvancedLockStatistics {
+        AdvancedLockStatistics {
+            total_locks: self.total_locks.load(Ordering::Relaxed),
+            waiting_transactions: self.waiting_transactions.load(Ordering::Relaxed),
+            deadlocks_detected: self.deadlocks_detected.load(Ordering::Relaxed),
+            lock_timeouts: self.lock_timeouts.load(Ordering::Relaxed),
+            lock_upgrades: self.lock_upgrades.load(Ordering::Relaxed),
+            last_updated: self.started_at
+                + Duration::from_micros(self.last_updated_us.load(Ordering::Relaxed)),
+        }
+    }
+}
+
 impl AdvancedLockManager {
     /// Creates a new advanced lock manager
     pub fn new(config: AdvancedLockConfig) -> Self {
         Self {
-            locks: Arc::new(RwLock::new(HashMap::new())),
-            waiting_queues: Arc::new(RwLock::new(HashMap::new())),
+            locks: Arc::new(DashMap::new()),
+            waiting_queues: Arc::new(DashMap::new()),
             wait_for_graph: Arc::new(Mutex::new(AdvancedWaitForGraph::new())),
-            transaction_locks: Arc::new(RwLock::new(HashMap::new())),
+            transaction_locks: Arc::new(DashMap::new()),
+            key_range_locks: Arc::new(Mutex::new(KeyRangeLocks::default())),
             config,
-            statistics: Arc::new(Mutex::new(AdvancedLockStatistics::new())),
+            statistics: Arc::new(LockCounters::new()),
         }
     }
 
@@ -428,11 +567,113 @@ impl AdvancedLockManager {
         }
     }
 
+    /// Locks the keys in `range` and the gaps between them until the transaction releases its
+    /// locks, waiting while other transactions hold key locks in it
+    pub async fn acquire_range_lock(
+        &self,
+        transaction_id: TransactionId,
+        range: KeyRange,
+        timeout: Option<Duration>,
+    ) -> Result<()> {
+        self.acquire_key_range_lock(transaction_id, timeout, |locks| {
+            let blockers = locks.key_owners(transaction_id, &range);
+            if blockers.is_empty() {
+                let ranges = locks.ranges.entry(transaction_id).or_default();
+                if !ranges.iter().any(|r| r.covers(&range)) {
+                    ranges.push(range);
+                }
+            }
+            blockers
+        })
+        .await
+    }
+
+    /// Locks `key` of `table` for a write until the transaction releases its locks, waiting
+    /// while other transactions hold range locks containing it
+    pub async fn acquire_key_lock(
+        &self,
+        transaction_id: TransactionId,
+        table: u64,
+        key: u64,
+        timeout: Option<Duration>,
+    ) -> Result<()> {
+        self.acquire_key_range_lock(transaction_id, timeout, |locks| {
+            let blockers = locks.range_owners(transaction_id, table, key);
+            if blockers.is_empty() {
+                locks
+                    .keys
+                    .entry(transaction_id)
+                    .or_default()
+                    .insert((table, key));
+            }
+            blockers
+        })
+        .await
+    }
+
+    /// Polls `try_grant` (which grants the lock and returns no blockers, or returns the
+    /// transactions blocking it) until it succeeds, the timeout expires or the transaction is
+    /// chosen as a deadlock victim
+    async fn acquire_key_range_lock(
+        &self,
+        transaction_id: TransactionId,
+        timeout: Option<Duration>,
+        mut try_grant: impl FnMut(&mut KeyRangeLocks) -> Vec<TransactionId>,
+    ) -> Result<()> {
+        let timeout = timeout.unwrap_or(self.config.lock_timeout);
+        let start_time = Instant::now();
+        let retry_interval = Duration::from_millis(10);
+        let mut waited = false;
+        let outcome = loop {
+            let blockers = {
+                let mut locks = self.key_range_locks.lock().unwrap();
+                try_grant(&mut locks)
+            };
+            if blockers.is_empty() {
+                break Ok(());
+            }
+            if start_time.elapsed() >= timeout {
+                self.update_statistics_timeout();
+                break Err(Error::timeout(format!(
+                    "Failed to acquire key-range lock for transaction {} within {:?}",
+                    transaction_id, timeout
+                )));
+            }
+            if self.config.auto_deadlock_detection {
+                {
+                    let mut graph = self.wait_for_graph.lock().unwrap();
+                    for blocker in blockers {
+                        graph.add_edge(transaction_id, blocker);
+                    }
+                }
+                waited = true;
+                if let Some(cycle) = self.detect_deadlock() {
+                    if cycle.contains(&transaction_id)
+                        && self.should_abort_transaction(&cycle, transaction_id)
+                    {
+                        self.statistics
+                            .deadlocks_detected
+                            .fetch_add(1, Ordering::Relaxed);
+                        break Err(Error::conflict(format!(
+                            "Deadlock detected: transaction {} chosen as victim",
+                            transaction_id
+                        )));
+                    }
+                }
+            }
+            tokio::time::sleep(retry_interval).await;
+        };
+        if waited {
+            let mut graph = self.wait_for_graph.lock().unwrap();
+            graph.remove_transaction(transaction_id);
+        }
+        outcome
+    }
+
     /// Checks lock compatibility
     fn is_lock_compatible(&self, resource_type: &ResourceType, lock_mode: &LockMode) -> bool {
-        let locks = self.locks.read().unwrap();
-        if let Some(resource_locks) = locks.get(resource_type) {
-            for lock in resource_locks {
+        if let Some(resource_locks) = self.locks.get(resource_type) {
+            for lock in resource_locks.iter() {
                 if !lock_mode.is_compatible(&lock.lock_mode) {
                     return false;
                 }
@@ -448,8 +689,11 @@ impl AdvancedLockManager {
         resource_type: &ResourceType,
         lock_mode: LockMode,
     ) -> Result<()> {
-        let mut locks = self.locks.write().unwrap();
-        let resource_locks = locks.entry(resource_type.clone()).or_insert_with(Vec::new);
+        // The entry guard write-locks only the shard holding `resource_type`.
+        let mut resource_locks = self
+            .locks
+            .entry(resource_type.clone())
+            .or_insert_with(Vec::new);
 
         // Check compatibility with existing locks
         for existing_lock in resource_locks.iter() {
@@ -479,15 +723,13 @@ impl AdvancedLockManager {
         };
 
         resource_locks.push(lock_info);
+        drop(resource_locks);
 
         // Update transaction information
-        {
-            let mut transaction_locks = self.transaction_locks.write().unwrap();
-            transaction_locks
-                .entry(transaction_id)
-                .or_insert_with(HashSet::new)
-                .insert(resource_type.clone());
-        }
+        self.transaction_locks
+            .entry(transaction_id)
+            .or_insert_with(HashSet::new)
+            .insert(resource_type.clone());
 
         Ok(())
     }
@@ -510,13 +752,10 @@ impl AdvancedLockManager {
         };
 
         // Add to waiting queue
-        {
-            let mut queues = self.waiting_queues.write().unwrap();
-            let queue = queues
-                .entry(resource_type.clone())
-                .or_insert_with(VecDeque::new);
-            queue.push_back(request);
-        }
+        self.waiting_queues
+            .entry(resource_type.clone())
+            .or_insert_with(VecDeque::new)
+            .push_back(request);
 
         // Update wait-for graph
         if let Some(owner) = self.get_lock_owner(&resource_type)? {
@@ -525,10 +764,9 @@ impl AdvancedLockManager {
         }
 
         // Update statistics
-        {
-            let mut stats = self.statistics.lock().unwrap();
-            stats.waiting_transactions += 1;
-        }
+        self.statistics
+            .waiting_transactions
+            .fetch_add(1, Ordering::Relaxed);
 
         Ok(())
     }
@@ -539,25 +777,21 @@ impl AdvancedLockManager {
         transaction_id: TransactionId,
         resource_type: ResourceType,
     ) -> Result<()> {
-        let mut locks = self.locks.write().unwrap();
-
-        if let Some(resource_locks) = locks.get_mut(&resource_type) {
+        if let Some(mut resource_locks) = self.locks.get_mut(&resource_type) {
             // Remove lock
             resource_locks.retain(|lock| lock.transaction_id != transaction_id);
-
-            // If resource is no longer locked, remove it
-            if resource_locks.is_empty() {
-                locks.remove(&resource_type);
-            }
         }
+        // If resource is no longer locked, remove it
+        self.locks.remove_if(&resource_type, |_, resource_locks| {
+            resource_locks.is_empty()
+        });
 
         // Update transaction information
-        {
-            let mut transaction_locks = self.transaction_locks.write().unwrap();
-            if let Some(transaction_resources) = transaction_locks.get_mut(&transaction_id) {
-                transaction_resources.remove(&resource_type);
-            }
+        if let Some(mut transaction_resources) = self.transaction_locks.get_mut(&transaction_id) {
+            transaction_resources.remove(&resource_type);
         }
+        self.transaction_locks
+            .remove_if(&transaction_id, |_, resources| resources.is_empty());
 
         // Remove from wait-for graph
         {
@@ -577,18 +811,21 @@ impl AdvancedLockManager {
     /// Releases all transaction locks
     pub fn release_all_locks(&self, transaction_id: TransactionId) -> Result<()> {
         // Get list of resources and immediately release read lock
-        let resources_to_release = {
-            let transaction_locks = self.transaction_locks.read().unwrap();
-            transaction_locks
-                .get(&transaction_id)
-                .map(|resources| resources.iter().cloned().collect::<Vec<_>>())
-                .unwrap_or_default()
-        }; // read lock is released here
+        let resources_to_release = self
+            .transaction_locks
+            .get(&transaction_id)
+            .map(|resources| resources.iter().cloned().collect::<Vec<_>>())
+            .unwrap_or_default(); // shard read lock is released here
 
         // Now can safely release locks
         for resource in resources_to_release {
             self.release_lock_internal(transaction_id, resource)?;
         }
+        {
+            let mut key_range_locks = self.key_range_locks.lock().unwrap();
+            key_range_locks.ranges.remove(&transaction_id);
+            key_range_locks.keys.remove(&transaction_id);
+        }
 
         Ok(())
     }
@@ -599,25 +836,21 @@ impl AdvancedLockManager {
         transaction_id: TransactionId,
         resource_type: ResourceType,
     ) -> Result<()> {
-        let mut locks = self.locks.write().unwrap();
-
-        if let Some(resource_locks) = locks.get_mut(&resource_type) {
+        if let Some(mut resource_locks) = self.locks.get_mut(&resource_type) {
             // Remove lock
             resource_locks.retain(|lock| lock.transaction_id != transaction_id);
-
-            // If resource is no longer locked, remove it
-            if resource_locks.is_empty() {
-                locks.remove(&resource_type);
-            }
         }
+        // If resource is no longer locked, remove it
+        self.locks.remove_if(&resource_type, |_, resource_locks| {
+            resource_locks.is_empty()
+        });
 
         // Update transaction information
-        {
-            let mut transaction_locks = self.transaction_locks.write().unwrap();
-            if let Some(transaction_resources) = transaction_locks.get_mut(&transaction_id) {
-                transaction_resources.remove(&resource_type);
-            }
+        if let Some(mut transaction_resources) = self.transaction_locks.get_mut(&transaction_id) {
+            transaction_resources.remove(&resource_type);
         }
+        self.transaction_locks
+            .remove_if(&transaction_id, |_, resources| resources.is_empty());
 
         // Remove from wait-for graph
         {
@@ -633,9 +866,7 @@ impl AdvancedLockManager {
 
     /// Gets lock owner on resource
     fn get_lock_owner(&self, resource_type: &ResourceType) -> Result<Option<TransactionId>> {
-        let locks = self.locks.read().unwrap();
-
-        if let Some(resource_locks) = locks.get(resource_type) {
+        if let Some(resource_locks) = self.locks.get(resource_type) {
             // Return transaction with strongest lock
             if let Some(strongest_lock) = resource_locks.iter().max_by_key(|l| l.lock_mode.level())
             {
@@ -648,9 +879,7 @@ impl AdvancedLockManager {
 
     /// Processes waiting queue for resource
     fn process_waiting_queue(&self, resource_type: &ResourceType) -> Result<()> {
-        let mut queues = self.waiting_queues.write().unwrap();
-
-        if let Some(queue) = queues.get_mut(resource_type) {
+        if let Some(mut queue) = self.waiting_queues.get_mut(resource_type) {
             let mut processed = 0;
             let max_process = queue.len(); // Protection from infinite loop
 
@@ -671,10 +900,7 @@ impl AdvancedLockManager {
                     )?;
 
                     // Update statistics
-                    {
-                        let mut stats = self.statistics.lock().unwrap();
-                        stats.waiting_transactions = stats.waiting_transactions.saturating_sub(1);
-                    }
+                    LockCounters::decrement(&self.statistics.waiting_transactions);
 
                     processed += 1;
                 } else {
@@ -692,10 +918,8 @@ impl AdvancedLockManager {
         resource_type: &ResourceType,
         requested_mode: &LockMode,
     ) -> Result<bool> {
-        let locks = self.locks.read().unwrap();
-
-        if let Some(resource_locks) = locks.get(resource_type) {
-            for existing_lock in resource_locks {
+        if let Some(resource_locks) = self.locks.get(resource_type) {
+            for existing_lock in resource_locks.iter() {
                 if !requested_mode.is_compatible(&existing_lock.lock_mode) {
                     return Ok(false);
                 }
@@ -719,18 +943,15 @@ impl AdvancedLockManager {
             self.release_all_locks(*victim)?;
 
             // Remove victim from waiting queue
-            {
-                let mut queues = self.waiting_queues.write().unwrap();
-                for queue in queues.values_mut() {
-                    queue.retain(|req| req.transaction_id != *victim);
-                }
+            for mut queue in self.waiting_queues.iter_mut() {
+                queue.retain(|req| req.transaction_id != *victim);
             }
 
             // Update statistics
-            {
-                let mut stats = self.statistics.lock().unwrap();
-                stats.deadlocks_detected += 1;
-            }
+            self.statistics
+                .deadlocks_detected
+                .fetch_add(1, Ordering::Relaxed);
+            self.statistics.touch();
         }
 
         Ok(())
@@ -755,15 +976,12 @@ impl AdvancedLockManager {
         transaction_id: TransactionId,
         resource_type: &ResourceType,
     ) {
-        let mut queues = self.waiting_queues.write().unwrap();
-        if let Some(queue) = queues.get_mut(resource_type) {
+        if let Some(mut queue) = self.waiting_queues.get_mut(resource_type) {
             queue.retain(|req| req.transaction_id != transaction_id);
-
-            // If queue is empty, remove it
-            if queue.is_empty() {
-                queues.remove(resource_type);
-            }
         }
+        // If queue is empty, remove it
+        self.waiting_queues
+            .remove_if(resource_type, |_, queue| queue.is_empty());
 
         // Remove from wait-for graph
         let mut graph = self.wait_for_graph.lock().unwrap();
@@ -772,57 +990,66 @@ impl AdvancedLockManager {
 
     /// Updates statistics when acquiring lock
     fn update_statistics_lock_acquired(&self) {
-        let mut stats = self.statistics.lock().unwrap();
-        stats.total_locks += 1;
-        stats.last_updated = Instant::now();
+        self.statistics.total_locks.fetch_add(1, Ordering::Relaxed);
+        self.statistics.touch();
     }
 
     /// Updates statistics on timeout
     fn update_statistics_timeout(&self) {
-        let mut stats = self.statistics.lock().unwrap();
-        stats.lock_timeouts += 1;
-        stats.last_updated = Instant::now();
+        self.statistics
+            .lock_timeouts
+            .fetch_add(1, Ordering::Relaxed);
+        self.statistics.touch();
     }
 
     /// Updates statistics on upgrade
     fn update_statistics_upgrade(&self) {
-        let mut stats = self.statistics.lock().unwrap();
-        stats.lock_upgrades += 1;
-        stats.last_updated = Instant::now();
+        self.statistics
+            .lock_upgrades
+            .fetch_add(1, Ordering::Relaxed);
+        self.statistics.touch();
     }
 
     /// Updates statistics when releasing lock
     fn update_statistics_lock_released(&self) {
-        let mut stats = self.statistics.lock().unwrap();
-        stats.total_locks = stats.total_locks.saturating_sub(1);
-        stats.last_updated = Instant::now();
+        LockCounters::decrement(&self.statistics.total_locks);
+        self.statistics.touch();
     }
 
     /// Gets statistics
     pub fn get_statistics(&self) -> AdvancedLockStatistics {
-        self.statistics.lock().unwrap().clone()
+        self.statistics.snapshot()
     }
 
     /// Gets lock information on resource
     pub fn get_resource_locks(&self, resource_type: &ResourceType) -> Vec<AdvancedLockInfo> {
-        let locks = self.locks.read().unwrap();
-        locks.get(resource_type).cloned().unwrap_or_default()
+        self.locks
+            .get(resource_type)
+            .map(|locks| locks.clone())
+            .unwrap_or_default()
     }
 
     /// Gets list of locked resources for transaction
     pub fn get_transaction_locks(&self, transaction_id: TransactionId) -> Vec<ResourceType> {
-        let transaction_locks = self.transaction_locks.read().unwrap();
-        transaction_locks
+        self.transaction_locks
+            .get(&transaction_id)
+            .map(|resources| resources.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Gets key ranges locked by transaction
+    pub fn get_range_locks(&self, transaction_id: TransactionId) -> Vec<KeyRange> {
+        self.key_range_locks
+            .lock()
+            .unwrap()
+            .ranges
             .get(&transaction_id)
             .cloned()
             .unwrap_or_default()
-            .into_iter()
-            .collect()
     }
 
     /// Gets number of transactions in waiting queue
     pub fn get_waiting_count(&self) -> usize {
-        let queues = self.waiting_queues.read().unwrap();
-        queues.values().map(|q| q.len()).sum()
+        self.waiting_queues.iter().map(|q| q.len()).sum()
     }
 }
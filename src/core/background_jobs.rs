@@ -0,0 +1,315 @@
+//! Background job framework for rustdb
+//!
+//! Periodic maintenance (checkpoints, transaction cleanup, performance analysis, ...) registers
+//! with a [`BackgroundJobManager`] instead of spawning its own loop. Each job runs on the tokio
+//! runtime that was current at registration, at most once at a time, on its interval or when
+//! triggered with [`BackgroundJobManager::run_now`]. Every run is a separate task, so a run that
+//! fails or panics is recorded in the job's status and the job keeps its schedule.
+
+use crate::common::{Error, Result};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+/// State of a background job
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobState {
+    /// Waiting for its next run
+    Idle,
+    /// A run is in progress
+    Running,
+    /// Scheduled runs are skipped until the job is resumed
+    Paused,
+}
+
+impl JobState {
+    /// Lower-case name used by status views
+    pub fn name(self) -> &'static str {
+        match self {
+            JobState::Idle => "idle",
+            JobState::Running => "running",
+            JobState::Paused => "paused",
+        }
+    }
+}
+
+/// Status snapshot of one registered job
+#[derive(Debug, Clone)]
+pub struct JobStatus {
+    /// Job name (unique per manager)
+    pub name: String,
+    /// Time between scheduled runs
+    pub interval: Duration,
+    /// Current state
+    pub state: JobState,
+    /// Completed runs, including failed ones
+    pub runs: u64,
+    /// Runs that returned an error or panicked
+    pub failures: u64,
+    /// Runs that panicked
+    pub panics: u64,
+    /// Error or panic message of the latest failed run
+    pub last_error: Option<String>,
+    /// Duration of the latest run
+    pub last_duration: Option<Duration>,
+    /// Unix time (ms) at which the latest run finished
+    pub last_finished_ms: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct JobStats {
+    running: bool,
+    runs: u64,
+    failures: u64,
+    panics: u64,
+    last_error: Option<String>,
+    last_duration: Option<Duration>,
+    last_finished_ms: Option<u64>,
+}
+
+struct Job {
+    interval: Duration,
+    paused: AtomicBool,
+    /// Set by `run_now`; runs the job once even when paused
+    triggered: AtomicBool,
+    wake: Notify,
+    stats: Mutex<JobStats>,
+    scheduler: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Job {
+    fn status(&self, name: &str) -> JobStatus {
+        let stats = self.stats.lock().unwrap();
+        let state = if stats.running {
+            JobState::Running
+        } else if self.paused.load(Ordering::Acquire) {
+            JobState::Paused
+        } else {
+            JobState::Idle
+        };
+        JobStatus {
+            name: name.to_string(),
+            interval: self.interval,
+            state,
+            runs: stats.runs,
+            failures: stats.failures,
+            panics: stats.panics,
+            last_error: stats.last_error.clone(),
+            last_duration: stats.last_duration,
+            last_finished_ms: stats.last_finished_ms,
+        }
+    }
+
+    fn abort(&self) {
+        if let Some(handle) = self.scheduler.lock().unwrap().take() {
+            handle.abort();
+        }
+    }
+}
+
+#[derive(Default)]
+struct JobRegistry {
+    jobs: Mutex<BTreeMap<String, Arc<Job>>>,
+}
+
+impl Drop for JobRegistry {
+    fn drop(&mut self) {
+        if let Ok(jobs) = self.jobs.get_mut() {
+            for job in jobs.values() {
+                job.abort();
+            }
+        }
+    }
+}
+
+/// Registry and scheduler of background jobs
+///
+/// Clones share the same jobs; jobs are stopped when the last clone is dropped, by
+/// [`Self::unregister`] or by [`Self::shutdown`].
+#[derive(Clone, Default)]
+pub struct BackgroundJobManager {
+    registry: Arc<JobRegistry>,
+}
+
+impl std::fmt::Debug for BackgroundJobManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BackgroundJobManager")
+            .field("jobs", &self.job_names())
+            .finish()
+    }
+}
+
+impl BackgroundJobManager {
+    /// Creates a manager without jobs
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `job` under `name`, first run immediately and then every `interval`
+    ///
+    /// Must be called within a tokio runtime; the job runs there. A job registered `paused`
+    /// only runs when triggered until it is resumed.
+    pub fn register<F, Fut>(
+        &self,
+        name: &str,
+        interval: Duration,
+        paused: bool,
+        mut job: F,
+    ) -> Result<()>
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let runtime = tokio::runtime::Handle::try_current().map_err(|_| {
+            Error::internal(format!(
+                "background job {name} registered outside a runtime"
+            ))
+        })?;
+        let mut jobs = self.registry.jobs.lock().unwrap();
+        if jobs.contains_key(name) {
+            return Err(Error::validation(format!(
+                "background job {name} is already registered"
+            )));
+        }
+        let entry = Arc::new(Job {
+            interval,
+            paused: AtomicBool::new(paused),
+            triggered: AtomicBool::new(false),
+            wake: Notify::new(),
+            stats: Mutex::new(JobStats::default()),
+            scheduler: Mutex::new(None),
+        });
+        let scheduled = entry.clone();
+        let handle = runtime.spawn(async move {
+            let mut ticker = tokio::time::interval(interval.max(Duration::from_millis(1)));
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = scheduled.wake.notified() => {}
+                }
+                let triggered = scheduled.triggered.swap(false, Ordering::AcqRel);
+                if scheduled.paused.load(Ordering::Acquire) && !triggered {
+                    continue;
+                }
+                run_once(&scheduled, &mut job).await;
+            }
+        });
+        *entry.scheduler.lock().unwrap() = Some(handle);
+        jobs.insert(name.to_string(), entry);
+        Ok(())
+    }
+
+    /// Stops and removes `name`; returns whether it was registered
+    ///
+    /// A run in progress completes on its own task.
+    pub fn unregister(&self, name: &str) -> bool {
+        let job = self.registry.jobs.lock().unwrap().remove(name);
+        job.map(|j| j.abort()).is_some()
+    }
+
+    /// Skips the scheduled runs of `name` until [`Self::resume`]
+    pub fn pause(&self, name: &str) -> Result<()> {
+        self.job(name)?.paused.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// Restores the schedule of a paused job
+    pub fn resume(&self, name: &str) -> Result<()> {
+        self.job(name)?.paused.store(false, Ordering::Release);
+        Ok(())
+    }
+
+    /// Runs `name` as soon as its current run (if any) finishes, even when paused
+    pub fn run_now(&self, name: &str) -> Result<()> {
+        let job = self.job(name)?;
+        job.triggered.store(true, Ordering::Release);
+        job.wake.notify_one();
+        Ok(())
+    }
+
+    /// Status of every job, ordered by name
+    pub fn status(&self) -> Vec<JobStatus> {
+        let jobs = self.registry.jobs.lock().unwrap();
+        jobs.iter().map(|(name, job)| job.status(name)).collect()
+    }
+
+    /// Status of `name`, if registered
+    pub fn job_status(&self, name: &str) -> Option<JobStatus> {
+        let jobs = self.registry.jobs.lock().unwrap();
+        jobs.get(name).map(|job| job.status(name))
+    }
+
+    /// Names of the registered jobs, ordered
+    pub fn job_names(&self) -> Vec<String> {
+        self.registry.jobs.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Stops and removes every job
+    pub fn shutdown(&self) {
+        let jobs = std::mem::take(&mut *self.registry.jobs.lock().unwrap());
+        for job in jobs.values() {
+            job.abort();
+        }
+    }
+
+    fn job(&self, name: &str) -> Result<Arc<Job>> {
+        self.registry
+            .jobs
+            .lock()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::validation(format!("background job {name} is not registered")))
+    }
+}
+
+/// Runs the job once on its own task and records the outcome
+async fn run_once<F, Fut>(job: &Job, make_run: &mut F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    job.stats.lock().unwrap().running = true;
+    let started = Instant::now();
+    let outcome = match std::panic::catch_unwind(AssertUnwindSafe(&mut *make_run)) {
+        Ok(run) => match tokio::spawn(run).await {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(e)) => Err((e.to_string(), false)),
+            Err(e) if e.is_panic() => Err((panic_message(e.into_panic()), true)),
+            Err(e) => Err((e.to_string(), false)),
+        },
+        Err(panic) => Err((panic_message(panic), true)),
+    };
+    let mut stats = job.stats.lock().unwrap();
+    stats.running = false;
+    stats.runs += 1;
+    stats.last_duration = Some(started.elapsed());
+    stats.last_finished_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_millis() as u64);
+    if let Err((message, panicked)) = outcome {
+        stats.failures += 1;
+        if panicked {
+            stats.panics += 1;
+        }
+        stats.last_error = Some(message);
+    }
+}
+
+fn panic_message(panic: Box<dyn std::any::Any + Send>) -> String {
+    match panic.downcast::<String>() {
+        Ok(s) => format!("panicked: {s}"),
+        Err(panic) => match panic.downcast::<&'static str>() {
+            Ok(s) => format!("panicked: {s}"),
+            Err(_) => "panicked".to_string(),
+        },
+    }
+}
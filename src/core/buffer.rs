@@ -1,13 +1,23 @@
 //! Buffer manager for rustdb
+//!
+//! A fixed-capacity pool of frames with pin counts, dirty tracking and LRU, Clock or adaptive
+//! eviction. Frames hold parsed [`Page`]s or, as [`BufferFrame`] allows, other page forms: each
+//! shard of the buffer pool of a table's file manager
+//! ([`crate::storage::io_optimization::ShardedPageCache`]) is a `BufferManager` of page images,
+//! so table scans, index scans and writes of the table's
+//! [`crate::storage::page_manager::PageManager`] share its frames, and a scan's pinned page stays
+//! in the pool until the scan moves on.
 
 use crate::common::{types::PageId, Error, Result};
 use crate::storage::page::Page;
 use std::collections::{HashMap, VecDeque};
+use std::fmt::Debug;
+use std::hash::Hash;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 /// Persists serialized page bytes (e.g. write to `.tbl` via file manager). Return `Ok(())` on success.
-pub type PageFlushCallback = Arc<dyn Fn(PageId, Vec<u8>) -> Result<()> + Send + Sync>;
+pub type PageFlushCallback<K = PageId> = Arc<dyn Fn(K, Vec<u8>) -> Result<()> + Send + Sync>;
 
 /// Buffer statistics
 #[derive(Debug, Clone)]
@@ -88,27 +98,95 @@ impl BufferStats {
     }
 }
 
+/// What a frame of a [`BufferManager`] holds
+///
+/// A parsed [`Page`] mirrors the frame's pin and dirty state into its header; a page image
+/// shared with readers (`Arc<Vec<u8>>`, as the buffer pool of a table's file manager caches it)
+/// only has the frame's own state.
+pub trait BufferFrame {
+    /// Serialized page, passed to the flush hook
+    fn to_bytes(&self) -> Result<Vec<u8>>;
+
+    /// Whether the page itself asks to stay cached (beyond the frame's pins)
+    fn is_pinned(&self) -> bool {
+        false
+    }
+
+    /// Whether the page itself is marked modified (beyond the frame's dirty flag)
+    fn is_dirty(&self) -> bool {
+        false
+    }
+
+    /// Mirrors the frame's pin state into the page
+    fn set_pinned(&mut self, _pinned: bool) {}
+
+    /// Mirrors the frame's dirty state into the page
+    fn set_dirty(&mut self, _dirty: bool) {}
+}
+
+impl BufferFrame for Page {
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        Page::to_bytes(self)
+    }
+
+    fn is_pinned(&self) -> bool {
+        self.header.is_pinned
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.header.is_dirty
+    }
+
+    fn set_pinned(&mut self, pinned: bool) {
+        if pinned {
+            self.header.pin();
+        } else {
+            self.header.unpin();
+        }
+    }
+
+    fn set_dirty(&mut self, dirty: bool) {
+        if dirty {
+            self.header.mark_dirty();
+        } else {
+            self.header.mark_clean();
+        }
+    }
+}
+
+impl BufferFrame for Arc<Vec<u8>> {
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(self.to_vec())
+    }
+}
+
 /// LRU cache entry
 #[derive(Debug, Clone)]
-struct LRUEntry {
+struct LRUEntry<F> {
     /// Page
-    page: Page,
+    page: F,
     /// Last access time
     last_access: Instant,
     /// Access count
     access_count: u32,
     /// Dirty page flag
     is_dirty: bool,
+    /// Pins held; a pinned page is never evicted
+    pin_count: u32,
+    /// Clock reference bit, set on access and cleared as the clock hand passes
+    referenced: bool,
 }
 
-impl LRUEntry {
+impl<F: BufferFrame> LRUEntry<F> {
     /// Creates new LRU entry
-    fn new(page: Page) -> Self {
+    fn new(page: F) -> Self {
         Self {
             page,
             last_access: Instant::now(),
             access_count: 1,
             is_dirty: false,
+            pin_count: 0,
+            referenced: true,
         }
     }
 
@@ -116,18 +194,29 @@ impl LRUEntry {
     fn touch(&mut self) {
         self.last_access = Instant::now();
         self.access_count += 1;
+        self.referenced = true;
+    }
+
+    /// Whether eviction must skip the page
+    fn is_pinned(&self) -> bool {
+        self.pin_count > 0 || self.page.is_pinned()
+    }
+
+    /// Whether the page must be flushed before it is dropped
+    fn needs_flush(&self) -> bool {
+        self.is_dirty || self.page.is_dirty()
     }
 
     /// Marks page as modified
     fn mark_dirty(&mut self) {
         self.is_dirty = true;
-        self.page.header.mark_dirty();
+        self.page.set_dirty(true);
     }
 
     /// Marks page as clean
     fn mark_clean(&mut self) {
         self.is_dirty = false;
-        self.page.header.mark_clean();
+        self.page.set_dirty(false);
     }
 }
 
@@ -143,34 +232,77 @@ pub enum EvictionStrategy {
 }
 
 /// Buffer manager with LRU cache
-pub struct BufferManager {
+///
+/// Frames hold `F` (parsed [`Page`]s by default) keyed by `K` (page ids by default; the buffer
+/// pool of a file manager keys page images by file and page).
+pub struct BufferManager<F = Page, K = PageId> {
     /// LRU page cache
-    cache: HashMap<PageId, LRUEntry>,
+    cache: HashMap<K, LRUEntry<F>>,
     /// LRU queue for tracking access order
-    lru_queue: VecDeque<PageId>,
+    lru_queue: VecDeque<K>,
     /// Maximum number of pages in cache
     max_pages: usize,
     /// Eviction strategy
     strategy: EvictionStrategy,
     /// Buffer statistics
     stats: BufferStats,
-    /// Pointer for Clock algorithm
+    /// Pages in insertion order, swept by the Clock hand
+    clock_ring: Vec<K>,
+    /// Position of the Clock hand in `clock_ring`
     clock_pointer: usize,
     /// Access counter for adaptive strategy
     access_counter: u64,
     /// When set, dirty pages are serialized and passed here on eviction and flush.
-    dirty_flush: Option<PageFlushCallback>,
+    dirty_flush: Option<PageFlushCallback<K>>,
 }
 
 impl BufferManager {
     /// Creates a new buffer manager
     pub fn new(max_pages: usize, strategy: EvictionStrategy) -> Self {
+        Self::with_frames(max_pages, strategy)
+    }
+
+    /// Adds page to cache
+    pub fn add_page(&mut self, page: Page) -> Result<()> {
+        let page_id = page.header.page_id;
+
+        // If page already exists, update it
+        if self.cache.contains_key(&page_id) {
+            self.update_page(page)?;
+            return Ok(());
+        }
+
+        // If limit exceeded, evict a page
+        if self.cache.len() >= self.max_pages {
+            self.evict_page()?;
+        }
+
+        self.insert_entry(page_id, page);
+        Ok(())
+    }
+
+    /// Updates existing page
+    pub fn update_page(&mut self, page: Page) -> Result<()> {
+        let page_id = page.header.page_id;
+        if self.replace_frame(page_id, page) {
+            if let Some(entry) = self.cache.get_mut(&page_id) {
+                entry.mark_dirty();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<F: BufferFrame, K: Copy + Eq + Hash + Debug> BufferManager<F, K> {
+    /// Creates a buffer manager of frames `F` keyed by `K` ([`BufferManager::new`] for pages)
+    pub fn with_frames(max_pages: usize, strategy: EvictionStrategy) -> Self {
         Self {
             cache: HashMap::new(),
             lru_queue: VecDeque::new(),
             max_pages,
             strategy,
             stats: BufferStats::new(),
+            clock_ring: Vec::new(),
             clock_pointer: 0,
             access_counter: 0,
             dirty_flush: None,
@@ -178,11 +310,11 @@ impl BufferManager {
     }
 
     /// Install a hook to persist dirty page contents. Without it, eviction/flush only clears dirty state (with a warning on eviction).
-    pub fn set_dirty_flush_hook(&mut self, hook: Option<PageFlushCallback>) {
+    pub fn set_dirty_flush_hook(&mut self, hook: Option<PageFlushCallback<K>>) {
         self.dirty_flush = hook;
     }
 
-    fn run_flush_hook(&mut self, page_id: PageId, bytes: Vec<u8>) -> Result<()> {
+    fn run_flush_hook(&mut self, page_id: K, bytes: Vec<u8>) -> Result<()> {
         let Some(ref hook) = self.dirty_flush else {
             return Ok(());
         };
@@ -192,7 +324,7 @@ impl BufferManager {
     }
 
     /// Gets page by ID
-    pub fn get_page(&mut self, page_id: PageId) -> Option<&Page> {
+    pub fn get_page(&mut self, page_id: K) -> Option<&F> {
         self.stats.record_read();
 
         if !self.cache.contains_key(&page_id) {
@@ -215,7 +347,7 @@ impl BufferManager {
     }
 
     /// Gets mutable reference to page
-    pub fn get_page_mut(&mut self, page_id: PageId) -> Option<&mut Page> {
+    pub fn get_page_mut(&mut self, page_id: K) -> Option<&mut F> {
         self.stats.record_write();
 
         if !self.cache.contains_key(&page_id) {
@@ -238,35 +370,25 @@ impl BufferManager {
         }
     }
 
-    /// Adds page to cache
-    pub fn add_page(&mut self, page: Page) -> Result<()> {
-        let page_id = page.header.page_id;
-
-        // If page already exists, update it
+    /// Caches `frame` as page `page_id` (not dirty: the caller wrote it through), replacing the
+    /// frame cached for it. A full pool evicts a page first; when every page is pinned the pool grows past its capacity until pins are
+    /// released, instead of failing like [`BufferManager::add_page`].
+    pub fn put_frame(&mut self, page_id: K, frame: F) -> Result<()> {
         if self.cache.contains_key(&page_id) {
-            self.update_page(page)?;
+            self.replace_frame(page_id, frame);
             return Ok(());
         }
-
-        // If limit exceeded, evict a page
-        if self.cache.len() >= self.max_pages {
+        if self.cache.len() >= self.max_pages && self.cache.values().any(|e| !e.is_pinned()) {
             self.evict_page()?;
         }
-
-        // Add page to cache
-        let entry = LRUEntry::new(page);
-        self.cache.insert(page_id, entry);
-        self.lru_queue.push_back(page_id);
-
+        self.insert_entry(page_id, frame);
         Ok(())
     }
 
-    /// Updates existing page
-    pub fn update_page(&mut self, page: Page) -> Result<()> {
-        let page_id = page.header.page_id;
-
+    /// Replaces the frame of a cached page; returns whether the page was cached
+    fn replace_frame(&mut self, page_id: K, frame: F) -> bool {
         if !self.cache.contains_key(&page_id) {
-            return Ok(());
+            return false;
         }
 
         // First update LRU order
@@ -274,21 +396,31 @@ impl BufferManager {
 
         // Then update page
         if let Some(entry) = self.cache.get_mut(&page_id) {
-            entry.page = page;
+            entry.page = frame;
             entry.touch();
-            entry.mark_dirty();
         }
+        true
+    }
 
-        Ok(())
+    fn insert_entry(&mut self, page_id: K, frame: F) {
+        self.cache.insert(page_id, LRUEntry::new(frame));
+        self.lru_queue.push_back(page_id);
+        self.clock_ring.push(page_id);
     }
 
     /// Removes page from cache
-    pub fn remove_page(&mut self, page_id: PageId) -> Option<Page> {
+    pub fn remove_page(&mut self, page_id: K) -> Option<F> {
         if let Some(entry) = self.cache.remove(&page_id) {
             // Remove from LRU queue
             if let Some(pos) = self.lru_queue.iter().position(|&id| id == page_id) {
                 self.lru_queue.remove(pos);
             }
+            if let Some(pos) = self.clock_ring.iter().position(|&id| id == page_id) {
+                self.clock_ring.remove(pos);
+                if pos < self.clock_pointer {
+                    self.clock_pointer -= 1;
+                }
+            }
             Some(entry.page)
         } else {
             None
@@ -306,12 +438,12 @@ impl BufferManager {
         let dirty = self
             .cache
             .get(&page_id)
-            .map(|e| e.is_dirty || e.page.header.is_dirty)
+            .map(|e| e.needs_flush())
             .unwrap_or(false);
         if dirty {
             if self.dirty_flush.is_none() {
                 log::warn!(
-                    "Evicting dirty page {} without flush hook; data may not be persisted",
+                    "Evicting dirty page {:?} without flush hook; data may not be persisted",
                     page_id
                 );
             } else {
@@ -326,63 +458,46 @@ impl BufferManager {
     }
 
     /// Evicts page using LRU algorithm
-    fn evict_lru(&mut self) -> Result<PageId> {
-        if let Some(page_id) = self.lru_queue.front().copied() {
-            // Check if page is pinned
-            if let Some(entry) = self.cache.get(&page_id) {
-                if entry.page.header.is_pinned {
-                    // Find next unpinned page
-                    for &id in self.lru_queue.iter().skip(1) {
-                        if let Some(entry) = self.cache.get(&id) {
-                            if !entry.page.header.is_pinned {
-                                return Ok(id);
-                            }
-                        }
-                    }
-                    return Err(Error::validation("All pages are pinned"));
-                }
-            }
-            Ok(page_id)
-        } else {
-            Err(Error::validation("Cache is empty"))
+    fn evict_lru(&mut self) -> Result<K> {
+        if self.lru_queue.is_empty() {
+            return Err(Error::validation("Cache is empty"));
         }
+        self.lru_queue
+            .iter()
+            .copied()
+            .find(|id| self.cache.get(id).is_some_and(|e| !e.is_pinned()))
+            .ok_or_else(|| Error::validation("All pages are pinned"))
     }
 
-    /// Evicts page using Clock algorithm
-    fn evict_clock(&mut self) -> Result<PageId> {
-        let mut attempts = 0;
-        let max_attempts = self.cache.len() * 2;
+    /// Evicts page using Clock algorithm: the hand sweeps the pages in insertion order, clearing
+    /// reference bits, and stops at the first unpinned page whose bit is already clear.
+    fn evict_clock(&mut self) -> Result<K> {
+        if self.clock_ring.is_empty() {
+            return Err(Error::validation("Cache is empty"));
+        }
 
-        while attempts < max_attempts {
-            let page_ids: Vec<PageId> = self.cache.keys().copied().collect();
-            if page_ids.is_empty() {
-                return Err(Error::validation("Cache is empty"));
+        // Two sweeps clear every reference bit, so an unpinned page is found by then.
+        for _ in 0..self.clock_ring.len() * 2 {
+            if self.clock_pointer >= self.clock_ring.len() {
+                self.clock_pointer = 0;
             }
-
-            let page_id = page_ids[self.clock_pointer % page_ids.len()];
-            self.clock_pointer = (self.clock_pointer + 1) % page_ids.len();
-
-            if let Some(entry) = self.cache.get(&page_id) {
-                if !entry.page.header.is_pinned {
-                    if entry.access_count == 0 {
+            let page_id = self.clock_ring[self.clock_pointer];
+            if let Some(entry) = self.cache.get_mut(&page_id) {
+                if !entry.is_pinned() {
+                    if !entry.referenced {
                         return Ok(page_id);
-                    } else {
-                        // Decrease access counter
-                        if let Some(entry_mut) = self.cache.get_mut(&page_id) {
-                            entry_mut.access_count = entry_mut.access_count.saturating_sub(1);
-                        }
                     }
+                    entry.referenced = false;
                 }
             }
-
-            attempts += 1;
+            self.clock_pointer += 1;
         }
 
-        Err(Error::validation("Failed to find page for eviction"))
+        Err(Error::validation("All pages are pinned"))
     }
 
     /// Evicts page using adaptive strategy
-    fn evict_adaptive(&mut self) -> Result<PageId> {
+    fn evict_adaptive(&mut self) -> Result<K> {
         // Adaptive strategy: combination of LRU and Clock
         let hit_ratio = self.stats.hit_ratio();
 
@@ -399,7 +514,7 @@ impl BufferManager {
     }
 
     /// Updates LRU order
-    fn update_lru_order(&mut self, page_id: PageId) {
+    fn update_lru_order(&mut self, page_id: K) {
         // Remove page from current position
         if let Some(pos) = self.lru_queue.iter().position(|&id| id == page_id) {
             self.lru_queue.remove(pos);
@@ -408,26 +523,65 @@ impl BufferManager {
         self.lru_queue.push_back(page_id);
     }
 
-    /// Pins page in memory
-    pub fn pin_page(&mut self, page_id: PageId) -> Result<()> {
+    /// Pins page in memory. Pins nest: the page stays until every pin is released.
+    pub fn pin_page(&mut self, page_id: K) -> Result<()> {
         if let Some(entry) = self.cache.get_mut(&page_id) {
-            entry.page.header.pin();
+            entry.pin_count += 1;
+            entry.page.set_pinned(true);
         } else {
             return Err(Error::validation("Page not found in cache"));
         }
         Ok(())
     }
 
-    /// Unpins page from memory
-    pub fn unpin_page(&mut self, page_id: PageId) -> Result<()> {
+    /// Releases one pin of the page
+    pub fn unpin_page(&mut self, page_id: K) -> Result<()> {
         if let Some(entry) = self.cache.get_mut(&page_id) {
-            entry.page.header.unpin();
+            entry.pin_count = entry.pin_count.saturating_sub(1);
+            if entry.pin_count == 0 {
+                entry.page.set_pinned(false);
+            }
         } else {
             return Err(Error::validation("Page not found in cache"));
         }
         Ok(())
     }
 
+    /// Releases one pin of the page and marks it dirty when the holder modified it
+    pub fn unpin_page_dirty(&mut self, page_id: K, is_dirty: bool) -> Result<()> {
+        if is_dirty {
+            if let Some(entry) = self.cache.get_mut(&page_id) {
+                entry.mark_dirty();
+            }
+        }
+        self.unpin_page(page_id)
+    }
+
+    /// Number of pins held on the page (0 when it is not cached)
+    pub fn pin_count(&self, page_id: K) -> u32 {
+        self.cache.get(&page_id).map_or(0, |e| e.pin_count)
+    }
+
+    /// Number of cached pages with at least one pin
+    pub fn pinned_page_count(&self) -> usize {
+        self.cache.values().filter(|e| e.pin_count > 0).count()
+    }
+
+    /// Maximum number of pages in cache
+    pub fn capacity(&self) -> usize {
+        self.max_pages
+    }
+
+    /// Changes the capacity, evicting (and flushing) pages down to it.
+    /// Fails when the remaining pages are all pinned.
+    pub fn set_capacity(&mut self, max_pages: usize) -> Result<()> {
+        self.max_pages = max_pages;
+        while self.cache.len() > self.max_pages {
+            self.evict_page()?;
+        }
+        Ok(())
+    }
+
     /// Returns buffer statistics
     pub fn get_stats(&self) -> BufferStats {
         self.stats.clone()
@@ -444,7 +598,7 @@ impl BufferManager {
     }
 
     /// Checks if cache contains page
-    pub fn contains_page(&self, page_id: PageId) -> bool {
+    pub fn contains_page(&self, page_id: K) -> bool {
         self.cache.contains_key(&page_id)
     }
 
@@ -456,7 +610,7 @@ impl BufferManager {
     /// Forces write of all dirty pages
     pub fn flush_dirty_pages(&mut self) -> Result<usize> {
         let mut flushed_count = 0;
-        let dirty_pages: Vec<PageId> = self
+        let dirty_pages: Vec<K> = self
             .cache
             .iter()
             .filter(|(_, entry)| entry.is_dirty)
@@ -467,7 +621,7 @@ impl BufferManager {
             let need_flush = self
                 .cache
                 .get(&page_id)
-                .map(|e| e.is_dirty || e.page.header.is_dirty)
+                .map(|e| e.needs_flush())
                 .unwrap_or(false);
             if need_flush && self.dirty_flush.is_some() {
                 let bytes = self.cache.get(&page_id).unwrap().page.to_bytes()?;
@@ -482,6 +636,14 @@ impl BufferManager {
         Ok(flushed_count)
     }
 
+    /// Drops every cached page, pinned or not, without flushing
+    pub fn clear(&mut self) {
+        self.cache.clear();
+        self.lru_queue.clear();
+        self.clock_ring.clear();
+        self.clock_pointer = 0;
+    }
+
     /// Changes eviction strategy
     pub fn set_eviction_strategy(&mut self, strategy: EvictionStrategy) {
         self.strategy = strategy;
@@ -597,6 +759,49 @@ mod tests {
         assert_eq!(manager.get_eviction_strategy(), EvictionStrategy::Adaptive);
     }
 
+    #[test]
+    fn test_clock_eviction_gives_referenced_pages_a_second_chance() {
+        let mut manager = BufferManager::new(3, EvictionStrategy::Clock);
+        for id in 1..=3 {
+            manager.add_page(Page::new(id)).unwrap();
+        }
+
+        // The first sweep clears every bit and evicts page 1; page 2 is then accessed again.
+        manager.add_page(Page::new(4)).unwrap();
+        assert!(!manager.contains_page(1));
+        manager.get_page(2);
+        manager.add_page(Page::new(5)).unwrap();
+
+        assert!(manager.contains_page(2));
+        assert!(!manager.contains_page(3));
+        assert!(manager.contains_page(4));
+        assert!(manager.contains_page(5));
+    }
+
+    #[test]
+    fn test_pins_nest_and_pinned_pages_survive_shrinking() {
+        let mut manager = BufferManager::new(3, EvictionStrategy::Clock);
+        for id in 1..=3 {
+            manager.add_page(Page::new(id)).unwrap();
+        }
+        manager.pin_page(1).unwrap();
+        manager.pin_page(1).unwrap();
+        manager.unpin_page(1).unwrap();
+        assert_eq!(manager.pin_count(1), 1);
+
+        manager.set_capacity(1).unwrap();
+        assert_eq!(manager.capacity(), 1);
+        assert_eq!(manager.page_count(), 1);
+        assert!(manager.contains_page(1));
+        assert!(manager.add_page(Page::new(4)).is_err());
+
+        manager.unpin_page_dirty(1, true).unwrap();
+        assert_eq!(manager.pin_count(1), 0);
+        assert_eq!(manager.dirty_page_count(), 1);
+        manager.add_page(Page::new(4)).unwrap();
+        assert!(!manager.contains_page(1));
+    }
+
     #[test]
     fn test_dirty_flush_hook_on_eviction() {
         use std::sync::atomic::{AtomicUsize, Ordering};
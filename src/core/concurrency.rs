@@ -1,14 +1,23 @@
 //! Comprehensive concurrency manager
 //!
 //! Combines MVCC, locks and deadlock detection
+//!
+//! At SERIALIZABLE a [`ConcurrencyManager::scan`] also locks the scanned key range, gaps
+//! included, and every write locks its key against such ranges: an insert into a range another
+//! transaction scanned waits until that transaction ends, and a scan waits for the uncommitted
+//! writes in its range, so repeating a scan returns no phantom rows.
 
 use crate::common::{Error, Result};
 use crate::core::advanced_lock_manager::{
-    AdvancedLockConfig, AdvancedLockManager, LockMode, ResourceType,
+    AdvancedLockConfig, AdvancedLockManager, KeyRange, LockMode, ResourceType,
+};
+use crate::core::mvcc::{
+    MVCCManager, RowKey, SnapshotInfo, SnapshotTransaction, Timestamp,
+    DEFAULT_SNAPSHOT_WARNING_THRESHOLD,
 };
-use crate::core::mvcc::{MVCCManager, RowKey, Timestamp};
 use crate::core::transaction::TransactionId;
 use std::collections::HashMap;
+use std::ops::RangeInclusive;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
@@ -51,6 +60,11 @@ pub struct ConcurrencyConfig {
     pub enable_mvcc: bool,
     /// MVCC automatic cleanup interval
     pub vacuum_interval: Duration,
+    /// Age after which a snapshot holding back cleanup is logged
+    pub snapshot_warning_threshold: Duration,
+    /// How long superseded versions stay readable with [`ConcurrencyManager::snapshot_as_of`]
+    /// (zero: no time travel)
+    pub version_retention: Duration,
 }
 
 impl Default for ConcurrencyConfig {
@@ -61,6 +75,8 @@ impl Default for ConcurrencyConfig {
             default_lock_granularity: LockGranularity::Row,
             enable_mvcc: true,
             vacuum_interval: Duration::from_secs(60),
+            snapshot_warning_threshold: DEFAULT_SNAPSHOT_WARNING_THRESHOLD,
+            version_retention: Duration::ZERO,
         }
     }
 }
@@ -75,19 +91,25 @@ pub struct ConcurrencyManager {
     config: ConcurrencyConfig,
     /// Simple row store when MVCC is disabled (lock + in-memory map).
     non_mvcc_store: Arc<Mutex<HashMap<RowKey, Vec<u8>>>>,
+    /// Isolation levels of running transactions
+    isolation_levels: Mutex<HashMap<TransactionId, IsolationLevel>>,
 }
 
 impl ConcurrencyManager {
     /// Creates a new concurrency manager
     pub fn new(config: ConcurrencyConfig) -> Self {
         let lock_manager = Arc::new(AdvancedLockManager::new(config.lock_config.clone()));
-        let mvcc_manager = Arc::new(MVCCManager::new());
+        let mvcc_manager = Arc::new(
+            MVCCManager::with_snapshot_warning_threshold(config.snapshot_warning_threshold)
+                .with_version_retention(config.version_retention),
+        );
 
         Self {
             lock_manager,
             mvcc_manager,
             config,
             non_mvcc_store: Arc::new(Mutex::new(HashMap::new())),
+            isolation_levels: Mutex::new(HashMap::new()),
         }
     }
 
@@ -97,8 +119,52 @@ impl ConcurrencyManager {
         transaction_id: TransactionId,
         isolation_level: IsolationLevel,
     ) -> Result<Timestamp> {
+        self.isolation_levels
+            .lock()
+            .unwrap()
+            .insert(transaction_id, isolation_level);
         // Return snapshot timestamp for transaction
-        Ok(Timestamp::now())
+        let snapshot = Timestamp::now();
+        if self.config.enable_mvcc {
+            self.mvcc_manager
+                .register_transaction_snapshot(transaction_id, snapshot);
+        }
+        Ok(snapshot)
+    }
+
+    /// Returns the isolation level the transaction began with (the configured default for
+    /// transactions not begun here)
+    pub fn isolation_level(&self, transaction_id: TransactionId) -> IsolationLevel {
+        self.isolation_levels
+            .lock()
+            .unwrap()
+            .get(&transaction_id)
+            .copied()
+            .unwrap_or(self.config.default_isolation_level)
+    }
+
+    /// Opens a long-lived read-only snapshot for backups and dumps
+    ///
+    /// VACUUM keeps the versions the snapshot sees until it is dropped.
+    pub fn open_snapshot(&self, label: &str) -> Result<SnapshotTransaction> {
+        if !self.config.enable_mvcc {
+            return Err(Error::database("Snapshot transactions require MVCC"));
+        }
+        Ok(self.mvcc_manager.open_snapshot(label))
+    }
+
+    /// Opens a read-only snapshot of the data as it was committed at `timestamp`, within
+    /// `version_retention`
+    pub fn snapshot_as_of(&self, label: &str, timestamp: Timestamp) -> Result<SnapshotTransaction> {
+        if !self.config.enable_mvcc {
+            return Err(Error::database("Snapshot transactions require MVCC"));
+        }
+        self.mvcc_manager.snapshot_as_of(label, timestamp)
+    }
+
+    /// Returns open snapshot transactions, oldest first
+    pub fn open_snapshots(&self) -> Vec<SnapshotInfo> {
+        self.mvcc_manager.open_snapshots()
     }
 
     /// Acquires read lock
@@ -108,7 +174,7 @@ impl ConcurrencyManager {
         resource: ResourceType,
         timeout: Option<Duration>,
     ) -> Result<()> {
-        match self.config.default_isolation_level {
+        match self.isolation_level(transaction_id) {
             IsolationLevel::ReadUncommitted => {
                 // Don't require lock for reading
                 Ok(())
@@ -164,6 +230,65 @@ impl ConcurrencyManager {
         }
     }
 
+    /// Reads the rows of `table_id` with IDs in `row_ids`, ordered by row ID
+    ///
+    /// At SERIALIZABLE the range stays locked until the transaction ends, so no other
+    /// transaction can insert into or delete from it meanwhile.
+    pub async fn scan(
+        &self,
+        transaction_id: TransactionId,
+        table_id: u32,
+        row_ids: RangeInclusive<u64>,
+        snapshot: Timestamp,
+    ) -> Result<Vec<(u64, Vec<u8>)>> {
+        if self.isolation_level(transaction_id) == IsolationLevel::Serializable {
+            self.lock_manager
+                .acquire_range_lock(
+                    transaction_id,
+                    KeyRange::new(table_id as u64, row_ids.clone()),
+                    None,
+                )
+                .await?;
+        }
+
+        if self.config.enable_mvcc {
+            Ok(self
+                .mvcc_manager
+                .scan_range(table_id, row_ids, transaction_id, snapshot))
+        } else {
+            let keys: Vec<RowKey> = {
+                let store = self
+                    .non_mvcc_store
+                    .lock()
+                    .map_err(|e| Error::internal(format!("non-MVCC store lock: {}", e)))?;
+                store
+                    .keys()
+                    .filter(|key| key.table_id == table_id && row_ids.contains(&key.row_id))
+                    .cloned()
+                    .collect()
+            };
+            let mut rows = Vec::with_capacity(keys.len());
+            for key in keys {
+                if let Some(data) = self.read(transaction_id, &key, snapshot).await? {
+                    rows.push((key.row_id, data));
+                }
+            }
+            rows.sort_unstable_by_key(|(row_id, _)| *row_id);
+            Ok(rows)
+        }
+    }
+
+    /// Locks `key` for a write: against range locks of other transactions' scans, then
+    /// exclusively
+    async fn lock_for_write(&self, transaction_id: TransactionId, key: &RowKey) -> Result<()> {
+        self.lock_manager
+            .acquire_key_lock(transaction_id, key.table_id as u64, key.row_id, None)
+            .await?;
+        let resource = ResourceType::Record(key.table_id as u64, key.row_id);
+        self.acquire_write_lock(transaction_id, resource, None)
+            .await
+    }
+
     /// Writes data with MVCC consideration
     pub async fn write(
         &self,
@@ -172,9 +297,7 @@ impl ConcurrencyManager {
         data: Vec<u8>,
     ) -> Result<()> {
         // Acquire write lock
-        let resource = ResourceType::Record(key.table_id as u64, key.row_id);
-        self.acquire_write_lock(transaction_id, resource, None)
-            .await?;
+        self.lock_for_write(transaction_id, &key).await?;
 
         if self.config.enable_mvcc {
             // Create new version
@@ -194,9 +317,7 @@ impl ConcurrencyManager {
     /// Deletes data
     pub async fn delete(&self, transaction_id: TransactionId, key: &RowKey) -> Result<()> {
         // Acquire write lock
-        let resource = ResourceType::Record(key.table_id as u64, key.row_id);
-        self.acquire_write_lock(transaction_id, resource, None)
-            .await?;
+        self.lock_for_write(transaction_id, key).await?;
 
         if self.config.enable_mvcc {
             // Mark for deletion
@@ -221,6 +342,10 @@ impl ConcurrencyManager {
 
         // Release all locks
         self.lock_manager.release_all_locks(transaction_id)?;
+        self.isolation_levels
+            .lock()
+            .unwrap()
+            .remove(&transaction_id);
 
         Ok(())
     }
@@ -234,6 +359,10 @@ impl ConcurrencyManager {
 
         // Release all locks
         self.lock_manager.release_all_locks(transaction_id)?;
+        self.isolation_levels
+            .lock()
+            .unwrap()
+            .remove(&transaction_id);
 
         Ok(())
     }
@@ -377,6 +506,36 @@ mod tests {
         assert_eq!(cleaned, 1);
     }
 
+    #[cfg_attr(miri, ignore)]
+    #[tokio::test]
+    async fn test_running_transaction_holds_vacuum_horizon() {
+        let manager = ConcurrencyManager::default();
+        let key = RowKey::new(1, 1);
+
+        let tx1 = TransactionId::new(1);
+        manager.write(tx1, key.clone(), vec![1]).await.unwrap();
+        manager.commit_transaction(tx1).unwrap();
+
+        let reader = TransactionId::new(2);
+        let snapshot = manager
+            .begin_transaction(reader, IsolationLevel::RepeatableRead)
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(2)).await;
+
+        let tx3 = TransactionId::new(3);
+        manager.write(tx3, key.clone(), vec![3]).await.unwrap();
+        manager.commit_transaction(tx3).unwrap();
+
+        assert_eq!(manager.vacuum().unwrap(), 0);
+        assert_eq!(
+            manager.read(reader, &key, snapshot).await.unwrap(),
+            Some(vec![1])
+        );
+
+        manager.commit_transaction(reader).unwrap();
+        assert_eq!(manager.vacuum().unwrap(), 1);
+    }
+
     #[cfg_attr(miri, ignore)]
     #[tokio::test]
     async fn test_non_mvcc_roundtrip() {
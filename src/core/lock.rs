@@ -3,6 +3,7 @@
 //! Implements a locking system with support for Shared/Exclusive locks,
 //! deadlock detection and two-phase locking (2PL).
 
+use crate::common::memory_tracking::{memory_scope, MemoryTag};
 use crate::common::{Error, Result};
 use crate::core::transaction::TransactionId;
 use std::collections::{HashMap, HashSet, VecDeque};
@@ -210,6 +211,7 @@ impl LockManager {
         lock_type: LockType,
         lock_mode: LockMode,
     ) -> Result<bool> {
+        let _memory = memory_scope(MemoryTag::LockTables);
         // Update statistics
         {
             let mut stats = self
@@ -2,6 +2,7 @@
 
 pub mod acid_manager;
 pub mod advanced_lock_manager;
+pub mod background_jobs;
 pub mod buffer;
 pub mod concurrency;
 pub mod lock;
@@ -13,9 +14,10 @@ pub mod transaction;
 // Re-export main types
 pub use acid_manager::{AcidConfig, AcidManager, AcidStatistics, VersionInfo};
 pub use advanced_lock_manager::{
-    AdvancedLockConfig, AdvancedLockInfo, AdvancedLockManager, AdvancedLockStatistics,
+    AdvancedLockConfig, AdvancedLockInfo, AdvancedLockManager, AdvancedLockStatistics, KeyRange,
     LockMode as AdvancedLockMode, ResourceType,
 };
+pub use background_jobs::{BackgroundJobManager, JobState, JobStatus};
 pub use concurrency::{
     ConcurrencyConfig, ConcurrencyManager, IsolationLevel as ConcurrencyIsolationLevel,
     LockGranularity,
@@ -24,7 +26,8 @@ pub use lock::{
     LockInfo, LockManager, LockManagerStats, LockMode, LockRequest, LockType, WaitForGraph,
 };
 pub use mvcc::{
-    MVCCManager, MVCCStatistics, RowKey, RowVersion, Timestamp, VersionId, VersionState,
+    MVCCManager, MVCCStatistics, RowKey, RowVersion, SnapshotInfo, SnapshotTransaction, Timestamp,
+    VersionId, VersionState,
 };
 pub use recovery_manager::{
     AdvancedRecoveryManager, AnalysisResult, RecoveryConfig, RecoveryStatistics,
@@ -1,12 +1,25 @@
 //! Multi-Version Concurrency Control (MVCC) system
 //!
 //! Provides transaction isolation through data versioning
+//!
+//! Readers that need a stable view for a long time (backups, dumps) open a
+//! [`SnapshotTransaction`]. Its timestamp, together with the snapshots of running
+//! transactions, forms the vacuum horizon: committed versions are only pruned once
+//! no registered snapshot can see them anymore.
+//!
+//! With a version retention ([`MVCCManager::with_version_retention`]) the horizon also trails
+//! the clock by the retention, and [`MVCCManager::snapshot_as_of`] opens snapshots of past
+//! instants within it (time travel).
 
 use crate::common::{Error, Result};
 use crate::core::transaction::TransactionId;
 use std::collections::{BTreeMap, HashMap};
+use std::ops::RangeInclusive;
 use std::sync::{Arc, Mutex, RwLock};
-use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Default age after which a snapshot that holds back vacuum is reported
+pub const DEFAULT_SNAPSHOT_WARNING_THRESHOLD: Duration = Duration::from_secs(600);
 
 /// Version identifier
 pub type VersionId = u64;
@@ -56,6 +69,8 @@ pub struct RowVersion {
     pub created_at: Timestamp,
     /// Deletion timestamp (if any)
     pub deleted_at: Option<Timestamp>,
+    /// Commit timestamp (once committed)
+    pub committed_at: Option<Timestamp>,
     /// Version state
     pub state: VersionState,
     /// Version data
@@ -78,6 +93,7 @@ impl RowVersion {
             deleted_by: None,
             created_at: Timestamp::now(),
             deleted_at: None,
+            committed_at: None,
             state: VersionState::Active,
             data,
             prev_version,
@@ -110,6 +126,7 @@ impl RowVersion {
     /// Marks version as committed
     pub fn commit(&mut self) {
         self.state = VersionState::Committed;
+        self.committed_at = Some(Timestamp::now());
     }
 
     /// Marks version as aborted
@@ -137,6 +154,123 @@ impl RowKey {
     }
 }
 
+/// Readers registered against the vacuum horizon
+#[derive(Debug, Default)]
+struct SnapshotRegistry {
+    /// Next snapshot identifier
+    next_id: u64,
+    /// Long-lived snapshots opened through [`MVCCManager::open_snapshot`]
+    snapshots: BTreeMap<u64, SnapshotEntry>,
+    /// Snapshot timestamps of running transactions
+    transactions: HashMap<TransactionId, Timestamp>,
+}
+
+#[derive(Debug, Clone)]
+struct SnapshotEntry {
+    label: String,
+    timestamp: Timestamp,
+    opened_at: Instant,
+}
+
+impl SnapshotRegistry {
+    /// Oldest timestamp any registered reader can still see
+    fn horizon(&self) -> Option<Timestamp> {
+        self.snapshots
+            .values()
+            .map(|entry| entry.timestamp)
+            .chain(self.transactions.values().copied())
+            .min()
+    }
+}
+
+/// Information about an open snapshot transaction
+#[derive(Debug, Clone)]
+pub struct SnapshotInfo {
+    /// Snapshot identifier
+    pub id: u64,
+    /// Label given when the snapshot was opened
+    pub label: String,
+    /// Snapshot timestamp
+    pub timestamp: Timestamp,
+    /// Time since the snapshot was opened
+    pub age: Duration,
+}
+
+/// Long-lived read-only view of committed data
+///
+/// Vacuum keeps every version visible to the snapshot until it is dropped.
+pub struct SnapshotTransaction {
+    id: u64,
+    timestamp: Timestamp,
+    versions: Arc<RwLock<HashMap<RowKey, Vec<RowVersion>>>>,
+    registry: Arc<Mutex<SnapshotRegistry>>,
+    statistics: Arc<Mutex<MVCCStatistics>>,
+}
+
+impl SnapshotTransaction {
+    /// Returns snapshot identifier
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns snapshot timestamp
+    pub fn timestamp(&self) -> Timestamp {
+        self.timestamp
+    }
+
+    /// Reads the row as it was committed at the snapshot timestamp
+    pub fn read(&self, key: &RowKey) -> Option<Vec<u8>> {
+        let versions = self.versions.read().unwrap();
+        versions.get(key).and_then(|row_versions| {
+            row_versions
+                .iter()
+                .rev()
+                .find(|version| self.sees(version))
+                .map(|version| version.data.clone())
+        })
+    }
+
+    /// Returns all rows of the table visible in the snapshot, ordered by row ID
+    pub fn scan_table(&self, table_id: u32) -> Vec<(u64, Vec<u8>)> {
+        let versions = self.versions.read().unwrap();
+        let mut rows: Vec<(u64, Vec<u8>)> = versions
+            .iter()
+            .filter(|(key, _)| key.table_id == table_id)
+            .filter_map(|(key, row_versions)| {
+                row_versions
+                    .iter()
+                    .rev()
+                    .find(|version| self.sees(version))
+                    .map(|version| (key.row_id, version.data.clone()))
+            })
+            .collect();
+        rows.sort_unstable_by_key(|(row_id, _)| *row_id);
+        rows
+    }
+
+    fn sees(&self, version: &RowVersion) -> bool {
+        version.state == VersionState::Committed
+            && version
+                .committed_at
+                .is_some_and(|committed_at| committed_at <= self.timestamp)
+            && version
+                .deleted_at
+                .is_none_or(|deleted_at| deleted_at > self.timestamp)
+    }
+}
+
+impl Drop for SnapshotTransaction {
+    fn drop(&mut self) {
+        if let Ok(mut registry) = self.registry.lock() {
+            registry.snapshots.remove(&self.id);
+        }
+        if let Ok(mut stats) = self.statistics.lock() {
+            stats.open_snapshots = stats.open_snapshots.saturating_sub(1);
+            stats.last_updated = Instant::now();
+        }
+    }
+}
+
 /// Version manager
 pub struct MVCCManager {
     /// Version storage (row key -> list of versions)
@@ -147,6 +281,12 @@ pub struct MVCCManager {
     min_active_transaction: Arc<RwLock<TransactionId>>,
     /// MVCC statistics
     statistics: Arc<Mutex<MVCCStatistics>>,
+    /// Snapshots that bound the vacuum horizon
+    snapshots: Arc<Mutex<SnapshotRegistry>>,
+    /// Age after which a snapshot blocking vacuum is reported
+    snapshot_warning_threshold: Duration,
+    /// How long superseded versions are kept for [`Self::snapshot_as_of`]
+    version_retention: Duration,
 }
 
 /// MVCC statistics
@@ -166,6 +306,12 @@ pub struct MVCCStatistics {
     pub vacuum_operations: u64,
     /// Versions cleaned during VACUUM
     pub versions_cleaned: u64,
+    /// Open snapshot transactions
+    pub open_snapshots: u64,
+    /// Versions the last VACUUM kept only because a snapshot could still see them
+    pub versions_held_by_snapshots: u64,
+    /// Warnings about snapshots holding back VACUUM for too long
+    pub snapshot_warnings: u64,
     /// Last update
     pub last_updated: Instant,
 }
@@ -180,6 +326,9 @@ impl MVCCStatistics {
             marked_for_deletion: 0,
             vacuum_operations: 0,
             versions_cleaned: 0,
+            open_snapshots: 0,
+            versions_held_by_snapshots: 0,
+            snapshot_warnings: 0,
             last_updated: Instant::now(),
         }
     }
@@ -188,14 +337,135 @@ impl MVCCStatistics {
 impl MVCCManager {
     /// Creates a new MVCC manager
     pub fn new() -> Self {
+        Self::with_snapshot_warning_threshold(DEFAULT_SNAPSHOT_WARNING_THRESHOLD)
+    }
+
+    /// Creates a new MVCC manager that warns about snapshots older than `threshold`
+    /// holding back VACUUM
+    pub fn with_snapshot_warning_threshold(threshold: Duration) -> Self {
         Self {
             versions: Arc::new(RwLock::new(HashMap::new())),
             version_counter: Arc::new(Mutex::new(1)),
             min_active_transaction: Arc::new(RwLock::new(TransactionId::new(0))),
             statistics: Arc::new(Mutex::new(MVCCStatistics::new())),
+            snapshots: Arc::new(Mutex::new(SnapshotRegistry::default())),
+            snapshot_warning_threshold: threshold,
+            version_retention: Duration::ZERO,
+        }
+    }
+
+    /// Keeps superseded and deleted versions for `retention` so that
+    /// [`Self::snapshot_as_of`] can read the data as it was up to `retention` ago
+    pub fn with_version_retention(mut self, retention: Duration) -> Self {
+        self.version_retention = retention;
+        self
+    }
+
+    /// Oldest instant [`Self::snapshot_as_of`] can read (`None` without version retention)
+    pub fn retained_since(&self) -> Option<Timestamp> {
+        if self.version_retention.is_zero() {
+            return None;
+        }
+        let retention = self.version_retention.as_micros() as u64;
+        Some(Timestamp(
+            Timestamp::now().value().saturating_sub(retention),
+        ))
+    }
+
+    /// Opens a long-lived snapshot of the committed state (for backups and dumps)
+    pub fn open_snapshot(&self, label: &str) -> SnapshotTransaction {
+        self.register_snapshot(label, Timestamp::now())
+    }
+
+    /// Opens a snapshot of the data as it was committed at `timestamp`, which must lie within
+    /// the version retention
+    ///
+    /// The snapshot holds back VACUUM like any other, so it stays readable while open even
+    /// once `timestamp` falls out of the retention window.
+    pub fn snapshot_as_of(&self, label: &str, timestamp: Timestamp) -> Result<SnapshotTransaction> {
+        let Some(retained_since) = self.retained_since() else {
+            return Err(Error::database("Time travel requires a version retention"));
+        };
+        if timestamp < retained_since {
+            return Err(Error::database(format!(
+                "Versions before {} are no longer retained",
+                retained_since.value()
+            )));
+        }
+        if timestamp > Timestamp::now() {
+            return Err(Error::database("Cannot read as of a future timestamp"));
+        }
+        Ok(self.register_snapshot(label, timestamp))
+    }
+
+    fn register_snapshot(&self, label: &str, timestamp: Timestamp) -> SnapshotTransaction {
+        let id = {
+            let mut registry = self.snapshots.lock().unwrap();
+            registry.next_id += 1;
+            let id = registry.next_id;
+            registry.snapshots.insert(
+                id,
+                SnapshotEntry {
+                    label: label.to_string(),
+                    timestamp,
+                    opened_at: Instant::now(),
+                },
+            );
+            id
+        };
+
+        {
+            let mut stats = self.statistics.lock().unwrap();
+            stats.open_snapshots += 1;
+            stats.last_updated = Instant::now();
+        }
+
+        SnapshotTransaction {
+            id,
+            timestamp,
+            versions: self.versions.clone(),
+            registry: self.snapshots.clone(),
+            statistics: self.statistics.clone(),
         }
     }
 
+    /// Returns open snapshot transactions, oldest first
+    pub fn open_snapshots(&self) -> Vec<SnapshotInfo> {
+        let registry = self.snapshots.lock().unwrap();
+        let mut snapshots: Vec<SnapshotInfo> = registry
+            .snapshots
+            .iter()
+            .map(|(id, entry)| SnapshotInfo {
+                id: *id,
+                label: entry.label.clone(),
+                timestamp: entry.timestamp,
+                age: entry.opened_at.elapsed(),
+            })
+            .collect();
+        snapshots.sort_by_key(|snapshot| (snapshot.timestamp, snapshot.id));
+        snapshots
+    }
+
+    /// Registers the snapshot timestamp of a running transaction
+    pub fn register_transaction_snapshot(
+        &self,
+        transaction_id: TransactionId,
+        snapshot_timestamp: Timestamp,
+    ) {
+        let mut registry = self.snapshots.lock().unwrap();
+        registry
+            .transactions
+            .insert(transaction_id, snapshot_timestamp);
+    }
+
+    /// Returns the oldest timestamp VACUUM must preserve
+    pub fn vacuum_horizon(&self) -> Timestamp {
+        let registry = self.snapshots.lock().unwrap();
+        let horizon = registry.horizon().unwrap_or_else(Timestamp::now);
+        self.retained_since()
+            .map_or(horizon, |since| horizon.min(since))
+    }
+
     /// Creates a new row version
     pub fn create_version(
         &self,
@@ -253,6 +523,31 @@ impl MVCCManager {
         Ok(None)
     }
 
+    /// Returns the rows of `table_id` with IDs in `row_ids` visible to the transaction, ordered
+    /// by row ID
+    pub fn scan_range(
+        &self,
+        table_id: u32,
+        row_ids: RangeInclusive<u64>,
+        transaction_id: TransactionId,
+        snapshot_timestamp: Timestamp,
+    ) -> Vec<(u64, Vec<u8>)> {
+        let versions = self.versions.read().unwrap();
+        let mut rows: Vec<(u64, Vec<u8>)> = versions
+            .iter()
+            .filter(|(key, _)| key.table_id == table_id && row_ids.contains(&key.row_id))
+            .filter_map(|(key, row_versions)| {
+                row_versions
+                    .iter()
+                    .rev()
+                    .find(|version| version.is_visible(transaction_id, snapshot_timestamp))
+                    .map(|version| (key.row_id, version.data.clone()))
+            })
+            .collect();
+        rows.sort_unstable_by_key(|(row_id, _)| *row_id);
+        rows
+    }
+
     /// Deletes row (creates new version with deletion mark)
     pub fn delete_version(&self, key: &RowKey, transaction_id: TransactionId) -> Result<()> {
         let mut versions = self.versions.write().unwrap();
@@ -277,6 +572,7 @@ impl MVCCManager {
 
     /// Commits transaction versions
     pub fn commit_transaction(&self, transaction_id: TransactionId) -> Result<()> {
+        self.release_transaction_snapshot(transaction_id);
         let mut versions = self.versions.write().unwrap();
         let mut committed_count = 0;
 
@@ -302,6 +598,7 @@ impl MVCCManager {
 
     /// Aborts transaction versions
     pub fn abort_transaction(&self, transaction_id: TransactionId) -> Result<()> {
+        self.release_transaction_snapshot(transaction_id);
         let mut versions = self.versions.write().unwrap();
         let mut aborted_count = 0;
 
@@ -326,14 +623,37 @@ impl MVCCManager {
     }
 
     /// Cleans old versions (VACUUM)
+    ///
+    /// Committed versions are pruned once they are superseded or deleted before
+    /// the vacuum horizon, so open snapshots keep seeing the rows they started with.
     pub fn vacuum(&self) -> Result<u64> {
         let min_active = *self.min_active_transaction.read().unwrap();
+        // Versions dead before `retained` are only kept for snapshots
+        let retained = self.retained_since().unwrap_or_else(Timestamp::now);
+        let horizon = self.vacuum_horizon().min(retained);
         let mut versions = self.versions.write().unwrap();
         let mut cleaned_count = 0;
+        let mut held_by_snapshots = 0;
 
         // Clean each version chain
         for row_versions in versions.values_mut() {
+            // Creation time of the oldest committed version newer than each entry
+            let mut superseded_at = vec![None; row_versions.len()];
+            let mut newer_committed: Option<Timestamp> = None;
+            for (index, version) in row_versions.iter().enumerate().rev() {
+                superseded_at[index] = newer_committed;
+                if version.state == VersionState::Committed {
+                    newer_committed = Some(
+                        newer_committed.map_or(version.created_at, |t| t.min(version.created_at)),
+                    );
+                }
+            }
+
+            let mut index = 0;
             row_versions.retain(|version| {
+                let superseded = superseded_at[index];
+                index += 1;
+
                 // Keep versions that:
                 // 1. Are active
                 // 2. Created by active transactions
@@ -341,8 +661,21 @@ impl MVCCManager {
                 let should_keep = match version.state {
                     VersionState::Active => version.created_by >= min_active,
                     VersionState::Committed => {
-                        // Keep last committed version
-                        true
+                        let dead_before = |horizon: Timestamp| {
+                            superseded.is_some_and(|at| at <= horizon)
+                                || version.deleted_at.is_some_and(|at| {
+                                    at <= horizon
+                                        && version.deleted_by.is_some_and(|tx| tx < min_active)
+                                })
+                        };
+                        if dead_before(horizon) {
+                            false
+                        } else {
+                            if dead_before(retained) {
+                                held_by_snapshots += 1;
+                            }
+                            true
+                        }
                     }
                     VersionState::Aborted | VersionState::MarkedForDeletion => {
                         // Remove aborted and marked for deletion
@@ -360,6 +693,13 @@ impl MVCCManager {
 
         // Remove empty chains
         versions.retain(|_, row_versions| !row_versions.is_empty());
+        drop(versions);
+
+        let warnings = if held_by_snapshots > 0 {
+            self.warn_about_blocking_snapshots(held_by_snapshots)
+        } else {
+            0
+        };
 
         // Update statistics
         {
@@ -367,12 +707,37 @@ impl MVCCManager {
             stats.vacuum_operations += 1;
             stats.versions_cleaned += cleaned_count;
             stats.total_versions = stats.total_versions.saturating_sub(cleaned_count);
+            stats.versions_held_by_snapshots = held_by_snapshots;
+            stats.snapshot_warnings += warnings;
             stats.last_updated = Instant::now();
         }
 
         Ok(cleaned_count)
     }
 
+    /// Logs open snapshots that have blocked cleanup for longer than the threshold
+    fn warn_about_blocking_snapshots(&self, held_versions: u64) -> u64 {
+        let mut warnings = 0;
+        for snapshot in self.open_snapshots() {
+            if snapshot.age >= self.snapshot_warning_threshold {
+                log::warn!(
+                    "Snapshot {} ({}) open for {:?} is preventing VACUUM from removing {} versions",
+                    snapshot.id,
+                    snapshot.label,
+                    snapshot.age,
+                    held_versions
+                );
+                warnings += 1;
+            }
+        }
+        warnings
+    }
+
+    fn release_transaction_snapshot(&self, transaction_id: TransactionId) {
+        let mut registry = self.snapshots.lock().unwrap();
+        registry.transactions.remove(&transaction_id);
+    }
+
     /// Updates minimum active transaction
     pub fn update_min_active_transaction(&self, transaction_id: TransactionId) {
         let mut min_active = self.min_active_transaction.write().unwrap();
@@ -528,4 +893,105 @@ mod tests {
         let read_data = manager.read_version(&key, tx2, snapshot).unwrap();
         assert_eq!(read_data, Some(data2));
     }
+
+    fn commit_row(manager: &MVCCManager, key: &RowKey, tx: u64, data: Vec<u8>) {
+        let tx = TransactionId::new(tx);
+        manager.create_version(key.clone(), tx, data).unwrap();
+        manager.commit_transaction(tx).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_holds_vacuum_horizon() {
+        let manager = MVCCManager::new();
+        let key = RowKey::new(1, 1);
+        commit_row(&manager, &key, 1, vec![1]);
+
+        let snapshot = manager.open_snapshot("backup");
+        std::thread::sleep(Duration::from_millis(2));
+        commit_row(&manager, &key, 2, vec![2]);
+        commit_row(&manager, &RowKey::new(1, 2), 3, vec![3]);
+
+        // The superseded version stays while the snapshot can see it
+        assert_eq!(manager.vacuum().unwrap(), 0);
+        assert_eq!(manager.get_statistics().versions_held_by_snapshots, 1);
+        assert_eq!(snapshot.read(&key), Some(vec![1]));
+        assert_eq!(snapshot.scan_table(1), vec![(1, vec![1])]);
+
+        drop(snapshot);
+        assert_eq!(manager.vacuum().unwrap(), 1);
+        assert_eq!(manager.get_version_count(&key), 1);
+        let snapshot = Timestamp::now();
+        let read_data = manager
+            .read_version(&key, TransactionId::new(4), snapshot)
+            .unwrap();
+        assert_eq!(read_data, Some(vec![2]));
+    }
+
+    #[test]
+    fn test_snapshot_as_of_reads_retained_versions() {
+        let key = RowKey::new(1, 1);
+        let manager = MVCCManager::new();
+        assert!(manager.snapshot_as_of("past", Timestamp::now()).is_err());
+
+        let manager = MVCCManager::new().with_version_retention(Duration::from_secs(3600));
+        commit_row(&manager, &key, 1, vec![1]);
+        std::thread::sleep(Duration::from_millis(2));
+        let before_update = Timestamp::now();
+        std::thread::sleep(Duration::from_millis(2));
+
+        // Created before `before_update` but committed after it: not visible then
+        let tx = TransactionId::new(2);
+        manager.create_version(key.clone(), tx, vec![2]).unwrap();
+        manager
+            .create_version(RowKey::new(1, 2), tx, vec![3])
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(2));
+        let before_commit = Timestamp::now();
+        std::thread::sleep(Duration::from_millis(2));
+        manager.commit_transaction(tx).unwrap();
+
+        // Retention keeps the superseded version without any snapshot open
+        assert_eq!(manager.vacuum().unwrap(), 0);
+        assert_eq!(manager.get_statistics().versions_held_by_snapshots, 0);
+        for at in [before_update, before_commit] {
+            let past = manager.snapshot_as_of("past", at).unwrap();
+            assert_eq!(past.read(&key), Some(vec![1]));
+            assert_eq!(past.scan_table(1), vec![(1, vec![1])]);
+        }
+        let now = manager.snapshot_as_of("now", Timestamp::now()).unwrap();
+        assert_eq!(now.scan_table(1), vec![(1, vec![2]), (2, vec![3])]);
+
+        let too_old = Timestamp(before_update.value() - 7_200_000_000);
+        assert!(manager.snapshot_as_of("too old", too_old).is_err());
+        let future = Timestamp(Timestamp::now().value() + 60_000_000);
+        assert!(manager.snapshot_as_of("future", future).is_err());
+    }
+
+    #[test]
+    fn test_long_running_snapshot_is_reported() {
+        let manager = MVCCManager::with_snapshot_warning_threshold(Duration::ZERO);
+        let key = RowKey::new(1, 1);
+        commit_row(&manager, &key, 1, vec![1]);
+
+        // Nothing to hold back yet, so no warning
+        let snapshot = manager.open_snapshot("dump");
+        manager.vacuum().unwrap();
+        assert_eq!(manager.get_statistics().snapshot_warnings, 0);
+
+        std::thread::sleep(Duration::from_millis(2));
+        commit_row(&manager, &key, 2, vec![2]);
+        manager.vacuum().unwrap();
+
+        let stats = manager.get_statistics();
+        assert_eq!(stats.snapshot_warnings, 1);
+        assert_eq!(stats.open_snapshots, 1);
+        let open = manager.open_snapshots();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].id, snapshot.id());
+        assert_eq!(open[0].label, "dump");
+
+        drop(snapshot);
+        assert!(manager.open_snapshots().is_empty());
+        assert_eq!(manager.get_statistics().open_snapshots, 0);
+    }
 }
@@ -417,6 +417,59 @@ async fn test_advanced_lock_manager_statistics() {
     .await;
 }
 
+#[cfg_attr(miri, ignore)]
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_advanced_lock_manager_concurrent_disjoint_resources() {
+    run_test_with_timeout(|| async {
+        let lock_manager = Arc::new(create_test_advanced_lock_manager());
+        let mut tasks = Vec::new();
+        for t in 0..8u64 {
+            let lock_manager = lock_manager.clone();
+            tasks.push(tokio::spawn(async move {
+                let transaction_id = TransactionId::new(t + 1);
+                for r in 0..50u64 {
+                    lock_manager
+                        .acquire_lock(
+                            transaction_id,
+                            ResourceType::Record(t, r),
+                            AdvancedLockMode::Exclusive,
+                            Some(Duration::from_millis(100)),
+                        )
+                        .await
+                        .unwrap();
+                }
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(lock_manager.get_statistics().total_locks, 8 * 50);
+        assert_eq!(
+            lock_manager
+                .get_transaction_locks(TransactionId::new(3))
+                .len(),
+            50
+        );
+
+        for t in 0..8u64 {
+            lock_manager
+                .release_all_locks(TransactionId::new(t + 1))
+                .unwrap();
+        }
+        let stats = lock_manager.get_statistics();
+        assert_eq!(stats.total_locks, 0);
+        assert_eq!(stats.waiting_transactions, 0);
+        assert!(lock_manager
+            .get_resource_locks(&ResourceType::Record(3, 7))
+            .is_empty());
+        assert!(lock_manager
+            .get_transaction_locks(TransactionId::new(3))
+            .is_empty());
+    })
+    .await;
+}
+
 #[cfg_attr(miri, ignore)]
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_advanced_lock_manager_resource_types() {
@@ -0,0 +1,99 @@
+//! Tests for the background job framework
+
+use crate::common::Error;
+use crate::core::background_jobs::{BackgroundJobManager, JobState};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Polls `cond` for up to two seconds
+async fn wait_until(mut cond: impl FnMut() -> bool) {
+    for _ in 0..200 {
+        if cond() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("condition not reached in time");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_job_failures_and_panics_do_not_stop_the_schedule() {
+    let jobs = BackgroundJobManager::new();
+    let calls = Arc::new(AtomicU64::new(0));
+    let counter = calls.clone();
+    jobs.register("flaky", Duration::from_millis(5), false, move || {
+        let n = counter.fetch_add(1, Ordering::SeqCst);
+        async move {
+            match n {
+                0 => Err(Error::internal("disk full")),
+                1 => panic!("boom"),
+                _ => Ok(()),
+            }
+        }
+    })
+    .unwrap();
+
+    wait_until(|| calls.load(Ordering::SeqCst) >= 4).await;
+    let status = jobs.job_status("flaky").unwrap();
+    assert!(status.runs >= 3);
+    assert_eq!(status.failures, 2);
+    assert_eq!(status.panics, 1);
+    assert!(status.last_error.unwrap().contains("boom"));
+    assert!(status.last_finished_ms.is_some());
+    jobs.shutdown();
+    assert!(jobs.status().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_paused_job_runs_only_when_triggered() {
+    let jobs = BackgroundJobManager::new();
+    let calls = Arc::new(AtomicU64::new(0));
+    let counter = calls.clone();
+    jobs.register("maintenance", Duration::from_millis(5), true, move || {
+        counter.fetch_add(1, Ordering::SeqCst);
+        async { Ok(()) }
+    })
+    .unwrap();
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+    assert_eq!(jobs.status()[0].state, JobState::Paused);
+
+    jobs.run_now("maintenance").unwrap();
+    wait_until(|| calls.load(Ordering::SeqCst) == 1).await;
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    jobs.resume("maintenance").unwrap();
+    wait_until(|| calls.load(Ordering::SeqCst) >= 3).await;
+    jobs.pause("maintenance").unwrap();
+    assert!(jobs.pause("missing").is_err());
+}
+
+#[tokio::test]
+async fn test_job_registration_and_removal() {
+    let jobs = BackgroundJobManager::new();
+    jobs.register("b", Duration::from_secs(60), false, || async { Ok(()) })
+        .unwrap();
+    jobs.register("a", Duration::from_secs(60), false, || async { Ok(()) })
+        .unwrap();
+    assert!(jobs
+        .register("a", Duration::from_secs(1), false, || async { Ok(()) })
+        .is_err());
+    assert_eq!(jobs.job_names(), vec!["a", "b"]);
+    assert_eq!(jobs.status()[1].interval, Duration::from_secs(60));
+
+    assert!(jobs.unregister("a"));
+    assert!(!jobs.unregister("a"));
+    assert!(jobs.run_now("a").is_err());
+    assert_eq!(jobs.job_names(), vec!["b"]);
+}
+
+#[test]
+fn test_job_registration_requires_a_runtime() {
+    let jobs = BackgroundJobManager::new();
+    assert!(jobs
+        .register("orphan", Duration::from_secs(1), false, || async { Ok(()) })
+        .is_err());
+}
@@ -1,6 +1,9 @@
 //! Tests for the concurrency management system
 
-use crate::core::{ConcurrencyManager, ResourceType, RowKey, Timestamp, TransactionId};
+use crate::core::{
+    ConcurrencyIsolationLevel, ConcurrencyManager, ResourceType, RowKey, Timestamp, TransactionId,
+};
+use std::sync::Arc;
 use std::time::Duration;
 
 #[cfg_attr(miri, ignore)]
@@ -209,3 +212,142 @@ async fn test_lock_conflict_with_timeout() {
 
     // Test passes as long as it does not hang
 }
+
+fn row(table_id: u32, row_id: u64) -> RowKey {
+    RowKey { table_id, row_id }
+}
+
+#[cfg_attr(miri, ignore)]
+#[tokio::test]
+async fn test_serializable_scan_blocks_inserts_into_its_range() {
+    let manager = Arc::new(ConcurrencyManager::new(Default::default()));
+    let (reader, writer) = (TransactionId(1), TransactionId(2));
+    let setup = TransactionId(3);
+    manager
+        .begin_transaction(setup, ConcurrencyIsolationLevel::ReadCommitted)
+        .unwrap();
+    manager.write(setup, row(1, 10), vec![10]).await.unwrap();
+    manager.commit_transaction(setup).unwrap();
+
+    let snapshot = manager
+        .begin_transaction(reader, ConcurrencyIsolationLevel::Serializable)
+        .unwrap();
+    manager
+        .begin_transaction(writer, ConcurrencyIsolationLevel::ReadCommitted)
+        .unwrap();
+    let first = manager.scan(reader, 1, 0..=100, snapshot).await.unwrap();
+    assert_eq!(first, vec![(10, vec![10])]);
+
+    // Keys outside the range, or of another table, are free.
+    manager.write(writer, row(1, 200), vec![2]).await.unwrap();
+    manager.write(writer, row(2, 50), vec![2]).await.unwrap();
+
+    // An insert into the gap waits for the scanning transaction.
+    let insert = tokio::spawn({
+        let manager = manager.clone();
+        async move { manager.write(writer, row(1, 50), vec![5]).await }
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!insert.is_finished());
+    let again = manager.scan(reader, 1, 0..=100, snapshot).await.unwrap();
+    assert_eq!(again, first);
+
+    manager.commit_transaction(reader).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), insert)
+        .await
+        .expect("insert resumes after the scan's commit")
+        .unwrap()
+        .unwrap();
+    manager.commit_transaction(writer).unwrap();
+}
+
+#[cfg_attr(miri, ignore)]
+#[tokio::test]
+async fn test_serializable_scan_waits_for_writes_in_its_range() {
+    let manager = Arc::new(ConcurrencyManager::new(Default::default()));
+    let (writer, reader) = (TransactionId(1), TransactionId(2));
+    manager
+        .begin_transaction(writer, ConcurrencyIsolationLevel::ReadCommitted)
+        .unwrap();
+    let snapshot = manager
+        .begin_transaction(reader, ConcurrencyIsolationLevel::Serializable)
+        .unwrap();
+    manager.write(writer, row(1, 5), vec![5]).await.unwrap();
+
+    let scan = tokio::spawn({
+        let manager = manager.clone();
+        async move { manager.scan(reader, 1, 0..=9, snapshot).await }
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!scan.is_finished());
+
+    manager.abort_transaction(writer).unwrap();
+    let rows = tokio::time::timeout(Duration::from_secs(5), scan)
+        .await
+        .expect("scan resumes after the writer ends")
+        .unwrap()
+        .unwrap();
+    assert!(rows.is_empty());
+}
+
+#[cfg_attr(miri, ignore)]
+#[tokio::test]
+async fn test_read_committed_scan_takes_no_range_lock() {
+    let manager = ConcurrencyManager::new(Default::default());
+    let (reader, writer) = (TransactionId(1), TransactionId(2));
+    let snapshot = manager
+        .begin_transaction(reader, ConcurrencyIsolationLevel::ReadCommitted)
+        .unwrap();
+    manager
+        .begin_transaction(writer, ConcurrencyIsolationLevel::ReadCommitted)
+        .unwrap();
+    assert!(manager
+        .scan(reader, 1, 0..=100, snapshot)
+        .await
+        .unwrap()
+        .is_empty());
+
+    tokio::time::timeout(
+        Duration::from_secs(1),
+        manager.write(writer, row(1, 50), vec![5]),
+    )
+    .await
+    .expect("no range lock to wait for")
+    .unwrap();
+}
+
+#[cfg_attr(miri, ignore)]
+#[tokio::test]
+async fn test_range_lock_deadlock_aborts_the_younger_transaction() {
+    let manager = Arc::new(ConcurrencyManager::new(Default::default()));
+    let (older, younger) = (TransactionId(1), TransactionId(2));
+    for tx in [older, younger] {
+        let snapshot = manager
+            .begin_transaction(tx, ConcurrencyIsolationLevel::Serializable)
+            .unwrap();
+        manager.scan(tx, 1, 0..=10, snapshot).await.unwrap();
+    }
+
+    // Each inserts into the range the other scanned.
+    let older_insert = tokio::spawn({
+        let manager = manager.clone();
+        async move { manager.write(older, row(1, 3), vec![1]).await }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let err = tokio::time::timeout(
+        Duration::from_secs(5),
+        manager.write(younger, row(1, 7), vec![2]),
+    )
+    .await
+    .expect("deadlock is detected")
+    .unwrap_err();
+    assert!(err.to_string().contains("Deadlock detected"), "{err}");
+
+    manager.abort_transaction(younger).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), older_insert)
+        .await
+        .expect("older insert resumes after the victim aborts")
+        .unwrap()
+        .unwrap();
+    manager.commit_transaction(older).unwrap();
+}
@@ -1,6 +1,7 @@
 //! Core module tests for rustdb
 
 pub mod acid_tests;
+pub mod background_jobs_tests;
 pub mod concurrency_tests;
 pub mod lock_tests;
 pub mod recovery_core_tests;
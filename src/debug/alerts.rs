@@ -0,0 +1,502 @@
+//! Alert rules for the performance analyzer
+//!
+//! An [`AlertRule`] watches one metric collected by
+//! [`PerformanceAnalyzer`](crate::debug::PerformanceAnalyzer): a threshold rule fires when the
+//! latest value is above or below a limit, a trend rule when it rose or dropped by a percentage
+//! against the mean of the previous samples (e.g. the cache hit ratio falling or lock waits
+//! spiking). Every firing is an [`AlertEvent`], written to the log (target `rustdb::alerts`) and
+//! handed to the registered callbacks, e.g. one posting to a webhook. A rule stays silent for its
+//! cooldown after it fired, so a metric stuck past its limit does not raise an alert storm.
+
+use crate::debug::performance_analyzer::{PerformanceMetric, SeverityLevel};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Alert events kept for [`AlertManager::recent_events`]
+const MAX_EVENTS: usize = 1000;
+
+/// Condition of an alert rule on the latest value of its metric
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AlertCondition {
+    /// Value greater than the limit
+    Above(f64),
+    /// Value less than the limit
+    Below(f64),
+    /// Value at least `percent` % above the mean of the previous `window` samples
+    RisesBy {
+        /// Increase over the baseline (%)
+        percent: f64,
+        /// Samples in the baseline
+        window: usize,
+    },
+    /// Value at least `percent` % below the mean of the previous `window` samples
+    DropsBy {
+        /// Decrease under the baseline (%)
+        percent: f64,
+        /// Samples in the baseline
+        window: usize,
+    },
+}
+
+impl AlertCondition {
+    /// Baseline samples the condition needs (0 for thresholds)
+    fn window(&self) -> usize {
+        match self {
+            AlertCondition::Above(_) | AlertCondition::Below(_) => 0,
+            AlertCondition::RisesBy { window, .. } | AlertCondition::DropsBy { window, .. } => {
+                *window
+            }
+        }
+    }
+
+    /// Whether `value` meets the condition given the baseline mean (trend rules without a full
+    /// baseline never fire)
+    fn matches(&self, value: f64, baseline: Option<f64>) -> bool {
+        match (self, baseline) {
+            (AlertCondition::Above(limit), _) => value > *limit,
+            (AlertCondition::Below(limit), _) => value < *limit,
+            (AlertCondition::RisesBy { percent, .. }, Some(base)) => {
+                value > base && value - base >= base.abs() * percent / 100.0
+            }
+            (AlertCondition::DropsBy { percent, .. }, Some(base)) => {
+                value < base && base - value >= base.abs() * percent / 100.0
+            }
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for AlertCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AlertCondition::Above(limit) => write!(f, "above {:.1}", limit),
+            AlertCondition::Below(limit) => write!(f, "below {:.1}", limit),
+            AlertCondition::RisesBy { percent, window } => {
+                write!(f, "rose {:.0}% over the last {} samples", percent, window)
+            }
+            AlertCondition::DropsBy { percent, window } => {
+                write!(
+                    f,
+                    "dropped {:.0}% over the last {} samples",
+                    percent, window
+                )
+            }
+        }
+    }
+}
+
+/// Alert rule on one metric
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRule {
+    /// Unique rule name
+    pub name: String,
+    /// Metric name (`cache_hit_ratio`, `lock_contention`, ...)
+    pub metric: String,
+    /// Condition on the latest value
+    pub condition: AlertCondition,
+    /// Severity of the events
+    pub severity: SeverityLevel,
+    /// Minimum time between two events of this rule
+    pub cooldown: Duration,
+}
+
+impl AlertRule {
+    /// Creates a warning rule with a five minute cooldown
+    pub fn new(name: &str, metric: &str, condition: AlertCondition) -> Self {
+        Self {
+            name: name.to_string(),
+            metric: metric.to_string(),
+            condition,
+            severity: SeverityLevel::Warning,
+            cooldown: Duration::from_secs(300),
+        }
+    }
+
+    /// Sets the severity
+    pub fn with_severity(mut self, severity: SeverityLevel) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Sets the cooldown
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Rules for a falling cache hit ratio and spiking lock contention
+    pub fn default_rules() -> Vec<AlertRule> {
+        vec![
+            AlertRule::new(
+                "cache_hit_ratio_low",
+                "cache_hit_ratio",
+                AlertCondition::Below(60.0),
+            )
+            .with_severity(SeverityLevel::Critical),
+            AlertRule::new(
+                "cache_hit_ratio_drop",
+                "cache_hit_ratio",
+                AlertCondition::DropsBy {
+                    percent: 20.0,
+                    window: 6,
+                },
+            ),
+            AlertRule::new(
+                "lock_contention_high",
+                "lock_contention",
+                AlertCondition::Above(40.0),
+            )
+            .with_severity(SeverityLevel::Critical),
+            AlertRule::new(
+                "lock_contention_spike",
+                "lock_contention",
+                AlertCondition::RisesBy {
+                    percent: 100.0,
+                    window: 6,
+                },
+            ),
+        ]
+    }
+}
+
+/// Alert raised by a rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertEvent {
+    /// Rule that fired
+    pub rule: String,
+    /// Metric name
+    pub metric: String,
+    /// Component of the metric
+    pub component: String,
+    /// Severity
+    pub severity: SeverityLevel,
+    /// Value that met the condition
+    pub value: f64,
+    /// Mean of the previous samples (trend rules)
+    pub baseline: Option<f64>,
+    /// Human-readable description
+    pub message: String,
+    /// Timestamp (microseconds since the Unix epoch)
+    pub fired_at: u64,
+}
+
+/// Receiver of alert events, e.g. a webhook client
+pub type AlertCallback = Arc<dyn Fn(&AlertEvent) + Send + Sync>;
+
+/// Alert counters
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AlertStats {
+    /// Rule evaluations
+    pub evaluations: u64,
+    /// Events emitted
+    pub fired: u64,
+    /// Matches swallowed by a cooldown
+    pub suppressed: u64,
+}
+
+/// Alert rules, their cooldown state and the recent events
+#[derive(Default)]
+pub struct AlertManager {
+    rules: RwLock<Vec<AlertRule>>,
+    callbacks: RwLock<Vec<AlertCallback>>,
+    last_fired: Mutex<HashMap<String, Instant>>,
+    events: RwLock<VecDeque<AlertEvent>>,
+    stats: RwLock<AlertStats>,
+}
+
+impl AlertManager {
+    /// Creates a manager without rules
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a rule, replacing the rule of the same name
+    pub fn add_rule(&self, rule: AlertRule) {
+        let mut rules = self.rules.write().unwrap();
+        rules.retain(|r| r.name != rule.name);
+        rules.push(rule);
+    }
+
+    /// Removes a rule; returns whether it existed
+    pub fn remove_rule(&self, name: &str) -> bool {
+        let mut rules = self.rules.write().unwrap();
+        let before = rules.len();
+        rules.retain(|r| r.name != name);
+        self.last_fired.lock().unwrap().remove(name);
+        rules.len() != before
+    }
+
+    /// Configured rules
+    pub fn rules(&self) -> Vec<AlertRule> {
+        self.rules.read().unwrap().clone()
+    }
+
+    /// Registers a callback invoked for every event
+    pub fn add_callback(&self, callback: AlertCallback) {
+        self.callbacks.write().unwrap().push(callback);
+    }
+
+    /// Evaluates the rules against freshly collected `metrics`, then logs the events and passes
+    /// them to the callbacks
+    ///
+    /// `history` holds earlier samples, oldest first, and provides the baseline of trend rules;
+    /// it must not contain `metrics` yet.
+    pub fn process(
+        &self,
+        metrics: &[PerformanceMetric],
+        history: &[PerformanceMetric],
+    ) -> Vec<AlertEvent> {
+        let events = self.evaluate(metrics, history, Instant::now());
+        self.emit(&events);
+        events
+    }
+
+    /// Evaluates the rules and records the events whose rule is out of its cooldown
+    pub(crate) fn evaluate(
+        &self,
+        metrics: &[PerformanceMetric],
+        history: &[PerformanceMetric],
+        now: Instant,
+    ) -> Vec<AlertEvent> {
+        let rules = self.rules.read().unwrap();
+        let mut last_fired = self.last_fired.lock().unwrap();
+        let mut stats = self.stats.write().unwrap();
+        let mut events = Vec::new();
+
+        for rule in rules.iter() {
+            let Some(metric) = metrics.iter().rev().find(|m| m.name == rule.metric) else {
+                continue;
+            };
+            stats.evaluations += 1;
+            let baseline = Self::baseline(history, &rule.metric, rule.condition.window());
+            if !rule.condition.matches(metric.value, baseline) {
+                continue;
+            }
+            if last_fired
+                .get(&rule.name)
+                .is_some_and(|at| now.duration_since(*at) < rule.cooldown)
+            {
+                stats.suppressed += 1;
+                continue;
+            }
+            last_fired.insert(rule.name.clone(), now);
+            stats.fired += 1;
+            events.push(AlertEvent {
+                rule: rule.name.clone(),
+                metric: metric.name.clone(),
+                component: metric.component.clone(),
+                severity: rule.severity.clone(),
+                value: metric.value,
+                baseline,
+                message: format!(
+                    "{} is {:.1}{}: {}",
+                    metric.name, metric.value, metric.unit, rule.condition
+                ),
+                fired_at: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_micros() as u64,
+            });
+        }
+
+        if !events.is_empty() {
+            let mut stored = self.events.write().unwrap();
+            stored.extend(events.iter().cloned());
+            while stored.len() > MAX_EVENTS {
+                stored.pop_front();
+            }
+        }
+        events
+    }
+
+    /// Mean of the last `window` samples of `metric` in `history`; `None` for threshold rules
+    /// and while fewer samples exist
+    fn baseline(history: &[PerformanceMetric], metric: &str, window: usize) -> Option<f64> {
+        if window == 0 {
+            return None;
+        }
+        let samples: Vec<f64> = history
+            .iter()
+            .rev()
+            .filter(|m| m.name == metric)
+            .take(window)
+            .map(|m| m.value)
+            .collect();
+        (samples.len() == window).then(|| samples.iter().sum::<f64>() / window as f64)
+    }
+
+    /// Logs `events` and passes them to the callbacks
+    pub(crate) fn emit(&self, events: &[AlertEvent]) {
+        if events.is_empty() {
+            return;
+        }
+        let callbacks = self.callbacks.read().unwrap().clone();
+        for event in events {
+            match event.severity {
+                SeverityLevel::Critical => tracing::error!(
+                    target: "rustdb::alerts",
+                    rule = %event.rule,
+                    metric = %event.metric,
+                    value = event.value,
+                    baseline = ?event.baseline,
+                    "{}",
+                    event.message
+                ),
+                SeverityLevel::Warning => tracing::warn!(
+                    target: "rustdb::alerts",
+                    rule = %event.rule,
+                    metric = %event.metric,
+                    value = event.value,
+                    baseline = ?event.baseline,
+                    "{}",
+                    event.message
+                ),
+                SeverityLevel::Info => tracing::info!(
+                    target: "rustdb::alerts",
+                    rule = %event.rule,
+                    metric = %event.metric,
+                    value = event.value,
+                    baseline = ?event.baseline,
+                    "{}",
+                    event.message
+                ),
+            }
+            for callback in &callbacks {
+                callback(event);
+            }
+        }
+    }
+
+    /// Most recent events, oldest first
+    pub fn recent_events(&self, limit: usize) -> Vec<AlertEvent> {
+        let events = self.events.read().unwrap();
+        let start = events.len().saturating_sub(limit);
+        events.iter().skip(start).cloned().collect()
+    }
+
+    /// Alert counters
+    pub fn stats(&self) -> AlertStats {
+        self.stats.read().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::debug::performance_analyzer::Thresholds;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn metric(name: &str, value: f64) -> PerformanceMetric {
+        PerformanceMetric {
+            name: name.to_string(),
+            value,
+            unit: "%".to_string(),
+            timestamp: 0,
+            component: "Test".to_string(),
+            thresholds: Thresholds {
+                warning: 0.0,
+                critical: 0.0,
+            },
+        }
+    }
+
+    #[test]
+    fn test_threshold_rule_respects_cooldown() {
+        let alerts = AlertManager::new();
+        alerts.add_rule(
+            AlertRule::new("locks", "lock_contention", AlertCondition::Above(40.0))
+                .with_cooldown(Duration::from_secs(60)),
+        );
+        let t0 = Instant::now();
+
+        assert!(alerts
+            .evaluate(&[metric("lock_contention", 10.0)], &[], t0)
+            .is_empty());
+        let fired = alerts.evaluate(&[metric("lock_contention", 45.0)], &[], t0);
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].rule, "locks");
+        assert_eq!(fired[0].value, 45.0);
+
+        // Still above the limit, but inside the cooldown
+        let later = t0 + Duration::from_secs(30);
+        assert!(alerts
+            .evaluate(&[metric("lock_contention", 50.0)], &[], later)
+            .is_empty());
+        let after = t0 + Duration::from_secs(61);
+        assert_eq!(
+            alerts
+                .evaluate(&[metric("lock_contention", 50.0)], &[], after)
+                .len(),
+            1
+        );
+
+        let stats = alerts.stats();
+        assert_eq!(stats.evaluations, 4);
+        assert_eq!(stats.fired, 2);
+        assert_eq!(stats.suppressed, 1);
+        assert_eq!(alerts.recent_events(10).len(), 2);
+    }
+
+    #[test]
+    fn test_trend_rule_needs_full_baseline() {
+        let alerts = AlertManager::new();
+        alerts.add_rule(AlertRule::new(
+            "cache_drop",
+            "cache_hit_ratio",
+            AlertCondition::DropsBy {
+                percent: 20.0,
+                window: 3,
+            },
+        ));
+        let now = Instant::now();
+        let short = vec![metric("cache_hit_ratio", 95.0), metric("cpu_usage", 5.0)];
+        assert!(alerts
+            .evaluate(&[metric("cache_hit_ratio", 50.0)], &short, now)
+            .is_empty());
+
+        let history = vec![
+            metric("cache_hit_ratio", 10.0),
+            metric("cache_hit_ratio", 90.0),
+            metric("cpu_usage", 5.0),
+            metric("cache_hit_ratio", 95.0),
+            metric("cache_hit_ratio", 85.0),
+        ];
+        assert!(alerts
+            .evaluate(&[metric("cache_hit_ratio", 80.0)], &history, now)
+            .is_empty());
+        let fired = alerts.evaluate(&[metric("cache_hit_ratio", 70.0)], &history, now);
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].baseline, Some(90.0));
+    }
+
+    #[test]
+    fn test_process_calls_callbacks() {
+        let alerts = AlertManager::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let seen = calls.clone();
+        alerts.add_callback(Arc::new(move |event: &AlertEvent| {
+            assert_eq!(event.metric, "cpu_usage");
+            seen.fetch_add(1, Ordering::SeqCst);
+        }));
+        alerts.add_rule(AlertRule::new(
+            "cpu",
+            "cpu_usage",
+            AlertCondition::Above(90.0),
+        ));
+        alerts.add_rule(
+            AlertRule::new("cpu", "cpu_usage", AlertCondition::Above(50.0))
+                .with_cooldown(Duration::ZERO),
+        );
+        assert_eq!(alerts.rules().len(), 1);
+
+        alerts.process(&[metric("cpu_usage", 60.0)], &[]);
+        alerts.process(&[metric("cpu_usage", 60.0)], &[]);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        assert!(alerts.remove_rule("cpu"));
+        assert!(alerts.process(&[metric("cpu_usage", 60.0)], &[]).is_empty());
+        assert!(!alerts.remove_rule("cpu"));
+    }
+}
@@ -4,9 +4,10 @@
 //! - Detailed operation logging
 //! - Query tracing
 //! - CPU and Memory profiling
-//! - Performance analysis
+//! - Performance analysis and alerting
 //! - Problem debugging
 
+pub mod alerts;
 pub mod debug_logger;
 pub mod performance_analyzer;
 pub mod profiler;
@@ -18,11 +19,14 @@ pub fn record_network_query_latency_ms(latency_ms: u64) {
     tracing::trace!(target: "rustdb::network::metrics", query_latency_ms = latency_ms);
 }
 
+pub use alerts::{AlertCondition, AlertEvent, AlertManager, AlertRule};
 pub use debug_logger::DebugLogger;
 pub use performance_analyzer::PerformanceAnalyzer;
 pub use profiler::Profiler;
 pub use query_tracer::QueryTracer;
 
+use crate::core::background_jobs::BackgroundJobManager;
+
 /// Debug configuration
 #[derive(Debug, Clone)]
 pub struct DebugConfig {
@@ -72,6 +76,8 @@ pub struct DebugManager {
     query_tracer: Option<QueryTracer>,
     profiler: Option<Profiler>,
     performance_analyzer: Option<PerformanceAnalyzer>,
+    /// Background jobs of the debug components
+    jobs: BackgroundJobManager,
 }
 
 impl DebugManager {
@@ -83,6 +89,7 @@ impl DebugManager {
             query_tracer: None,
             profiler: None,
             performance_analyzer: None,
+            jobs: BackgroundJobManager::new(),
         };
 
         // Initialize components based on configuration
@@ -98,7 +105,10 @@ impl DebugManager {
             manager.profiler = Some(Profiler::new(&config));
         }
 
-        manager.performance_analyzer = Some(PerformanceAnalyzer::new(&config));
+        manager.performance_analyzer = Some(PerformanceAnalyzer::with_background_jobs(
+            &config,
+            manager.jobs.clone(),
+        ));
 
         manager
     }
@@ -128,6 +138,11 @@ impl DebugManager {
         self.performance_analyzer.as_ref()
     }
 
+    /// Gets background jobs of the debug components
+    pub fn background_jobs(&self) -> &BackgroundJobManager {
+        &self.jobs
+    }
+
     /// Updates configuration
     pub fn update_config(&mut self, new_config: DebugConfig) {
         self.config = new_config.clone();
@@ -1,13 +1,16 @@
 //! Performance analyzer for rustdb
 //!
-//! Provides tools for analyzing system performance and identifying bottlenecks
+//! Provides tools for analyzing system performance and identifying bottlenecks.
+//! Every collection is also checked against the alert rules of [`PerformanceAnalyzer::alerts`]
+//! (see [`crate::debug::alerts`]).
 
+use crate::core::background_jobs::BackgroundJobManager;
+use crate::debug::alerts::AlertManager;
 use crate::debug::DebugConfig;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tokio::task::JoinHandle;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 /// Bottleneck type
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,7 +43,7 @@ impl std::fmt::Display for BottleneckType {
 }
 
 /// Severity level of a bottleneck
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SeverityLevel {
     /// Informational
     Info,
@@ -144,75 +147,108 @@ pub struct PerformanceAnalyzer {
     config: DebugConfig,
     analyses: Arc<RwLock<Vec<PerformanceAnalysis>>>,
     stats: Arc<RwLock<AnalysisStats>>,
-    background_handle: Option<JoinHandle<()>>,
+    jobs: BackgroundJobManager,
     metrics_history: Arc<RwLock<Vec<PerformanceMetric>>>,
+    alerts: Arc<AlertManager>,
 }
 
+/// Background job collecting and analyzing metrics every `metrics_collection_interval` seconds
+pub const PERFORMANCE_ANALYSIS_JOB: &str = "performance_analysis";
+
 impl PerformanceAnalyzer {
-    /// Creates a new performance analyzer
+    /// Creates a new performance analyzer (must be called within a tokio runtime)
     pub fn new(config: &DebugConfig) -> Self {
-        let mut analyzer = Self {
+        Self::with_background_jobs(config, BackgroundJobManager::new())
+    }
+
+    /// Creates a new performance analyzer whose analysis job runs in `jobs`
+    pub fn with_background_jobs(config: &DebugConfig, jobs: BackgroundJobManager) -> Self {
+        let analyzer = Self {
             config: config.clone(),
             analyses: Arc::new(RwLock::new(Vec::new())),
             stats: Arc::new(RwLock::new(AnalysisStats::default())),
-            background_handle: None,
+            jobs,
             metrics_history: Arc::new(RwLock::new(Vec::new())),
+            alerts: Arc::new(AlertManager::new()),
         };
 
-        // Start background analysis task
-        analyzer.start_background_analysis();
+        // Start background analysis job
+        analyzer
+            .start_background_analysis()
+            .expect("performance analysis job");
 
         analyzer
     }
 
-    /// Starts background analysis job
-    fn start_background_analysis(&mut self) {
+    /// Registers the background analysis job
+    fn start_background_analysis(&self) -> crate::common::Result<()> {
         let analyses = self.analyses.clone();
         let stats = self.stats.clone();
         let metrics_history = self.metrics_history.clone();
-        let config = self.config.clone();
-
-        self.background_handle = Some(tokio::spawn(async move {
-            let mut interval =
-                tokio::time::interval(Duration::from_secs(config.metrics_collection_interval));
-
-            loop {
-                interval.tick().await;
-
-                // Collect metrics
-                let metrics = Self::collect_performance_metrics();
+        let alerts = self.alerts.clone();
+
+        self.jobs.register(
+            PERFORMANCE_ANALYSIS_JOB,
+            Duration::from_secs(self.config.metrics_collection_interval),
+            false,
+            move || {
+                Self::analyze_once(
+                    analyses.clone(),
+                    stats.clone(),
+                    metrics_history.clone(),
+                    alerts.clone(),
+                )
+            },
+        )
+    }
 
-                // Append to history
-                {
-                    let mut history = metrics_history.write().unwrap();
-                    history.extend(metrics.clone());
+    /// One run of the analysis job
+    async fn analyze_once(
+        analyses: Arc<RwLock<Vec<PerformanceAnalysis>>>,
+        stats: Arc<RwLock<AnalysisStats>>,
+        metrics_history: Arc<RwLock<Vec<PerformanceMetric>>>,
+        alerts: Arc<AlertManager>,
+    ) -> crate::common::Result<()> {
+        // Collect metrics
+        let metrics = Self::collect_performance_metrics();
+
+        // Check alert rules against the earlier samples
+        let events = {
+            let history = metrics_history.read().unwrap();
+            alerts.evaluate(&metrics, &history, Instant::now())
+        };
+        alerts.emit(&events);
 
-                    // Limit history size
-                    let len = history.len();
-                    if len > 10000 {
-                        history.drain(0..len - 10000);
-                    }
-                }
+        // Append to history
+        {
+            let mut history = metrics_history.write().unwrap();
+            history.extend(metrics.clone());
 
-                // Perform analysis
-                let analysis = Self::perform_analysis(&metrics);
+            // Limit history size
+            let len = history.len();
+            if len > 10000 {
+                history.drain(0..len - 10000);
+            }
+        }
 
-                // Store analysis
-                {
-                    let mut analyses = analyses.write().unwrap();
-                    analyses.push(analysis.clone());
+        // Perform analysis
+        let analysis = Self::perform_analysis(&metrics);
 
-                    // Limit number of saved analyses
-                    let len = analyses.len();
-                    if len > 1000 {
-                        analyses.drain(0..len - 1000);
-                    }
-                }
+        // Store analysis
+        {
+            let mut analyses = analyses.write().unwrap();
+            analyses.push(analysis.clone());
 
-                // Update statistics snapshot
-                Self::update_analysis_stats(&stats, &analysis);
+            // Limit number of saved analyses
+            let len = analyses.len();
+            if len > 1000 {
+                analyses.drain(0..len - 1000);
             }
-        }));
+        }
+
+        // Update statistics snapshot
+        Self::update_analysis_stats(&stats, &analysis);
+        Ok(())
     }
 
     /// Collects performance metrics
@@ -566,6 +602,11 @@ impl PerformanceAnalyzer {
         self.stats.read().unwrap().clone()
     }
 
+    /// Alert rules checked on every collection; add rules and callbacks here
+    pub fn alerts(&self) -> &Arc<AlertManager> {
+        &self.alerts
+    }
+
     /// Generates performance analysis report
     pub fn generate_performance_report(&self) -> String {
         let stats = self.get_stats();
@@ -643,6 +684,19 @@ impl PerformanceAnalyzer {
             }
         }
 
+        // Alerts
+        let alerts = self.alerts.recent_events(10);
+        if !alerts.is_empty() {
+            report.push_str("Recent alerts:\n");
+            for alert in &alerts {
+                report.push_str(&format!(
+                    "  [{}] {}: {}\n",
+                    alert.severity, alert.rule, alert.message
+                ));
+            }
+            report.push_str("\n");
+        }
+
         // Improvement guidance
         report.push_str("Improvement guidance:\n");
         if stats.avg_performance_score < 70.0 {
@@ -683,6 +737,12 @@ impl PerformanceAnalyzer {
             "Average score: {:.1}/100\n",
             stats.avg_performance_score
         ));
+        let alert_stats = self.alerts.stats();
+        report.push_str(&format!("Alert rules: {}\n", self.alerts.rules().len()));
+        report.push_str(&format!(
+            "Alerts fired: {} ({} suppressed by cooldown)\n",
+            alert_stats.fired, alert_stats.suppressed
+        ));
         report.push_str(&format!(
             "Metrics collection interval: {} sec\n",
             self.config.metrics_collection_interval
@@ -691,11 +751,14 @@ impl PerformanceAnalyzer {
         report
     }
 
+    /// Background jobs of this analyzer
+    pub fn background_jobs(&self) -> &BackgroundJobManager {
+        &self.jobs
+    }
+
     /// Stops analyzer
     pub fn shutdown(&mut self) {
-        if let Some(handle) = self.background_handle.take() {
-            handle.abort();
-        }
+        self.jobs.unregister(PERFORMANCE_ANALYSIS_JOB);
     }
 }
 
@@ -786,6 +849,37 @@ mod tests {
             .any(|b| matches!(b.bottleneck_type, BottleneckType::Cpu)));
     }
 
+    #[cfg_attr(miri, ignore)]
+    #[tokio::test]
+    async fn test_analysis_raises_alerts() {
+        use crate::debug::alerts::{AlertCondition, AlertRule};
+
+        let analyses = Arc::new(RwLock::new(Vec::new()));
+        let stats = Arc::new(RwLock::new(AnalysisStats::default()));
+        let history = Arc::new(RwLock::new(Vec::new()));
+        let alerts = Arc::new(AlertManager::new());
+        alerts.add_rule(
+            AlertRule::new("cpu_any", "cpu_usage", AlertCondition::Above(-1.0))
+                .with_cooldown(Duration::from_secs(3600)),
+        );
+
+        for _ in 0..2 {
+            PerformanceAnalyzer::analyze_once(
+                analyses.clone(),
+                stats.clone(),
+                history.clone(),
+                alerts.clone(),
+            )
+            .await
+            .unwrap();
+        }
+
+        let events = alerts.recent_events(10);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].component, "System");
+        assert_eq!(alerts.stats().suppressed, 1);
+    }
+
     #[test]
     fn test_recommendations() {
         let cpu_recommendations =
@@ -1,9 +1,19 @@
 //! CPU and memory profiler for rustdb
 //!
 //! Provides tools for profiling performance and memory usage
+//!
+//! With the `cpu-profiling` feature, [`Profiler::capture_cpu_profile`] also samples the call
+//! stacks of every thread (pprof-rs, `SIGPROF`) for a given time and renders them as a
+//! flamegraph SVG or a pprof protobuf (`go tool pprof`).
+//!
+//! [`Profiler::memory_by_subsystem`] reports live heap bytes per subsystem (buffer pool, plan
+//! cache, lock tables, ...) when the counting allocator of [`crate::common::memory_tracking`]
+//! is installed (`memory-profiling` feature of the `rustdb` binary).
 
 #![allow(clippy::absurd_extreme_comparisons)]
 
+use crate::common::memory_tracking::{memory_by_tag, tracking_active, SubsystemMemory};
+use crate::common::{Error, Result};
 use crate::debug::DebugConfig;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -75,6 +85,42 @@ pub struct PerformanceSnapshot {
     pub additional_metrics: HashMap<String, f64>,
 }
 
+/// Sampling frequency of CPU profiles (Hz); not a divisor of common timer rates, so samples do
+/// not lock step with periodic work
+pub const CPU_PROFILE_FREQUENCY: i32 = 99;
+
+/// Output format of a CPU profile
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CpuProfileFormat {
+    /// Flamegraph SVG
+    Flamegraph,
+    /// pprof protobuf (`profile.proto`)
+    Pprof,
+}
+
+impl CpuProfileFormat {
+    /// File extension of the format
+    pub fn extension(self) -> &'static str {
+        match self {
+            CpuProfileFormat::Flamegraph => "svg",
+            CpuProfileFormat::Pprof => "pb",
+        }
+    }
+}
+
+/// CPU profile captured by [`Profiler::capture_cpu_profile`]
+#[derive(Debug, Clone)]
+pub struct CpuProfile {
+    /// Format of `data`
+    pub format: CpuProfileFormat,
+    /// Rendered profile
+    pub data: Vec<u8>,
+    /// Stack samples taken
+    pub samples: u64,
+    /// Sampling time
+    pub duration: Duration,
+}
+
 /// Profiling stats
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ProfilingStats {
@@ -185,6 +231,110 @@ impl Profiler {
         }
     }
 
+    /// Samples the call stacks of all threads for `duration`, blocking the calling thread
+    ///
+    /// One capture runs at a time per process; a concurrent call fails.
+    #[cfg(feature = "cpu-profiling")]
+    pub fn capture_cpu_profile(duration: Duration, format: CpuProfileFormat) -> Result<CpuProfile> {
+        use pprof::protos::Message;
+
+        let profiling_error = |e: pprof::Error| Error::internal(format!("CPU profiling: {e}"));
+        let guard = pprof::ProfilerGuardBuilder::default()
+            .frequency(CPU_PROFILE_FREQUENCY)
+            .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+            .build()
+            .map_err(profiling_error)?;
+        std::thread::sleep(duration);
+        let report = guard.report().build().map_err(profiling_error)?;
+        drop(guard);
+
+        let samples = report.data.values().map(|&n| n.max(0) as u64).sum();
+        let mut data = Vec::new();
+        match format {
+            CpuProfileFormat::Flamegraph => {
+                report.flamegraph(&mut data).map_err(profiling_error)?
+            }
+            CpuProfileFormat::Pprof => {
+                report
+                    .pprof()
+                    .map_err(profiling_error)?
+                    .encode(&mut data)
+                    .map_err(|e| Error::internal(format!("CPU profiling: {e}")))?
+            }
+        }
+        Ok(CpuProfile {
+            format,
+            data,
+            samples,
+            duration,
+        })
+    }
+
+    /// Samples the call stacks of all threads for `duration` (needs the `cpu-profiling` feature)
+    #[cfg(not(feature = "cpu-profiling"))]
+    pub fn capture_cpu_profile(
+        _duration: Duration,
+        _format: CpuProfileFormat,
+    ) -> Result<CpuProfile> {
+        Err(Error::unsupported(
+            "CPU profiling (build with the `cpu-profiling` feature)",
+        ))
+    }
+
+    /// Live heap use per subsystem; all zero unless the counting allocator is installed (see
+    /// [`crate::common::memory_tracking`])
+    pub fn memory_by_subsystem() -> Vec<SubsystemMemory> {
+        memory_by_tag()
+    }
+
+    /// Resident set size of this process, from `/proc/self/status` (Linux only)
+    pub fn resident_memory_bytes() -> Option<u64> {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        let kb = status
+            .lines()
+            .find_map(|l| l.strip_prefix("VmRSS:"))?
+            .trim()
+            .strip_suffix("kB")?
+            .trim()
+            .parse::<u64>()
+            .ok()?;
+        Some(kb * 1024)
+    }
+
+    /// Generates a report of heap use by subsystem next to the resident set size
+    pub fn generate_memory_report() -> String {
+        let mut report = String::new();
+        report.push_str("=== Memory by subsystem ===\n\n");
+        if let Some(rss) = Self::resident_memory_bytes() {
+            report.push_str(&format!(
+                "Resident set size: {:.2} MB\n",
+                rss as f64 / (1024.0 * 1024.0)
+            ));
+        }
+        if !tracking_active() {
+            report.push_str(
+                "Allocation tracking is off (build the server with the `memory-profiling` feature)\n",
+            );
+            return report;
+        }
+        let subsystems = Self::memory_by_subsystem();
+        let tracked: u64 = subsystems.iter().map(|m| m.live_bytes).sum();
+        report.push_str(&format!(
+            "Tracked heap: {:.2} MB\n\n",
+            tracked as f64 / (1024.0 * 1024.0)
+        ));
+        for m in subsystems {
+            report.push_str(&format!(
+                "  {:<12} {:>10.2} MB live  {:>12} allocations  {:>10.2} MB allocated\n",
+                m.tag.name(),
+                m.live_bytes as f64 / (1024.0 * 1024.0),
+                m.allocations,
+                m.allocated_bytes as f64 / (1024.0 * 1024.0)
+            ));
+        }
+        report
+    }
+
     /// Collects a performance snapshot
     fn collect_performance_snapshot(config: &DebugConfig) -> PerformanceSnapshot {
         let timestamp = SystemTime::now()
@@ -615,6 +765,43 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "cpu-profiling")]
+    #[test]
+    fn test_capture_cpu_profile() {
+        let stop = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let busy = {
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                let mut x = 0u64;
+                while !stop.load(std::sync::atomic::Ordering::Relaxed) {
+                    x = std::hint::black_box(x.wrapping_mul(31).wrapping_add(7));
+                }
+            })
+        };
+
+        let svg =
+            Profiler::capture_cpu_profile(Duration::from_millis(300), CpuProfileFormat::Flamegraph)
+                .unwrap();
+        let pprof =
+            Profiler::capture_cpu_profile(Duration::from_millis(300), CpuProfileFormat::Pprof)
+                .unwrap();
+        stop.store(true, std::sync::atomic::Ordering::Relaxed);
+        busy.join().unwrap();
+
+        assert!(svg.samples > 0);
+        assert!(String::from_utf8_lossy(&svg.data).contains("<svg"));
+        assert_eq!(pprof.format, CpuProfileFormat::Pprof);
+        assert!(!pprof.data.is_empty());
+    }
+
+    #[cfg(not(feature = "cpu-profiling"))]
+    #[test]
+    fn test_capture_cpu_profile_needs_feature() {
+        let err = Profiler::capture_cpu_profile(Duration::from_secs(1), CpuProfileFormat::Pprof)
+            .unwrap_err();
+        assert!(matches!(err, Error::Unsupported { .. }));
+    }
+
     #[test]
     fn test_trend_analysis() {
         let snapshots = vec![
@@ -663,4 +850,17 @@ mod tests {
         assert!(trend.is_some());
         assert!(trend.unwrap().contains("Increasing"));
     }
+
+    #[test]
+    fn test_memory_report() {
+        let subsystems = Profiler::memory_by_subsystem();
+        assert_eq!(subsystems.len(), 5);
+        #[cfg(target_os = "linux")]
+        assert!(Profiler::resident_memory_bytes().is_some_and(|b| b > 0));
+
+        let report = Profiler::generate_memory_report();
+        assert!(report.contains("Memory by subsystem"));
+        // Another test may count its allocations while this report is generated.
+        assert!(report.contains("buffer_pool") || report.contains("tracking is off"));
+    }
 }
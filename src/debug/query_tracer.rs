@@ -10,6 +10,9 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::task::JoinHandle;
 use uuid::Uuid;
 
+/// Completed traces kept in memory; older ones are dropped first
+const MAX_COMPLETED_TRACES: usize = 1000;
+
 /// Query execution stage
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum QueryStage {
@@ -175,13 +178,17 @@ impl QueryTracer {
         tracer
     }
 
-    /// Starts background cleanup job
+    /// Starts background cleanup job (only inside a Tokio runtime; without one, completed traces
+    /// are still bounded by [`MAX_COMPLETED_TRACES`])
     fn start_background_cleanup(&mut self) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
         let active_traces = self.active_traces.clone();
         let completed_traces = self.completed_traces.clone();
         let _config = self.config.clone();
 
-        self.background_handle = Some(tokio::spawn(async move {
+        self.background_handle = Some(runtime.spawn(async move {
             let mut interval = tokio::time::interval(Duration::from_secs(60));
 
             loop {
@@ -216,8 +223,8 @@ impl QueryTracer {
 
                     // Limit number of completed traces
                     let len = completed.len();
-                    if len > 1000 {
-                        completed.drain(0..len - 1000);
+                    if len > MAX_COMPLETED_TRACES {
+                        completed.drain(0..len - MAX_COMPLETED_TRACES);
                     }
                 }
             }
@@ -367,6 +374,9 @@ impl QueryTracer {
             // Move to completed list
             {
                 let mut completed_traces = self.completed_traces.write().unwrap();
+                if completed_traces.len() >= MAX_COMPLETED_TRACES {
+                    completed_traces.remove(0);
+                }
                 completed_traces.push(trace);
             }
 
@@ -395,6 +405,16 @@ impl QueryTracer {
         active_traces.values().cloned().collect()
     }
 
+    /// Returns a completed trace by ID
+    pub fn get_completed_trace(&self, query_id: &str) -> Option<QueryTrace> {
+        let completed_traces = self.completed_traces.read().unwrap();
+        completed_traces
+            .iter()
+            .rev()
+            .find(|t| t.query_id == query_id)
+            .cloned()
+    }
+
     /// Returns recently completed traces
     pub fn get_completed_traces(&self, limit: usize) -> Vec<QueryTrace> {
         let completed_traces = self.completed_traces.read().unwrap();
@@ -4,6 +4,11 @@
 //! - [`Db`] owns the engine
 //! - [`Connection`] carries per-session state
 //! - [`Transaction`] is a safe RAII wrapper around `BEGIN/COMMIT/ROLLBACK`
+//! - [`RetryPolicy`] reruns transactions that fail with a serialization failure
+//!   ([`Connection::transaction_with_retry`])
+//! - [`Connection::execute_params`] binds `?` placeholders as quoted literals
+//! - [`Connection::query_as`] maps result rows into structs ([`FromRow`])
+//! - [`Db::table`] / [`Connection::fetch`] run queries built with [`crate::query_builder`]
 //! - [`Config`] controls durability / WAL / checkpoint defaults (safe-by-default for embedded)
 //!
 //! # Defaults
@@ -19,12 +24,19 @@
 //! - [`Connection`] also rolls back any *pending* transaction on drop, so a panic between
 //!   `BEGIN` and the first DML still leaves the heap consistent (no orphaned uncommitted data).
 
-use crate::common::{DurabilityMode, Result};
-use crate::network::engine::{EngineError, EngineHandle, EngineOutput, SessionContext};
+use crate::common::connection_url::{ConnectionUrl, EmbeddedUrl};
+use crate::common::row_mapping::{FromRow, ResultRow, ToValue};
+use crate::common::{DurabilityMode, Error, Result};
+use crate::network::engine::{
+    engine_error_code, EngineError, EngineHandle, EngineOutput, SessionContext,
+};
 use crate::network::sql_engine::SqlEngineConfig;
 use crate::network::SqlEngine;
+use crate::parser::bind_params;
+use crate::query_builder::TableQuery;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 /// Embedded configuration for opening a database.
 ///
@@ -88,6 +100,29 @@ impl Config {
         self.checkpoints_enabled = enabled;
         self
     }
+
+    /// Defaults overridden by the `durability`, `wal` and `checkpoints` parameters of `url`.
+    pub fn from_url(url: &EmbeddedUrl) -> Result<Self> {
+        let mut config = Self::default();
+        if let Some(durability) = url.params.get("durability") {
+            config.durability = match durability.to_ascii_lowercase().as_str() {
+                "safe" => DurabilityMode::Safe,
+                "fast" => DurabilityMode::Fast,
+                other => {
+                    return Err(Error::validation(format!(
+                        "durability={other}: expected safe or fast"
+                    )))
+                }
+            };
+        }
+        if let Some(wal) = url.flag("wal")? {
+            config.wal_enabled = wal;
+        }
+        if let Some(checkpoints) = url.flag("checkpoints")? {
+            config.checkpoints_enabled = checkpoints;
+        }
+        Ok(config)
+    }
 }
 
 impl From<Config> for SqlEngineConfig {
@@ -96,6 +131,8 @@ impl From<Config> for SqlEngineConfig {
             wal_enabled: c.wal_enabled,
             durability: c.durability,
             checkpoints_enabled: c.checkpoints_enabled,
+            wal_archive_url: None,
+            ..SqlEngineConfig::default()
         }
     }
 }
@@ -125,6 +162,17 @@ impl Db {
         })
     }
 
+    /// Open the database named by an embedded connection URL, `rustdb:///data/dir?durability=fast`
+    /// (see [`crate::common::connection_url`]); server URLs are rejected.
+    pub fn open_url(url: &str) -> Result<Self> {
+        match ConnectionUrl::parse(url)? {
+            ConnectionUrl::Embedded(url) => Self::open(&url.data_dir, Config::from_url(&url)?),
+            ConnectionUrl::Server(_) => Err(Error::validation(
+                "an embedded database needs a rustdb:///<data directory> URL, not a server URL",
+            )),
+        }
+    }
+
     /// Borrow the underlying engine.
     pub fn engine(&self) -> &SqlEngine {
         self.engine.as_ref()
@@ -164,6 +212,17 @@ impl Db {
             ctx: SessionContext::default(),
         }
     }
+
+    /// [`Connection::query_as`] on a fresh connection.
+    pub fn query_as<T: FromRow>(&self, sql: &str) -> std::result::Result<Vec<T>, EngineError> {
+        self.connect().query_as(sql)
+    }
+
+    /// Starts a query on `table` ([`crate::query_builder::table`]); run it with
+    /// [`Connection::fetch`] or [`Connection::fetch_as`].
+    pub fn table(&self, table: &str) -> TableQuery {
+        crate::query_builder::table(table)
+    }
 }
 
 /// Per-session SQL connection for embedded usage.
@@ -181,6 +240,42 @@ impl Connection {
         self.engine.execute_sql(sql, &mut self.ctx)
     }
 
+    /// Executes one statement with its `?` placeholders bound to `params` as literals
+    /// ([`crate::parser::bind_params`]). SQL with more than one statement, or a placeholder
+    /// count that does not match, fails with [`engine_error_code::PROTOCOL`].
+    pub fn execute_params(
+        &mut self,
+        sql: &str,
+        params: &[&dyn ToValue],
+    ) -> std::result::Result<EngineOutput, EngineError> {
+        let sql = bind_params(sql, params)
+            .map_err(|e| EngineError::new(engine_error_code::PROTOCOL, e.to_string()))?;
+        self.execute(&sql)
+    }
+
+    /// Runs a query and maps each result row into `T` (see [`FromRow`], usually derived with
+    /// `#[derive(rustdb::FromRow)]`). Rows that do not fit `T`, or a statement without a result
+    /// set, fail with [`engine_error_code::RESULT_MAPPING`].
+    pub fn query_as<T: FromRow>(&mut self, sql: &str) -> std::result::Result<Vec<T>, EngineError> {
+        map_rows(self.execute(sql)?)
+    }
+
+    /// Runs a [`TableQuery`] built with [`crate::query_builder`].
+    pub fn fetch(&mut self, query: &TableQuery) -> std::result::Result<EngineOutput, EngineError> {
+        let sql = query
+            .to_sql()
+            .map_err(|e| EngineError::new(engine_error_code::INTERNAL, e.to_string()))?;
+        self.execute(&sql)
+    }
+
+    /// [`Self::fetch`], with rows mapped like [`Self::query_as`].
+    pub fn fetch_as<T: FromRow>(
+        &mut self,
+        query: &TableQuery,
+    ) -> std::result::Result<Vec<T>, EngineError> {
+        map_rows(self.fetch(query)?)
+    }
+
     /// Whether this session currently has an open transaction.
     pub fn in_transaction(&self) -> bool {
         self.ctx.transaction.is_some()
@@ -194,6 +289,120 @@ impl Connection {
             active: true,
         })
     }
+
+    /// Runs `body` in a transaction and commits it. When `BEGIN`, a statement of `body` or the
+    /// `COMMIT` fails with a retryable error ([`EngineError::is_retryable`]), the attempt is
+    /// rolled back and the whole transaction runs again after a pause, as `policy` allows; `body`
+    /// must therefore be safe to run more than once. Other errors, and the retryable error of the
+    /// last attempt, are returned as they are.
+    pub fn transaction_with_retry<T>(
+        &mut self,
+        policy: &RetryPolicy,
+        mut body: impl FnMut(&mut Transaction<'_>) -> std::result::Result<T, EngineError>,
+    ) -> std::result::Result<T, EngineError> {
+        let mut attempt = 1;
+        loop {
+            let result = self.begin().and_then(|mut tx| {
+                // On error `tx` is dropped, which rolls the attempt back.
+                let out = body(&mut tx)?;
+                tx.commit()?;
+                Ok(out)
+            });
+            match result {
+                Err(e) if e.is_retryable() && attempt < policy.max_attempts => {
+                    std::thread::sleep(policy.backoff(attempt));
+                    attempt += 1;
+                }
+                other => return other,
+            }
+        }
+    }
+
+    /// Runs one statement, retrying it like [`Self::transaction_with_retry`]. Inside an open
+    /// transaction the statement runs once: only the whole transaction could be retried.
+    pub fn execute_with_retry(
+        &mut self,
+        sql: &str,
+        policy: &RetryPolicy,
+    ) -> std::result::Result<EngineOutput, EngineError> {
+        let mut attempt = 1;
+        loop {
+            match self.execute(sql) {
+                Err(e)
+                    if e.is_retryable()
+                        && !self.in_transaction()
+                        && attempt < policy.max_attempts =>
+                {
+                    std::thread::sleep(policy.backoff(attempt));
+                    attempt += 1;
+                }
+                other => return other,
+            }
+        }
+    }
+}
+
+/// Maps a result set into `T` rows as [`Connection::query_as`] does; also used by clients of
+/// a server, whose results have the same shape.
+pub fn map_rows<T: FromRow>(out: EngineOutput) -> std::result::Result<Vec<T>, EngineError> {
+    let EngineOutput::ResultSet { columns, rows } = out else {
+        return Err(EngineError::new(
+            engine_error_code::RESULT_MAPPING,
+            "statement returned no result set",
+        ));
+    };
+    rows.iter()
+        .map(|cells| {
+            T::from_row(&ResultRow::new(&columns, cells))
+                .map_err(|e| EngineError::new(engine_error_code::RESULT_MAPPING, e.to_string()))
+        })
+        .collect()
+}
+
+/// Bounded retries with exponential backoff, for [`Connection::transaction_with_retry`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts in total, the first included; `1` disables retries.
+    pub max_attempts: u32,
+    /// Pause after the first failed attempt; doubled after each later one.
+    pub initial_backoff: Duration,
+    /// Longest pause.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy with `max_attempts` attempts and the default backoff.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Policy with the given first and longest pause.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Pause after failed attempt number `attempt` (from 1).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(attempt.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
 }
 
 impl Drop for Connection {
@@ -246,6 +455,7 @@ impl Drop for Transaction<'_> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::network::engine::engine_error_code;
     use tempfile::TempDir;
 
     #[test]
@@ -356,6 +566,65 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn embedded_transaction_with_retry_reruns_serialization_failures() -> Result<()> {
+        let dir = TempDir::new().unwrap();
+        let db = Db::open(dir.path(), Config::default())?;
+        let mut conn = db.connect();
+        conn.execute("CREATE TABLE r (a INT)").unwrap();
+        let policy = RetryPolicy::default().with_backoff(Duration::ZERO, Duration::ZERO);
+        let conflict = || EngineError::new(engine_error_code::SERIALIZATION_FAILURE, "conflict");
+
+        // Failed attempts are rolled back: only the third insert remains.
+        let mut attempts = 0;
+        let out = conn.transaction_with_retry(&policy, |tx| {
+            attempts += 1;
+            tx.execute("INSERT INTO r (a) VALUES (1)")?;
+            if attempts < 3 {
+                return Err(conflict());
+            }
+            Ok(attempts)
+        });
+        assert_eq!(out, Ok(3));
+        match conn.execute("SELECT a FROM r").unwrap() {
+            EngineOutput::ResultSet { rows, .. } => assert_eq!(rows.len(), 1),
+            _ => panic!("expected result set"),
+        }
+
+        // The last failure is returned once the attempts run out.
+        attempts = 0;
+        let out: std::result::Result<(), _> =
+            conn.transaction_with_retry(&policy.clone().with_max_attempts(2), |_| {
+                attempts += 1;
+                Err(conflict())
+            });
+        assert_eq!(
+            out.unwrap_err().code,
+            engine_error_code::SERIALIZATION_FAILURE
+        );
+        assert_eq!(attempts, 2);
+
+        // Other errors are not retried.
+        attempts = 0;
+        let out = conn.transaction_with_retry(&policy, |tx| {
+            attempts += 1;
+            tx.execute("SELEC 1")
+        });
+        assert!(out.is_err());
+        assert_eq!(attempts, 1);
+        assert!(!conn.in_transaction());
+        Ok(())
+    }
+
+    #[test]
+    fn retry_backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy::default()
+            .with_backoff(Duration::from_millis(10), Duration::from_millis(50));
+        let pauses: Vec<_> = (1..=5).map(|n| policy.backoff(n).as_millis()).collect();
+        assert_eq!(pauses, vec![10, 20, 40, 50, 50]);
+        assert_eq!(policy.backoff(100), Duration::from_millis(50));
+    }
+
     #[test]
     fn embedded_open_default_uses_safe_defaults() -> Result<()> {
         let dir = TempDir::new().unwrap();
@@ -364,4 +633,19 @@ mod tests {
         assert!(db.wal_enabled());
         Ok(())
     }
+
+    #[test]
+    fn embedded_open_url_maps_parameters_to_config() -> Result<()> {
+        let dir = TempDir::new().unwrap();
+        let url = format!(
+            "rustdb://{}?durability=fast&checkpoints=off",
+            dir.path().display()
+        );
+        let db = Db::open_url(&url)?;
+        assert_eq!(db.durability(), DurabilityMode::Fast);
+        assert!(!db.config().checkpoints_enabled);
+        assert!(Db::open_url("rustdb://localhost:5432/db").is_err());
+        assert!(Db::open_url(&format!("rustdb://{}?wal=maybe", dir.path().display())).is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,127 @@
+//! What each plan node of a query did, for `EXPLAIN ANALYZE`
+//!
+//! While the guard returned by [`analyze_scope`] lives, the executor wraps the operator of every
+//! plan node it builds on the current thread in a recorder of the rows the node returned, the
+//! time spent in it (its inputs included) and the memory and temporary files of its
+//! [`OperatorStatistics`]. The recorder reports when the operator is dropped, so nodes stopped
+//! early (under a `LIMIT`) report what they did until then. [`AnalyzeScope::finish`] hands the
+//! reports over by node [`fingerprint`], summed over nodes that share one.
+//!
+//! Plans built outside a scope are not instrumented.
+
+use crate::common::clock::Instant;
+use crate::common::Result;
+use crate::executor::operators::{Operator, OperatorStatistics};
+use crate::planner::cardinality_feedback::fingerprint;
+use crate::planner::planner::PlanNode;
+use crate::planner::NodeActuals;
+use crate::Row;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::Duration;
+
+thread_local! {
+    /// Reports to each open scope, outermost first.
+    static SCOPES: RefCell<Vec<HashMap<u64, NodeActuals>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Records what the plan nodes executed on the current thread do until the guard is dropped
+pub fn analyze_scope() -> AnalyzeScope {
+    let depth = SCOPES.with(|s| {
+        let mut s = s.borrow_mut();
+        s.push(HashMap::new());
+        s.len() - 1
+    });
+    AnalyzeScope {
+        depth,
+        _not_send: std::marker::PhantomData,
+    }
+}
+
+/// Guard returned by [`analyze_scope`]
+pub struct AnalyzeScope {
+    depth: usize,
+    // The reports are per thread: the guard must be dropped where it was created.
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+impl AnalyzeScope {
+    /// Closes the scope; returns what the nodes run in it did, by fingerprint.
+    pub fn finish(self) -> HashMap<u64, NodeActuals> {
+        SCOPES.with(|s| {
+            s.borrow_mut()
+                .get_mut(self.depth)
+                .map(std::mem::take)
+                .unwrap_or_default()
+        })
+    }
+}
+
+impl Drop for AnalyzeScope {
+    fn drop(&mut self) {
+        // Also closes scopes opened inside this one and not dropped yet.
+        SCOPES.with(|s| s.borrow_mut().truncate(self.depth));
+    }
+}
+
+/// `input`, the operator of `node`, recorded when a scope is active (else `input` unchanged)
+pub(crate) fn instrument(node: &PlanNode, input: Box<dyn Operator>) -> Box<dyn Operator> {
+    if SCOPES.with(|s| s.borrow().is_empty()) {
+        return input;
+    }
+    Box::new(AnalyzedOperator {
+        input,
+        fingerprint: fingerprint(node),
+        rows: 0,
+        elapsed: Duration::ZERO,
+    })
+}
+
+/// Passes its input's rows through, timing them; reports when dropped
+struct AnalyzedOperator {
+    input: Box<dyn Operator>,
+    fingerprint: u64,
+    rows: u64,
+    elapsed: Duration,
+}
+
+impl Operator for AnalyzedOperator {
+    fn next(&mut self) -> Result<Option<Row>> {
+        let started = Instant::now();
+        let row = self.input.next();
+        self.elapsed += started.elapsed();
+        if let Ok(Some(_)) = &row {
+            self.rows += 1;
+        }
+        row
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()
+    }
+
+    fn get_schema(&self) -> Result<Vec<String>> {
+        self.input.get_schema()
+    }
+
+    fn get_statistics(&self) -> OperatorStatistics {
+        self.input.get_statistics()
+    }
+}
+
+impl Drop for AnalyzedOperator {
+    fn drop(&mut self) {
+        let statistics = self.input.get_statistics();
+        SCOPES.with(|s| {
+            if let Some(scope) = s.borrow_mut().last_mut() {
+                let actual = scope.entry(self.fingerprint).or_default();
+                actual.rows += self.rows;
+                actual.time_ms += self.elapsed.as_secs_f64() * 1000.0;
+                actual.peak_memory_bytes =
+                    actual.peak_memory_bytes.max(statistics.memory_used_bytes);
+                actual.spill_count += statistics.spill_count;
+                actual.spilled_bytes += statistics.spilled_bytes;
+            }
+        });
+    }
+}
@@ -0,0 +1,119 @@
+//! Per-query arena for short-lived values
+//!
+//! Expression evaluation produces temporaries that only live until the value is turned into an
+//! output [`crate::common::types::ColumnValue`] (function result keys, formatted blobs, ...).
+//! Operators that evaluate expressions own a [`QueryArena`], allocate those temporaries from it
+//! and reset it after each row, so the arena's chunks are reused for the whole query and freed
+//! wholesale when the operator tree is dropped at query end.
+//!
+//! Entry points evaluating single rows outside an operator tree (`UPDATE … SET`, `CHECK`
+//! constraints, system views) use a per-thread scratch arena via [`with_scratch_arena`].
+
+use bumpalo::Bump;
+use std::cell::RefCell;
+
+/// Bump allocator for the temporaries of one query
+#[derive(Debug, Default)]
+pub struct QueryArena {
+    bump: Bump,
+    /// Largest number of bytes allocated between two resets
+    peak_bytes: usize,
+}
+
+impl QueryArena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an arena whose first chunk holds at least `bytes` bytes
+    pub fn with_capacity(bytes: usize) -> Self {
+        Self {
+            bump: Bump::with_capacity(bytes),
+            peak_bytes: 0,
+        }
+    }
+
+    /// Underlying allocator, for `bumpalo::collections` values
+    pub fn bump(&self) -> &Bump {
+        &self.bump
+    }
+
+    /// Copies `s` into the arena
+    pub fn alloc_str(&self, s: &str) -> &str {
+        self.bump.alloc_str(s)
+    }
+
+    /// Bytes allocated since the last reset
+    pub fn allocated_bytes(&self) -> usize {
+        self.bump.allocated_bytes() - self.bump.chunk_capacity()
+    }
+
+    /// Bytes reserved by the arena's chunks
+    pub fn capacity_bytes(&self) -> usize {
+        self.bump.allocated_bytes()
+    }
+
+    /// Largest [`Self::allocated_bytes`] seen at a reset
+    pub fn peak_bytes(&self) -> usize {
+        self.peak_bytes.max(self.allocated_bytes())
+    }
+
+    /// Frees every value at once, keeping the largest chunk for reuse.
+    pub fn reset(&mut self) {
+        self.peak_bytes = self.peak_bytes();
+        self.bump.reset();
+    }
+}
+
+thread_local! {
+    static SCRATCH: RefCell<QueryArena> = RefCell::new(QueryArena::new());
+}
+
+/// Runs `f` with this thread's scratch arena and resets it afterwards.
+///
+/// A nested call gets a fresh arena instead of the (already borrowed) thread one.
+pub fn with_scratch_arena<R>(f: impl FnOnce(&QueryArena) -> R) -> R {
+    SCRATCH.with(|cell| match cell.try_borrow_mut() {
+        Ok(mut arena) => {
+            let out = f(&arena);
+            arena.reset();
+            out
+        }
+        Err(_) => f(&QueryArena::new()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reset_frees_values_and_keeps_peak() {
+        let mut arena = QueryArena::with_capacity(1024);
+        let s = arena.alloc_str("temporary");
+        assert_eq!(s, "temporary");
+        assert!(arena.allocated_bytes() >= "temporary".len());
+        let capacity = arena.capacity_bytes();
+
+        arena.reset();
+        assert_eq!(arena.allocated_bytes(), 0);
+        assert!(arena.peak_bytes() >= "temporary".len());
+        assert_eq!(arena.capacity_bytes(), capacity);
+    }
+
+    #[test]
+    fn scratch_arena_is_reset_after_each_use() {
+        let first = with_scratch_arena(|arena| {
+            arena.alloc_str("abc");
+            arena.allocated_bytes()
+        });
+        assert!(first >= 3);
+        let second = with_scratch_arena(|arena| arena.allocated_bytes());
+        assert_eq!(second, 0);
+        let nested = with_scratch_arena(|outer| {
+            outer.alloc_str("x");
+            with_scratch_arena(|inner| inner.alloc_str("y").to_string())
+        });
+        assert_eq!(nested, "y");
+    }
+}
@@ -0,0 +1,133 @@
+//! Row counts of the plan nodes a query runs, for cardinality feedback
+//! ([`crate::planner::cardinality_feedback`])
+//!
+//! While the guard returned by [`cardinality_scope`] lives, the executor wraps the operator of
+//! every scan and filter it builds on the current thread in a counter. A node that runs to its
+//! end reports the rows it returned, with its fingerprint and the plan's estimate, to the
+//! innermost scope; [`CardinalityScope::finish`] hands them over. Nodes stopped early (under a
+//! `LIMIT`, or by an error) report nothing, since their count is not their cardinality.
+//!
+//! Plans built outside a scope are not instrumented.
+
+use crate::common::Result;
+use crate::executor::operators::{Operator, OperatorStatistics};
+use crate::planner::cardinality_feedback::{fingerprint, is_observed};
+use crate::planner::planner::PlanNode;
+use crate::Row;
+use std::cell::RefCell;
+
+/// Rows one plan node returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeCardinality {
+    /// [`fingerprint`] of the node
+    pub fingerprint: u64,
+    /// Rows the plan estimated
+    pub estimated_rows: u64,
+    /// Rows the node returned
+    pub actual_rows: u64,
+}
+
+thread_local! {
+    /// Counts reported to each open scope, outermost first.
+    static SCOPES: RefCell<Vec<Vec<NodeCardinality>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Counts the rows of the plans executed on the current thread until the guard is dropped
+pub fn cardinality_scope() -> CardinalityScope {
+    let depth = SCOPES.with(|s| {
+        let mut s = s.borrow_mut();
+        s.push(Vec::new());
+        s.len() - 1
+    });
+    CardinalityScope {
+        depth,
+        _not_send: std::marker::PhantomData,
+    }
+}
+
+/// Guard returned by [`cardinality_scope`]
+pub struct CardinalityScope {
+    depth: usize,
+    // The counts are per thread: the guard must be dropped where it was created.
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+impl CardinalityScope {
+    /// Closes the scope; returns the counts of the nodes that ran to their end in it.
+    pub fn finish(self) -> Vec<NodeCardinality> {
+        SCOPES.with(|s| {
+            s.borrow_mut()
+                .get_mut(self.depth)
+                .map(std::mem::take)
+                .unwrap_or_default()
+        })
+    }
+}
+
+impl Drop for CardinalityScope {
+    fn drop(&mut self) {
+        // Also closes scopes opened inside this one and not dropped yet.
+        SCOPES.with(|s| s.borrow_mut().truncate(self.depth));
+    }
+}
+
+/// `input`, the operator of `node`, counting its rows when a scope is active and the node is
+/// observed (else `input` unchanged)
+pub(crate) fn observe(node: &PlanNode, input: Box<dyn Operator>) -> Box<dyn Operator> {
+    if !is_observed(node) || SCOPES.with(|s| s.borrow().is_empty()) {
+        return input;
+    }
+    Box::new(CountedOperator {
+        input,
+        fingerprint: fingerprint(node),
+        estimated_rows: node.estimated_rows() as u64,
+        rows: 0,
+        reported: false,
+    })
+}
+
+/// Passes its input's rows through and reports their number at the end of the first pass
+struct CountedOperator {
+    input: Box<dyn Operator>,
+    fingerprint: u64,
+    estimated_rows: u64,
+    rows: u64,
+    reported: bool,
+}
+
+impl Operator for CountedOperator {
+    fn next(&mut self) -> Result<Option<Row>> {
+        let row = self.input.next()?;
+        match &row {
+            Some(_) => self.rows += 1,
+            None if !self.reported => {
+                self.reported = true;
+                let seen = NodeCardinality {
+                    fingerprint: self.fingerprint,
+                    estimated_rows: self.estimated_rows,
+                    actual_rows: self.rows,
+                };
+                SCOPES.with(|s| {
+                    if let Some(scope) = s.borrow_mut().last_mut() {
+                        scope.push(seen);
+                    }
+                });
+            }
+            None => {}
+        }
+        Ok(row)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.rows = 0;
+        self.input.reset()
+    }
+
+    fn get_schema(&self) -> Result<Vec<String>> {
+        self.input.get_schema()
+    }
+
+    fn get_statistics(&self) -> OperatorStatistics {
+        self.input.get_statistics()
+    }
+}
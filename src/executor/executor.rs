@@ -4,17 +4,24 @@
 //! Supports parallel table scan when enabled.
 
 use crate::common::{Error, Result};
+use crate::executor::analyze::instrument;
+use crate::executor::cardinality::observe;
+use crate::executor::external_sort::{SortConfig, SortKey, DEFAULT_WORK_MEM};
+use crate::executor::governor::{govern, Charge};
 use crate::executor::operators::{
-    ConditionalScanOperator, GroupByOperator, IndexCondition, IndexOperator, JoinCondition,
-    JoinOperator, JoinType, LimitOperator, NestedLoopJoinOperator, OffsetOperator, Operator,
-    ProjectionOperator, ScanOperatorFactory, SortOperator,
+    ConditionalScanOperator, GroupByOperator, HashJoinOperator, IndexCondition, IndexOperator,
+    JoinCondition, JoinOperator, JoinType, LimitOperator, MergeJoinOperator,
+    NestedLoopJoinOperator, OffsetOperator, Operator, ProjectionOperator, ScanSource, SortOperator,
 };
 use crate::planner::planner::{
-    AntiJoinNode, DistinctNode, ExecutionPlan, FilterNode, GroupByNode, IndexScanNode, JoinNode,
-    LimitNode, OffsetNode, PlanNode, ProjectionNode, SemiJoinNode, SetOpNode, SortNode,
-    TableScanNode,
+    AntiJoinNode, DistinctNode, ExecutionPlan, FilterNode, GroupByNode, IndexScanNode,
+    JoinAlgorithm, JoinNode, LimitNode, OffsetNode, PlanNode, ProjectionNode, SemiJoinNode,
+    SetOpNode, SortNode, TableScanNode,
 };
 use crate::Row;
+#[cfg(feature = "native")]
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use tracing::info_span;
 
@@ -25,6 +32,11 @@ pub struct QueryExecutorConfig {
     pub enable_parallel_execution: bool,
     /// Number of worker threads for parallel scan
     pub num_worker_threads: usize,
+    /// Bytes of rows a sort keeps in memory before it spills them to runs
+    pub work_mem: usize,
+    /// Directory for the runs of sorts (default: the system temp directory)
+    #[cfg(feature = "native")]
+    pub temp_dir: Option<PathBuf>,
 }
 
 impl Default for QueryExecutorConfig {
@@ -32,43 +44,69 @@ impl Default for QueryExecutorConfig {
         Self {
             enable_parallel_execution: true,
             num_worker_threads: 4,
+            work_mem: DEFAULT_WORK_MEM,
+            #[cfg(feature = "native")]
+            temp_dir: None,
         }
     }
 }
 
 /// Query executor - builds operator tree from plan and executes
 pub struct QueryExecutor {
-    /// Scan operator factory (page manager + indexes)
-    scan_factory: Arc<ScanOperatorFactory>,
+    /// Scans of base tables (page manager + indexes in the engine)
+    scan_factory: Arc<dyn ScanSource>,
     /// Configuration
     config: QueryExecutorConfig,
+    /// Work memory of sorts; starts at `config.work_mem`
+    work_mem: AtomicUsize,
 }
 
 impl QueryExecutor {
     /// Creates a new query executor
-    pub fn new(scan_factory: Arc<ScanOperatorFactory>) -> Result<Self> {
-        Ok(Self {
-            scan_factory,
-            config: QueryExecutorConfig::default(),
-        })
+    pub fn new(scan_factory: Arc<dyn ScanSource>) -> Result<Self> {
+        Self::with_config(scan_factory, QueryExecutorConfig::default())
     }
 
-    /// Shared scan factory (indexes, per-table heaps).
-    pub fn scan_factory(&self) -> &Arc<ScanOperatorFactory> {
+    /// Shared scan source (indexes, per-table heaps).
+    pub fn scan_factory(&self) -> &Arc<dyn ScanSource> {
         &self.scan_factory
     }
 
     /// Creates executor with custom config
     pub fn with_config(
-        scan_factory: Arc<ScanOperatorFactory>,
+        scan_factory: Arc<dyn ScanSource>,
         config: QueryExecutorConfig,
     ) -> Result<Self> {
         Ok(Self {
             scan_factory,
+            work_mem: AtomicUsize::new(config.work_mem),
             config,
         })
     }
 
+    /// Work memory of sorts, in bytes
+    pub fn work_mem(&self) -> usize {
+        self.work_mem.load(Ordering::Relaxed)
+    }
+
+    /// Sets the work memory of the sorts of later queries.
+    pub fn set_work_mem(&self, bytes: usize) {
+        self.work_mem.store(bytes, Ordering::Relaxed);
+    }
+
+    /// Work memory and temp directory of the sorts of later queries.
+    pub(crate) fn sort_config(&self) -> SortConfig {
+        SortConfig {
+            work_mem: self.work_mem(),
+            #[cfg(feature = "native")]
+            temp_dir: self
+                .config
+                .temp_dir
+                .clone()
+                .unwrap_or_else(std::env::temp_dir),
+        }
+    }
+
     /// Executes the plan and returns all result rows
     pub fn execute(&self, plan: &ExecutionPlan) -> Result<Vec<Row>> {
         let span = info_span!("executor.execute");
@@ -90,9 +128,13 @@ impl QueryExecutor {
 
     /// Builds operator tree from plan node
     fn build_operator(&self, node: &PlanNode) -> Result<Box<dyn Operator>> {
-        match node {
-            PlanNode::TableScan(ts) => self.build_table_scan(ts),
-            PlanNode::IndexScan(idx) => self.build_index_scan(idx),
+        let operator = match node {
+            PlanNode::TableScan(ts) => Ok(govern(self.build_table_scan(ts)?, Charge::RowsRead)),
+            PlanNode::IndexScan(idx) => Ok(govern(self.build_index_scan(idx)?, Charge::RowsRead)),
+            PlanNode::ForeignScan(fs) => Ok(govern(
+                self.scan_factory.create_foreign_scan(fs.clone())?,
+                Charge::RowsRead,
+            )),
             PlanNode::Filter(f) => self.build_filter(f),
             PlanNode::Projection(p) => self.build_projection(p),
             PlanNode::Join(j) => self.build_join(j),
@@ -108,7 +150,8 @@ impl QueryExecutor {
                 "Unsupported plan node: {:?}",
                 node
             ))),
-        }
+        }?;
+        Ok(instrument(node, observe(node, operator)))
     }
 
     fn build_table_scan(&self, ts: &TableScanNode) -> Result<Box<dyn Operator>> {
@@ -167,12 +210,13 @@ impl QueryExecutor {
                     } else {
                         ts.columns.clone()
                     };
-                    return self.scan_factory.create_table_scan(
+                    let scan = self.scan_factory.create_table_scan(
                         ts.table_name.clone(),
                         ts.filter.clone(),
                         Some(eq),
                         schema,
-                    );
+                    )?;
+                    return Ok(govern(scan, Charge::RowsRead));
                 }
             }
         }
@@ -194,14 +238,14 @@ impl QueryExecutor {
     }
 
     fn build_distinct(&self, d: &DistinctNode) -> Result<Box<dyn Operator>> {
-        let input = self.build_operator(&d.input)?;
+        let input = govern(self.build_operator(&d.input)?, Charge::TempBytes);
         let operator = crate::executor::operators::DistinctOperator::new(input)?;
         Ok(Box::new(operator))
     }
 
     fn build_set_op(&self, s: &SetOpNode) -> Result<Box<dyn Operator>> {
-        let left = self.build_operator(&s.left)?;
-        let right = self.build_operator(&s.right)?;
+        let left = govern(self.build_operator(&s.left)?, Charge::TempBytes);
+        let right = govern(self.build_operator(&s.right)?, Charge::TempBytes);
         let operator =
             crate::executor::operators::SetOpOperator::new(left, right, s.op.clone(), s.all)?;
         Ok(Box::new(operator))
@@ -223,7 +267,7 @@ impl QueryExecutor {
 
     fn build_join(&self, j: &JoinNode) -> Result<Box<dyn Operator>> {
         let left = self.build_operator(&j.left)?;
-        let right = self.build_operator(&j.right)?;
+        let right = govern(self.build_operator(&j.right)?, Charge::TempBytes);
         let join_condition = Self::parse_join_condition(&j.condition);
         let join_type = match j.join_type {
             crate::planner::planner::JoinType::Inner => JoinType::Inner,
@@ -232,11 +276,33 @@ impl QueryExecutor {
             crate::planner::planner::JoinType::Full => JoinType::FullOuter,
             crate::planner::planner::JoinType::Cross => JoinType::Inner,
         };
-        let operator = NestedLoopJoinOperator::new(left, right, join_condition, join_type, 100)?;
-        Ok(Box::new(operator))
+        Ok(match j.algorithm {
+            JoinAlgorithm::NestedLoop => Box::new(NestedLoopJoinOperator::new(
+                left,
+                right,
+                join_condition,
+                join_type,
+                100,
+            )?),
+            JoinAlgorithm::Hash => Box::new(HashJoinOperator::new(
+                left,
+                right,
+                join_condition,
+                join_type,
+                100,
+            )?),
+            JoinAlgorithm::Merge => Box::new(MergeJoinOperator::new(
+                left,
+                right,
+                join_condition,
+                join_type,
+            )?),
+        })
     }
 
     fn parse_join_condition(condition: &str) -> JoinCondition {
+        // Reordered joins may carry several `a=b` conjuncts; the operators take the first.
+        let condition = condition.split(" AND ").next().unwrap_or_default();
         if condition.contains('=') {
             let parts: Vec<&str> = condition.split('=').map(|s| s.trim()).collect();
             if parts.len() >= 2 {
@@ -257,7 +323,7 @@ impl QueryExecutor {
     }
 
     fn build_group_by(&self, g: &GroupByNode) -> Result<Box<dyn Operator>> {
-        let input = self.build_operator(&g.input)?;
+        let input = govern(self.build_operator(&g.input)?, Charge::TempBytes);
         let result_schema: Vec<String> = g
             .group_columns
             .iter()
@@ -278,19 +344,20 @@ impl QueryExecutor {
     }
 
     fn build_sort(&self, s: &SortNode) -> Result<Box<dyn Operator>> {
-        let input = self.build_operator(&s.input)?;
-        let sort_keys: Vec<(String, bool)> = s
+        let input = govern(self.build_operator(&s.input)?, Charge::TempBytes);
+        let sort_keys: Vec<SortKey> = s
             .sort_columns
             .iter()
             .map(|c| {
-                (
+                SortKey::new(
                     c.column.clone(),
                     matches!(c.direction, crate::planner::planner::SortDirection::Asc),
                 )
+                .with_nulls_first(c.nulls_first())
             })
             .collect();
         let schema = input.get_schema()?;
-        let operator = SortOperator::new(input, sort_keys, schema)?;
+        let operator = SortOperator::new(input, sort_keys, schema)?.with_config(self.sort_config());
         Ok(Box::new(operator))
     }
 
@@ -0,0 +1,500 @@
+//! External merge sort behind [`SortOperator`](crate::executor::operators::SortOperator)
+//!
+//! Rows are buffered with their encoded sort keys ([`sort_key`]) until their estimated size
+//! passes the work memory; the buffer is then sorted and written to disk as a run, and buffering
+//! starts over. At the end the runs and the rows still buffered are merged, at most
+//! [`MERGE_FAN_IN`] sources at a time (earlier runs first, so rows with equal keys keep their
+//! input order). A sort that fits in the work memory never touches the disk.
+//!
+//! Runs are files of a [`FileManager`] over a directory of their own under the temp directory,
+//! written and read a block at a time; the directory is removed once the sorted rows are dropped.
+//! A run holds its records back to back: key length (`u32`), key, row length (`u32`), row
+//! (bincode). Runs need the `native` feature (files); without it a sort keeps every row in
+//! memory whatever the work memory.
+
+pub use crate::common::config::DEFAULT_WORK_MEM;
+use crate::common::key_encoding;
+use crate::common::{Error, Result};
+use crate::executor::governor::row_bytes;
+#[cfg(feature = "native")]
+use crate::storage::file_manager::{FileId, FileManager, BLOCK_SIZE};
+use crate::Row;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+#[cfg(feature = "native")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "native")]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Runs merged at once; more are first merged into longer runs
+pub const MERGE_FAN_IN: usize = 64;
+
+/// Bytes of bookkeeping counted for each buffered row on top of its values and key
+const ROW_OVERHEAD: usize = 64;
+
+/// Key markers: NULLs first, then values, then NULLs last
+const NULL_FIRST: u8 = 0x00;
+const NOT_NULL: u8 = 0x01;
+const NULL_LAST: u8 = 0x02;
+
+/// Sort directories created by this process, for unique names
+#[cfg(feature = "native")]
+static SORT_DIRS: AtomicU64 = AtomicU64::new(0);
+
+/// One column of a sort order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    /// Column name
+    pub column: String,
+    /// Ascending (else descending)
+    pub ascending: bool,
+    /// NULLs before the other values (else after them)
+    pub nulls_first: bool,
+}
+
+impl SortKey {
+    /// `column` in the given direction, NULLs sorting as the largest values
+    pub fn new(column: impl Into<String>, ascending: bool) -> Self {
+        Self {
+            column: column.into(),
+            ascending,
+            nulls_first: !ascending,
+        }
+    }
+
+    /// Puts NULLs first or last
+    pub fn with_nulls_first(mut self, nulls_first: bool) -> Self {
+        self.nulls_first = nulls_first;
+        self
+    }
+}
+
+impl From<(String, bool)> for SortKey {
+    fn from((column, ascending): (String, bool)) -> Self {
+        Self::new(column, ascending)
+    }
+}
+
+/// Memory and temp space of a sort
+#[derive(Debug, Clone)]
+pub struct SortConfig {
+    /// Bytes of rows kept in memory before they are written to a run
+    pub work_mem: usize,
+    /// Directory for the runs
+    #[cfg(feature = "native")]
+    pub temp_dir: PathBuf,
+}
+
+impl Default for SortConfig {
+    fn default() -> Self {
+        Self {
+            work_mem: DEFAULT_WORK_MEM,
+            #[cfg(feature = "native")]
+            temp_dir: std::env::temp_dir(),
+        }
+    }
+}
+
+/// What a sort used
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SortStatistics {
+    /// Most bytes of rows buffered at once
+    pub peak_memory_bytes: usize,
+    /// Runs written, including those of intermediate merges
+    pub runs: usize,
+    /// Bytes written to runs
+    pub spilled_bytes: u64,
+}
+
+/// Encoded sort key of `row`: byte order is the sort order of `keys`, column by column
+/// (values ordered like [`crate::common::types::ColumnValue::total_cmp`]). A missing column is
+/// NULL.
+pub fn sort_key(row: &Row, keys: &[SortKey]) -> Vec<u8> {
+    let mut key = Vec::new();
+    for k in keys {
+        match row
+            .get_value(&k.column)
+            .filter(|v| !v.is_null() && !v.data_type.is_null())
+        {
+            None => key.push(if k.nulls_first { NULL_FIRST } else { NULL_LAST }),
+            Some(value) => {
+                key.push(NOT_NULL);
+                if k.ascending {
+                    key_encoding::encode_value(value, &mut key);
+                } else {
+                    key_encoding::encode_value_descending(value, &mut key);
+                }
+            }
+        }
+    }
+    key
+}
+
+/// Collects rows and returns them sorted, spilling to disk past the work memory
+pub struct ExternalSorter {
+    keys: Vec<SortKey>,
+    #[cfg_attr(not(feature = "native"), allow(dead_code))]
+    config: SortConfig,
+    buffer: Vec<(Vec<u8>, Row)>,
+    buffered_bytes: usize,
+    spill: Option<SpillFiles>,
+    runs: Vec<Run>,
+    statistics: SortStatistics,
+}
+
+impl ExternalSorter {
+    /// Sorter by `keys`
+    pub fn new(keys: Vec<SortKey>, config: SortConfig) -> Self {
+        Self {
+            keys,
+            config,
+            buffer: Vec::new(),
+            buffered_bytes: 0,
+            spill: None,
+            runs: Vec::new(),
+            statistics: SortStatistics::default(),
+        }
+    }
+
+    /// Adds a row.
+    pub fn push(&mut self, row: Row) -> Result<()> {
+        let key = sort_key(&row, &self.keys);
+        self.buffered_bytes += key.len() + row_bytes(&row) as usize + ROW_OVERHEAD;
+        self.buffer.push((key, row));
+        self.statistics.peak_memory_bytes =
+            self.statistics.peak_memory_bytes.max(self.buffered_bytes);
+        #[cfg(feature = "native")]
+        if self.buffered_bytes > self.config.work_mem {
+            self.spill_buffer()?;
+        }
+        Ok(())
+    }
+
+    /// The rows added, in sort order
+    pub fn finish(mut self) -> Result<SortedRows> {
+        sort_entries(&mut self.buffer);
+        #[cfg(feature = "native")]
+        if let Some(spill) = self.spill.take() {
+            return self.merge_spilled(spill);
+        }
+        Ok(SortedRows {
+            merger: Merger::new(vec![Source::Memory(self.buffer.into_iter())], None)?,
+            spill: None,
+            statistics: self.statistics,
+        })
+    }
+
+    /// Merges the runs and the sorted buffer.
+    #[cfg(feature = "native")]
+    fn merge_spilled(mut self, mut spill: SpillFiles) -> Result<SortedRows> {
+        let mut runs = std::mem::take(&mut self.runs);
+        // Leave room for the buffered rows in the last merge.
+        while runs.len() >= MERGE_FAN_IN {
+            let merged: Vec<Run> = runs.drain(..MERGE_FAN_IN).collect();
+            let run = merge_runs(&mut spill, merged)?;
+            self.statistics.runs += 1;
+            self.statistics.spilled_bytes += run.len;
+            runs.insert(0, run);
+        }
+        let mut sources: Vec<Source> = runs.into_iter().map(Source::run).collect();
+        sources.push(Source::Memory(self.buffer.into_iter()));
+        Ok(SortedRows {
+            merger: Merger::new(sources, Some(&mut spill))?,
+            spill: Some(spill),
+            statistics: self.statistics,
+        })
+    }
+
+    /// Sorts the buffer into a new run.
+    #[cfg(feature = "native")]
+    fn spill_buffer(&mut self) -> Result<()> {
+        sort_entries(&mut self.buffer);
+        let spill = match &mut self.spill {
+            Some(spill) => spill,
+            None => self
+                .spill
+                .insert(SpillFiles::create(&self.config.temp_dir)?),
+        };
+        let mut writer = spill.create_run()?;
+        for (key, row) in self.buffer.drain(..) {
+            writer.write(&mut spill.files, &key, &row)?;
+        }
+        let run = writer.finish(&mut spill.files)?;
+        self.statistics.runs += 1;
+        self.statistics.spilled_bytes += run.len;
+        self.runs.push(run);
+        self.buffered_bytes = 0;
+        Ok(())
+    }
+}
+
+/// Sorted rows of an [`ExternalSorter`]; removes its runs when dropped
+pub struct SortedRows {
+    merger: Merger,
+    spill: Option<SpillFiles>,
+    statistics: SortStatistics,
+}
+
+impl SortedRows {
+    /// Next row in sort order
+    pub fn next_row(&mut self) -> Result<Option<Row>> {
+        Ok(self.merger.next(self.spill.as_mut())?.map(|(_, row)| row))
+    }
+
+    /// What the sort used
+    pub fn statistics(&self) -> SortStatistics {
+        self.statistics
+    }
+}
+
+/// Stable sort by key
+fn sort_entries(entries: &mut [(Vec<u8>, Row)]) {
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+}
+
+/// Merges `runs` into one run and deletes them.
+#[cfg(feature = "native")]
+fn merge_runs(spill: &mut SpillFiles, runs: Vec<Run>) -> Result<Run> {
+    let names: Vec<String> = runs.iter().map(|r| r.name.clone()).collect();
+    let mut merger = Merger::new(runs.into_iter().map(Source::run).collect(), Some(spill))?;
+    let mut writer = spill.create_run()?;
+    while let Some((key, row)) = merger.next(Some(spill))? {
+        writer.write(&mut spill.files, &key, &row)?;
+    }
+    let run = writer.finish(&mut spill.files)?;
+    for name in names {
+        spill.files.delete_file(&name)?;
+    }
+    Ok(run)
+}
+
+/// A directory of runs
+#[cfg(feature = "native")]
+struct SpillFiles {
+    files: FileManager,
+    dir: PathBuf,
+    next_run: usize,
+}
+
+#[cfg(feature = "native")]
+impl SpillFiles {
+    fn create(temp_dir: &Path) -> Result<Self> {
+        let dir = temp_dir.join(format!(
+            "rustdb_sort_{}_{}",
+            std::process::id(),
+            SORT_DIRS.fetch_add(1, Ordering::Relaxed)
+        ));
+        Ok(Self {
+            files: FileManager::new(&dir)?,
+            dir,
+            next_run: 0,
+        })
+    }
+
+    fn create_run(&mut self) -> Result<RunWriter> {
+        let name = format!("run_{}.tmp", self.next_run);
+        self.next_run += 1;
+        let file_id = self.files.create_file(&name)?;
+        Ok(RunWriter {
+            file_id,
+            name,
+            block: Vec::with_capacity(2 * BLOCK_SIZE),
+            blocks: 0,
+            len: 0,
+        })
+    }
+}
+
+#[cfg(feature = "native")]
+impl Drop for SpillFiles {
+    fn drop(&mut self) {
+        let _ = self.files.close_all();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// A sorted run on disk
+#[cfg(feature = "native")]
+struct Run {
+    file_id: FileId,
+    name: String,
+    /// Bytes of records
+    len: u64,
+}
+
+#[cfg(feature = "native")]
+struct RunWriter {
+    file_id: FileId,
+    name: String,
+    /// Bytes not written yet
+    block: Vec<u8>,
+    /// Blocks written
+    blocks: u64,
+    len: u64,
+}
+
+#[cfg(feature = "native")]
+impl RunWriter {
+    fn write(&mut self, files: &mut FileManager, key: &[u8], row: &Row) -> Result<()> {
+        let row = crate::common::bincode_io::serialize(row)
+            .map_err(|e| Error::internal(format!("sort run: {e}")))?;
+        for part in [key, &row] {
+            self.block
+                .extend_from_slice(&(part.len() as u32).to_le_bytes());
+            self.block.extend_from_slice(part);
+            self.len += 4 + part.len() as u64;
+        }
+        while self.block.len() >= BLOCK_SIZE {
+            files.write_block(self.file_id, self.blocks, &self.block[..BLOCK_SIZE])?;
+            self.block.drain(..BLOCK_SIZE);
+            self.blocks += 1;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self, files: &mut FileManager) -> Result<Run> {
+        if !self.block.is_empty() {
+            self.block.resize(BLOCK_SIZE, 0);
+            files.write_block(self.file_id, self.blocks, &self.block)?;
+        }
+        Ok(Run {
+            file_id: self.file_id,
+            name: self.name,
+            len: self.len,
+        })
+    }
+}
+
+#[cfg(feature = "native")]
+struct RunReader {
+    file_id: FileId,
+    /// Next block to read
+    block: u64,
+    buffer: Vec<u8>,
+    position: usize,
+    /// Bytes of records not consumed yet
+    remaining: u64,
+}
+
+#[cfg(feature = "native")]
+impl RunReader {
+    fn next(&mut self, spill: &mut SpillFiles) -> Result<Option<(Vec<u8>, Row)>> {
+        let files = &mut spill.files;
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        let key = self.read_part(files)?;
+        let row = self.read_part(files)?;
+        let row = crate::common::bincode_io::deserialize(&row)
+            .map_err(|e| Error::internal(format!("sort run: {e}")))?;
+        Ok(Some((key, row)))
+    }
+
+    fn read_part(&mut self, files: &mut FileManager) -> Result<Vec<u8>> {
+        let len = u32::from_le_bytes(self.read(files, 4)?.try_into().expect("4 bytes")) as usize;
+        self.read(files, len)
+    }
+
+    fn read(&mut self, files: &mut FileManager, len: usize) -> Result<Vec<u8>> {
+        if len as u64 > self.remaining {
+            return Err(Error::internal("sort run ends inside a record"));
+        }
+        while self.buffer.len() - self.position < len {
+            self.buffer.drain(..self.position);
+            self.position = 0;
+            let block = files.read_block(self.file_id, self.block)?;
+            self.buffer.extend_from_slice(&block);
+            self.block += 1;
+        }
+        let bytes = self.buffer[self.position..self.position + len].to_vec();
+        self.position += len;
+        self.remaining -= len as u64;
+        Ok(bytes)
+    }
+}
+
+/// Sorted rows to merge
+enum Source {
+    Memory(std::vec::IntoIter<(Vec<u8>, Row)>),
+    Run(RunReader),
+}
+
+/// Without `native` there are no runs: these types have no values.
+#[cfg(not(feature = "native"))]
+enum SpillFiles {}
+
+#[cfg(not(feature = "native"))]
+enum Run {}
+
+#[cfg(not(feature = "native"))]
+enum RunReader {}
+
+#[cfg(not(feature = "native"))]
+impl RunReader {
+    fn next(&mut self, _spill: &mut SpillFiles) -> Result<Option<(Vec<u8>, Row)>> {
+        match *self {}
+    }
+}
+
+impl Source {
+    #[cfg(feature = "native")]
+    fn run(run: Run) -> Self {
+        Source::Run(RunReader {
+            file_id: run.file_id,
+            block: 0,
+            buffer: Vec::new(),
+            position: 0,
+            remaining: run.len,
+        })
+    }
+
+    fn next(&mut self, spill: Option<&mut SpillFiles>) -> Result<Option<(Vec<u8>, Row)>> {
+        match self {
+            Source::Memory(rows) => Ok(rows.next()),
+            Source::Run(reader) => match spill {
+                Some(spill) => reader.next(spill),
+                None => Err(Error::internal("sort run read without its files")),
+            },
+        }
+    }
+}
+
+/// Merges sources by key; equal keys come from the earlier source first.
+struct Merger {
+    sources: Vec<Source>,
+    /// Key and source of each source's next row
+    heap: BinaryHeap<Reverse<(Vec<u8>, usize)>>,
+    /// Next row of each source
+    heads: Vec<Option<Row>>,
+}
+
+impl Merger {
+    fn new(mut sources: Vec<Source>, mut spill: Option<&mut SpillFiles>) -> Result<Self> {
+        let mut heap = BinaryHeap::with_capacity(sources.len());
+        let mut heads = Vec::with_capacity(sources.len());
+        for (i, source) in sources.iter_mut().enumerate() {
+            let head = source.next(spill.as_deref_mut())?;
+            heads.push(head.map(|(key, row)| {
+                heap.push(Reverse((key, i)));
+                row
+            }));
+        }
+        Ok(Self {
+            sources,
+            heap,
+            heads,
+        })
+    }
+
+    fn next(&mut self, spill: Option<&mut SpillFiles>) -> Result<Option<(Vec<u8>, Row)>> {
+        let Some(Reverse((key, i))) = self.heap.pop() else {
+            return Ok(None);
+        };
+        let row = self.heads[i].take().expect("queued source has a row");
+        if let Some((next_key, next_row)) = self.sources[i].next(spill)? {
+            self.heads[i] = Some(next_row);
+            self.heap.push(Reverse((next_key, i)));
+        }
+        Ok(Some((key, row)))
+    }
+}
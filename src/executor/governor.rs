@@ -0,0 +1,207 @@
+//! Resource governor: per-query limits on the rows a plan reads and the temp space it holds
+//!
+//! Limits apply to the plans executed on the current thread while the guard returned by
+//! [`governor_scope`] lives. The executor then charges every row a table, index or foreign scan
+//! produces to the rows-read budget, and every row a blocking operator buffers (sort, grouping,
+//! `DISTINCT`, set operations, the inner side of a join) to the temp-space budget, by the
+//! [`crate::common::types::DataType::size`] of its values. The first charge past a limit fails the plan with
+//! [`Error::ResourceLimit`]; the scope remembers which limit it was (see
+//! [`GovernorScope::exceeded`]).
+//!
+//! Scopes nest, and a row is charged to every open scope: a statement may run under both its
+//! role's and its tenant's limits, and stops at the first one it goes past.
+//!
+//! Plans built outside a scope are not instrumented, so ungoverned queries pay nothing.
+
+use crate::common::{Error, Result};
+use crate::executor::operators::{Operator, OperatorStatistics};
+use crate::Row;
+use std::cell::RefCell;
+
+/// Limits of one query; `None` is unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryLimits {
+    /// Rows scans may produce
+    pub max_rows_read: Option<u64>,
+    /// Bytes blocking operators may buffer
+    pub max_temp_bytes: Option<u64>,
+}
+
+impl QueryLimits {
+    /// Whether any limit is set
+    pub fn is_limited(&self) -> bool {
+        self.max_rows_read.is_some() || self.max_temp_bytes.is_some()
+    }
+}
+
+/// Resources charged so far in a scope
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryUsage {
+    /// Rows produced by scans
+    pub rows_read: u64,
+    /// Bytes buffered by blocking operators
+    pub temp_bytes: u64,
+}
+
+/// A limit a query went past
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitExceeded {
+    /// [`QueryLimits::max_rows_read`], with its value
+    RowsRead(u64),
+    /// [`QueryLimits::max_temp_bytes`], with its value
+    TempBytes(u64),
+}
+
+struct Budget {
+    limits: QueryLimits,
+    usage: QueryUsage,
+    exceeded: Option<LimitExceeded>,
+}
+
+thread_local! {
+    /// Budgets of the open scopes, outermost first.
+    static BUDGETS: RefCell<Vec<Budget>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Governs the plans executed on the current thread by `limits` until the guard is dropped
+///
+/// Scopes nest: rows are charged to this scope and to every enclosing one.
+pub fn governor_scope(limits: QueryLimits) -> GovernorScope {
+    let depth = BUDGETS.with(|b| {
+        let mut b = b.borrow_mut();
+        b.push(Budget {
+            limits,
+            usage: QueryUsage::default(),
+            exceeded: None,
+        });
+        b.len() - 1
+    });
+    GovernorScope {
+        depth,
+        _not_send: std::marker::PhantomData,
+    }
+}
+
+/// Guard returned by [`governor_scope`]
+pub struct GovernorScope {
+    depth: usize,
+    // The budget is per thread: the guard must be dropped where it was created.
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+impl GovernorScope {
+    /// Resources charged in this scope so far
+    pub fn usage(&self) -> QueryUsage {
+        BUDGETS.with(|b| {
+            b.borrow()
+                .get(self.depth)
+                .map(|b| b.usage)
+                .unwrap_or_default()
+        })
+    }
+
+    /// The limit a plan of this scope went past, if any
+    pub fn exceeded(&self) -> Option<LimitExceeded> {
+        BUDGETS.with(|b| b.borrow().get(self.depth).and_then(|b| b.exceeded))
+    }
+}
+
+impl Drop for GovernorScope {
+    fn drop(&mut self) {
+        // Also closes scopes opened inside this one and not dropped yet.
+        BUDGETS.with(|b| b.borrow_mut().truncate(self.depth));
+    }
+}
+
+/// What the rows passing a [`GovernedOperator`] are charged to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Charge {
+    RowsRead,
+    TempBytes,
+}
+
+/// `input`, charging its rows to `charge` when a scope is active (else `input` unchanged)
+pub(crate) fn govern(input: Box<dyn Operator>, charge: Charge) -> Box<dyn Operator> {
+    if BUDGETS.with(|b| !b.borrow().is_empty()) {
+        Box::new(GovernedOperator { input, charge })
+    } else {
+        input
+    }
+}
+
+fn charge_row(charge: Charge, row: &Row) -> Result<()> {
+    let bytes = match charge {
+        Charge::RowsRead => 0,
+        Charge::TempBytes => row_bytes(row),
+    };
+    BUDGETS.with(|b| {
+        let mut first_exceeded = None;
+        for budget in b.borrow_mut().iter_mut() {
+            let exceeded = match charge {
+                Charge::RowsRead => {
+                    budget.usage.rows_read += 1;
+                    budget
+                        .limits
+                        .max_rows_read
+                        .filter(|&max| budget.usage.rows_read > max)
+                        .map(LimitExceeded::RowsRead)
+                }
+                Charge::TempBytes => {
+                    budget.usage.temp_bytes += bytes;
+                    budget
+                        .limits
+                        .max_temp_bytes
+                        .filter(|&max| budget.usage.temp_bytes > max)
+                        .map(LimitExceeded::TempBytes)
+                }
+            };
+            if let Some(limit) = exceeded {
+                budget.exceeded = Some(limit);
+                first_exceeded.get_or_insert(limit);
+            }
+        }
+        match first_exceeded {
+            Some(limit) => Err(Error::resource_limit(match limit {
+                LimitExceeded::RowsRead(max) => format!("query read more than {max} rows"),
+                LimitExceeded::TempBytes(max) => {
+                    format!("query needs more than {max} bytes of temp space")
+                }
+            })),
+            None => Ok(()),
+        }
+    })
+}
+
+pub(crate) fn row_bytes(row: &Row) -> u64 {
+    row.iter()
+        .map(|(_, value)| value.data_type.size())
+        .sum::<usize>() as u64
+}
+
+/// Passes its input's rows through, charging each to the current budget
+struct GovernedOperator {
+    input: Box<dyn Operator>,
+    charge: Charge,
+}
+
+impl Operator for GovernedOperator {
+    fn next(&mut self) -> Result<Option<Row>> {
+        let row = self.input.next()?;
+        if let Some(row) = &row {
+            charge_row(self.charge, row)?;
+        }
+        Ok(row)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()
+    }
+
+    fn get_schema(&self) -> Result<Vec<String>> {
+        self.input.get_schema()
+    }
+
+    fn get_statistics(&self) -> OperatorStatistics {
+        self.input.get_statistics()
+    }
+}
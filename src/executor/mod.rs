@@ -1,13 +1,21 @@
 //! Query executor for rustdb
 
+pub mod analyze;
+pub mod arena;
+pub mod cardinality;
 pub mod executor;
+pub mod external_sort;
+pub mod governor;
 pub mod operators;
 pub mod result;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "native"))]
 mod tests;
 
+pub use analyze::{analyze_scope, AnalyzeScope};
+pub use cardinality::{cardinality_scope, CardinalityScope, NodeCardinality};
 pub use executor::{QueryExecutor, QueryExecutorConfig};
+pub use governor::{governor_scope, GovernorScope, LimitExceeded, QueryLimits, QueryUsage};
 pub use operators::{
     // New aggregation and sorting operators
     AggregateFunction,
@@ -18,7 +26,6 @@ pub use operators::{
     HashJoinOperator,
     IndexCondition,
     IndexOperator,
-    IndexScanOperator,
     JoinCondition,
     JoinOperator,
     JoinType,
@@ -27,8 +34,11 @@ pub use operators::{
     Operator,
     OperatorStatistics,
     RangeScanOperator,
-    ScanOperatorFactory,
+    ScanSource,
     SortGroupByOperator,
     SortOperator,
-    TableScanOperator,
+};
+#[cfg(feature = "native")]
+pub use operators::{
+    ForeignScanOperator, IndexScanOperator, ScanOperatorFactory, TableScanOperator,
 };
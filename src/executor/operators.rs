@@ -1,27 +1,45 @@
 //! Execution operators for rustdb
 
-use crate::common::types::{ColumnValue, DataType};
+use crate::common::clock::Instant;
+use crate::common::key_encoding;
+use crate::common::types::{ColumnValue, DataType, RowSchema};
 use crate::common::{Error, Result};
+use crate::executor::arena::{with_scratch_arena, QueryArena};
+use crate::executor::external_sort::{ExternalSorter, SortConfig, SortKey, SortedRows};
+use crate::executor::governor::row_bytes;
 use crate::parser::ast::{BinaryOperator, Expression, InList, Literal, UnaryOperator, WhenClause};
 use crate::planner::planner::AggregateFunction as PlanAggregateFunction;
+use crate::planner::planner::ForeignScanNode;
 use crate::planner::planner::ProjectionColumn;
 use crate::planner::planner::SetOpType;
 use crate::planner::planner::SimpleEqualityFilter;
 use crate::planner::{ExecutionPlan, PlanNode};
-use crate::storage::index::BPlusTree;
+#[cfg(feature = "native")]
+use crate::storage::cached_file_manager::PinnedPage;
+#[cfg(feature = "native")]
+use crate::storage::foreign::{open_foreign_scan, ForeignReader, ForeignScanRequest};
+#[cfg(feature = "native")]
 use crate::storage::index::Index;
-use crate::storage::index_registry::IndexRegistry;
+#[cfg(feature = "native")]
+use crate::storage::index_registry::{index_key, IndexRegistry, SecondaryIndex, INDEX_KEY_MAX};
+#[cfg(feature = "native")]
 use crate::storage::page_manager::{
-    PageManager as StoragePageManager, PageManagerConfig, PageManagerMutex,
+    PageManager as StoragePageManager, PageManagerConfig, PageManagerLock,
 };
+#[cfg(feature = "native")]
+use crate::storage::shred::Keyring;
 use crate::storage::tuple::Tuple;
-use crate::{RecordId, Row};
+#[cfg(feature = "native")]
+use crate::RecordId;
+use crate::Row;
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+#[cfg(feature = "native")]
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex, RwLock};
-use std::time::Instant;
+use std::sync::Arc;
+#[cfg(feature = "native")]
+use std::sync::{Mutex, RwLock};
 
 /// Base trait for all operators
 pub trait Operator {
@@ -51,14 +69,26 @@ pub struct OperatorStatistics {
     pub io_operations: usize,
     /// Number of memory operations
     pub memory_operations: usize,
-    /// Memory used in bytes
+    /// Most memory held at once (buffered rows, hash tables, expression temporaries), in bytes
     pub memory_used_bytes: usize,
+    /// Times rows were written to temporary files because they did not fit in memory
+    #[serde(default)]
+    pub spill_count: usize,
+    /// Bytes written to temporary files
+    #[serde(default)]
+    pub spilled_bytes: u64,
 }
 
 pub struct ProjectionOperator {
     input: Box<dyn Operator>,
     columns: Vec<ProjectionColumn>,
     wildcard: bool,
+    /// Output layout shared by every produced row
+    output_schema: Arc<RowSchema>,
+    /// Output position of each entry of `columns`
+    output_positions: Vec<usize>,
+    /// Temporaries of the expressions of the current row
+    arena: QueryArena,
     statistics: OperatorStatistics,
 }
 
@@ -78,7 +108,7 @@ fn eval_value_to_column_value(v: EvalValue) -> ColumnValue {
             }
         }
         EvalValue::Float(f) => ColumnValue::new(DataType::Double(f)),
-        EvalValue::String(s) => ColumnValue::new(DataType::Varchar(s)),
+        EvalValue::String(s) => ColumnValue::new(DataType::Varchar(s.to_string())),
     }
 }
 
@@ -100,17 +130,31 @@ fn narrow_numeric_column_value(cv: ColumnValue) -> ColumnValue {
 /// Used by the SQL engine for `UPDATE … SET col = <expr>` and for `INSERT … VALUES` cells that may
 /// contain arithmetic on literals.
 pub fn eval_scalar_expression(row: &Row, expr: &Expression) -> ColumnValue {
-    let ev = eval_expression(row, expr);
-    narrow_numeric_column_value(eval_value_to_column_value(ev))
+    with_scratch_arena(|arena| {
+        let ev = eval_expression(row, expr, arena);
+        narrow_numeric_column_value(eval_value_to_column_value(ev))
+    })
 }
 
 impl ProjectionOperator {
     pub fn new(input: Box<dyn Operator>, columns: Vec<ProjectionColumn>) -> Result<Self> {
         let wildcard = columns.iter().any(|c| c.name == "*");
+        let names: Vec<&str> = columns
+            .iter()
+            .map(|c| c.alias.as_deref().unwrap_or(&c.name))
+            .collect();
+        let output_schema = Arc::new(RowSchema::new(names.iter().copied()));
+        let output_positions = names
+            .iter()
+            .filter_map(|name| output_schema.position(name))
+            .collect();
         Ok(Self {
             input,
             columns,
             wildcard,
+            output_schema,
+            output_positions,
+            arena: QueryArena::new(),
             statistics: OperatorStatistics::default(),
         })
     }
@@ -118,7 +162,7 @@ impl ProjectionOperator {
 
 impl Operator for ProjectionOperator {
     fn next(&mut self) -> Result<Option<Row>> {
-        let start_time = std::time::Instant::now();
+        let start_time = Instant::now();
         let Some(row) = self.input.next()? else {
             self.statistics.execution_time_ms = start_time.elapsed().as_millis() as u64;
             return Ok(None);
@@ -131,18 +175,19 @@ impl Operator for ProjectionOperator {
             return Ok(Some(row));
         }
 
-        let mut out = Row::new();
+        let mut out = Row::with_schema(self.output_schema.clone());
         out.version = row.version;
         out.created_at = row.created_at;
         out.updated_at = row.updated_at;
-        for c in &self.columns {
-            let out_name = c.alias.clone().unwrap_or_else(|| c.name.clone());
+        for (c, &position) in self.columns.iter().zip(&self.output_positions) {
             let v = match &c.expression {
-                Some(expr) => eval_expression(&row, expr),
+                Some(expr) => eval_expression(&row, expr, &self.arena),
                 None => EvalValue::Null,
             };
-            out.set_value(&out_name, eval_value_to_column_value(v));
+            out.set_value_at(position, eval_value_to_column_value(v));
         }
+        self.statistics.memory_used_bytes = self.arena.peak_bytes();
+        self.arena.reset();
 
         self.statistics.rows_returned += 1;
         self.statistics.execution_time_ms = start_time.elapsed().as_millis() as u64;
@@ -203,28 +248,38 @@ impl GroupByOperator {
         if !self.results.is_empty() {
             return Ok(());
         }
-        use std::collections::HashMap;
-        let mut groups: HashMap<Vec<String>, Vec<Row>> = HashMap::new();
+        // Groups in order of first appearance
+        let mut group_of: HashMap<Vec<String>, usize> = HashMap::new();
+        let mut groups: Vec<Vec<Row>> = Vec::new();
         while let Some(row) = self.input.next()? {
             self.statistics.rows_processed += 1;
             let key: Vec<String> = self
                 .group_columns
                 .iter()
                 .map(|c| {
-                    row.values
-                        .get(c)
+                    row.get_value(c)
                         .map(|cv| format!("{:?}", cv.data_type))
                         .unwrap_or_else(|| "NULL".to_string())
                 })
                 .collect();
-            groups.entry(key).or_default().push(row);
+            self.statistics.memory_used_bytes += row_bytes(&row) as usize;
+            let next = groups.len();
+            let group = *group_of.entry(key).or_insert(next);
+            if group == next {
+                groups.push(Vec::new());
+            }
+            groups[group].push(row);
+        }
+        // Aggregates without GROUP BY have one group, even over no rows.
+        if groups.is_empty() && self.group_columns.is_empty() {
+            groups.push(Vec::new());
         }
 
-        for (key, rows) in groups {
+        for rows in groups {
             let mut out = Row::new();
-            for (i, col) in self.group_columns.iter().enumerate() {
-                let v = key.get(i).cloned().unwrap_or_else(|| "NULL".to_string());
-                out.set_value(col, ColumnValue::new(DataType::Text(v)));
+            for col in &self.group_columns {
+                let v = rows[0].get_value(col).cloned();
+                out.set_value(col, v.unwrap_or_else(ColumnValue::null));
             }
             for agg in &self.aggregates {
                 let out_name = agg
@@ -250,7 +305,7 @@ impl GroupByOperator {
                 } else {
                     let mut c = 0i64;
                     for r in rows {
-                        if let Some(v) = r.values.get(&arg) {
+                        if let Some(v) = r.get_value(&arg) {
                             if !v.is_null {
                                 c += 1;
                             }
@@ -260,31 +315,49 @@ impl GroupByOperator {
                 }
             }
             "SUM" | "AVG" => {
-                let mut sum = 0f64;
-                let mut n = 0f64;
-                for r in rows {
-                    let Some(cv) = r.values.get(&arg) else {
-                        continue;
-                    };
-                    match column_value_to_eval(cv) {
-                        EvalValue::Int(i) => {
-                            sum += i as f64;
-                            n += 1.0;
-                        }
-                        EvalValue::Float(f) => {
-                            sum += f;
-                            n += 1.0;
+                let (sum, int_sum, n) = with_scratch_arena(|arena| {
+                    let mut sum = 0f64;
+                    // Exact sum while every value is an integer
+                    let mut int_sum = Some(0i64);
+                    let mut n = 0f64;
+                    for r in rows {
+                        let Some(cv) = r.get_value(&arg) else {
+                            continue;
+                        };
+                        match column_value_to_eval(cv, arena) {
+                            EvalValue::Int(i) => {
+                                sum += i as f64;
+                                int_sum = int_sum.and_then(|s| s.checked_add(i));
+                                n += 1.0;
+                            }
+                            EvalValue::Float(f) => {
+                                sum += f;
+                                int_sum = None;
+                                n += 1.0;
+                            }
+                            _ => {}
                         }
-                        _ => {}
                     }
+                    (sum, int_sum, n)
+                });
+                match int_sum {
+                    _ if n == 0.0 => ColumnValue::null(),
+                    Some(s) if name == "SUM" => ColumnValue::new(DataType::BigInt(s)),
+                    _ if name == "SUM" => ColumnValue::new(DataType::Double(sum)),
+                    _ => ColumnValue::new(DataType::Double(sum / n)),
                 }
-                if n == 0.0 {
-                    ColumnValue::null()
-                } else if name == "SUM" {
-                    ColumnValue::new(DataType::Double(sum))
+            }
+            "MIN" | "MAX" => {
+                let values = rows
+                    .iter()
+                    .filter_map(|r| r.get_value(&arg))
+                    .filter(|v| !v.is_null());
+                let extreme = if name == "MIN" {
+                    values.min_by(|a, b| a.total_cmp(b))
                 } else {
-                    ColumnValue::new(DataType::Double(sum / n))
-                }
+                    values.max_by(|a, b| a.total_cmp(b))
+                };
+                extreme.cloned().unwrap_or_else(ColumnValue::null)
             }
             _ => ColumnValue::null(),
         }
@@ -321,10 +394,11 @@ impl Operator for GroupByOperator {
 }
 
 /// Table scan operator — reads heap records via [`StoragePageManager::select`] and deserializes [`Tuple`] to [`Row`].
+#[cfg(feature = "native")]
 pub struct TableScanOperator {
     #[allow(dead_code)]
     table_name: String,
-    page_manager: Arc<PageManagerMutex>,
+    page_manager: Arc<PageManagerLock>,
     filter_condition: Option<String>,
     pushdown_equality: Option<SimpleEqualityFilter>,
     /// Projection column names from the plan (`*` = all tuple columns).
@@ -333,15 +407,21 @@ pub struct TableScanOperator {
     // Streaming scan state (avoid materializing all rows).
     page_ids: Option<Vec<u64>>,
     page_pos: usize,
-    page_records: Vec<(u32, Vec<u8>)>,
+    /// Current page, pinned so records are decoded straight from the buffer pool.
+    page: Option<PinnedPage>,
     record_pos: usize,
+    /// Schema shared by the produced rows
+    row_layout: Option<Arc<RowSchema>>,
+    /// Opens sealed tuples
+    keyring: Arc<Keyring>,
 }
 
+#[cfg(feature = "native")]
 impl TableScanOperator {
     /// Create new table scan operator
     pub fn new(
         table_name: String,
-        page_manager: Arc<PageManagerMutex>,
+        page_manager: Arc<PageManagerLock>,
         filter_condition: Option<String>,
         pushdown_equality: Option<SimpleEqualityFilter>,
         schema: Vec<String>,
@@ -355,11 +435,19 @@ impl TableScanOperator {
             statistics: OperatorStatistics::default(),
             page_ids: None,
             page_pos: 0,
-            page_records: Vec::new(),
+            page: None,
             record_pos: 0,
+            row_layout: None,
+            keyring: Arc::default(),
         })
     }
 
+    /// Opens sealed tuples with `keyring` (see [`crate::storage::shred`])
+    pub fn with_keyring(mut self, keyring: Arc<Keyring>) -> Self {
+        self.keyring = keyring;
+        self
+    }
+
     fn ensure_initialized(&mut self) -> Result<()> {
         if self.page_ids.is_some() {
             return Ok(());
@@ -369,12 +457,12 @@ impl TableScanOperator {
             table = %self.table_name
         );
         let _g = span.enter();
-        let mut pm = self.page_manager.lock();
+        let pm = self.page_manager.read();
         let ids = pm.all_page_ids()?;
         self.statistics.io_operations = self.statistics.io_operations.saturating_add(1);
         self.page_ids = Some(ids);
         self.page_pos = 0;
-        self.page_records.clear();
+        self.page = None;
         self.record_pos = 0;
         Ok(())
     }
@@ -396,55 +484,66 @@ impl TableScanOperator {
         );
         let _g = span.enter();
 
-        let mut pm = self.page_manager.lock();
-        self.page_records = pm.records_from_page(page_id)?;
+        let pm = self.page_manager.read();
+        self.page = Some(pm.pin_page(page_id)?);
         self.record_pos = 0;
         self.statistics.io_operations = self.statistics.io_operations.saturating_add(1);
         Ok(true)
     }
 
-    fn tuple_to_row(tuple: &Tuple, projection: &[String]) -> Row {
+    /// Converts a heap tuple to a [`Row`] of the `projection` columns (`*` = all tuple columns).
+    ///
+    /// `layout` caches the output schema: it is reused while tuples have the same columns, so
+    /// rows of a scan share one schema.
+    fn tuple_to_row(
+        tuple: &Tuple,
+        projection: &[String],
+        layout: &mut Option<Arc<RowSchema>>,
+    ) -> Row {
         let wildcard = projection.is_empty() || projection.iter().any(|c| c == "*");
-        let cap = if wildcard {
-            tuple.values.len().saturating_add(1)
-        } else {
-            projection.len().saturating_add(1)
-        };
+        let fits = layout
+            .as_ref()
+            .is_some_and(|schema| !wildcard || Self::layout_has_tuple_columns(schema, tuple));
+        if !fits {
+            let schema = if wildcard {
+                let mut keys: Vec<&str> = tuple.values.keys().map(String::as_str).collect();
+                keys.sort_unstable();
+                let implicit_id = (!tuple.values.contains_key("id")).then_some("id");
+                RowSchema::new(implicit_id.into_iter().chain(keys))
+            } else {
+                RowSchema::new(projection.iter().map(String::as_str))
+            };
+            *layout = Some(Arc::new(schema));
+        }
+        let schema = layout.as_ref().expect("layout set above");
 
         // Avoid per-column timestamp updates in the scan hot-path.
-        let mut row = Row::with_capacity(cap);
+        let mut row = Row::with_schema(schema.clone());
         row.version = tuple.version;
         row.created_at = tuple.created_at;
         row.updated_at = tuple.updated_at;
-
-        if wildcard {
-            if !tuple.values.contains_key("id") {
-                row.set_value_fast("id", ColumnValue::new(DataType::BigInt(tuple.id as i64)));
-            }
-            let mut keys: Vec<_> = tuple.values.keys().cloned().collect();
-            keys.sort();
-            for k in keys {
-                if let Some(v) = tuple.values.get(&k) {
-                    row.set_value_fast(&k, v.clone());
-                }
-            }
-        } else {
-            for p in projection {
-                if p == "id" {
-                    let cv = tuple
-                        .values
-                        .get("id")
-                        .cloned()
-                        .unwrap_or_else(|| ColumnValue::new(DataType::BigInt(tuple.id as i64)));
-                    row.set_value_fast("id", cv);
-                } else if let Some(v) = tuple.values.get(p) {
-                    row.set_value_fast(p, v.clone());
-                }
-            }
+        for (position, name) in schema.columns().iter().enumerate() {
+            let value = match tuple.values.get(name) {
+                Some(v) => v.clone(),
+                // Tuple id is implicit; may or may not be stored in tuple.values.
+                None if name == "id" => ColumnValue::new(DataType::BigInt(tuple.id as i64)),
+                None => continue,
+            };
+            row.set_value_at(position, value);
         }
         row
     }
 
+    /// Whether `schema` lists exactly the columns of `tuple` (plus the implicit `id`)
+    fn layout_has_tuple_columns(schema: &RowSchema, tuple: &Tuple) -> bool {
+        let implicit_id = !tuple.values.contains_key("id");
+        schema.len() == tuple.values.len() + implicit_id as usize
+            && schema
+                .columns()
+                .iter()
+                .all(|c| tuple.values.contains_key(c) || (implicit_id && c == "id"))
+    }
+
     fn tuple_matches_simple_equality(tuple: &Tuple, eq: &SimpleEqualityFilter) -> bool {
         let col = eq.column.as_str();
         let cv = if col == "id" {
@@ -489,25 +588,31 @@ impl TableScanOperator {
     }
 }
 
+#[cfg(feature = "native")]
 impl Operator for TableScanOperator {
     fn next(&mut self) -> Result<Option<Row>> {
-        let start_time = std::time::Instant::now();
+        let start_time = Instant::now();
         self.ensure_initialized()?;
 
         loop {
             // Need a page loaded?
-            if self.record_pos >= self.page_records.len() {
+            let slot_count = self.page.as_ref().map_or(0, |p| p.slot_count());
+            if self.record_pos >= slot_count {
                 if !self.load_next_page()? {
                     self.statistics.execution_time_ms = start_time.elapsed().as_millis() as u64;
                     return Ok(None);
                 }
+                continue;
             }
 
-            while self.record_pos < self.page_records.len() {
-                let (_off, data) = &self.page_records[self.record_pos];
+            while self.record_pos < slot_count {
+                let index = self.record_pos;
                 self.record_pos += 1;
 
-                let tuple = match Tuple::from_bytes(data) {
+                let Some((_off, data)) = self.page.as_ref().and_then(|p| p.record(index)) else {
+                    continue;
+                };
+                let tuple = match self.keyring.decode(data) {
                     Ok(t) => t,
                     Err(_) => continue,
                 };
@@ -521,7 +626,7 @@ impl Operator for TableScanOperator {
                     }
                 }
 
-                let row = Self::tuple_to_row(&tuple, &self.schema);
+                let row = Self::tuple_to_row(&tuple, &self.schema, &mut self.row_layout);
                 self.statistics.rows_processed += 1;
                 if self.apply_filter(&row) {
                     self.statistics.rows_returned += 1;
@@ -535,7 +640,7 @@ impl Operator for TableScanOperator {
     fn reset(&mut self) -> Result<()> {
         self.page_ids = None;
         self.page_pos = 0;
-        self.page_records.clear();
+        self.page = None;
         self.record_pos = 0;
         self.statistics = OperatorStatistics::default();
         Ok(())
@@ -556,16 +661,115 @@ impl Operator for TableScanOperator {
     }
 }
 
+/// Foreign table scan operator: streams rows from the table's adapter
+#[cfg(feature = "native")]
+pub struct ForeignScanOperator {
+    scan: ForeignScanNode,
+    /// Base directory for relative `path` options
+    base_dir: Option<PathBuf>,
+    /// Indexes of `scan.projection` into `scan.columns`
+    projection: Vec<usize>,
+    /// Opened on the first `next` (and again after `reset`)
+    reader: Option<Box<dyn ForeignReader>>,
+    statistics: OperatorStatistics,
+}
+
+#[cfg(feature = "native")]
+impl ForeignScanOperator {
+    /// Create new foreign scan operator
+    pub fn new(scan: ForeignScanNode, base_dir: Option<PathBuf>) -> Result<Self> {
+        let projection = scan
+            .projection
+            .iter()
+            .map(|name| {
+                scan.columns
+                    .iter()
+                    .position(|(c, _)| c == name)
+                    .ok_or_else(|| {
+                        Error::query_execution(format!(
+                            "foreign table {} has no column {}",
+                            scan.table_name, name
+                        ))
+                    })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            scan,
+            base_dir,
+            projection,
+            reader: None,
+            statistics: OperatorStatistics::default(),
+        })
+    }
+}
+
+#[cfg(feature = "native")]
+impl Operator for ForeignScanOperator {
+    fn next(&mut self) -> Result<Option<Row>> {
+        let start_time = Instant::now();
+        if self.reader.is_none() {
+            let _g = tracing::info_span!(
+                "operator.foreign_scan.open",
+                table = %self.scan.table_name,
+                adapter = %self.scan.table.adapter
+            )
+            .entered();
+            self.reader = Some(open_foreign_scan(
+                &self.scan.table,
+                self.base_dir.as_deref(),
+                ForeignScanRequest {
+                    table_name: &self.scan.table_name,
+                    columns: &self.scan.columns,
+                    projection: self.projection.clone(),
+                    limit: self.scan.limit,
+                    filter: self.scan.filter.as_deref(),
+                },
+            )?);
+            self.statistics.io_operations = self.statistics.io_operations.saturating_add(1);
+        }
+        let Some(reader) = self.reader.as_mut() else {
+            return Ok(None);
+        };
+        let values = reader.next_row()?;
+        self.statistics.execution_time_ms += start_time.elapsed().as_millis() as u64;
+        let Some(values) = values else {
+            return Ok(None);
+        };
+        let mut row = Row::with_capacity(values.len());
+        for (name, value) in self.scan.projection.iter().zip(values) {
+            row.set_value_fast(name, value);
+        }
+        self.statistics.rows_processed += 1;
+        self.statistics.rows_returned += 1;
+        Ok(Some(row))
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.reader = None;
+        self.statistics = OperatorStatistics::default();
+        Ok(())
+    }
+
+    fn get_schema(&self) -> Result<Vec<String>> {
+        Ok(self.scan.projection.clone())
+    }
+
+    fn get_statistics(&self) -> OperatorStatistics {
+        self.statistics.clone()
+    }
+}
+
 /// Index scan operator
+#[cfg(feature = "native")]
 pub struct IndexScanOperator {
     /// Table name
     table_name: String,
     /// Index name
     index_name: String,
-    /// Index for scanning: key = column value, value = list of record IDs
-    index: Arc<Mutex<BPlusTree<String, Vec<RecordId>>>>,
+    /// Index for scanning (B+ tree or hash): key = column value, value = list of record IDs
+    index: Arc<Mutex<SecondaryIndex>>,
     /// Page manager
-    page_manager: Arc<PageManagerMutex>,
+    page_manager: Arc<PageManagerLock>,
     /// Search conditions
     search_conditions: Vec<IndexCondition>,
     /// Current position in index result
@@ -574,6 +778,10 @@ pub struct IndexScanOperator {
     index_result: Vec<RecordId>,
     /// Table schema
     schema: Vec<String>,
+    /// Schema shared by the produced rows
+    row_layout: Option<Arc<RowSchema>>,
+    /// Opens sealed tuples
+    keyring: Arc<Keyring>,
     /// Statistics
     statistics: OperatorStatistics,
 }
@@ -601,13 +809,14 @@ pub enum IndexOperator {
     In,
 }
 
+#[cfg(feature = "native")]
 impl IndexScanOperator {
     /// Create new index scan operator
     pub fn new(
         table_name: String,
         index_name: String,
-        index: Arc<Mutex<BPlusTree<String, Vec<RecordId>>>>,
-        page_manager: Arc<PageManagerMutex>,
+        index: Arc<Mutex<SecondaryIndex>>,
+        page_manager: Arc<PageManagerLock>,
         search_conditions: Vec<IndexCondition>,
         schema: Vec<String>,
     ) -> Result<Self> {
@@ -621,6 +830,8 @@ impl IndexScanOperator {
             index_result: Vec::new(),
             schema,
             statistics: OperatorStatistics::default(),
+            row_layout: None,
+            keyring: Arc::default(),
         };
 
         // Perform index search
@@ -629,6 +840,12 @@ impl IndexScanOperator {
         Ok(operator)
     }
 
+    /// Opens sealed tuples with `keyring` (see [`crate::storage::shred`])
+    pub fn with_keyring(mut self, keyring: Arc<Keyring>) -> Self {
+        self.keyring = keyring;
+        self
+    }
+
     /// Perform index search using real B+ tree
     fn perform_index_search(&mut self) -> Result<()> {
         let index = self
@@ -637,8 +854,8 @@ impl IndexScanOperator {
             .map_err(|_| Error::lock("index lock poisoned"))?;
 
         if self.search_conditions.is_empty() {
-            // No conditions: range search over all keys (use min/max string bounds)
-            let results = index.range_search(&String::new(), &"\u{10FFFF}".to_string())?;
+            // No conditions: range search over all keys
+            let results = index.range_search(&String::new(), &INDEX_KEY_MAX.to_string())?;
             self.index_result = results.into_iter().flat_map(|(_, ids)| ids).collect();
         } else {
             let all_eq = self
@@ -646,18 +863,13 @@ impl IndexScanOperator {
                 .iter()
                 .all(|c| matches!(c.operator, IndexOperator::Equal));
             if all_eq && !self.search_conditions.is_empty() {
-                let key = self
-                    .search_conditions
-                    .iter()
-                    .map(|c| c.value.as_str())
-                    .collect::<Vec<_>>()
-                    .join("\0");
+                let key = index_key(self.search_conditions.iter().map(|c| c.value.as_str()));
                 if let Some(ids) = index.search(&key)? {
                     self.index_result = ids;
                 }
             } else {
                 let cond = &self.search_conditions[0];
-                let key = cond.value.clone();
+                let key = index_key([cond.value.as_str()]);
                 match cond.operator {
                     IndexOperator::Equal => {
                         if let Some(ids) = index.search(&key)? {
@@ -669,9 +881,13 @@ impl IndexScanOperator {
                     | IndexOperator::GreaterThan
                     | IndexOperator::GreaterThanOrEqual
                     | IndexOperator::Between => {
-                        let (start, end) = self.range_bounds_from_conditions();
+                        let (start, end, excluded) = self.range_bounds_from_conditions();
                         let results = index.range_search(&start, &end)?;
-                        self.index_result = results.into_iter().flat_map(|(_, ids)| ids).collect();
+                        self.index_result = results
+                            .into_iter()
+                            .filter(|(k, _)| !excluded.iter().any(|x| k.starts_with(x.as_str())))
+                            .flat_map(|(_, ids)| ids)
+                            .collect();
                     }
                     IndexOperator::In => {
                         let results = index.range_search(&key.clone(), &key)?;
@@ -685,43 +901,70 @@ impl IndexScanOperator {
         Ok(())
     }
 
-    fn range_bounds_from_conditions(&self) -> (String, String) {
-        let mut start = String::new();
-        let mut end = "\u{10FFFF}".to_string();
+    /// Inclusive key bounds of the range conditions, and the keys of exclusive bounds, whose
+    /// entries (and those of longer keys starting with them) are not in the range. A range
+    /// bounded on one side stays within the kind of its value (numbers, strings, ...), so it
+    /// never reaches NULL keys.
+    fn range_bounds_from_conditions(&self) -> (String, String, Vec<String>) {
+        let mut start = None;
+        let mut end = None;
+        let mut excluded = Vec::new();
         for cond in &self.search_conditions {
+            let key = index_key([cond.value.as_str()]);
             match cond.operator {
-                IndexOperator::GreaterThan | IndexOperator::GreaterThanOrEqual => {
-                    start = cond.value.clone();
+                IndexOperator::GreaterThan => {
+                    excluded.push(key.clone());
+                    start = Some(key);
                 }
-                IndexOperator::LessThan | IndexOperator::LessThanOrEqual => {
-                    end = cond.value.clone();
+                IndexOperator::GreaterThanOrEqual => start = Some(key),
+                IndexOperator::LessThan => {
+                    excluded.push(key.clone());
+                    end = Some(format!("{key}{INDEX_KEY_MAX}"));
                 }
+                IndexOperator::LessThanOrEqual => end = Some(format!("{key}{INDEX_KEY_MAX}")),
                 _ => {}
             }
         }
-        (start, end)
+        // The first character of a key is the kind of its first value.
+        let kind = start
+            .as_deref()
+            .or(end.as_deref())
+            .and_then(|key| key.chars().next());
+        let start = start.unwrap_or_else(|| kind.map(String::from).unwrap_or_default());
+        let end = end.unwrap_or_else(|| match kind {
+            Some(kind) => format!("{kind}{INDEX_KEY_MAX}"),
+            None => INDEX_KEY_MAX.to_string(),
+        });
+        (start, end, excluded)
     }
 
     /// Load record by ID from PageManager
     fn load_record(&mut self, record_id: RecordId) -> Result<Option<Row>> {
-        let mut pm = self.page_manager.lock();
+        let pm = self.page_manager.read();
         let data = pm.get_record(record_id)?;
         self.statistics.io_operations += 1;
 
         let row = match data {
-            Some(bytes) => Self::bytes_to_row(&bytes, &self.schema),
+            Some(bytes) => {
+                Self::bytes_to_row(&self.keyring, &bytes, &self.schema, &mut self.row_layout)
+            }
             None => None,
         };
         Ok(row)
     }
 
     /// Convert heap tuple bytes to a projection [`Row`] (same encoding as [`TableScanOperator`]).
-    fn bytes_to_row(bytes: &[u8], schema: &[String]) -> Option<Row> {
-        let tuple = Tuple::from_bytes(bytes).ok()?;
+    fn bytes_to_row(
+        keyring: &Keyring,
+        bytes: &[u8],
+        schema: &[String],
+        layout: &mut Option<Arc<RowSchema>>,
+    ) -> Option<Row> {
+        let tuple = keyring.decode(bytes).ok()?;
         if tuple.is_deleted {
             return None;
         }
-        Some(TableScanOperator::tuple_to_row(&tuple, schema))
+        Some(TableScanOperator::tuple_to_row(&tuple, schema, layout))
     }
 
     /// Apply search conditions to row
@@ -730,9 +973,10 @@ impl IndexScanOperator {
     }
 }
 
+#[cfg(feature = "native")]
 impl Operator for IndexScanOperator {
     fn next(&mut self) -> Result<Option<Row>> {
-        let start_time = std::time::Instant::now();
+        let start_time = Instant::now();
 
         while self.current_position < self.index_result.len() {
             let record_id = self.index_result[self.current_position];
@@ -815,7 +1059,7 @@ impl RangeScanOperator {
 
 impl Operator for RangeScanOperator {
     fn next(&mut self) -> Result<Option<Row>> {
-        let start_time = std::time::Instant::now();
+        let start_time = Instant::now();
 
         while let Some(row) = self.base_operator.next()? {
             self.statistics.rows_processed += 1;
@@ -859,17 +1103,23 @@ pub struct ConditionalScanOperator {
     predicate: Option<Expression>,
     /// Structured equality when available (SELECT WHERE column = literal)
     equality: Option<SimpleEqualityFilter>,
+    /// Literal of `equality`, converted once
+    equality_value: Option<ColumnValue>,
+    /// Temporaries of the predicate of the current row
+    arena: QueryArena,
     /// Statistics
     statistics: OperatorStatistics,
 }
 
+/// Intermediate expression value; strings borrow from the row, the expression or the
+/// [`QueryArena`] of the evaluating operator.
 #[derive(Debug, Clone, PartialEq)]
-enum EvalValue {
+enum EvalValue<'a> {
     Null,
     Bool(TriBool),
     Int(i64),
     Float(f64),
-    String(String),
+    String(&'a str),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -905,7 +1155,7 @@ fn tri_or(a: TriBool, b: TriBool) -> TriBool {
     }
 }
 
-fn column_value_to_eval(cv: &ColumnValue) -> EvalValue {
+fn column_value_to_eval<'a>(cv: &'a ColumnValue, arena: &'a QueryArena) -> EvalValue<'a> {
     if cv.is_null {
         return EvalValue::Null;
     }
@@ -923,61 +1173,65 @@ fn column_value_to_eval(cv: &ColumnValue) -> EvalValue {
         | DataType::Text(s)
         | DataType::Date(s)
         | DataType::Time(s)
-        | DataType::Timestamp(s) => EvalValue::String(s.clone()),
-        DataType::Blob(b) => EvalValue::String(format!("{:?}", b)),
+        | DataType::Timestamp(s) => EvalValue::String(s),
+        DataType::Blob(b) => {
+            EvalValue::String(bumpalo::format!(in arena.bump(), "{:?}", b).into_bump_str())
+        }
     }
 }
 
-fn literal_to_eval(l: &Literal) -> EvalValue {
+fn literal_to_eval(l: &Literal) -> EvalValue<'_> {
     match l {
         Literal::Null => EvalValue::Null,
         Literal::Boolean(b) => EvalValue::Bool(if *b { TriBool::True } else { TriBool::False }),
         Literal::Integer(n) => EvalValue::Int(*n),
         Literal::Float(f) => EvalValue::Float(*f),
-        Literal::String(s) => EvalValue::String(s.clone()),
+        Literal::String(s) => EvalValue::String(s),
     }
 }
 
-fn eval_expression(row: &Row, expr: &Expression) -> EvalValue {
+fn eval_expression<'a>(row: &'a Row, expr: &'a Expression, arena: &'a QueryArena) -> EvalValue<'a> {
     match expr {
         Expression::Literal(l) => literal_to_eval(l),
         Expression::Identifier(name) => row
-            .values
-            .get(name)
-            .map(column_value_to_eval)
+            .get_value(name)
+            .map(|cv| column_value_to_eval(cv, arena))
             .unwrap_or(EvalValue::Null),
         Expression::QualifiedIdentifier { column, .. } => row
-            .values
-            .get(column)
-            .map(column_value_to_eval)
+            .get_value(column)
+            .map(|cv| column_value_to_eval(cv, arena))
             .unwrap_or(EvalValue::Null),
         Expression::Function { name, args } => {
-            let n = name.to_uppercase();
-            let arg = args
-                .first()
-                .map(|e| match e {
-                    Expression::Identifier(s) => s.clone(),
-                    Expression::QualifiedIdentifier { column, .. } => column.clone(),
-                    _ => "*".to_string(),
-                })
-                .unwrap_or_else(|| "*".to_string());
-            let key = format!("{n}({arg})");
-            row.values
-                .get(&key)
-                .map(column_value_to_eval)
-                .unwrap_or(EvalValue::Null)
+            let arg = match args.first() {
+                Some(Expression::Identifier(s)) => s.as_str(),
+                Some(Expression::QualifiedIdentifier { column, .. }) => column.as_str(),
+                _ => "*",
+            };
+            // Aggregates are computed below the projection under their `NAME(arg)` key.
+            let mut key = bumpalo::collections::String::with_capacity_in(
+                name.len() + arg.len() + 2,
+                arena.bump(),
+            );
+            key.extend(name.chars().flat_map(char::to_uppercase));
+            key.push('(');
+            key.push_str(arg);
+            key.push(')');
+            match row.get_value(&key) {
+                Some(cv) => column_value_to_eval(cv, arena),
+                None => eval_scalar_function(row, name, args, arena),
+            }
         }
         Expression::UnaryOp {
             op: UnaryOperator::Not,
             expr,
-        } => match eval_expression(row, expr) {
+        } => match eval_expression(row, expr, arena) {
             EvalValue::Bool(b) => EvalValue::Bool(tri_not(b)),
             EvalValue::Null => EvalValue::Bool(TriBool::Unknown),
             _ => EvalValue::Bool(TriBool::Unknown),
         },
         Expression::BinaryOp { left, op, right } => {
-            let lv = eval_expression(row, left);
-            let rv = eval_expression(row, right);
+            let lv = eval_expression(row, left, arena);
+            let rv = eval_expression(row, right, arena);
             match op {
                 BinaryOperator::And => match (lv, rv) {
                     (EvalValue::Bool(a), EvalValue::Bool(b)) => EvalValue::Bool(tri_and(a, b)),
@@ -1060,7 +1314,7 @@ fn eval_expression(row: &Row, expr: &Expression) -> EvalValue {
             }
         }
         Expression::IsNull { expr, negated } => {
-            let v = eval_expression(row, expr);
+            let v = eval_expression(row, expr, arena);
             let is_null = matches!(v, EvalValue::Null);
             let out = if *negated { !is_null } else { is_null };
             EvalValue::Bool(if out { TriBool::True } else { TriBool::False })
@@ -1070,19 +1324,19 @@ fn eval_expression(row: &Row, expr: &Expression) -> EvalValue {
             pattern,
             negated,
         } => {
-            let v = eval_expression(row, expr);
-            let p = eval_expression(row, pattern);
+            let v = eval_expression(row, expr, arena);
+            let p = eval_expression(row, pattern, arena);
             let (EvalValue::String(s), EvalValue::String(pat)) = (v, p) else {
                 return EvalValue::Bool(TriBool::Unknown);
             };
-            let m = like_match(&s, &pat);
+            let m = like_match(s, pat);
             let out = if *negated { !m } else { m };
             EvalValue::Bool(if out { TriBool::True } else { TriBool::False })
         }
         Expression::Between { expr, low, high } => {
-            let v = eval_expression(row, expr);
-            let lo = eval_expression(row, low);
-            let hi = eval_expression(row, high);
+            let v = eval_expression(row, expr, arena);
+            let lo = eval_expression(row, low, arena);
+            let hi = eval_expression(row, high, arena);
             if matches!(v, EvalValue::Null)
                 || matches!(lo, EvalValue::Null)
                 || matches!(hi, EvalValue::Null)
@@ -1104,7 +1358,7 @@ fn eval_expression(row: &Row, expr: &Expression) -> EvalValue {
             })
         }
         Expression::In { expr, list } => {
-            let v = eval_expression(row, expr);
+            let v = eval_expression(row, expr, arena);
             if matches!(v, EvalValue::Null) {
                 return EvalValue::Bool(TriBool::Unknown);
             }
@@ -1112,7 +1366,7 @@ fn eval_expression(row: &Row, expr: &Expression) -> EvalValue {
                 InList::Values(vals) => {
                     let mut has_null = false;
                     for e in vals {
-                        let ev = eval_expression(row, e);
+                        let ev = eval_expression(row, e, arena);
                         if matches!(ev, EvalValue::Null) {
                             has_null = true;
                             continue;
@@ -1137,22 +1391,24 @@ fn eval_expression(row: &Row, expr: &Expression) -> EvalValue {
         } => {
             // Searched CASE when expr is None; Simple CASE when expr is Some.
             if let Some(e) = expr {
-                let base = eval_expression(row, e);
+                let base = eval_expression(row, e, arena);
                 for wc in when_clauses {
-                    let wv = eval_expression(row, &wc.condition);
+                    let wv = eval_expression(row, &wc.condition, arena);
                     if compare_eval(&base, &wv) == Some(std::cmp::Ordering::Equal) {
-                        return eval_expression(row, &wc.result);
+                        return eval_expression(row, &wc.result, arena);
                     }
                 }
             } else {
                 for wc in when_clauses {
-                    if let EvalValue::Bool(TriBool::True) = eval_expression(row, &wc.condition) {
-                        return eval_expression(row, &wc.result);
+                    if let EvalValue::Bool(TriBool::True) =
+                        eval_expression(row, &wc.condition, arena)
+                    {
+                        return eval_expression(row, &wc.result, arena);
                     }
                 }
             }
             if let Some(e) = else_clause {
-                eval_expression(row, e)
+                eval_expression(row, e, arena)
             } else {
                 EvalValue::Null
             }
@@ -1161,12 +1417,40 @@ fn eval_expression(row: &Row, expr: &Expression) -> EvalValue {
     }
 }
 
-fn eval_predicate(row: &Row, expr: &Expression) -> bool {
-    matches!(eval_expression(row, expr), EvalValue::Bool(TriBool::True))
+/// Scalar functions of one argument (`LOWER`, `UPPER`, `ABS`); NULL for others and for NULL or
+/// mistyped arguments.
+fn eval_scalar_function<'a>(
+    row: &'a Row,
+    name: &str,
+    args: &'a [Expression],
+    arena: &'a QueryArena,
+) -> EvalValue<'a> {
+    let [arg] = args else {
+        return EvalValue::Null;
+    };
+    let v = eval_expression(row, arg, arena);
+    match (name.to_ascii_uppercase().as_str(), v) {
+        ("LOWER", EvalValue::String(s)) => {
+            EvalValue::String(arena.bump().alloc_str(&s.to_lowercase()))
+        }
+        ("UPPER", EvalValue::String(s)) => {
+            EvalValue::String(arena.bump().alloc_str(&s.to_uppercase()))
+        }
+        ("ABS", EvalValue::Int(n)) => n.checked_abs().map_or(EvalValue::Null, EvalValue::Int),
+        ("ABS", EvalValue::Float(f)) => EvalValue::Float(f.abs()),
+        _ => EvalValue::Null,
+    }
+}
+
+fn eval_predicate(row: &Row, expr: &Expression, arena: &QueryArena) -> bool {
+    matches!(
+        eval_expression(row, expr, arena),
+        EvalValue::Bool(TriBool::True)
+    )
 }
 
 pub(crate) fn eval_predicate_expression(row: &Row, expr: &Expression) -> bool {
-    eval_predicate(row, expr)
+    with_scratch_arena(|arena| eval_predicate(row, expr, arena))
 }
 
 fn compare_eval(a: &EvalValue, b: &EvalValue) -> Option<std::cmp::Ordering> {
@@ -1236,23 +1520,30 @@ impl ConditionalScanOperator {
         predicate: Option<Expression>,
         equality: Option<SimpleEqualityFilter>,
     ) -> Result<Self> {
+        let equality_value = equality
+            .as_ref()
+            .map(|eq| filter_literal_to_column_value(&eq.literal));
         Ok(Self {
             base_operator,
             condition,
             predicate,
             equality,
+            equality_value,
+            arena: QueryArena::new(),
             statistics: OperatorStatistics::default(),
         })
     }
 
     /// Evaluate condition for row
-    fn evaluate_condition(&self, row: &Row) -> bool {
+    fn evaluate_condition(&mut self, row: &Row) -> bool {
         if let Some(ref p) = self.predicate {
-            return eval_predicate(row, p);
+            let matched = eval_predicate(row, p, &self.arena);
+            self.statistics.memory_used_bytes = self.arena.peak_bytes();
+            self.arena.reset();
+            return matched;
         }
-        if let Some(ref eq) = self.equality {
-            let expected = filter_literal_to_column_value(&eq.literal);
-            let Some(cv) = row.values.get(&eq.column) else {
+        if let (Some(eq), Some(expected)) = (&self.equality, &self.equality_value) {
+            let Some(cv) = row.get_value(&eq.column) else {
                 return false;
             };
             return cv.data_type == expected.data_type && cv.is_null == expected.is_null;
@@ -1264,7 +1555,7 @@ impl ConditionalScanOperator {
 
 impl Operator for ConditionalScanOperator {
     fn next(&mut self) -> Result<Option<Row>> {
-        let start_time = std::time::Instant::now();
+        let start_time = Instant::now();
 
         while let Some(row) = self.base_operator.next()? {
             self.statistics.rows_processed += 1;
@@ -1295,6 +1586,13 @@ impl Operator for ConditionalScanOperator {
     }
 }
 
+/// Identity of a row for DISTINCT and set operations: its columns and values in name order.
+fn row_identity_key(row: &Row) -> String {
+    let mut entries: Vec<(&str, &ColumnValue)> = row.iter().collect();
+    entries.sort_unstable_by_key(|(name, _)| *name);
+    format!("{:?}", entries)
+}
+
 /// DISTINCT operator: de-duplicates rows by their projected values.
 pub struct DistinctOperator {
     input: Box<dyn Operator>,
@@ -1321,7 +1619,7 @@ impl Operator for DistinctOperator {
                 return Ok(None);
             };
             self.statistics.rows_processed += 1;
-            let key = format!("{:?}", row.values);
+            let key = row_identity_key(&row);
             if self.seen.insert(key) {
                 self.statistics.rows_returned += 1;
                 self.statistics.execution_time_ms = start_time.elapsed().as_millis() as u64;
@@ -1391,7 +1689,7 @@ impl SetOpOperator {
             rrows.push(r);
         }
 
-        let key = |row: &Row| format!("{:?}", row.values);
+        let key = row_identity_key;
 
         let out = match self.op {
             SetOpType::Union => {
@@ -1625,6 +1923,20 @@ pub struct JoinCondition {
     pub operator: JoinOperator,
 }
 
+impl JoinCondition {
+    /// Whether the rows satisfy the condition; never when a join column is missing or NULL, or
+    /// the values are not comparable ([`ColumnValue::sql_cmp`])
+    pub fn holds(&self, left_row: &Row, right_row: &Row) -> bool {
+        match (
+            left_row.get_value(&self.left_column),
+            right_row.get_value(&self.right_column),
+        ) {
+            (Some(left), Some(right)) => self.operator.holds(left, right),
+            _ => false,
+        }
+    }
+}
+
 /// Join comparison operator
 #[derive(Debug, Clone)]
 pub enum JoinOperator {
@@ -1636,6 +1948,74 @@ pub enum JoinOperator {
     GreaterThanOrEqual,
 }
 
+impl JoinOperator {
+    /// `left <op> right` under SQL semantics: false when the comparison is unknown
+    pub fn holds(&self, left: &ColumnValue, right: &ColumnValue) -> bool {
+        let Some(order) = left.sql_cmp(right) else {
+            return false;
+        };
+        match self {
+            JoinOperator::Equal => order.is_eq(),
+            JoinOperator::NotEqual => order.is_ne(),
+            JoinOperator::LessThan => order.is_lt(),
+            JoinOperator::LessThanOrEqual => order.is_le(),
+            JoinOperator::GreaterThan => order.is_gt(),
+            JoinOperator::GreaterThanOrEqual => order.is_ge(),
+        }
+    }
+}
+
+/// Concatenates a left and a right row into one output row
+///
+/// The output schema is built once per pair of input schemas and shared by the produced rows; a
+/// right column with the same name as a left one replaces it.
+#[derive(Default)]
+struct RowCombiner {
+    /// Left and right input schemas, output schema, output position of each right column
+    cached: Option<(Arc<RowSchema>, Arc<RowSchema>, Arc<RowSchema>, Vec<usize>)>,
+}
+
+impl RowCombiner {
+    fn combine(&mut self, left: &Row, right: &Row) -> Row {
+        let fresh = self.cached.as_ref().is_some_and(|(l, r, _, _)| {
+            Arc::ptr_eq(l, left.schema()) && Arc::ptr_eq(r, right.schema())
+        });
+        if !fresh {
+            let columns = left
+                .schema()
+                .columns()
+                .iter()
+                .chain(right.schema().columns());
+            let combined = Arc::new(RowSchema::new(columns.map(String::as_str)));
+            let right_positions = right
+                .schema()
+                .columns()
+                .iter()
+                .filter_map(|c| combined.position(c))
+                .collect();
+            self.cached = Some((
+                left.schema().clone(),
+                right.schema().clone(),
+                combined,
+                right_positions,
+            ));
+        }
+        let (_, _, combined, right_positions) = self.cached.as_ref().expect("schema cached above");
+        let mut row = Row::with_schema(combined.clone());
+        for position in 0..left.schema().len() {
+            if let Some(value) = left.get_value_at(position) {
+                row.set_value_at(position, value.clone());
+            }
+        }
+        for (position, &output) in right_positions.iter().enumerate() {
+            if let Some(value) = right.get_value_at(position) {
+                row.set_value_at(output, value.clone());
+            }
+        }
+        row
+    }
+}
+
 /// Nested Loop Join operator
 pub struct NestedLoopJoinOperator {
     /// Left input operator
@@ -1658,6 +2038,8 @@ pub struct NestedLoopJoinOperator {
     statistics: OperatorStatistics,
     /// Block size for block nested loop
     block_size: usize,
+    /// Builds output rows
+    combiner: RowCombiner,
 }
 
 impl NestedLoopJoinOperator {
@@ -1685,6 +2067,7 @@ impl NestedLoopJoinOperator {
             right_buffer: Vec::new(),
             schema: left_schema,
             statistics: OperatorStatistics::default(),
+            combiner: RowCombiner::default(),
             block_size,
         })
     }
@@ -1709,59 +2092,13 @@ impl NestedLoopJoinOperator {
 
     /// Check join condition
     fn check_join_condition(&self, left_row: &Row, right_row: &Row) -> bool {
-        let left_value = left_row.get_value(&self.join_condition.left_column);
-        let right_value = right_row.get_value(&self.join_condition.right_column);
-
-        match (left_value, right_value) {
-            (Some(left), Some(right)) => match self.join_condition.operator {
-                JoinOperator::Equal => left == right,
-                JoinOperator::NotEqual => left != right,
-                JoinOperator::LessThan => {
-                    self.compare_values(left, right) == std::cmp::Ordering::Less
-                }
-                JoinOperator::LessThanOrEqual => {
-                    self.compare_values(left, right) != std::cmp::Ordering::Greater
-                }
-                JoinOperator::GreaterThan => {
-                    self.compare_values(left, right) == std::cmp::Ordering::Greater
-                }
-                JoinOperator::GreaterThanOrEqual => {
-                    self.compare_values(left, right) != std::cmp::Ordering::Less
-                }
-            },
-            _ => false,
-        }
-    }
-
-    /// Compare column values
-    fn compare_values(&self, left: &ColumnValue, right: &ColumnValue) -> std::cmp::Ordering {
-        // Simplified comparison - compare string representations
-        let left_str = format!("{:?}", left);
-        let right_str = format!("{:?}", right);
-        left_str.cmp(&right_str)
-    }
-
-    /// Combine rows
-    fn combine_rows(&self, left_row: &Row, right_row: &Row) -> Row {
-        let mut combined_row = Row::new();
-
-        // Copy values from left row
-        for (column, value) in &left_row.values {
-            combined_row.set_value(column, value.clone());
-        }
-
-        // Copy values from right row
-        for (column, value) in &right_row.values {
-            combined_row.set_value(column, value.clone());
-        }
-
-        combined_row
+        self.join_condition.holds(left_row, right_row)
     }
 }
 
 impl Operator for NestedLoopJoinOperator {
     fn next(&mut self) -> Result<Option<Row>> {
-        let start_time = std::time::Instant::now();
+        let start_time = Instant::now();
 
         loop {
             // If we don't have current row from left input, get next one
@@ -1786,7 +2123,7 @@ impl Operator for NestedLoopJoinOperator {
                 self.current_right_position += 1;
 
                 if self.check_join_condition(left_row, right_row) {
-                    let combined_row = self.combine_rows(left_row, right_row);
+                    let combined_row = self.combiner.combine(left_row, right_row);
                     self.statistics.rows_returned += 1;
                     self.statistics.execution_time_ms = start_time.elapsed().as_millis() as u64;
                     return Ok(Some(combined_row));
@@ -1827,6 +2164,21 @@ impl Operator for NestedLoopJoinOperator {
     }
 }
 
+/// Whether the join column of `row` is missing or NULL: such rows join no row
+fn null_join_key(row: &Row, column: &str) -> bool {
+    row.get_value(column).is_none_or(ColumnValue::is_null)
+}
+
+/// Order-preserving binary key of a join column (a missing column is encoded as NULL)
+fn join_key(row: &Row, column: &str) -> Vec<u8> {
+    let mut key = Vec::new();
+    match row.get_value(column) {
+        Some(value) => key_encoding::encode_value(value, &mut key),
+        None => key_encoding::encode_value(&ColumnValue::null(), &mut key),
+    }
+    key
+}
+
 /// Hash Join operator
 pub struct HashJoinOperator {
     /// Left input operator
@@ -1837,8 +2189,8 @@ pub struct HashJoinOperator {
     join_condition: JoinCondition,
     /// Join type
     join_type: JoinType,
-    /// Hash table for right input
-    hash_table: HashMap<String, Vec<Row>>,
+    /// Hash table for right input, keyed by the encoded join column
+    hash_table: HashMap<Vec<u8>, Vec<Row>>,
     /// Current row from left input
     current_left_row: Option<Row>,
     /// Current position in match list
@@ -1851,6 +2203,8 @@ pub struct HashJoinOperator {
     statistics: OperatorStatistics,
     /// Hash table size
     hash_table_size: usize,
+    /// Builds output rows
+    combiner: RowCombiner,
 }
 
 impl HashJoinOperator {
@@ -1879,6 +2233,7 @@ impl HashJoinOperator {
             current_matches: Vec::new(),
             schema: left_schema,
             statistics: OperatorStatistics::default(),
+            combiner: RowCombiner::default(),
             hash_table_size,
         };
 
@@ -1891,10 +2246,15 @@ impl HashJoinOperator {
     /// Build hash table from right input
     fn build_hash_table(&mut self) -> Result<()> {
         self.hash_table.clear();
+        self.statistics.memory_used_bytes = 0;
 
         // Scan right input and build hash table
         while let Some(row) = self.right_input.next()? {
+            if null_join_key(&row, &self.join_condition.right_column) {
+                continue;
+            }
             let key = self.get_join_key(&row, &self.join_condition.right_column);
+            self.statistics.memory_used_bytes += key.len() + row_bytes(&row) as usize;
             self.hash_table
                 .entry(key)
                 .or_insert_with(Vec::new)
@@ -1906,69 +2266,19 @@ impl HashJoinOperator {
     }
 
     /// Get join key
-    fn get_join_key(&self, row: &Row, column: &str) -> String {
-        if let Some(value) = row.get_value(column) {
-            format!("{:?}", value)
-        } else {
-            "NULL".to_string()
-        }
+    fn get_join_key(&self, row: &Row, column: &str) -> Vec<u8> {
+        join_key(row, column)
     }
 
     /// Check join condition
     fn check_join_condition(&self, left_row: &Row, right_row: &Row) -> bool {
-        let left_value = left_row.get_value(&self.join_condition.left_column);
-        let right_value = right_row.get_value(&self.join_condition.right_column);
-
-        match (left_value, right_value) {
-            (Some(left), Some(right)) => match self.join_condition.operator {
-                JoinOperator::Equal => left == right,
-                JoinOperator::NotEqual => left != right,
-                JoinOperator::LessThan => {
-                    self.compare_values(left, right) == std::cmp::Ordering::Less
-                }
-                JoinOperator::LessThanOrEqual => {
-                    self.compare_values(left, right) != std::cmp::Ordering::Greater
-                }
-                JoinOperator::GreaterThan => {
-                    self.compare_values(left, right) == std::cmp::Ordering::Greater
-                }
-                JoinOperator::GreaterThanOrEqual => {
-                    self.compare_values(left, right) != std::cmp::Ordering::Less
-                }
-            },
-            _ => false,
-        }
-    }
-
-    /// Compare column values
-    fn compare_values(&self, left: &ColumnValue, right: &ColumnValue) -> std::cmp::Ordering {
-        // Simplified comparison - compare string representations
-        let left_str = format!("{:?}", left);
-        let right_str = format!("{:?}", right);
-        left_str.cmp(&right_str)
-    }
-
-    /// Combine rows
-    fn combine_rows(&self, left_row: &Row, right_row: &Row) -> Row {
-        let mut combined_row = Row::new();
-
-        // Copy values from left row
-        for (column, value) in &left_row.values {
-            combined_row.set_value(column, value.clone());
-        }
-
-        // Copy values from right row
-        for (column, value) in &right_row.values {
-            combined_row.set_value(column, value.clone());
-        }
-
-        combined_row
+        self.join_condition.holds(left_row, right_row)
     }
 }
 
 impl Operator for HashJoinOperator {
     fn next(&mut self) -> Result<Option<Row>> {
-        let start_time = std::time::Instant::now();
+        let start_time = Instant::now();
 
         loop {
             // If we don't have current row from left input, get next one
@@ -1981,9 +2291,13 @@ impl Operator for HashJoinOperator {
 
                 // Find matches in hash table
                 let left_row = self.current_left_row.as_ref().unwrap();
-                let key = self.get_join_key(left_row, &self.join_condition.left_column);
-
-                self.current_matches = self.hash_table.get(&key).cloned().unwrap_or_default();
+                self.current_matches = if null_join_key(left_row, &self.join_condition.left_column)
+                {
+                    Vec::new()
+                } else {
+                    let key = self.get_join_key(left_row, &self.join_condition.left_column);
+                    self.hash_table.get(&key).cloned().unwrap_or_default()
+                };
                 self.current_match_position = 0;
             }
 
@@ -1996,7 +2310,7 @@ impl Operator for HashJoinOperator {
                 self.current_match_position += 1;
 
                 if self.check_join_condition(left_row, right_row) {
-                    let combined_row = self.combine_rows(left_row, right_row);
+                    let combined_row = self.combiner.combine(left_row, right_row);
                     self.statistics.rows_returned += 1;
                     self.statistics.execution_time_ms = start_time.elapsed().as_millis() as u64;
                     return Ok(Some(combined_row));
@@ -2055,6 +2369,8 @@ pub struct MergeJoinOperator {
     schema: Vec<String>,
     /// Statistics
     statistics: OperatorStatistics,
+    /// Builds output rows
+    combiner: RowCombiner,
 }
 
 impl MergeJoinOperator {
@@ -2084,20 +2400,17 @@ impl MergeJoinOperator {
             right_buffer_pos: 0,
             schema: left_schema,
             statistics: OperatorStatistics::default(),
+            combiner: RowCombiner::default(),
         })
     }
 
     /// Get join key
-    fn get_join_key(&self, row: &Row, column: &str) -> String {
-        if let Some(value) = row.get_value(column) {
-            format!("{:?}", value)
-        } else {
-            "NULL".to_string()
-        }
+    fn get_join_key(&self, row: &Row, column: &str) -> Vec<u8> {
+        join_key(row, column)
     }
 
     /// Compare join keys
-    fn compare_keys(&self, left_key: &str, right_key: &str) -> std::cmp::Ordering {
+    fn compare_keys(&self, left_key: &[u8], right_key: &[u8]) -> std::cmp::Ordering {
         left_key.cmp(right_key)
     }
 
@@ -2122,6 +2435,12 @@ impl MergeJoinOperator {
             let right_key = self.get_join_key(right_row, &self.join_condition.right_column);
 
             match self.compare_keys(&left_key, &right_key) {
+                // NULL keys sort last and match nothing: skip the left ones.
+                std::cmp::Ordering::Equal
+                    if null_join_key(left_row, &self.join_condition.left_column) =>
+                {
+                    self.current_left_row = self.left_input.next()?;
+                }
                 std::cmp::Ordering::Equal => {
                     // Load all rows with same keys
                     let target_key = left_key.clone();
@@ -2161,28 +2480,11 @@ impl MergeJoinOperator {
 
         Ok(())
     }
-
-    /// Combine rows
-    fn combine_rows(&self, left_row: &Row, right_row: &Row) -> Row {
-        let mut combined_row = Row::new();
-
-        // Copy values from left row
-        for (column, value) in &left_row.values {
-            combined_row.set_value(column, value.clone());
-        }
-
-        // Copy values from right row
-        for (column, value) in &right_row.values {
-            combined_row.set_value(column, value.clone());
-        }
-
-        combined_row
-    }
 }
 
 impl Operator for MergeJoinOperator {
     fn next(&mut self) -> Result<Option<Row>> {
-        let start_time = std::time::Instant::now();
+        let start_time = Instant::now();
 
         loop {
             // If buffers are empty, load new matching keys
@@ -2191,9 +2493,9 @@ impl Operator for MergeJoinOperator {
             {
                 self.load_matching_keys()?;
 
-                // If no more data, finish
+                // If either input is exhausted, no more rows match
                 if self.left_buffer.is_empty() || self.right_buffer.is_empty() {
-                    if self.current_left_row.is_none() && self.current_right_row.is_none() {
+                    if self.current_left_row.is_none() || self.current_right_row.is_none() {
                         break;
                     }
                     continue;
@@ -2206,7 +2508,7 @@ impl Operator for MergeJoinOperator {
 
             self.statistics.rows_processed += 1;
 
-            let combined_row = self.combine_rows(left_row, right_row);
+            let combined_row = self.combiner.combine(left_row, right_row);
             self.statistics.rows_returned += 1;
             self.statistics.execution_time_ms = start_time.elapsed().as_millis() as u64;
 
@@ -2347,13 +2649,54 @@ impl Operator for OffsetOperator {
     }
 }
 
+/// Opens the base-table scans of the plans a [`QueryExecutor`](crate::executor::QueryExecutor)
+/// runs
+///
+/// [`ScanOperatorFactory`] reads the engine's heap files (`native` only); the in-memory
+/// [`playground`](crate::playground) scans its own tables. Index and foreign scans fail unless
+/// the source has them.
+pub trait ScanSource: Send + Sync {
+    /// Scan of `table_name` producing the `schema` columns (`*` = all of them); rows not
+    /// matching `pushdown_equality` may be skipped.
+    fn create_table_scan(
+        &self,
+        table_name: String,
+        filter: Option<String>,
+        pushdown_equality: Option<SimpleEqualityFilter>,
+        schema: Vec<String>,
+    ) -> Result<Box<dyn Operator>>;
+
+    /// Scan of `table_name` through its index `index_name`
+    fn create_index_scan(
+        &self,
+        table_name: String,
+        index_name: String,
+        _search_conditions: Vec<IndexCondition>,
+        _schema: Vec<String>,
+    ) -> Result<Box<dyn Operator>> {
+        Err(Error::query_execution(format!(
+            "Index {} of {} cannot be scanned here",
+            index_name, table_name
+        )))
+    }
+
+    /// Scan of a foreign table
+    fn create_foreign_scan(&self, scan: ForeignScanNode) -> Result<Box<dyn Operator>> {
+        Err(Error::query_execution(format!(
+            "Foreign table {} cannot be scanned here",
+            scan.table_name
+        )))
+    }
+}
+
 /// Factory for creating scan operators
+#[cfg(feature = "native")]
 pub struct ScanOperatorFactory {
     /// Page manager
-    default_page_manager: Arc<PageManagerMutex>,
-    table_page_managers: Arc<Mutex<HashMap<String, Arc<PageManagerMutex>>>>,
-    /// Indexes: (table_name, index_name) -> B+ tree (used when `index_registry` is `None`, e.g. tests)
-    indexes: Mutex<HashMap<(String, String), Arc<Mutex<BPlusTree<String, Vec<RecordId>>>>>>,
+    default_page_manager: Arc<PageManagerLock>,
+    table_page_managers: Arc<Mutex<HashMap<String, Arc<PageManagerLock>>>>,
+    /// Indexes: (table_name, index_name) -> index (used when `index_registry` is `None`, e.g. tests)
+    indexes: Mutex<HashMap<(String, String), Arc<Mutex<SecondaryIndex>>>>,
     /// When set, `CREATE INDEX` / DML maintain this registry and index scans resolve through it.
     index_registry: Option<Arc<RwLock<IndexRegistry>>>,
     /// When set, a table name not yet in `table_page_managers` opens `<data_dir>/<table>.tbl`
@@ -2361,11 +2704,14 @@ pub struct ScanOperatorFactory {
     /// lets `SELECT` in a new process see rows inserted into a named heap file earlier.
     data_dir: Option<PathBuf>,
     pm_config: PageManagerConfig,
+    /// Opens the sealed tuples of the tables scanned
+    keyring: Arc<Keyring>,
 }
 
+#[cfg(feature = "native")]
 impl ScanOperatorFactory {
     /// Create new scan operator factory
-    pub fn new(page_manager: Arc<PageManagerMutex>) -> Self {
+    pub fn new(page_manager: Arc<PageManagerLock>) -> Self {
         Self {
             default_page_manager: page_manager,
             table_page_managers: Arc::new(Mutex::new(HashMap::new())),
@@ -2373,12 +2719,13 @@ impl ScanOperatorFactory {
             index_registry: None,
             data_dir: None,
             pm_config: PageManagerConfig::default(),
+            keyring: Arc::default(),
         }
     }
 
     pub fn with_tables(
-        default_page_manager: Arc<PageManagerMutex>,
-        table_page_managers: Arc<Mutex<HashMap<String, Arc<PageManagerMutex>>>>,
+        default_page_manager: Arc<PageManagerLock>,
+        table_page_managers: Arc<Mutex<HashMap<String, Arc<PageManagerLock>>>>,
         data_dir: PathBuf,
         index_registry: Option<Arc<RwLock<IndexRegistry>>>,
     ) -> Self {
@@ -2389,10 +2736,17 @@ impl ScanOperatorFactory {
             index_registry,
             data_dir: Some(data_dir),
             pm_config: PageManagerConfig::default(),
+            keyring: Arc::default(),
         }
     }
 
-    fn page_manager_for_table(&self, table_name: &str) -> Result<Arc<PageManagerMutex>> {
+    /// Scans open sealed tuples with `keyring` (an engine's, see [`crate::storage::shred`])
+    pub fn with_keyring(mut self, keyring: Arc<Keyring>) -> Self {
+        self.keyring = keyring;
+        self
+    }
+
+    fn page_manager_for_table(&self, table_name: &str) -> Result<Arc<PageManagerLock>> {
         let mut g = self
             .table_page_managers
             .lock()
@@ -2403,8 +2757,8 @@ impl ScanOperatorFactory {
         if let Some(ref dir) = self.data_dir {
             let pm = match StoragePageManager::open(dir.clone(), table_name, self.pm_config.clone())
             {
-                Ok(pm) => Arc::new(PageManagerMutex::new(pm)),
-                Err(_) => Arc::new(PageManagerMutex::new(StoragePageManager::new(
+                Ok(pm) => Arc::new(PageManagerLock::new(pm)),
+                Err(_) => Arc::new(PageManagerLock::new(StoragePageManager::new(
                     dir.clone(),
                     table_name,
                     self.pm_config.clone(),
@@ -2420,7 +2774,7 @@ impl ScanOperatorFactory {
     pub fn register_table_page_manager(
         &self,
         table_name: &str,
-        pm: Arc<PageManagerMutex>,
+        pm: Arc<PageManagerLock>,
     ) -> Result<()> {
         let mut g = self
             .table_page_managers
@@ -2431,12 +2785,7 @@ impl ScanOperatorFactory {
     }
 
     /// Add index for table (tests / examples; uses the local map when no shared [`IndexRegistry`]).
-    pub fn add_index(
-        &self,
-        table_name: &str,
-        index_name: &str,
-        index: Arc<Mutex<BPlusTree<String, Vec<RecordId>>>>,
-    ) {
+    pub fn add_index(&self, table_name: &str, index_name: &str, index: Arc<Mutex<SecondaryIndex>>) {
         let mut g = self
             .indexes
             .lock()
@@ -2453,7 +2802,14 @@ impl ScanOperatorFactory {
         schema: Vec<String>,
     ) -> Result<Box<dyn Operator>> {
         let pm = self.page_manager_for_table(&table_name)?;
-        let operator = TableScanOperator::new(table_name, pm, filter, pushdown_equality, schema)?;
+        let operator = TableScanOperator::new(table_name, pm, filter, pushdown_equality, schema)?
+            .with_keyring(self.keyring.clone());
+        Ok(Box::new(operator))
+    }
+
+    /// Create foreign table scan operator; relative adapter paths resolve against the data directory
+    pub fn create_foreign_scan(&self, scan: ForeignScanNode) -> Result<Box<dyn Operator>> {
+        let operator = ForeignScanOperator::new(scan, self.data_dir.clone())?;
         Ok(Box::new(operator))
     }
 
@@ -2503,11 +2859,45 @@ impl ScanOperatorFactory {
         };
         let pm = self.page_manager_for_table(&table_name)?;
         let operator =
-            IndexScanOperator::new(table_name, index_name, index, pm, search_conditions, schema)?;
+            IndexScanOperator::new(table_name, index_name, index, pm, search_conditions, schema)?
+                .with_keyring(self.keyring.clone());
         Ok(Box::new(operator))
     }
 }
 
+#[cfg(feature = "native")]
+impl ScanSource for ScanOperatorFactory {
+    fn create_table_scan(
+        &self,
+        table_name: String,
+        filter: Option<String>,
+        pushdown_equality: Option<SimpleEqualityFilter>,
+        schema: Vec<String>,
+    ) -> Result<Box<dyn Operator>> {
+        ScanOperatorFactory::create_table_scan(self, table_name, filter, pushdown_equality, schema)
+    }
+
+    fn create_index_scan(
+        &self,
+        table_name: String,
+        index_name: String,
+        search_conditions: Vec<IndexCondition>,
+        schema: Vec<String>,
+    ) -> Result<Box<dyn Operator>> {
+        ScanOperatorFactory::create_index_scan(
+            self,
+            table_name,
+            index_name,
+            search_conditions,
+            schema,
+        )
+    }
+
+    fn create_foreign_scan(&self, scan: ForeignScanNode) -> Result<Box<dyn Operator>> {
+        ScanOperatorFactory::create_foreign_scan(self, scan)
+    }
+}
+
 /// Aggregate function type
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum AggregateFunction {
@@ -2676,25 +3066,26 @@ impl Operator for HashGroupByOperator {
 pub struct SortOperator {
     /// Input operator
     input: Box<dyn Operator>,
-    /// Sort keys: column name and ascending flag
-    sort_keys: Vec<(String, bool)>,
+    /// Sort keys
+    sort_keys: Vec<SortKey>,
+    /// Work memory and temp directory of the sort
+    config: SortConfig,
     /// Result schema
     result_schema: Vec<String>,
     /// Statistics
     statistics: OperatorStatistics,
-    /// Sorted rows
-    sorted_rows: Vec<Row>,
-    /// Current index
-    current_index: usize,
+    /// Sorted rows, once the input is read
+    sorted: Option<SortedRows>,
 }
 
 impl SortOperator {
-    /// Create new sort operator
-    pub fn new(
+    /// Create new sort operator with the default [`SortConfig`]
+    pub fn new<K: Into<SortKey>>(
         input: Box<dyn Operator>,
-        sort_keys: Vec<(String, bool)>,
+        sort_keys: impl IntoIterator<Item = K>,
         result_schema: Vec<String>,
     ) -> Result<Self> {
+        let sort_keys: Vec<SortKey> = sort_keys.into_iter().map(Into::into).collect();
         if sort_keys.is_empty() {
             return Err(Error::QueryExecution {
                 message: "Sort requires at least one sort key".to_string(),
@@ -2704,93 +3095,53 @@ impl SortOperator {
         Ok(Self {
             input,
             sort_keys,
+            config: SortConfig::default(),
             result_schema,
             statistics: OperatorStatistics::default(),
-            sorted_rows: Vec::new(),
-            current_index: 0,
+            sorted: None,
         })
     }
 
-    fn compare_by_keys(a: &Row, b: &Row, keys: &[(String, bool)]) -> std::cmp::Ordering {
-        use std::cmp::Ordering;
-        for (name, ascending) in keys {
-            let va = a
-                .values
-                .get(name)
-                .map(column_value_to_eval)
-                .unwrap_or(EvalValue::Null);
-            let vb = b
-                .values
-                .get(name)
-                .map(column_value_to_eval)
-                .unwrap_or(EvalValue::Null);
-            let ord = match (&va, &vb) {
-                (EvalValue::Null, EvalValue::Null) => Ordering::Equal,
-                (EvalValue::Null, _) => Ordering::Greater,
-                (_, EvalValue::Null) => Ordering::Less,
-                (EvalValue::Bool(a), EvalValue::Bool(b)) => a.cmp(b),
-                (EvalValue::Int(a), EvalValue::Int(b)) => a.cmp(b),
-                (EvalValue::Float(a), EvalValue::Float(b)) => {
-                    a.partial_cmp(b).unwrap_or(Ordering::Equal)
-                }
-                (EvalValue::Int(a), EvalValue::Float(b)) => {
-                    (*a as f64).partial_cmp(b).unwrap_or(Ordering::Equal)
-                }
-                (EvalValue::Float(a), EvalValue::Int(b)) => {
-                    a.partial_cmp(&(*b as f64)).unwrap_or(Ordering::Equal)
-                }
-                (EvalValue::String(a), EvalValue::String(b)) => a.cmp(b),
-                // different types: order by type name to keep deterministic
-                _ => format!("{:?}", va).cmp(&format!("{:?}", vb)),
-            };
-            if ord != Ordering::Equal {
-                return if *ascending { ord } else { ord.reverse() };
-            }
-        }
-        Ordering::Equal
+    /// Sorts within `config`: past its work memory rows spill to runs in its temp directory
+    pub fn with_config(mut self, config: SortConfig) -> Self {
+        self.config = config;
+        self
     }
 
-    /// Load and sort all rows (simplified version)
-    fn load_and_sort(&mut self) -> Result<()> {
-        let mut rows = Vec::new();
-
-        // Read all rows from input operator
+    /// Reads and sorts all input rows.
+    fn load_and_sort(&mut self) -> Result<SortedRows> {
+        let mut sorter = ExternalSorter::new(self.sort_keys.clone(), self.config.clone());
         while let Some(row) = self.input.next()? {
-            rows.push(row);
+            sorter.push(row)?;
             self.statistics.rows_processed += 1;
         }
-
-        let keys = self.sort_keys.clone();
-        rows.sort_by(|a, b| Self::compare_by_keys(a, b, &keys));
-
-        self.sorted_rows = rows;
-        self.current_index = 0;
-
-        Ok(())
+        let sorted = sorter.finish()?;
+        let used = sorted.statistics();
+        self.statistics.memory_used_bytes = used.peak_memory_bytes;
+        self.statistics.spill_count = used.runs;
+        self.statistics.spilled_bytes = used.spilled_bytes;
+        Ok(sorted)
     }
 }
 
 impl Operator for SortOperator {
     fn next(&mut self) -> Result<Option<Row>> {
-        // If this is first call, load and sort data
-        if self.sorted_rows.is_empty() && self.current_index == 0 {
-            self.load_and_sort()?;
-        }
-
-        // Return next row
-        if self.current_index < self.sorted_rows.len() {
-            let row = self.sorted_rows[self.current_index].clone();
-            self.current_index += 1;
+        let sorted = match &mut self.sorted {
+            Some(sorted) => sorted,
+            None => {
+                let sorted = self.load_and_sort()?;
+                self.sorted.insert(sorted)
+            }
+        };
+        let row = sorted.next_row()?;
+        if row.is_some() {
             self.statistics.rows_returned += 1;
-            return Ok(Some(row));
         }
-
-        Ok(None)
+        Ok(row)
     }
 
     fn reset(&mut self) -> Result<()> {
-        self.sorted_rows.clear();
-        self.current_index = 0;
+        self.sorted = None;
         self.statistics = OperatorStatistics::default();
         self.input.reset()?;
         Ok(())
@@ -2,10 +2,13 @@
 
 use crate::common::types::{ColumnValue, DataType};
 use crate::common::Result;
+use crate::executor::external_sort::{SortConfig, SortKey, MERGE_FAN_IN};
 use crate::executor::operators::{
-    AggregateFunction, AggregationSortOperatorFactory, HashGroupByOperator, Operator,
-    SortGroupByOperator, SortOperator,
+    AggregateFunction, AggregationSortOperatorFactory, ConditionalScanOperator,
+    HashGroupByOperator, Operator, ProjectionOperator, SortGroupByOperator, SortOperator,
 };
+use crate::parser::ast::{Expression, Literal};
+use crate::planner::planner::ProjectionColumn;
 use crate::Row;
 use std::collections::HashMap;
 
@@ -38,6 +41,10 @@ impl TestOperator {
             data.push(row);
         }
 
+        Self::from_rows(data)
+    }
+
+    fn from_rows(data: Vec<Row>) -> Self {
         Self {
             data,
             current_index: 0,
@@ -144,6 +151,115 @@ fn test_sort_operator() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_sort_operator_orders_numbers_by_value_with_nulls_last() -> Result<()> {
+    let values = [
+        ColumnValue::new(DataType::Integer(10)),
+        ColumnValue::null(),
+        ColumnValue::new(DataType::Double(9.5)),
+        ColumnValue::new(DataType::BigInt(-3)),
+        ColumnValue::new(DataType::SmallInt(9)),
+    ];
+    let rows = values
+        .iter()
+        .map(|v| {
+            let mut row = Row::new();
+            row.set_value("v", v.clone());
+            row
+        })
+        .collect::<Vec<_>>();
+    let sorted = |ascending: bool| -> Result<Vec<ColumnValue>> {
+        let input = Box::new(TestOperator::from_rows(rows.clone()));
+        let mut operator = SortOperator::new(
+            input,
+            vec![("v".to_string(), ascending)],
+            vec!["v".to_string()],
+        )?;
+        let mut out = Vec::new();
+        while let Some(row) = operator.next()? {
+            out.push(row.get_value("v").unwrap().clone());
+        }
+        Ok(out)
+    };
+
+    let expected = [3, 4, 2, 0, 1].map(|i| values[i].clone()).to_vec();
+    assert_eq!(sorted(true)?, expected);
+    let mut descending = expected;
+    descending.reverse();
+    assert_eq!(sorted(false)?, descending);
+    Ok(())
+}
+
+#[test]
+fn test_sort_operator_spills_runs_and_merges_them_in_order() -> Result<()> {
+    // 1000 rows: `g` in 0..7 with every fifth NULL, `seq` the input position.
+    let rows = (0..1000)
+        .map(|i| {
+            let mut row = Row::new();
+            let g = if i % 5 == 0 {
+                ColumnValue::null()
+            } else {
+                ColumnValue::new(DataType::Integer((i * 7919) % 7))
+            };
+            row.set_value("g", g);
+            row.set_value("seq", ColumnValue::new(DataType::Integer(i)));
+            row
+        })
+        .collect::<Vec<_>>();
+    let temp = tempfile::tempdir().unwrap();
+    let sorted = |keys: Vec<SortKey>| -> Result<(Vec<(Option<i32>, i32)>, usize)> {
+        let input = Box::new(TestOperator::from_rows(rows.clone()));
+        let mut operator = SortOperator::new(input, keys, vec!["g".into(), "seq".into()])?
+            .with_config(SortConfig {
+                // A few rows per run: more runs than one merge takes
+                work_mem: 1024,
+                temp_dir: temp.path().to_path_buf(),
+            });
+        let mut out = Vec::new();
+        while let Some(row) = operator.next()? {
+            let int = |name: &str| match row.get_value(name).map(|v| &v.data_type) {
+                Some(DataType::Integer(n)) if !row.get_value(name).unwrap().is_null => Some(*n),
+                _ => None,
+            };
+            out.push((int("g"), int("seq").unwrap()));
+        }
+        Ok((out, operator.get_statistics().spill_count))
+    };
+
+    let (ascending, runs) = sorted(vec![SortKey::new("g", true)])?;
+    assert!(runs > MERGE_FAN_IN, "{runs} runs");
+    let mut expected: Vec<_> = rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let g = row.get_value("g").unwrap();
+            let g = match g.data_type {
+                DataType::Integer(n) if !g.is_null => Some(n),
+                _ => None,
+            };
+            (g, i as i32)
+        })
+        .collect();
+    // Stable: equal keys keep their input order; NULLs last ascending.
+    expected.sort_by_key(|(g, _)| (g.is_none(), *g));
+    assert_eq!(ascending, expected);
+
+    let (descending, _) = sorted(vec![
+        SortKey::new("g", false).with_nulls_first(false),
+        SortKey::new("seq", false),
+    ])?;
+    expected.sort_by_key(|(g, seq)| (g.is_none(), std::cmp::Reverse(*g), std::cmp::Reverse(*seq)));
+    assert_eq!(descending, expected);
+
+    let (nulls_first, _) = sorted(vec![SortKey::new("g", true).with_nulls_first(true)])?;
+    assert!(nulls_first[..200].iter().all(|(g, _)| g.is_none()));
+    assert!(nulls_first[200..].windows(2).all(|w| w[0] <= w[1]));
+
+    // The runs are gone once the operators are dropped.
+    assert_eq!(std::fs::read_dir(temp.path()).unwrap().count(), 0);
+    Ok(())
+}
+
 #[test]
 fn test_sort_group_by_operator() -> Result<()> {
     let input = Box::new(TestOperator::new());
@@ -281,3 +397,77 @@ fn test_aggregate_functions() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_filter_and_projection_evaluate_with_row_arena() -> Result<()> {
+    let rows = (0..20)
+        .map(|i| {
+            let mut row = Row::new();
+            row.set_value(
+                "name",
+                ColumnValue::new(DataType::Varchar(format!("user_{i}"))),
+            );
+            row.set_value("COUNT(name)", ColumnValue::new(DataType::BigInt(i)));
+            row.set_value(
+                "payload",
+                ColumnValue::new(DataType::Blob(vec![i as u8; 4])),
+            );
+            row
+        })
+        .collect();
+    let like = Expression::Like {
+        expr: Box::new(Expression::Identifier("name".to_string())),
+        pattern: Box::new(Expression::Literal(Literal::String("user_1%".to_string()))),
+        negated: false,
+    };
+    let filter = ConditionalScanOperator::new(
+        Box::new(TestOperator::from_rows(rows)),
+        String::new(),
+        Some(like),
+        None,
+    )?;
+    let columns = vec![
+        ProjectionColumn {
+            name: "name".to_string(),
+            expression: Some(Expression::Identifier("name".to_string())),
+            alias: None,
+        },
+        ProjectionColumn {
+            name: "count(name)".to_string(),
+            expression: Some(Expression::Function {
+                name: "count".to_string(),
+                args: vec![Expression::Identifier("name".to_string())],
+            }),
+            alias: Some("n".to_string()),
+        },
+        ProjectionColumn {
+            name: "payload".to_string(),
+            expression: Some(Expression::Identifier("payload".to_string())),
+            alias: None,
+        },
+    ];
+    let mut projection = ProjectionOperator::new(Box::new(filter), columns)?;
+
+    let mut out = Vec::new();
+    while let Some(row) = projection.next()? {
+        out.push(row);
+    }
+    // user_1 and user_10..user_19
+    assert_eq!(out.len(), 11);
+    assert_eq!(
+        out[1].get_value("name").map(|v| &v.data_type),
+        Some(&DataType::Varchar("user_10".to_string()))
+    );
+    assert_eq!(
+        out[1].get_value("n").map(|v| &v.data_type),
+        Some(&DataType::Integer(10))
+    );
+    // Blobs are formatted into the arena, which is reset after every row.
+    assert_eq!(
+        out[1].get_value("payload").map(|v| &v.data_type),
+        Some(&DataType::Varchar("[10, 10, 10, 10]".to_string()))
+    );
+    let used = projection.get_statistics().memory_used_bytes;
+    assert!(used >= "[10, 10, 10, 10]".len() && used < 1024);
+    Ok(())
+}
@@ -26,6 +26,7 @@ fn test_executor_config_creation() -> Result<()> {
     let config = QueryExecutorConfig {
         enable_parallel_execution: false,
         num_worker_threads: 1,
+        ..Default::default()
     };
     let _executor = QueryExecutor::with_config(factory, config)?;
     Ok(())
@@ -39,7 +40,7 @@ fn test_executor_simple_table_scan() -> Result<()> {
     // Insert test data
     {
         let data = b"1\tAlice\t30".to_vec();
-        let mut pm = page_manager.lock();
+        let mut pm = page_manager.write();
         pm.insert(&data)?;
     }
 
@@ -75,6 +76,7 @@ fn test_executor_with_config() -> Result<()> {
     let config = QueryExecutorConfig {
         enable_parallel_execution: false,
         num_worker_threads: 2,
+        ..Default::default()
     };
     let executor = QueryExecutor::with_config(factory, config)?;
 
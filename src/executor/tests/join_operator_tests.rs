@@ -1,11 +1,23 @@
 //! Tests for connection statements
 
 use super::common;
+use crate::common::types::{ColumnValue, DataType};
 use crate::common::Result;
 use crate::executor::operators::{
     HashJoinOperator, JoinCondition, JoinOperator, JoinType, MergeJoinOperator,
-    NestedLoopJoinOperator, Operator, TableScanOperator,
+    NestedLoopJoinOperator, Operator, SortOperator, TableScanOperator,
 };
+use crate::storage::page_manager::PageManagerLock;
+use crate::storage::tuple::Tuple;
+use std::sync::Arc;
+
+fn insert_tuple(pm: &Arc<PageManagerLock>, id: u64, values: &[(&str, DataType)]) {
+    let mut tuple = Tuple::new(id);
+    for (name, value) in values {
+        tuple.set_value(name, ColumnValue::new(value.clone()));
+    }
+    pm.write().insert(&tuple.to_bytes().unwrap()).unwrap();
+}
 
 #[test]
 fn test_nested_loop_join_creation() -> Result<()> {
@@ -382,3 +394,159 @@ fn test_join_operator_trait_implementation() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_joins_match_numeric_keys_across_integer_types() -> Result<()> {
+    let (_left_dir, users) = common::create_test_page_manager();
+    let (_right_dir, emails) = common::create_test_page_manager();
+    for id in [2, 10, 9] {
+        insert_tuple(&users, id as u64, &[("id", DataType::Integer(id))]);
+    }
+    for user_id in [9i64, 10, 11] {
+        insert_tuple(
+            &emails,
+            user_id as u64,
+            &[
+                ("user_id", DataType::BigInt(user_id)),
+                ("email", DataType::Varchar(format!("u{user_id}@x"))),
+            ],
+        );
+    }
+    let scan = |pm: &Arc<PageManagerLock>, columns: &[&str]| -> Result<Box<dyn Operator>> {
+        Ok(Box::new(TableScanOperator::new(
+            "t".to_string(),
+            pm.clone(),
+            None,
+            None,
+            columns.iter().map(|c| c.to_string()).collect(),
+        )?))
+    };
+    let condition = || JoinCondition {
+        left_column: "id".to_string(),
+        right_column: "user_id".to_string(),
+        operator: JoinOperator::Equal,
+    };
+    let emails_of = |mut op: Box<dyn Operator>| -> Result<Vec<String>> {
+        let mut out = Vec::new();
+        while let Some(row) = op.next()? {
+            if let Some(ColumnValue {
+                data_type: DataType::Varchar(email),
+                ..
+            }) = row.get_value("email")
+            {
+                out.push(email.clone());
+            }
+        }
+        out.sort();
+        Ok(out)
+    };
+
+    let hash = HashJoinOperator::new(
+        scan(&users, &["id"])?,
+        scan(&emails, &["user_id", "email"])?,
+        condition(),
+        JoinType::Inner,
+        16,
+    )?;
+    assert_eq!(emails_of(Box::new(hash))?, ["u10@x", "u9@x"]);
+
+    let nested = NestedLoopJoinOperator::new(
+        scan(&users, &["id"])?,
+        scan(&emails, &["user_id", "email"])?,
+        condition(),
+        JoinType::Inner,
+        16,
+    )?;
+    assert_eq!(emails_of(Box::new(nested))?, ["u10@x", "u9@x"]);
+    Ok(())
+}
+
+#[test]
+fn test_joins_never_match_null_keys() -> Result<()> {
+    let (_left_dir, users) = common::create_test_page_manager();
+    let (_right_dir, emails) = common::create_test_page_manager();
+    let ids = [
+        DataType::Null,
+        DataType::Integer(3),
+        DataType::Integer(1),
+        DataType::Double(2.0),
+    ];
+    for (i, id) in ids.into_iter().enumerate() {
+        insert_tuple(&users, i as u64, &[("id", id)]);
+    }
+    let user_ids = [
+        (DataType::BigInt(2), "u2@x"),
+        (DataType::Null, "unull@x"),
+        (DataType::BigInt(1), "u1@x"),
+        (DataType::BigInt(5), "u5@x"),
+        (DataType::BigInt(7), "u7@x"),
+    ];
+    for (i, (user_id, email)) in user_ids.into_iter().enumerate() {
+        insert_tuple(
+            &emails,
+            i as u64,
+            &[
+                ("user_id", user_id),
+                ("email", DataType::Varchar(email.to_string())),
+            ],
+        );
+    }
+    // Sorted on the join column, NULLs last, for the merge join.
+    let scan = |pm: &Arc<PageManagerLock>, columns: &[&str]| -> Result<Box<dyn Operator>> {
+        let columns: Vec<String> = columns.iter().map(|c| c.to_string()).collect();
+        let scan =
+            TableScanOperator::new("t".to_string(), pm.clone(), None, None, columns.clone())?;
+        Ok(Box::new(SortOperator::new(
+            Box::new(scan),
+            vec![(columns[0].clone(), true)],
+            columns,
+        )?))
+    };
+    let condition = |operator| JoinCondition {
+        left_column: "id".to_string(),
+        right_column: "user_id".to_string(),
+        operator,
+    };
+    let emails_of = |mut op: Box<dyn Operator>| -> Result<Vec<String>> {
+        let mut out = Vec::new();
+        while let Some(row) = op.next()? {
+            if let Some(ColumnValue {
+                data_type: DataType::Varchar(email),
+                ..
+            }) = row.get_value("email")
+            {
+                out.push(email.clone());
+            }
+        }
+        out.sort();
+        Ok(out)
+    };
+
+    let hash = HashJoinOperator::new(
+        scan(&users, &["id"])?,
+        scan(&emails, &["user_id", "email"])?,
+        condition(JoinOperator::Equal),
+        JoinType::Inner,
+        16,
+    )?;
+    assert_eq!(emails_of(Box::new(hash))?, ["u1@x", "u2@x"]);
+
+    let merge = MergeJoinOperator::new(
+        scan(&users, &["id"])?,
+        scan(&emails, &["user_id", "email"])?,
+        condition(JoinOperator::Equal),
+        JoinType::Inner,
+    )?;
+    assert_eq!(emails_of(Box::new(merge))?, ["u1@x", "u2@x"]);
+
+    // 3 > 1, 3 > 2 and 2.0 > 1; nothing compares with NULL.
+    let nested = NestedLoopJoinOperator::new(
+        scan(&users, &["id"])?,
+        scan(&emails, &["user_id", "email"])?,
+        condition(JoinOperator::GreaterThan),
+        JoinType::Inner,
+        16,
+    )?;
+    assert_eq!(emails_of(Box::new(nested))?, ["u1@x", "u1@x", "u2@x"]);
+    Ok(())
+}
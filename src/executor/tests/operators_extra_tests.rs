@@ -132,7 +132,7 @@ fn test_aggregation_sort_factory_both_modes() -> Result<()> {
 #[test]
 fn test_index_scan_operator_variants() -> Result<()> {
     let (_tmp, pm) = common::create_test_page_manager();
-    let idx = Arc::new(Mutex::new(crate::storage::index::BPlusTree::new(3)));
+    let idx = Arc::new(Mutex::new(crate::storage::index::BPlusTree::new(3).into()));
     let schema = vec!["k".to_string(), "v".to_string()];
     for (iop, val) in [
         (IndexOperator::LessThan, "5"),
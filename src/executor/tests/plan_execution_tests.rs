@@ -4,9 +4,9 @@ use super::common;
 use crate::executor::{QueryExecutor, QueryExecutorConfig};
 use crate::planner::planner::{
     AggregateFunction, DistinctNode, ExecutionPlan, FilterNode, GroupByNode, IndexCondition,
-    IndexScanNode, InsertNode, JoinNode, JoinType, LimitNode, OffsetNode, PlanMetadata, PlanNode,
-    PlanStatistics, ProjectionColumn, ProjectionNode, SortColumn, SortDirection, SortNode,
-    TableScanNode,
+    IndexScanNode, InsertNode, JoinAlgorithm, JoinNode, JoinType, LimitNode, OffsetNode,
+    PlanMetadata, PlanNode, PlanStatistics, ProjectionColumn, ProjectionNode, SortColumn,
+    SortDirection, SortNode, TableScanNode,
 };
 use std::sync::Arc;
 use std::time::SystemTime;
@@ -16,6 +16,7 @@ fn plan_meta() -> PlanMetadata {
         estimated_cost: 1.0,
         estimated_rows: 10,
         created_at: SystemTime::now(),
+        actual_rows: None,
         statistics: PlanStatistics {
             operator_count: 3,
             max_depth: 3,
@@ -159,6 +160,7 @@ fn test_executor_sort() -> crate::common::Result<()> {
             sort_columns: vec![SortColumn {
                 column: "id".to_string(),
                 direction: SortDirection::Desc,
+                nulls: None,
             }],
             input: Box::new(PlanNode::Limit(LimitNode {
                 limit: 5,
@@ -237,6 +239,7 @@ fn test_executor_join_small() -> crate::common::Result<()> {
             left: Box::new(left),
             right: Box::new(right),
             cost: 1.0,
+            algorithm: JoinAlgorithm::NestedLoop,
         }),
         metadata: plan_meta(),
     };
@@ -254,6 +257,7 @@ fn test_executor_join_types() -> crate::common::Result<()> {
         QueryExecutorConfig {
             enable_parallel_execution: false,
             num_worker_threads: 1,
+            ..Default::default()
         },
     )?;
     let make_join = |jt: JoinType| ExecutionPlan {
@@ -271,6 +275,7 @@ fn test_executor_join_types() -> crate::common::Result<()> {
                 cost: 1.0,
             })),
             cost: 1.0,
+            algorithm: JoinAlgorithm::NestedLoop,
         }),
         metadata: plan_meta(),
     };
@@ -319,7 +324,7 @@ fn test_executor_index_scan_with_index() -> crate::common::Result<()> {
         pm.clone(),
     ));
     let idx = Arc::new(std::sync::Mutex::new(
-        crate::storage::index::BPlusTree::new(4),
+        crate::storage::index::BPlusTree::new(4).into(),
     ));
     factory.add_index("t", "ix_id", idx);
     let ex = QueryExecutor::new(factory)?;
@@ -52,7 +52,7 @@ fn test_table_scan_with_filter() -> Result<()> {
 #[test]
 fn test_index_scan_operator_creation() -> Result<()> {
     let (_temp, page_manager) = common::create_test_page_manager();
-    let index = Arc::new(Mutex::new(BPlusTree::new(3)));
+    let index = Arc::new(Mutex::new(BPlusTree::new(3).into()));
     let schema = vec!["id".to_string(), "name".to_string(), "age".to_string()];
 
     let search_conditions = vec![IndexCondition {
@@ -242,3 +242,204 @@ fn test_operator_trait_implementation() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_table_scan_rows_share_one_schema() -> Result<()> {
+    let (_temp, page_manager) = common::create_test_page_manager();
+    common::seed_id_data_rows(&page_manager, 5);
+
+    for projection in [
+        vec!["*".to_string()],
+        vec!["data".to_string(), "id".to_string()],
+    ] {
+        let mut operator = TableScanOperator::new(
+            "t".to_string(),
+            page_manager.clone(),
+            None,
+            None,
+            projection,
+        )?;
+        let mut rows = Vec::new();
+        while let Some(row) = operator.next()? {
+            rows.push(row);
+        }
+        assert_eq!(rows.len(), 5);
+        assert!(rows
+            .iter()
+            .all(|r| Arc::ptr_eq(r.schema(), rows[0].schema())));
+        assert!(rows
+            .iter()
+            .all(|r| r.has_column("id") && r.has_column("data")));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_table_scan_reads_stored_tuples_and_skips_deleted_ones() -> Result<()> {
+    use crate::common::types::{ColumnValue, DataType};
+    use crate::storage::page_manager::{PageManager, PageManagerConfig, PageManagerLock};
+    use crate::storage::tuple::Tuple;
+
+    let (temp, page_manager) = common::create_test_page_manager();
+    let tuple = |id: u64, name: &str| {
+        let mut t = Tuple::new(id);
+        t.set_value(
+            "name",
+            ColumnValue::new(DataType::Varchar(name.to_string())),
+        );
+        t
+    };
+    {
+        let mut pm = page_manager.write();
+        let mut record_ids = Vec::new();
+        for (id, name) in [(1, "ann"), (2, "bo"), (3, "cy"), (4, "di")] {
+            record_ids.push(pm.insert(&tuple(id, name).to_bytes()?)?.record_id);
+        }
+        // A freed slot and a tombstoned tuple are both skipped; an update is seen in place.
+        pm.delete(record_ids[1])?;
+        let mut tombstone = tuple(3, "cy");
+        tombstone.is_deleted = true;
+        pm.update(record_ids[2], &tombstone.to_bytes()?)?;
+        pm.update(record_ids[3], &tuple(4, "dee").to_bytes()?)?;
+        pm.flush_dirty_pages()?;
+    }
+    drop(page_manager);
+
+    // Read back from the table file.
+    let reopened = PageManager::open(
+        temp.path().to_path_buf(),
+        "test_table",
+        PageManagerConfig::default(),
+    )?;
+    let mut operator = TableScanOperator::new(
+        "test_table".to_string(),
+        Arc::new(PageManagerLock::new(reopened)),
+        None,
+        None,
+        vec!["id".to_string(), "name".to_string()],
+    )?;
+    let mut rows = Vec::new();
+    while let Some(row) = operator.next()? {
+        let name = match row.get_value("name").map(|v| &v.data_type) {
+            Some(DataType::Varchar(name)) => name.clone(),
+            other => panic!("unexpected name {other:?}"),
+        };
+        rows.push(name);
+    }
+    rows.sort();
+    assert_eq!(rows, ["ann", "dee"]);
+    Ok(())
+}
+
+#[test]
+fn test_table_scan_and_page_manager_share_pinned_buffer_frames() -> Result<()> {
+    use crate::common::types::{ColumnValue, DataType};
+    use crate::storage::tuple::Tuple;
+
+    let (_temp, page_manager) = common::create_test_page_manager();
+    let record_id = {
+        let mut pm = page_manager.write();
+        let mut t = Tuple::new(1);
+        t.set_value("name", ColumnValue::new(DataType::Varchar("ann".into())));
+        let record_id = pm.insert(&t.to_bytes()?)?.record_id;
+        pm.flush_dirty_pages()?;
+        record_id
+    };
+    let page_id = record_id >> 32;
+    let lookups = || {
+        page_manager
+            .read()
+            .buffer_pool_shard_stats()
+            .iter()
+            .fold((0, 0), |(h, m), s| (h + s.hits, m + s.misses))
+    };
+
+    let mut operator = TableScanOperator::new(
+        "test_table".to_string(),
+        page_manager.clone(),
+        None,
+        None,
+        vec!["id".to_string(), "name".to_string()],
+    )?;
+    assert!(operator.next()?.is_some());
+    // The scan holds the page's frame pinned in the table's buffer pool ...
+    assert_eq!(page_manager.read().page_pin_count(page_id), 1);
+
+    // ... where the page manager finds the same frame instead of reading the page again.
+    let (hits, misses) = lookups();
+    assert!(page_manager.read().get_record(record_id)?.is_some());
+    assert_eq!(lookups(), (hits + 1, misses));
+    assert_eq!(page_manager.read().page_pin_count(page_id), 1);
+
+    drop(operator);
+    assert_eq!(page_manager.read().page_pin_count(page_id), 0);
+    Ok(())
+}
+
+#[test]
+fn test_index_range_scan_orders_integer_keys_by_value() -> Result<()> {
+    use crate::common::types::{ColumnValue, DataType};
+    use crate::storage::index::Index;
+    use crate::storage::index_registry::index_key;
+    use crate::storage::tuple::Tuple;
+
+    let (_temp, page_manager) = common::create_test_page_manager();
+    let mut tree = BPlusTree::new(3);
+    {
+        let mut pm = page_manager.write();
+        // Index texts as the engine writes them: `9` sorts before `10`, NULL (``) after both.
+        for (id, k) in [
+            (1, Some(8)),
+            (2, Some(9)),
+            (3, Some(10)),
+            (4, Some(11)),
+            (5, None),
+        ] {
+            let mut t = Tuple::new(id);
+            let value = k.map_or(DataType::Null, DataType::Integer);
+            t.set_value("k", ColumnValue::new(value));
+            let record_id = pm.insert(&t.to_bytes()?)?.record_id;
+            let text = k.map(|k| k.to_string()).unwrap_or_default();
+            tree.insert(index_key([text.as_str()]), vec![record_id])?;
+        }
+    }
+    let index = Arc::new(Mutex::new(tree.into()));
+    let scan = |conditions: &[(IndexOperator, &str)]| -> Result<Vec<i32>> {
+        let conditions = conditions
+            .iter()
+            .map(|(operator, value)| IndexCondition {
+                column: "k".to_string(),
+                operator: operator.clone(),
+                value: value.to_string(),
+            })
+            .collect();
+        let mut operator = IndexScanOperator::new(
+            "test_table".to_string(),
+            "idx_k".to_string(),
+            index.clone(),
+            page_manager.clone(),
+            conditions,
+            vec!["k".to_string()],
+        )?;
+        let mut keys = Vec::new();
+        while let Some(row) = operator.next()? {
+            match row.get_value("k").map(|v| &v.data_type) {
+                Some(DataType::Integer(k)) => keys.push(*k),
+                other => panic!("unexpected k {other:?}"),
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    };
+
+    use IndexOperator::*;
+    assert_eq!(
+        scan(&[(GreaterThanOrEqual, "9"), (LessThanOrEqual, "10")])?,
+        [9, 10]
+    );
+    assert_eq!(scan(&[(GreaterThan, "9"), (LessThan, "11")])?, [10]);
+    assert_eq!(scan(&[(GreaterThan, "9")])?, [10, 11]);
+    assert_eq!(scan(&[(LessThan, "10")])?, [8, 9]);
+    assert_eq!(scan(&[(Equal, "10")])?, [10]);
+    Ok(())
+}
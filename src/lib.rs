@@ -46,31 +46,57 @@
 #![allow(unused_comparisons)]
 #![allow(unused_must_use)]
 
+// Modules behind `native` need threads, tokio, sockets or files; the rest (parser, planner,
+// catalog, in-memory storage and `playground`) also build for `wasm32-unknown-unknown`.
 pub mod analyzer;
+#[cfg(feature = "native")]
+pub mod bench;
 pub mod catalog;
+#[cfg(feature = "native")]
 pub mod cli;
 pub mod common;
+#[cfg(feature = "native")]
 pub mod core;
+#[cfg(feature = "native")]
 pub mod debug;
+#[cfg(feature = "native")]
 pub mod embedded;
 pub mod executor;
+#[cfg(feature = "native")]
 pub mod logging;
+#[cfg(feature = "native")]
 pub mod network;
 pub mod parser;
 pub mod planner;
+pub mod playground;
+pub mod query_builder;
+#[cfg(feature = "native")]
+pub mod replay;
+#[cfg(feature = "native")]
+pub mod result_display;
+#[cfg(feature = "native")]
 pub mod sql_session;
 pub mod storage;
 pub mod test_env;
+#[cfg(feature = "native")]
 pub mod tpcc_workload;
+#[cfg(feature = "native")]
 pub mod tracing_setup;
 
 pub use common::DurabilityMode;
-pub use embedded::{Config, Connection, Db, Transaction};
+#[cfg(feature = "native")]
+pub use embedded::{Config, Connection, Db, RetryPolicy, Transaction};
+#[cfg(feature = "native")]
 pub use network::SqlEngine;
+#[cfg(feature = "native")]
 pub use sql_session::SqlSession;
 
 pub use common::error::{Error, Result};
+pub use common::row_mapping::{FromRow, FromValue, ResultRow, ToValue};
 pub use common::types::*;
+/// `#[derive(FromRow)]` (feature `derive`): maps result columns to the struct's fields by name.
+#[cfg(feature = "derive")]
+pub use rustdb_derive::FromRow;
 
 use std::path::PathBuf;
 
@@ -78,22 +104,144 @@ use std::path::PathBuf;
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// Main database handle (data directory and lifecycle).
+///
+/// [`Self::open`] opens an [`SqlEngine`] on the directory, which sets up the heap files and
+/// buffer pool, the catalog, the WAL (replaying it) and the transaction bookkeeping;
+/// [`Self::execute`] then runs statements through the full parse, analyze, plan and execute
+/// pipeline in one session.
 pub struct Database {
     data_path: Option<PathBuf>,
+    /// Tables [`Self::into_sql_engine`] loads before returning (see [`Self::preload_schema`]).
+    preload: Vec<String>,
+    /// Engine opened on `data_path`
+    #[cfg(feature = "native")]
+    engine: Option<SqlEngine>,
+    /// Session [`Self::execute`] runs in (its open transaction, settings, ...)
+    #[cfg(feature = "native")]
+    session: network::engine::SessionContext,
+}
+
+/// Outcome of one statement run with [`Database::execute`]
+#[cfg(feature = "native")]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryResult {
+    /// Output column names (empty for statements without a result set)
+    pub columns: Vec<String>,
+    /// Output rows; values line up with `columns`
+    pub rows: Vec<Vec<DataType>>,
+    /// Rows inserted, updated or deleted
+    pub rows_affected: u64,
+}
+
+#[cfg(feature = "native")]
+impl QueryResult {
+    /// Row `index`, with values looked up by column name
+    pub fn row(&self, index: usize) -> Option<ResultRow<'_>> {
+        self.rows
+            .get(index)
+            .map(|values| ResultRow::from_values(&self.columns, values.clone()))
+    }
+}
+
+#[cfg(feature = "native")]
+impl QueryResult {
+    /// Runs `run` and keeps the values the engine built its result set from (see
+    /// [`network::sql_engine::typed_result_scope`]); cells of any other result set are kept as
+    /// [`DataType::Text`].
+    fn capture(
+        run: impl FnOnce() -> std::result::Result<
+            network::engine::EngineOutput,
+            network::engine::EngineError,
+        >,
+    ) -> Result<Self> {
+        let scope = network::sql_engine::typed_result_scope();
+        let output = run().map_err(|e| Error::database(e.message))?;
+        let values = scope.finish(&output);
+        Ok(match output {
+            network::engine::EngineOutput::ResultSet { columns, rows } => Self {
+                rows: match values {
+                    Some(values) => values
+                        .into_iter()
+                        .map(|row| {
+                            row.into_iter()
+                                .map(common::row_mapping::result_value)
+                                .collect()
+                        })
+                        .collect(),
+                    None => rows
+                        .into_iter()
+                        .map(|cells| cells.into_iter().map(DataType::Text).collect())
+                        .collect(),
+                },
+                columns,
+                rows_affected: 0,
+            },
+            network::engine::EngineOutput::ExecutionOk { rows_affected } => Self {
+                rows_affected,
+                ..Self::default()
+            },
+        })
+    }
+}
+
+/// Statement prepared with [`Database::prepare`]: parsed once, then run with
+/// [`Self::execute_with_params`] as often as needed in the database's session.
+#[cfg(feature = "native")]
+pub struct PreparedStatement<'db> {
+    db: &'db mut Database,
+    prepared: network::sql_engine::PreparedSql,
+}
+
+#[cfg(feature = "native")]
+impl PreparedStatement<'_> {
+    /// Values [`Self::execute_with_params`] takes
+    pub fn param_count(&self) -> usize {
+        self.prepared.param_count()
+    }
+
+    /// Runs the statement with `params[n - 1]` as the value of placeholder `$n` (`?`
+    /// placeholders are numbered in order).
+    ///
+    /// Returns an error when `params` does not supply one value per placeholder, or when the
+    /// statement fails.
+    pub fn execute_with_params(&mut self, params: &[ColumnValue]) -> Result<QueryResult> {
+        let engine = self
+            .db
+            .engine
+            .as_ref()
+            .ok_or_else(|| Error::database("database closed"))?;
+        let session = &mut self.db.session;
+        QueryResult::capture(|| engine.execute_prepared(&self.prepared, params, session))
+    }
 }
 
 impl Database {
     /// Creates a new in-memory database handle (no persistent directory until [`Self::open`]).
     pub fn new() -> Result<Self> {
-        Ok(Self { data_path: None })
+        Ok(Self {
+            data_path: None,
+            preload: Vec::new(),
+            #[cfg(feature = "native")]
+            engine: None,
+            #[cfg(feature = "native")]
+            session: Default::default(),
+        })
     }
 
-    /// Opens or creates a database directory at `path`.
+    /// Opens or creates a database directory at `path` (and, with the `native` feature, the
+    /// engine on it, recovering from the WAL).
     pub fn open(path: &str) -> Result<Self> {
         let p = PathBuf::from(path);
         std::fs::create_dir_all(&p)
             .map_err(|e| Error::database(format!("create_dir {}: {}", path, e)))?;
-        Ok(Self { data_path: Some(p) })
+        Ok(Self {
+            #[cfg(feature = "native")]
+            engine: Some(SqlEngine::open(p.clone())?),
+            #[cfg(feature = "native")]
+            session: Default::default(),
+            data_path: Some(p),
+            preload: Vec::new(),
+        })
     }
 
     /// Active data directory, if any.
@@ -101,32 +249,104 @@ impl Database {
         self.data_path.as_deref()
     }
 
-    /// Releases resources and clears the handle.
+    /// Has [`Self::into_sql_engine`] load the constraint maps and indexes of `tables` (and of
+    /// the tables linked to them by foreign keys) before returning, rather than on their first
+    /// use (see [`SqlEngine::preload_schema`]).
+    pub fn preload_schema(&mut self, tables: &[&str]) {
+        self.preload.extend(tables.iter().map(|t| t.to_string()));
+    }
+
+    /// Releases resources and clears the handle: a transaction left open by [`Self::execute`]
+    /// is rolled back and the engine is closed.
     pub fn close(&mut self) -> Result<()> {
+        #[cfg(feature = "native")]
+        if let Some(engine) = self.engine.take() {
+            use network::engine::EngineHandle;
+            if self.session.transaction.is_some() {
+                let _ = engine.execute_sql("ROLLBACK", &mut self.session);
+            }
+        }
         self.data_path = None;
         Ok(())
     }
 
-    /// Consumes this handle and opens an [`SqlEngine`] on the same data directory.
+    /// Runs one SQL statement in this handle's session.
+    ///
+    /// Returns an error if the database was never opened with [`Self::open`], or when the
+    /// statement fails.
+    #[cfg(feature = "native")]
+    pub fn execute(&mut self, sql: &str) -> Result<QueryResult> {
+        use network::engine::EngineHandle;
+        let engine = self
+            .engine
+            .as_ref()
+            .ok_or_else(|| Error::database("no data directory; call Database::open first"))?;
+        let session = &mut self.session;
+        QueryResult::capture(|| engine.execute_sql(sql, session))
+    }
+
+    /// Parses `sql`, one statement whose values may be `$1`, `$2`, ... or `?` placeholders, for
+    /// [`PreparedStatement::execute_with_params`], which runs it without parsing it again.
+    ///
+    /// Returns an error if the database was never opened with [`Self::open`], or when `sql` is
+    /// not one statement or has placeholders outside `SELECT`, `INSERT`, `UPDATE`, `DELETE` or
+    /// `EXPLAIN`.
+    #[cfg(feature = "native")]
+    pub fn prepare(&mut self, sql: &str) -> Result<PreparedStatement<'_>> {
+        let engine = self
+            .engine
+            .as_ref()
+            .ok_or_else(|| Error::database("no data directory; call Database::open first"))?;
+        let prepared = engine
+            .prepare(sql)
+            .map_err(|e| Error::database(e.message))?;
+        Ok(PreparedStatement { db: self, prepared })
+    }
+
+    /// Consumes this handle and returns its [`SqlEngine`].
     ///
     /// Returns an error if the database was never opened with [`Self::open`].
+    #[cfg(feature = "native")]
     pub fn into_sql_engine(mut self) -> Result<SqlEngine> {
-        let path = self
-            .data_path
+        if self.session.transaction.is_some() {
+            self.execute("ROLLBACK")?;
+        }
+        let engine = self
+            .engine
             .take()
             .ok_or_else(|| Error::database("no data directory; call Database::open first"))?;
-        SqlEngine::open(path)
+        self.data_path = None;
+        if !self.preload.is_empty() {
+            let tables: Vec<&str> = self.preload.iter().map(String::as_str).collect();
+            engine.preload_schema(&tables)?;
+        }
+        Ok(engine)
+    }
+
+    /// Dry run of `sql` against this database's catalog: parses, resolves and plans each
+    /// statement without executing it or opening the engine (see [`network::sql_engine::Validator`]).
+    ///
+    /// DDL in `sql` is seen by the statements after it but is not applied.
+    #[cfg(feature = "native")]
+    pub fn validate(&self, sql: &str) -> Result<network::sql_engine::ValidationReport> {
+        let mut validator = match &self.data_path {
+            Some(path) => network::sql_engine::Validator::for_data_dir(path),
+            None => network::sql_engine::Validator::empty(),
+        }
+        .map_err(|e| Error::database(e.message))?;
+        Ok(validator.check(sql))
     }
 }
 
 /// Ensures `path` exists and returns an [`SqlEngine`] rooted there.
+#[cfg(feature = "native")]
 pub fn open_sql_engine(path: impl AsRef<std::path::Path>) -> Result<SqlEngine> {
-    let p = path.as_ref().to_path_buf();
-    let _ = Database::open(
-        p.to_str()
+    Database::open(
+        path.as_ref()
+            .to_str()
             .ok_or_else(|| Error::database("invalid UTF-8 in database path"))?,
-    )?;
-    SqlEngine::open(p)
+    )?
+    .into_sql_engine()
 }
 
 impl Drop for Database {
@@ -159,4 +379,71 @@ mod crate_tests {
         db.close()?;
         Ok(())
     }
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn test_database_execute_runs_sql_and_close_rolls_back() -> Result<()> {
+        let dir = tempfile::TempDir::new().map_err(|e| Error::database(e.to_string()))?;
+        let path = dir.path().to_str().expect("utf-8");
+        {
+            let mut db = Database::open(path)?;
+            db.execute("CREATE TABLE users (id INTEGER, name VARCHAR(20))")?;
+            let inserted =
+                db.execute("INSERT INTO users (id, name) VALUES (1, 'ann'), (2, 'bo')")?;
+            assert_eq!(inserted.rows_affected, 2);
+            db.execute("BEGIN TRANSACTION")?;
+            db.execute("INSERT INTO users (id, name) VALUES (3, 'cy')")?;
+            assert!(db.execute("SELEC 1").is_err());
+            db.close()?;
+            assert!(db.execute("SELECT id FROM users").is_err());
+        }
+
+        let mut db = Database::open(path)?;
+        let result = db.execute("SELECT id, name FROM users ORDER BY id")?;
+        assert_eq!(result.columns, ["id", "name"]);
+        assert_eq!(result.rows.len(), 2);
+        let row = result.row(1).expect("second row");
+        assert_eq!(row.get::<i64>("id")?, 2);
+        assert_eq!(row.get::<String>("name")?, "bo");
+        assert!(Database::new()?.execute("SELECT 1").is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn test_prepared_statement_binds_parameters() -> Result<()> {
+        use crate::{ColumnValue, DataType};
+        let dir = tempfile::TempDir::new().map_err(|e| Error::database(e.to_string()))?;
+        let mut db = Database::open(dir.path().to_str().expect("utf-8"))?;
+        db.execute("CREATE TABLE users (id INTEGER, name VARCHAR(20), score REAL)")?;
+
+        let mut insert = db.prepare("INSERT INTO users (id, name, score) VALUES (?, ?, ?)")?;
+        assert_eq!(insert.param_count(), 3);
+        for (id, name, score) in [(1, "ann", 1.5), (2, "o'neil", 2.0), (3, "cy", -3.25)] {
+            let params = [
+                ColumnValue::new(DataType::Integer(id)),
+                ColumnValue::new(DataType::Varchar(name.to_string())),
+                ColumnValue::new(DataType::Double(score)),
+            ];
+            assert_eq!(insert.execute_with_params(&params)?.rows_affected, 1);
+        }
+        assert!(insert
+            .execute_with_params(&[ColumnValue::new(DataType::Integer(4))])
+            .is_err());
+
+        let mut select = db.prepare("SELECT name, score FROM users WHERE id = $1 OR id = $2")?;
+        assert_eq!(select.param_count(), 2);
+        let ids = |a, b| [a, b].map(|id| ColumnValue::new(DataType::Integer(id)));
+        let result = select.execute_with_params(&ids(2, 2))?;
+        assert_eq!(result.rows.len(), 1);
+        let row = result.row(0).expect("row");
+        assert_eq!(row.get::<String>("name")?, "o'neil");
+        assert_eq!(row.get::<f64>("score")?, 2.0);
+        assert_eq!(select.execute_with_params(&ids(1, 3))?.rows.len(), 2);
+
+        assert!(db.prepare("SELECT 1; SELECT 2").is_err());
+        assert!(db.prepare("SELECT ? FROM users WHERE id = $1").is_err());
+        assert!(db.execute("SELECT name FROM users WHERE id = ?").is_err());
+        Ok(())
+    }
 }
@@ -5,6 +5,11 @@
 //! - Capturing the state of active transactions
 //! - Flushing dirty pages to disk
 //! - Managing log size
+//!
+//! Checkpoints are written by a background task. [`CheckpointManager::create_checkpoint`]
+//! resolves with the [`CheckpointInfo`] of the checkpoint the task wrote for that request, and
+//! [`CheckpointManager::wait_for_checkpoint`] waits until a checkpoint at or past a given LSN has
+//! completed, whoever triggered it.
 
 use crate::common::{Error, Result};
 use crate::logging::log_record::{LogRecord, LogSequenceNumber, TransactionId};
@@ -12,9 +17,10 @@ use crate::logging::log_writer::LogWriter;
 use crate::storage::database_file::PageId;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
-use tokio::sync::{mpsc, Notify};
+use tokio::sync::{mpsc, oneshot, watch};
 use tokio::task::JoinHandle;
 
 /// Checkpoint information
@@ -59,6 +65,8 @@ pub struct CheckpointConfig {
     pub flush_batch_size: usize,
     /// When true, suppress `println!` progress output (e.g. embedders / SqlEngine).
     pub quiet: bool,
+    /// File replaced atomically with the [`CheckpointInfo`] of every completed checkpoint
+    pub metadata_path: Option<PathBuf>,
 }
 
 impl Default for CheckpointConfig {
@@ -73,6 +81,7 @@ impl Default for CheckpointConfig {
             flush_threads: 4,
             flush_batch_size: 100,
             quiet: false,
+            metadata_path: None,
         }
     }
 }
@@ -125,11 +134,11 @@ enum CheckpointCommand {
     /// Create checkpoint
     CreateCheckpoint {
         trigger: CheckpointTrigger,
-        response_tx: Option<tokio::sync::oneshot::Sender<Result<CheckpointInfo>>>,
+        response_tx: Option<oneshot::Sender<Result<CheckpointInfo>>>,
     },
     /// Get statistics
     GetStatistics {
-        response_tx: tokio::sync::oneshot::Sender<CheckpointStatistics>,
+        response_tx: oneshot::Sender<CheckpointStatistics>,
     },
     /// Stop the system
     Shutdown,
@@ -150,8 +159,8 @@ pub struct CheckpointManager {
     checkpoint_id_generator: Arc<Mutex<u64>>,
     /// Command channel
     command_tx: mpsc::UnboundedSender<CheckpointCommand>,
-    /// Checkpoint completion notifications
-    checkpoint_notify: Arc<Notify>,
+    /// LSN of the last completed checkpoint, published by the background task
+    checkpoint_lsn: watch::Receiver<LogSequenceNumber>,
     /// Background task
     background_handle: Option<JoinHandle<()>>,
     /// Active transactions (external source)
@@ -166,6 +175,7 @@ impl CheckpointManager {
     /// Creates a new checkpoint manager
     pub fn new(config: CheckpointConfig, log_writer: Arc<LogWriter>) -> Self {
         let (command_tx, command_rx) = mpsc::unbounded_channel();
+        let (checkpoint_lsn_tx, checkpoint_lsn) = watch::channel(0);
 
         let mut manager = Self {
             config: config.clone(),
@@ -173,7 +183,7 @@ impl CheckpointManager {
             statistics: Arc::new(RwLock::new(CheckpointStatistics::default())),
             checkpoint_id_generator: Arc::new(Mutex::new(1)),
             command_tx,
-            checkpoint_notify: Arc::new(Notify::new()),
+            checkpoint_lsn,
             background_handle: None,
             active_transactions: Arc::new(RwLock::new(HashSet::new())),
             dirty_pages: Arc::new(RwLock::new(HashSet::new())),
@@ -181,7 +191,7 @@ impl CheckpointManager {
         };
 
         // Start background task
-        manager.start_background_task(command_rx);
+        manager.start_background_task(command_rx, checkpoint_lsn_tx);
 
         manager
     }
@@ -205,12 +215,12 @@ impl CheckpointManager {
     fn start_background_task(
         &mut self,
         mut command_rx: mpsc::UnboundedReceiver<CheckpointCommand>,
+        checkpoint_lsn: watch::Sender<LogSequenceNumber>,
     ) {
         let config = self.config.clone();
         let log_writer = self.log_writer.clone();
         let statistics = self.statistics.clone();
         let checkpoint_id_gen = self.checkpoint_id_generator.clone();
-        let checkpoint_notify = self.checkpoint_notify.clone();
         let active_transactions = self.active_transactions.clone();
         let dirty_pages = self.dirty_pages.clone();
         let dirty_page_flusher = self.dirty_page_flusher.clone();
@@ -242,11 +252,19 @@ impl CheckpointManager {
                                     trigger,
                                 ).await;
 
+                                if let Ok(info) = &result {
+                                    checkpoint_lsn.send_if_modified(|last| {
+                                        let advanced = info.lsn > *last;
+                                        if advanced {
+                                            *last = info.lsn;
+                                        }
+                                        advanced
+                                    });
+                                }
+
                                 if let Some(tx) = response_tx {
                                     let _ = tx.send(result);
                                 }
-
-                                checkpoint_notify.notify_waiters();
                             }
                             CheckpointCommand::GetStatistics { response_tx } => {
                                 let stats = statistics.read().unwrap().clone();
@@ -416,6 +434,17 @@ impl CheckpointManager {
             flushed_pages,
         };
 
+        // Persist the checkpoint metadata; a crash keeps the previous file intact.
+        if let Some(path) = config.metadata_path.clone() {
+            let json = serde_json::to_vec_pretty(&checkpoint_info)
+                .map_err(|e| Error::internal(format!("Checkpoint metadata encode: {}", e)))?;
+            tokio::task::spawn_blocking(move || {
+                crate::storage::atomic_file::write_atomic(&path, &json)
+            })
+            .await
+            .map_err(|e| Error::internal(format!("Checkpoint metadata join error: {}", e)))??;
+        }
+
         // Update statistics
         {
             let mut stats = statistics.write().unwrap();
@@ -472,40 +501,55 @@ impl CheckpointManager {
     }
 
     /// Creates a manual checkpoint
+    ///
+    /// Resolves once the background task has written the checkpoint; the returned
+    /// [`CheckpointInfo::lsn`] is the LSN of its WAL record.
     pub async fn create_checkpoint(&self) -> Result<CheckpointInfo> {
-        let (response_tx, response_rx) = tokio::sync::oneshot::channel();
-
-        self.command_tx
-            .send(CheckpointCommand::CreateCheckpoint {
-                trigger: CheckpointTrigger::Manual,
-                response_tx: Some(response_tx),
-            })
-            .map_err(|_| Error::internal("Failed to send checkpoint creation command"))?;
-
-        response_rx
-            .await
-            .map_err(|_| Error::internal("Failed to receive checkpoint creation result"))?
+        self.request_checkpoint(CheckpointTrigger::Manual).await
     }
 
     /// Creates a checkpoint during shutdown
     pub async fn create_shutdown_checkpoint(&self) -> Result<CheckpointInfo> {
-        let (response_tx, response_rx) = tokio::sync::oneshot::channel();
+        self.request_checkpoint(CheckpointTrigger::Shutdown).await
+    }
+
+    /// Queues a checkpoint with the background task and waits for its result
+    async fn request_checkpoint(&self, trigger: CheckpointTrigger) -> Result<CheckpointInfo> {
+        let (response_tx, response_rx) = oneshot::channel();
 
         self.command_tx
             .send(CheckpointCommand::CreateCheckpoint {
-                trigger: CheckpointTrigger::Shutdown,
+                trigger,
                 response_tx: Some(response_tx),
             })
-            .map_err(|_| Error::internal("Failed to send shutdown checkpoint command"))?;
+            .map_err(|_| Error::internal("Failed to send checkpoint creation command"))?;
 
         response_rx
             .await
-            .map_err(|_| Error::internal("Failed to receive shutdown checkpoint result"))?
+            .map_err(|_| Error::internal("Failed to receive checkpoint creation result"))?
+    }
+
+    /// Reads checkpoint metadata written through [`CheckpointConfig::metadata_path`]
+    ///
+    /// Returns `Ok(None)` when no checkpoint has completed yet.
+    pub fn load_metadata(path: &Path) -> Result<Option<CheckpointInfo>> {
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        serde_json::from_slice(&bytes).map(Some).map_err(|e| {
+            Error::database(format!(
+                "Corrupt checkpoint metadata {}: {}",
+                path.display(),
+                e
+            ))
+        })
     }
 
     /// Returns checkpoint statistics
     pub async fn get_statistics(&self) -> CheckpointStatistics {
-        let (response_tx, response_rx) = tokio::sync::oneshot::channel();
+        let (response_tx, response_rx) = oneshot::channel();
 
         if self
             .command_tx
@@ -518,13 +562,22 @@ impl CheckpointManager {
         }
     }
 
-    /// Waits for current checkpoint to finish
-    pub async fn wait_for_checkpoint(&self, timeout: Duration) -> Result<()> {
-        tokio::time::timeout(timeout, self.checkpoint_notify.notified())
-            .await
-            .map_err(|_| Error::database("Timeout waiting for checkpoint completion"))?;
+    /// LSN of the last completed checkpoint (0 before the first one)
+    pub fn last_checkpoint_lsn(&self) -> LogSequenceNumber {
+        *self.checkpoint_lsn.borrow()
+    }
 
-        Ok(())
+    /// Waits until a checkpoint whose record is at or past `lsn` has completed
+    ///
+    /// Returns the LSN of that checkpoint; returns immediately if one already has. Fails if the
+    /// manager shuts down first.
+    pub async fn wait_for_checkpoint(&self, lsn: LogSequenceNumber) -> Result<LogSequenceNumber> {
+        let mut checkpoint_lsn = self.checkpoint_lsn.clone();
+        let done = checkpoint_lsn
+            .wait_for(|&done| done >= lsn)
+            .await
+            .map_err(|_| Error::database("Checkpoint manager stopped before the checkpoint"))?;
+        Ok(*done)
     }
 
     /// Shuts down the checkpoint manager
@@ -715,24 +768,44 @@ mod tests {
         Ok(())
     }
 
-    // #[tokio::test]
-    // async fn test_wait_for_checkpoint() -> Result<()> {
-    //     let manager = create_test_checkpoint_manager().await?;
-
-    //     // Start checkpoint creation in background
-    //     let manager_clone = manager;
-    //     let checkpoint_task = tokio::spawn(async move {
-    //         tokio::time::sleep(Duration::from_millis(50)).await;
-    //         manager_clone.create_checkpoint().await
-    //     });
-
-    //     // Wait for checkpoint completion
-    //     let wait_result = manager.wait_for_checkpoint(Duration::from_secs(1)).await;
-    //     assert!(wait_result.is_ok());
+    #[tokio::test]
+    async fn test_wait_for_checkpoint() -> Result<()> {
+        let manager = Arc::new(create_test_checkpoint_manager().await?);
+        assert_eq!(manager.last_checkpoint_lsn(), 0);
+
+        // A waiter registered before the checkpoint is woken with its LSN
+        let waiter = {
+            let manager = manager.clone();
+            tokio::spawn(async move { manager.wait_for_checkpoint(1).await })
+        };
+        let checkpoint_info = manager.create_checkpoint().await?;
+        let waited_lsn = tokio::time::timeout(Duration::from_secs(5), waiter)
+            .await
+            .expect("waiter not woken")
+            .unwrap()?;
+        assert_eq!(waited_lsn, checkpoint_info.lsn);
+        assert_eq!(manager.last_checkpoint_lsn(), checkpoint_info.lsn);
+
+        // Already reached: returns without another checkpoint
+        assert_eq!(
+            manager.wait_for_checkpoint(checkpoint_info.lsn).await?,
+            checkpoint_info.lsn
+        );
 
-    //     let checkpoint_info = checkpoint_task.await.unwrap()?;
-    //     assert!(checkpoint_info.id > 0);
+        // Not reached yet: resolved by the next checkpoint, not by an earlier one
+        let pending = tokio::time::timeout(
+            Duration::from_millis(50),
+            manager.wait_for_checkpoint(checkpoint_info.lsn + 1),
+        )
+        .await;
+        assert!(pending.is_err());
+        let next = manager.create_checkpoint().await?;
+        assert!(next.lsn > checkpoint_info.lsn);
+        assert_eq!(
+            manager.wait_for_checkpoint(checkpoint_info.lsn + 1).await?,
+            next.lsn
+        );
 
-    //     Ok(())
-    // }
+        Ok(())
+    }
 }
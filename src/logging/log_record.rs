@@ -18,6 +18,9 @@ pub type LogSequenceNumber = u64;
 /// Transaction identifier
 pub type TransactionId = u64;
 
+/// Upper bound on a single encoded WAL record accepted by [`LogRecord::decode`].
+pub const MAX_LOG_RECORD_BYTES: usize = 64 * 1024 * 1024;
+
 /// Log record type
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum LogRecordType {
@@ -406,6 +409,76 @@ impl LogRecord {
         record
     }
 
+    /// Creates a marker carrying the text of a successful DDL statement (logical replication
+    /// replays it; recovery ignores it)
+    pub fn new_ddl_statement(lsn: LogSequenceNumber, sql: &str) -> Self {
+        let mut record = Self::new(lsn, LogRecordType::MetadataUpdate, LogOperationData::Empty);
+        record
+            .metadata
+            .insert("kind".to_string(), "ddl".to_string());
+        record.metadata.insert("sql".to_string(), sql.to_string());
+        record.priority = LogPriority::Critical;
+        record.update_size_and_checksum();
+        record
+    }
+
+    /// DDL text of a [`Self::new_ddl_statement`] record
+    pub fn ddl_statement(&self) -> Option<&str> {
+        if self.record_type != LogRecordType::MetadataUpdate
+            || self.metadata.get("kind").map(String::as_str) != Some("ddl")
+        {
+            return None;
+        }
+        self.metadata.get("sql").map(String::as_str)
+    }
+
+    /// Creates a marker stating that values of `sequence` below `high_water` may have been
+    /// handed out (restart resumes above the largest mark; recovery otherwise ignores it)
+    pub fn new_sequence_high_water(
+        lsn: LogSequenceNumber,
+        sequence: &str,
+        high_water: u64,
+    ) -> Self {
+        let mut record = Self::new(lsn, LogRecordType::MetadataUpdate, LogOperationData::Empty);
+        record
+            .metadata
+            .insert("kind".to_string(), "sequence".to_string());
+        record
+            .metadata
+            .insert("sequence".to_string(), sequence.to_string());
+        record
+            .metadata
+            .insert("high_water".to_string(), high_water.to_string());
+        record.priority = LogPriority::Critical;
+        record.update_size_and_checksum();
+        record
+    }
+
+    /// Sequence name and high-water mark of a [`Self::new_sequence_high_water`] record
+    pub fn sequence_high_water(&self) -> Option<(&str, u64)> {
+        if self.record_type != LogRecordType::MetadataUpdate
+            || self.metadata.get("kind").map(String::as_str) != Some("sequence")
+        {
+            return None;
+        }
+        let sequence = self.metadata.get("sequence")?;
+        let high_water = self.metadata.get("high_water")?.parse().ok()?;
+        Some((sequence.as_str(), high_water))
+    }
+
+    /// Marks a commit or DDL record as replayed from a bidirectional replication peer, where
+    /// it committed at `commit_time` (Unix seconds)
+    pub fn set_origin_commit_time(&mut self, commit_time: u64) {
+        self.metadata
+            .insert("origin_commit_time".to_string(), commit_time.to_string());
+        self.update_size_and_checksum();
+    }
+
+    /// Peer commit time set by [`Self::set_origin_commit_time`]
+    pub fn origin_commit_time(&self) -> Option<u64> {
+        self.metadata.get("origin_commit_time")?.parse().ok()
+    }
+
     /// Returns record size in bytes
     pub fn size(&self) -> u32 {
         self.record_size
@@ -458,24 +531,51 @@ impl LogRecord {
             .map_err(|e| Error::internal(&format!("Log record deserialization error: {}", e)))
     }
 
-    /// Reads length-prefixed bincode records from a WAL file (same format as [`crate::logging::log_writer::LogWriter`] batch writes).
-    pub fn read_log_records_from_file(path: &std::path::Path) -> Result<Vec<LogRecord>> {
-        let bytes = std::fs::read(path)
-            .map_err(|e| Error::internal(format!("Failed to read log file {:?}: {}", path, e)))?;
+    /// Decodes one record read back from disk.
+    ///
+    /// Unlike [`Self::deserialize`], this treats `data` as untrusted: lengths are bounded by
+    /// [`MAX_LOG_RECORD_BYTES`], trailing bytes are rejected, and malformed input always
+    /// surfaces as an error (never a panic). Recovery and the fuzz target go through here.
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        if data.len() > MAX_LOG_RECORD_BYTES {
+            return Err(Error::validation(format!(
+                "Log record of {} bytes exceeds limit of {} bytes",
+                data.len(),
+                MAX_LOG_RECORD_BYTES
+            )));
+        }
+        crate::common::bincode_io::deserialize_bounded::<Self, MAX_LOG_RECORD_BYTES>(data)
+            .map_err(|e| Error::internal(format!("Log record decode error: {}", e)))
+    }
+
+    /// Decodes a buffer of length-prefixed records (the on-disk WAL segment format).
+    ///
+    /// A truncated tail (torn write) ends decoding; a corrupt record in the middle is an error.
+    pub fn decode_stream(bytes: &[u8]) -> Result<Vec<LogRecord>> {
         let mut records = Vec::new();
         let mut i = 0usize;
-        while i + 4 <= bytes.len() {
-            let len = u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap()) as usize;
+        while let Some(prefix) = bytes.get(i..i + 4) {
+            let len = u32::from_le_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]) as usize;
             i += 4;
-            if len == 0 || i + len > bytes.len() {
+            let Some(body) = bytes.get(i..i.saturating_add(len)) else {
+                break;
+            };
+            if len == 0 {
                 break;
             }
-            records.push(LogRecord::deserialize(&bytes[i..i + len])?);
+            records.push(LogRecord::decode(body)?);
             i += len;
         }
         Ok(records)
     }
 
+    /// Reads length-prefixed bincode records from a WAL file (same format as [`crate::logging::log_writer::LogWriter`] batch writes).
+    pub fn read_log_records_from_file(path: &std::path::Path) -> Result<Vec<LogRecord>> {
+        let bytes = std::fs::read(path)
+            .map_err(|e| Error::internal(format!("Failed to read log file {:?}: {}", path, e)))?;
+        LogRecord::decode_stream(&bytes)
+    }
+
     /// Reads all `*.log` files in a directory (sorted by name) and concatenates records.
     pub fn read_log_records_from_directory(dir: &std::path::Path) -> Result<Vec<LogRecord>> {
         let mut paths: Vec<std::path::PathBuf> = std::fs::read_dir(dir)
@@ -826,6 +926,18 @@ mod tests {
         assert_eq!(record.get_metadata("nonexistent"), None);
     }
 
+    #[test]
+    fn test_sequence_high_water_roundtrip() {
+        let record = LogRecord::new_sequence_high_water(1, "tuple_id", 4097);
+        let deserialized = LogRecord::deserialize(&record.serialize().unwrap()).unwrap();
+        assert_eq!(deserialized.sequence_high_water(), Some(("tuple_id", 4097)));
+        assert_eq!(deserialized.ddl_statement(), None);
+        assert_eq!(
+            LogRecord::new_ddl_statement(2, "DROP TABLE t").sequence_high_water(),
+            None
+        );
+    }
+
     #[test]
     fn test_record_iterator() {
         let records = vec![
@@ -863,4 +975,39 @@ mod tests {
             "CHECKPOINT 1 (active transactions: 1)"
         );
     }
+
+    #[test]
+    fn test_decode_rejects_malformed_input() {
+        let record = LogRecord::new_data_insert(7, 3, 1, 10, 0, vec![1, 2, 3], None);
+        let bytes = record.serialize().unwrap();
+        assert_eq!(LogRecord::decode(&bytes).unwrap().lsn, 7);
+
+        // Truncated, trailing garbage, and a bogus huge length prefix for the payload.
+        assert!(LogRecord::decode(&bytes[..bytes.len() - 1]).is_err());
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(LogRecord::decode(&trailing).is_err());
+        let mut huge = bytes.clone();
+        let n = huge.len();
+        huge[n - 8..].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(LogRecord::decode(&huge).is_err());
+        assert!(LogRecord::decode(&[0xff; 64]).is_err());
+    }
+
+    #[test]
+    fn test_decode_stream_stops_at_torn_tail() {
+        let mut buf = Vec::new();
+        for lsn in 1..=3 {
+            let bytes = LogRecord::new_transaction_begin(lsn, lsn, IsolationLevel::ReadCommitted)
+                .serialize()
+                .unwrap();
+            buf.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            buf.extend_from_slice(&bytes);
+        }
+        buf.extend_from_slice(&u32::MAX.to_le_bytes());
+        buf.extend_from_slice(&[1, 2, 3]);
+        let records = LogRecord::decode_stream(&buf).unwrap();
+        assert_eq!(records.len(), 3);
+        assert!(LogRecord::decode_stream(&[1, 2]).unwrap().is_empty());
+    }
 }
@@ -5,15 +5,25 @@
 //! - Asynchronous writing with priority control
 //! - Log file rotation and size management
 //! - Integration with the I/O optimization subsystem
+//!
+//! A flush gathers up to [`LogWriterConfig::write_batch_max_records`] length-prefixed records into
+//! one vectored write (`writev`). New segments can be preallocated to their full size
+//! (`fallocate` on Linux), so appends stay within the file's length and a commit sync only has to
+//! flush data. Segments retired by [`LogWriter::cleanup_old_logs`] are zero-filled and kept
+//! for reuse as new segments. A zero length prefix ends a segment for
+//! [`LogRecord::decode_stream`], so preallocated and recycled space reads as end of log.
 
 use crate::common::{Error, Result};
 use crate::logging::log_record::{LogRecord, LogSequenceNumber};
+use crate::logging::metrics::LoggingMetricsManager;
+use crate::storage::atomic_file::{sync_dir, sync_parent_dir};
 use crate::storage::io_optimization::{BufferedIoManager, IoBufferConfig};
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::fs::{File, OpenOptions};
-use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::io::{IoSlice, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::{mpsc, oneshot, Semaphore};
@@ -51,6 +61,14 @@ pub struct LogWriterConfig {
     /// When true, commit waits for WAL fsync (durable). When false, commit returns immediately
     /// without fsync - higher throughput but risk of data loss on crash (PostgreSQL's synchronous_commit=off).
     pub synchronous_commit: bool,
+    /// Maximum number of records gathered into one vectored write
+    pub write_batch_max_records: usize,
+    /// Maximum number of bytes gathered into one vectored write (a larger record is written alone)
+    pub write_batch_max_bytes: usize,
+    /// Preallocate new segments to `max_log_file_size` so appends do not change the file size
+    pub preallocate_segments: bool,
+    /// Maximum number of retired segments kept for reuse by [`LogWriter::cleanup_old_logs`]
+    pub max_recycled_segments: usize,
 }
 
 /// Log synchronization level
@@ -108,6 +126,10 @@ impl Default for LogWriterConfig {
             group_commit_enabled: true,
             force_flush_immediately: false, // Use group commit for better TPS
             synchronous_commit: true,       // Wait for fsync by default (durable)
+            write_batch_max_records: 256,
+            write_batch_max_bytes: 1024 * 1024, // 1 MB
+            preallocate_segments: false,
+            max_recycled_segments: 2,
         }
     }
 }
@@ -137,11 +159,31 @@ pub struct LogFileInfo {
 
 /// State of the current log file for writing
 struct LogFileState {
-    writer: BufWriter<File>,
+    file: File,
     path: PathBuf,
+    /// End of the written records; the file cursor stays here
     size: u64,
+    /// File length when opened; beyond `size` for preallocated and recycled segments
+    allocated: u64,
+}
+
+/// Segment being written and the retired segments kept for reuse
+#[derive(Default)]
+struct LogSegments {
+    current: Option<LogFileState>,
+    /// Zero-filled retired segments (`*.recycled`), used before creating new files
+    recycled: Vec<PathBuf>,
+    /// Vectored write calls issued
+    vectored_writes: u64,
+    /// Segments preallocated on creation
+    preallocated: u64,
+    /// Segments taken from `recycled`
+    reused: u64,
 }
 
+/// Extension of retired segments waiting for reuse (ignored by log readers, which read `*.log`)
+const RECYCLED_SEGMENT_EXTENSION: &str = "recycled";
+
 /// Double buffer: while one buffer is flushed to disk, new writes go to the other
 struct DoubleBuffer {
     active: VecDeque<LogRecord>,
@@ -206,6 +248,12 @@ pub struct LogWriterStatistics {
     pub max_buffer_size_reached: usize,
     /// Write throughput (records/sec)
     pub write_throughput: f64,
+    /// Number of vectored write calls
+    pub vectored_writes: u64,
+    /// Number of segments preallocated on creation
+    pub segments_preallocated: u64,
+    /// Number of segments reused from the recycle pool
+    pub segments_recycled: u64,
 }
 
 /// Log writer implementation
@@ -222,8 +270,8 @@ pub struct LogWriter {
     sync_waiters: Arc<Mutex<Vec<oneshot::Sender<Result<()>>>>>,
     /// Channel for write requests
     write_tx: mpsc::UnboundedSender<LogWriteRequest>,
-    /// LSN generator
-    lsn_generator: Arc<Mutex<LogSequenceNumber>>,
+    /// Next LSN to hand out
+    lsn_generator: Arc<AtomicU64>,
     /// Statistics
     statistics: Arc<RwLock<LogWriterStatistics>>,
     /// Semaphore to limit concurrent operations
@@ -236,8 +284,10 @@ pub struct LogWriter {
     group_commit_handle: Option<JoinHandle<()>>,
     /// Optimized I/O manager
     io_manager: Option<Arc<BufferedIoManager>>,
-    /// Current log file for writing (shared with background tasks)
-    log_file_state: Arc<Mutex<Option<LogFileState>>>,
+    /// Current log file for writing and recycled segments (shared with background tasks)
+    log_file_state: Arc<Mutex<LogSegments>>,
+    /// Optional metrics receiving commit latencies
+    metrics: Arc<RwLock<Option<Arc<LoggingMetricsManager>>>>,
 }
 
 impl LogWriter {
@@ -271,14 +321,15 @@ impl LogWriter {
             write_buffer: Arc::new(Mutex::new(DoubleBuffer::new())),
             sync_waiters: sync_waiters.clone(),
             write_tx,
-            lsn_generator: Arc::new(Mutex::new(1)),
+            lsn_generator: Arc::new(AtomicU64::new(1)),
             statistics: Arc::new(RwLock::new(LogWriterStatistics::default())),
             semaphore,
             background_handle: None,
             writer_handle: None,
             group_commit_handle: None,
             io_manager,
-            log_file_state: Arc::new(Mutex::new(None)),
+            log_file_state: Arc::new(Mutex::new(LogSegments::default())),
+            metrics: Arc::new(RwLock::new(None)),
         };
 
         // Load existing log files
@@ -293,6 +344,7 @@ impl LogWriter {
     /// Loads existing log files
     fn load_existing_log_files(&mut self) -> Result<()> {
         let mut files = Vec::new();
+        let mut recycled = Vec::new();
         let mut max_lsn = 0;
 
         if self.config.log_directory.exists() {
@@ -305,7 +357,9 @@ impl LogWriter {
                 })?;
                 let path = entry.path();
 
-                if path.extension().and_then(|s| s.to_str()) == Some("log") {
+                if path.extension().and_then(|s| s.to_str()) == Some(RECYCLED_SEGMENT_EXTENSION) {
+                    recycled.push(path);
+                } else if path.extension().and_then(|s| s.to_str()) == Some("log") {
                     if let Ok(file_info) = self.analyze_log_file(&path) {
                         if file_info.last_lsn > max_lsn {
                             max_lsn = file_info.last_lsn;
@@ -320,25 +374,19 @@ impl LogWriter {
         files.sort_by_key(|f| f.created_at);
 
         // Set the last file as the current file and resume appending to it.
-        if let Some(latest_file) = files.last() {
-            *self.current_file.write().unwrap() = Some(latest_file.clone());
-            if let Ok(file) = OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(&latest_file.path)
-            {
-                *self.log_file_state.lock().unwrap() = Some(LogFileState {
-                    writer: BufWriter::new(file),
-                    path: latest_file.path.clone(),
-                    size: latest_file.size,
-                });
+        {
+            let mut segments = self.log_file_state.lock().unwrap();
+            if let Some(latest_file) = files.last() {
+                *self.current_file.write().unwrap() = Some(latest_file.clone());
+                segments.current = Self::resume_segment(&latest_file.path).ok();
             }
+            segments.recycled = recycled;
         }
 
         *self.log_files.write().unwrap() = files;
 
         // Set next LSN
-        *self.lsn_generator.lock().unwrap() = max_lsn + 1;
+        self.lsn_generator.store(max_lsn + 1, Ordering::Relaxed);
 
         Ok(())
     }
@@ -428,6 +476,7 @@ impl LogWriter {
 
             loop {
                 interval.tick().await;
+                let waiters = Self::take_sync_waiters(&flush_waiters);
                 Self::flush_write_buffer(
                     &flush_buffer,
                     &flush_stats,
@@ -435,7 +484,7 @@ impl LogWriter {
                     &flush_config,
                 )
                 .await;
-                Self::notify_sync_waiters(&flush_waiters);
+                Self::notify_sync_waiters(waiters);
             }
         }));
 
@@ -458,19 +507,27 @@ impl LogWriter {
                         !waiters.is_empty()
                     };
                     if should_flush {
+                        let waiters = Self::take_sync_waiters(&gc_waiters);
                         Self::flush_write_buffer(&gc_buffer, &gc_stats, &gc_log_file, &gc_config)
                             .await;
-                        Self::notify_sync_waiters(&gc_waiters);
+                        Self::notify_sync_waiters(waiters);
                     }
                 }
             }));
         }
     }
 
-    /// Notifies all pending sync waiters after a flush
-    fn notify_sync_waiters(sync_waiters: &Arc<Mutex<Vec<oneshot::Sender<Result<()>>>>>) {
-        let mut waiters = sync_waiters.lock().unwrap();
-        for tx in waiters.drain(..) {
+    /// Takes the pending sync waiters before a flush. A request buffers its record before it
+    /// registers as a waiter, so the flush that follows covers every waiter taken here.
+    fn take_sync_waiters(
+        sync_waiters: &Arc<Mutex<Vec<oneshot::Sender<Result<()>>>>>,
+    ) -> Vec<oneshot::Sender<Result<()>>> {
+        std::mem::take(&mut *sync_waiters.lock().unwrap())
+    }
+
+    /// Notifies the sync waiters taken before a flush
+    fn notify_sync_waiters(waiters: Vec<oneshot::Sender<Result<()>>>) {
+        for tx in waiters {
             let _ = tx.send(Ok(()));
         }
     }
@@ -485,7 +542,7 @@ impl LogWriter {
         _log_files: Arc<RwLock<Vec<LogFileInfo>>>,
         config: LogWriterConfig,
         _io_manager: Option<Arc<BufferedIoManager>>,
-        log_file_state: Arc<Mutex<Option<LogFileState>>>,
+        log_file_state: Arc<Mutex<LogSegments>>,
     ) {
         let start_time = Instant::now();
 
@@ -540,9 +597,13 @@ impl LogWriter {
             let _ = tx.send(Ok(()));
         }
 
-        if should_flush {
+        let flushed_waiters = if should_flush {
+            let waiters = Self::take_sync_waiters(&sync_waiters);
             Self::flush_write_buffer(&write_buffer, &statistics, &log_file_state, &config).await;
-        }
+            Some(waiters)
+        } else {
+            None
+        };
 
         // Update remaining statistics (before notify so caller sees consistent state)
         {
@@ -566,8 +627,8 @@ impl LogWriter {
             }
         }
 
-        if should_flush {
-            Self::notify_sync_waiters(&sync_waiters);
+        if let Some(waiters) = flushed_waiters {
+            Self::notify_sync_waiters(waiters);
         }
         if let Some(tx) = legacy_response_tx {
             let _ = tx.send(Ok(()));
@@ -575,31 +636,37 @@ impl LogWriter {
     }
 
     /// Flushes write buffer to disk
+    ///
+    /// The records are taken from the buffer under the segment lock, so a flush finding the
+    /// buffer empty still waits for a concurrent flush to finish writing.
     async fn flush_write_buffer(
         write_buffer: &Arc<Mutex<DoubleBuffer>>,
         statistics: &Arc<RwLock<LogWriterStatistics>>,
-        log_file_state: &Arc<Mutex<Option<LogFileState>>>,
+        log_file_state: &Arc<Mutex<LogSegments>>,
         config: &LogWriterConfig,
     ) {
-        let records_to_write: Vec<LogRecord> = {
-            let mut buffer = write_buffer.lock().unwrap();
-            if buffer.len() == 0 {
-                return;
-            }
-
-            buffer.take_for_flush().into_iter().collect()
-        };
-
+        let buffer = write_buffer.clone();
         let config = config.clone();
         let log_file_state = log_file_state.clone();
         let write_result = tokio::task::spawn_blocking(move || {
-            Self::write_records_to_file(&records_to_write, &log_file_state, &config)
+            let mut segments = log_file_state.lock().unwrap();
+            let records_to_write: Vec<LogRecord> = {
+                let mut buffer = buffer.lock().unwrap();
+                if buffer.len() == 0 {
+                    return Ok(false);
+                }
+                buffer.take_for_flush().into_iter().collect()
+            };
+            Self::write_records_to_file(&records_to_write, &mut segments, &config).map(|()| true)
         })
-        .await;
+        .await
+        .map_err(|e| Error::internal(&format!("Log flush join error: {}", e)))
+        .and_then(|written| written);
 
         match write_result {
-            Ok(Ok(())) => {}
-            Ok(Err(_)) | Err(_) => {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(_) => {
                 if let Ok(mut stats) = statistics.write() {
                     stats.write_errors += 1;
                 }
@@ -621,19 +688,18 @@ impl LogWriter {
     }
 
     /// Writes records to the log file (blocking, run in spawn_blocking).
-    /// Batch serialization: pre-serialize all records before the write loop to minimize syscalls.
+    /// Records are serialized, then written in vectored batches of at most
+    /// `write_batch_max_records` records / `write_batch_max_bytes` bytes.
     fn write_records_to_file(
         records: &[LogRecord],
-        log_file_state: &Arc<Mutex<Option<LogFileState>>>,
+        segments: &mut LogSegments,
         config: &LogWriterConfig,
     ) -> Result<()> {
-        use std::io::Write as _;
-
         if records.is_empty() {
             return Ok(());
         }
 
-        // Pre-serialize all records before acquiring file lock (batch serialization)
+        // Serialize all records before the first write (batch serialization)
         let serialized: Vec<Vec<u8>> = records
             .iter()
             .map(|r| {
@@ -641,91 +707,148 @@ impl LogWriter {
                     .map_err(|e| Error::internal(&format!("Failed to serialize log record: {}", e)))
             })
             .collect::<Result<Vec<_>>>()?;
+        let prefixes: Vec<[u8; 4]> = serialized
+            .iter()
+            .map(|data| (data.len() as u32).to_le_bytes())
+            .collect();
 
-        // Build single buffer: [len1, data1, len2, data2, ...] for fewer syscalls
-        let total_size: usize = serialized.iter().map(|d| 4 + d.len()).sum();
-        let mut batch = Vec::with_capacity(total_size);
-        for data in &serialized {
-            batch.extend_from_slice(&(data.len() as u32).to_le_bytes());
-            batch.extend_from_slice(data);
+        // Get or create log file
+        if segments.current.is_none() {
+            segments.current = Some(Self::open_segment(segments, config)?);
         }
 
-        let mut state_guard = log_file_state.lock().unwrap();
+        let max_records = config.write_batch_max_records.max(1);
+        let mut start = 0;
+        while start < serialized.len() {
+            let mut end = start;
+            let mut batch_bytes = 0u64;
+            while end < serialized.len() && end - start < max_records {
+                let len = 4 + serialized[end].len() as u64;
+                if end > start && batch_bytes + len > config.write_batch_max_bytes as u64 {
+                    break;
+                }
+                batch_bytes += len;
+                end += 1;
+            }
 
-        // Get or create log file
-        if state_guard.is_none() {
-            let timestamp = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs();
-            let path = config.log_directory.join(format!("wal_{}.log", timestamp));
-            let file = OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(&path)
-                .map_err(|e| Error::internal(&format!("Failed to open log file: {}", e)))?;
-            *state_guard = Some(LogFileState {
-                writer: BufWriter::new(file),
-                path: path.clone(),
-                size: 0,
-            });
-        }
+            // Rotate if batch would exceed max file size
+            let state = segments.current.as_ref().unwrap();
+            if state.size > 0 && state.size + batch_bytes > config.max_log_file_size {
+                if config.synchronous_commit {
+                    Self::sync_segment(state)?;
+                }
+                segments.current = Some(Self::open_segment(segments, config)?);
+            }
 
-        let state = state_guard.as_mut().unwrap();
-
-        // Rotate if batch would exceed max file size
-        if state.size > 0 && state.size + batch.len() as u64 > config.max_log_file_size {
-            state
-                .writer
-                .flush()
-                .map_err(|e| Error::internal(&format!("Failed to flush log file: {}", e)))?;
-            if config.synchronous_commit {
-                state
-                    .writer
-                    .get_mut()
-                    .sync_all()
-                    .map_err(|e| Error::internal(&format!("Failed to sync log file: {}", e)))?;
+            let mut slices = Vec::with_capacity(2 * (end - start));
+            for (prefix, data) in prefixes[start..end].iter().zip(&serialized[start..end]) {
+                slices.push(IoSlice::new(prefix));
+                slices.push(IoSlice::new(data));
             }
+            let state = segments.current.as_mut().unwrap();
+            segments.vectored_writes += Self::write_all_vectored(&mut state.file, &mut slices)
+                .map_err(|e| Error::internal(&format!("Failed to write log records: {}", e)))?;
+            state.size += batch_bytes;
+            start = end;
+        }
 
-            let timestamp = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs();
-            let path = config.log_directory.join(format!("wal_{}.log", timestamp));
-            let file = OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(&path)
-                .map_err(|e| Error::internal(&format!("Failed to open new log file: {}", e)))?;
-            *state_guard = Some(LogFileState {
-                writer: BufWriter::new(file),
-                path,
-                size: 0,
-            });
+        if config.synchronous_commit {
+            Self::sync_segment(segments.current.as_ref().unwrap())?;
         }
 
-        let state = state_guard.as_mut().unwrap();
-        state
-            .writer
-            .write_all(&batch)
-            .map_err(|e| Error::internal(&format!("Failed to write log records: {}", e)))?;
-        state.size += batch.len() as u64;
-
-        if let Some(state) = state_guard.as_mut() {
-            state
-                .writer
-                .flush()
-                .map_err(|e| Error::internal(&format!("Failed to flush log file: {}", e)))?;
-            if config.synchronous_commit {
-                state
-                    .writer
-                    .get_mut()
-                    .sync_all()
-                    .map_err(|e| Error::internal(&format!("Failed to sync log file: {}", e)))?;
+        Ok(())
+    }
+
+    /// Writes all of `bufs`, returning the number of `writev` calls it took
+    fn write_all_vectored(file: &mut File, mut bufs: &mut [IoSlice<'_>]) -> std::io::Result<u64> {
+        let mut calls = 0;
+        while !bufs.is_empty() {
+            match file.write_vectored(bufs) {
+                Ok(0) => return Err(std::io::ErrorKind::WriteZero.into()),
+                Ok(written) => {
+                    calls += 1;
+                    IoSlice::advance_slices(&mut bufs, written);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
             }
         }
+        Ok(calls)
+    }
 
-        Ok(())
+    /// Makes the written records durable. While they stay within the length the segment was
+    /// opened with (preallocated or recycled), the file size is unchanged and `fdatasync` suffices.
+    fn sync_segment(state: &LogFileState) -> Result<()> {
+        let synced = if state.size <= state.allocated {
+            state.file.sync_data()
+        } else {
+            state.file.sync_all()
+        };
+        synced.map_err(|e| Error::internal(&format!("Failed to sync log file: {}", e)))
+    }
+
+    /// Opens a new segment: a recycled one when available, else a new file, preallocated when
+    /// `preallocate_segments` is set
+    fn open_segment(segments: &mut LogSegments, config: &LogWriterConfig) -> Result<LogFileState> {
+        let path = Self::new_segment_path(&config.log_directory);
+        let file = match segments.recycled.pop() {
+            Some(recycled) => {
+                std::fs::rename(&recycled, &path)?;
+                segments.reused += 1;
+                OpenOptions::new().write(true).open(&path)?
+            }
+            None => {
+                let file = OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&path)
+                    .map_err(|e| Error::internal(&format!("Failed to open log file: {}", e)))?;
+                if config.preallocate_segments {
+                    preallocate(&file, config.max_log_file_size)?;
+                    segments.preallocated += 1;
+                }
+                file
+            }
+        };
+        if config.synchronous_commit {
+            sync_parent_dir(&path)?;
+        }
+        let allocated = file.metadata()?.len();
+        Ok(LogFileState {
+            file,
+            path,
+            size: 0,
+            allocated,
+        })
+    }
+
+    /// Reopens an existing segment to append after its last complete record
+    fn resume_segment(path: &Path) -> Result<LogFileState> {
+        let bytes = std::fs::read(path)?;
+        let size = written_len(&bytes);
+        let mut file = OpenOptions::new().write(true).open(path)?;
+        file.seek(SeekFrom::Start(size))?;
+        Ok(LogFileState {
+            file,
+            path: path.to_path_buf(),
+            size,
+            allocated: bytes.len() as u64,
+        })
+    }
+
+    /// Path of a new segment, unique even for several segments opened within one second
+    fn new_segment_path(log_directory: &Path) -> PathBuf {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let mut path = log_directory.join(format!("wal_{}.log", timestamp));
+        let mut suffix = 1;
+        while path.exists() {
+            path = log_directory.join(format!("wal_{}_{}.log", timestamp, suffix));
+            suffix += 1;
+        }
+        path
     }
 
     /// Writes a log record and waits until it is flushed to the log file.
@@ -735,6 +858,7 @@ impl LogWriter {
             record.lsn = self.generate_lsn();
         }
 
+        let started = Instant::now();
         let (response_tx, response_rx) = oneshot::channel();
         let request = LogWriteRequest {
             record: record.clone(),
@@ -751,6 +875,7 @@ impl LogWriter {
         response_rx
             .await
             .map_err(|_| Error::internal("Failed to receive durable log write result"))??;
+        self.record_commit_latency(started.elapsed());
 
         Ok(record.lsn)
     }
@@ -787,6 +912,7 @@ impl LogWriter {
         if record.lsn == 0 {
             record.lsn = self.generate_lsn();
         }
+        let started = Instant::now();
 
         if !self.config.synchronous_commit {
             // No sync: buffer only, return immediately (PostgreSQL's synchronous_commit=off)
@@ -799,6 +925,7 @@ impl LogWriter {
             self.write_tx
                 .send(request)
                 .map_err(|_| Error::internal("Failed to send log write request"))?;
+            self.record_commit_latency(started.elapsed());
             return Ok(record.lsn);
         }
 
@@ -817,10 +944,22 @@ impl LogWriter {
         response_rx
             .await
             .map_err(|_| Error::internal("Failed to receive synchronous log write result"))??;
+        self.record_commit_latency(started.elapsed());
 
         Ok(record.lsn)
     }
 
+    /// Reports the latency of synchronous and durable writes (commits) to `metrics`
+    pub fn set_metrics(&self, metrics: Arc<LoggingMetricsManager>) {
+        *self.metrics.write().unwrap() = Some(metrics);
+    }
+
+    fn record_commit_latency(&self, latency: Duration) {
+        if let Some(metrics) = self.metrics.read().unwrap().as_ref() {
+            metrics.record_commit_latency(latency);
+        }
+    }
+
     /// Forces flushing of all buffers to disk
     pub async fn flush(&self) -> Result<()> {
         Self::flush_write_buffer(
@@ -841,21 +980,22 @@ impl LogWriter {
 
     /// Generates next LSN
     fn generate_lsn(&self) -> LogSequenceNumber {
-        let mut generator = self.lsn_generator.lock().unwrap();
-        let lsn = *generator;
-        *generator += 1;
-        lsn
+        self.lsn_generator.fetch_add(1, Ordering::Relaxed)
     }
 
     /// Returns current LSN
     pub fn current_lsn(&self) -> LogSequenceNumber {
-        let generator = self.lsn_generator.lock().unwrap();
-        *generator - 1
+        self.lsn_generator.load(Ordering::Relaxed) - 1
     }
 
     /// Returns log writer statistics
     pub fn get_statistics(&self) -> LogWriterStatistics {
-        self.statistics.read().unwrap().clone()
+        let mut stats = self.statistics.read().unwrap().clone();
+        let segments = self.log_file_state.lock().unwrap();
+        stats.vectored_writes = segments.vectored_writes;
+        stats.segments_preallocated = segments.preallocated;
+        stats.segments_recycled = segments.reused;
+        stats
     }
 
     /// Returns current log file information
@@ -863,15 +1003,33 @@ impl LogWriter {
         self.current_file.read().unwrap().clone()
     }
 
+    /// Path of the segment being appended to; `None` after a rotation until the next write
+    pub fn current_segment_path(&self) -> Option<PathBuf> {
+        let segments = self.log_file_state.lock().unwrap();
+        segments.current.as_ref().map(|state| state.path.clone())
+    }
+
     /// Returns list of all log files
     pub fn get_log_files(&self) -> Vec<LogFileInfo> {
         self.log_files.read().unwrap().clone()
     }
 
-    /// Performs log file rotation
+    /// Performs log file rotation: flushes buffered records and closes the current segment, so
+    /// the next write starts a new one
     pub async fn rotate_log_file(&self) -> Result<()> {
-        // In real implementation file rotation logic would go here
-        {
+        self.flush().await?;
+        let segments = self.log_file_state.clone();
+        let rotated = tokio::task::spawn_blocking(move || {
+            let mut segments = segments.lock().unwrap();
+            match segments.current.take() {
+                Some(state) => Self::sync_segment(&state).map(|()| true),
+                None => Ok(false),
+            }
+        })
+        .await
+        .map_err(|e| Error::internal(&format!("Log rotation join error: {}", e)))??;
+
+        if rotated {
             let mut stats = self.statistics.write().unwrap();
             stats.file_rotations += 1;
         }
@@ -893,19 +1051,92 @@ impl LogWriter {
         Ok(results)
     }
 
-    /// Cleans up old log files
+    /// Retires all but the newest `keep_files` segments besides the one being written
+    ///
+    /// Up to `max_recycled_segments` retired segments are zero-filled and kept for reuse as new
+    /// segments, the others are deleted. Only call this for segments recovery no longer needs
+    /// (older than the last checkpoint). Returns the number of segments retired.
     pub async fn cleanup_old_logs(&self, keep_files: u32) -> Result<u32> {
-        let files = self.get_log_files();
-        let mut removed_count = 0;
-
-        if files.len() > keep_files as usize {
-            let files_to_remove = files.len() - keep_files as usize;
+        let segments = self.log_file_state.clone();
+        let log_files = self.log_files.clone();
+        let config = self.config.clone();
+        tokio::task::spawn_blocking(move || {
+            Self::retire_segments(&segments, &log_files, &config, keep_files as usize)
+        })
+        .await
+        .map_err(|e| Error::internal(&format!("Log cleanup join error: {}", e)))?
+    }
 
-            // In real implementation files would be deleted here
-            removed_count = files_to_remove as u32;
+    fn retire_segments(
+        segments: &Arc<Mutex<LogSegments>>,
+        log_files: &Arc<RwLock<Vec<LogFileInfo>>>,
+        config: &LogWriterConfig,
+        keep_files: usize,
+    ) -> Result<u32> {
+        let current = segments
+            .lock()
+            .unwrap()
+            .current
+            .as_ref()
+            .map(|state| state.path.clone());
+        let mut old = Vec::new();
+        for entry in std::fs::read_dir(&config.log_directory)? {
+            let path = entry?.path();
+            if path.extension().and_then(|s| s.to_str()) == Some("log")
+                && Some(&path) != current.as_ref()
+            {
+                let modified = std::fs::metadata(&path)?.modified().unwrap_or(UNIX_EPOCH);
+                old.push((modified, path));
+            }
+        }
+        old.sort();
+
+        let retire = old.len().saturating_sub(keep_files);
+        for (_, path) in old.into_iter().take(retire) {
+            let recycle = segments.lock().unwrap().recycled.len() < config.max_recycled_segments;
+            if recycle {
+                let recycled = Self::recycle_segment(&path, config)?;
+                segments.lock().unwrap().recycled.push(recycled);
+            } else {
+                std::fs::remove_file(&path)?;
+            }
+            log_files.write().unwrap().retain(|f| f.path != path);
         }
+        if retire > 0 {
+            sync_dir(&config.log_directory)?;
+        }
+
+        Ok(retire as u32)
+    }
 
-        Ok(removed_count)
+    /// Zero-fills a retired segment so it reads as empty, and renames it into the recycle pool
+    fn recycle_segment(path: &Path, config: &LogWriterConfig) -> Result<PathBuf> {
+        let mut file = OpenOptions::new().write(true).open(path)?;
+        let len = file.metadata()?.len();
+        let zeros = vec![0u8; 64 * 1024];
+        let mut written = 0u64;
+        while written < len {
+            let chunk = (len - written).min(zeros.len() as u64) as usize;
+            file.write_all(&zeros[..chunk])?;
+            written += chunk as u64;
+        }
+        if config.preallocate_segments && len < config.max_log_file_size {
+            preallocate(&file, config.max_log_file_size)?;
+        }
+        file.sync_all()?;
+
+        let mut n = 0;
+        let target = loop {
+            let candidate = config
+                .log_directory
+                .join(format!("segment_{}.{}", n, RECYCLED_SEGMENT_EXTENSION));
+            if !candidate.exists() {
+                break candidate;
+            }
+            n += 1;
+        };
+        std::fs::rename(path, &target)?;
+        Ok(target)
     }
 
     /// Returns total size of all log files
@@ -923,6 +1154,47 @@ impl LogWriter {
     }
 }
 
+/// Length of the complete records at the start of a segment, i.e. what
+/// [`LogRecord::decode_stream`] reads; the rest is preallocated space or a torn tail
+fn written_len(bytes: &[u8]) -> u64 {
+    let mut end = 0usize;
+    while let Some(prefix) = bytes.get(end..end + 4) {
+        let len = u32::from_le_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]) as usize;
+        if len == 0 || bytes.len() - (end + 4) < len {
+            break;
+        }
+        end += 4 + len;
+    }
+    end as u64
+}
+
+/// Allocates `len` bytes for `file`; the new space reads as zeros
+#[cfg(target_os = "linux")]
+fn preallocate(file: &File, len: u64) -> Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    // SAFETY: `fallocate` only reads its scalar arguments; the descriptor is owned by `file`,
+    // which outlives the call.
+    let ret = unsafe { libc::fallocate(file.as_raw_fd(), 0, 0, len as libc::off_t) };
+    if ret == 0 {
+        return Ok(());
+    }
+    let err = std::io::Error::last_os_error();
+    if err.raw_os_error() == Some(libc::EOPNOTSUPP) {
+        // Filesystem without fallocate: extend the file instead (sparse, allocated on write)
+        file.set_len(len)?;
+        return Ok(());
+    }
+    Err(err.into())
+}
+
+/// Extends `file` to `len` bytes (sparse where the platform has no `fallocate`)
+#[cfg(not(target_os = "linux"))]
+fn preallocate(file: &File, len: u64) -> Result<()> {
+    file.set_len(len)?;
+    Ok(())
+}
+
 impl Drop for LogWriter {
     fn drop(&mut self) {
         // Stop background tasks
@@ -1133,4 +1405,185 @@ mod tests {
 
         Ok(())
     }
+
+    fn segment_paths(dir: &Path, extension: &str) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p.extension().and_then(|s| s.to_str()) == Some(extension))
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[cfg_attr(miri, ignore)]
+    #[tokio::test]
+    async fn test_flush_gathers_records_into_vectored_writes() -> Result<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = LogWriterConfig::default();
+        config.log_directory = temp_dir.path().to_path_buf();
+        config.write_batch_max_records = 4;
+
+        let writer = LogWriter::new(config)?;
+        for i in 0..10 {
+            let record = LogRecord::new_data_insert(0, 100, 1, i, 0, vec![i as u8; 16], None);
+            writer.write_log(record).await?;
+        }
+        writer.flush().await?;
+
+        let records = LogRecord::read_log_records_from_directory(temp_dir.path())?;
+        assert_eq!(records.len(), 10);
+        let stats = writer.get_statistics();
+        assert!(stats.vectored_writes >= 3);
+        assert!(stats.vectored_writes <= 10);
+
+        Ok(())
+    }
+
+    #[cfg_attr(miri, ignore)]
+    #[tokio::test]
+    async fn test_preallocated_segment_resumes_after_last_record() -> Result<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = LogWriterConfig::default();
+        config.log_directory = temp_dir.path().to_path_buf();
+        config.max_log_file_size = 64 * 1024;
+        config.preallocate_segments = true;
+
+        {
+            let writer = LogWriter::new(config.clone())?;
+            for i in 0..3 {
+                writer
+                    .write_log_sync(LogRecord::new_transaction_commit(0, i, vec![], None))
+                    .await?;
+            }
+            assert_eq!(writer.get_statistics().segments_preallocated, 1);
+        }
+        let segments = segment_paths(temp_dir.path(), "log");
+        assert_eq!(segments.len(), 1);
+        assert_eq!(std::fs::metadata(&segments[0])?.len(), 64 * 1024);
+
+        let writer = LogWriter::new(config)?;
+        writer
+            .write_log_sync(LogRecord::new_transaction_commit(0, 3, vec![], None))
+            .await?;
+
+        assert_eq!(segment_paths(temp_dir.path(), "log"), segments);
+        assert_eq!(std::fs::metadata(&segments[0])?.len(), 64 * 1024);
+        let records = LogRecord::read_log_records_from_file(&segments[0])?;
+        let lsns: Vec<_> = records.iter().map(|r| r.lsn).collect();
+        assert_eq!(lsns, vec![1, 2, 3, 4]);
+
+        Ok(())
+    }
+
+    #[cfg_attr(miri, ignore)]
+    #[tokio::test]
+    async fn test_cleanup_recycles_retired_segments() -> Result<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = LogWriterConfig::default();
+        config.log_directory = temp_dir.path().to_path_buf();
+        config.max_log_file_size = 256;
+        config.max_recycled_segments = 1;
+
+        let writer = LogWriter::new(config)?;
+        let write_rotating = |first: u64| {
+            let writer = &writer;
+            async move {
+                for i in first..first + 4 {
+                    let record = LogRecord::new_data_insert(0, i, 1, i, 0, vec![7; 200], None);
+                    writer.write_log_sync(record).await?;
+                }
+                Ok::<_, Error>(())
+            }
+        };
+        write_rotating(0).await?;
+        assert_eq!(segment_paths(temp_dir.path(), "log").len(), 4);
+
+        // Three old segments retired: one kept zero-filled for reuse, two deleted
+        assert_eq!(writer.cleanup_old_logs(0).await?, 3);
+        let recycled = segment_paths(temp_dir.path(), RECYCLED_SEGMENT_EXTENSION);
+        assert_eq!(recycled.len(), 1);
+        assert!(std::fs::read(&recycled[0])?.iter().all(|b| *b == 0));
+        assert_eq!(segment_paths(temp_dir.path(), "log").len(), 1);
+
+        // The next rotation reuses the recycled segment instead of creating a file
+        write_rotating(4).await?;
+        assert_eq!(writer.get_statistics().segments_recycled, 1);
+        assert!(segment_paths(temp_dir.path(), RECYCLED_SEGMENT_EXTENSION).is_empty());
+        let lsns: Vec<_> = LogRecord::read_log_records_from_directory(temp_dir.path())?
+            .iter()
+            .map(|r| r.lsn)
+            .collect();
+        assert_eq!(lsns, (4..=8).collect::<Vec<_>>());
+
+        Ok(())
+    }
+
+    #[cfg_attr(miri, ignore)]
+    #[tokio::test]
+    async fn test_rotate_starts_new_segment() -> Result<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = LogWriterConfig::default();
+        config.log_directory = temp_dir.path().to_path_buf();
+
+        let writer = LogWriter::new(config)?;
+        writer
+            .write_log(LogRecord::new_transaction_begin(
+                0,
+                1,
+                IsolationLevel::ReadCommitted,
+            ))
+            .await?;
+        writer.rotate_log_file().await?;
+        // Nothing written since the last rotation: no segment to close
+        writer.rotate_log_file().await?;
+        writer
+            .write_log_sync(LogRecord::new_transaction_begin(
+                0,
+                2,
+                IsolationLevel::ReadCommitted,
+            ))
+            .await?;
+
+        assert_eq!(segment_paths(temp_dir.path(), "log").len(), 2);
+        assert_eq!(writer.get_statistics().file_rotations, 1);
+        let records = LogRecord::read_log_records_from_directory(temp_dir.path())?;
+        assert_eq!(records.len(), 2);
+
+        Ok(())
+    }
+
+    #[cfg_attr(miri, ignore)]
+    #[tokio::test]
+    async fn test_commit_latency_reaches_logging_metrics() -> Result<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = LogWriterConfig::default();
+        config.log_directory = temp_dir.path().to_path_buf();
+
+        let writer = LogWriter::new(config)?;
+        let metrics = Arc::new(LoggingMetricsManager::new());
+        writer.set_metrics(metrics.clone());
+        writer
+            .write_log(LogRecord::new_transaction_begin(
+                0,
+                1,
+                IsolationLevel::ReadCommitted,
+            ))
+            .await?;
+        for tx in 1..=5 {
+            writer
+                .write_log_sync(LogRecord::new_transaction_commit(0, tx, vec![], None))
+                .await?;
+        }
+
+        let commit_timing = metrics.get_metrics().commit_timing;
+        assert_eq!(commit_timing.count, 5);
+        assert!(commit_timing.p99_time_us >= commit_timing.p95_time_us);
+        assert!(commit_timing.p99_time_us <= commit_timing.max_time_us);
+        assert!(metrics
+            .export_prometheus()
+            .contains("rustdb_logging_commit_latency_microseconds_p99"));
+
+        Ok(())
+    }
 }
@@ -215,6 +215,9 @@ pub struct LoggingMetrics {
     pub checkpoint_timing: TimingMetric,
     /// Recovery time
     pub recovery_timing: TimingMetric,
+    /// Commit latency: synchronous log write until acknowledged (see
+    /// [`crate::logging::log_writer::LogWriter::set_metrics`])
+    pub commit_timing: TimingMetric,
 
     // Size metrics
     /// Log record sizes
@@ -256,6 +259,7 @@ impl LoggingMetrics {
             sync_timing: TimingMetric::default(),
             checkpoint_timing: TimingMetric::default(),
             recovery_timing: TimingMetric::default(),
+            commit_timing: TimingMetric::default(),
             log_record_size: SizeMetric::default(),
             log_file_size: SizeMetric::default(),
             buffer_utilization: 0.0,
@@ -390,6 +394,12 @@ impl LoggingMetrics {
         self.recovery_timing.record_time(duration);
     }
 
+    /// Records the latency of one commit
+    pub fn record_commit_latency(&mut self, duration: Duration) {
+        self.update_timestamp();
+        self.commit_timing.record_time(duration);
+    }
+
     /// Updates buffer utilization
     pub fn update_buffer_utilization(&mut self, utilization: f64) {
         self.buffer_utilization = utilization.clamp(0.0, 100.0);
@@ -522,6 +532,21 @@ impl LoggingMetrics {
             self.write_timing.p99_time_us
         ));
 
+        output.push_str(&format!(
+            "# HELP rustdb_logging_commit_latency_microseconds Commit latency\n"
+        ));
+        output.push_str(&format!(
+            "# TYPE rustdb_logging_commit_latency_microseconds histogram\n"
+        ));
+        output.push_str(&format!(
+            "rustdb_logging_commit_latency_microseconds_avg {}\n",
+            self.commit_timing.avg_time_us
+        ));
+        output.push_str(&format!(
+            "rustdb_logging_commit_latency_microseconds_p99 {}\n",
+            self.commit_timing.p99_time_us
+        ));
+
         // Resource metrics
         output.push_str(&format!(
             "# HELP rustdb_logging_buffer_utilization_percent Buffer utilization percentage\n"
@@ -600,6 +625,10 @@ impl LoggingMetrics {
             "95th percentile write time: {:.2} ms\n",
             self.write_timing.p95_time_us as f64 / 1000.0
         ));
+        report.push_str(&format!(
+            "99th percentile commit latency: {:.2} ms\n",
+            self.commit_timing.p99_time_us as f64 / 1000.0
+        ));
         report.push_str("\n");
 
         // Resources
@@ -715,6 +744,12 @@ impl LoggingMetricsManager {
         metrics.record_checkpoint_operation(duration, success);
     }
 
+    /// Records the latency of one commit
+    pub fn record_commit_latency(&self, duration: Duration) {
+        let mut metrics = self.metrics.write().unwrap();
+        metrics.record_commit_latency(duration);
+    }
+
     /// Updates resource metrics
     pub fn update_resource_metrics(
         &self,
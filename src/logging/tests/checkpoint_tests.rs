@@ -41,6 +41,7 @@ fn test_checkpoint_config_custom() {
         flush_threads: 8,
         flush_batch_size: 200,
         quiet: false,
+        metadata_path: None,
     };
 
     assert_eq!(config.checkpoint_interval.as_secs(), 60);
@@ -98,6 +99,7 @@ async fn test_checkpoint_config_validation() {
         flush_threads: 2,
         flush_batch_size: 50,
         quiet: false,
+        metadata_path: None,
     };
     let log_writer = create_test_log_writer();
     let _manager = CheckpointManager::new(config, log_writer);
@@ -119,6 +121,7 @@ async fn test_checkpoint_config_disabled_auto() {
         flush_threads: 4,
         flush_batch_size: 100,
         quiet: false,
+        metadata_path: None,
     };
     let log_writer = create_test_log_writer();
     let _manager = CheckpointManager::new(config, log_writer);
@@ -159,6 +162,7 @@ async fn test_checkpoint_config_thread_settings() {
             flush_threads: thread_count,
             flush_batch_size: 100,
             quiet: false,
+            metadata_path: None,
         };
         let log_writer = create_test_log_writer();
         let _manager = CheckpointManager::new(config, log_writer);
@@ -184,6 +188,7 @@ async fn test_checkpoint_config_batch_sizes() {
             flush_threads: 4,
             flush_batch_size: batch_size,
             quiet: false,
+            metadata_path: None,
         };
         let log_writer = create_test_log_writer();
         let _manager = CheckpointManager::new(config, log_writer);
@@ -192,3 +197,32 @@ async fn test_checkpoint_config_batch_sizes() {
         assert!(true);
     }
 }
+
+#[cfg_attr(miri, ignore)]
+#[tokio::test]
+async fn test_checkpoint_writes_metadata_file() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let log_writer = Arc::new(
+        LogWriter::new(LogWriterConfig {
+            log_directory: temp_dir.path().join("wal"),
+            ..LogWriterConfig::default()
+        })
+        .unwrap(),
+    );
+    let path = temp_dir.path().join("checkpoint.json");
+    let config = CheckpointConfig {
+        quiet: true,
+        metadata_path: Some(path.clone()),
+        ..CheckpointConfig::default()
+    };
+    let manager = CheckpointManager::new(config, log_writer);
+    assert!(CheckpointManager::load_metadata(&path).unwrap().is_none());
+
+    let first = manager.create_checkpoint().await.unwrap();
+    let second = manager.create_checkpoint().await.unwrap();
+    let stored = CheckpointManager::load_metadata(&path).unwrap().unwrap();
+    assert_eq!(stored.id, second.id);
+    assert_eq!(stored.lsn, second.lsn);
+    assert!(stored.lsn > first.lsn);
+    assert!(!crate::storage::atomic_file::temp_path(&path).exists());
+}
@@ -185,3 +185,27 @@ fn test_wal_get_transaction_info() {
         }
     });
 }
+
+#[cfg_attr(miri, ignore)]
+#[tokio::test]
+async fn test_wal_registers_background_jobs() {
+    use crate::core::background_jobs::{BackgroundJobManager, JobState};
+    use crate::logging::wal::{WAL_CHECKPOINT_JOB, WAL_CLEANUP_JOB};
+
+    let mut config = WalConfig::default();
+    config.auto_checkpoint = false;
+    let jobs = BackgroundJobManager::new();
+    let wal = WriteAheadLog::with_background_jobs(config, jobs.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(jobs.job_names(), vec![WAL_CHECKPOINT_JOB, WAL_CLEANUP_JOB]);
+    assert_eq!(
+        jobs.job_status(WAL_CHECKPOINT_JOB).unwrap().state,
+        JobState::Paused
+    );
+    assert!(wal.create_checkpoint().await.is_ok());
+
+    drop(wal);
+    assert!(jobs.job_names().is_empty());
+}
@@ -5,18 +5,28 @@
 //! - Ensures transaction atomicity and durability
 //! - Supports recovery after failures
 //! - Integrates with transaction and locking systems
+//!
+//! Each transaction keeps its own LSN chain (`prev_lsn` of a record is the transaction's previous
+//! record) in a [`DashMap`] entry, so concurrent transactions only share a map shard, the
+//! [`LogWriter`]'s atomic LSN counter and the WAL's atomic statistics counters.
+//!
+//! [`WriteAheadLog::create_checkpoint`] returns the LSN of the checkpoint record it wrote, and
+//! [`WriteAheadLog::wait_for_checkpoint`] waits for a checkpoint at or past an LSN, including
+//! those written by the `wal_checkpoint` background job.
 
 use crate::common::{Error, Result};
+use crate::core::background_jobs::BackgroundJobManager;
 use crate::logging::log_record::{IsolationLevel, LogRecord, LogSequenceNumber, TransactionId};
 use crate::logging::log_writer::{LogWriter, LogWriterConfig};
 use crate::storage::database_file::PageId;
+use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tokio::sync::{mpsc, Notify};
-use tokio::task::JoinHandle;
+use tokio::sync::{watch, Notify};
 
 /// WAL system configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -205,6 +215,30 @@ pub struct WalStatistics {
     pub forced_syncs: u64,
 }
 
+/// Counters behind [`WalStatistics`], updated without a lock
+#[derive(Debug, Default)]
+struct WalCounters {
+    total_transactions: AtomicU64,
+    committed_transactions: AtomicU64,
+    aborted_transactions: AtomicU64,
+    total_log_records: AtomicU64,
+    timeout_count: AtomicU64,
+    forced_syncs: AtomicU64,
+}
+
+impl WalCounters {
+    fn add(counter: &AtomicU64, n: u64) {
+        counter.fetch_add(n, Ordering::Relaxed);
+    }
+
+    fn get(counter: &AtomicU64) -> u64 {
+        counter.load(Ordering::Relaxed)
+    }
+}
+
+/// Per-transaction state, sharded by transaction id
+type TransactionTable = DashMap<TransactionId, TransactionInfo>;
+
 /// Write-Ahead Logging system
 pub struct WriteAheadLog {
     /// Configuration
@@ -212,111 +246,93 @@ pub struct WriteAheadLog {
     /// Log writer system
     log_writer: Arc<LogWriter>,
     /// Active transactions
-    transactions: Arc<RwLock<HashMap<TransactionId, TransactionInfo>>>,
+    transactions: Arc<TransactionTable>,
     /// Transaction ID generator
-    transaction_id_generator: Arc<Mutex<TransactionId>>,
+    transaction_id_generator: AtomicU64,
     /// Statistics
-    statistics: Arc<RwLock<WalStatistics>>,
+    statistics: Arc<WalCounters>,
+    /// LSN of the last completed checkpoint
+    checkpoint_lsn: Arc<watch::Sender<LogSequenceNumber>>,
     /// Transaction completion notifications
     commit_notify: Arc<Notify>,
-    /// Background tasks
-    background_handle: Option<JoinHandle<()>>,
-    /// Command channel
-    command_tx: mpsc::UnboundedSender<WalCommand>,
+    /// Background jobs ([`WAL_CHECKPOINT_JOB`], [`WAL_CLEANUP_JOB`])
+    jobs: BackgroundJobManager,
 }
 
-/// WAL management commands
-#[derive(Debug)]
-enum WalCommand {
-    /// Create checkpoint
-    CreateCheckpoint,
-    /// Cleanup finished transactions
-    CleanupTransactions,
-    /// Check timeouts
-    CheckTimeouts,
-}
+/// Background job creating automatic checkpoints (paused unless `auto_checkpoint`)
+pub const WAL_CHECKPOINT_JOB: &str = "wal_checkpoint";
+/// Background job dropping finished transactions and aborting timed-out ones
+pub const WAL_CLEANUP_JOB: &str = "wal_cleanup";
 
 impl WriteAheadLog {
     /// Create new WAL system
-    pub async fn new(mut config: WalConfig) -> Result<Self> {
+    pub async fn new(config: WalConfig) -> Result<Self> {
+        Self::with_background_jobs(config, BackgroundJobManager::new()).await
+    }
+
+    /// Create new WAL system whose background jobs run in `jobs`
+    pub async fn with_background_jobs(
+        mut config: WalConfig,
+        jobs: BackgroundJobManager,
+    ) -> Result<Self> {
         config.log_writer_config.synchronous_commit = config.synchronous_commit;
         let log_writer = Arc::new(LogWriter::new(config.log_writer_config.clone())?);
-        let (command_tx, command_rx) = mpsc::unbounded_channel();
 
-        let mut wal = Self {
+        let wal = Self {
             config: config.clone(),
             log_writer,
-            transactions: Arc::new(RwLock::new(HashMap::new())),
-            transaction_id_generator: Arc::new(Mutex::new(1)),
-            statistics: Arc::new(RwLock::new(WalStatistics::default())),
+            transactions: Arc::new(DashMap::new()),
+            transaction_id_generator: AtomicU64::new(1),
+            statistics: Arc::new(WalCounters::default()),
+            checkpoint_lsn: Arc::new(watch::Sender::new(0)),
             commit_notify: Arc::new(Notify::new()),
-            background_handle: None,
-            command_tx,
+            jobs,
         };
 
-        // Start background tasks
-        wal.start_background_tasks(command_rx).await;
+        wal.register_background_jobs()?;
 
         Ok(wal)
     }
 
-    /// Start background tasks
-    async fn start_background_tasks(
-        &mut self,
-        mut command_rx: mpsc::UnboundedReceiver<WalCommand>,
-    ) {
+    /// Registers the periodic checkpoint and cleanup jobs
+    fn register_background_jobs(&self) -> Result<()> {
         let transactions = self.transactions.clone();
-        let statistics = self.statistics.clone();
-        let config = self.config.clone();
+        let checkpoint_lsn = self.checkpoint_lsn.clone();
         let log_writer = self.log_writer.clone();
-        let command_sender = self.command_tx.clone();
-
-        self.background_handle = Some(tokio::spawn(async move {
-            let mut checkpoint_interval = tokio::time::interval(config.checkpoint_interval);
-            let mut cleanup_interval = tokio::time::interval(Duration::from_secs(30));
-
-            loop {
-                tokio::select! {
-                    // Command processing
-                    Some(command) = command_rx.recv() => {
-                        Self::handle_command(command, &transactions, &statistics, &log_writer).await;
-                    }
-
-                    // Automatic checkpoints
-                    _ = checkpoint_interval.tick() => {
-                        if config.auto_checkpoint {
-                            let _ = command_sender.send(WalCommand::CreateCheckpoint);
-                        }
-                    }
-
-                    // Periodic cleanup
-                    _ = cleanup_interval.tick() => {
-                        let _ = command_sender.send(WalCommand::CleanupTransactions);
-                        let _ = command_sender.send(WalCommand::CheckTimeouts);
-                    }
+        self.jobs.register(
+            WAL_CHECKPOINT_JOB,
+            self.config.checkpoint_interval,
+            !self.config.auto_checkpoint,
+            move || {
+                let transactions = transactions.clone();
+                let checkpoint_lsn = checkpoint_lsn.clone();
+                let log_writer = log_writer.clone();
+                async move {
+                    Self::create_checkpoint_internal(&transactions, &checkpoint_lsn, &log_writer)
+                        .await
+                        .map(|_| ())
                 }
-            }
-        }));
+            },
+        )?;
+
+        let transactions = self.transactions.clone();
+        let statistics = self.statistics.clone();
+        self.jobs
+            .register(WAL_CLEANUP_JOB, Duration::from_secs(30), false, move || {
+                let transactions = transactions.clone();
+                let statistics = statistics.clone();
+                async move {
+                    Self::cleanup_finished_transactions(&transactions, &statistics).await;
+                    Self::check_transaction_timeouts(&transactions, &statistics).await;
+                    Ok(())
+                }
+            })
     }
 
-    /// Handle management command
-    async fn handle_command(
-        command: WalCommand,
-        transactions: &Arc<RwLock<HashMap<TransactionId, TransactionInfo>>>,
-        statistics: &Arc<RwLock<WalStatistics>>,
-        log_writer: &Arc<LogWriter>,
-    ) {
-        match command {
-            WalCommand::CreateCheckpoint => {
-                Self::create_checkpoint_internal(transactions, statistics, log_writer).await;
-            }
-            WalCommand::CleanupTransactions => {
-                Self::cleanup_finished_transactions(transactions, statistics).await;
-            }
-            WalCommand::CheckTimeouts => {
-                Self::check_transaction_timeouts(transactions, statistics).await;
-            }
-        }
+    /// Background jobs of this WAL (shared with other components when created through
+    /// [`Self::with_background_jobs`])
+    pub fn background_jobs(&self) -> &BackgroundJobManager {
+        &self.jobs
     }
 
     /// Begin new transaction
@@ -325,20 +341,14 @@ impl WriteAheadLog {
         isolation_level: IsolationLevel,
     ) -> Result<TransactionId> {
         // Check active transaction limit
-        {
-            let transactions = self.transactions.read().unwrap();
-            if transactions.len() >= self.config.max_active_transactions {
-                return Err(Error::database("Active transaction limit exceeded"));
-            }
+        if self.transactions.len() >= self.config.max_active_transactions {
+            return Err(Error::database("Active transaction limit exceeded"));
         }
 
         // Generate transaction ID
-        let transaction_id = {
-            let mut generator = self.transaction_id_generator.lock().unwrap();
-            let id = *generator;
-            *generator += 1;
-            id
-        };
+        let transaction_id = self
+            .transaction_id_generator
+            .fetch_add(1, Ordering::Relaxed);
 
         // Create transaction information
         let transaction_info = TransactionInfo::new(transaction_id, isolation_level);
@@ -348,21 +358,13 @@ impl WriteAheadLog {
         let lsn = self.log_writer.write_log(begin_record).await?;
 
         // Update transaction information
-        {
-            let mut transactions = self.transactions.write().unwrap();
-            let mut tx_info = transaction_info;
-            tx_info.set_lsn(lsn);
-            transactions.insert(transaction_id, tx_info);
-        }
+        let mut tx_info = transaction_info;
+        tx_info.set_lsn(lsn);
+        self.transactions.insert(transaction_id, tx_info);
 
         // Update statistics
-        {
-            let mut stats = self.statistics.write().unwrap();
-            stats.total_transactions += 1;
-            stats.active_transactions += 1;
-            stats.total_log_records += 1; // Count BEGIN record
-            stats.current_lsn = lsn;
-        }
+        WalCounters::add(&self.statistics.total_transactions, 1);
+        WalCounters::add(&self.statistics.total_log_records, 1); // Count BEGIN record
 
         Ok(transaction_id)
     }
@@ -371,8 +373,7 @@ impl WriteAheadLog {
     pub async fn commit_transaction(&self, transaction_id: TransactionId) -> Result<()> {
         // Get transaction information
         let (dirty_pages, last_lsn) = {
-            let mut transactions = self.transactions.write().unwrap();
-            if let Some(tx_info) = transactions.get_mut(&transaction_id) {
+            if let Some(mut tx_info) = self.transactions.get_mut(&transaction_id) {
                 if tx_info.state != TransactionState::Active {
                     return Err(Error::database("Transaction is not active"));
                 }
@@ -393,23 +394,15 @@ impl WriteAheadLog {
         let commit_lsn = self.log_writer.write_log_sync(commit_record).await?;
 
         // Update transaction state
-        {
-            let mut transactions = self.transactions.write().unwrap();
-            if let Some(tx_info) = transactions.get_mut(&transaction_id) {
-                tx_info.state = TransactionState::Committed;
-                tx_info.set_lsn(commit_lsn);
-            }
+        if let Some(mut tx_info) = self.transactions.get_mut(&transaction_id) {
+            tx_info.state = TransactionState::Committed;
+            tx_info.set_lsn(commit_lsn);
         }
 
         // Update statistics
-        {
-            let mut stats = self.statistics.write().unwrap();
-            stats.committed_transactions += 1;
-            stats.active_transactions = stats.active_transactions.saturating_sub(1);
-            stats.total_log_records += 1; // Count COMMIT record
-            stats.current_lsn = commit_lsn;
-            stats.forced_syncs += 1;
-        }
+        WalCounters::add(&self.statistics.committed_transactions, 1);
+        WalCounters::add(&self.statistics.total_log_records, 1); // Count COMMIT record
+        WalCounters::add(&self.statistics.forced_syncs, 1);
 
         // Notify about transaction completion
         self.commit_notify.notify_waiters();
@@ -421,8 +414,7 @@ impl WriteAheadLog {
     pub async fn abort_transaction(&self, transaction_id: TransactionId) -> Result<()> {
         // Get transaction information
         let last_lsn = {
-            let mut transactions = self.transactions.write().unwrap();
-            if let Some(tx_info) = transactions.get_mut(&transaction_id) {
+            if let Some(mut tx_info) = self.transactions.get_mut(&transaction_id) {
                 if tx_info.state == TransactionState::Committed {
                     return Err(Error::database("Cannot abort committed transaction"));
                 }
@@ -439,22 +431,14 @@ impl WriteAheadLog {
         let abort_lsn = self.log_writer.write_log_sync(abort_record).await?;
 
         // Update transaction information
-        {
-            let mut transactions = self.transactions.write().unwrap();
-            if let Some(tx_info) = transactions.get_mut(&transaction_id) {
-                tx_info.set_lsn(abort_lsn);
-            }
+        if let Some(mut tx_info) = self.transactions.get_mut(&transaction_id) {
+            tx_info.set_lsn(abort_lsn);
         }
 
         // Update statistics
-        {
-            let mut stats = self.statistics.write().unwrap();
-            stats.aborted_transactions += 1;
-            stats.active_transactions = stats.active_transactions.saturating_sub(1);
-            stats.total_log_records += 1; // Count ABORT record
-            stats.current_lsn = abort_lsn;
-            stats.forced_syncs += 1;
-        }
+        WalCounters::add(&self.statistics.aborted_transactions, 1);
+        WalCounters::add(&self.statistics.total_log_records, 1); // Count ABORT record
+        WalCounters::add(&self.statistics.forced_syncs, 1);
 
         Ok(())
     }
@@ -468,13 +452,8 @@ impl WriteAheadLog {
         record_offset: u16,
         data: Vec<u8>,
     ) -> Result<LogSequenceNumber> {
-        self.validate_transaction(transaction_id)?;
-
         // Get previous transaction LSN
-        let prev_lsn = {
-            let transactions = self.transactions.read().unwrap();
-            transactions.get(&transaction_id).and_then(|tx| tx.last_lsn)
-        };
+        let prev_lsn = self.chain_head(transaction_id)?;
 
         // Write log record
         let insert_record = LogRecord::new_data_insert(
@@ -489,20 +468,7 @@ impl WriteAheadLog {
         let lsn = self.log_writer.write_log(insert_record).await?;
 
         // Update transaction information
-        {
-            let mut transactions = self.transactions.write().unwrap();
-            if let Some(tx_info) = transactions.get_mut(&transaction_id) {
-                tx_info.set_lsn(lsn);
-                tx_info.add_dirty_page(file_id, page_id);
-            }
-        }
-
-        // Update statistics
-        {
-            let mut stats = self.statistics.write().unwrap();
-            stats.total_log_records += 1;
-            stats.current_lsn = lsn;
-        }
+        self.extend_chain(transaction_id, lsn, file_id, page_id);
 
         Ok(lsn)
     }
@@ -517,12 +483,7 @@ impl WriteAheadLog {
         old_data: Vec<u8>,
         new_data: Vec<u8>,
     ) -> Result<LogSequenceNumber> {
-        self.validate_transaction(transaction_id)?;
-
-        let prev_lsn = {
-            let transactions = self.transactions.read().unwrap();
-            transactions.get(&transaction_id).and_then(|tx| tx.last_lsn)
-        };
+        let prev_lsn = self.chain_head(transaction_id)?;
 
         let update_record = LogRecord::new_data_update(
             0,
@@ -536,19 +497,7 @@ impl WriteAheadLog {
         );
         let lsn = self.log_writer.write_log(update_record).await?;
 
-        {
-            let mut transactions = self.transactions.write().unwrap();
-            if let Some(tx_info) = transactions.get_mut(&transaction_id) {
-                tx_info.set_lsn(lsn);
-                tx_info.add_dirty_page(file_id, page_id);
-            }
-        }
-
-        {
-            let mut stats = self.statistics.write().unwrap();
-            stats.total_log_records += 1;
-            stats.current_lsn = lsn;
-        }
+        self.extend_chain(transaction_id, lsn, file_id, page_id);
 
         Ok(lsn)
     }
@@ -562,12 +511,7 @@ impl WriteAheadLog {
         record_offset: u16,
         old_data: Vec<u8>,
     ) -> Result<LogSequenceNumber> {
-        self.validate_transaction(transaction_id)?;
-
-        let prev_lsn = {
-            let transactions = self.transactions.read().unwrap();
-            transactions.get(&transaction_id).and_then(|tx| tx.last_lsn)
-        };
+        let prev_lsn = self.chain_head(transaction_id)?;
 
         let delete_record = LogRecord::new_data_delete(
             0,
@@ -580,132 +524,126 @@ impl WriteAheadLog {
         );
         let lsn = self.log_writer.write_log(delete_record).await?;
 
-        {
-            let mut transactions = self.transactions.write().unwrap();
-            if let Some(tx_info) = transactions.get_mut(&transaction_id) {
-                tx_info.set_lsn(lsn);
-                tx_info.add_dirty_page(file_id, page_id);
-            }
-        }
+        self.extend_chain(transaction_id, lsn, file_id, page_id);
 
-        {
-            let mut stats = self.statistics.write().unwrap();
-            stats.total_log_records += 1;
-            stats.current_lsn = lsn;
+        Ok(lsn)
+    }
+
+    /// Last LSN of an active transaction's chain (`prev_lsn` of its next record)
+    fn chain_head(&self, transaction_id: TransactionId) -> Result<Option<LogSequenceNumber>> {
+        match self.transactions.get(&transaction_id) {
+            Some(tx_info) if tx_info.state == TransactionState::Active => Ok(tx_info.last_lsn),
+            Some(_) => Err(Error::database("Transaction is not active")),
+            None => Err(Error::database("Transaction not found")),
         }
+    }
 
-        Ok(lsn)
+    /// Appends a written data record to its transaction's chain
+    fn extend_chain(
+        &self,
+        transaction_id: TransactionId,
+        lsn: LogSequenceNumber,
+        file_id: u32,
+        page_id: PageId,
+    ) {
+        if let Some(mut tx_info) = self.transactions.get_mut(&transaction_id) {
+            tx_info.set_lsn(lsn);
+            tx_info.add_dirty_page(file_id, page_id);
+        }
+        WalCounters::add(&self.statistics.total_log_records, 1);
     }
 
-    /// Create checkpoint
+    /// Create checkpoint, returning the LSN of its record once it is written
     pub async fn create_checkpoint(&self) -> Result<LogSequenceNumber> {
-        let _ = self.command_tx.send(WalCommand::CreateCheckpoint);
+        Self::create_checkpoint_internal(&self.transactions, &self.checkpoint_lsn, &self.log_writer)
+            .await
+    }
 
-        // Wait for checkpoint creation to complete
-        tokio::time::sleep(Duration::from_millis(100)).await;
+    /// LSN of the last completed checkpoint (0 before the first one)
+    pub fn last_checkpoint_lsn(&self) -> LogSequenceNumber {
+        *self.checkpoint_lsn.borrow()
+    }
 
-        Ok(self.get_current_lsn())
+    /// Waits until a checkpoint whose record is at or past `lsn` has completed and returns its
+    /// LSN; returns immediately if one already has
+    pub async fn wait_for_checkpoint(&self, lsn: LogSequenceNumber) -> Result<LogSequenceNumber> {
+        let mut checkpoint_lsn = self.checkpoint_lsn.subscribe();
+        let done = checkpoint_lsn
+            .wait_for(|&done| done >= lsn)
+            .await
+            .map_err(|_| Error::database("WAL closed before the checkpoint"))?;
+        Ok(*done)
     }
 
     /// Internal checkpoint creation implementation
     async fn create_checkpoint_internal(
-        transactions: &Arc<RwLock<HashMap<TransactionId, TransactionInfo>>>,
-        statistics: &Arc<RwLock<WalStatistics>>,
+        transactions: &Arc<TransactionTable>,
+        checkpoint_lsn: &watch::Sender<LogSequenceNumber>,
         log_writer: &Arc<LogWriter>,
-    ) {
-        let (active_txs, dirty_pages, checkpoint_id) = {
-            let txs = transactions.read().unwrap();
-            let stats = statistics.read().unwrap();
-
-            let active_txs: Vec<_> = txs
-                .values()
-                .filter(|tx| tx.state == TransactionState::Active)
-                .map(|tx| tx.id)
-                .collect();
-
-            let dirty_pages: Vec<_> = txs
-                .values()
-                .flat_map(|tx| tx.dirty_pages.iter())
-                .copied()
-                .collect();
-
-            let checkpoint_id = stats.last_checkpoint_lsn + 1;
-
-            (active_txs, dirty_pages, checkpoint_id)
-        };
+    ) -> Result<LogSequenceNumber> {
+        let mut active_txs = Vec::new();
+        let mut dirty_pages = Vec::new();
+        for tx in transactions.iter() {
+            if tx.state == TransactionState::Active {
+                active_txs.push(tx.id);
+            }
+            dirty_pages.extend(tx.dirty_pages.iter().copied());
+        }
+        let checkpoint_id = *checkpoint_lsn.borrow() + 1;
 
         // Write checkpoint record
         let current_lsn = log_writer.current_lsn();
         let checkpoint_record =
             LogRecord::new_checkpoint(0, checkpoint_id, active_txs, dirty_pages, current_lsn);
 
-        if let Ok(lsn) = log_writer.write_log_sync(checkpoint_record).await {
-            let mut stats = statistics.write().unwrap();
-            stats.last_checkpoint_lsn = lsn;
-            stats.current_lsn = lsn;
-        }
+        let lsn = log_writer.write_log_sync(checkpoint_record).await?;
+        checkpoint_lsn.send_if_modified(|last| {
+            let advanced = lsn > *last;
+            if advanced {
+                *last = lsn;
+            }
+            advanced
+        });
+        Ok(lsn)
     }
 
     /// Cleanup finished transactions
     async fn cleanup_finished_transactions(
-        transactions: &Arc<RwLock<HashMap<TransactionId, TransactionInfo>>>,
-        _statistics: &Arc<RwLock<WalStatistics>>,
+        transactions: &Arc<TransactionTable>,
+        _statistics: &Arc<WalCounters>,
     ) {
-        let mut txs = transactions.write().unwrap();
-        txs.retain(|_, tx| {
+        transactions.retain(|_, tx| {
             tx.state == TransactionState::Active || tx.state == TransactionState::Preparing
         });
     }
 
     /// Check transaction timeouts
     async fn check_transaction_timeouts(
-        transactions: &Arc<RwLock<HashMap<TransactionId, TransactionInfo>>>,
-        statistics: &Arc<RwLock<WalStatistics>>,
+        transactions: &Arc<TransactionTable>,
+        statistics: &Arc<WalCounters>,
     ) {
         let timeout = Duration::from_secs(300); // 5 minutes
-        let mut timed_out_txs = Vec::new();
-
-        {
-            let txs = transactions.read().unwrap();
-            for (id, tx) in txs.iter() {
-                if tx.state == TransactionState::Active && tx.is_timed_out(timeout) {
-                    timed_out_txs.push(*id);
-                }
-            }
-        }
+        let timed_out = transactions
+            .iter()
+            .filter(|tx| tx.state == TransactionState::Active && tx.is_timed_out(timeout))
+            .count();
 
-        if !timed_out_txs.is_empty() {
-            let mut stats = statistics.write().unwrap();
-            stats.timeout_count += timed_out_txs.len() as u64;
-        }
-    }
-
-    /// Validate transaction
-    fn validate_transaction(&self, transaction_id: TransactionId) -> Result<()> {
-        let transactions = self.transactions.read().unwrap();
-        if let Some(tx_info) = transactions.get(&transaction_id) {
-            if tx_info.state != TransactionState::Active {
-                return Err(Error::database("Transaction is not active"));
-            }
-            Ok(())
-        } else {
-            Err(Error::database("Transaction not found"))
-        }
+        WalCounters::add(&statistics.timeout_count, timed_out as u64);
     }
 
     /// Get transaction information
     pub fn get_transaction_info(&self, transaction_id: TransactionId) -> Option<TransactionInfo> {
-        let transactions = self.transactions.read().unwrap();
-        transactions.get(&transaction_id).cloned()
+        self.transactions
+            .get(&transaction_id)
+            .map(|tx| tx.value().clone())
     }
 
     /// Get list of active transactions
     pub fn get_active_transactions(&self) -> Vec<TransactionInfo> {
-        let transactions = self.transactions.read().unwrap();
-        transactions
-            .values()
+        self.transactions
+            .iter()
             .filter(|tx| tx.state == TransactionState::Active)
-            .cloned()
+            .map(|tx| tx.value().clone())
             .collect()
     }
 
@@ -736,21 +674,28 @@ impl WriteAheadLog {
 
     /// Get WAL statistics
     pub fn get_statistics(&self) -> WalStatistics {
-        let mut stats = self.statistics.read().unwrap().clone();
+        let counters = &self.statistics;
+        let mut stats = WalStatistics {
+            total_transactions: WalCounters::get(&counters.total_transactions),
+            committed_transactions: WalCounters::get(&counters.committed_transactions),
+            aborted_transactions: WalCounters::get(&counters.aborted_transactions),
+            total_log_records: WalCounters::get(&counters.total_log_records),
+            timeout_count: WalCounters::get(&counters.timeout_count),
+            last_checkpoint_lsn: self.last_checkpoint_lsn(),
+            forced_syncs: WalCounters::get(&counters.forced_syncs),
+            ..WalStatistics::default()
+        };
 
         // Update current values
-        let transactions = self.transactions.read().unwrap();
-        stats.active_transactions = transactions
-            .values()
-            .filter(|tx| tx.state == TransactionState::Active)
-            .count() as u64;
-
-        // Calculate average transaction duration
-        let total_duration: u64 = transactions
-            .values()
-            .filter(|tx| tx.state != TransactionState::Active)
-            .map(|tx| tx.duration().as_millis() as u64)
-            .sum();
+        let mut total_duration = 0u64;
+        for tx in self.transactions.iter() {
+            if tx.state == TransactionState::Active {
+                stats.active_transactions += 1;
+            } else {
+                // Calculate average transaction duration
+                total_duration += tx.duration().as_millis() as u64;
+            }
+        }
 
         let completed_count = stats.committed_transactions + stats.aborted_transactions;
         if completed_count > 0 {
@@ -766,10 +711,7 @@ impl WriteAheadLog {
     pub async fn force_sync(&self) -> Result<()> {
         self.log_writer.flush().await?;
 
-        {
-            let mut stats = self.statistics.write().unwrap();
-            stats.forced_syncs += 1;
-        }
+        WalCounters::add(&self.statistics.forced_syncs, 1);
 
         Ok(())
     }
@@ -779,14 +721,12 @@ impl WriteAheadLog {
         let start = tokio::time::Instant::now();
 
         while start.elapsed() < timeout {
+            if self
+                .transactions
+                .iter()
+                .all(|tx| tx.state != TransactionState::Active)
             {
-                let transactions = self.transactions.read().unwrap();
-                if transactions
-                    .values()
-                    .all(|tx| tx.state != TransactionState::Active)
-                {
-                    return Ok(());
-                }
+                return Ok(());
             }
 
             // Wait for transaction completion notification or timeout
@@ -810,9 +750,8 @@ impl WriteAheadLog {
 
 impl Drop for WriteAheadLog {
     fn drop(&mut self) {
-        if let Some(handle) = self.background_handle.take() {
-            handle.abort();
-        }
+        self.jobs.unregister(WAL_CHECKPOINT_JOB);
+        self.jobs.unregister(WAL_CLEANUP_JOB);
     }
 }
 
@@ -939,6 +878,43 @@ mod tests {
         Ok(())
     }
 
+    #[cfg_attr(miri, ignore)]
+    #[tokio::test]
+    async fn test_wait_for_checkpoint_resolves_with_checkpoint_lsn() -> Result<()> {
+        let wal = Arc::new(create_test_wal().await?);
+        let tx_id = wal.begin_transaction(IsolationLevel::ReadCommitted).await?;
+        let insert_lsn = wal.log_insert(tx_id, 1, 10, 0, vec![1, 2, 3]).await?;
+
+        let waiter = {
+            let wal = wal.clone();
+            tokio::spawn(async move { wal.wait_for_checkpoint(insert_lsn).await })
+        };
+        let checkpoint_lsn = wal.create_checkpoint().await?;
+        assert!(checkpoint_lsn > insert_lsn);
+        assert_eq!(checkpoint_lsn, wal.get_current_lsn());
+
+        let waited = tokio::time::timeout(Duration::from_secs(5), waiter)
+            .await
+            .expect("waiter not woken")
+            .unwrap()?;
+        assert_eq!(waited, checkpoint_lsn);
+        assert_eq!(wal.get_statistics().last_checkpoint_lsn, checkpoint_lsn);
+
+        // Records written after the checkpoint need the next one
+        wal.commit_transaction(tx_id).await?;
+        let commit_lsn = wal.get_current_lsn();
+        let pending = tokio::time::timeout(
+            Duration::from_millis(50),
+            wal.wait_for_checkpoint(commit_lsn),
+        )
+        .await;
+        assert!(pending.is_err());
+        let next = wal.create_checkpoint().await?;
+        assert_eq!(wal.wait_for_checkpoint(commit_lsn).await?, next);
+
+        Ok(())
+    }
+
     #[cfg_attr(miri, ignore)]
     #[tokio::test]
     async fn test_lsn_ordering() -> Result<()> {
@@ -978,4 +954,47 @@ mod tests {
 
         Ok(())
     }
+
+    #[cfg_attr(miri, ignore)]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_transactions_keep_separate_lsn_chains() -> Result<()> {
+        let wal = Arc::new(create_test_wal().await?);
+
+        let mut tasks = Vec::new();
+        for t in 0..8u32 {
+            let wal = wal.clone();
+            tasks.push(tokio::spawn(async move {
+                let tx_id = wal.begin_transaction(IsolationLevel::ReadCommitted).await?;
+                let mut lsns = Vec::new();
+                for page in 0..10u64 {
+                    lsns.push(wal.log_insert(tx_id, t, page, 0, vec![t as u8]).await?);
+                }
+                let info = wal.get_transaction_info(tx_id).unwrap();
+                assert_eq!(info.last_lsn, lsns.last().copied());
+                assert_eq!(info.dirty_pages.len(), 10);
+                wal.commit_transaction(tx_id).await?;
+                Ok::<_, Error>(lsns)
+            }));
+        }
+        let mut all_lsns = Vec::new();
+        for task in tasks {
+            let lsns = task.await.unwrap()?;
+            // Each transaction's chain is increasing
+            assert!(lsns.windows(2).all(|w| w[0] < w[1]));
+            all_lsns.extend(lsns);
+        }
+        let count = all_lsns.len();
+        all_lsns.sort_unstable();
+        all_lsns.dedup();
+        assert_eq!(all_lsns.len(), count);
+
+        let stats = wal.get_statistics();
+        assert_eq!(stats.total_transactions, 8);
+        assert_eq!(stats.committed_transactions, 8);
+        assert_eq!(stats.active_transactions, 0);
+        assert_eq!(stats.total_log_records, 8 * 12);
+        assert!(stats.current_lsn >= *all_lsns.last().unwrap());
+
+        Ok(())
+    }
 }
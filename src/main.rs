@@ -6,6 +6,12 @@ use rustdb::cli::{Cli, Commands};
 use rustdb::common::{set_language, t, Language, MessageKey};
 use rustdb::{Database, VERSION};
 
+/// Counts heap use per subsystem (`rustdb_stat_memory`, `Profiler::memory_by_subsystem`).
+#[cfg(feature = "memory-profiling")]
+#[global_allocator]
+static ALLOC: rustdb::common::memory_tracking::TrackingAllocator =
+    rustdb::common::memory_tracking::TrackingAllocator;
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize CLI with internationalization support
     let cli = Cli::init();
@@ -20,9 +26,41 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         query,
         batch_file,
         database,
+        url,
+        trace_out,
+        check,
+        plan_out,
+        ..
     }) = &cli.command
     {
-        return cli.execute_query_sync(query.as_deref(), database.as_ref(), batch_file.as_deref());
+        return cli.execute_query_sync(
+            query.as_deref(),
+            database.as_ref(),
+            url.as_deref(),
+            batch_file.as_deref(),
+            trace_out.as_deref(),
+            *check,
+            plan_out.as_deref(),
+            cli.query_display_options(),
+        );
+    }
+
+    if let Some(Commands::Import { .. }) = &cli.command {
+        return cli.run_import_sync();
+    }
+
+    if let Some(Commands::Bench { .. }) = &cli.command {
+        return cli.run_bench_sync();
+    }
+
+    if let Some(
+        Commands::Check { .. }
+        | Commands::Vacuum { .. }
+        | Commands::Analyze { .. }
+        | Commands::Checkpoint { .. },
+    ) = &cli.command
+    {
+        std::process::exit(cli.run_maintenance_sync());
     }
 
     let rt = tokio::runtime::Builder::new_multi_thread()
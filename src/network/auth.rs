@@ -0,0 +1,507 @@
+//! Client authentication: pluggable [`Authenticator`] backends chosen by pg_hba-style rules.
+//!
+//! A server started with `network.hba_file` expects each connection to open with an
+//! `Authenticate` frame carrying a user and a password or token. The first [`HbaRule`] matching
+//! the user and the client address names the method; [`Authentication`] runs that method's
+//! backend and, on success, serves the connection as the user: its sessions hold the role of
+//! the same name (see `network.max_connections_per_role`) and cannot `SET role` to another.
+//!
+//! | Method | Accepts | Options |
+//! |--------|---------|---------|
+//! | `trust` | every connection | |
+//! | `reject` | no connection | |
+//! | `password` | a password matching a [`hash_password`] hash | `file` (`user:hash` lines) |
+//! | `ldap` | a successful simple bind as `<ldapprefix><user><ldapsuffix>` (plain LDAP) | `ldapserver`, `ldapport` (389), `ldapprefix`, `ldapsuffix` |
+//! | `token` | an HS256 JWT whose `sub` is the user and whose `exp` has not passed | `secret` or `secret_file`, `issuer`, `audience` |
+//!
+//! Other backends (PAM or OS accounts, another token issuer) are plugged in with
+//! [`Authentication::with_method`]; [`Authentication::check_methods`] reports rules naming a
+//! method nobody registered.
+
+use crate::common::config::{read_hba_file, ConfigError, HbaRule};
+use base64::engine::general_purpose::{STANDARD_NO_PAD, URL_SAFE_NO_PAD};
+use base64::Engine as _;
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{hmac, pbkdf2};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
+use std::net::{IpAddr, TcpStream, ToSocketAddrs};
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// What a connecting client presents.
+#[derive(Clone)]
+pub struct Credentials {
+    pub user: String,
+    /// Password or token, as the method expects.
+    pub secret: Option<String>,
+    pub remote: IpAddr,
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("user", &self.user)
+            .field("secret", &self.secret.as_ref().map(|_| "…"))
+            .field("remote", &self.remote)
+            .finish()
+    }
+}
+
+/// Why a connection was not authenticated.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum AuthError {
+    #[error("no authentication rule for user \"{user}\" from {remote}")]
+    NoMatchingRule { user: String, remote: IpAddr },
+    #[error("unknown authentication method \"{0}\"")]
+    UnknownMethod(String),
+    /// The credentials were checked and refused.
+    #[error("{0}")]
+    Rejected(String),
+    /// The credentials could not be checked (backend unreachable, rule misconfigured).
+    #[error("authentication backend unavailable: {0}")]
+    Unavailable(String),
+}
+
+/// An authentication method.
+pub trait Authenticator: Send + Sync {
+    /// Checks `credentials` under the options of the rule that selected this method. Runs on a
+    /// blocking thread, so it may do network or file I/O.
+    fn authenticate(
+        &self,
+        credentials: &Credentials,
+        options: &BTreeMap<String, String>,
+    ) -> Result<(), AuthError>;
+}
+
+/// Rules and the methods they select.
+pub struct Authentication {
+    rules: Vec<HbaRule>,
+    methods: HashMap<String, Arc<dyn Authenticator>>,
+}
+
+impl std::fmt::Debug for Authentication {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut methods: Vec<_> = self.methods.keys().collect();
+        methods.sort();
+        f.debug_struct("Authentication")
+            .field("rules", &self.rules)
+            .field("methods", &methods)
+            .finish()
+    }
+}
+
+impl Authentication {
+    /// `rules` with the built-in methods.
+    pub fn new(rules: Vec<HbaRule>) -> Self {
+        let mut methods: HashMap<String, Arc<dyn Authenticator>> = HashMap::new();
+        methods.insert("trust".to_string(), Arc::new(Trust));
+        methods.insert("reject".to_string(), Arc::new(Reject));
+        methods.insert(
+            "password".to_string(),
+            Arc::new(PasswordAuthenticator::default()),
+        );
+        methods.insert("ldap".to_string(), Arc::new(LdapAuthenticator));
+        methods.insert("token".to_string(), Arc::new(TokenAuthenticator));
+        Self { rules, methods }
+    }
+
+    /// Rules read from `path` (see [`HbaRule`]), with the built-in methods.
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        Ok(Self::new(read_hba_file(path)?))
+    }
+
+    /// Registers (or replaces) the backend of the method `name`.
+    pub fn with_method(mut self, name: &str, authenticator: Arc<dyn Authenticator>) -> Self {
+        self.methods
+            .insert(name.to_ascii_lowercase(), authenticator);
+        self
+    }
+
+    pub fn rules(&self) -> &[HbaRule] {
+        &self.rules
+    }
+
+    /// Fails on the first rule whose method has no backend.
+    pub fn check_methods(&self) -> Result<(), ConfigError> {
+        match self
+            .rules
+            .iter()
+            .find(|r| !self.methods.contains_key(&r.method))
+        {
+            Some(rule) => Err(ConfigError::new(
+                format!("line {}", rule.line),
+                format!("unknown authentication method {:?}", rule.method),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Authenticates `credentials` with the method of the first matching rule.
+    pub fn authenticate(&self, credentials: &Credentials) -> Result<(), AuthError> {
+        let rule = self
+            .rules
+            .iter()
+            .find(|r| r.matches(&credentials.user, credentials.remote))
+            .ok_or_else(|| AuthError::NoMatchingRule {
+                user: credentials.user.clone(),
+                remote: credentials.remote,
+            })?;
+        let method = self
+            .methods
+            .get(&rule.method)
+            .ok_or_else(|| AuthError::UnknownMethod(rule.method.clone()))?;
+        method.authenticate(credentials, &rule.options)
+    }
+}
+
+fn secret(credentials: &Credentials) -> Result<&str, AuthError> {
+    credentials
+        .secret
+        .as_deref()
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| {
+            AuthError::Rejected(format!(
+                "no password or token given for user \"{}\"",
+                credentials.user
+            ))
+        })
+}
+
+fn required<'a>(options: &'a BTreeMap<String, String>, name: &str) -> Result<&'a str, AuthError> {
+    options
+        .get(name)
+        .map(String::as_str)
+        .ok_or_else(|| AuthError::Unavailable(format!("the rule has no `{name}` option")))
+}
+
+struct Trust;
+
+impl Authenticator for Trust {
+    fn authenticate(&self, _: &Credentials, _: &BTreeMap<String, String>) -> Result<(), AuthError> {
+        Ok(())
+    }
+}
+
+struct Reject;
+
+impl Authenticator for Reject {
+    fn authenticate(
+        &self,
+        credentials: &Credentials,
+        _: &BTreeMap<String, String>,
+    ) -> Result<(), AuthError> {
+        Err(AuthError::Rejected(format!(
+            "connections of user \"{}\" from {} are rejected",
+            credentials.user, credentials.remote
+        )))
+    }
+}
+
+/// PBKDF2 rounds of new password hashes.
+const PBKDF2_ITERATIONS: u32 = 100_000;
+
+/// Hash for a `password` rule's file: `pbkdf2-sha256$<iterations>$<salt>$<hash>` (base64).
+pub fn hash_password(password: &str) -> String {
+    let mut salt = [0u8; 16];
+    SystemRandom::new()
+        .fill(&mut salt)
+        .expect("system random source");
+    let mut hash = [0u8; 32];
+    let iterations = NonZeroU32::new(PBKDF2_ITERATIONS).expect("non-zero iterations");
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        &salt,
+        password.as_bytes(),
+        &mut hash,
+    );
+    format!(
+        "pbkdf2-sha256${PBKDF2_ITERATIONS}${}${}",
+        STANDARD_NO_PAD.encode(salt),
+        STANDARD_NO_PAD.encode(hash)
+    )
+}
+
+/// Whether `password` matches `hash` (from [`hash_password`]); malformed hashes match nothing.
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    let parts: Vec<&str> = hash.trim().split('$').collect();
+    let ["pbkdf2-sha256", iterations, salt, expected] = parts[..] else {
+        return false;
+    };
+    let Some(iterations) = iterations.parse().ok().and_then(NonZeroU32::new) else {
+        return false;
+    };
+    let (Ok(salt), Ok(expected)) = (
+        STANDARD_NO_PAD.decode(salt),
+        STANDARD_NO_PAD.decode(expected),
+    ) else {
+        return false;
+    };
+    pbkdf2::verify(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        &salt,
+        password.as_bytes(),
+        &expected,
+    )
+    .is_ok()
+}
+
+/// The `password` method: hashes come from the rule's `file` option (`user:hash` lines, `#`
+/// comments; re-read on each attempt) or, without one, from [`Self::with_user`].
+#[derive(Default)]
+pub struct PasswordAuthenticator {
+    users: HashMap<String, String>,
+}
+
+impl PasswordAuthenticator {
+    /// Accepts `user` (case-insensitive) with `password`.
+    pub fn with_user(mut self, user: &str, password: &str) -> Self {
+        self.users
+            .insert(user.to_ascii_lowercase(), hash_password(password));
+        self
+    }
+}
+
+impl Authenticator for PasswordAuthenticator {
+    fn authenticate(
+        &self,
+        credentials: &Credentials,
+        options: &BTreeMap<String, String>,
+    ) -> Result<(), AuthError> {
+        let password = secret(credentials)?;
+        let hash = match options.get("file") {
+            Some(file) => std::fs::read_to_string(file)
+                .map_err(|e| AuthError::Unavailable(format!("password file {file}: {e}")))?
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .filter_map(|l| l.split_once(':'))
+                .find(|(user, _)| user.trim().eq_ignore_ascii_case(&credentials.user))
+                .map(|(_, hash)| hash.to_string()),
+            None => self
+                .users
+                .get(&credentials.user.to_ascii_lowercase())
+                .cloned(),
+        };
+        match hash {
+            Some(hash) if verify_password(password, &hash) => Ok(()),
+            _ => Err(AuthError::Rejected(format!(
+                "password authentication failed for user \"{}\"",
+                credentials.user
+            ))),
+        }
+    }
+}
+
+/// Connect, read and write bound of an LDAP bind.
+const LDAP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Largest LDAP response accepted.
+const LDAP_MAX_MESSAGE: usize = 64 * 1024;
+
+/// LDAP `invalidCredentials` result code.
+const LDAP_INVALID_CREDENTIALS: u32 = 49;
+
+/// The `ldap` method: a simple bind as the user on the rule's server.
+struct LdapAuthenticator;
+
+impl Authenticator for LdapAuthenticator {
+    fn authenticate(
+        &self,
+        credentials: &Credentials,
+        options: &BTreeMap<String, String>,
+    ) -> Result<(), AuthError> {
+        let password = secret(credentials)?;
+        let user = &credentials.user;
+        // The user is spliced into a DN: refuse anything that would change its structure.
+        if user
+            .chars()
+            .any(|c| ",+\"\\<>;=#".contains(c) || c.is_control())
+        {
+            return Err(AuthError::Rejected(format!(
+                "user \"{user}\" cannot be used in an LDAP DN"
+            )));
+        }
+        let server = required(options, "ldapserver")?;
+        let port = match options.get("ldapport") {
+            Some(p) => p
+                .parse()
+                .map_err(|_| AuthError::Unavailable(format!("invalid ldapport {p:?}")))?,
+            None => 389,
+        };
+        let option = |name: &str| options.get(name).map_or("", String::as_str);
+        let dn = format!("{}{user}{}", option("ldapprefix"), option("ldapsuffix"));
+        match ldap_simple_bind(server, port, &dn, password) {
+            Ok(0) => Ok(()),
+            Ok(LDAP_INVALID_CREDENTIALS) => Err(AuthError::Rejected(format!(
+                "LDAP authentication failed for user \"{user}\""
+            ))),
+            Ok(code) => Err(AuthError::Rejected(format!(
+                "LDAP bind as \"{dn}\" failed with result code {code}"
+            ))),
+            Err(e) => Err(AuthError::Unavailable(format!(
+                "LDAP server {server}:{port}: {e}"
+            ))),
+        }
+    }
+}
+
+/// Sends an LDAPv3 simple `BindRequest` and returns the `resultCode` of the response.
+fn ldap_simple_bind(server: &str, port: u16, dn: &str, password: &str) -> std::io::Result<u32> {
+    let addr = (server, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| std::io::Error::other("address does not resolve"))?;
+    let mut stream = TcpStream::connect_timeout(&addr, LDAP_TIMEOUT)?;
+    stream.set_read_timeout(Some(LDAP_TIMEOUT))?;
+    stream.set_write_timeout(Some(LDAP_TIMEOUT))?;
+    // LDAPMessage { messageID 1, [APPLICATION 0] BindRequest { version 3, name, simple [0] } }
+    let mut bind = ber(0x02, &[3]);
+    bind.extend(ber(0x04, dn.as_bytes()));
+    bind.extend(ber(0x80, password.as_bytes()));
+    let mut message = ber(0x02, &[1]);
+    message.extend(ber(0x60, &bind));
+    stream.write_all(&ber(0x30, &message))?;
+    // LDAPMessage { messageID, [APPLICATION 1] BindResponse { resultCode ENUMERATED, ... } }
+    let response = read_ber(&mut stream, 0x30)?;
+    let mut rest = response.as_slice();
+    read_ber(&mut rest, 0x02)?;
+    let bind_response = read_ber(&mut rest, 0x61)?;
+    let code = read_ber(&mut bind_response.as_slice(), 0x0a)?;
+    Ok(code.iter().fold(0, |acc, b| acc << 8 | u32::from(*b)))
+}
+
+/// BER element with definite length.
+fn ber(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    if content.len() < 0x80 {
+        out.push(content.len() as u8);
+    } else {
+        let len = (content.len() as u32).to_be_bytes();
+        let skip = len.iter().take_while(|b| **b == 0).count();
+        out.push(0x80 | (len.len() - skip) as u8);
+        out.extend_from_slice(&len[skip..]);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+/// Reads one BER element tagged `tag` from `input` and returns its content.
+fn read_ber(input: &mut impl Read, tag: u8) -> std::io::Result<Vec<u8>> {
+    let invalid = |m: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, m.to_string());
+    let mut head = [0u8; 2];
+    input.read_exact(&mut head)?;
+    if head[0] != tag {
+        return Err(invalid("unexpected LDAP response"));
+    }
+    let len = if head[1] < 0x80 {
+        usize::from(head[1])
+    } else {
+        let n = usize::from(head[1] & 0x7f);
+        if n == 0 || n > 4 {
+            return Err(invalid("unsupported LDAP length encoding"));
+        }
+        let mut len = [0u8; 4];
+        input.read_exact(&mut len[4 - n..])?;
+        u32::from_be_bytes(len) as usize
+    };
+    if len > LDAP_MAX_MESSAGE {
+        return Err(invalid("LDAP response too large"));
+    }
+    let mut content = vec![0; len];
+    input.read_exact(&mut content)?;
+    Ok(content)
+}
+
+/// The `token` method: an HS256 JSON Web Token signed with the rule's secret.
+struct TokenAuthenticator;
+
+impl Authenticator for TokenAuthenticator {
+    fn authenticate(
+        &self,
+        credentials: &Credentials,
+        options: &BTreeMap<String, String>,
+    ) -> Result<(), AuthError> {
+        let token = secret(credentials)?;
+        let key = match (options.get("secret"), options.get("secret_file")) {
+            (Some(secret), _) => secret.clone(),
+            (None, Some(file)) => std::fs::read_to_string(file)
+                .map_err(|e| AuthError::Unavailable(format!("token secret file {file}: {e}")))?
+                .trim_end()
+                .to_string(),
+            (None, None) => {
+                return Err(AuthError::Unavailable(
+                    "the rule has no `secret` or `secret_file` option".to_string(),
+                ))
+            }
+        };
+        let rejected = |reason: &str| {
+            AuthError::Rejected(format!(
+                "invalid token for user \"{}\": {reason}",
+                credentials.user
+            ))
+        };
+        let claims = verify_jwt(token, key.as_bytes()).map_err(rejected)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let sub = claims["sub"].as_str().unwrap_or_default();
+        if !sub.eq_ignore_ascii_case(&credentials.user) {
+            return Err(rejected("issued to another subject"));
+        }
+        match claims["exp"].as_u64() {
+            Some(exp) if exp > now => {}
+            Some(_) => return Err(rejected("expired")),
+            None => return Err(rejected("no `exp` claim")),
+        }
+        if claims["nbf"].as_u64().is_some_and(|nbf| nbf > now) {
+            return Err(rejected("not valid yet"));
+        }
+        if let Some(issuer) = options.get("issuer") {
+            if claims["iss"].as_str() != Some(issuer.as_str()) {
+                return Err(rejected("wrong issuer"));
+            }
+        }
+        if let Some(audience) = options.get("audience") {
+            let aud = &claims["aud"];
+            let ok = aud.as_str() == Some(audience.as_str())
+                || aud
+                    .as_array()
+                    .is_some_and(|a| a.iter().any(|v| v.as_str() == Some(audience.as_str())));
+            if !ok {
+                return Err(rejected("wrong audience"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks the HS256 signature of `token` and returns its claims.
+fn verify_jwt(token: &str, key: &[u8]) -> Result<serde_json::Value, &'static str> {
+    let decode = |part: &str| -> Result<serde_json::Value, &'static str> {
+        let bytes = URL_SAFE_NO_PAD.decode(part).map_err(|_| "malformed")?;
+        serde_json::from_slice(&bytes).map_err(|_| "malformed")
+    };
+    let parts: Vec<&str> = token.trim().split('.').collect();
+    let [header, claims, signature] = parts[..] else {
+        return Err("not a JWT");
+    };
+    if decode(header)?["alg"].as_str() != Some("HS256") {
+        return Err("unsupported algorithm (expected HS256)");
+    }
+    let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| "malformed")?;
+    let signed = &token.trim()[..header.len() + 1 + claims.len()];
+    hmac::verify(
+        &hmac::Key::new(hmac::HMAC_SHA256, key),
+        signed.as_bytes(),
+        &signature,
+    )
+    .map_err(|_| "bad signature")?;
+    decode(claims)
+}
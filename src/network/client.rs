@@ -8,12 +8,17 @@ use quinn::Connection;
 use rustls::pki_types::CertificateDer;
 use thiserror::Error;
 
+use crate::common::types::DataType;
 use crate::network::framing::{
-    decode_server_frame_v1, encode_client_message_v1, ClientMessage, ProtocolError, QueryPayload,
-    ServerMessage, MAX_FRAME_PAYLOAD_BYTES,
+    compress_frame, decode_server_frame_v1, encode_client_message_v1, AuthenticatePayload,
+    ClientMessage, CopyDataPayload, CopyInPayload, ProtocolError, QueryPayload,
+    QueryWithFormatPayload, ResultFormat, ServerMessage, MAX_FRAME_PAYLOAD_BYTES,
+};
+use crate::network::query_stream::{read_application_frame, DEFAULT_COMPRESSION_MIN_BYTES};
+use crate::network::server::{
+    ensure_rustls_crypto_provider, negotiated_compression, ServerConfig, ALPN_RUSTDB_V1,
+    ALPN_RUSTDB_V1_LZ4,
 };
-use crate::network::query_stream::read_application_frame;
-use crate::network::server::{ensure_rustls_crypto_provider, ServerConfig, ALPN_RUSTDB_V1};
 use crate::network::transport::transport_config_arc;
 
 /// Build a [`quinn::ClientConfig`] that trusts the given certificates (e.g. dev server leaf) and uses [`ALPN_RUSTDB_V1`].
@@ -30,11 +35,40 @@ pub fn build_quinn_client_config(
     )
 }
 
+/// Same as [`build_quinn_client_config`], but also offering [`ALPN_RUSTDB_V1_LZ4`]: a server
+/// that allows it compresses large responses, and the client may compress large requests
+/// (see [`negotiated_compression`]). Servers without compression fall back to plain frames.
+pub fn build_compressed_quinn_client_config(
+    trusted_certs: &[CertificateDer<'_>],
+) -> Result<quinn::ClientConfig, QuicClientError> {
+    let d = ServerConfig::default();
+    client_config(
+        trusted_certs,
+        d.max_concurrent_streams_per_connection,
+        d.connection_timeout,
+        vec![ALPN_RUSTDB_V1_LZ4.to_vec(), ALPN_RUSTDB_V1.to_vec()],
+    )
+}
+
 /// Same as [`build_quinn_client_config`], but with explicit stream and idle limits (mirror the server's [`ServerConfig`]).
 pub fn build_quinn_client_config_with_limits(
     trusted_certs: &[CertificateDer<'_>],
     max_concurrent_streams: usize,
     idle_timeout: Duration,
+) -> Result<quinn::ClientConfig, QuicClientError> {
+    client_config(
+        trusted_certs,
+        max_concurrent_streams,
+        idle_timeout,
+        vec![ALPN_RUSTDB_V1.to_vec()],
+    )
+}
+
+fn client_config(
+    trusted_certs: &[CertificateDer<'_>],
+    max_concurrent_streams: usize,
+    idle_timeout: Duration,
+    alpn_protocols: Vec<Vec<u8>>,
 ) -> Result<quinn::ClientConfig, QuicClientError> {
     ensure_rustls_crypto_provider();
     let mut roots = rustls::RootCertStore::empty();
@@ -44,7 +78,7 @@ pub fn build_quinn_client_config_with_limits(
     let mut rustls_client = rustls::ClientConfig::builder()
         .with_root_certificates(Arc::new(roots))
         .with_no_client_auth();
-    rustls_client.alpn_protocols = vec![ALPN_RUSTDB_V1.to_vec()];
+    rustls_client.alpn_protocols = alpn_protocols;
     let quic = quinn::crypto::rustls::QuicClientConfig::try_from(rustls_client)?;
     let mut cfg = quinn::ClientConfig::new(Arc::new(quic));
     let transport = transport_config_arc(max_concurrent_streams, idle_timeout)?;
@@ -90,10 +124,104 @@ pub async fn connect(
 pub async fn query_once(
     connection: &Connection,
     sql: &str,
+) -> Result<ServerMessage, QuicClientError> {
+    request_once(
+        connection,
+        &ClientMessage::Query(QueryPayload {
+            sql: sql.to_string(),
+        }),
+    )
+    .await
+}
+
+/// [`query_once`], with the result set in `format` ([`ServerMessage::BinaryResultSet`] for
+/// [`ResultFormat::Binary`]).
+pub async fn query_once_with_format(
+    connection: &Connection,
+    sql: &str,
+    format: ResultFormat,
+) -> Result<ServerMessage, QuicClientError> {
+    request_once(
+        connection,
+        &ClientMessage::QueryWithFormat(QueryWithFormatPayload {
+            sql: sql.to_string(),
+            result_format: format,
+        }),
+    )
+    .await
+}
+
+async fn request_once(
+    connection: &Connection,
+    msg: &ClientMessage,
+) -> Result<ServerMessage, QuicClientError> {
+    let (mut send, mut recv) = connection.open_bi().await?;
+    let mut frame = encode_client_message_v1(msg)?;
+    if negotiated_compression(connection) {
+        if let Some(smaller) = compress_frame(&frame, DEFAULT_COMPRESSION_MIN_BYTES) {
+            frame = smaller;
+        }
+    }
+    send.write_all(&frame).await?;
+    let _ = send.finish();
+    let response = read_application_frame(&mut recv, MAX_FRAME_PAYLOAD_BYTES)
+        .await
+        .map_err(QuicClientError::from)?;
+    Ok(decode_server_frame_v1(&response)?)
+}
+
+/// `CopyData` frames of a COPY carry about this many bytes of rows each.
+pub const COPY_DATA_BATCH_BYTES: usize = 1 << 20;
+
+/// Bulk-loads `rows` into `table` with a COPY on one stream (see [`crate::network::copy_in`]):
+/// the server answers once, `ExecutionOk` with the rows loaded or the error that stopped them.
+///
+/// `columns` names the columns each row gives values for; empty means all, in table order.
+pub async fn copy_in(
+    connection: &Connection,
+    table: &str,
+    columns: &[&str],
+    rows: &[Vec<DataType>],
+) -> Result<ServerMessage, QuicClientError> {
+    let column_count = rows.first().map_or(columns.len(), Vec::len);
+    let compressed = negotiated_compression(connection);
+    let (mut send, mut recv) = connection.open_bi().await?;
+    let start = ClientMessage::CopyIn(CopyInPayload {
+        table: table.to_string(),
+        columns: columns.iter().map(|c| c.to_string()).collect(),
+    });
+    let batches = CopyDataPayload::batches(column_count, rows, COPY_DATA_BATCH_BYTES);
+    for msg in std::iter::once(start)
+        .chain(batches.into_iter().map(ClientMessage::CopyData))
+        .chain(std::iter::once(ClientMessage::CopyDone))
+    {
+        let mut frame = encode_client_message_v1(&msg)?;
+        if compressed {
+            if let Some(smaller) = compress_frame(&frame, DEFAULT_COMPRESSION_MIN_BYTES) {
+                frame = smaller;
+            }
+        }
+        send.write_all(&frame).await?;
+    }
+    let _ = send.finish();
+    let response = read_application_frame(&mut recv, MAX_FRAME_PAYLOAD_BYTES)
+        .await
+        .map_err(QuicClientError::from)?;
+    Ok(decode_server_frame_v1(&response)?)
+}
+
+/// Sends the [`ClientMessage::Authenticate`] frame a server with authentication rules expects
+/// first (see [`crate::network::auth`]) and returns its answer: `ExecutionOk`, or an `Error`
+/// after which the server closes the connection.
+pub async fn authenticate(
+    connection: &Connection,
+    user: &str,
+    secret: Option<&str>,
 ) -> Result<ServerMessage, QuicClientError> {
     let (mut send, mut recv) = connection.open_bi().await?;
-    let frame = encode_client_message_v1(&ClientMessage::Query(QueryPayload {
-        sql: sql.to_string(),
+    let frame = encode_client_message_v1(&ClientMessage::Authenticate(AuthenticatePayload {
+        user: user.to_string(),
+        secret: secret.map(str::to_string),
     }))?;
     send.write_all(&frame).await?;
     let _ = send.finish();
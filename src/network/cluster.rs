@@ -0,0 +1,924 @@
+//! Experimental hash-sharded cluster: a coordinator in front of rustdb shard servers
+//!
+//! [`Coordinator`] implements [`EngineHandle`], so it can be served by
+//! [`crate::network::server::QuicServer`] like a single node while the data lives on shard
+//! servers reached over the QUIC protocol. Every table has a distribution key column and a row
+//! lives on shard `xxh64(key) % shard_count` (see [`shard_index`]). Statements are routed as
+//! follows:
+//!
+//! - `CREATE TABLE`, `CREATE INDEX`, `ALTER TABLE` and `DROP TABLE` run on every shard, one
+//!   after the other (not atomically). A new table is distributed by the column configured in
+//!   [`ClusterConfig::distribution_keys`], else by its first `PRIMARY KEY` column.
+//! - `INSERT ... VALUES` (with a column list) splits its rows by the key value.
+//! - `SELECT`, `UPDATE` and `DELETE` whose `WHERE` pins the key (a `key = literal` conjunct) go
+//!   to one shard. Others are scattered to every shard and gathered: row counts are summed, rows
+//!   are concatenated, sorted by `ORDER BY` and cut to `LIMIT` on the coordinator. Scattered
+//!   `SELECT`s cannot use aggregates, `GROUP BY`, `DISTINCT`, `OFFSET` or joins.
+//! - A write that touches several shards commits with two-phase commit: each participant runs
+//!   `PREPARE TRANSACTION '<gid>'`, then `COMMIT PREPARED` once all of them prepared, or
+//!   `ROLLBACK PREPARED` when one failed. `BEGIN` … `COMMIT` on the coordinator keeps one shard
+//!   session per participant and commits the same way.
+//!
+//! Prepared transactions live in shard memory only: a shard restart between the two phases
+//! rolls its part back, and a `COMMIT PREPARED` failure is reported but not retried.
+
+use crate::common::Error;
+use crate::network::client::{
+    build_quinn_client_config, connect, make_client_endpoint, QuicClientError,
+};
+use crate::network::engine::{
+    engine_error_code, EngineError, EngineHandle, EngineOutput, SessionContext,
+};
+use crate::network::framing::{
+    decode_server_frame_v1, encode_client_message_v1, ClientMessage, QueryPayload, ServerMessage,
+    MAX_FRAME_PAYLOAD_BYTES,
+};
+use crate::network::query_stream::read_application_frame;
+use crate::parser::ast::{
+    AlterTableOperation, BinaryOperator, ColumnConstraint, CreateTableStatement, Expression,
+    InsertStatement, InsertValues, Literal, OrderDirection, SelectItem, SelectStatement,
+    TableConstraint, TableReference, UnaryOperator,
+};
+use crate::parser::{SqlParser, SqlStatement};
+use crate::planner::planner::expression_to_sql;
+use quinn::{Connection, RecvStream, SendStream};
+use rustls::pki_types::CertificateDer;
+use std::cmp::Ordering;
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::{Mutex, RwLock};
+use twox_hash::XxHash64;
+
+/// One shard server
+#[derive(Debug, Clone)]
+pub struct ShardNode {
+    pub addr: SocketAddr,
+    /// TLS name on the shard certificate
+    pub server_name: String,
+    /// Shard leaf certificate (as written by `rustdb server --cert-out`)
+    pub certificate: CertificateDer<'static>,
+}
+
+impl ShardNode {
+    /// Shard whose certificate is issued for the IP of `addr` (the `rustdb server` default)
+    pub fn new(addr: SocketAddr, certificate: CertificateDer<'static>) -> Self {
+        Self {
+            addr,
+            server_name: addr.ip().to_string(),
+            certificate,
+        }
+    }
+}
+
+/// Shards and distribution keys of a cluster
+#[derive(Debug, Clone, Default)]
+pub struct ClusterConfig {
+    /// Shard servers; the order defines shard numbers and must not change once data is loaded
+    pub shards: Vec<ShardNode>,
+    /// Distribution key column by table name
+    pub distribution_keys: HashMap<String, String>,
+}
+
+/// Shard of a distribution key in canonical text form (integers in decimal, strings unquoted)
+pub fn shard_index(key: &str, shard_count: usize) -> usize {
+    (XxHash64::oneshot(0, key.as_bytes()) % shard_count.max(1) as u64) as usize
+}
+
+/// Canonical distribution key text of a literal (`None` for NULL and non-literals)
+fn key_value(expr: &Expression) -> Option<String> {
+    match expr {
+        Expression::Literal(Literal::Integer(i)) => Some(i.to_string()),
+        Expression::Literal(Literal::Float(f)) if f.fract() == 0.0 && f.abs() < 9.0e15 => {
+            Some((*f as i64).to_string())
+        }
+        Expression::Literal(Literal::Float(f)) => Some(f.to_string()),
+        Expression::Literal(Literal::Boolean(b)) => Some(b.to_string()),
+        // String literals keep their source quoting.
+        Expression::Literal(Literal::String(s)) => Some(
+            s.strip_prefix('\'')
+                .and_then(|s| s.strip_suffix('\''))
+                .unwrap_or(s)
+                .replace("\\'", "'"),
+        ),
+        Expression::UnaryOp {
+            op: UnaryOperator::Minus,
+            expr,
+        } => match expr.as_ref() {
+            Expression::Literal(Literal::Integer(_) | Literal::Float(_)) => {
+                key_value(expr).map(|v| format!("-{}", v))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn split_conjuncts<'a>(expr: &'a Expression, out: &mut Vec<&'a Expression>) {
+    match expr {
+        Expression::BinaryOp {
+            left,
+            op: BinaryOperator::And,
+            right,
+        } => {
+            split_conjuncts(left, out);
+            split_conjuncts(right, out);
+        }
+        other => out.push(other),
+    }
+}
+
+/// Open shard session of a cluster transaction: one QUIC stream inside its own `BEGIN`
+struct ShardSession {
+    send: SendStream,
+    recv: RecvStream,
+}
+
+impl ShardSession {
+    async fn query(&mut self, sql: &str) -> std::result::Result<ServerMessage, QuicClientError> {
+        let frame = encode_client_message_v1(&ClientMessage::Query(QueryPayload {
+            sql: sql.to_string(),
+        }))?;
+        self.send.write_all(&frame).await?;
+        let response = read_application_frame(&mut self.recv, MAX_FRAME_PAYLOAD_BYTES).await?;
+        Ok(decode_server_frame_v1(&response)?)
+    }
+}
+
+/// Coordinator side of a `BEGIN` … `COMMIT` (kept in the client's [`SessionContext`])
+#[derive(Default)]
+pub struct ClusterTransaction {
+    /// Shard sessions opened so far, by shard number
+    participants: BTreeMap<usize, ShardSession>,
+}
+
+impl std::fmt::Debug for ClusterTransaction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClusterTransaction")
+            .field(
+                "participants",
+                &self.participants.keys().collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// Where a statement runs
+enum Route {
+    One(usize),
+    All,
+}
+
+/// Coordinator of a hash-sharded cluster (see the module docs)
+pub struct Coordinator {
+    shards: Vec<ShardNode>,
+    /// Distribution key column by table (lowercase names)
+    keys: RwLock<HashMap<String, String>>,
+    endpoint: quinn::Endpoint,
+    connections: Mutex<Vec<Option<Connection>>>,
+    /// Prefix of two-phase commit ids, unique per coordinator instance
+    gid_prefix: String,
+    next_gid: AtomicU64,
+    /// Drives the shard connections; `None` only while dropping
+    runtime: Option<tokio::runtime::Runtime>,
+}
+
+impl Coordinator {
+    /// Creates the coordinator; shard connections are opened on first use
+    pub fn new(config: ClusterConfig) -> crate::common::Result<Self> {
+        if config.shards.is_empty() {
+            return Err(Error::validation("a cluster needs at least one shard"));
+        }
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .thread_name("rustdb-coordinator")
+            .enable_all()
+            .build()
+            .map_err(|e| Error::database(format!("coordinator runtime: {}", e)))?;
+        let certificates: Vec<_> = config
+            .shards
+            .iter()
+            .map(|s| s.certificate.clone())
+            .collect();
+        let endpoint_error =
+            |e: &dyn std::fmt::Display| Error::database(format!("coordinator endpoint: {}", e));
+        let client = build_quinn_client_config(&certificates).map_err(|e| endpoint_error(&e))?;
+        let endpoint = {
+            let _runtime = runtime.enter();
+            make_client_endpoint(client).map_err(|e| endpoint_error(&e))?
+        };
+        let started = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        Ok(Self {
+            connections: Mutex::new(vec![None; config.shards.len()]),
+            shards: config.shards,
+            keys: RwLock::new(
+                config
+                    .distribution_keys
+                    .into_iter()
+                    .map(|(t, c)| (t.to_lowercase(), c.to_lowercase()))
+                    .collect(),
+            ),
+            endpoint,
+            gid_prefix: format!("rustdb-2pc-{}-{:x}", std::process::id(), started),
+            next_gid: AtomicU64::new(1),
+            runtime: Some(runtime),
+        })
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Distribution key column of `table`, if the coordinator knows the table
+    pub fn distribution_key(&self, table: &str) -> Option<String> {
+        self.keys.read().ok()?.get(&table.to_lowercase()).cloned()
+    }
+
+    fn block_on<F: std::future::Future>(&self, future: F) -> F::Output {
+        self.runtime
+            .as_ref()
+            .expect("coordinator runtime is only taken on drop")
+            .block_on(future)
+    }
+
+    fn table_key(&self, table: &str) -> Result<String, EngineError> {
+        self.distribution_key(table).ok_or_else(|| {
+            unsupported(format!(
+                "table {} is not distributed (create it through the coordinator or configure \
+                 its distribution key)",
+                table
+            ))
+        })
+    }
+
+    /// Shard pinned by a `key = literal` conjunct of `where_clause`; unqualified columns count
+    /// only when `unqualified` (no joins)
+    fn pinned_shard(
+        &self,
+        where_clause: Option<&Expression>,
+        key: &str,
+        names: &[&str],
+        unqualified: bool,
+    ) -> Option<usize> {
+        let is_key = |e: &Expression| match e {
+            Expression::Identifier(c) => unqualified && c.eq_ignore_ascii_case(key),
+            Expression::QualifiedIdentifier { table, column } => {
+                column.eq_ignore_ascii_case(key)
+                    && names.iter().any(|n| n.eq_ignore_ascii_case(table))
+            }
+            _ => false,
+        };
+        let mut conjuncts = Vec::new();
+        split_conjuncts(where_clause?, &mut conjuncts);
+        conjuncts
+            .into_iter()
+            .find_map(|c| match c {
+                Expression::BinaryOp {
+                    left,
+                    op: BinaryOperator::Equal,
+                    right,
+                } if is_key(left) => key_value(right),
+                Expression::BinaryOp {
+                    left,
+                    op: BinaryOperator::Equal,
+                    right,
+                } if is_key(right) => key_value(left),
+                _ => None,
+            })
+            .map(|value| shard_index(&value, self.shards.len()))
+    }
+
+    async fn connection(&self, shard: usize) -> Result<Connection, EngineError> {
+        if let Some(conn) = self
+            .connections
+            .lock()
+            .map_err(|_| lock_poisoned())?
+            .get(shard)
+            .and_then(Option::as_ref)
+            .filter(|c| c.close_reason().is_none())
+        {
+            return Ok(conn.clone());
+        }
+        let node = &self.shards[shard];
+        let conn = connect(&self.endpoint, node.addr, &node.server_name)
+            .await
+            .map_err(|e| shard_error(shard, &e))?;
+        self.connections.lock().map_err(|_| lock_poisoned())?[shard] = Some(conn.clone());
+        Ok(conn)
+    }
+
+    /// Runs one statement per `(shard, sql)` and returns the outputs in order: inside `txn`
+    /// when given, otherwise as independent auto-commit statements
+    async fn run(
+        &self,
+        txn: Option<&mut ClusterTransaction>,
+        work: Vec<(usize, String)>,
+    ) -> Result<Vec<EngineOutput>, EngineError> {
+        if let Some(txn) = txn {
+            let mut outputs = Vec::with_capacity(work.len());
+            for (shard, sql) in work {
+                let session = self.participant(txn, shard).await?;
+                outputs.push(shard_output(
+                    shard,
+                    session
+                        .query(&sql)
+                        .await
+                        .map_err(|e| shard_error(shard, &e))?,
+                )?);
+            }
+            return Ok(outputs);
+        }
+        let mut pending = Vec::with_capacity(work.len());
+        for (shard, sql) in work {
+            let conn = self.connection(shard).await?;
+            pending.push((
+                shard,
+                tokio::spawn(async move {
+                    let (send, recv) = conn.open_bi().await?;
+                    ShardSession { send, recv }.query(&sql).await
+                }),
+            ));
+        }
+        let mut outputs = Vec::with_capacity(pending.len());
+        for (shard, handle) in pending {
+            let message = handle
+                .await
+                .map_err(|e| shard_error(shard, &e))?
+                .map_err(|e| shard_error(shard, &e))?;
+            outputs.push(shard_output(shard, message)?);
+        }
+        Ok(outputs)
+    }
+
+    /// Shard session of `txn`, opened with `BEGIN TRANSACTION` on first use
+    async fn participant<'t>(
+        &self,
+        txn: &'t mut ClusterTransaction,
+        shard: usize,
+    ) -> Result<&'t mut ShardSession, EngineError> {
+        let session = match txn.participants.entry(shard) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => {
+                let conn = self.connection(shard).await?;
+                let (send, recv) = conn.open_bi().await.map_err(|e| shard_error(shard, &e))?;
+                let mut session = ShardSession { send, recv };
+                let begin = session
+                    .query("BEGIN TRANSACTION")
+                    .await
+                    .map_err(|e| shard_error(shard, &e))?;
+                shard_output(shard, begin)?;
+                e.insert(session)
+            }
+        };
+        Ok(session)
+    }
+
+    /// Writes on several shards without an open transaction: one statement per shard inside an
+    /// implicit cluster transaction, committed in two phases
+    async fn run_atomic(
+        &self,
+        work: Vec<(usize, String)>,
+    ) -> Result<Vec<EngineOutput>, EngineError> {
+        let mut txn = ClusterTransaction::default();
+        match self.run(Some(&mut txn), work).await {
+            Ok(outputs) => {
+                self.commit(txn).await?;
+                Ok(outputs)
+            }
+            Err(e) => {
+                self.rollback(txn).await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn commit(&self, mut txn: ClusterTransaction) -> Result<(), EngineError> {
+        if txn.participants.len() <= 1 {
+            for (&shard, session) in txn.participants.iter_mut() {
+                let reply = session
+                    .query("COMMIT")
+                    .await
+                    .map_err(|e| shard_error(shard, &e))?;
+                shard_output(shard, reply)?;
+            }
+            return Ok(());
+        }
+
+        let gid = format!(
+            "{}-{}",
+            self.gid_prefix,
+            self.next_gid.fetch_add(1, AtomicOrdering::Relaxed)
+        );
+        // Phase one: every participant detaches its transaction under `gid`.
+        let mut prepared = Vec::new();
+        let mut failure = None;
+        for (&shard, session) in txn.participants.iter_mut() {
+            let reply = session
+                .query(&format!("PREPARE TRANSACTION '{}'", gid))
+                .await
+                .map_err(|e| shard_error(shard, &e))
+                .and_then(|m| shard_output(shard, m));
+            match reply {
+                Ok(_) => prepared.push(shard),
+                Err(e) => {
+                    failure = Some(e);
+                    break;
+                }
+            }
+        }
+        tracing::debug!(gid = %gid, prepared = prepared.len(), failed = failure.is_some(), "cluster commit");
+
+        if let Some(error) = failure {
+            for (&shard, session) in txn.participants.iter_mut() {
+                let sql = if prepared.contains(&shard) {
+                    format!("ROLLBACK PREPARED '{}'", gid)
+                } else {
+                    "ROLLBACK".to_string()
+                };
+                let _ = session.query(&sql).await;
+            }
+            return Err(error);
+        }
+        // Phase two: every participant prepared, so the transaction is committed.
+        let mut result = Ok(());
+        for (&shard, session) in txn.participants.iter_mut() {
+            let reply = session
+                .query(&format!("COMMIT PREPARED '{}'", gid))
+                .await
+                .map_err(|e| shard_error(shard, &e))
+                .and_then(|m| shard_output(shard, m));
+            if let Err(e) = reply {
+                tracing::warn!(gid = %gid, shard, error = %e.message, "COMMIT PREPARED failed");
+                result = result.and(Err(e));
+            }
+        }
+        result
+    }
+
+    async fn rollback(&self, mut txn: ClusterTransaction) {
+        for session in txn.participants.values_mut() {
+            let _ = session.query("ROLLBACK").await;
+        }
+    }
+
+    /// Runs `work` in the session transaction, as one auto-commit statement, or atomically
+    /// across shards
+    fn execute(
+        &self,
+        ctx: &mut SessionContext,
+        work: Vec<(usize, String)>,
+        write: bool,
+    ) -> Result<Vec<EngineOutput>, EngineError> {
+        match ctx.cluster_transaction.as_mut() {
+            Some(txn) => self.block_on(self.run(Some(txn), work)),
+            None if write && work.len() > 1 => self.block_on(self.run_atomic(work)),
+            None => self.block_on(self.run(None, work)),
+        }
+    }
+
+    fn broadcast_ddl(&self, ctx: &SessionContext, sql: &str) -> Result<(), EngineError> {
+        if ctx.cluster_transaction.is_some() {
+            return Err(EngineError::new(
+                engine_error_code::DDL_IN_TRANSACTION,
+                "DDL is not supported inside an explicit transaction",
+            ));
+        }
+        let work = (0..self.shards.len())
+            .map(|s| (s, sql.to_string()))
+            .collect();
+        self.block_on(self.run(None, work)).map(|_| ())
+    }
+
+    fn create_table(
+        &self,
+        ctx: &SessionContext,
+        sql: &str,
+        ct: &CreateTableStatement,
+    ) -> Result<EngineOutput, EngineError> {
+        let key = match self.distribution_key(&ct.table_name) {
+            Some(key) => key,
+            None => first_primary_key(ct).ok_or_else(|| {
+                unsupported(format!(
+                    "table {} needs a distribution key: declare a PRIMARY KEY or configure one",
+                    ct.table_name
+                ))
+            })?,
+        };
+        if !ct.columns.iter().any(|c| c.name.eq_ignore_ascii_case(&key)) {
+            return Err(unsupported(format!(
+                "distribution key {} is not a column of {}",
+                key, ct.table_name
+            )));
+        }
+        self.broadcast_ddl(ctx, sql)?;
+        self.keys
+            .write()
+            .map_err(|_| lock_poisoned())?
+            .insert(ct.table_name.to_lowercase(), key.to_lowercase());
+        Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+    }
+
+    fn insert(
+        &self,
+        ctx: &mut SessionContext,
+        insert: &InsertStatement,
+    ) -> Result<EngineOutput, EngineError> {
+        let key = self.table_key(&insert.table)?;
+        let (Some(columns), InsertValues::Values(rows)) = (&insert.columns, &insert.values) else {
+            return Err(unsupported(
+                "INSERT through the coordinator needs a column list and VALUES",
+            ));
+        };
+        let key_index = columns
+            .iter()
+            .position(|c| c.eq_ignore_ascii_case(&key))
+            .ok_or_else(|| {
+                unsupported(format!(
+                    "INSERT into {} must set the distribution key {}",
+                    insert.table, key
+                ))
+            })?;
+        let mut by_shard: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+        for row in rows {
+            let value = row.get(key_index).and_then(key_value).ok_or_else(|| {
+                unsupported(format!(
+                    "distribution key {} must be a non-NULL literal",
+                    key
+                ))
+            })?;
+            let values = row
+                .iter()
+                .map(|e| expression_to_sql(e, &|_, _| false))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| unsupported("INSERT values must be literal expressions"))?;
+            by_shard
+                .entry(shard_index(&value, self.shards.len()))
+                .or_default()
+                .push(format!("({})", values.join(", ")));
+        }
+        let work = by_shard
+            .into_iter()
+            .map(|(shard, values)| {
+                (
+                    shard,
+                    format!(
+                        "INSERT INTO {} ({}) VALUES {}",
+                        insert.table,
+                        columns.join(", "),
+                        values.join(", ")
+                    ),
+                )
+            })
+            .collect();
+        Ok(sum_rows_affected(self.execute(ctx, work, true)?))
+    }
+
+    /// UPDATE / DELETE: one shard when the key is pinned, else every shard
+    fn write(
+        &self,
+        ctx: &mut SessionContext,
+        sql: &str,
+        table: &str,
+        where_clause: Option<&Expression>,
+    ) -> Result<EngineOutput, EngineError> {
+        let key = self.table_key(table)?;
+        let route = match self.pinned_shard(where_clause, &key, &[table], true) {
+            Some(shard) => Route::One(shard),
+            None => Route::All,
+        };
+        let work = self.route_work(route, sql);
+        Ok(sum_rows_affected(self.execute(ctx, work, true)?))
+    }
+
+    fn select(
+        &self,
+        ctx: &mut SessionContext,
+        sql: &str,
+        select: &SelectStatement,
+    ) -> Result<EngineOutput, EngineError> {
+        let Some(from) = &select.from else {
+            // No table: any shard can evaluate it.
+            return self
+                .execute(ctx, vec![(0, sql.to_string())], false)
+                .map(first);
+        };
+        let TableReference::Table { name, alias, .. } = &from.table else {
+            return Err(unsupported(
+                "subqueries in FROM are not supported by the coordinator",
+            ));
+        };
+        if !from.joins.is_empty() {
+            return Err(unsupported("joins are not supported by the coordinator"));
+        }
+        let key = self.table_key(name)?;
+        let mut names = vec![name.as_str()];
+        names.extend(alias.as_deref());
+        if let Some(shard) = self.pinned_shard(select.where_clause.as_ref(), &key, &names, true) {
+            return self
+                .execute(ctx, vec![(shard, sql.to_string())], false)
+                .map(first);
+        }
+
+        if select.distinct
+            || !select.group_by.is_empty()
+            || select.having.is_some()
+            || select.offset.is_some()
+            || select.select_list.iter().any(|item| {
+                matches!(
+                    item,
+                    SelectItem::Expression {
+                        expr: Expression::Function { .. },
+                        ..
+                    }
+                )
+            })
+        {
+            return Err(unsupported(
+                "a SELECT over all shards cannot use aggregates, GROUP BY, DISTINCT or OFFSET \
+                 (pin the distribution key in WHERE to run it on one shard)",
+            ));
+        }
+        let outputs = self.execute(ctx, self.route_work(Route::All, sql), false)?;
+        let mut columns = Vec::new();
+        let mut rows = Vec::new();
+        for output in outputs {
+            if let EngineOutput::ResultSet {
+                columns: shard_columns,
+                rows: shard_rows,
+            } = output
+            {
+                if columns.is_empty() {
+                    columns = shard_columns;
+                }
+                rows.extend(shard_rows);
+            }
+        }
+        if !select.order_by.is_empty() && !rows.is_empty() {
+            let mut keys = Vec::with_capacity(select.order_by.len());
+            for item in &select.order_by {
+                let column = match &item.expr {
+                    Expression::Identifier(c) => c,
+                    Expression::QualifiedIdentifier { column, .. } => column,
+                    _ => {
+                        return Err(unsupported(
+                            "ORDER BY over all shards must name output columns",
+                        ))
+                    }
+                };
+                let index = columns
+                    .iter()
+                    .position(|c| c.eq_ignore_ascii_case(column))
+                    .ok_or_else(|| {
+                        unsupported(format!(
+                            "ORDER BY column {} must be selected to sort across shards",
+                            column
+                        ))
+                    })?;
+                keys.push((index, item.direction == OrderDirection::Desc));
+            }
+            rows.sort_by(|a, b| {
+                keys.iter()
+                    .map(|&(i, desc)| {
+                        let order = compare_cells(&a[i], &b[i]);
+                        if desc {
+                            order.reverse()
+                        } else {
+                            order
+                        }
+                    })
+                    .find(|o| o.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            });
+        }
+        if let Some(limit) = select.limit {
+            rows.truncate(limit as usize);
+        }
+        Ok(EngineOutput::ResultSet { columns, rows })
+    }
+
+    fn route_work(&self, route: Route, sql: &str) -> Vec<(usize, String)> {
+        match route {
+            Route::One(shard) => vec![(shard, sql.to_string())],
+            Route::All => (0..self.shards.len())
+                .map(|s| (s, sql.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl Drop for Coordinator {
+    fn drop(&mut self) {
+        // The coordinator may be dropped on an async thread, where a blocking shutdown panics.
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl EngineHandle for Coordinator {
+    fn execute_sql(
+        &self,
+        sql: &str,
+        ctx: &mut SessionContext,
+    ) -> Result<EngineOutput, EngineError> {
+        let mut statements = SqlParser::new(sql)
+            .and_then(|mut p| p.parse_multiple())
+            .map_err(|e| EngineError::new(engine_error_code::INTERNAL, e.to_string()))?;
+        if statements.len() != 1 {
+            return Err(unsupported("the coordinator runs one statement per query"));
+        }
+        match statements.remove(0) {
+            SqlStatement::Select(select) => self.select(ctx, sql, &select),
+            SqlStatement::Insert(insert) => self.insert(ctx, &insert),
+            SqlStatement::Update(update) => {
+                let key = self.table_key(&update.table)?;
+                if update
+                    .assignments
+                    .iter()
+                    .any(|a| a.column.eq_ignore_ascii_case(&key))
+                {
+                    return Err(unsupported(format!(
+                        "cannot UPDATE distribution key {} (DELETE and INSERT the row instead)",
+                        key
+                    )));
+                }
+                self.write(ctx, sql, &update.table, update.where_clause.as_ref())
+            }
+            SqlStatement::Delete(delete) => {
+                self.write(ctx, sql, &delete.table, delete.where_clause.as_ref())
+            }
+            SqlStatement::CreateTable(ct) => self.create_table(ctx, sql, &ct),
+            SqlStatement::CreateIndex(ci) => {
+                self.table_key(&ci.table_name)?;
+                self.broadcast_ddl(ctx, sql)?;
+                Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+            }
+            SqlStatement::AlterTable(alt) => {
+                let key = self.table_key(&alt.table_name)?;
+                let new_key = match &alt.operation {
+                    AlterTableOperation::DropColumn(c) if c.eq_ignore_ascii_case(&key) => {
+                        return Err(unsupported(format!("cannot drop distribution key {}", key)))
+                    }
+                    AlterTableOperation::RenameColumn { old_name, new_name }
+                        if old_name.eq_ignore_ascii_case(&key) =>
+                    {
+                        new_name.to_lowercase()
+                    }
+                    _ => key,
+                };
+                self.broadcast_ddl(ctx, sql)?;
+                let mut keys = self.keys.write().map_err(|_| lock_poisoned())?;
+                keys.remove(&alt.table_name.to_lowercase());
+                let table = match &alt.operation {
+                    AlterTableOperation::RenameTable(t) => t.to_lowercase(),
+                    _ => alt.table_name.to_lowercase(),
+                };
+                keys.insert(table, new_key);
+                Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+            }
+            SqlStatement::DropIndex(_) => {
+                self.broadcast_ddl(ctx, sql)?;
+                Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+            }
+            SqlStatement::DropTable(dt) => {
+                self.broadcast_ddl(ctx, sql)?;
+                self.keys
+                    .write()
+                    .map_err(|_| lock_poisoned())?
+                    .remove(&dt.table_name.to_lowercase());
+                Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+            }
+            SqlStatement::BeginTransaction => {
+                if ctx.cluster_transaction.is_some() {
+                    return Err(EngineError::new(
+                        engine_error_code::ALREADY_IN_TRANSACTION,
+                        "already in a transaction",
+                    ));
+                }
+                ctx.cluster_transaction = Some(ClusterTransaction::default());
+                Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+            }
+            SqlStatement::CommitTransaction => {
+                let txn = take_transaction(ctx)?;
+                self.block_on(self.commit(txn))?;
+                Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+            }
+            SqlStatement::RollbackTransaction => {
+                let txn = take_transaction(ctx)?;
+                self.block_on(self.rollback(txn));
+                Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+            }
+            _ => Err(unsupported(
+                "this SQL statement type is not supported by the cluster coordinator",
+            )),
+        }
+    }
+}
+
+fn take_transaction(ctx: &mut SessionContext) -> Result<ClusterTransaction, EngineError> {
+    ctx.cluster_transaction.take().ok_or_else(|| {
+        EngineError::new(
+            engine_error_code::NO_ACTIVE_TRANSACTION,
+            "no active transaction",
+        )
+    })
+}
+
+fn first_primary_key(ct: &CreateTableStatement) -> Option<String> {
+    ct.columns
+        .iter()
+        .find(|c| c.constraints.contains(&ColumnConstraint::PrimaryKey))
+        .map(|c| c.name.clone())
+        .or_else(|| {
+            ct.constraints.iter().find_map(|c| match c {
+                TableConstraint::PrimaryKey(columns) => columns.first().cloned(),
+                _ => None,
+            })
+        })
+}
+
+fn shard_output(shard: usize, message: ServerMessage) -> Result<EngineOutput, EngineError> {
+    match message {
+        ServerMessage::ResultSet(p) => Ok(EngineOutput::ResultSet {
+            columns: p.columns,
+            rows: p.rows,
+        }),
+        ServerMessage::ExecutionOk(p) => Ok(EngineOutput::ExecutionOk {
+            rows_affected: p.rows_affected,
+        }),
+        ServerMessage::Error(e) => Err(EngineError::new(
+            e.code,
+            format!("shard {}: {}", shard, e.message),
+        )),
+        other => Err(EngineError::new(
+            engine_error_code::PROTOCOL,
+            format!("shard {}: unexpected response {:?}", shard, other),
+        )),
+    }
+}
+
+fn first(mut outputs: Vec<EngineOutput>) -> EngineOutput {
+    outputs
+        .pop()
+        .unwrap_or(EngineOutput::ExecutionOk { rows_affected: 0 })
+}
+
+fn sum_rows_affected(outputs: Vec<EngineOutput>) -> EngineOutput {
+    EngineOutput::ExecutionOk {
+        rows_affected: outputs
+            .iter()
+            .map(|o| match o {
+                EngineOutput::ExecutionOk { rows_affected } => *rows_affected,
+                _ => 0,
+            })
+            .sum(),
+    }
+}
+
+/// Orders two result cells (`Debug` text such as `Integer(5)`, `Varchar("a")`, `Null`): NULL
+/// first, numbers numerically, other values by their text
+fn compare_cells(a: &str, b: &str) -> Ordering {
+    fn payload(cell: &str) -> Option<&str> {
+        if cell == "Null" || cell == "NULL" {
+            return None;
+        }
+        let inner = cell
+            .split_once('(')
+            .and_then(|(_, rest)| rest.strip_suffix(')'))
+            .unwrap_or(cell);
+        Some(
+            inner
+                .strip_prefix('"')
+                .and_then(|s| s.strip_suffix('"'))
+                .unwrap_or(inner),
+        )
+    }
+    match (payload(a), payload(b)) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Less,
+        (Some(_), None) => Ordering::Greater,
+        (Some(x), Some(y)) => match (x.parse::<f64>(), y.parse::<f64>()) {
+            (Ok(x), Ok(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+            _ => x.cmp(y),
+        },
+    }
+}
+
+fn unsupported(message: impl Into<String>) -> EngineError {
+    EngineError::new(engine_error_code::UNSUPPORTED_SQL, message)
+}
+
+fn shard_error(shard: usize, e: &dyn std::fmt::Display) -> EngineError {
+    EngineError::new(
+        engine_error_code::INTERNAL,
+        format!("shard {}: {}", shard, e),
+    )
+}
+
+fn lock_poisoned() -> EngineError {
+    EngineError::new(engine_error_code::INTERNAL, "coordinator lock poisoned")
+}
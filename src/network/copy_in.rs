@@ -0,0 +1,183 @@
+//! Bulk loads over the wire: `CopyIn`, `CopyData`… , `CopyDone` on one stream.
+//!
+//! Each `CopyData` batch becomes multi-row `INSERT … VALUES` statements of up to
+//! [`COPY_INSERT_ROWS`] rows, so rows take the engine's batch insert path (one table lock and one
+//! flush per statement) instead of a statement, a parse and a round trip each.
+//!
+//! Only `CopyDone` is answered: `ExecutionOk` with the rows loaded, or the first error. A failure
+//! before it is kept, the rest of the data is discarded, and `CopyDone` reports it. Outside a
+//! transaction the load runs in one of its own, committed by `CopyDone` and rolled back on
+//! failure; inside one, the rows join it and a failure leaves earlier batches to the
+//! transaction's `COMMIT` / `ROLLBACK`.
+
+use crate::common::row_mapping::sql_literal;
+use crate::network::engine::{
+    engine_error_code, EngineError, EngineHandle, EngineOutput, SessionContext,
+};
+use crate::network::framing::{CopyDataPayload, CopyInPayload};
+use crate::parser::quoting::quote_identifier;
+
+/// Rows per `INSERT` statement a `CopyData` batch is split into.
+pub const COPY_INSERT_ROWS: usize = 500;
+
+/// COPY in progress on a session (between `CopyIn` and `CopyDone`).
+#[derive(Debug)]
+pub(crate) struct CopyIn {
+    /// `INSERT INTO <table> (<columns>) VALUES`, quoted.
+    insert_prefix: String,
+    /// Values per row, when the `CopyIn` frame named the columns.
+    column_count: Option<usize>,
+    rows: u64,
+    /// Set when `CopyIn` opened the transaction the load runs in.
+    implicit_transaction: bool,
+    error: Option<EngineError>,
+}
+
+/// `CopyIn`: starts a load on the session. Never fails: an error is reported by `CopyDone`.
+pub(crate) fn start(engine: &dyn EngineHandle, ctx: &mut SessionContext, copy: CopyInPayload) {
+    let mut state = CopyIn {
+        insert_prefix: String::new(),
+        column_count: (!copy.columns.is_empty()).then_some(copy.columns.len()),
+        rows: 0,
+        implicit_transaction: false,
+        error: None,
+    };
+    match insert_prefix(&copy) {
+        Ok(prefix) => state.insert_prefix = prefix,
+        Err(e) => state.error = Some(e),
+    }
+    if state.error.is_none() && ctx.transaction.is_none() && ctx.cluster_transaction.is_none() {
+        match engine.execute_sql("BEGIN TRANSACTION", ctx) {
+            Ok(_) => state.implicit_transaction = true,
+            Err(e) => state.error = Some(e),
+        }
+    }
+    ctx.copy_in = Some(state);
+}
+
+/// `CopyData`: inserts a batch. `Err` only when no load is in progress (then the frame is
+/// answered); failures of the load itself are kept for `CopyDone`.
+pub(crate) fn data(
+    engine: &dyn EngineHandle,
+    ctx: &mut SessionContext,
+    batch: CopyDataPayload,
+) -> Result<(), EngineError> {
+    let Some(mut state) = ctx.copy_in.take() else {
+        return Err(EngineError::new(
+            engine_error_code::PROTOCOL,
+            "CopyData without a CopyIn on this stream",
+        ));
+    };
+    if state.error.is_none() {
+        if let Err(e) = insert_batch(engine, ctx, &mut state, &batch) {
+            fail(engine, ctx, &mut state, e);
+        }
+    }
+    ctx.copy_in = Some(state);
+    Ok(())
+}
+
+/// `CopyDone`: ends the load, committing its own transaction; the answer to the whole COPY.
+pub(crate) fn finish(
+    engine: &dyn EngineHandle,
+    ctx: &mut SessionContext,
+) -> Result<EngineOutput, EngineError> {
+    let Some(state) = ctx.copy_in.take() else {
+        return Err(EngineError::new(
+            engine_error_code::PROTOCOL,
+            "CopyDone without a CopyIn on this stream",
+        ));
+    };
+    if let Some(e) = state.error {
+        return Err(e);
+    }
+    if state.implicit_transaction {
+        engine.execute_sql("COMMIT", ctx)?;
+    }
+    Ok(EngineOutput::ExecutionOk {
+        rows_affected: state.rows,
+    })
+}
+
+/// A frame other than `CopyData` / `CopyDone` arrived during a load: the load fails with the
+/// error returned for that frame.
+pub(crate) fn interrupt(engine: &dyn EngineHandle, ctx: &mut SessionContext) -> EngineError {
+    let err = EngineError::new(
+        engine_error_code::PROTOCOL,
+        "COPY in progress: only CopyData and CopyDone frames are accepted until CopyDone",
+    );
+    if let Some(mut state) = ctx.copy_in.take() {
+        if state.error.is_none() {
+            fail(engine, ctx, &mut state, err.clone());
+        }
+        ctx.copy_in = Some(state);
+    }
+    err
+}
+
+/// Records the load's first error and rolls back its own transaction, releasing its locks.
+fn fail(engine: &dyn EngineHandle, ctx: &mut SessionContext, state: &mut CopyIn, e: EngineError) {
+    if std::mem::take(&mut state.implicit_transaction) {
+        let _ = engine.execute_sql("ROLLBACK", ctx);
+    }
+    state.error = Some(EngineError::new(
+        e.code,
+        format!("COPY failed after {} rows: {}", state.rows, e.message),
+    ));
+}
+
+fn insert_prefix(copy: &CopyInPayload) -> Result<String, EngineError> {
+    let quote = |name: &str| {
+        quote_identifier(name)
+            .map_err(|e| EngineError::new(engine_error_code::PROTOCOL, e.to_string()))
+    };
+    let mut prefix = format!("INSERT INTO {}", quote(&copy.table)?);
+    if !copy.columns.is_empty() {
+        let columns = copy
+            .columns
+            .iter()
+            .map(|c| quote(c))
+            .collect::<Result<Vec<_>, _>>()?;
+        prefix.push_str(&format!(" ({})", columns.join(", ")));
+    }
+    prefix.push_str(" VALUES ");
+    Ok(prefix)
+}
+
+fn insert_batch(
+    engine: &dyn EngineHandle,
+    ctx: &mut SessionContext,
+    state: &mut CopyIn,
+    batch: &CopyDataPayload,
+) -> Result<(), EngineError> {
+    if let Some(expected) = state.column_count {
+        if batch.column_count as usize != expected {
+            return Err(EngineError::new(
+                engine_error_code::PROTOCOL,
+                format!(
+                    "CopyData rows have {} values, CopyIn named {expected} columns",
+                    batch.column_count
+                ),
+            ));
+        }
+    }
+    let rows = batch
+        .rows()
+        .map_err(|e| EngineError::new(engine_error_code::PROTOCOL, e.to_string()))?;
+    for chunk in rows.chunks(COPY_INSERT_ROWS) {
+        let mut sql = state.insert_prefix.clone();
+        for (i, row) in chunk.iter().enumerate() {
+            if i > 0 {
+                sql.push_str(", ");
+            }
+            let values: Vec<String> = row.iter().map(sql_literal).collect();
+            sql.push('(');
+            sql.push_str(&values.join(", "));
+            sql.push(')');
+        }
+        if let EngineOutput::ExecutionOk { rows_affected } = engine.execute_sql(&sql, ctx)? {
+            state.rows += rows_affected;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,57 @@
+//! Loading `mysqldump` / `pg_dump` scripts through the dump compatibility layer
+//!
+//! [`translate_dump`] turns the script into statements the strict parser accepts; this
+//! module runs them on an [`EngineHandle`] one by one, stopping at the first failure.
+
+use crate::network::engine::{
+    engine_error_code, EngineError, EngineHandle, EngineOutput, SessionContext,
+};
+use crate::parser::dump::{translate_dump, DumpDialect, DumpWarning};
+
+/// Outcome of [`import_dump`]
+#[derive(Debug, Clone, Default)]
+pub struct DumpImportReport {
+    /// Dialect the script was read as
+    pub dialect: DumpDialect,
+    /// Statements executed after translation (a `COPY` block may become several `INSERT`s)
+    pub statements_executed: usize,
+    /// Sum of `rows_affected` over all statements
+    pub rows_affected: u64,
+    /// Clauses and statements that were ignored or mapped
+    pub warnings: Vec<DumpWarning>,
+}
+
+/// Translates `script` and executes it on `engine` in the session `ctx`.
+///
+/// Errors carry the dump line of the failing statement; statements before it stay applied
+/// (each runs in autocommit unless the dump itself opens a transaction).
+pub fn import_dump(
+    engine: &dyn EngineHandle,
+    ctx: &mut SessionContext,
+    script: &str,
+    dialect: DumpDialect,
+) -> Result<DumpImportReport, EngineError> {
+    let translated = translate_dump(script, dialect)
+        .map_err(|e| EngineError::new(engine_error_code::UNSUPPORTED_SQL, e.to_string()))?;
+    let mut report = DumpImportReport {
+        dialect: translated.dialect,
+        warnings: translated.warnings,
+        ..Default::default()
+    };
+    for statement in &translated.statements {
+        match engine.execute_sql(&statement.sql, ctx) {
+            Ok(EngineOutput::ExecutionOk { rows_affected }) => {
+                report.rows_affected += rows_affected;
+            }
+            Ok(EngineOutput::ResultSet { .. }) => {}
+            Err(e) => {
+                return Err(EngineError::new(
+                    e.code,
+                    format!("line {}: {}", statement.line, e.message),
+                ));
+            }
+        }
+        report.statements_executed += 1;
+    }
+    Ok(report)
+}
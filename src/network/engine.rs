@@ -28,6 +28,43 @@ pub mod engine_error_code {
     pub const ALREADY_IN_TRANSACTION: u32 = 2007;
     /// DDL is not allowed inside an explicit transaction (minimal Phase 6 rule).
     pub const DDL_IN_TRANSACTION: u32 = 2008;
+    /// `PREPARE TRANSACTION` with an id already in use, or `COMMIT PREPARED` /
+    /// `ROLLBACK PREPARED` for an id that is not prepared.
+    pub const PREPARED_TRANSACTION: u32 = 2009;
+    /// `COMMIT` is durable locally, but the synchronous replica quorum did not acknowledge it in
+    /// time (see [`crate::network::replication::SyncTimeoutPolicy::Error`]).
+    pub const SYNC_REPLICATION_TIMEOUT: u32 = 2010;
+    /// `EXPORT SNAPSHOT` / `SET TRANSACTION SNAPSHOT` outside a fresh transaction, an unknown
+    /// snapshot id, a write in a transaction that reads from an imported snapshot, or an
+    /// `AS OF TIMESTAMP` outside the retained history.
+    pub const INVALID_SNAPSHOT: u32 = 2011;
+    /// `SET` / `SHOW` of an unknown configuration parameter, an invalid value, or `SET` of a
+    /// parameter that only changes on restart.
+    pub const INVALID_PARAMETER: u32 = 2012;
+    /// A replica did not apply the commit named by the session's `read_after_lsn` token within
+    /// `read_after_lsn_timeout`.
+    pub const REPLICA_LAG_TIMEOUT: u32 = 2013;
+    /// The transaction could not be serialized with a concurrent one (`SERIALIZABLE` /
+    /// `REPEATABLE READ` waited too long for the serialization lock). Nothing was changed;
+    /// running the whole transaction again may succeed (see [`EngineError::is_retryable`]).
+    pub const SERIALIZATION_FAILURE: u32 = 2014;
+    /// `SET role`: the role already holds as many sessions as `network.max_connections_per_role`
+    /// allows.
+    pub const TOO_MANY_CONNECTIONS: u32 = 2015;
+    /// The session stayed idle inside an open transaction for longer than
+    /// `network.idle_in_transaction_timeout_ms`; the transaction was rolled back and the session
+    /// closed.
+    pub const IDLE_IN_TRANSACTION_TIMEOUT: u32 = 2016;
+    /// The connection's `Authenticate` frame was refused (see [`crate::network::auth`]), or a
+    /// session of an authenticated connection tried to `SET role` to another user.
+    pub const AUTHENTICATION_FAILED: u32 = 2017;
+    /// [`crate::embedded::Connection::query_as`] could not map a result row into the requested
+    /// type (missing column, wrong type, no result set). Raised by the embedded API, not the server.
+    pub const RESULT_MAPPING: u32 = 2018;
+    /// The session's role went past one of its quotas: `quotas.queries_per_second` (the
+    /// statement did not run; retrying later may succeed), `quotas.max_rows_read` or
+    /// `quotas.max_temp_bytes` (the statement was stopped).
+    pub const QUOTA_EXCEEDED: u32 = 2019;
 }
 
 use crate::common::types::RecordId;
@@ -97,6 +134,13 @@ pub struct SqlTransaction {
     pub(crate) touched_tables: HashSet<String>,
     /// Heap inserts whose secondary indexes are applied at `COMMIT` (native TPC-C fast path).
     pub(crate) pending_index_inserts: Vec<PendingIndexInsert>,
+    /// Snapshot exported or imported by this transaction; reads are served from it.
+    pub(crate) snapshot: Option<crate::network::sql_engine::TransactionSnapshot>,
+    /// Tuple ids reserved for this transaction's inserts (see `sql_engine::sequences`).
+    pub(crate) tuple_ids: std::ops::Range<u64>,
+    /// PRIMARY KEY / UNIQUE keys this transaction wrote, locked until it ends (see
+    /// `sql_engine::key_locks`).
+    pub(crate) key_locks: crate::network::sql_engine::HeldKeyLocks,
 }
 
 impl std::fmt::Debug for SqlTransaction {
@@ -110,6 +154,7 @@ impl std::fmt::Debug for SqlTransaction {
             .field("touched_tables", &self.touched_tables.len())
             .field("pending_index_inserts", &self.pending_index_inserts.len())
             .field("strong_iso_held", &self.strong_iso.is_some())
+            .field("snapshot", &self.snapshot)
             .finish()
     }
 }
@@ -129,6 +174,9 @@ impl SqlTransaction {
             wal_last_lsn: None,
             touched_tables: HashSet::new(),
             pending_index_inserts: Vec::new(),
+            snapshot: None,
+            tuple_ids: 0..0,
+            key_locks: Default::default(),
         }
     }
 }
@@ -148,11 +196,47 @@ pub struct SessionContext {
     /// Reused row serialization buffer for native TPC-C inserts in a transaction.
     pub(crate) tpcc_row_bytes_buf: Vec<u8>,
     /// Per-transaction page managers (avoids `table_page_managers` map lock on hot DML/COMMIT).
-    pub(crate) txn_pm_cache: HashMap<String, Arc<crate::storage::page_manager::PageManagerMutex>>,
+    pub(crate) txn_pm_cache: HashMap<String, Arc<crate::storage::page_manager::PageManagerLock>>,
     /// Reused buffer for deferred index column maps (native TPC-C inserts).
     pub(crate) tpcc_index_column_map_buf: HashMap<String, String>,
     /// Last `COMMIT` flush breakdown (native TPC-C gap accounting).
     pub(crate) last_commit_flush_phases: Option<crate::network::sql_engine_wal::CommitFlushPhaseUs>,
+    /// Open `BEGIN` … `COMMIT` when the engine is a cluster [`crate::network::cluster::Coordinator`].
+    pub(crate) cluster_transaction: Option<crate::network::cluster::ClusterTransaction>,
+    /// Set while replaying a bidirectional peer's transaction: its commit time there (Unix
+    /// seconds). Commits and DDL of the session are marked with it in the WAL so they are not
+    /// replicated back (see [`crate::network::replication::ConflictResolver`]).
+    pub(crate) peer_commit_time: Option<u64>,
+    /// Set by `SET trace = on`: file the session's query traces are appended to.
+    pub(crate) trace_file: Option<std::path::PathBuf>,
+    /// Session number in workload captures (`SET workload_capture = on`); assigned on the
+    /// session's first captured statement.
+    pub(crate) capture_session: Option<u64>,
+    /// Primary WAL position of the session's last commit (`SHOW commit_lsn`); `0` before one.
+    pub(crate) last_commit_lsn: u64,
+    /// `SET read_after_lsn`: on a replica, statements wait until this commit is applied.
+    pub(crate) read_after_lsn: u64,
+    /// `SET read_after_lsn_timeout` (milliseconds); the default when `None`.
+    pub(crate) read_after_lsn_timeout: Option<std::time::Duration>,
+    /// `SET role`: the role the session holds a connection slot of.
+    pub(crate) role: Option<crate::network::sql_engine::RoleSlot>,
+    /// `SET tenant`: the tenant whose tables the session sees and creates.
+    pub(crate) tenant: Option<String>,
+    /// Set while a read of audited tables runs; logged when it finishes (see `audit.select_tables`).
+    pub(crate) audited_read: Option<crate::network::sql_engine::AuditedRead>,
+    /// Column masks of the session this one reads for (reads forwarded to a snapshot); without
+    /// them, the session's own role decides (see `masking.columns`).
+    pub(crate) column_masks: Option<crate::network::sql_engine::ColumnMasks>,
+    /// Bulk load between a `CopyIn` and its `CopyDone` frame (see [`crate::network::copy_in`]).
+    pub(crate) copy_in: Option<crate::network::copy_in::CopyIn>,
+}
+
+impl SessionContext {
+    /// File receiving this session's query traces (one JSON object per line) while
+    /// `SET trace = on` is in effect.
+    pub fn trace_file(&self) -> Option<&std::path::Path> {
+        self.trace_file.as_deref()
+    }
 }
 
 impl std::fmt::Debug for SessionContext {
@@ -169,6 +253,18 @@ impl std::fmt::Debug for SessionContext {
                 "tpcc_index_column_map_buf_len",
                 &self.tpcc_index_column_map_buf.len(),
             )
+            .field("cluster_transaction", &self.cluster_transaction)
+            .field("peer_commit_time", &self.peer_commit_time)
+            .field("trace_file", &self.trace_file)
+            .field("capture_session", &self.capture_session)
+            .field("last_commit_lsn", &self.last_commit_lsn)
+            .field("read_after_lsn", &self.read_after_lsn)
+            .field("read_after_lsn_timeout", &self.read_after_lsn_timeout)
+            .field("role", &self.role)
+            .field("tenant", &self.tenant)
+            .field("audited_read", &self.audited_read)
+            .field("column_masks", &self.column_masks)
+            .field("copy_in", &self.copy_in)
             .finish()
     }
 }
@@ -185,6 +281,18 @@ impl Default for SessionContext {
             txn_pm_cache: HashMap::new(),
             tpcc_index_column_map_buf: HashMap::new(),
             last_commit_flush_phases: None,
+            cluster_transaction: None,
+            peer_commit_time: None,
+            trace_file: None,
+            capture_session: None,
+            last_commit_lsn: 0,
+            read_after_lsn: 0,
+            read_after_lsn_timeout: None,
+            role: None,
+            tenant: None,
+            audited_read: None,
+            column_masks: None,
+            copy_in: None,
         }
     }
 }
@@ -216,6 +324,12 @@ impl EngineError {
             message: message.into(),
         }
     }
+
+    /// Whether the failed transaction may succeed when run again from `BEGIN`, as
+    /// [`crate::embedded::Connection::transaction_with_retry`] does.
+    pub fn is_retryable(&self) -> bool {
+        self.code == engine_error_code::SERIALIZATION_FAILURE
+    }
 }
 
 impl From<EngineError> for ErrorPayload {
@@ -270,6 +384,80 @@ pub trait EngineHandle: Send + Sync {
         ))
     }
 
+    /// Stages a consistent copy of the data directory for a new replica (see
+    /// [`crate::network::replication`]). Default: not supported.
+    fn base_backup(&self) -> Result<crate::network::replication::BaseBackup, EngineError> {
+        Err(EngineError::new(
+            engine_error_code::PROTOCOL,
+            "BaseBackup not supported",
+        ))
+    }
+
+    /// Transactions committed after `after_lsn`, decoded from the WAL for a replica (see
+    /// [`crate::network::replication`]). Default: not supported.
+    fn committed_changes(
+        &self,
+        after_lsn: u64,
+    ) -> Result<Vec<crate::network::framing::ReplicatedTransactionPayload>, EngineError> {
+        let _ = after_lsn;
+        Err(EngineError::new(
+            engine_error_code::PROTOCOL,
+            "StartReplication not supported",
+        ))
+    }
+
+    /// Replica registry of the engine, for acknowledgements and synchronous commit (see
+    /// [`crate::network::replication::ReplicationTracker`]). Default: none.
+    fn replication_tracker(&self) -> Option<Arc<crate::network::replication::ReplicationTracker>> {
+        None
+    }
+
+    /// Binds a new session of a connection authenticated as `user` to the role of that name
+    /// (see [`crate::network::auth`]). Default: accepted without role accounting.
+    fn assign_role(&self, ctx: &mut SessionContext, user: &str) -> Result<(), EngineError> {
+        let _ = (ctx, user);
+        Ok(())
+    }
+
+    /// Parses `sql` once for repeated runs with different parameter values (see
+    /// [`crate::network::sql_engine::PreparedSql`]). Default: not supported.
+    fn prepare(&self, sql: &str) -> Result<crate::network::sql_engine::PreparedSql, EngineError> {
+        let _ = sql;
+        Err(EngineError::new(
+            engine_error_code::PROTOCOL,
+            "prepared statements not supported",
+        ))
+    }
+
+    /// Runs a statement from [`Self::prepare`] with `params` as its parameter values. Default:
+    /// not supported.
+    fn execute_prepared(
+        &self,
+        prepared: &crate::network::sql_engine::PreparedSql,
+        params: &[crate::common::types::ColumnValue],
+        ctx: &mut SessionContext,
+    ) -> Result<EngineOutput, EngineError> {
+        let _ = (prepared, params, ctx);
+        Err(EngineError::new(
+            engine_error_code::PROTOCOL,
+            "prepared statements not supported",
+        ))
+    }
+
+    /// Result columns of a statement from [`Self::prepare`] without running it, `None` when it
+    /// returns no rows. Default: not supported.
+    fn describe_prepared(
+        &self,
+        prepared: &crate::network::sql_engine::PreparedSql,
+        ctx: &SessionContext,
+    ) -> Result<Option<Vec<crate::network::sql_engine::ResultColumn>>, EngineError> {
+        let _ = (prepared, ctx);
+        Err(EngineError::new(
+            engine_error_code::PROTOCOL,
+            "prepared statements not supported",
+        ))
+    }
+
     /// Whether the network layer may memoize and serve **pre-encoded** wire frames for deterministic
     /// `SELECT` queries without `FROM` (literal projections).
     ///
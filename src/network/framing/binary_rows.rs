@@ -0,0 +1,212 @@
+//! Binary row encoding of [`BinaryResultSetPayload`] and [`CopyDataPayload`]: rows one after
+//! another, each cell a tag byte followed by its value, little-endian throughout.
+//!
+//! | Tag | Value | Bytes |
+//! |-----|-------|-------|
+//! | `0` | NULL | none |
+//! | `1` | boolean | 1 (`0` / `1`) |
+//! | `2` / `3` / `4` / `5` | `TINYINT` / `SMALLINT` / `INTEGER` / `BIGINT` | 1 / 2 / 4 / 8 |
+//! | `6` / `7` | `FLOAT` / `DOUBLE` (IEEE 754) | 4 / 8 |
+//! | `8` / `9` / `10` | `CHAR` / `VARCHAR` / `TEXT` | `u32` length + UTF-8 |
+//! | `11` / `12` / `13` | `DATE` / `TIME` / `TIMESTAMP` | `u32` length + UTF-8 |
+//! | `14` | `BLOB` | `u32` length + bytes |
+//!
+//! Strings are the values themselves, without the SQL quotes text cells keep.
+
+use super::messages::{BinaryResultSetPayload, CopyDataPayload};
+use super::ProtocolError;
+use crate::common::row_mapping::parse_cell;
+use crate::common::types::DataType;
+
+impl BinaryResultSetPayload {
+    /// Encodes text result rows (cells as the engine renders them, see
+    /// [`crate::common::row_mapping::parse_cell`]).
+    pub fn from_text_rows(columns: Vec<String>, rows: &[Vec<String>]) -> Self {
+        let mut data = Vec::with_capacity(rows.len() * columns.len() * 9);
+        for row in rows {
+            for cell in row {
+                encode_value(&parse_cell(cell), &mut data);
+            }
+        }
+        Self {
+            columns,
+            row_count: rows.len() as u64,
+            data,
+        }
+    }
+
+    /// Decoded rows, one value per column.
+    pub fn rows(&self) -> Result<Vec<Vec<DataType>>, ProtocolError> {
+        decode_rows(&self.data, self.row_count, self.columns.len())
+    }
+}
+
+impl CopyDataPayload {
+    /// Empty batch of rows with `column_count` values each.
+    pub fn new(column_count: usize) -> Self {
+        Self {
+            column_count: column_count as u32,
+            row_count: 0,
+            data: Vec::new(),
+        }
+    }
+
+    /// Appends `row`, which must have `column_count` values like every row of the batch.
+    pub fn push_row(&mut self, row: &[DataType]) {
+        debug_assert_eq!(row.len(), self.column_count as usize);
+        for value in row {
+            encode_value(value, &mut self.data);
+        }
+        self.row_count += 1;
+    }
+
+    /// `rows` (all of `column_count` values) split into batches of about `batch_bytes` encoded
+    /// bytes, at least one row each.
+    pub fn batches(column_count: usize, rows: &[Vec<DataType>], batch_bytes: usize) -> Vec<Self> {
+        let mut batches = Vec::new();
+        let mut batch = Self::new(column_count);
+        for row in rows {
+            batch.push_row(row);
+            if batch.data.len() >= batch_bytes {
+                batches.push(std::mem::replace(&mut batch, Self::new(column_count)));
+            }
+        }
+        if batch.row_count > 0 {
+            batches.push(batch);
+        }
+        batches
+    }
+
+    /// Decoded rows, `column_count` values each.
+    pub fn rows(&self) -> Result<Vec<Vec<DataType>>, ProtocolError> {
+        decode_rows(&self.data, self.row_count, self.column_count as usize)
+    }
+}
+
+fn decode_rows(
+    data: &[u8],
+    row_count: u64,
+    column_count: usize,
+) -> Result<Vec<Vec<DataType>>, ProtocolError> {
+    let mut reader = Reader { data };
+    let mut rows = Vec::with_capacity(row_count.min(1 << 16) as usize);
+    for _ in 0..row_count {
+        let row = (0..column_count)
+            .map(|_| reader.value())
+            .collect::<Result<Vec<_>, _>>()?;
+        rows.push(row);
+    }
+    if !reader.data.is_empty() {
+        return Err(malformed(format!(
+            "{} bytes after the last row",
+            reader.data.len()
+        )));
+    }
+    Ok(rows)
+}
+
+/// Appends `value` in the binary row encoding.
+pub fn encode_value(value: &DataType, out: &mut Vec<u8>) {
+    let text = |tag: u8, s: &str, out: &mut Vec<u8>| {
+        out.push(tag);
+        out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+        out.extend_from_slice(s.as_bytes());
+    };
+    match value {
+        DataType::Null => out.push(0),
+        DataType::Boolean(b) => out.extend_from_slice(&[1, u8::from(*b)]),
+        DataType::TinyInt(n) => out.extend_from_slice(&[2, n.to_le_bytes()[0]]),
+        DataType::SmallInt(n) => {
+            out.push(3);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        DataType::Integer(n) => {
+            out.push(4);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        DataType::BigInt(n) => {
+            out.push(5);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        DataType::Float(f) => {
+            out.push(6);
+            out.extend_from_slice(&f.to_le_bytes());
+        }
+        DataType::Double(f) => {
+            out.push(7);
+            out.extend_from_slice(&f.to_le_bytes());
+        }
+        DataType::Char(s) => text(8, s, out),
+        DataType::Varchar(s) => text(9, s, out),
+        DataType::Text(s) => text(10, s, out),
+        DataType::Date(s) => text(11, s, out),
+        DataType::Time(s) => text(12, s, out),
+        DataType::Timestamp(s) => text(13, s, out),
+        DataType::Blob(b) => {
+            out.push(14);
+            out.extend_from_slice(&(b.len() as u32).to_le_bytes());
+            out.extend_from_slice(b);
+        }
+    }
+}
+
+fn malformed(why: String) -> ProtocolError {
+    ProtocolError::PostcardDecode(format!("binary rows: {why}"))
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], ProtocolError> {
+        let bytes = self
+            .data
+            .split_first_chunk::<N>()
+            .map(|(head, rest)| {
+                self.data = rest;
+                *head
+            })
+            .ok_or_else(|| malformed(format!("truncated value: need {N} bytes")))?;
+        Ok(bytes)
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>, ProtocolError> {
+        let len = u32::from_le_bytes(self.take()?) as usize;
+        if self.data.len() < len {
+            return Err(malformed(format!(
+                "truncated value: need {len} bytes, have {}",
+                self.data.len()
+            )));
+        }
+        let (head, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(head.to_vec())
+    }
+
+    fn string(&mut self) -> Result<String, ProtocolError> {
+        String::from_utf8(self.bytes()?).map_err(|_| malformed("string is not UTF-8".into()))
+    }
+
+    fn value(&mut self) -> Result<DataType, ProtocolError> {
+        let [tag] = self.take()?;
+        Ok(match tag {
+            0 => DataType::Null,
+            1 => DataType::Boolean(self.take::<1>()?[0] != 0),
+            2 => DataType::TinyInt(i8::from_le_bytes(self.take()?)),
+            3 => DataType::SmallInt(i16::from_le_bytes(self.take()?)),
+            4 => DataType::Integer(i32::from_le_bytes(self.take()?)),
+            5 => DataType::BigInt(i64::from_le_bytes(self.take()?)),
+            6 => DataType::Float(f32::from_le_bytes(self.take()?)),
+            7 => DataType::Double(f64::from_le_bytes(self.take()?)),
+            8 => DataType::Char(self.string()?),
+            9 => DataType::Varchar(self.string()?),
+            10 => DataType::Text(self.string()?),
+            11 => DataType::Date(self.string()?),
+            12 => DataType::Time(self.string()?),
+            13 => DataType::Timestamp(self.string()?),
+            14 => DataType::Blob(self.bytes()?),
+            other => return Err(malformed(format!("unknown value tag {other}"))),
+        })
+    }
+}
@@ -2,12 +2,15 @@
 
 use std::io::Write;
 
+use super::compression::inflate_frame;
 use super::error::FrameDirection;
 use super::header::{FrameHeader, FRAME_HEADER_LEN, MAX_FRAME_PAYLOAD_BYTES, PROTOCOL_VERSION_V1};
 use super::messages::{
-    ClientHelloPayload, ClientMessage, ErrorPayload, ExecuteScriptPayload, ExecuteTpccPayload,
-    ExecutionOkPayload, MessageKind, QueryPayload, ResultSetPayload, ServerMessage,
-    ServerReadyPayload,
+    AuthenticatePayload, BackupChunkPayload, BackupEndPayload, BaseBackupPayload,
+    BinaryResultSetPayload, ClientHelloPayload, ClientMessage, CopyDataPayload, CopyInPayload,
+    ErrorPayload, ExecuteScriptPayload, ExecuteTpccPayload, ExecutionOkPayload, MessageKind,
+    QueryPayload, QueryWithFormatPayload, ReplicatedTransactionPayload, ReplicationAckPayload,
+    ResultSetPayload, ServerMessage, ServerReadyPayload, StartReplicationPayload,
 };
 use super::{EncodeError, ProtocolError};
 
@@ -90,6 +93,35 @@ pub fn encode_client_message_write<W: Write>(
             MessageKind::ExecuteTpcc,
             postcard::to_allocvec(p).map_err(EncodeError::Postcard)?,
         ),
+        ClientMessage::BaseBackup(p) => (
+            MessageKind::BaseBackup,
+            postcard::to_allocvec(p).map_err(EncodeError::Postcard)?,
+        ),
+        ClientMessage::StartReplication(p) => (
+            MessageKind::StartReplication,
+            postcard::to_allocvec(p).map_err(EncodeError::Postcard)?,
+        ),
+        ClientMessage::ReplicationAck(p) => (
+            MessageKind::ReplicationAck,
+            postcard::to_allocvec(p).map_err(EncodeError::Postcard)?,
+        ),
+        ClientMessage::Authenticate(p) => (
+            MessageKind::Authenticate,
+            postcard::to_allocvec(p).map_err(EncodeError::Postcard)?,
+        ),
+        ClientMessage::QueryWithFormat(p) => (
+            MessageKind::QueryWithFormat,
+            postcard::to_allocvec(p).map_err(EncodeError::Postcard)?,
+        ),
+        ClientMessage::CopyIn(p) => (
+            MessageKind::CopyIn,
+            postcard::to_allocvec(p).map_err(EncodeError::Postcard)?,
+        ),
+        ClientMessage::CopyData(p) => (
+            MessageKind::CopyData,
+            postcard::to_allocvec(p).map_err(EncodeError::Postcard)?,
+        ),
+        ClientMessage::CopyDone => (MessageKind::CopyDone, Vec::new()),
     };
     check_payload_len(payload_bytes.len())?;
     let header = FrameHeader {
@@ -137,6 +169,22 @@ pub fn encode_server_message_write<W: Write>(
             MessageKind::ServerReady,
             postcard::to_allocvec(p).map_err(EncodeError::Postcard)?,
         ),
+        ServerMessage::BackupChunk(p) => (
+            MessageKind::BackupChunk,
+            postcard::to_allocvec(p).map_err(EncodeError::Postcard)?,
+        ),
+        ServerMessage::BackupEnd(p) => (
+            MessageKind::BackupEnd,
+            postcard::to_allocvec(p).map_err(EncodeError::Postcard)?,
+        ),
+        ServerMessage::ReplicatedTransaction(p) => (
+            MessageKind::ReplicatedTransaction,
+            postcard::to_allocvec(p).map_err(EncodeError::Postcard)?,
+        ),
+        ServerMessage::BinaryResultSet(p) => (
+            MessageKind::BinaryResultSet,
+            postcard::to_allocvec(p).map_err(EncodeError::Postcard)?,
+        ),
     };
     check_payload_len(payload_bytes.len())?;
     let header = FrameHeader {
@@ -165,11 +213,23 @@ pub fn decode_client_frame(
     }
     let kind = MessageKind::try_from(header.message_kind)
         .map_err(|_| ProtocolError::UnknownMessageKind(header.message_kind))?;
+    if kind == MessageKind::Compressed {
+        let inner = inflate_frame(&header, payload_slice(frame, &header)?)?;
+        return decode_client_frame(expected_protocol_version, &inner);
+    }
     match kind {
         MessageKind::Query
         | MessageKind::ClientHello
         | MessageKind::ExecuteScript
-        | MessageKind::ExecuteTpcc => {}
+        | MessageKind::ExecuteTpcc
+        | MessageKind::BaseBackup
+        | MessageKind::StartReplication
+        | MessageKind::ReplicationAck
+        | MessageKind::Authenticate
+        | MessageKind::QueryWithFormat
+        | MessageKind::CopyIn
+        | MessageKind::CopyData
+        | MessageKind::CopyDone => {}
         _ => {
             return Err(ProtocolError::WrongDirection {
                 kind,
@@ -199,6 +259,46 @@ pub fn decode_client_frame(
                 .map_err(|e| ProtocolError::PostcardDecode(format!("{e:?}")))?;
             Ok(ClientMessage::ExecuteTpcc(p))
         }
+        MessageKind::BaseBackup => {
+            let p: BaseBackupPayload = postcard::from_bytes(body)
+                .map_err(|e| ProtocolError::PostcardDecode(format!("{e:?}")))?;
+            Ok(ClientMessage::BaseBackup(p))
+        }
+        MessageKind::StartReplication => {
+            let p: StartReplicationPayload = postcard::from_bytes(body)
+                .map_err(|e| ProtocolError::PostcardDecode(format!("{e:?}")))?;
+            Ok(ClientMessage::StartReplication(p))
+        }
+        MessageKind::ReplicationAck => {
+            let p: ReplicationAckPayload = postcard::from_bytes(body)
+                .map_err(|e| ProtocolError::PostcardDecode(format!("{e:?}")))?;
+            Ok(ClientMessage::ReplicationAck(p))
+        }
+        MessageKind::Authenticate => {
+            let p: AuthenticatePayload = postcard::from_bytes(body)
+                .map_err(|e| ProtocolError::PostcardDecode(format!("{e:?}")))?;
+            Ok(ClientMessage::Authenticate(p))
+        }
+        MessageKind::QueryWithFormat => {
+            let p: QueryWithFormatPayload = postcard::from_bytes(body)
+                .map_err(|e| ProtocolError::PostcardDecode(format!("{e:?}")))?;
+            Ok(ClientMessage::QueryWithFormat(p))
+        }
+        MessageKind::CopyIn => {
+            let p: CopyInPayload = postcard::from_bytes(body)
+                .map_err(|e| ProtocolError::PostcardDecode(format!("{e:?}")))?;
+            Ok(ClientMessage::CopyIn(p))
+        }
+        MessageKind::CopyData => {
+            let p: CopyDataPayload = postcard::from_bytes(body)
+                .map_err(|e| ProtocolError::PostcardDecode(format!("{e:?}")))?;
+            Ok(ClientMessage::CopyData(p))
+        }
+        MessageKind::CopyDone if body.is_empty() => Ok(ClientMessage::CopyDone),
+        MessageKind::CopyDone => Err(ProtocolError::PostcardDecode(format!(
+            "CopyDone carries no payload, got {} bytes",
+            body.len()
+        ))),
         _ => unreachable!(),
     }
 }
@@ -217,11 +317,19 @@ pub fn decode_server_frame(
     }
     let kind = MessageKind::try_from(header.message_kind)
         .map_err(|_| ProtocolError::UnknownMessageKind(header.message_kind))?;
+    if kind == MessageKind::Compressed {
+        let inner = inflate_frame(&header, payload_slice(frame, &header)?)?;
+        return decode_server_frame(expected_protocol_version, &inner);
+    }
     match kind {
         MessageKind::ResultSet
         | MessageKind::ExecutionOk
         | MessageKind::Error
-        | MessageKind::ServerReady => {}
+        | MessageKind::ServerReady
+        | MessageKind::BackupChunk
+        | MessageKind::BackupEnd
+        | MessageKind::ReplicatedTransaction
+        | MessageKind::BinaryResultSet => {}
         _ => {
             return Err(ProtocolError::WrongDirection {
                 kind,
@@ -251,6 +359,26 @@ pub fn decode_server_frame(
                 .map_err(|e| ProtocolError::PostcardDecode(format!("{e:?}")))?;
             Ok(ServerMessage::ServerReady(p))
         }
+        MessageKind::BackupChunk => {
+            let p: BackupChunkPayload = postcard::from_bytes(body)
+                .map_err(|e| ProtocolError::PostcardDecode(format!("{e:?}")))?;
+            Ok(ServerMessage::BackupChunk(p))
+        }
+        MessageKind::BackupEnd => {
+            let p: BackupEndPayload = postcard::from_bytes(body)
+                .map_err(|e| ProtocolError::PostcardDecode(format!("{e:?}")))?;
+            Ok(ServerMessage::BackupEnd(p))
+        }
+        MessageKind::ReplicatedTransaction => {
+            let p: ReplicatedTransactionPayload = postcard::from_bytes(body)
+                .map_err(|e| ProtocolError::PostcardDecode(format!("{e:?}")))?;
+            Ok(ServerMessage::ReplicatedTransaction(p))
+        }
+        MessageKind::BinaryResultSet => {
+            let p: BinaryResultSetPayload = postcard::from_bytes(body)
+                .map_err(|e| ProtocolError::PostcardDecode(format!("{e:?}")))?;
+            Ok(ServerMessage::BinaryResultSet(p))
+        }
         _ => unreachable!(),
     }
 }
@@ -0,0 +1,87 @@
+//! Per-connection frame compression: a frame's payload, lz4-compressed, wrapped in a
+//! [`MessageKind::Compressed`] frame.
+//!
+//! Payload of a `Compressed` frame: the `u16` message kind of the frame it carries
+//! (little-endian), then an lz4 block prefixed with its uncompressed length as `u32`. Peers only
+//! send such frames once the connection negotiated compression (ALPN `rustdb-v1-lz4`, see
+//! [`crate::network::server::ALPN_RUSTDB_V1_LZ4`]); decoders accept them either way.
+
+use super::header::{FrameHeader, FRAME_HEADER_LEN, MAX_FRAME_PAYLOAD_BYTES};
+use super::messages::MessageKind;
+use super::ProtocolError;
+
+/// Compresses `frame` (header + payload) when its payload is at least `min_payload_bytes` and
+/// lz4 makes it smaller; `None` means send the frame as it is.
+pub fn compress_frame(frame: &[u8], min_payload_bytes: usize) -> Option<Vec<u8>> {
+    let header = FrameHeader::decode(frame).ok()?;
+    let payload = frame.get(FRAME_HEADER_LEN..)?;
+    if payload.len() < min_payload_bytes.max(1)
+        || header.message_kind == MessageKind::Compressed.as_u16()
+    {
+        return None;
+    }
+    let block = lz4_flex::compress_prepend_size(payload);
+    let payload_len = 2 + block.len();
+    if payload_len >= payload.len() {
+        return None;
+    }
+    let mut hdr = [0u8; FRAME_HEADER_LEN];
+    FrameHeader {
+        protocol_version: header.protocol_version,
+        message_kind: MessageKind::Compressed.as_u16(),
+        payload_len: payload_len as u32,
+    }
+    .encode_into(&mut hdr);
+    let mut out = Vec::with_capacity(FRAME_HEADER_LEN + payload_len);
+    out.extend_from_slice(&hdr);
+    out.extend_from_slice(&header.message_kind.to_le_bytes());
+    out.extend_from_slice(&block);
+    Some(out)
+}
+
+/// Size of the frame a `Compressed` frame inflates to, read from its header and length prefix
+/// without decompressing; `None` for any other frame.
+pub fn inflated_frame_len(frame: &[u8]) -> Option<usize> {
+    let header = FrameHeader::decode(frame).ok()?;
+    if header.message_kind != MessageKind::Compressed.as_u16() {
+        return None;
+    }
+    let prefix = frame.get(FRAME_HEADER_LEN + 2..FRAME_HEADER_LEN + 6)?;
+    let len = u32::from_le_bytes(prefix.try_into().ok()?) as usize;
+    Some(FRAME_HEADER_LEN + len)
+}
+
+/// The frame a `Compressed` frame carries (header + payload), for the decoders to parse.
+pub(super) fn inflate_frame(header: &FrameHeader, body: &[u8]) -> Result<Vec<u8>, ProtocolError> {
+    let (kind, block) = body
+        .split_first_chunk::<2>()
+        .ok_or_else(|| ProtocolError::Decompress("missing the inner message kind".into()))?;
+    let message_kind = u16::from_le_bytes(*kind);
+    if message_kind == MessageKind::Compressed.as_u16() {
+        return Err(ProtocolError::Decompress("nested compressed frame".into()));
+    }
+    let declared = block
+        .first_chunk::<4>()
+        .map(|n| u32::from_le_bytes(*n))
+        .ok_or_else(|| ProtocolError::Decompress("missing the uncompressed length".into()))?;
+    // Checked before inflating, so a small frame cannot claim an arbitrarily large buffer.
+    if declared > MAX_FRAME_PAYLOAD_BYTES {
+        return Err(ProtocolError::PayloadTooLarge {
+            len: declared,
+            max: MAX_FRAME_PAYLOAD_BYTES,
+        });
+    }
+    let payload = lz4_flex::decompress_size_prepended(block)
+        .map_err(|e| ProtocolError::Decompress(e.to_string()))?;
+    let mut hdr = [0u8; FRAME_HEADER_LEN];
+    FrameHeader {
+        protocol_version: header.protocol_version,
+        message_kind,
+        payload_len: payload.len() as u32,
+    }
+    .encode_into(&mut hdr);
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + payload.len());
+    frame.extend_from_slice(&hdr);
+    frame.extend_from_slice(&payload);
+    Ok(frame)
+}
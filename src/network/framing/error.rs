@@ -55,6 +55,10 @@ pub enum ProtocolError {
     /// Postcard could not deserialize the payload for this message kind.
     #[error("postcard decode error: {0}")]
     PostcardDecode(String),
+
+    /// A `Compressed` frame could not be inflated into the frame it carries.
+    #[error("compressed frame: {0}")]
+    Decompress(String),
 }
 
 /// Whether we are decoding a client-originated or server-originated frame.
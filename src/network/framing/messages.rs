@@ -16,6 +16,20 @@ pub enum MessageKind {
     ServerReady = 6,
     ExecuteScript = 7,
     ExecuteTpcc = 8,
+    BaseBackup = 9,
+    BackupChunk = 10,
+    BackupEnd = 11,
+    StartReplication = 12,
+    ReplicatedTransaction = 13,
+    ReplicationAck = 14,
+    Authenticate = 15,
+    QueryWithFormat = 16,
+    BinaryResultSet = 17,
+    /// Another frame's payload, lz4-compressed; either direction (see [`super::compress_frame`]).
+    Compressed = 18,
+    CopyIn = 19,
+    CopyData = 20,
+    CopyDone = 21,
 }
 
 impl MessageKind {
@@ -38,6 +52,19 @@ impl TryFrom<u16> for MessageKind {
             6 => Ok(MessageKind::ServerReady),
             7 => Ok(MessageKind::ExecuteScript),
             8 => Ok(MessageKind::ExecuteTpcc),
+            9 => Ok(MessageKind::BaseBackup),
+            10 => Ok(MessageKind::BackupChunk),
+            11 => Ok(MessageKind::BackupEnd),
+            12 => Ok(MessageKind::StartReplication),
+            13 => Ok(MessageKind::ReplicatedTransaction),
+            14 => Ok(MessageKind::ReplicationAck),
+            15 => Ok(MessageKind::Authenticate),
+            16 => Ok(MessageKind::QueryWithFormat),
+            17 => Ok(MessageKind::BinaryResultSet),
+            18 => Ok(MessageKind::Compressed),
+            19 => Ok(MessageKind::CopyIn),
+            20 => Ok(MessageKind::CopyData),
+            21 => Ok(MessageKind::CopyDone),
             _ => Err(()),
         }
     }
@@ -51,6 +78,23 @@ pub struct QueryPayload {
     pub sql: String,
 }
 
+/// How a query's rows come back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ResultFormat {
+    /// [`ResultSetPayload`]: cells as text.
+    #[default]
+    Text,
+    /// [`BinaryResultSetPayload`]: typed values (see `framing::binary_rows`).
+    Binary,
+}
+
+/// SQL text and the format its result set should use; chosen per statement.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryWithFormatPayload {
+    pub sql: String,
+    pub result_format: ResultFormat,
+}
+
 /// Optional capability probe (v1 minimal).
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ClientHelloPayload {
@@ -72,6 +116,94 @@ pub struct ExecuteTpccPayload {
     pub global_txn_id: u64,
 }
 
+/// Replication: request a consistent base copy of the server's data directory.
+///
+/// Answered on the same stream by [`BackupChunkPayload`] frames and one [`BackupEndPayload`]
+/// (see [`crate::network::replication`]).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BaseBackupPayload {
+    /// Free-form label recorded in the server log.
+    pub label: String,
+    /// Only send what changed since this earlier backup (an incremental backup).
+    pub since: Option<IncrementalBasePayload>,
+}
+
+/// Earlier backup an incremental backup is taken against.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IncrementalBasePayload {
+    /// [`BackupEndPayload::start_lsn`] of that backup.
+    pub start_lsn: u64,
+    /// Every file of the data directory as of that backup.
+    pub files: Vec<BackupFileBlocks>,
+}
+
+/// File of an earlier backup: its checksum and a digest per page, to find the changed pages.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupFileBlocks {
+    pub path: String,
+    pub sha256: [u8; 32],
+    pub blocks: Vec<u64>,
+}
+
+/// Replication: stream committed changes after `start_lsn` (e.g. [`BackupEndPayload::start_lsn`]).
+///
+/// Answered on the same stream by [`ReplicatedTransactionPayload`] frames, in commit order, until
+/// the client stops reading; the client reports progress with [`ReplicationAckPayload`] frames.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StartReplicationPayload {
+    pub start_lsn: u64,
+    /// Free-form label recorded in the server log.
+    pub label: String,
+}
+
+/// Replication: the replica applied every transaction up to `applied_lsn`.
+///
+/// Sent on the `StartReplication` stream; synchronous commits on the server wait for these.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplicationAckPayload {
+    pub applied_lsn: u64,
+}
+
+/// Credentials, sent on the first stream of a connection to a server with authentication rules
+/// (see [`crate::network::auth`]).
+///
+/// The server answers `ExecutionOk` and serves the connection as `user`, or answers `Error`
+/// and closes it. `secret` is the password or token the matching rule's method checks.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthenticatePayload {
+    pub user: String,
+    pub secret: Option<String>,
+}
+
+impl std::fmt::Debug for AuthenticatePayload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthenticatePayload")
+            .field("user", &self.user)
+            .field("secret", &self.secret.as_ref().map(|_| "…"))
+            .finish()
+    }
+}
+
+/// Bulk load: the following [`CopyDataPayload`] frames insert rows into `table`, until a
+/// `CopyDone` frame ends the load (see [`crate::network::copy_in`]).
+///
+/// `columns` lists the columns each row gives values for, in order; empty means every column of
+/// the table in table order. Only `CopyDone` is answered.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CopyInPayload {
+    pub table: String,
+    pub columns: Vec<String>,
+}
+
+/// Bulk load rows in the binary row encoding (`framing::binary_rows`): `row_count` rows of
+/// `column_count` values, back to back in `data`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CopyDataPayload {
+    pub column_count: u32,
+    pub row_count: u64,
+    pub data: Vec<u8>,
+}
+
 /// Messages sent from client to server.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ClientMessage {
@@ -79,6 +211,14 @@ pub enum ClientMessage {
     ClientHello(ClientHelloPayload),
     ExecuteScript(ExecuteScriptPayload),
     ExecuteTpcc(ExecuteTpccPayload),
+    BaseBackup(BaseBackupPayload),
+    StartReplication(StartReplicationPayload),
+    ReplicationAck(ReplicationAckPayload),
+    Authenticate(AuthenticatePayload),
+    QueryWithFormat(QueryWithFormatPayload),
+    CopyIn(CopyInPayload),
+    CopyData(CopyDataPayload),
+    CopyDone,
 }
 
 // --- Server → client payloads ------------------------------------------------
@@ -90,6 +230,15 @@ pub struct ResultSetPayload {
     pub rows: Vec<Vec<String>>,
 }
 
+/// Tabular result in the binary row encoding (`framing::binary_rows`): `row_count` rows of one
+/// value per column, back to back in `data`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BinaryResultSetPayload {
+    pub columns: Vec<String>,
+    pub row_count: u64,
+    pub data: Vec<u8>,
+}
+
 /// Non-query statement finished (DDL/DML without row set).
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ExecutionOkPayload {
@@ -108,6 +257,96 @@ pub struct ServerReadyPayload {
     pub server_version: String,
 }
 
+/// Piece of one base backup file; a file's chunks arrive in order, one file after another.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupChunkPayload {
+    /// Path relative to the data directory, `/`-separated.
+    pub path: String,
+    /// Byte offset of `data` in the file.
+    pub offset: u64,
+    pub data: Vec<u8>,
+}
+
+/// Last frame of a base backup.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupEndPayload {
+    /// Highest WAL LSN contained in the copy; WAL streaming resumes after it.
+    pub start_lsn: u64,
+    pub files: u64,
+    pub bytes: u64,
+    /// Lowest WAL LSN contained in the copy (`0` without WAL).
+    pub first_lsn: u64,
+    /// Version of the server that took the backup.
+    pub engine_version: String,
+    /// One entry per file, in the order the files were sent.
+    pub digests: Vec<BackupFileDigest>,
+    /// Set when the request asked for an incremental backup.
+    pub incremental: Option<IncrementalEndPayload>,
+}
+
+/// What an incremental backup contains beyond the files sent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IncrementalEndPayload {
+    /// [`IncrementalBasePayload::start_lsn`] of the request.
+    pub base_start_lsn: u64,
+    /// Every file of the data directory, whether sent whole, sent as a page delta or unchanged.
+    pub state: Vec<BackupFileDigest>,
+    /// Files of `state` sent as page deltas, under `<path>.delta`.
+    pub deltas: Vec<String>,
+}
+
+/// Size and SHA-256 of one base backup file as the server read it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupFileDigest {
+    pub path: String,
+    pub size: u64,
+    pub sha256: [u8; 32],
+}
+
+/// Column value of a replicated row, rendered as a SQL literal (`NULL`, `42`, `'text'`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplicatedColumn {
+    pub name: String,
+    pub literal: String,
+}
+
+/// One logical change of a replicated transaction; `key` names the primary key columns of
+/// `table` (empty without a primary key).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RowChangePayload {
+    Insert {
+        table: String,
+        key: Vec<String>,
+        row: Vec<ReplicatedColumn>,
+    },
+    Update {
+        table: String,
+        key: Vec<String>,
+        old: Vec<ReplicatedColumn>,
+        new: Vec<ReplicatedColumn>,
+    },
+    Delete {
+        table: String,
+        key: Vec<String>,
+        old: Vec<ReplicatedColumn>,
+    },
+    /// Schema change, replayed as its original statement.
+    Ddl { sql: String },
+}
+
+/// Committed transaction decoded from the server's WAL.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplicatedTransactionPayload {
+    /// LSN of the commit record (of the DDL record for schema changes).
+    pub commit_lsn: u64,
+    /// Commit time on the server, Unix seconds.
+    pub commit_time: u64,
+    /// Set when the server itself replayed the transaction from a bidirectional peer: the commit
+    /// time there.
+    pub origin_commit_time: Option<u64>,
+    pub changes: Vec<RowChangePayload>,
+}
+
 /// Messages sent from server to client.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ServerMessage {
@@ -115,4 +354,8 @@ pub enum ServerMessage {
     ExecutionOk(ExecutionOkPayload),
     Error(ErrorPayload),
     ServerReady(ServerReadyPayload),
+    BackupChunk(BackupChunkPayload),
+    BackupEnd(BackupEndPayload),
+    ReplicatedTransaction(ReplicatedTransactionPayload),
+    BinaryResultSet(BinaryResultSetPayload),
 }
@@ -2,11 +2,14 @@
 //!
 //! See `docs/network/framing.md`.
 
+mod binary_rows;
 mod codec;
+mod compression;
 mod error;
 mod header;
 pub mod messages;
 
+pub use binary_rows::encode_value;
 pub use codec::{
     cached_execution_ok_frame_v1, classify_server_frame_v1, decode_client_frame,
     decode_client_frame_v1, decode_server_frame, decode_server_frame_v1, encode_client_message,
@@ -15,12 +18,17 @@ pub use codec::{
     encode_server_message, encode_server_message_v1, encode_server_message_write,
     server_frame_message_kind, ServerFrameClass, TPCC_WIRE_KIND_ORDER_STATUS,
 };
+pub use compression::{compress_frame, inflated_frame_len};
 pub use error::{EncodeError, FrameDirection, ProtocolError};
 pub use header::{
     FrameHeader, FRAME_HEADER_LEN, FRAME_MAGIC, MAX_FRAME_PAYLOAD_BYTES, PROTOCOL_VERSION_V1,
 };
 pub use messages::{
-    ClientHelloPayload, ClientMessage, ErrorPayload, ExecuteScriptPayload, ExecuteTpccPayload,
-    ExecutionOkPayload, MessageKind, QueryPayload, ResultSetPayload, ServerMessage,
-    ServerReadyPayload,
+    AuthenticatePayload, BackupChunkPayload, BackupEndPayload, BackupFileBlocks, BackupFileDigest,
+    BaseBackupPayload, BinaryResultSetPayload, ClientHelloPayload, ClientMessage, CopyDataPayload,
+    CopyInPayload, ErrorPayload, ExecuteScriptPayload, ExecuteTpccPayload, ExecutionOkPayload,
+    IncrementalBasePayload, IncrementalEndPayload, MessageKind, QueryPayload,
+    QueryWithFormatPayload, ReplicatedColumn, ReplicatedTransactionPayload, ReplicationAckPayload,
+    ResultFormat, ResultSetPayload, RowChangePayload, ServerMessage, ServerReadyPayload,
+    StartReplicationPayload,
 };
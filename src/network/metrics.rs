@@ -22,6 +22,12 @@ pub struct QuicNetworkSnapshot {
     pub handshake_failures: u64,
     pub connections_refused: u64,
     pub read_frame_errors: u64,
+    pub sessions_reaped_idle_in_transaction: u64,
+    pub authentication_failures: u64,
+    pub compressed_connections: u64,
+    pub frames_compressed: u64,
+    pub frames_decompressed: u64,
+    pub compression_bytes_saved: u64,
     pub queries_handled: u64,
     pub queries_ok: u64,
     pub queries_error_response: u64,
@@ -39,6 +45,21 @@ pub struct QuicNetworkMetrics {
     pub handshake_failures: AtomicU64,
     pub connections_refused: AtomicU64,
     pub read_frame_errors: AtomicU64,
+    /// Sessions rolled back and closed for idling in a transaction (see
+    /// [`crate::network::query_stream::StreamPolicy::idle_in_transaction_timeout`]).
+    pub sessions_reaped_idle_in_transaction: AtomicU64,
+    /// Connections closed because their `Authenticate` frame was missing or refused (see
+    /// [`crate::network::auth`]).
+    pub authentication_failures: AtomicU64,
+    /// Connections that negotiated lz4 frame compression (see
+    /// [`crate::network::framing::compress_frame`]).
+    pub compressed_connections: AtomicU64,
+    /// Response frames sent compressed.
+    pub frames_compressed: AtomicU64,
+    /// Request frames received compressed.
+    pub frames_decompressed: AtomicU64,
+    /// Bytes compression kept off the wire, both directions.
+    pub compression_bytes_saved: AtomicU64,
     pub queries_handled: AtomicU64,
     pub queries_ok: AtomicU64,
     pub queries_error_response: AtomicU64,
@@ -56,6 +77,12 @@ impl Default for QuicNetworkMetrics {
             handshake_failures: AtomicU64::new(0),
             connections_refused: AtomicU64::new(0),
             read_frame_errors: AtomicU64::new(0),
+            sessions_reaped_idle_in_transaction: AtomicU64::new(0),
+            authentication_failures: AtomicU64::new(0),
+            compressed_connections: AtomicU64::new(0),
+            frames_compressed: AtomicU64::new(0),
+            frames_decompressed: AtomicU64::new(0),
+            compression_bytes_saved: AtomicU64::new(0),
             queries_handled: AtomicU64::new(0),
             queries_ok: AtomicU64::new(0),
             queries_error_response: AtomicU64::new(0),
@@ -75,6 +102,14 @@ impl QuicNetworkMetrics {
             handshake_failures: self.handshake_failures.load(Ordering::Relaxed),
             connections_refused: self.connections_refused.load(Ordering::Relaxed),
             read_frame_errors: self.read_frame_errors.load(Ordering::Relaxed),
+            sessions_reaped_idle_in_transaction: self
+                .sessions_reaped_idle_in_transaction
+                .load(Ordering::Relaxed),
+            authentication_failures: self.authentication_failures.load(Ordering::Relaxed),
+            compressed_connections: self.compressed_connections.load(Ordering::Relaxed),
+            frames_compressed: self.frames_compressed.load(Ordering::Relaxed),
+            frames_decompressed: self.frames_decompressed.load(Ordering::Relaxed),
+            compression_bytes_saved: self.compression_bytes_saved.load(Ordering::Relaxed),
             queries_handled: self.queries_handled.load(Ordering::Relaxed),
             queries_ok: self.queries_ok.load(Ordering::Relaxed),
             queries_error_response: self.queries_error_response.load(Ordering::Relaxed),
@@ -89,6 +124,25 @@ impl QuicNetworkMetrics {
         self.read_frame_errors.fetch_add(1, Ordering::Relaxed);
     }
 
+    pub fn record_idle_in_transaction_reaped(&self) {
+        self.sessions_reaped_idle_in_transaction
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A response frame of `original` bytes went out as `sent` bytes.
+    pub fn record_frame_compressed(&self, original: u64, sent: u64) {
+        self.frames_compressed.fetch_add(1, Ordering::Relaxed);
+        self.compression_bytes_saved
+            .fetch_add(original.saturating_sub(sent), Ordering::Relaxed);
+    }
+
+    /// A request frame of `received` bytes inflated to `original` bytes.
+    pub fn record_frame_decompressed(&self, original: u64, received: u64) {
+        self.frames_decompressed.fetch_add(1, Ordering::Relaxed);
+        self.compression_bytes_saved
+            .fetch_add(original.saturating_sub(received), Ordering::Relaxed);
+    }
+
     pub fn record_query_handled(
         &self,
         outcome: QueryHandledOutcome,
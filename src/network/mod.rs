@@ -1,11 +1,17 @@
 //! Network layer for rustdb
 
+pub mod auth;
 pub mod client;
+pub mod cluster;
 pub mod connection;
+pub mod copy_in;
+pub mod dump_import;
 pub mod engine;
 pub mod framing;
 pub mod metrics;
+pub mod pg_wire;
 pub mod query_stream;
+pub mod replication;
 pub mod server;
 pub mod sql_commit_log;
 pub mod sql_constraints;
@@ -0,0 +1,1298 @@
+//! PostgreSQL wire protocol (v3) over TCP, so `psql`, libpq and other PostgreSQL drivers can
+//! talk to the engine.
+//!
+//! `rustdb server --pg-port <port>` (or `network.pg_port`) starts a [`PgWireServer`] next to the
+//! QUIC listener. Each connection is one session, served by its own SQL worker thread like a
+//! QUIC stream; a transaction left open when the client goes away is rolled back.
+//!
+//! | Part | Support |
+//! |------|---------|
+//! | Startup | protocol 3.0; SSL and GSSAPI encryption requests are declined, so clients go on in plain text |
+//! | Authentication | none, or with `network.hba_file` a cleartext password checked by [`Authentication`] (sent unencrypted) |
+//! | Simple query | several `;`-separated statements per message, run in order until the first error |
+//! | Extended query | Parse, Bind, Describe, Execute (with a row limit and `PortalSuspended`), Close, Sync, Flush |
+//! | Errors | `ErrorResponse` with a SQLSTATE mapped from the engine code (see [`sqlstate`]; `42601` for syntax errors); the rest of an extended-query batch is skipped until Sync |
+//!
+//! COPY, function calls and cancel requests are not supported.
+//!
+//! **Values.** Result columns are `bool`, `int8` (every integer), `float8` (every float), `bytea`,
+//! and `text` for everything else, in the text or binary format the client asks for. Parse
+//! prepares the statement in the engine ([`EngineHandle::prepare`]) and Execute runs it with the
+//! bound parameters (`$1`, `$2`, ...) as values ([`EngineHandle::execute_prepared`]), so a
+//! statement is parsed and planned once however often it runs; `bool`, `int2`/`int4`/`int8`,
+//! `float4`/`float8`, `bytea` and text values are accepted in both formats. A parameter whose type
+//! the client left unspecified is a number when its text is a plain integer, a string otherwise.
+//!
+//! **Describe.** A prepared statement is described from its plan without running it
+//! ([`EngineHandle::describe_prepared`]): columns are typed from the catalog and the select list,
+//! `text` where that does not fix a type, and its portals return rows in those columns. A portal
+//! of a statement that was not described is described by running it (Execute then returns those
+//! rows), its columns typed from their first non-NULL value, as are the results of simple
+//! queries.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::mpsc as sync_mpsc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+use tracing::{info, warn};
+
+use crate::common::row_mapping::parse_cell;
+use crate::common::types::{ColumnValue, DataType};
+use crate::network::auth::{AuthError, Authentication, Credentials};
+use crate::network::engine::{
+    engine_error_code, EngineError, EngineHandle, EngineOutput, SessionContext,
+};
+use crate::network::query_stream::{in_transaction, open_session};
+use crate::network::server::ServerConfig;
+use crate::network::sql_engine::PreparedSql;
+
+const PROTOCOL_V3: i32 = 196_608;
+const SSL_REQUEST: i32 = 80_877_103;
+const GSSENC_REQUEST: i32 = 80_877_104;
+const CANCEL_REQUEST: i32 = 80_877_102;
+
+/// Version reported as `server_version`; drivers parse it for feature checks.
+const SERVER_VERSION: &str = "14.0 (RustDB)";
+
+/// Type OIDs of the values this module reads and writes
+mod oid {
+    pub const UNSPECIFIED: u32 = 0;
+    pub const BOOL: u32 = 16;
+    pub const BYTEA: u32 = 17;
+    pub const INT8: u32 = 20;
+    pub const INT2: u32 = 21;
+    pub const INT4: u32 = 23;
+    pub const TEXT: u32 = 25;
+    pub const FLOAT4: u32 = 700;
+    pub const FLOAT8: u32 = 701;
+    pub const NUMERIC: u32 = 1700;
+}
+
+/// Errors of the PostgreSQL listener
+pub type PgWireResult<T> = std::result::Result<T, std::io::Error>;
+
+/// TCP listener speaking the PostgreSQL wire protocol
+pub struct PgWireServer {
+    listener: TcpListener,
+    config: Arc<ServerConfig>,
+    active: Arc<AtomicUsize>,
+}
+
+impl PgWireServer {
+    /// Binds `config.host` on `port`; `max_connections`, `query_timeout`, `max_sql_bytes`,
+    /// `connection_timeout` and `authentication` of `config` apply to the connections.
+    pub async fn bind(config: ServerConfig, port: u16) -> PgWireResult<Self> {
+        let listener = TcpListener::bind((config.host.as_str(), port)).await?;
+        info!(addr = %listener.local_addr()?, "PostgreSQL wire protocol listener bound");
+        Ok(Self {
+            listener,
+            config: Arc::new(config),
+            active: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Connections being served
+    pub fn active_connections(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// Accepts connections until the listener fails, one task per connection.
+    pub async fn run(&self, engine: Arc<dyn EngineHandle>) -> PgWireResult<()> {
+        static BACKEND_IDS: AtomicU32 = AtomicU32::new(1);
+        loop {
+            let (stream, remote) = self.listener.accept().await?;
+            let _ = stream.set_nodelay(true);
+            let active = self.active.clone();
+            if active.fetch_add(1, Ordering::Relaxed) >= self.config.max_connections.max(1) {
+                active.fetch_sub(1, Ordering::Relaxed);
+                warn!(%remote, "refusing PostgreSQL connection: max_connections reached");
+                tokio::spawn(refuse(stream));
+                continue;
+            }
+            let backend_id = BACKEND_IDS.fetch_add(1, Ordering::Relaxed);
+            let (engine, config) = (engine.clone(), self.config.clone());
+            tokio::spawn(async move {
+                if let Err(e) = serve_connection(stream, remote, engine, config, backend_id).await {
+                    info!(%remote, error = %e, "PostgreSQL connection ended with an error");
+                }
+                active.fetch_sub(1, Ordering::Relaxed);
+            });
+        }
+    }
+}
+
+async fn refuse(mut stream: TcpStream) {
+    let mut out = Vec::new();
+    error_response(
+        &mut out,
+        "FATAL",
+        "53300",
+        "sorry, too many clients already",
+        None,
+    );
+    let _ = stream.write_all(&out).await;
+}
+
+/// Serves one client connection: startup, authentication, then queries until Terminate or EOF.
+pub async fn serve_connection(
+    stream: TcpStream,
+    remote: SocketAddr,
+    engine: Arc<dyn EngineHandle>,
+    config: Arc<ServerConfig>,
+    backend_id: u32,
+) -> PgWireResult<()> {
+    let (read, write) = stream.into_split();
+    let mut conn = Connection {
+        reader: BufReader::new(read),
+        writer: write,
+        out: Vec::with_capacity(4096),
+        max_message_bytes: config.max_sql_bytes.saturating_add(1 << 16),
+        query_timeout: config.query_timeout,
+        worker: None,
+        statements: HashMap::new(),
+        portals: HashMap::new(),
+        in_transaction: false,
+    };
+    let Some(startup) =
+        tokio::time::timeout(config.connection_timeout, conn.startup(remote, &config))
+            .await
+            .unwrap_or_else(|_| {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "no startup message in time",
+                ))
+            })?
+    else {
+        return Ok(());
+    };
+    let ctx = match open_session(engine.as_ref(), startup.authenticated.as_deref()) {
+        Ok(ctx) => ctx,
+        Err(e) => {
+            error_response(
+                &mut conn.out,
+                "FATAL",
+                sqlstate(e.code),
+                &e.message,
+                Some(e.code),
+            );
+            return conn.flush().await;
+        }
+    };
+    conn.worker = Some(SessionWorker::spawn(engine, ctx));
+
+    message(&mut conn.out, b'R', |b| put_i32(b, 0));
+    for (name, value) in [
+        ("server_version", SERVER_VERSION),
+        ("server_encoding", "UTF8"),
+        ("client_encoding", "UTF8"),
+        ("DateStyle", "ISO, MDY"),
+        ("TimeZone", "UTC"),
+        ("integer_datetimes", "on"),
+        // String literals take backslash escapes.
+        ("standard_conforming_strings", "off"),
+        ("application_name", startup.application_name.as_str()),
+        ("session_authorization", startup.user.as_str()),
+    ] {
+        message(&mut conn.out, b'S', |b| {
+            put_cstr(b, name);
+            put_cstr(b, value);
+        });
+    }
+    message(&mut conn.out, b'K', |b| {
+        put_i32(b, backend_id as i32);
+        put_i32(b, 0);
+    });
+    conn.ready_for_query();
+    conn.flush().await?;
+    info!(%remote, user = %startup.user, "PostgreSQL session started");
+    conn.serve().await
+}
+
+/// What the startup message said
+struct Startup {
+    user: String,
+    application_name: String,
+    /// User the connection authenticated as, when authentication is required
+    authenticated: Option<String>,
+}
+
+/// A prepared statement (`Parse`)
+struct Prepared {
+    statement: Arc<PreparedSql>,
+    /// Declared parameter types, [`oid::UNSPECIFIED`] where the client gave none
+    param_types: Vec<u32>,
+    /// Result columns, once the statement was described and returns rows
+    columns: Option<Vec<Column>>,
+}
+
+/// A bound statement (`Bind`)
+struct Portal {
+    statement: Arc<PreparedSql>,
+    params: Vec<ColumnValue>,
+    /// Result columns of the described statement
+    columns: Option<Vec<Column>>,
+    result_formats: Vec<i16>,
+    /// Result of the statement once it ran (on Describe or the first Execute)
+    result: Option<PortalResult>,
+}
+
+struct PortalResult {
+    columns: Vec<Column>,
+    rows: std::vec::IntoIter<Vec<DataType>>,
+    tag: String,
+}
+
+#[derive(Clone)]
+struct Column {
+    name: String,
+    type_oid: u32,
+}
+
+/// Work for a session worker; it sends its own reply
+type Job = Box<dyn FnOnce(&dyn EngineHandle, &mut SessionContext) + Send>;
+
+/// Runs the statements of one session on a dedicated thread (the engine blocks).
+struct SessionWorker {
+    jobs: sync_mpsc::Sender<Job>,
+}
+
+impl SessionWorker {
+    fn spawn(engine: Arc<dyn EngineHandle>, mut ctx: SessionContext) -> Self {
+        let (jobs, job_rx) = sync_mpsc::channel::<Job>();
+        std::thread::Builder::new()
+            .name("rustdb-pg-conn-sql".to_string())
+            .spawn(move || {
+                while let Ok(job) = job_rx.recv() {
+                    job(engine.as_ref(), &mut ctx);
+                }
+                if in_transaction(&ctx) {
+                    let _ = engine.execute_sql("ROLLBACK", &mut ctx);
+                }
+            })
+            .expect("spawn rustdb-pg-conn-sql worker");
+        Self { jobs }
+    }
+}
+
+struct Connection {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+    /// Messages not yet written to the socket
+    out: Vec<u8>,
+    max_message_bytes: usize,
+    query_timeout: Duration,
+    worker: Option<SessionWorker>,
+    statements: HashMap<String, Prepared>,
+    portals: HashMap<String, Portal>,
+    in_transaction: bool,
+}
+
+impl Connection {
+    /// Reads the startup packet (declining encryption requests) and authenticates. `None` when
+    /// the client went away or was refused (the refusal is already sent).
+    async fn startup(
+        &mut self,
+        remote: SocketAddr,
+        config: &ServerConfig,
+    ) -> PgWireResult<Option<Startup>> {
+        let body = loop {
+            let len = self.reader.read_i32().await?;
+            if !(8..=10_000).contains(&len) {
+                return Err(malformed("bad startup packet length"));
+            }
+            let mut body = vec![0u8; len as usize - 4];
+            self.reader.read_exact(&mut body).await?;
+            let code = i32::from_be_bytes([body[0], body[1], body[2], body[3]]);
+            match code {
+                SSL_REQUEST | GSSENC_REQUEST => self.writer.write_all(b"N").await?,
+                CANCEL_REQUEST => return Ok(None),
+                code if code >> 16 == 3 => break body,
+                code => {
+                    let message = format!(
+                        "unsupported frontend protocol {}.{}: server supports 3.0",
+                        code >> 16,
+                        code & 0xffff
+                    );
+                    error_response(&mut self.out, "FATAL", "0A000", &message, None);
+                    self.flush().await?;
+                    return Ok(None);
+                }
+            }
+        };
+        let mut params = HashMap::new();
+        let mut fields = Reader::new(&body[4..]);
+        while let Some(name) = fields.cstr().filter(|n| !n.is_empty()) {
+            let value = fields.cstr().unwrap_or_default();
+            params.insert(name, value);
+        }
+        let Some(user) = params.remove("user") else {
+            error_response(
+                &mut self.out,
+                "FATAL",
+                "28000",
+                "no PostgreSQL user name specified in startup packet",
+                None,
+            );
+            self.flush().await?;
+            return Ok(None);
+        };
+        let application_name = params.remove("application_name").unwrap_or_default();
+
+        let mut authenticated = None;
+        if let Some(authentication) = &config.authentication {
+            // AuthenticationCleartextPassword
+            message(&mut self.out, b'R', |b| put_i32(b, 3));
+            self.flush().await?;
+            let password = match self.read_message().await? {
+                Some((b'p', body)) => Reader::new(&body).cstr(),
+                _ => None,
+            };
+            let credentials = Credentials {
+                user: user.clone(),
+                secret: password,
+                remote: remote.ip(),
+            };
+            if let Err(e) = authenticate(authentication.clone(), credentials).await {
+                warn!(%remote, user, reason = %e, "authentication failed");
+                let message = format!("password authentication failed for user \"{user}\"");
+                error_response(
+                    &mut self.out,
+                    "FATAL",
+                    "28P01",
+                    &message,
+                    Some(engine_error_code::AUTHENTICATION_FAILED),
+                );
+                self.flush().await?;
+                return Ok(None);
+            }
+            info!(%remote, user, "connection authenticated");
+            authenticated = Some(user.clone());
+        }
+        Ok(Some(Startup {
+            user,
+            application_name,
+            authenticated,
+        }))
+    }
+
+    /// Answers messages until Terminate or the end of the stream.
+    async fn serve(&mut self) -> PgWireResult<()> {
+        // After an error in an extended-query batch, messages are skipped until Sync.
+        let mut skipping = false;
+        while let Some((kind, body)) = self.read_message().await? {
+            if skipping && kind != b'S' {
+                continue;
+            }
+            let outcome = match kind {
+                b'Q' => {
+                    let sql = Reader::new(&body).cstr().unwrap_or_default();
+                    self.simple_query(&sql).await;
+                    self.ready_for_query();
+                    self.flush().await?;
+                    continue;
+                }
+                b'P' => self.parse(&body).await,
+                b'B' => self.bind(&body),
+                b'D' => self.describe(&body).await,
+                b'E' => self.execute(&body).await,
+                b'C' => self.close(&body),
+                b'S' => {
+                    skipping = false;
+                    self.portals.remove("");
+                    self.ready_for_query();
+                    self.flush().await?;
+                    continue;
+                }
+                b'H' => {
+                    self.flush().await?;
+                    continue;
+                }
+                b'X' => return Ok(()),
+                other => Err(EngineError::new(
+                    engine_error_code::PROTOCOL,
+                    format!("unsupported message type '{}'", other as char),
+                )),
+            };
+            if let Err(e) = outcome {
+                self.engine_error(&e);
+                skipping = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// `Q`: runs each statement of `sql`, stopping at the first error.
+    async fn simple_query(&mut self, sql: &str) {
+        let statements = split_statements(sql);
+        if statements.is_empty() {
+            message(&mut self.out, b'I', |_| {});
+            return;
+        }
+        for statement in statements {
+            match self.run(statement).await {
+                Ok(output) => {
+                    let mut result = portal_result(statement, output);
+                    if !result.columns.is_empty() || returns_rows(statement) {
+                        self.row_description(&result.columns, &[]);
+                    }
+                    self.data_rows(&mut result, &[], 0);
+                    self.command_complete(&result.tag);
+                }
+                Err(e) => {
+                    self.engine_error(&e);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// `P`: name, statement, parameter types.
+    async fn parse(&mut self, body: &[u8]) -> Result<(), EngineError> {
+        let mut r = Reader::new(body);
+        let (name, sql) = (r.cstr_or_err()?, r.cstr_or_err()?);
+        let declared = r.i16_or_err()?.max(0) as usize;
+        let mut param_types = (0..declared)
+            .map(|_| r.i32_or_err().map(|t| t as u32))
+            .collect::<Result<Vec<_>, _>>()?;
+        if !name.is_empty() && self.statements.contains_key(&name) {
+            return Err(EngineError::new(
+                engine_error_code::PROTOCOL,
+                format!("prepared statement \"{name}\" already exists"),
+            ));
+        }
+        let statement = self.run_job(move |engine, _| engine.prepare(&sql)).await?;
+        param_types.resize(statement.param_count(), oid::UNSPECIFIED);
+        self.statements.insert(
+            name,
+            Prepared {
+                statement: Arc::new(statement),
+                param_types,
+                columns: None,
+            },
+        );
+        message(&mut self.out, b'1', |_| {});
+        Ok(())
+    }
+
+    /// `B`: portal, statement, parameter formats and values, result formats.
+    fn bind(&mut self, body: &[u8]) -> Result<(), EngineError> {
+        let mut r = Reader::new(body);
+        let (portal, statement) = (r.cstr_or_err()?, r.cstr_or_err()?);
+        let prepared = self.statements.get(&statement).ok_or_else(|| {
+            EngineError::new(
+                engine_error_code::PROTOCOL,
+                format!("prepared statement \"{statement}\" does not exist"),
+            )
+        })?;
+        let formats = r.formats()?;
+        let count = r.i16_or_err()?.max(0) as usize;
+        if count != prepared.param_types.len() {
+            return Err(EngineError::new(
+                engine_error_code::PROTOCOL,
+                format!(
+                    "bind message supplies {count} parameters, but prepared statement \"{statement}\" requires {}",
+                    prepared.param_types.len()
+                ),
+            ));
+        }
+        let mut values = Vec::with_capacity(count);
+        for (i, &type_oid) in prepared.param_types.iter().enumerate() {
+            let len = r.i32_or_err()?;
+            let bytes = if len < 0 {
+                None
+            } else {
+                Some(r.take(len as usize).ok_or_else(truncated)?)
+            };
+            let value = decode_param(bytes, format_of(&formats, i), type_oid)?;
+            values.push(ColumnValue::new(value));
+        }
+        let result_formats = r.formats()?;
+        self.portals.insert(
+            portal,
+            Portal {
+                statement: prepared.statement.clone(),
+                params: values,
+                columns: prepared.columns.clone(),
+                result_formats,
+                result: None,
+            },
+        );
+        message(&mut self.out, b'2', |_| {});
+        Ok(())
+    }
+
+    /// `D`: describes a statement (`S`) or a portal (`P`).
+    async fn describe(&mut self, body: &[u8]) -> Result<(), EngineError> {
+        let mut r = Reader::new(body);
+        let kind = r.take(1).ok_or_else(truncated)?[0];
+        let name = r.cstr_or_err()?;
+        if kind == b'S' {
+            let prepared = self.statements.get(&name).ok_or_else(|| {
+                EngineError::new(
+                    engine_error_code::PROTOCOL,
+                    format!("prepared statement \"{name}\" does not exist"),
+                )
+            })?;
+            let types: Vec<u32> = prepared
+                .param_types
+                .iter()
+                .map(|&t| if t == oid::UNSPECIFIED { oid::TEXT } else { t })
+                .collect();
+            let statement = prepared.statement.clone();
+            let described = self
+                .run_job(move |engine, ctx| engine.describe_prepared(&statement, ctx))
+                .await?;
+            message(&mut self.out, b't', |b| {
+                put_i16(b, types.len() as i16);
+                for t in &types {
+                    put_i32(b, *t as i32);
+                }
+            });
+            let Some(described) = described else {
+                message(&mut self.out, b'n', |_| {});
+                return Ok(());
+            };
+            let columns: Vec<Column> = described
+                .into_iter()
+                .map(|(name, value)| Column {
+                    name,
+                    type_oid: type_oid(&value),
+                })
+                .collect();
+            self.row_description(&columns, &[]);
+            if let Some(prepared) = self.statements.get_mut(&name) {
+                prepared.columns = Some(columns);
+            }
+            return Ok(());
+        }
+        let portal = self.portal(&name)?;
+        if let (None, Some(columns)) = (&portal.result, &portal.columns) {
+            let (columns, formats) = (columns.clone(), portal.result_formats.clone());
+            self.row_description(&columns, &formats);
+            return Ok(());
+        }
+        let sql = portal.statement.sql().to_string();
+        if portal.result.is_none() && !returns_rows(&sql) {
+            message(&mut self.out, b'n', |_| {});
+            return Ok(());
+        }
+        if portal.result.is_none() {
+            let result = self.run_portal(&name).await?;
+            self.portal(&name)?.result = Some(result);
+        }
+        let portal = self.portals.get(&name).expect("portal checked above");
+        let result = portal.result.as_ref().expect("portal ran above");
+        if result.columns.is_empty() && !returns_rows(&sql) {
+            message(&mut self.out, b'n', |_| {});
+        } else {
+            let (columns, formats) = (&result.columns, &portal.result_formats);
+            row_description(&mut self.out, columns, formats);
+        }
+        Ok(())
+    }
+
+    /// `E`: portal, row limit (`0`: all rows).
+    async fn execute(&mut self, body: &[u8]) -> Result<(), EngineError> {
+        let mut r = Reader::new(body);
+        let name = r.cstr_or_err()?;
+        let max_rows = r.i32_or_err()?.max(0) as u64;
+        if self.portal(&name)?.result.is_none() {
+            let result = self.run_portal(&name).await?;
+            self.portal(&name)?.result = Some(result);
+        }
+        let mut portal = self.portals.remove(&name).expect("portal checked above");
+        let result = portal.result.as_mut().expect("portal ran above");
+        let suspended = self.data_rows(result, &portal.result_formats, max_rows);
+        if suspended {
+            message(&mut self.out, b's', |_| {});
+        } else {
+            let tag = result.tag.clone();
+            self.command_complete(&tag);
+        }
+        self.portals.insert(name, portal);
+        Ok(())
+    }
+
+    /// `C`: closes a statement (`S`) or a portal (`P`).
+    fn close(&mut self, body: &[u8]) -> Result<(), EngineError> {
+        let mut r = Reader::new(body);
+        let kind = r.take(1).ok_or_else(truncated)?[0];
+        let name = r.cstr_or_err()?;
+        if kind == b'S' {
+            self.statements.remove(&name);
+        } else {
+            self.portals.remove(&name);
+        }
+        message(&mut self.out, b'3', |_| {});
+        Ok(())
+    }
+
+    fn portal(&mut self, name: &str) -> Result<&mut Portal, EngineError> {
+        self.portals.get_mut(name).ok_or_else(|| {
+            EngineError::new(
+                engine_error_code::PROTOCOL,
+                format!("portal \"{name}\" does not exist"),
+            )
+        })
+    }
+
+    /// Runs `sql` on the session worker, within the query timeout.
+    async fn run(&mut self, sql: &str) -> Result<EngineOutput, EngineError> {
+        if sql.len() > self.max_message_bytes {
+            return Err(EngineError::new(
+                engine_error_code::SQL_TOO_LONG,
+                "SQL text exceeds the configured maximum length",
+            ));
+        }
+        let owned = sql.to_string();
+        let result = self
+            .run_job(move |engine, ctx| engine.execute_sql(&owned, ctx))
+            .await;
+        client_parameter_ok(sql, result)
+    }
+
+    /// Runs the statement of portal `name` with its parameters; its rows are in the described
+    /// columns of the statement, if any.
+    async fn run_portal(&mut self, name: &str) -> Result<PortalResult, EngineError> {
+        let portal = self.portal(name)?;
+        let (statement, params) = (portal.statement.clone(), portal.params.clone());
+        let columns = portal.columns.clone();
+        let job_statement = statement.clone();
+        let result = self
+            .run_job(move |engine, ctx| engine.execute_prepared(&job_statement, &params, ctx))
+            .await;
+        let output = client_parameter_ok(statement.sql(), result)?;
+        let result = portal_result(statement.sql(), output);
+        Ok(match columns {
+            Some(columns) => in_columns(result, columns),
+            None => result,
+        })
+    }
+
+    /// Runs `job` on the session worker, within the query timeout.
+    async fn run_job<T: Send + 'static>(
+        &mut self,
+        job: impl FnOnce(&dyn EngineHandle, &mut SessionContext) -> Result<T, EngineError>
+            + Send
+            + 'static,
+    ) -> Result<T, EngineError> {
+        let worker = self.worker.as_ref().expect("session started");
+        let (reply, reply_rx) = oneshot::channel();
+        worker
+            .jobs
+            .send(Box::new(move |engine, ctx| {
+                let result = job(engine, ctx);
+                let _ = reply.send((result, in_transaction(ctx)));
+            }))
+            .map_err(|_| EngineError::new(engine_error_code::INTERNAL, "session worker stopped"))?;
+        match tokio::time::timeout(self.query_timeout, reply_rx).await {
+            Ok(Ok((result, in_transaction))) => {
+                self.in_transaction = in_transaction;
+                result
+            }
+            Ok(Err(_)) => Err(EngineError::new(
+                engine_error_code::INTERNAL,
+                "session worker stopped",
+            )),
+            Err(_) => Err(EngineError::new(
+                engine_error_code::QUERY_TIMEOUT,
+                "query exceeded per-query timeout",
+            )),
+        }
+    }
+
+    fn row_description(&mut self, columns: &[Column], formats: &[i16]) {
+        row_description(&mut self.out, columns, formats);
+    }
+
+    /// Sends up to `max_rows` (`0`: all) remaining rows; whether rows are left.
+    fn data_rows(&mut self, result: &mut PortalResult, formats: &[i16], max_rows: u64) -> bool {
+        let mut sent = 0;
+        while max_rows == 0 || sent < max_rows {
+            let Some(row) = result.rows.next() else {
+                return false;
+            };
+            message(&mut self.out, b'D', |b| {
+                put_i16(b, row.len() as i16);
+                for (i, value) in row.iter().enumerate() {
+                    match encode_value(value, result.columns[i].type_oid, format_of(formats, i)) {
+                        Some(bytes) => {
+                            put_i32(b, bytes.len() as i32);
+                            b.extend_from_slice(&bytes);
+                        }
+                        None => put_i32(b, -1),
+                    }
+                }
+            });
+            sent += 1;
+        }
+        result.rows.len() > 0
+    }
+
+    fn command_complete(&mut self, tag: &str) {
+        message(&mut self.out, b'C', |b| put_cstr(b, tag));
+    }
+
+    fn engine_error(&mut self, e: &EngineError) {
+        let code = if e.message.starts_with("SQL parsing error") {
+            "42601"
+        } else {
+            sqlstate(e.code)
+        };
+        error_response(&mut self.out, "ERROR", code, &e.message, Some(e.code));
+    }
+
+    fn ready_for_query(&mut self) {
+        let status = if self.in_transaction { b'T' } else { b'I' };
+        message(&mut self.out, b'Z', |b| b.push(status));
+    }
+
+    async fn flush(&mut self) -> PgWireResult<()> {
+        if !self.out.is_empty() {
+            self.writer.write_all(&self.out).await?;
+            self.out.clear();
+        }
+        Ok(())
+    }
+
+    /// Next message as `(type, body)`; `None` at the end of the stream.
+    async fn read_message(&mut self) -> PgWireResult<Option<(u8, Vec<u8>)>> {
+        let kind = match self.reader.read_u8().await {
+            Ok(kind) => kind,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let len = self.reader.read_i32().await?;
+        if len < 4 || len as usize - 4 > self.max_message_bytes {
+            return Err(malformed("bad message length"));
+        }
+        let mut body = vec![0u8; len as usize - 4];
+        self.reader.read_exact(&mut body).await?;
+        Ok(Some((kind, body)))
+    }
+}
+
+async fn authenticate(
+    authentication: Arc<Authentication>,
+    credentials: Credentials,
+) -> Result<(), AuthError> {
+    tokio::task::spawn_blocking(move || authentication.authenticate(&credentials))
+        .await
+        .unwrap_or_else(|e| Err(AuthError::Unavailable(e.to_string())))
+}
+
+/// Rows and command tag of an engine output for `sql`
+fn portal_result(sql: &str, output: EngineOutput) -> PortalResult {
+    match output {
+        EngineOutput::ResultSet { columns, rows } => {
+            let rows: Vec<Vec<DataType>> = rows
+                .iter()
+                .map(|row| row.iter().map(|cell| parse_cell(cell)).collect())
+                .collect();
+            let columns = columns
+                .into_iter()
+                .enumerate()
+                .map(|(i, name)| Column {
+                    name,
+                    type_oid: rows
+                        .iter()
+                        .map(|row| &row[i])
+                        .find(|v| !matches!(v, DataType::Null))
+                        .map_or(oid::TEXT, type_oid),
+                })
+                .collect();
+            let tag = match first_keyword(sql).as_str() {
+                "SELECT" | "WITH" | "VALUES" | "TABLE" => format!("SELECT {}", rows.len()),
+                other => other.to_string(),
+            };
+            PortalResult {
+                columns,
+                rows: rows.into_iter(),
+                tag,
+            }
+        }
+        EngineOutput::ExecutionOk { rows_affected } => PortalResult {
+            columns: Vec::new(),
+            rows: Vec::new().into_iter(),
+            tag: command_tag(sql, rows_affected),
+        },
+    }
+}
+
+fn row_description(out: &mut Vec<u8>, columns: &[Column], formats: &[i16]) {
+    message(out, b'T', |b| {
+        put_i16(b, columns.len() as i16);
+        for (i, column) in columns.iter().enumerate() {
+            put_cstr(b, &column.name);
+            put_i32(b, 0); // table OID
+            put_i16(b, 0); // column number
+            put_i32(b, column.type_oid as i32);
+            put_i16(b, type_size(column.type_oid));
+            put_i32(b, -1); // type modifier
+            put_i16(b, format_of(formats, i));
+        }
+    });
+}
+
+/// `CommandComplete` tag of a statement that returned no rows
+fn command_tag(sql: &str, rows_affected: u64) -> String {
+    let words: Vec<String> = sql
+        .split_whitespace()
+        .take(4)
+        .map(|w| w.trim_end_matches(';').to_ascii_uppercase())
+        .collect();
+    let first = words.first().map(String::as_str).unwrap_or_default();
+    match first {
+        "INSERT" => format!("INSERT 0 {rows_affected}"),
+        "UPDATE" | "DELETE" | "MERGE" | "COPY" => format!("{first} {rows_affected}"),
+        "CREATE" | "DROP" | "ALTER" => {
+            let object = words[1..]
+                .iter()
+                .find(|w| {
+                    !matches!(
+                        w.as_str(),
+                        "UNIQUE" | "OR" | "REPLACE" | "TEMP" | "TEMPORARY"
+                    )
+                })
+                .map(|w| w.trim_end_matches('(').to_string())
+                .unwrap_or_default();
+            format!("{first} {object}").trim_end().to_string()
+        }
+        "START" => "START TRANSACTION".to_string(),
+        "ABORT" => "ROLLBACK".to_string(),
+        "END" => "COMMIT".to_string(),
+        _ => first.to_string(),
+    }
+}
+
+/// Upper-cased first word of `sql`
+fn first_keyword(sql: &str) -> String {
+    sql.trim_start_matches(|c: char| c.is_whitespace() || c == '(')
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .next()
+        .unwrap_or_default()
+        .to_ascii_uppercase()
+}
+
+/// Whether `sql` answers with rows whatever they are
+fn returns_rows(sql: &str) -> bool {
+    matches!(
+        first_keyword(sql).as_str(),
+        "SELECT" | "WITH" | "VALUES" | "TABLE" | "SHOW" | "EXPLAIN"
+    )
+}
+
+/// `result` in `columns`, by name: a column the result lacks is NULL
+fn in_columns(result: PortalResult, columns: Vec<Column>) -> PortalResult {
+    let positions: Vec<Option<usize>> = columns
+        .iter()
+        .map(|c| result.columns.iter().position(|r| r.name == c.name))
+        .collect();
+    let rows: Vec<Vec<DataType>> = result
+        .rows
+        .map(|row| {
+            positions
+                .iter()
+                .map(|p| p.map_or(DataType::Null, |i| row[i].clone()))
+                .collect()
+        })
+        .collect();
+    PortalResult {
+        columns,
+        rows: rows.into_iter(),
+        tag: result.tag,
+    }
+}
+
+/// `result` of `sql`, with the error of a [`sets_client_parameter`] statement turned into success
+fn client_parameter_ok(
+    sql: &str,
+    result: Result<EngineOutput, EngineError>,
+) -> Result<EngineOutput, EngineError> {
+    match result {
+        Err(e) if e.code == engine_error_code::INVALID_PARAMETER && sets_client_parameter(sql) => {
+            Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+        }
+        result => result,
+    }
+}
+
+/// `SET` of a parameter PostgreSQL drivers send at connection start, which the engine does not
+/// know; such statements succeed without effect.
+fn sets_client_parameter(sql: &str) -> bool {
+    const CLIENT_PARAMETERS: &[&str] = &[
+        "application_name",
+        "client_encoding",
+        "client_min_messages",
+        "datestyle",
+        "extra_float_digits",
+        "intervalstyle",
+        "search_path",
+        "standard_conforming_strings",
+        "timezone",
+    ];
+    let mut words = sql.split(|c: char| c.is_whitespace() || c == '=');
+    words.next().is_some_and(|w| w.eq_ignore_ascii_case("SET"))
+        && words
+            .find(|w| !w.is_empty() && !w.eq_ignore_ascii_case("SESSION"))
+            .is_some_and(|name| CLIENT_PARAMETERS.contains(&name.to_ascii_lowercase().as_str()))
+}
+
+/// SQLSTATE of an engine error code
+pub fn sqlstate(code: u32) -> &'static str {
+    match code {
+        engine_error_code::PROTOCOL => "08P01",
+        engine_error_code::SQL_TOO_LONG => "54000",
+        engine_error_code::QUERY_TIMEOUT => "57014",
+        engine_error_code::RESULT_ROWS_TOO_LARGE => "54000",
+        engine_error_code::UNSUPPORTED_SQL => "0A000",
+        engine_error_code::CONSTRAINT_VIOLATION => "23000",
+        engine_error_code::NO_ACTIVE_TRANSACTION => "25P01",
+        engine_error_code::ALREADY_IN_TRANSACTION => "25001",
+        engine_error_code::DDL_IN_TRANSACTION => "25001",
+        engine_error_code::PREPARED_TRANSACTION => "42704",
+        engine_error_code::SYNC_REPLICATION_TIMEOUT => "58000",
+        engine_error_code::INVALID_SNAPSHOT => "22023",
+        engine_error_code::INVALID_PARAMETER => "22023",
+        engine_error_code::REPLICA_LAG_TIMEOUT => "57014",
+        engine_error_code::SERIALIZATION_FAILURE => "40001",
+        engine_error_code::TOO_MANY_CONNECTIONS => "53300",
+        engine_error_code::IDLE_IN_TRANSACTION_TIMEOUT => "25P03",
+        engine_error_code::AUTHENTICATION_FAILED => "28P01",
+        engine_error_code::QUOTA_EXCEEDED => "53400",
+        _ => "XX000",
+    }
+}
+
+fn error_response(out: &mut Vec<u8>, severity: &str, code: &str, text: &str, engine: Option<u32>) {
+    message(out, b'E', |b| {
+        for (field, value) in [
+            (b'S', severity),
+            (b'V', severity),
+            (b'C', code),
+            (b'M', text),
+        ] {
+            b.push(field);
+            put_cstr(b, value);
+        }
+        if let Some(engine) = engine {
+            b.push(b'D');
+            put_cstr(b, &format!("RustDB error code {engine}"));
+        }
+        b.push(0);
+    });
+}
+
+/// Type OID of a result value
+fn type_oid(value: &DataType) -> u32 {
+    match value {
+        DataType::Boolean(_) => oid::BOOL,
+        DataType::TinyInt(_)
+        | DataType::SmallInt(_)
+        | DataType::Integer(_)
+        | DataType::BigInt(_) => oid::INT8,
+        DataType::Float(_) | DataType::Double(_) => oid::FLOAT8,
+        DataType::Blob(_) => oid::BYTEA,
+        _ => oid::TEXT,
+    }
+}
+
+fn type_size(type_oid: u32) -> i16 {
+    match type_oid {
+        oid::BOOL => 1,
+        oid::INT8 | oid::FLOAT8 => 8,
+        _ => -1,
+    }
+}
+
+fn as_i64(value: &DataType) -> Option<i64> {
+    match value {
+        DataType::TinyInt(n) => Some(i64::from(*n)),
+        DataType::SmallInt(n) => Some(i64::from(*n)),
+        DataType::Integer(n) => Some(i64::from(*n)),
+        DataType::BigInt(n) => Some(*n),
+        _ => None,
+    }
+}
+
+fn as_f64(value: &DataType) -> Option<f64> {
+    match value {
+        DataType::Float(f) => Some(f64::from(*f)),
+        DataType::Double(f) => Some(*f),
+        other => as_i64(other).map(|n| n as f64),
+    }
+}
+
+/// Text form of a value (`None` for NULL)
+fn text_value(value: &DataType) -> Option<String> {
+    Some(match value {
+        DataType::Null => return None,
+        DataType::Boolean(b) => if *b { "t" } else { "f" }.to_string(),
+        DataType::Float(f) => f.to_string(),
+        DataType::Double(f) => f.to_string(),
+        DataType::Blob(bytes) => {
+            let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+            format!("\\x{hex}")
+        }
+        DataType::Char(s)
+        | DataType::Varchar(s)
+        | DataType::Text(s)
+        | DataType::Date(s)
+        | DataType::Time(s)
+        | DataType::Timestamp(s) => s.clone(),
+        other => match as_i64(other) {
+            Some(n) => n.to_string(),
+            None => format!("{other:?}"),
+        },
+    })
+}
+
+/// Wire bytes of `value` in a column of `type_oid` (`None` for NULL)
+fn encode_value(value: &DataType, type_oid: u32, format: i16) -> Option<Vec<u8>> {
+    if format == 0 {
+        return text_value(value).map(String::into_bytes);
+    }
+    match (type_oid, value) {
+        (_, DataType::Null) => None,
+        (oid::BOOL, DataType::Boolean(b)) => Some(vec![u8::from(*b)]),
+        (oid::INT8, v) if as_i64(v).is_some() => as_i64(v).map(|n| n.to_be_bytes().to_vec()),
+        (oid::FLOAT8, v) if as_f64(v).is_some() => as_f64(v).map(|f| f.to_be_bytes().to_vec()),
+        (oid::BYTEA, DataType::Blob(bytes)) => Some(bytes.clone()),
+        // Text is its own binary form; other mismatches fall back to it too.
+        (_, v) => text_value(v).map(String::into_bytes),
+    }
+}
+
+/// A parameter value sent by the client
+fn decode_param(bytes: Option<&[u8]>, format: i16, type_oid: u32) -> Result<DataType, EngineError> {
+    let Some(bytes) = bytes else {
+        return Ok(DataType::Null);
+    };
+    let invalid = |what: &str| {
+        EngineError::new(
+            engine_error_code::PROTOCOL,
+            format!("invalid {what} parameter value"),
+        )
+    };
+    // A parameter of unspecified type is sent as text, whatever the format.
+    if format != 0 && type_oid != oid::UNSPECIFIED {
+        let fixed = |n: usize| -> Result<&[u8], EngineError> {
+            (bytes.len() == n)
+                .then_some(bytes)
+                .ok_or_else(|| invalid("binary"))
+        };
+        return Ok(match type_oid {
+            oid::BOOL => DataType::Boolean(fixed(1)?[0] != 0),
+            oid::INT2 => DataType::BigInt(i16::from_be_bytes(fixed(2)?.try_into().unwrap()).into()),
+            oid::INT4 => DataType::BigInt(i32::from_be_bytes(fixed(4)?.try_into().unwrap()).into()),
+            oid::INT8 => DataType::BigInt(i64::from_be_bytes(fixed(8)?.try_into().unwrap())),
+            oid::FLOAT4 => {
+                DataType::Double(f32::from_be_bytes(fixed(4)?.try_into().unwrap()).into())
+            }
+            oid::FLOAT8 => DataType::Double(f64::from_be_bytes(fixed(8)?.try_into().unwrap())),
+            oid::BYTEA => DataType::Blob(bytes.to_vec()),
+            oid::NUMERIC => {
+                return Err(EngineError::new(
+                    engine_error_code::UNSUPPORTED_SQL,
+                    "binary numeric parameters are not supported; send them as text",
+                ))
+            }
+            _ => {
+                DataType::Varchar(String::from_utf8(bytes.to_vec()).map_err(|_| invalid("UTF-8"))?)
+            }
+        });
+    }
+    let text = std::str::from_utf8(bytes).map_err(|_| invalid("UTF-8"))?;
+    Ok(match type_oid {
+        oid::BOOL => match text.to_ascii_lowercase().as_str() {
+            "t" | "true" | "1" | "on" | "yes" | "y" => DataType::Boolean(true),
+            "f" | "false" | "0" | "off" | "no" | "n" => DataType::Boolean(false),
+            _ => return Err(invalid("boolean")),
+        },
+        oid::INT2 | oid::INT4 | oid::INT8 => {
+            DataType::BigInt(text.trim().parse().map_err(|_| invalid("integer"))?)
+        }
+        oid::FLOAT4 | oid::FLOAT8 | oid::NUMERIC => {
+            DataType::Double(text.trim().parse().map_err(|_| invalid("numeric"))?)
+        }
+        oid::BYTEA => DataType::Blob(decode_bytea(text).ok_or_else(|| invalid("bytea"))?),
+        oid::UNSPECIFIED => match text.parse::<i64>() {
+            Ok(n) if n.to_string() == text => DataType::BigInt(n),
+            _ => DataType::Varchar(text.to_string()),
+        },
+        _ => DataType::Varchar(text.to_string()),
+    })
+}
+
+/// `\x`-hex text form of `bytea`
+fn decode_bytea(text: &str) -> Option<Vec<u8>> {
+    let hex = text.strip_prefix("\\x")?;
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Format code of column or parameter `i`: none given means text, one applies to all.
+fn format_of(formats: &[i16], i: usize) -> i16 {
+    match formats {
+        [] => 0,
+        [all] => *all,
+        each => each.get(i).copied().unwrap_or(0),
+    }
+}
+
+/// Calls `f` with the byte offset and character of every character of `sql` outside string
+/// literals, quoted identifiers and comments.
+fn for_each_code_char(sql: &str, mut f: impl FnMut(usize, char)) {
+    let mut chars = sql.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                while let Some((_, d)) = chars.next() {
+                    if d == '\\' && c == '\'' {
+                        chars.next();
+                    } else if d == c {
+                        break;
+                    }
+                }
+            }
+            '-' if chars.peek().is_some_and(|&(_, d)| d == '-') => {
+                for (_, d) in chars.by_ref() {
+                    if d == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek().is_some_and(|&(_, d)| d == '*') => {
+                chars.next();
+                let mut prev = ' ';
+                for (_, d) in chars.by_ref() {
+                    if prev == '*' && d == '/' {
+                        break;
+                    }
+                    prev = d;
+                }
+            }
+            c => f(i, c),
+        }
+    }
+}
+
+/// Non-empty statements of a simple query
+fn split_statements(sql: &str) -> Vec<&str> {
+    let mut ends = Vec::new();
+    for_each_code_char(sql, |i, c| {
+        if c == ';' {
+            ends.push(i);
+        }
+    });
+    let mut start = 0;
+    let mut statements = Vec::new();
+    for end in ends.into_iter().chain(std::iter::once(sql.len())) {
+        let statement = sql[start..end].trim();
+        if !statement.is_empty() && !is_only_comments(statement) {
+            statements.push(statement);
+        }
+        start = end + 1;
+    }
+    statements
+}
+
+fn is_only_comments(sql: &str) -> bool {
+    let mut code = false;
+    for_each_code_char(sql, |_, c| code |= !c.is_whitespace());
+    !code
+}
+
+fn malformed(why: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, why.to_string())
+}
+
+fn truncated() -> EngineError {
+    EngineError::new(engine_error_code::PROTOCOL, "truncated message")
+}
+
+/// Appends a message of type `kind` whose body `body` writes.
+fn message(out: &mut Vec<u8>, kind: u8, body: impl FnOnce(&mut Vec<u8>)) {
+    out.push(kind);
+    let at = out.len();
+    out.extend_from_slice(&[0; 4]);
+    body(out);
+    let len = (out.len() - at) as i32;
+    out[at..at + 4].copy_from_slice(&len.to_be_bytes());
+}
+
+fn put_i16(out: &mut Vec<u8>, v: i16) {
+    out.extend_from_slice(&v.to_be_bytes());
+}
+
+fn put_i32(out: &mut Vec<u8>, v: i32) {
+    out.extend_from_slice(&v.to_be_bytes());
+}
+
+fn put_cstr(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(s.as_bytes());
+    out.push(0);
+}
+
+/// Reads the fields of a message body.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.data.len() < n {
+            return None;
+        }
+        let (head, rest) = self.data.split_at(n);
+        self.data = rest;
+        Some(head)
+    }
+
+    fn cstr(&mut self) -> Option<String> {
+        let end = self.data.iter().position(|&b| b == 0)?;
+        let s = String::from_utf8_lossy(&self.data[..end]).into_owned();
+        self.data = &self.data[end + 1..];
+        Some(s)
+    }
+
+    fn cstr_or_err(&mut self) -> Result<String, EngineError> {
+        self.cstr().ok_or_else(truncated)
+    }
+
+    fn i16_or_err(&mut self) -> Result<i16, EngineError> {
+        let b = self.take(2).ok_or_else(truncated)?;
+        Ok(i16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn i32_or_err(&mut self) -> Result<i32, EngineError> {
+        let b = self.take(4).ok_or_else(truncated)?;
+        Ok(i32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// A count followed by that many format codes
+    fn formats(&mut self) -> Result<Vec<i16>, EngineError> {
+        let n = self.i16_or_err()?.max(0);
+        (0..n).map(|_| self.i16_or_err()).collect()
+    }
+}
@@ -5,7 +5,10 @@
 //! See `docs/network/stream-models.md`.
 
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::mpsc::{self as sync_mpsc, RecvTimeoutError};
 use std::sync::{Arc, LazyLock};
@@ -17,17 +20,22 @@ use thiserror::Error;
 use tokio::sync::OwnedSemaphorePermit;
 use tracing::{info, info_span, instrument, warn, Instrument};
 
+pub use crate::common::config::{DEFAULT_COMPRESSION_MIN_BYTES, DEFAULT_MAX_PIPELINED_REQUESTS};
+use crate::network::auth::{AuthError, Authentication, Credentials};
+use crate::network::copy_in;
 use crate::network::engine::{
     engine_error_code, EngineError, EngineHandle, EngineOutput, SessionContext,
 };
 use crate::network::framing::{
-    cached_execution_ok_frame_v1, decode_client_frame_v1, encode_execution_ok_frame_write,
-    encode_server_message_v1, encode_server_message_write, ClientMessage, ExecuteScriptPayload,
-    ExecuteTpccPayload, ExecutionOkPayload, FrameHeader, ProtocolError, QueryPayload,
-    ServerMessage, FRAME_HEADER_LEN, MAX_FRAME_PAYLOAD_BYTES, PROTOCOL_VERSION_V1,
-    TPCC_WIRE_KIND_ORDER_STATUS,
+    cached_execution_ok_frame_v1, compress_frame, decode_client_frame_v1,
+    encode_execution_ok_frame_write, encode_server_message_v1, encode_server_message_write,
+    inflated_frame_len, BaseBackupPayload, BinaryResultSetPayload, ClientMessage, ErrorPayload,
+    ExecuteScriptPayload, ExecuteTpccPayload, ExecutionOkPayload, FrameHeader, ProtocolError,
+    QueryPayload, ResultFormat, ServerMessage, StartReplicationPayload, FRAME_HEADER_LEN,
+    MAX_FRAME_PAYLOAD_BYTES, PROTOCOL_VERSION_V1, TPCC_WIRE_KIND_ORDER_STATUS,
 };
 use crate::network::metrics::{QueryHandledOutcome, QuicMetrics};
+use crate::network::replication::{self, ReplicationError};
 
 /// Limits for stream handling (from [`crate::network::server::ServerConfig`]).
 #[derive(Debug, Clone)]
@@ -38,6 +46,15 @@ pub struct StreamPolicy {
     pub max_result_rows: usize,
     /// Max payload bytes accepted per frame on the wire (clamped to protocol max).
     pub max_frame_payload_bytes: u32,
+    /// How long a session may wait for its next frame inside an open transaction before it is
+    /// rolled back and its stream closed (`None`: no limit).
+    pub idle_in_transaction_timeout: Option<Duration>,
+    /// Requests of one stream read and queued ahead of their answers; `1` answers each frame
+    /// before reading the next.
+    pub max_pipelined_requests: usize,
+    /// On a connection that negotiated compression, response payloads at least this large are
+    /// sent lz4-compressed (when that makes them smaller).
+    pub compression_min_bytes: usize,
 }
 
 impl Default for StreamPolicy {
@@ -48,6 +65,9 @@ impl Default for StreamPolicy {
             max_sql_bytes: 1024 * 1024,
             max_result_rows: 65_536,
             max_frame_payload_bytes: MAX_FRAME_PAYLOAD_BYTES,
+            idle_in_transaction_timeout: None,
+            max_pipelined_requests: DEFAULT_MAX_PIPELINED_REQUESTS,
+            compression_min_bytes: DEFAULT_COMPRESSION_MIN_BYTES,
         }
     }
 }
@@ -178,6 +198,11 @@ pub fn dispatch_client_message_with_ctx(
     session_ctx: &mut SessionContext,
     queue_wait_us: Option<u64>,
 ) -> Result<Arc<[u8]>, DispatchError> {
+    if session_ctx.copy_in.is_some()
+        && !matches!(msg, ClientMessage::CopyData(_) | ClientMessage::CopyDone)
+    {
+        return Err(copy_in::interrupt(engine, session_ctx).into());
+    }
     match msg {
         ClientMessage::Query(q) => {
             let span = info_span!(
@@ -186,13 +211,7 @@ pub fn dispatch_client_message_with_ctx(
                 sql = %summarize_sql(&q.sql)
             );
             let _g = span.enter();
-            if q.sql.len() > policy.max_sql_bytes {
-                return Err(EngineError::new(
-                    engine_error_code::SQL_TOO_LONG,
-                    "SQL text exceeds configured max_sql_bytes",
-                )
-                .into());
-            }
+            check_sql_len(&q.sql, policy)?;
 
             // Ultra-hot path: deterministic literal projections without FROM.
             // Serve the already encoded frame to skip engine + postcard encode overhead.
@@ -205,22 +224,7 @@ pub fn dispatch_client_message_with_ctx(
 
             let out = engine.execute_sql(&q.sql, session_ctx)?;
             let out = enforce_max_result_rows(out, policy.max_result_rows)?;
-            let server = out.into_server_message();
-
-            // Encode using a thread-local buffer (still allocates postcard payload internally,
-            // but avoids reallocating the frame buffer each request).
-            let bytes: Arc<[u8]> = TL_ENCODE_BUF.with(|b| {
-                let mut buf = b.borrow_mut();
-                buf.clear();
-                // Keep a small minimum capacity for common tiny responses.
-                let cap = buf.capacity();
-                if cap < 256 {
-                    buf.reserve(256 - cap);
-                }
-                encode_server_message_write(PROTOCOL_VERSION_V1, &server, &mut *buf)?;
-                let owned = std::mem::take(&mut *buf);
-                Ok::<_, DispatchError>(Arc::from(owned.into_boxed_slice()))
-            })?;
+            let bytes = encode_response(&out.into_server_message())?;
             if engine.supports_select_no_from_wire_cache() && likely_select_without_from(&q.sql) {
                 let mut g = SELECT_NO_FROM_WIRE_CACHE.write();
                 if g.len() >= SELECT_NO_FROM_WIRE_CACHE_MAX_ENTRIES && !g.contains_key(&q.sql) {
@@ -232,9 +236,48 @@ pub fn dispatch_client_message_with_ctx(
             }
             Ok(bytes)
         }
+        ClientMessage::QueryWithFormat(q) if q.result_format == ResultFormat::Text => {
+            dispatch_client_message_with_ctx(
+                ClientMessage::Query(QueryPayload { sql: q.sql }),
+                engine,
+                policy,
+                session_ctx,
+                queue_wait_us,
+            )
+        }
+        ClientMessage::QueryWithFormat(q) => {
+            let span = info_span!(
+                "sql.query",
+                sql_len = q.sql.len(),
+                sql = %summarize_sql(&q.sql),
+                result_format = ?q.result_format
+            );
+            let _g = span.enter();
+            check_sql_len(&q.sql, policy)?;
+            let out = engine.execute_sql(&q.sql, session_ctx)?;
+            let server = match enforce_max_result_rows(out, policy.max_result_rows)? {
+                EngineOutput::ResultSet { columns, rows } => ServerMessage::BinaryResultSet(
+                    BinaryResultSetPayload::from_text_rows(columns, &rows),
+                ),
+                out => out.into_server_message(),
+            };
+            encode_response(&server)
+        }
         ClientMessage::ExecuteScript(script) => {
             dispatch_execute_script(script, engine, policy, session_ctx)
         }
+        ClientMessage::CopyIn(copy) => {
+            copy_in::start(engine, session_ctx, copy);
+            Ok(no_answer())
+        }
+        ClientMessage::CopyData(batch) => {
+            let _g = info_span!("sql.copy_data", rows = batch.row_count).entered();
+            copy_in::data(engine, session_ctx, batch)?;
+            Ok(no_answer())
+        }
+        ClientMessage::CopyDone => {
+            encode_response(&copy_in::finish(engine, session_ctx)?.into_server_message())
+        }
         ClientMessage::ExecuteTpcc(tpcc) => {
             dispatch_execute_tpcc(tpcc, engine, policy, session_ctx, queue_wait_us)
         }
@@ -243,9 +286,31 @@ pub fn dispatch_client_message_with_ctx(
             "expected Query frame on this bidirectional stream (ClientHello is not supported here)",
         )
         .into()),
+        ClientMessage::BaseBackup(_) => Err(EngineError::new(
+            engine_error_code::PROTOCOL,
+            "BaseBackup is only served by the QUIC stream handler",
+        )
+        .into()),
+        ClientMessage::Authenticate(_) => Err(EngineError::new(
+            engine_error_code::PROTOCOL,
+            "Authenticate is only accepted as the first frame of a connection",
+        )
+        .into()),
+        ClientMessage::StartReplication(_) | ClientMessage::ReplicationAck(_) => {
+            Err(EngineError::new(
+                engine_error_code::PROTOCOL,
+                "replication frames are only served by the QUIC stream handler",
+            )
+            .into())
+        }
     }
 }
 
+/// Result of a frame that is not answered (`CopyIn`, `CopyData`); the stream writes nothing.
+fn no_answer() -> Arc<[u8]> {
+    Arc::from(Vec::new().into_boxed_slice())
+}
+
 fn dispatch_execute_script(
     script: ExecuteScriptPayload,
     engine: &dyn EngineHandle,
@@ -405,6 +470,34 @@ pub fn dispatch_client_frame(
     dispatch_client_message(msg, engine, policy)
 }
 
+fn check_sql_len(sql: &str, policy: &StreamPolicy) -> Result<(), DispatchError> {
+    if sql.len() > policy.max_sql_bytes {
+        return Err(EngineError::new(
+            engine_error_code::SQL_TOO_LONG,
+            "SQL text exceeds configured max_sql_bytes",
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Encodes a response frame in a thread-local buffer (still allocates the postcard payload
+/// internally, but avoids reallocating the frame buffer each request).
+fn encode_response(server: &ServerMessage) -> Result<Arc<[u8]>, DispatchError> {
+    TL_ENCODE_BUF.with(|b| {
+        let mut buf = b.borrow_mut();
+        buf.clear();
+        // Keep a small minimum capacity for common tiny responses.
+        let cap = buf.capacity();
+        if cap < 256 {
+            buf.reserve(256 - cap);
+        }
+        encode_server_message_write(PROTOCOL_VERSION_V1, server, &mut *buf)?;
+        let owned = std::mem::take(&mut *buf);
+        Ok(Arc::from(owned.into_boxed_slice()))
+    })
+}
+
 fn likely_select_without_from(sql: &str) -> bool {
     let s = sql.trim_start();
     if s.len() < 6 {
@@ -459,16 +552,23 @@ fn enforce_max_result_rows(
 /// Each QUIC stream gets its own [`SessionContext`] on the worker that owns that shard
 /// (`stream_id % worker_count`), so `BEGIN` / `COMMIT` still span frames on one stream.
 pub(crate) struct ConnectionSqlSessions {
+    engine: Arc<dyn EngineHandle>,
     worker_txs: Vec<sync_mpsc::Sender<ConnectionSqlCommand>>,
     worker_count: usize,
     next_stream_id: AtomicU64,
 }
 
+/// Response to one dispatched frame, and whether its session is left inside a transaction.
+struct SessionReply {
+    result: Result<Arc<[u8]>, DispatchError>,
+    in_transaction: bool,
+}
+
 enum ConnectionSqlCommand {
     Dispatch {
         stream_id: u64,
         msg: ClientMessage,
-        reply_tx: sync_mpsc::Sender<Result<Arc<[u8]>, DispatchError>>,
+        reply_tx: sync_mpsc::Sender<SessionReply>,
         queued_at: Instant,
     },
     EndStream {
@@ -488,13 +588,15 @@ impl Drop for StreamSessionGuard {
 }
 
 impl ConnectionSqlSessions {
-    fn new(engine: Arc<dyn EngineHandle>, policy: StreamPolicy) -> Arc<Self> {
+    /// Sessions of a connection authenticated as `user` start with that user's role.
+    fn new(engine: Arc<dyn EngineHandle>, policy: StreamPolicy, user: Option<String>) -> Arc<Self> {
         let worker_count = connection_sql_worker_count();
         let mut worker_txs = Vec::with_capacity(worker_count);
         for worker_idx in 0..worker_count {
             let (job_tx, job_rx) = sync_mpsc::channel::<ConnectionSqlCommand>();
             let engine_worker = engine.clone();
             let policy_worker = policy.clone();
+            let user = user.clone();
             std::thread::Builder::new()
                 .name(format!("rustdb-quic-conn-sql-{worker_idx}"))
                 .spawn(move || {
@@ -521,15 +623,33 @@ impl ConnectionSqlSessions {
                                     tpcc_kind = ?tpcc_kind,
                                 )
                                 .entered();
-                                let ctx = sessions.entry(stream_id).or_default();
-                                let r = dispatch_client_message_with_ctx(
+                                let ctx = match sessions.entry(stream_id) {
+                                    Entry::Occupied(e) => e.into_mut(),
+                                    Entry::Vacant(e) => {
+                                        match open_session(engine_worker.as_ref(), user.as_deref())
+                                        {
+                                            Ok(ctx) => e.insert(ctx),
+                                            Err(err) => {
+                                                let _ = reply_tx.send(SessionReply {
+                                                    result: Err(err.into()),
+                                                    in_transaction: false,
+                                                });
+                                                continue;
+                                            }
+                                        }
+                                    }
+                                };
+                                let result = dispatch_client_message_with_ctx(
                                     msg,
                                     engine_worker.as_ref(),
                                     &policy_worker,
                                     ctx,
                                     Some(queue_wait_us),
                                 );
-                                let _ = reply_tx.send(r);
+                                let _ = reply_tx.send(SessionReply {
+                                    result,
+                                    in_transaction: in_transaction(ctx),
+                                });
                             }
                             ConnectionSqlCommand::EndStream { stream_id } => {
                                 rollback_stream_if_needed(
@@ -554,6 +674,7 @@ impl ConnectionSqlSessions {
             worker_txs.push(job_tx);
         }
         Arc::new(Self {
+            engine,
             worker_txs,
             worker_count,
             next_stream_id: AtomicU64::new(0),
@@ -572,7 +693,7 @@ impl ConnectionSqlSessions {
         &self,
         stream_id: u64,
         msg: ClientMessage,
-        reply_tx: sync_mpsc::Sender<Result<Arc<[u8]>, DispatchError>>,
+        reply_tx: sync_mpsc::Sender<SessionReply>,
         queued_at: Instant,
     ) -> Result<(), ()> {
         self.worker_txs[self.worker_for_stream(stream_id)]
@@ -591,6 +712,22 @@ impl ConnectionSqlSessions {
     }
 }
 
+/// Context of a new session, bound to the connection's authenticated user if any.
+pub(crate) fn open_session(
+    engine: &dyn EngineHandle,
+    user: Option<&str>,
+) -> Result<SessionContext, EngineError> {
+    let mut ctx = SessionContext::default();
+    if let Some(user) = user {
+        engine.assign_role(&mut ctx, user)?;
+    }
+    Ok(ctx)
+}
+
+pub(crate) fn in_transaction(ctx: &SessionContext) -> bool {
+    ctx.transaction.is_some() || ctx.cluster_transaction.is_some()
+}
+
 fn rollback_stream_if_needed(
     sessions: &mut HashMap<u64, SessionContext>,
     stream_id: u64,
@@ -600,7 +737,7 @@ fn rollback_stream_if_needed(
     let Some(mut ctx) = sessions.remove(&stream_id) else {
         return;
     };
-    if ctx.transaction.is_some() {
+    if in_transaction(&ctx) {
         let _ = dispatch_client_message_with_ctx(
             ClientMessage::Query(QueryPayload {
                 sql: "ROLLBACK".to_string(),
@@ -630,7 +767,24 @@ async fn write_error_response(
     Ok(len)
 }
 
-/// One bidirectional stream: read one request frame, run engine (with timeout), write one response.
+/// Frames served per bidirectional stream before the server finishes it (`CopyData` frames
+/// excepted).
+///
+/// Variant A compatibility: old clients use one query per stream; newer clients may send
+/// multiple frames on the same stream for better throughput.
+pub const MAX_FRAMES_PER_STREAM: usize = 1024;
+
+/// One bidirectional stream: read request frames, run them in order on the stream's session
+/// (each with its own timeout) and write one response per frame, in the same order. A COPY
+/// (`CopyIn`, `CopyData`… , `CopyDone`) gets one response, to its `CopyDone`.
+///
+/// Requests are pipelined: up to [`StreamPolicy::max_pipelined_requests`] frames are read and
+/// queued on the session while earlier ones still run, so a client need not wait for each
+/// answer. The per-query timeout of a request starts once the answer before it is written.
+///
+/// A session left inside a transaction must send its next frame within
+/// [`StreamPolicy::idle_in_transaction_timeout`]; otherwise it is reaped: rolled back, told
+/// so with an `IDLE_IN_TRANSACTION_TIMEOUT` error frame, and its stream closed.
 ///
 /// Parent span **`network.query_stream`** groups per-stream work in `tracing` / Chrome traces;
 /// nested spans include `network.read_frame`, `network.queue_wait`, `dispatch_client_frame`,
@@ -642,155 +796,479 @@ async fn write_error_response(
 )]
 pub(crate) async fn handle_query_bidi_stream(
     mut send: SendStream,
-    mut recv: RecvStream,
+    recv: RecvStream,
     conn_sessions: Arc<ConnectionSqlSessions>,
     policy: Arc<StreamPolicy>,
     metrics: Option<QuicMetrics>,
+    compressed: bool,
     _permit: OwnedSemaphorePermit,
 ) {
     let _keep_permit = _permit;
     let max_frame = policy.max_frame_payload_bytes.min(MAX_FRAME_PAYLOAD_BYTES);
+    let max_pipelined = policy.max_pipelined_requests.max(1);
     let stream_id = conn_sessions.alloc_stream_id();
-    let _stream_guard = StreamSessionGuard {
+    let stream_guard = StreamSessionGuard {
         sessions: conn_sessions.clone(),
         stream_id,
     };
 
-    // Variant A compatibility: old clients use one query per stream; newer clients may send
-    // multiple frames on the same stream for better throughput.
-    const MAX_FRAMES_PER_STREAM: usize = 1024;
+    // Requests read and dispatched, oldest first; the oldest one's answer is awaited by `answer`.
+    let mut pending: VecDeque<PendingRequest> = VecDeque::new();
+    let mut answer: Option<ReplyFuture> = None;
+    let mut reading = Some(read_frame_owned(recv, max_frame, Vec::new()));
+    let mut frames_read = 0;
+    // A base backup or replication request, served once the requests before it are answered.
+    let mut takeover: Option<(ClientMessage, RecvStream)> = None;
+    let mut in_transaction = false;
 
-    let mut frame_buf = Vec::new();
+    loop {
+        if answer.is_none() {
+            if let Some(request) = pending.pop_front() {
+                answer = Some(request.into_reply(policy.query_timeout, in_transaction));
+            }
+        }
+        if answer.is_none() && reading.is_none() {
+            break;
+        }
+        let can_read =
+            reading.is_some() && pending.len() + usize::from(answer.is_some()) < max_pipelined;
+        let idle_limit = match policy.idle_in_transaction_timeout {
+            Some(limit) if in_transaction && answer.is_none() => Some(limit),
+            _ => None,
+        };
 
-    for _ in 0..MAX_FRAMES_PER_STREAM {
-        // Includes socket/stream wait time (dominant under load). Helps distinguish pure compute spans below.
-        let read_res = read_application_frame_into(&mut recv, max_frame, &mut frame_buf)
-            .instrument(tracing::info_span!("network.read_frame"))
-            .await;
-        let queued_at = match read_res {
-            Ok(()) => Instant::now(),
-            Err(ReadFrameError::Recv(_)) => {
-                // Client closed the stream (EOF / reset). Treat as graceful end-of-stream.
-                let _ = send.finish();
-                return;
+        tokio::select! {
+            biased;
+            (reply, frame_len, t0) = async { answer.as_mut().expect("guarded").await }, if answer.is_some() => {
+                answer = None;
+                let result = match reply {
+                    Ok(reply) => {
+                        in_transaction = reply.in_transaction;
+                        reply.result
+                    }
+                    Err(e) => Err(e),
+                };
+                let latency_ns = t0.elapsed().as_nanos().min(u128::from(u64::MAX)) as u64;
+                let record_metrics = |outcome: QueryHandledOutcome, bytes_out: u64| {
+                    if let Some(m) = metrics.as_ref() {
+                        let ms = latency_ns / 1_000_000;
+                        crate::debug::record_network_query_latency_ms(ms);
+                        m.record_query_handled(outcome, frame_len, bytes_out, latency_ns);
+                    }
+                };
+                match result {
+                    // `CopyIn` / `CopyData`: the COPY is answered once, at `CopyDone`.
+                    Ok(bytes) if bytes.is_empty() => record_metrics(QueryHandledOutcome::Ok, 0),
+                    Ok(bytes) => {
+                        let bytes = match compressed
+                            .then(|| compress_frame(&bytes, policy.compression_min_bytes))
+                            .flatten()
+                        {
+                            Some(smaller) => {
+                                if let Some(m) = metrics.as_ref() {
+                                    m.record_frame_compressed(
+                                        bytes.len() as u64,
+                                        smaller.len() as u64,
+                                    );
+                                }
+                                Arc::from(smaller.into_boxed_slice())
+                            }
+                            None => bytes,
+                        };
+                        let out_len = bytes.len() as u64;
+                        // Includes QUIC send backpressure/wait; encode cost is tracked inside dispatch (TLS buffer).
+                        if let Err(e) = async { send.write_all(bytes.as_ref()).await }
+                            .instrument(tracing::info_span!("network.write_response", out_len))
+                            .await
+                        {
+                            warn!(error = %e, "write response failed");
+                            record_metrics(QueryHandledOutcome::WriteFailed, 0);
+                            return;
+                        }
+                        record_metrics(QueryHandledOutcome::Ok, out_len);
+                    }
+                    Err(ref e) => {
+                        match write_error_response(&mut send, e)
+                            .instrument(tracing::info_span!("network.write_response"))
+                            .await
+                        {
+                            Ok(len) => record_metrics(QueryHandledOutcome::ErrorResponse, len),
+                            Err(_) => {
+                                let _ = send.reset(quinn::VarInt::from_u32(0));
+                                record_metrics(QueryHandledOutcome::WriteFailed, 0);
+                                return;
+                            }
+                        }
+                    }
+                }
             }
-            Err(e) => {
-                warn!(error = %e, "failed to read request frame");
+            (recv, frame_buf, read_res) = async { reading.as_mut().expect("guarded").await }, if can_read => {
+                reading = None;
+                let queued_at = match read_res {
+                    Ok(()) => Instant::now(),
+                    // Client closed the stream (EOF / reset): answer what is queued, then end.
+                    Err(ReadFrameError::Recv(_)) => continue,
+                    Err(e) => {
+                        warn!(error = %e, "failed to read request frame");
+                        if let Some(m) = metrics.as_ref() {
+                            m.record_read_frame_error();
+                        }
+                        let _ = send.reset(quinn::VarInt::from_u32(0));
+                        return;
+                    }
+                };
+                if let (Some(m), Some(inflated)) = (metrics.as_ref(), inflated_frame_len(&frame_buf)) {
+                    m.record_frame_decompressed(inflated as u64, frame_buf.len() as u64);
+                }
+
+                // Decode cost (no network wait) so we can compare with `network.read_frame`.
+                // Kept outside `dispatch_client_frame` for time-slicing in Chrome traces.
+                let decode_span = info_span!("network.decode_frame", frame_len = frame_buf.len());
+                let decoded = decode_span.in_scope(|| decode_client_frame_v1(&frame_buf));
+                let frame_len = frame_buf.len() as u64;
+                // A COPY may carry any number of data frames; they do not count against the cap.
+                if !matches!(decoded, Ok(ClientMessage::CopyData(_))) {
+                    frames_read += 1;
+                }
+                let request = match decoded {
+                    Ok(msg @ (ClientMessage::BaseBackup(_) | ClientMessage::StartReplication(_))) => {
+                        // These answer with many frames and own the rest of the stream.
+                        takeover = Some((msg, recv));
+                        continue;
+                    }
+                    Err(e) => PendingRequest::ready(Err(e.into()), frame_len),
+                    Ok(msg) => {
+                        let (reply_tx, reply_rx) = sync_mpsc::channel::<SessionReply>();
+                        if conn_sessions
+                            .dispatch(stream_id, msg, reply_tx, queued_at)
+                            .is_err()
+                        {
+                            PendingRequest::ready(
+                                Err(EngineError::new(
+                                    engine_error_code::INTERNAL,
+                                    "connection sql worker disconnected",
+                                )
+                                .into()),
+                                frame_len,
+                            )
+                        } else {
+                            PendingRequest {
+                                reply: PendingReply::Dispatched(reply_rx),
+                                frame_len,
+                                t0: Instant::now(),
+                            }
+                        }
+                    }
+                };
+                pending.push_back(request);
+                // Abuse guard: past the per-stream maximum, answer what was read and finish.
+                if frames_read < MAX_FRAMES_PER_STREAM {
+                    reading = Some(read_frame_owned(recv, max_frame, frame_buf));
+                }
+            }
+            _ = tokio::time::sleep(idle_limit.unwrap_or_default()), if idle_limit.is_some() => {
+                let limit = idle_limit.unwrap_or_default();
+                warn!(
+                    stream_id,
+                    idle_ms = limit.as_millis() as u64,
+                    "reaping session idle in transaction"
+                );
+                // Roll back before answering, so the locks are free once the client hears of it.
+                drop(stream_guard);
                 if let Some(m) = metrics.as_ref() {
-                    m.record_read_frame_error();
+                    m.record_idle_in_transaction_reaped();
                 }
-                let _ = send.reset(quinn::VarInt::from_u32(0));
+                let err = DispatchError::Engine(EngineError::new(
+                    engine_error_code::IDLE_IN_TRANSACTION_TIMEOUT,
+                    format!(
+                        "terminating session: idle in transaction for more than {} ms; the transaction was rolled back",
+                        limit.as_millis()
+                    ),
+                ));
+                let _ = write_error_response(&mut send, &err).await;
+                let _ = send.finish();
+                drop(reading);
                 return;
             }
-        };
+        }
+    }
+
+    match takeover {
+        Some((ClientMessage::BaseBackup(req), _recv)) => {
+            serve_base_backup(&mut send, conn_sessions.engine.clone(), req).await;
+        }
+        Some((ClientMessage::StartReplication(req), recv)) => {
+            // Replication streams until the replica goes away.
+            serve_replication(&mut send, recv, conn_sessions.engine.clone(), req).await;
+        }
+        _ => {}
+    }
+    let _ = send.finish();
+}
+
+/// Answer to one request: the session's reply, the request frame size and when it was dispatched.
+type ReplyFuture =
+    Pin<Box<dyn Future<Output = (Result<SessionReply, DispatchError>, u64, Instant)> + Send>>;
 
-        // Decode cost (no network wait) so we can compare with `network.read_frame`.
-        // Kept outside `dispatch_client_frame` for time-slicing in Chrome traces.
-        let decode_span = info_span!("network.decode_frame", frame_len = frame_buf.len());
-        let decoded = decode_span.in_scope(|| decode_client_frame_v1(&frame_buf));
-
-        let t0 = Instant::now();
-        let timeout_dur = policy.query_timeout;
-
-        let result: Result<Arc<[u8]>, DispatchError> = match decoded {
-            Err(e) => Err(e.into()),
-            Ok(msg) => {
-                let (reply_tx, reply_rx) = sync_mpsc::channel::<Result<Arc<[u8]>, DispatchError>>();
-                if conn_sessions
-                    .dispatch(stream_id, msg, reply_tx, queued_at)
-                    .is_err()
-                {
-                    Err(EngineError::new(
-                        engine_error_code::INTERNAL,
-                        "connection sql worker disconnected",
+/// A request read from a stream and not answered yet.
+struct PendingRequest {
+    reply: PendingReply,
+    frame_len: u64,
+    t0: Instant,
+}
+
+enum PendingReply {
+    /// Queued on the session's worker.
+    Dispatched(sync_mpsc::Receiver<SessionReply>),
+    /// Answered without reaching the session (undecodable frame, worker gone).
+    Ready(Result<Arc<[u8]>, DispatchError>),
+}
+
+impl PendingRequest {
+    fn ready(result: Result<Arc<[u8]>, DispatchError>, frame_len: u64) -> Self {
+        Self {
+            reply: PendingReply::Ready(result),
+            frame_len,
+            t0: Instant::now(),
+        }
+    }
+
+    /// Waits up to `timeout` for the reply; requests that never reached the session leave
+    /// `in_transaction` as it was.
+    fn into_reply(self, timeout: Duration, in_transaction: bool) -> ReplyFuture {
+        let Self {
+            reply,
+            frame_len,
+            t0,
+        } = self;
+        Box::pin(async move {
+            let reply = match reply {
+                PendingReply::Ready(result) => Ok(SessionReply {
+                    result,
+                    in_transaction,
+                }),
+                PendingReply::Dispatched(reply_rx) => {
+                    let joined = tokio::task::spawn_blocking(
+                        move || -> Result<SessionReply, DispatchError> {
+                            match reply_rx.recv_timeout(timeout) {
+                                Ok(reply) => Ok(reply),
+                                Err(RecvTimeoutError::Timeout) => Err(EngineError::new(
+                                    engine_error_code::QUERY_TIMEOUT,
+                                    "query exceeded per-query timeout",
+                                )
+                                .into()),
+                                Err(RecvTimeoutError::Disconnected) => Err(EngineError::new(
+                                    engine_error_code::INTERNAL,
+                                    "connection sql worker disconnected before reply",
+                                )
+                                .into()),
+                            }
+                        },
                     )
-                    .into())
-                } else {
-                    let join_result = tokio::task::spawn_blocking(move || {
-                        match reply_rx.recv_timeout(timeout_dur) {
-                            Ok(dispatch_res) => dispatch_res,
-                            Err(RecvTimeoutError::Timeout) => Err(EngineError::new(
-                                engine_error_code::QUERY_TIMEOUT,
-                                "query exceeded per-query timeout",
-                            )
-                            .into()),
-                            Err(RecvTimeoutError::Disconnected) => Err(EngineError::new(
-                                engine_error_code::INTERNAL,
-                                "connection sql worker disconnected before reply",
-                            )
-                            .into()),
-                        }
-                    })
                     .await;
-                    match join_result {
-                        Ok(r) => r,
+                    match joined {
+                        Ok(reply) => reply,
                         Err(e) => Err(DispatchError::Engine(EngineError::new(
                             engine_error_code::INTERNAL,
                             format!("spawn_blocking join: {e}"),
                         ))),
                     }
                 }
-            }
-        };
+            };
+            (reply, frame_len, t0)
+        })
+    }
+}
 
-        let latency_ns = t0.elapsed().as_nanos().min(u128::from(u64::MAX)) as u64;
-        let frame_len = frame_buf.len() as u64;
-        let record_metrics = |outcome: QueryHandledOutcome, bytes_out: u64| {
-            if let Some(m) = metrics.as_ref() {
-                let ms = latency_ns / 1_000_000;
-                crate::debug::record_network_query_latency_ms(ms);
-                m.record_query_handled(outcome, frame_len, bytes_out, latency_ns);
-            }
-        };
+/// Next request frame; the stream and buffer travel with the future so the handler can keep
+/// it across loop turns while answers are written.
+type ReadFuture =
+    Pin<Box<dyn Future<Output = (RecvStream, Vec<u8>, Result<(), ReadFrameError>)> + Send>>;
 
-        match result {
-            Ok(bytes) => {
-                let out_len = bytes.len() as u64;
-                // Includes QUIC send backpressure/wait; encode cost is tracked inside dispatch (TLS buffer).
-                if let Err(e) = async { send.write_all(bytes.as_ref()).await }
-                    .instrument(tracing::info_span!("network.write_response", out_len))
-                    .await
-                {
-                    warn!(error = %e, "write response failed");
-                    record_metrics(QueryHandledOutcome::WriteFailed, 0);
-                    return;
-                }
-                record_metrics(QueryHandledOutcome::Ok, out_len);
-            }
-            Err(ref e) => {
-                match write_error_response(&mut send, e)
-                    .instrument(tracing::info_span!("network.write_response"))
-                    .await
-                {
-                    Ok(len) => record_metrics(QueryHandledOutcome::ErrorResponse, len),
-                    Err(_) => {
-                        let _ = send.reset(quinn::VarInt::from_u32(0));
-                        record_metrics(QueryHandledOutcome::WriteFailed, 0);
-                        return;
-                    }
-                }
-            }
+fn read_frame_owned(mut recv: RecvStream, max_frame: u32, mut frame_buf: Vec<u8>) -> ReadFuture {
+    Box::pin(async move {
+        // Includes socket/stream wait time (dominant under load). Helps distinguish pure compute spans below.
+        let res = read_application_frame_into(&mut recv, max_frame, &mut frame_buf)
+            .instrument(tracing::info_span!("network.read_frame"))
+            .await;
+        (recv, frame_buf, res)
+    })
+}
+
+/// Stages a base backup off the async workers, then streams it (see [`crate::network::replication`]).
+async fn serve_base_backup(
+    send: &mut SendStream,
+    engine: Arc<dyn EngineHandle>,
+    req: BaseBackupPayload,
+) {
+    let BaseBackupPayload { label, since } = req;
+    let staged = tokio::task::spawn_blocking(move || {
+        let mut backup = engine.base_backup()?;
+        if let Some(base) = since {
+            backup.make_incremental(&base).map_err(|e| {
+                EngineError::new(
+                    engine_error_code::INTERNAL,
+                    format!("incremental backup: {e}"),
+                )
+            })?;
+        }
+        Ok(backup)
+    })
+    .await
+    .unwrap_or_else(|e| {
+        Err(EngineError::new(
+            engine_error_code::INTERNAL,
+            format!("spawn_blocking join: {e}"),
+        ))
+    });
+    let backup = match staged {
+        Ok(b) => b,
+        Err(e) => {
+            let _ = write_error_response(send, &DispatchError::Engine(e)).await;
+            return;
+        }
+    };
+    match replication::send_base_backup(send, &backup).await {
+        Ok(end) => info!(
+            %label,
+            files = end.files,
+            bytes = end.bytes,
+            start_lsn = end.start_lsn,
+            "base backup sent"
+        ),
+        Err(e) => {
+            warn!(%label, error = %e, "base backup failed");
+            let err = EngineError::new(engine_error_code::INTERNAL, format!("base backup: {e}"));
+            let _ = write_error_response(send, &DispatchError::Engine(err)).await;
         }
     }
+    // Removing the staged copy is blocking file I/O.
+    let _ = tokio::task::spawn_blocking(move || drop(backup)).await;
+}
 
-    // Abuse guard: we processed the per-stream maximum; close the send side.
+/// Streams committed transactions to a replica (see [`crate::network::replication`]).
+async fn serve_replication(
+    send: &mut SendStream,
+    recv: RecvStream,
+    engine: Arc<dyn EngineHandle>,
+    req: StartReplicationPayload,
+) {
+    let label = req.label;
+    info!(%label, start_lsn = req.start_lsn, "replication stream started");
+    match replication::stream_committed_changes(send, recv, engine, req.start_lsn, &label).await {
+        Ok(sent_lsn) => info!(%label, sent_lsn, "replication stream ended"),
+        Err(ReplicationError::Server { code, message }) => {
+            let err = EngineError::new(code, message);
+            let _ = write_error_response(send, &DispatchError::Engine(err)).await;
+        }
+        Err(e) => warn!(%label, error = %e, "replication stream failed"),
+    }
+}
+
+/// Reads the `Authenticate` frame a connection must open with (on its first bidirectional
+/// stream, within `timeout`) and checks it against `authentication`. Answers `ExecutionOk` and
+/// returns the user, or answers a generic `AUTHENTICATION_FAILED` error (the reason is only
+/// logged) and returns `None`; the caller then closes the connection.
+pub async fn authenticate_connection(
+    connection: &Connection,
+    authentication: Arc<Authentication>,
+    policy: &StreamPolicy,
+    timeout: Duration,
+) -> Option<String> {
+    let remote = connection.remote_address();
+    let (mut send, mut recv) = match tokio::time::timeout(timeout, connection.accept_bi()).await {
+        Ok(Ok(streams)) => streams,
+        Ok(Err(e)) => {
+            info!(%remote, error = %e, "connection ended before authenticating");
+            return None;
+        }
+        Err(_) => {
+            warn!(%remote, "no Authenticate frame within {} ms", timeout.as_millis());
+            return None;
+        }
+    };
+    let frame = match tokio::time::timeout(
+        timeout,
+        read_application_frame(&mut recv, policy.max_frame_payload_bytes),
+    )
+    .await
+    {
+        Ok(Ok(frame)) => frame,
+        Ok(Err(e)) => {
+            warn!(%remote, error = %e, "failed to read the Authenticate frame");
+            return None;
+        }
+        Err(_) => {
+            warn!(%remote, "no Authenticate frame within {} ms", timeout.as_millis());
+            return None;
+        }
+    };
+    let (credentials, outcome) = match decode_client_frame_v1(&frame) {
+        Ok(ClientMessage::Authenticate(p)) => {
+            let credentials = Credentials {
+                user: p.user,
+                secret: p.secret,
+                remote: remote.ip(),
+            };
+            let checked = credentials.clone();
+            let outcome =
+                tokio::task::spawn_blocking(move || authentication.authenticate(&checked))
+                    .await
+                    .unwrap_or_else(|e| Err(AuthError::Unavailable(e.to_string())));
+            (Some(credentials), outcome)
+        }
+        Ok(_) => (
+            None,
+            Err(AuthError::Rejected(
+                "the first frame is not Authenticate".to_string(),
+            )),
+        ),
+        Err(e) => (None, Err(AuthError::Rejected(e.to_string()))),
+    };
+    let user = credentials.as_ref().map_or("", |c| c.user.as_str());
+    let reply = match &outcome {
+        Ok(()) => {
+            info!(%remote, user, "connection authenticated");
+            ServerMessage::ExecutionOk(ExecutionOkPayload { rows_affected: 0 })
+        }
+        Err(e) => {
+            warn!(%remote, user, reason = %e, "authentication failed");
+            ServerMessage::Error(ErrorPayload {
+                code: engine_error_code::AUTHENTICATION_FAILED,
+                message: format!("authentication failed for user \"{user}\""),
+            })
+        }
+    };
+    if let Ok(bytes) = encode_server_message_v1(&reply) {
+        let _ = send.write_all(&bytes).await;
+    }
     let _ = send.finish();
+    if outcome.is_err() {
+        // The caller closes the connection next: give the client a chance to read the reply.
+        let _ = tokio::time::timeout(timeout, send.stopped()).await;
+    }
+    outcome.ok().and(credentials).map(|c| c.user)
 }
 
 /// Accept bidirectional streams on `connection` until closed (Variant A).
 ///
 /// The semaphore capacity matches [`StreamPolicy::max_concurrent_streams_per_connection`], which is
 /// kept in sync with QUIC `max_concurrent_bidi_streams` via [`crate::network::transport::build_rustdb_transport_config`].
+/// Sessions of a connection authenticated as `user` (see [`authenticate_connection`]) hold that
+/// user's role. On a `compressed` connection (see
+/// [`crate::network::server::negotiated_compression`]) large responses are sent lz4-compressed.
 pub async fn run_connection_streams(
     connection: Connection,
     engine: Arc<dyn EngineHandle>,
     policy: Arc<StreamPolicy>,
     metrics: Option<QuicMetrics>,
+    user: Option<String>,
+    compressed: bool,
 ) {
     let max = policy.max_concurrent_streams_per_connection.max(1);
     let sem = Arc::new(tokio::sync::Semaphore::new(max));
     let remote = connection.remote_address();
-    let conn_sessions = ConnectionSqlSessions::new(engine.clone(), (*policy).clone());
+    let conn_sessions = ConnectionSqlSessions::new(engine.clone(), (*policy).clone(), user);
 
     loop {
         // Includes waiting for the peer to open a stream.
@@ -833,7 +1311,7 @@ pub async fn run_connection_streams(
         let pol = policy.clone();
         let m = metrics.clone();
         tokio::spawn(async move {
-            handle_query_bidi_stream(send, recv, sessions, pol, m, permit).await;
+            handle_query_bidi_stream(send, recv, sessions, pol, m, compressed, permit).await;
         });
     }
 }
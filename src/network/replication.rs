@@ -0,0 +1,2246 @@
+//! Replication protocol: base backups for provisioning replicas, then logical WAL streaming.
+//!
+//! A new replica opens a bidirectional stream and sends one [`ClientMessage::BaseBackup`] frame.
+//! The server stages a consistent copy of its data directory ([`BaseBackup`], produced by
+//! [`EngineHandle::base_backup`]) and streams it back on the same stream as
+//! [`ServerMessage::BackupChunk`] frames followed by one [`ServerMessage::BackupEnd`] that carries
+//! the WAL position contained in the copy. [`fetch_base_backup`] writes the files into an empty
+//! directory, which then opens as an ordinary data directory: WAL recovery on open undoes the
+//! transactions that were still in flight while the copy was taken. The end frame also carries a
+//! SHA-256 per file; the received files must match them before the [`BackupManifest`] is written,
+//! and [`restore_base_backup`] checks a kept backup against that manifest again (optionally
+//! signed) before copying anything into a data directory.
+//!
+//! The manifest also records a digest per [`BACKUP_BLOCK_BYTES`] block of every file. Pages carry
+//! no LSN, so [`fetch_incremental_backup`] sends these digests of an earlier backup and the
+//! server answers with the blocks whose contents changed since (file-granular for new files,
+//! nothing for unchanged ones); [`restore_backup_chain`] layers such incrementals over their full
+//! backup.
+//!
+//! [`push_backup`] uploads a kept backup to [`RemoteStorage`] (a directory, or an S3 bucket with
+//! feature `s3`) and [`pull_backup`] downloads and verifies it again.
+//!
+//! [`run_replica`] keeps such a directory up to date: it sends [`ClientMessage::StartReplication`]
+//! from the recorded WAL position and the server answers with every transaction committed since,
+//! decoded from its WAL ([`EngineHandle::committed_changes`]), as
+//! [`ServerMessage::ReplicatedTransaction`] frames. The replica replays each one as SQL in a
+//! transaction of its own and records the commit LSN, so a restarted replica resumes where it
+//! stopped. With [`ReplicaConfig::apply_delay`] the replica stays that far behind the primary
+//! (a "time machine" for undoing a bad `DELETE`: [`ReplicaStatus::pause`] it before the change is
+//! due and read the old rows back). Stream flow control holds the primary back while the replica
+//! waits, so delayed transactions are not buffered in memory.
+//!
+//! The replica acknowledges each applied commit LSN with a [`ClientMessage::ReplicationAck`] on
+//! the same stream. The primary tracks these per replica ([`ReplicationTracker`]): with
+//! [`SynchronousCommitConfig`] a `COMMIT` returns only once a quorum of the named replicas has
+//! acknowledged it, or when [`SynchronousCommitConfig::timeout`] runs out (then
+//! [`SyncTimeoutPolicy`] decides whether the client sees success or an error; the transaction is
+//! committed on the primary either way).
+//!
+//! Two writable nodes can follow each other ([`ReplicaConfig::conflict_resolver`]). Commits that
+//! replay a peer's transaction are marked in the WAL with the peer's commit time, so each node
+//! skips its own transactions coming back. Each node also tracks the latest version of every row
+//! from its own WAL. When a peer's change expects a different version than the local one, both
+//! nodes wrote the row and a [`ConflictResolver`] picks the outcome ([`LastWriterWins`] by
+//! default in the CLI).
+//!
+//! Rows are matched by their old values: `UPDATE` and `DELETE` on a replica touch every row equal
+//! to the primary's row image (one row, for tables with a primary key).
+//!
+//! [`EngineHandle::base_backup`]: crate::network::engine::EngineHandle::base_backup
+//! [`EngineHandle::committed_changes`]: crate::network::engine::EngineHandle::committed_changes
+
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use quinn::{Connection, RecvStream, SendStream};
+use ring::{digest, hmac};
+use thiserror::Error;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::common::config::ReplicationConfig;
+use crate::logging::log_record::LogRecord;
+use crate::network::engine::{EngineHandle, SessionContext};
+use crate::network::framing::{
+    decode_client_frame_v1, decode_server_frame_v1, encode_client_message_v1,
+    encode_server_message_v1, BackupChunkPayload, BackupEndPayload, BackupFileBlocks,
+    BackupFileDigest, BaseBackupPayload, ClientMessage, EncodeError, IncrementalBasePayload,
+    IncrementalEndPayload, ProtocolError, ReplicatedColumn, ReplicatedTransactionPayload,
+    ReplicationAckPayload, RowChangePayload, ServerMessage, StartReplicationPayload,
+    MAX_FRAME_PAYLOAD_BYTES,
+};
+use crate::network::query_stream::{read_application_frame_into, ReadFrameError};
+use crate::storage::remote::{RemoteStorage, RemoteStorageError};
+
+/// Bytes of file data per [`ServerMessage::BackupChunk`] frame.
+pub const BACKUP_CHUNK_BYTES: usize = 1024 * 1024;
+
+/// Where base backups are staged while they stream, relative to the data directory.
+const STAGING_DIR: &str = ".rustdb/base_backup";
+
+/// LSN a replica resumes streaming after, relative to its data directory.
+const REPLICA_LSN_FILE: &str = ".rustdb/replica_lsn";
+
+/// [`BackupManifest`] of a replica, relative to its data directory.
+const BACKUP_MANIFEST_FILE: &str = ".rustdb/backup_manifest.json";
+
+/// Unit incremental backups compare and copy: one heap page.
+pub const BACKUP_BLOCK_BYTES: usize = crate::storage::database_file::PAGE_SIZE;
+
+/// Suffix of a file sent as a page delta by an incremental backup.
+const DELTA_SUFFIX: &str = ".delta";
+
+/// First bytes of a page delta: the magic, then the file's length as a little-endian `u64`, then
+/// `(page index: u64 LE, page bytes)` for every changed page (the last page may be short).
+const DELTA_MAGIC: &[u8; 8] = b"RDBDELTA";
+
+/// How often the server looks for new commits while the replica is caught up.
+const STREAM_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How often a paused replica checks whether it was resumed.
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+static NEXT_STAGING_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Failure while sending or receiving a base backup or the replication stream.
+#[derive(Debug, Error)]
+pub enum ReplicationError {
+    #[error("I/O: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("encode: {0}")]
+    Encode(#[from] EncodeError),
+    #[error("frame protocol: {0}")]
+    Protocol(#[from] ProtocolError),
+    #[error("QUIC connection: {0}")]
+    Connection(#[from] quinn::ConnectionError),
+    #[error("write stream: {0}")]
+    Write(#[from] quinn::WriteError),
+    #[error("read frame: {0}")]
+    ReadFrame(String),
+    /// The server answered with an `Error` frame.
+    #[error("server error {code}: {message}")]
+    Server { code: u32, message: String },
+    /// The replica could not replay a transaction; replication stops before it.
+    #[error("apply commit LSN {commit_lsn}: {message}")]
+    Apply { commit_lsn: u64, message: String },
+    #[error("remote storage: {0}")]
+    Remote(#[from] RemoteStorageError),
+    #[error("{0}")]
+    Invalid(String),
+}
+
+impl From<ReadFrameError> for ReplicationError {
+    fn from(e: ReadFrameError) -> Self {
+        ReplicationError::ReadFrame(e.to_string())
+    }
+}
+
+/// Staged copy of a data directory, removed when dropped.
+#[derive(Debug)]
+pub struct BaseBackup {
+    root: PathBuf,
+    files: Vec<String>,
+    first_lsn: u64,
+    start_lsn: u64,
+    incremental: Option<IncrementalEndPayload>,
+}
+
+impl BaseBackup {
+    /// Staged files, relative to [`Self::root`] and `/`-separated.
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    /// Highest WAL LSN in the copy (`0` without WAL).
+    pub fn start_lsn(&self) -> u64 {
+        self.start_lsn
+    }
+
+    /// Lowest WAL LSN in the copy (`0` without WAL).
+    pub fn first_lsn(&self) -> u64 {
+        self.first_lsn
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Turns the staged copy into an incremental backup over `base`: files still matching the
+    /// base's checksum are dropped, files the base had are replaced by their changed pages
+    /// (`<path>.delta`), new files stay whole.
+    pub(crate) fn make_incremental(
+        &mut self,
+        base: &IncrementalBasePayload,
+    ) -> Result<(), ReplicationError> {
+        if base.start_lsn > self.start_lsn {
+            return Err(ReplicationError::Invalid(format!(
+                "incremental base at LSN {} is ahead of this server (LSN {})",
+                base.start_lsn, self.start_lsn
+            )));
+        }
+        let mut state = Vec::with_capacity(self.files.len());
+        let mut deltas = Vec::new();
+        let mut files = Vec::with_capacity(self.files.len());
+        for rel in &self.files {
+            let path = self.root.join(rel);
+            let (size, sha256) = digest_file(std::fs::File::open(&path)?, None)?;
+            state.push(BackupFileDigest {
+                path: rel.clone(),
+                size,
+                sha256,
+            });
+            let delta = format!("{rel}{DELTA_SUFFIX}");
+            match base.files.iter().find(|f| &f.path == rel) {
+                Some(old) if old.sha256 == sha256 => {}
+                Some(old) if !self.files.contains(&delta) => {
+                    write_delta(&path, &self.root.join(&delta), &old.blocks)?;
+                    std::fs::remove_file(&path)?;
+                    deltas.push(rel.clone());
+                    files.push(delta);
+                }
+                _ => files.push(rel.clone()),
+            }
+        }
+        self.files = files;
+        self.incremental = Some(IncrementalEndPayload {
+            base_start_lsn: base.start_lsn,
+            state,
+            deltas,
+        });
+        Ok(())
+    }
+}
+
+impl Drop for BaseBackup {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+/// Summary of a base backup written by [`fetch_base_backup`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BaseBackupInfo {
+    pub start_lsn: u64,
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// Written by [`fetch_base_backup`] once every file of the backup is synced and matches the
+/// server's checksums; its presence marks a complete copy, and [`verify_backup`] checks the copy
+/// against it.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct BackupManifest {
+    pub label: String,
+    /// Version of the server that took the backup.
+    pub engine_version: String,
+    /// Lowest WAL LSN in the backup (`0` without WAL).
+    pub first_lsn: u64,
+    /// Highest WAL LSN in the backup; streaming resumes after it.
+    pub start_lsn: u64,
+    /// Files received, relative to the data directory, in the order they were sent.
+    pub files: Vec<BackupManifestFile>,
+    pub bytes: u64,
+    /// Digest of every [`BACKUP_BLOCK_BYTES`] block of every file in [`Self::state`]; an
+    /// incremental backup taken on top of this one sends the blocks that no longer match.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub blocks: BTreeMap<String, Vec<u64>>,
+    /// Set for a backup taken by [`fetch_incremental_backup`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub incremental: Option<IncrementalManifest>,
+    /// Hex HMAC-SHA256 of the manifest without this field, see [`BackupManifest::sign`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// One file of a [`BackupManifest`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct BackupManifestFile {
+    pub path: String,
+    pub size: u64,
+    /// Hex SHA-256 of the file contents.
+    pub sha256: String,
+}
+
+/// What an incremental [`BackupManifest`] records beyond its files.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct IncrementalManifest {
+    /// `start_lsn` of the backup this one is layered on.
+    pub base_start_lsn: u64,
+    /// Every file of the data directory as of this backup, as restored.
+    pub state: Vec<BackupManifestFile>,
+    /// Files of `state` stored as page deltas (`<path>.delta`); the others are stored whole or
+    /// unchanged since the base.
+    pub deltas: Vec<String>,
+}
+
+impl BackupManifest {
+    /// Files of the data directory as of this backup: [`Self::files`] for a full backup.
+    pub fn state(&self) -> &[BackupManifestFile] {
+        match &self.incremental {
+            Some(inc) => &inc.state,
+            None => &self.files,
+        }
+    }
+
+    /// Signs the manifest with the HMAC-SHA256 key `key`, replacing any earlier signature.
+    pub fn sign(&mut self, key: &[u8]) -> Result<(), ReplicationError> {
+        self.signature = None;
+        let tag = hmac::sign(
+            &hmac::Key::new(hmac::HMAC_SHA256, key),
+            &self.signed_bytes()?,
+        );
+        self.signature = Some(to_hex(tag.as_ref()));
+        Ok(())
+    }
+
+    /// Checks the signature against `key`; unsigned manifests fail.
+    pub fn verify_signature(&self, key: &[u8]) -> Result<(), ReplicationError> {
+        let tag = self
+            .signature
+            .as_deref()
+            .and_then(from_hex)
+            .ok_or_else(|| ReplicationError::Invalid("backup manifest is not signed".into()))?;
+        let unsigned = BackupManifest {
+            signature: None,
+            ..self.clone()
+        };
+        hmac::verify(
+            &hmac::Key::new(hmac::HMAC_SHA256, key),
+            &unsigned.signed_bytes()?,
+            &tag,
+        )
+        .map_err(|_| ReplicationError::Invalid("backup manifest signature does not match".into()))
+    }
+
+    fn signed_bytes(&self) -> Result<Vec<u8>, ReplicationError> {
+        serde_json::to_vec(self)
+            .map_err(|e| ReplicationError::Invalid(format!("backup manifest: {e}")))
+    }
+
+    /// What a server needs to take an incremental backup on top of this one.
+    fn incremental_base(&self) -> Result<IncrementalBasePayload, ReplicationError> {
+        let files = self
+            .state()
+            .iter()
+            .map(|f| {
+                let sha256 = from_hex(&f.sha256)
+                    .and_then(|b| b.try_into().ok())
+                    .ok_or_else(|| {
+                        ReplicationError::Invalid(format!(
+                            "bad checksum for {} in manifest",
+                            f.path
+                        ))
+                    })?;
+                Ok(BackupFileBlocks {
+                    path: f.path.clone(),
+                    sha256,
+                    blocks: self.blocks.get(&f.path).cloned().unwrap_or_default(),
+                })
+            })
+            .collect::<Result<_, ReplicationError>>()?;
+        Ok(IncrementalBasePayload {
+            start_lsn: self.start_lsn,
+            files,
+        })
+    }
+}
+
+/// Reads the [`BackupManifest`] of a data directory provisioned by [`fetch_base_backup`].
+pub fn read_backup_manifest(data_dir: &Path) -> Result<Option<BackupManifest>, ReplicationError> {
+    let path = data_dir.join(BACKUP_MANIFEST_FILE);
+    let bytes = match std::fs::read(&path) {
+        Ok(b) => b,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    serde_json::from_slice(&bytes)
+        .map(Some)
+        .map_err(|e| ReplicationError::Invalid(format!("{} is corrupt: {e}", path.display())))
+}
+
+/// Checks a backup written by [`fetch_base_backup`] against its manifest: every listed file
+/// present with its size and checksum, no other files, and with `signing_key` a valid signature.
+///
+/// Only meaningful for a backup that was never opened as a data directory (opening it changes
+/// the files).
+pub fn verify_backup(
+    backup_dir: &Path,
+    signing_key: Option<&[u8]>,
+) -> Result<BackupManifest, ReplicationError> {
+    let manifest = read_backup_manifest(backup_dir)?.ok_or_else(|| {
+        ReplicationError::Invalid(format!(
+            "{} has no backup manifest (incomplete backup?)",
+            backup_dir.display()
+        ))
+    })?;
+    if let Some(key) = signing_key {
+        manifest.verify_signature(key)?;
+    }
+    let mut present = Vec::new();
+    list_files(backup_dir, "", Path::new(""), &mut present)?;
+    for rel in present {
+        let known = rel == BACKUP_MANIFEST_FILE || manifest.files.iter().any(|f| f.path == rel);
+        if !known {
+            return Err(ReplicationError::Invalid(format!(
+                "backup file {rel} is not in the manifest"
+            )));
+        }
+    }
+    for file in &manifest.files {
+        let path = backup_dir.join(checked_relative_path(&file.path)?);
+        let (size, sha256) = match std::fs::File::open(&path) {
+            Ok(f) => digest_file(f, None)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(ReplicationError::Invalid(format!(
+                    "backup file {} is missing",
+                    file.path
+                )))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        check_file(file, size, &sha256)?;
+    }
+    Ok(manifest)
+}
+
+/// Verifies the backup in `backup_dir` ([`verify_backup`]) and only then copies it into
+/// `data_dir`, which must be missing or empty (see [`restore_backup_chain`]).
+pub fn restore_base_backup(
+    backup_dir: &Path,
+    data_dir: &Path,
+    signing_key: Option<&[u8]>,
+) -> Result<BackupManifest, ReplicationError> {
+    restore_backup_chain(backup_dir, &[], data_dir, signing_key)
+}
+
+/// Restores a full backup with `incrementals` taken on top of it, oldest first, into `data_dir`,
+/// which must be missing or empty.
+///
+/// Every backup is verified ([`verify_backup`]) and must layer on the one before it. The files
+/// are assembled next to `data_dir` and checked against the last manifest; `data_dir` is only
+/// created once they all match.
+pub fn restore_backup_chain(
+    base_dir: &Path,
+    incrementals: &[PathBuf],
+    data_dir: &Path,
+    signing_key: Option<&[u8]>,
+) -> Result<BackupManifest, ReplicationError> {
+    let base = verify_backup(base_dir, signing_key)?;
+    if base.incremental.is_some() {
+        return Err(ReplicationError::Invalid(format!(
+            "{} is an incremental backup; restore starts from a full backup",
+            base_dir.display()
+        )));
+    }
+    let mut chain = vec![(base_dir, base)];
+    for dir in incrementals {
+        let manifest = verify_backup(dir, signing_key)?;
+        let previous = chain.last().map_or(0, |(_, m)| m.start_lsn);
+        match &manifest.incremental {
+            Some(inc) if inc.base_start_lsn == previous => {}
+            Some(inc) => {
+                return Err(ReplicationError::Invalid(format!(
+                    "{} layers on the backup at LSN {}, not the one at LSN {previous}",
+                    dir.display(),
+                    inc.base_start_lsn
+                )))
+            }
+            None => {
+                return Err(ReplicationError::Invalid(format!(
+                    "{} is a full backup, not an incremental one",
+                    dir.display()
+                )))
+            }
+        }
+        chain.push((dir.as_path(), manifest));
+    }
+    if data_dir.exists() && std::fs::read_dir(data_dir)?.next().is_some() {
+        return Err(ReplicationError::Invalid(format!(
+            "restore target {} is not empty",
+            data_dir.display()
+        )));
+    }
+    let name = data_dir.file_name().ok_or_else(|| {
+        ReplicationError::Invalid(format!("restore target {} has no name", data_dir.display()))
+    })?;
+    let staging = data_dir.with_file_name(format!(
+        ".{}.restoring-{}",
+        name.to_string_lossy(),
+        std::process::id()
+    ));
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+    if let Err(e) = layer_backups(&chain, &staging) {
+        let _ = std::fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+    if data_dir.exists() {
+        std::fs::remove_dir(data_dir)?;
+    }
+    std::fs::rename(&staging, data_dir)?;
+    Ok(chain.pop().expect("chain starts with the base").1)
+}
+
+/// Uploads the backup in `backup_dir` to `remote` after verifying it ([`verify_backup`]). The
+/// manifest goes up last, so a backup whose upload was interrupted has none and
+/// [`pull_backup`] refuses it.
+pub fn push_backup(
+    backup_dir: &Path,
+    remote: &dyn RemoteStorage,
+) -> Result<BackupManifest, ReplicationError> {
+    let manifest = verify_backup(backup_dir, None)?;
+    let mut files: Vec<&str> = manifest.files.iter().map(|f| f.path.as_str()).collect();
+    files.push(BACKUP_MANIFEST_FILE);
+    for rel in files {
+        let mut file = std::fs::File::open(backup_dir.join(rel))?;
+        let len = file.metadata()?.len();
+        remote.put(rel, &mut file, len)?;
+    }
+    Ok(manifest)
+}
+
+/// Downloads a backup uploaded by [`push_backup`] into `dest`, which must be missing or empty,
+/// and verifies it ([`verify_backup`]) with `signing_key`.
+pub fn pull_backup(
+    remote: &dyn RemoteStorage,
+    dest: &Path,
+    signing_key: Option<&[u8]>,
+) -> Result<BackupManifest, ReplicationError> {
+    if dest.exists() && std::fs::read_dir(dest)?.next().is_some() {
+        return Err(ReplicationError::Invalid(format!(
+            "backup destination {} is not empty",
+            dest.display()
+        )));
+    }
+    let mut bytes = Vec::new();
+    match remote.get(BACKUP_MANIFEST_FILE, &mut bytes) {
+        Err(RemoteStorageError::NotFound(_)) => {
+            return Err(ReplicationError::Invalid(
+                "remote backup has no manifest (incomplete upload?)".into(),
+            ))
+        }
+        other => other?,
+    };
+    let manifest: BackupManifest = serde_json::from_slice(&bytes)
+        .map_err(|e| ReplicationError::Invalid(format!("remote backup manifest: {e}")))?;
+    for rel in manifest.files.iter().map(|f| f.path.as_str()) {
+        let target = dest.join(checked_relative_path(rel)?);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut out = std::fs::File::create(&target)?;
+        remote.get(rel, &mut out)?;
+        out.sync_all()?;
+    }
+    let path = dest.join(BACKUP_MANIFEST_FILE);
+    std::fs::create_dir_all(path.parent().expect("manifest lives in .rustdb"))?;
+    std::fs::write(&path, &bytes)?;
+    verify_backup(dest, signing_key)
+}
+
+/// Assembles the backups of a verified chain in `staging` and checks the result.
+fn layer_backups(
+    chain: &[(&Path, BackupManifest)],
+    staging: &Path,
+) -> Result<(), ReplicationError> {
+    for (dir, manifest) in chain {
+        let Some(inc) = &manifest.incremental else {
+            for file in &manifest.files {
+                copy_backup_file(dir, staging, &file.path)?;
+            }
+            continue;
+        };
+        for file in &inc.state {
+            let target = staging.join(checked_relative_path(&file.path)?);
+            if inc.deltas.contains(&file.path) {
+                let delta = dir.join(format!("{}{DELTA_SUFFIX}", file.path));
+                let mut out = std::fs::OpenOptions::new().write(true).open(&target)?;
+                apply_delta(&delta, &mut out)?;
+                out.sync_all()?;
+            } else if manifest.files.iter().any(|f| f.path == file.path) {
+                copy_backup_file(dir, staging, &file.path)?;
+            }
+        }
+        let mut present = Vec::new();
+        list_files(staging, "", Path::new(""), &mut present)?;
+        for rel in present {
+            if !inc.state.iter().any(|f| f.path == rel) {
+                std::fs::remove_file(staging.join(rel))?;
+            }
+        }
+    }
+    let (last_dir, last) = chain.last().expect("chain starts with the base");
+    let mut present = Vec::new();
+    list_files(staging, "", Path::new(""), &mut present)?;
+    if let Some(extra) = present
+        .iter()
+        .find(|rel| !last.state().iter().any(|f| &f.path == *rel))
+    {
+        return Err(ReplicationError::Invalid(format!(
+            "restored file {extra} is not in the manifest"
+        )));
+    }
+    for file in last.state() {
+        let (size, sha256) = match std::fs::File::open(staging.join(&file.path)) {
+            Ok(f) => digest_file(f, None)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(ReplicationError::Invalid(format!(
+                    "restored file {} is missing",
+                    file.path
+                )))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        check_file(file, size, &sha256)?;
+    }
+    if last_dir.join(BACKUP_MANIFEST_FILE).is_file() {
+        copy_backup_file(last_dir, staging, BACKUP_MANIFEST_FILE)?;
+    }
+    Ok(())
+}
+
+/// Copies `rel` from `dir` to `staging`, replacing an older copy.
+fn copy_backup_file(dir: &Path, staging: &Path, rel: &str) -> Result<(), ReplicationError> {
+    let target = staging.join(checked_relative_path(rel)?);
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut out = std::fs::File::create(&target)?;
+    digest_file(std::fs::File::open(dir.join(rel))?, Some(&mut out))?;
+    out.sync_all()?;
+    Ok(())
+}
+
+/// Writes the blocks of `src` whose digest differs from `base_blocks` as a page delta to `dest`.
+fn write_delta(src: &Path, dest: &Path, base_blocks: &[u64]) -> Result<(), ReplicationError> {
+    use std::io::Write;
+    let mut input = std::fs::File::open(src)?;
+    let mut out = std::io::BufWriter::new(std::fs::File::create(dest)?);
+    out.write_all(DELTA_MAGIC)?;
+    out.write_all(&input.metadata()?.len().to_le_bytes())?;
+    let mut block = vec![0u8; BACKUP_BLOCK_BYTES];
+    for index in 0u64.. {
+        let n = read_block(&mut input, &mut block)?;
+        if n == 0 {
+            break;
+        }
+        if base_blocks.get(index as usize) != Some(&block_digest(&block[..n])) {
+            out.write_all(&index.to_le_bytes())?;
+            out.write_all(&block[..n])?;
+        }
+    }
+    out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    Ok(())
+}
+
+/// Calls `f` with every block of the page delta at `path`; returns the file length it records.
+fn read_delta(
+    path: &Path,
+    mut f: impl FnMut(u64, &[u8]) -> Result<(), ReplicationError>,
+) -> Result<u64, ReplicationError> {
+    let mut input = std::io::BufReader::new(std::fs::File::open(path)?);
+    let invalid = || ReplicationError::Invalid(format!("{} is not a page delta", path.display()));
+    let mut head = [0u8; 16];
+    if read_block(&mut input, &mut head)? != head.len() || &head[..8] != DELTA_MAGIC {
+        return Err(invalid());
+    }
+    let len = u64::from_le_bytes(head[8..].try_into().expect("8 bytes"));
+    let mut block = vec![0u8; BACKUP_BLOCK_BYTES];
+    loop {
+        let mut index = [0u8; 8];
+        match read_block(&mut input, &mut index)? {
+            0 => return Ok(len),
+            8 => {}
+            _ => return Err(invalid()),
+        }
+        let index = u64::from_le_bytes(index);
+        let offset = index
+            .checked_mul(BACKUP_BLOCK_BYTES as u64)
+            .filter(|&o| o < len)
+            .ok_or_else(invalid)?;
+        let n = (len - offset).min(BACKUP_BLOCK_BYTES as u64) as usize;
+        if read_block(&mut input, &mut block[..n])? != n {
+            return Err(invalid());
+        }
+        f(index, &block[..n])?;
+    }
+}
+
+/// Applies the page delta at `path` to `out`.
+fn apply_delta(path: &Path, out: &mut std::fs::File) -> Result<(), ReplicationError> {
+    use std::io::{Seek, SeekFrom, Write};
+    let len = read_delta(path, |index, block| {
+        out.seek(SeekFrom::Start(index * BACKUP_BLOCK_BYTES as u64))?;
+        out.write_all(block)?;
+        Ok(())
+    })?;
+    out.set_len(len)?;
+    Ok(())
+}
+
+/// Block digests of the file at `path`.
+fn block_digests(path: &Path) -> Result<Vec<u64>, ReplicationError> {
+    let mut input = std::fs::File::open(path)?;
+    let mut block = vec![0u8; BACKUP_BLOCK_BYTES];
+    let mut out = Vec::new();
+    loop {
+        let n = read_block(&mut input, &mut block)?;
+        if n == 0 {
+            return Ok(out);
+        }
+        out.push(block_digest(&block[..n]));
+    }
+}
+
+/// Block digests after applying the page delta at `delta` to a file with `base` digests.
+fn patched_block_digests(delta: &Path, base: &[u64]) -> Result<Vec<u64>, ReplicationError> {
+    let mut blocks = base.to_vec();
+    let len = read_delta(delta, |index, block| {
+        let index = index as usize;
+        if blocks.len() <= index {
+            blocks.resize(index + 1, 0);
+        }
+        blocks[index] = block_digest(block);
+        Ok(())
+    })?;
+    blocks.truncate(len.div_ceil(BACKUP_BLOCK_BYTES as u64) as usize);
+    Ok(blocks)
+}
+
+/// First eight bytes of the block's SHA-256.
+fn block_digest(block: &[u8]) -> u64 {
+    let hash = digest::digest(&digest::SHA256, block);
+    u64::from_le_bytes(hash.as_ref()[..8].try_into().expect("8 bytes"))
+}
+
+/// Fills `buf` from `input` unless it ends first; returns the bytes read.
+fn read_block(input: &mut impl std::io::Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match input.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Size and SHA-256 of `file`, copying it into `copy_to` along the way.
+fn digest_file(
+    mut file: std::fs::File,
+    mut copy_to: Option<&mut std::fs::File>,
+) -> Result<(u64, [u8; 32]), ReplicationError> {
+    use std::io::{Read, Write};
+    let mut ctx = digest::Context::new(&digest::SHA256);
+    let mut buf = vec![0u8; BACKUP_CHUNK_BYTES];
+    let mut size = 0u64;
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        ctx.update(&buf[..n]);
+        if let Some(out) = copy_to.as_mut() {
+            out.write_all(&buf[..n])?;
+        }
+        size += n as u64;
+    }
+    Ok((size, sha256_of(ctx)))
+}
+
+fn check_file(
+    file: &BackupManifestFile,
+    size: u64,
+    sha256: &[u8; 32],
+) -> Result<(), ReplicationError> {
+    if size != file.size {
+        return Err(ReplicationError::Invalid(format!(
+            "backup file {} has {size} bytes, the manifest lists {}",
+            file.path, file.size
+        )));
+    }
+    if to_hex(sha256) != file.sha256 {
+        return Err(ReplicationError::Invalid(format!(
+            "backup file {} does not match its checksum",
+            file.path
+        )));
+    }
+    Ok(())
+}
+
+fn sha256_of(ctx: digest::Context) -> [u8; 32] {
+    ctx.finish()
+        .as_ref()
+        .try_into()
+        .expect("SHA-256 digests are 32 bytes")
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Builds a [`BaseBackup`] under `data_dir`; the engine decides when each file is copied.
+pub(crate) struct BaseBackupBuilder {
+    data_dir: PathBuf,
+    backup: BaseBackup,
+}
+
+impl BaseBackupBuilder {
+    pub(crate) fn new(data_dir: &Path) -> std::io::Result<Self> {
+        let id = NEXT_STAGING_ID.fetch_add(1, Ordering::Relaxed);
+        let root = data_dir
+            .join(STAGING_DIR)
+            .join(format!("{}-{id}", std::process::id()));
+        if root.exists() {
+            std::fs::remove_dir_all(&root)?;
+        }
+        std::fs::create_dir_all(&root)?;
+        Ok(Self {
+            data_dir: data_dir.to_path_buf(),
+            backup: BaseBackup {
+                root,
+                files: Vec::new(),
+                first_lsn: 0,
+                start_lsn: 0,
+                incremental: None,
+            },
+        })
+    }
+
+    /// Every file under the data directory (staging area excluded), sorted.
+    pub(crate) fn source_files(&self) -> std::io::Result<Vec<String>> {
+        let mut out = Vec::new();
+        let staging = self.data_dir.join(STAGING_DIR);
+        list_files(&self.data_dir, "", &staging, &mut out)?;
+        out.sort();
+        Ok(out)
+    }
+
+    /// Copies `rel` from the data directory into the staging area.
+    pub(crate) fn copy(&mut self, rel: &str) -> std::io::Result<()> {
+        let dest = self.backup.root.join(rel);
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        match std::fs::copy(self.data_dir.join(rel), &dest) {
+            Ok(_) => {}
+            // Dropped by a concurrent writer (e.g. a WAL segment or LSM run) since listing.
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        }
+        self.backup.files.push(rel.to_string());
+        Ok(())
+    }
+
+    /// Where `rel` is copied to in the staging area.
+    pub(crate) fn staged_path(&self, rel: &str) -> PathBuf {
+        self.backup.root.join(rel)
+    }
+
+    /// Finishes the backup; `wal_dir` is the WAL directory relative to the data directory.
+    pub(crate) fn finish(mut self, wal_dir: &str) -> BaseBackup {
+        let staged_wal = self.backup.root.join(wal_dir);
+        if staged_wal.is_dir() {
+            let lsns = LogRecord::read_log_records_from_directory(&staged_wal)
+                .map(|records| records.iter().map(|r| r.lsn).collect::<Vec<_>>())
+                .unwrap_or_default();
+            self.backup.first_lsn = lsns.iter().copied().min().unwrap_or(0);
+            self.backup.start_lsn = lsns.iter().copied().max().unwrap_or(0);
+        }
+        self.backup
+    }
+}
+
+fn list_files(root: &Path, rel: &str, skip: &Path, out: &mut Vec<String>) -> std::io::Result<()> {
+    let dir = root.join(rel);
+    if dir == skip {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(&dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let child = if rel.is_empty() {
+            name
+        } else {
+            format!("{rel}/{name}")
+        };
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            list_files(root, &child, skip, out)?;
+        } else if file_type.is_file() {
+            out.push(child);
+        }
+    }
+    Ok(())
+}
+
+/// Streams `backup` as chunk frames and a closing [`BackupEndPayload`].
+pub(crate) async fn send_base_backup(
+    send: &mut SendStream,
+    backup: &BaseBackup,
+) -> Result<BackupEndPayload, ReplicationError> {
+    let mut bytes = 0u64;
+    let mut digests = Vec::with_capacity(backup.files().len());
+    let mut buf = vec![0u8; BACKUP_CHUNK_BYTES];
+    for rel in backup.files() {
+        let mut file = tokio::fs::File::open(backup.root().join(rel)).await?;
+        let mut offset = 0u64;
+        let mut ctx = digest::Context::new(&digest::SHA256);
+        loop {
+            let n = file.read(&mut buf).await?;
+            ctx.update(&buf[..n]);
+            // Empty files still get one chunk so the replica creates them.
+            if n == 0 && offset > 0 {
+                break;
+            }
+            let frame =
+                encode_server_message_v1(&ServerMessage::BackupChunk(BackupChunkPayload {
+                    path: rel.clone(),
+                    offset,
+                    data: buf[..n].to_vec(),
+                }))?;
+            send.write_all(&frame).await?;
+            offset += n as u64;
+            if n == 0 {
+                break;
+            }
+        }
+        bytes += offset;
+        digests.push(BackupFileDigest {
+            path: rel.clone(),
+            size: offset,
+            sha256: sha256_of(ctx),
+        });
+    }
+    let end = BackupEndPayload {
+        start_lsn: backup.start_lsn(),
+        files: backup.files().len() as u64,
+        bytes,
+        first_lsn: backup.first_lsn(),
+        engine_version: env!("CARGO_PKG_VERSION").to_string(),
+        digests,
+        incremental: backup.incremental.clone(),
+    };
+    let frame = encode_server_message_v1(&ServerMessage::BackupEnd(end.clone()))?;
+    send.write_all(&frame).await?;
+    Ok(end)
+}
+
+/// Requests a base backup over `connection` and writes it into `dest`, which must be missing or
+/// empty. Every file is checked against the server's checksum before the [`BackupManifest`] is
+/// written; with `signing_key` the manifest is signed ([`BackupManifest::sign`]).
+pub async fn fetch_base_backup(
+    connection: &Connection,
+    label: &str,
+    dest: &Path,
+    signing_key: Option<&[u8]>,
+) -> Result<BaseBackupInfo, ReplicationError> {
+    fetch_backup(connection, label, dest, None, signing_key).await
+}
+
+/// Like [`fetch_base_backup`], but only fetches what changed since the backup in `base_dir`
+/// (full or incremental): files that differ are sent as their changed [`BACKUP_BLOCK_BYTES`]
+/// blocks, unchanged files not at all. [`restore_backup_chain`] layers the result over its base.
+pub async fn fetch_incremental_backup(
+    connection: &Connection,
+    label: &str,
+    dest: &Path,
+    base_dir: &Path,
+    signing_key: Option<&[u8]>,
+) -> Result<BaseBackupInfo, ReplicationError> {
+    let base = read_backup_manifest(base_dir)?.ok_or_else(|| {
+        ReplicationError::Invalid(format!(
+            "{} has no backup manifest (incomplete backup?)",
+            base_dir.display()
+        ))
+    })?;
+    if let Some(key) = signing_key {
+        base.verify_signature(key)?;
+    }
+    fetch_backup(connection, label, dest, Some(base), signing_key).await
+}
+
+async fn fetch_backup(
+    connection: &Connection,
+    label: &str,
+    dest: &Path,
+    base: Option<BackupManifest>,
+    signing_key: Option<&[u8]>,
+) -> Result<BaseBackupInfo, ReplicationError> {
+    if dest.exists() && std::fs::read_dir(dest)?.next().is_some() {
+        return Err(ReplicationError::Invalid(format!(
+            "base backup target {} is not empty",
+            dest.display()
+        )));
+    }
+    tokio::fs::create_dir_all(dest).await?;
+
+    let (mut send, mut recv) = connection.open_bi().await?;
+    let request = encode_client_message_v1(&ClientMessage::BaseBackup(BaseBackupPayload {
+        label: label.to_string(),
+        since: base
+            .as_ref()
+            .map(BackupManifest::incremental_base)
+            .transpose()?,
+    }))?;
+    send.write_all(&request).await?;
+    let _ = send.finish();
+
+    let mut frame = Vec::new();
+    let mut current: Option<(String, tokio::fs::File, u64, digest::Context)> = None;
+    let mut received: Vec<BackupManifestFile> = Vec::new();
+    let mut files = 0u64;
+    let mut bytes = 0u64;
+    loop {
+        read_application_frame_into(&mut recv, MAX_FRAME_PAYLOAD_BYTES, &mut frame).await?;
+        match decode_server_frame_v1(&frame)? {
+            ServerMessage::BackupChunk(chunk) => {
+                if current.as_ref().map(|(path, ..)| path) != Some(&chunk.path) {
+                    if let Some(done) = current.take() {
+                        received.push(finish_received_file(done).await?);
+                    }
+                    let target = dest.join(checked_relative_path(&chunk.path)?);
+                    if let Some(parent) = target.parent() {
+                        tokio::fs::create_dir_all(parent).await?;
+                    }
+                    let file = tokio::fs::File::create(&target).await?;
+                    let ctx = digest::Context::new(&digest::SHA256);
+                    current = Some((chunk.path.clone(), file, 0, ctx));
+                    files += 1;
+                }
+                let (path, file, written, ctx) =
+                    current.as_mut().expect("current file was just set");
+                if chunk.offset != *written {
+                    return Err(ReplicationError::Invalid(format!(
+                        "chunk of {path} at offset {} after {written} bytes",
+                        chunk.offset
+                    )));
+                }
+                file.write_all(&chunk.data).await?;
+                ctx.update(&chunk.data);
+                *written += chunk.data.len() as u64;
+                bytes += chunk.data.len() as u64;
+            }
+            ServerMessage::BackupEnd(end) => {
+                if let Some(done) = current.take() {
+                    received.push(finish_received_file(done).await?);
+                }
+                if end.files != files || end.bytes != bytes {
+                    return Err(ReplicationError::Invalid(format!(
+                        "base backup ended after {files} files / {bytes} bytes, server sent {} / {}",
+                        end.files, end.bytes
+                    )));
+                }
+                check_received_files(&received, &end.digests)?;
+                let incremental = match (end.incremental, &base) {
+                    (None, None) => None,
+                    (Some(inc), Some(_)) => Some(IncrementalManifest {
+                        base_start_lsn: inc.base_start_lsn,
+                        state: inc
+                            .state
+                            .into_iter()
+                            .map(|d| BackupManifestFile {
+                                path: d.path,
+                                size: d.size,
+                                sha256: to_hex(&d.sha256),
+                            })
+                            .collect(),
+                        deltas: inc.deltas,
+                    }),
+                    _ => {
+                        return Err(ReplicationError::Invalid(
+                            "server answered with the wrong kind of backup".into(),
+                        ))
+                    }
+                };
+                let mut manifest = BackupManifest {
+                    label: label.to_string(),
+                    engine_version: end.engine_version,
+                    first_lsn: end.first_lsn,
+                    start_lsn: end.start_lsn,
+                    files: received,
+                    bytes,
+                    blocks: BTreeMap::new(),
+                    incremental,
+                    signature: None,
+                };
+                write_replica_lsn(dest, end.start_lsn).await?;
+                let dir = dest.to_path_buf();
+                manifest = tokio::task::spawn_blocking(move || {
+                    // The resume LSN is checked (and signed) like the files received.
+                    let (size, sha256) =
+                        digest_file(std::fs::File::open(dir.join(REPLICA_LSN_FILE))?, None)?;
+                    let lsn_file = BackupManifestFile {
+                        path: REPLICA_LSN_FILE.to_string(),
+                        size,
+                        sha256: to_hex(&sha256),
+                    };
+                    manifest.files.retain(|f| f.path != REPLICA_LSN_FILE);
+                    manifest.files.push(lsn_file.clone());
+                    if let Some(inc) = &mut manifest.incremental {
+                        inc.state.retain(|f| f.path != REPLICA_LSN_FILE);
+                        inc.deltas.retain(|path| path != REPLICA_LSN_FILE);
+                        inc.state.push(lsn_file);
+                    }
+                    manifest.blocks = received_blocks(&dir, &manifest, base.as_ref())?;
+                    Ok::<_, ReplicationError>(manifest)
+                })
+                .await
+                .map_err(|e| ReplicationError::Invalid(format!("block digest task: {e}")))??;
+                if let Some(key) = signing_key {
+                    manifest.sign(key)?;
+                }
+                let json = serde_json::to_vec_pretty(&manifest)
+                    .map_err(|e| ReplicationError::Invalid(format!("backup manifest: {e}")))?;
+                write_file_atomic(dest.join(BACKUP_MANIFEST_FILE), json).await?;
+                return Ok(BaseBackupInfo {
+                    start_lsn: end.start_lsn,
+                    files,
+                    bytes,
+                });
+            }
+            ServerMessage::Error(e) => {
+                return Err(ReplicationError::Server {
+                    code: e.code,
+                    message: e.message,
+                })
+            }
+            other => {
+                return Err(ReplicationError::Invalid(format!(
+                    "unexpected frame during base backup: {other:?}"
+                )))
+            }
+        }
+    }
+}
+
+/// Syncs a file received by [`fetch_base_backup`] and returns its manifest entry.
+async fn finish_received_file(
+    (path, file, size, ctx): (String, tokio::fs::File, u64, digest::Context),
+) -> Result<BackupManifestFile, ReplicationError> {
+    file.sync_all().await?;
+    Ok(BackupManifestFile {
+        path,
+        size,
+        sha256: to_hex(&sha256_of(ctx)),
+    })
+}
+
+/// Block digests of every file of `manifest`'s state, from the files in `dir` and for an
+/// incremental backup the blocks of its `base`.
+fn received_blocks(
+    dir: &Path,
+    manifest: &BackupManifest,
+    base: Option<&BackupManifest>,
+) -> Result<BTreeMap<String, Vec<u64>>, ReplicationError> {
+    let mut blocks = BTreeMap::new();
+    for file in manifest.state() {
+        let stored = manifest.files.iter().any(|f| f.path == file.path);
+        let digests = match (&manifest.incremental, base) {
+            (Some(inc), Some(base)) if inc.deltas.contains(&file.path) => {
+                let delta = dir.join(format!("{}{DELTA_SUFFIX}", file.path));
+                let old = base.blocks.get(&file.path).map_or(&[][..], Vec::as_slice);
+                patched_block_digests(&delta, old)?
+            }
+            (Some(_), Some(base)) if !stored => {
+                let unchanged = base
+                    .state()
+                    .iter()
+                    .any(|f| f.path == file.path && f.sha256 == file.sha256);
+                if !unchanged {
+                    return Err(ReplicationError::Invalid(format!(
+                        "server skipped {} as unchanged, but the base backup has another copy",
+                        file.path
+                    )));
+                }
+                base.blocks.get(&file.path).cloned().unwrap_or_default()
+            }
+            _ => block_digests(&dir.join(checked_relative_path(&file.path)?))?,
+        };
+        blocks.insert(file.path.clone(), digests);
+    }
+    Ok(blocks)
+}
+
+/// Compares the files [`fetch_base_backup`] wrote with the server's [`BackupFileDigest`]s.
+fn check_received_files(
+    received: &[BackupManifestFile],
+    digests: &[BackupFileDigest],
+) -> Result<(), ReplicationError> {
+    if received.len() != digests.len() {
+        return Err(ReplicationError::Invalid(format!(
+            "base backup has {} files, server listed {} checksums",
+            received.len(),
+            digests.len()
+        )));
+    }
+    for (file, digest) in received.iter().zip(digests) {
+        if file.path != digest.path {
+            return Err(ReplicationError::Invalid(format!(
+                "base backup checksum for {} arrived for {}",
+                digest.path, file.path
+            )));
+        }
+        if file.size != digest.size || file.sha256 != to_hex(&digest.sha256) {
+            return Err(ReplicationError::Invalid(format!(
+                "base backup file {} does not match the server's checksum",
+                file.path
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Rejects absolute paths and `..` so a server cannot write outside the target directory.
+fn checked_relative_path(path: &str) -> Result<PathBuf, ReplicationError> {
+    let rel = PathBuf::from(path);
+    let plain = !path.is_empty() && rel.components().all(|c| matches!(c, Component::Normal(_)));
+    if plain {
+        Ok(rel)
+    } else {
+        Err(ReplicationError::Invalid(format!(
+            "base backup path {path:?} is not relative"
+        )))
+    }
+}
+
+/// Streams transactions committed after `start_lsn` until the replica stops reading; returns the
+/// last commit LSN sent.
+///
+/// The replica registers with the engine's [`ReplicationTracker`] under `name`, and its
+/// [`ClientMessage::ReplicationAck`] frames on `recv` count toward synchronous commit.
+pub(crate) async fn stream_committed_changes(
+    send: &mut SendStream,
+    recv: RecvStream,
+    engine: Arc<dyn EngineHandle>,
+    start_lsn: u64,
+    name: &str,
+) -> Result<u64, ReplicationError> {
+    let tracker = engine.replication_tracker();
+    let acks = tracker
+        .as_ref()
+        .map(|t| tokio::spawn(read_acks(recv, t.register(name, start_lsn))));
+    let _stop_acks = AbortOnDrop(acks);
+    let mut sent_lsn = start_lsn;
+    loop {
+        // Created before reading the WAL so a commit logged meanwhile still wakes us.
+        let committed = tracker.as_ref().map(|t| t.committed());
+        let eng = engine.clone();
+        let after = sent_lsn;
+        let batch = tokio::task::spawn_blocking(move || eng.committed_changes(after))
+            .await
+            .map_err(|e| ReplicationError::Invalid(format!("spawn_blocking join: {e}")))?
+            .map_err(|e| ReplicationError::Server {
+                code: e.code,
+                message: e.message,
+            })?;
+        for tx in batch {
+            let commit_lsn = tx.commit_lsn;
+            let frame = encode_server_message_v1(&ServerMessage::ReplicatedTransaction(tx))?;
+            // Blocks on stream flow control while the replica holds back delayed commits.
+            send.write_all(&frame).await?;
+            sent_lsn = commit_lsn;
+        }
+        let next_commit = async {
+            match committed {
+                Some(n) => n.await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            _ = tokio::time::sleep(STREAM_POLL_INTERVAL) => {}
+            _ = next_commit => {}
+            _ = send.stopped() => return Ok(sent_lsn),
+        }
+    }
+}
+
+/// Records the replica's acknowledgements until it closes its side of the stream.
+async fn read_acks(mut recv: RecvStream, registration: ReplicaRegistration) {
+    let mut frame = Vec::new();
+    while read_application_frame_into(&mut recv, MAX_FRAME_PAYLOAD_BYTES, &mut frame)
+        .await
+        .is_ok()
+    {
+        match decode_client_frame_v1(&frame) {
+            Ok(ClientMessage::ReplicationAck(ack)) => registration.ack(ack.applied_lsn),
+            other => {
+                tracing::warn!(frame = ?other, "unexpected frame on replication stream");
+                return;
+            }
+        }
+    }
+}
+
+struct AbortOnDrop(Option<tokio::task::JoinHandle<()>>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        if let Some(task) = &self.0 {
+            task.abort();
+        }
+    }
+}
+
+/// How a replica follows its primary (see [`run_replica`]).
+#[derive(Debug, Clone, Default)]
+pub struct ReplicaConfig {
+    /// Minimum age of a commit, measured from its commit time on the primary, before the replica
+    /// applies it. Zero applies commits as they arrive.
+    pub apply_delay: Duration,
+    /// Name the primary logs and matches against [`SynchronousStandbys::names`].
+    pub label: String,
+    /// Bidirectional mode, for two writable nodes that each follow the other: transactions the
+    /// peer replayed from this node are skipped, and rows both nodes changed are settled by
+    /// the resolver (e.g. [`LastWriterWins`]). Every replicated table needs a primary key.
+    pub conflict_resolver: Option<Arc<dyn ConflictResolver>>,
+}
+
+/// Progress of [`run_replica`], shared with whoever inspects or pauses the replica.
+#[derive(Debug, Default)]
+pub struct ReplicaStatus {
+    received_lsn: AtomicU64,
+    applied_lsn: AtomicU64,
+    applied_commit_time: AtomicU64,
+    paused: AtomicBool,
+    conflicts: AtomicU64,
+    /// Wakes [`Self::wait_for_applied`] when `applied_lsn` moves.
+    applied_lock: Mutex<()>,
+    applied_changed: Condvar,
+}
+
+impl ReplicaStatus {
+    /// Commit LSN of the newest transaction received from the primary.
+    pub fn received_lsn(&self) -> u64 {
+        self.received_lsn.load(Ordering::Acquire)
+    }
+
+    /// Commit LSN of the last transaction applied (the base backup position before the first).
+    pub fn applied_lsn(&self) -> u64 {
+        self.applied_lsn.load(Ordering::Acquire)
+    }
+
+    /// Blocks until the replica has applied the primary commit at `lsn` (or a later one), for at
+    /// most `timeout`. Returns whether it has.
+    pub fn wait_for_applied(&self, lsn: u64, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut guard = self.applied_lock.lock().unwrap_or_else(|e| e.into_inner());
+        while self.applied_lsn() < lsn {
+            let Some(left) = deadline.checked_duration_since(Instant::now()) else {
+                return false;
+            };
+            guard = self
+                .applied_changed
+                .wait_timeout(guard, left)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+        true
+    }
+
+    fn set_applied_lsn(&self, lsn: u64) {
+        self.applied_lsn.store(lsn, Ordering::Release);
+        // Taking the lock orders the store before a waiter's check and its wait.
+        drop(self.applied_lock.lock().unwrap_or_else(|e| e.into_inner()));
+        self.applied_changed.notify_all();
+    }
+
+    /// Primary commit time (Unix seconds) of the last applied transaction; `0` before the first.
+    pub fn applied_commit_time(&self) -> u64 {
+        self.applied_commit_time.load(Ordering::Acquire)
+    }
+
+    /// Stops applying at the next transaction boundary; received transactions stay queued on
+    /// the stream.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::Release);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::Release);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Acquire)
+    }
+
+    /// Rows handed to [`ReplicaConfig::conflict_resolver`] so far.
+    pub fn conflicts(&self) -> u64 {
+        self.conflicts.load(Ordering::Acquire)
+    }
+}
+
+/// Follows the primary behind `connection`, applying its commits to `engine` (opened on
+/// `data_dir`, which must come from [`fetch_base_backup`]) until the stream ends or a
+/// transaction fails to apply. A crash between applying a transaction and recording its LSN
+/// applies it again after the restart.
+pub async fn run_replica(
+    connection: &Connection,
+    engine: Arc<dyn EngineHandle>,
+    data_dir: &Path,
+    config: &ReplicaConfig,
+    status: &ReplicaStatus,
+) -> Result<(), ReplicationError> {
+    let mut applied_lsn = read_replica_lsn(data_dir).await?;
+    status.set_applied_lsn(applied_lsn);
+
+    let (mut send, mut recv) = connection.open_bi().await?;
+    let request =
+        encode_client_message_v1(&ClientMessage::StartReplication(StartReplicationPayload {
+            start_lsn: applied_lsn,
+            label: config.label.clone(),
+        }))?;
+    send.write_all(&request).await?;
+    send_ack(&mut send, applied_lsn).await?;
+
+    let versions = Arc::new(std::sync::Mutex::new(RowVersions::default()));
+    let mut frame = Vec::new();
+    loop {
+        read_application_frame_into(&mut recv, MAX_FRAME_PAYLOAD_BYTES, &mut frame).await?;
+        let tx = match decode_server_frame_v1(&frame)? {
+            ServerMessage::ReplicatedTransaction(tx) => tx,
+            ServerMessage::Error(e) => {
+                return Err(ReplicationError::Server {
+                    code: e.code,
+                    message: e.message,
+                })
+            }
+            other => {
+                return Err(ReplicationError::Invalid(format!(
+                    "unexpected frame during replication: {other:?}"
+                )))
+            }
+        };
+        status.received_lsn.store(tx.commit_lsn, Ordering::Release);
+        if tx.commit_lsn <= applied_lsn {
+            send_ack(&mut send, applied_lsn).await?;
+            continue;
+        }
+        let commit_lsn = tx.commit_lsn;
+        let commit_time = tx.commit_time;
+        // The peer's copy of one of our own transactions.
+        let echoed = tx.origin_commit_time.is_some();
+        match config.conflict_resolver.clone() {
+            Some(_) if echoed => {}
+            Some(resolver) => {
+                wait_until_due(commit_time, config.apply_delay, status).await;
+                let eng = engine.clone();
+                let versions = versions.clone();
+                let conflicts = tokio::task::spawn_blocking(move || {
+                    let mut versions = versions.lock().unwrap_or_else(|e| e.into_inner());
+                    apply_with_conflicts(eng.as_ref(), &tx, resolver.as_ref(), &mut versions)
+                })
+                .await
+                .map_err(|e| ReplicationError::Invalid(format!("spawn_blocking join: {e}")))??;
+                status.conflicts.fetch_add(conflicts, Ordering::AcqRel);
+            }
+            None => {
+                wait_until_due(commit_time, config.apply_delay, status).await;
+                let eng = engine.clone();
+                tokio::task::spawn_blocking(move || apply_transaction(eng.as_ref(), &tx))
+                    .await
+                    .map_err(|e| {
+                        ReplicationError::Invalid(format!("spawn_blocking join: {e}"))
+                    })??;
+            }
+        }
+        write_replica_lsn(data_dir, commit_lsn).await?;
+        applied_lsn = commit_lsn;
+        status.set_applied_lsn(commit_lsn);
+        status
+            .applied_commit_time
+            .store(commit_time, Ordering::Release);
+        send_ack(&mut send, commit_lsn).await?;
+    }
+}
+
+async fn send_ack(send: &mut SendStream, applied_lsn: u64) -> Result<(), ReplicationError> {
+    let frame = encode_client_message_v1(&ClientMessage::ReplicationAck(ReplicationAckPayload {
+        applied_lsn,
+    }))?;
+    send.write_all(&frame).await?;
+    Ok(())
+}
+
+/// Sleeps until `commit_time + delay` has passed and the replica is not paused.
+async fn wait_until_due(commit_time: u64, delay: Duration, status: &ReplicaStatus) {
+    let due = UNIX_EPOCH + Duration::from_secs(commit_time) + delay;
+    loop {
+        let remaining = due
+            .duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO);
+        if remaining.is_zero() && !status.is_paused() {
+            return;
+        }
+        // Capped so a pause, or a resume, is noticed while a long delay runs down.
+        let nap = if remaining.is_zero() {
+            PAUSE_POLL_INTERVAL
+        } else {
+            remaining.min(Duration::from_secs(1))
+        };
+        tokio::time::sleep(nap).await;
+    }
+}
+
+/// Replays one replicated transaction (a lone DDL statement runs outside a transaction).
+fn apply_transaction(
+    engine: &dyn EngineHandle,
+    tx: &ReplicatedTransactionPayload,
+) -> Result<(), ReplicationError> {
+    let apply_err = |message: String| ReplicationError::Apply {
+        commit_lsn: tx.commit_lsn,
+        message,
+    };
+    let mut ctx = SessionContext::default();
+    if let [RowChangePayload::Ddl { sql }] = tx.changes.as_slice() {
+        return engine
+            .execute_sql(sql, &mut ctx)
+            .map(|_| ())
+            .map_err(|e| apply_err(e.message));
+    }
+    if tx.changes.is_empty() {
+        return Ok(());
+    }
+    let statements = tx
+        .changes
+        .iter()
+        .filter_map(|c| change_to_sql(c).transpose())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(apply_err)?;
+    run_in_transaction(engine, &mut ctx, &statements).map_err(apply_err)
+}
+
+/// Runs `statements` as one transaction (nothing when empty).
+fn run_in_transaction(
+    engine: &dyn EngineHandle,
+    ctx: &mut SessionContext,
+    statements: &[String],
+) -> Result<(), String> {
+    if statements.is_empty() {
+        return Ok(());
+    }
+    engine
+        .execute_sql("BEGIN TRANSACTION", ctx)
+        .map_err(|e| e.message)?;
+    for sql in statements {
+        if let Err(e) = engine.execute_sql(sql, ctx) {
+            let _ = engine.execute_sql("ROLLBACK", ctx);
+            return Err(format!("{}: {sql}", e.message));
+        }
+    }
+    engine
+        .execute_sql("COMMIT", ctx)
+        .map(|_| ())
+        .map_err(|e| e.message)
+}
+
+/// SQL statement replaying `change`; `None` for an update that changes no column.
+fn change_to_sql(change: &RowChangePayload) -> Result<Option<String>, String> {
+    Ok(Some(match change {
+        RowChangePayload::Insert { table, row, .. } => insert_sql(table, row),
+        RowChangePayload::Update {
+            table, old, new, ..
+        } => {
+            let set = new
+                .iter()
+                .filter(|c| !old.contains(c))
+                .map(|c| format!("{} = {}", c.name, c.literal))
+                .collect::<Vec<_>>();
+            if set.is_empty() {
+                return Ok(None);
+            }
+            format!(
+                "UPDATE {table} SET {} WHERE {}",
+                set.join(", "),
+                row_predicate(table, old)?
+            )
+        }
+        RowChangePayload::Delete { table, old, .. } => {
+            format!("DELETE FROM {table} WHERE {}", row_predicate(table, old)?)
+        }
+        RowChangePayload::Ddl { sql } => sql.clone(),
+    }))
+}
+
+/// `INSERT` listing every column of `row`.
+fn insert_sql(table: &str, row: &[ReplicatedColumn]) -> String {
+    format!(
+        "INSERT INTO {table} ({}) VALUES ({})",
+        row.iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+        row.iter()
+            .map(|c| c.literal.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// `col = literal AND …` over the non-NULL columns of a row image.
+fn row_predicate(table: &str, row: &[ReplicatedColumn]) -> Result<String, String> {
+    let terms = row
+        .iter()
+        .filter(|c| c.literal != "NULL")
+        .map(|c| format!("{} = {}", c.name, c.literal))
+        .collect::<Vec<_>>();
+    if terms.is_empty() {
+        return Err(format!("row of {table} has only NULL columns"));
+    }
+    Ok(terms.join(" AND "))
+}
+
+/// A row both nodes of a bidirectional pair changed: the peer's transaction expects a different
+/// version of the row than this node has.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowConflict {
+    pub table: String,
+    /// Primary key of the row, as SQL literals.
+    pub key: Vec<ReplicatedColumn>,
+    /// The row on this node (`None`: deleted or never inserted here).
+    pub local: Option<Vec<ReplicatedColumn>>,
+    /// Commit time (Unix seconds) of the local version, on the node that wrote it.
+    pub local_commit_time: u64,
+    /// The row as the peer's transaction leaves it (`None`: deleted).
+    pub remote: Option<Vec<ReplicatedColumn>>,
+    /// Commit time (Unix seconds) of the peer's transaction.
+    pub remote_commit_time: u64,
+}
+
+/// Outcome of a [`RowConflict`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    KeepLocal,
+    ApplyRemote,
+    /// Store this row instead (`None` deletes it), e.g. a merge of both versions.
+    Replace(Option<Vec<ReplicatedColumn>>),
+}
+
+/// Settles [`RowConflict`]s in bidirectional replication ([`ReplicaConfig::conflict_resolver`]).
+///
+/// Both nodes see every conflict, with `local` and `remote` swapped; a resolver must reach the
+/// same row on both sides or the nodes diverge.
+pub trait ConflictResolver: std::fmt::Debug + Send + Sync {
+    fn resolve(&self, conflict: &RowConflict) -> Resolution;
+}
+
+/// The newer commit wins. Commit times have one-second resolution; on a tie the greater row
+/// image (compared column by column as literals, a deleted row being least) wins on both nodes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LastWriterWins;
+
+impl ConflictResolver for LastWriterWins {
+    fn resolve(&self, conflict: &RowConflict) -> Resolution {
+        let order = |row: &Option<Vec<ReplicatedColumn>>| {
+            row.as_ref().map(|r| {
+                r.iter()
+                    .map(|c| (c.name.clone(), c.literal.clone()))
+                    .collect::<Vec<_>>()
+            })
+        };
+        let local = (conflict.local_commit_time, order(&conflict.local));
+        let remote = (conflict.remote_commit_time, order(&conflict.remote));
+        if remote > local {
+            Resolution::ApplyRemote
+        } else {
+            Resolution::KeepLocal
+        }
+    }
+}
+
+/// Latest version of each row this node knows from its own WAL, by `(table, key literals)`.
+///
+/// Rows last written before the oldest WAL record are unknown and applied without a conflict
+/// check; DDL forgets every version.
+#[derive(Debug, Default)]
+struct RowVersions {
+    seen_lsn: u64,
+    rows: std::collections::HashMap<(String, Vec<String>), RowVersion>,
+}
+
+#[derive(Debug)]
+struct RowVersion {
+    commit_time: u64,
+    row: Option<Vec<ReplicatedColumn>>,
+}
+
+impl RowVersions {
+    /// Catches up with local commits, including replayed ones (at the peer's commit time).
+    fn refresh(&mut self, engine: &dyn EngineHandle) -> Result<(), String> {
+        for tx in engine
+            .committed_changes(self.seen_lsn)
+            .map_err(|e| e.message)?
+        {
+            let commit_time = tx.origin_commit_time.unwrap_or(tx.commit_time);
+            for change in &tx.changes {
+                let Some((table, key, before, after)) = row_change_parts(change) else {
+                    self.rows.clear();
+                    continue;
+                };
+                if let Some(k) = key_literals(key, before.or(after)) {
+                    self.record(table, k, commit_time, after.cloned());
+                }
+            }
+            self.seen_lsn = tx.commit_lsn;
+        }
+        Ok(())
+    }
+
+    fn record(
+        &mut self,
+        table: &str,
+        key: Vec<String>,
+        commit_time: u64,
+        row: Option<Vec<ReplicatedColumn>>,
+    ) {
+        self.rows
+            .insert((table.to_string(), key), RowVersion { commit_time, row });
+    }
+}
+
+/// `(table, key columns, row before, row after)`; `None` for DDL.
+fn row_change_parts(
+    change: &RowChangePayload,
+) -> Option<(
+    &str,
+    &[String],
+    Option<&Vec<ReplicatedColumn>>,
+    Option<&Vec<ReplicatedColumn>>,
+)> {
+    match change {
+        RowChangePayload::Insert { table, key, row } => Some((table, key, None, Some(row))),
+        RowChangePayload::Update {
+            table,
+            key,
+            old,
+            new,
+        } => Some((table, key, Some(old), Some(new))),
+        RowChangePayload::Delete { table, key, old } => Some((table, key, Some(old), None)),
+        RowChangePayload::Ddl { .. } => None,
+    }
+}
+
+/// Statement turning the local row of `conflict` into `target`, addressing it by key.
+fn replace_row_sql(
+    table: &str,
+    conflict: &RowConflict,
+    target: Option<&[ReplicatedColumn]>,
+) -> Result<Option<String>, String> {
+    let key = || row_predicate(table, &conflict.key);
+    Ok(match (conflict.local.is_some(), target) {
+        (true, Some(row)) => {
+            let set = row
+                .iter()
+                .filter(|c| !conflict.key.iter().any(|k| k.name == c.name))
+                .map(|c| format!("{} = {}", c.name, c.literal))
+                .collect::<Vec<_>>();
+            if set.is_empty() {
+                return Ok(None);
+            }
+            Some(format!(
+                "UPDATE {table} SET {} WHERE {}",
+                set.join(", "),
+                key()?
+            ))
+        }
+        (false, Some(row)) => Some(insert_sql(table, row)),
+        (true, None) => Some(format!("DELETE FROM {table} WHERE {}", key()?)),
+        (false, None) => None,
+    })
+}
+
+/// Literals of the `key` columns in `row` (`None` without a key).
+fn key_literals(key: &[String], row: Option<&Vec<ReplicatedColumn>>) -> Option<Vec<String>> {
+    let row = row?;
+    if key.is_empty() {
+        return None;
+    }
+    key.iter()
+        .map(|k| row.iter().find(|c| &c.name == k).map(|c| c.literal.clone()))
+        .collect()
+}
+
+/// Replays a peer's transaction, settling rows that changed here too with `resolver`; returns
+/// the number of conflicts.
+fn apply_with_conflicts(
+    engine: &dyn EngineHandle,
+    tx: &ReplicatedTransactionPayload,
+    resolver: &dyn ConflictResolver,
+    versions: &mut RowVersions,
+) -> Result<u64, ReplicationError> {
+    let apply_err = |message: String| ReplicationError::Apply {
+        commit_lsn: tx.commit_lsn,
+        message,
+    };
+    versions.refresh(engine).map_err(apply_err)?;
+    let mut ctx = SessionContext {
+        peer_commit_time: Some(tx.commit_time),
+        ..Default::default()
+    };
+    if let [RowChangePayload::Ddl { sql }] = tx.changes.as_slice() {
+        return engine
+            .execute_sql(sql, &mut ctx)
+            .map(|_| 0)
+            .map_err(|e| apply_err(e.message));
+    }
+    let mut statements = Vec::new();
+    let mut conflicts = 0;
+    for change in &tx.changes {
+        let Some((table, key, before, after)) = row_change_parts(change) else {
+            statements.extend(change_to_sql(change).map_err(apply_err)?);
+            continue;
+        };
+        let key_values = key_literals(key, before.or(after)).ok_or_else(|| {
+            apply_err(format!(
+                "bidirectional replication needs a primary key on {table}"
+            ))
+        })?;
+        let local = versions.rows.get(&(table.to_string(), key_values.clone()));
+        let target = match local {
+            Some(local) if local.row.as_ref() != before => {
+                if local.row.as_ref() == after {
+                    // Both nodes made the same change.
+                    continue;
+                }
+                conflicts += 1;
+                let conflict = RowConflict {
+                    table: table.to_string(),
+                    key: key
+                        .iter()
+                        .zip(&key_values)
+                        .map(|(name, literal)| ReplicatedColumn {
+                            name: name.clone(),
+                            literal: literal.clone(),
+                        })
+                        .collect(),
+                    local: local.row.clone(),
+                    local_commit_time: local.commit_time,
+                    remote: after.cloned(),
+                    remote_commit_time: tx.commit_time,
+                };
+                let target = match resolver.resolve(&conflict) {
+                    Resolution::KeepLocal => continue,
+                    Resolution::ApplyRemote => conflict.remote.clone(),
+                    Resolution::Replace(row) => row,
+                };
+                statements.extend(
+                    replace_row_sql(table, &conflict, target.as_deref()).map_err(apply_err)?,
+                );
+                target
+            }
+            _ => {
+                statements.extend(change_to_sql(change).map_err(apply_err)?);
+                after.cloned()
+            }
+        };
+        versions.record(table, key_values, tx.commit_time, target);
+    }
+    run_in_transaction(engine, &mut ctx, &statements).map_err(apply_err)?;
+    Ok(conflicts)
+}
+
+async fn read_replica_lsn(data_dir: &Path) -> Result<u64, ReplicationError> {
+    let path = data_dir.join(REPLICA_LSN_FILE);
+    let text = match tokio::fs::read_to_string(&path).await {
+        Ok(t) => t,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(ReplicationError::Invalid(format!(
+                "{} was not provisioned by a base backup (no {REPLICA_LSN_FILE})",
+                data_dir.display()
+            )))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    text.trim()
+        .parse()
+        .map_err(|_| ReplicationError::Invalid(format!("{} is corrupt", path.display())))
+}
+
+/// Records the LSN streaming resumes after (atomic replace, so a crash keeps the old value).
+pub async fn write_replica_lsn(data_dir: &Path, lsn: u64) -> Result<(), ReplicationError> {
+    write_file_atomic(
+        data_dir.join(REPLICA_LSN_FILE),
+        lsn.to_string().into_bytes(),
+    )
+    .await
+}
+
+/// Creates the parent directory of `path` and replaces `path` with `data` (write-temp, fsync,
+/// rename, directory fsync).
+async fn write_file_atomic(path: PathBuf, data: Vec<u8>) -> Result<(), ReplicationError> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::task::spawn_blocking(move || crate::storage::atomic_file::write_atomic(&path, &data))
+        .await
+        .map_err(|e| ReplicationError::Invalid(format!("metadata write task: {e}")))?
+        .map_err(|e| ReplicationError::Io(std::io::Error::other(e.to_string())))
+}
+
+/// `synchronous_standby_names`: `ANY k (name, …)`, `k (name, …)` or a plain list (`ANY 1`).
+///
+/// Names are replica labels ([`ReplicaConfig::label`]); `*` matches any replica.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SynchronousStandbys {
+    pub quorum: usize,
+    pub names: Vec<String>,
+}
+
+impl SynchronousStandbys {
+    fn matches(&self, name: &str) -> bool {
+        self.names.iter().any(|n| n == "*" || n == name)
+    }
+}
+
+impl std::str::FromStr for SynchronousStandbys {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        let body = match s.get(..4) {
+            Some(p) if p.eq_ignore_ascii_case("ANY ") => s[4..].trim_start(),
+            _ => s,
+        };
+        let (quorum, list) = match body.split_once('(') {
+            Some((k, rest)) => {
+                let list = rest
+                    .strip_suffix(')')
+                    .ok_or_else(|| format!("missing ')' in {s:?}"))?;
+                let k = k
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| format!("expected a replica count before '(' in {s:?}"))?;
+                (k, list)
+            }
+            None => (1, body),
+        };
+        let names: Vec<String> = list
+            .split(',')
+            .map(|n| n.trim().trim_matches('"').to_string())
+            .filter(|n| !n.is_empty())
+            .collect();
+        if names.is_empty() {
+            return Err(format!("no standby names in {s:?}"));
+        }
+        if quorum == 0 || (quorum > names.len() && !names.iter().any(|n| n == "*")) {
+            return Err(format!(
+                "quorum {quorum} is not between 1 and {} in {s:?}",
+                names.len()
+            ));
+        }
+        Ok(Self { quorum, names })
+    }
+}
+
+/// What a commit does when the quorum has not acknowledged it within the timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyncTimeoutPolicy {
+    /// Report success; the commit is durable on the primary only.
+    #[default]
+    CommitLocally,
+    /// Fail the `COMMIT` with [`engine_error_code::SYNC_REPLICATION_TIMEOUT`] (the transaction
+    /// stays committed on the primary).
+    ///
+    /// [`engine_error_code::SYNC_REPLICATION_TIMEOUT`]: crate::network::engine::engine_error_code::SYNC_REPLICATION_TIMEOUT
+    Error,
+}
+
+impl std::str::FromStr for SyncTimeoutPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "local" | "commit_locally" => Ok(Self::CommitLocally),
+            "error" => Ok(Self::Error),
+            other => Err(format!(
+                "unknown synchronous commit timeout policy {other:?} (local, error)"
+            )),
+        }
+    }
+}
+
+/// Synchronous commit: every `COMMIT` waits for [`SynchronousStandbys::quorum`] of the named
+/// replicas to acknowledge its LSN.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SynchronousCommitConfig {
+    pub standbys: SynchronousStandbys,
+    pub timeout: Duration,
+    pub on_timeout: SyncTimeoutPolicy,
+}
+
+impl SynchronousCommitConfig {
+    /// Synchronous commit as configured; `None` when `synchronous_standby_names` is empty.
+    pub fn from_config(config: &ReplicationConfig) -> Result<Option<Self>, String> {
+        if config.synchronous_standby_names.trim().is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            standbys: config.synchronous_standby_names.parse()?,
+            timeout: Duration::from_millis(config.synchronous_commit_timeout_ms),
+            on_timeout: config.synchronous_commit_timeout_policy.parse()?,
+        }))
+    }
+}
+
+/// Commit not acknowledged by the synchronous quorum in time.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("commit LSN {commit_lsn} is durable locally, but only {acked} of {quorum} synchronous replicas acknowledged it within {timeout:?}")]
+pub struct SyncReplicationTimeout {
+    pub commit_lsn: u64,
+    pub acked: usize,
+    pub quorum: usize,
+    pub timeout: Duration,
+}
+
+/// Acknowledgement state of one connected replica.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplicaAckStatistics {
+    pub name: String,
+    /// Counts toward the synchronous quorum.
+    pub synchronous: bool,
+    pub acked_lsn: u64,
+    /// Primary commits (by LSN) the replica has not acknowledged yet.
+    pub lag_lsn: u64,
+    /// Age of the oldest commit the replica has not acknowledged ([`Duration::ZERO`] when caught
+    /// up).
+    pub ack_lag: Duration,
+}
+
+/// Snapshot of [`ReplicationTracker`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplicationStatistics {
+    pub last_commit_lsn: u64,
+    pub replicas: Vec<ReplicaAckStatistics>,
+    /// Commits that waited for the synchronous quorum.
+    pub synchronous_commits: u64,
+    /// Of those, commits whose wait timed out.
+    pub synchronous_timeouts: u64,
+}
+
+/// Commits remembered for [`ReplicaAckStatistics::ack_lag`] while replicas are behind.
+const MAX_PENDING_COMMITS: usize = 65_536;
+
+#[derive(Debug)]
+struct ConnectedReplica {
+    name: String,
+    acked_lsn: u64,
+}
+
+#[derive(Debug, Default)]
+struct TrackerState {
+    sync: Option<SynchronousCommitConfig>,
+    replicas: std::collections::BTreeMap<u64, ConnectedReplica>,
+    next_id: u64,
+    last_commit_lsn: u64,
+    /// `(commit LSN, commit instant)` not yet acknowledged by every connected replica.
+    pending: std::collections::VecDeque<(u64, std::time::Instant)>,
+    synchronous_commits: u64,
+    synchronous_timeouts: u64,
+}
+
+impl TrackerState {
+    /// Distinct synchronous standby names that acknowledged `lsn`.
+    fn acked_by(&self, sync: &SynchronousCommitConfig, lsn: u64) -> usize {
+        self.replicas
+            .values()
+            .filter(|r| r.acked_lsn >= lsn && sync.standbys.matches(&r.name))
+            .map(|r| r.name.as_str())
+            .collect::<std::collections::BTreeSet<_>>()
+            .len()
+    }
+
+    fn prune_pending(&mut self) {
+        let min_acked = self
+            .replicas
+            .values()
+            .map(|r| r.acked_lsn)
+            .min()
+            .unwrap_or(u64::MAX);
+        while self
+            .pending
+            .front()
+            .is_some_and(|(lsn, _)| *lsn <= min_acked)
+        {
+            self.pending.pop_front();
+        }
+    }
+}
+
+/// Connected replicas of a primary: wakes replication streams on commit, collects their
+/// acknowledgements and makes commits wait for the synchronous quorum.
+#[derive(Debug, Default)]
+pub struct ReplicationTracker {
+    state: std::sync::Mutex<TrackerState>,
+    acked: std::sync::Condvar,
+    commits: tokio::sync::Notify,
+}
+
+impl ReplicationTracker {
+    /// Sets (or with `None`, clears) synchronous commit; takes effect for the next commit.
+    pub fn set_synchronous_commit(&self, config: Option<SynchronousCommitConfig>) {
+        self.lock().sync = config;
+        self.acked.notify_all();
+    }
+
+    pub fn synchronous_commit(&self) -> Option<SynchronousCommitConfig> {
+        self.lock().sync.clone()
+    }
+
+    /// Registers a replica streaming from `start_lsn`; it is dropped from the quorum with the
+    /// returned handle.
+    pub fn register(self: &Arc<Self>, name: &str, start_lsn: u64) -> ReplicaRegistration {
+        let mut st = self.lock();
+        let id = st.next_id;
+        st.next_id += 1;
+        st.replicas.insert(
+            id,
+            ConnectedReplica {
+                name: name.to_string(),
+                acked_lsn: start_lsn,
+            },
+        );
+        drop(st);
+        self.acked.notify_all();
+        ReplicaRegistration {
+            tracker: self.clone(),
+            id,
+        }
+    }
+
+    /// Called by the engine once commit `lsn` is in the WAL.
+    pub fn commit_logged(&self, lsn: u64) {
+        let mut st = self.lock();
+        st.last_commit_lsn = st.last_commit_lsn.max(lsn);
+        if !st.replicas.is_empty() {
+            if st.pending.len() == MAX_PENDING_COMMITS {
+                st.pending.pop_front();
+            }
+            st.pending.push_back((lsn, std::time::Instant::now()));
+        }
+        drop(st);
+        self.commits.notify_waiters();
+    }
+
+    /// Resolves on the next [`Self::commit_logged`].
+    pub(crate) fn committed(&self) -> tokio::sync::futures::Notified<'_> {
+        self.commits.notified()
+    }
+
+    /// Blocks until the synchronous quorum acknowledged `lsn` (immediately without synchronous
+    /// commit). On timeout, `Ok` under [`SyncTimeoutPolicy::CommitLocally`].
+    pub fn wait_for_quorum(&self, lsn: u64) -> Result<(), SyncReplicationTimeout> {
+        let mut st = self.lock();
+        let Some(sync) = st.sync.clone() else {
+            return Ok(());
+        };
+        st.synchronous_commits += 1;
+        let deadline = std::time::Instant::now() + sync.timeout;
+        loop {
+            let acked = st.acked_by(&sync, lsn);
+            if acked >= sync.standbys.quorum {
+                return Ok(());
+            }
+            let now = std::time::Instant::now();
+            if now >= deadline {
+                st.synchronous_timeouts += 1;
+                let err = SyncReplicationTimeout {
+                    commit_lsn: lsn,
+                    acked,
+                    quorum: sync.standbys.quorum,
+                    timeout: sync.timeout,
+                };
+                tracing::warn!(error = %err, "synchronous replication timed out");
+                return match sync.on_timeout {
+                    SyncTimeoutPolicy::CommitLocally => Ok(()),
+                    SyncTimeoutPolicy::Error => Err(err),
+                };
+            }
+            st = self
+                .acked
+                .wait_timeout(st, deadline - now)
+                .map(|(g, _)| g)
+                .unwrap_or_else(|e| e.into_inner().0);
+            // Reconfigured (or switched off) while waiting.
+            if st.sync.as_ref() != Some(&sync) {
+                return Ok(());
+            }
+        }
+    }
+
+    pub fn statistics(&self) -> ReplicationStatistics {
+        let st = self.lock();
+        let now = std::time::Instant::now();
+        let replicas = st
+            .replicas
+            .values()
+            .map(|r| ReplicaAckStatistics {
+                name: r.name.clone(),
+                synchronous: st
+                    .sync
+                    .as_ref()
+                    .is_some_and(|s| s.standbys.matches(&r.name)),
+                acked_lsn: r.acked_lsn,
+                lag_lsn: st.last_commit_lsn.saturating_sub(r.acked_lsn),
+                ack_lag: st
+                    .pending
+                    .iter()
+                    .find(|(lsn, _)| *lsn > r.acked_lsn)
+                    .map_or(Duration::ZERO, |(_, at)| now.duration_since(*at)),
+            })
+            .collect();
+        ReplicationStatistics {
+            last_commit_lsn: st.last_commit_lsn,
+            replicas,
+            synchronous_commits: st.synchronous_commits,
+            synchronous_timeouts: st.synchronous_timeouts,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, TrackerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A replica's place in [`ReplicationTracker`], removed on drop.
+#[derive(Debug)]
+pub struct ReplicaRegistration {
+    tracker: Arc<ReplicationTracker>,
+    id: u64,
+}
+
+impl ReplicaRegistration {
+    /// Records that the replica applied everything up to `lsn`.
+    pub fn ack(&self, lsn: u64) {
+        let mut st = self.tracker.lock();
+        if let Some(r) = st.replicas.get_mut(&self.id) {
+            r.acked_lsn = r.acked_lsn.max(lsn);
+        }
+        st.prune_pending();
+        drop(st);
+        self.tracker.acked.notify_all();
+    }
+}
+
+impl Drop for ReplicaRegistration {
+    fn drop(&mut self) {
+        let mut st = self.tracker.lock();
+        st.replicas.remove(&self.id);
+        st.prune_pending();
+    }
+}
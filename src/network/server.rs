@@ -10,15 +10,23 @@ use thiserror::Error;
 use tracing::{info, warn};
 
 use crate::common::Result;
+use crate::network::auth::Authentication;
 use crate::network::engine::EngineHandle;
 use crate::network::framing::MAX_FRAME_PAYLOAD_BYTES;
 use crate::network::metrics::{QuicMetrics, QuicNetworkMetrics};
-use crate::network::query_stream::{run_connection_streams, StreamPolicy};
+use crate::network::query_stream::{
+    authenticate_connection, run_connection_streams, StreamPolicy, DEFAULT_COMPRESSION_MIN_BYTES,
+    DEFAULT_MAX_PIPELINED_REQUESTS,
+};
 use crate::network::transport::build_rustdb_transport_config;
 
 /// ALPN token for RustDB over QUIC (must match the client). See `docs/network/quic-and-quinn.md`.
 pub const ALPN_RUSTDB_V1: &[u8] = b"rustdb-v1";
 
+/// ALPN token of [`ALPN_RUSTDB_V1`] with lz4 frame compression: a client offering it first asks
+/// for compression, and a server with [`ServerConfig::compression`] accepts by selecting it.
+pub const ALPN_RUSTDB_V1_LZ4: &[u8] = b"rustdb-v1-lz4";
+
 /// Server configuration
 #[derive(Debug, Clone)]
 pub struct ServerConfig {
@@ -38,6 +46,19 @@ pub struct ServerConfig {
     pub max_result_rows: usize,
     /// Max application payload per frame (cannot exceed [`MAX_FRAME_PAYLOAD_BYTES`]).
     pub max_frame_payload_bytes: u32,
+    /// Sessions idle this long inside an open transaction are rolled back and their stream
+    /// closed; `None` never reaps them.
+    pub idle_in_transaction_timeout: Option<std::time::Duration>,
+    /// Requests a stream may send ahead of their answers (see
+    /// [`StreamPolicy::max_pipelined_requests`]).
+    pub max_pipelined_requests: usize,
+    /// Offer lz4 frame compression ([`ALPN_RUSTDB_V1_LZ4`]) to clients that ask for it.
+    pub compression: bool,
+    /// Payloads below this size are sent uncompressed on a compressed connection.
+    pub compression_min_bytes: usize,
+    /// When set, every connection must open with an `Authenticate` frame accepted by these
+    /// rules (within `connection_timeout`) and is served as the authenticated user.
+    pub authentication: Option<Arc<Authentication>>,
 }
 
 impl Default for ServerConfig {
@@ -53,6 +74,11 @@ impl Default for ServerConfig {
             max_sql_bytes: 1024 * 1024,
             max_result_rows: 65_536,
             max_frame_payload_bytes: MAX_FRAME_PAYLOAD_BYTES,
+            idle_in_transaction_timeout: None,
+            max_pipelined_requests: DEFAULT_MAX_PIPELINED_REQUESTS,
+            compression: true,
+            compression_min_bytes: DEFAULT_COMPRESSION_MIN_BYTES,
+            authentication: None,
         }
     }
 }
@@ -65,6 +91,9 @@ impl From<&ServerConfig> for StreamPolicy {
             max_sql_bytes: c.max_sql_bytes,
             max_result_rows: c.max_result_rows,
             max_frame_payload_bytes: c.max_frame_payload_bytes.min(MAX_FRAME_PAYLOAD_BYTES),
+            idle_in_transaction_timeout: c.idle_in_transaction_timeout,
+            max_pipelined_requests: c.max_pipelined_requests,
+            compression_min_bytes: c.compression_min_bytes,
         }
     }
 }
@@ -116,7 +145,12 @@ pub fn build_quinn_server_config(
         .with_no_client_auth()
         .with_single_cert(vec![cert_der], PrivateKeyDer::Pkcs8(key_der))?;
 
-    rustls_config.alpn_protocols = vec![ALPN_RUSTDB_V1.to_vec()];
+    // The server's order wins: compression whenever the client offers it too.
+    rustls_config.alpn_protocols = if config.compression {
+        vec![ALPN_RUSTDB_V1_LZ4.to_vec(), ALPN_RUSTDB_V1.to_vec()]
+    } else {
+        vec![ALPN_RUSTDB_V1.to_vec()]
+    };
 
     let quic_crypto = QuicServerConfig::try_from(rustls_config)?;
     let mut server_config = quinn::ServerConfig::with_crypto(Arc::new(quic_crypto));
@@ -228,7 +262,12 @@ impl QuicServer {
             let eng = engine.clone();
             let pol = policy.clone();
             let m = metrics.clone();
-            tokio::spawn(handle_incoming_quic(incoming, m, eng, pol));
+            let auth = self
+                .config
+                .authentication
+                .clone()
+                .map(|a| (a, self.config.connection_timeout));
+            tokio::spawn(handle_incoming_quic(incoming, m, eng, pol, auth));
         }
 
         Ok(())
@@ -240,6 +279,7 @@ async fn handle_incoming_quic(
     metrics: QuicMetrics,
     engine: Arc<dyn EngineHandle>,
     policy: Arc<StreamPolicy>,
+    authentication: Option<(Arc<Authentication>, std::time::Duration)>,
 ) {
     let conn = match incoming.await {
         Ok(c) => c,
@@ -257,7 +297,36 @@ async fn handle_incoming_quic(
         "QUIC connection established (Variant A: bidi streams per query)"
     );
 
-    run_connection_streams(conn.clone(), engine, policy, Some(metrics.clone())).await;
+    let mut user = None;
+    if let Some((authentication, timeout)) = authentication {
+        match authenticate_connection(&conn, authentication, &policy, timeout).await {
+            Some(u) => user = Some(u),
+            None => {
+                metrics
+                    .authentication_failures
+                    .fetch_add(1, Ordering::Relaxed);
+                metrics.active_connections.fetch_sub(1, Ordering::Relaxed);
+                conn.close(quinn::VarInt::from_u32(1), b"authentication failed");
+                return;
+            }
+        }
+    }
+
+    let compressed = negotiated_compression(&conn);
+    if compressed {
+        metrics
+            .compressed_connections
+            .fetch_add(1, Ordering::Relaxed);
+    }
+    run_connection_streams(
+        conn.clone(),
+        engine,
+        policy,
+        Some(metrics.clone()),
+        user,
+        compressed,
+    )
+    .await;
     metrics.active_connections.fetch_sub(1, Ordering::Relaxed);
     info!(
         remote = %conn.remote_address(),
@@ -265,6 +334,15 @@ async fn handle_incoming_quic(
     );
 }
 
+/// Whether the TLS handshake of `conn` selected [`ALPN_RUSTDB_V1_LZ4`], so both peers may send
+/// compressed frames.
+pub fn negotiated_compression(conn: &quinn::Connection) -> bool {
+    conn.handshake_data()
+        .and_then(|data| data.downcast::<quinn::crypto::rustls::HandshakeData>().ok())
+        .and_then(|data| data.protocol)
+        .is_some_and(|protocol| protocol == ALPN_RUSTDB_V1_LZ4)
+}
+
 /// Database server (pre-QUIC stub API).
 pub struct Server {
     config: ServerConfig,
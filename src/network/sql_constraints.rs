@@ -397,6 +397,9 @@ mod tests {
             foreign_keys: vec![],
             check_constraints: vec![],
             secondary_indexes: vec![],
+            foreign: None,
+            encrypt_by: None,
+            tenant: None,
         }
     }
 
@@ -419,6 +422,9 @@ mod tests {
             foreign_keys: vec![],
             check_constraints: vec![],
             secondary_indexes: vec![],
+            foreign: None,
+            encrypt_by: None,
+            tenant: None,
         };
         let mut t = Tuple::new(1);
         t.set_value("col1", ColumnValue::new(DataType::Integer(99)));
@@ -442,6 +448,9 @@ mod tests {
             foreign_keys: vec![],
             check_constraints: vec![],
             secondary_indexes: vec![],
+            foreign: None,
+            encrypt_by: None,
+            tenant: None,
         };
         let mut t = Tuple::new(1);
         t.set_value("ID", ColumnValue::new(DataType::Integer(7)));
@@ -499,6 +508,9 @@ mod tests {
             foreign_keys: vec![],
             check_constraints: vec![],
             secondary_indexes: vec![],
+            foreign: None,
+            encrypt_by: None,
+            tenant: None,
         });
         let fk = ForeignKeyConstraintDef {
             name: "fk".to_string(),
@@ -528,6 +540,9 @@ mod tests {
             }],
             check_constraints: vec![],
             secondary_indexes: vec![],
+            foreign: None,
+            encrypt_by: None,
+            tenant: None,
         };
         cat.register_schema(child.clone());
 
@@ -583,6 +598,9 @@ mod tests {
             }],
             check_constraints: vec![],
             secondary_indexes: vec![],
+            foreign: None,
+            encrypt_by: None,
+            tenant: None,
         };
         cat.register_schema(child.clone());
 
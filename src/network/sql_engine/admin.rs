@@ -0,0 +1,225 @@
+//! Operational commands through SQL: `CHECKPOINT`, `FLUSH LOGS`, `SHOW ENGINE STATUS` and
+//! `PROFILE CPU`.
+//!
+//! They do the same as the `rustdb_tool` subcommands, but on the running server, so any client
+//! can script them. `SET GLOBAL` is parsed as a plain `SET` (see `settings`), which is server-wide.
+//!
+//! `PROFILE CPU FOR <n> SECONDS` samples every thread of the server while the session waits, and
+//! writes the profile to `<data_dir>/profiles/` (needs the `cpu-profiling` feature).
+
+use super::{
+    lock_poisoned_engine, persistent_indexes, rows_to_engine_output, EngineOutput, SqlEngineState,
+};
+use crate::common::memory_tracking::{memory_by_tag, tracking_active};
+use crate::common::types::{ColumnValue, DataType, Row};
+use crate::common::DurabilityMode;
+use crate::debug::profiler::{CpuProfileFormat, Profiler};
+use crate::network::engine::{engine_error_code, EngineError};
+use crate::network::sql_engine_wal::{SqlEngineWal, WAL_ARCHIVE_JOB};
+use crate::parser::ast::ProfileFormat;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Longest `PROFILE CPU` capture, in seconds.
+const MAX_PROFILE_SECONDS: u64 = 300;
+
+/// CPU profiles, relative to the data directory.
+const PROFILE_DIR: &str = "profiles";
+
+fn require_wal<'a>(
+    state: &'a SqlEngineState,
+    command: &str,
+) -> Result<&'a SqlEngineWal, EngineError> {
+    state.wal.as_ref().ok_or_else(|| {
+        EngineError::new(
+            engine_error_code::UNSUPPORTED_SQL,
+            format!("{command} requires the WAL"),
+        )
+    })
+}
+
+/// `CHECKPOINT`: flush dirty heap pages, append a checkpoint record and checkpoint the stored
+/// indexes.
+pub(super) fn checkpoint(state: &SqlEngineState) -> Result<EngineOutput, EngineError> {
+    require_wal(state, "CHECKPOINT")?
+        .checkpoint()
+        .map_err(|e| EngineError::new(engine_error_code::INTERNAL, e.to_string()))?;
+    persistent_indexes::checkpoint(state)?;
+    Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+}
+
+/// `FLUSH LOGS`: write buffered WAL records and start a new WAL segment (archived right away
+/// when WAL archiving is configured).
+pub(super) fn flush_logs(state: &SqlEngineState) -> Result<EngineOutput, EngineError> {
+    require_wal(state, "FLUSH LOGS")?.rotate()?;
+    // Archive the closed segment now rather than on the next tick (when archiving is on).
+    let _ = state.background_jobs.run_now(WAL_ARCHIVE_JOB);
+    Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+}
+
+/// `PROFILE CPU FOR <n> SECONDS [FORMAT ...]`: one row with the written file and sample count.
+pub(super) fn profile_cpu(
+    state: &SqlEngineState,
+    seconds: u64,
+    format: ProfileFormat,
+) -> Result<EngineOutput, EngineError> {
+    if !cfg!(feature = "cpu-profiling") {
+        return Err(EngineError::new(
+            engine_error_code::UNSUPPORTED_SQL,
+            "PROFILE CPU requires a server built with the `cpu-profiling` feature",
+        ));
+    }
+    if !(1..=MAX_PROFILE_SECONDS).contains(&seconds) {
+        return Err(EngineError::new(
+            engine_error_code::INVALID_PARAMETER,
+            format!("profile duration must be between 1 and {MAX_PROFILE_SECONDS} seconds"),
+        ));
+    }
+    let format = match format {
+        ProfileFormat::Flamegraph => CpuProfileFormat::Flamegraph,
+        ProfileFormat::Pprof => CpuProfileFormat::Pprof,
+    };
+    let internal = |e: String| EngineError::new(engine_error_code::INTERNAL, e);
+    let profile = Profiler::capture_cpu_profile(Duration::from_secs(seconds), format)
+        .map_err(|e| internal(e.to_string()))?;
+
+    let dir = state.data_dir.join(PROFILE_DIR);
+    let started_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let file = dir.join(format!("cpu-{started_ms}.{}", format.extension()));
+    std::fs::create_dir_all(&dir)
+        .and_then(|()| std::fs::write(&file, &profile.data))
+        .map_err(|e| internal(format!("cannot write {}: {e}", file.display())))?;
+
+    let mut row = Row::new();
+    row.set_value(
+        "file",
+        ColumnValue::new(DataType::Varchar(format!("'{}'", file.display()))),
+    );
+    row.set_value(
+        "format",
+        ColumnValue::new(DataType::Varchar(format!("'{}'", format.extension()))),
+    );
+    row.set_value(
+        "seconds",
+        ColumnValue::new(DataType::BigInt(profile.duration.as_secs() as i64)),
+    );
+    row.set_value(
+        "samples",
+        ColumnValue::new(DataType::BigInt(profile.samples as i64)),
+    );
+    row.set_value(
+        "bytes",
+        ColumnValue::new(DataType::BigInt(profile.data.len() as i64)),
+    );
+    rows_to_engine_output(vec![row])
+}
+
+/// `SHOW ENGINE STATUS`: one `(section, name, value)` row per counter.
+pub(super) fn show_engine_status(state: &SqlEngineState) -> Result<EngineOutput, EngineError> {
+    let mut entries: Vec<(&str, &str, String)> = vec![
+        (
+            "engine",
+            "durability",
+            match state.durability {
+                DurabilityMode::Safe => "safe",
+                DurabilityMode::Fast => "fast",
+            }
+            .to_string(),
+        ),
+        ("engine", "wal_enabled", state.wal.is_some().to_string()),
+        (
+            "transactions",
+            "prepared",
+            state
+                .prepared_transactions
+                .lock()
+                .map_err(|_| lock_poisoned_engine())?
+                .len()
+                .to_string(),
+        ),
+        (
+            "transactions",
+            "exported_snapshots",
+            state
+                .snapshot_exports
+                .lock()
+                .map_err(|_| lock_poisoned_engine())?
+                .len()
+                .to_string(),
+        ),
+    ];
+
+    if let Some(wal) = state.wal.as_ref() {
+        let stats = wal.writer_statistics();
+        entries.extend([
+            ("wal", "current_lsn", wal.current_lsn().to_string()),
+            (
+                "wal",
+                "records_written",
+                stats.total_records_written.to_string(),
+            ),
+            (
+                "wal",
+                "bytes_written",
+                stats.total_bytes_written.to_string(),
+            ),
+            ("wal", "sync_operations", stats.sync_operations.to_string()),
+            ("wal", "file_rotations", stats.file_rotations.to_string()),
+            ("wal", "write_errors", stats.write_errors.to_string()),
+            (
+                "wal",
+                "buffered_records",
+                stats.current_buffer_size.to_string(),
+            ),
+        ]);
+        if let Some(stats) = wal.checkpoint_statistics() {
+            entries.extend([
+                ("checkpoint", "total", stats.total_checkpoints.to_string()),
+                ("checkpoint", "auto", stats.auto_checkpoints.to_string()),
+                ("checkpoint", "forced", stats.forced_checkpoints.to_string()),
+                ("checkpoint", "failed", stats.failed_checkpoints.to_string()),
+                (
+                    "checkpoint",
+                    "last_lsn",
+                    stats.last_checkpoint_lsn.to_string(),
+                ),
+                (
+                    "checkpoint",
+                    "last_time_unix",
+                    stats.last_checkpoint_time.to_string(),
+                ),
+                (
+                    "checkpoint",
+                    "flushed_pages",
+                    stats.total_flushed_pages.to_string(),
+                ),
+            ]);
+        }
+    }
+
+    entries.push((
+        "memory",
+        "resident_bytes",
+        Profiler::resident_memory_bytes().map_or_else(|| "unknown".to_string(), |b| b.to_string()),
+    ));
+    entries.push(("memory", "tracking", tracking_active().to_string()));
+    if tracking_active() {
+        let live: u64 = memory_by_tag().iter().map(|m| m.live_bytes).sum();
+        entries.push(("memory", "tracked_live_bytes", live.to_string()));
+    }
+
+    let text = |s: &str| ColumnValue::new(DataType::Varchar(format!("'{s}'")));
+    let rows = entries
+        .into_iter()
+        .map(|(section, name, value)| {
+            let mut row = Row::new();
+            row.set_value("section", text(section));
+            row.set_value("name", text(name));
+            row.set_value("value", text(&value));
+            row
+        })
+        .collect();
+    rows_to_engine_output(rows)
+}
@@ -2,7 +2,7 @@
 
 use super::{
     expr_to_column_value, lock_poisoned_engine, map_db_err, persist_catalog,
-    rebuild_all_constraint_runtime, sql_type_to_column_datatype, table_page_manager,
+    rebuild_all_constraint_runtime, shredding, sql_type_to_column_datatype, table_page_manager,
     validate_new_table_fks, EngineError, SqlEngineState,
 };
 use crate::catalog::schema::{
@@ -23,7 +23,7 @@ mod expr_walk {
         match expr {
             Expression::Identifier(s) => s == col,
             Expression::QualifiedIdentifier { column, .. } => column == col,
-            Expression::Literal(_) => false,
+            Expression::Literal(_) | Expression::Parameter(_) => false,
             Expression::BinaryOp { left, right, .. } => {
                 mentions_column(left, col) || mentions_column(right, col)
             }
@@ -109,6 +109,7 @@ fn rename_column_in_expression(expr: &Expression, table: &str, old: &str, new: &
             }
         }
         Expression::Literal(l) => Expression::Literal(l.clone()),
+        Expression::Parameter(n) => Expression::Parameter(*n),
         Expression::BinaryOp { left, op, right } => Expression::BinaryOp {
             left: Box::new(rename_column_in_expression(left, table, old, new)),
             op: op.clone(),
@@ -185,20 +186,25 @@ where
     F: FnMut(&mut Tuple) -> Result<(), EngineError>,
 {
     let pm = table_page_manager(state, table)?;
-    let snapshot = pm.lock().select(None).map_err(map_db_err)?;
+    // Rows are resealed under the altered schema's ENCRYPT BY column.
+    {
+        let cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
+        shredding::refresh_tables(&state.crypto_shredding, &cat)?;
+    }
+    let snapshot = pm.write().select(None).map_err(map_db_err)?;
     for (rid, data) in snapshot {
-        let mut t = Tuple::from_bytes(&data).map_err(map_db_err)?;
+        let mut t = shredding::decode_tuple(state, &data).map_err(map_db_err)?;
         f(&mut t)?;
-        let bytes = t.to_bytes().map_err(map_db_err)?;
-        pm.lock().update(rid, &bytes).map_err(map_db_err)?;
+        let bytes = shredding::tuple_bytes(state, table, &t)?;
+        pm.write().update(rid, &bytes).map_err(map_db_err)?;
     }
-    pm.lock().flush_dirty_pages().map_err(map_db_err)?;
+    pm.write().flush_dirty_pages().map_err(map_db_err)?;
     Ok(())
 }
 
 fn row_count(state: &SqlEngineState, table: &str) -> Result<usize, EngineError> {
     let pm = table_page_manager(state, table)?;
-    let n = pm.lock().select(None).map_err(map_db_err)?.len();
+    let n = pm.write().select(None).map_err(map_db_err)?.len();
     Ok(n)
 }
 
@@ -393,6 +399,11 @@ pub(super) fn drop_column(
             "cannot DROP COLUMN {col}: used in PRIMARY KEY, UNIQUE, or FOREIGN KEY; drop constraints first"
         )));
     }
+    if schema.encrypt_by.as_deref() == Some(col) {
+        return Err(err(format!(
+            "cannot DROP COLUMN {col}: rows of {table} are encrypted by it"
+        )));
+    }
     if column_referenced_by_fk(&cat, table, col) {
         return Err(err(format!(
             "cannot DROP COLUMN {col}: referenced by a FOREIGN KEY from another table"
@@ -483,6 +494,9 @@ pub(super) fn rename_column(
         for chk in &mut schema.check_constraints {
             chk.expr = rename_column_in_expression(&chk.expr, table, old_name, new_name);
         }
+        if schema.encrypt_by.as_deref() == Some(old_name) {
+            schema.encrypt_by = Some(new_name.to_string());
+        }
     }
     rewrite_tuples(state, table, |t: &mut Tuple| {
         if let Some(v) = t.values.remove(old_name) {
@@ -531,6 +545,12 @@ pub(super) fn rename_table(
                 format!("rename heap file: {e}"),
             )
         })?;
+        crate::storage::tiering::ColdTier::rename_files(&old_path, &new_path).map_err(|e| {
+            EngineError::new(
+                engine_error_code::INTERNAL,
+                format!("rename cold extents: {e}"),
+            )
+        })?;
     }
 
     {
@@ -623,6 +643,13 @@ pub(super) fn modify_column(
     let old_snap = {
         let cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
         let sch = table_must_exist(&cat, table)?;
+        if sch.encrypt_by.as_deref() == Some(col_name.as_str())
+            && !shredding::is_subject_type(&new_col.data_type)
+        {
+            return Err(unsupported(format!(
+                "{col_name} encrypts the rows of {table} and must stay a text or integer column"
+            )));
+        }
         sch.columns
             .iter()
             .find(|x| x.name == col_name)
@@ -632,9 +659,9 @@ pub(super) fn modify_column(
 
     if new_col.not_null && !old_snap.not_null {
         let pm = table_page_manager(state, table)?;
-        let snapshot = pm.lock().select(None).map_err(map_db_err)?;
+        let snapshot = pm.write().select(None).map_err(map_db_err)?;
         for (_, data) in snapshot {
-            let t = Tuple::from_bytes(&data).map_err(map_db_err)?;
+            let t = shredding::decode_tuple(state, &data).map_err(map_db_err)?;
             let bad = match t.values.get(&col_name) {
                 None => true,
                 Some(v) => v.is_null,
@@ -0,0 +1,261 @@
+//! Read auditing: who read which sensitive table, and the `rustdb_stat_audit` view.
+//!
+//! `audit.select_tables = 'customers, payments=0.1'` marks tables whose reads are audited. Each
+//! finished `SELECT` (or set operation) reading one of them appends an event to
+//! `<data_dir>/audit/select.jsonl`: time, the session's role, the audited tables read, the
+//! statement, its row count and error. To keep busy tables from flooding the log, a table may
+//! carry a sampling rate (a statement is logged with the highest rate among the audited tables
+//! it reads, and its event records that rate), `audit.exempt_roles` skips trusted roles such as
+//! ETL jobs, and `audit.max_events_per_second` caps the events written. Sampled-out, exempted
+//! and suppressed reads are still counted per table in `rustdb_stat_audit`.
+//!
+//! The role is the one the session holds (`SET role` or the authenticated user, see `roles`);
+//! sessions without one are audited as `none`.
+
+use super::{lock_poisoned_engine, EngineError, EngineOutput, SqlEngineState};
+use crate::common::config::{parse_audit_tables, AuditConfig};
+use crate::common::types::{ColumnValue, DataType, Row};
+use crate::network::engine::SessionContext;
+use crate::network::sql_engine::RoleSlot;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// Audit log, relative to the data directory.
+const AUDIT_FILE: &str = "audit/select.jsonl";
+
+/// Read auditing state of one engine.
+#[derive(Default)]
+pub(super) struct SelectAudit {
+    /// Checked before anything else, so engines without audited tables pay one load per read.
+    enabled: AtomicBool,
+    policy: RwLock<Arc<AuditPolicy>>,
+    log: Mutex<AuditLog>,
+    counters: Mutex<HashMap<String, AuditCounters>>,
+    sample_state: AtomicU64,
+}
+
+#[derive(Default)]
+struct AuditPolicy {
+    tables: BTreeMap<String, f64>,
+    exempt_roles: BTreeSet<String>,
+    max_events_per_second: u64,
+}
+
+#[derive(Default)]
+struct AuditLog {
+    file: Option<File>,
+    /// Unix second of the current rate window and the events written in it.
+    window: u64,
+    window_events: u64,
+}
+
+#[derive(Default)]
+struct AuditCounters {
+    logged: u64,
+    sampled_out: u64,
+    exempted: u64,
+    suppressed: u64,
+}
+
+/// A read picked for auditing, logged once the statement finishes.
+pub(crate) struct AuditedRead {
+    tables: Vec<String>,
+    sample_rate: f64,
+}
+
+impl std::fmt::Debug for AuditedRead {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(&self.tables).finish()
+    }
+}
+
+/// Applies the `audit.*` parameters.
+pub(super) fn configure(audit: &SelectAudit, config: &AuditConfig) -> Result<(), String> {
+    let policy = AuditPolicy {
+        tables: parse_audit_tables(&config.select_tables)?,
+        exempt_roles: config
+            .exempt_roles
+            .split(',')
+            .map(|r| r.trim().to_ascii_lowercase())
+            .filter(|r| !r.is_empty())
+            .collect(),
+        max_events_per_second: config.max_events_per_second,
+    };
+    let enabled = !policy.tables.is_empty();
+    // A poisoned policy is replaced whole, so its state does not matter.
+    let mut current = audit.policy.write().unwrap_or_else(|e| e.into_inner());
+    *current = Arc::new(policy);
+    audit.enabled.store(enabled, Ordering::Release);
+    Ok(())
+}
+
+/// Decides whether a read of `tables` (computed only when auditing is on) by `ctx` is logged.
+pub(super) fn audited_read(
+    audit: &SelectAudit,
+    ctx: &SessionContext,
+    tables: impl FnOnce() -> Vec<String>,
+) -> Option<AuditedRead> {
+    if !audit.enabled.load(Ordering::Acquire) {
+        return None;
+    }
+    let policy = audit.policy.read().ok()?.clone();
+    let audited: Vec<String> = tables()
+        .into_iter()
+        .map(|t| t.to_ascii_lowercase())
+        .filter(|t| policy.tables.contains_key(t))
+        .collect();
+    if audited.is_empty() {
+        return None;
+    }
+    let role = ctx.role.as_ref().map_or("none", RoleSlot::role);
+    if policy.exempt_roles.contains(role) {
+        count(audit, &audited, |c| c.exempted += 1);
+        return None;
+    }
+    let sample_rate = audited.iter().map(|t| policy.tables[t]).fold(0.0, f64::max);
+    if sample_rate < 1.0 && next_sample(audit) >= sample_rate {
+        count(audit, &audited, |c| c.sampled_out += 1);
+        return None;
+    }
+    Some(AuditedRead {
+        tables: audited,
+        sample_rate,
+    })
+}
+
+/// Uniform draw in `[0, 1)` (splitmix64 over a shared counter).
+fn next_sample(audit: &SelectAudit) -> f64 {
+    let mut z = audit
+        .sample_state
+        .fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed)
+        .wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+fn count(audit: &SelectAudit, tables: &[String], bump: impl Fn(&mut AuditCounters)) {
+    // Like statistics, auditing counters are best effort.
+    if let Ok(mut counters) = audit.counters.lock() {
+        for table in tables {
+            bump(counters.entry(table.clone()).or_default());
+        }
+    }
+}
+
+/// Logs the read `ctx` was picked for (see [`audited_read`]) with the statement's outcome.
+pub(super) fn record(
+    audit: &SelectAudit,
+    data_dir: &Path,
+    ctx: &mut SessionContext,
+    sql: &str,
+    result: &Result<EngineOutput, EngineError>,
+) {
+    let Some(read) = ctx.audited_read.take() else {
+        return;
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let max = audit.policy.read().map_or(0, |p| p.max_events_per_second);
+    let Ok(mut log) = audit.log.lock() else {
+        return;
+    };
+    if log.window != now.as_secs() {
+        log.window = now.as_secs();
+        log.window_events = 0;
+    }
+    if max > 0 && log.window_events >= max {
+        drop(log);
+        count(audit, &read.tables, |c| c.suppressed += 1);
+        return;
+    }
+    let (rows, error) = match result {
+        Ok(EngineOutput::ResultSet { rows, .. }) => (rows.len() as u64, None),
+        Ok(EngineOutput::ExecutionOk { rows_affected }) => (*rows_affected, None),
+        Err(e) => (0, Some(e.message.as_str())),
+    };
+    let event = serde_json::json!({
+        "time_ms": now.as_millis() as u64,
+        "role": ctx.role.as_ref().map_or("none", RoleSlot::role),
+        "tables": read.tables,
+        "sample_rate": read.sample_rate,
+        "sql": sql,
+        "rows": rows,
+        "error": error,
+    });
+    let path = data_dir.join(AUDIT_FILE);
+    if log.file.is_none() {
+        let opened = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| OpenOptions::new().create(true).append(true).open(&path));
+        match opened {
+            Ok(file) => log.file = Some(file),
+            Err(e) => {
+                warn!(file = %path.display(), error = %e, "cannot open audit log");
+                return;
+            }
+        }
+    }
+    let written = match log.file.as_mut() {
+        Some(file) => writeln!(file, "{event}"),
+        None => return,
+    };
+    match written {
+        Ok(()) => {
+            log.window_events += 1;
+            drop(log);
+            count(audit, &read.tables, |c| c.logged += 1);
+        }
+        Err(e) => warn!(file = %path.display(), error = %e, "cannot write audit event"),
+    }
+}
+
+/// `rustdb_stat_audit`: per audited (or formerly audited) table, its sampling rate and how many
+/// reads were logged, sampled out, exempted and suppressed by the rate cap.
+pub(super) fn audit_rows(state: &SqlEngineState) -> Result<Vec<Row>, EngineError> {
+    let audit = &state.select_audit;
+    let policy = audit
+        .policy
+        .read()
+        .map_err(|_| lock_poisoned_engine())?
+        .clone();
+    let counters = audit.counters.lock().map_err(|_| lock_poisoned_engine())?;
+    let mut tables: Vec<&String> = counters.keys().chain(policy.tables.keys()).collect();
+    tables.sort();
+    tables.dedup();
+    let big_int = |n: u64| ColumnValue::new(DataType::BigInt(n as i64));
+    Ok(tables
+        .into_iter()
+        .map(|table| {
+            let c = counters.get(table);
+            let mut row = Row::new();
+            row.set_value(
+                "table_name",
+                ColumnValue::new(DataType::Varchar(format!("'{table}'"))),
+            );
+            row.set_value(
+                "sample_rate",
+                policy
+                    .tables
+                    .get(table)
+                    .map_or_else(ColumnValue::null, |&r| {
+                        ColumnValue::new(DataType::Double(r))
+                    }),
+            );
+            row.set_value("logged", big_int(c.map_or(0, |c| c.logged)));
+            row.set_value("sampled_out", big_int(c.map_or(0, |c| c.sampled_out)));
+            row.set_value("exempted", big_int(c.map_or(0, |c| c.exempted)));
+            row.set_value("suppressed", big_int(c.map_or(0, |c| c.suppressed)));
+            row
+        })
+        .collect())
+}
@@ -0,0 +1,121 @@
+//! Automatic `ANALYZE` of tables whose statistics are out of date: the `auto_analyze`
+//! background job.
+//!
+//! Each commit adds the rows it inserted, updated or deleted to the changed-row counter of their
+//! tables. With `auto_analyze.interval_secs` set (the default), the job looks at the counters
+//! every that many seconds and analyzes each table with more than
+//! `auto_analyze.threshold + auto_analyze.scale_factor * rows` changed rows, `rows` being the
+//! table's row count at its last `ANALYZE` (0 for a table never analyzed, so the check after a
+//! bulk load into a new table analyzes it). An `ANALYZE`, automatic or not, takes the changes it
+//! saw off the counter; `rustdb_stat_tables` shows what is left. Counters are kept in memory and
+//! start from zero after a restart.
+
+use super::{lock_poisoned_engine, maintenance, EngineError, SqlEngineState};
+use crate::common::config::AutoAnalyzeConfig;
+use crate::network::engine::{engine_error_code, UndoEntry};
+use crate::network::sql_engine_wal::AUTO_ANALYZE_JOB;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Rows changed in each table since its last `ANALYZE`.
+#[derive(Default)]
+pub(super) struct ModifiedRows(Mutex<HashMap<String, u64>>);
+
+/// A transaction with undo log `undo` commits.
+pub(super) fn record_commit(counts: &ModifiedRows, undo: &[UndoEntry]) {
+    if undo.is_empty() {
+        return;
+    }
+    let Ok(mut tables) = counts.0.lock() else {
+        return;
+    };
+    for entry in undo {
+        let (UndoEntry::Insert { table, .. }
+        | UndoEntry::Delete { table, .. }
+        | UndoEntry::Update { table, .. }) = entry;
+        match tables.get_mut(table) {
+            Some(n) => *n += 1,
+            None => {
+                tables.insert(table.clone(), 1);
+            }
+        }
+    }
+}
+
+/// Rows changed in `table` since its last `ANALYZE`.
+pub(super) fn modified_rows(counts: &ModifiedRows, table: &str) -> u64 {
+    counts
+        .0
+        .lock()
+        .map_or(0, |tables| tables.get(table).copied().unwrap_or(0))
+}
+
+/// `ANALYZE` of `table` saw `changed` of its changed rows (the count when it started).
+pub(super) fn analyzed(counts: &ModifiedRows, table: &str, changed: u64) {
+    if let Ok(mut tables) = counts.0.lock() {
+        if let Some(n) = tables.get_mut(table) {
+            *n = n.saturating_sub(changed);
+            if *n == 0 {
+                tables.remove(table);
+            }
+        }
+    }
+}
+
+fn auto_analyze_config(state: &SqlEngineState) -> Result<AutoAnalyzeConfig, EngineError> {
+    Ok(state
+        .settings
+        .read()
+        .map_err(|_| lock_poisoned_engine())?
+        .config()
+        .auto_analyze
+        .clone())
+}
+
+/// Registers the auto-analyze job unless `auto_analyze.interval_secs` is 0 (WAL-backed engines
+/// only, like the other background jobs).
+pub(super) fn setup(state: &Arc<SqlEngineState>) -> Result<(), EngineError> {
+    let config = auto_analyze_config(state)?;
+    let Some(wal) = state.wal.as_ref() else {
+        return Ok(());
+    };
+    if config.interval_secs == 0 || state.background_jobs.job_status(AUTO_ANALYZE_JOB).is_some() {
+        return Ok(());
+    }
+    wal.setup_auto_analyze(state.clone(), Duration::from_secs(config.interval_secs))
+        .map_err(|e| {
+            EngineError::new(
+                engine_error_code::INTERNAL,
+                format!("auto-analyze job: {e}"),
+            )
+        })
+}
+
+/// One check: analyzes the local tables with too many changed rows; returns their names.
+pub(crate) fn run_auto_analyze(state: &SqlEngineState) -> Result<Vec<String>, EngineError> {
+    let config = auto_analyze_config(state)?;
+    let rows = maintenance::analyzed_rows(state)?;
+    let tables = maintenance::local_tables(state, None)?;
+    {
+        // Counters of dropped tables.
+        let mut counts = state
+            .modified_rows
+            .0
+            .lock()
+            .map_err(|_| lock_poisoned_engine())?;
+        counts.retain(|t, _| tables.contains(t));
+    }
+    let due: Vec<String> = tables
+        .into_iter()
+        .filter(|table| {
+            let limit = config.threshold as f64
+                + config.scale_factor * rows.get(table).copied().unwrap_or(0) as f64;
+            modified_rows(&state.modified_rows, table) as f64 > limit
+        })
+        .collect();
+    if !due.is_empty() {
+        maintenance::analyze_tables(state, due.clone(), true)?;
+    }
+    Ok(due)
+}
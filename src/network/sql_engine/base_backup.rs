@@ -0,0 +1,104 @@
+//! Base backup staging for [`super::SqlEngineState`] (replica provisioning, see
+//! [`crate::network::replication`]).
+//!
+//! Writers are held off while the data directory is copied: the global `storage_access` lock
+//! (DDL, `ROLLBACK`, `INSERT ... SELECT`, row-locked DML), every table storage lock (the other
+//! DML paths) and, while its heap file is flushed and copied, each page manager (`COMMIT`
+//! flushes). Rows of transactions that are open during the copy are already on the heap; the
+//! copied WAL still lists those transactions as active, so recovery on the replica undoes them.
+//! The WAL is copied last so it covers every heap change in the copy.
+//!
+//! Heap files are copied with the pages of their cold extents (see `tiering`) written back in,
+//! and without their tier maps, so a backup never depends on the cold tablespace.
+
+use super::{
+    acquire_table_storage_write_lock, lock_poisoned_engine, map_db_err, table_storage_lock_arc,
+    EngineError, SqlEngineState,
+};
+use crate::network::engine::engine_error_code;
+use crate::network::replication::{BaseBackup, BaseBackupBuilder};
+use crate::storage::tiering::ColdTier;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+/// WAL directory relative to the data directory.
+const WAL_DIR: &str = ".rustdb/wal";
+
+pub(super) fn stage_base_backup(state: &SqlEngineState) -> Result<BaseBackup, EngineError> {
+    let _storage = state
+        .storage_access
+        .write()
+        .map_err(|_| lock_poisoned_engine())?;
+    let mut tables: BTreeSet<String> = state
+        .catalog
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?
+        .table_names()
+        .into_iter()
+        .collect();
+    tables.extend(
+        state
+            .table_storage_locks
+            .lock()
+            .map_err(|_| lock_poisoned_engine())?
+            .keys()
+            .cloned(),
+    );
+    let locks = tables
+        .iter()
+        .map(|t| table_storage_lock_arc(state, t))
+        .collect::<Result<Vec<_>, _>>()?;
+    let _table_writes = locks
+        .iter()
+        .zip(&tables)
+        .map(|(l, t)| acquire_table_storage_write_lock(l, t))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut heaps: HashMap<String, Arc<crate::storage::page_manager::PageManagerLock>> = state
+        .table_page_managers
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?
+        .iter()
+        .map(|(t, pm)| (format!("{t}.tbl"), pm.clone()))
+        .collect();
+    heaps.insert(
+        "default.tbl".to_string(),
+        state.default_page_manager.clone(),
+    );
+
+    let mut builder = BaseBackupBuilder::new(&state.data_dir).map_err(backup_io_error)?;
+    let wal_prefix = format!("{WAL_DIR}/");
+    let files = builder.source_files().map_err(backup_io_error)?;
+    for rel in files
+        .iter()
+        .filter(|f| !f.starts_with(&wal_prefix) && !f.ends_with(".tiers"))
+    {
+        let _pm = match heaps.get(rel.as_str()) {
+            Some(pm) => {
+                let mut pm = pm.write();
+                pm.flush_dirty_pages().map_err(map_db_err)?;
+                Some(pm)
+            }
+            None => None,
+        };
+        builder.copy(rel).map_err(backup_io_error)?;
+        if rel.ends_with(".tbl") && builder.staged_path(rel).is_file() {
+            ColdTier::load(&state.data_dir.join(rel))
+                .and_then(|tiers| tiers.write_cold_pages_into(&builder.staged_path(rel)))
+                .map_err(map_db_err)?;
+        }
+    }
+    if let Some(w) = state.wal.as_ref() {
+        w.flush_buffered()?;
+    }
+    // Listed again: the flush may have rotated into a new segment.
+    let files = builder.source_files().map_err(backup_io_error)?;
+    for rel in files.iter().filter(|f| f.starts_with(&wal_prefix)) {
+        builder.copy(rel).map_err(backup_io_error)?;
+    }
+    Ok(builder.finish(WAL_DIR))
+}
+
+fn backup_io_error(e: std::io::Error) -> EngineError {
+    EngineError::new(engine_error_code::INTERNAL, format!("base backup: {e}"))
+}
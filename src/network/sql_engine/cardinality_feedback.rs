@@ -0,0 +1,51 @@
+//! Cardinality feedback of the engine ([`crate::planner::cardinality_feedback`]).
+//!
+//! Each `SELECT` (so also `EXPLAIN ANALYZE` and `INSERT ... SELECT`) counts the rows its scans
+//! and filters return, and the optimizer plans the same scans and predicates with those counts
+//! from then on; `EXPLAIN` shows the corrected `rows=`. The counts are kept in
+//! `<data_dir>/statistics/cardinality_feedback.json`, written at checkpoints and when the engine
+//! closes. Plans already in the plan cache keep the estimates they were made with.
+
+use super::{map_db_err, EngineError, SqlEngineState};
+use crate::common::types::Row;
+use crate::executor::cardinality_scope;
+use crate::planner::{CardinalityFeedback, ExecutionPlan};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Observed row counts, relative to the data directory.
+const FEEDBACK_FILE: &str = "statistics/cardinality_feedback.json";
+
+/// The store saved in `data_dir`, or an empty one if it cannot be read.
+pub(super) fn load(data_dir: &Path) -> Arc<CardinalityFeedback> {
+    match CardinalityFeedback::load(&data_dir.join(FEEDBACK_FILE)) {
+        Ok(feedback) => Arc::new(feedback),
+        Err(e) => {
+            tracing::warn!(error = %e, "cardinality feedback not loaded; starting empty");
+            Arc::new(CardinalityFeedback::new())
+        }
+    }
+}
+
+/// Runs `plan`, recording the rows of its scans and filters.
+pub(super) fn execute(
+    state: &SqlEngineState,
+    plan: &ExecutionPlan,
+) -> Result<Vec<Row>, EngineError> {
+    let scope = cardinality_scope();
+    let rows = state.executor.execute(plan).map_err(map_db_err)?;
+    for seen in scope.finish() {
+        state
+            .cardinality_feedback
+            .record(seen.fingerprint, seen.estimated_rows, seen.actual_rows);
+    }
+    Ok(rows)
+}
+
+/// Writes the store if it changed.
+pub(super) fn save(state: &SqlEngineState) -> Result<(), EngineError> {
+    state
+        .cardinality_feedback
+        .save(&state.data_dir.join(FEEDBACK_FILE))
+        .map_err(map_db_err)
+}
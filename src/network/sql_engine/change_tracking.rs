@@ -0,0 +1,269 @@
+//! Change tracking for incremental extraction: which rows of a table changed since an LSN.
+//!
+//! `change_tracking.tables = 'orders, customers'` marks tables whose changes are tracked. Each
+//! commit that changed rows of one of them appends, per changed row, its table, the commit's
+//! LSN and its primary key to `<data_dir>/change_tracking/changes.jsonl`, read back by the
+//! `rustdb_change_tracking` view. An ETL job keeps the highest `commit_lsn` it has seen and asks
+//! for the rows changed after it:
+//!
+//! ```sql
+//! SELECT pk FROM rustdb_change_tracking WHERE table_name = 'orders' AND commit_lsn > 42
+//! ```
+//!
+//! Only keys are recorded, not row images (for those see `logical_decoding`): the job reads the
+//! rows back by key, and a key whose row is gone was deleted. The key is the primary key as SQL
+//! literals (`7`, `'ann'`, or `1, 'a'` for a composite key); rows of tables without one are
+//! keyed by their tuple id. An update that changes the key records the old and the new one.
+//!
+//! Tracking needs the WAL, which assigns the commit LSN, and starts when a table is listed:
+//! earlier changes are not recorded. Entries are appended after the commit record, so a crash
+//! in between loses them; they are kept until the file is removed.
+
+use super::logical_decoding::sql_literal;
+use super::{
+    lock_poisoned_engine, map_db_err, shredding, table_page_manager, EngineError, SqlEngineState,
+};
+use crate::common::config::ChangeTrackingConfig;
+use crate::common::types::{ColumnValue, DataType, Row};
+use crate::network::engine::{engine_error_code, UndoEntry};
+use crate::storage::tuple::Tuple;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Change log, relative to the data directory.
+const CHANGES_FILE: &str = "change_tracking/changes.jsonl";
+
+/// Change tracking state of one engine.
+#[derive(Default)]
+pub(super) struct ChangeTracking {
+    /// Checked before anything else, so commits without tracked tables pay one load.
+    enabled: AtomicBool,
+    /// Tracked tables, in lowercase.
+    tables: RwLock<Arc<BTreeSet<String>>>,
+    log: Mutex<ChangeLog>,
+}
+
+#[derive(Default)]
+struct ChangeLog {
+    file: Option<File>,
+    /// Recorded changes in commit order, read from the file on first use.
+    changes: Option<Vec<TrackedChange>>,
+}
+
+struct TrackedChange {
+    table: String,
+    commit_lsn: u64,
+    pk: String,
+}
+
+/// Keys of the tracked rows a transaction changed, to be recorded once it commits.
+#[derive(Debug, Default)]
+pub(super) struct ChangedKeys(BTreeSet<(String, String)>);
+
+/// Applies the `change_tracking.*` parameters.
+pub(super) fn configure(tracking: &ChangeTracking, config: &ChangeTrackingConfig) {
+    let tables: BTreeSet<String> = config
+        .tables
+        .split(',')
+        .map(|t| t.trim().to_ascii_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect();
+    let enabled = !tables.is_empty();
+    // A poisoned table set is replaced whole, so its state does not matter.
+    let mut current = tracking.tables.write().unwrap_or_else(|e| e.into_inner());
+    *current = Arc::new(tables);
+    tracking.enabled.store(enabled, Ordering::Release);
+}
+
+/// Primary keys of the rows of tracked tables changed by the transaction with `undo`; computed
+/// before the commit record is written, so a key that cannot be rendered fails the commit.
+pub(super) fn changed_keys(
+    state: &SqlEngineState,
+    undo: &[UndoEntry],
+) -> Result<ChangedKeys, EngineError> {
+    let mut keys = ChangedKeys::default();
+    if undo.is_empty() || !state.change_tracking.enabled.load(Ordering::Acquire) {
+        return Ok(keys);
+    }
+    let tables = state
+        .change_tracking
+        .tables
+        .read()
+        .map_err(|_| lock_poisoned_engine())?
+        .clone();
+    let mut key_columns: HashMap<&str, Vec<String>> = HashMap::new();
+    for entry in undo {
+        let (table, payload, rid) = match entry {
+            UndoEntry::Insert { table, payload, .. } | UndoEntry::Delete { table, payload, .. } => {
+                (table, payload, None)
+            }
+            UndoEntry::Update {
+                table,
+                rid,
+                old_payload,
+            } => (table, old_payload, Some(*rid)),
+        };
+        if !tables.contains(&table.to_ascii_lowercase()) {
+            continue;
+        }
+        if !key_columns.contains_key(table.as_str()) {
+            let columns = state
+                .catalog
+                .lock()
+                .map_err(|_| lock_poisoned_engine())?
+                .schema(table)
+                .and_then(|s| s.primary_key.as_ref())
+                .map(|(_, columns)| columns.clone())
+                .unwrap_or_default();
+            key_columns.insert(table, columns);
+        }
+        let columns = &key_columns[table.as_str()];
+        let mut images = vec![shredding::decode_tuple(state, payload).map_err(map_db_err)?];
+        // The row as updated, for an update of the key itself.
+        if let Some(rid) = rid {
+            let current = table_page_manager(state, table)?
+                .write()
+                .get_record(rid)
+                .ok()
+                .flatten();
+            if let Some(bytes) = current {
+                images.push(shredding::decode_tuple(state, &bytes).map_err(map_db_err)?);
+            }
+        }
+        for tuple in images {
+            keys.0.insert((table.clone(), row_key(&tuple, columns)?));
+        }
+    }
+    Ok(keys)
+}
+
+fn row_key(tuple: &Tuple, columns: &[String]) -> Result<String, EngineError> {
+    if columns.is_empty() {
+        return Ok(tuple.id.to_string());
+    }
+    let values = columns
+        .iter()
+        .map(|c| match tuple.get_value(c) {
+            Some(v) => sql_literal(v),
+            None => Ok("NULL".to_string()),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(values.join(", "))
+}
+
+/// Appends `keys` as changed by the commit at `commit_lsn`.
+pub(super) fn record(
+    state: &SqlEngineState,
+    keys: ChangedKeys,
+    commit_lsn: u64,
+) -> Result<(), EngineError> {
+    if keys.0.is_empty() {
+        return Ok(());
+    }
+    let mut log = state
+        .change_tracking
+        .log
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?;
+    load(state, &mut log)?;
+    let path = state.data_dir.join(CHANGES_FILE);
+    if log.file.is_none() {
+        let file = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| OpenOptions::new().create(true).append(true).open(&path))
+            .map_err(|e| io_error(&path, e))?;
+        log.file = Some(file);
+    }
+    let changes: Vec<TrackedChange> = keys
+        .0
+        .into_iter()
+        .map(|(table, pk)| TrackedChange {
+            table,
+            commit_lsn,
+            pk,
+        })
+        .collect();
+    let mut lines = String::new();
+    for c in &changes {
+        let line = serde_json::json!({ "table": c.table, "lsn": c.commit_lsn, "pk": c.pk });
+        lines.push_str(&format!("{line}\n"));
+    }
+    let fsync = state.durability.fsync_on_commit();
+    if let Some(file) = log.file.as_mut() {
+        file.write_all(lines.as_bytes())
+            .and_then(|()| if fsync { file.sync_data() } else { Ok(()) })
+            .map_err(|e| io_error(&path, e))?;
+    }
+    log.changes.get_or_insert_with(Vec::new).extend(changes);
+    Ok(())
+}
+
+/// Reads the change log into `log` unless already done; a torn last line (a crash while
+/// appending) is skipped.
+fn load(state: &SqlEngineState, log: &mut ChangeLog) -> Result<(), EngineError> {
+    if log.changes.is_some() {
+        return Ok(());
+    }
+    let path = state.data_dir.join(CHANGES_FILE);
+    let mut changes = Vec::new();
+    match File::open(&path) {
+        Ok(file) => {
+            for line in BufReader::new(file).lines() {
+                let line = line.map_err(|e| io_error(&path, e))?;
+                let Ok(v) = serde_json::from_str::<serde_json::Value>(&line) else {
+                    continue;
+                };
+                if let (Some(table), Some(commit_lsn), Some(pk)) =
+                    (v["table"].as_str(), v["lsn"].as_u64(), v["pk"].as_str())
+                {
+                    changes.push(TrackedChange {
+                        table: table.to_string(),
+                        commit_lsn,
+                        pk: pk.to_string(),
+                    });
+                }
+            }
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(io_error(&path, e)),
+    }
+    log.changes = Some(changes);
+    Ok(())
+}
+
+fn io_error(path: &std::path::Path, e: std::io::Error) -> EngineError {
+    EngineError::new(
+        engine_error_code::INTERNAL,
+        format!("change tracking log {}: {e}", path.display()),
+    )
+}
+
+/// `rustdb_change_tracking`: one row per changed row and commit, in commit order.
+pub(super) fn change_rows(state: &SqlEngineState) -> Result<Vec<Row>, EngineError> {
+    let mut log = state
+        .change_tracking
+        .log
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?;
+    load(state, &mut log)?;
+    let text = |s: &str| ColumnValue::new(DataType::Varchar(format!("'{s}'")));
+    Ok(log
+        .changes
+        .iter()
+        .flatten()
+        .map(|c| {
+            let mut row = Row::new();
+            row.set_value("table_name", text(&c.table));
+            row.set_value(
+                "commit_lsn",
+                ColumnValue::new(DataType::BigInt(c.commit_lsn as i64)),
+            );
+            row.set_value("pk", text(&c.pk));
+            row
+        })
+        .collect())
+}
@@ -0,0 +1,149 @@
+//! `CREATE DATABASE <name> [TEMPLATE <database>]`.
+//!
+//! A database is a data directory, and the databases of a server are the directories next to its
+//! own, named after them (`CREATE DATABASE staging` on a server over `/srv/rustdb/prod` creates
+//! `/srv/rustdb/staging`). Without a template the directory starts empty and is initialized when
+//! an engine first opens it.
+//!
+//! `TEMPLATE` clones the template's files, catalog included. Files are copied with
+//! [`std::fs::copy`], which shares extents where the filesystem can (`copy_file_range` on btrfs
+//! and XFS, `clonefile` on APFS), so a clone costs little until one side writes. The server's own
+//! database is staged like a base backup (see `base_backup`): writers wait during the copy, and
+//! WAL recovery undoes on first open the transactions that were in flight. Any other template is
+//! copied as it is on disk and, as in PostgreSQL, must not be open while it is cloned. Either
+//! way a clone gets the pages of cold table extents in its own heap files rather than sharing the
+//! template's cold tablespace (see `tiering`), and keys of its own for its sealed rows (see
+//! `shredding`).
+
+use super::{base_backup, shredding, EngineOutput, SqlEngineState};
+use crate::network::engine::{engine_error_code, EngineError, SessionContext};
+use crate::storage::tiering::ColdTier;
+use std::path::{Path, PathBuf};
+
+/// Files of a data directory that describe where it came from rather than its data; a clone
+/// does not keep them.
+const NOT_CLONED: &[&str] = &[".rustdb/replica_lsn", ".rustdb/backup_manifest.json"];
+
+/// Base backups being staged, relative to the data directory (never cloned).
+const STAGING_DIR: &str = ".rustdb/base_backup";
+
+pub(super) fn create_database(
+    state: &SqlEngineState,
+    ctx: &SessionContext,
+    name: &str,
+    template: Option<&str>,
+) -> Result<EngineOutput, EngineError> {
+    if ctx.transaction.is_some() {
+        return Err(EngineError::new(
+            engine_error_code::DDL_IN_TRANSACTION,
+            "CREATE DATABASE cannot run inside a transaction",
+        ));
+    }
+    let target = database_dir(state, name)?;
+    if target.exists() {
+        return Err(EngineError::new(
+            engine_error_code::INVALID_PARAMETER,
+            format!("database {name} already exists"),
+        ));
+    }
+    let Some(template) = template else {
+        std::fs::create_dir_all(&target).map_err(|e| io_error(name, e))?;
+        return Ok(EngineOutput::ExecutionOk { rows_affected: 0 });
+    };
+    // Assembled next to the target, so a failed clone leaves no half-copied database behind.
+    let staging = target.with_file_name(format!(".{name}.creating-{}", std::process::id()));
+    let cloned = clone_into(state, template, &staging).and_then(|()| {
+        for rel in NOT_CLONED {
+            match std::fs::remove_file(staging.join(rel)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(io_error(name, e))
+                }
+                _ => {}
+            }
+        }
+        shredding::inherit_keys(&staging)?;
+        std::fs::rename(&staging, &target).map_err(|e| io_error(name, e))
+    });
+    if cloned.is_err() {
+        let _ = std::fs::remove_dir_all(&staging);
+    }
+    cloned.map(|()| EngineOutput::ExecutionOk { rows_affected: 0 })
+}
+
+/// Copies the database `template` into `dest`.
+fn clone_into(state: &SqlEngineState, template: &str, dest: &Path) -> Result<(), EngineError> {
+    if state.data_dir.file_name() == Some(template.as_ref()) {
+        let backup = base_backup::stage_base_backup(state)?;
+        // The staged copy lives inside this data directory; moving it out is the cheapest clone.
+        // The backup then finds nothing left to remove when it is dropped.
+        if std::fs::rename(backup.root(), dest).is_ok() {
+            return Ok(());
+        }
+        return copy_tree(backup.root(), dest, None).map_err(|e| io_error(template, e));
+    }
+    let source = database_dir(state, template)?;
+    if !source.is_dir() {
+        return Err(EngineError::new(
+            engine_error_code::INVALID_PARAMETER,
+            format!("template database {template} does not exist"),
+        ));
+    }
+    copy_tree(&source, dest, Some(&source.join(STAGING_DIR))).map_err(|e| io_error(template, e))
+}
+
+/// Directory of the database `name`, next to the server's data directory.
+fn database_dir(state: &SqlEngineState, name: &str) -> Result<PathBuf, EngineError> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid {
+        return Err(EngineError::new(
+            engine_error_code::INVALID_PARAMETER,
+            format!("invalid database name {name:?}"),
+        ));
+    }
+    let parent = state.data_dir.parent().ok_or_else(|| {
+        EngineError::new(
+            engine_error_code::UNSUPPORTED_SQL,
+            "CREATE DATABASE needs a data directory with a parent directory",
+        )
+    })?;
+    Ok(parent.join(name))
+}
+
+/// Copies the files under `src` into `dest` (created), leaving out the directory `skip`; heap
+/// files are copied with their cold pages and without their tier maps.
+fn copy_tree(src: &Path, dest: &Path, skip: Option<&Path>) -> std::io::Result<()> {
+    std::fs::create_dir_all(dest)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let path = entry.path();
+        if Some(path.as_path()) == skip {
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_tree(&path, &dest.join(entry.file_name()), skip)?;
+        } else if path.extension().is_some_and(|ext| ext == "tiers") {
+            continue;
+        } else if file_type.is_file() {
+            let copy = dest.join(entry.file_name());
+            std::fs::copy(&path, &copy)?;
+            if path.extension().is_some_and(|ext| ext == "tbl") {
+                ColdTier::load(&path)
+                    .and_then(|tiers| tiers.write_cold_pages_into(&copy))
+                    .map_err(|e| std::io::Error::other(e.to_string()))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn io_error(database: &str, e: std::io::Error) -> EngineError {
+    EngineError::new(
+        engine_error_code::INTERNAL,
+        format!("CREATE DATABASE ({database}): {e}"),
+    )
+}
@@ -0,0 +1,331 @@
+//! Index advisor: `SELECT * FROM index_advisor()`.
+//!
+//! Candidate indexes come from the statement history of `query_stats`. For every recorded
+//! single-table `SELECT`, `UPDATE` and `DELETE`, the columns its `WHERE` compares to a literal
+//! with `=` (under `AND`) form a candidate key. Fingerprints show literals as `?`, so a statement
+//! is re-parsed with a placeholder value: only the shape of its `WHERE` matters here.
+//!
+//! Each candidate is costed what-if: the statement's row lookup, as a `SELECT`, is planned against
+//! a copy of the index registry that also holds the candidate as a hypothetical (empty) index. A
+//! candidate is proposed only if the optimizer then seeks it, and no existing index already gives
+//! a seek on all of its columns.
+//!
+//! The benefit is estimated from cardinalities read from the table heap (each candidate table is
+//! scanned once per call, without storage locks, like `CREATE INDEX CONCURRENTLY`): a lookup
+//! reads all `n` rows of the table without the index and about `n / d` with it, `d` being the
+//! number of distinct keys. Key columns are ordered most selective first; proposals are ranked by
+//! the rows saved over the recorded calls.
+
+use super::{
+    column_value_to_index_string, find_index_scan_node, lock_poisoned_engine, map_db_err,
+    shredding, table_page_manager, EngineError, SqlEngineState,
+};
+use crate::common::types::{ColumnValue, DataType, Row};
+use crate::parser::ast::{FromClause, SelectItem, SelectStatement, SqlStatement, TableReference};
+use crate::parser::SqlParser;
+use crate::planner::planner::extract_equality_filters;
+use crate::planner::QueryOptimizer;
+use crate::storage::index_registry::IndexRegistry;
+use crate::storage::tuple::Tuple;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Name of the table function.
+pub(super) const FUNCTION: &str = "index_advisor";
+
+/// Registry name of the candidate while it is costed.
+const HYPOTHETICAL_INDEX: &str = "hypothetical";
+
+/// A proposed index, merged over the statements that would use it.
+struct Candidate {
+    /// Statements (fingerprints) whose lookup would seek the index
+    queries: u64,
+    calls: u64,
+    total: Duration,
+}
+
+/// Row and distinct key counts of one table.
+#[derive(Default)]
+struct Cardinality {
+    rows: u64,
+    /// Distinct values per candidate column
+    column_distinct: HashMap<String, u64>,
+    /// Distinct keys per candidate (columns sorted by name)
+    key_distinct: HashMap<Vec<String>, u64>,
+}
+
+/// Rows of `index_advisor()`, by estimated rows saved, highest first.
+pub(super) fn advise(state: &SqlEngineState) -> Result<Vec<Row>, EngineError> {
+    let registry = state
+        .index_registry
+        .read()
+        .map_err(|_| lock_poisoned_engine())?
+        .clone();
+
+    // (table, key columns sorted by name) -> candidate
+    let mut candidates: BTreeMap<(String, Vec<String>), Candidate> = BTreeMap::new();
+    for stmt in state.statement_stats.workload() {
+        let Some(lookup) = row_lookup(&stmt.query) else {
+            continue;
+        };
+        let Some(columns) = equality_columns(&lookup) else {
+            continue;
+        };
+        let table = table_name(&lookup).to_string();
+        if !seeks_hypothetical_index(state, &registry, &lookup, &table, &columns) {
+            continue;
+        }
+        let c = candidates
+            .entry((table, columns))
+            .or_insert_with(|| Candidate {
+                queries: 0,
+                calls: 0,
+                total: Duration::ZERO,
+            });
+        c.queries += 1;
+        c.calls += stmt.calls;
+        c.total += stmt.total;
+    }
+
+    let mut keys_by_table: BTreeMap<&str, Vec<&Vec<String>>> = BTreeMap::new();
+    for (table, columns) in candidates.keys() {
+        keys_by_table.entry(table).or_default().push(columns);
+    }
+    let mut cardinalities = HashMap::new();
+    for (table, keys) in keys_by_table {
+        cardinalities.insert(table.to_string(), table_cardinality(state, table, &keys)?);
+    }
+
+    let mut proposals = Vec::new();
+    for ((table, mut columns), c) in candidates {
+        let Some(card) = cardinalities.get(&table) else {
+            continue;
+        };
+        let distinct = card.key_distinct.get(&columns).copied().unwrap_or(0).max(1);
+        let rows_per_call = card.rows.div_ceil(distinct);
+        let rows_saved = c.calls * (card.rows - rows_per_call);
+        if rows_saved == 0 {
+            continue;
+        }
+        columns.sort_by_key(|col| {
+            std::cmp::Reverse(card.column_distinct.get(col).copied().unwrap_or(0))
+        });
+        let text = |s: &str| ColumnValue::new(DataType::Varchar(format!("'{s}'")));
+        let int = |n: u64| ColumnValue::new(DataType::BigInt(n as i64));
+        let mut row = Row::new();
+        row.set_value("table_name", text(&table));
+        row.set_value("index_columns", text(&columns.join(", ")));
+        row.set_value(
+            "create_statement",
+            text(&format!(
+                "CREATE INDEX {table}_{}_idx ON {table} ({})",
+                columns.join("_"),
+                columns.join(", ")
+            )),
+        );
+        row.set_value("queries", int(c.queries));
+        row.set_value("calls", int(c.calls));
+        row.set_value(
+            "total_time_ms",
+            ColumnValue::new(DataType::Double(c.total.as_secs_f64() * 1000.0)),
+        );
+        row.set_value("table_rows", int(card.rows));
+        row.set_value("distinct_keys", int(distinct));
+        row.set_value("est_rows_per_call", int(rows_per_call));
+        row.set_value("est_rows_saved", int(rows_saved));
+        row.set_value(
+            "est_benefit_pct",
+            ColumnValue::new(DataType::Double(
+                100.0 * (card.rows - rows_per_call) as f64 / card.rows as f64,
+            )),
+        );
+        proposals.push((rows_saved, row));
+    }
+    // Stable for equal savings: candidates are visited by table and columns.
+    proposals.sort_by(|a, b| b.0.cmp(&a.0));
+    Ok(proposals.into_iter().map(|(_, row)| row).collect())
+}
+
+/// The row lookup of a recorded single-table `SELECT`, `UPDATE` or `DELETE`, as a `SELECT`.
+fn row_lookup(fingerprint: &str) -> Option<SelectStatement> {
+    let sql = with_placeholder_literals(fingerprint);
+    let stmt = SqlParser::new(&sql).ok()?.parse().ok()?;
+    let (table, where_clause) = match stmt {
+        SqlStatement::Select(sel) => {
+            let from = sel.from.as_ref()?;
+            let TableReference::Table { name, .. } = &from.table else {
+                return None;
+            };
+            if !from.joins.is_empty() {
+                return None;
+            }
+            (name.clone(), sel.where_clause)
+        }
+        SqlStatement::Update(u) => (u.table, u.where_clause),
+        SqlStatement::Delete(d) => (d.table, d.where_clause),
+        _ => return None,
+    };
+    Some(SelectStatement {
+        distinct: false,
+        select_list: vec![SelectItem::Wildcard],
+        from: Some(FromClause {
+            table: TableReference::Table {
+                name: table,
+                alias: None,
+                as_of: None,
+            },
+            joins: Vec::new(),
+        }),
+        where_clause: Some(where_clause?),
+        group_by: Vec::new(),
+        having: None,
+        order_by: Vec::new(),
+        limit: None,
+        offset: None,
+    })
+}
+
+/// `fingerprint` with every `?` outside quoted identifiers replaced by `0`.
+fn with_placeholder_literals(fingerprint: &str) -> String {
+    let mut out = String::with_capacity(fingerprint.len());
+    let mut quote = None;
+    for c in fingerprint.chars() {
+        match (quote, c) {
+            (None, '?') => out.push('0'),
+            (None, '"' | '`') => {
+                quote = Some(c);
+                out.push(c);
+            }
+            (Some(q), c) if c == q => {
+                quote = None;
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn table_name(lookup: &SelectStatement) -> &str {
+    match lookup.from.as_ref().map(|f| &f.table) {
+        Some(TableReference::Table { name, .. }) => name,
+        _ => "",
+    }
+}
+
+/// Columns compared to a literal with `=`, sorted by name; `None` when there are none.
+fn equality_columns(lookup: &SelectStatement) -> Option<Vec<String>> {
+    let mut columns: Vec<String> = extract_equality_filters(lookup.where_clause.as_ref()?)
+        .into_keys()
+        .collect();
+    columns.sort();
+    (!columns.is_empty()).then_some(columns)
+}
+
+/// Whether the optimizer seeks a hypothetical index on `columns` for `lookup`, while the
+/// existing indexes give no seek on all of them.
+fn seeks_hypothetical_index(
+    state: &SqlEngineState,
+    registry: &IndexRegistry,
+    lookup: &SelectStatement,
+    table: &str,
+    columns: &[String],
+) -> bool {
+    let index_seek = |registry: IndexRegistry| {
+        let plan = state
+            .planner
+            .create_plan(&SqlStatement::Select(lookup.clone()))
+            .ok()?;
+        let optimized = QueryOptimizer::new()
+            .ok()?
+            .with_index_registry(Arc::new(registry))
+            .optimize(plan)
+            .ok()?;
+        find_index_scan_node(&optimized.optimized_plan.root)
+            .map(|scan| (scan.index_name.clone(), scan.conditions.len()))
+    };
+    if index_seek(registry.clone()).is_some_and(|(_, n)| n >= columns.len()) {
+        return false;
+    }
+    let mut hypothetical = registry.clone();
+    if hypothetical
+        .create_index(table, HYPOTHETICAL_INDEX, columns.to_vec())
+        .is_err()
+    {
+        return false;
+    }
+    index_seek(hypothetical).is_some_and(|(name, _)| name == HYPOTHETICAL_INDEX)
+}
+
+/// Counts the rows of `table` and the distinct values of each candidate key and key column.
+fn table_cardinality(
+    state: &SqlEngineState,
+    table: &str,
+    keys: &[&Vec<String>],
+) -> Result<Cardinality, EngineError> {
+    let columns: HashSet<&String> = keys.iter().flat_map(|k| k.iter()).collect();
+    let mut column_values: HashMap<&String, HashSet<String>> =
+        columns.iter().map(|c| (*c, HashSet::new())).collect();
+    let mut key_values: Vec<HashSet<String>> = vec![HashSet::new(); keys.len()];
+    let mut card = Cardinality::default();
+
+    let pm = table_page_manager(state, table)?;
+    let page_ids = pm.read().all_page_ids().map_err(map_db_err)?;
+    let mut values = HashMap::new();
+    for page_id in page_ids {
+        let records = pm.read().records_from_page(page_id).map_err(map_db_err)?;
+        for (_, bytes) in records {
+            let tuple = shredding::decode_tuple(state, &bytes).map_err(map_db_err)?;
+            card.rows += 1;
+            values.clear();
+            for c in &columns {
+                if let Some(cv) = tuple.values.get(c.as_str()) {
+                    values.insert((*c).clone(), column_value_to_index_string(cv));
+                }
+            }
+            for (c, seen) in column_values.iter_mut() {
+                if let Some(v) = values.get(c.as_str()) {
+                    if !seen.contains(v) {
+                        seen.insert(v.clone());
+                    }
+                }
+            }
+            for (key, seen) in keys.iter().zip(key_values.iter_mut()) {
+                if let Ok(k) = IndexRegistry::build_index_key_from_map(key, &values) {
+                    seen.insert(k);
+                }
+            }
+        }
+    }
+    card.column_distinct = column_values
+        .into_iter()
+        .map(|(c, seen)| (c.clone(), seen.len() as u64))
+        .collect();
+    card.key_distinct = keys
+        .iter()
+        .zip(key_values)
+        .map(|(k, seen)| ((*k).clone(), seen.len() as u64))
+        .collect();
+    Ok(card)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_of_update_and_delete_is_a_select_on_the_table() {
+        let lookup = row_lookup("UPDATE t SET v = ? WHERE a = ? AND \"b?\" = ?").unwrap();
+        assert_eq!(table_name(&lookup), "t");
+        assert_eq!(
+            equality_columns(&lookup),
+            Some(vec!["\"b?\"".to_string(), "a".to_string()])
+        );
+
+        let lookup = row_lookup("DELETE FROM t WHERE id > ?").unwrap();
+        assert_eq!(equality_columns(&lookup), None);
+        assert!(row_lookup("DELETE FROM t").is_none());
+        assert!(row_lookup("SELECT * FROM a JOIN b ON a.x = b.x WHERE a.y = ?").is_none());
+    }
+}
@@ -0,0 +1,595 @@
+//! Parallel secondary index builds (`CREATE INDEX [CONCURRENTLY]` and index rebuild on open).
+//!
+//! Heap pages are handed out to workers one at a time. A worker pins each page under the table's
+//! shared lock, like a table scan, and decodes it into `(index key, record id)` pairs after
+//! releasing the lock; the pairs go to the worker's external sorter (see
+//! [`crate::executor::external_sort`]), which spills sorted runs to the temp directory past the
+//! worker's share of the work memory. The workers' sorted outputs are then merged and loaded into
+//! the index one key at a time, so rows sharing a key cost a single index insert.
+//! Worker count: `RUSTDB_INDEX_BUILD_WORKERS` (default: available parallelism, at most 8), never
+//! more than the table has pages.
+//!
+//! `CREATE INDEX CONCURRENTLY` scans the same way into a private index while writes continue, then
+//! catches up on the row changes the registry logged meanwhile (see [`build_index_concurrently`]).
+//! Builds of partial indexes skip the rows their predicate does not match; builds of expression
+//! indexes evaluate the key expressions of each row.
+//!
+//! Builds report progress in the `rustdb_stat_progress_create_index` system view (see
+//! `system_views`), which also keeps the last few finished builds.
+
+use super::{
+    column_value_to_index_string, lock_poisoned_engine, map_db_err, table_page_manager,
+    tuple_as_eval_row, EngineError, SqlEngineState,
+};
+use crate::common::types::{ColumnValue, DataType, PageId, RecordId, Row};
+use crate::executor::external_sort::{ExternalSorter, SortConfig, SortKey, SortedRows};
+use crate::executor::operators::{eval_predicate_expression, eval_scalar_expression};
+use crate::network::engine::engine_error_code;
+use crate::storage::index::Index;
+use crate::storage::index_registry::{IndexChangeLog, IndexOptions, IndexRegistry};
+use crate::storage::page_manager::{PageManager, PageManagerLock};
+use crate::storage::shred::Keyring;
+use crate::storage::tuple::Tuple;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Finished builds kept for the progress view.
+const FINISHED_BUILDS_KEPT: usize = 16;
+
+/// A concurrent build stops replaying its change log in rounds once a round is shorter than
+/// this; the rest is applied while the registry is locked.
+const CATCH_UP_BATCH: usize = 1024;
+
+/// Columns of the rows the workers sort: the index key, then the record id.
+const KEY_COLUMN: &str = "key";
+const RECORD_ID_COLUMN: &str = "record_id";
+
+/// Running and recently finished index builds, oldest first (owned by `SqlEngineState`).
+pub(super) type IndexBuilds = Mutex<VecDeque<Arc<IndexBuildProgress>>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+enum BuildPhase {
+    /// Workers read, decode and sort heap pages.
+    Scanning = 0,
+    /// Sorted runs are merged into the B+Tree.
+    Loading = 1,
+    /// Row changes made during the build are applied (`CREATE INDEX CONCURRENTLY`).
+    CatchingUp = 2,
+    Done = 3,
+    Failed = 4,
+}
+
+impl BuildPhase {
+    fn from_u8(v: u8) -> Self {
+        match v {
+            0 => Self::Scanning,
+            1 => Self::Loading,
+            2 => Self::CatchingUp,
+            3 => Self::Done,
+            _ => Self::Failed,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Scanning => "scanning heap",
+            Self::Loading => "loading tree",
+            Self::CatchingUp => "catching up",
+            Self::Done => "done",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// Live counters of one index build.
+#[derive(Debug)]
+pub(super) struct IndexBuildProgress {
+    table: String,
+    index: String,
+    workers: usize,
+    started: Instant,
+    phase: AtomicU8,
+    pages_total: u64,
+    pages_done: AtomicU64,
+    tuples_scanned: AtomicU64,
+    tuples_loaded: AtomicU64,
+    /// Logged row changes replayed onto a concurrent build.
+    changes_applied: AtomicU64,
+    /// Set when the build ends.
+    elapsed_ms: AtomicU64,
+}
+
+impl IndexBuildProgress {
+    fn phase(&self) -> BuildPhase {
+        BuildPhase::from_u8(self.phase.load(Ordering::Acquire))
+    }
+
+    fn set_phase(&self, phase: BuildPhase) {
+        if matches!(phase, BuildPhase::Done | BuildPhase::Failed) {
+            self.elapsed_ms
+                .store(self.started.elapsed().as_millis() as u64, Ordering::Relaxed);
+        }
+        self.phase.store(phase as u8, Ordering::Release);
+    }
+
+    /// One row of `rustdb_stat_progress_create_index`.
+    fn to_row(&self) -> Row {
+        let phase = self.phase();
+        let elapsed_ms = match phase {
+            BuildPhase::Done | BuildPhase::Failed => self.elapsed_ms.load(Ordering::Relaxed),
+            _ => self.started.elapsed().as_millis() as u64,
+        };
+        let text = |s: &str| ColumnValue::new(DataType::Varchar(format!("'{s}'")));
+        let int = |n: u64| ColumnValue::new(DataType::BigInt(n as i64));
+        let mut row = Row::new();
+        row.set_value("table_name", text(&self.table));
+        row.set_value("index_name", text(&self.index));
+        row.set_value("phase", text(phase.name()));
+        row.set_value("workers", int(self.workers as u64));
+        row.set_value("pages_total", int(self.pages_total));
+        row.set_value("pages_done", int(self.pages_done.load(Ordering::Relaxed)));
+        row.set_value(
+            "tuples_scanned",
+            int(self.tuples_scanned.load(Ordering::Relaxed)),
+        );
+        row.set_value(
+            "tuples_loaded",
+            int(self.tuples_loaded.load(Ordering::Relaxed)),
+        );
+        row.set_value(
+            "changes_applied",
+            int(self.changes_applied.load(Ordering::Relaxed)),
+        );
+        row.set_value("elapsed_ms", int(elapsed_ms));
+        row
+    }
+}
+
+/// Rows of the `rustdb_stat_progress_create_index` system view.
+pub(super) fn progress_rows(state: &SqlEngineState) -> Result<Vec<Row>, EngineError> {
+    let builds = state
+        .index_builds
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?;
+    Ok(builds.iter().map(|b| b.to_row()).collect())
+}
+
+fn index_build_workers() -> usize {
+    if let Ok(s) = std::env::var("RUSTDB_INDEX_BUILD_WORKERS") {
+        if let Ok(n) = s.parse::<usize>() {
+            return n.clamp(1, 32);
+        }
+    }
+    std::thread::available_parallelism()
+        .map(|n| n.get().clamp(1, 8))
+        .unwrap_or(1)
+}
+
+/// Fills the (registered, empty) index `index_name` from every row of `table`'s heap.
+pub(super) fn build_index_from_heap(
+    state: &SqlEngineState,
+    table: &str,
+    index_name: &str,
+) -> Result<(), EngineError> {
+    let (columns, options) = state
+        .index_registry
+        .read()
+        .map_err(|_| lock_poisoned_engine())?
+        .get_index_entry(table, index_name)
+        .map(|e| (e.columns.clone(), e.options.clone()))
+        .ok_or_else(|| {
+            EngineError::new(
+                engine_error_code::INTERNAL,
+                format!("index {index_name} on {table} is not registered"),
+            )
+        })?;
+    let pm = table_page_manager(state, table)?;
+    let page_ids = pm.read().all_page_ids().map_err(map_db_err)?;
+    let progress = register_progress(state, table, index_name, page_ids.len())?;
+    let out = run_build(state, &pm, &page_ids, &columns, &options, &progress);
+    progress.set_phase(if out.is_ok() {
+        BuildPhase::Done
+    } else {
+        BuildPhase::Failed
+    });
+    out
+}
+
+/// Builds `index_name` on `table` while the table stays writable (`CREATE INDEX CONCURRENTLY`).
+///
+/// The name is reserved in the registry first, which from then on logs every row change to the
+/// table for it. The heap is scanned into a private tree, the log is replayed onto it in rounds
+/// until a round is short, and the registry then applies the rest and registers the index under
+/// its write lock. Replaying a change the scan already saw is a no-op (see
+/// [`crate::storage::index_registry::IndexChange::apply_to`]).
+pub(super) fn build_index_concurrently(
+    state: &SqlEngineState,
+    table: &str,
+    index_name: &str,
+    columns: &[String],
+    options: IndexOptions,
+) -> Result<(), EngineError> {
+    let changes = state
+        .index_registry
+        .write()
+        .map_err(|_| lock_poisoned_engine())?
+        .begin_index_build(table, index_name, columns.to_vec(), options.clone())
+        .map_err(|e| EngineError::new(engine_error_code::CONSTRAINT_VIOLATION, e.to_string()))?;
+    super::refresh_index_columns_cache_for_table(state, table);
+    let out = table_page_manager(state, table).and_then(|pm| {
+        let page_ids = pm.read().all_page_ids().map_err(map_db_err)?;
+        let progress = register_progress(state, table, index_name, page_ids.len())?;
+        let out = run_concurrent_build(
+            state, &pm, &page_ids, columns, &options, &changes, &progress,
+        );
+        progress.set_phase(if out.is_ok() {
+            BuildPhase::Done
+        } else {
+            BuildPhase::Failed
+        });
+        out
+    });
+    if out.is_err() {
+        if let Ok(mut ir) = state.index_registry.write() {
+            ir.abort_index_build(table, index_name);
+        }
+        super::refresh_index_columns_cache_for_table(state, table);
+    }
+    out
+}
+
+/// Adds a build to the progress view, dropping the oldest finished builds beyond the limit.
+fn register_progress(
+    state: &SqlEngineState,
+    table: &str,
+    index_name: &str,
+    pages: usize,
+) -> Result<Arc<IndexBuildProgress>, EngineError> {
+    let progress = Arc::new(IndexBuildProgress {
+        table: table.to_string(),
+        index: index_name.to_string(),
+        workers: index_build_workers().min(pages).max(1),
+        started: Instant::now(),
+        phase: AtomicU8::new(BuildPhase::Scanning as u8),
+        pages_total: pages as u64,
+        pages_done: AtomicU64::new(0),
+        tuples_scanned: AtomicU64::new(0),
+        tuples_loaded: AtomicU64::new(0),
+        changes_applied: AtomicU64::new(0),
+        elapsed_ms: AtomicU64::new(0),
+    });
+    let mut builds = state
+        .index_builds
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?;
+    builds.push_back(progress.clone());
+    while builds.len() > FINISHED_BUILDS_KEPT
+        && builds
+            .front()
+            .is_some_and(|b| matches!(b.phase(), BuildPhase::Done | BuildPhase::Failed))
+    {
+        builds.pop_front();
+    }
+    Ok(progress)
+}
+
+fn run_build(
+    state: &SqlEngineState,
+    pm: &PageManagerLock,
+    page_ids: &[PageId],
+    columns: &[String],
+    options: &IndexOptions,
+    progress: &IndexBuildProgress,
+) -> Result<(), EngineError> {
+    let runs = scan_sorted_runs(state, pm, page_ids, columns, options, progress)?;
+    progress.set_phase(BuildPhase::Loading);
+    let mut merged = MergedRuns::new(runs)?;
+    let entries = merged.by_ref().inspect(|(_, ids)| {
+        progress
+            .tuples_loaded
+            .fetch_add(ids.len() as u64, Ordering::Relaxed);
+    });
+    state
+        .index_registry
+        .read()
+        .map_err(|_| lock_poisoned_engine())?
+        .load_into_named_index(&progress.table, &progress.index, entries)
+        .map_err(|e| {
+            EngineError::new(engine_error_code::INTERNAL, format!("index backfill: {e}"))
+        })?;
+    merged.finish()
+}
+
+fn run_concurrent_build(
+    state: &SqlEngineState,
+    pm: &PageManagerLock,
+    page_ids: &[PageId],
+    columns: &[String],
+    options: &IndexOptions,
+    changes: &IndexChangeLog,
+    progress: &IndexBuildProgress,
+) -> Result<(), EngineError> {
+    let runs = scan_sorted_runs(state, pm, page_ids, columns, options, progress)?;
+    progress.set_phase(BuildPhase::Loading);
+    let mut tree = state
+        .index_registry
+        .read()
+        .map_err(|_| lock_poisoned_engine())?
+        .new_build_index(&progress.table, &progress.index)
+        .map_err(map_db_err)?;
+    let mut merged = MergedRuns::new(runs)?;
+    for (key, ids) in merged.by_ref() {
+        let loaded = ids.len() as u64;
+        tree.insert(key, ids).map_err(map_db_err)?;
+        progress.tuples_loaded.fetch_add(loaded, Ordering::Relaxed);
+    }
+    merged.finish()?;
+    progress.set_phase(BuildPhase::CatchingUp);
+    loop {
+        let batch = std::mem::take(&mut *changes.lock().map_err(|_| lock_poisoned_engine())?);
+        for change in &batch {
+            change.apply_to(&mut tree).map_err(map_db_err)?;
+        }
+        progress
+            .changes_applied
+            .fetch_add(batch.len() as u64, Ordering::Relaxed);
+        if batch.len() < CATCH_UP_BATCH {
+            break;
+        }
+    }
+    state
+        .index_registry
+        .write()
+        .map_err(|_| lock_poisoned_engine())?
+        .finish_index_build(&progress.table, &progress.index, tree)
+        .map_err(|e| EngineError::new(engine_error_code::INTERNAL, format!("index build: {e}")))
+}
+
+/// Runs `progress.workers` scan workers over `page_ids`, which share the engine's work memory;
+/// one sorted output per worker.
+fn scan_sorted_runs(
+    state: &SqlEngineState,
+    pm: &PageManagerLock,
+    page_ids: &[PageId],
+    columns: &[String],
+    options: &IndexOptions,
+    progress: &IndexBuildProgress,
+) -> Result<Vec<SortedRows>, EngineError> {
+    let mut config = state.executor.sort_config();
+    config.work_mem = (config.work_mem / progress.workers).max(1);
+    let next_page = AtomicUsize::new(0);
+    std::thread::scope(|s| {
+        let workers: Vec<_> = (0..progress.workers)
+            .map(|_| {
+                let config = config.clone();
+                let next_page = &next_page;
+                s.spawn(move || {
+                    scan_sorted_run(
+                        state.crypto_shredding.keyring(),
+                        pm,
+                        page_ids,
+                        next_page,
+                        columns,
+                        options,
+                        progress,
+                        config,
+                    )
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|w| {
+                w.join().unwrap_or_else(|_| {
+                    Err(EngineError::new(
+                        engine_error_code::INTERNAL,
+                        "index build worker panicked",
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+    })
+}
+
+/// Worker: decodes pages until none are left and returns their entries (of the rows matching
+/// the index predicate) sorted, spilling past `config.work_mem`.
+fn scan_sorted_run(
+    keyring: &Keyring,
+    pm: &PageManagerLock,
+    page_ids: &[PageId],
+    next_page: &AtomicUsize,
+    columns: &[String],
+    options: &IndexOptions,
+    progress: &IndexBuildProgress,
+    config: SortConfig,
+) -> Result<SortedRows, EngineError> {
+    let mut sorter = ExternalSorter::new(
+        vec![
+            SortKey::new(KEY_COLUMN, true),
+            SortKey::new(RECORD_ID_COLUMN, true),
+        ],
+        config,
+    );
+    let mut values = HashMap::with_capacity(columns.len());
+    loop {
+        let i = next_page.fetch_add(1, Ordering::Relaxed);
+        let Some(&page_id) = page_ids.get(i) else {
+            break;
+        };
+        // The pin keeps the page; the table lock is released before it is decoded.
+        let page = pm.read().pin_page(page_id).map_err(map_db_err)?;
+        let mut scanned = 0;
+        for (slot, bytes) in page.records() {
+            scanned += 1;
+            let tuple = keyring.decode(bytes).map_err(map_db_err)?;
+            let row = (options.predicate.is_some() || !options.expressions.is_empty())
+                .then(|| tuple_as_eval_row(&tuple));
+            if let (Some(p), Some(row)) = (&options.predicate, &row) {
+                if !eval_predicate_expression(row, p) {
+                    continue;
+                }
+            }
+            values.clear();
+            for c in columns {
+                if let Some(cv) = tuple.values.get(c) {
+                    values.insert(c.clone(), column_value_to_index_string(cv));
+                }
+            }
+            if let Some(row) = &row {
+                for (key, expr) in &options.expressions {
+                    let cv = eval_scalar_expression(row, expr);
+                    values.insert(key.clone(), column_value_to_index_string(&cv));
+                }
+            }
+            let key =
+                IndexRegistry::build_index_key_from_map(columns, &values).map_err(map_db_err)?;
+            let record_id = PageManager::record_id_for_slot(page_id, slot);
+            sorter.push(entry_row(key, record_id)).map_err(map_db_err)?;
+        }
+        progress
+            .tuples_scanned
+            .fetch_add(scanned, Ordering::Relaxed);
+        progress.pages_done.fetch_add(1, Ordering::Relaxed);
+    }
+    sorter.finish().map_err(map_db_err)
+}
+
+/// Row of one index entry, as the workers sort it.
+fn entry_row(key: String, record_id: RecordId) -> Row {
+    let mut row = Row::new();
+    row.set_value(KEY_COLUMN, ColumnValue::new(DataType::Varchar(key)));
+    row.set_value(
+        RECORD_ID_COLUMN,
+        ColumnValue::new(DataType::BigInt(record_id as i64)),
+    );
+    row
+}
+
+/// Next entry of a worker's sorted output.
+fn next_entry(run: &mut SortedRows) -> Result<Option<(String, RecordId)>, EngineError> {
+    let Some(mut row) = run.next_row().map_err(map_db_err)? else {
+        return Ok(None);
+    };
+    let key = row.remove_value(KEY_COLUMN).map(|cv| cv.data_type);
+    let record_id = row.get_value(RECORD_ID_COLUMN).map(|cv| &cv.data_type);
+    match (key, record_id) {
+        (Some(DataType::Varchar(key)), Some(DataType::BigInt(id))) => {
+            Ok(Some((key, *id as RecordId)))
+        }
+        _ => Err(EngineError::new(
+            engine_error_code::INTERNAL,
+            "index build: malformed sorted entry",
+        )),
+    }
+}
+
+/// K-way merge of the workers' sorted outputs, grouping record ids by key.
+///
+/// Stops at the first error reading an output; [`Self::finish`] returns it.
+struct MergedRuns {
+    runs: Vec<SortedRows>,
+    heads: BinaryHeap<Reverse<(String, RecordId, usize)>>,
+    error: Option<EngineError>,
+}
+
+impl MergedRuns {
+    fn new(mut runs: Vec<SortedRows>) -> Result<Self, EngineError> {
+        let mut heads = BinaryHeap::with_capacity(runs.len());
+        for (i, run) in runs.iter_mut().enumerate() {
+            if let Some((key, rid)) = next_entry(run)? {
+                heads.push(Reverse((key, rid, i)));
+            }
+        }
+        Ok(Self {
+            runs,
+            heads,
+            error: None,
+        })
+    }
+
+    fn pop(&mut self) -> Option<(String, RecordId)> {
+        let Reverse((key, rid, i)) = self.heads.pop()?;
+        match next_entry(&mut self.runs[i]) {
+            Ok(Some((k, r))) => self.heads.push(Reverse((k, r, i))),
+            Ok(None) => {}
+            Err(e) => {
+                self.heads.clear();
+                self.error = Some(e);
+            }
+        }
+        Some((key, rid))
+    }
+
+    /// Error that ended the merge early, if any.
+    fn finish(self) -> Result<(), EngineError> {
+        self.error.map_or(Ok(()), Err)
+    }
+}
+
+impl Iterator for MergedRuns {
+    type Item = (String, Vec<RecordId>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, rid) = self.pop()?;
+        let mut ids = vec![rid];
+        while self
+            .heads
+            .peek()
+            .is_some_and(|Reverse((k, _, _))| *k == key)
+        {
+            if let Some((_, r)) = self.pop() {
+                ids.push(r);
+            }
+        }
+        Some((key, ids))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sorted output of entries pushed in the given order, spilled every few entries.
+    fn sorted_run(entries: &[(&str, RecordId)], temp_dir: &std::path::Path) -> SortedRows {
+        let config = SortConfig {
+            work_mem: 1,
+            temp_dir: temp_dir.to_path_buf(),
+        };
+        let keys = vec![
+            SortKey::new(KEY_COLUMN, true),
+            SortKey::new(RECORD_ID_COLUMN, true),
+        ];
+        let mut sorter = ExternalSorter::new(keys, config);
+        for (key, id) in entries {
+            sorter.push(entry_row(key.to_string(), *id)).unwrap();
+        }
+        sorter.finish().unwrap()
+    }
+
+    #[test]
+    fn merged_runs_group_ids_by_key_in_order() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let runs = vec![
+            sorted_run(&[("c", 5), ("a", 1)], dir.path()),
+            sorted_run(&[("b", 2), ("a", 3)], dir.path()),
+            sorted_run(&[], dir.path()),
+            sorted_run(&[("c", 4)], dir.path()),
+        ];
+        assert!(runs[0].statistics().runs > 0);
+        let mut merged = MergedRuns::new(runs).unwrap();
+        let entries: Vec<_> = merged.by_ref().collect();
+        merged.finish().unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                ("a".to_string(), vec![1, 3]),
+                ("b".to_string(), vec![2]),
+                ("c".to_string(), vec![4, 5]),
+            ]
+        );
+    }
+}
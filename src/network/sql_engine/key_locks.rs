@@ -0,0 +1,145 @@
+//! PRIMARY KEY and UNIQUE key locks, held until the writing transaction ends.
+//!
+//! The key maps of the constraint runtime change when a statement writes a row, before its
+//! transaction commits: a deleted row's key is free at once, and a rollback puts it back. So a
+//! transaction that inserts a key, or frees one by deleting or updating its row, locks the key
+//! until it commits or rolls back. Another transaction writing a locked key fails with
+//! `SERIALIZATION_FAILURE` (retry the transaction) instead of deciding on a key whose fate is
+//! open: an insert beside a deleted row that a rollback would restore, or a unique violation
+//! against a row that may never commit. Locks are taken under the constraint runtime lock, so
+//! the check and the map change it guards are atomic.
+//!
+//! The locks belong to the [`crate::network::engine::SqlTransaction`] (prepared transactions keep
+//! them) and are released when it is dropped, after its commit or rollback.
+
+use super::{lock_poisoned_engine, sql_constraints, EngineError, SqlEngineState};
+use crate::catalog::schema::TableSchema;
+use crate::network::engine::{engine_error_code, SessionContext};
+use crate::storage::tuple::Tuple;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Owners (transactions) of the engine's locked keys.
+type LockTable = Arc<Mutex<HashMap<KeyLock, u64>>>;
+
+/// One key of one constraint.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct KeyLock {
+    table: String,
+    constraint: String,
+    key: String,
+}
+
+/// Locked keys of one engine.
+#[derive(Default)]
+pub(super) struct KeyLocks(LockTable);
+
+/// Keys locked by one transaction; dropping it releases them.
+#[derive(Default)]
+pub(crate) struct HeldKeyLocks {
+    owner: u64,
+    table: Option<LockTable>,
+    keys: Vec<KeyLock>,
+}
+
+impl Drop for HeldKeyLocks {
+    fn drop(&mut self) {
+        let Some(table) = &self.table else {
+            return;
+        };
+        let mut held = table.lock().unwrap_or_else(|e| e.into_inner());
+        for key in self.keys.drain(..) {
+            if held.get(&key) == Some(&self.owner) {
+                held.remove(&key);
+            }
+        }
+    }
+}
+
+static NEXT_OWNER: AtomicU64 = AtomicU64::new(1);
+
+/// The PRIMARY KEY and UNIQUE keys of `tuple` (those it has values for).
+fn row_keys(table: &str, tuple: &Tuple, schema: &TableSchema) -> Vec<KeyLock> {
+    let constraints = schema
+        .primary_key
+        .iter()
+        .map(|(name, cols)| (name, cols))
+        .chain(
+            schema
+                .unique_constraints
+                .iter()
+                .map(|u| (&u.name, &u.columns)),
+        );
+    constraints
+        .filter_map(|(name, cols)| {
+            let key = sql_constraints::composite_key_from_tuple_with_schema(tuple, cols, schema);
+            key.ok().map(|key| KeyLock {
+                table: table.to_string(),
+                constraint: name.clone(),
+                key,
+            })
+        })
+        .collect()
+}
+
+/// Fails when another open transaction holds a key of `tuple` (call with the constraint runtime
+/// locked, before checking the row against its maps).
+pub(super) fn check_row_keys(
+    state: &SqlEngineState,
+    ctx: &SessionContext,
+    table: &str,
+    tuple: &Tuple,
+    schema: &TableSchema,
+) -> Result<(), EngineError> {
+    let owner = ctx.transaction.as_ref().map_or(0, |tx| tx.key_locks.owner);
+    let held = state
+        .key_locks
+        .0
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?;
+    for key in row_keys(table, tuple, schema) {
+        if held.get(&key).is_some_and(|o| *o != owner) {
+            return Err(EngineError::new(
+                engine_error_code::SERIALIZATION_FAILURE,
+                format!(
+                    "could not serialize access: a concurrent transaction is writing a key of {} on {table}",
+                    key.constraint
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Locks the keys of `tuple` for the session's transaction until it ends (call with the
+/// constraint runtime locked, before freeing the keys or after claiming them in its maps).
+/// Fails, locking nothing, when another open transaction holds one of them.
+pub(super) fn lock_row_keys(
+    state: &SqlEngineState,
+    ctx: &mut SessionContext,
+    table: &str,
+    tuple: &Tuple,
+    schema: &TableSchema,
+) -> Result<(), EngineError> {
+    check_row_keys(state, ctx, table, tuple, schema)?;
+    let Some(tx) = ctx.transaction.as_mut() else {
+        return Ok(());
+    };
+    let locks = &mut tx.key_locks;
+    if locks.table.is_none() {
+        locks.owner = NEXT_OWNER.fetch_add(1, Ordering::Relaxed);
+        locks.table = Some(state.key_locks.0.clone());
+    }
+    let mut held = state
+        .key_locks
+        .0
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?;
+    for key in row_keys(table, tuple, schema) {
+        if held.insert(key.clone(), locks.owner).is_none() {
+            locks.keys.push(key);
+        }
+    }
+    Ok(())
+}
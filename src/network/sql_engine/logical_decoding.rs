@@ -0,0 +1,283 @@
+//! Logical decoding of the structured WAL for replication (see [`crate::network::replication`]).
+//!
+//! Heap records carry whole tuples, so committed transactions decode into row images without
+//! touching the heap: records are grouped by WAL transaction id and emitted at their commit
+//! record, aborted transactions are dropped. A row that a transaction inserts and then updates or
+//! deletes collapses into its final image (e.g. the compensating delete of an insert that failed a
+//! constraint). DDL reaches the WAL as statement text ([`log_ddl`]) and replays as its own
+//! transaction. Commits without row changes are emitted as empty transactions.
+
+use super::{lock_poisoned_engine, map_db_err, table_page_manager, EngineError, SqlEngineState};
+use crate::common::types::{ColumnValue, DataType};
+use crate::logging::log_record::{LogRecord, LogRecordType, TransactionId};
+use crate::network::engine::{engine_error_code, SessionContext};
+use crate::network::framing::{ReplicatedColumn, ReplicatedTransactionPayload, RowChangePayload};
+use crate::network::sql_engine_wal::log_record_operation_parts;
+use crate::storage::shred::Keyring;
+use std::collections::HashMap;
+
+/// WAL directory relative to the data directory.
+pub(super) const WAL_DIR: &str = ".rustdb/wal";
+
+/// Records the text of a DDL statement that just succeeded (no-op without WAL), marked with the
+/// session's peer commit time, as the session's last commit.
+pub(super) fn log_ddl(
+    state: &SqlEngineState,
+    sql: &str,
+    ctx: &mut SessionContext,
+) -> Result<(), EngineError> {
+    match state.wal.as_ref() {
+        Some(wal) => {
+            let lsn = wal.log_ddl(sql, ctx.peer_commit_time)?;
+            ctx.last_commit_lsn = lsn;
+            replicate_commit(state, lsn)
+        }
+        None => Ok(()),
+    }
+}
+
+/// Wakes replication streams for commit `lsn` and waits for the synchronous quorum, if any.
+pub(super) fn replicate_commit(state: &SqlEngineState, lsn: u64) -> Result<(), EngineError> {
+    state.replication.commit_logged(lsn);
+    state
+        .replication
+        .wait_for_quorum(lsn)
+        .map_err(|e| EngineError::new(engine_error_code::SYNC_REPLICATION_TIMEOUT, e.to_string()))
+}
+
+/// Transactions (and DDL statements) committed after `after_lsn`, in commit order.
+pub(super) fn committed_after(
+    state: &SqlEngineState,
+    after_lsn: u64,
+) -> Result<Vec<ReplicatedTransactionPayload>, EngineError> {
+    let Some(wal) = state.wal.as_ref() else {
+        return Err(EngineError::new(
+            engine_error_code::PROTOCOL,
+            "logical replication requires the WAL",
+        ));
+    };
+    wal.flush_buffered()?;
+    let tables = tables_by_file_id(state)?;
+    let records = LogRecord::read_log_records_from_directory(&state.data_dir.join(WAL_DIR))
+        .map_err(map_db_err)?;
+
+    let mut open: HashMap<TransactionId, Vec<LogRecord>> = HashMap::new();
+    let mut out = Vec::new();
+    for r in records {
+        if let Some(sql) = r.ddl_statement() {
+            if r.lsn > after_lsn {
+                out.push(ReplicatedTransactionPayload {
+                    commit_lsn: r.lsn,
+                    commit_time: r.timestamp,
+                    origin_commit_time: r.origin_commit_time(),
+                    changes: vec![RowChangePayload::Ddl {
+                        sql: sql.to_string(),
+                    }],
+                });
+            }
+            continue;
+        }
+        let Some(tid) = r.transaction_id else {
+            continue;
+        };
+        match r.record_type {
+            LogRecordType::DataInsert | LogRecordType::DataUpdate | LogRecordType::DataDelete => {
+                open.entry(tid).or_default().push(r)
+            }
+            LogRecordType::TransactionAbort => {
+                open.remove(&tid);
+            }
+            LogRecordType::TransactionCommit => {
+                let ops = open.remove(&tid).unwrap_or_default();
+                if r.lsn <= after_lsn {
+                    continue;
+                }
+                // Sent even without row changes so replica acks reach every commit LSN that a
+                // synchronous commit may be waiting on.
+                out.push(ReplicatedTransactionPayload {
+                    commit_lsn: r.lsn,
+                    commit_time: r.timestamp,
+                    origin_commit_time: r.origin_commit_time(),
+                    changes: decode_changes(state.crypto_shredding.keyring(), &ops, &tables)?,
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(out)
+}
+
+pub(super) struct DecodedTable {
+    pub(super) name: String,
+    /// Primary key columns.
+    key: Vec<String>,
+}
+
+/// Heap `file_id` → table, for every local table of the catalog.
+pub(super) fn tables_by_file_id(
+    state: &SqlEngineState,
+) -> Result<HashMap<u32, DecodedTable>, EngineError> {
+    let mut tables = {
+        let cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
+        cat.table_names()
+            .into_iter()
+            .filter_map(|t| {
+                let schema = cat.schema(&t).filter(|s| s.foreign.is_none())?;
+                let key = schema
+                    .primary_key
+                    .as_ref()
+                    .map(|(_, cols)| cols.clone())
+                    .unwrap_or_default();
+                Some(DecodedTable { name: t, key })
+            })
+            .collect::<Vec<_>>()
+    };
+    // Same open order as WAL replay, so file ids match the ones in the log.
+    tables.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut out = HashMap::with_capacity(tables.len());
+    for table in tables {
+        let pm = table_page_manager(state, &table.name)?;
+        let fid = pm.read().file_id();
+        out.insert(fid, table);
+    }
+    Ok(out)
+}
+
+fn decode_changes(
+    keyring: &Keyring,
+    ops: &[LogRecord],
+    tables: &HashMap<u32, DecodedTable>,
+) -> Result<Vec<RowChangePayload>, EngineError> {
+    let mut changes: Vec<Option<RowChangePayload>> = Vec::with_capacity(ops.len());
+    // Rows inserted by this transaction, by heap location → index in `changes`.
+    let mut inserted: HashMap<(u32, u64, u16), usize> = HashMap::new();
+    for r in ops {
+        let Some((kind, op)) = log_record_operation_parts(r) else {
+            continue;
+        };
+        // Tables dropped since; their DDL is replayed too.
+        let Some(table) = tables.get(&op.file_id) else {
+            continue;
+        };
+        let loc = (op.file_id, op.page_id, op.record_offset);
+        match kind {
+            LogRecordType::DataInsert => {
+                let row = decode_row(keyring, op.new_data.as_deref())?;
+                inserted.insert(loc, changes.len());
+                changes.push(Some(RowChangePayload::Insert {
+                    table: table.name.clone(),
+                    key: table.key.clone(),
+                    row,
+                }));
+            }
+            LogRecordType::DataUpdate => {
+                let new = decode_row(keyring, op.new_data.as_deref())?;
+                match inserted.get(&loc) {
+                    Some(&i) => {
+                        changes[i] = Some(RowChangePayload::Insert {
+                            table: table.name.clone(),
+                            key: table.key.clone(),
+                            row: new,
+                        })
+                    }
+                    None => changes.push(Some(RowChangePayload::Update {
+                        table: table.name.clone(),
+                        key: table.key.clone(),
+                        old: decode_row(keyring, op.old_data.as_deref())?,
+                        new,
+                    })),
+                }
+            }
+            LogRecordType::DataDelete => match inserted.remove(&loc) {
+                Some(i) => changes[i] = None,
+                None => changes.push(Some(RowChangePayload::Delete {
+                    table: table.name.clone(),
+                    key: table.key.clone(),
+                    old: decode_row(keyring, op.old_data.as_deref())?,
+                })),
+            },
+            _ => {}
+        }
+    }
+    Ok(changes.into_iter().flatten().collect())
+}
+
+/// Row image as SQL literals, sorted by column name.
+fn decode_row(
+    keyring: &Keyring,
+    bytes: Option<&[u8]>,
+) -> Result<Vec<ReplicatedColumn>, EngineError> {
+    let bytes = bytes.ok_or_else(|| {
+        EngineError::new(
+            engine_error_code::INTERNAL,
+            "WAL heap record without row image",
+        )
+    })?;
+    let tuple = keyring.decode(bytes).map_err(map_db_err)?;
+    let mut row = tuple
+        .values
+        .iter()
+        .map(|(name, v)| {
+            Ok(ReplicatedColumn {
+                name: name.clone(),
+                literal: sql_literal(v)?,
+            })
+        })
+        .collect::<Result<Vec<_>, EngineError>>()?;
+    row.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(row)
+}
+
+pub(super) fn sql_literal(v: &ColumnValue) -> Result<String, EngineError> {
+    if v.is_null() {
+        return Ok("NULL".to_string());
+    }
+    Ok(match &v.data_type {
+        DataType::Null => "NULL".to_string(),
+        DataType::Boolean(b) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+        DataType::TinyInt(n) => n.to_string(),
+        DataType::SmallInt(n) => n.to_string(),
+        DataType::Integer(n) => n.to_string(),
+        DataType::BigInt(n) => n.to_string(),
+        DataType::Float(f) => format!("{f:?}"),
+        DataType::Double(f) => format!("{f:?}"),
+        DataType::Char(s)
+        | DataType::Varchar(s)
+        | DataType::Text(s)
+        | DataType::Date(s)
+        | DataType::Time(s)
+        | DataType::Timestamp(s) => string_literal(s),
+        DataType::Blob(_) => {
+            return Err(EngineError::new(
+                engine_error_code::UNSUPPORTED_SQL,
+                "BLOB values cannot be replicated",
+            ))
+        }
+    })
+}
+
+/// String values keep the quoting of the literal they were inserted with; others are quoted.
+fn string_literal(s: &str) -> String {
+    if s.len() >= 2 && s.starts_with('\'') && s.ends_with('\'') {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', "''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literals_round_trip_stored_values() {
+        let lit = |d: DataType| sql_literal(&ColumnValue::new(d)).unwrap();
+        assert_eq!(lit(DataType::Integer(-3)), "-3");
+        assert_eq!(lit(DataType::Double(2.0)), "2.0");
+        assert_eq!(lit(DataType::Boolean(true)), "TRUE");
+        assert_eq!(lit(DataType::Null), "NULL");
+        assert_eq!(lit(DataType::Varchar("'one'".into())), "'one'");
+        assert_eq!(lit(DataType::Text("it's".into())), "'it''s'");
+        assert!(sql_literal(&ColumnValue::new(DataType::Blob(vec![1]))).is_err());
+    }
+}
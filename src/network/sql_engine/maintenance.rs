@@ -0,0 +1,548 @@
+//! Maintenance statements for health checks and cron jobs: `CHECK TABLE` and `ANALYZE`.
+//!
+//! `CHECK TABLE [t]` reads every page of a table (every local table when none is named): each
+//! record must decode, each row must be found under its key in every index of the table (in
+//! the partial indexes whose predicate it matches), and every index entry must point at a row
+//! the index covers. It returns one row per table with `status` `ok` or
+//! `damaged` and the first problems found; nothing is repaired (rebuild a damaged index with
+//! `DROP INDEX` / `CREATE INDEX`).
+//!
+//! `ANALYZE [t]` counts the rows and pages of a table and the nulls and distinct values of each
+//! column, with the NULL fraction and the most common values (those more frequent than the
+//! average value, up to [`MAX_MOST_COMMON_VALUES`]) and their frequencies, and keeps them in `<data_dir>/statistics/tables.json` across restarts;
+//! `rustdb_stat_tables` and `rustdb_stat_columns` show them. The planner does not read them yet.
+//! [`auto_analyze`] runs it again for tables with many changes since.
+//!
+//! Both scan under the storage read lock, so writers wait for them; `rustdb check` and
+//! `rustdb analyze` run them from the command line.
+
+use super::{
+    add_computed_index_values, auto_analyze, column_value_to_index_string,
+    ensure_no_active_transaction, lock_poisoned_engine, map_db_err, rows_to_engine_output,
+    shredding, table_page_manager, EngineError, EngineOutput, SessionContext, SqlEngineState,
+};
+use crate::common::types::{ColumnValue, DataType, RecordId, Row};
+use crate::network::engine::engine_error_code;
+use crate::storage::atomic_file::write_atomic;
+use crate::storage::index::Index;
+use crate::storage::index_registry::{IndexRegistry, INDEX_KEY_MAX};
+use crate::storage::page_manager::PageManager;
+use crate::storage::tuple::Tuple;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Statistics of analyzed tables, relative to the data directory.
+const STATS_FILE: &str = "statistics/tables.json";
+
+/// Problems listed per table by `CHECK TABLE`; further ones are only counted.
+const MAX_REPORTED_PROBLEMS: usize = 5;
+
+/// Most common values kept per column by `ANALYZE`.
+pub(super) const MAX_MOST_COMMON_VALUES: usize = 10;
+
+/// Table statistics of one engine, read from the file on first use.
+#[derive(Default)]
+pub(super) struct TableStatistics(Mutex<Option<BTreeMap<String, TableStats>>>);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct TableStats {
+    rows: u64,
+    pages: u64,
+    analyzed_at_ms: u64,
+    /// Taken by [`auto_analyze`] rather than an `ANALYZE` statement.
+    #[serde(default)]
+    auto: bool,
+    columns: BTreeMap<String, ColumnStats>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ColumnStats {
+    nulls: u64,
+    distinct: u64,
+    #[serde(default)]
+    null_frac: f64,
+    /// Most frequent first
+    #[serde(default)]
+    most_common: Vec<MostCommon>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MostCommon {
+    /// The value as index keys spell it
+    value: String,
+    /// Fraction of the table's rows
+    frequency: f64,
+}
+
+/// The named local table, or every local table of the catalog.
+pub(super) fn local_tables(
+    state: &SqlEngineState,
+    table: Option<&str>,
+) -> Result<Vec<String>, EngineError> {
+    let cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
+    match table {
+        Some(t) => match cat.schema(t) {
+            Some(schema) if schema.foreign.is_none() => Ok(vec![t.to_string()]),
+            Some(_) => Err(EngineError::new(
+                engine_error_code::UNSUPPORTED_SQL,
+                format!("{t} is a foreign table"),
+            )),
+            None => Err(EngineError::new(
+                engine_error_code::UNSUPPORTED_SQL,
+                format!("table {t} does not exist"),
+            )),
+        },
+        None => Ok(cat
+            .table_names()
+            .into_iter()
+            .filter(|t| cat.schema(t).is_some_and(|s| s.foreign.is_none()))
+            .collect()),
+    }
+}
+
+/// Calls `f` with each record of `table`, or with the error decoding it; returns the page count.
+pub(super) fn scan(
+    state: &SqlEngineState,
+    table: &str,
+    mut f: impl FnMut(RecordId, Result<Tuple, String>),
+) -> Result<u64, EngineError> {
+    let pm = table_page_manager(state, table)?;
+    let page_ids = pm.read().all_page_ids().map_err(map_db_err)?;
+    for &page_id in &page_ids {
+        let records = pm.read().records_from_page(page_id).map_err(map_db_err)?;
+        for (slot, bytes) in records {
+            let rid = PageManager::record_id_for_slot(page_id, slot);
+            f(
+                rid,
+                shredding::decode_tuple(state, &bytes).map_err(|e| e.to_string()),
+            );
+        }
+    }
+    Ok(page_ids.len() as u64)
+}
+
+/// `CHECK TABLE [table]`: one `(table_name, status, rows, problems, message)` row per table.
+pub(super) fn check_tables(
+    state: &SqlEngineState,
+    ctx: &SessionContext,
+    table: Option<&str>,
+) -> Result<EngineOutput, EngineError> {
+    ensure_no_active_transaction(ctx)?;
+    let _storage = state
+        .storage_access
+        .read()
+        .map_err(|_| lock_poisoned_engine())?;
+    let registry = state
+        .index_registry
+        .read()
+        .map_err(|_| lock_poisoned_engine())?
+        .clone();
+    let mut out = Vec::new();
+    for table in local_tables(state, table)? {
+        let (rows, problems) = check_table(state, &registry, &table)?;
+        let mut row = Row::new();
+        row.set_value("table_name", text(&table));
+        row.set_value(
+            "status",
+            text(if problems.is_empty() { "ok" } else { "damaged" }),
+        );
+        row.set_value("rows", int(rows));
+        row.set_value("problems", int(problems.len() as u64));
+        let mut message = problems
+            .iter()
+            .take(MAX_REPORTED_PROBLEMS)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("; ");
+        if problems.len() > MAX_REPORTED_PROBLEMS {
+            let more = problems.len() - MAX_REPORTED_PROBLEMS;
+            message.push_str(&format!("; and {more} more"));
+        }
+        row.set_value("message", text(&message));
+        out.push(row);
+    }
+    rows_to_engine_output(out)
+}
+
+/// Rows of `table` and the problems found in it.
+fn check_table(
+    state: &SqlEngineState,
+    registry: &IndexRegistry,
+    table: &str,
+) -> Result<(u64, Vec<String>), EngineError> {
+    let indexes = registry.list_indexes_for_table(table);
+    let mut problems = Vec::new();
+    let mut rows = 0;
+    let mut rids = HashSet::new();
+    // (index, key, rid) of each row, looked up once the scan released the heap.
+    let mut expected: Vec<(usize, String, RecordId)> = Vec::new();
+    scan(state, table, |rid, tuple| match tuple {
+        Ok(tuple) => {
+            rows += 1;
+            rids.insert(rid);
+            if indexes.is_empty() {
+                return;
+            }
+            let mut values: HashMap<String, String> = tuple
+                .values
+                .iter()
+                .map(|(c, v)| (c.clone(), column_value_to_index_string(v)))
+                .collect();
+            add_computed_index_values(registry, table, &tuple, &mut values);
+            for (i, (name, columns)) in indexes.iter().enumerate() {
+                if !registry.covers_row(table, name, &values) {
+                    continue;
+                }
+                if let Ok(key) = IndexRegistry::build_index_key_from_map(columns, &values) {
+                    expected.push((i, key, rid));
+                }
+            }
+        }
+        Err(e) => problems.push(format!("record {rid} does not decode: {e}")),
+    })?;
+
+    for (i, (name, _)) in indexes.iter().enumerate() {
+        let Some(index) = registry.get_index(table, name) else {
+            continue;
+        };
+        let index = index.lock().map_err(|_| lock_poisoned_engine())?;
+        let mut missing = 0;
+        let mut covered = HashSet::new();
+        for (_, key, rid) in expected.iter().filter(|(j, _, _)| *j == i) {
+            covered.insert(*rid);
+            let found = index.search(key).map_err(map_db_err)?;
+            if !found.is_some_and(|ids| ids.contains(rid)) {
+                missing += 1;
+            }
+        }
+        if missing > 0 {
+            problems.push(format!("index {name} misses {missing} row(s)"));
+        }
+        let (mut dangling, mut excluded) = (0, 0);
+        for rid in index
+            .range_search(&String::new(), &INDEX_KEY_MAX.to_string())
+            .map_err(map_db_err)?
+            .into_iter()
+            .flat_map(|(_, ids)| ids)
+        {
+            if !rids.contains(&rid) {
+                dangling += 1;
+            } else if !covered.contains(&rid) {
+                excluded += 1;
+            }
+        }
+        if dangling > 0 {
+            problems.push(format!(
+                "index {name} has {dangling} entry(ies) without a row"
+            ));
+        }
+        if excluded > 0 {
+            problems.push(format!(
+                "index {name} has {excluded} entry(ies) of rows its predicate excludes"
+            ));
+        }
+    }
+    Ok((rows, problems))
+}
+
+/// `ANALYZE [table]`: collects and saves statistics, one `(table_name, rows, pages, columns)`
+/// row per table.
+pub(super) fn analyze(
+    state: &SqlEngineState,
+    ctx: &SessionContext,
+    table: Option<&str>,
+) -> Result<EngineOutput, EngineError> {
+    ensure_no_active_transaction(ctx)?;
+    let tables = local_tables(state, table)?;
+    rows_to_engine_output(analyze_tables(state, tables, false)?)
+}
+
+/// Collects and saves the statistics of `tables` (`auto`: for [`auto_analyze`]); one
+/// `(table_name, rows, pages, columns)` row per table.
+pub(super) fn analyze_tables(
+    state: &SqlEngineState,
+    tables: Vec<String>,
+    auto: bool,
+) -> Result<Vec<Row>, EngineError> {
+    let mut analyzed = Vec::new();
+    {
+        let _storage = state
+            .storage_access
+            .read()
+            .map_err(|_| lock_poisoned_engine())?;
+        for table in tables {
+            // Changes committed from here on are not all in the statistics.
+            let changed = auto_analyze::modified_rows(&state.modified_rows, &table);
+            let mut stats = table_stats(state, &table)?;
+            stats.auto = auto;
+            analyzed.push((table, stats, changed));
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut saved = state
+        .table_stats
+        .0
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?;
+    let stats = load(state, &mut saved)?;
+    for (table, table_stats, changed) in analyzed {
+        auto_analyze::analyzed(&state.modified_rows, &table, changed);
+        let mut row = Row::new();
+        row.set_value("table_name", text(&table));
+        row.set_value("rows", int(table_stats.rows));
+        row.set_value("pages", int(table_stats.pages));
+        row.set_value("columns", int(table_stats.columns.len() as u64));
+        out.push(row);
+        stats.insert(table, table_stats);
+    }
+    // Tables dropped since they were analyzed.
+    let names: HashSet<String> = state
+        .catalog
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?
+        .table_names()
+        .into_iter()
+        .collect();
+    stats.retain(|t, _| names.contains(t));
+    save(state, stats)?;
+    Ok(out)
+}
+
+/// Row count of each analyzed table as of its last `ANALYZE`.
+pub(super) fn analyzed_rows(state: &SqlEngineState) -> Result<HashMap<String, u64>, EngineError> {
+    let mut saved = state
+        .table_stats
+        .0
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?;
+    Ok(load(state, &mut saved)?
+        .iter()
+        .map(|(table, stats)| (table.clone(), stats.rows))
+        .collect())
+}
+
+fn table_stats(state: &SqlEngineState, table: &str) -> Result<TableStats, EngineError> {
+    let mut stats = TableStats::default();
+    let mut counts: HashMap<String, HashMap<String, u64>> = HashMap::new();
+    let mut non_null: HashMap<String, u64> = HashMap::new();
+    let mut bad = None;
+    stats.pages = scan(state, table, |rid, tuple| {
+        let tuple = match tuple {
+            Ok(tuple) => tuple,
+            Err(e) => {
+                bad.get_or_insert_with(|| format!("record {rid} of {table} does not decode: {e}"));
+                return;
+            }
+        };
+        stats.rows += 1;
+        for (column, value) in &tuple.values {
+            if !value.is_null && !matches!(value.data_type, DataType::Null) {
+                *non_null.entry(column.clone()).or_default() += 1;
+                *counts
+                    .entry(column.clone())
+                    .or_default()
+                    .entry(column_value_to_index_string(value))
+                    .or_default() += 1;
+            }
+        }
+    })?;
+    if let Some(message) = bad {
+        return Err(EngineError::new(engine_error_code::INTERNAL, message));
+    }
+    let columns = state
+        .catalog
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?
+        .schema(table)
+        .map(|s| s.columns.iter().map(|c| c.name.clone()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    for column in columns {
+        // A row without the column (NULL, or added by a later ALTER TABLE) counts as NULL.
+        let non_null = non_null.get(&column).copied().unwrap_or(0);
+        let nulls = stats.rows - non_null;
+        let values = counts.remove(&column).unwrap_or_default();
+        let distinct = values.len() as u64;
+        let fraction = |n: u64| n as f64 / stats.rows.max(1) as f64;
+        let most_common = most_common_values(values, non_null)
+            .into_iter()
+            .map(|(value, n)| MostCommon {
+                value,
+                frequency: fraction(n),
+            })
+            .collect();
+        stats.columns.insert(
+            column,
+            ColumnStats {
+                nulls,
+                distinct,
+                null_frac: fraction(nulls),
+                most_common,
+            },
+        );
+    }
+    stats.analyzed_at_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    Ok(stats)
+}
+
+/// The values of `counts` (`non_null` rows in all) seen more than once and more often than
+/// the average value, most frequent first, up to [`MAX_MOST_COMMON_VALUES`].
+fn most_common_values(counts: HashMap<String, u64>, non_null: u64) -> Vec<(String, u64)> {
+    let distinct = counts.len() as u64;
+    let mut common: Vec<(String, u64)> = counts
+        .into_iter()
+        .filter(|&(_, n)| n > 1 && n * distinct > non_null)
+        .collect();
+    common.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    common.truncate(MAX_MOST_COMMON_VALUES);
+    common
+}
+
+fn load<'a>(
+    state: &SqlEngineState,
+    saved: &'a mut Option<BTreeMap<String, TableStats>>,
+) -> Result<&'a mut BTreeMap<String, TableStats>, EngineError> {
+    if saved.is_none() {
+        let path = state.data_dir.join(STATS_FILE);
+        let stats = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| {
+                EngineError::new(
+                    engine_error_code::INTERNAL,
+                    format!("{}: {e}", path.display()),
+                )
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                return Err(EngineError::new(
+                    engine_error_code::INTERNAL,
+                    format!("{}: {e}", path.display()),
+                ))
+            }
+        };
+        *saved = Some(stats);
+    }
+    Ok(saved.get_or_insert_with(BTreeMap::new))
+}
+
+fn save(state: &SqlEngineState, stats: &BTreeMap<String, TableStats>) -> Result<(), EngineError> {
+    let path = state.data_dir.join(STATS_FILE);
+    let internal = |e: String| EngineError::new(engine_error_code::INTERNAL, e);
+    let json = serde_json::to_vec_pretty(stats).map_err(|e| internal(e.to_string()))?;
+    path.parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .map_err(|e| internal(format!("{}: {e}", path.display())))?;
+    write_atomic(&path, &json).map_err(map_db_err)
+}
+
+/// `rustdb_stat_tables`: rows and pages of each analyzed table, and the rows changed since.
+pub(super) fn table_rows(state: &SqlEngineState) -> Result<Vec<Row>, EngineError> {
+    let mut saved = state
+        .table_stats
+        .0
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?;
+    Ok(load(state, &mut saved)?
+        .iter()
+        .map(|(table, stats)| {
+            let mut row = Row::new();
+            row.set_value("table_name", text(table));
+            row.set_value("rows", int(stats.rows));
+            row.set_value("pages", int(stats.pages));
+            row.set_value("analyzed_at_ms", int(stats.analyzed_at_ms));
+            row.set_value(
+                "auto_analyzed",
+                ColumnValue::new(DataType::Boolean(stats.auto)),
+            );
+            row.set_value(
+                "modified_rows",
+                int(auto_analyze::modified_rows(&state.modified_rows, table)),
+            );
+            row
+        })
+        .collect())
+}
+
+/// `rustdb_stat_columns`: nulls, distinct values and most common values of each column of the
+/// analyzed tables; `most_common_values` lists `value (frequency)` most frequent first.
+pub(super) fn column_rows(state: &SqlEngineState) -> Result<Vec<Row>, EngineError> {
+    let mut saved = state
+        .table_stats
+        .0
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?;
+    let mut out = Vec::new();
+    for (table, stats) in load(state, &mut saved)?.iter() {
+        for (column, c) in &stats.columns {
+            let mut row = Row::new();
+            row.set_value("table_name", text(table));
+            row.set_value("column_name", text(column));
+            row.set_value("nulls", int(c.nulls));
+            row.set_value("distinct_values", int(c.distinct));
+            row.set_value("null_frac", ColumnValue::new(DataType::Double(c.null_frac)));
+            let common: Vec<String> = c
+                .most_common
+                .iter()
+                .map(|m| format!("{} ({:.3})", m.value, m.frequency))
+                .collect();
+            row.set_value("most_common_values", text(&common.join(", ")));
+            out.push(row);
+        }
+    }
+    Ok(out)
+}
+
+fn text(s: &str) -> ColumnValue {
+    ColumnValue::new(DataType::Varchar(format!("'{s}'")))
+}
+
+fn int(n: u64) -> ColumnValue {
+    ColumnValue::new(DataType::BigInt(n as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::engine::EngineHandle;
+    use crate::network::SqlEngine;
+    use crate::storage::index_registry::index_key;
+    use tempfile::TempDir;
+
+    #[test]
+    fn check_table_reports_rows_missing_from_an_index() {
+        let dir = TempDir::new().unwrap();
+        let eng = SqlEngine::open(dir.path().to_path_buf()).unwrap();
+        let mut ctx = SessionContext::default();
+        for sql in [
+            "CREATE TABLE items (id INT PRIMARY KEY, tag TEXT)",
+            "CREATE INDEX items_tag ON items (tag)",
+            "INSERT INTO items (id, tag) VALUES (1, 'a'), (2, 'b')",
+        ] {
+            eng.execute_sql(sql, &mut ctx).unwrap();
+        }
+        let registry = eng.state_for_test().index_registry.read().unwrap().clone();
+        let index = registry.get_index("items", "items_tag").unwrap();
+        assert!(index.lock().unwrap().delete(&index_key(["'b'"])).unwrap());
+
+        let EngineOutput::ResultSet { rows, .. } =
+            check_tables(eng.state_for_test(), &ctx, Some("items")).unwrap()
+        else {
+            panic!("expected rows");
+        };
+        // Columns by name: message, problems, rows, status, table_name.
+        assert_eq!(
+            rows,
+            vec![vec![
+                "Varchar(\"'index items_tag misses 1 row(s)'\")",
+                "BigInt(1)",
+                "BigInt(2)",
+                "Varchar(\"'damaged'\")",
+                "Varchar(\"'items'\")",
+            ]]
+        );
+    }
+}
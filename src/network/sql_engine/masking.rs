@@ -0,0 +1,287 @@
+//! Column masking: sensitive columns shown masked to roles without the `UNMASK` privilege.
+//!
+//! `masking.columns = 'customers.card=last4, patients.ssn'` names the masked columns and how each
+//! is shown (see [`ColumnMask`]); roles listed in `masking.unmask_roles` hold
+//! [`Permission::Unmask`] and read them as stored. Masks apply to the rows a read's plan
+//! produces, once the executor has run it and before they are encoded: `SELECT`, set operations,
+//! `INSERT ... SELECT` and reads forwarded to an exported snapshot. Filters, joins and sorts see
+//! stored values, as with dynamic data masking elsewhere: masking keeps raw values out of test
+//! and analyst sessions, it does not stop a determined role from probing them with `WHERE`
+//! (`audit.select_tables` records who reads what).
+//!
+//! Outputs are matched to masked columns through the select list. A masked column, or its
+//! `MIN` / `MAX`, keeps the column's mask under any alias; `COUNT` is not masked; any other
+//! expression over a masked column is masked `full`. Other outputs named like a masked column of
+//! a table the statement reads (`*`, `GROUP BY` keys) are masked as well, even when they come
+//! from an unmasked table of a join. The role is the one the session holds (see `roles`).
+
+use super::{collect_physical_tables_for_read_stmt, EngineError, RoleSlot};
+use crate::analyzer::access_checker::{Permission, Role};
+use crate::common::config::{parse_masked_columns, ColumnMask, MaskingConfig};
+use crate::common::types::{ColumnValue, DataType, Row};
+use crate::network::engine::{engine_error_code, SessionContext};
+use crate::parser::ast::{Expression, SelectItem, SelectStatement, SqlStatement, TableReference};
+use crate::planner::planner::expr_to_short_name;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+
+/// Masking policy of one engine.
+#[derive(Default)]
+pub(super) struct ColumnMasking {
+    policy: RwLock<Arc<MaskingPolicy>>,
+}
+
+#[derive(Default)]
+struct MaskingPolicy {
+    columns: Arc<BTreeMap<(String, String), ColumnMask>>,
+    roles: HashMap<String, Role>,
+}
+
+/// Masked columns (`(table, column)` to mask) a session's reads apply.
+#[derive(Debug, Clone)]
+pub(crate) struct ColumnMasks(Arc<BTreeMap<(String, String), ColumnMask>>);
+
+/// Applies the `masking.*` parameters.
+pub(super) fn configure(masking: &ColumnMasking, config: &MaskingConfig) -> Result<(), String> {
+    let policy = MaskingPolicy {
+        columns: Arc::new(parse_masked_columns(&config.columns)?),
+        roles: config
+            .unmask_roles
+            .split(',')
+            .map(|r| r.trim().to_ascii_lowercase())
+            .filter(|r| !r.is_empty())
+            .map(|r| (r.clone(), Role::new(r).with_permission(Permission::Unmask)))
+            .collect(),
+    };
+    // A poisoned policy is replaced whole, so its state does not matter.
+    *masking.policy.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(policy);
+    Ok(())
+}
+
+/// Masks `ctx`'s reads apply: none when nothing is masked or its role holds `UNMASK`.
+pub(super) fn session_masks(masking: &ColumnMasking, ctx: &SessionContext) -> Option<ColumnMasks> {
+    if let Some(masks) = &ctx.column_masks {
+        return Some(masks.clone());
+    }
+    let policy = masking
+        .policy
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    if policy.columns.is_empty() {
+        return None;
+    }
+    let unmask = ctx
+        .role
+        .as_ref()
+        .and_then(|slot| policy.roles.get(RoleSlot::role(slot)))
+        .is_some_and(|role| role.has_permission(&Permission::Unmask));
+    (!unmask).then(|| ColumnMasks(policy.columns.clone()))
+}
+
+/// Masks the values of `rows`, produced by the read `stmt`, that show masked columns.
+pub(super) fn mask_rows(
+    masks: &ColumnMasks,
+    stmt: &SqlStatement,
+    rows: &mut [Row],
+) -> Result<(), EngineError> {
+    let tables = collect_physical_tables_for_read_stmt(stmt);
+    let mut masked: HashMap<String, ColumnMask> = HashMap::new();
+    for ((table, column), &mask) in masks.0.iter() {
+        if tables.iter().any(|t| t.eq_ignore_ascii_case(table)) {
+            let strictest = masked.entry(column.clone()).or_insert(mask);
+            *strictest = (*strictest).max(mask);
+        }
+    }
+    if masked.is_empty() {
+        return Ok(());
+    }
+    let mut outputs = HashMap::new();
+    match stmt {
+        SqlStatement::Select(sel) => {
+            for (name, mask) in select_outputs(sel, &masked, &mut outputs) {
+                merge(&mut outputs, name, mask);
+            }
+        }
+        SqlStatement::SetOperation(set) => {
+            // Set operations name their columns after the left side and pair them by position.
+            if [&set.left, &set.right]
+                .iter()
+                .any(|s| s.select_list.contains(&SelectItem::Wildcard))
+            {
+                return Err(EngineError::new(
+                    engine_error_code::UNSUPPORTED_SQL,
+                    "set operations over masked columns must list their columns instead of *",
+                ));
+            }
+            let right = select_outputs(&set.right, &masked, &mut outputs);
+            let left = select_outputs(&set.left, &masked, &mut outputs);
+            for (i, (name, mask)) in left.into_iter().enumerate() {
+                merge(&mut outputs, name, mask.max(right.get(i).and_then(|r| r.1)));
+            }
+        }
+        _ => {}
+    }
+    for row in rows {
+        let names: Vec<String> = row.column_names().map(str::to_string).collect();
+        for name in names {
+            let mask = match outputs.get(&name) {
+                Some(&mask) => mask,
+                None => {
+                    let column = name.rsplit('.').next().unwrap_or(&name);
+                    masked.get(&column.to_ascii_lowercase()).copied()
+                }
+            };
+            if let (Some(mask), Some(value)) = (mask, row.get_value_mut(&name)) {
+                *value = mask_value(mask, value);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn merge(
+    outputs: &mut HashMap<String, Option<ColumnMask>>,
+    name: String,
+    mask: Option<ColumnMask>,
+) {
+    let current = outputs.entry(name).or_insert(mask);
+    *current = (*current).max(mask);
+}
+
+/// Output names of `sel`'s select list and their masks. The masked outputs of the derived tables
+/// it reads from are added to `outputs` and count as masked columns for `sel`.
+fn select_outputs(
+    sel: &SelectStatement,
+    masked: &HashMap<String, ColumnMask>,
+    outputs: &mut HashMap<String, Option<ColumnMask>>,
+) -> Vec<(String, Option<ColumnMask>)> {
+    let mut masked = masked.clone();
+    let derived = sel
+        .from
+        .iter()
+        .flat_map(|from| std::iter::once(&from.table).chain(from.joins.iter().map(|j| &j.table)));
+    for table in derived {
+        if let TableReference::Subquery { query, .. } = table {
+            for (name, mask) in select_outputs(query, &masked, outputs) {
+                if let Some(mask) = mask {
+                    let strictest = masked.entry(name.to_ascii_lowercase()).or_insert(mask);
+                    *strictest = (*strictest).max(mask);
+                    merge(outputs, name, Some(mask));
+                }
+            }
+        }
+    }
+    sel.select_list
+        .iter()
+        .filter_map(|item| match item {
+            SelectItem::Wildcard => None,
+            SelectItem::Expression { expr, alias } => {
+                let name = alias.clone().unwrap_or_else(|| expr_to_short_name(expr));
+                Some((name, expression_mask(expr, &masked)))
+            }
+        })
+        .collect()
+}
+
+fn expression_mask(expr: &Expression, masked: &HashMap<String, ColumnMask>) -> Option<ColumnMask> {
+    match expr {
+        Expression::Identifier(column) | Expression::QualifiedIdentifier { column, .. } => {
+            masked.get(&column.to_ascii_lowercase()).copied()
+        }
+        Expression::Function { name, .. } if name.eq_ignore_ascii_case("count") => None,
+        Expression::Function { name, args }
+            if name.eq_ignore_ascii_case("min") || name.eq_ignore_ascii_case("max") =>
+        {
+            args.iter().filter_map(|a| expression_mask(a, masked)).max()
+        }
+        _ => references_masked(expr, masked).then_some(ColumnMask::Full),
+    }
+}
+
+fn references_masked(expr: &Expression, masked: &HashMap<String, ColumnMask>) -> bool {
+    let any = |exprs: &[&Expression]| exprs.iter().any(|e| references_masked(e, masked));
+    match expr {
+        Expression::Identifier(column) | Expression::QualifiedIdentifier { column, .. } => {
+            masked.contains_key(&column.to_ascii_lowercase())
+        }
+        Expression::Literal(_) | Expression::Parameter(_) | Expression::Exists(_) => false,
+        Expression::BinaryOp { left, right, .. } => any(&[left, right]),
+        Expression::UnaryOp { expr, .. } | Expression::IsNull { expr, .. } => any(&[expr]),
+        Expression::Function { args, .. } => args.iter().any(|a| references_masked(a, masked)),
+        Expression::Case {
+            expr,
+            when_clauses,
+            else_clause,
+        } => {
+            expr.iter()
+                .chain(else_clause)
+                .any(|e| references_masked(e, masked))
+                || when_clauses.iter().any(|w| any(&[&w.condition, &w.result]))
+        }
+        // `IN` with a subquery yields a boolean; only the tested value can show a masked column.
+        Expression::In { expr, .. } => any(&[expr]),
+        Expression::Between { expr, low, high } => any(&[expr, low, high]),
+        Expression::Like { expr, pattern, .. } => any(&[expr, pattern]),
+    }
+}
+
+/// `value` as `mask` shows it.
+fn mask_value(mask: ColumnMask, value: &ColumnValue) -> ColumnValue {
+    let integer = match value.data_type {
+        DataType::TinyInt(n) => Some(i64::from(n)),
+        DataType::SmallInt(n) => Some(i64::from(n)),
+        DataType::Integer(n) => Some(i64::from(n)),
+        DataType::BigInt(n) => Some(n),
+        _ => None,
+    };
+    let data_type = match &value.data_type {
+        _ if value.is_null => return value.clone(),
+        _ if mask == ColumnMask::Null => return ColumnValue::null(),
+        DataType::Char(s) => DataType::Char(mask_text(mask, s)),
+        DataType::Varchar(s) => DataType::Varchar(mask_text(mask, s)),
+        DataType::Text(s) => DataType::Text(mask_text(mask, s)),
+        _ => match integer {
+            Some(n) if mask == ColumnMask::Last4 => {
+                DataType::Varchar(mask_text(mask, &format!("'{n}'")))
+            }
+            _ => return ColumnValue::null(),
+        },
+    };
+    ColumnValue::new(data_type)
+}
+
+/// Masks the string `s`, keeping the quotes string literals are stored with.
+fn mask_text(mask: ColumnMask, s: &str) -> String {
+    let quoted = s.strip_prefix('\'').and_then(|s| s.strip_suffix('\''));
+    let inner = quoted.unwrap_or(s);
+    let masked: String = match mask {
+        ColumnMask::Last4 => {
+            let shown = inner.chars().filter(|c| c.is_alphanumeric()).count();
+            // Values of four characters or fewer would be shown whole.
+            let mut hidden = if shown > 4 { shown - 4 } else { shown };
+            inner
+                .chars()
+                .map(|c| {
+                    if c.is_alphanumeric() && hidden > 0 {
+                        hidden -= 1;
+                        '*'
+                    } else {
+                        c
+                    }
+                })
+                .collect()
+        }
+        ColumnMask::Email => match inner.split_once('@') {
+            Some((local, domain)) if !local.is_empty() => {
+                format!("{}****@{domain}", local.chars().next().unwrap_or('*'))
+            }
+            _ => "****".to_string(),
+        },
+        ColumnMask::Full | ColumnMask::Null => "****".to_string(),
+    };
+    match quoted {
+        Some(_) => format!("'{masked}'"),
+        None => masked,
+    }
+}
@@ -12,6 +12,7 @@
 //!   `UPDATE`, and `DELETE` take an exclusive (`write`) lock on the target table only.
 //! - A **global** `SqlEngineState::storage_access` still serializes DDL, `ROLLBACK`, and
 //!   `INSERT ... SELECT` (mixed read/write on the same statement) against storage-wide invariants.
+//!   Row-locked DML holds it shared so a base backup (see `base_backup`) can exclude all writers.
 //! - Read committed baseline: each statement sees data committed before that statement began,
 //!   excluding the current session’s own uncommitted writes which are already on the heap.
 //! - Stronger isolation ([`crate::network::engine::SqlIsolationLevel::RepeatableRead`] /
@@ -23,13 +24,17 @@
 //!   call [`SqlEngine::checkpoint`] for a manual checkpoint (flushes heaps + writes a checkpoint record).
 //!   After each successful write of `catalog.json` (DDL), the WAL records a `MetadataUpdate` marker when WAL is on.
 //! - With WAL enabled, you may set **`RUSTDB_DEFER_HEAP_FLUSH_AFTER_DML=1`** to skip `flush_dirty_pages`
-//!   after successful implicit auto-commit DML (higher throughput; heap catches up at checkpoint /
-//!   `COMMIT`). Explicit `BEGIN … COMMIT` transactions defer per-statement heap flush and flush only
+//!   after successful implicit auto-commit DML (higher throughput; the background writer catches
+//!   the heap up, see below). Explicit `BEGIN … COMMIT` transactions defer per-statement heap flush and flush only
 //!   heap page managers for tables touched by DML on `COMMIT` / `ROLLBACK` (see
 //!   `SqlTransaction::touched_tables`).
 //! - Bench-only (**`RUSTDB_DEFER_HEAP_FLUSH_ON_COMMIT=1`**, WAL on): skip synchronous heap flush on
 //!   explicit `COMMIT` and on `ROLLBACK` without undo (`scripts/tpcc_throughput_ci.sh`). WAL and
 //!   `commits.log` remain the CI durability path; checkpoint and `ROLLBACK` with undo still flush.
+//! - Heap flushes write each table's dirty pages in file order. A commit that defers its flush
+//!   (either flag above) queues the pages it dirtied as a commit group; the `background_writer`
+//!   job (every `RUSTDB_BGWRITER_INTERVAL_MS`, default 200) writes queued groups together with one
+//!   `fsync` per heap file, bounding how much WAL recovery replays after a crash.
 //! - WAL group commit knobs: `RUSTDB_GROUP_COMMIT_ENABLED`, `RUSTDB_GROUP_COMMIT_INTERVAL_MS`,
 //!   `RUSTDB_GROUP_COMMIT_MAX_BATCH`, `RUSTDB_FORCE_FLUSH_IMMEDIATELY` (see `crate::network::sql_engine_wal`).
 //!   bench TPC-C preset sets defaults in `scripts/tpcc_env_presets.sh`).
@@ -53,54 +58,122 @@
 //! Use `RUST_LOG=rustdb::sql_phases=info` to filter.
 
 use crate::catalog::schema::{
-    CheckConstraint, ForeignKeyConstraintDef, SchemaManager, TableSchema, UniqueConstraintDef,
+    CheckConstraint, ForeignKeyConstraintDef, ForeignTableDef, SchemaManager, SecondaryIndexDef,
+    TableSchema, UniqueConstraintDef,
 };
+use crate::common::config::{ConfigReload, LayeredConfig};
+use crate::common::memory_tracking::{memory_scope, MemoryTag};
 use crate::common::types::{ColumnValue, DataType, RecordId};
 use crate::common::DurabilityMode;
 use crate::common::Error as DbError;
+use crate::core::background_jobs::BackgroundJobManager;
 use crate::executor::operators::{
     eval_predicate_expression, eval_scalar_expression, ScanOperatorFactory,
 };
-use crate::executor::QueryExecutor;
+use crate::executor::{analyze_scope, QueryExecutor, QueryExecutorConfig};
 use crate::network::engine::{
     engine_error_code, EngineError, EngineHandle, EngineOutput, PendingIndexInsert, SessionContext,
     SqlIsolationLevel, SqlTransaction, UndoEntry,
 };
+use crate::network::replication::ReplicationTracker;
 use crate::network::sql_commit_log;
 use crate::network::sql_constraints::{self, ConstraintRuntime};
 use crate::parser::ast::{
     AlterTableOperation, AlterTableStatement, BinaryOperator, ColumnConstraint,
-    CreateIndexStatement, CreateTableStatement, DataType as SqlDataType, DeleteStatement,
-    DropTableStatement, ExplainStatement, Expression, FromClause, InList, InsertStatement,
+    CreateForeignTableStatement, CreateIndexStatement, CreateTableStatement,
+    DataType as SqlDataType, DeleteStatement, DropIndexStatement, DropTableStatement,
+    ExplainFormat, ExplainStatement, Expression, FromClause, InList, IndexMethod, InsertStatement,
     InsertValues, Literal, SelectItem, SelectStatement, TableConstraint, TableReference,
     UpdateStatement,
 };
+use crate::parser::prepared::parameter_count;
 use crate::parser::{SqlParser, SqlStatement};
 use crate::planner::planner::IndexScanNode;
 use crate::planner::{
-    format_explain_output, ExecutionPlan, ExplainFormatOptions, OptimizationResult, PlanNode,
-    QueryOptimizer, QueryPlanner,
+    format_explain_analyze_output, CardinalityFeedback, ExecutionPlan, ExplainFormatOptions,
+    OptimizationResult, PlanNode, QueryOptimizer, QueryPlanner,
 };
-use crate::storage::index_registry::IndexRegistry;
+use crate::storage::foreign::validate_foreign_table;
+use crate::storage::index_registry::{IndexOptions, IndexRegistry};
+use crate::storage::lsm::{row_store::lsm_table_dir, LsmConfig};
 use crate::storage::page_manager::InsertResult;
-use crate::storage::page_manager::{PageManager, PageManagerConfig, PageManagerMutex};
+use crate::storage::page_manager::{
+    PageManager, PageManagerConfig, PageManagerLock, StorageEngineKind,
+};
 use crate::storage::row_locks::RowLockManager;
+use crate::storage::shred::Keyring;
 use crate::storage::tuple::Tuple;
 use crate::Row;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, Mutex, RwLock};
-use std::time::Instant;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
 use tracing::{info, info_span};
 
+mod admin;
 mod alter_table_ops;
+mod audit;
+mod auto_analyze;
+mod base_backup;
+mod cardinality_feedback;
+mod change_tracking;
+mod databases;
+mod index_advisor;
+mod index_build;
+mod key_locks;
+mod logical_decoding;
+mod maintenance;
+mod masking;
+mod persistent_indexes;
+mod prepared;
+mod query_stats;
+mod quotas;
+mod read_your_writes;
+mod roles;
+mod row_counts;
+mod schema_cache;
+mod sequences;
+mod session_trace;
+mod settings;
+mod shredding;
+mod snapshots;
+mod space_usage;
+mod startup;
+mod system_views;
+mod table_io;
+mod tenants;
+mod tiering;
+mod time_travel;
 mod tpcc_native;
+mod typed_results;
+mod validate;
+mod workload_capture;
+
+pub use prepared::{PreparedSql, ResultColumn};
+pub use startup::{RecoveryReport, StartupPhase};
+pub use table_io::TableIoStatistics;
+pub use tiering::TableTieringStatistics;
+pub use typed_results::{typed_result_scope, TypedResultScope};
+pub use validate::{StatementCheck, ValidationReport, Validator};
+
+pub(crate) use audit::AuditedRead;
+pub(crate) use auto_analyze::run_auto_analyze;
+pub(crate) use key_locks::HeldKeyLocks;
+pub(crate) use masking::ColumnMasks;
+pub(crate) use roles::RoleSlot;
+pub(crate) use sequences::TUPLE_ID_SEQUENCE;
+pub(crate) use snapshots::TransactionSnapshot;
+pub(crate) use tiering::run_tiering;
 
 /// Global lock: at most one [`SqlIsolationLevel::RepeatableRead`] or [`SqlIsolationLevel::Serializable`]
 /// engine transaction across all sessions.
 static STRONG_ISO_LOCK: parking_lot::Mutex<()> = parking_lot::Mutex::new(());
 
+/// How long `BEGIN` waits for [`STRONG_ISO_LOCK`] before failing with a retryable
+/// serialization failure.
+const SERIALIZATION_LOCK_WAIT: Duration = Duration::from_secs(10);
+
 // Test-only crash injection (enabled via env vars) to deterministically simulate
 // "process crash mid-statement" while keeping the test runner alive.
 //
@@ -160,6 +233,13 @@ pub struct SqlEngineConfig {
     /// Disabling skips checkpoint setup entirely (manual `SqlEngine::checkpoint` will then error).
     /// The legacy env var `RUSTDB_DISABLE_CHECKPOINT` still wins over an enabled value.
     pub checkpoints_enabled: bool,
+    /// Upload closed WAL segments to this [`crate::storage::remote::open_remote`] URL
+    /// (`s3://bucket/prefix` or a directory), under `wal/`. Defaults to `RUSTDB_WAL_ARCHIVE_URL`.
+    pub wal_archive_url: Option<String>,
+    /// Rebuild the constraint maps and secondary indexes of a table when a statement first uses
+    /// it rather than on open (see [`SqlEngine::preload_schema`]). Defaults to on unless
+    /// `RUSTDB_EAGER_CATALOG` is set.
+    pub lazy_catalog: bool,
 }
 
 impl Default for SqlEngineConfig {
@@ -176,17 +256,21 @@ impl Default for SqlEngineConfig {
             wal_enabled: true,
             durability,
             checkpoints_enabled: true,
+            wal_archive_url: std::env::var("RUSTDB_WAL_ARCHIVE_URL")
+                .ok()
+                .filter(|v| !v.is_empty()),
+            lazy_catalog: std::env::var_os("RUSTDB_EAGER_CATALOG").is_none(),
         }
     }
 }
 
 pub(crate) struct SqlEngineState {
-    data_dir: PathBuf,
+    pub(crate) data_dir: PathBuf,
     durability: DurabilityMode,
-    pub(crate) default_page_manager: Arc<PageManagerMutex>,
-    pub(crate) table_page_managers: Arc<Mutex<HashMap<String, Arc<PageManagerMutex>>>>,
-    /// Monotonic id assigned to inserted [`Tuple`] rows (persisted in tuple bytes).
-    next_tuple_id: AtomicU64,
+    pub(crate) default_page_manager: Arc<PageManagerLock>,
+    pub(crate) table_page_managers: Arc<Mutex<HashMap<String, Arc<PageManagerLock>>>>,
+    /// Monotonic id assigned to inserted [`Tuple`] rows (persisted in tuple bytes; see `sequences`).
+    tuple_ids: sequences::TupleIdSequence,
     planner: QueryPlanner,
     optimizer: Mutex<QueryOptimizer>,
     executor: QueryExecutor,
@@ -195,7 +279,7 @@ pub(crate) struct SqlEngineState {
     /// Cache for deterministic `SELECT` queries without `FROM` (literal projections only).
     ///
     /// These queries are common in benchmarks (`SELECT 1`) and are safe to memoize.
-    select_no_from_cache: Mutex<HashMap<String, EngineOutput>>,
+    select_no_from_cache: Mutex<HashMap<String, (EngineOutput, Vec<DataType>)>>,
     /// LRU of normalized SQL → optimized plans (DML validation + read path; invalidated on DDL).
     dml_plan_validation_cache: Mutex<DmlPlanValidationCache>,
     pub(crate) catalog: Mutex<SchemaManager>,
@@ -210,6 +294,75 @@ pub(crate) struct SqlEngineState {
     wal: Option<crate::network::sql_engine_wal::SqlEngineWal>,
     /// Secondary-index column names per table (refreshed on `CREATE INDEX` / open).
     index_columns_by_table: Mutex<HashMap<String, Arc<Vec<String>>>>,
+    /// Transactions detached by `PREPARE TRANSACTION`, by global id (in memory only).
+    prepared_transactions: Mutex<HashMap<String, PreparedSqlTransaction>>,
+    /// Connected replicas and synchronous commit (see [`crate::network::replication`]).
+    replication: Arc<ReplicationTracker>,
+    /// Snapshots of open `EXPORT SNAPSHOT` transactions, by id (see `snapshots`).
+    snapshot_exports: snapshots::SnapshotExports,
+    /// Progress of running and recent index builds (see `index_build`).
+    index_builds: index_build::IndexBuilds,
+    /// Periodic maintenance (timed checkpoints); jobs run on the WAL runtime.
+    pub(crate) background_jobs: BackgroundJobManager,
+    /// Server configuration (`SHOW` / `SET`, see `settings`); defaults until [`SqlEngine::configure`].
+    settings: RwLock<LayeredConfig>,
+    /// Startup self-check of the open that created this state (see `startup`).
+    recovery_report: OnceLock<startup::RecoveryReport>,
+    /// Per-fingerprint statistics of completed statements (see `query_stats`).
+    statement_stats: query_stats::StatementStats,
+    /// Records statements of sessions with `SET trace = on` (see `session_trace`); created on
+    /// first use.
+    query_tracer: OnceLock<crate::debug::QueryTracer>,
+    /// Server-wide statement capture, `SET workload_capture = on` (see `workload_capture`).
+    workload_capture: workload_capture::WorkloadCapture,
+    /// Apply progress when the engine is a replica (see `read_your_writes`).
+    replica_status: OnceLock<Arc<crate::network::replication::ReplicaStatus>>,
+    /// Sessions per role, for `network.max_connections_per_role` (see `roles`).
+    role_sessions: roles::RoleSessions,
+    /// Per-role `quotas.*` and their refusals (see `quotas`).
+    role_quotas: quotas::RoleQuotas,
+    /// Audit of reads of `audit.select_tables` (see `audit`).
+    select_audit: audit::SelectAudit,
+    /// Masking of `masking.columns` (see `masking`).
+    column_masking: masking::ColumnMasking,
+    /// Subject keys of `ENCRYPT BY` tables (see `shredding`).
+    crypto_shredding: shredding::CryptoShredding,
+    /// Tenant keys, quotas and usage (see `tenants`).
+    tenants: tenants::Tenants,
+    /// Tables whose constraint maps and indexes load on first use (see `schema_cache`).
+    schema_cache: schema_cache::SchemaCache,
+    /// Committed row counts answering `SELECT COUNT(*)` (see `row_counts`).
+    row_counts: row_counts::RowCounts,
+    /// PRIMARY KEY / UNIQUE keys locked by open transactions (see `key_locks`).
+    key_locks: key_locks::KeyLocks,
+    /// Changed rows of `change_tracking.tables` (see `change_tracking`).
+    change_tracking: change_tracking::ChangeTracking,
+    /// Statistics collected by `ANALYZE` (see `maintenance`).
+    table_stats: maintenance::TableStatistics,
+    /// Rows changed per table since its last `ANALYZE` (see `auto_analyze`).
+    modified_rows: auto_analyze::ModifiedRows,
+    /// Rows scans and filters returned, correcting the optimizer's estimates (see
+    /// `cardinality_feedback`).
+    cardinality_feedback: Arc<CardinalityFeedback>,
+}
+
+impl Drop for SqlEngineState {
+    fn drop(&mut self) {
+        // Closing the engine checkpoints stored indexes, so the next open redoes little WAL.
+        if let Err(e) = persistent_indexes::checkpoint(self) {
+            tracing::warn!(error = %e.message, "index checkpoint on close failed");
+        }
+        if let Err(e) = cardinality_feedback::save(self) {
+            tracing::warn!(error = %e.message, "saving cardinality feedback on close failed");
+        }
+    }
+}
+
+/// A session transaction parked by `PREPARE TRANSACTION` until `COMMIT PREPARED` /
+/// `ROLLBACK PREPARED` (from any session).
+struct PreparedSqlTransaction {
+    transaction: SqlTransaction,
+    txn_pm_cache: HashMap<String, Arc<PageManagerLock>>,
 }
 
 impl SqlEngine {
@@ -238,30 +391,51 @@ impl SqlEngine {
             Some(c) => c,
             None => SchemaManager::new()?,
         };
+        let mut report = startup::RecoveryReport::default();
+        startup::check_data_files(&mut report, &data_dir);
         let pm = match PageManager::open(data_dir.clone(), "default", PageManagerConfig::default())
         {
             Ok(pm) => pm,
             Err(_) => PageManager::new(data_dir.clone(), "default", PageManagerConfig::default())?,
         };
-        let pm = Arc::new(PageManagerMutex::new(pm));
-        let table_pms: Arc<Mutex<HashMap<String, Arc<PageManagerMutex>>>> =
+        let pm = Arc::new(PageManagerLock::new(pm));
+        let table_pms: Arc<Mutex<HashMap<String, Arc<PageManagerLock>>>> =
             Arc::new(Mutex::new(HashMap::new()));
-        let index_registry = Arc::new(RwLock::new(IndexRegistry::new()));
-        let factory = Arc::new(ScanOperatorFactory::with_tables(
-            pm.clone(),
-            table_pms.clone(),
+        let index_registry = Arc::new(RwLock::new(IndexRegistry::with_storage_dir(
             data_dir.clone(),
-            Some(index_registry.clone()),
-        ));
-        let executor = QueryExecutor::new(factory)?;
+        )));
+        let keyring = Arc::new(Keyring::default());
+        let factory = Arc::new(
+            ScanOperatorFactory::with_tables(
+                pm.clone(),
+                table_pms.clone(),
+                data_dir.clone(),
+                Some(index_registry.clone()),
+            )
+            .with_keyring(keyring.clone()),
+        );
+        // Sort runs left behind by a crash
+        let temp_dir = data_dir.join("tmp");
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let executor = QueryExecutor::with_config(
+            factory,
+            QueryExecutorConfig {
+                temp_dir: Some(temp_dir),
+                ..Default::default()
+            },
+        )?;
+        let cardinality_feedback = cardinality_feedback::load(&data_dir);
         let state = Arc::new(SqlEngineState {
             data_dir,
             durability: config.durability,
             default_page_manager: pm,
             table_page_managers: table_pms,
-            next_tuple_id: AtomicU64::new(1),
+            tuple_ids: Default::default(),
             planner: QueryPlanner::new()?,
-            optimizer: Mutex::new(QueryOptimizer::new()?),
+            optimizer: Mutex::new(
+                QueryOptimizer::new()?.with_cardinality_feedback(cardinality_feedback.clone()),
+            ),
+            cardinality_feedback,
             executor,
             index_registry,
             select_no_from_cache: Mutex::new(HashMap::new()),
@@ -273,30 +447,123 @@ impl SqlEngine {
             row_locks: RowLockManager::new(),
             wal,
             index_columns_by_table: Mutex::new(HashMap::new()),
+            prepared_transactions: Mutex::new(HashMap::new()),
+            replication: Arc::new(ReplicationTracker::default()),
+            snapshot_exports: Default::default(),
+            index_builds: Default::default(),
+            background_jobs: BackgroundJobManager::new(),
+            settings: RwLock::new(LayeredConfig::default()),
+            recovery_report: OnceLock::new(),
+            statement_stats: query_stats::StatementStats::default(),
+            query_tracer: OnceLock::new(),
+            workload_capture: Default::default(),
+            replica_status: OnceLock::new(),
+            role_sessions: Default::default(),
+            role_quotas: Default::default(),
+            select_audit: Default::default(),
+            column_masking: Default::default(),
+            crypto_shredding: shredding::CryptoShredding::with_keyring(keyring),
+            tenants: Default::default(),
+            schema_cache: Default::default(),
+            row_counts: Default::default(),
+            key_locks: Default::default(),
+            change_tracking: Default::default(),
+            table_stats: Default::default(),
+            modified_rows: Default::default(),
         });
+        shredding::load_keys(state.as_ref())
+            .map_err(|e| DbError::database(format!("subject keys on open: {}", e.message)))?;
+        tenants::load(state.as_ref())
+            .map_err(|e| DbError::database(format!("tenant keys on open: {}", e.message)))?;
         if state.wal.is_some() && wal_dir.is_dir() {
-            crate::network::sql_engine_wal::replay_wal_into_engine(
+            let started = Instant::now();
+            let stats = crate::network::sql_engine_wal::replay_wal_into_engine(
                 state.as_ref(),
                 &wal_dir,
                 state.wal.as_ref(),
             )
             .map_err(|e| DbError::database(format!("WAL replay on open: {e}")))?;
+            state.tuple_ids.restore(stats.tuple_id_high_water);
+            startup::record_wal_replay(&mut report, started, stats);
         }
         if let Some(ref wal) = state.wal {
             if config.checkpoints_enabled {
                 wal.setup_checkpoint(state.clone())
                     .map_err(|e| DbError::database(format!("checkpoint setup on open: {e}")))?;
             }
+            wal.setup_background_writer(state.clone())
+                .map_err(|e| DbError::database(format!("background writer setup on open: {e}")))?;
+            if let Some(url) = &config.wal_archive_url {
+                wal.setup_wal_archive(state.clone(), &wal_dir, url)
+                    .map_err(|e| DbError::database(format!("WAL archive setup on open: {e}")))?;
+            }
+        }
+        tenants::finish_drops(state.as_ref())
+            .map_err(|e| DbError::database(format!("DROP TENANT on open: {}", e.message)))?;
+        {
+            let cat = state
+                .catalog
+                .lock()
+                .map_err(|_| DbError::internal("Lock poisoned"))?;
+            startup::check_catalog(&mut report, &cat);
         }
+        let started = Instant::now();
+        let deferred = if config.lazy_catalog {
+            schema_cache::defer_all(state.as_ref())
+                .map_err(|e| DbError::database(format!("catalog on open: {}", e.message)))?
+        } else {
+            0
+        };
         rebuild_all_constraint_runtime(state.as_ref()).map_err(|e| {
             DbError::database(format!("catalog/constraint rebuild on open: {}", e.message))
         })?;
-        rebuild_secondary_indexes_from_catalog(state.as_ref()).map_err(|e| {
+        report.finish_phase(
+            "constraint_rebuild",
+            started,
+            match deferred {
+                0 => "constraint runtime rebuilt".to_string(),
+                n => format!("constraint runtime rebuilt; {n} tables load on first use"),
+            },
+            Vec::new(),
+        );
+        let started = Instant::now();
+        rebuild_secondary_indexes_from_catalog(state.as_ref(), None).map_err(|e| {
             DbError::database(format!("secondary index rebuild on open: {}", e.message))
         })?;
         rebuild_index_columns_cache(state.as_ref()).map_err(|e| {
             DbError::database(format!("index columns cache on open: {}", e.message))
         })?;
+        let indexes: usize = {
+            let cat = state
+                .catalog
+                .lock()
+                .map_err(|_| DbError::internal("Lock poisoned"))?;
+            cat.table_names()
+                .iter()
+                .filter_map(|t| cat.schema(t))
+                .map(|s| s.secondary_indexes.len())
+                .sum()
+        };
+        report.finish_phase(
+            "index_rebuild",
+            started,
+            format!("{indexes} secondary indexes rebuilt"),
+            Vec::new(),
+        );
+        let started = Instant::now();
+        register_foreign_tables_from_catalog(state.as_ref()).map_err(|e| {
+            DbError::database(format!("foreign table registration on open: {}", e.message))
+        })?;
+        report.finish_phase(
+            "foreign_tables",
+            started,
+            "foreign tables registered".to_string(),
+            Vec::new(),
+        );
+        shredding::reseal_inherited(state.as_ref())
+            .map_err(|e| DbError::database(format!("inherited keys on open: {}", e.message)))?;
+        report.log_summary();
+        let _ = state.recovery_report.set(report);
         Ok(Self { state })
     }
 
@@ -307,7 +574,9 @@ impl SqlEngine {
             .wal
             .as_ref()
             .ok_or_else(|| DbError::database("WAL disabled; checkpoint unavailable"))?;
-        wal.checkpoint()
+        wal.checkpoint()?;
+        persistent_indexes::checkpoint(&self.state).map_err(|e| DbError::database(e.message))?;
+        cardinality_feedback::save(&self.state).map_err(|e| DbError::database(e.message))
     }
 
     /// Flush buffered WAL records (integration tests and explicit teardown before drop).
@@ -334,11 +603,92 @@ impl SqlEngine {
         &self.state.data_dir
     }
 
+    /// Startup self-check and recovery of this engine's open; listed by the
+    /// `rustdb_startup_report` system view.
+    pub fn recovery_report(&self) -> &RecoveryReport {
+        self.state
+            .recovery_report
+            .get()
+            .expect("recovery report is set by open")
+    }
+
+    /// Background jobs of this engine (pause, resume or trigger them by name); listed by the
+    /// `rustdb_stat_background_jobs` system view.
+    pub fn background_jobs(&self) -> &BackgroundJobManager {
+        &self.state.background_jobs
+    }
+
+    /// Installs the server configuration shown by `SHOW` and changed by `SET`, applying its
+    /// runtime parameters (interface language, synchronous commit), starting the tiering job
+    /// when `tiering.cold_tablespace` is set and the auto-analyze job unless
+    /// `auto_analyze.interval_secs` is 0.
+    pub fn configure(&self, config: LayeredConfig) -> Result<(), DbError> {
+        settings::configure(&self.state, config).map_err(|e| DbError::validation(e.message))?;
+        tiering::setup(&self.state).map_err(|e| DbError::database(e.message))?;
+        auto_analyze::setup(&self.state).map_err(|e| DbError::database(e.message))
+    }
+
+    /// Current server configuration, including runtime `SET`s.
+    pub fn settings(&self) -> Result<LayeredConfig, DbError> {
+        self.state
+            .settings
+            .read()
+            .map(|s| s.clone())
+            .map_err(|_| DbError::internal("Lock poisoned"))
+    }
+
+    /// Re-reads the configuration file and environment (e.g. on `SIGHUP`) and applies the
+    /// runtime parameters; the others are reported as pending until restart.
+    pub fn reload_config(&self) -> Result<ConfigReload, DbError> {
+        settings::reload(&self.state).map_err(|e| DbError::validation(e.message))
+    }
+
+    /// Replicas streaming from this engine; configures synchronous commit.
+    pub fn replication(&self) -> &Arc<ReplicationTracker> {
+        &self.state.replication
+    }
+
     #[cfg(test)]
     pub(crate) fn state_for_test(&self) -> &SqlEngineState {
         self.state.as_ref()
     }
 
+    /// Loads the constraint maps and secondary indexes of `tables` (and of the tables linked to
+    /// them by foreign keys) now, so the first statements using them do not wait for it;
+    /// returns how many tables were loaded. Services sensitive to first-query latency call it
+    /// after open (see [`SqlEngineConfig::lazy_catalog`]).
+    pub fn preload_schema(&self, tables: &[&str]) -> Result<usize, DbError> {
+        schema_cache::preload(&self.state, tables).map_err(|e| DbError::database(e.message))
+    }
+
+    /// Disk I/O and buffer pool counters of each table used since open, by name (the
+    /// `rustdb_stat_table_io` view).
+    pub fn table_io_statistics(&self) -> Result<Vec<TableIoStatistics>, DbError> {
+        table_io::collect(&self.state).map_err(|e| DbError::database(e.message))
+    }
+
+    /// [`Self::table_io_statistics`] in the Prometheus text exposition format.
+    pub fn table_io_prometheus(&self) -> Result<String, DbError> {
+        Ok(table_io::prometheus(&self.table_io_statistics()?))
+    }
+
+    /// Runs the tiering job's pass now over the tables used since open; returns the extents
+    /// moved (none without `tiering.cold_tablespace`).
+    pub fn run_tiering(&self) -> Result<usize, DbError> {
+        tiering::run_tiering(&self.state).map_err(|e| DbError::database(e.message))
+    }
+
+    /// Runs the auto-analyze job's check now; returns the tables it analyzed.
+    pub fn run_auto_analyze(&self) -> Result<Vec<String>, DbError> {
+        auto_analyze::run_auto_analyze(&self.state).map_err(|e| DbError::database(e.message))
+    }
+
+    /// Hot and cold extents of each table used since open and the moves between them, by name
+    /// (the `rustdb_stat_tiering` view).
+    pub fn tiering_statistics(&self) -> Result<Vec<TableTieringStatistics>, DbError> {
+        tiering::collect(&self.state).map_err(|e| DbError::database(e.message))
+    }
+
     /// Checkpoint statistics (returns `None` when WAL or checkpoints are disabled).
     pub fn checkpoint_statistics(
         &self,
@@ -365,7 +715,8 @@ impl SqlEngine {
         // This avoids repeated parse/AST construction in tight loops (e.g. select_literal bench).
         if likely_select_without_from(sql) {
             if let Ok(g) = state.select_no_from_cache.lock() {
-                if let Some(cached) = g.get(sql) {
+                if let Some((cached, values)) = g.get(sql) {
+                    record_select_without_from(cached, values);
                     return Ok(cached.clone());
                 }
             }
@@ -397,24 +748,99 @@ impl SqlEngine {
                 "only one SQL statement per request is supported",
             ));
         }
-        let stmt = &stmts[0];
+        if sql.contains(['?', '$']) && parameter_count(&stmts[0]) > 0 {
+            return Err(EngineError::new(
+                engine_error_code::PROTOCOL,
+                "statement has parameter placeholders; run it with SqlEngine::execute_prepared",
+            ));
+        }
+        Self::execute_statement(state, sql, &stmts[0], ctx)
+    }
+
+    /// Runs `stmt`, parsed from `sql`.
+    pub(crate) fn execute_statement(
+        state: &SqlEngineState,
+        sql: &str,
+        stmt: &SqlStatement,
+        ctx: &mut SessionContext,
+    ) -> Result<EngineOutput, EngineError> {
+        Self::execute_planned(state, sql, stmt, None, ctx)
+    }
+
+    /// [`Self::execute_statement`] for a statement bound from a prepared one (with `sql` its
+    /// text; see [`prepared`]): `plan` gives its plan, in place of planning `stmt`.
+    fn execute_planned(
+        state: &SqlEngineState,
+        sql: &str,
+        stmt: &SqlStatement,
+        plan: Option<PreparedPlan<'_>>,
+        ctx: &mut SessionContext,
+    ) -> Result<EngineOutput, EngineError> {
+        tenants::check_access(state, ctx, stmt)?;
+        schema_cache::load_statement_tables(state, stmt)?;
+        if !matches!(
+            stmt,
+            SqlStatement::SetParameter { .. } | SqlStatement::ShowParameter(_)
+        ) {
+            read_your_writes::wait_for_token(state, ctx)?;
+        }
+        if let SqlStatement::Select(sel) = stmt {
+            if let Some(view) = system_views::system_view_name(sel) {
+                return system_views::execute_system_view(state, view, sel);
+            }
+            if let Some(out) = query_stats::execute_reset(state, sel) {
+                return out;
+            }
+            if let Some(out) = space_usage::execute_size_functions(state, sel) {
+                return out;
+            }
+        }
+        if matches!(
+            stmt,
+            SqlStatement::Select(_) | SqlStatement::SetOperation(_)
+        ) {
+            ctx.audited_read = audit::audited_read(&state.select_audit, ctx, || {
+                collect_physical_tables_for_read_stmt(stmt)
+            });
+        }
+        if let SqlStatement::Select(sel) = stmt {
+            if let Some((table, at)) = time_travel::as_of_table(sel)? {
+                return time_travel::execute_as_of(state, ctx, stmt, sel, table, at);
+            }
+        }
+        if let Some(snapshot) = ctx.transaction.as_ref().and_then(|tx| tx.snapshot.as_ref()) {
+            let masks = masking::session_masks(&state.column_masking, ctx);
+            if let Some(out) = snapshots::execute_in_snapshot(snapshot, sql, stmt, masks) {
+                return out;
+            }
+        }
+        if let SqlStatement::Select(sel) = stmt {
+            if let Some(out) = row_counts::execute_count(state, ctx, sel) {
+                return out;
+            }
+        }
         match stmt {
             SqlStatement::Explain(ex) => execute_explain(state, sql, ctx, ex),
             SqlStatement::Select(sel) if sel.from.is_none() => {
-                let out = eval_select_without_from(sel)?;
+                let (out, values) = eval_select_without_from(sel)?;
+                record_select_without_from(&out, &values);
                 if likely_select_without_from(sql) {
                     if let Ok(mut g) = state.select_no_from_cache.lock() {
-                        g.insert(sql.to_string(), out.clone());
+                        g.insert(sql.to_string(), (out.clone(), values));
                     }
                 }
                 Ok(out)
             }
             SqlStatement::Select(_) | SqlStatement::SetOperation(_) => {
                 let table_names = collect_physical_tables_for_read_stmt(stmt);
+                let masks = masking::session_masks(&state.column_masking, ctx);
                 let optimized_plan = {
                     let s = info_span!("sql.plan");
                     let _sg = s.enter();
-                    plan_and_optimize_read(state, sql, stmt)?
+                    match plan {
+                        Some(plan) => plan(state)?,
+                        None => plan_and_optimize_read(state, sql, stmt)?,
+                    }
                 };
                 let skip_read =
                     select_skip_table_read_lock_tables(state, &table_names, &optimized_plan.root);
@@ -423,14 +849,15 @@ impl SqlEngine {
                         .storage_access
                         .read()
                         .map_err(|_| lock_poisoned_engine())?;
-                    let rows = {
+                    let _memory = memory_scope(MemoryTag::Execution);
+                    let mut rows = {
                         let s = info_span!("sql.exec_plan");
                         let _sg = s.enter();
-                        state
-                            .executor
-                            .execute(&optimized_plan)
-                            .map_err(map_db_err)?
+                        cardinality_feedback::execute(state, &optimized_plan)?
                     };
+                    if let Some(masks) = &masks {
+                        masking::mask_rows(masks, stmt, &mut rows)?;
+                    }
                     {
                         let s = info_span!("sql.encode_rows", row_count = rows.len());
                         let _eg = s.enter();
@@ -451,14 +878,15 @@ impl SqlEngine {
                         .zip(locked_tables.iter())
                         .map(|(l, table)| acquire_table_storage_read_lock(l, table))
                         .collect::<Result<_, _>>()?;
-                    let rows = {
+                    let _memory = memory_scope(MemoryTag::Execution);
+                    let mut rows = {
                         let s = info_span!("sql.exec_plan");
                         let _sg = s.enter();
-                        state
-                            .executor
-                            .execute(&optimized_plan)
-                            .map_err(map_db_err)?
+                        cardinality_feedback::execute(state, &optimized_plan)?
                     };
+                    if let Some(masks) = &masks {
+                        masking::mask_rows(masks, stmt, &mut rows)?;
+                    }
                     {
                         let s = info_span!("sql.encode_rows", row_count = rows.len());
                         let _eg = s.enter();
@@ -469,6 +897,7 @@ impl SqlEngine {
             SqlStatement::Insert(ins) => {
                 let s = info_span!("sql.insert", table = %ins.table);
                 let _sg = s.enter();
+                ensure_not_foreign_table(state, &ins.table)?;
                 match &ins.values {
                     InsertValues::Select(_) => {
                         let _storage = state
@@ -476,7 +905,7 @@ impl SqlEngine {
                             .write()
                             .map_err(|_| lock_poisoned_engine())?;
                         execute_dml_autocommit(state, ctx, |state, ctx| {
-                            execute_insert(state, ctx, sql, stmt, ins)
+                            execute_insert(state, ctx, sql, stmt, ins, plan)
                         })
                     }
                     InsertValues::Values(_) => {
@@ -495,7 +924,7 @@ impl SqlEngine {
                                 .as_ref()
                                 .map(|(l, t)| acquire_table_storage_write_lock(l, t))
                                 .transpose()?;
-                            execute_insert(state, ctx, sql, stmt, ins)
+                            execute_insert(state, ctx, sql, stmt, ins, plan)
                         })
                     }
                 }
@@ -503,6 +932,7 @@ impl SqlEngine {
             SqlStatement::Update(upd) => {
                 let s = info_span!("sql.update", table = %upd.table);
                 let _sg = s.enter();
+                ensure_not_foreign_table(state, &upd.table)?;
                 with_dml_write_lock(
                     state,
                     &upd.table,
@@ -510,7 +940,7 @@ impl SqlEngine {
                     ctx.skip_dml_storage_lock,
                     || {
                         execute_dml_autocommit(state, ctx, |state, ctx| {
-                            execute_update(state, ctx, sql, stmt, upd)
+                            execute_update(state, ctx, sql, stmt, upd, plan)
                         })
                     },
                 )
@@ -518,6 +948,7 @@ impl SqlEngine {
             SqlStatement::Delete(del) => {
                 let s = info_span!("sql.delete", table = %del.table);
                 let _sg = s.enter();
+                ensure_not_foreign_table(state, &del.table)?;
                 with_dml_write_lock(
                     state,
                     &del.table,
@@ -525,7 +956,7 @@ impl SqlEngine {
                     ctx.skip_dml_storage_lock,
                     || {
                         execute_dml_autocommit(state, ctx, |state, ctx| {
-                            execute_delete(state, ctx, sql, stmt, del)
+                            execute_delete(state, ctx, sql, stmt, del, plan)
                         })
                     },
                 )
@@ -537,11 +968,17 @@ impl SqlEngine {
                     index = %ci.index_name
                 );
                 let _sg = s.enter();
+                ensure_not_foreign_table(state, &ci.table_name)?;
+                if ci.concurrently {
+                    return execute_create_index_concurrently(state, ctx, ci)
+                        .and_then(|out| logical_decoding::log_ddl(state, sql, ctx).map(|()| out));
+                }
                 let _storage = state
                     .storage_access
                     .write()
                     .map_err(|_| lock_poisoned_engine())?;
                 execute_create_index(state, ctx, ci)
+                    .and_then(|out| logical_decoding::log_ddl(state, sql, ctx).map(|()| out))
             }
             SqlStatement::CreateTable(ct) => {
                 let s = info_span!("sql.create_table", table = %ct.table_name);
@@ -551,6 +988,16 @@ impl SqlEngine {
                     .write()
                     .map_err(|_| lock_poisoned_engine())?;
                 execute_create_table(state, ctx, ct)
+                    .and_then(|out| logical_decoding::log_ddl(state, sql, ctx).map(|()| out))
+            }
+            SqlStatement::CreateForeignTable(cf) => {
+                let s = info_span!("sql.create_foreign_table", table = %cf.table_name);
+                let _sg = s.enter();
+                let _storage = state
+                    .storage_access
+                    .write()
+                    .map_err(|_| lock_poisoned_engine())?;
+                execute_create_foreign_table(state, ctx, cf)
             }
             SqlStatement::DropTable(dt) => {
                 let s = info_span!("sql.drop_table", table = %dt.table_name);
@@ -559,16 +1006,32 @@ impl SqlEngine {
                     .storage_access
                     .write()
                     .map_err(|_| lock_poisoned_engine())?;
+                ensure_no_index_build(state, &dt.table_name)?;
                 execute_drop_table(state, ctx, dt)
+                    .and_then(|out| logical_decoding::log_ddl(state, sql, ctx).map(|()| out))
+            }
+            SqlStatement::DropIndex(di) => {
+                let s = info_span!("sql.drop_index", index = %di.index_name);
+                let _sg = s.enter();
+                let _storage = state
+                    .storage_access
+                    .write()
+                    .map_err(|_| lock_poisoned_engine())?;
+                execute_drop_index(state, ctx, di)
+                    .and_then(|out| logical_decoding::log_ddl(state, sql, ctx).map(|()| out))
             }
             SqlStatement::AlterTable(alt) => {
                 let s = info_span!("sql.alter_table", table = %alt.table_name);
                 let _sg = s.enter();
+                ensure_not_foreign_table(state, &alt.table_name)?;
                 let _storage = state
                     .storage_access
                     .write()
                     .map_err(|_| lock_poisoned_engine())?;
-                execute_alter_table(state, ctx, alt)
+                ensure_no_index_build(state, &alt.table_name)?;
+                let out = execute_alter_table(state, ctx, alt);
+                row_counts::invalidate(&state.row_counts, &alt.table_name);
+                out.and_then(|out| logical_decoding::log_ddl(state, sql, ctx).map(|()| out))
             }
             SqlStatement::BeginTransaction => begin_transaction(state, ctx),
             SqlStatement::CommitTransaction => commit_transaction(state, ctx),
@@ -579,6 +1042,101 @@ impl SqlEngine {
                     .map_err(|_| lock_poisoned_engine())?;
                 rollback_transaction(state, ctx)
             }
+            SqlStatement::PrepareTransaction(gid) => prepare_transaction(state, ctx, gid.clone()),
+            SqlStatement::ExportSnapshot => snapshots::export_snapshot(state, ctx),
+            SqlStatement::SetTransactionSnapshot(id) => snapshots::import_snapshot(state, ctx, id),
+            SqlStatement::SetParameter { name, value }
+                if name.eq_ignore_ascii_case(session_trace::TRACE_PARAMETER) =>
+            {
+                session_trace::set_trace(state, ctx, value)
+            }
+            SqlStatement::SetParameter { name, value }
+                if name.eq_ignore_ascii_case(workload_capture::CAPTURE_PARAMETER) =>
+            {
+                workload_capture::set_capture(&state.workload_capture, &state.data_dir, value)
+            }
+            SqlStatement::SetParameter { name, value } if read_your_writes::is_parameter(name) => {
+                read_your_writes::set_parameter(ctx, name, value)
+            }
+            SqlStatement::SetParameter { name, value }
+                if name.eq_ignore_ascii_case(roles::ROLE_PARAMETER) =>
+            {
+                roles::set_role(state, ctx, value)
+            }
+            SqlStatement::SetParameter { name, value }
+                if name.eq_ignore_ascii_case(tenants::TENANT_PARAMETER) =>
+            {
+                tenants::set_tenant(state, ctx, value)
+            }
+            SqlStatement::SetParameter { name, value } => {
+                settings::set_parameter(state, name, value)
+            }
+            SqlStatement::ShowParameter(Some(name))
+                if name.eq_ignore_ascii_case(session_trace::TRACE_PARAMETER) =>
+            {
+                session_trace::show_trace(ctx)
+            }
+            SqlStatement::ShowParameter(Some(name))
+                if name.eq_ignore_ascii_case(workload_capture::CAPTURE_PARAMETER) =>
+            {
+                workload_capture::show_capture(&state.workload_capture)
+            }
+            SqlStatement::ShowParameter(Some(name)) if read_your_writes::is_parameter(name) => {
+                read_your_writes::show_parameter(ctx, name)
+            }
+            SqlStatement::ShowParameter(Some(name))
+                if name.eq_ignore_ascii_case(roles::ROLE_PARAMETER) =>
+            {
+                roles::show_role(ctx)
+            }
+            SqlStatement::ShowParameter(Some(name))
+                if name.eq_ignore_ascii_case(tenants::TENANT_PARAMETER) =>
+            {
+                tenants::show_tenant(ctx)
+            }
+            SqlStatement::ShowParameter(name) => settings::show_parameter(state, name.as_deref()),
+            SqlStatement::CreateDatabase { name, template } => {
+                databases::create_database(state, ctx, name, template.as_deref())
+            }
+            SqlStatement::CreateTenant { name, quotas } => {
+                tenants::create_tenant(state, ctx, name, quotas)
+            }
+            SqlStatement::AlterTenant { name, quotas } => {
+                tenants::alter_tenant(state, ctx, name, quotas)
+            }
+            SqlStatement::DropTenant { name, if_exists } => {
+                tenants::drop_tenant(state, ctx, name, *if_exists)
+            }
+            SqlStatement::Checkpoint => admin::checkpoint(state),
+            SqlStatement::FlushLogs => admin::flush_logs(state),
+            SqlStatement::ShowEngineStatus => admin::show_engine_status(state),
+            SqlStatement::ShowTableStatus => space_usage::show_table_status(state),
+            SqlStatement::Forget(subject) => shredding::forget(state, ctx, subject),
+            SqlStatement::Vacuum(table) => {
+                let _storage = state
+                    .storage_access
+                    .write()
+                    .map_err(|_| lock_poisoned_engine())?;
+                shredding::vacuum(state, ctx, table.as_deref())
+            }
+            SqlStatement::CheckTable(table) => {
+                maintenance::check_tables(state, ctx, table.as_deref())
+            }
+            SqlStatement::Analyze(table) => maintenance::analyze(state, ctx, table.as_deref()),
+            SqlStatement::FlashbackTable { table, timestamp } => {
+                time_travel::flashback_table(state, ctx, table, timestamp)
+            }
+            SqlStatement::ProfileCpu { seconds, format } => {
+                admin::profile_cpu(state, *seconds, *format)
+            }
+            SqlStatement::CommitPrepared(gid) => finish_prepared_transaction(state, ctx, gid, true),
+            SqlStatement::RollbackPrepared(gid) => {
+                let _storage = state
+                    .storage_access
+                    .write()
+                    .map_err(|_| lock_poisoned_engine())?;
+                finish_prepared_transaction(state, ctx, gid, false)
+            }
             _ => Err(EngineError::new(
                 engine_error_code::UNSUPPORTED_SQL,
                 "this SQL statement type is not supported by the server engine yet",
@@ -618,13 +1176,61 @@ fn summarize_sql(sql: &str) -> String {
     format!("{}…", &s[..MAX])
 }
 
+impl SqlEngine {
+    /// Runs one statement of a session through `run`, with the role quota and tenant admission
+    /// around it and the workload capture, audit and statement statistics after it.
+    fn run_session_statement(
+        &self,
+        sql: &str,
+        ctx: &mut SessionContext,
+        run: impl FnOnce(&SqlEngineState, &mut SessionContext) -> Result<EngineOutput, EngineError>,
+    ) -> Result<EngineOutput, EngineError> {
+        let start = query_stats::StatementStart::now();
+        let started = Instant::now();
+        ctx.audited_read = None;
+        let quota = quotas::admit(&self.state.role_quotas, ctx)?;
+        let tenant = tenants::admit(self.state.as_ref(), ctx)?;
+        let out = run(self.state.as_ref(), ctx);
+        // The tenant's scope opened last and closes first.
+        let out = match tenant {
+            Some(scope) => tenants::finish(self.state.as_ref(), scope, out),
+            None => out,
+        };
+        let out = match quota {
+            Some(scope) => quotas::finish(&self.state.role_quotas, scope, out),
+            None => out,
+        };
+        workload_capture::record(
+            &self.state.workload_capture,
+            ctx,
+            sql,
+            started,
+            started.elapsed(),
+            &out,
+        );
+        audit::record(
+            &self.state.select_audit,
+            &self.state.data_dir,
+            ctx,
+            sql,
+            &out,
+        );
+        let out = out?;
+        self.state.statement_stats.record(sql, &start, &out);
+        Ok(out)
+    }
+}
+
 impl EngineHandle for SqlEngine {
     fn execute_sql(
         &self,
         sql: &str,
         ctx: &mut SessionContext,
     ) -> Result<EngineOutput, EngineError> {
-        Self::execute_sql_inner(self.state.as_ref(), sql, ctx)
+        self.run_session_statement(sql, ctx, |state, ctx| match ctx.trace_file.clone() {
+            Some(file) => session_trace::execute_traced(state, sql, ctx, &file),
+            None => Self::execute_sql_inner(state, sql, ctx),
+        })
     }
 
     fn execute_tpcc(
@@ -634,9 +1240,50 @@ impl EngineHandle for SqlEngine {
         global_txn_id: u64,
         ctx: &mut SessionContext,
     ) -> Result<EngineOutput, EngineError> {
+        schema_cache::load_all(self.state.as_ref())?;
         tpcc_native::execute_tpcc(self.state.as_ref(), kind, seed, global_txn_id, ctx)
     }
 
+    fn base_backup(&self) -> Result<crate::network::replication::BaseBackup, EngineError> {
+        base_backup::stage_base_backup(self.state.as_ref())
+    }
+
+    fn committed_changes(
+        &self,
+        after_lsn: u64,
+    ) -> Result<Vec<crate::network::framing::ReplicatedTransactionPayload>, EngineError> {
+        logical_decoding::committed_after(self.state.as_ref(), after_lsn)
+    }
+
+    fn replication_tracker(&self) -> Option<Arc<ReplicationTracker>> {
+        Some(self.state.replication.clone())
+    }
+
+    fn assign_role(&self, ctx: &mut SessionContext, user: &str) -> Result<(), EngineError> {
+        roles::assign_role(&self.state, ctx, user)
+    }
+
+    fn prepare(&self, sql: &str) -> Result<PreparedSql, EngineError> {
+        SqlEngine::prepare(self, sql)
+    }
+
+    fn execute_prepared(
+        &self,
+        prepared: &PreparedSql,
+        params: &[ColumnValue],
+        ctx: &mut SessionContext,
+    ) -> Result<EngineOutput, EngineError> {
+        SqlEngine::execute_prepared(self, prepared, params, ctx)
+    }
+
+    fn describe_prepared(
+        &self,
+        prepared: &PreparedSql,
+        ctx: &SessionContext,
+    ) -> Result<Option<Vec<ResultColumn>>, EngineError> {
+        SqlEngine::describe_prepared(self, prepared, ctx)
+    }
+
     fn supports_select_no_from_wire_cache(&self) -> bool {
         true
     }
@@ -647,7 +1294,12 @@ fn db_err_is_record_not_found(e: &DbError) -> bool {
 }
 
 fn map_db_err(e: DbError) -> EngineError {
-    EngineError::new(engine_error_code::INTERNAL, e.to_string())
+    match e {
+        DbError::ResourceLimit { message } => {
+            EngineError::new(engine_error_code::QUOTA_EXCEEDED, message)
+        }
+        e => EngineError::new(engine_error_code::INTERNAL, e.to_string()),
+    }
 }
 
 fn heap_delete_idempotent(pm: &mut PageManager, rid: RecordId) -> Result<bool, EngineError> {
@@ -673,7 +1325,7 @@ pub(crate) struct TpccDeferredInsertBreakdown {
     pub encode_us: u64,
     /// Page-manager lookup + `Mutex` wait + `PageManager::insert`.
     pub heap_us: u64,
-    /// Time blocked on `Arc<PageManagerMutex>::lock` for the table.
+    /// Time blocked on `Arc<PageManagerLock>::lock` for the table.
     pub pm_lock_wait_us: u64,
     /// Time in `PageManager::insert` while holding the table mutex.
     pub pm_insert_us: u64,
@@ -814,12 +1466,12 @@ fn insert_rows_tuple_tpcc_deferred_inner(
         return empty();
     }
 
-    record_touched_table(ctx, table);
+    record_touched_table(state, ctx, table);
 
     let t_encode = profile.then(Instant::now);
     let mut encoded: Vec<Vec<u8>> = Vec::with_capacity(tuples.len());
     for tuple in tuples {
-        encoded.push(tuple.to_bytes().map_err(map_db_err)?);
+        encoded.push(shredding::tuple_bytes(state, table, tuple)?);
     }
     let encode_us = t_encode.map(phase_elapsed_us).unwrap_or(0);
 
@@ -829,7 +1481,7 @@ fn insert_rows_tuple_tpcc_deferred_inner(
     let t_heap = profile.then(Instant::now);
     let t_lock = Instant::now();
     let (heap_rows, file_id, pm_lock_wait_us, pm_insert_us) = {
-        let mut pm = pm_for_table.lock();
+        let mut pm = pm_for_table.write();
         let pm_lock_wait_us = t_lock.elapsed().as_micros() as u64;
         let t_insert = Instant::now();
         let file_id = pm.file_id();
@@ -949,10 +1601,10 @@ fn insert_row_tuple_tpcc_immediate(
     table: &str,
     tuple: Tuple,
 ) -> Result<TpccInsertTimings, EngineError> {
-    record_touched_table(ctx, table);
-    let bytes = tuple.to_bytes().map_err(map_db_err)?;
+    record_touched_table(state, ctx, table);
+    let bytes = shredding::tuple_bytes(state, table, &tuple)?;
     let pm_for_table = table_page_manager(state, table)?;
-    let mut pm = pm_for_table.lock();
+    let mut pm = pm_for_table.write();
     let ins = pm.insert(&bytes).map_err(map_db_err)?;
     maybe_simulate_dml_crash("insert_row");
     let wal_clock = state.wal.is_some().then(Instant::now);
@@ -1057,14 +1709,35 @@ fn flush_heap_after_dml_success(
     if in_explicit_txn {
         return Ok(());
     }
-    let defer = state.wal.is_some()
-        && std::env::var_os("RUSTDB_DEFER_HEAP_FLUSH_AFTER_DML").is_some_and(|v| v != "0");
-    if !defer {
+    let defer =
+        state.wal.is_some() && crate::network::sql_engine_wal::heap_flush_after_dml_deferred();
+    if defer {
+        pm.queue_commit_group();
+    } else {
         pm.flush_dirty_pages().map_err(map_db_err)?;
     }
     Ok(())
 }
 
+/// Queues the heap pages a `COMMIT` that skipped its flush dirtied (in the tables it touched)
+/// for the background writer (see `sql_engine_wal::setup_background_writer`).
+fn queue_commit_group(
+    state: &SqlEngineState,
+    ctx: &SessionContext,
+    touched: &HashSet<String>,
+) -> Result<(), EngineError> {
+    let pms: Vec<Arc<PageManagerLock>> = if !ctx.txn_pm_cache.is_empty() {
+        ctx.txn_pm_cache.values().cloned().collect()
+    } else {
+        let map = state.table_page_managers.lock().map_err(|_| {
+            EngineError::new(engine_error_code::INTERNAL, "table pm map lock poisoned")
+        })?;
+        touched.iter().filter_map(|t| map.get(t).cloned()).collect()
+    };
+    crate::network::sql_engine_wal::queue_commit_group(&pms);
+    Ok(())
+}
+
 /// `expr` must use only shapes supported by [`match_where_tuple`] (for pushdown into [`PageManager::select`]).
 fn validate_dml_where_structure(expr: &Expression) -> Result<(), EngineError> {
     match expr {
@@ -1240,6 +1913,11 @@ fn with_dml_write_lock<T>(
                     return f();
                 }
                 log_dml_lock_path(table, "row", None, Some(rids.len()));
+                // Shared: only excludes storage-wide writers such as a base backup.
+                let _storage = state
+                    .storage_access
+                    .read()
+                    .map_err(|_| lock_poisoned_engine())?;
                 return state.row_locks.with_write_locks(table, rids, f);
             }
             log_dml_lock_path(
@@ -1267,7 +1945,7 @@ fn with_dml_write_lock<T>(
 /// Physical base table names from [`TableReference::Table`] only (not `QualifiedIdentifier`).
 fn collect_tables_expr(out: &mut HashSet<String>, expr: &Expression) {
     match expr {
-        Expression::Literal(_) | Expression::Identifier(_) => {}
+        Expression::Literal(_) | Expression::Identifier(_) | Expression::Parameter(_) => {}
         Expression::QualifiedIdentifier { .. } => {}
         Expression::BinaryOp { left, right, .. } => {
             collect_tables_expr(out, left);
@@ -1328,6 +2006,7 @@ fn collect_tables_table_reference(out: &mut HashSet<String>, tr: &TableReference
         TableReference::Subquery { query, .. } => {
             collect_tables_for_select(out, query);
         }
+        TableReference::Function { .. } => {}
     }
 }
 
@@ -1382,6 +2061,102 @@ fn collect_physical_tables_for_read_stmt(stmt: &SqlStatement) -> Vec<String> {
     names
 }
 
+/// Adds the tables `stmt` names to `out` (read, written, altered or referenced by a foreign key);
+/// returns the name of the table it creates, if any.
+fn collect_statement_tables<'a>(
+    out: &mut HashSet<String>,
+    stmt: &'a SqlStatement,
+) -> Option<&'a str> {
+    match stmt {
+        SqlStatement::Select(sel) => collect_tables_for_select(out, sel),
+        SqlStatement::SetOperation(op) => {
+            collect_tables_for_select(out, &op.left);
+            collect_tables_for_select(out, &op.right);
+        }
+        SqlStatement::Explain(explain) => return collect_statement_tables(out, &explain.statement),
+        SqlStatement::Insert(insert) => {
+            out.insert(insert.table.clone());
+            if let InsertValues::Select(sel) = &insert.values {
+                collect_tables_for_select(out, sel);
+            }
+        }
+        SqlStatement::Update(update) => {
+            out.insert(update.table.clone());
+            for assignment in &update.assignments {
+                collect_tables_expr(out, &assignment.value);
+            }
+            if let Some(w) = &update.where_clause {
+                collect_tables_expr(out, w);
+            }
+        }
+        SqlStatement::Delete(delete) => {
+            out.insert(delete.table.clone());
+            if let Some(w) = &delete.where_clause {
+                collect_tables_expr(out, w);
+            }
+        }
+        SqlStatement::CreateTable(ct) => {
+            out.insert(ct.table_name.clone());
+            for constraint in &ct.constraints {
+                if let TableConstraint::ForeignKey {
+                    referenced_table, ..
+                } = constraint
+                {
+                    out.insert(referenced_table.clone());
+                }
+            }
+            for column in &ct.columns {
+                for constraint in &column.constraints {
+                    if let ColumnConstraint::References { table, .. } = constraint {
+                        out.insert(table.clone());
+                    }
+                }
+            }
+            return Some(&ct.table_name);
+        }
+        SqlStatement::CreateForeignTable(cf) => {
+            out.insert(cf.table_name.clone());
+            return Some(&cf.table_name);
+        }
+        SqlStatement::CreateIndex(ci) => {
+            out.insert(ci.table_name.clone());
+        }
+        SqlStatement::AlterTable(at) => {
+            out.insert(at.table_name.clone());
+            match &at.operation {
+                AlterTableOperation::AddConstraint {
+                    definition:
+                        TableConstraint::ForeignKey {
+                            referenced_table, ..
+                        },
+                    ..
+                } => {
+                    out.insert(referenced_table.clone());
+                }
+                AlterTableOperation::RenameTable(new_name) => {
+                    out.insert(new_name.clone());
+                    return Some(new_name);
+                }
+                _ => {}
+            }
+        }
+        SqlStatement::DropTable(dt) => {
+            out.insert(dt.table_name.clone());
+        }
+        SqlStatement::DropIndex(di) => {
+            out.extend(di.table_name.clone());
+        }
+        SqlStatement::Vacuum(Some(table))
+        | SqlStatement::CheckTable(Some(table))
+        | SqlStatement::Analyze(Some(table))
+        | SqlStatement::FlashbackTable { table, .. } => {
+            out.insert(table.clone());
+        }
+        _ => {}
+    }
+    None
+}
+
 fn execute_dml_autocommit<T>(
     state: &SqlEngineState,
     ctx: &mut SessionContext,
@@ -1439,7 +2214,20 @@ fn begin_transaction(
         iso,
         SqlIsolationLevel::RepeatableRead | SqlIsolationLevel::Serializable
     ) {
-        Some(STRONG_ISO_LOCK.lock())
+        Some(
+            STRONG_ISO_LOCK
+                .try_lock_for(SERIALIZATION_LOCK_WAIT)
+                .ok_or_else(|| {
+                    EngineError::new(
+                        engine_error_code::SERIALIZATION_FAILURE,
+                        format!(
+                            "could not serialize access: a concurrent transaction held the \
+                             serialization lock for {}s",
+                            SERIALIZATION_LOCK_WAIT.as_secs()
+                        ),
+                    )
+                })?,
+        )
     } else {
         None
     };
@@ -1480,9 +2268,10 @@ fn commit_transaction(
     let _g = span.enter();
     let mut commit_wal_us = 0u64;
     let mut commit_log_commit_wait_us = 0u64;
+    let tracked = change_tracking::changed_keys(state, &tx.undo)?;
     if let Some(ref wal) = state.wal {
         let t0 = Instant::now();
-        wal.log_commit(&mut tx)?;
+        wal.log_commit(&mut tx, ctx.peer_commit_time)?;
         commit_log_commit_wait_us = t0.elapsed().as_micros() as u64;
         commit_wal_us = commit_log_commit_wait_us;
     }
@@ -1498,6 +2287,8 @@ fn commit_transaction(
     span.record("commit_index_batch_us", commit_index_batch_us);
 
     let touched: HashSet<String> = std::mem::take(&mut tx.touched_tables);
+    row_counts::commit(&state.row_counts, &touched, &tx.undo);
+    auto_analyze::record_commit(&state.modified_rows, &tx.undo);
     let flush_tables_count = touched.len();
     span.record("flush_tables_count", flush_tables_count);
     let skip_heap_flush =
@@ -1505,13 +2296,14 @@ fn commit_transaction(
     let commit_heap_flush_skipped = u8::from(skip_heap_flush);
     let flush_clock = Instant::now();
     let (_flushed_pages, flush_phases) = if skip_heap_flush {
+        queue_commit_group(state, ctx, &touched)?;
         (
             0,
             crate::network::sql_engine_wal::CommitFlushPhaseUs::default(),
         )
     } else if !ctx.txn_pm_cache.is_empty() {
-        let mut pms: Vec<Arc<PageManagerMutex>> = ctx.txn_pm_cache.values().cloned().collect();
-        pms.sort_by_key(|pm| pm.lock().file_id());
+        let mut pms: Vec<Arc<PageManagerLock>> = ctx.txn_pm_cache.values().cloned().collect();
+        pms.sort_by_key(|pm| pm.read().file_id());
         crate::network::sql_engine_wal::flush_page_managers_cached(&pms).map_err(map_db_err)?
     } else if touched.is_empty() {
         (
@@ -1557,7 +2349,13 @@ fn commit_transaction(
     }
     ctx.last_commit_flush_phases = Some(flush_phases);
     ctx.txn_pm_cache.clear();
+    let commit_lsn = tx.wal_last_lsn.filter(|_| state.wal.is_some());
     drop(tx);
+    if let Some(lsn) = commit_lsn {
+        ctx.last_commit_lsn = lsn;
+        change_tracking::record(state, tracked, lsn)?;
+        logical_decoding::replicate_commit(state, lsn)?;
+    }
     Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
 }
 
@@ -1584,15 +2382,19 @@ fn rollback_transaction(
         )
     })?;
     let _pending_index = std::mem::take(&mut tx.pending_index_inserts);
-    let mut flush_tables = std::mem::take(&mut tx.touched_tables);
+    let written = std::mem::take(&mut tx.touched_tables);
+    let mut flush_tables = written.clone();
     let undo = std::mem::take(&mut tx.undo);
     for op in undo.iter() {
         flush_tables.insert(undo_entry_table(op));
     }
     let had_undo = !undo.is_empty();
-    for op in undo.into_iter().rev() {
-        apply_undo(state, op)?;
-    }
+    let undone = undo
+        .into_iter()
+        .rev()
+        .try_for_each(|op| apply_undo(state, op));
+    row_counts::abort(&state.row_counts, &written);
+    undone?;
     // Rebuilding from heap is expensive and races with concurrent DML that already
     // updated the in-memory maps (e.g. another thread's committed INSERT). Only rescan
     // after we applied undo entries that may have left maps inconsistent with the heap.
@@ -1607,8 +2409,8 @@ fn rollback_transaction(
     let _ = if skip_heap_flush {
         Ok(0)
     } else if !ctx.txn_pm_cache.is_empty() {
-        let mut pms: Vec<Arc<PageManagerMutex>> = ctx.txn_pm_cache.values().cloned().collect();
-        pms.sort_by_key(|pm| pm.lock().file_id());
+        let mut pms: Vec<Arc<PageManagerLock>> = ctx.txn_pm_cache.values().cloned().collect();
+        pms.sort_by_key(|pm| pm.read().file_id());
         crate::network::sql_engine_wal::flush_page_managers_cached(&pms).map(|(n, _)| n)
     } else {
         crate::network::sql_engine_wal::flush_page_managers_for_tables(state, &flush_tables)
@@ -1626,6 +2428,80 @@ fn rollback_transaction(
     Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
 }
 
+/// `PREPARE TRANSACTION`: detaches the session transaction under `gid`. Its undo log, WAL
+/// transaction and isolation lock stay with it; prepared transactions are not persisted, so a
+/// restart rolls them back through WAL recovery.
+fn prepare_transaction(
+    state: &SqlEngineState,
+    ctx: &mut SessionContext,
+    gid: String,
+) -> Result<EngineOutput, EngineError> {
+    let tx = ctx.transaction.take().ok_or_else(|| {
+        EngineError::new(
+            engine_error_code::NO_ACTIVE_TRANSACTION,
+            "no active transaction",
+        )
+    })?;
+    let mut prepared = state
+        .prepared_transactions
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?;
+    if prepared.contains_key(&gid) {
+        ctx.transaction = Some(tx);
+        return Err(EngineError::new(
+            engine_error_code::PREPARED_TRANSACTION,
+            format!("transaction identifier '{}' is already in use", gid),
+        ));
+    }
+    prepared.insert(
+        gid,
+        PreparedSqlTransaction {
+            transaction: tx,
+            txn_pm_cache: std::mem::take(&mut ctx.txn_pm_cache),
+        },
+    );
+    Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+}
+
+/// `COMMIT PREPARED` / `ROLLBACK PREPARED`: finishes a prepared transaction as if its own
+/// session ran `COMMIT` / `ROLLBACK`.
+fn finish_prepared_transaction(
+    state: &SqlEngineState,
+    ctx: &mut SessionContext,
+    gid: &str,
+    commit: bool,
+) -> Result<EngineOutput, EngineError> {
+    if ctx.transaction.is_some() {
+        return Err(EngineError::new(
+            engine_error_code::ALREADY_IN_TRANSACTION,
+            "COMMIT PREPARED / ROLLBACK PREPARED cannot run inside a transaction",
+        ));
+    }
+    let prepared = state
+        .prepared_transactions
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?
+        .remove(gid)
+        .ok_or_else(|| {
+            EngineError::new(
+                engine_error_code::PREPARED_TRANSACTION,
+                format!("prepared transaction '{}' does not exist", gid),
+            )
+        })?;
+    let mut session = SessionContext {
+        transaction: Some(prepared.transaction),
+        txn_pm_cache: prepared.txn_pm_cache,
+        ..SessionContext::default()
+    };
+    if commit {
+        let out = commit_transaction(state, &mut session);
+        ctx.last_commit_lsn = ctx.last_commit_lsn.max(session.last_commit_lsn);
+        out
+    } else {
+        rollback_transaction(state, &mut session)
+    }
+}
+
 fn ensure_no_active_transaction(ctx: &SessionContext) -> Result<(), EngineError> {
     if ctx.transaction.is_some() {
         return Err(EngineError::new(
@@ -1650,9 +2526,12 @@ fn undo_entry_table(entry: &UndoEntry) -> String {
     }
 }
 
-fn record_touched_table(ctx: &mut SessionContext, table: &str) {
+fn record_touched_table(state: &SqlEngineState, ctx: &mut SessionContext, table: &str) {
     if let Some(tx) = ctx.transaction.as_mut() {
-        tx.touched_tables.insert(table.to_string());
+        if !tx.touched_tables.contains(table) {
+            tx.touched_tables.insert(table.to_string());
+            row_counts::begin_write(&state.row_counts, table);
+        }
     }
 }
 
@@ -1664,7 +2543,7 @@ fn apply_undo(state: &SqlEngineState, op: UndoEntry) -> Result<(), EngineError>
             payload,
         } => {
             let pm = table_page_manager(state, &table)?;
-            let tuple = Tuple::from_bytes(&payload).map_err(map_db_err)?;
+            let tuple = shredding::decode_tuple(state, &payload).map_err(map_db_err)?;
             let cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
             let sch = cat.schema(&table).cloned();
             let cat_clone = cat.clone();
@@ -1676,7 +2555,7 @@ fn apply_undo(state: &SqlEngineState, op: UndoEntry) -> Result<(), EngineError>
                     .map_err(|_| lock_poisoned_engine())?;
                 sql_constraints::unregister_row(&mut rt, &table, rid, &tuple, s, &cat_clone)?;
             }
-            let mut g = pm.lock();
+            let mut g = pm.write();
             match g.delete(rid) {
                 Ok(_) => {}
                 Err(e) => {
@@ -1707,7 +2586,7 @@ fn apply_undo(state: &SqlEngineState, op: UndoEntry) -> Result<(), EngineError>
             payload,
         } => {
             let pm = table_page_manager(state, &table)?;
-            let mut g = pm.lock();
+            let mut g = pm.write();
             let _ins = g.insert(&payload).map_err(map_db_err)?;
         }
         UndoEntry::Update {
@@ -1716,7 +2595,7 @@ fn apply_undo(state: &SqlEngineState, op: UndoEntry) -> Result<(), EngineError>
             old_payload,
         } => {
             let pm = table_page_manager(state, &table)?;
-            let mut g = pm.lock();
+            let mut g = pm.write();
             g.update(rid, &old_payload).map_err(map_db_err)?;
         }
     }
@@ -1727,14 +2606,14 @@ pub(crate) fn table_has_dirty_heap_pages(state: &SqlEngineState, table: &str) ->
     let Ok(pm) = table_page_manager(state, table) else {
         return false;
     };
-    let guard = pm.lock();
+    let guard = pm.read();
     guard.dirty_page_count() > 0
 }
 
 pub(crate) fn table_page_manager(
     state: &SqlEngineState,
     table: &str,
-) -> Result<Arc<PageManagerMutex>, EngineError> {
+) -> Result<Arc<PageManagerLock>, EngineError> {
     {
         let g = state
             .table_page_managers
@@ -1749,7 +2628,7 @@ pub(crate) fn table_page_manager(
         Err(_) => PageManager::new(state.data_dir.clone(), table, PageManagerConfig::default())
             .map_err(map_db_err)?,
     };
-    let pm = Arc::new(PageManagerMutex::new(pm));
+    let pm = Arc::new(PageManagerLock::new(pm));
     {
         let mut g = state
             .table_page_managers
@@ -1793,7 +2672,7 @@ pub(crate) fn table_page_manager_cached_storage(
     state: &SqlEngineState,
     ctx: &mut SessionContext,
     storage_key: &str,
-) -> Result<Arc<PageManagerMutex>, EngineError> {
+) -> Result<Arc<PageManagerLock>, EngineError> {
     if let Some(pm) = ctx.txn_pm_cache.get(storage_key) {
         return Ok(pm.clone());
     }
@@ -1807,7 +2686,7 @@ pub(crate) fn table_page_manager_cached(
     state: &SqlEngineState,
     ctx: &mut SessionContext,
     table: &str,
-) -> Result<Arc<PageManagerMutex>, EngineError> {
+) -> Result<Arc<PageManagerLock>, EngineError> {
     table_page_manager_cached_storage(state, ctx, table)
 }
 
@@ -1829,7 +2708,11 @@ fn index_columns_for_table(state: &SqlEngineState, table: &str) -> Arc<Vec<Strin
 fn refresh_index_columns_cache_for_table(state: &SqlEngineState, table: &str) {
     let mut cols: Vec<String> = Vec::new();
     if let Ok(ir) = state.index_registry.read() {
-        for (_idx_name, col_list) in ir.list_indexes_for_table(table) {
+        let indexes = ir.list_indexes_for_table(table);
+        for (_idx_name, col_list) in indexes
+            .into_iter()
+            .chain(ir.list_index_builds_for_table(table))
+        {
             for c in col_list {
                 if !cols.iter().any(|x| x == &c) {
                     cols.push(c.clone());
@@ -1867,6 +2750,11 @@ fn tpcc_pending_index_column_map(
             column_map.insert(c.clone(), column_value_to_index_string(cv));
         }
     }
+    let ir = state
+        .index_registry
+        .read()
+        .map_err(|_| lock_poisoned_engine())?;
+    add_computed_index_values(&ir, table, tuple, &mut column_map);
     Ok(column_map)
 }
 
@@ -1876,8 +2764,20 @@ fn execute_create_table(
     ct: &CreateTableStatement,
 ) -> Result<EngineOutput, EngineError> {
     ensure_no_active_transaction(ctx)?;
-    let _ = table_page_manager(state, &ct.table_name)?;
-    let schema = table_schema_from_create_table(ct)?;
+    ensure_not_foreign_table(state, &ct.table_name)?;
+    let engine = match ct.engine.as_deref() {
+        Some(name) => StorageEngineKind::from_name(name)
+            .map_err(|e| EngineError::new(engine_error_code::UNSUPPORTED_SQL, e.to_string()))?,
+        None => StorageEngineKind::Heap,
+    };
+    match engine {
+        StorageEngineKind::Heap => {
+            let _ = table_page_manager(state, &ct.table_name)?;
+        }
+        StorageEngineKind::Lsm => create_lsm_table_storage(state, &ct.table_name)?,
+    }
+    let mut schema = table_schema_from_create_table(ct)?;
+    schema.tenant = ctx.tenant.clone();
     {
         let cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
         validate_new_table_fks(&cat, &schema)?;
@@ -1891,6 +2791,115 @@ fn execute_create_table(
     Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
 }
 
+/// `CREATE FOREIGN TABLE`: catalog entry only; the adapter reads the external data at scan time.
+fn execute_create_foreign_table(
+    state: &SqlEngineState,
+    ctx: &SessionContext,
+    cf: &CreateForeignTableStatement,
+) -> Result<EngineOutput, EngineError> {
+    use crate::common::types::Column;
+    ensure_no_active_transaction(ctx)?;
+    let foreign = ForeignTableDef {
+        adapter: cf.adapter.clone(),
+        options: cf.options.clone(),
+    };
+    validate_foreign_table(&foreign, Some(&state.data_dir))
+        .map_err(|e| EngineError::new(engine_error_code::UNSUPPORTED_SQL, e.to_string()))?;
+    let schema = TableSchema {
+        table_name: cf.table_name.clone(),
+        columns: cf
+            .columns
+            .iter()
+            .map(|c| Column::new(c.name.clone(), sql_type_to_column_datatype(&c.data_type)))
+            .collect(),
+        primary_key: None,
+        unique_constraints: Vec::new(),
+        foreign_keys: Vec::new(),
+        check_constraints: Vec::new(),
+        secondary_indexes: Vec::new(),
+        foreign: Some(foreign),
+        encrypt_by: None,
+        tenant: ctx.tenant.clone(),
+    };
+    {
+        let mut cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
+        if cat.schema(&cf.table_name).is_some() {
+            return Err(EngineError::new(
+                engine_error_code::CONSTRAINT_VIOLATION,
+                format!("table {} already exists", cf.table_name),
+            ));
+        }
+        cat.register_schema(schema.clone());
+    }
+    state
+        .planner
+        .register_foreign_table(&schema)
+        .map_err(map_db_err)?;
+    persist_catalog(state)?;
+    Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+}
+
+/// Foreign tables are read-only: DML, DDL on them and `CREATE TABLE` over their name fail.
+fn ensure_not_foreign_table(state: &SqlEngineState, table: &str) -> Result<(), EngineError> {
+    let cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
+    match cat.schema(table).and_then(|s| s.foreign.as_ref()) {
+        Some(foreign) => Err(EngineError::new(
+            engine_error_code::UNSUPPORTED_SQL,
+            format!(
+                "{table} is a foreign table ({} adapter) and is read-only",
+                foreign.adapter
+            ),
+        )),
+        None => Ok(()),
+    }
+}
+
+fn register_foreign_tables_from_catalog(state: &SqlEngineState) -> Result<(), EngineError> {
+    let cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
+    for table in cat.table_names() {
+        if let Some(schema) = cat.schema(&table) {
+            state
+                .planner
+                .register_foreign_table(schema)
+                .map_err(map_db_err)?;
+        }
+    }
+    Ok(())
+}
+
+/// Creates `<table>.lsm/` and registers its page manager; later opens find the directory
+/// and pick the LSM backend on their own (see [`PageManager::open`]).
+fn create_lsm_table_storage(state: &SqlEngineState, table: &str) -> Result<(), EngineError> {
+    let mut g = state
+        .table_page_managers
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?;
+    if let Some(pm) = g.get(table) {
+        if pm.read().storage_engine() == StorageEngineKind::Lsm {
+            return Ok(());
+        }
+        return Err(EngineError::new(
+            engine_error_code::CONSTRAINT_VIOLATION,
+            format!("table {table} already has heap storage"),
+        ));
+    }
+    if state.data_dir.join(format!("{table}.tbl")).exists() {
+        return Err(EngineError::new(
+            engine_error_code::CONSTRAINT_VIOLATION,
+            format!("table {table} already has heap storage"),
+        ));
+    }
+    let pm = PageManager::open_lsm(
+        state.data_dir.clone(),
+        table,
+        PageManagerConfig::default(),
+        LsmConfig::default(),
+    )
+    .map_err(map_db_err)?;
+    g.insert(table.to_string(), Arc::new(PageManagerLock::new(pm)));
+    Ok(())
+}
+
 fn execute_drop_table(
     state: &SqlEngineState,
     ctx: &SessionContext,
@@ -1965,21 +2974,29 @@ fn physical_drop_table(state: &SqlEngineState, table: &str) -> Result<(), Engine
         let cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
         cat.clone()
     };
-    if let Some(ref sch) = schema {
+    let loaded = !schema_cache::is_cold(&state.schema_cache, table);
+    if let Some(sch) = schema.as_ref().filter(|s| s.foreign.is_none() && loaded) {
         let pm = table_page_manager(state, table)?;
-        let snapshot = pm.lock().select(None).map_err(map_db_err)?;
+        let snapshot = pm.write().select(None).map_err(map_db_err)?;
         let mut rt = state
             .constraint_runtime
             .lock()
             .map_err(|_| lock_poisoned_engine())?;
         for (rid, data) in snapshot {
-            let tuple = Tuple::from_bytes(&data).map_err(map_db_err)?;
+            let tuple = shredding::decode_tuple(state, &data).map_err(map_db_err)?;
             sql_constraints::unregister_row(&mut rt, table, rid, &tuple, sch, &cat_snapshot)?;
         }
         rt.clear_table_maps(table);
         rt.clear_fk_refs_to_parent(table);
     }
+    drop_table_storage(state, table)
+}
 
+/// Removes `table` from the catalog, indexes and planner, and deletes its files; the constraint
+/// runtime is left to the caller.
+fn drop_table_storage(state: &SqlEngineState, table: &str) -> Result<(), EngineError> {
+    schema_cache::forget(&state.schema_cache, table);
+    row_counts::invalidate(&state.row_counts, table);
     {
         let mut ir = state
             .index_registry
@@ -1996,7 +3013,13 @@ fn physical_drop_table(state: &SqlEngineState, table: &str) -> Result<(), Engine
         g.remove(table);
     }
     let path = state.data_dir.join(format!("{table}.tbl"));
+    let _ = crate::storage::tiering::ColdTier::remove_files(&path);
     let _ = std::fs::remove_file(path);
+    let _ = std::fs::remove_dir_all(lsm_table_dir(&state.data_dir, table));
+    state
+        .planner
+        .unregister_foreign_table(table)
+        .map_err(map_db_err)?;
     let mut cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
     cat.drop_table(table);
     rebuild_optimizer_with_indexes(state)?;
@@ -2097,6 +3120,31 @@ fn tuple_to_index_column_map(tuple: &Tuple) -> HashMap<String, String> {
     m
 }
 
+/// Adds to the index column map `m` what the indexes on `table` compute from `tuple`: the
+/// values of their key expressions, and marks for the partial indexes whose predicate it
+/// matches, so index maintenance gives the row entries in them.
+fn add_computed_index_values(
+    ir: &IndexRegistry,
+    table: &str,
+    tuple: &Tuple,
+    m: &mut HashMap<String, String>,
+) {
+    let mut row = None;
+    for (key, expr) in ir.index_expressions(table) {
+        if !m.contains_key(key) {
+            let row = row.get_or_insert_with(|| tuple_as_eval_row(tuple));
+            let value = column_value_to_index_string(&eval_scalar_expression(row, expr));
+            m.insert(key.to_string(), value);
+        }
+    }
+    for (name, predicate) in ir.partial_index_predicates(table) {
+        let row = row.get_or_insert_with(|| tuple_as_eval_row(tuple));
+        if eval_predicate_expression(row, predicate) {
+            m.insert(IndexRegistry::partial_index_key(name), String::new());
+        }
+    }
+}
+
 /// Index column map for deferred TPC-C inserts (only columns referenced by secondary indexes).
 fn tuple_to_index_column_map_for_table(
     state: &SqlEngineState,
@@ -2222,11 +3270,12 @@ fn sync_index_after_insert(
     rid: RecordId,
     tuple: &Tuple,
 ) -> Result<(), EngineError> {
-    let m = tuple_to_index_column_map(tuple);
-    let mut ir = state
+    let mut m = tuple_to_index_column_map(tuple);
+    let ir = state
         .index_registry
         .write()
         .map_err(|_| lock_poisoned_engine())?;
+    add_computed_index_values(&ir, table, tuple, &mut m);
     ir.insert_into_indexes(table, rid, &m)
         .map_err(|e| EngineError::new(engine_error_code::INTERNAL, format!("index insert: {e}")))?;
     Ok(())
@@ -2239,12 +3288,14 @@ fn sync_index_after_update(
     old_tuple: &Tuple,
     new_tuple: &Tuple,
 ) -> Result<(), EngineError> {
-    let old_m = tuple_to_index_column_map(old_tuple);
-    let new_m = tuple_to_index_column_map(new_tuple);
-    let mut ir = state
+    let mut old_m = tuple_to_index_column_map(old_tuple);
+    let mut new_m = tuple_to_index_column_map(new_tuple);
+    let ir = state
         .index_registry
         .write()
         .map_err(|_| lock_poisoned_engine())?;
+    add_computed_index_values(&ir, table, old_tuple, &mut old_m);
+    add_computed_index_values(&ir, table, new_tuple, &mut new_m);
     ir.update_indexes(table, rid, &old_m, &new_m)
         .map_err(|e| EngineError::new(engine_error_code::INTERNAL, format!("index update: {e}")))?;
     Ok(())
@@ -2256,11 +3307,12 @@ fn sync_index_after_delete(
     rid: RecordId,
     tuple: &Tuple,
 ) -> Result<(), EngineError> {
-    let m = tuple_to_index_column_map(tuple);
-    let mut ir = state
+    let mut m = tuple_to_index_column_map(tuple);
+    let ir = state
         .index_registry
         .write()
         .map_err(|_| lock_poisoned_engine())?;
+    add_computed_index_values(&ir, table, tuple, &mut m);
     ir.delete_from_indexes(table, rid, &m)
         .map_err(|e| EngineError::new(engine_error_code::INTERNAL, format!("index delete: {e}")))?;
     Ok(())
@@ -2272,13 +3324,14 @@ fn rebuild_optimizer_with_indexes(state: &SqlEngineState) -> Result<(), EngineEr
         .read()
         .map_err(|_| lock_poisoned_engine())?
         .clone();
+    let optimizer = QueryOptimizer::new()
+        .map_err(map_db_err)?
+        .with_cardinality_feedback(state.cardinality_feedback.clone());
     let mut opt = state.optimizer.lock().map_err(|_| lock_poisoned_engine())?;
     *opt = if snapshot.is_empty() {
-        QueryOptimizer::new().map_err(map_db_err)?
+        optimizer
     } else {
-        QueryOptimizer::new()
-            .map_err(map_db_err)?
-            .with_index_registry(Arc::new(snapshot))
+        optimizer.with_index_registry(Arc::new(snapshot))
     };
     invalidate_dml_plan_validation_cache(state);
     Ok(())
@@ -2354,6 +3407,7 @@ fn plan_and_optimize(
             "statement type cannot be planned",
         ));
     }
+    let _memory = memory_scope(MemoryTag::PlanCache);
     let cache_key = normalize_sql_for_plan_cache(sql);
     let epoch = if use_read_cache {
         let cache = state
@@ -2410,6 +3464,10 @@ fn strip_explain_prefix(sql: &str) -> String {
         let head = &rest.as_bytes()[..7];
         if head.eq_ignore_ascii_case(b"ANALYZE") || head.eq_ignore_ascii_case(b"VERBOSE") {
             rest = rest[7..].trim_start();
+        } else if head[..6].eq_ignore_ascii_case(b"FORMAT") && head[6].is_ascii_whitespace() {
+            // `FORMAT <name>`
+            let after = rest[7..].trim_start();
+            rest = after[after.find(char::is_whitespace).unwrap_or(after.len())..].trim_start();
         } else {
             break;
         }
@@ -2418,10 +3476,12 @@ fn strip_explain_prefix(sql: &str) -> String {
 }
 
 fn explain_lines_to_output(lines: Vec<String>) -> EngineOutput {
-    EngineOutput::ResultSet {
+    let out = EngineOutput::ResultSet {
         columns: vec!["QUERY PLAN".to_string()],
         rows: lines.into_iter().map(|l| vec![l]).collect(),
-    }
+    };
+    typed_results::record_text(&out);
+    out
 }
 
 fn execute_explain(
@@ -2438,9 +3498,12 @@ fn execute_explain(
         analyze: ex.analyze,
         ..Default::default()
     };
+    let mut actuals = HashMap::new();
     if ex.analyze {
         let t0 = Instant::now();
+        let scope = analyze_scope();
         let out = SqlEngine::execute_sql_inner(state, &inner_sql, ctx)?;
+        actuals = scope.finish();
         format_opts.execution_time_ms = Some(t0.elapsed().as_millis() as u64);
         match &out {
             EngineOutput::ResultSet { rows, .. } => {
@@ -2451,7 +3514,19 @@ fn execute_explain(
             }
         }
     }
-    let lines = format_explain_output(&plan, &opt_result, format_opts);
+    let graph = |mut plan: ExecutionPlan| {
+        plan.metadata.actual_rows = format_opts
+            .rows_returned
+            .or(format_opts.rows_affected.map(|n| n as usize));
+        plan
+    };
+    let lines = match ex.format {
+        ExplainFormat::Text => {
+            format_explain_analyze_output(&plan, &opt_result, format_opts, &actuals)
+        }
+        ExplainFormat::Json => vec![graph(plan).to_json()],
+        ExplainFormat::Dot => vec![graph(plan).to_dot()],
+    };
     Ok(explain_lines_to_output(lines))
 }
 
@@ -2522,6 +3597,57 @@ fn execute_create_index(
     ci: &CreateIndexStatement,
 ) -> Result<EngineOutput, EngineError> {
     ensure_no_active_transaction(ctx)?;
+    validate_create_index(state, ci)?;
+    let table = ci.table_name.as_str();
+    {
+        let mut reg = state
+            .index_registry
+            .write()
+            .map_err(|_| lock_poisoned_engine())?;
+        reg.create_index_with_options(table, &ci.index_name, ci.columns.clone(), index_options(ci))
+            .map_err(|e| {
+                EngineError::new(engine_error_code::CONSTRAINT_VIOLATION, e.to_string())
+            })?;
+    }
+    index_build::build_index_from_heap(state, table, &ci.index_name)?;
+    record_created_index(state, ci)?;
+    Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+}
+
+/// `CREATE INDEX CONCURRENTLY`: the build runs without storage locks (see
+/// `index_build::build_index_concurrently`); only the catalog update at the end is exclusive.
+fn execute_create_index_concurrently(
+    state: &SqlEngineState,
+    ctx: &SessionContext,
+    ci: &CreateIndexStatement,
+) -> Result<EngineOutput, EngineError> {
+    ensure_no_active_transaction(ctx)?;
+    {
+        let _storage = state
+            .storage_access
+            .read()
+            .map_err(|_| lock_poisoned_engine())?;
+        validate_create_index(state, ci)?;
+    }
+    index_build::build_index_concurrently(
+        state,
+        &ci.table_name,
+        &ci.index_name,
+        &ci.columns,
+        index_options(ci),
+    )?;
+    let _storage = state
+        .storage_access
+        .write()
+        .map_err(|_| lock_poisoned_engine())?;
+    record_created_index(state, ci)?;
+    Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+}
+
+fn validate_create_index(
+    state: &SqlEngineState,
+    ci: &CreateIndexStatement,
+) -> Result<(), EngineError> {
     let table = ci.table_name.as_str();
     let cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
     let Some(schema) = cat.schema(table) else {
@@ -2530,28 +3656,39 @@ fn execute_create_index(
             format!("table {table} does not exist"),
         ));
     };
-    for col in &ci.columns {
-        if !schema.columns.iter().any(|c| c.name == *col) {
+    for col in ci.key_table_columns() {
+        if !schema.columns.iter().any(|c| c.name == col) {
             return Err(EngineError::new(
                 engine_error_code::CONSTRAINT_VIOLATION,
                 format!("unknown column {table}.{col}"),
             ));
         }
     }
-    drop(cat);
-    {
-        let mut reg = state
-            .index_registry
-            .write()
-            .map_err(|_| lock_poisoned_engine())?;
-        reg.create_index(table, &ci.index_name, ci.columns.clone())
-            .map_err(|e| {
-                EngineError::new(engine_error_code::CONSTRAINT_VIOLATION, e.to_string())
-            })?;
+    if ci.concurrently && ci.method == IndexMethod::Hash {
+        return Err(EngineError::new(
+            engine_error_code::UNSUPPORTED_SQL,
+            "hash indexes cannot be built concurrently",
+        ));
     }
-    backfill_index_from_heap(state, table, &ci.index_name)?;
+    Ok(())
+}
+
+/// Access method, predicate and key expressions of the index `ci` creates.
+fn index_options(ci: &CreateIndexStatement) -> IndexOptions {
+    IndexOptions {
+        method: ci.method,
+        predicate: ci.where_clause.clone(),
+        expressions: ci.expressions.clone(),
+    }
+}
+
+/// Persists a built index in the catalog and makes it visible to the optimizer.
+fn record_created_index(
+    state: &SqlEngineState,
+    ci: &CreateIndexStatement,
+) -> Result<(), EngineError> {
+    let table = ci.table_name.as_str();
     {
-        use crate::catalog::schema::SecondaryIndexDef;
         let mut cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
         let Some(sch) = cat.schema_mut(table) else {
             return Err(EngineError::new(
@@ -2566,27 +3703,123 @@ fn execute_create_index(
         {
             sch.secondary_indexes.push(SecondaryIndexDef {
                 name: ci.index_name.clone(),
+                method: ci.method,
                 columns: ci.columns.clone(),
+                predicate: ci.where_clause.clone(),
+                expressions: ci.expressions.clone(),
             });
         }
     }
     persist_catalog(state)?;
     rebuild_optimizer_with_indexes(state)?;
     refresh_index_columns_cache_for_table(state, table);
+    Ok(())
+}
+
+/// Table of the index `di` drops: the named table when it has the index, else the only table
+/// with an index of that name. `Ok(None)` when there is none; `Err` when several tables have one.
+fn drop_index_table(
+    cat: &SchemaManager,
+    di: &DropIndexStatement,
+) -> Result<Option<String>, String> {
+    let mut tables = cat.tables_with_index(&di.index_name);
+    if let Some(table) = &di.table_name {
+        tables.retain(|t| t == table);
+    }
+    match tables.len() {
+        0 | 1 => Ok(tables.pop()),
+        _ => Err(format!(
+            "index {} exists on several tables ({}); name one with ON <table>",
+            di.index_name,
+            tables.join(", ")
+        )),
+    }
+}
+
+/// `DROP INDEX`: removes the index from the catalog, the registry and the planner, and deletes
+/// its files.
+fn execute_drop_index(
+    state: &SqlEngineState,
+    ctx: &SessionContext,
+    di: &DropIndexStatement,
+) -> Result<EngineOutput, EngineError> {
+    ensure_no_active_transaction(ctx)?;
+    let table = {
+        let cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
+        drop_index_table(&cat, di)
+            .map_err(|e| EngineError::new(engine_error_code::CONSTRAINT_VIOLATION, e))?
+    };
+    let Some(table) = table else {
+        if di.if_exists {
+            return Ok(EngineOutput::ExecutionOk { rows_affected: 0 });
+        }
+        let on = di
+            .table_name
+            .as_ref()
+            .map(|t| format!(" on {t}"))
+            .unwrap_or_default();
+        return Err(EngineError::new(
+            engine_error_code::CONSTRAINT_VIOLATION,
+            format!("index {}{on} does not exist", di.index_name),
+        ));
+    };
+    ensure_no_index_build(state, &table)?;
+    state
+        .catalog
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?
+        .drop_secondary_index(&table, &di.index_name);
+    persist_catalog(state)?;
+    {
+        let mut reg = state
+            .index_registry
+            .write()
+            .map_err(|_| lock_poisoned_engine())?;
+        // A cold table has not registered its indexes yet; only the catalog knows them.
+        if reg.get_index_entry(&table, &di.index_name).is_some() {
+            reg.drop_index(&table, &di.index_name).map_err(map_db_err)?;
+        }
+    }
+    rebuild_optimizer_with_indexes(state)?;
+    refresh_index_columns_cache_for_table(state, &table);
     Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
 }
 
+/// Rejects DDL on `table` while `CREATE INDEX CONCURRENTLY` builds an index on it.
+fn ensure_no_index_build(state: &SqlEngineState, table: &str) -> Result<(), EngineError> {
+    let builds = state
+        .index_registry
+        .read()
+        .map_err(|_| lock_poisoned_engine())?
+        .list_index_builds_for_table(table);
+    match builds.first() {
+        Some((index, _)) => Err(EngineError::new(
+            engine_error_code::CONSTRAINT_VIOLATION,
+            format!("index {index} is being built on table {table}"),
+        )),
+        None => Ok(()),
+    }
+}
+
 /// Rebuilds in-memory secondary indexes from catalog metadata after reopen (CI seed path).
-fn rebuild_secondary_indexes_from_catalog(state: &SqlEngineState) -> Result<(), EngineError> {
-    let index_defs: Vec<(String, String, Vec<String>)> = {
+/// Rebuilds the secondary indexes of the catalog's tables from their rows, or of `only` those.
+fn rebuild_secondary_indexes_from_catalog(
+    state: &SqlEngineState,
+    only: Option<&HashSet<String>>,
+) -> Result<(), EngineError> {
+    let index_defs: Vec<(String, SecondaryIndexDef)> = {
         let cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
         let mut out = Vec::new();
         for table in cat.table_names() {
-            let Some(sch) = cat.schema(&table) else {
+            let wanted = match only {
+                Some(tables) => tables.contains(&table),
+                None => !schema_cache::is_cold(&state.schema_cache, &table),
+            };
+            let Some(sch) = cat.schema(&table).filter(|_| wanted) else {
                 continue;
             };
             for idx in &sch.secondary_indexes {
-                out.push((table.clone(), idx.name.clone(), idx.columns.clone()));
+                out.push((table.clone(), idx.clone()));
             }
         }
         out
@@ -2599,52 +3832,57 @@ fn rebuild_secondary_indexes_from_catalog(state: &SqlEngineState) -> Result<(),
             .index_registry
             .write()
             .map_err(|_| lock_poisoned_engine())?;
-        for (table, index_name, columns) in &index_defs {
-            if reg.get_index_entry(table, index_name).is_some() {
+        for (table, idx) in &index_defs {
+            if reg.get_index_entry(table, &idx.name).is_some() {
                 continue;
             }
-            reg.create_index(table, index_name, columns.clone())
+            let options = IndexOptions {
+                method: idx.method,
+                predicate: idx.predicate.clone(),
+                expressions: idx.expressions.clone(),
+            };
+            // Stored indexes reopen their files; the loop below brings them up to date.
+            reg.open_index_with_options(table, &idx.name, idx.columns.clone(), options)
                 .map_err(|e| {
                     EngineError::new(engine_error_code::INTERNAL, format!("index rebuild: {e}"))
                 })?;
         }
     }
-    for (table, index_name, _) in index_defs {
-        backfill_index_from_heap(state, &table, &index_name)?;
+    let mut wal_changes = None;
+    for (table, idx) in index_defs {
+        if persistent_indexes::restore(state, &table, &idx.name, &mut wal_changes)? {
+            continue;
+        }
+        index_build::build_index_from_heap(state, &table, &idx.name)?;
     }
     rebuild_optimizer_with_indexes(state)?;
     Ok(())
 }
 
-fn backfill_index_from_heap(
-    state: &SqlEngineState,
-    table: &str,
-    index_name: &str,
-) -> Result<(), EngineError> {
-    let pm = table_page_manager(state, table)?;
-    let snapshot = pm.lock().select(None).map_err(map_db_err)?;
-    let mut ir = state
-        .index_registry
-        .write()
-        .map_err(|_| lock_poisoned_engine())?;
-    for (rid, data) in snapshot {
-        let tuple = Tuple::from_bytes(&data).map_err(map_db_err)?;
-        let m = tuple_to_index_column_map(&tuple);
-        ir.insert_into_named_index(table, index_name, rid, &m)
-            .map_err(|e| {
-                EngineError::new(engine_error_code::INTERNAL, format!("index backfill: {e}"))
-            })?;
-    }
-    Ok(())
+/// Plan of a statement bound from a prepared one (see [`prepared`])
+type PreparedPlan<'a> = &'a dyn Fn(&SqlEngineState) -> Result<ExecutionPlan, EngineError>;
+
+/// Schema epoch plans are made in: DDL and index changes advance it, so a plan made in an older
+/// one is stale.
+fn plan_epoch(state: &SqlEngineState) -> Result<u64, EngineError> {
+    Ok(state
+        .dml_plan_validation_cache
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?
+        .schema_epoch)
 }
 
+/// Checks that `stmt` can be planned; a prepared statement's plan already is.
 fn validate_plan(
     state: &SqlEngineState,
     sql: &str,
     stmt: &SqlStatement,
+    plan: Option<PreparedPlan<'_>>,
 ) -> Result<(), EngineError> {
-    let _ = plan_and_optimize(state, sql, stmt, false)?;
-    Ok(())
+    match plan {
+        Some(plan) => plan(state).map(drop),
+        None => plan_and_optimize(state, sql, stmt, false).map(drop),
+    }
 }
 
 fn execute_insert(
@@ -2653,9 +3891,10 @@ fn execute_insert(
     sql: &str,
     stmt: &SqlStatement,
     insert: &InsertStatement,
+    plan: Option<PreparedPlan<'_>>,
 ) -> Result<EngineOutput, EngineError> {
-    validate_plan(state, sql, stmt)?;
-    record_touched_table(ctx, &insert.table);
+    validate_plan(state, sql, stmt, plan)?;
+    record_touched_table(state, ctx, &insert.table);
     match &insert.values {
         InsertValues::Select(sel) => {
             // Plan/execute the SELECT subquery and insert its resulting rows.
@@ -2670,16 +3909,21 @@ fn execute_insert(
                 .map_err(|_| lock_poisoned_engine())?
                 .optimize(plan)
                 .map_err(map_db_err)?;
-            let rows = state
-                .executor
-                .execute(&optimized.optimized_plan)
-                .map_err(map_db_err)?;
+            let mut rows = cardinality_feedback::execute(state, &optimized.optimized_plan)?;
+            if let Some(masks) = masking::session_masks(&state.column_masking, ctx) {
+                masking::mask_rows(&masks, &select_stmt, &mut rows)?;
+            }
 
             let pm_for_table = table_page_manager(state, &insert.table)?;
             let mut rows_affected = 0u64;
             for r in rows {
-                let tuple =
-                    build_insert_tuple_from_row(state, &insert.table, insert.columns.as_ref(), &r)?;
+                let tuple = build_insert_tuple_from_row(
+                    state,
+                    ctx,
+                    &insert.table,
+                    insert.columns.as_ref(),
+                    &r,
+                )?;
                 rows_affected += insert_heap_row_in_execute_insert(
                     state,
                     ctx,
@@ -2689,7 +3933,7 @@ fn execute_insert(
                 )?;
             }
             {
-                let mut pm = pm_for_table.lock();
+                let mut pm = pm_for_table.write();
                 flush_heap_after_dml_success(state, ctx, &mut pm)?;
             }
             Ok(EngineOutput::ExecutionOk { rows_affected })
@@ -2698,7 +3942,8 @@ fn execute_insert(
             let mut rows_affected = 0u64;
             let pm_for_table = table_page_manager(state, &insert.table)?;
             for row in rows {
-                let tuple = build_insert_tuple(state, &insert.table, insert.columns.as_ref(), row)?;
+                let tuple =
+                    build_insert_tuple(state, ctx, &insert.table, insert.columns.as_ref(), row)?;
                 rows_affected += insert_heap_row_in_execute_insert(
                     state,
                     ctx,
@@ -2708,7 +3953,7 @@ fn execute_insert(
                 )?;
             }
             {
-                let mut pm = pm_for_table.lock();
+                let mut pm = pm_for_table.write();
                 flush_heap_after_dml_success(state, ctx, &mut pm)?;
             }
             Ok(EngineOutput::ExecutionOk { rows_affected })
@@ -2718,11 +3963,12 @@ fn execute_insert(
 
 fn build_insert_tuple(
     state: &SqlEngineState,
+    ctx: &mut SessionContext,
     table: &str,
     columns: Option<&Vec<String>>,
     row: &[Expression],
 ) -> Result<Tuple, EngineError> {
-    let id = state.next_tuple_id.fetch_add(1, Ordering::Relaxed);
+    let id = sequences::next_tuple_id(state, ctx)?;
     let mut tuple = Tuple::new(id);
     let col_names: Vec<String> = match columns {
         Some(cols) => {
@@ -2746,23 +3992,24 @@ fn build_insert_tuple(
 
 fn build_insert_tuple_from_row(
     state: &SqlEngineState,
+    ctx: &mut SessionContext,
     table: &str,
     columns: Option<&Vec<String>>,
     row: &Row,
 ) -> Result<Tuple, EngineError> {
-    let id = state.next_tuple_id.fetch_add(1, Ordering::Relaxed);
+    let id = sequences::next_tuple_id(state, ctx)?;
     let mut tuple = Tuple::new(id);
 
     let col_names: Vec<String> = match columns {
         Some(cols) => cols.clone(),
         None => {
-            let mut keys: Vec<String> = row.values.keys().cloned().collect();
+            let mut keys: Vec<String> = row.column_names().map(str::to_string).collect();
             keys.sort();
             keys
         }
     };
     for name in col_names {
-        let Some(cv) = row.values.get(&name) else {
+        let Some(cv) = row.get_value(&name) else {
             continue;
         };
         tuple.set_value(&name, cv.clone());
@@ -2785,11 +4032,12 @@ fn execute_update(
     sql: &str,
     stmt: &SqlStatement,
     update: &UpdateStatement,
+    plan: Option<PreparedPlan<'_>>,
 ) -> Result<EngineOutput, EngineError> {
-    validate_plan(state, sql, stmt)?;
-    record_touched_table(ctx, &update.table);
+    validate_plan(state, sql, stmt, plan)?;
+    record_touched_table(state, ctx, &update.table);
     let pm_for_table = table_page_manager(state, &update.table)?;
-    let mut pm = pm_for_table.lock();
+    let mut pm = pm_for_table.write();
     let scan_clock = sql_phase_log_enabled().then(Instant::now);
     let (snapshot, where_pre_filtered) = match &update.where_clause {
         None => (pm.select(None).map_err(map_db_err)?, false),
@@ -2801,8 +4049,9 @@ fn execute_update(
                 (rows, skip_where)
             } else {
                 let expr = expr.clone();
+                let keyring = state.crypto_shredding.keyring().clone();
                 let pred = Box::new(move |data: &[u8]| {
-                    let tuple = Tuple::from_bytes(data).expect("heap tuple must deserialize");
+                    let tuple = keyring.decode(data).expect("heap tuple must deserialize");
                     match_where_tuple(&expr, &tuple).expect("WHERE validated for heap predicate")
                 });
                 (pm.select(Some(pred)).map_err(map_db_err)?, true)
@@ -2814,7 +4063,7 @@ fn execute_update(
     let row_clock = sql_phase_log_enabled().then(Instant::now);
     let mut rows_affected = 0u64;
     for (rid, data) in snapshot {
-        let mut tuple = Tuple::from_bytes(&data).map_err(map_db_err)?;
+        let mut tuple = shredding::decode_tuple(state, &data).map_err(map_db_err)?;
         let keep = if where_pre_filtered {
             true
         } else {
@@ -2837,6 +4086,7 @@ fn execute_update(
                 .constraint_runtime
                 .lock()
                 .map_err(|_| lock_poisoned_engine())?;
+            key_locks::lock_row_keys(state, ctx, &update.table, &old_tuple, sch)?;
             sql_constraints::unregister_row(
                 &mut rt,
                 &update.table,
@@ -2873,14 +4123,18 @@ fn execute_update(
                 .constraint_runtime
                 .lock()
                 .map_err(|_| lock_poisoned_engine())?;
-            if let Err(e) = sql_constraints::register_row(
-                &mut rt,
-                &update.table,
-                rid,
-                &tuple,
-                sch,
-                &cat_snapshot,
-            ) {
+            if let Err(e) = key_locks::check_row_keys(state, ctx, &update.table, &tuple, sch)
+                .and_then(|()| {
+                    sql_constraints::register_row(
+                        &mut rt,
+                        &update.table,
+                        rid,
+                        &tuple,
+                        sch,
+                        &cat_snapshot,
+                    )
+                })
+            {
                 let _ = sql_constraints::register_row(
                     &mut rt,
                     &update.table,
@@ -2892,7 +4146,7 @@ fn execute_update(
                 return Err(e);
             }
         }
-        let new_bytes = tuple.to_bytes().map_err(map_db_err)?;
+        let new_bytes = shredding::tuple_bytes(state, &update.table, &tuple)?;
         if let Some(tx) = ctx.transaction.as_mut() {
             if let Some(ref wal) = state.wal {
                 let page_id = (rid >> 32) as u64;
@@ -2947,11 +4201,12 @@ fn execute_delete(
     sql: &str,
     stmt: &SqlStatement,
     delete: &DeleteStatement,
+    plan: Option<PreparedPlan<'_>>,
 ) -> Result<EngineOutput, EngineError> {
-    validate_plan(state, sql, stmt)?;
-    record_touched_table(ctx, &delete.table);
+    validate_plan(state, sql, stmt, plan)?;
+    record_touched_table(state, ctx, &delete.table);
     let pm_for_table = table_page_manager(state, &delete.table)?;
-    let mut pm = pm_for_table.lock();
+    let mut pm = pm_for_table.write();
     let scan_clock = sql_phase_log_enabled().then(Instant::now);
     let (snapshot, where_pre_filtered) = match &delete.where_clause {
         None => (pm.select(None).map_err(map_db_err)?, false),
@@ -2963,8 +4218,9 @@ fn execute_delete(
                 (rows, skip_where)
             } else {
                 let expr = expr.clone();
+                let keyring = state.crypto_shredding.keyring().clone();
                 let pred = Box::new(move |data: &[u8]| {
-                    let tuple = Tuple::from_bytes(data).expect("heap tuple must deserialize");
+                    let tuple = keyring.decode(data).expect("heap tuple must deserialize");
                     match_where_tuple(&expr, &tuple).expect("WHERE validated for heap predicate")
                 });
                 (pm.select(Some(pred)).map_err(map_db_err)?, true)
@@ -2981,7 +4237,7 @@ fn execute_delete(
     let schema = cat_snapshot.schema(&delete.table).cloned();
     let mut to_delete: Vec<(RecordId, Vec<u8>)> = Vec::new();
     for (rid, data) in snapshot {
-        let tuple = Tuple::from_bytes(&data).map_err(map_db_err)?;
+        let tuple = shredding::decode_tuple(state, &data).map_err(map_db_err)?;
         let keep = if where_pre_filtered {
             true
         } else {
@@ -3011,12 +4267,13 @@ fn execute_delete(
     }
     let mut rows_affected = 0u64;
     for (rid, data) in to_delete {
-        let tuple = Tuple::from_bytes(&data).map_err(map_db_err)?;
+        let tuple = shredding::decode_tuple(state, &data).map_err(map_db_err)?;
         if let Some(ref sch) = schema {
             let mut rt = state
                 .constraint_runtime
                 .lock()
                 .map_err(|_| lock_poisoned_engine())?;
+            key_locks::lock_row_keys(state, ctx, &delete.table, &tuple, sch)?;
             sql_constraints::unregister_row(
                 &mut rt,
                 &delete.table,
@@ -3216,6 +4473,27 @@ fn table_schema_from_create_table(ct: &CreateTableStatement) -> Result<TableSche
         }
     }
 
+    if let Some(subject) = &ct.encrypt_by {
+        match columns.iter().find(|c| &c.name == subject) {
+            Some(c) if shredding::is_subject_type(&c.data_type) => {}
+            Some(_) => {
+                return Err(EngineError::new(
+                    engine_error_code::UNSUPPORTED_SQL,
+                    format!("ENCRYPT BY column {subject} must be a text or integer column"),
+                ))
+            }
+            None => {
+                return Err(EngineError::new(
+                    engine_error_code::UNSUPPORTED_SQL,
+                    format!(
+                        "ENCRYPT BY column {subject} is not a column of {}",
+                        ct.table_name
+                    ),
+                ))
+            }
+        }
+    }
+
     Ok(TableSchema {
         table_name: ct.table_name.clone(),
         columns,
@@ -3224,6 +4502,9 @@ fn table_schema_from_create_table(ct: &CreateTableStatement) -> Result<TableSche
         foreign_keys,
         check_constraints: checks,
         secondary_indexes: Vec::new(),
+        foreign: None,
+        encrypt_by: ct.encrypt_by.clone(),
+        tenant: None,
     })
 }
 
@@ -3402,6 +4683,7 @@ fn rebuild_all_constraint_runtime(state: &SqlEngineState) -> Result<(), EngineEr
         .lock()
         .map_err(|_| lock_poisoned_engine())?
         .clone();
+    shredding::refresh_tables(&state.crypto_shredding, &cat)?;
     {
         let mut rt = state
             .constraint_runtime
@@ -3411,39 +4693,58 @@ fn rebuild_all_constraint_runtime(state: &SqlEngineState) -> Result<(), EngineEr
     }
     let order = table_registration_order(&cat)?;
     for t in order {
-        let schema = cat.schema(&t).expect("schema").clone();
-        let pm = table_page_manager(state, &t)?;
-        let snapshot = pm.lock().select(None).map_err(map_db_err)?;
-        let mut rt = state
-            .constraint_runtime
-            .lock()
-            .map_err(|_| lock_poisoned_engine())?;
-        for (rid, data) in snapshot {
-            let tuple = Tuple::from_bytes(&data).map_err(map_db_err)?;
-            if let Err(e) = sql_constraints::register_row(&mut rt, &t, rid, &tuple, &schema, &cat) {
-                // On open, we rebuild runtime PK/UNIQUE/FK maps from persisted heap rows.
-                // If a row is missing a key column (or has NULL in a key), the heap is already
-                // inconsistent with the catalog. Prefer keeping the database open and skipping
-                // the bad row, so later statements can surface proper constraint errors.
-                //
-                // This path is intentionally narrow: we only swallow the specific "missing key"
-                // / "NULL key" failures that otherwise brick the database on startup.
-                let msg = e.message.to_ascii_lowercase();
-                let is_missing_key = msg.contains("missing value for key column");
-                let is_null_key = msg.contains("null key column value");
-                if e.code == engine_error_code::CONSTRAINT_VIOLATION
-                    && (is_missing_key || is_null_key)
-                {
-                    tracing::warn!(
-                        table = %t,
-                        rid = rid,
-                        err = %e,
-                        "skipping heap row during constraint rebuild (invalid key)"
-                    );
-                    continue;
-                }
-                return Err(e);
+        // Loaded with the tables it is linked to on first use (see `schema_cache`).
+        if schema_cache::is_cold(&state.schema_cache, &t) {
+            continue;
+        }
+        register_table_rows(state, &cat, &t)?;
+    }
+    Ok(())
+}
+
+/// Registers the stored rows of `table` in the constraint runtime.
+fn register_table_rows(
+    state: &SqlEngineState,
+    cat: &SchemaManager,
+    t: &str,
+) -> Result<(), EngineError> {
+    let Some(schema) = cat.schema(t).filter(|s| s.foreign.is_none()) else {
+        return Ok(());
+    };
+    let pm = table_page_manager(state, t)?;
+    let snapshot = pm.write().select(None).map_err(map_db_err)?;
+    let mut rt = state
+        .constraint_runtime
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?;
+    for (rid, data) in snapshot {
+        let tuple = shredding::decode_tuple(state, &data).map_err(map_db_err)?;
+        if tuple.is_deleted {
+            // Sealed with a forgotten subject key (see `shredding`).
+            continue;
+        }
+        if let Err(e) = sql_constraints::register_row(&mut rt, t, rid, &tuple, schema, cat) {
+            // On open, we rebuild runtime PK/UNIQUE/FK maps from persisted heap rows.
+            // If a row is missing a key column (or has NULL in a key), the heap is already
+            // inconsistent with the catalog. Prefer keeping the database open and skipping
+            // the bad row, so later statements can surface proper constraint errors.
+            //
+            // This path is intentionally narrow: we only swallow the specific "missing key"
+            // / "NULL key" failures that otherwise brick the database on startup.
+            let msg = e.message.to_ascii_lowercase();
+            let is_missing_key = msg.contains("missing value for key column");
+            let is_null_key = msg.contains("null key column value");
+            if e.code == engine_error_code::CONSTRAINT_VIOLATION && (is_missing_key || is_null_key)
+            {
+                tracing::warn!(
+                    table = %t,
+                    rid = rid,
+                    err = %e,
+                    "skipping heap row during constraint rebuild (invalid key)"
+                );
+                continue;
             }
+            return Err(e);
         }
     }
     Ok(())
@@ -3606,7 +4907,7 @@ pub(crate) fn insert_row_tuple(
     table: &str,
     tuple: Tuple,
 ) -> Result<(), EngineError> {
-    record_touched_table(ctx, table);
+    record_touched_table(state, ctx, table);
     let pm_for_table = table_page_manager(state, table)?;
     if table_has_primary_key(state, table) {
         insert_heap_row_pk_serialized(state, ctx, table, &tuple, &pm_for_table)?;
@@ -3628,10 +4929,10 @@ fn insert_row_tuple_inner(
     table: &str,
     tuple: Tuple,
 ) -> Result<(), EngineError> {
-    record_touched_table(ctx, table);
-    let bytes = tuple.to_bytes().map_err(map_db_err)?;
+    record_touched_table(state, ctx, table);
+    let bytes = shredding::tuple_bytes(state, table, &tuple)?;
     let pm_for_table = table_page_manager(state, table)?;
-    let mut pm = pm_for_table.lock();
+    let mut pm = pm_for_table.write();
     let ins = pm.insert(&bytes).map_err(map_db_err)?;
     maybe_simulate_dml_crash("insert_row");
     if let Some(tx) = ctx.transaction.as_mut() {
@@ -3647,7 +4948,7 @@ fn insert_row_tuple_inner(
             wal.log_data_insert(tx, pm.file_id(), page_id, record_offset, &bytes)?;
         }
     }
-    if let Err(e) = register_row_for_insert(state, table, ins.record_id, &tuple) {
+    if let Err(e) = register_row_for_insert(state, ctx, table, ins.record_id, &tuple) {
         if let Some(tx) = ctx.transaction.as_mut() {
             if let Some(ref wal) = state.wal {
                 let page_id = (ins.record_id >> 32) as u64;
@@ -3699,7 +5000,7 @@ fn insert_heap_row_after_bytes(
             wal.log_data_insert(tx, pm.file_id(), page_id, record_offset, &bytes)?;
         }
     }
-    if let Err(e) = register_row_for_insert(state, table, ins.record_id, tuple) {
+    if let Err(e) = register_row_for_insert(state, ctx, table, ins.record_id, tuple) {
         if let Some(tx) = ctx.transaction.as_mut() {
             if let Some(ref wal) = state.wal {
                 let page_id = (ins.record_id >> 32) as u64;
@@ -3734,7 +5035,7 @@ fn insert_heap_row_pk_serialized(
     ctx: &mut SessionContext,
     table: &str,
     tuple: &Tuple,
-    pm_for_table: &Arc<PageManagerMutex>,
+    pm_for_table: &Arc<PageManagerLock>,
 ) -> Result<u64, EngineError> {
     let mut rt = state
         .constraint_runtime
@@ -3744,8 +5045,8 @@ fn insert_heap_row_pk_serialized(
     let Some(schema) = cat.schema(table).cloned() else {
         drop(cat);
         drop(rt);
-        let bytes = tuple.to_bytes().map_err(map_db_err)?;
-        let mut pm = pm_for_table.lock();
+        let bytes = shredding::tuple_bytes(state, table, tuple)?;
+        let mut pm = pm_for_table.write();
         let ins = pm.insert(&bytes).map_err(map_db_err)?;
         insert_heap_row_after_bytes(state, ctx, table, tuple, &mut pm, bytes, ins)?;
         return Ok(1);
@@ -3753,10 +5054,11 @@ fn insert_heap_row_pk_serialized(
     let snapshot = cat.clone();
     drop(cat);
 
+    key_locks::check_row_keys(state, ctx, table, tuple, &schema)?;
     sql_constraints::validate_new_row_for_insert(&rt, table, tuple, &schema, &snapshot)?;
 
-    let bytes = tuple.to_bytes().map_err(map_db_err)?;
-    let mut pm = pm_for_table.lock();
+    let bytes = shredding::tuple_bytes(state, table, tuple)?;
+    let mut pm = pm_for_table.write();
     let ins = pm.insert(&bytes).map_err(map_db_err)?;
     maybe_simulate_dml_crash("insert_row");
     if let Some(tx) = ctx.transaction.as_mut() {
@@ -3797,6 +5099,7 @@ fn insert_heap_row_pk_serialized(
         pm.flush_dirty_pages().map_err(map_db_err)?;
         return Err(e);
     }
+    key_locks::lock_row_keys(state, ctx, table, tuple, &schema)?;
     drop(rt);
     sync_index_after_insert(state, table, ins.record_id, tuple)?;
     push_undo(
@@ -3815,14 +5118,14 @@ fn insert_heap_row_in_execute_insert(
     ctx: &mut SessionContext,
     table: &str,
     tuple: &Tuple,
-    pm_for_table: &Arc<PageManagerMutex>,
+    pm_for_table: &Arc<PageManagerLock>,
 ) -> Result<u64, EngineError> {
     if table_has_primary_key(state, table) {
         return insert_heap_row_pk_serialized(state, ctx, table, tuple, pm_for_table);
     }
     insert_row_with_optional_pk_lock(state, table, tuple, || {
-        let bytes = tuple.to_bytes().map_err(map_db_err)?;
-        let mut pm = pm_for_table.lock();
+        let bytes = shredding::tuple_bytes(state, table, tuple)?;
+        let mut pm = pm_for_table.write();
         let ins = pm.insert(&bytes).map_err(map_db_err)?;
         insert_heap_row_after_bytes(state, ctx, table, tuple, &mut pm, bytes, ins)?;
         Ok(1u64)
@@ -3843,19 +5146,19 @@ fn tpcc_locked_row_update<F>(
     ctx: &mut SessionContext,
     table: &str,
     rid: RecordId,
-    pm_for_table: &Arc<PageManagerMutex>,
+    pm_for_table: &Arc<PageManagerLock>,
     update_fn: &mut F,
 ) -> Result<u64, EngineError>
 where
     F: FnMut(&mut Tuple) -> Result<(), EngineError>,
 {
-    let mut pm = pm_for_table.lock();
+    let mut pm = pm_for_table.write();
     let Some(data) = pm.get_record(rid).map_err(map_db_err)? else {
         return Ok(0);
     };
-    let mut tuple = Tuple::from_bytes(&data).map_err(map_db_err)?;
+    let mut tuple = shredding::decode_tuple(state, &data).map_err(map_db_err)?;
     update_fn(&mut tuple)?;
-    let new_bytes = tuple.to_bytes().map_err(map_db_err)?;
+    let new_bytes = shredding::tuple_bytes(state, table, &tuple)?;
     if let Some(tx) = ctx.transaction.as_mut() {
         if let Some(ref wal) = state.wal {
             let page_id = (rid >> 32) as u64;
@@ -3900,9 +5203,9 @@ pub(crate) fn update_rows_by_equalities<F>(
 where
     F: FnMut(&mut Tuple) -> Result<(), EngineError>,
 {
-    record_touched_table(ctx, table);
+    record_touched_table(state, ctx, table);
     let pm_for_table = table_page_manager_cached(state, ctx, table)?;
-    let mut pm = pm_for_table.lock();
+    let mut pm = pm_for_table.write();
     let (rows, exact_key) = {
         let ir = state
             .index_registry
@@ -3968,10 +5271,10 @@ where
     let skip_row_validate = ctx.tpcc_kind.is_some();
 
     let apply = move || -> Result<u64, EngineError> {
-        let mut pm = pm_for_table.lock();
+        let mut pm = pm_for_table.write();
         let mut rows_affected = 0u64;
         for (rid, data) in rows {
-            let mut tuple = Tuple::from_bytes(&data).map_err(map_db_err)?;
+            let mut tuple = shredding::decode_tuple(state, &data).map_err(map_db_err)?;
             let old_tuple = tuple.clone();
             let cat_snapshot = {
                 let cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
@@ -3985,6 +5288,7 @@ where
                         .constraint_runtime
                         .lock()
                         .map_err(|_| lock_poisoned_engine())?;
+                    key_locks::lock_row_keys(state, ctx, table, &old_tuple, sch)?;
                     sql_constraints::unregister_row(
                         &mut rt,
                         table,
@@ -4005,10 +5309,12 @@ where
                         .constraint_runtime
                         .lock()
                         .map_err(|_| lock_poisoned_engine())?;
+                    key_locks::check_row_keys(state, ctx, table, &tuple, sch)?;
                     sql_constraints::register_row(&mut rt, table, rid, &tuple, sch, &cat_snapshot)?;
+                    key_locks::lock_row_keys(state, ctx, table, &tuple, sch)?;
                 }
             }
-            let new_bytes = tuple.to_bytes().map_err(map_db_err)?;
+            let new_bytes = shredding::tuple_bytes(state, table, &tuple)?;
             if let Some(tx) = ctx.transaction.as_mut() {
                 if let Some(ref wal) = state.wal {
                     let page_id = (rid >> 32) as u64;
@@ -4097,12 +5403,12 @@ pub(crate) fn bump_district_next_o_id(
     phase_us: Option<&mut RowUpdatePhaseUs>,
 ) -> Result<Option<u64>, EngineError> {
     const TABLE: &str = "district";
-    record_touched_table(ctx, TABLE);
+    record_touched_table(state, ctx, TABLE);
     let equalities = equalities_map_i32(&[("d_w_id", w_id), ("d_id", d_id)]);
     let pm_for_table = table_page_manager_cached(state, ctx, TABLE)?;
 
     let rid = {
-        let mut pm = pm_for_table.lock();
+        let mut pm = pm_for_table.write();
         let ir = state
             .index_registry
             .read()
@@ -4169,9 +5475,9 @@ pub(crate) fn delete_rows_by_equalities(
     table: &str,
     equalities: &HashMap<String, String>,
 ) -> Result<u64, EngineError> {
-    record_touched_table(ctx, table);
+    record_touched_table(state, ctx, table);
     let pm_for_table = table_page_manager_cached(state, ctx, table)?;
-    let mut pm = pm_for_table.lock();
+    let mut pm = pm_for_table.write();
     let (rows, exact_key) = {
         let ir = state
             .index_registry
@@ -4209,7 +5515,7 @@ pub(crate) fn delete_rows_by_equalities(
 
     let batch_index = !exact_key && rows.len() > 1;
     let apply = move || -> Result<u64, EngineError> {
-        let mut pm = pm_for_table.lock();
+        let mut pm = pm_for_table.write();
         let cat_snapshot = {
             let cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
             cat.clone()
@@ -4228,13 +5534,14 @@ pub(crate) fn delete_rows_by_equalities(
             None
         };
         for (rid, data) in rows {
-            let tuple = Tuple::from_bytes(&data).map_err(map_db_err)?;
+            let tuple = shredding::decode_tuple(state, &data).map_err(map_db_err)?;
             if tracks {
                 if let Some(ref sch) = schema {
                     let mut rt = state
                         .constraint_runtime
                         .lock()
                         .map_err(|_| lock_poisoned_engine())?;
+                    key_locks::lock_row_keys(state, ctx, table, &tuple, sch)?;
                     sql_constraints::unregister_row(
                         &mut rt,
                         table,
@@ -4264,7 +5571,8 @@ pub(crate) fn delete_rows_by_equalities(
                 Err(e) => return Err(map_db_err(e)),
             }
             if let Some(ref mut ir) = batch_ir {
-                let m = tuple_to_index_column_map(&tuple);
+                let mut m = tuple_to_index_column_map(&tuple);
+                add_computed_index_values(ir, table, &tuple, &mut m);
                 ir.delete_from_indexes(table, rid, &m).map_err(|e| {
                     EngineError::new(engine_error_code::INTERNAL, format!("index delete: {e}"))
                 })?;
@@ -4319,7 +5627,7 @@ pub(crate) fn tpcc_order_status_row_count(
     }
     drop(ir);
     let pm = table_page_manager(state, "oorder")?;
-    let mut pm = pm.lock();
+    let mut pm = pm.write();
     let mut count = 0u64;
     for rid in rids {
         if pm.get_record(rid).map_err(map_db_err)?.is_some() {
@@ -4348,13 +5656,13 @@ pub(crate) fn tpcc_stock_level_row_count(
     };
     drop(ir);
     let pm = table_page_manager(state, "stock")?;
-    let mut pm = pm.lock();
+    let mut pm = pm.write();
     let mut count = 0u64;
     for rid in rids {
         let Some(data) = pm.get_record(rid).map_err(map_db_err)? else {
             continue;
         };
-        let tuple = Tuple::from_bytes(&data).map_err(map_db_err)?;
+        let tuple = shredding::decode_tuple(state, &data).map_err(map_db_err)?;
         if tuple_i32_field(&tuple, "s_qty")? < threshold {
             count += 1;
         }
@@ -4370,6 +5678,7 @@ fn table_tracks_constraints(schema: &TableSchema) -> bool {
 
 fn register_row_for_insert(
     state: &SqlEngineState,
+    ctx: &mut SessionContext,
     table: &str,
     rid: RecordId,
     tuple: &Tuple,
@@ -4388,7 +5697,9 @@ fn register_row_for_insert(
         .constraint_runtime
         .lock()
         .map_err(|_| lock_poisoned_engine())?;
+    key_locks::check_row_keys(state, ctx, table, tuple, &schema)?;
     sql_constraints::register_row(&mut rt, table, rid, tuple, &schema, &snapshot)?;
+    key_locks::lock_row_keys(state, ctx, table, tuple, &schema)?;
     drop(rt);
     sync_index_after_insert(state, table, rid, tuple)?;
     Ok(())
@@ -4433,8 +5744,7 @@ fn apply_defaults_and_validate(
 
     // Enforce CHECK
     if !schema.check_constraints.is_empty() {
-        let mut row = Row::new();
-        row.values = tuple.values.clone();
+        let row = tuple_as_eval_row(tuple);
         for chk in &schema.check_constraints {
             if !eval_predicate_expression(&row, &chk.expr) {
                 return Err(EngineError::new(
@@ -4487,9 +5797,13 @@ fn literal_to_data_type(l: &Literal) -> DataType {
     }
 }
 
-fn eval_select_without_from(sel: &SelectStatement) -> Result<EngineOutput, EngineError> {
+/// The one-row result of `sel`, with the values of its cells.
+fn eval_select_without_from(
+    sel: &SelectStatement,
+) -> Result<(EngineOutput, Vec<DataType>), EngineError> {
     let mut columns = Vec::new();
     let mut row = Vec::new();
+    let mut values = Vec::new();
     for (i, item) in sel.select_list.iter().enumerate() {
         match item {
             SelectItem::Wildcard => {
@@ -4502,13 +5816,23 @@ fn eval_select_without_from(sel: &SelectStatement) -> Result<EngineOutput, Engin
                 let name = alias.clone().unwrap_or_else(|| format!("col{}", i + 1));
                 columns.push(name);
                 row.push(expr_to_string(expr)?);
+                if let Expression::Literal(l) = expr {
+                    values.push(literal_to_data_type(l));
+                }
             }
         }
     }
-    Ok(EngineOutput::ResultSet {
+    let out = EngineOutput::ResultSet {
         columns,
         rows: vec![row],
-    })
+    };
+    Ok((out, values))
+}
+
+fn record_select_without_from(out: &EngineOutput, values: &[DataType]) {
+    if let EngineOutput::ResultSet { columns, .. } = out {
+        typed_results::record(columns, || vec![values.to_vec()]);
+    }
 }
 
 fn expr_to_string(expr: &Expression) -> Result<String, EngineError> {
@@ -4533,21 +5857,34 @@ fn literal_to_string(l: &Literal) -> String {
 
 fn rows_to_engine_output(rows: Vec<Row>) -> Result<EngineOutput, EngineError> {
     if rows.is_empty() {
+        typed_results::record(&[], Vec::new);
         return Ok(EngineOutput::ResultSet {
             columns: vec![],
             rows: vec![],
         });
     }
-    let mut columns: Vec<String> = rows[0].values.keys().cloned().collect();
+    let mut columns: Vec<String> = rows[0].column_names().map(str::to_string).collect();
     columns.sort();
+    typed_results::record(&columns, || {
+        rows.iter()
+            .map(|r| {
+                columns
+                    .iter()
+                    .map(|c| {
+                        r.get_value(c)
+                            .map_or(DataType::Null, |cv| cv.data_type.clone())
+                    })
+                    .collect()
+            })
+            .collect()
+    });
     let data: Vec<Vec<String>> = rows
         .iter()
         .map(|r| {
             columns
                 .iter()
                 .map(|c| {
-                    r.values
-                        .get(c)
+                    r.get_value(c)
                         .map(|cv| format!("{:?}", cv.data_type))
                         .unwrap_or_else(|| "NULL".to_string())
                 })
@@ -5269,6 +6606,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn tuple_ids_are_not_reissued_after_failed_statement_and_reopen() {
+        let dir = TempDir::new().unwrap();
+        let issued = {
+            let eng = SqlEngine::open(dir.path().to_path_buf()).unwrap();
+            let mut ctx = SessionContext::default();
+            eng.execute_sql("CREATE TABLE tseq (id INT PRIMARY KEY)", &mut ctx)
+                .unwrap();
+            eng.execute_sql("BEGIN TRANSACTION", &mut ctx).unwrap();
+            eng.execute_sql("INSERT INTO tseq (id) VALUES (1)", &mut ctx)
+                .unwrap();
+            let cached = ctx.transaction.as_ref().unwrap().tuple_ids.clone();
+            assert!(eng
+                .execute_sql("INSERT INTO tseq (id) VALUES (1)", &mut ctx)
+                .is_err());
+            // The failed statement's id stays consumed.
+            assert_eq!(
+                ctx.transaction.as_ref().unwrap().tuple_ids.start,
+                cached.start + 1
+            );
+            eng.execute_sql("COMMIT", &mut ctx).unwrap();
+            eng.execute_sql("INSERT INTO tseq (id) VALUES (2)", &mut ctx)
+                .unwrap();
+            eng.state.tuple_ids.reserve(None, 1).unwrap().start
+        };
+
+        let eng = SqlEngine::open(dir.path().to_path_buf()).unwrap();
+        let next = eng.state.tuple_ids.reserve(None, 1).unwrap().start;
+        assert!(next >= issued, "id {next} reissued (issued up to {issued})");
+    }
+
     #[test]
     fn district_update_exact_index_enables_row_lock_path() {
         let dir = TempDir::new().unwrap();
@@ -5379,6 +6747,8 @@ mod tests {
             .unwrap();
         }
         let eng = SqlEngine::open(path).unwrap();
+        // Indexes of a table are rebuilt on its first use (see `schema_cache`).
+        eng.preload_schema(&["district"]).expect("preload");
         let mut ctx = SessionContext::default();
         let mut eq = HashMap::new();
         eq.insert("d_w_id".to_string(), "1".to_string());
@@ -5485,24 +6855,24 @@ mod tests {
         eng.execute_sql("INSERT INTO tb (k) VALUES (0)", &mut ctx)
             .unwrap();
         let pm_b = table_page_manager(eng.state_for_test(), "tb").unwrap();
-        assert_eq!(pm_b.lock().dirty_page_count(), 0);
+        assert_eq!(pm_b.read().dirty_page_count(), 0);
 
         eng.execute_sql("BEGIN TRANSACTION", &mut ctx).unwrap();
         eng.execute_sql("INSERT INTO ta (k) VALUES (1)", &mut ctx)
             .unwrap();
         let pm_a = table_page_manager(eng.state_for_test(), "ta").unwrap();
         assert!(
-            pm_a.lock().dirty_page_count() > 0,
+            pm_a.read().dirty_page_count() > 0,
             "touched table ta should have dirty pages before COMMIT"
         );
         assert_eq!(
-            pm_b.lock().dirty_page_count(),
+            pm_b.read().dirty_page_count(),
             0,
             "untouched table tb should not be flushed mid-txn"
         );
         eng.execute_sql("COMMIT", &mut ctx).unwrap();
-        assert_eq!(pm_a.lock().dirty_page_count(), 0);
-        assert_eq!(pm_b.lock().dirty_page_count(), 0);
+        assert_eq!(pm_a.read().dirty_page_count(), 0);
+        assert_eq!(pm_b.read().dirty_page_count(), 0);
     }
 
     #[test]
@@ -5518,13 +6888,13 @@ mod tests {
         eng.execute_sql("INSERT INTO defer_flush (k) VALUES (2)", &mut ctx)
             .unwrap();
         let pm = table_page_manager(eng.state_for_test(), "defer_flush").unwrap();
-        let dirty_mid_txn = pm.lock().dirty_page_count();
+        let dirty_mid_txn = pm.read().dirty_page_count();
         assert!(
             dirty_mid_txn > 0,
             "expected dirty heap pages before COMMIT, got {dirty_mid_txn}"
         );
         eng.execute_sql("COMMIT", &mut ctx).unwrap();
-        let dirty_after_commit = pm.lock().dirty_page_count();
+        let dirty_after_commit = pm.read().dirty_page_count();
         assert_eq!(
             dirty_after_commit, 0,
             "COMMIT should flush dirty heap pages"
@@ -5534,6 +6904,8 @@ mod tests {
     #[test]
     fn explicit_transaction_defers_heap_flush_on_commit_when_env_set() {
         std::env::set_var("RUSTDB_DEFER_HEAP_FLUSH_ON_COMMIT", "1");
+        // Scheduled background writer runs would race the assertions below.
+        std::env::set_var("RUSTDB_BGWRITER_INTERVAL_MS", "600000");
         let dir = TempDir::new().unwrap();
         let eng = SqlEngine::open(dir.path().to_path_buf()).unwrap();
         assert!(eng.wal_enabled());
@@ -5547,13 +6919,26 @@ mod tests {
         eng.execute_sql("INSERT INTO defer_commit_flush (k) VALUES (1)", &mut ctx)
             .unwrap();
         let pm = table_page_manager(eng.state_for_test(), "defer_commit_flush").unwrap();
-        assert!(pm.lock().dirty_page_count() > 0);
+        assert!(pm.read().dirty_page_count() > 0);
         eng.execute_sql("COMMIT", &mut ctx).unwrap();
         assert!(
-            pm.lock().dirty_page_count() > 0,
+            pm.read().dirty_page_count() > 0,
             "COMMIT should skip heap flush when RUSTDB_DEFER_HEAP_FLUSH_ON_COMMIT=1"
         );
+        assert!(pm.read().queued_commit_page_count() > 0);
+
+        // The background writer writes the commit group.
+        eng.background_jobs()
+            .run_now(crate::network::sql_engine_wal::BACKGROUND_WRITER_JOB)
+            .unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while pm.read().dirty_page_count() > 0 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(pm.read().dirty_page_count(), 0);
+        assert_eq!(pm.read().queued_commit_page_count(), 0);
         std::env::remove_var("RUSTDB_DEFER_HEAP_FLUSH_ON_COMMIT");
+        std::env::remove_var("RUSTDB_BGWRITER_INTERVAL_MS");
     }
 
     #[test]
@@ -5658,6 +7043,48 @@ mod tests {
         assert_eq!(out, EngineOutput::ExecutionOk { rows_affected: 1 });
     }
 
+    #[test]
+    fn sql_engine_prepared_select_binds_into_its_plan() {
+        let dir = TempDir::new().unwrap();
+        let eng = SqlEngine::open(dir.path().to_path_buf()).unwrap();
+        let mut ctx = SessionContext::default();
+        eng.execute_sql("CREATE TABLE tp (k INT, n INT)", &mut ctx)
+            .unwrap();
+        eng.execute_sql("CREATE INDEX tp_k ON tp (k)", &mut ctx)
+            .unwrap();
+        for i in 0..8 {
+            let ins = format!("INSERT INTO tp (k, n) VALUES ({i}, {})", i * 10);
+            eng.execute_sql(&ins, &mut ctx).unwrap();
+        }
+        let select = eng.prepare("SELECT n FROM tp WHERE k = $1").unwrap();
+        let run = |ctx: &mut SessionContext, k: i64| {
+            let param = ColumnValue::new(DataType::BigInt(k));
+            match eng.execute_prepared(&select, &[param], ctx).unwrap() {
+                EngineOutput::ResultSet { rows, .. } => rows,
+                other => panic!("expected result set, got {other:?}"),
+            }
+        };
+        let cached_plans = |eng: &SqlEngine| {
+            eng.state
+                .dml_plan_validation_cache
+                .lock()
+                .unwrap()
+                .plans
+                .len()
+        };
+        assert_eq!(run(&mut ctx, 3), vec![vec!["Integer(30)".to_string()]]);
+        let plans = cached_plans(&eng);
+        for i in 0..8 {
+            assert_eq!(run(&mut ctx, i), vec![vec![format!("Integer({})", i * 10)]]);
+        }
+        // Each run binds its value into the plan of the handle: no plan per value.
+        assert_eq!(cached_plans(&eng), plans);
+
+        eng.execute_sql("DROP INDEX tp_k", &mut ctx).unwrap();
+        assert_eq!(run(&mut ctx, 5), vec![vec!["Integer(50)".to_string()]]);
+        assert!(run(&mut ctx, 9).is_empty());
+    }
+
     #[test]
     fn sql_engine_rollback_survives_page_splits() {
         // Regression: record ids encode byte offsets, so page split/merge can invalidate them.
@@ -0,0 +1,194 @@
+//! Stored indexes across restarts.
+//!
+//! Secondary indexes keep their pages in the data directory: a B+ tree index in
+//! `<table>.<index>.bpt` (see [`crate::storage::index::paged_btree`]), a hash index
+//! (`CREATE INDEX ... USING HASH`) in `<table>.<index>.lhx` (see
+//! [`crate::storage::index::linear_hash`]). `CHECKPOINT` and closing the engine write their
+//! changed pages durably, tagged with the WAL position they include; indexes of tables with open
+//! write transactions are skipped, since they may hold uncommitted changes.
+//!
+//! On open, an index is brought up to date by redoing on top of its checkpoint the committed
+//! WAL changes to its table logged after that position. It is cleared and rebuilt from the heap
+//! instead when it was never checkpointed, when the WAL no longer reaches back to its checkpoint
+//! (or the engine runs without a WAL and the index changed after it), or when a logged row
+//! cannot be decoded.
+
+use super::{
+    add_computed_index_values, lock_poisoned_engine, map_db_err, row_counts, table_page_manager,
+    tuple_to_index_column_map, EngineError, SqlEngineState,
+};
+use crate::common::types::RecordId;
+use crate::common::Result as DbResult;
+use crate::logging::log_record::{LogRecord, LogRecordType};
+use crate::network::sql_engine_wal::{committed_changes, log_record_operation_parts};
+use crate::storage::index::PersistentIndex;
+use crate::storage::index_registry::{IndexChange, IndexRegistry};
+use crate::storage::page_manager::PageManager;
+use crate::storage::shred::Keyring;
+
+/// Committed WAL changes, read once per catalog load that needs them.
+pub(super) type WalChanges = Option<(Option<u64>, Vec<LogRecord>)>;
+
+/// Brings the stored index `index_name` on `table` up to date from its files and the WAL.
+/// Returns `false` when it was cleared instead (or is kept in memory) and must be rebuilt from
+/// the heap.
+pub(super) fn restore(
+    state: &SqlEngineState,
+    table: &str,
+    index_name: &str,
+    wal_changes: &mut WalChanges,
+) -> Result<bool, EngineError> {
+    let registry = state
+        .index_registry
+        .read()
+        .map_err(|_| lock_poisoned_engine())?;
+    let Some(entry) = registry.get_index_entry(table, index_name) else {
+        return Ok(false);
+    };
+    let mut index = entry.index.lock().map_err(|_| lock_poisoned_engine())?;
+    let Some(stored) = index.as_persistent_mut() else {
+        return Ok(false);
+    };
+    let changes = match (stored.checkpoint_lsn(), &state.wal) {
+        (None, _) => None,
+        (Some(_), None) => (!stored.modified_since_checkpoint()).then(Vec::new),
+        (Some(lsn), Some(_)) => {
+            if wal_changes.is_none() {
+                let wal_dir = state.data_dir.join(".rustdb").join("wal");
+                *wal_changes = Some(committed_changes(&wal_dir).map_err(map_db_err)?);
+            }
+            let (oldest, redo) = wal_changes.as_ref().expect("read above");
+            let reaches_back = oldest.map_or(lsn == 0, |oldest| oldest <= lsn + 1);
+            let file_id = table_page_manager(state, table)?.read().file_id();
+            reaches_back.then(|| {
+                redo.iter()
+                    .filter(|r| r.lsn > lsn)
+                    .filter(|r| {
+                        log_record_operation_parts(r).is_some_and(|(_, op)| op.file_id == file_id)
+                    })
+                    .collect()
+            })
+        }
+    };
+    let restored = match changes {
+        Some(changes) => {
+            match redo_changes(
+                state.crypto_shredding.keyring(),
+                &registry,
+                table,
+                index_name,
+                &entry.columns,
+                stored,
+                &changes,
+            ) {
+                Ok(()) => {
+                    tracing::info!(
+                        table,
+                        index = index_name,
+                        redone = changes.len(),
+                        "index restored"
+                    );
+                    true
+                }
+                Err(e) => {
+                    tracing::warn!(table, index = index_name, error = %e, "index redo failed; rebuilding");
+                    false
+                }
+            }
+        }
+        None => false,
+    };
+    if !restored {
+        stored.clear().map_err(map_db_err)?;
+    }
+    Ok(restored)
+}
+
+/// Applies the row changes of `records` (committed, in LSN order) to `index`.
+fn redo_changes(
+    keyring: &Keyring,
+    registry: &IndexRegistry,
+    table: &str,
+    index_name: &str,
+    columns: &[String],
+    index: &mut dyn PersistentIndex<Key = String, Value = Vec<RecordId>>,
+    records: &[&LogRecord],
+) -> DbResult<()> {
+    let key_of = |bytes: &Option<Vec<u8>>| -> DbResult<Option<String>> {
+        let Some(bytes) = bytes else {
+            return Ok(None);
+        };
+        let tuple = keyring.decode(bytes)?;
+        if tuple.is_deleted {
+            return Ok(None);
+        }
+        let mut values = tuple_to_index_column_map(&tuple);
+        add_computed_index_values(registry, table, &tuple, &mut values);
+        if !registry.covers_row(table, index_name, &values) {
+            return Ok(None);
+        }
+        IndexRegistry::build_index_key_from_map(columns, &values).map(Some)
+    };
+    for record in records {
+        let Some((kind, op)) = log_record_operation_parts(record) else {
+            continue;
+        };
+        let record_id = PageManager::record_id_for_slot(op.page_id, u32::from(op.record_offset));
+        let (old, new) = match kind {
+            LogRecordType::DataInsert => (None, key_of(&op.new_data)?),
+            LogRecordType::DataUpdate => (key_of(&op.old_data)?, key_of(&op.new_data)?),
+            LogRecordType::DataDelete => (key_of(&op.old_data)?, None),
+            _ => continue,
+        };
+        if let Some(key) = old {
+            IndexChange::Delete { key, record_id }.apply_to(index)?;
+        }
+        if let Some(key) = new {
+            IndexChange::Insert { key, record_id }.apply_to(index)?;
+        }
+    }
+    Ok(())
+}
+
+/// Checkpoints the stored indexes of the tables no open transaction writes.
+pub(super) fn checkpoint(state: &SqlEngineState) -> Result<(), EngineError> {
+    let indexes = state
+        .index_registry
+        .read()
+        .map_err(|_| lock_poisoned_engine())?
+        .persistent_indexes();
+    for ((table, name), index) in indexes {
+        let mut index = index.lock().map_err(|_| lock_poisoned_engine())?;
+        let Some(stored) = index.as_persistent_mut() else {
+            continue;
+        };
+        // Read before checking for writers: a transaction logging changes to the table at or
+        // below this position has started writing it by then, so the index is skipped unless
+        // that transaction finished (and its changes reached the index).
+        let lsn = state.wal.as_ref().map_or(0, |wal| wal.current_lsn());
+        if row_counts::has_writers(&state.row_counts, &table) {
+            tracing::debug!(
+                table,
+                index = name,
+                "index checkpoint skipped: open writers"
+            );
+            continue;
+        }
+        // The WAL must hold everything the checkpoint includes before the index does.
+        if let Some(wal) = &state.wal {
+            let flushed = if tokio::runtime::Handle::try_current().is_ok() {
+                // On an async thread blocking on the WAL runtime panics: flush from a helper.
+                std::thread::scope(|s| {
+                    s.spawn(|| wal.flush_buffered())
+                        .join()
+                        .unwrap_or_else(|_| Err(lock_poisoned_engine()))
+                })
+            } else {
+                wal.flush_buffered()
+            };
+            flushed?;
+        }
+        stored.checkpoint(lsn).map_err(map_db_err)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,397 @@
+//! Prepared statements: [`SqlEngine::prepare`] parses a statement with `$n` or `?` placeholders
+//! once, and [`SqlEngine::execute_prepared`] binds values into the parsed statement
+//! ([`crate::parser::prepared::bind_parameters`]) and runs it without going back to the parser.
+//!
+//! A `SELECT` is planned on its first run with the placeholders in place, and the handle keeps
+//! that plan: later runs bind their values into it ([`ExecutionPlan::bind_parameters`]) instead
+//! of planning again, until DDL or an index change makes it stale. `INSERT`, `UPDATE` and
+//! `DELETE` check their plan the same way. [`SqlEngine::describe_prepared`] gives the result
+//! columns from that plan without running the statement.
+//!
+//! A bound statement still has a text: the prepared SQL with each placeholder replaced by the
+//! literal of its value, which statement statistics, the audit log and session traces record.
+//! Placeholders stand for values in `SELECT`, `INSERT`, `UPDATE`, `DELETE` and `EXPLAIN`; BLOB
+//! values cannot be bound.
+
+use std::sync::{Arc, Mutex};
+
+use super::{
+    map_db_err, plan_and_optimize, plan_epoch, schema_cache, session_trace, settings, system_views,
+    tenants, time_travel, EngineError, EngineOutput, SqlEngine, SqlEngineState,
+};
+use crate::common::row_mapping::{parse_cell, sql_literal};
+use crate::common::types::{ColumnValue, DataType};
+use crate::network::engine::{engine_error_code, SessionContext};
+use crate::parser::ast::{SelectItem, SelectStatement, TableReference};
+use crate::parser::prepared::{bind_parameters, parameter_count};
+use crate::parser::quoting::placeholders;
+use crate::parser::{Expression, Literal, SqlParser, SqlStatement};
+use crate::planner::planner::expr_to_short_name;
+use crate::planner::{ExecutionPlan, PlanNode};
+
+/// A parsed statement waiting for its parameter values (see [`SqlEngine::prepare`])
+#[derive(Debug, Clone)]
+pub struct PreparedSql {
+    sql: String,
+    statement: SqlStatement,
+    param_count: usize,
+    /// `(start, end, n)`: character range of each placeholder of `sql` and the parameter it
+    /// stands for
+    placeholders: Vec<(usize, usize, usize)>,
+    /// Plan of `statement` with its placeholders and the schema epoch it was made in, once made
+    plan: Arc<Mutex<Option<(u64, Arc<ExecutionPlan>)>>>,
+}
+
+/// Result column of a prepared statement: name and a value of its type, [`DataType::Null`] when
+/// the statement does not fix it (see [`SqlEngine::describe_prepared`])
+pub type ResultColumn = (String, DataType);
+
+impl PreparedSql {
+    /// SQL the statement was prepared from
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    /// Values [`SqlEngine::execute_prepared`] takes: the highest placeholder
+    pub fn param_count(&self) -> usize {
+        self.param_count
+    }
+
+    /// `sql` with each placeholder replaced by the text of its value
+    fn bound_sql(&self, values: &[String]) -> String {
+        let chars: Vec<char> = self.sql.chars().collect();
+        let mut out = String::with_capacity(self.sql.len());
+        let mut copied = 0;
+        for &(start, end, n) in &self.placeholders {
+            out.extend(&chars[copied..start]);
+            out.push_str(&values[n - 1]);
+            copied = end;
+        }
+        out.extend(&chars[copied..]);
+        out
+    }
+
+    /// The plan of the statement with its placeholders, made now unless the handle has one of
+    /// the current schema epoch
+    fn plan(&self, state: &SqlEngineState) -> Result<Arc<ExecutionPlan>, EngineError> {
+        let epoch = plan_epoch(state)?;
+        if let Some((made, plan)) = self.cached_plan()?.as_ref() {
+            if *made == epoch {
+                return Ok(plan.clone());
+            }
+        }
+        let (plan, _) = plan_and_optimize(state, &self.sql, &self.statement, false)?;
+        let plan = Arc::new(plan);
+        *self.cached_plan()? = Some((epoch, plan.clone()));
+        Ok(plan)
+    }
+
+    fn cached_plan(
+        &self,
+    ) -> Result<std::sync::MutexGuard<'_, Option<(u64, Arc<ExecutionPlan>)>>, EngineError> {
+        self.plan.lock().map_err(|_| super::lock_poisoned_engine())
+    }
+}
+
+impl SqlEngine {
+    /// Parses `sql`, one statement whose values may be `$1`, `$2`, ... or `?` placeholders, for
+    /// [`Self::execute_prepared`].
+    pub fn prepare(&self, sql: &str) -> Result<PreparedSql, EngineError> {
+        let mut stmts = SqlParser::new(sql)
+            .and_then(|mut p| p.parse_multiple())
+            .map_err(map_db_err)?;
+        let statement = match (stmts.pop(), stmts.is_empty()) {
+            (Some(statement), true) => statement,
+            _ => {
+                return Err(EngineError::new(
+                    engine_error_code::PROTOCOL,
+                    "a prepared statement must be exactly one SQL statement",
+                ))
+            }
+        };
+        let placeholders = placeholders(sql).map_err(map_db_err)?;
+        let param_count = parameter_count(&statement);
+        if placeholders.iter().any(|&(_, _, n)| n > param_count) {
+            return Err(EngineError::new(
+                engine_error_code::UNSUPPORTED_SQL,
+                "parameters are only supported as values in SELECT, INSERT, UPDATE, DELETE and EXPLAIN",
+            ));
+        }
+        Ok(PreparedSql {
+            sql: sql.to_string(),
+            statement,
+            param_count,
+            placeholders,
+            plan: Arc::default(),
+        })
+    }
+
+    /// Runs `prepared` in session `ctx` with `params[n - 1]` as the value of placeholder `$n`.
+    pub fn execute_prepared(
+        &self,
+        prepared: &PreparedSql,
+        params: &[ColumnValue],
+        ctx: &mut SessionContext,
+    ) -> Result<EngineOutput, EngineError> {
+        if params.len() != prepared.param_count {
+            return Err(EngineError::new(
+                engine_error_code::PROTOCOL,
+                format!(
+                    "prepared statement takes {} parameters but {} were given",
+                    prepared.param_count,
+                    params.len()
+                ),
+            ));
+        }
+        let literals = params.iter().map(literal).collect::<Result<Vec<_>, _>>()?;
+        let texts: Vec<String> = literals.iter().map(literal_text).collect();
+        let values: Vec<Expression> = literals.iter().cloned().map(Expression::Literal).collect();
+        let statement = bind_parameters(&prepared.statement, &values).map_err(map_db_err)?;
+        let sql = prepared.bound_sql(&texts);
+        let plan = |state: &SqlEngineState| Ok(prepared.plan(state)?.bind_parameters(&literals));
+        self.run_session_statement(&sql, ctx, |state, ctx| match ctx.trace_file.clone() {
+            // A trace shows every phase, parsing included.
+            Some(file) => session_trace::execute_traced(state, &sql, ctx, &file),
+            None => Self::execute_planned(state, &sql, &statement, Some(&plan), ctx),
+        })
+    }
+
+    /// Result columns of `prepared` in result order (by name), without running it: from its
+    /// plan for a `SELECT` over tables, from the select list where no plan is made (system
+    /// views, `AS OF`, no `FROM`). `SHOW`, which only reads a setting, is described by its
+    /// value. `None` for statements that return no rows.
+    pub fn describe_prepared(
+        &self,
+        prepared: &PreparedSql,
+        ctx: &SessionContext,
+    ) -> Result<Option<Vec<ResultColumn>>, EngineError> {
+        let state = self.state.as_ref();
+        let stmt = &prepared.statement;
+        tenants::check_access(state, ctx, stmt)?;
+        schema_cache::load_statement_tables(state, stmt)?;
+        let mut columns = match stmt {
+            SqlStatement::Explain(_) => {
+                vec![("QUERY PLAN".to_string(), DataType::Text(String::new()))]
+            }
+            SqlStatement::ShowParameter(name) => {
+                match settings::show_parameter(state, name.as_deref())? {
+                    EngineOutput::ResultSet { columns, rows } => columns
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, c)| {
+                            let value = rows.first().map_or(DataType::Null, |r| parse_cell(&r[i]));
+                            (c, value)
+                        })
+                        .collect(),
+                    EngineOutput::ExecutionOk { .. } => Vec::new(),
+                }
+            }
+            SqlStatement::Select(sel)
+                if sel.from.is_none()
+                    || system_views::system_view_name(sel).is_some()
+                    || time_travel::as_of_table(sel)?.is_some() =>
+            {
+                select_list_columns(state, sel)?
+            }
+            SqlStatement::Select(_) | SqlStatement::SetOperation(_) => {
+                plan_columns(state, &*prepared.plan(state)?)?
+            }
+            _ => return Ok(None),
+        };
+        columns.sort_by(|a, b| a.0.cmp(&b.0));
+        columns.dedup_by(|a, b| a.0 == b.0);
+        Ok(Some(columns))
+    }
+}
+
+/// Result columns of a planned `SELECT`: those of its topmost projection
+fn plan_columns(
+    state: &SqlEngineState,
+    plan: &ExecutionPlan,
+) -> Result<Vec<ResultColumn>, EngineError> {
+    let mut node = &plan.root;
+    let projection = loop {
+        match node {
+            PlanNode::Projection(projection) => break projection,
+            other => match other.inputs().first() {
+                Some(input) => node = input,
+                None => return Ok(Vec::new()),
+            },
+        }
+    };
+    let mut tables = Vec::new();
+    scanned_tables(&projection.input, &mut tables);
+    let table_columns = catalog_columns(state, &tables);
+    if projection.columns.iter().any(|c| c.name == "*") {
+        return wildcard_columns(&tables, table_columns);
+    }
+    let known: Vec<ResultColumn> = table_columns.into_iter().flatten().flatten().collect();
+    Ok(projection
+        .columns
+        .iter()
+        .map(|c| {
+            let name = c.alias.clone().unwrap_or_else(|| c.name.clone());
+            let value = c
+                .expression
+                .as_ref()
+                .map_or(DataType::Null, |e| expression_type(e, &known));
+            (name, value)
+        })
+        .collect())
+}
+
+/// Result columns of a `SELECT` no plan is made for, from its select list
+fn select_list_columns(
+    state: &SqlEngineState,
+    sel: &SelectStatement,
+) -> Result<Vec<ResultColumn>, EngineError> {
+    let tables: Vec<String> = sel
+        .from
+        .iter()
+        .flat_map(|from| std::iter::once(&from.table).chain(from.joins.iter().map(|j| &j.table)))
+        .filter_map(|table| match table {
+            TableReference::Table { name, .. } | TableReference::Function { name, .. } => {
+                Some(name.clone())
+            }
+            TableReference::Subquery { .. } => None,
+        })
+        .collect();
+    let table_columns = catalog_columns(state, &tables);
+    if sel
+        .select_list
+        .iter()
+        .any(|item| matches!(item, SelectItem::Wildcard))
+    {
+        return wildcard_columns(&tables, table_columns);
+    }
+    let known: Vec<ResultColumn> = table_columns.into_iter().flatten().flatten().collect();
+    Ok(sel
+        .select_list
+        .iter()
+        .filter_map(|item| match item {
+            SelectItem::Expression { expr, alias } => Some((
+                alias.clone().unwrap_or_else(|| expr_to_short_name(expr)),
+                expression_type(expr, &known),
+            )),
+            SelectItem::Wildcard => None,
+        })
+        .collect())
+}
+
+/// Tables a plan reads, in plan order
+fn scanned_tables(node: &PlanNode, out: &mut Vec<String>) {
+    match node {
+        PlanNode::TableScan(scan) if !scan.table_name.is_empty() => {
+            out.push(scan.table_name.clone())
+        }
+        PlanNode::IndexScan(scan) => out.push(scan.table_name.clone()),
+        PlanNode::ForeignScan(scan) => out.push(scan.table_name.clone()),
+        _ => {}
+    }
+    for input in node.inputs() {
+        scanned_tables(input, out);
+    }
+}
+
+/// Declared columns of each of `tables`, `None` for a table without a schema in the catalog
+fn catalog_columns(state: &SqlEngineState, tables: &[String]) -> Vec<Option<Vec<ResultColumn>>> {
+    let Ok(cat) = state.catalog.lock() else {
+        return vec![None; tables.len()];
+    };
+    tables
+        .iter()
+        .map(|table| {
+            cat.schema(table).map(|schema| {
+                schema
+                    .columns
+                    .iter()
+                    .map(|c| (c.name.clone(), c.data_type.clone()))
+                    .collect()
+            })
+        })
+        .collect()
+}
+
+/// Columns `*` stands for: every declared column of the tables read. Relations without declared
+/// columns (system views, tables created by their first `INSERT`) only show theirs when run.
+fn wildcard_columns(
+    tables: &[String],
+    table_columns: Vec<Option<Vec<ResultColumn>>>,
+) -> Result<Vec<ResultColumn>, EngineError> {
+    let mut out = Vec::new();
+    for (table, columns) in tables.iter().zip(table_columns) {
+        let Some(columns) = columns else {
+            return Err(EngineError::new(
+                engine_error_code::UNSUPPORTED_SQL,
+                format!(
+                    "the columns of * over {table} are only known once it runs; describe the bound statement instead"
+                ),
+            ));
+        };
+        out.extend(columns);
+    }
+    Ok(out)
+}
+
+/// A value of the type `expr` evaluates to, [`DataType::Null`] when that depends on the values
+fn expression_type(expr: &Expression, columns: &[ResultColumn]) -> DataType {
+    match expr {
+        Expression::Identifier(name) | Expression::QualifiedIdentifier { column: name, .. } => {
+            columns
+                .iter()
+                .find(|(c, _)| c == name)
+                .map_or(DataType::Null, |(_, t)| t.clone())
+        }
+        Expression::Literal(Literal::Boolean(_)) => DataType::Boolean(false),
+        Expression::Literal(Literal::Integer(_)) => DataType::BigInt(0),
+        Expression::Literal(Literal::Float(_)) => DataType::Double(0.0),
+        Expression::Literal(Literal::String(_)) => DataType::Text(String::new()),
+        Expression::Function { name, args } => match name.to_ascii_uppercase().as_str() {
+            "COUNT" => DataType::BigInt(0),
+            "AVG" => DataType::Double(0.0),
+            "SUM" | "MIN" | "MAX" => args
+                .first()
+                .map_or(DataType::Null, |arg| expression_type(arg, columns)),
+            _ => DataType::Null,
+        },
+        _ => DataType::Null,
+    }
+}
+
+/// The literal a parameter value binds as, the one its SQL text would parse to
+fn literal(value: &ColumnValue) -> Result<Literal, EngineError> {
+    if value.is_null() {
+        return Ok(Literal::Null);
+    }
+    Ok(match &value.data_type {
+        DataType::Null => Literal::Null,
+        DataType::Boolean(b) => Literal::Boolean(*b),
+        DataType::TinyInt(n) => Literal::Integer(i64::from(*n)),
+        DataType::SmallInt(n) => Literal::Integer(i64::from(*n)),
+        DataType::Integer(n) => Literal::Integer(i64::from(*n)),
+        DataType::BigInt(n) => Literal::Integer(*n),
+        // Through the shortest decimal of the f32, as the literal `1.1` gives 1.1
+        DataType::Float(f) => Literal::Float(f.to_string().parse().unwrap_or(f64::from(*f))),
+        DataType::Double(f) => Literal::Float(*f),
+        DataType::Blob(_) => {
+            return Err(EngineError::new(
+                engine_error_code::UNSUPPORTED_SQL,
+                "BLOB values cannot be bound to parameters",
+            ))
+        }
+        // The lexer keeps a string literal's quotes and escapes in its value.
+        text => Literal::String(sql_literal(text)),
+    })
+}
+
+fn literal_text(literal: &Literal) -> String {
+    match literal {
+        Literal::Null => "NULL".to_string(),
+        Literal::Boolean(b) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+        Literal::Integer(n) => n.to_string(),
+        // `1.0`, not `1`: the text names the statement and must not read as an integer.
+        Literal::Float(f) if f.is_finite() && f.fract() == 0.0 => format!("{f}.0"),
+        Literal::Float(f) => f.to_string(),
+        Literal::String(s) => s.clone(),
+    }
+}
@@ -0,0 +1,328 @@
+//! Statistics of completed statements, grouped by fingerprint (like `pg_stat_statements`).
+//!
+//! A fingerprint is the SQL text with whitespace collapsed and string and number literals
+//! replaced by `?`, so `SELECT * FROM t WHERE id = 1` and `... id = 2` count as one statement.
+//! Only statements that succeed are recorded. Buffer hits and misses are the page cache lookups
+//! the statement made on its session thread.
+//!
+//! The store keeps at most [`STATEMENT_STATS_CAPACITY`] fingerprints in memory; a new one
+//! replaces the least called. Rows are served by the `rustdb_stat_statements` system view and
+//! cleared by `SELECT rustdb_stat_statements_reset()`.
+
+use super::{EngineError, EngineOutput, SqlEngineState};
+use crate::common::types::{ColumnValue, DataType, Row};
+use crate::parser::ast::{Expression, SelectItem, SelectStatement};
+use crate::storage::io_optimization::thread_page_cache_lookups;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Fingerprints kept by the store.
+pub(super) const STATEMENT_STATS_CAPACITY: usize = 1000;
+
+/// Function that clears the store.
+const RESET_FUNCTION: &str = "rustdb_stat_statements_reset";
+
+#[derive(Debug, Clone)]
+struct StatementEntry {
+    /// Fingerprint text (literals shown as `?`)
+    query: String,
+    calls: u64,
+    total: Duration,
+    min: Duration,
+    max: Duration,
+    /// Rows returned or affected, over all calls
+    rows: u64,
+    buffer_hits: u64,
+    buffer_misses: u64,
+}
+
+/// Per-fingerprint statement statistics, keyed by query id (owned by `SqlEngineState`).
+pub(super) struct StatementStats {
+    capacity: usize,
+    entries: Mutex<HashMap<u64, StatementEntry>>,
+}
+
+/// One fingerprint of the recorded workload (see `index_advisor`).
+pub(super) struct WorkloadStatement {
+    /// Fingerprint text (literals shown as `?`)
+    pub(super) query: String,
+    pub(super) calls: u64,
+    pub(super) total: Duration,
+}
+
+/// Clock and page cache counters taken when a statement starts.
+pub(super) struct StatementStart {
+    started: Instant,
+    lookups: (u64, u64),
+}
+
+impl StatementStart {
+    pub(super) fn now() -> Self {
+        Self {
+            started: Instant::now(),
+            lookups: thread_page_cache_lookups(),
+        }
+    }
+}
+
+impl Default for StatementStats {
+    fn default() -> Self {
+        Self::with_capacity(STATEMENT_STATS_CAPACITY)
+    }
+}
+
+impl StatementStats {
+    pub(super) fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Adds a completed call of `sql` that started at `start`.
+    pub(super) fn record(&self, sql: &str, start: &StatementStart, out: &EngineOutput) {
+        let elapsed = start.started.elapsed();
+        let (hits, misses) = thread_page_cache_lookups();
+        let rows = match out {
+            EngineOutput::ResultSet { rows, .. } => rows.len() as u64,
+            EngineOutput::ExecutionOk { rows_affected } => *rows_affected,
+        };
+        let query = fingerprint(sql);
+        let id = query_id(&query);
+        // Statistics are best effort: a poisoned store stops recording instead of failing queries.
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        if entries.len() >= self.capacity && !entries.contains_key(&id) {
+            if let Some(victim) = entries
+                .iter()
+                .min_by_key(|(_, e)| e.calls)
+                .map(|(id, _)| *id)
+            {
+                entries.remove(&victim);
+            }
+        }
+        let entry = entries.entry(id).or_insert_with(|| StatementEntry {
+            query,
+            calls: 0,
+            total: Duration::ZERO,
+            min: Duration::MAX,
+            max: Duration::ZERO,
+            rows: 0,
+            buffer_hits: 0,
+            buffer_misses: 0,
+        });
+        entry.calls += 1;
+        entry.total += elapsed;
+        entry.min = entry.min.min(elapsed);
+        entry.max = entry.max.max(elapsed);
+        entry.rows += rows;
+        entry.buffer_hits += hits.saturating_sub(start.lookups.0);
+        entry.buffer_misses += misses.saturating_sub(start.lookups.1);
+    }
+
+    /// Drops every entry; returns how many there were.
+    pub(super) fn reset(&self) -> usize {
+        self.entries.lock().map_or(0, |mut e| {
+            let n = e.len();
+            e.clear();
+            n
+        })
+    }
+
+    /// Recorded fingerprints, most called first.
+    pub(super) fn workload(&self) -> Vec<WorkloadStatement> {
+        let mut out: Vec<WorkloadStatement> = self
+            .entries
+            .lock()
+            .map(|e| {
+                e.values()
+                    .map(|e| WorkloadStatement {
+                        query: e.query.clone(),
+                        calls: e.calls,
+                        total: e.total,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        out.sort_by(|a, b| b.calls.cmp(&a.calls).then_with(|| a.query.cmp(&b.query)));
+        out
+    }
+
+    /// Rows of the `rustdb_stat_statements` view, by total time, highest first.
+    pub(super) fn rows(&self) -> Vec<Row> {
+        let mut entries: Vec<(u64, StatementEntry)> = self
+            .entries
+            .lock()
+            .map(|e| e.iter().map(|(id, e)| (*id, e.clone())).collect())
+            .unwrap_or_default();
+        entries.sort_by(|a, b| b.1.total.cmp(&a.1.total));
+
+        let text = |s: &str| ColumnValue::new(DataType::Varchar(format!("'{s}'")));
+        let int = |n: u64| ColumnValue::new(DataType::BigInt(n as i64));
+        let ms = |d: Duration| ColumnValue::new(DataType::Double(d.as_secs_f64() * 1000.0));
+        entries
+            .into_iter()
+            .map(|(id, e)| {
+                let mut row = Row::new();
+                row.set_value("query_id", text(&format!("{id:016x}")));
+                row.set_value("query", text(&e.query));
+                row.set_value("calls", int(e.calls));
+                row.set_value("total_time_ms", ms(e.total));
+                row.set_value("mean_time_ms", ms(e.total / e.calls.max(1) as u32));
+                row.set_value("min_time_ms", ms(e.min));
+                row.set_value("max_time_ms", ms(e.max));
+                row.set_value("rows", int(e.rows));
+                row.set_value("buffer_hits", int(e.buffer_hits));
+                row.set_value("buffer_misses", int(e.buffer_misses));
+                row
+            })
+            .collect()
+    }
+}
+
+/// Runs `SELECT rustdb_stat_statements_reset()`; `None` for any other statement.
+pub(super) fn execute_reset(
+    state: &SqlEngineState,
+    sel: &SelectStatement,
+) -> Option<Result<EngineOutput, EngineError>> {
+    let [SelectItem::Expression {
+        expr: Expression::Function { name, args },
+        ..
+    }] = sel.select_list.as_slice()
+    else {
+        return None;
+    };
+    if sel.from.is_some() || !args.is_empty() || !name.eq_ignore_ascii_case(RESET_FUNCTION) {
+        return None;
+    }
+    let removed = state.statement_stats.reset();
+    Some(Ok(EngineOutput::ExecutionOk {
+        rows_affected: removed as u64,
+    }))
+}
+
+/// `sql` with whitespace collapsed and literals replaced by `?` (quoted identifiers are kept).
+pub(super) fn fingerprint(sql: &str) -> String {
+    let sql = sql.trim().trim_end_matches(';').trim_end();
+    let mut out = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    let mut pending_space = false;
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            pending_space = true;
+            continue;
+        }
+        if pending_space && !out.is_empty() {
+            out.push(' ');
+        }
+        pending_space = false;
+        match c {
+            '\'' => {
+                // `''` inside a string is an escaped quote.
+                while let Some(c) = chars.next() {
+                    if c == '\'' {
+                        if chars.peek() == Some(&'\'') {
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                out.push('?');
+            }
+            '"' | '`' => {
+                out.push(c);
+                for q in chars.by_ref() {
+                    out.push(q);
+                    if q == c {
+                        break;
+                    }
+                }
+            }
+            c if c.is_ascii_digit()
+                && !out
+                    .chars()
+                    .next_back()
+                    .is_some_and(|p| p.is_alphanumeric() || p == '_') =>
+            {
+                while chars
+                    .peek()
+                    .is_some_and(|n| n.is_ascii_alphanumeric() || *n == '.')
+                {
+                    chars.next();
+                }
+                out.push('?');
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Stable 64-bit id of a fingerprint (FNV-1a).
+fn query_id(fingerprint: &str) -> u64 {
+    fingerprint.bytes().fold(0xcbf2_9ce4_8422_2325, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ok(rows_affected: u64) -> EngineOutput {
+        EngineOutput::ExecutionOk { rows_affected }
+    }
+
+    #[test]
+    fn fingerprint_replaces_literals() {
+        assert_eq!(
+            fingerprint("SELECT a1,  b FROM t2\n WHERE id = 42 AND name = 'it''s' AND x > 1.5e3;"),
+            "SELECT a1, b FROM t2 WHERE id = ? AND name = ? AND x > ?"
+        );
+        assert_eq!(
+            fingerprint("INSERT INTO \"t 1\" VALUES (1, 'a')"),
+            "INSERT INTO \"t 1\" VALUES (?, ?)"
+        );
+    }
+
+    #[test]
+    fn store_groups_calls_and_evicts_least_called() {
+        let stats = StatementStats::with_capacity(2);
+        for id in 0..3 {
+            let start = StatementStart::now();
+            stats.record(
+                &format!("UPDATE t SET v = 0 WHERE id = {id}"),
+                &start,
+                &ok(1),
+            );
+        }
+        let start = StatementStart::now();
+        stats.record("DELETE FROM t", &start, &ok(5));
+        let start = StatementStart::now();
+        stats.record("SELECT 1", &start, &ok(0));
+
+        let rows = stats.rows();
+        assert_eq!(rows.len(), 2);
+        let update = rows
+            .iter()
+            .find(|r| {
+                r.get_value("query").map(|v| format!("{:?}", v.data_type))
+                    == Some("Varchar(\"'UPDATE t SET v = ? WHERE id = ?'\")".to_string())
+            })
+            .expect("most called statement kept");
+        assert_eq!(
+            update.get_value("calls").map(|v| v.data_type.clone()),
+            Some(DataType::BigInt(3))
+        );
+        assert_eq!(
+            update.get_value("rows").map(|v| v.data_type.clone()),
+            Some(DataType::BigInt(3))
+        );
+
+        assert_eq!(stats.reset(), 2);
+        assert!(stats.rows().is_empty());
+    }
+}
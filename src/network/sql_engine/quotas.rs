@@ -0,0 +1,220 @@
+//! Per-role resource quotas and the `rustdb_stat_quotas` view.
+//!
+//! `quotas.queries_per_second`, `quotas.max_rows_read` and `quotas.max_temp_bytes` take
+//! `role=limit` pairs like `network.max_connections_per_role` (`reporting=50, etl=5`). Roles not
+//! listed, and sessions without a role, are not limited; the role is the one the session holds
+//! (`SET role` or the authenticated user, see `roles`).
+//!
+//! - **Rate:** all sessions of a role share a token bucket refilled at its rate and holding up to
+//!   one second of statements. A statement finding it empty fails with `QUOTA_EXCEEDED` before
+//!   it runs, so the client may retry it later.
+//! - **Rows read and temp space:** each statement of the role runs under the executor's resource
+//!   governor (see [`crate::executor::governor`]), which stops its plans once they read more rows
+//!   from tables and indexes, or buffer more bytes for sorts, grouping and joins, than the role
+//!   may. The statement fails with `QUOTA_EXCEEDED` and, like any failed statement, changes
+//!   nothing on its own.
+//!
+//! `rustdb_stat_quotas` shows each limited role's quotas and how many statements each refused.
+
+use super::{lock_poisoned_engine, EngineError, EngineOutput, RoleSlot, SqlEngineState};
+use crate::common::config::{parse_role_limits, ConfigError, QuotaConfig};
+use crate::common::types::{ColumnValue, DataType, Row};
+use crate::executor::{governor_scope, GovernorScope, LimitExceeded, QueryLimits};
+use crate::network::engine::{engine_error_code, SessionContext};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Instant;
+
+/// Quotas of one engine.
+#[derive(Default)]
+pub(super) struct RoleQuotas {
+    /// Checked before anything else, so engines without quotas pay one load per statement.
+    enabled: AtomicBool,
+    policy: RwLock<Arc<QuotaPolicy>>,
+    usage: Mutex<HashMap<String, RoleUsage>>,
+}
+
+#[derive(Default)]
+struct QuotaPolicy {
+    queries_per_second: BTreeMap<String, usize>,
+    max_rows_read: BTreeMap<String, usize>,
+    max_temp_bytes: BTreeMap<String, usize>,
+}
+
+#[derive(Default)]
+struct RoleUsage {
+    /// Statements the bucket holds, as of the instant (none until the role's first statement).
+    bucket: Option<(f64, Instant)>,
+    throttled: u64,
+    rows_read_exceeded: u64,
+    temp_bytes_exceeded: u64,
+}
+
+/// Governor scope of a statement run under the quotas of `role`.
+pub(super) struct QuotaScope {
+    role: String,
+    governor: GovernorScope,
+}
+
+/// Applies the `quotas.*` parameters.
+pub(super) fn configure(quotas: &RoleQuotas, config: &QuotaConfig) -> Result<(), ConfigError> {
+    config.validate()?;
+    // Validated just above.
+    let parse = |value: &str| parse_role_limits(value).unwrap_or_default();
+    let policy = QuotaPolicy {
+        queries_per_second: parse(&config.queries_per_second),
+        max_rows_read: parse(&config.max_rows_read),
+        max_temp_bytes: parse(&config.max_temp_bytes),
+    };
+    let enabled = !(policy.queries_per_second.is_empty()
+        && policy.max_rows_read.is_empty()
+        && policy.max_temp_bytes.is_empty());
+    // A poisoned policy is replaced whole, so its state does not matter.
+    *quotas.policy.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(policy);
+    quotas.enabled.store(enabled, Ordering::Release);
+    Ok(())
+}
+
+/// Admits a statement of `ctx` under its role's rate, and scopes its per-statement limits.
+pub(super) fn admit(
+    quotas: &RoleQuotas,
+    ctx: &SessionContext,
+) -> Result<Option<QuotaScope>, EngineError> {
+    if !quotas.enabled.load(Ordering::Acquire) {
+        return Ok(None);
+    }
+    let Some(role) = ctx.role.as_ref().map(RoleSlot::role) else {
+        return Ok(None);
+    };
+    let policy = quotas
+        .policy
+        .read()
+        .map_err(|_| lock_poisoned_engine())?
+        .clone();
+    if let Some(&rate) = policy.queries_per_second.get(role) {
+        let mut usage = quotas.usage.lock().map_err(|_| lock_poisoned_engine())?;
+        let role_usage = usage.entry(role.to_string()).or_default();
+        if !take_token(&mut role_usage.bucket, rate as f64) {
+            role_usage.throttled += 1;
+            return Err(EngineError::new(
+                engine_error_code::QUOTA_EXCEEDED,
+                format!(
+                    "role \"{role}\" may run {rate} statements per second \
+                     (quotas.queries_per_second); retry later"
+                ),
+            ));
+        }
+    }
+    let limit = |limits: &BTreeMap<String, usize>| limits.get(role).map(|&n| n as u64);
+    let limits = QueryLimits {
+        max_rows_read: limit(&policy.max_rows_read),
+        max_temp_bytes: limit(&policy.max_temp_bytes),
+    };
+    Ok(limits.is_limited().then(|| QuotaScope {
+        role: role.to_string(),
+        governor: governor_scope(limits),
+    }))
+}
+
+/// Takes a statement from a token bucket refilled at `rate` per second and holding up to one
+/// second of statements (full until first used); false when it is empty.
+pub(super) fn take_token(bucket: &mut Option<(f64, Instant)>, rate: f64) -> bool {
+    let now = Instant::now();
+    let tokens = match *bucket {
+        Some((tokens, at)) => (tokens + now.duration_since(at).as_secs_f64() * rate).min(rate),
+        None => rate,
+    };
+    let admitted = tokens >= 1.0;
+    *bucket = Some((if admitted { tokens - 1.0 } else { tokens }, now));
+    admitted
+}
+
+/// Ends the statement's scope: a statement stopped by a quota fails with `QUOTA_EXCEEDED`,
+/// naming the role and the quota, and is counted.
+pub(super) fn finish(
+    quotas: &RoleQuotas,
+    scope: QuotaScope,
+    out: Result<EngineOutput, EngineError>,
+) -> Result<EngineOutput, EngineError> {
+    let Some(exceeded) = scope.governor.exceeded() else {
+        return out;
+    };
+    drop(scope.governor);
+    if let Ok(mut usage) = quotas.usage.lock() {
+        let role_usage = usage.entry(scope.role.clone()).or_default();
+        match exceeded {
+            LimitExceeded::RowsRead(_) => role_usage.rows_read_exceeded += 1,
+            LimitExceeded::TempBytes(_) => role_usage.temp_bytes_exceeded += 1,
+        }
+    }
+    let role = scope.role;
+    Err(EngineError::new(
+        engine_error_code::QUOTA_EXCEEDED,
+        match exceeded {
+            LimitExceeded::RowsRead(max) => format!(
+                "statement read more than {max} rows, the limit of role \"{role}\" \
+                 (quotas.max_rows_read)"
+            ),
+            LimitExceeded::TempBytes(max) => format!(
+                "statement needs more than {max} bytes of temp space, the limit of role \
+                 \"{role}\" (quotas.max_temp_bytes)"
+            ),
+        },
+    ))
+}
+
+/// `rustdb_stat_quotas`: per role with quotas (or refused statements), its quotas and the
+/// statements refused by each.
+pub(super) fn quota_rows(state: &SqlEngineState) -> Result<Vec<Row>, EngineError> {
+    let quotas = &state.role_quotas;
+    let policy = quotas
+        .policy
+        .read()
+        .map_err(|_| lock_poisoned_engine())?
+        .clone();
+    let usage = quotas.usage.lock().map_err(|_| lock_poisoned_engine())?;
+    let mut roles: Vec<&String> = usage
+        .iter()
+        .filter(|(_, u)| u.throttled + u.rows_read_exceeded + u.temp_bytes_exceeded > 0)
+        .map(|(role, _)| role)
+        .chain(policy.queries_per_second.keys())
+        .chain(policy.max_rows_read.keys())
+        .chain(policy.max_temp_bytes.keys())
+        .collect();
+    roles.sort();
+    roles.dedup();
+    let big_int = |n: u64| ColumnValue::new(DataType::BigInt(n as i64));
+    let limit = |limits: &BTreeMap<String, usize>, role: &str| {
+        limits
+            .get(role)
+            .map_or_else(ColumnValue::null, |&n| big_int(n as u64))
+    };
+    Ok(roles
+        .into_iter()
+        .map(|role| {
+            let u = usage.get(role);
+            let mut row = Row::new();
+            row.set_value(
+                "role",
+                ColumnValue::new(DataType::Varchar(format!("'{role}'"))),
+            );
+            row.set_value(
+                "queries_per_second",
+                limit(&policy.queries_per_second, role),
+            );
+            row.set_value("max_rows_read", limit(&policy.max_rows_read, role));
+            row.set_value("max_temp_bytes", limit(&policy.max_temp_bytes, role));
+            row.set_value("throttled", big_int(u.map_or(0, |u| u.throttled)));
+            row.set_value(
+                "rows_read_exceeded",
+                big_int(u.map_or(0, |u| u.rows_read_exceeded)),
+            );
+            row.set_value(
+                "temp_bytes_exceeded",
+                big_int(u.map_or(0, |u| u.temp_bytes_exceeded)),
+            );
+            row
+        })
+        .collect())
+}
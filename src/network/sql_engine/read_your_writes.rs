@@ -0,0 +1,132 @@
+//! Read-your-writes on replicas: `SHOW commit_lsn`, `SET read_after_lsn` and
+//! `SET read_after_lsn_timeout`.
+//!
+//! After writing on the primary, a client takes `SHOW commit_lsn` (the primary WAL position of
+//! the session's last commit, DDL included) as a token and passes it to its replica session with
+//! `SET read_after_lsn = <token>`. From then on every statement of that session first waits until
+//! the replica has applied the commit ([`ReplicaStatus::wait_for_applied`]), or fails with
+//! `REPLICA_LAG_TIMEOUT` after `read_after_lsn_timeout` milliseconds. `SET` and `SHOW` do not
+//! wait, so a token can always be replaced or reset to `0`.
+//!
+//! An engine that does not follow a primary has nothing to wait for: it is the primary that
+//! issued the token.
+
+use super::{rows_to_engine_output, EngineError, EngineOutput, SqlEngine, SqlEngineState};
+use crate::common::types::{ColumnValue, DataType, Row};
+use crate::network::engine::{engine_error_code, SessionContext};
+use crate::network::replication::ReplicaStatus;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Session parameter (read-only) holding the token of the session's last commit.
+pub(super) const COMMIT_LSN_PARAMETER: &str = "commit_lsn";
+
+/// Session parameter holding the token statements wait for.
+pub(super) const READ_AFTER_PARAMETER: &str = "read_after_lsn";
+
+/// Session parameter bounding the wait, in milliseconds.
+pub(super) const READ_AFTER_TIMEOUT_PARAMETER: &str = "read_after_lsn_timeout";
+
+/// Wait bound until the session sets `read_after_lsn_timeout`.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+impl SqlEngine {
+    /// Marks this engine as a replica whose apply loop reports to `status` (see
+    /// [`crate::network::replication::run_replica`]): sessions with `SET read_after_lsn` then
+    /// wait for it. Only the first status is kept.
+    pub fn follow_replica_status(&self, status: Arc<ReplicaStatus>) {
+        let _ = self.state.replica_status.set(status);
+    }
+}
+
+/// `SET read_after_lsn = <lsn>` and `SET read_after_lsn_timeout = <ms>`
+pub(super) fn set_parameter(
+    ctx: &mut SessionContext,
+    name: &str,
+    value: &str,
+) -> Result<EngineOutput, EngineError> {
+    if name.eq_ignore_ascii_case(COMMIT_LSN_PARAMETER) {
+        return Err(EngineError::new(
+            engine_error_code::INVALID_PARAMETER,
+            format!("parameter \"{COMMIT_LSN_PARAMETER}\" cannot be set"),
+        ));
+    }
+    let number = value
+        .trim()
+        .trim_matches('\'')
+        .parse::<u64>()
+        .map_err(|_| {
+            EngineError::new(
+                engine_error_code::INVALID_PARAMETER,
+                format!("invalid value for parameter \"{name}\": {value} (expected a number)"),
+            )
+        })?;
+    if name.eq_ignore_ascii_case(READ_AFTER_PARAMETER) {
+        ctx.read_after_lsn = number;
+    } else {
+        ctx.read_after_lsn_timeout = Some(Duration::from_millis(number));
+    }
+    Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+}
+
+/// `SHOW commit_lsn`, `SHOW read_after_lsn` and `SHOW read_after_lsn_timeout`
+pub(super) fn show_parameter(
+    ctx: &SessionContext,
+    name: &str,
+) -> Result<EngineOutput, EngineError> {
+    let (key, setting) = if name.eq_ignore_ascii_case(COMMIT_LSN_PARAMETER) {
+        (COMMIT_LSN_PARAMETER, ctx.last_commit_lsn)
+    } else if name.eq_ignore_ascii_case(READ_AFTER_PARAMETER) {
+        (READ_AFTER_PARAMETER, ctx.read_after_lsn)
+    } else {
+        (
+            READ_AFTER_TIMEOUT_PARAMETER,
+            ctx.read_after_lsn_timeout
+                .unwrap_or(DEFAULT_TIMEOUT)
+                .as_millis() as u64,
+        )
+    };
+    let text = |s: &str| ColumnValue::new(DataType::Varchar(format!("'{s}'")));
+    let mut row = Row::new();
+    row.set_value("name", text(key));
+    row.set_value("setting", text(&setting.to_string()));
+    row.set_value("source", text("session"));
+    rows_to_engine_output(vec![row])
+}
+
+/// Whether `name` is one of this module's session parameters.
+pub(super) fn is_parameter(name: &str) -> bool {
+    [
+        COMMIT_LSN_PARAMETER,
+        READ_AFTER_PARAMETER,
+        READ_AFTER_TIMEOUT_PARAMETER,
+    ]
+    .iter()
+    .any(|p| p.eq_ignore_ascii_case(name))
+}
+
+/// Waits, on a replica, until the commit named by the session's token is applied.
+pub(super) fn wait_for_token(
+    state: &SqlEngineState,
+    ctx: &SessionContext,
+) -> Result<(), EngineError> {
+    let Some(status) = state.replica_status.get() else {
+        return Ok(());
+    };
+    let lsn = ctx.read_after_lsn;
+    if status.applied_lsn() >= lsn {
+        return Ok(());
+    }
+    let timeout = ctx.read_after_lsn_timeout.unwrap_or(DEFAULT_TIMEOUT);
+    if status.wait_for_applied(lsn, timeout) {
+        return Ok(());
+    }
+    Err(EngineError::new(
+        engine_error_code::REPLICA_LAG_TIMEOUT,
+        format!(
+            "replica has applied LSN {} and did not reach read_after_lsn {lsn} within {} ms",
+            status.applied_lsn(),
+            timeout.as_millis()
+        ),
+    ))
+}
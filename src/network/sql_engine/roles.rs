@@ -0,0 +1,191 @@
+//! Session roles: `SET role = '<name>'`, `SHOW role` and the `rustdb_stat_roles` view.
+//!
+//! A session declares the role it works for with `SET role`. `network.max_connections_per_role`
+//! (`reporting=5, etl=2`) caps how many sessions may hold each listed role at once: past the cap
+//! `SET role` fails with `TOO_MANY_CONNECTIONS` and the session keeps its previous role. The
+//! session releases its role on `SET role = none`, on switching roles and when it ends, which is
+//! when the server drops its [`SessionContext`]. Role names are case-insensitive; roles that are
+//! not listed are not limited.
+//!
+//! Sessions of a connection authenticated as a user (see [`crate::network::auth`]) start with
+//! that user's role, counted against the same caps, and cannot `SET role` away from it.
+
+use super::{
+    lock_poisoned_engine, rows_to_engine_output, EngineError, EngineOutput, SqlEngineState,
+};
+use crate::common::config::parse_role_limits;
+use crate::common::types::{ColumnValue, DataType, Row};
+use crate::network::engine::{engine_error_code, SessionContext};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+/// Session parameter holding the session's role.
+pub(super) const ROLE_PARAMETER: &str = "role";
+
+/// Sessions and refusals per role, shared with the [`RoleSlot`]s of the sessions.
+#[derive(Default)]
+pub(super) struct RoleSessions {
+    roles: Arc<Mutex<HashMap<String, RoleCounters>>>,
+}
+
+#[derive(Default)]
+struct RoleCounters {
+    sessions: u64,
+    refused: u64,
+}
+
+/// A session's claim on its role; dropping it (with the session) frees the slot.
+pub(crate) struct RoleSlot {
+    role: String,
+    /// Assigned by authentication rather than `SET role`.
+    authenticated: bool,
+    roles: Arc<Mutex<HashMap<String, RoleCounters>>>,
+}
+
+impl RoleSlot {
+    pub(crate) fn role(&self) -> &str {
+        &self.role
+    }
+}
+
+impl std::fmt::Debug for RoleSlot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.role)
+    }
+}
+
+impl Drop for RoleSlot {
+    fn drop(&mut self) {
+        // A poisoned map only loses the count: the slot must not panic while a session unwinds.
+        if let Ok(mut roles) = self.roles.lock() {
+            if let Some(c) = roles.get_mut(&self.role) {
+                c.sessions = c.sessions.saturating_sub(1);
+            }
+        }
+    }
+}
+
+/// Per-role caps currently configured.
+fn role_limits(state: &SqlEngineState) -> Result<BTreeMap<String, usize>, EngineError> {
+    let settings = state.settings.read().map_err(|_| lock_poisoned_engine())?;
+    parse_role_limits(&settings.config().network.max_connections_per_role).map_err(|m| {
+        EngineError::new(
+            engine_error_code::INVALID_PARAMETER,
+            format!("network.max_connections_per_role: {m}"),
+        )
+    })
+}
+
+/// `SET role = <name> | none`
+pub(super) fn set_role(
+    state: &SqlEngineState,
+    ctx: &mut SessionContext,
+    value: &str,
+) -> Result<EngineOutput, EngineError> {
+    let role = value.trim().to_ascii_lowercase();
+    if ctx.role.as_ref().is_some_and(|slot| slot.role == role) {
+        return Ok(EngineOutput::ExecutionOk { rows_affected: 0 });
+    }
+    if let Some(slot) = ctx.role.as_ref().filter(|slot| slot.authenticated) {
+        return Err(EngineError::new(
+            engine_error_code::AUTHENTICATION_FAILED,
+            format!(
+                "session is authenticated as \"{}\" and cannot change its role",
+                slot.role
+            ),
+        ));
+    }
+    if role.is_empty() || role == "none" {
+        ctx.role = None;
+        return Ok(EngineOutput::ExecutionOk { rows_affected: 0 });
+    }
+    claim(state, ctx, role, false)?;
+    Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+}
+
+/// Role of a new session of a connection authenticated as `user`.
+pub(super) fn assign_role(
+    state: &SqlEngineState,
+    ctx: &mut SessionContext,
+    user: &str,
+) -> Result<(), EngineError> {
+    claim(state, ctx, user.trim().to_ascii_lowercase(), true)
+}
+
+/// Takes a slot of `role` for the session, within the role's cap.
+fn claim(
+    state: &SqlEngineState,
+    ctx: &mut SessionContext,
+    role: String,
+    authenticated: bool,
+) -> Result<(), EngineError> {
+    let limit = role_limits(state)?.get(&role).copied();
+    let shared = &state.role_sessions.roles;
+    let mut roles = shared.lock().map_err(|_| lock_poisoned_engine())?;
+    let counters = roles.entry(role.clone()).or_default();
+    if let Some(max) = limit {
+        if counters.sessions >= max as u64 {
+            counters.refused += 1;
+            return Err(EngineError::new(
+                engine_error_code::TOO_MANY_CONNECTIONS,
+                format!("too many connections for role \"{role}\" (max {max})"),
+            ));
+        }
+    }
+    counters.sessions += 1;
+    drop(roles);
+    // The previous slot, if any, is released only now that the new one is held.
+    ctx.role = Some(RoleSlot {
+        role,
+        authenticated,
+        roles: Arc::clone(shared),
+    });
+    Ok(())
+}
+
+/// `SHOW role`
+pub(super) fn show_role(ctx: &SessionContext) -> Result<EngineOutput, EngineError> {
+    let text = |s: &str| ColumnValue::new(DataType::Varchar(format!("'{s}'")));
+    let mut row = Row::new();
+    row.set_value("name", text(ROLE_PARAMETER));
+    row.set_value(
+        "setting",
+        text(ctx.role.as_ref().map_or("none", RoleSlot::role)),
+    );
+    row.set_value("source", text("session"));
+    rows_to_engine_output(vec![row])
+}
+
+/// `rustdb_stat_roles`: sessions, cap and refused `SET role` per role seen or configured.
+pub(super) fn role_rows(state: &SqlEngineState) -> Result<Vec<Row>, EngineError> {
+    let limits = role_limits(state)?;
+    let roles = state
+        .role_sessions
+        .roles
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?;
+    let mut names: Vec<&String> = roles.keys().chain(limits.keys()).collect();
+    names.sort();
+    names.dedup();
+    let big_int = |n: u64| ColumnValue::new(DataType::BigInt(n as i64));
+    Ok(names
+        .into_iter()
+        .map(|name| {
+            let counters = roles.get(name);
+            let mut row = Row::new();
+            row.set_value(
+                "role",
+                ColumnValue::new(DataType::Varchar(format!("'{name}'"))),
+            );
+            row.set_value("sessions", big_int(counters.map_or(0, |c| c.sessions)));
+            row.set_value(
+                "max_connections",
+                limits
+                    .get(name)
+                    .map_or_else(ColumnValue::null, |&max| big_int(max as u64)),
+            );
+            row.set_value("refused", big_int(counters.map_or(0, |c| c.refused)));
+            row
+        })
+        .collect())
+}
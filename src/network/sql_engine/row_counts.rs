@@ -0,0 +1,256 @@
+//! Row counts per table, answering `SELECT COUNT(*) FROM t` without a heap scan.
+//!
+//! The engine keeps the committed row count of each table once it has counted it: each
+//! transaction's inserts and deletes (read from its undo log) are merged into the counts of the
+//! tables it wrote when it commits. An unqualified `SELECT COUNT(*) FROM t` (no `WHERE`,
+//! grouping, joins or ordering) then returns that count plus the session's own uncommitted
+//! changes to `t`. It is exact while no other transaction writes `t`; during concurrent writes
+//! it leaves out their uncommitted rows, which a heap scan would count.
+//!
+//! A table's count is unknown after open and after `ALTER TABLE`: its first `COUNT(*)` counts
+//! the entries of one of its full (not partial) secondary indexes (an index-only scan) or,
+//! without one, its heap rows, and keeps the result if no transaction wrote the table meanwhile.
+
+use super::{
+    acquire_table_storage_read_lock, lock_poisoned_engine, maintenance, map_db_err,
+    rows_to_engine_output, table_storage_lock_arc, EngineError, EngineOutput, SessionContext,
+    SqlEngineState,
+};
+use crate::common::types::{ColumnValue, DataType, Row};
+use crate::network::engine::UndoEntry;
+use crate::parser::ast::{Expression, SelectItem, SelectStatement, TableReference};
+use crate::storage::index::Index;
+use crate::storage::index_registry::{SecondaryIndex, INDEX_KEY_MAX};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+/// Row counts of one engine's tables.
+#[derive(Default)]
+pub(super) struct RowCounts(Mutex<HashMap<String, TableRows>>);
+
+#[derive(Default)]
+struct TableRows {
+    /// Committed rows, once counted.
+    rows: Option<u64>,
+    /// Open transactions that wrote the table.
+    writers: usize,
+    /// Bumped by every write and invalidation, so a count is only kept if nothing changed the
+    /// table while it was taken.
+    version: u64,
+}
+
+/// A transaction starts writing `table` (before its first row change).
+pub(super) fn begin_write(counts: &RowCounts, table: &str) {
+    if let Ok(mut tables) = counts.0.lock() {
+        let t = tables.entry(table.to_string()).or_default();
+        t.writers += 1;
+        t.version += 1;
+    }
+}
+
+/// A transaction that wrote `written` commits: merges the row changes in its undo log.
+pub(super) fn commit(counts: &RowCounts, written: &HashSet<String>, undo: &[UndoEntry]) {
+    let mut deltas: HashMap<&str, i64> = HashMap::new();
+    for entry in undo {
+        match entry {
+            UndoEntry::Insert { table, .. } => *deltas.entry(table).or_default() += 1,
+            UndoEntry::Delete { table, .. } => *deltas.entry(table).or_default() -= 1,
+            UndoEntry::Update { .. } => {}
+        }
+    }
+    let Ok(mut tables) = counts.0.lock() else {
+        return;
+    };
+    for (table, delta) in deltas {
+        let t = tables.entry(table.to_string()).or_default();
+        t.rows = t.rows.map(|rows| rows.saturating_add_signed(delta));
+        t.version += 1;
+    }
+    finish_writes(&mut tables, written);
+}
+
+/// A transaction that wrote `written` rolled back (after its undo log was applied).
+pub(super) fn abort(counts: &RowCounts, written: &HashSet<String>) {
+    if let Ok(mut tables) = counts.0.lock() {
+        finish_writes(&mut tables, written);
+    }
+}
+
+fn finish_writes(tables: &mut HashMap<String, TableRows>, written: &HashSet<String>) {
+    for table in written {
+        if let Some(t) = tables.get_mut(table) {
+            t.writers = t.writers.saturating_sub(1);
+            t.version += 1;
+        }
+    }
+}
+
+/// Whether a transaction that wrote `table` is still open.
+pub(super) fn has_writers(counts: &RowCounts, table: &str) -> bool {
+    counts.0.lock().map_or(true, |tables| {
+        tables.get(table).is_some_and(|t| t.writers > 0)
+    })
+}
+
+/// Forgets the count of `table` (dropped, or rewritten by `ALTER TABLE`).
+pub(super) fn invalidate(counts: &RowCounts, table: &str) {
+    if let Ok(mut tables) = counts.0.lock() {
+        if let Some(t) = tables.get_mut(table) {
+            t.rows = None;
+            t.version += 1;
+        }
+    }
+}
+
+/// Answers `sel` when it is an unqualified `SELECT COUNT(*) FROM <table>` of a stored table.
+pub(super) fn execute_count(
+    state: &SqlEngineState,
+    ctx: &SessionContext,
+    sel: &SelectStatement,
+) -> Option<Result<EngineOutput, EngineError>> {
+    let (table, column) = count_star(sel)?;
+    let stored = match state.catalog.lock() {
+        Ok(cat) => cat.schema(table).map(|s| s.foreign.is_none()),
+        Err(_) => return Some(Err(lock_poisoned_engine())),
+    };
+    if stored != Some(true) {
+        return None;
+    }
+    Some(count_rows(state, ctx, table).and_then(|n| {
+        let mut row = Row::new();
+        row.set_value(&column, ColumnValue::new(DataType::BigInt(n as i64)));
+        rows_to_engine_output(vec![row])
+    }))
+}
+
+/// The table and result column of `SELECT COUNT(*) [AS column] FROM table`.
+fn count_star(sel: &SelectStatement) -> Option<(&str, String)> {
+    let from = sel.from.as_ref()?;
+    let TableReference::Table {
+        name, as_of: None, ..
+    } = &from.table
+    else {
+        return None;
+    };
+    let [SelectItem::Expression {
+        expr: Expression::Function {
+            name: function,
+            args,
+        },
+        alias,
+    }] = sel.select_list.as_slice()
+    else {
+        return None;
+    };
+    let unqualified = from.joins.is_empty()
+        && sel.where_clause.is_none()
+        && sel.group_by.is_empty()
+        && sel.having.is_none()
+        && sel.order_by.is_empty()
+        && sel.limit != Some(0)
+        && sel.offset.unwrap_or(0) == 0;
+    let star = matches!(args.as_slice(), [Expression::Identifier(a)] if a == "*");
+    (unqualified && star && function.eq_ignore_ascii_case("COUNT")).then(|| {
+        (
+            name.as_str(),
+            alias.clone().unwrap_or_else(|| "COUNT(*)".into()),
+        )
+    })
+}
+
+fn count_rows(
+    state: &SqlEngineState,
+    ctx: &SessionContext,
+    table: &str,
+) -> Result<u64, EngineError> {
+    let own = own_delta(ctx, table);
+    let counts = &state.row_counts;
+    let (writers, version) = {
+        let tables = counts.0.lock().map_err(|_| lock_poisoned_engine())?;
+        match tables.get(table) {
+            Some(TableRows {
+                rows: Some(rows), ..
+            }) => return Ok(rows.saturating_add_signed(own.unwrap_or(0))),
+            Some(t) => (t.writers, t.version),
+            None => (0, 0),
+        }
+    };
+
+    let lock = table_storage_lock_arc(state, table)?;
+    let _read = acquire_table_storage_read_lock(&lock, table)?;
+    // Indexes lag behind the heap while a transaction writes the table (its inserts reach
+    // them at commit).
+    let indexed = if writers == 0 {
+        index_entries(state, table)?
+    } else {
+        None
+    };
+    let n = match indexed {
+        Some(n) => n,
+        None => heap_rows(state, table)?,
+    };
+    if writers == 0 {
+        let mut tables = counts.0.lock().map_err(|_| lock_poisoned_engine())?;
+        let t = tables.entry(table.to_string()).or_default();
+        if t.version == version && t.writers == 0 {
+            t.rows = Some(n);
+        }
+    }
+    Ok(n)
+}
+
+/// Rows the session's transaction inserted into `table` minus those it deleted, if it wrote it.
+fn own_delta(ctx: &SessionContext, table: &str) -> Option<i64> {
+    let tx = ctx.transaction.as_ref()?;
+    tx.touched_tables.contains(table).then(|| {
+        tx.undo
+            .iter()
+            .map(|entry| match entry {
+                UndoEntry::Insert { table: t, .. } if t == table => 1,
+                UndoEntry::Delete { table: t, .. } if t == table => -1,
+                _ => 0,
+            })
+            .sum()
+    })
+}
+
+/// Entries of the first full (not partial) B-tree index of `table` (one per row), if it has
+/// one. A hash index is not used: reading it means visiting every bucket, no cheaper than the
+/// heap.
+fn index_entries(state: &SqlEngineState, table: &str) -> Result<Option<u64>, EngineError> {
+    let registry = state
+        .index_registry
+        .read()
+        .map_err(|_| lock_poisoned_engine())?;
+    for (name, _) in registry.list_indexes_for_table(table) {
+        let Some(entry) = registry.get_index_entry(table, &name) else {
+            continue;
+        };
+        if entry.options.predicate.is_some() {
+            continue;
+        }
+        let index = entry.index.lock().map_err(|_| lock_poisoned_engine())?;
+        if matches!(*index, SecondaryIndex::Hash(_)) {
+            continue;
+        }
+        let entries = index
+            .range_search(&String::new(), &INDEX_KEY_MAX.to_string())
+            .map_err(map_db_err)?
+            .into_iter()
+            .map(|(_, ids)| ids.len() as u64)
+            .sum();
+        return Ok(Some(entries));
+    }
+    Ok(None)
+}
+
+/// Readable rows stored in `table`.
+fn heap_rows(state: &SqlEngineState, table: &str) -> Result<u64, EngineError> {
+    let mut rows = 0;
+    maintenance::scan(state, table, |_, tuple| {
+        if tuple.is_ok_and(|t| !t.is_deleted) {
+            rows += 1;
+        }
+    })?;
+    Ok(rows)
+}
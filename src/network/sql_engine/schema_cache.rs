@@ -0,0 +1,241 @@
+//! Lazy catalog loading: tables whose constraint maps and indexes load on first use, and the
+//! `rustdb_stat_schema_cache` view.
+//!
+//! Opening a data directory reads the catalog, but registering every stored row in the
+//! PRIMARY KEY / UNIQUE / FOREIGN KEY maps and rebuilding every secondary index reads every
+//! table. With [`super::SqlEngineConfig::lazy_catalog`] on, open leaves the tables needing it
+//! *cold* instead: the first statement naming a cold table loads it, together with the tables
+//! linked to it by foreign keys (their maps count each other's rows), before it runs. That
+//! statement waits for the load; the wait is a *stall*.
+//!
+//! [`super::SqlEngine::preload_schema`] (and `Database::preload_schema`) loads tables up front
+//! for services that cannot afford a stall on their first queries. Statements that may touch
+//! any table (`VACUUM`, `CHECK TABLE` and `ANALYZE` without a table, `FORGET`, `DROP TENANT`,
+//! `SHOW TABLE STATUS` and the size functions, `DROP INDEX` without a table, the native TPC-C
+//! transactions) load every cold table first.
+//!
+//! `rustdb_stat_schema_cache` shows how many tables are still cold, how many loads stalled a
+//! statement and for how long, and how many tables were preloaded.
+
+use super::{
+    collect_statement_tables, lock_poisoned_engine, rebuild_secondary_indexes_from_catalog,
+    refresh_index_columns_cache_for_table, register_table_rows, table_registration_order,
+    EngineError, SqlEngineState,
+};
+use crate::catalog::schema::SchemaManager;
+use crate::common::types::{ColumnValue, DataType, Row};
+use crate::parser::ast::{DropIndexStatement, SqlStatement};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Cold tables of one engine and the statistics of their loads.
+#[derive(Default)]
+pub(super) struct SchemaCache {
+    /// Checked before anything else, so engines with every table loaded pay one load per
+    /// statement.
+    any_cold: AtomicBool,
+    /// Held while tables load, so statements needing them wait for the load.
+    cold: Mutex<HashSet<String>>,
+    stalls: AtomicU64,
+    stalled_tables: AtomicU64,
+    stall_us: AtomicU64,
+    max_stall_us: AtomicU64,
+    preloaded_tables: AtomicU64,
+}
+
+/// Marks the catalog's tables with keys, foreign keys or secondary indexes cold (on open,
+/// before the constraint runtime is rebuilt); returns how many.
+pub(super) fn defer_all(state: &SqlEngineState) -> Result<usize, EngineError> {
+    let cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
+    let tables: HashSet<String> = cat
+        .table_names()
+        .into_iter()
+        .filter(|t| {
+            cat.schema(t).is_some_and(|s| {
+                s.foreign.is_none()
+                    && (s.primary_key.is_some()
+                        || !s.unique_constraints.is_empty()
+                        || !s.foreign_keys.is_empty()
+                        || !s.secondary_indexes.is_empty())
+            })
+        })
+        .collect();
+    let n = tables.len();
+    let cache = &state.schema_cache;
+    *cache.cold.lock().map_err(|_| lock_poisoned_engine())? = tables;
+    cache.any_cold.store(n > 0, Ordering::Release);
+    Ok(n)
+}
+
+/// Whether `table` has not been loaded yet.
+pub(super) fn is_cold(cache: &SchemaCache, table: &str) -> bool {
+    cache.any_cold.load(Ordering::Acquire)
+        && cache.cold.lock().map_or(true, |cold| cold.contains(table))
+}
+
+/// Forgets `table` (dropped, or about to be re-created).
+pub(super) fn forget(cache: &SchemaCache, table: &str) {
+    if !cache.any_cold.load(Ordering::Acquire) {
+        return;
+    }
+    if let Ok(mut cold) = cache.cold.lock() {
+        cold.remove(table);
+        cache.any_cold.store(!cold.is_empty(), Ordering::Release);
+    }
+}
+
+/// Loads the cold tables `stmt` uses before it runs.
+pub(super) fn load_statement_tables(
+    state: &SqlEngineState,
+    stmt: &SqlStatement,
+) -> Result<(), EngineError> {
+    if !state.schema_cache.any_cold.load(Ordering::Acquire) {
+        return Ok(());
+    }
+    match stmt {
+        SqlStatement::Vacuum(None)
+        | SqlStatement::CheckTable(None)
+        | SqlStatement::Analyze(None)
+        | SqlStatement::Forget(_)
+        | SqlStatement::ShowTableStatus
+        | SqlStatement::DropIndex(DropIndexStatement {
+            table_name: None, ..
+        })
+        | SqlStatement::DropTenant { .. } => load_all(state),
+        _ => {
+            let mut tables = HashSet::new();
+            collect_statement_tables(&mut tables, stmt);
+            load(state, tables, false).map(drop)
+        }
+    }
+}
+
+/// Loads every cold table.
+pub(super) fn load_all(state: &SqlEngineState) -> Result<(), EngineError> {
+    if !state.schema_cache.any_cold.load(Ordering::Acquire) {
+        return Ok(());
+    }
+    let tables = state
+        .schema_cache
+        .cold
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?
+        .clone();
+    load(state, tables, false).map(drop)
+}
+
+/// Loads `tables` ahead of their first use; returns how many tables were loaded.
+pub(super) fn preload(state: &SqlEngineState, tables: &[&str]) -> Result<usize, EngineError> {
+    load(state, tables.iter().map(|t| t.to_string()).collect(), true)
+}
+
+/// Loads the cold tables among `tables` and those linked to them by foreign keys.
+fn load(
+    state: &SqlEngineState,
+    tables: HashSet<String>,
+    preload: bool,
+) -> Result<usize, EngineError> {
+    let cache = &state.schema_cache;
+    if !cache.any_cold.load(Ordering::Acquire) {
+        return Ok(0);
+    }
+    let started = Instant::now();
+    let mut cold = cache.cold.lock().map_err(|_| lock_poisoned_engine())?;
+    let cat = state
+        .catalog
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?
+        .clone();
+    let linked = linked_tables(&cat, &cold, tables);
+    if linked.is_empty() {
+        return Ok(0);
+    }
+    {
+        let mut rt = state
+            .constraint_runtime
+            .lock()
+            .map_err(|_| lock_poisoned_engine())?;
+        // Nothing is registered for a cold table, unless an earlier load of it failed midway.
+        for t in &linked {
+            rt.clear_table_maps(t);
+            rt.clear_fk_refs_to_parent(t);
+        }
+    }
+    for t in table_registration_order(&cat)? {
+        if linked.contains(&t) {
+            register_table_rows(state, &cat, &t)?;
+        }
+    }
+    rebuild_secondary_indexes_from_catalog(state, Some(&linked))?;
+    for t in &linked {
+        refresh_index_columns_cache_for_table(state, t);
+        cold.remove(t);
+    }
+    cache.any_cold.store(!cold.is_empty(), Ordering::Release);
+    drop(cold);
+
+    let n = linked.len() as u64;
+    let elapsed_us = started.elapsed().as_micros() as u64;
+    if preload {
+        cache.preloaded_tables.fetch_add(n, Ordering::Relaxed);
+    } else {
+        cache.stalls.fetch_add(1, Ordering::Relaxed);
+        cache.stalled_tables.fetch_add(n, Ordering::Relaxed);
+        cache.stall_us.fetch_add(elapsed_us, Ordering::Relaxed);
+        cache.max_stall_us.fetch_max(elapsed_us, Ordering::Relaxed);
+    }
+    let mut names: Vec<&String> = linked.iter().collect();
+    names.sort();
+    tracing::info!(
+        tables = ?names,
+        elapsed_us,
+        preload,
+        "loaded cold tables"
+    );
+    Ok(linked.len())
+}
+
+/// The cold tables among `tables`, with the cold tables linked to them by foreign keys in
+/// either direction.
+fn linked_tables(
+    cat: &SchemaManager,
+    cold: &HashSet<String>,
+    tables: HashSet<String>,
+) -> HashSet<String> {
+    let mut pending: Vec<String> = tables.into_iter().filter(|t| cold.contains(t)).collect();
+    let mut out = HashSet::new();
+    while let Some(t) = pending.pop() {
+        if !out.insert(t.clone()) {
+            continue;
+        }
+        let parents = cat
+            .schema(&t)
+            .into_iter()
+            .flat_map(|s| s.foreign_keys.iter().map(|fk| fk.referenced_table.clone()));
+        pending.extend(
+            parents
+                .chain(cat.tables_with_fk_to(&t))
+                .filter(|n| cold.contains(n) && !out.contains(n)),
+        );
+    }
+    out
+}
+
+/// `rustdb_stat_schema_cache`: one row with the tables still cold, the statements stalled by
+/// loads (and the tables and time those loads took), and the tables preloaded.
+pub(super) fn schema_cache_rows(state: &SqlEngineState) -> Result<Vec<Row>, EngineError> {
+    let cache = &state.schema_cache;
+    let cold = cache.cold.lock().map_err(|_| lock_poisoned_engine())?.len() as u64;
+    let big_int = |n: u64| ColumnValue::new(DataType::BigInt(n as i64));
+    let load = |n: &AtomicU64| big_int(n.load(Ordering::Relaxed));
+    let mut row = Row::new();
+    row.set_value("cold_tables", big_int(cold));
+    row.set_value("stalls", load(&cache.stalls));
+    row.set_value("stalled_tables", load(&cache.stalled_tables));
+    row.set_value("stall_us", load(&cache.stall_us));
+    row.set_value("max_stall_us", load(&cache.max_stall_us));
+    row.set_value("preloaded_tables", load(&cache.preloaded_tables));
+    Ok(vec![row])
+}
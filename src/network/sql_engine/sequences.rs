@@ -0,0 +1,97 @@
+//! Tuple id sequence with WAL-logged high-water marks.
+//!
+//! Every inserted [`crate::storage::tuple::Tuple`] takes its id from one engine-wide sequence.
+//! Before an id beyond the last logged mark is handed out, a new mark [`SEQUENCE_BLOCK`] ids
+//! further is appended to the WAL. The log is written in order, so every row record that reaches
+//! the WAL is preceded by a mark covering its id; on open the sequence resumes above the largest
+//! mark and never reissues an id, including ids consumed by rolled-back statements.
+//!
+//! Explicit transactions reserve [`TRANSACTION_CACHE`] ids at a time (`SqlTransaction::tuple_ids`).
+//! A failed statement does not give its ids back: rows it inserted may still be in the
+//! transaction, so the cache only moves forward and its remainder is dropped when the transaction
+//! ends. Without WAL the sequence starts from `1` on every open.
+
+use super::{lock_poisoned_engine, SqlEngineState};
+use crate::network::engine::{EngineError, SessionContext};
+use crate::network::sql_engine_wal::SqlEngineWal;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Name of the tuple id sequence in WAL high-water records.
+pub(crate) const TUPLE_ID_SEQUENCE: &str = "tuple_id";
+
+/// Ids covered by one WAL high-water mark.
+const SEQUENCE_BLOCK: u64 = 4096;
+
+/// Ids an explicit transaction reserves at once.
+const TRANSACTION_CACHE: u64 = 32;
+
+/// Engine-wide tuple id allocator (owned by `SqlEngineState`).
+pub(crate) struct TupleIdSequence {
+    /// Next id not yet handed out.
+    next: AtomicU64,
+    /// Ids below this value are covered by a WAL high-water mark.
+    logged: AtomicU64,
+    /// Serializes appending high-water marks.
+    log_lock: Mutex<()>,
+}
+
+impl Default for TupleIdSequence {
+    fn default() -> Self {
+        Self {
+            next: AtomicU64::new(1),
+            logged: AtomicU64::new(1),
+            log_lock: Mutex::new(()),
+        }
+    }
+}
+
+impl TupleIdSequence {
+    /// Resumes above `high_water` (largest mark found by WAL replay).
+    pub(crate) fn restore(&self, high_water: u64) {
+        self.next.fetch_max(high_water, Ordering::SeqCst);
+        self.logged.fetch_max(high_water, Ordering::SeqCst);
+    }
+
+    /// Reserves `count` consecutive ids, logging a new high-water mark first when needed.
+    pub(crate) fn reserve(
+        &self,
+        wal: Option<&SqlEngineWal>,
+        count: u64,
+    ) -> Result<Range<u64>, EngineError> {
+        let start = self.next.fetch_add(count, Ordering::SeqCst);
+        let end = start + count;
+        if let Some(wal) = wal {
+            if self.logged.load(Ordering::SeqCst) < end {
+                let _guard = self.log_lock.lock().map_err(|_| lock_poisoned_engine())?;
+                if self.logged.load(Ordering::SeqCst) < end {
+                    let high_water = end + SEQUENCE_BLOCK;
+                    wal.log_sequence_high_water(TUPLE_ID_SEQUENCE, high_water)?;
+                    self.logged.store(high_water, Ordering::SeqCst);
+                }
+            }
+        }
+        Ok(start..end)
+    }
+}
+
+/// Takes the next tuple id for an insert in `ctx`, from the transaction cache when one is open.
+pub(crate) fn next_tuple_id(
+    state: &SqlEngineState,
+    ctx: &mut SessionContext,
+) -> Result<u64, EngineError> {
+    let wal = state.wal.as_ref();
+    match ctx.transaction.as_mut() {
+        Some(tx) if !tx.implicit_autocommit => {
+            if let Some(id) = tx.tuple_ids.next() {
+                return Ok(id);
+            }
+            let mut ids = state.tuple_ids.reserve(wal, TRANSACTION_CACHE)?;
+            let id = ids.next().unwrap_or_default();
+            tx.tuple_ids = ids;
+            Ok(id)
+        }
+        _ => Ok(state.tuple_ids.reserve(wal, 1)?.start),
+    }
+}
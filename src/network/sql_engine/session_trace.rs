@@ -0,0 +1,202 @@
+//! Per-session query tracing: `SET trace = on | off` and `SHOW trace`.
+//!
+//! Unlike the server parameters in `settings`, `trace` only affects the session that sets it.
+//! While it is on, each statement of the session is recorded by the engine's
+//! [`QueryTracer`]: parse, plan (the optimized plan tree, for statements that have one) and
+//! execution events with their timings, then the outcome. Every finished trace is appended as
+//! one JSON object per line to `<data_dir>/traces/session-<id>-<unix ms>.jsonl`; the path is
+//! shown by `SHOW trace` and [`SessionContext::trace_file`], and `rustdb query --trace-out`
+//! copies it out.
+//!
+//! Planning is repeated for the trace, so its timing is that of a fresh plan; the execution
+//! event covers the statement as the engine runs it.
+
+use super::{
+    is_explainable_statement, plan_and_optimize, rows_to_engine_output, EngineError, EngineOutput,
+    SqlEngine, SqlEngineState,
+};
+use crate::common::types::{ColumnValue, DataType, Row};
+use crate::debug::query_tracer::{QueryStage, QueryStatus};
+use crate::debug::{DebugConfig, QueryTracer};
+use crate::network::engine::{engine_error_code, SessionContext};
+use crate::parser::SqlParser;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// Session parameter switching tracing on and off.
+pub(super) const TRACE_PARAMETER: &str = "trace";
+
+/// Trace files, relative to the data directory.
+const TRACE_DIR: &str = "traces";
+
+/// Names trace files of sessions without a server-assigned id.
+static LOCAL_SESSIONS: AtomicU64 = AtomicU64::new(1);
+
+fn tracer(state: &SqlEngineState) -> &QueryTracer {
+    state.query_tracer.get_or_init(|| {
+        QueryTracer::new(&DebugConfig {
+            enable_query_tracing: true,
+            ..Default::default()
+        })
+    })
+}
+
+/// `SET trace = on | off`
+pub(super) fn set_trace(
+    state: &SqlEngineState,
+    ctx: &mut SessionContext,
+    value: &str,
+) -> Result<EngineOutput, EngineError> {
+    let on = match value.to_ascii_lowercase().as_str() {
+        "on" | "true" | "1" => true,
+        "off" | "false" | "0" => false,
+        _ => {
+            return Err(EngineError::new(
+                engine_error_code::INVALID_PARAMETER,
+                format!("invalid value for parameter \"trace\": {value} (expected on or off)"),
+            ))
+        }
+    };
+    if !on {
+        ctx.trace_file = None;
+    } else if ctx.trace_file.is_none() {
+        let dir = state.data_dir.join(TRACE_DIR);
+        std::fs::create_dir_all(&dir).map_err(|e| {
+            EngineError::new(
+                engine_error_code::INTERNAL,
+                format!("cannot create trace directory {}: {e}", dir.display()),
+            )
+        })?;
+        let session = ctx.session_id.map_or_else(
+            || format!("local{}", LOCAL_SESSIONS.fetch_add(1, Ordering::Relaxed)),
+            |id| id.to_string(),
+        );
+        let started_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        ctx.trace_file = Some(dir.join(format!("session-{session}-{started_ms}.jsonl")));
+    }
+    Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+}
+
+/// `SHOW trace`: whether the session is traced, and into which file.
+pub(super) fn show_trace(ctx: &SessionContext) -> Result<EngineOutput, EngineError> {
+    let text = |s: &str| ColumnValue::new(DataType::Varchar(format!("'{s}'")));
+    let mut row = Row::new();
+    row.set_value("name", text(TRACE_PARAMETER));
+    row.set_value(
+        "setting",
+        text(if ctx.trace_file.is_some() {
+            "on"
+        } else {
+            "off"
+        }),
+    );
+    row.set_value("source", text("session"));
+    row.set_value(
+        "trace_file",
+        ctx.trace_file
+            .as_ref()
+            .map_or_else(ColumnValue::null, |p| text(&p.display().to_string())),
+    );
+    rows_to_engine_output(vec![row])
+}
+
+/// Runs `sql` for a traced session and appends its trace to `trace_file`.
+pub(super) fn execute_traced(
+    state: &SqlEngineState,
+    sql: &str,
+    ctx: &mut SessionContext,
+    trace_file: &Path,
+) -> Result<EngineOutput, EngineError> {
+    let tracer = tracer(state);
+    let query_id = tracer.start_trace(sql);
+
+    let t0 = Instant::now();
+    let parsed = SqlParser::new(sql).and_then(|mut p| p.parse_multiple());
+    let parse_time = t0.elapsed();
+    match &parsed {
+        Ok(stmts) => tracer.add_event(
+            &query_id,
+            QueryStage::Parsing,
+            "Parsed",
+            Some(serde_json::json!({ "statements": stmts.len() })),
+            Some(parse_time),
+            None,
+            None,
+        ),
+        Err(e) => tracer.add_event(
+            &query_id,
+            QueryStage::Parsing,
+            "Parse failed",
+            Some(serde_json::json!({ "error": e.to_string() })),
+            Some(parse_time),
+            None,
+            None,
+        ),
+    }
+    if let Ok([stmt]) = parsed.as_deref() {
+        if is_explainable_statement(stmt) {
+            let t0 = Instant::now();
+            if let Ok((_, opt)) = plan_and_optimize(state, sql, stmt, false) {
+                let plan = serde_json::to_value(&opt.optimized_plan.root).ok();
+                tracer.add_event(
+                    &query_id,
+                    QueryStage::Planning,
+                    "Planned",
+                    plan.map(|p| serde_json::json!({ "plan": p })),
+                    Some(t0.elapsed()),
+                    None,
+                    None,
+                );
+            }
+        }
+    }
+
+    let t0 = Instant::now();
+    let out = SqlEngine::execute_sql_inner(state, sql, ctx);
+    let rows = out.as_ref().ok().map(|o| match o {
+        EngineOutput::ResultSet { rows, .. } => rows.len() as u64,
+        EngineOutput::ExecutionOk { rows_affected } => *rows_affected,
+    });
+    tracer.add_event(
+        &query_id,
+        QueryStage::Execution,
+        "Executed",
+        None,
+        Some(t0.elapsed()),
+        None,
+        rows,
+    );
+    match &out {
+        Ok(_) => tracer.finish_trace(&query_id, QueryStatus::Completed, rows, None, None),
+        Err(e) => tracer.finish_trace(
+            &query_id,
+            QueryStatus::Failed,
+            None,
+            None,
+            Some(e.message.clone()),
+        ),
+    }
+
+    // A trace that cannot be written is logged; the statement's result stands.
+    if let Some(trace) = tracer.get_completed_trace(&query_id) {
+        let written = serde_json::to_string(&trace)
+            .map_err(std::io::Error::other)
+            .and_then(|line| {
+                let mut file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(trace_file)?;
+                writeln!(file, "{line}")
+            });
+        if let Err(e) = written {
+            warn!(file = %trace_file.display(), error = %e, "cannot write query trace");
+        }
+    }
+    out
+}
@@ -0,0 +1,114 @@
+//! Server configuration through SQL: `SHOW`, `SET` and the `rustdb_settings` system view.
+//!
+//! `SET` changes a runtime parameter (see [`ConfigParameter::runtime`]) for the whole server,
+//! effective immediately and not undone by `ROLLBACK`; parameters that need a restart are
+//! rejected. Runtime values win over every other configuration layer, also across reloads.
+
+use super::{
+    audit, change_tracking, lock_poisoned_engine, masking, quotas, rows_to_engine_output,
+    EngineOutput, SqlEngineState,
+};
+use crate::common::config::{
+    parameter, ConfigError, ConfigParameter, ConfigReload, DatabaseConfig, LayeredConfig,
+    PARAMETERS,
+};
+use crate::common::set_language;
+use crate::common::types::{ColumnValue, DataType, Row};
+use crate::network::engine::{engine_error_code, EngineError};
+use crate::network::replication::SynchronousCommitConfig;
+
+fn config_error(e: ConfigError) -> EngineError {
+    EngineError::new(engine_error_code::INVALID_PARAMETER, e.to_string())
+}
+
+/// Applies the runtime parameters of `config` to the running engine.
+pub(super) fn apply_runtime(
+    state: &SqlEngineState,
+    config: &DatabaseConfig,
+) -> Result<(), ConfigError> {
+    let sync = SynchronousCommitConfig::from_config(&config.replication)
+        .map_err(|m| ConfigError::new("replication.synchronous_standby_names", m))?;
+    set_language(config.language).map_err(|m| ConfigError::new("language", m))?;
+    state.replication.set_synchronous_commit(sync);
+    audit::configure(&state.select_audit, &config.audit)
+        .map_err(|m| ConfigError::new("audit.select_tables", m))?;
+    masking::configure(&state.column_masking, &config.masking)
+        .map_err(|m| ConfigError::new("masking.columns", m))?;
+    change_tracking::configure(&state.change_tracking, &config.change_tracking);
+    quotas::configure(&state.role_quotas, &config.quotas)?;
+    state.executor.set_work_mem(config.execution.work_mem);
+    Ok(())
+}
+
+/// Replaces the engine's configuration, applying its runtime parameters.
+pub(super) fn configure(state: &SqlEngineState, config: LayeredConfig) -> Result<(), EngineError> {
+    let mut settings = state.settings.write().map_err(|_| lock_poisoned_engine())?;
+    apply_runtime(state, config.config()).map_err(config_error)?;
+    *settings = config;
+    Ok(())
+}
+
+/// Re-reads the configuration file and environment, applying the runtime parameters.
+pub(super) fn reload(state: &SqlEngineState) -> Result<ConfigReload, EngineError> {
+    let mut settings = state.settings.write().map_err(|_| lock_poisoned_engine())?;
+    let mut next = settings.clone();
+    let reload = next.reload().map_err(config_error)?;
+    apply_runtime(state, next.config()).map_err(config_error)?;
+    *settings = next;
+    Ok(reload)
+}
+
+/// `SET <parameter> = <value>`
+pub(super) fn set_parameter(
+    state: &SqlEngineState,
+    name: &str,
+    value: &str,
+) -> Result<EngineOutput, EngineError> {
+    let mut settings = state.settings.write().map_err(|_| lock_poisoned_engine())?;
+    let mut next = settings.clone();
+    next.set(name, value).map_err(config_error)?;
+    apply_runtime(state, next.config()).map_err(config_error)?;
+    *settings = next;
+    Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+}
+
+/// `SHOW <parameter>` / `SHOW ALL`: same columns as `rustdb_settings`
+pub(super) fn show_parameter(
+    state: &SqlEngineState,
+    name: Option<&str>,
+) -> Result<EngineOutput, EngineError> {
+    let settings = state.settings.read().map_err(|_| lock_poisoned_engine())?;
+    let rows = match name {
+        Some(name) => {
+            let param = parameter(name).map_err(config_error)?;
+            vec![setting_row(&settings, param)]
+        }
+        None => PARAMETERS
+            .iter()
+            .map(|p| setting_row(&settings, p))
+            .collect(),
+    };
+    rows_to_engine_output(rows)
+}
+
+pub(super) fn setting_rows(state: &SqlEngineState) -> Result<Vec<Row>, EngineError> {
+    let settings = state.settings.read().map_err(|_| lock_poisoned_engine())?;
+    Ok(PARAMETERS
+        .iter()
+        .map(|p| setting_row(&settings, p))
+        .collect())
+}
+
+fn setting_row(settings: &LayeredConfig, param: &ConfigParameter) -> Row {
+    let text = |s: &str| ColumnValue::new(DataType::Varchar(format!("'{s}'")));
+    let mut row = Row::new();
+    row.set_value("name", text(param.key));
+    row.set_value("setting", text(&param.value(settings.config())));
+    row.set_value("source", text(settings.source(param.key).name()));
+    row.set_value(
+        "runtime",
+        ColumnValue::new(DataType::Boolean(param.runtime)),
+    );
+    row.set_value("description", text(param.description));
+    row
+}
@@ -0,0 +1,598 @@
+//! Crypto-shredding: `ENCRYPT BY` tables, `FORGET` and `VACUUM`.
+//!
+//! Rows of a table created with `ENCRYPT BY <column>` are sealed with the key of their subject,
+//! the row's value of that column (a customer id or email; a unique column gives each row its own
+//! key), before they reach table pages or the WAL (see [`crate::storage::shred`]). A subject's key
+//! is created with its first row and kept in `<data_dir>/keys/subjects.json` under the SHA-256 of
+//! the subject, so the key file does not name anyone.
+//!
+//! `FORGET <subject>` deletes the subject's rows from every encrypted table, then destroys the
+//! key: copies of those rows that survive in page free space, WAL segments or replicas' logs can
+//! no longer be decrypted, and read as deleted. The tables it deleted from are scheduled for
+//! `VACUUM`, which rewrites their pages with the free space zeroed. Both append a record to
+//! `<data_dir>/audit/forget.jsonl` (the subject's SHA-256, the key id, rows deleted, pages
+//! overwritten) as evidence of the deletion.
+//!
+//! Backups of the data directory taken before a `FORGET` still hold the key; run the `FORGET`
+//! again after restoring one. Replicas apply the deletes but keep their own key files. A clone
+//! (`CREATE DATABASE ... TEMPLATE`) starts with its template's keys marked inherited: on its
+//! first open it reseals its rows under keys of its own and destroys the inherited ones, so the
+//! clone and its template share no key. Its WAL from before the clone is no longer readable.
+//!
+//! Tables of a tenant are sealed too: with the tenant's key, or for `ENCRYPT BY` tables with
+//! subject keys kept among the tenant's keys (see `tenants`). A `FORGET` in a tenant session
+//! forgets the subject in that tenant only.
+
+use super::{ensure_no_active_transaction, map_db_err, tenants, SqlEngineState};
+use super::{lock_poisoned_engine, table_page_manager, EngineError, EngineOutput, SqlEngine};
+use crate::catalog::schema::SchemaManager;
+use crate::common::types::{ColumnValue, DataType};
+use crate::common::Result as DbResult;
+use crate::network::engine::{engine_error_code, SessionContext};
+use crate::parser::ast::Literal;
+use crate::storage::atomic_file::write_atomic;
+use crate::storage::shred::{self, KeyId, Keyring};
+use crate::storage::tuple::Tuple;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use ring::digest::{digest, SHA256};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Subject keys, relative to the data directory.
+const KEYS_FILE: &str = "keys/subjects.json";
+/// Deletion evidence, relative to the data directory.
+const FORGET_LOG: &str = "audit/forget.jsonl";
+
+/// Crypto-shredding state of one engine.
+#[derive(Default)]
+pub(super) struct CryptoShredding {
+    /// Checked before anything else, so engines without encrypted tables pay one load per write.
+    enabled: AtomicBool,
+    /// How each sealed table is sealed (refreshed from the catalog on DDL and open).
+    tables: RwLock<HashMap<String, Sealing>>,
+    /// Contents of [`KEYS_FILE`]. Held while the file is rewritten, so sessions inserting a
+    /// subject's first rows agree on its key.
+    keys: Mutex<KeyFile>,
+    /// The keys of [`KEYS_FILE`] and of the tenants, shared with the scan operators.
+    keyring: Arc<Keyring>,
+}
+
+impl CryptoShredding {
+    /// State opening and sealing rows with `keyring`.
+    pub(super) fn with_keyring(keyring: Arc<Keyring>) -> Self {
+        Self {
+            keyring,
+            ..Self::default()
+        }
+    }
+
+    pub(super) fn keyring(&self) -> &Arc<Keyring> {
+        &self.keyring
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct KeyFile {
+    /// Subject SHA-256 (hex) to its key.
+    subjects: BTreeMap<String, SubjectKey>,
+    /// Tables with rows deleted by `FORGET` that `VACUUM` has not overwritten yet.
+    #[serde(default)]
+    pending_vacuum: BTreeSet<String>,
+    /// Keys a clone inherited from its template (its subject and tenant keys). They open the
+    /// rows not resealed yet, and are destroyed once every row is.
+    #[serde(default)]
+    inherited: Vec<SubjectKey>,
+}
+
+/// How the rows of a table are sealed.
+struct Sealing {
+    /// `ENCRYPT BY` column: rows are sealed with their subject's key.
+    encrypt_by: Option<String>,
+    /// Owning tenant: subject keys are the tenant's, and rows of tables without `ENCRYPT BY`
+    /// are sealed with the tenant's key.
+    tenant: Option<String>,
+}
+
+/// A key as kept in key files.
+#[derive(Serialize, Deserialize)]
+pub(super) struct SubjectKey {
+    /// Key id (hex), as written in the tuples it seals.
+    id: String,
+    /// Key (base64).
+    key: String,
+}
+
+impl SubjectKey {
+    /// A new key, not registered yet.
+    pub(super) fn generate() -> Result<Self, EngineError> {
+        let (id, key) = shred::generate_key().map_err(map_db_err)?;
+        Ok(Self {
+            id: to_hex(&id),
+            key: STANDARD.encode(key),
+        })
+    }
+
+    pub(super) fn id(&self) -> Result<KeyId, EngineError> {
+        parse_key_id(&self.id)
+    }
+
+    /// Makes the key available to seal and open tuples.
+    pub(super) fn register(&self, keyring: &Keyring) -> Result<KeyId, EngineError> {
+        let id = self.id()?;
+        let bytes = STANDARD
+            .decode(&self.key)
+            .map_err(|e| internal(format!("subject key {}: {e}", self.id)))?;
+        keyring.register(id, &bytes).map_err(map_db_err)?;
+        Ok(id)
+    }
+}
+
+/// Loads the subject keys of the data directory into the keyring; runs on open, before anything
+/// reads table rows.
+pub(super) fn load_keys(state: &SqlEngineState) -> Result<(), EngineError> {
+    let file = read_keys(&state.data_dir)?;
+    for key in file.subjects.values().chain(&file.inherited) {
+        key.register(state.crypto_shredding.keyring())?;
+    }
+    *state
+        .crypto_shredding
+        .keys
+        .lock()
+        .map_err(|_| lock_poisoned_engine())? = file;
+    Ok(())
+}
+
+/// Picks up the `ENCRYPT BY` and tenant tables of `cat`.
+pub(super) fn refresh_tables(
+    shredding: &CryptoShredding,
+    cat: &SchemaManager,
+) -> Result<(), EngineError> {
+    let tables: HashMap<String, Sealing> = cat
+        .table_names()
+        .into_iter()
+        .filter_map(|t| {
+            let schema = cat.schema(&t).filter(|s| s.foreign.is_none())?;
+            let sealing = Sealing {
+                encrypt_by: schema.encrypt_by.clone(),
+                tenant: schema.tenant.clone(),
+            };
+            (sealing.encrypt_by.is_some() || sealing.tenant.is_some()).then_some((t, sealing))
+        })
+        .collect();
+    shredding
+        .enabled
+        .store(!tables.is_empty(), Ordering::Release);
+    *shredding
+        .tables
+        .write()
+        .map_err(|_| lock_poisoned_engine())? = tables;
+    Ok(())
+}
+
+/// Bytes `tuple` is stored as in `table`: sealed with its subject's key when the table is
+/// encrypted, else with its tenant's key when it has one.
+pub(super) fn tuple_bytes(
+    state: &SqlEngineState,
+    table: &str,
+    tuple: &Tuple,
+) -> Result<Vec<u8>, EngineError> {
+    let shredding = &state.crypto_shredding;
+    if !shredding.enabled.load(Ordering::Acquire) {
+        return tuple.to_bytes().map_err(map_db_err);
+    }
+    let sealing = shredding
+        .tables
+        .read()
+        .map_err(|_| lock_poisoned_engine())?
+        .get(table)
+        .map(|s| (s.encrypt_by.clone(), s.tenant.clone()));
+    let id = match sealing {
+        // Tables listed are sealed one way or the other.
+        None | Some((None, None)) => return tuple.to_bytes().map_err(map_db_err),
+        Some((None, Some(tenant))) => tenants::tenant_key(state, &tenant)?,
+        Some((Some(column), tenant)) => {
+            let subject = tuple
+                .get_value(&column)
+                .and_then(subject_of_value)
+                .ok_or_else(|| {
+                    EngineError::new(
+                        engine_error_code::CONSTRAINT_VIOLATION,
+                        format!(
+                            "{table}.{column} (ENCRYPT BY) must hold a non-NULL text or integer"
+                        ),
+                    )
+                })?;
+            match tenant {
+                Some(tenant) => tenants::subject_key(state, &tenant, &subject)?,
+                None => subject_key(state, &subject)?,
+            }
+        }
+    };
+    shredding
+        .keyring
+        .seal(tuple, &id)
+        .and_then(|sealed| sealed.to_bytes())
+        .map_err(map_db_err)
+}
+
+/// `bytes` of a stored tuple, opened with the engine's keys when sealed.
+pub(super) fn decode_tuple(state: &SqlEngineState, bytes: &[u8]) -> DbResult<Tuple> {
+    state.crypto_shredding.keyring.decode(bytes)
+}
+
+/// Whether `column` may be an `ENCRYPT BY` column (text or integer).
+pub(super) fn is_subject_type(column: &DataType) -> bool {
+    matches!(
+        column,
+        DataType::TinyInt(_)
+            | DataType::SmallInt(_)
+            | DataType::Integer(_)
+            | DataType::BigInt(_)
+            | DataType::Char(_)
+            | DataType::Varchar(_)
+            | DataType::Text(_)
+    )
+}
+
+/// `FORGET <subject>`: deletes the subject's rows from every encrypted table (of the session's
+/// tenant) and destroys its key.
+pub(super) fn forget(
+    state: &SqlEngineState,
+    ctx: &mut SessionContext,
+    subject: &Literal,
+) -> Result<EngineOutput, EngineError> {
+    ensure_no_active_transaction(ctx)?;
+    let subject = match subject {
+        Literal::String(s) => unquote(s).to_string(),
+        Literal::Integer(n) => n.to_string(),
+        _ => {
+            return Err(EngineError::new(
+                engine_error_code::UNSUPPORTED_SQL,
+                "FORGET expects a string or integer subject",
+            ))
+        }
+    };
+    let numeric = subject.parse::<i64>().ok();
+    let tables: Vec<(String, String, bool)> = {
+        let cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
+        cat.table_names()
+            .into_iter()
+            .filter_map(|t| {
+                let schema = cat.schema(&t).filter(|s| s.tenant == ctx.tenant)?;
+                let column = schema.encrypt_by.clone()?;
+                let integer = schema.columns.iter().any(|c| {
+                    c.name == column
+                        && matches!(
+                            c.data_type,
+                            DataType::TinyInt(_)
+                                | DataType::SmallInt(_)
+                                | DataType::Integer(_)
+                                | DataType::BigInt(_)
+                        )
+                });
+                Some((t, column, integer))
+            })
+            .collect()
+    };
+    let mut rows = 0;
+    let mut deleted_from = Vec::new();
+    for (table, column, integer) in &tables {
+        let value = match (integer, numeric) {
+            (true, Some(n)) => n.to_string(),
+            // A subject that is not a number has no rows in an integer column.
+            (true, None) => continue,
+            (false, _) => format!("'{}'", subject.replace('\'', "''")),
+        };
+        let sql = format!("DELETE FROM {table} WHERE {column} = {value}");
+        if let EngineOutput::ExecutionOk { rows_affected } =
+            SqlEngine::execute_sql_inner(state, &sql, ctx)?
+        {
+            rows += rows_affected;
+            if rows_affected > 0 {
+                deleted_from.push(table.clone());
+            }
+        }
+    }
+
+    let hash = subject_hash(&subject);
+    let key_id = {
+        let mut keys = state
+            .crypto_shredding
+            .keys
+            .lock()
+            .map_err(|_| lock_poisoned_engine())?;
+        let removed = match &ctx.tenant {
+            Some(tenant) => tenants::forget_subject(state, tenant, &hash)?,
+            None => keys.subjects.remove(&hash),
+        };
+        keys.pending_vacuum.extend(deleted_from.iter().cloned());
+        save_keys(state, &keys)?;
+        removed
+            .map(|key| parse_key_id(&key.id).map(|id| (id, key.id)))
+            .transpose()?
+    };
+    if let Some((id, _)) = &key_id {
+        state.crypto_shredding.keyring.forget(id);
+    }
+    log_evidence(
+        state,
+        serde_json::json!({
+            "event": "forget",
+            "subject_sha256": hash,
+            "key_id": key_id.map(|(_, hex)| hex),
+            "tables": deleted_from,
+            "rows": rows,
+        }),
+    )?;
+    Ok(EngineOutput::ExecutionOk {
+        rows_affected: rows,
+    })
+}
+
+/// `VACUUM [table]`: rewrites the pages of `table`, or of the tables `FORGET` scheduled, with
+/// their free space zeroed.
+pub(super) fn vacuum(
+    state: &SqlEngineState,
+    ctx: &SessionContext,
+    table: Option<&str>,
+) -> Result<EngineOutput, EngineError> {
+    ensure_no_active_transaction(ctx)?;
+    let tables: Vec<String> = match table {
+        Some(t) => {
+            let cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
+            match cat.schema(t) {
+                Some(schema) if schema.foreign.is_none() => vec![t.to_string()],
+                Some(_) => {
+                    return Err(EngineError::new(
+                        engine_error_code::UNSUPPORTED_SQL,
+                        format!("{t} is a foreign table"),
+                    ))
+                }
+                None => {
+                    return Err(EngineError::new(
+                        engine_error_code::UNSUPPORTED_SQL,
+                        format!("table {t} does not exist"),
+                    ))
+                }
+            }
+        }
+        None => state
+            .crypto_shredding
+            .keys
+            .lock()
+            .map_err(|_| lock_poisoned_engine())?
+            .pending_vacuum
+            .iter()
+            .cloned()
+            .collect(),
+    };
+    let mut pages = 0;
+    for table in &tables {
+        let exists = state
+            .catalog
+            .lock()
+            .map_err(|_| lock_poisoned_engine())?
+            .schema(table)
+            .is_some();
+        let rewritten = if exists {
+            let pm = table_page_manager(state, table)?;
+            let mut pm = pm.write();
+            pm.scrub_free_space().map_err(map_db_err)?
+        } else {
+            // Dropped since it was scheduled: its files are gone.
+            0
+        };
+        pages += u64::from(rewritten);
+        {
+            let mut keys = state
+                .crypto_shredding
+                .keys
+                .lock()
+                .map_err(|_| lock_poisoned_engine())?;
+            if keys.pending_vacuum.remove(table) {
+                save_keys(state, &keys)?;
+            }
+        }
+        log_evidence(
+            state,
+            serde_json::json!({
+                "event": "vacuum",
+                "table": table,
+                "pages": rewritten,
+            }),
+        )?;
+    }
+    Ok(EngineOutput::ExecutionOk {
+        rows_affected: pages,
+    })
+}
+
+/// Key id of `subject`, creating (and persisting) its key on first use.
+fn subject_key(state: &SqlEngineState, subject: &str) -> Result<KeyId, EngineError> {
+    let hash = subject_hash(subject);
+    let mut keys = state
+        .crypto_shredding
+        .keys
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?;
+    if let Some(key) = keys.subjects.get(&hash) {
+        return key.id();
+    }
+    let key = SubjectKey::generate()?;
+    // The key must be durable before any row sealed with it is.
+    keys.subjects.insert(hash.clone(), key);
+    if let Err(e) = save_keys(state, &keys) {
+        keys.subjects.remove(&hash);
+        return Err(e);
+    }
+    keys.subjects[&hash].register(&state.crypto_shredding.keyring)
+}
+
+/// Makes the keys of the data directory `dir`, a clone being created, inherited: the clone
+/// creates keys of its own as it reseals its rows (see [`reseal_inherited`]), so a `FORGET` on
+/// either side never leaves the other able to read what it destroyed.
+pub(super) fn inherit_keys(dir: &Path) -> Result<(), EngineError> {
+    let mut file = read_keys(dir)?;
+    let subjects = std::mem::take(&mut file.subjects).into_values();
+    file.inherited
+        .extend(subjects.chain(tenants::inherit_keys(dir)?));
+    if file.inherited.is_empty() {
+        return Ok(());
+    }
+    write_keys(dir, &file)
+}
+
+/// Reseals the rows of every sealed table when the data directory holds inherited keys, then
+/// destroys those keys. Runs on open, once the engine can run statements; a clone that stops
+/// halfway reseals again on its next open.
+pub(super) fn reseal_inherited(state: &SqlEngineState) -> Result<(), EngineError> {
+    let inherited = {
+        let keys = state
+            .crypto_shredding
+            .keys
+            .lock()
+            .map_err(|_| lock_poisoned_engine())?;
+        keys.inherited
+            .iter()
+            .map(SubjectKey::id)
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    if inherited.is_empty() {
+        return Ok(());
+    }
+    let tables: Vec<(String, Option<String>, String)> = {
+        let cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
+        cat.table_names()
+            .into_iter()
+            .filter_map(|t| {
+                let schema = cat.schema(&t).filter(|s| s.foreign.is_none())?;
+                if schema.encrypt_by.is_none() && schema.tenant.is_none() {
+                    return None;
+                }
+                // Setting a column to itself rewrites the row, sealed with the current keys.
+                let column = schema
+                    .encrypt_by
+                    .clone()
+                    .or_else(|| schema.columns.first().map(|c| c.name.clone()))?;
+                Some((t, schema.tenant.clone(), column))
+            })
+            .collect()
+    };
+    for (table, tenant, column) in tables {
+        let mut ctx = SessionContext {
+            tenant,
+            ..SessionContext::default()
+        };
+        SqlEngine::execute_sql_inner(
+            state,
+            &format!("UPDATE {table} SET {column} = {column}"),
+            &mut ctx,
+        )?;
+    }
+    let mut keys = state
+        .crypto_shredding
+        .keys
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?;
+    keys.inherited.clear();
+    save_keys(state, &keys)?;
+    for id in &inherited {
+        state.crypto_shredding.keyring.forget(id);
+    }
+    Ok(())
+}
+
+fn save_keys(state: &SqlEngineState, keys: &KeyFile) -> Result<(), EngineError> {
+    write_keys(&state.data_dir, keys)
+}
+
+fn read_keys(dir: &Path) -> Result<KeyFile, EngineError> {
+    let path = dir.join(KEYS_FILE);
+    match std::fs::read(&path) {
+        Ok(bytes) => {
+            serde_json::from_slice(&bytes).map_err(|e| internal(format!("{}: {e}", path.display())))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(KeyFile::default()),
+        Err(e) => Err(internal(format!("{}: {e}", path.display()))),
+    }
+}
+
+fn write_keys(dir: &Path, keys: &KeyFile) -> Result<(), EngineError> {
+    let path = dir.join(KEYS_FILE);
+    let json = serde_json::to_vec_pretty(keys).map_err(|e| internal(e.to_string()))?;
+    path.parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .map_err(|e| internal(format!("{}: {e}", path.display())))?;
+    write_atomic(&path, &json).map_err(map_db_err)
+}
+
+fn log_evidence(state: &SqlEngineState, mut event: serde_json::Value) -> Result<(), EngineError> {
+    let time_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    event["time_ms"] = time_ms.into();
+    let path = state.data_dir.join(FORGET_LOG);
+    path.parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|()| OpenOptions::new().create(true).append(true).open(&path))
+        .and_then(|mut file| {
+            writeln!(file, "{event}")?;
+            file.sync_data()
+        })
+        .map_err(|e| internal(format!("{}: {e}", path.display())))
+}
+
+/// Subject a stored value names: integers as decimal, strings without their quotes.
+fn subject_of_value(value: &ColumnValue) -> Option<String> {
+    if value.is_null {
+        return None;
+    }
+    match &value.data_type {
+        DataType::TinyInt(n) => Some(n.to_string()),
+        DataType::SmallInt(n) => Some(n.to_string()),
+        DataType::Integer(n) => Some(n.to_string()),
+        DataType::BigInt(n) => Some(n.to_string()),
+        DataType::Char(s) | DataType::Varchar(s) | DataType::Text(s) => {
+            Some(unquote(s).to_string())
+        }
+        _ => None,
+    }
+}
+
+fn unquote(s: &str) -> &str {
+    s.strip_prefix('\'')
+        .and_then(|s| s.strip_suffix('\''))
+        .unwrap_or(s)
+}
+
+pub(super) fn subject_hash(subject: &str) -> String {
+    to_hex(digest(&SHA256, subject.as_bytes()).as_ref())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn parse_key_id(hex: &str) -> Result<KeyId, EngineError> {
+    let mut id = KeyId::default();
+    if hex.len() != id.len() * 2 {
+        return Err(internal(format!("malformed subject key id {hex}")));
+    }
+    for (i, byte) in id.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|_| internal(format!("malformed subject key id {hex}")))?;
+    }
+    Ok(id)
+}
+
+fn internal(message: String) -> EngineError {
+    EngineError::new(engine_error_code::INTERNAL, message)
+}
@@ -0,0 +1,239 @@
+//! Snapshot export and import across sessions (`EXPORT SNAPSHOT` / `SET TRANSACTION SNAPSHOT`).
+//!
+//! Rows are updated in place, so a snapshot cannot be a visibility horizon over the live heap:
+//! exporting stages a base backup (see `base_backup`) and opens a private engine on the copy,
+//! whose WAL recovery undoes the transactions that were in flight. `SELECT`, set operations and
+//! `EXPLAIN` of a transaction holding a snapshot run against that engine, so parallel
+//! connections that import the same id read identical data (consistent parallel dumps and index
+//! builds). Such transactions are read-only.
+//!
+//! An id is importable while the exporting transaction is open; importers keep the snapshot until
+//! their own transaction ends.
+
+use super::{
+    base_backup, lock_poisoned_engine, map_db_err, typed_results, ColumnMasks, SqlEngine,
+    SqlEngineConfig, SqlEngineState,
+};
+use crate::common::DurabilityMode;
+use crate::network::engine::{
+    engine_error_code, EngineError, EngineHandle, EngineOutput, SessionContext, SqlTransaction,
+};
+use crate::network::replication::BaseBackup;
+use crate::parser::ast::SqlStatement;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Snapshots importable by id (owned by `SqlEngineState`).
+pub(super) type SnapshotExports = Arc<Mutex<HashMap<String, Arc<SnapshotView>>>>;
+
+/// Engine over a staged copy of the data directory.
+pub(crate) struct SnapshotView {
+    engine: Option<SqlEngine>,
+    backup: Option<BaseBackup>,
+}
+
+impl Drop for SnapshotView {
+    fn drop(&mut self) {
+        let engine = self.engine.take();
+        let backup = self.backup.take();
+        // The engine's WAL owns a runtime, which must not be dropped on an async worker; the
+        // staged copy is removed once the engine has closed its files.
+        let _ = std::thread::Builder::new()
+            .name("rustdb-snapshot-drop".to_string())
+            .spawn(move || {
+                drop(engine);
+                drop(backup);
+            });
+    }
+}
+
+/// Snapshot attached to a [`SqlTransaction`].
+pub(crate) struct TransactionSnapshot {
+    id: String,
+    view: Arc<SnapshotView>,
+    /// Registry to remove `id` from when the exporting transaction ends (`None` when imported).
+    exported_to: Option<SnapshotExports>,
+}
+
+impl TransactionSnapshot {
+    /// Id to pass to `SET TRANSACTION SNAPSHOT`.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl std::fmt::Debug for TransactionSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TransactionSnapshot")
+            .field("id", &self.id)
+            .field("exported", &self.exported_to.is_some())
+            .finish()
+    }
+}
+
+impl Drop for TransactionSnapshot {
+    fn drop(&mut self) {
+        if let Some(exports) = self.exported_to.take() {
+            if let Ok(mut g) = exports.lock() {
+                g.remove(&self.id);
+            }
+        }
+    }
+}
+
+/// `EXPORT SNAPSHOT`: materializes the current committed state and returns its id. Exporting
+/// again in the same transaction returns the same id.
+pub(super) fn export_snapshot(
+    state: &SqlEngineState,
+    ctx: &mut SessionContext,
+) -> Result<EngineOutput, EngineError> {
+    let tx = fresh_transaction(ctx, "EXPORT SNAPSHOT")?;
+    if let Some(snapshot) = tx.snapshot.as_ref() {
+        if snapshot.exported_to.is_none() {
+            return Err(EngineError::new(
+                engine_error_code::INVALID_SNAPSHOT,
+                "cannot export a snapshot from a transaction that imported one",
+            ));
+        }
+        return Ok(snapshot_id_output(snapshot.id()));
+    }
+    if state.wal.is_none() {
+        return Err(EngineError::new(
+            engine_error_code::INVALID_SNAPSHOT,
+            "EXPORT SNAPSHOT requires the WAL",
+        ));
+    }
+    let backup = base_backup::stage_base_backup(state)?;
+    let id = format!(
+        "{:08X}-{:016X}",
+        tx.wal_tx_id.unwrap_or(0),
+        backup.start_lsn()
+    );
+    let engine = SqlEngine::open_with_config(
+        backup.root().to_path_buf(),
+        SqlEngineConfig {
+            wal_enabled: true,
+            durability: DurabilityMode::Fast,
+            checkpoints_enabled: false,
+            wal_archive_url: None,
+            ..SqlEngineConfig::default()
+        },
+    )
+    .map_err(map_db_err)?;
+    let view = Arc::new(SnapshotView {
+        engine: Some(engine),
+        backup: Some(backup),
+    });
+    state
+        .snapshot_exports
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?
+        .insert(id.clone(), view.clone());
+    let out = snapshot_id_output(&id);
+    tx.snapshot = Some(TransactionSnapshot {
+        id,
+        view,
+        exported_to: Some(state.snapshot_exports.clone()),
+    });
+    Ok(out)
+}
+
+/// `SET TRANSACTION SNAPSHOT 'id'`: reads of the current transaction use snapshot `id`.
+pub(super) fn import_snapshot(
+    state: &SqlEngineState,
+    ctx: &mut SessionContext,
+    id: &str,
+) -> Result<EngineOutput, EngineError> {
+    let tx = fresh_transaction(ctx, "SET TRANSACTION SNAPSHOT")?;
+    if tx.snapshot.is_some() {
+        return Err(EngineError::new(
+            engine_error_code::INVALID_SNAPSHOT,
+            "the transaction already uses a snapshot",
+        ));
+    }
+    let view = state
+        .snapshot_exports
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?
+        .get(id)
+        .cloned()
+        .ok_or_else(|| {
+            EngineError::new(
+                engine_error_code::INVALID_SNAPSHOT,
+                format!("snapshot '{id}' does not exist"),
+            )
+        })?;
+    tx.snapshot = Some(TransactionSnapshot {
+        id: id.to_string(),
+        view,
+        exported_to: None,
+    });
+    Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+}
+
+/// Runs `stmt` against `snapshot` when it reads or writes data; `None` for statements that do
+/// not touch tables (transaction control, snapshot statements, `SELECT` without `FROM`).
+pub(super) fn execute_in_snapshot(
+    snapshot: &TransactionSnapshot,
+    sql: &str,
+    stmt: &SqlStatement,
+    column_masks: Option<ColumnMasks>,
+) -> Option<Result<EngineOutput, EngineError>> {
+    match stmt {
+        SqlStatement::Select(sel) if sel.from.is_none() => None,
+        SqlStatement::Select(_) | SqlStatement::SetOperation(_) | SqlStatement::Explain(_) => {
+            let engine = snapshot.view.engine.as_ref()?;
+            // The snapshot's engine masks what the session's own role would see masked.
+            let mut ctx = SessionContext {
+                column_masks,
+                ..SessionContext::default()
+            };
+            Some(engine.execute_sql(sql, &mut ctx))
+        }
+        SqlStatement::Insert(_)
+        | SqlStatement::Update(_)
+        | SqlStatement::Delete(_)
+        | SqlStatement::CreateTable(_)
+        | SqlStatement::CreateForeignTable(_)
+        | SqlStatement::CreateIndex(_)
+        | SqlStatement::DropTable(_)
+        | SqlStatement::DropIndex(_)
+        | SqlStatement::AlterTable(_) => Some(Err(EngineError::new(
+            engine_error_code::INVALID_SNAPSHOT,
+            format!(
+                "transaction reading snapshot '{}' is read-only",
+                snapshot.id()
+            ),
+        ))),
+        _ => None,
+    }
+}
+
+/// Open transaction that has not written yet.
+fn fresh_transaction<'a>(
+    ctx: &'a mut SessionContext,
+    statement: &str,
+) -> Result<&'a mut SqlTransaction, EngineError> {
+    let tx = ctx.transaction.as_mut().ok_or_else(|| {
+        EngineError::new(
+            engine_error_code::INVALID_SNAPSHOT,
+            format!("{statement} must run inside a transaction"),
+        )
+    })?;
+    if !tx.undo.is_empty() || !tx.touched_tables.is_empty() {
+        return Err(EngineError::new(
+            engine_error_code::INVALID_SNAPSHOT,
+            format!("{statement} must run before the transaction writes"),
+        ));
+    }
+    Ok(tx)
+}
+
+fn snapshot_id_output(id: &str) -> EngineOutput {
+    let out = EngineOutput::ResultSet {
+        columns: vec!["snapshot_id".to_string()],
+        rows: vec![vec![id.to_string()]],
+    };
+    typed_results::record_text(&out);
+    out
+}
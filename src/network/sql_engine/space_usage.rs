@@ -0,0 +1,229 @@
+//! Space used by tables and indexes: `table_size('t')`, `index_size('i')`, `database_size()` and
+//! `SHOW TABLE STATUS`.
+//!
+//! The size functions return the bytes of the objects' files on disk: a table's heap file (the
+//! files of an `ENGINE lsm` table), an index's page and log files, and for `database_size()`
+//! every local table of the catalog with its indexes. Changes not yet flushed or checkpointed
+//! are not counted. They are answered for `SELECT`s without `FROM` whose every column is one of
+//! them, like `SELECT table_size('orders') AS heap, index_size('idx_customer')`.
+//!
+//! `SHOW TABLE STATUS` returns one row per table followed by one per index of the table, with
+//! the size on disk, the pages (changes not yet on disk included), the bytes of those pages
+//! that hold no data and the share of those free bytes that is fragmented: on heap pages the
+//! bytes outside each page's longest free run, in indexes the unused bytes of pages holding
+//! entries (see [`SpaceUsage`]). Counting free space reads every page.
+
+use super::{
+    lock_poisoned_engine, maintenance, map_db_err, rows_to_engine_output, schema_cache,
+    table_page_manager, EngineError, EngineOutput, SqlEngineState,
+};
+use crate::common::types::{ColumnValue, DataType, RecordId, Row};
+use crate::common::Error as DbError;
+use crate::network::engine::engine_error_code;
+use crate::parser::ast::{Expression, Literal, SelectItem, SelectStatement};
+use crate::storage::index::PersistentIndex;
+use crate::storage::page::SpaceUsage;
+
+/// A size function call of a `SELECT` list
+enum SizeFunction<'a> {
+    Table(&'a str),
+    Index(&'a str),
+    Database,
+}
+
+/// Answers `sel` when it is a `SELECT` without `FROM` of size functions only; `None` otherwise.
+pub(super) fn execute_size_functions(
+    state: &SqlEngineState,
+    sel: &SelectStatement,
+) -> Option<Result<EngineOutput, EngineError>> {
+    if sel.from.is_some() || sel.select_list.is_empty() {
+        return None;
+    }
+    let calls = sel
+        .select_list
+        .iter()
+        .map(|item| match item {
+            SelectItem::Expression {
+                expr: Expression::Function { name, args },
+                alias,
+            } => size_function(name, args)
+                .map(|f| f.map(|f| (alias.clone().unwrap_or(name.to_lowercase()), f))),
+            _ => None,
+        })
+        .collect::<Option<Result<Vec<_>, _>>>()?;
+    Some(calls.and_then(|calls| {
+        schema_cache::load_all(state)?;
+        let mut row = Row::new();
+        for (column, f) in calls {
+            let bytes = match f {
+                SizeFunction::Table(table) => table_size(state, table)?,
+                SizeFunction::Index(index) => index_size(state, index)?,
+                SizeFunction::Database => database_size(state)?,
+            };
+            row.set_value(&column, int(bytes));
+        }
+        rows_to_engine_output(vec![row])
+    }))
+}
+
+/// The size function `name` called with `args`; `None` for other functions.
+fn size_function<'a>(
+    name: &str,
+    args: &'a [Expression],
+) -> Option<Result<SizeFunction<'a>, EngineError>> {
+    let name = name.to_ascii_lowercase();
+    let object = || match args {
+        [Expression::Literal(Literal::String(s))] => Ok(unquote(s)),
+        _ => Err(EngineError::new(
+            engine_error_code::UNSUPPORTED_SQL,
+            format!("{name}() expects one name string"),
+        )),
+    };
+    match name.as_str() {
+        "table_size" => Some(object().map(SizeFunction::Table)),
+        "index_size" => Some(object().map(SizeFunction::Index)),
+        "database_size" if args.is_empty() => Some(Ok(SizeFunction::Database)),
+        "database_size" => Some(Err(EngineError::new(
+            engine_error_code::UNSUPPORTED_SQL,
+            "database_size() takes no arguments",
+        ))),
+        _ => None,
+    }
+}
+
+/// Bytes of the files of `table`.
+fn table_size(state: &SqlEngineState, table: &str) -> Result<u64, EngineError> {
+    maintenance::local_tables(state, Some(table))?;
+    table_page_manager(state, table)?
+        .read()
+        .file_size()
+        .map_err(map_db_err)
+}
+
+/// Bytes of the files of the index `index`, which must be unique among the tables.
+fn index_size(state: &SqlEngineState, index: &str) -> Result<u64, EngineError> {
+    let tables: Vec<String> = maintenance::local_tables(state, None)?
+        .into_iter()
+        .filter(|table| {
+            index_names(state, table).is_ok_and(|names| names.iter().any(|n| n == index))
+        })
+        .collect();
+    match tables.as_slice() {
+        [table] => with_stored_index(state, table, index, |stored| stored.file_size()),
+        [] => Err(EngineError::new(
+            engine_error_code::UNSUPPORTED_SQL,
+            format!("index {index} does not exist"),
+        )),
+        _ => Err(EngineError::new(
+            engine_error_code::UNSUPPORTED_SQL,
+            format!(
+                "index {index} exists on several tables ({})",
+                tables.join(", ")
+            ),
+        )),
+    }
+}
+
+/// Bytes of the files of every local table and its indexes.
+fn database_size(state: &SqlEngineState) -> Result<u64, EngineError> {
+    let mut bytes = 0;
+    for table in maintenance::local_tables(state, None)? {
+        bytes += table_size(state, &table)?;
+        for index in index_names(state, &table)? {
+            bytes += with_stored_index(state, &table, &index, |stored| stored.file_size())?;
+        }
+    }
+    Ok(bytes)
+}
+
+/// `SHOW TABLE STATUS`: `(name, kind, table_name, size_bytes, pages, free_bytes,
+/// fragmentation)` of every local table and index.
+pub(super) fn show_table_status(state: &SqlEngineState) -> Result<EngineOutput, EngineError> {
+    let _storage = state
+        .storage_access
+        .read()
+        .map_err(|_| lock_poisoned_engine())?;
+    let mut tables = maintenance::local_tables(state, None)?;
+    tables.sort();
+    let mut rows = Vec::new();
+    for table in tables {
+        let pm = table_page_manager(state, &table)?;
+        let (size, usage) = {
+            let mut pm = pm.write();
+            let size = pm.file_size().map_err(map_db_err)?;
+            (size, pm.space_usage().map_err(map_db_err)?)
+        };
+        rows.push(status_row(&table, "table", &table, size, usage));
+        let mut indexes = index_names(state, &table)?;
+        indexes.sort();
+        for index in indexes {
+            let (size, usage) = with_stored_index(state, &table, &index, |stored| {
+                Ok((stored.file_size()?, stored.space_usage()?))
+            })?;
+            rows.push(status_row(&index, "index", &table, size, usage));
+        }
+    }
+    rows_to_engine_output(rows)
+}
+
+fn status_row(name: &str, kind: &str, table: &str, size: u64, usage: SpaceUsage) -> Row {
+    let mut row = Row::new();
+    row.set_value("name", text(name));
+    row.set_value("kind", text(kind));
+    row.set_value("table_name", text(table));
+    row.set_value("size_bytes", int(size));
+    row.set_value("pages", int(usage.pages));
+    row.set_value("free_bytes", int(usage.free_bytes));
+    row.set_value(
+        "fragmentation",
+        ColumnValue::new(DataType::Double(usage.fragmentation())),
+    );
+    row
+}
+
+fn index_names(state: &SqlEngineState, table: &str) -> Result<Vec<String>, EngineError> {
+    Ok(state
+        .index_registry
+        .read()
+        .map_err(|_| lock_poisoned_engine())?
+        .list_indexes_for_table(table)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect())
+}
+
+/// Runs `f` on the stored index `index` of `table`; in-memory indexes take no space on disk
+/// and report [`Default`] values.
+fn with_stored_index<R: Default>(
+    state: &SqlEngineState,
+    table: &str,
+    index: &str,
+    f: impl FnOnce(&dyn PersistentIndex<Key = String, Value = Vec<RecordId>>) -> Result<R, DbError>,
+) -> Result<R, EngineError> {
+    let registry = state
+        .index_registry
+        .read()
+        .map_err(|_| lock_poisoned_engine())?;
+    let Some(entry) = registry.get_index_entry(table, index) else {
+        return Ok(R::default());
+    };
+    let mut index = entry.index.lock().map_err(|_| lock_poisoned_engine())?;
+    match index.as_persistent_mut() {
+        Some(stored) => f(&*stored).map_err(map_db_err),
+        None => Ok(R::default()),
+    }
+}
+
+fn unquote(s: &str) -> &str {
+    s.strip_prefix('\'')
+        .and_then(|s| s.strip_suffix('\''))
+        .unwrap_or(s)
+}
+
+fn text(s: &str) -> ColumnValue {
+    ColumnValue::new(DataType::Varchar(format!("'{s}'")))
+}
+
+fn int(n: u64) -> ColumnValue {
+    ColumnValue::new(DataType::BigInt(n as i64))
+}
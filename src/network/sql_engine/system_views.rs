@@ -0,0 +1,217 @@
+//! Read-only system views over engine state, queried with `SELECT ... FROM <view>`.
+//!
+//! A view is computed on each query; it supports `WHERE`, the select list (`*`, columns and
+//! aliased expressions) and `LIMIT` / `OFFSET`, but no joins, grouping or ordering. Column
+//! order in results is by name, as for tables.
+//!
+//! | View | Rows |
+//! |------|------|
+//! | `rustdb_stat_progress_create_index` | running and recently finished index builds |
+//! | `rustdb_stat_background_jobs` | registered background jobs and their last runs |
+//! | `rustdb_settings` | configuration parameters, as listed by `SHOW ALL` |
+//! | `rustdb_startup_report` | phases of the startup self-check and recovery (see `startup`) |
+//! | `rustdb_stat_statements` | calls, time, rows and buffer hits per statement fingerprint, by total time (see `query_stats`) |
+//! | `rustdb_stat_memory` | live heap bytes per subsystem (zero unless built with `memory-profiling`) |
+//! | `rustdb_stat_roles` | sessions, connection cap and refused `SET role` per role (see `roles`) |
+//! | `rustdb_stat_quotas` | quotas and statements refused by each, per role (see `quotas`) |
+//! | `rustdb_stat_tenants` | tables, bytes on disk, quotas and usage per tenant (see `tenants`) |
+//! | `rustdb_stat_schema_cache` | tables still cold, and the stalls and preloads that loaded the others (see `schema_cache`) |
+//! | `rustdb_stat_audit` | logged, sampled-out, exempted and suppressed reads per audited table (see `audit`) |
+//! | `rustdb_change_tracking` | primary keys of changed rows of tracked tables, by commit LSN (see `change_tracking`) |
+//! | `rustdb_stat_tables` | rows and pages of the tables as of their last `ANALYZE`, rows changed since (see `maintenance`) |
+//! | `rustdb_stat_columns` | nulls, NULL fraction, distinct and most common values per column of the analyzed tables (see `maintenance`) |
+//! | `rustdb_stat_table_io` | disk reads and writes, buffer pool hits and file growth per table (see `table_io`) |
+//! | `rustdb_stat_tiering` | hot and cold extents, moves between the tiers and cold page I/O per table (see `tiering`) |
+//!
+//! Table functions without arguments are served the same way:
+//!
+//! | Function | Rows |
+//! |----------|------|
+//! | `index_advisor()` | indexes proposed from the statement history, by estimated benefit (see `index_advisor`) |
+
+use super::{
+    audit, change_tracking, index_advisor, index_build, maintenance, quotas, roles,
+    rows_to_engine_output, schema_cache, settings, table_io, tenants, tiering, EngineError,
+    EngineOutput, SqlEngineState,
+};
+use crate::common::memory_tracking::memory_by_tag;
+use crate::common::types::{ColumnValue, DataType, Row};
+use crate::executor::operators::{eval_predicate_expression, eval_scalar_expression};
+use crate::network::engine::engine_error_code;
+use crate::parser::ast::{Expression, SelectItem, SelectStatement, TableReference};
+
+const VIEWS: &[&str] = &[
+    "rustdb_stat_progress_create_index",
+    "rustdb_stat_background_jobs",
+    "rustdb_settings",
+    "rustdb_startup_report",
+    "rustdb_stat_statements",
+    "rustdb_stat_memory",
+    "rustdb_stat_roles",
+    "rustdb_stat_quotas",
+    "rustdb_stat_tenants",
+    "rustdb_stat_schema_cache",
+    "rustdb_stat_audit",
+    "rustdb_change_tracking",
+    "rustdb_stat_tables",
+    "rustdb_stat_columns",
+    "rustdb_stat_table_io",
+    "rustdb_stat_tiering",
+];
+
+const FUNCTIONS: &[&str] = &[index_advisor::FUNCTION];
+
+/// System view (or table function) `sel` reads from, if it reads a single one.
+pub(super) fn system_view_name(sel: &SelectStatement) -> Option<&'static str> {
+    let from = sel.from.as_ref()?;
+    let view = match &from.table {
+        TableReference::Table { name, .. } => VIEWS.iter().find(|v| v.eq_ignore_ascii_case(name)),
+        TableReference::Function { name, args, .. } if args.is_empty() => {
+            FUNCTIONS.iter().find(|f| f.eq_ignore_ascii_case(name))
+        }
+        _ => None,
+    }?;
+    from.joins.is_empty().then_some(*view)
+}
+
+pub(super) fn execute_system_view(
+    state: &SqlEngineState,
+    view: &str,
+    sel: &SelectStatement,
+) -> Result<EngineOutput, EngineError> {
+    let rows = match view {
+        "rustdb_stat_progress_create_index" => index_build::progress_rows(state)?,
+        "rustdb_stat_background_jobs" => background_job_rows(state),
+        "rustdb_settings" => settings::setting_rows(state)?,
+        "rustdb_startup_report" => state
+            .recovery_report
+            .get()
+            .map(|r| r.rows())
+            .unwrap_or_default(),
+        "rustdb_stat_statements" => state.statement_stats.rows(),
+        "rustdb_stat_memory" => memory_rows(),
+        "rustdb_stat_roles" => roles::role_rows(state)?,
+        "rustdb_stat_quotas" => quotas::quota_rows(state)?,
+        "rustdb_stat_tenants" => tenants::tenant_rows(state)?,
+        "rustdb_stat_schema_cache" => schema_cache::schema_cache_rows(state)?,
+        "rustdb_stat_audit" => audit::audit_rows(state)?,
+        "rustdb_change_tracking" => change_tracking::change_rows(state)?,
+        "rustdb_stat_tables" => maintenance::table_rows(state)?,
+        "rustdb_stat_columns" => maintenance::column_rows(state)?,
+        "rustdb_stat_table_io" => table_io::table_io_rows(state)?,
+        "rustdb_stat_tiering" => tiering::tiering_rows(state)?,
+        index_advisor::FUNCTION => index_advisor::advise(state)?,
+        _ => Vec::new(),
+    };
+    rows_to_engine_output(select_rows(rows, sel, &format!("system view {view}"))?)
+}
+
+/// Applies the `WHERE`, select list and `LIMIT` / `OFFSET` of `sel` to the rows of `relation`
+/// (named in errors), which supports nothing else.
+pub(super) fn select_rows(
+    rows: Vec<Row>,
+    sel: &SelectStatement,
+    relation: &str,
+) -> Result<Vec<Row>, EngineError> {
+    if !sel.group_by.is_empty() || sel.having.is_some() || !sel.order_by.is_empty() {
+        return Err(EngineError::new(
+            engine_error_code::UNSUPPORTED_SQL,
+            format!("{relation} supports only WHERE and LIMIT"),
+        ));
+    }
+    let offset = sel.offset.unwrap_or(0) as usize;
+    let limit = sel.limit.map_or(usize::MAX, |n| n as usize);
+    rows.into_iter()
+        .filter(|r| {
+            sel.where_clause
+                .as_ref()
+                .is_none_or(|w| eval_predicate_expression(r, w))
+        })
+        .skip(offset)
+        .take(limit)
+        .map(|r| project(&r, &sel.select_list, relation))
+        .collect()
+}
+
+fn memory_rows() -> Vec<Row> {
+    let int = |n: u64| ColumnValue::new(DataType::BigInt(n as i64));
+    memory_by_tag()
+        .into_iter()
+        .map(|m| {
+            let mut row = Row::new();
+            row.set_value(
+                "subsystem",
+                ColumnValue::new(DataType::Varchar(format!("'{}'", m.tag.name()))),
+            );
+            row.set_value("live_bytes", int(m.live_bytes));
+            row.set_value("allocations", int(m.allocations));
+            row.set_value("allocated_bytes", int(m.allocated_bytes));
+            row
+        })
+        .collect()
+}
+
+fn background_job_rows(state: &SqlEngineState) -> Vec<Row> {
+    let text = |s: &str| ColumnValue::new(DataType::Varchar(format!("'{s}'")));
+    let int = |n: u64| ColumnValue::new(DataType::BigInt(n as i64));
+    let opt = |v: Option<ColumnValue>| v.unwrap_or_else(ColumnValue::null);
+    state
+        .background_jobs
+        .status()
+        .into_iter()
+        .map(|job| {
+            let mut row = Row::new();
+            row.set_value("job_name", text(&job.name));
+            row.set_value("state", text(job.state.name()));
+            row.set_value("interval_ms", int(job.interval.as_millis() as u64));
+            row.set_value("runs", int(job.runs));
+            row.set_value("failures", int(job.failures));
+            row.set_value("panics", int(job.panics));
+            row.set_value("last_error", opt(job.last_error.as_deref().map(text)));
+            row.set_value(
+                "last_duration_ms",
+                opt(job.last_duration.map(|d| int(d.as_millis() as u64))),
+            );
+            row.set_value("last_finished_ms", opt(job.last_finished_ms.map(int)));
+            row
+        })
+        .collect()
+}
+
+fn project(row: &Row, items: &[SelectItem], relation: &str) -> Result<Row, EngineError> {
+    let mut out = Row::with_capacity(items.len());
+    for item in items {
+        match item {
+            SelectItem::Wildcard => {
+                for (name, value) in row.iter() {
+                    out.set_value_fast(name, value.clone());
+                }
+            }
+            SelectItem::Expression { expr, alias } => {
+                let name = match (alias, expr) {
+                    (Some(a), _) => a.clone(),
+                    (None, Expression::Identifier(c)) => c.clone(),
+                    (None, Expression::QualifiedIdentifier { column, .. }) => column.clone(),
+                    (None, _) => {
+                        return Err(EngineError::new(
+                            engine_error_code::UNSUPPORTED_SQL,
+                            format!("expressions over {relation} need an alias"),
+                        ))
+                    }
+                };
+                let value = match expr {
+                    Expression::Identifier(c)
+                    | Expression::QualifiedIdentifier { column: c, .. } => {
+                        row.get_value(c).cloned()
+                    }
+                    _ => None,
+                };
+                out.set_value_fast(
+                    &name,
+                    value.unwrap_or_else(|| eval_scalar_expression(row, expr)),
+                );
+            }
+        }
+    }
+    Ok(out)
+}
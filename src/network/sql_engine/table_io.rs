@@ -0,0 +1,136 @@
+//! Per-table I/O and buffer pool counters: the `rustdb_stat_table_io` view and their
+//! Prometheus export.
+//!
+//! Every heap table has its own page manager, with its own buffer pool over its `.tbl` file, so
+//! the counters of a table are those of its file: pages read from and written to disk, buffer
+//! pool hits and misses, file extensions and size. They count from the engine open, for the
+//! tables used since (a table's page manager opens on first use), and locate the hot tables
+//! that the file manager's global statistics add up.
+
+use super::{lock_poisoned_engine, EngineError, SqlEngineState};
+use crate::common::types::{ColumnValue, DataType, Row};
+use crate::storage::cached_file_manager::FileIoStatistics;
+use std::fmt::Write as _;
+
+/// I/O counters of one table (see [`super::SqlEngine::table_io_statistics`])
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableIoStatistics {
+    /// Table name
+    pub table: String,
+    /// Counters of the table's heap file
+    pub io: FileIoStatistics,
+}
+
+/// Counters of the tables with an open page manager, by table name.
+pub(super) fn collect(state: &SqlEngineState) -> Result<Vec<TableIoStatistics>, EngineError> {
+    let managers: Vec<_> = state
+        .table_page_managers
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?
+        .iter()
+        .map(|(table, pm)| (table.clone(), pm.clone()))
+        .collect();
+    let mut stats: Vec<TableIoStatistics> = managers
+        .into_iter()
+        .map(|(table, pm)| TableIoStatistics {
+            io: pm.read().io_statistics(),
+            table,
+        })
+        .collect();
+    stats.sort_by(|a, b| a.table.cmp(&b.table));
+    Ok(stats)
+}
+
+/// `rustdb_stat_table_io`: one row per table.
+pub(super) fn table_io_rows(state: &SqlEngineState) -> Result<Vec<Row>, EngineError> {
+    let int = |n: u64| ColumnValue::new(DataType::BigInt(n as i64));
+    Ok(collect(state)?
+        .into_iter()
+        .map(|t| {
+            let mut row = Row::new();
+            row.set_value(
+                "table_name",
+                ColumnValue::new(DataType::Varchar(format!("'{}'", t.table))),
+            );
+            row.set_value("reads", int(t.io.reads));
+            row.set_value("writes", int(t.io.writes));
+            row.set_value("cache_hits", int(t.io.cache_hits));
+            row.set_value("cache_misses", int(t.io.cache_misses));
+            row.set_value(
+                "hit_ratio",
+                ColumnValue::new(DataType::Double(t.io.hit_ratio())),
+            );
+            row.set_value("extensions", int(t.io.extensions));
+            row.set_value("pages", int(t.io.pages));
+            row
+        })
+        .collect())
+}
+
+/// The counters in the Prometheus text format, labelled by table.
+pub(super) fn prometheus(stats: &[TableIoStatistics]) -> String {
+    type Metric = (
+        &'static str,
+        &'static str,
+        &'static str,
+        fn(&FileIoStatistics) -> f64,
+    );
+    const METRICS: &[Metric] = &[
+        (
+            "rustdb_table_page_reads_total",
+            "counter",
+            "Heap pages read from disk",
+            |s| s.reads as f64,
+        ),
+        (
+            "rustdb_table_page_writes_total",
+            "counter",
+            "Heap pages written to disk",
+            |s| s.writes as f64,
+        ),
+        (
+            "rustdb_table_buffer_hits_total",
+            "counter",
+            "Page lookups served by the buffer pool",
+            |s| s.cache_hits as f64,
+        ),
+        (
+            "rustdb_table_buffer_misses_total",
+            "counter",
+            "Page lookups that went to disk",
+            |s| s.cache_misses as f64,
+        ),
+        (
+            "rustdb_table_buffer_hit_ratio",
+            "gauge",
+            "Share of page lookups served by the buffer pool",
+            |s| s.hit_ratio(),
+        ),
+        (
+            "rustdb_table_file_extensions_total",
+            "counter",
+            "Times the heap file was extended",
+            |s| s.extensions as f64,
+        ),
+        (
+            "rustdb_table_pages",
+            "gauge",
+            "Pages in the heap file",
+            |s| s.pages as f64,
+        ),
+    ];
+    let mut out = String::new();
+    for (name, kind, help, value) in METRICS {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} {kind}");
+        for t in stats {
+            let table = t
+                .table
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            let _ = writeln!(out, "{name}{{table=\"{table}\"}} {}", value(&t.io));
+        }
+    }
+    out
+}
@@ -0,0 +1,670 @@
+//! Tenants: `CREATE TENANT`, `ALTER TENANT`, `DROP TENANT`, `SET tenant` and the
+//! `rustdb_stat_tenants` view.
+//!
+//! A tenant owns the tables its sessions create: a session that runs `SET tenant = '<name>'`
+//! creates tables owned by the tenant, and sees only those (statements naming any other table
+//! fail as if it did not exist). Sessions without a tenant see every table and are the ones that
+//! create, alter and drop tenants. Table names are shared by all tenants.
+//!
+//! - **Keys:** each tenant has its own key, created with the tenant and kept with its quotas in
+//!   `<data_dir>/keys/tenants.json`. Rows of the tenant's tables are sealed with it before they
+//!   reach table pages or the WAL; `ENCRYPT BY` tables of the tenant use subject keys kept among
+//!   the tenant's keys instead (see `shredding`).
+//! - **Quotas:** `CREATE TENANT <name> WITH (queries_per_second = 100, max_rows_read = 100000)`
+//!   (or `ALTER TENANT <name> SET (...)`, `NONE` removing a quota) limits the tenant's sessions
+//!   like `quotas.*` limits a role (see `quotas`), on top of their role's quotas.
+//! - **Statistics:** `rustdb_stat_tenants` shows each tenant's tables, their size on disk, its
+//!   quotas, and the statements, rows read and temp space of its sessions.
+//!
+//! `DROP TENANT` commits by rewriting the key file without the tenant: from then on none of the
+//! tenant's rows can be read, wherever their bytes survive. It then drops the tenant's tables
+//! and deletes their files; if the server stops before it is done, opening the data directory
+//! finishes the drop.
+
+use super::shredding::{subject_hash, SubjectKey};
+use super::{
+    collect_statement_tables, drop_table_storage, ensure_no_active_transaction,
+    lock_poisoned_engine, map_db_err, persist_catalog, physical_drop_table, quotas,
+    rows_to_engine_output, EngineError, EngineOutput, SqlEngineState,
+};
+use crate::common::types::{ColumnValue, DataType, Row};
+use crate::executor::{governor_scope, GovernorScope, LimitExceeded, QueryLimits};
+use crate::network::engine::{engine_error_code, SessionContext};
+use crate::parser::ast::{SqlStatement, TenantQuota};
+use crate::storage::atomic_file::write_atomic;
+use crate::storage::lsm::row_store::lsm_table_dir;
+use crate::storage::shred::{self, KeyId};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Session parameter holding the session's tenant.
+pub(super) const TENANT_PARAMETER: &str = "tenant";
+
+/// Tenant keys and quotas, relative to the data directory.
+const TENANTS_FILE: &str = "keys/tenants.json";
+
+/// Tenants of one engine.
+#[derive(Default)]
+pub(super) struct Tenants {
+    /// Contents of [`TENANTS_FILE`]. Held while the file is rewritten.
+    file: Mutex<TenantFile>,
+    usage: Mutex<HashMap<String, TenantUsage>>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct TenantFile {
+    tenants: BTreeMap<String, TenantEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TenantEntry {
+    /// Seals the rows of the tenant's tables without `ENCRYPT BY`.
+    key: SubjectKey,
+    /// Subject SHA-256 (hex) to its key, for the tenant's `ENCRYPT BY` tables.
+    #[serde(default)]
+    subjects: BTreeMap<String, SubjectKey>,
+    #[serde(default)]
+    quotas: TenantQuotas,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct TenantQuotas {
+    queries_per_second: Option<u64>,
+    max_rows_read: Option<u64>,
+    max_temp_bytes: Option<u64>,
+}
+
+impl TenantQuotas {
+    fn apply(&mut self, quotas: &[TenantQuota]) -> Result<(), EngineError> {
+        for quota in quotas {
+            let slot = match quota.name.as_str() {
+                "queries_per_second" => &mut self.queries_per_second,
+                "max_rows_read" => &mut self.max_rows_read,
+                "max_temp_bytes" => &mut self.max_temp_bytes,
+                other => {
+                    return Err(EngineError::new(
+                        engine_error_code::INVALID_PARAMETER,
+                        format!(
+                            "unknown tenant quota {other} (expected queries_per_second, \
+                             max_rows_read or max_temp_bytes)"
+                        ),
+                    ))
+                }
+            };
+            *slot = quota.limit;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct TenantUsage {
+    /// Statements the bucket holds, as of the instant (none until the tenant's first statement).
+    bucket: Option<(f64, Instant)>,
+    statements: u64,
+    rows_read: u64,
+    temp_bytes: u64,
+    throttled: u64,
+    rows_read_exceeded: u64,
+    temp_bytes_exceeded: u64,
+}
+
+/// Governor scope of a statement of a tenant session.
+pub(super) struct TenantScope {
+    tenant: String,
+    governor: GovernorScope,
+}
+
+/// Loads the tenant keys of the data directory into the keyring; runs on open, before anything
+/// reads table rows.
+pub(super) fn load(state: &SqlEngineState) -> Result<(), EngineError> {
+    let file = read_file(&state.data_dir)?;
+    for entry in file.tenants.values() {
+        entry.key.register(state.crypto_shredding.keyring())?;
+        for key in entry.subjects.values() {
+            key.register(state.crypto_shredding.keyring())?;
+        }
+    }
+    *state
+        .tenants
+        .file
+        .lock()
+        .map_err(|_| lock_poisoned_engine())? = file;
+    Ok(())
+}
+
+/// Gives every tenant of the data directory `dir` (a clone being created) a new key and no
+/// subject keys; returns the keys it had, which the clone keeps until its rows are resealed.
+pub(super) fn inherit_keys(dir: &Path) -> Result<Vec<SubjectKey>, EngineError> {
+    let mut file = read_file(dir)?;
+    let mut inherited = Vec::new();
+    for entry in file.tenants.values_mut() {
+        inherited.push(std::mem::replace(&mut entry.key, SubjectKey::generate()?));
+        inherited.extend(std::mem::take(&mut entry.subjects).into_values());
+    }
+    if !inherited.is_empty() {
+        write_file(dir, &file)?;
+    }
+    Ok(inherited)
+}
+
+/// Finishes `DROP TENANT`s the server stopped in the middle of: drops the tables of tenants that
+/// no longer exist. Runs on open, before the constraint runtime is built.
+pub(super) fn finish_drops(state: &SqlEngineState) -> Result<(), EngineError> {
+    let orphans: Vec<String> = {
+        let file = state
+            .tenants
+            .file
+            .lock()
+            .map_err(|_| lock_poisoned_engine())?;
+        let cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
+        cat.table_names()
+            .into_iter()
+            .filter(|t| {
+                cat.schema(t)
+                    .and_then(|s| s.tenant.as_ref())
+                    .is_some_and(|tenant| !file.tenants.contains_key(tenant))
+            })
+            .collect()
+    };
+    if orphans.is_empty() {
+        return Ok(());
+    }
+    for table in &orphans {
+        drop_table_storage(state, table)?;
+    }
+    persist_catalog(state)
+}
+
+/// `CREATE TENANT <name> [WITH (...)]`
+pub(super) fn create_tenant(
+    state: &SqlEngineState,
+    ctx: &SessionContext,
+    name: &str,
+    quotas: &[TenantQuota],
+) -> Result<EngineOutput, EngineError> {
+    ensure_no_active_transaction(ctx)?;
+    let name = name.to_ascii_lowercase();
+    let mut tenant_quotas = TenantQuotas::default();
+    tenant_quotas.apply(quotas)?;
+    let mut file = state
+        .tenants
+        .file
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?;
+    if file.tenants.contains_key(&name) {
+        return Err(EngineError::new(
+            engine_error_code::CONSTRAINT_VIOLATION,
+            format!("tenant {name} already exists"),
+        ));
+    }
+    let entry = TenantEntry {
+        key: SubjectKey::generate()?,
+        subjects: BTreeMap::new(),
+        quotas: tenant_quotas,
+    };
+    entry.key.register(state.crypto_shredding.keyring())?;
+    file.tenants.insert(name.clone(), entry);
+    if let Err(e) = save(state, &file) {
+        file.tenants.remove(&name);
+        return Err(e);
+    }
+    Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+}
+
+/// `ALTER TENANT <name> SET (...)`
+pub(super) fn alter_tenant(
+    state: &SqlEngineState,
+    ctx: &SessionContext,
+    name: &str,
+    quotas: &[TenantQuota],
+) -> Result<EngineOutput, EngineError> {
+    ensure_no_active_transaction(ctx)?;
+    let name = name.to_ascii_lowercase();
+    let mut file = state
+        .tenants
+        .file
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?;
+    let entry = file
+        .tenants
+        .get_mut(&name)
+        .ok_or_else(|| no_such_tenant(&name))?;
+    let previous = entry.quotas;
+    entry.quotas.apply(quotas)?;
+    if let Err(e) = save(state, &file) {
+        if let Some(entry) = file.tenants.get_mut(&name) {
+            entry.quotas = previous;
+        }
+        return Err(e);
+    }
+    Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+}
+
+/// `DROP TENANT [IF EXISTS] <name>`: destroys the tenant's keys, then drops its tables.
+pub(super) fn drop_tenant(
+    state: &SqlEngineState,
+    ctx: &SessionContext,
+    name: &str,
+    if_exists: bool,
+) -> Result<EngineOutput, EngineError> {
+    ensure_no_active_transaction(ctx)?;
+    let name = name.to_ascii_lowercase();
+    let _storage = state
+        .storage_access
+        .write()
+        .map_err(|_| lock_poisoned_engine())?;
+    let tables: Vec<String> = {
+        let cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
+        let owned = |t: &str| {
+            cat.schema(t)
+                .is_some_and(|s| s.tenant.as_deref() == Some(name.as_str()))
+        };
+        let tables: Vec<String> = cat.table_names().into_iter().filter(|t| owned(t)).collect();
+        for table in &tables {
+            if let Some(dep) = cat.tables_with_fk_to(table).into_iter().find(|t| !owned(t)) {
+                return Err(EngineError::new(
+                    engine_error_code::CONSTRAINT_VIOLATION,
+                    format!(
+                        "cannot DROP TENANT {name}: {table} is referenced by foreign key from \
+                         {dep}"
+                    ),
+                ));
+            }
+        }
+        tables
+    };
+    let removed = {
+        let mut file = state
+            .tenants
+            .file
+            .lock()
+            .map_err(|_| lock_poisoned_engine())?;
+        let Some(entry) = file.tenants.remove(&name) else {
+            if if_exists {
+                return Ok(EngineOutput::ExecutionOk { rows_affected: 0 });
+            }
+            return Err(no_such_tenant(&name));
+        };
+        // The commit point: without its keys the tenant's rows are gone.
+        if let Err(e) = save(state, &file) {
+            file.tenants.insert(name.clone(), entry);
+            return Err(e);
+        }
+        entry
+    };
+    // The keys stay loaded until the tables are dropped, which reads their rows.
+    for table in &tables {
+        physical_drop_table(state, table)?;
+    }
+    persist_catalog(state)?;
+    for key in std::iter::once(&removed.key).chain(removed.subjects.values()) {
+        state.crypto_shredding.keyring().forget(&key.id()?);
+    }
+    if let Ok(mut usage) = state.tenants.usage.lock() {
+        usage.remove(&name);
+    }
+    Ok(EngineOutput::ExecutionOk {
+        rows_affected: tables.len() as u64,
+    })
+}
+
+/// `SET tenant = <name> | none`
+pub(super) fn set_tenant(
+    state: &SqlEngineState,
+    ctx: &mut SessionContext,
+    value: &str,
+) -> Result<EngineOutput, EngineError> {
+    ensure_no_active_transaction(ctx)?;
+    let tenant = value.trim().to_ascii_lowercase();
+    if tenant.is_empty() || tenant == "none" {
+        ctx.tenant = None;
+        return Ok(EngineOutput::ExecutionOk { rows_affected: 0 });
+    }
+    let exists = state
+        .tenants
+        .file
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?
+        .tenants
+        .contains_key(&tenant);
+    if !exists {
+        return Err(EngineError::new(
+            engine_error_code::INVALID_PARAMETER,
+            format!("tenant {tenant} does not exist"),
+        ));
+    }
+    ctx.tenant = Some(tenant);
+    Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+}
+
+/// `SHOW tenant`
+pub(super) fn show_tenant(ctx: &SessionContext) -> Result<EngineOutput, EngineError> {
+    let text = |s: &str| ColumnValue::new(DataType::Varchar(format!("'{s}'")));
+    let mut row = Row::new();
+    row.set_value("name", text(TENANT_PARAMETER));
+    row.set_value("setting", text(ctx.tenant.as_deref().unwrap_or("none")));
+    row.set_value("source", text("session"));
+    rows_to_engine_output(vec![row])
+}
+
+/// Fails statements of a tenant session that name tables of other tenants, or manage tenants
+/// and databases.
+pub(super) fn check_access(
+    state: &SqlEngineState,
+    ctx: &SessionContext,
+    stmt: &SqlStatement,
+) -> Result<(), EngineError> {
+    let Some(tenant) = ctx.tenant.as_deref() else {
+        return Ok(());
+    };
+    let managed = match stmt {
+        SqlStatement::CreateTenant { .. } => Some("CREATE TENANT"),
+        SqlStatement::AlterTenant { .. } => Some("ALTER TENANT"),
+        SqlStatement::DropTenant { .. } => Some("DROP TENANT"),
+        SqlStatement::CreateDatabase { .. } => Some("CREATE DATABASE"),
+        _ => None,
+    };
+    if let Some(what) = managed {
+        return Err(EngineError::new(
+            engine_error_code::UNSUPPORTED_SQL,
+            format!("{what} is not allowed in a session of tenant {tenant} (SET tenant = none)"),
+        ));
+    }
+    let mut tables = HashSet::new();
+    let created = collect_statement_tables(&mut tables, stmt);
+    let cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
+    let mut tables: Vec<String> = tables.into_iter().collect();
+    tables.sort();
+    for table in tables {
+        let Some(schema) = cat.schema(&table) else {
+            continue;
+        };
+        if schema.tenant.as_deref() == Some(tenant) {
+            continue;
+        }
+        return Err(EngineError::new(
+            engine_error_code::CONSTRAINT_VIOLATION,
+            if created == Some(table.as_str()) {
+                format!("table name {table} is taken")
+            } else {
+                format!("table {table} does not exist")
+            },
+        ));
+    }
+    Ok(())
+}
+
+/// Admits a statement of a tenant session under the tenant's rate, and scopes its per-statement
+/// limits (and usage counts).
+pub(super) fn admit(
+    state: &SqlEngineState,
+    ctx: &SessionContext,
+) -> Result<Option<TenantScope>, EngineError> {
+    let Some(tenant) = ctx.tenant.as_deref() else {
+        return Ok(None);
+    };
+    let quotas = state
+        .tenants
+        .file
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?
+        .tenants
+        .get(tenant)
+        .map(|entry| entry.quotas);
+    let Some(quotas) = quotas else {
+        return Err(EngineError::new(
+            engine_error_code::INVALID_PARAMETER,
+            format!("tenant {tenant} was dropped (SET tenant = none)"),
+        ));
+    };
+    {
+        let mut usage = state
+            .tenants
+            .usage
+            .lock()
+            .map_err(|_| lock_poisoned_engine())?;
+        let tenant_usage = usage.entry(tenant.to_string()).or_default();
+        if let Some(rate) = quotas.queries_per_second {
+            if !quotas::take_token(&mut tenant_usage.bucket, rate as f64) {
+                tenant_usage.throttled += 1;
+                return Err(EngineError::new(
+                    engine_error_code::QUOTA_EXCEEDED,
+                    format!(
+                        "tenant \"{tenant}\" may run {rate} statements per second \
+                         (queries_per_second); retry later"
+                    ),
+                ));
+            }
+        }
+        tenant_usage.statements += 1;
+    }
+    Ok(Some(TenantScope {
+        tenant: tenant.to_string(),
+        governor: governor_scope(QueryLimits {
+            max_rows_read: quotas.max_rows_read,
+            max_temp_bytes: quotas.max_temp_bytes,
+        }),
+    }))
+}
+
+/// Ends the statement's scope, counting its usage: a statement stopped by a quota fails with
+/// `QUOTA_EXCEEDED`, naming the tenant and the quota.
+pub(super) fn finish(
+    state: &SqlEngineState,
+    scope: TenantScope,
+    out: Result<EngineOutput, EngineError>,
+) -> Result<EngineOutput, EngineError> {
+    let used = scope.governor.usage();
+    let exceeded = scope.governor.exceeded();
+    drop(scope.governor);
+    if let Ok(mut usage) = state.tenants.usage.lock() {
+        let tenant_usage = usage.entry(scope.tenant.clone()).or_default();
+        tenant_usage.rows_read += used.rows_read;
+        tenant_usage.temp_bytes += used.temp_bytes;
+        match exceeded {
+            Some(LimitExceeded::RowsRead(_)) => tenant_usage.rows_read_exceeded += 1,
+            Some(LimitExceeded::TempBytes(_)) => tenant_usage.temp_bytes_exceeded += 1,
+            None => {}
+        }
+    }
+    let Some(exceeded) = exceeded else {
+        return out;
+    };
+    let tenant = scope.tenant;
+    Err(EngineError::new(
+        engine_error_code::QUOTA_EXCEEDED,
+        match exceeded {
+            LimitExceeded::RowsRead(max) => format!(
+                "statement read more than {max} rows, the limit of tenant \"{tenant}\" \
+                 (max_rows_read)"
+            ),
+            LimitExceeded::TempBytes(max) => format!(
+                "statement needs more than {max} bytes of temp space, the limit of tenant \
+                 \"{tenant}\" (max_temp_bytes)"
+            ),
+        },
+    ))
+}
+
+/// Key id sealing the rows of `tenant`'s tables without `ENCRYPT BY`.
+pub(super) fn tenant_key(state: &SqlEngineState, tenant: &str) -> Result<KeyId, EngineError> {
+    state
+        .tenants
+        .file
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?
+        .tenants
+        .get(tenant)
+        .ok_or_else(|| no_such_tenant(tenant))?
+        .key
+        .id()
+}
+
+/// Key id of `subject` among `tenant`'s keys, creating (and persisting) it on first use.
+pub(super) fn subject_key(
+    state: &SqlEngineState,
+    tenant: &str,
+    subject: &str,
+) -> Result<KeyId, EngineError> {
+    let hash = subject_hash(subject);
+    let mut file = state
+        .tenants
+        .file
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?;
+    let entry = file
+        .tenants
+        .get_mut(tenant)
+        .ok_or_else(|| no_such_tenant(tenant))?;
+    if let Some(key) = entry.subjects.get(&hash) {
+        return key.id();
+    }
+    // The key must be durable before any row sealed with it is.
+    entry.subjects.insert(hash.clone(), SubjectKey::generate()?);
+    let saved = save(state, &file);
+    let entry = file
+        .tenants
+        .get_mut(tenant)
+        .ok_or_else(|| no_such_tenant(tenant))?;
+    if let Err(e) = saved {
+        entry.subjects.remove(&hash);
+        return Err(e);
+    }
+    entry.subjects[&hash].register(state.crypto_shredding.keyring())
+}
+
+/// Removes the key of the subject with SHA-256 `hash` from `tenant`'s keys (persisted), for
+/// `FORGET` in a session of the tenant.
+pub(super) fn forget_subject(
+    state: &SqlEngineState,
+    tenant: &str,
+    hash: &str,
+) -> Result<Option<SubjectKey>, EngineError> {
+    let mut file = state
+        .tenants
+        .file
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?;
+    let removed = file
+        .tenants
+        .get_mut(tenant)
+        .ok_or_else(|| no_such_tenant(tenant))?
+        .subjects
+        .remove(hash);
+    if removed.is_some() {
+        save(state, &file)?;
+    }
+    Ok(removed)
+}
+
+/// `rustdb_stat_tenants`: per tenant, its tables and their bytes on disk, its quotas, and the
+/// statements, rows read, temp space and refusals of its sessions.
+pub(super) fn tenant_rows(state: &SqlEngineState) -> Result<Vec<Row>, EngineError> {
+    let quotas: BTreeMap<String, TenantQuotas> = state
+        .tenants
+        .file
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?
+        .tenants
+        .iter()
+        .map(|(name, entry)| (name.clone(), entry.quotas))
+        .collect();
+    let mut tables: HashMap<String, (u64, u64)> = HashMap::new();
+    {
+        let cat = state.catalog.lock().map_err(|_| lock_poisoned_engine())?;
+        for table in cat.table_names() {
+            let Some(tenant) = cat.schema(&table).and_then(|s| s.tenant.clone()) else {
+                continue;
+            };
+            let counts = tables.entry(tenant).or_default();
+            counts.0 += 1;
+            counts.1 += table_bytes(state, &table);
+        }
+    }
+    let usage = state
+        .tenants
+        .usage
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?;
+    let big_int = |n: u64| ColumnValue::new(DataType::BigInt(n as i64));
+    let limit = |limit: Option<u64>| limit.map_or_else(ColumnValue::null, big_int);
+    Ok(quotas
+        .into_iter()
+        .map(|(tenant, quotas)| {
+            let u = usage.get(&tenant);
+            let count = |f: fn(&TenantUsage) -> u64| big_int(u.map_or(0, f));
+            let (table_count, bytes) = tables.get(&tenant).copied().unwrap_or_default();
+            let mut row = Row::new();
+            row.set_value(
+                "tenant",
+                ColumnValue::new(DataType::Varchar(format!("'{tenant}'"))),
+            );
+            row.set_value("tables", big_int(table_count));
+            row.set_value("bytes", big_int(bytes));
+            row.set_value("queries_per_second", limit(quotas.queries_per_second));
+            row.set_value("max_rows_read", limit(quotas.max_rows_read));
+            row.set_value("max_temp_bytes", limit(quotas.max_temp_bytes));
+            row.set_value("statements", count(|u| u.statements));
+            row.set_value("rows_read", count(|u| u.rows_read));
+            row.set_value("temp_bytes", count(|u| u.temp_bytes));
+            row.set_value("throttled", count(|u| u.throttled));
+            row.set_value("rows_read_exceeded", count(|u| u.rows_read_exceeded));
+            row.set_value("temp_bytes_exceeded", count(|u| u.temp_bytes_exceeded));
+            row
+        })
+        .collect())
+}
+
+/// Bytes of `table`'s files (heap file, or LSM directory).
+fn table_bytes(state: &SqlEngineState, table: &str) -> u64 {
+    let heap =
+        std::fs::metadata(state.data_dir.join(format!("{table}.tbl"))).map_or(0, |m| m.len());
+    let lsm = std::fs::read_dir(lsm_table_dir(&state.data_dir, table))
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok()?.metadata().ok())
+                .map(|m| m.len())
+                .sum::<u64>()
+        })
+        .unwrap_or(0);
+    heap + lsm
+}
+
+fn save(state: &SqlEngineState, file: &TenantFile) -> Result<(), EngineError> {
+    write_file(&state.data_dir, file)
+}
+
+fn read_file(dir: &Path) -> Result<TenantFile, EngineError> {
+    let path = dir.join(TENANTS_FILE);
+    match std::fs::read(&path) {
+        Ok(bytes) => {
+            serde_json::from_slice(&bytes).map_err(|e| internal(format!("{}: {e}", path.display())))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(TenantFile::default()),
+        Err(e) => Err(internal(format!("{}: {e}", path.display()))),
+    }
+}
+
+fn write_file(dir: &Path, file: &TenantFile) -> Result<(), EngineError> {
+    let path = dir.join(TENANTS_FILE);
+    let json = serde_json::to_vec_pretty(file).map_err(|e| internal(e.to_string()))?;
+    path.parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .map_err(|e| internal(format!("{}: {e}", path.display())))?;
+    write_atomic(&path, &json).map_err(map_db_err)
+}
+
+fn no_such_tenant(name: &str) -> EngineError {
+    EngineError::new(
+        engine_error_code::CONSTRAINT_VIOLATION,
+        format!("tenant {name} does not exist"),
+    )
+}
+
+fn internal(message: String) -> EngineError {
+    EngineError::new(engine_error_code::INTERNAL, message)
+}
@@ -0,0 +1,138 @@
+//! Hot/cold tiering of heap tables: the `tiering` background job and the
+//! `rustdb_stat_tiering` view.
+//!
+//! With `tiering.cold_tablespace` set, the job runs every `tiering.interval_secs`: for each table
+//! with an open page manager it closes the window of page accesses per extent and moves extents
+//! between the table's `.tbl` file and `<cold_tablespace>/<table>.tbl.cold` as the runtime
+//! parameters `tiering.cold_after_windows`, `tiering.promote_accesses` and
+//! `tiering.max_moves_per_run` say (see [`crate::storage::tiering`]). Pages keep their ids, so
+//! queries, indexes and WAL redo do not notice a move; a read of a cold page is slower, and
+//! reads that keep coming bring its extent back.
+
+use super::{lock_poisoned_engine, EngineError, SqlEngineState};
+use crate::common::config::TieringConfig;
+use crate::common::types::{ColumnValue, DataType, Row};
+use crate::network::engine::engine_error_code;
+use crate::network::sql_engine_wal::TIERING_JOB;
+use crate::storage::tiering::{TieringPolicy, TieringStatistics};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Tiering counters of one table (see [`super::SqlEngine::tiering_statistics`])
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableTieringStatistics {
+    /// Table name
+    pub table: String,
+    /// Extents of the table's heap file per tier and the moves between them
+    pub tiering: TieringStatistics,
+}
+
+fn tiering_config(state: &SqlEngineState) -> Result<TieringConfig, EngineError> {
+    Ok(state
+        .settings
+        .read()
+        .map_err(|_| lock_poisoned_engine())?
+        .config()
+        .tiering
+        .clone())
+}
+
+/// Cold tablespace directory of `config`, resolved against the data directory
+fn cold_dir(state: &SqlEngineState, config: &TieringConfig) -> Option<PathBuf> {
+    let dir = config.cold_tablespace.trim();
+    (!dir.is_empty()).then(|| state.data_dir.join(dir))
+}
+
+/// Registers the tiering job once a cold tablespace is configured (WAL-backed engines only,
+/// like the other background jobs).
+pub(super) fn setup(state: &Arc<SqlEngineState>) -> Result<(), EngineError> {
+    let config = tiering_config(state)?;
+    let Some(wal) = state.wal.as_ref() else {
+        return Ok(());
+    };
+    if cold_dir(state, &config).is_none() || state.background_jobs.job_status(TIERING_JOB).is_some()
+    {
+        return Ok(());
+    }
+    wal.setup_tiering(
+        state.clone(),
+        Duration::from_secs(config.interval_secs.max(1)),
+    )
+    .map_err(|e| EngineError::new(engine_error_code::INTERNAL, format!("tiering job: {e}")))
+}
+
+/// One tiering run over the tables with an open page manager; returns the extents moved
+/// (none without a cold tablespace).
+pub(crate) fn run_tiering(state: &SqlEngineState) -> Result<usize, EngineError> {
+    let config = tiering_config(state)?;
+    let Some(cold_dir) = cold_dir(state, &config) else {
+        return Ok(0);
+    };
+    let policy = TieringPolicy {
+        cold_after_windows: config.cold_after_windows,
+        promote_accesses: config.promote_accesses,
+        max_moves_per_run: config.max_moves_per_run,
+    };
+    let managers: Vec<_> = state
+        .table_page_managers
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?
+        .iter()
+        .map(|(table, pm)| (table.clone(), pm.clone()))
+        .collect();
+    let mut moved = 0;
+    for (table, pm) in managers {
+        let plan = pm.write().run_tiering(&cold_dir, &policy).map_err(|e| {
+            EngineError::new(
+                engine_error_code::INTERNAL,
+                format!("tiering of {table}: {e}"),
+            )
+        })?;
+        moved += plan.promote.len() + plan.demote.len();
+    }
+    Ok(moved)
+}
+
+/// Counters of the tables with an open page manager, by table name.
+pub(super) fn collect(state: &SqlEngineState) -> Result<Vec<TableTieringStatistics>, EngineError> {
+    let managers: Vec<_> = state
+        .table_page_managers
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?
+        .iter()
+        .map(|(table, pm)| (table.clone(), pm.clone()))
+        .collect();
+    let mut stats: Vec<TableTieringStatistics> = managers
+        .into_iter()
+        .map(|(table, pm)| TableTieringStatistics {
+            tiering: pm.read().tiering_statistics(),
+            table,
+        })
+        .collect();
+    stats.sort_by(|a, b| a.table.cmp(&b.table));
+    Ok(stats)
+}
+
+/// `rustdb_stat_tiering`: one row per table.
+pub(super) fn tiering_rows(state: &SqlEngineState) -> Result<Vec<Row>, EngineError> {
+    let int = |n: u64| ColumnValue::new(DataType::BigInt(n as i64));
+    Ok(collect(state)?
+        .into_iter()
+        .map(|t| {
+            let mut row = Row::new();
+            row.set_value(
+                "table_name",
+                ColumnValue::new(DataType::Varchar(format!("'{}'", t.table))),
+            );
+            row.set_value("hot_extents", int(t.tiering.hot_extents));
+            row.set_value("cold_extents", int(t.tiering.cold_extents));
+            row.set_value("extents_demoted", int(t.tiering.extents_demoted));
+            row.set_value("extents_promoted", int(t.tiering.extents_promoted));
+            row.set_value("pages_moved", int(t.tiering.pages_moved));
+            row.set_value("cold_reads", int(t.tiering.cold_reads));
+            row.set_value("cold_writes", int(t.tiering.cold_writes));
+            row
+        })
+        .collect())
+}
@@ -0,0 +1,425 @@
+//! Time travel: `SELECT ... FROM t AS OF TIMESTAMP '2026-10-17 08:00:00'`.
+//!
+//! Rows are updated in place, so the engine keeps no version chains (unlike
+//! [`crate::core::mvcc::MVCCManager`] with a version retention); the history of its rows is the
+//! WAL, whose heap records carry the old and new image of every change. A table as of an instant
+//! is its current rows with the changes of every transaction that committed after that instant,
+//! or has not committed yet, undone newest first.
+//!
+//! `history.retention_secs` bounds how far back a query may read (zero, the default, disables
+//! time travel); the WAL must also reach back that far. Timestamps are UTC, and commit times have
+//! whole-second resolution: a transaction is visible as of every instant from the start of the
+//! second it committed in. A table changed by DDL since the instant cannot be read as of it.
+//!
+//! Like a system view, an `AS OF` query reads a single table and supports `WHERE`, the select
+//! list and `LIMIT` / `OFFSET`. Masked columns stay masked.
+//!
+//! `FLASHBACK TABLE t TO TIMESTAMP '...'` writes that state back as the table's current rows.
+
+use super::logical_decoding::{tables_by_file_id, WAL_DIR};
+use super::{
+    acquire_table_storage_read_lock, acquire_table_storage_write_lock,
+    ensure_no_active_transaction, execute_delete, execute_dml_autocommit, insert_row_tuple,
+    lock_poisoned_engine, map_db_err, masking, rows_to_engine_output, system_views,
+    table_page_manager, table_storage_lock_arc, EngineError, EngineOutput, SqlEngineState,
+};
+use crate::common::key_encoding::parse_time;
+use crate::common::types::{ColumnValue, DataType, Row};
+use crate::logging::log_record::{LogRecord, LogRecordType, TransactionId};
+use crate::network::engine::{engine_error_code, SessionContext};
+use crate::network::sql_engine_wal::log_record_operation_parts;
+use crate::parser::ast::{
+    AlterTableOperation, DeleteStatement, SelectStatement, SqlStatement, TableReference,
+};
+use crate::parser::SqlParser;
+use crate::storage::shred::Keyring;
+use crate::storage::tuple::Tuple;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Table and timestamp of a single-table `SELECT ... FROM t AS OF TIMESTAMP '...'`; `AS OF`
+/// in a join is refused here, deeper in the statement by the planner.
+pub(super) fn as_of_table(sel: &SelectStatement) -> Result<Option<(&str, &str)>, EngineError> {
+    let Some(from) = sel.from.as_ref() else {
+        return Ok(None);
+    };
+    let as_of =
+        |table: &TableReference| matches!(table, TableReference::Table { as_of: Some(_), .. });
+    if !from.joins.is_empty() && (as_of(&from.table) || from.joins.iter().any(|j| as_of(&j.table)))
+    {
+        return Err(EngineError::new(
+            engine_error_code::UNSUPPORTED_SQL,
+            "AS OF TIMESTAMP cannot be used in a join",
+        ));
+    }
+    Ok(match &from.table {
+        TableReference::Table {
+            name,
+            as_of: Some(at),
+            ..
+        } => Some((name, at)),
+        _ => None,
+    })
+}
+
+pub(super) fn execute_as_of(
+    state: &SqlEngineState,
+    ctx: &SessionContext,
+    stmt: &SqlStatement,
+    sel: &SelectStatement,
+    table: &str,
+    at: &str,
+) -> Result<EngineOutput, EngineError> {
+    let at_secs = history_point(state, table, at, "AS OF TIMESTAMP")?;
+    let rows = {
+        let _storage = state
+            .storage_access
+            .read()
+            .map_err(|_| lock_poisoned_engine())?;
+        let lock = table_storage_lock_arc(state, table)?;
+        let _table_read = acquire_table_storage_read_lock(&lock, table)?;
+        history(state, table, at, at_secs)?.into_rows()
+    };
+    let relation = format!("{table} AS OF TIMESTAMP");
+    let mut rows = system_views::select_rows(rows, sel, &relation)?;
+    if let Some(masks) = masking::session_masks(&state.column_masking, ctx) {
+        masking::mask_rows(&masks, stmt, &mut rows)?;
+    }
+    rows_to_engine_output(rows)
+}
+
+/// `FLASHBACK TABLE <table> TO TIMESTAMP '<at>'`: one transaction deletes the table's rows and
+/// inserts those committed at `at`, so the rewrite is WAL-logged (and replicated) like any DML.
+pub(super) fn flashback_table(
+    state: &SqlEngineState,
+    ctx: &mut SessionContext,
+    table: &str,
+    at: &str,
+) -> Result<EngineOutput, EngineError> {
+    ensure_no_active_transaction(ctx)?;
+    let at_secs = history_point(state, table, at, "FLASHBACK TABLE")?;
+    // Reads and rewrites the same table, like `INSERT ... SELECT`.
+    let _storage = state
+        .storage_access
+        .write()
+        .map_err(|_| lock_poisoned_engine())?;
+    let lock = table_storage_lock_arc(state, table)?;
+    let _table_write = acquire_table_storage_write_lock(&lock, table)?;
+    let tuples = history(state, table, at, at_secs)?.into_tuples();
+    let delete = DeleteStatement {
+        table: table.to_string(),
+        where_clause: None,
+    };
+    let sql = format!("DELETE FROM {table}");
+    let stmt = SqlStatement::Delete(delete.clone());
+    execute_dml_autocommit(state, ctx, |state, ctx| {
+        execute_delete(state, ctx, &sql, &stmt, &delete, None)?;
+        let rows_affected = tuples.len() as u64;
+        for tuple in tuples {
+            insert_row_tuple(state, ctx, table, tuple)?;
+        }
+        Ok(EngineOutput::ExecutionOk { rows_affected })
+    })
+}
+
+/// Checks that `table` may be read as of `at` and returns that instant in Unix seconds; `what`
+/// names the statement in errors.
+fn history_point(
+    state: &SqlEngineState,
+    table: &str,
+    at: &str,
+    what: &str,
+) -> Result<u64, EngineError> {
+    let retention = state
+        .settings
+        .read()
+        .map_err(|_| lock_poisoned_engine())?
+        .config()
+        .history
+        .retention_secs;
+    if retention == 0 {
+        return Err(EngineError::new(
+            engine_error_code::UNSUPPORTED_SQL,
+            format!("{what} requires history.retention_secs"),
+        ));
+    }
+    let at_secs = unix_seconds(at).ok_or_else(|| {
+        invalid(format!(
+            "invalid timestamp '{at}' (expected YYYY-MM-DD HH:MM:SS)"
+        ))
+    })?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    if at_secs > now {
+        return Err(invalid(format!("'{at}' is in the future")));
+    }
+    if at_secs < now.saturating_sub(retention) {
+        return Err(invalid(format!(
+            "'{at}' is older than history.retention_secs ({retention})"
+        )));
+    }
+    let local = state
+        .catalog
+        .lock()
+        .map_err(|_| lock_poisoned_engine())?
+        .schema(table)
+        .map(|s| s.foreign.is_none());
+    match local {
+        Some(true) => Ok(at_secs),
+        Some(false) => Err(EngineError::new(
+            engine_error_code::UNSUPPORTED_SQL,
+            format!("foreign table {table} has no history"),
+        )),
+        None => Err(EngineError::new(
+            engine_error_code::UNSUPPORTED_SQL,
+            format!("table {table} does not exist"),
+        )),
+    }
+}
+
+/// Rows of `table` as committed at `at_secs` (Unix seconds); the caller holds the table's
+/// storage lock.
+fn history(
+    state: &SqlEngineState,
+    table: &str,
+    at: &str,
+    at_secs: u64,
+) -> Result<HeapRows, EngineError> {
+    let Some(wal) = state.wal.as_ref() else {
+        return Err(EngineError::new(
+            engine_error_code::UNSUPPORTED_SQL,
+            "time travel requires the WAL",
+        ));
+    };
+    let file_id = tables_by_file_id(state)?
+        .into_iter()
+        .find_map(|(fid, t)| (t.name == table).then_some(fid));
+    let mut rows = HeapRows::default();
+    let pm = table_page_manager(state, table)?;
+    let page_ids = pm.read().all_page_ids().map_err(map_db_err)?;
+    for page_id in page_ids {
+        for (_, bytes) in pm.read().records_from_page(page_id).map_err(map_db_err)? {
+            rows.insert(
+                state
+                    .crypto_shredding
+                    .keyring()
+                    .decode(&bytes)
+                    .map_err(map_db_err)?,
+            );
+        }
+    }
+
+    wal.flush_buffered()?;
+    let records = LogRecord::read_log_records_from_directory(&state.data_dir.join(WAL_DIR))
+        .map_err(map_db_err)?;
+    if records.first().is_some_and(|r| r.timestamp > at_secs) {
+        return Err(invalid(format!("the WAL does not reach back to '{at}'")));
+    }
+    let mut open: HashMap<TransactionId, Vec<LogRecord>> = HashMap::new();
+    let mut undo = Vec::new();
+    for r in records {
+        if let Some(sql) = r.ddl_statement() {
+            if r.timestamp > at_secs && ddl_changes_table(sql, table) {
+                return Err(invalid(format!(
+                    "table {table} was changed by DDL after '{at}'"
+                )));
+            }
+            continue;
+        }
+        let Some(tid) = r.transaction_id else {
+            continue;
+        };
+        match r.record_type {
+            LogRecordType::DataInsert | LogRecordType::DataUpdate | LogRecordType::DataDelete => {
+                if log_record_operation_parts(&r).is_some_and(|(_, op)| Some(op.file_id) == file_id)
+                {
+                    open.entry(tid).or_default().push(r);
+                }
+            }
+            LogRecordType::TransactionAbort => {
+                open.remove(&tid);
+            }
+            LogRecordType::TransactionCommit => {
+                let ops = open.remove(&tid).unwrap_or_default();
+                if r.timestamp > at_secs {
+                    undo.extend(ops);
+                }
+            }
+            _ => {}
+        }
+    }
+    // Transactions still in flight changed the heap too.
+    undo.extend(open.into_values().flatten());
+    undo.sort_by_key(|r| r.lsn);
+
+    for r in undo.iter().rev() {
+        let Some((kind, op)) = log_record_operation_parts(r) else {
+            continue;
+        };
+        let old = decode(state.crypto_shredding.keyring(), op.old_data.as_deref())?;
+        let new = decode(state.crypto_shredding.keyring(), op.new_data.as_deref())?;
+        match kind {
+            LogRecordType::DataInsert => {
+                if let Some(new) = new {
+                    rows.remove(&new);
+                }
+            }
+            LogRecordType::DataUpdate => {
+                if let Some(new) = new {
+                    rows.remove(&new);
+                }
+                if let Some(old) = old {
+                    rows.insert(old);
+                }
+            }
+            LogRecordType::DataDelete => {
+                if let Some(old) = old {
+                    rows.insert(old);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(rows)
+}
+
+/// Rows of a table by value; rows with equal values are interchangeable.
+#[derive(Default)]
+struct HeapRows {
+    /// Heap order; `None` once removed.
+    tuples: Vec<Option<Tuple>>,
+    by_value: HashMap<String, Vec<usize>>,
+}
+
+impl HeapRows {
+    fn insert(&mut self, tuple: Tuple) {
+        if tuple.is_deleted {
+            return;
+        }
+        self.by_value
+            .entry(value_key(&tuple))
+            .or_default()
+            .push(self.tuples.len());
+        self.tuples.push(Some(tuple));
+    }
+
+    fn remove(&mut self, tuple: &Tuple) {
+        let slot = self
+            .by_value
+            .get_mut(&value_key(tuple))
+            .and_then(|slots| slots.pop());
+        if let Some(slot) = slot {
+            self.tuples[slot] = None;
+        }
+    }
+
+    fn into_tuples(self) -> Vec<Tuple> {
+        self.tuples.into_iter().flatten().collect()
+    }
+
+    fn into_rows(self) -> Vec<Row> {
+        self.into_tuples()
+            .into_iter()
+            .map(|tuple| {
+                let mut names: Vec<&String> = tuple.values.keys().collect();
+                names.sort();
+                let mut row = Row::new();
+                // Tuple id is implicit, as in table scans.
+                if !tuple.values.contains_key("id") {
+                    row.set_value("id", ColumnValue::new(DataType::BigInt(tuple.id as i64)));
+                }
+                for name in names {
+                    row.set_value(name, tuple.values[name].clone());
+                }
+                row
+            })
+            .collect()
+    }
+}
+
+fn value_key(tuple: &Tuple) -> String {
+    let mut values: Vec<_> = tuple.values.iter().collect();
+    values.sort_by(|a, b| a.0.cmp(b.0));
+    format!("{}:{values:?}", tuple.id)
+}
+
+/// Heap image of a WAL record; `None` when absent or no longer readable (crypto-shredded).
+fn decode(keyring: &Keyring, bytes: Option<&[u8]>) -> Result<Option<Tuple>, EngineError> {
+    let Some(bytes) = bytes else {
+        return Ok(None);
+    };
+    let tuple = keyring.decode(bytes).map_err(map_db_err)?;
+    Ok((!tuple.is_deleted).then_some(tuple))
+}
+
+/// Whether the DDL statement `sql` creates, alters, renames or drops `table`.
+fn ddl_changes_table(sql: &str, table: &str) -> bool {
+    let Ok(stmt) = SqlParser::new(sql).and_then(|mut p| p.parse()) else {
+        return false;
+    };
+    match stmt {
+        SqlStatement::CreateTable(ct) => ct.table_name.eq_ignore_ascii_case(table),
+        SqlStatement::DropTable(dt) => dt.table_name.eq_ignore_ascii_case(table),
+        SqlStatement::AlterTable(alt) => {
+            alt.table_name.eq_ignore_ascii_case(table)
+                || matches!(&alt.operation, AlterTableOperation::RenameTable(to)
+                    if to.eq_ignore_ascii_case(table))
+        }
+        _ => false,
+    }
+}
+
+/// `YYYY-MM-DD[ HH:MM[:SS[.f]]]` (UTC, also with `T` and a trailing `Z`) as Unix seconds.
+fn unix_seconds(literal: &str) -> Option<u64> {
+    let literal = literal.trim();
+    let literal = literal.strip_suffix('Z').unwrap_or(literal);
+    let (date, time) = match literal.split_once([' ', 'T']) {
+        Some((date, time)) => (date, parse_time(time)?.0),
+        None => (literal, 0),
+    };
+    let mut parts = date.splitn(3, '-');
+    let mut field = |len: usize| -> Option<i64> {
+        let s = parts.next()?;
+        (s.len() == len && s.bytes().all(|b| b.is_ascii_digit())).then(|| s.parse().ok())?
+    };
+    let (year, month, day) = (field(4)?, field(2)?, field(2)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    // Days since 1970-01-01 (Howard Hinnant's algorithm).
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    u64::try_from(days * 86_400 + time).ok()
+}
+
+fn invalid(message: String) -> EngineError {
+    EngineError::new(engine_error_code::INVALID_SNAPSHOT, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_are_read_as_utc() {
+        assert_eq!(unix_seconds("1970-01-01 00:00:00"), Some(0));
+        assert_eq!(unix_seconds("2013-05-24T00:00:00Z"), Some(1_369_353_600));
+        assert_eq!(unix_seconds("2000-02-29 11:59:59.75"), Some(951_825_599));
+        assert_eq!(unix_seconds("2000-03-01"), Some(951_868_800));
+        for bad in [
+            "",
+            "2000-13-01",
+            "20-01-01 00:00",
+            "2000-01-01 25:00",
+            "yesterday",
+        ] {
+            assert_eq!(unix_seconds(bad), None, "{bad}");
+        }
+    }
+}
@@ -116,9 +116,7 @@ fn run_new_order_native(
     }
 
     let t0 = profile.then(Instant::now);
-    let id = state
-        .next_tuple_id
-        .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let id = super::sequences::next_tuple_id(state, ctx)?;
     let ins = insert_row_tuple_tpcc_deferred(
         state,
         ctx,
@@ -143,9 +141,7 @@ fn run_new_order_native(
     }
 
     let t0 = profile.then(Instant::now);
-    let id = state
-        .next_tuple_id
-        .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let id = super::sequences::next_tuple_id(state, ctx)?;
     let ins = insert_row_tuple_tpcc_deferred(
         state,
         ctx,
@@ -199,9 +195,7 @@ fn run_new_order_native(
 
     let mut order_line_tuples = Vec::with_capacity(order_line_cnt as usize);
     for ol_number in 1..=order_line_cnt {
-        let id = state
-            .next_tuple_id
-            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let id = super::sequences::next_tuple_id(state, ctx)?;
         order_line_tuples.push(new_tuple_with_columns(
             id,
             &[
@@ -0,0 +1,83 @@
+//! The values a result set was built from, for callers in the same process
+//!
+//! [`EngineOutput::ResultSet`] carries every cell as text (the `Debug` form of its value), which
+//! is what goes over the wire. While the guard returned by [`typed_result_scope`] lives, each
+//! result set the engine builds on the current thread is also recorded as the values behind its
+//! cells; [`TypedResultScope::finish`] returns those of the statement's output. Text-only
+//! outputs (`EXPLAIN`, snapshot ids) are recorded as [`DataType::Text`].
+
+use super::EngineOutput;
+use crate::common::types::DataType;
+use std::cell::RefCell;
+
+/// Columns and rows of one result set
+type TypedResult = (Vec<String>, Vec<Vec<DataType>>);
+
+thread_local! {
+    /// Last result set built in each open scope, outermost first.
+    static SCOPES: RefCell<Vec<Option<TypedResult>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Records the values of the result sets built on the current thread until the guard is dropped
+pub fn typed_result_scope() -> TypedResultScope {
+    let depth = SCOPES.with(|s| {
+        let mut s = s.borrow_mut();
+        s.push(None);
+        s.len() - 1
+    });
+    TypedResultScope {
+        depth,
+        _not_send: std::marker::PhantomData,
+    }
+}
+
+/// Guard returned by [`typed_result_scope`]
+pub struct TypedResultScope {
+    depth: usize,
+    // The results are per thread: the guard must be dropped where it was created.
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+impl TypedResultScope {
+    /// Closes the scope; returns the values behind the rows of `output`, or `None` when it is
+    /// not a result set or was not built in this scope (a statement run elsewhere, or one
+    /// whose output was reshaped after it was built).
+    pub fn finish(self, output: &EngineOutput) -> Option<Vec<Vec<DataType>>> {
+        let (columns, rows) = SCOPES.with(|s| s.borrow_mut().get_mut(self.depth)?.take())?;
+        match output {
+            EngineOutput::ResultSet {
+                columns: out_columns,
+                rows: out_rows,
+            } if *out_columns == columns && out_rows.len() == rows.len() => Some(rows),
+            _ => None,
+        }
+    }
+}
+
+impl Drop for TypedResultScope {
+    fn drop(&mut self) {
+        // Also closes scopes opened inside this one and not dropped yet.
+        SCOPES.with(|s| s.borrow_mut().truncate(self.depth));
+    }
+}
+
+/// Records the result set with `columns` built from the values `rows` returns, when a scope is
+/// active (`rows` is not called otherwise).
+pub(super) fn record(columns: &[String], rows: impl FnOnce() -> Vec<Vec<DataType>>) {
+    SCOPES.with(|s| {
+        if let Some(last) = s.borrow_mut().last_mut() {
+            *last = Some((columns.to_vec(), rows()));
+        }
+    });
+}
+
+/// Records `output`, a result set of text cells, as [`DataType::Text`] values.
+pub(super) fn record_text(output: &EngineOutput) {
+    if let EngineOutput::ResultSet { columns, rows } = output {
+        record(columns, || {
+            rows.iter()
+                .map(|row| row.iter().map(|c| DataType::Text(c.clone())).collect())
+                .collect()
+        });
+    }
+}
@@ -0,0 +1,969 @@
+//! Dry runs: check statements against the catalog without executing them.
+//!
+//! A [`Validator`] parses each statement, resolves its tables and columns against a copy of the
+//! catalog, checks literals against the column types they are stored in, and plans the
+//! statements that have a plan (with the indexes known so far), reporting the plan as `EXPLAIN`
+//! shows it. DDL is applied to the copy only, so later statements of a migration script see the
+//! tables, columns and indexes created by earlier ones; nothing is written to disk.
+//!
+//! The checks are stricter than execution in places: the engine coerces or stores literals of
+//! any type, and reads from a missing table as from an empty one, while the validator reports
+//! both.
+
+use super::{
+    apply_table_constraint_to_schema, drop_constraint_by_name, drop_index_table,
+    lock_poisoned_engine, sql_type_to_column_datatype, system_views,
+    table_schema_from_create_table, validate_new_table_fks, EngineError, SqlEngine,
+};
+use crate::catalog::schema::{ForeignTableDef, SecondaryIndexDef, TableSchema};
+use crate::catalog::SchemaManager;
+use crate::common::config::parameter;
+use crate::common::key_encoding;
+use crate::common::types::{Column, DataType};
+use crate::network::engine::engine_error_code;
+use crate::parser::ast::{
+    AlterTableOperation, Expression, FromClause, InList, IndexMethod, InsertValues, Literal,
+    SelectItem, SelectStatement, SqlStatement, TableReference, UnaryOperator,
+};
+use crate::parser::SqlParser;
+use crate::planner::explain_format::{format_explain_output, ExplainFormatOptions};
+use crate::planner::{QueryOptimizer, QueryPlanner};
+use crate::storage::index_registry::{IndexOptions, IndexRegistry};
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Outcome of [`Validator::check`]: one entry per statement of the script.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ValidationReport {
+    /// Set when the script does not parse (no statement is checked then).
+    pub parse_error: Option<String>,
+    pub statements: Vec<StatementCheck>,
+}
+
+/// Findings for one statement.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatementCheck {
+    /// Statement kind and target, e.g. `INSERT INTO users`.
+    pub statement: String,
+    /// Unknown objects, type errors and planning failures: the statement would fail or misbehave.
+    pub errors: Vec<String>,
+    /// Suspicious but valid, e.g. `DELETE` without `WHERE`.
+    pub warnings: Vec<String>,
+    /// Chosen plan (`EXPLAIN` lines) of queries and DML without errors.
+    pub plan: Vec<String>,
+}
+
+impl ValidationReport {
+    /// No parse error and no statement with errors.
+    pub fn is_valid(&self) -> bool {
+        self.parse_error.is_none() && self.statements.iter().all(|s| s.errors.is_empty())
+    }
+
+    /// Parse error plus statement errors.
+    pub fn error_count(&self) -> usize {
+        usize::from(self.parse_error.is_some())
+            + self
+                .statements
+                .iter()
+                .map(|s| s.errors.len())
+                .sum::<usize>()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(e) = &self.parse_error {
+            writeln!(f, "parse error: {e}")?;
+        }
+        for (i, s) in self.statements.iter().enumerate() {
+            let status = if s.errors.is_empty() { "ok" } else { "FAILED" };
+            writeln!(f, "statement {}: {} ... {status}", i + 1, s.statement)?;
+            for e in &s.errors {
+                writeln!(f, "  error: {e}")?;
+            }
+            for w in &s.warnings {
+                writeln!(f, "  warning: {w}")?;
+            }
+            if !s.plan.is_empty() {
+                writeln!(f, "  plan:")?;
+                for line in s.plan.iter().filter(|l| !l.is_empty()) {
+                    writeln!(f, "    {line}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks statements against a private copy of a catalog (see the module docs).
+pub struct Validator {
+    catalog: SchemaManager,
+    indexes: IndexRegistry,
+    planner: QueryPlanner,
+}
+
+impl Validator {
+    fn new(catalog: SchemaManager, indexes: IndexRegistry) -> Result<Self, EngineError> {
+        let planner = QueryPlanner::new().map_err(internal)?;
+        for table in catalog.table_names() {
+            if let Some(schema) = catalog.schema(&table) {
+                planner.register_foreign_table(schema).map_err(internal)?;
+            }
+        }
+        Ok(Self {
+            catalog,
+            indexes,
+            planner,
+        })
+    }
+
+    /// Validator over an empty database.
+    pub fn empty() -> Result<Self, EngineError> {
+        Self::new(
+            SchemaManager::new().map_err(internal)?,
+            IndexRegistry::new(),
+        )
+    }
+
+    /// Validator over the catalog saved in `data_dir` (empty when there is none), read without
+    /// opening the engine: no WAL replay, no locks, nothing written.
+    pub fn for_data_dir(data_dir: &Path) -> Result<Self, EngineError> {
+        let Some(catalog) =
+            SchemaManager::try_load_catalog_from_data_dir(data_dir).map_err(internal)?
+        else {
+            return Self::empty();
+        };
+        let mut indexes = IndexRegistry::new();
+        for table in catalog.table_names() {
+            for idx in catalog
+                .schema(&table)
+                .map(|s| s.secondary_indexes.as_slice())
+                .unwrap_or_default()
+            {
+                let options = IndexOptions {
+                    method: idx.method,
+                    predicate: idx.predicate.clone(),
+                    expressions: idx.expressions.clone(),
+                };
+                indexes
+                    .create_index_with_options(&table, &idx.name, idx.columns.clone(), options)
+                    .map_err(|e| internal(format!("index {}: {e}", idx.name)))?;
+            }
+        }
+        Self::new(catalog, indexes)
+    }
+
+    /// Checks the statements of `sql` in order, applying their DDL to the catalog copy.
+    pub fn check(&mut self, sql: &str) -> ValidationReport {
+        let statements = match SqlParser::new(sql).and_then(|mut p| p.parse_multiple()) {
+            Ok(statements) => statements,
+            Err(e) => {
+                return ValidationReport {
+                    parse_error: Some(e.to_string()),
+                    statements: Vec::new(),
+                }
+            }
+        };
+        ValidationReport {
+            parse_error: None,
+            statements: statements.iter().map(|s| self.check_statement(s)).collect(),
+        }
+    }
+
+    fn check_statement(&mut self, stmt: &SqlStatement) -> StatementCheck {
+        let mut check = StatementCheck {
+            statement: describe(stmt),
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            plan: Vec::new(),
+        };
+        let planned = match stmt {
+            SqlStatement::Explain(ex) => {
+                return StatementCheck {
+                    statement: check.statement,
+                    ..self.check_statement(&ex.statement)
+                }
+            }
+            SqlStatement::Select(sel) => {
+                if system_views::system_view_name(sel).is_some() {
+                    return check;
+                }
+                self.check_select(sel, None, &mut check.errors);
+                true
+            }
+            SqlStatement::SetOperation(set) => {
+                self.check_select(&set.left, None, &mut check.errors);
+                self.check_select(&set.right, None, &mut check.errors);
+                true
+            }
+            SqlStatement::Insert(insert) => {
+                self.check_insert(insert, &mut check.errors);
+                true
+            }
+            SqlStatement::Update(update) => {
+                if let Some(schema) = self.table(&update.table, &mut check.errors) {
+                    for a in &update.assignments {
+                        match schema.columns.iter().find(|c| c.name == a.column) {
+                            Some(col) => {
+                                check_value(&a.value, col, &update.table, &mut check.errors)
+                            }
+                            None => check.errors.push(unknown_column(&update.table, &a.column)),
+                        }
+                    }
+                    let scope = Scope::table(&update.table, schema);
+                    for a in &update.assignments {
+                        self.check_expression(&a.value, &scope, &mut check.errors);
+                    }
+                    if let Some(w) = &update.where_clause {
+                        self.check_expression(w, &scope, &mut check.errors);
+                    }
+                }
+                if update.where_clause.is_none() {
+                    check
+                        .warnings
+                        .push("UPDATE without WHERE changes every row".into());
+                }
+                true
+            }
+            SqlStatement::Delete(delete) => {
+                if let Some(schema) = self.table(&delete.table, &mut check.errors) {
+                    if let Some(w) = &delete.where_clause {
+                        let scope = Scope::table(&delete.table, schema);
+                        self.check_expression(w, &scope, &mut check.errors);
+                    }
+                }
+                if delete.where_clause.is_none() {
+                    check
+                        .warnings
+                        .push("DELETE without WHERE removes every row".into());
+                }
+                true
+            }
+            _ => {
+                self.apply_ddl(stmt, &mut check);
+                false
+            }
+        };
+        if planned && check.errors.is_empty() {
+            match self.plan(stmt) {
+                Ok(lines) => check.plan = lines,
+                Err(e) => check.errors.push(format!("cannot plan: {e}")),
+            }
+        }
+        check
+    }
+
+    /// Checks DDL and other statements without a plan, applying DDL to the catalog copy.
+    fn apply_ddl(&mut self, stmt: &SqlStatement, check: &mut StatementCheck) {
+        let errors = &mut check.errors;
+        match stmt {
+            SqlStatement::CreateTable(ct) => {
+                if self.catalog.schema(&ct.table_name).is_some() {
+                    if ct.if_not_exists {
+                        check.warnings.push(format!(
+                            "table {} already exists; the statement does nothing",
+                            ct.table_name
+                        ));
+                    } else {
+                        errors.push(format!("table {} already exists", ct.table_name));
+                    }
+                    return;
+                }
+                let created = table_schema_from_create_table(ct).and_then(|schema| {
+                    validate_new_table_fks(&self.catalog, &schema)?;
+                    Ok(schema)
+                });
+                match created {
+                    Ok(schema) => {
+                        self.catalog.register_schema(schema);
+                    }
+                    Err(e) => errors.push(e.message),
+                }
+            }
+            SqlStatement::CreateForeignTable(cf) => {
+                if self.catalog.schema(&cf.table_name).is_some() {
+                    errors.push(format!("table {} already exists", cf.table_name));
+                    return;
+                }
+                // The adapter and its options are checked on execution, against the files.
+                let schema = TableSchema {
+                    table_name: cf.table_name.clone(),
+                    columns: cf
+                        .columns
+                        .iter()
+                        .map(|c| {
+                            Column::new(c.name.clone(), sql_type_to_column_datatype(&c.data_type))
+                        })
+                        .collect(),
+                    primary_key: None,
+                    unique_constraints: Vec::new(),
+                    foreign_keys: Vec::new(),
+                    check_constraints: Vec::new(),
+                    secondary_indexes: Vec::new(),
+                    foreign: Some(ForeignTableDef {
+                        adapter: cf.adapter.clone(),
+                        options: cf.options.clone(),
+                    }),
+                    encrypt_by: None,
+                    tenant: None,
+                };
+                if let Err(e) = self.planner.register_foreign_table(&schema) {
+                    errors.push(e.to_string());
+                }
+                self.catalog.register_schema(schema);
+            }
+            SqlStatement::CreateIndex(ci) => {
+                let Some(schema) = self.catalog.schema_mut(&ci.table_name) else {
+                    errors.push(unknown_table(&ci.table_name));
+                    return;
+                };
+                for col in ci.key_table_columns() {
+                    if !schema.columns.iter().any(|c| c.name == col) {
+                        errors.push(unknown_column(&ci.table_name, col));
+                    }
+                }
+                if schema
+                    .secondary_indexes
+                    .iter()
+                    .any(|i| i.name == ci.index_name)
+                {
+                    errors.push(format!(
+                        "index {} already exists on {}",
+                        ci.index_name, ci.table_name
+                    ));
+                }
+                if ci.concurrently && ci.method == IndexMethod::Hash {
+                    errors.push("hash indexes cannot be built concurrently".to_string());
+                }
+                if !errors.is_empty() {
+                    return;
+                }
+                schema.secondary_indexes.push(SecondaryIndexDef {
+                    name: ci.index_name.clone(),
+                    method: ci.method,
+                    columns: ci.columns.clone(),
+                    predicate: ci.where_clause.clone(),
+                    expressions: ci.expressions.clone(),
+                });
+                let options = IndexOptions {
+                    method: ci.method,
+                    predicate: ci.where_clause.clone(),
+                    expressions: ci.expressions.clone(),
+                };
+                if let Err(e) = self.indexes.create_index_with_options(
+                    &ci.table_name,
+                    &ci.index_name,
+                    ci.columns.clone(),
+                    options,
+                ) {
+                    errors.push(e.to_string());
+                }
+            }
+            SqlStatement::DropIndex(di) => match drop_index_table(&self.catalog, di) {
+                Ok(Some(table)) => {
+                    self.catalog.drop_secondary_index(&table, &di.index_name);
+                    if let Err(e) = self.indexes.drop_index(&table, &di.index_name) {
+                        errors.push(e.to_string());
+                    }
+                }
+                Ok(None) if di.if_exists => check.warnings.push(format!(
+                    "index {} does not exist; the statement does nothing",
+                    di.index_name
+                )),
+                Ok(None) => errors.push(format!("index {} does not exist", di.index_name)),
+                Err(e) => errors.push(e),
+            },
+            SqlStatement::AlterTable(at) => {
+                let table = at.table_name.as_str();
+                if let AlterTableOperation::RenameTable(new_name) = &at.operation {
+                    if self.catalog.schema(table).is_none() {
+                        errors.push(unknown_table(table));
+                    } else if self.catalog.schema(new_name).is_some() {
+                        errors.push(format!("table {new_name} already exists"));
+                    } else if let Err(e) = self.catalog.rename_table(table, new_name) {
+                        errors.push(e.to_string());
+                    }
+                    return;
+                }
+                let Some(schema) = self.catalog.schema_mut(table) else {
+                    errors.push(unknown_table(table));
+                    return;
+                };
+                let position = |name: &str| schema.columns.iter().position(|c| c.name == name);
+                match &at.operation {
+                    AlterTableOperation::AddColumn(cd) => match position(&cd.name) {
+                        Some(_) => {
+                            errors.push(format!("column {table}.{} already exists", cd.name))
+                        }
+                        None => schema.columns.push(Column::new(
+                            cd.name.clone(),
+                            sql_type_to_column_datatype(&cd.data_type),
+                        )),
+                    },
+                    AlterTableOperation::DropColumn(name) => match position(name) {
+                        Some(i) => {
+                            schema.columns.remove(i);
+                        }
+                        None => errors.push(unknown_column(table, name)),
+                    },
+                    AlterTableOperation::ModifyColumn(cd) => match position(&cd.name) {
+                        Some(i) => {
+                            schema.columns[i].data_type = sql_type_to_column_datatype(&cd.data_type)
+                        }
+                        None => errors.push(unknown_column(table, &cd.name)),
+                    },
+                    AlterTableOperation::RenameColumn { old_name, new_name } => {
+                        match (position(old_name), position(new_name)) {
+                            (None, _) => errors.push(unknown_column(table, old_name)),
+                            (Some(_), Some(_)) => {
+                                errors.push(format!("column {table}.{new_name} already exists"))
+                            }
+                            (Some(i), None) => schema.columns[i].name = new_name.clone(),
+                        }
+                    }
+                    AlterTableOperation::AddConstraint { name, definition } => {
+                        if let Err(e) =
+                            apply_table_constraint_to_schema(schema, name.as_deref(), definition)
+                        {
+                            errors.push(e.message);
+                        } else if let Some(schema) = self.catalog.schema(table) {
+                            if let Err(e) = validate_new_table_fks(&self.catalog, schema) {
+                                errors.push(e.message);
+                            }
+                        }
+                    }
+                    AlterTableOperation::DropConstraint(name) => {
+                        if let Err(e) = drop_constraint_by_name(schema, name) {
+                            errors.push(e.message);
+                        }
+                    }
+                    AlterTableOperation::RenameTable(_) => {}
+                }
+            }
+            SqlStatement::DropTable(dt) => {
+                if self.catalog.schema(&dt.table_name).is_none() {
+                    if dt.if_exists {
+                        check.warnings.push(format!(
+                            "table {} does not exist; the statement does nothing",
+                            dt.table_name
+                        ));
+                    } else {
+                        errors.push(unknown_table(&dt.table_name));
+                    }
+                    return;
+                }
+                let dependents = self.catalog.tables_with_fk_to(&dt.table_name);
+                if !dependents.is_empty() && !dt.cascade {
+                    errors.push(format!(
+                        "cannot DROP TABLE {}: referenced by foreign key from {:?} (use CASCADE)",
+                        dt.table_name, dependents
+                    ));
+                    return;
+                }
+                let mut dropped = vec![dt.table_name.clone()];
+                while let Some(table) = dropped.pop() {
+                    if self.catalog.schema(&table).is_none() {
+                        continue;
+                    }
+                    dropped.extend(self.catalog.tables_with_fk_to(&table));
+                    self.catalog.drop_table(&table);
+                    self.indexes.remove_all_indexes_for_table(&table);
+                }
+            }
+            SqlStatement::SetParameter { name, .. } => {
+                let session_parameter = [
+                    super::session_trace::TRACE_PARAMETER,
+                    super::workload_capture::CAPTURE_PARAMETER,
+                    super::roles::ROLE_PARAMETER,
+                ]
+                .iter()
+                .any(|p| p.eq_ignore_ascii_case(name))
+                    || super::read_your_writes::is_parameter(name);
+                if !session_parameter {
+                    if let Err(e) = parameter(name) {
+                        errors.push(e.to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_insert(&self, insert: &crate::parser::ast::InsertStatement, errors: &mut Vec<String>) {
+        let table = insert.table.as_str();
+        let Some(schema) = self.table(table, errors) else {
+            return;
+        };
+        let columns: Vec<&Column> = match &insert.columns {
+            Some(names) => names
+                .iter()
+                .filter_map(|n| {
+                    let col = schema.columns.iter().find(|c| c.name == *n);
+                    if col.is_none() {
+                        errors.push(unknown_column(table, n));
+                    }
+                    col
+                })
+                .collect(),
+            None => schema.columns.iter().collect(),
+        };
+        match &insert.values {
+            InsertValues::Values(rows) => {
+                let expected = insert.columns.as_ref().map_or(columns.len(), Vec::len);
+                for (i, row) in rows.iter().enumerate() {
+                    if row.len() != expected {
+                        errors.push(format!(
+                            "VALUES row {} has {} values for {expected} columns",
+                            i + 1,
+                            row.len()
+                        ));
+                    } else if columns.len() == expected {
+                        for (value, col) in row.iter().zip(&columns) {
+                            check_value(value, col, table, errors);
+                        }
+                    }
+                }
+            }
+            InsertValues::Select(sel) => self.check_select(sel, None, errors),
+        }
+        if insert.columns.is_some() {
+            for col in &schema.columns {
+                let listed = columns.iter().any(|c| c.name == col.name);
+                if col.not_null && col.default_value.is_none() && !listed {
+                    errors.push(format!(
+                        "column {table}.{} is NOT NULL without a default and is not set",
+                        col.name
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Schema of `name`, or an error for a missing table.
+    fn table(&self, name: &str, errors: &mut Vec<String>) -> Option<&TableSchema> {
+        let schema = self.catalog.schema(name);
+        if schema.is_none() {
+            errors.push(unknown_table(name));
+        }
+        schema
+    }
+
+    fn check_select(
+        &self,
+        sel: &SelectStatement,
+        parent: Option<&Scope>,
+        errors: &mut Vec<String>,
+    ) {
+        let mut scope = Scope {
+            relations: Vec::new(),
+            aliases: Vec::new(),
+            parent,
+        };
+        if let Some(from) = &sel.from {
+            self.add_from(from, parent, &mut scope, errors);
+        }
+        for item in &sel.select_list {
+            if let SelectItem::Expression { expr, alias } = item {
+                self.check_expression(expr, &scope, errors);
+                scope.aliases.extend(alias.clone());
+            }
+        }
+        if let Some(from) = &sel.from {
+            for join in &from.joins {
+                if let Some(on) = &join.condition {
+                    self.check_expression(on, &scope, errors);
+                }
+            }
+        }
+        for expr in sel
+            .where_clause
+            .iter()
+            .chain(&sel.group_by)
+            .chain(&sel.having)
+        {
+            self.check_expression(expr, &scope, errors);
+        }
+        for item in &sel.order_by {
+            self.check_expression(&item.expr, &scope, errors);
+        }
+    }
+
+    fn add_from<'s>(
+        &'s self,
+        from: &'s FromClause,
+        parent: Option<&Scope>,
+        scope: &mut Scope<'s, '_>,
+        errors: &mut Vec<String>,
+    ) {
+        for table in std::iter::once(&from.table).chain(from.joins.iter().map(|j| &j.table)) {
+            let relation = match table {
+                TableReference::Table { name, alias, .. } => Relation {
+                    name: alias.as_deref().unwrap_or(name),
+                    table: name,
+                    schema: self.table(name, errors),
+                },
+                TableReference::Subquery { query, alias } => {
+                    self.check_select(query, parent, errors);
+                    Relation {
+                        name: alias,
+                        table: alias,
+                        schema: None,
+                    }
+                }
+                TableReference::Function { name, alias, .. } => {
+                    errors.push(format!("function {name}() does not exist"));
+                    Relation {
+                        name: alias.as_deref().unwrap_or(name),
+                        table: name,
+                        schema: None,
+                    }
+                }
+            };
+            scope.relations.push(relation);
+        }
+    }
+
+    fn check_expression(&self, expr: &Expression, scope: &Scope, errors: &mut Vec<String>) {
+        match expr {
+            Expression::Literal(_) | Expression::Parameter(_) => {}
+            Expression::Identifier(name) => {
+                if name != "*" && !scope.resolves(name) {
+                    errors.push(format!("column {name} does not exist"));
+                }
+            }
+            Expression::QualifiedIdentifier { table, column } => {
+                if let Some(e) = scope.resolve_qualified(table, column) {
+                    errors.push(e);
+                }
+            }
+            Expression::BinaryOp { left, right, .. } => {
+                self.check_expression(left, scope, errors);
+                self.check_expression(right, scope, errors);
+            }
+            Expression::UnaryOp { expr, .. } | Expression::IsNull { expr, .. } => {
+                self.check_expression(expr, scope, errors)
+            }
+            Expression::Function { args, .. } => {
+                for a in args {
+                    self.check_expression(a, scope, errors);
+                }
+            }
+            Expression::Case {
+                expr,
+                when_clauses,
+                else_clause,
+            } => {
+                for e in expr.iter().chain(else_clause) {
+                    self.check_expression(e, scope, errors);
+                }
+                for w in when_clauses {
+                    self.check_expression(&w.condition, scope, errors);
+                    self.check_expression(&w.result, scope, errors);
+                }
+            }
+            Expression::Exists(sel) => self.check_select(sel, Some(scope), errors),
+            Expression::In { expr, list } => {
+                self.check_expression(expr, scope, errors);
+                match list {
+                    InList::Values(values) => {
+                        for v in values {
+                            self.check_expression(v, scope, errors);
+                        }
+                    }
+                    InList::Subquery(sel) => self.check_select(sel, Some(scope), errors),
+                }
+            }
+            Expression::Between { expr, low, high } => {
+                for e in [expr, low, high] {
+                    self.check_expression(e, scope, errors);
+                }
+            }
+            Expression::Like { expr, pattern, .. } => {
+                self.check_expression(expr, scope, errors);
+                self.check_expression(pattern, scope, errors);
+            }
+        }
+    }
+
+    /// `EXPLAIN` lines of the optimized plan, with the indexes known to the validator.
+    fn plan(&self, stmt: &SqlStatement) -> Result<Vec<String>, String> {
+        let plan = self.planner.create_plan(stmt).map_err(|e| e.to_string())?;
+        let optimizer = QueryOptimizer::new().map_err(|e| e.to_string())?;
+        let optimizer = if self.indexes.is_empty() {
+            optimizer
+        } else {
+            optimizer.with_index_registry(Arc::new(self.indexes.clone()))
+        };
+        let optimized = optimizer.optimize(plan).map_err(|e| e.to_string())?;
+        Ok(format_explain_output(
+            &optimized.optimized_plan,
+            &optimized,
+            ExplainFormatOptions::default(),
+        ))
+    }
+}
+
+impl SqlEngine {
+    /// A [`Validator`] over a copy of this engine's catalog and indexes.
+    pub fn validator(&self) -> Result<Validator, EngineError> {
+        let catalog = self
+            .state
+            .catalog
+            .lock()
+            .map_err(|_| lock_poisoned_engine())?
+            .clone();
+        let indexes = self
+            .state
+            .index_registry
+            .read()
+            .map_err(|_| lock_poisoned_engine())?
+            .clone();
+        Validator::new(catalog, indexes)
+    }
+
+    /// Dry run of `sql`: parses, resolves and plans its statements without executing them.
+    pub fn validate(&self, sql: &str) -> Result<ValidationReport, EngineError> {
+        Ok(self.validator()?.check(sql))
+    }
+}
+
+/// Tables of a `FROM` clause and the select list aliases, for resolving column references.
+struct Scope<'s, 'p> {
+    relations: Vec<Relation<'s>>,
+    aliases: Vec<String>,
+    /// Enclosing query of a correlated subquery
+    parent: Option<&'p Scope<'s, 'p>>,
+}
+
+struct Relation<'s> {
+    /// Alias, or the table name
+    name: &'s str,
+    table: &'s str,
+    /// `None` when the columns are not known (subqueries, missing tables)
+    schema: Option<&'s TableSchema>,
+}
+
+impl<'s> Scope<'s, '_> {
+    fn table(name: &'s str, schema: &'s TableSchema) -> Self {
+        Scope {
+            relations: vec![Relation {
+                name,
+                table: name,
+                schema: Some(schema),
+            }],
+            aliases: Vec::new(),
+            parent: None,
+        }
+    }
+
+    fn resolves(&self, column: &str) -> bool {
+        self.aliases.iter().any(|a| a == column)
+            || self.relations.iter().any(|r| match r.schema {
+                Some(s) => s.columns.iter().any(|c| c.name == column),
+                None => true,
+            })
+            || self.parent.is_some_and(|p| p.resolves(column))
+    }
+
+    /// Error for `table.column`, if it does not resolve.
+    fn resolve_qualified(&self, table: &str, column: &str) -> Option<String> {
+        match self.relations.iter().find(|r| r.name == table) {
+            Some(Relation {
+                schema: Some(s),
+                table: name,
+                ..
+            }) => {
+                (!s.columns.iter().any(|c| c.name == column)).then(|| unknown_column(name, column))
+            }
+            Some(_) => None,
+            None => match self.parent {
+                Some(p) => p.resolve_qualified(table, column),
+                None => Some(format!("table {table} is not in the FROM clause")),
+            },
+        }
+    }
+}
+
+/// Checks a value stored into `col` when it is a literal.
+fn check_value(value: &Expression, col: &Column, table: &str, errors: &mut Vec<String>) {
+    let literal = match value {
+        Expression::Literal(l) => l.clone(),
+        Expression::UnaryOp {
+            op: UnaryOperator::Minus,
+            expr,
+        } => match expr.as_ref() {
+            Expression::Literal(Literal::Integer(n)) => Literal::Integer(-n),
+            Expression::Literal(Literal::Float(x)) => Literal::Float(-x),
+            _ => return,
+        },
+        _ => return,
+    };
+    if let Some(problem) = literal_problem(&literal, &col.data_type) {
+        errors.push(format!("{table}.{}: {problem}", col.name));
+    }
+}
+
+/// Why `literal` cannot be stored in a column of type `ty`, if it cannot.
+fn literal_problem(literal: &Literal, ty: &DataType) -> Option<String> {
+    let integer_range = |n: i64| match ty {
+        DataType::TinyInt(_) => i8::try_from(n).is_ok(),
+        DataType::SmallInt(_) => i16::try_from(n).is_ok(),
+        DataType::Integer(_) => i32::try_from(n).is_ok(),
+        _ => true,
+    };
+    let type_name = type_name(ty);
+    let is_integer = matches!(
+        ty,
+        DataType::TinyInt(_) | DataType::SmallInt(_) | DataType::Integer(_) | DataType::BigInt(_)
+    );
+    let is_float = matches!(ty, DataType::Float(_) | DataType::Double(_));
+    let is_text = matches!(
+        ty,
+        DataType::Char(_) | DataType::Varchar(_) | DataType::Text(_)
+    );
+    let ok = match literal {
+        Literal::Null => return None,
+        Literal::Integer(n) if is_integer => {
+            if !integer_range(*n) {
+                return Some(format!("{n} is out of range for {type_name}"));
+            }
+            true
+        }
+        Literal::Integer(_) => is_float || is_text,
+        Literal::Float(_) => is_float || is_text,
+        Literal::Boolean(_) => matches!(ty, DataType::Boolean(_)) || is_text,
+        Literal::String(s) => {
+            // The parser keeps the quotes of string literals.
+            let s = s
+                .strip_prefix('\'')
+                .and_then(|s| s.strip_suffix('\''))
+                .unwrap_or(s)
+                .trim();
+            match ty {
+                _ if is_integer => s.parse::<i64>().is_ok_and(integer_range),
+                _ if is_float => s.parse::<f64>().is_ok(),
+                DataType::Boolean(_) => matches!(
+                    s.to_ascii_lowercase().as_str(),
+                    "true" | "false" | "t" | "f" | "1" | "0" | "yes" | "no"
+                ),
+                DataType::Date(_) => key_encoding::parse_date(s).is_some(),
+                DataType::Time(_) => key_encoding::parse_time(s).is_some(),
+                DataType::Timestamp(_) => {
+                    key_encoding::parse_timestamp(s).is_some()
+                        || key_encoding::parse_date(s).is_some()
+                }
+                _ => true,
+            }
+        }
+    };
+    (!ok).then(|| format!("{} is not a valid {type_name}", literal_text(literal)))
+}
+
+fn literal_text(literal: &Literal) -> String {
+    match literal {
+        Literal::Null => "NULL".to_string(),
+        Literal::Boolean(b) => b.to_string().to_uppercase(),
+        Literal::Integer(n) => n.to_string(),
+        Literal::Float(x) => x.to_string(),
+        Literal::String(s) => s.clone(),
+    }
+}
+
+fn type_name(ty: &DataType) -> &'static str {
+    match ty {
+        DataType::Null => "NULL",
+        DataType::Boolean(_) => "BOOLEAN",
+        DataType::TinyInt(_) => "TINYINT",
+        DataType::SmallInt(_) => "SMALLINT",
+        DataType::Integer(_) => "INTEGER",
+        DataType::BigInt(_) => "BIGINT",
+        DataType::Float(_) => "REAL",
+        DataType::Double(_) => "DOUBLE",
+        DataType::Char(_) => "CHAR",
+        DataType::Varchar(_) => "VARCHAR",
+        DataType::Text(_) => "TEXT",
+        DataType::Date(_) => "DATE",
+        DataType::Time(_) => "TIME",
+        DataType::Timestamp(_) => "TIMESTAMP",
+        DataType::Blob(_) => "BLOB",
+    }
+}
+
+fn unknown_table(table: &str) -> String {
+    format!("table {table} does not exist")
+}
+
+fn unknown_column(table: &str, column: &str) -> String {
+    format!("unknown column {table}.{column}")
+}
+
+fn internal(e: impl fmt::Display) -> EngineError {
+    EngineError::new(engine_error_code::INTERNAL, e.to_string())
+}
+
+/// Statement kind and target, e.g. `CREATE INDEX users_email_idx ON users`.
+fn describe(stmt: &SqlStatement) -> String {
+    match stmt {
+        SqlStatement::Select(sel) => match sel.from.as_ref().map(|f| &f.table) {
+            Some(TableReference::Table { name, .. }) => format!("SELECT FROM {name}"),
+            _ => "SELECT".to_string(),
+        },
+        SqlStatement::SetOperation(_) => "SELECT (set operation)".to_string(),
+        SqlStatement::Insert(i) => format!("INSERT INTO {}", i.table),
+        SqlStatement::Update(u) => format!("UPDATE {}", u.table),
+        SqlStatement::Delete(d) => format!("DELETE FROM {}", d.table),
+        SqlStatement::CreateTable(ct) => format!("CREATE TABLE {}", ct.table_name),
+        SqlStatement::CreateForeignTable(cf) => format!("CREATE FOREIGN TABLE {}", cf.table_name),
+        SqlStatement::CreateIndex(ci) => {
+            format!("CREATE INDEX {} ON {}", ci.index_name, ci.table_name)
+        }
+        SqlStatement::AlterTable(at) => format!("ALTER TABLE {}", at.table_name),
+        SqlStatement::DropTable(dt) => format!("DROP TABLE {}", dt.table_name),
+        SqlStatement::DropIndex(di) => format!("DROP INDEX {}", di.index_name),
+        SqlStatement::CreateDatabase { name, .. } => format!("CREATE DATABASE {name}"),
+        SqlStatement::FlashbackTable { table, .. } => format!("FLASHBACK TABLE {table}"),
+        SqlStatement::Explain(ex) => format!("EXPLAIN {}", describe(&ex.statement)),
+        SqlStatement::SetParameter { name, .. } => format!("SET {name}"),
+        SqlStatement::ShowParameter(Some(name)) => format!("SHOW {name}"),
+        other => format!("{other:?}")
+            .split(['(', ' ', '{'])
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literals_against_column_types() {
+        let int = DataType::Integer(0);
+        assert_eq!(literal_problem(&Literal::Integer(7), &int), None);
+        assert_eq!(literal_problem(&Literal::String("'42'".into()), &int), None);
+        assert_eq!(
+            literal_problem(&Literal::Integer(1 << 40), &int).as_deref(),
+            Some("1099511627776 is out of range for INTEGER")
+        );
+        assert_eq!(
+            literal_problem(&Literal::String("'abc'".into()), &int).as_deref(),
+            Some("'abc' is not a valid INTEGER")
+        );
+        assert!(literal_problem(&Literal::Float(1.5), &int).is_some());
+        assert!(literal_problem(&Literal::Boolean(true), &DataType::Double(0.0)).is_some());
+        assert_eq!(literal_problem(&Literal::Null, &int), None);
+        let date = DataType::Date(String::new());
+        assert_eq!(
+            literal_problem(&Literal::String("'2024-02-29'".into()), &date),
+            None
+        );
+        assert!(literal_problem(&Literal::String("'2024-13-01'".into()), &date).is_some());
+        assert_eq!(
+            literal_problem(&Literal::Integer(3), &DataType::Varchar(String::new())),
+            None
+        );
+    }
+}
@@ -0,0 +1,173 @@
+//! Workload capture: `SET workload_capture = on | off` and `SHOW workload_capture`.
+//!
+//! Unlike `trace`, the capture is server-wide: while it is on, every statement finished by any
+//! session is appended as one [`CapturedStatement`] (JSON) per line to
+//! `<data_dir>/captures/capture-<unix ms>.jsonl`, with its session, start offset, duration,
+//! row count and error. `rustdb replay` re-runs such a file against another instance (see
+//! [`crate::replay`]). Sessions are numbered on their first captured statement, so the numbers
+//! only map statements to sessions within the engine's lifetime.
+//!
+//! Turning the capture off closes the file; turning it on again starts a new one.
+
+use super::{lock_poisoned_engine, rows_to_engine_output, EngineError, EngineOutput};
+use crate::common::types::{ColumnValue, DataType, Row};
+use crate::network::engine::{engine_error_code, SessionContext};
+use crate::replay::CapturedStatement;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// Server parameter switching the capture on and off.
+pub(super) const CAPTURE_PARAMETER: &str = "workload_capture";
+
+/// Capture files, relative to the data directory.
+const CAPTURE_DIR: &str = "captures";
+
+/// Capture state of one engine.
+#[derive(Default)]
+pub(super) struct WorkloadCapture {
+    /// Checked before taking `current`, so uncaptured statements do not contend on it.
+    active: AtomicBool,
+    current: Mutex<Option<ActiveCapture>>,
+    next_session: AtomicU64,
+}
+
+struct ActiveCapture {
+    path: PathBuf,
+    file: File,
+    started: Instant,
+    statements: u64,
+}
+
+/// `SET workload_capture = on | off`
+pub(super) fn set_capture(
+    capture: &WorkloadCapture,
+    data_dir: &Path,
+    value: &str,
+) -> Result<EngineOutput, EngineError> {
+    let on = match value.to_ascii_lowercase().as_str() {
+        "on" | "true" | "1" => true,
+        "off" | "false" | "0" => false,
+        _ => {
+            return Err(EngineError::new(
+                engine_error_code::INVALID_PARAMETER,
+                format!(
+                "invalid value for parameter \"{CAPTURE_PARAMETER}\": {value} (expected on or off)"
+            ),
+            ))
+        }
+    };
+    let mut current = capture.current.lock().map_err(|_| lock_poisoned_engine())?;
+    if !on {
+        capture.active.store(false, Ordering::Release);
+        *current = None;
+    } else if current.is_none() {
+        let dir = data_dir.join(CAPTURE_DIR);
+        let started_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let path = dir.join(format!("capture-{started_ms}.jsonl"));
+        let file = std::fs::create_dir_all(&dir)
+            .and_then(|_| File::create(&path))
+            .map_err(|e| {
+                EngineError::new(
+                    engine_error_code::INTERNAL,
+                    format!("cannot create capture file {}: {e}", path.display()),
+                )
+            })?;
+        *current = Some(ActiveCapture {
+            path,
+            file,
+            started: Instant::now(),
+            statements: 0,
+        });
+        capture.active.store(true, Ordering::Release);
+    }
+    Ok(EngineOutput::ExecutionOk { rows_affected: 0 })
+}
+
+/// `SHOW workload_capture`: whether statements are captured, into which file, and how many so far.
+pub(super) fn show_capture(capture: &WorkloadCapture) -> Result<EngineOutput, EngineError> {
+    let current = capture.current.lock().map_err(|_| lock_poisoned_engine())?;
+    let text = |s: &str| ColumnValue::new(DataType::Varchar(format!("'{s}'")));
+    let mut row = Row::new();
+    row.set_value("name", text(CAPTURE_PARAMETER));
+    row.set_value(
+        "setting",
+        text(if current.is_some() { "on" } else { "off" }),
+    );
+    row.set_value("source", text("runtime"));
+    row.set_value(
+        "capture_file",
+        current
+            .as_ref()
+            .map_or_else(ColumnValue::null, |c| text(&c.path.display().to_string())),
+    );
+    row.set_value(
+        "statements",
+        ColumnValue::new(DataType::BigInt(
+            current.as_ref().map_or(0, |c| c.statements) as i64,
+        )),
+    );
+    rows_to_engine_output(vec![row])
+}
+
+/// Appends a statement of `ctx` that ran from `started` for `elapsed`, if a capture is on and
+/// was already on when it started.
+pub(super) fn record(
+    capture: &WorkloadCapture,
+    ctx: &mut SessionContext,
+    sql: &str,
+    started: Instant,
+    elapsed: Duration,
+    result: &Result<EngineOutput, EngineError>,
+) {
+    if !capture.active.load(Ordering::Acquire) {
+        return;
+    }
+    // Like statistics, the capture is best effort: a poisoned lock stops it, not the queries.
+    let Ok(mut current) = capture.current.lock() else {
+        return;
+    };
+    let Some(active) = current.as_mut() else {
+        return;
+    };
+    let Some(offset) = started.checked_duration_since(active.started) else {
+        return;
+    };
+    let session = *ctx
+        .capture_session
+        .get_or_insert_with(|| capture.next_session.fetch_add(1, Ordering::Relaxed) + 1);
+    let (rows, error) = match result {
+        Ok(EngineOutput::ResultSet { rows, .. }) => (rows.len() as u64, None),
+        Ok(EngineOutput::ExecutionOk { rows_affected }) => (*rows_affected, None),
+        Err(e) => (0, Some(e.message.clone())),
+    };
+    let statement = CapturedStatement {
+        session,
+        offset_us: offset.as_micros() as u64,
+        duration_us: elapsed.as_micros() as u64,
+        sql: sql.to_string(),
+        rows,
+        error,
+    };
+    let written = serde_json::to_string(&statement)
+        .map_err(std::io::Error::other)
+        .and_then(|mut line| {
+            line.push('\n');
+            active.file.write_all(line.as_bytes())
+        });
+    match written {
+        Ok(()) => active.statements += 1,
+        Err(e) => warn!(
+            file = %active.path.display(),
+            error = %e,
+            "cannot write captured statement"
+        ),
+    }
+}
@@ -4,14 +4,16 @@ use crate::common::Error as DbError;
 use crate::common::Result as DbResult;
 use crate::logging::checkpoint::{CheckpointConfig, CheckpointManager, DirtyPageFlusher};
 use crate::logging::log_record::{
-    IsolationLevel as LogIsolationLevel, LogOperationData, LogRecord, LogRecordType, TransactionId,
+    IsolationLevel as LogIsolationLevel, LogOperationData, LogRecord, LogRecordType,
+    LogSequenceNumber, TransactionId,
 };
 use crate::logging::log_writer::{LogWriter, LogWriterConfig};
 use crate::logging::recovery::{RecoveryConfig, RecoveryManager};
 use crate::network::engine::{engine_error_code, EngineError, SqlIsolationLevel, SqlTransaction};
+use crate::storage::database_file::PageId;
 use crate::storage::page_manager::PageManager;
 use rayon::prelude::*;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::path::Path;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
@@ -19,13 +21,48 @@ use std::time::{Duration, Instant};
 use tokio::runtime::Runtime;
 
 pub struct SqlEngineWal {
-    runtime: Runtime,
+    /// Taken on drop (see `Drop`).
+    runtime: Option<Runtime>,
     writer: Arc<LogWriter>,
     next_tx_id: AtomicU64,
-    checkpoint: Mutex<Option<CheckpointManager>>,
+    checkpoint: Mutex<Option<Arc<CheckpointManager>>>,
 }
 
+/// Engine background job running timed checkpoints (paused unless `RUSTDB_AUTO_CHECKPOINT=1`).
+pub const CHECKPOINT_JOB: &str = "checkpoint";
+
+/// Engine background job writing the heap pages of commits that deferred their flush (see
+/// [`SqlEngineWal::setup_background_writer`]).
+pub const BACKGROUND_WRITER_JOB: &str = "background_writer";
+
+/// Engine background job moving table extents between the tiers (see
+/// [`SqlEngineWal::setup_tiering`]).
+pub const TIERING_JOB: &str = "tiering";
+
+/// Engine background job analyzing tables with many changed rows (see
+/// [`SqlEngineWal::setup_auto_analyze`]).
+pub const AUTO_ANALYZE_JOB: &str = "auto_analyze";
+
+/// Default interval of [`BACKGROUND_WRITER_JOB`] (`RUSTDB_BGWRITER_INTERVAL_MS`).
+pub const DEFAULT_BGWRITER_INTERVAL_MS: u64 = 200;
+
+/// Engine background job uploading closed WAL segments (see [`SqlEngineWal::setup_wal_archive`]).
+pub const WAL_ARCHIVE_JOB: &str = "wal_archive";
+
+/// Key prefix of archived WAL segments in remote storage.
+pub const WAL_ARCHIVE_PREFIX: &str = "wal/";
+
+/// Metadata of the latest checkpoint, relative to the data directory
+/// (see [`CheckpointManager::load_metadata`]).
+pub const CHECKPOINT_METADATA_FILE: &str = ".rustdb/checkpoint.json";
+
 impl SqlEngineWal {
+    fn runtime(&self) -> &Runtime {
+        self.runtime
+            .as_ref()
+            .expect("WAL runtime is only taken on drop")
+    }
+
     /// Opens WAL under `wal_dir` (e.g. `data_dir/.rustdb/wal`), after recovery has run.
     pub fn open(wal_dir: &Path, synchronous_commit: bool) -> DbResult<Self> {
         let mut cfg = LogWriterConfig::default();
@@ -53,6 +90,11 @@ impl SqlEngineWal {
             .as_deref()
             .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
             .unwrap_or(false);
+        cfg.preallocate_segments = std::env::var("RUSTDB_WAL_PREALLOCATE")
+            .ok()
+            .as_deref()
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
         let runtime = Runtime::new().map_err(|e| DbError::database(e.to_string()))?;
         let writer = {
             let _guard = runtime.enter();
@@ -64,7 +106,7 @@ impl SqlEngineWal {
                 .unwrap_or(0);
         let next = max_tid.saturating_add(1).max(1);
         Ok(Self {
-            runtime,
+            runtime: Some(runtime),
             writer,
             next_tx_id: AtomicU64::new(next),
             checkpoint: Mutex::new(None),
@@ -72,6 +114,9 @@ impl SqlEngineWal {
     }
 
     /// Wires [`CheckpointManager`] to the same [`LogWriter`] and flushes all table heaps on checkpoint.
+    ///
+    /// Timed checkpoints run as [`CHECKPOINT_JOB`] in the engine's background jobs, on this WAL's
+    /// runtime.
     pub fn setup_checkpoint(
         &self,
         state: Arc<crate::network::sql_engine::SqlEngineState>,
@@ -79,32 +124,182 @@ impl SqlEngineWal {
         if std::env::var_os("RUSTDB_DISABLE_CHECKPOINT").is_some() {
             return Ok(());
         }
-        let _guard = self.runtime.enter();
+        let _guard = self.runtime().enter();
         let mut cfg = CheckpointConfig::default();
         cfg.quiet = true;
-        cfg.enable_auto_checkpoint =
-            matches!(std::env::var("RUSTDB_AUTO_CHECKPOINT").as_deref(), Ok("1"));
+        cfg.enable_auto_checkpoint = false;
+        cfg.metadata_path = Some(state.data_dir.join(CHECKPOINT_METADATA_FILE));
         if let Ok(s) = std::env::var("RUSTDB_CHECKPOINT_INTERVAL_SECS") {
             if let Ok(secs) = s.parse::<u64>() {
                 cfg.checkpoint_interval = Duration::from_secs(secs.max(1));
             }
         }
-        let mut cm = CheckpointManager::new(cfg, self.writer.clone());
-        let st = state.clone();
-        let flusher: DirtyPageFlusher = Arc::new(move || flush_all_page_managers(&st));
+        let interval = cfg.checkpoint_interval;
+        let cm = CheckpointManager::new(cfg, self.writer.clone());
+        // Weak: the state owns this WAL and its jobs, which own the flusher.
+        let st = Arc::downgrade(&state);
+        let flusher: DirtyPageFlusher = Arc::new(move || match st.upgrade() {
+            Some(st) => flush_all_page_managers(&st),
+            None => Ok(0),
+        });
         cm.set_dirty_page_flusher(flusher);
+        let cm = Arc::new(cm);
+        let auto = matches!(std::env::var("RUSTDB_AUTO_CHECKPOINT").as_deref(), Ok("1"));
+        let mgr = cm.clone();
+        state
+            .background_jobs
+            .register(CHECKPOINT_JOB, interval, !auto, move || {
+                let mgr = mgr.clone();
+                async move { mgr.create_checkpoint().await.map(|_| ()) }
+            })?;
         *self.checkpoint.lock().unwrap() = Some(cm);
         Ok(())
     }
 
+    /// Registers [`BACKGROUND_WRITER_JOB`] when commits or auto-commit DML defer their heap flush
+    /// (`RUSTDB_DEFER_HEAP_FLUSH_ON_COMMIT`, `RUSTDB_DEFER_HEAP_FLUSH_AFTER_DML`).
+    ///
+    /// Such a commit queues the pages it dirtied as a commit group (see
+    /// [`PageManager::queue_commit_group`]); every `RUSTDB_BGWRITER_INTERVAL_MS` (default
+    /// [`DEFAULT_BGWRITER_INTERVAL_MS`]) the job writes the queued groups of every table in file
+    /// order and fsyncs each heap file once, so recovery after a crash replays at most that
+    /// interval of commits instead of everything since the last checkpoint.
+    pub fn setup_background_writer(
+        &self,
+        state: Arc<crate::network::sql_engine::SqlEngineState>,
+    ) -> DbResult<()> {
+        if heap_flush_on_commit_enabled() && !heap_flush_after_dml_deferred() {
+            return Ok(());
+        }
+        let interval = std::env::var("RUSTDB_BGWRITER_INTERVAL_MS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_BGWRITER_INTERVAL_MS);
+        let _guard = self.runtime().enter();
+        let st = Arc::downgrade(&state);
+        state.background_jobs.register(
+            BACKGROUND_WRITER_JOB,
+            Duration::from_millis(interval.max(1)),
+            false,
+            move || {
+                let st = st.clone();
+                async move {
+                    tokio::task::spawn_blocking(move || match st.upgrade() {
+                        Some(st) => flush_commit_groups(&st).map(|_| ()),
+                        None => Ok(()),
+                    })
+                    .await
+                    .map_err(|e| DbError::internal(format!("background writer: {e}")))?
+                }
+            },
+        )
+    }
+
+    /// Runs [`crate::network::sql_engine::run_tiering`] as [`TIERING_JOB`] every `interval`
+    /// (see `tiering.cold_tablespace`).
+    pub fn setup_tiering(
+        &self,
+        state: Arc<crate::network::sql_engine::SqlEngineState>,
+        interval: Duration,
+    ) -> DbResult<()> {
+        let _guard = self.runtime().enter();
+        let st = Arc::downgrade(&state);
+        state
+            .background_jobs
+            .register(TIERING_JOB, interval, false, move || {
+                let st = st.clone();
+                async move {
+                    tokio::task::spawn_blocking(move || match st.upgrade() {
+                        Some(st) => crate::network::sql_engine::run_tiering(&st)
+                            .map(|_| ())
+                            .map_err(|e| DbError::database(e.message)),
+                        None => Ok(()),
+                    })
+                    .await
+                    .map_err(|e| DbError::internal(format!("tiering: {e}")))?
+                }
+            })
+    }
+
+    /// Runs [`crate::network::sql_engine::run_auto_analyze`] as [`AUTO_ANALYZE_JOB`] every
+    /// `interval` (see `auto_analyze.interval_secs`).
+    pub fn setup_auto_analyze(
+        &self,
+        state: Arc<crate::network::sql_engine::SqlEngineState>,
+        interval: Duration,
+    ) -> DbResult<()> {
+        let _guard = self.runtime().enter();
+        let st = Arc::downgrade(&state);
+        state
+            .background_jobs
+            .register(AUTO_ANALYZE_JOB, interval, false, move || {
+                let st = st.clone();
+                async move {
+                    tokio::task::spawn_blocking(move || match st.upgrade() {
+                        Some(st) => crate::network::sql_engine::run_auto_analyze(&st)
+                            .map(|_| ())
+                            .map_err(|e| DbError::database(e.message)),
+                        None => Ok(()),
+                    })
+                    .await
+                    .map_err(|e| DbError::internal(format!("auto-analyze: {e}")))?
+                }
+            })
+    }
+
+    /// Archives closed segments of `wal_dir` to the remote storage at `url` as
+    /// [`WAL_ARCHIVE_JOB`], every `RUSTDB_WAL_ARCHIVE_INTERVAL_SECS` (default 60) and after each
+    /// `FLUSH LOGS`. Segments already present remotely are not uploaded again.
+    ///
+    /// The segment resumed on open is closed first, so archived segments never grow afterwards.
+    pub fn setup_wal_archive(
+        &self,
+        state: Arc<crate::network::sql_engine::SqlEngineState>,
+        wal_dir: &Path,
+        url: &str,
+    ) -> DbResult<()> {
+        let remote = crate::storage::remote::open_remote(url)
+            .map_err(|e| DbError::database(format!("WAL archive {url}: {e}")))?;
+        let interval = std::env::var("RUSTDB_WAL_ARCHIVE_INTERVAL_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .map_or(Duration::from_secs(60), |secs| {
+                Duration::from_secs(secs.max(1))
+            });
+        self.rotate()
+            .map_err(|e| DbError::database(format!("WAL archive: {}", e.message)))?;
+        let _guard = self.runtime().enter();
+        let writer = self.writer.clone();
+        let wal_dir = wal_dir.to_path_buf();
+        // `None` until the remote listing has been read once.
+        let archived: Arc<Mutex<Option<HashSet<String>>>> = Arc::new(Mutex::new(None));
+        state
+            .background_jobs
+            .register(WAL_ARCHIVE_JOB, interval, false, move || {
+                let (writer, remote, wal_dir, archived) = (
+                    writer.clone(),
+                    remote.clone(),
+                    wal_dir.clone(),
+                    archived.clone(),
+                );
+                async move {
+                    tokio::task::spawn_blocking(move || {
+                        archive_closed_segments(&writer, &*remote, &wal_dir, &archived)
+                    })
+                    .await
+                    .map_err(|e| DbError::internal(format!("WAL archive task: {e}")))?
+                }
+            })?;
+        Ok(())
+    }
+
     pub fn checkpoint(&self) -> DbResult<()> {
-        let guard = self.checkpoint.lock().unwrap();
-        let Some(mgr) = guard.as_ref() else {
+        let Some(mgr) = self.checkpoint.lock().unwrap().clone() else {
             return Err(DbError::database(
                 "checkpoints disabled (WAL off or RUSTDB_DISABLE_CHECKPOINT)",
             ));
         };
-        self.runtime
+        self.runtime()
             .block_on(async { mgr.create_checkpoint().await })
             .map_err(|e| DbError::database(e.to_string()))?;
         Ok(())
@@ -116,7 +311,10 @@ impl SqlEngineWal {
     ) -> Option<crate::logging::checkpoint::CheckpointStatistics> {
         let guard = self.checkpoint.lock().ok()?;
         let mgr = guard.as_ref()?;
-        Some(self.runtime.block_on(async { mgr.get_statistics().await }))
+        Some(
+            self.runtime()
+                .block_on(async { mgr.get_statistics().await }),
+        )
     }
 
     pub fn log_begin(
@@ -129,7 +327,7 @@ impl SqlEngineWal {
         let log_iso = map_sql_isolation_to_log(iso);
         let record = LogRecord::new_transaction_begin(0, tid, log_iso);
         let lsn = self
-            .runtime
+            .runtime()
             .block_on(self.writer.write_log_durable(record))
             .map_err(|e| EngineError::new(engine_error_code::INTERNAL, e.to_string()))?;
         tx.wal_begin_lsn = Some(lsn);
@@ -137,7 +335,12 @@ impl SqlEngineWal {
         Ok(())
     }
 
-    pub fn log_commit(&self, tx: &mut SqlTransaction) -> std::result::Result<(), EngineError> {
+    /// `origin_commit_time`: see [`LogRecord::set_origin_commit_time`].
+    pub fn log_commit(
+        &self,
+        tx: &mut SqlTransaction,
+        origin_commit_time: Option<u64>,
+    ) -> std::result::Result<(), EngineError> {
         let tid = tx.wal_tx_id.ok_or_else(|| {
             EngineError::new(
                 engine_error_code::INTERNAL,
@@ -145,9 +348,12 @@ impl SqlEngineWal {
             )
         })?;
         let prev = tx.wal_last_lsn.or(tx.wal_begin_lsn);
-        let record = LogRecord::new_transaction_commit(0, tid, vec![], prev);
+        let mut record = LogRecord::new_transaction_commit(0, tid, vec![], prev);
+        if let Some(t) = origin_commit_time {
+            record.set_origin_commit_time(t);
+        }
         let lsn = self
-            .runtime
+            .runtime()
             .block_on(self.writer.write_log_durable(record))
             .map_err(|e| EngineError::new(engine_error_code::INTERNAL, e.to_string()))?;
         tx.wal_last_lsn = Some(lsn);
@@ -164,7 +370,7 @@ impl SqlEngineWal {
         let prev = tx.wal_last_lsn.or(tx.wal_begin_lsn);
         let record = LogRecord::new_transaction_abort(0, tid, prev);
         let lsn = self
-            .runtime
+            .runtime()
             .block_on(self.writer.write_log_durable(record))
             .map_err(|e| EngineError::new(engine_error_code::INTERNAL, e.to_string()))?;
         tx.wal_last_lsn = Some(lsn);
@@ -179,7 +385,7 @@ impl SqlEngineWal {
         prev_lsn: Option<u64>,
     ) -> std::result::Result<(), EngineError> {
         let record = LogRecord::new_transaction_abort(0, tid, prev_lsn);
-        self.runtime
+        self.runtime()
             .block_on(self.writer.write_log_durable(record))
             .map_err(|e| EngineError::new(engine_error_code::INTERNAL, e.to_string()))?;
         Ok(())
@@ -210,7 +416,7 @@ impl SqlEngineWal {
             prev,
         );
         let lsn = self
-            .runtime
+            .runtime()
             // Flush to the log file so crash recovery can reliably UNDO uncommitted writes,
             // without necessarily waiting for fsync on every statement.
             .block_on(self.writer.write_log_durable(record))
@@ -246,7 +452,7 @@ impl SqlEngineWal {
             prev,
         );
         let lsn = self
-            .runtime
+            .runtime()
             .block_on(self.writer.write_log_durable(record))
             .map_err(|e| EngineError::new(engine_error_code::INTERNAL, e.to_string()))?;
         tx.wal_last_lsn = Some(lsn);
@@ -271,7 +477,7 @@ impl SqlEngineWal {
         let record =
             LogRecord::new_data_delete(0, tid, file_id, page_id, record_offset, old_data, prev);
         let lsn = self
-            .runtime
+            .runtime()
             .block_on(self.writer.write_log_durable(record))
             .map_err(|e| EngineError::new(engine_error_code::INTERNAL, e.to_string()))?;
         tx.wal_last_lsn = Some(lsn);
@@ -282,25 +488,132 @@ impl SqlEngineWal {
     /// sequence reflects catalog persistence (replay ignores this record; it is not tied to a user transaction).
     pub fn log_catalog_snapshot(&self) -> std::result::Result<(), EngineError> {
         let record = LogRecord::new_catalog_snapshot(0);
-        self.runtime
+        self.runtime()
             .block_on(self.writer.write_log_durable(record))
             .map_err(|e| EngineError::new(engine_error_code::INTERNAL, e.to_string()))?;
         Ok(())
     }
 
+    /// Append the text of a successful DDL statement for logical replication (replay ignores it).
+    pub fn log_ddl(
+        &self,
+        sql: &str,
+        origin_commit_time: Option<u64>,
+    ) -> std::result::Result<LogSequenceNumber, EngineError> {
+        let mut record = LogRecord::new_ddl_statement(0, sql);
+        if let Some(t) = origin_commit_time {
+            record.set_origin_commit_time(t);
+        }
+        self.runtime()
+            .block_on(self.writer.write_log_durable(record))
+            .map_err(|e| EngineError::new(engine_error_code::INTERNAL, e.to_string()))
+    }
+
+    /// Append a high-water mark for `sequence` before values below `high_water` are handed out,
+    /// so they are never reissued after a restart (see [`WalReplayStats::tuple_id_high_water`]).
+    pub fn log_sequence_high_water(
+        &self,
+        sequence: &str,
+        high_water: u64,
+    ) -> std::result::Result<(), EngineError> {
+        let record = LogRecord::new_sequence_high_water(0, sequence, high_water);
+        self.runtime()
+            .block_on(self.writer.write_log_durable(record))
+            .map_err(|e| EngineError::new(engine_error_code::INTERNAL, e.to_string()))?;
+        Ok(())
+    }
+
+    /// Flush buffered WAL records and close the current segment, so later records go to a new
+    /// one (`FLUSH LOGS`).
+    pub fn rotate(&self) -> std::result::Result<(), EngineError> {
+        self.runtime()
+            .block_on(self.writer.rotate_log_file())
+            .map_err(|e| EngineError::new(engine_error_code::INTERNAL, e.to_string()))
+    }
+
+    /// Write counters of the log writer.
+    pub fn writer_statistics(&self) -> crate::logging::log_writer::LogWriterStatistics {
+        self.writer.get_statistics()
+    }
+
+    /// LSN of the last record written.
+    pub fn current_lsn(&self) -> LogSequenceNumber {
+        self.writer.current_lsn()
+    }
+
     /// Flush buffered WAL records (and fsync when `synchronous_commit` is enabled).
     pub fn flush_buffered(&self) -> std::result::Result<(), EngineError> {
-        self.runtime
+        self.runtime()
             .block_on(self.writer.flush())
             .map_err(|e| EngineError::new(engine_error_code::INTERNAL, e.to_string()))
     }
 }
 
+/// Uploads every `.log` segment of `wal_dir` that `writer` no longer appends to and that is not
+/// in `archived` yet.
+fn archive_closed_segments(
+    writer: &LogWriter,
+    remote: &dyn crate::storage::remote::RemoteStorage,
+    wal_dir: &Path,
+    archived: &Mutex<Option<HashSet<String>>>,
+) -> DbResult<()> {
+    let remote_err = |e: crate::storage::remote::RemoteStorageError| {
+        DbError::database(format!("WAL archive: {e}"))
+    };
+    let mut archived = archived.lock().unwrap();
+    if archived.is_none() {
+        let listed = remote.list(WAL_ARCHIVE_PREFIX).map_err(remote_err)?;
+        *archived = Some(
+            listed
+                .iter()
+                .filter_map(|k| k.strip_prefix(WAL_ARCHIVE_PREFIX))
+                .map(str::to_string)
+                .collect(),
+        );
+    }
+    let archived = archived.as_mut().expect("listed above");
+    let mut segments: Vec<String> = std::fs::read_dir(wal_dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".log") && !archived.contains(name))
+        .collect();
+    // Asked after listing: a segment opened meanwhile is still the current one.
+    let current = writer
+        .current_segment_path()
+        .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()));
+    segments.retain(|name| Some(name) != current.as_ref());
+    segments.sort();
+    for name in segments {
+        let mut file = std::fs::File::open(wal_dir.join(&name))?;
+        let len = file.metadata()?.len();
+        remote
+            .put(&format!("{WAL_ARCHIVE_PREFIX}{name}"), &mut file, len)
+            .map_err(remote_err)?;
+        archived.insert(name);
+    }
+    Ok(())
+}
+
 impl Drop for SqlEngineWal {
     fn drop(&mut self) {
+        let Some(runtime) = self.runtime.take() else {
+            return;
+        };
         // Do not abort the writer with records still in the in-memory buffer (fixes flaky
         // crash_matrix recovery tests and data loss on engine teardown).
-        let _ = self.runtime.block_on(self.writer.flush());
+        if tokio::runtime::Handle::try_current().is_ok() {
+            // The last engine handle was dropped on an async thread, where blocking on this
+            // runtime or a blocking shutdown panics: flush from a helper thread instead.
+            let writer = &self.writer;
+            std::thread::scope(|s| {
+                s.spawn(|| {
+                    let _ = runtime.block_on(writer.flush());
+                });
+            });
+            runtime.shutdown_background();
+        } else {
+            let _ = runtime.block_on(self.writer.flush());
+        }
     }
 }
 
@@ -335,6 +648,12 @@ pub(crate) fn heap_flush_on_commit_enabled() -> bool {
     std::env::var_os("RUSTDB_DEFER_HEAP_FLUSH_ON_COMMIT").is_none_or(|v| v == "0")
 }
 
+/// Whether implicit auto-commit DML leaves its dirty heap pages to the background writer
+/// (**`RUSTDB_DEFER_HEAP_FLUSH_AFTER_DML=1`**, WAL on).
+pub(crate) fn heap_flush_after_dml_deferred() -> bool {
+    std::env::var_os("RUSTDB_DEFER_HEAP_FLUSH_AFTER_DML").is_some_and(|v| v != "0")
+}
+
 /// Microsecond breakdown for `COMMIT` heap flush (logged on `rustdb::sql_phases`).
 #[derive(Debug, Clone, Copy, Default)]
 pub(crate) struct CommitFlushPhaseUs {
@@ -344,10 +663,10 @@ pub(crate) struct CommitFlushPhaseUs {
 }
 
 fn flush_pm_writes_only(
-    pm: &Arc<crate::storage::page_manager::PageManagerMutex>,
+    pm: &Arc<crate::storage::page_manager::PageManagerLock>,
 ) -> DbResult<(usize, Option<u32>, u64)> {
     let lock_t0 = Instant::now();
-    let mut guard = pm.lock();
+    let mut guard = pm.write();
     let pm_lock_wait_us = lock_t0.elapsed().as_micros() as u64;
     if guard.dirty_page_count() == 0 {
         return Ok((0, None, pm_lock_wait_us));
@@ -360,7 +679,7 @@ fn flush_pm_writes_only(
 }
 
 fn sync_heap_files_after_coalesced_flush(
-    sync_targets: Vec<(u32, Arc<crate::storage::page_manager::PageManagerMutex>)>,
+    sync_targets: Vec<(u32, Arc<crate::storage::page_manager::PageManagerLock>)>,
 ) -> DbResult<u64> {
     if bench_defer_heap_fsync_enabled() {
         return Ok(0);
@@ -372,7 +691,7 @@ fn sync_heap_files_after_coalesced_flush(
             continue;
         }
         let lock_t0 = Instant::now();
-        pm.lock()
+        pm.write()
             .sync_heap_file()
             .map_err(|e| DbError::database(e.to_string()))?;
         let _ = lock_t0;
@@ -381,7 +700,7 @@ fn sync_heap_files_after_coalesced_flush(
 }
 
 fn coalesced_flush_page_managers(
-    pms: Vec<Arc<crate::storage::page_manager::PageManagerMutex>>,
+    pms: Vec<Arc<crate::storage::page_manager::PageManagerLock>>,
 ) -> DbResult<(usize, u64, u64)> {
     if pms.is_empty() {
         return Ok((0, 0, 0));
@@ -436,7 +755,7 @@ pub(crate) fn flush_page_managers_for_tables(
         .lock()
         .map_err(|_| DbError::database("table pm map lock poisoned"))?;
     let table_map_lock_us = map_t0.elapsed().as_micros() as u64;
-    let mut pms: Vec<Arc<crate::storage::page_manager::PageManagerMutex>> =
+    let mut pms: Vec<Arc<crate::storage::page_manager::PageManagerLock>> =
         Vec::with_capacity(sorted.len());
     let mut pm_lock_wait_us = 0u64;
     for name in &sorted {
@@ -444,7 +763,7 @@ pub(crate) fn flush_page_managers_for_tables(
             continue;
         };
         let lock_t0 = Instant::now();
-        let dirty = pm.lock().dirty_page_count() > 0;
+        let dirty = pm.read().dirty_page_count() > 0;
         pm_lock_wait_us += lock_t0.elapsed().as_micros() as u64;
         if dirty {
             pms.push(pm.clone());
@@ -466,22 +785,22 @@ pub(crate) fn flush_page_managers_for_tables(
 ///
 /// Skips managers with no dirty pages. `CommitFlushPhaseUs::table_map_lock_us` is always zero.
 pub(crate) fn flush_page_managers_cached(
-    pms: &[Arc<crate::storage::page_manager::PageManagerMutex>],
+    pms: &[Arc<crate::storage::page_manager::PageManagerLock>],
 ) -> DbResult<(usize, CommitFlushPhaseUs)> {
     if pms.is_empty() {
         return Ok((0, CommitFlushPhaseUs::default()));
     }
-    let mut dirty_pms: Vec<Arc<crate::storage::page_manager::PageManagerMutex>> = Vec::new();
+    let mut dirty_pms: Vec<Arc<crate::storage::page_manager::PageManagerLock>> = Vec::new();
     let mut pm_lock_wait_us = 0u64;
     for pm in pms {
         let lock_t0 = Instant::now();
-        let dirty = pm.lock().dirty_page_count() > 0;
+        let dirty = pm.read().dirty_page_count() > 0;
         pm_lock_wait_us += lock_t0.elapsed().as_micros() as u64;
         if dirty {
             dirty_pms.push(pm.clone());
         }
     }
-    dirty_pms.sort_by_key(|pm| pm.lock().file_id());
+    dirty_pms.sort_by_key(|pm| pm.read().file_id());
     let (flushed, flush_pm_wait, heap_fsync_us) = coalesced_flush_page_managers(dirty_pms)?;
     Ok((
         flushed,
@@ -493,10 +812,55 @@ pub(crate) fn flush_page_managers_cached(
     ))
 }
 
+/// Queues the dirty pages of `pms` as one commit group for [`BACKGROUND_WRITER_JOB`] (a
+/// `COMMIT` that skipped its heap flush).
+pub(crate) fn queue_commit_group(pms: &[Arc<crate::storage::page_manager::PageManagerLock>]) {
+    for pm in pms {
+        if pm.read().dirty_page_count() > 0 {
+            pm.write().queue_commit_group();
+        }
+    }
+}
+
+/// Writes the queued commit groups of every heap (see [`PageManager::queue_commit_group`]), in
+/// file order, with one `fsync` per heap file. Returns the pages written.
+pub(crate) fn flush_commit_groups(
+    state: &crate::network::sql_engine::SqlEngineState,
+) -> DbResult<usize> {
+    let mut pms: Vec<Arc<crate::storage::page_manager::PageManagerLock>> =
+        vec![state.default_page_manager.clone()];
+    let map = state
+        .table_page_managers
+        .lock()
+        .map_err(|_| DbError::database("table pm map lock poisoned"))?;
+    pms.extend(map.values().cloned());
+    drop(map);
+    let mut total = 0;
+    let mut sync_targets = Vec::new();
+    for pm in pms {
+        if pm.read().queued_commit_page_count() == 0 {
+            continue;
+        }
+        let (n, file_id) = {
+            let mut guard = pm.write();
+            let n = guard
+                .flush_commit_groups_no_sync()
+                .map_err(|e| DbError::database(e.to_string()))?;
+            (n, guard.file_id())
+        };
+        total += n;
+        if n > 0 {
+            sync_targets.push((file_id, pm));
+        }
+    }
+    sync_heap_files_after_coalesced_flush(sync_targets)?;
+    Ok(total)
+}
+
 pub(crate) fn flush_all_page_managers(
     state: &crate::network::sql_engine::SqlEngineState,
 ) -> DbResult<usize> {
-    let mut pms: Vec<Arc<crate::storage::page_manager::PageManagerMutex>> = Vec::new();
+    let mut pms: Vec<Arc<crate::storage::page_manager::PageManagerLock>> = Vec::new();
     pms.push(state.default_page_manager.clone());
     let map = state
         .table_page_managers
@@ -510,15 +874,104 @@ pub(crate) fn flush_all_page_managers(
     Ok(n)
 }
 
-pub fn replay_wal_into_engine(
+/// What [`replay_wal_into_engine`] re-applied and rolled back (startup recovery report).
+#[derive(Debug, Clone, Default)]
+pub(crate) struct WalReplayStats {
+    /// Records of finished transactions re-applied to the heap.
+    pub records_redone: usize,
+    /// Data changes of unfinished transactions taken back.
+    pub records_undone: usize,
+    /// Unfinished transactions that got an abort marker, ascending.
+    pub transactions_rolled_back: Vec<TransactionId>,
+    /// Distinct `(file_id, page_id)` pages changed by REDO or UNDO.
+    pub pages_repaired: usize,
+    /// Largest logged high-water mark of the tuple id sequence (`0` when none was logged).
+    pub tuple_id_high_water: u64,
+    /// Workers REDO ran on (one per heap file at most).
+    pub redo_workers: usize,
+    /// Time spent in REDO.
+    pub redo_duration: Duration,
+    /// Heap pages read ahead into the buffer pool for REDO.
+    pub pages_prefetched: usize,
+}
+
+/// How many records ahead of the one being redone the prefetcher is asked for its page.
+const REDO_PREFETCH_DISTANCE: usize = 64;
+
+/// Re-applies `records` (one heap file's, in LSN order) under a single page manager lock, while
+/// a helper thread reads the pages of upcoming records into the buffer pool. Returns how many
+/// applied, the pages they changed and how many pages the helper read.
+fn redo_file_records(
+    pm: &crate::storage::page_manager::PageManagerLock,
+    records: &[&LogRecord],
+) -> (usize, HashSet<(u32, PageId)>, usize) {
+    let page_of = |r: &LogRecord| match &r.operation_data {
+        LogOperationData::Record(op) => Some(op.page_id),
+        _ => None,
+    };
+    let mut g = pm.write();
+    let prefetcher = g.page_prefetcher();
+    std::thread::scope(|s| {
+        let (ahead, requests) = std::sync::mpsc::channel::<PageId>();
+        let helper = prefetcher.map(|mut prefetcher| {
+            s.spawn(move || {
+                for page_id in requests {
+                    prefetcher.prefetch(page_id);
+                }
+                prefetcher.pages_loaded()
+            })
+        });
+        let mut last_requested = None;
+        let mut request = |r: &LogRecord| {
+            let Some(page_id) = page_of(r).filter(|_| helper.is_some()) else {
+                return;
+            };
+            if last_requested != Some(page_id) {
+                last_requested = Some(page_id);
+                let _ = ahead.send(page_id);
+            }
+        };
+        records
+            .iter()
+            .take(REDO_PREFETCH_DISTANCE)
+            .for_each(|r| request(r));
+
+        let mut applied = 0;
+        let mut pages = HashSet::new();
+        for (i, r) in records.iter().enumerate() {
+            if let Some(upcoming) = records.get(i + REDO_PREFETCH_DISTANCE) {
+                request(upcoming);
+            }
+            if g.apply_log_record_recovery(r, true).is_ok() {
+                applied += 1;
+                if let LogOperationData::Record(op) = &r.operation_data {
+                    pages.insert((op.file_id, op.page_id));
+                }
+            }
+        }
+        drop(ahead);
+        let prefetched = helper.map_or(0, |h| h.join().unwrap_or(0));
+        (applied, pages, prefetched)
+    })
+}
+
+pub(crate) fn replay_wal_into_engine(
     state: &crate::network::sql_engine::SqlEngineState,
     wal_dir: &Path,
     wal: Option<&SqlEngineWal>,
-) -> DbResult<()> {
+) -> DbResult<WalReplayStats> {
     use crate::logging::log_record::LogRecordType;
     use std::collections::HashMap;
 
-    let (redo, undo_per_tx, pending_abort) = analyze_wal_for_replay(wal_dir)?;
+    let recs = LogRecord::read_log_records_from_directory(wal_dir)?;
+    let tuple_id_high_water = recs
+        .iter()
+        .filter_map(LogRecord::sequence_high_water)
+        .filter(|(sequence, _)| *sequence == crate::network::sql_engine::TUPLE_ID_SEQUENCE)
+        .map(|(_, high_water)| high_water)
+        .max()
+        .unwrap_or(0);
+    let (redo, undo_per_tx, pending_abort) = analyze_log_records(recs);
 
     // Ensure table page managers exist for all catalog tables so WAL file_ids can match.
     let mut table_names = {
@@ -527,6 +980,9 @@ pub fn replay_wal_into_engine(
             .lock()
             .map_err(|_| DbError::database("catalog lock poisoned"))?;
         cat.table_names()
+            .into_iter()
+            .filter(|t| cat.schema(t).is_some_and(|s| s.foreign.is_none()))
+            .collect::<Vec<_>>()
     };
     // Ensure deterministic file_id allocation across opens (important for WAL replay), regardless of
     // underlying map iteration order.
@@ -540,11 +996,11 @@ pub fn replay_wal_into_engine(
     // Applying every record to every page manager relies on filtering inside PageManager and
     // becomes incorrect if multiple managers reference the same file_id (which can happen during
     // open + catalog/table PM wiring).
-    let mut pm_by_file_id: HashMap<u32, Arc<crate::storage::page_manager::PageManagerMutex>> =
+    let mut pm_by_file_id: HashMap<u32, Arc<crate::storage::page_manager::PageManagerLock>> =
         HashMap::new();
     {
         let default = state.default_page_manager.clone();
-        let fid = default.lock().file_id();
+        let fid = default.read().file_id();
         pm_by_file_id.insert(fid, default);
     }
     {
@@ -553,7 +1009,7 @@ pub fn replay_wal_into_engine(
             .lock()
             .map_err(|_| DbError::database("table pm map lock poisoned"))?;
         for (_name, pm) in map.iter() {
-            let fid = pm.lock().file_id();
+            let fid = pm.read().file_id();
             pm_by_file_id.entry(fid).or_insert_with(|| pm.clone());
         }
     }
@@ -566,20 +1022,46 @@ pub fn replay_wal_into_engine(
         }
     };
 
-    // Apply REDO.
+    let mut stats = WalReplayStats {
+        tuple_id_high_water,
+        ..WalReplayStats::default()
+    };
+    let mut repaired_pages = HashSet::new();
+    let record_page = |r: &LogRecord| match &r.operation_data {
+        LogOperationData::Record(op) => Some((op.file_id, op.page_id)),
+        _ => None,
+    };
+
+    // Apply REDO. Records of one heap file touch only its page manager, so files are replayed
+    // in parallel, each by one worker in LSN order (which keeps the order of every page) with a
+    // helper reading ahead the pages its next records change.
     {
+        let redo_t0 = Instant::now();
+        let mut by_file: BTreeMap<u32, Vec<&LogRecord>> = BTreeMap::new();
         for r in &redo {
-            if let Some(fid) = record_file_id(r) {
-                if let Some(pm) = pm_by_file_id.get(&fid) {
-                    let mut g = pm.lock();
-                    let _ = g.apply_log_record_recovery(r, true);
-                }
+            if let Some(fid) = record_file_id(r).filter(|fid| pm_by_file_id.contains_key(fid)) {
+                by_file.entry(fid).or_default().push(r);
             }
         }
+        let partitions: Vec<_> = by_file
+            .iter()
+            .map(|(fid, records)| (&pm_by_file_id[fid], records))
+            .collect();
+        let replayed: Vec<_> = partitions
+            .par_iter()
+            .map(|(pm, records)| redo_file_records(pm, records))
+            .collect();
+        for (applied, pages, prefetched) in replayed {
+            stats.records_redone += applied;
+            repaired_pages.extend(pages);
+            stats.pages_prefetched += prefetched;
+        }
+        stats.redo_workers = partitions.len().min(rayon::current_num_threads());
+        stats.redo_duration = redo_t0.elapsed();
     }
 
     // Apply UNDO for active txs (reverse order per tx).
-    let mut aborted_during_undo = HashSet::new();
+    let mut rolled_back = HashSet::new();
     for tx_ops in undo_per_tx {
         let tx_id = tx_ops.first().and_then(|r| r.transaction_id);
         let last_lsn = tx_ops.first().map(|r| r.lsn);
@@ -592,8 +1074,11 @@ pub fn replay_wal_into_engine(
             }
             if let Some(fid) = record_file_id(r) {
                 if let Some(pm) = pm_by_file_id.get(&fid) {
-                    let mut g = pm.lock();
-                    let _ = g.apply_log_record_recovery(r, false);
+                    let mut g = pm.write();
+                    if g.apply_log_record_recovery(r, false).is_ok() {
+                        stats.records_undone += 1;
+                        repaired_pages.extend(record_page(r));
+                    }
                 }
             }
         }
@@ -609,14 +1094,16 @@ pub fn replay_wal_into_engine(
             wal.log_abort_by_id(tid, last_lsn).map_err(|e| {
                 DbError::database(format!("append recovery abort marker: {}", e.message))
             })?;
-            aborted_during_undo.insert(tid);
+        }
+        if let Some(tid) = tx_id {
+            rolled_back.insert(tid);
         }
     }
 
     // BEGIN-without-COMMIT/ABORT (and no DML ops recovered) still needs an abort marker so
     // subsequent opens do not treat the transaction as active forever.
     for (tid, last_lsn) in pending_abort {
-        if aborted_during_undo.contains(&tid) {
+        if rolled_back.contains(&tid) {
             continue;
         }
         if let Some(wal) = wal {
@@ -624,9 +1111,13 @@ pub fn replay_wal_into_engine(
                 DbError::database(format!("append recovery abort marker: {}", e.message))
             })?;
         }
+        rolled_back.insert(tid);
     }
 
-    Ok(())
+    stats.transactions_rolled_back = rolled_back.into_iter().collect();
+    stats.transactions_rolled_back.sort_unstable();
+    stats.pages_repaired = repaired_pages.len();
+    Ok(stats)
 }
 
 /// Returns `(redo_records, undo_groups, pending_abort_markers)`.
@@ -638,7 +1129,26 @@ pub fn analyze_wal_for_replay(
     Vec<(TransactionId, Option<u64>)>,
 )> {
     let recs = LogRecord::read_log_records_from_directory(wal_dir)?;
+    Ok(analyze_log_records(recs))
+}
+
+/// Data records of committed transactions in LSN order, with the oldest LSN still in the log
+/// (`None` when it is empty). Redoing index changes from a position needs the log to reach
+/// back to it.
+pub(crate) fn committed_changes(wal_dir: &Path) -> DbResult<(Option<u64>, Vec<LogRecord>)> {
+    let recs = LogRecord::read_log_records_from_directory(wal_dir)?;
+    let oldest = recs.iter().map(|r| r.lsn).min();
+    let (redo, _, _) = analyze_log_records(recs);
+    Ok((oldest, redo))
+}
 
+fn analyze_log_records(
+    recs: Vec<LogRecord>,
+) -> (
+    Vec<LogRecord>,
+    Vec<Vec<LogRecord>>,
+    Vec<(TransactionId, Option<u64>)>,
+) {
     #[derive(Default)]
     struct TxBuf {
         committed: bool,
@@ -692,7 +1202,7 @@ pub fn analyze_wal_for_replay(
     }
 
     redo.sort_by_key(|r| r.lsn);
-    Ok((redo, undo, pending_abort))
+    (redo, undo, pending_abort)
 }
 
 pub fn log_record_operation_parts(
@@ -761,16 +1271,16 @@ mod tests {
 
         let pm_clean = table_page_manager(state, "t_clean").unwrap();
         let pm_dirty = table_page_manager(state, "t_dirty").unwrap();
-        assert_eq!(pm_clean.lock().dirty_page_count(), 0);
-        assert!(pm_dirty.lock().dirty_page_count() > 0);
+        assert_eq!(pm_clean.read().dirty_page_count(), 0);
+        assert!(pm_dirty.read().dirty_page_count() > 0);
 
         let mut tables = HashSet::new();
         tables.insert("t_clean".to_string());
         tables.insert("t_dirty".to_string());
         let (flushed, _metrics) = flush_page_managers_for_tables(state, &tables).unwrap();
         assert!(flushed > 0);
-        assert_eq!(pm_clean.lock().dirty_page_count(), 0);
-        assert_eq!(pm_dirty.lock().dirty_page_count(), 0);
+        assert_eq!(pm_clean.read().dirty_page_count(), 0);
+        assert_eq!(pm_dirty.read().dirty_page_count(), 0);
 
         eng.execute_sql("COMMIT", &mut ctx).unwrap();
     }
@@ -801,15 +1311,15 @@ mod tests {
 
         let pm_clean = table_page_manager(state, "t_clean").unwrap();
         let pm_dirty = table_page_manager(state, "t_dirty").unwrap();
-        assert_eq!(pm_clean.lock().dirty_page_count(), 0);
-        assert!(pm_dirty.lock().dirty_page_count() > 0);
+        assert_eq!(pm_clean.read().dirty_page_count(), 0);
+        assert!(pm_dirty.read().dirty_page_count() > 0);
 
         let pms = vec![pm_clean.clone(), pm_dirty.clone()];
         let (flushed, phases) = flush_page_managers_cached(&pms).unwrap();
         assert!(flushed > 0);
         assert_eq!(phases.table_map_lock_us, 0);
-        assert_eq!(pm_clean.lock().dirty_page_count(), 0);
-        assert_eq!(pm_dirty.lock().dirty_page_count(), 0);
+        assert_eq!(pm_clean.read().dirty_page_count(), 0);
+        assert_eq!(pm_dirty.read().dirty_page_count(), 0);
 
         eng.execute_sql("COMMIT", &mut ctx).unwrap();
     }
@@ -0,0 +1,290 @@
+//! Client authentication: hba rules, built-in methods and the QUIC handshake.
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::net::{IpAddr, TcpListener};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use ring::hmac;
+
+use crate::common::config::parse_hba_rules;
+use crate::network::auth::{
+    hash_password, verify_password, AuthError, Authentication, Authenticator, Credentials,
+    PasswordAuthenticator,
+};
+
+fn credentials(user: &str, secret: Option<&str>, remote: &str) -> Credentials {
+    Credentials {
+        user: user.to_string(),
+        secret: secret.map(str::to_string),
+        remote: remote.parse::<IpAddr>().unwrap(),
+    }
+}
+
+fn authentication(rules: &str) -> Authentication {
+    Authentication::new(parse_hba_rules("test", rules).expect("rules"))
+}
+
+/// HS256 JWT over `claims`.
+fn sign_token(claims: &serde_json::Value, secret: &str) -> String {
+    let header = URL_SAFE_NO_PAD.encode(br#"{"alg":"HS256","typ":"JWT"}"#);
+    let payload = URL_SAFE_NO_PAD.encode(claims.to_string());
+    let signed = format!("{header}.{payload}");
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let signature = URL_SAFE_NO_PAD.encode(hmac::sign(&key, signed.as_bytes()));
+    format!("{signed}.{signature}")
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[test]
+fn first_matching_rule_picks_the_method() {
+    let hash = hash_password("s3cret");
+    assert!(verify_password("s3cret", &hash));
+    assert!(!verify_password("guess", &hash));
+    assert!(!verify_password("s3cret", "plain-text"));
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let passwords = dir.path().join("passwords");
+    std::fs::write(&passwords, format!("# users\nalice:{hash}\n")).unwrap();
+    let auth = authentication(&format!(
+        "host alice 127.0.0.1 password file=\"{}\"\n\
+         host bob all reject\n\
+         host all 10.0.0.0/8 trust\n",
+        passwords.display()
+    ));
+
+    assert_eq!(
+        auth.authenticate(&credentials("alice", Some("s3cret"), "127.0.0.1")),
+        Ok(())
+    );
+    assert!(matches!(
+        auth.authenticate(&credentials("ALICE", Some("guess"), "127.0.0.1")),
+        Err(AuthError::Rejected(_))
+    ));
+    assert!(matches!(
+        auth.authenticate(&credentials("alice", None, "127.0.0.1")),
+        Err(AuthError::Rejected(_))
+    ));
+    assert!(matches!(
+        auth.authenticate(&credentials("bob", Some("anything"), "10.1.2.3")),
+        Err(AuthError::Rejected(_))
+    ));
+    assert_eq!(
+        auth.authenticate(&credentials("carol", None, "10.1.2.3")),
+        Ok(())
+    );
+    assert!(matches!(
+        auth.authenticate(&credentials("carol", None, "192.168.0.1")),
+        Err(AuthError::NoMatchingRule { .. })
+    ));
+}
+
+#[test]
+fn unknown_methods_are_reported_until_registered() {
+    struct Pam;
+    impl Authenticator for Pam {
+        fn authenticate(
+            &self,
+            credentials: &Credentials,
+            options: &BTreeMap<String, String>,
+        ) -> Result<(), AuthError> {
+            assert_eq!(options.get("service").map(String::as_str), Some("rustdb"));
+            match credentials.secret.as_deref() {
+                Some("pam-ok") => Ok(()),
+                _ => Err(AuthError::Rejected("pam".to_string())),
+            }
+        }
+    }
+
+    let auth = authentication("host all all pam service=rustdb");
+    let err = auth.check_methods().unwrap_err();
+    assert!(err.to_string().contains("pam"), "{err}");
+    assert_eq!(
+        auth.authenticate(&credentials("alice", Some("pam-ok"), "::1")),
+        Err(AuthError::UnknownMethod("pam".to_string()))
+    );
+
+    let auth = auth.with_method("pam", Arc::new(Pam));
+    auth.check_methods().expect("registered");
+    assert_eq!(
+        auth.authenticate(&credentials("alice", Some("pam-ok"), "::1")),
+        Ok(())
+    );
+    assert!(auth
+        .authenticate(&credentials("alice", Some("no"), "::1"))
+        .is_err());
+}
+
+#[test]
+fn token_method_checks_signature_subject_and_lifetime() {
+    let auth = authentication("host all all token secret=k3y issuer=idp audience=rustdb");
+    let check = |token: &str| auth.authenticate(&credentials("alice", Some(token), "127.0.0.1"));
+    let claims = |sub: &str, exp: u64| serde_json::json!({ "sub": sub, "exp": exp, "iss": "idp", "aud": ["other", "rustdb"] });
+
+    assert_eq!(
+        check(&sign_token(&claims("alice", now() + 60), "k3y")),
+        Ok(())
+    );
+    for token in [
+        sign_token(&claims("alice", now() + 60), "wrong key"),
+        sign_token(&claims("bob", now() + 60), "k3y"),
+        sign_token(&claims("alice", now() - 60), "k3y"),
+        sign_token(&serde_json::json!({ "sub": "alice", "iss": "idp" }), "k3y"),
+        sign_token(
+            &serde_json::json!({ "sub": "alice", "exp": now() + 60, "iss": "evil", "aud": "rustdb" }),
+            "k3y",
+        ),
+        "not.a.token".to_string(),
+    ] {
+        assert!(
+            matches!(check(&token), Err(AuthError::Rejected(_))),
+            "{token} was accepted"
+        );
+    }
+
+    let auth = authentication("host all all token");
+    assert!(matches!(
+        auth.authenticate(&credentials("alice", Some("x"), "127.0.0.1")),
+        Err(AuthError::Unavailable(_))
+    ));
+}
+
+#[test]
+fn ldap_method_binds_as_the_user() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = std::thread::spawn(move || {
+        for _ in 0..2 {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![0u8; 512];
+            let n = stream.read(&mut request).unwrap();
+            let request = &request[..n];
+            let contains = |needle: &[u8]| request.windows(needle.len()).any(|w| w == needle);
+            assert!(contains(b"uid=alice,ou=people,dc=example"));
+            let code = if contains(b"right") { 0 } else { 49 };
+            // LDAPMessage { 1, BindResponse { resultCode, matchedDN "", diagnosticMessage "" } }
+            stream
+                .write_all(&[
+                    0x30, 0x0c, 0x02, 0x01, 0x01, 0x61, 0x07, 0x0a, 0x01, code, 0x04, 0x00, 0x04,
+                    0x00,
+                ])
+                .unwrap();
+        }
+    });
+    let auth = authentication(&format!(
+        "host all all ldap ldapserver=127.0.0.1 ldapport={port} ldapprefix=uid= \
+         ldapsuffix=\",ou=people,dc=example\""
+    ));
+
+    assert_eq!(
+        auth.authenticate(&credentials("alice", Some("right"), "127.0.0.1")),
+        Ok(())
+    );
+    assert!(matches!(
+        auth.authenticate(&credentials("alice", Some("wrong"), "127.0.0.1")),
+        Err(AuthError::Rejected(_))
+    ));
+    server.join().unwrap();
+
+    // Refused before any bind: DN injection and anonymous binds.
+    assert!(matches!(
+        auth.authenticate(&credentials("alice,ou=admins", Some("right"), "127.0.0.1")),
+        Err(AuthError::Rejected(_))
+    ));
+    assert!(matches!(
+        auth.authenticate(&credentials("alice", Some(""), "127.0.0.1")),
+        Err(AuthError::Rejected(_))
+    ));
+}
+
+#[cfg_attr(miri, ignore = "QUIC / tokio I/O not supported under Miri")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn quic_connections_authenticate_and_hold_their_role() {
+    use crate::network::client::{
+        authenticate, build_quinn_client_config, connect, make_client_endpoint, query_once,
+    };
+    use crate::network::engine::engine_error_code;
+    use crate::network::framing::ServerMessage;
+    use crate::network::server::{QuicServer, ServerConfig};
+    use crate::network::sql_engine::SqlEngine;
+
+    let dir = tempfile::TempDir::new().expect("tempdir");
+    let engine = Arc::new(SqlEngine::open(dir.path().to_path_buf()).expect("open engine"));
+    let authentication = authentication("host all all password").with_method(
+        "password",
+        Arc::new(PasswordAuthenticator::default().with_user("alice", "s3cret")),
+    );
+    let srv = Arc::new(
+        QuicServer::bind(ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 0,
+            authentication: Some(Arc::new(authentication)),
+            ..Default::default()
+        })
+        .expect("bind server"),
+    );
+    let addr = srv.local_addr().expect("local addr");
+    let client_cfg =
+        build_quinn_client_config(std::slice::from_ref(srv.pinned_certificate())).expect("cfg");
+    let server = tokio::spawn({
+        let srv = srv.clone();
+        async move {
+            let _ = srv.run(engine).await;
+        }
+    });
+    let endpoint = make_client_endpoint(client_cfg).expect("client endpoint");
+
+    let conn = connect(&endpoint, addr, "127.0.0.1")
+        .await
+        .expect("connect");
+    match authenticate(&conn, "alice", Some("s3cret"))
+        .await
+        .expect("authenticate")
+    {
+        ServerMessage::ExecutionOk(_) => {}
+        other => panic!("unexpected message: {other:?}"),
+    }
+    match query_once(&conn, "SHOW role").await.expect("show") {
+        ServerMessage::ResultSet(p) => assert!(format!("{:?}", p.rows).contains("alice")),
+        other => panic!("unexpected message: {other:?}"),
+    }
+    match query_once(&conn, "SET role = 'etl'").await.expect("set") {
+        ServerMessage::Error(e) => assert_eq!(e.code, engine_error_code::AUTHENTICATION_FAILED),
+        other => panic!("unexpected message: {other:?}"),
+    }
+
+    // A wrong password and a connection that skips authentication are both refused and closed.
+    let conn = connect(&endpoint, addr, "127.0.0.1")
+        .await
+        .expect("connect");
+    match authenticate(&conn, "alice", Some("guess"))
+        .await
+        .expect("authenticate")
+    {
+        ServerMessage::Error(e) => {
+            assert_eq!(e.code, engine_error_code::AUTHENTICATION_FAILED);
+            assert!(!e.message.contains("guess"));
+        }
+        other => panic!("unexpected message: {other:?}"),
+    }
+    conn.closed().await;
+    let conn = connect(&endpoint, addr, "127.0.0.1")
+        .await
+        .expect("connect");
+    match query_once(&conn, "SELECT 1").await.expect("query") {
+        ServerMessage::Error(e) => assert_eq!(e.code, engine_error_code::AUTHENTICATION_FAILED),
+        other => panic!("unexpected message: {other:?}"),
+    }
+    conn.closed().await;
+    assert_eq!(srv.metrics().snapshot().authentication_failures, 2);
+    server.abort();
+}
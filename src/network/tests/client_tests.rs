@@ -58,3 +58,568 @@ async fn quic_client_query_roundtrip_localhost() {
     server.abort();
     panic!("connect failed: {:?}", last_err);
 }
+
+/// Rows of a result set, or the plan lines of an `EXPLAIN`
+fn result_rows(out: EngineOutput) -> Vec<Vec<String>> {
+    match out {
+        EngineOutput::ResultSet { rows, .. } => rows,
+        other => panic!("expected ResultSet, got {:?}", other),
+    }
+}
+
+#[cfg_attr(miri, ignore = "QUIC / tokio I/O not supported under Miri")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn rustdb_foreign_table_streams_remote_rows_with_pushdown() {
+    use crate::network::engine::{EngineHandle, SessionContext};
+    use crate::network::SqlEngine;
+
+    let remote_dir = tempfile::TempDir::new().expect("tempdir");
+    let remote_path = remote_dir.path().to_path_buf();
+    // The engine blocks on its own WAL runtime, so it runs off the test's async workers.
+    let remote = tokio::task::spawn_blocking(move || {
+        let remote = Arc::new(SqlEngine::open(remote_path).expect("open remote"));
+        let mut ctx = SessionContext::default();
+        remote
+            .execute_sql(
+                "CREATE TABLE items (id INT PRIMARY KEY, name TEXT, qty INT)",
+                &mut ctx,
+            )
+            .expect("create items");
+        for (id, name, qty) in [
+            (1, "a", 5),
+            (2, "b", 3),
+            (3, "c d", 9),
+            (4, "d", 1),
+            (5, "e", 7),
+        ] {
+            remote
+                .execute_sql(
+                    &format!("INSERT INTO items (id, name, qty) VALUES ({id}, '{name}', {qty})"),
+                    &mut ctx,
+                )
+                .expect("insert");
+        }
+        remote
+    })
+    .await
+    .expect("remote setup");
+    let srv = Arc::new(
+        QuicServer::bind(ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 0,
+            ..Default::default()
+        })
+        .expect("bind server"),
+    );
+    let addr = srv.local_addr().expect("local addr");
+    let local_dir = tempfile::TempDir::new().expect("tempdir");
+    std::fs::write(
+        local_dir.path().join("server.der"),
+        srv.pinned_certificate().as_ref(),
+    )
+    .expect("write cert");
+    let server = tokio::spawn({
+        let srv = srv.clone();
+        async move {
+            let _ = srv.run(remote).await;
+        }
+    });
+
+    let local_path = local_dir.path().to_path_buf();
+    let plan = tokio::task::spawn_blocking(move || {
+        let eng = SqlEngine::open(local_path).expect("open local");
+        let mut ctx = SessionContext::default();
+        let mut run = |sql: &str| result_rows(eng.execute_sql(sql, &mut ctx).expect(sql));
+        eng.execute_sql(
+            &format!(
+                "CREATE FOREIGN TABLE stock (id INT, name TEXT, qty INT) USING rustdb \
+                 OPTIONS (addr '{addr}', cert 'server.der', table 'items', fetch_size '2')"
+            ),
+            &mut SessionContext::default(),
+        )
+        .expect("create foreign");
+
+        // Five rows arrive over three pages of two.
+        let all = run("SELECT id, name FROM stock ORDER BY id");
+        assert_eq!(all.len(), 5);
+        assert_eq!(all[2], vec!["Integer(3)", "Varchar(\"'c d'\")"]);
+
+        let filtered = run("SELECT id FROM stock WHERE qty > 2 AND name <> 'c d' ORDER BY id");
+        assert_eq!(
+            filtered,
+            vec![vec!["Integer(1)"], vec!["Integer(2)"], vec!["Integer(5)"]]
+        );
+        assert_eq!(
+            run("SELECT id FROM stock WHERE qty BETWEEN 4 AND 8 LIMIT 1").len(),
+            1
+        );
+
+        eng.execute_sql(
+            "CREATE TABLE wanted (item INT PRIMARY KEY)",
+            &mut SessionContext::default(),
+        )
+        .expect("create wanted");
+        eng.execute_sql(
+            "INSERT INTO wanted (item) VALUES (3), (4)",
+            &mut SessionContext::default(),
+        )
+        .expect("insert wanted");
+        assert_eq!(
+            run("SELECT s.name FROM wanted w JOIN stock s ON w.item = s.id \
+                 WHERE s.qty > 2 AND w.item > 0"),
+            vec![vec!["Varchar(\"'c d'\")"]]
+        );
+
+        [
+            "EXPLAIN SELECT id FROM stock WHERE qty > 2 AND name <> 'c d' LIMIT 2",
+            "EXPLAIN SELECT s.name FROM wanted w JOIN stock s ON w.item = s.id \
+             WHERE s.qty > 2 AND w.item > 0",
+        ]
+        .map(|sql| {
+            run(sql)
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+    })
+    .await
+    .expect("local queries");
+    server.abort();
+
+    assert!(
+        plan[0].contains("Foreign Scan on stock using rustdb")
+            && plan[0].contains("limit=2 filter=(qty > 2) AND (name <> 'c d')"),
+        "{}",
+        plan[0]
+    );
+    // Only the conjunct on the foreign table is pushed. The rows are those the scan returned
+    // when the join ran above (cardinality feedback).
+    assert!(
+        plan[1].contains(
+            "Foreign Scan on stock AS s using rustdb (cost=2.00 rows=4) filter=(qty > 2)\n"
+        ),
+        "{}",
+        plan[1]
+    );
+}
+
+#[cfg_attr(miri, ignore = "QUIC / tokio I/O not supported under Miri")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn session_idle_in_transaction_is_rolled_back_and_closed() {
+    use crate::network::engine::engine_error_code;
+    use crate::network::framing::{
+        decode_server_frame_v1, encode_client_message_v1, ClientMessage, QueryPayload,
+        MAX_FRAME_PAYLOAD_BYTES,
+    };
+    use crate::network::query_stream::read_application_frame;
+    use crate::network::sql_engine::SqlEngine;
+    use std::time::Duration;
+
+    let dir = tempfile::TempDir::new().expect("tempdir");
+    let engine = Arc::new(SqlEngine::open(dir.path().to_path_buf()).expect("open engine"));
+    let srv = Arc::new(
+        QuicServer::bind(ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 0,
+            idle_in_transaction_timeout: Some(Duration::from_millis(200)),
+            ..Default::default()
+        })
+        .expect("bind server"),
+    );
+    let addr = srv.local_addr().expect("local addr");
+    let client_cfg =
+        build_quinn_client_config(std::slice::from_ref(srv.pinned_certificate())).expect("cfg");
+    let server = tokio::spawn({
+        let srv = srv.clone();
+        async move {
+            let _ = srv.run(engine).await;
+        }
+    });
+    let endpoint = make_client_endpoint(client_cfg).expect("client endpoint");
+    let conn = connect(&endpoint, addr, "127.0.0.1")
+        .await
+        .expect("connect");
+    query_once(&conn, "CREATE TABLE t (id INTEGER)")
+        .await
+        .expect("create");
+
+    let (mut send, mut recv) = conn.open_bi().await.expect("open stream");
+    for sql in ["BEGIN TRANSACTION", "INSERT INTO t VALUES (1)"] {
+        let frame = encode_client_message_v1(&ClientMessage::Query(QueryPayload {
+            sql: sql.to_string(),
+        }))
+        .expect("encode");
+        send.write_all(&frame).await.expect("send");
+        let reply = read_application_frame(&mut recv, MAX_FRAME_PAYLOAD_BYTES)
+            .await
+            .expect("reply");
+        match decode_server_frame_v1(&reply).expect("decode") {
+            ServerMessage::ExecutionOk(_) => {}
+            other => panic!("{sql}: unexpected message: {other:?}"),
+        }
+    }
+    // The session now idles inside its transaction until the server reaps it.
+    let reply = read_application_frame(&mut recv, MAX_FRAME_PAYLOAD_BYTES)
+        .await
+        .expect("reaper error frame");
+    match decode_server_frame_v1(&reply).expect("decode") {
+        ServerMessage::Error(e) => {
+            assert_eq!(e.code, engine_error_code::IDLE_IN_TRANSACTION_TIMEOUT)
+        }
+        other => panic!("expected an error frame, got {other:?}"),
+    }
+    assert_eq!(
+        srv.metrics().snapshot().sessions_reaped_idle_in_transaction,
+        1
+    );
+
+    // The rollback runs on the session's worker; it lands shortly after the error frame.
+    let mut rows = None;
+    for _ in 0..50 {
+        match query_once(&conn, "SELECT * FROM t").await.expect("select") {
+            ServerMessage::ResultSet(p) if p.rows.is_empty() => {
+                rows = Some(0);
+                break;
+            }
+            ServerMessage::ResultSet(p) => rows = Some(p.rows.len()),
+            other => panic!("unexpected message: {other:?}"),
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(rows, Some(0), "the idle transaction was not rolled back");
+    server.abort();
+}
+
+#[cfg_attr(miri, ignore = "QUIC / tokio I/O not supported under Miri")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn pipelined_requests_are_answered_in_order() {
+    use crate::network::engine::engine_error_code;
+    use crate::network::framing::{
+        decode_server_frame_v1, encode_client_message_v1, ClientMessage, QueryPayload,
+        MAX_FRAME_PAYLOAD_BYTES,
+    };
+    use crate::network::query_stream::read_application_frame;
+    use crate::network::sql_engine::SqlEngine;
+
+    let dir = tempfile::TempDir::new().expect("tempdir");
+    let engine = Arc::new(SqlEngine::open(dir.path().to_path_buf()).expect("open engine"));
+    let srv = Arc::new(
+        QuicServer::bind(ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 0,
+            max_pipelined_requests: 4,
+            ..Default::default()
+        })
+        .expect("bind server"),
+    );
+    let addr = srv.local_addr().expect("local addr");
+    let client_cfg =
+        build_quinn_client_config(std::slice::from_ref(srv.pinned_certificate())).expect("cfg");
+    let server = tokio::spawn({
+        let srv = srv.clone();
+        async move {
+            let _ = srv.run(engine).await;
+        }
+    });
+    let endpoint = make_client_endpoint(client_cfg).expect("client endpoint");
+    let conn = connect(&endpoint, addr, "127.0.0.1")
+        .await
+        .expect("connect");
+
+    // More requests than the pipeline depth, all sent before any answer is read.
+    let mut sqls = vec![
+        "CREATE TABLE t (id INTEGER PRIMARY KEY)".to_string(),
+        "BEGIN TRANSACTION".to_string(),
+    ];
+    sqls.extend((1..=6).map(|i| format!("INSERT INTO t VALUES ({i})")));
+    sqls.push("INSERT INTO t VALUES (1)".to_string());
+    sqls.push("COMMIT".to_string());
+    sqls.push("SELECT id FROM t".to_string());
+
+    let (mut send, mut recv) = conn.open_bi().await.expect("open stream");
+    for sql in &sqls {
+        let frame =
+            encode_client_message_v1(&ClientMessage::Query(QueryPayload { sql: sql.clone() }))
+                .expect("encode");
+        send.write_all(&frame).await.expect("send");
+    }
+    let _ = send.finish();
+
+    let mut answers = Vec::new();
+    for _ in &sqls {
+        let reply = read_application_frame(&mut recv, MAX_FRAME_PAYLOAD_BYTES)
+            .await
+            .expect("reply");
+        answers.push(decode_server_frame_v1(&reply).expect("decode"));
+    }
+    for (sql, answer) in sqls.iter().zip(&answers).take(8) {
+        assert!(
+            matches!(answer, ServerMessage::ExecutionOk(_)),
+            "{sql}: {answer:?}"
+        );
+    }
+    match &answers[8] {
+        ServerMessage::Error(e) => assert_eq!(e.code, engine_error_code::CONSTRAINT_VIOLATION),
+        other => panic!("duplicate key: {other:?}"),
+    }
+    match &answers[10] {
+        ServerMessage::ResultSet(p) => assert_eq!(p.rows.len(), 6),
+        other => panic!("select: {other:?}"),
+    }
+    server.abort();
+}
+
+#[cfg_attr(miri, ignore = "QUIC / tokio I/O not supported under Miri")]
+#[tokio::test]
+async fn binary_result_format_returns_typed_values() {
+    use crate::common::types::DataType;
+    use crate::network::client::query_once_with_format;
+    use crate::network::framing::ResultFormat;
+
+    let srv = Arc::new(
+        QuicServer::bind(ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 0,
+            ..Default::default()
+        })
+        .expect("bind server"),
+    );
+    let addr = srv.local_addr().expect("local addr");
+    let engine = Arc::new(StubEngine::fixed_ok(EngineOutput::ResultSet {
+        columns: vec!["id".into(), "name".into()],
+        rows: vec![
+            vec!["Integer(42)".into(), "Varchar(\"'ann'\")".into()],
+            vec!["BigInt(-1)".into(), "Null".into()],
+        ],
+    }));
+    let client_cfg =
+        build_quinn_client_config(std::slice::from_ref(srv.pinned_certificate())).expect("cfg");
+    let server = tokio::spawn({
+        let srv = srv.clone();
+        async move {
+            let _ = srv.run(engine).await;
+        }
+    });
+    let endpoint = make_client_endpoint(client_cfg).expect("client endpoint");
+    let conn = connect(&endpoint, addr, "127.0.0.1")
+        .await
+        .expect("connect");
+
+    match query_once_with_format(&conn, "SELECT id, name FROM t", ResultFormat::Binary)
+        .await
+        .expect("binary query")
+    {
+        ServerMessage::BinaryResultSet(p) => {
+            assert_eq!(p.columns, vec!["id", "name"]);
+            assert_eq!(
+                p.rows().expect("decode rows"),
+                vec![
+                    vec![DataType::Integer(42), DataType::Varchar("ann".into())],
+                    vec![DataType::BigInt(-1), DataType::Null],
+                ]
+            );
+        }
+        other => panic!("unexpected message: {other:?}"),
+    }
+    match query_once_with_format(&conn, "SELECT id, name FROM t", ResultFormat::Text)
+        .await
+        .expect("text query")
+    {
+        ServerMessage::ResultSet(p) => assert_eq!(p.rows[0][0], "Integer(42)"),
+        other => panic!("unexpected message: {other:?}"),
+    }
+    server.abort();
+}
+
+#[cfg_attr(miri, ignore = "QUIC / tokio I/O not supported under Miri")]
+#[tokio::test]
+async fn compression_is_negotiated_per_connection() {
+    use crate::network::client::build_compressed_quinn_client_config;
+    use crate::network::server::negotiated_compression;
+
+    let rows: Vec<Vec<String>> = (0..500)
+        .map(|i| {
+            vec![
+                format!("Integer({i})"),
+                "Varchar(\"'a repeated value'\")".into(),
+            ]
+        })
+        .collect();
+    let engine = Arc::new(StubEngine::fixed_ok(EngineOutput::ResultSet {
+        columns: vec!["id".into(), "name".into()],
+        rows: rows.clone(),
+    }));
+    let bind = |compression| {
+        Arc::new(
+            QuicServer::bind(ServerConfig {
+                host: "127.0.0.1".to_string(),
+                port: 0,
+                compression,
+                ..Default::default()
+            })
+            .expect("bind server"),
+        )
+    };
+    let srv = bind(true);
+    let plain_srv = bind(false);
+    let mut servers = Vec::new();
+    for s in [&srv, &plain_srv] {
+        let s = s.clone();
+        let engine = engine.clone();
+        servers.push(tokio::spawn(async move {
+            let _ = s.run(engine).await;
+        }));
+    }
+    let dial = |srv: &Arc<QuicServer>, compress: bool| {
+        let cert = srv.pinned_certificate().clone();
+        let addr = srv.local_addr().expect("local addr");
+        async move {
+            let cfg = if compress {
+                build_compressed_quinn_client_config(std::slice::from_ref(&cert))
+            } else {
+                build_quinn_client_config(std::slice::from_ref(&cert))
+            }
+            .expect("client cfg");
+            let endpoint = make_client_endpoint(cfg).expect("client endpoint");
+            let conn = connect(&endpoint, addr, "127.0.0.1")
+                .await
+                .expect("connect");
+            (endpoint, conn)
+        }
+    };
+
+    // Asked for and offered: the large result set and the large request travel compressed.
+    let (_endpoint, conn) = dial(&srv, true).await;
+    assert!(negotiated_compression(&conn));
+    let sql = format!(
+        "SELECT id, name FROM t WHERE name IN ({})",
+        vec!["'x'"; 400].join(", ")
+    );
+    match query_once(&conn, &sql).await.expect("query") {
+        ServerMessage::ResultSet(p) => assert_eq!(p.rows, rows),
+        other => panic!("unexpected message: {other:?}"),
+    }
+    let m = srv.metrics().snapshot();
+    assert_eq!(m.compressed_connections, 1);
+    assert_eq!(m.frames_compressed, 1);
+    assert_eq!(m.frames_decompressed, 1);
+    assert!(m.compression_bytes_saved > 0);
+
+    // Not asked for, or not offered: plain frames.
+    let (_endpoint, conn) = dial(&srv, false).await;
+    assert!(!negotiated_compression(&conn));
+    assert!(matches!(
+        query_once(&conn, "SELECT 1").await.expect("query"),
+        ServerMessage::ResultSet(_)
+    ));
+    let (_endpoint, conn) = dial(&plain_srv, true).await;
+    assert!(!negotiated_compression(&conn));
+    assert!(matches!(
+        query_once(&conn, "SELECT 1").await.expect("query"),
+        ServerMessage::ResultSet(_)
+    ));
+    assert_eq!(srv.metrics().snapshot().frames_compressed, 1);
+    assert_eq!(plain_srv.metrics().snapshot().frames_compressed, 0);
+    for s in servers {
+        s.abort();
+    }
+}
+
+#[cfg_attr(miri, ignore = "QUIC / tokio I/O not supported under Miri")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn copy_in_loads_rows_in_one_answer() {
+    use crate::common::types::DataType;
+    use crate::network::client::copy_in;
+    use crate::network::engine::engine_error_code;
+    use crate::network::sql_engine::SqlEngine;
+
+    let dir = tempfile::TempDir::new().expect("tempdir");
+    let engine = Arc::new(SqlEngine::open(dir.path().to_path_buf()).expect("open engine"));
+    let srv = Arc::new(
+        QuicServer::bind(ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 0,
+            ..Default::default()
+        })
+        .expect("bind server"),
+    );
+    let addr = srv.local_addr().expect("local addr");
+    let client_cfg =
+        build_quinn_client_config(std::slice::from_ref(srv.pinned_certificate())).expect("cfg");
+    let server = tokio::spawn({
+        let srv = srv.clone();
+        async move {
+            let _ = srv.run(engine).await;
+        }
+    });
+    let endpoint = make_client_endpoint(client_cfg).expect("client endpoint");
+    let conn = connect(&endpoint, addr, "127.0.0.1")
+        .await
+        .expect("connect");
+    query_once(&conn, "CREATE TABLE items (n INTEGER, name TEXT)")
+        .await
+        .expect("create");
+    let count = |conn: quinn::Connection| async move {
+        match query_once(&conn, "SELECT n FROM items")
+            .await
+            .expect("select")
+        {
+            ServerMessage::ResultSet(p) => p.rows.len(),
+            other => panic!("unexpected message: {other:?}"),
+        }
+    };
+
+    // More rows than one INSERT batch, in one COPY.
+    let rows: Vec<Vec<DataType>> = (0..1200)
+        .map(|i| vec![DataType::Integer(i), DataType::Text(format!("item '{i}'"))])
+        .collect();
+    match copy_in(&conn, "items", &["n", "name"], &rows)
+        .await
+        .expect("copy")
+    {
+        ServerMessage::ExecutionOk(p) => assert_eq!(p.rows_affected, 1200),
+        other => panic!("unexpected message: {other:?}"),
+    }
+    assert_eq!(count(conn.clone()).await, 1200);
+    match query_once(&conn, "SELECT name FROM items WHERE n = 7")
+        .await
+        .expect("select")
+    {
+        ServerMessage::ResultSet(p) => assert_eq!(
+            crate::common::row_mapping::parse_cell(&p.rows[0][0]),
+            DataType::Varchar("item '7'".into())
+        ),
+        other => panic!("unexpected message: {other:?}"),
+    }
+
+    // A failing COPY is answered with its error and loads nothing.
+    match copy_in(&conn, "items", &["n"], &rows).await.expect("copy") {
+        ServerMessage::Error(e) => assert_eq!(e.code, engine_error_code::PROTOCOL),
+        other => panic!("unexpected message: {other:?}"),
+    }
+    assert_eq!(count(conn.clone()).await, 1200);
+
+    // The first INSERT batch succeeds, the second hits a duplicate key: the whole COPY rolls back.
+    query_once(&conn, "CREATE TABLE keyed (k INTEGER PRIMARY KEY, v TEXT)")
+        .await
+        .expect("create");
+    let mut dup: Vec<Vec<DataType>> = (0..600)
+        .map(|i| vec![DataType::Integer(i), DataType::Text("v".into())])
+        .collect();
+    dup.push(dup[0].clone());
+    match copy_in(&conn, "keyed", &[], &dup).await.expect("copy") {
+        ServerMessage::Error(e) => {
+            assert!(e.message.starts_with("COPY failed after 500 rows"), "{e:?}")
+        }
+        other => panic!("unexpected message: {other:?}"),
+    }
+    match query_once(&conn, "SELECT k FROM keyed")
+        .await
+        .expect("select")
+    {
+        ServerMessage::ResultSet(p) => assert!(p.rows.is_empty()),
+        other => panic!("unexpected message: {other:?}"),
+    }
+    server.abort();
+}
@@ -0,0 +1,211 @@
+//! Hash-sharded cluster coordinator against two loopback shard servers.
+
+use std::sync::Arc;
+
+use tempfile::TempDir;
+
+use crate::network::cluster::{shard_index, ClusterConfig, Coordinator, ShardNode};
+use crate::network::engine::{engine_error_code, EngineHandle, EngineOutput, SessionContext};
+use crate::network::server::{QuicServer, ServerConfig};
+use crate::network::SqlEngine;
+
+struct Shard {
+    engine: Arc<SqlEngine>,
+    node: ShardNode,
+    server: tokio::task::JoinHandle<()>,
+    _dir: TempDir,
+}
+
+async fn start_shard() -> Shard {
+    let dir = TempDir::new().expect("tempdir");
+    let path = dir.path().to_path_buf();
+    // The engine blocks on its own WAL runtime, so it is opened off the async workers.
+    let engine =
+        tokio::task::spawn_blocking(move || Arc::new(SqlEngine::open(path).expect("open")))
+            .await
+            .expect("open shard");
+    let srv = Arc::new(
+        QuicServer::bind(ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 0,
+            ..Default::default()
+        })
+        .expect("bind shard"),
+    );
+    let node = ShardNode::new(
+        srv.local_addr().expect("local addr"),
+        srv.pinned_certificate().clone(),
+    );
+    let server = tokio::spawn({
+        let engine = engine.clone();
+        async move {
+            let _ = srv.run(engine).await;
+        }
+    });
+    Shard {
+        engine,
+        node,
+        server,
+        _dir: dir,
+    }
+}
+
+fn rows(out: EngineOutput) -> Vec<Vec<String>> {
+    match out {
+        EngineOutput::ResultSet { rows, .. } => rows,
+        other => panic!("expected ResultSet, got {:?}", other),
+    }
+}
+
+fn affected(out: EngineOutput) -> u64 {
+    match out {
+        EngineOutput::ExecutionOk { rows_affected } => rows_affected,
+        other => panic!("expected ExecutionOk, got {:?}", other),
+    }
+}
+
+/// Ids stored on one shard, read directly from its engine
+fn shard_ids(engine: &SqlEngine) -> Vec<i64> {
+    let mut ids: Vec<i64> = rows(
+        engine
+            .execute_sql("SELECT id FROM accounts", &mut SessionContext::default())
+            .expect("shard select"),
+    )
+    .into_iter()
+    .map(|r| {
+        r[0].trim_start_matches("Integer(")
+            .trim_end_matches(')')
+            .parse()
+            .expect("integer id")
+    })
+    .collect();
+    ids.sort_unstable();
+    ids
+}
+
+#[cfg_attr(miri, ignore = "QUIC / tokio I/O not supported under Miri")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn coordinator_routes_scatters_and_commits_across_shards() {
+    let shards = vec![start_shard().await, start_shard().await];
+    let engines: Vec<_> = shards.iter().map(|s| s.engine.clone()).collect();
+    let config = ClusterConfig {
+        shards: shards.iter().map(|s| s.node.clone()).collect(),
+        ..Default::default()
+    };
+
+    tokio::task::spawn_blocking(move || {
+        let coordinator = Coordinator::new(config).expect("coordinator");
+        let mut ctx = SessionContext::default();
+        let mut run = |sql: &str| coordinator.execute_sql(sql, &mut ctx);
+
+        run("CREATE TABLE accounts (id INT PRIMARY KEY, owner TEXT, balance INT)").expect("create");
+        assert_eq!(
+            affected(
+                run("INSERT INTO accounts (id, owner, balance) VALUES \
+                     (1, 'ann', 10), (2, 'bob', 20), (3, 'cid', 30), \
+                     (4, 'dan', 40), (5, 'eve', 50), (6, 'fay', 60)")
+                .expect("insert")
+            ),
+            6
+        );
+        // Every row lives on the shard its key hashes to.
+        for (shard, engine) in engines.iter().enumerate() {
+            let expected: Vec<i64> = (1..=6)
+                .filter(|id| shard_index(&id.to_string(), 2) == shard)
+                .collect();
+            assert_eq!(shard_ids(engine), expected, "shard {shard}");
+        }
+
+        assert_eq!(
+            rows(run("SELECT owner FROM accounts WHERE id = 4 AND balance = 40").expect("pinned")),
+            vec![vec!["Varchar(\"'dan'\")"]]
+        );
+        assert_eq!(
+            rows(
+                run("SELECT id, balance FROM accounts WHERE balance > 10 \
+                     ORDER BY balance DESC LIMIT 3")
+                .expect("scatter")
+            ),
+            vec![
+                vec!["Integer(60)", "Integer(6)"],
+                vec!["Integer(50)", "Integer(5)"],
+                vec!["Integer(40)", "Integer(4)"],
+            ]
+        );
+        assert_eq!(
+            run("SELECT COUNT(*) FROM accounts").unwrap_err().code,
+            engine_error_code::UNSUPPORTED_SQL
+        );
+        assert_eq!(
+            run("UPDATE accounts SET id = 9 WHERE id = 1")
+                .unwrap_err()
+                .code,
+            engine_error_code::UNSUPPORTED_SQL
+        );
+
+        // A transfer between rows on different shards commits in two phases.
+        let on_shard = |shard: usize| {
+            (1..=6)
+                .find(|id: &i64| shard_index(&id.to_string(), 2) == shard)
+                .expect("both shards hold rows")
+        };
+        let (from, to) = (on_shard(0), on_shard(1));
+        run("BEGIN TRANSACTION").expect("begin");
+        run(&format!(
+            "UPDATE accounts SET balance = 0 WHERE id = {from}"
+        ))
+        .expect("debit");
+        run(&format!(
+            "UPDATE accounts SET balance = 1000 WHERE id = {to}"
+        ))
+        .expect("credit");
+        run("COMMIT").expect("commit");
+        assert_eq!(
+            rows(
+                run(&format!(
+                    "SELECT balance FROM accounts WHERE id = {from} OR id = {to} ORDER BY balance"
+                ))
+                .expect("after transfer")
+            ),
+            vec![vec!["Integer(0)"], vec!["Integer(1000)"]]
+        );
+
+        run("BEGIN TRANSACTION").expect("begin");
+        assert_eq!(
+            affected(run("DELETE FROM accounts").expect("delete all")),
+            6
+        );
+        run("ROLLBACK").expect("rollback");
+        assert_eq!(
+            rows(run("SELECT id FROM accounts").expect("after rollback")).len(),
+            6
+        );
+
+        // A duplicate key on one shard aborts the rows sent to the other one as well.
+        let fresh_on_other_shard = (7..100)
+            .find(|id: &i64| shard_index(&id.to_string(), 2) == 1)
+            .expect("key on shard 1");
+        assert!(run(&format!(
+            "INSERT INTO accounts (id, owner, balance) VALUES ({from}, 'dup', 1), \
+             ({fresh_on_other_shard}, 'new', 1)"
+        ))
+        .is_err());
+        assert!(!shard_ids(&engines[1]).contains(&fresh_on_other_shard));
+        assert_eq!(
+            shard_ids(&engines[0]).len() + shard_ids(&engines[1]).len(),
+            6
+        );
+
+        run("DROP TABLE accounts").expect("drop");
+        assert_eq!(
+            run("SELECT id FROM accounts").unwrap_err().code,
+            engine_error_code::UNSUPPORTED_SQL
+        );
+    })
+    .await
+    .expect("coordinator queries");
+
+    for shard in shards {
+        shard.server.abort();
+    }
+}
@@ -3,14 +3,15 @@
 use std::io::Write;
 
 use crate::network::framing::{
-    cached_execution_ok_frame_v1, classify_server_frame_v1, decode_client_frame_v1,
+    cached_execution_ok_frame_v1, classify_server_frame_v1, compress_frame, decode_client_frame_v1,
     decode_server_frame_v1, encode_client_message_v1, encode_client_message_write,
     encode_execute_tpcc_frame_v1, encode_execution_ok_frame, encode_server_message_v1,
-    server_frame_message_kind, ClientHelloPayload, ClientMessage, EncodeError, ErrorPayload,
+    inflated_frame_len, server_frame_message_kind, AuthenticatePayload, BinaryResultSetPayload,
+    ClientHelloPayload, ClientMessage, CopyDataPayload, CopyInPayload, EncodeError, ErrorPayload,
     ExecuteScriptPayload, ExecuteTpccPayload, ExecutionOkPayload, FrameDirection, FrameHeader,
-    MessageKind, ProtocolError, QueryPayload, ResultSetPayload, ServerFrameClass, ServerMessage,
-    ServerReadyPayload, FRAME_HEADER_LEN, FRAME_MAGIC, MAX_FRAME_PAYLOAD_BYTES,
-    PROTOCOL_VERSION_V1,
+    MessageKind, ProtocolError, QueryPayload, QueryWithFormatPayload, ResultFormat,
+    ResultSetPayload, ServerFrameClass, ServerMessage, ServerReadyPayload, FRAME_HEADER_LEN,
+    FRAME_MAGIC, MAX_FRAME_PAYLOAD_BYTES, PROTOCOL_VERSION_V1,
 };
 
 #[test]
@@ -93,6 +94,18 @@ fn roundtrip_client_hello() {
     assert_eq!(out, msg);
 }
 
+#[test]
+fn roundtrip_client_authenticate_hides_secret_in_debug() {
+    let msg = ClientMessage::Authenticate(AuthenticatePayload {
+        user: "alice".into(),
+        secret: Some("s3cret".into()),
+    });
+    let wire = encode_client_message_v1(&msg).unwrap();
+    let out = decode_client_frame_v1(&wire).unwrap();
+    assert_eq!(out, msg);
+    assert!(!format!("{out:?}").contains("s3cret"));
+}
+
 #[test]
 fn roundtrip_server_all_variants() {
     let cases = vec![
@@ -116,6 +129,143 @@ fn roundtrip_server_all_variants() {
     }
 }
 
+#[test]
+fn roundtrip_query_with_format_and_binary_rows() {
+    use crate::common::types::DataType;
+
+    let msg = ClientMessage::QueryWithFormat(QueryWithFormatPayload {
+        sql: "SELECT id, name FROM t".into(),
+        result_format: ResultFormat::Binary,
+    });
+    let wire = encode_client_message_v1(&msg).unwrap();
+    assert_eq!(decode_client_frame_v1(&wire).unwrap(), msg);
+
+    let rows = vec![
+        vec![
+            "Integer(-7)".to_string(),
+            "Varchar(\"'ann'\")".to_string(),
+            "Double(2.5)".to_string(),
+        ],
+        vec![
+            "Null".to_string(),
+            "BigInt(9000000000)".to_string(),
+            "Boolean(true)".to_string(),
+        ],
+    ];
+    let payload =
+        BinaryResultSetPayload::from_text_rows(vec!["a".into(), "b".into(), "c".into()], &rows);
+    let msg = ServerMessage::BinaryResultSet(payload);
+    let wire = encode_server_message_v1(&msg).unwrap();
+    let ServerMessage::BinaryResultSet(out) = decode_server_frame_v1(&wire).unwrap() else {
+        panic!("expected BinaryResultSet");
+    };
+    assert_eq!(out.row_count, 2);
+    let values = out.rows().unwrap();
+    assert_eq!(values[0][0], DataType::Integer(-7));
+    assert_eq!(values[0][1], DataType::Varchar("ann".into()));
+    assert_eq!(values[0][2], DataType::Double(2.5));
+    assert_eq!(
+        values[1],
+        vec![
+            DataType::Null,
+            DataType::BigInt(9_000_000_000),
+            DataType::Boolean(true)
+        ]
+    );
+
+    let mut truncated = out.clone();
+    truncated.data.pop();
+    assert!(truncated.rows().is_err());
+    let mut unknown = out;
+    unknown.data[0] = 99;
+    assert!(unknown.rows().is_err());
+}
+
+#[test]
+fn roundtrip_copy_frames() {
+    use crate::common::types::DataType;
+
+    let msg = ClientMessage::CopyIn(CopyInPayload {
+        table: "orders".into(),
+        columns: vec!["id".into(), "note".into()],
+    });
+    let wire = encode_client_message_v1(&msg).unwrap();
+    assert_eq!(decode_client_frame_v1(&wire).unwrap(), msg);
+
+    let rows: Vec<Vec<DataType>> = (0..10)
+        .map(|i| vec![DataType::Integer(i), DataType::Text(format!("note {i}"))])
+        .collect();
+    let batches = CopyDataPayload::batches(2, &rows, 64);
+    assert!(batches.len() > 1);
+    let mut decoded = Vec::new();
+    for batch in batches {
+        let msg = ClientMessage::CopyData(batch);
+        let wire = encode_client_message_v1(&msg).unwrap();
+        let ClientMessage::CopyData(out) = decode_client_frame_v1(&wire).unwrap() else {
+            panic!("expected CopyData");
+        };
+        decoded.extend(out.rows().unwrap());
+    }
+    assert_eq!(decoded, rows);
+
+    let wire = encode_client_message_v1(&ClientMessage::CopyDone).unwrap();
+    assert_eq!(wire.len(), FRAME_HEADER_LEN);
+    assert_eq!(
+        decode_client_frame_v1(&wire).unwrap(),
+        ClientMessage::CopyDone
+    );
+}
+
+#[test]
+fn compressed_frames_decode_to_the_frame_they_carry() {
+    let msg = ServerMessage::ResultSet(ResultSetPayload {
+        columns: vec!["id".into(), "name".into()],
+        rows: (0..200)
+            .map(|i| vec![format!("Integer({i})"), "Varchar(\"'same text'\")".into()])
+            .collect(),
+    });
+    let wire = encode_server_message_v1(&msg).unwrap();
+    let compressed = compress_frame(&wire, 1024).expect("large repetitive frame compresses");
+    assert!(compressed.len() < wire.len());
+    assert_eq!(
+        server_frame_message_kind(&compressed).unwrap(),
+        MessageKind::Compressed
+    );
+    assert_eq!(inflated_frame_len(&compressed), Some(wire.len()));
+    assert_eq!(inflated_frame_len(&wire), None);
+    assert_eq!(decode_server_frame_v1(&compressed).unwrap(), msg);
+    // Below the threshold, or already compressed: sent as is.
+    assert!(compress_frame(&wire, wire.len()).is_none());
+    assert!(compress_frame(&compressed, 1).is_none());
+
+    let request = ClientMessage::Query(QueryPayload {
+        sql: format!(
+            "SELECT 1 FROM t WHERE name IN ({})",
+            vec!["'x'"; 500].join(", ")
+        ),
+    });
+    let wire = encode_client_message_v1(&request).unwrap();
+    let compressed = compress_frame(&wire, 64).unwrap();
+    assert_eq!(decode_client_frame_v1(&compressed).unwrap(), request);
+
+    // A damaged block, or one claiming more than a frame may hold, is a protocol error.
+    let mut damaged = compressed.clone();
+    damaged.truncate(damaged.len() - 4);
+    let len = (damaged.len() - FRAME_HEADER_LEN) as u32;
+    damaged[8..12].copy_from_slice(&len.to_le_bytes());
+    assert!(matches!(
+        decode_client_frame_v1(&damaged),
+        Err(ProtocolError::Decompress(_))
+    ));
+    let mut oversized = compressed;
+    oversized[FRAME_HEADER_LEN + 2..FRAME_HEADER_LEN + 6]
+        .copy_from_slice(&(MAX_FRAME_PAYLOAD_BYTES + 1).to_le_bytes());
+    assert!(matches!(
+        decode_client_frame_v1(&oversized),
+        Err(ProtocolError::PayloadTooLarge { .. })
+    ));
+}
+
 #[test]
 fn wrong_magic() {
     let mut wire =
@@ -237,6 +387,7 @@ fn framing_error_display_covers_error_types() {
         ProtocolError::PayloadTooLarge { len: 3, max: 2 }.to_string(),
         ProtocolError::TruncatedFrame { need: 20, got: 10 }.to_string(),
         ProtocolError::PostcardDecode("bad".into()).to_string(),
+        ProtocolError::Decompress("bad".into()).to_string(),
     ];
     assert!(displays.iter().all(|s| !s.is_empty()));
 }
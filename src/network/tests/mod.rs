@@ -1,11 +1,15 @@
 //! Network module tests
 
+pub mod auth_tests;
 pub mod client_tests;
+pub mod cluster_tests;
 pub mod connection_tests;
 pub mod engine_tests;
 pub mod framing_tests;
 pub mod metrics_tests;
+pub mod pg_wire_tests;
 pub mod query_stream_tests;
+pub mod replication_tests;
 pub mod server_tests;
 pub mod sql_engine_dispatch_tests;
 pub mod sql_full_query_tests;
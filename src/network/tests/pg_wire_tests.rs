@@ -0,0 +1,284 @@
+//! PostgreSQL wire protocol listener, driven by `tokio-postgres` and by raw protocol messages.
+
+use std::sync::Arc;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_postgres::{NoTls, SimpleQueryMessage};
+
+use crate::common::config::parse_hba_rules;
+use crate::network::auth::{Authentication, PasswordAuthenticator};
+use crate::network::engine::EngineHandle;
+use crate::network::pg_wire::PgWireServer;
+use crate::network::server::ServerConfig;
+use crate::network::sql_engine::SqlEngine;
+
+/// Starts a listener on a free port over a fresh engine; returns its port.
+async fn start(dir: &tempfile::TempDir, authentication: Option<Authentication>) -> u16 {
+    let engine: Arc<dyn EngineHandle> =
+        Arc::new(SqlEngine::open(dir.path().to_path_buf()).expect("open engine"));
+    let server = PgWireServer::bind(
+        ServerConfig {
+            host: "127.0.0.1".to_string(),
+            authentication: authentication.map(Arc::new),
+            ..Default::default()
+        },
+        0,
+    )
+    .await
+    .expect("bind");
+    let port = server.local_addr().expect("local addr").port();
+    tokio::spawn(async move {
+        let _ = server.run(engine).await;
+    });
+    port
+}
+
+async fn connect(port: u16, user: &str, password: Option<&str>) -> tokio_postgres::Client {
+    let mut config = tokio_postgres::Config::new();
+    config.host("127.0.0.1").port(port).user(user);
+    if let Some(password) = password {
+        config.password(password);
+    }
+    let (client, connection) = config.connect(NoTls).await.expect("connect");
+    tokio::spawn(connection);
+    client
+}
+
+fn rows(messages: &[SimpleQueryMessage]) -> Vec<Vec<Option<String>>> {
+    messages
+        .iter()
+        .filter_map(|m| match m {
+            SimpleQueryMessage::Row(row) => Some(
+                (0..row.len())
+                    .map(|i| row.get(i).map(str::to_string))
+                    .collect(),
+            ),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn simple_queries_run_statement_by_statement() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let port = start(&dir, None).await;
+    let client = connect(port, "app", None).await;
+
+    client
+        .simple_query("CREATE TABLE t (id INT PRIMARY KEY, name VARCHAR(20))")
+        .await
+        .expect("create");
+    let done = client
+        .simple_query(
+            "INSERT INTO t (id, name) VALUES (1, 'a'); INSERT INTO t (id, name) VALUES (2, 'b;c')",
+        )
+        .await
+        .expect("inserts");
+    let counts: Vec<u64> = done
+        .iter()
+        .filter_map(|m| match m {
+            SimpleQueryMessage::CommandComplete(n) => Some(*n),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(counts, vec![1, 1]);
+
+    let result = client
+        .simple_query("SELECT id, name FROM t ORDER BY id")
+        .await
+        .expect("select");
+    assert_eq!(
+        rows(&result),
+        vec![
+            vec![Some("1".to_string()), Some("a".to_string())],
+            vec![Some("2".to_string()), Some("b;c".to_string())],
+        ]
+    );
+
+    let err = client
+        .simple_query("INSERT INTO t (id, name) VALUES (1, 'dup')")
+        .await
+        .expect_err("duplicate key");
+    assert_eq!(
+        err.code().map(|c| c.code()),
+        Some("23000"),
+        "unexpected error: {err:?}"
+    );
+
+    // A transaction spans queries; dropping the connection inside one rolls it back.
+    client
+        .simple_query("BEGIN TRANSACTION")
+        .await
+        .expect("begin");
+    client
+        .simple_query("INSERT INTO t (id, name) VALUES (3, 'c')")
+        .await
+        .expect("insert in transaction");
+    drop(client);
+    let client = connect(port, "app", None).await;
+    let count = client
+        .query_one("SELECT COUNT(*) FROM t", &[])
+        .await
+        .expect("count");
+    assert_eq!(count.get::<_, i64>(0), 2);
+}
+
+#[tokio::test]
+async fn extended_queries_bind_parameters() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let port = start(&dir, None).await;
+    let client = connect(port, "app", None).await;
+
+    client
+        .batch_execute("CREATE TABLE t (id INT PRIMARY KEY, name VARCHAR(20))")
+        .await
+        .expect("create");
+    let insert = client
+        .prepare("INSERT INTO t (id, name) VALUES ($1, $2)")
+        .await
+        .expect("prepare");
+    for (id, name) in [("1", "it's"), ("2", "b")] {
+        assert_eq!(client.execute(&insert, &[&id, &name]).await.unwrap(), 1);
+    }
+    let updated = client
+        .execute("UPDATE t SET name = $1 WHERE id = $2", &[&"z", &"2"])
+        .await
+        .expect("update");
+    assert_eq!(updated, 1);
+
+    let rows = client
+        .query("SELECT id, name FROM t ORDER BY id", &[])
+        .await
+        .expect("query");
+    let values: Vec<(i64, String)> = rows.iter().map(|r| (r.get(0), r.get(1))).collect();
+    assert_eq!(values, vec![(1, "it's".to_string()), (2, "z".to_string())]);
+}
+
+#[tokio::test]
+async fn prepared_statements_are_described_without_rows() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let port = start(&dir, None).await;
+    let client = connect(port, "app", None).await;
+    client
+        .batch_execute("CREATE TABLE t (id INT PRIMARY KEY, name VARCHAR(20), score REAL)")
+        .await
+        .expect("create");
+
+    let select = client
+        .prepare("SELECT name, score, COUNT(*) AS n FROM t WHERE id = $1 GROUP BY name, score")
+        .await
+        .expect("prepare");
+    let columns: Vec<(&str, &str)> = select
+        .columns()
+        .iter()
+        .map(|c| (c.name(), c.type_().name()))
+        .collect();
+    assert_eq!(
+        columns,
+        vec![("n", "int8"), ("name", "text"), ("score", "float8")]
+    );
+    assert!(client
+        .query(&select, &[&"1"])
+        .await
+        .expect("query")
+        .is_empty());
+
+    client
+        .batch_execute("INSERT INTO t (id, name, score) VALUES (1, 'a', 2.5)")
+        .await
+        .expect("insert");
+    let rows = client.query(&select, &[&"1"]).await.expect("query");
+    let values: Vec<(i64, String, f64)> = rows
+        .iter()
+        .map(|r| (r.get("n"), r.get("name"), r.get("score")))
+        .collect();
+    assert_eq!(values, vec![(1, "a".to_string(), 2.5)]);
+}
+
+#[tokio::test]
+async fn execute_with_a_row_limit_suspends_the_portal() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let port = start(&dir, None).await;
+    let client = connect(port, "app", None).await;
+    client
+        .batch_execute("CREATE TABLE t (id INT PRIMARY KEY)")
+        .await
+        .unwrap();
+    client
+        .batch_execute("INSERT INTO t (id) VALUES (1), (2)")
+        .await
+        .unwrap();
+    drop(client);
+
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let mut startup = Vec::new();
+    startup.extend_from_slice(&196_608i32.to_be_bytes());
+    startup.extend_from_slice(b"user\0app\0\0");
+    let mut packet = ((startup.len() + 4) as i32).to_be_bytes().to_vec();
+    packet.extend_from_slice(&startup);
+    stream.write_all(&packet).await.unwrap();
+    assert_eq!(read_until(&mut stream, b'Z').await.last(), Some(&b'Z'));
+
+    let mut batch = Vec::new();
+    push(&mut batch, b'P', b"\0SELECT id FROM t ORDER BY id\0\0\0");
+    push(&mut batch, b'B', b"p\0\0\0\0\0\0\0\0");
+    push(&mut batch, b'D', b"Pp\0");
+    push(&mut batch, b'E', b"p\0\0\0\0\x01");
+    push(&mut batch, b'E', b"p\0\0\0\0\x01");
+    push(&mut batch, b'S', b"");
+    stream.write_all(&batch).await.unwrap();
+    let kinds = read_until(&mut stream, b'Z').await;
+    assert_eq!(
+        kinds,
+        vec![b'1', b'2', b'T', b'D', b's', b'D', b'C', b'Z'],
+        "{}",
+        String::from_utf8_lossy(&kinds)
+    );
+}
+
+#[tokio::test]
+async fn password_authentication_is_checked() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let authentication =
+        Authentication::new(parse_hba_rules("test", "host all all password").expect("rules"))
+            .with_method(
+                "password",
+                Arc::new(PasswordAuthenticator::default().with_user("alice", "s3cret")),
+            );
+    let port = start(&dir, Some(authentication)).await;
+
+    let client = connect(port, "alice", Some("s3cret")).await;
+    let role = client.simple_query("SHOW role").await.expect("show role");
+    assert!(format!("{:?}", rows(&role)).contains("alice"));
+
+    let mut config = tokio_postgres::Config::new();
+    config
+        .host("127.0.0.1")
+        .port(port)
+        .user("alice")
+        .password("guess");
+    let err = config.connect(NoTls).await.err().expect("wrong password");
+    assert_eq!(err.code().map(|c| c.code()), Some("28P01"));
+}
+
+fn push(out: &mut Vec<u8>, kind: u8, body: &[u8]) {
+    out.push(kind);
+    out.extend_from_slice(&((body.len() + 4) as i32).to_be_bytes());
+    out.extend_from_slice(body);
+}
+
+/// Types of the messages read up to and including the first of type `last`.
+async fn read_until(stream: &mut TcpStream, last: u8) -> Vec<u8> {
+    let mut kinds = Vec::new();
+    loop {
+        let kind = stream.read_u8().await.expect("message type");
+        let len = stream.read_i32().await.expect("message length");
+        let mut body = vec![0u8; len as usize - 4];
+        stream.read_exact(&mut body).await.expect("message body");
+        kinds.push(kind);
+        if kind == last {
+            return kinds;
+        }
+    }
+}
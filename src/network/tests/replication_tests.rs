@@ -0,0 +1,1066 @@
+//! Replication protocol against a loopback server.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tempfile::TempDir;
+
+use crate::network::client::{build_quinn_client_config, connect, make_client_endpoint};
+use crate::network::engine::engine_error_code;
+use crate::network::engine::{EngineHandle, EngineOutput, SessionContext, StubEngine};
+use crate::network::framing::ReplicatedColumn;
+use crate::network::replication::{
+    fetch_base_backup, fetch_incremental_backup, pull_backup, push_backup, read_backup_manifest,
+    restore_backup_chain, restore_base_backup, run_replica, verify_backup, write_replica_lsn,
+    ConflictResolver, LastWriterWins, ReplicaConfig, ReplicaStatus, ReplicationError,
+    ReplicationTracker, Resolution, RowConflict, SyncTimeoutPolicy, SynchronousCommitConfig,
+    SynchronousStandbys,
+};
+use crate::network::server::{QuicServer, ServerConfig};
+use crate::network::SqlEngine;
+use crate::storage::remote::{LocalStorage, RemoteStorage};
+
+/// Serves `engine` on a loopback port and returns a client connection plus the accept loop.
+async fn serve(engine: Arc<dyn EngineHandle>) -> (quinn::Connection, tokio::task::JoinHandle<()>) {
+    let srv = Arc::new(
+        QuicServer::bind(ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 0,
+            ..Default::default()
+        })
+        .expect("bind server"),
+    );
+    let addr = srv.local_addr().expect("local addr");
+    let client_cfg = build_quinn_client_config(std::slice::from_ref(srv.pinned_certificate()))
+        .expect("client cfg");
+    let endpoint = make_client_endpoint(client_cfg).expect("client endpoint");
+    let server = tokio::spawn(async move {
+        let _ = srv.run(engine).await;
+    });
+    let conn = connect(&endpoint, addr, "127.0.0.1")
+        .await
+        .expect("connect");
+    (conn, server)
+}
+
+fn rows(out: EngineOutput) -> Vec<Vec<String>> {
+    match out {
+        EngineOutput::ResultSet { rows, .. } => rows,
+        other => panic!("expected ResultSet, got {:?}", other),
+    }
+}
+
+#[cfg_attr(miri, ignore = "QUIC / tokio I/O not supported under Miri")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn base_backup_provisions_replica_without_in_flight_rows() {
+    let primary_dir = TempDir::new().expect("tempdir");
+    let path = primary_dir.path().to_path_buf();
+    // The engine blocks on its own WAL runtime, so it runs off the async workers.
+    let (engine, open_txn) = tokio::task::spawn_blocking(move || {
+        let engine = Arc::new(SqlEngine::open(path).expect("open primary"));
+        let mut ctx = SessionContext::default();
+        for sql in [
+            "CREATE TABLE items (id INT, name TEXT)",
+            "INSERT INTO items (id, name) VALUES (1, 'one'), (2, 'two')",
+            "CREATE TABLE tags (tag TEXT)",
+            "INSERT INTO tags (tag) VALUES ('red')",
+        ] {
+            engine.execute_sql(sql, &mut ctx).expect(sql);
+        }
+        // Still open while the backup runs: the replica must not see this row.
+        let mut open_txn = SessionContext::default();
+        engine
+            .execute_sql("BEGIN TRANSACTION", &mut open_txn)
+            .expect("begin");
+        engine
+            .execute_sql(
+                "INSERT INTO items (id, name) VALUES (3, 'three')",
+                &mut open_txn,
+            )
+            .expect("uncommitted insert");
+        (engine, open_txn)
+    })
+    .await
+    .expect("primary setup");
+
+    let (conn, server) = serve(engine.clone()).await;
+    let replica_dir = TempDir::new().expect("tempdir");
+    let target = replica_dir.path().join("replica");
+    let info = fetch_base_backup(&conn, "test replica", &target, None)
+        .await
+        .expect("base backup");
+    assert!(info.start_lsn > 0, "{info:?}");
+    assert!(target.join(".rustdb/catalog.json").is_file());
+    let manifest = read_backup_manifest(&target)
+        .expect("read manifest")
+        .expect("manifest written");
+    assert_eq!(manifest.label, "test replica");
+    assert_eq!(manifest.start_lsn, info.start_lsn);
+    // The files received and the LSN streaming resumes after.
+    assert_eq!(manifest.files.len() as u64, info.files + 1);
+    assert_eq!(manifest.bytes, info.bytes);
+    for path in [".rustdb/catalog.json", ".rustdb/replica_lsn"] {
+        assert!(manifest.files.iter().any(|f| f.path == path), "{path}");
+    }
+    assert!(manifest.first_lsn > 0 && manifest.first_lsn <= manifest.start_lsn);
+    assert_eq!(manifest.engine_version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(manifest.signature, None);
+    assert!(
+        !target.join(".rustdb/base_backup").exists(),
+        "staging area must not be copied"
+    );
+
+    // A second backup into the now populated directory is refused before contacting the server.
+    assert!(matches!(
+        fetch_base_backup(&conn, "again", &target, None).await,
+        Err(ReplicationError::Invalid(_))
+    ));
+
+    tokio::task::spawn_blocking(move || {
+        let mut open_txn = open_txn;
+        engine
+            .execute_sql("COMMIT", &mut open_txn)
+            .expect("commit after backup");
+        assert!(
+            std::fs::read_dir(engine.data_dir().join(".rustdb/base_backup"))
+                .map(|mut d| d.next().is_none())
+                .unwrap_or(true),
+            "staged copy is removed once sent"
+        );
+
+        let replica = SqlEngine::open(target).expect("open replica");
+        let mut ctx = SessionContext::default();
+        let mut items = rows(
+            replica
+                .execute_sql("SELECT id, name FROM items", &mut ctx)
+                .expect("replica items"),
+        );
+        items.sort();
+        assert_eq!(
+            items,
+            vec![
+                vec!["Integer(1)", "Varchar(\"'one'\")"],
+                vec!["Integer(2)", "Varchar(\"'two'\")"],
+            ]
+        );
+        assert_eq!(
+            rows(
+                replica
+                    .execute_sql("SELECT tag FROM tags", &mut ctx)
+                    .expect("replica tags")
+            ),
+            vec![vec!["Varchar(\"'red'\")"]]
+        );
+        replica
+            .execute_sql("INSERT INTO tags (tag) VALUES ('blue')", &mut ctx)
+            .expect("replica is writable");
+    })
+    .await
+    .expect("replica checks");
+    server.abort();
+}
+
+#[cfg_attr(miri, ignore = "QUIC / tokio I/O not supported under Miri")]
+#[tokio::test]
+async fn base_backup_reports_unsupported_engine() {
+    let (conn, server) = serve(Arc::new(StubEngine::default())).await;
+    let target = TempDir::new().expect("tempdir");
+    match fetch_base_backup(&conn, "stub", target.path(), None).await {
+        Err(ReplicationError::Server { code, .. }) => {
+            assert_eq!(code, crate::network::engine::engine_error_code::PROTOCOL)
+        }
+        other => panic!("expected server error, got {other:?}"),
+    }
+    server.abort();
+}
+
+#[cfg_attr(miri, ignore = "QUIC / tokio I/O not supported under Miri")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn signed_manifest_is_verified_before_restore() {
+    let primary_dir = TempDir::new().expect("tempdir");
+    let path = primary_dir.path().to_path_buf();
+    let engine = tokio::task::spawn_blocking(move || {
+        let engine = Arc::new(SqlEngine::open(path).expect("open primary"));
+        let mut ctx = SessionContext::default();
+        for sql in [
+            "CREATE TABLE items (id INT, name TEXT)",
+            "INSERT INTO items (id, name) VALUES (1, 'one')",
+        ] {
+            engine.execute_sql(sql, &mut ctx).expect(sql);
+        }
+        engine
+    })
+    .await
+    .expect("primary setup");
+    let (conn, server) = serve(engine).await;
+    let dirs = TempDir::new().expect("tempdir");
+    let backup = dirs.path().join("backup");
+    let key = b"0123456789abcdef0123456789abcdef";
+    fetch_base_backup(&conn, "signed", &backup, Some(key))
+        .await
+        .expect("base backup");
+    server.abort();
+
+    let manifest = verify_backup(&backup, Some(key)).expect("verify");
+    assert!(manifest.signature.is_some());
+    assert!(matches!(
+        verify_backup(&backup, Some(b"another key, another signature")),
+        Err(ReplicationError::Invalid(_))
+    ));
+
+    let restored = dirs.path().join("restored");
+    restore_base_backup(&backup, &restored, Some(key)).expect("restore");
+    assert_eq!(
+        verify_backup(&restored, Some(key)).expect("verify copy"),
+        manifest
+    );
+    assert!(matches!(
+        restore_base_backup(&backup, &restored, Some(key)),
+        Err(ReplicationError::Invalid(_))
+    ));
+
+    // Tampering: a changed table file, an extra file, an edited manifest.
+    let table = backup.join(
+        &manifest
+            .files
+            .iter()
+            .find(|f| f.path.ends_with("items.tbl"))
+            .expect("table file")
+            .path,
+    );
+    let original = std::fs::read(&table).expect("read table");
+    let mut changed = original.clone();
+    changed[original.len() / 2] ^= 0xff;
+    std::fs::write(&table, &changed).expect("tamper");
+    let untouched = dirs.path().join("untouched");
+    let err = restore_base_backup(&backup, &untouched, Some(key)).unwrap_err();
+    assert!(err.to_string().contains("checksum"), "{err}");
+    assert!(
+        !untouched.exists(),
+        "nothing is written before verification"
+    );
+    std::fs::write(&table, &original).expect("untamper");
+
+    // The LSN streaming resumes after is covered too.
+    let lsn_file = backup.join(".rustdb/replica_lsn");
+    let lsn = std::fs::read_to_string(&lsn_file).expect("lsn file");
+    std::fs::write(&lsn_file, format!("{lsn}0")).expect("tamper lsn");
+    let err = verify_backup(&backup, Some(key)).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("backup file .rustdb/replica_lsn has"),
+        "{err}"
+    );
+    std::fs::write(&lsn_file, lsn).expect("untamper lsn");
+
+    std::fs::write(backup.join("extra.tbl"), b"x").expect("extra file");
+    let err = verify_backup(&backup, Some(key)).unwrap_err();
+    assert!(err.to_string().contains("not in the manifest"), "{err}");
+    std::fs::remove_file(backup.join("extra.tbl")).expect("remove extra");
+
+    let manifest_path = backup.join(".rustdb/backup_manifest.json");
+    let json = std::fs::read_to_string(&manifest_path).expect("read manifest");
+    std::fs::write(&manifest_path, json.replace("\"signed\"", "\"forged\"")).expect("edit");
+    let err = verify_backup(&backup, Some(key)).unwrap_err();
+    assert!(err.to_string().contains("signature"), "{err}");
+    verify_backup(&backup, None).expect("without a key only the files are checked");
+
+    std::fs::remove_file(&manifest_path).expect("drop manifest");
+    let err = verify_backup(&backup, None).unwrap_err();
+    assert!(err.to_string().contains("no backup manifest"), "{err}");
+}
+
+#[cfg_attr(miri, ignore = "QUIC / tokio I/O not supported under Miri")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn backups_round_trip_through_remote_storage() {
+    let primary_dir = TempDir::new().expect("tempdir");
+    let path = primary_dir.path().to_path_buf();
+    let engine =
+        tokio::task::spawn_blocking(move || Arc::new(SqlEngine::open(path).expect("open primary")))
+            .await
+            .expect("open primary");
+    exec(
+        &engine,
+        &[
+            "CREATE TABLE items (id INT, name TEXT)",
+            "INSERT INTO items (id, name) VALUES (1, 'one')",
+        ],
+    )
+    .await;
+    let (conn, server) = serve(engine).await;
+    let dirs = TempDir::new().expect("tempdir");
+    let backup = dirs.path().join("backup");
+    let key = b"0123456789abcdef0123456789abcdef";
+    fetch_base_backup(&conn, "remote", &backup, Some(key))
+        .await
+        .expect("base backup");
+    server.abort();
+
+    let remote = LocalStorage::new(dirs.path().join("bucket"));
+    let pulled = dirs.path().join("pulled");
+    let err = pull_backup(&remote, &pulled, None).unwrap_err();
+    assert!(err.to_string().contains("no manifest"), "{err}");
+    let manifest = push_backup(&backup, &remote).expect("push");
+    assert_eq!(
+        pull_backup(&remote, &pulled, Some(key)).expect("pull"),
+        manifest
+    );
+    assert!(matches!(
+        pull_backup(&remote, &pulled, None),
+        Err(ReplicationError::Invalid(_))
+    ));
+
+    // A corrupted object fails verification after the download.
+    let table = &manifest
+        .files
+        .iter()
+        .find(|f| f.path.ends_with("items.tbl"))
+        .expect("table file")
+        .path;
+    remote
+        .put(table, &mut &b"garbage"[..], 7)
+        .expect("corrupt object");
+    let err = pull_backup(&remote, &dirs.path().join("corrupt"), Some(key)).unwrap_err();
+    assert!(err.to_string().contains("7 bytes"), "{err}");
+}
+
+#[cfg_attr(miri, ignore = "QUIC / tokio I/O not supported under Miri")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn incremental_backups_restore_over_their_base() {
+    let primary_dir = TempDir::new().expect("tempdir");
+    let path = primary_dir.path().to_path_buf();
+    let engine =
+        tokio::task::spawn_blocking(move || Arc::new(SqlEngine::open(path).expect("open primary")))
+            .await
+            .expect("open primary");
+    exec(
+        &engine,
+        &[
+            "CREATE TABLE items (id INT, name TEXT)",
+            "INSERT INTO items (id, name) VALUES (1, 'one'), (2, 'two')",
+            "CREATE TABLE tags (tag TEXT)",
+            "INSERT INTO tags (tag) VALUES ('red')",
+        ],
+    )
+    .await;
+    let (conn, server) = serve(engine.clone()).await;
+    let dirs = TempDir::new().expect("tempdir");
+    let full = dirs.path().join("full");
+    let first = dirs.path().join("inc1");
+    let second = dirs.path().join("inc2");
+    fetch_base_backup(&conn, "full", &full, None)
+        .await
+        .expect("full backup");
+
+    exec(
+        &engine,
+        &["INSERT INTO items (id, name) VALUES (3, 'three')"],
+    )
+    .await;
+    let info = fetch_incremental_backup(&conn, "inc1", &first, &full, None)
+        .await
+        .expect("first incremental");
+    let full_manifest = verify_backup(&full, None).expect("verify full");
+    let manifest = verify_backup(&first, None).expect("verify incremental");
+    let inc = manifest.incremental.as_ref().expect("incremental manifest");
+    assert_eq!(inc.base_start_lsn, full_manifest.start_lsn);
+    assert!(
+        inc.deltas.iter().any(|p| p.ends_with("items.tbl")),
+        "{inc:?}"
+    );
+    assert!(
+        !manifest
+            .files
+            .iter()
+            .any(|f| f.path.starts_with("tags.tbl")),
+        "unchanged tables are not sent: {:?}",
+        manifest.files
+    );
+    assert!(info.bytes < full_manifest.bytes, "{info:?}");
+
+    exec(
+        &engine,
+        &[
+            "UPDATE items SET name = 'uno' WHERE id = 1",
+            "DELETE FROM tags WHERE tag = 'red'",
+        ],
+    )
+    .await;
+    fetch_incremental_backup(&conn, "inc2", &second, &first, None)
+        .await
+        .expect("second incremental");
+    server.abort();
+
+    // Each incremental must follow the backup it was taken against.
+    let restored = dirs.path().join("restored");
+    for (base, incs) in [
+        (&full, vec![second.clone()]),
+        (&full, vec![second.clone(), first.clone()]),
+        (&first, vec![second.clone()]),
+    ] {
+        assert!(matches!(
+            restore_backup_chain(base, &incs, &restored, None),
+            Err(ReplicationError::Invalid(_))
+        ));
+        assert!(!restored.exists());
+    }
+    let manifest =
+        restore_backup_chain(&full, &[first, second], &restored, None).expect("restore chain");
+    assert_eq!(manifest.label, "inc2");
+
+    let expected_items = query(&engine, "SELECT id, name FROM items").await;
+    tokio::task::spawn_blocking(move || {
+        let replica = SqlEngine::open(restored).expect("open restored");
+        let mut ctx = SessionContext::default();
+        let mut items = rows(
+            replica
+                .execute_sql("SELECT id, name FROM items", &mut ctx)
+                .expect("restored items"),
+        );
+        items.sort();
+        assert_eq!(items, expected_items);
+        assert!(rows(
+            replica
+                .execute_sql("SELECT tag FROM tags", &mut ctx)
+                .expect("restored tags")
+        )
+        .is_empty());
+    })
+    .await
+    .expect("restored checks");
+}
+
+/// Opens a primary with `setup` applied, serves it and clones it into a fresh replica directory.
+async fn primary_and_replica(
+    setup: &'static [&'static str],
+) -> (
+    Arc<SqlEngine>,
+    quinn::Connection,
+    tokio::task::JoinHandle<()>,
+    TempDir,
+    PathBuf,
+) {
+    let primary_dir = TempDir::new().expect("tempdir");
+    let path = primary_dir.path().to_path_buf();
+    let engine = tokio::task::spawn_blocking(move || {
+        let engine = Arc::new(SqlEngine::open(path).expect("open primary"));
+        let mut ctx = SessionContext::default();
+        for sql in setup {
+            engine.execute_sql(sql, &mut ctx).expect(sql);
+        }
+        engine
+    })
+    .await
+    .expect("primary setup");
+    let (conn, server) = serve(engine.clone()).await;
+    let target = primary_dir.path().join("replica");
+    fetch_base_backup(&conn, "test replica", &target, None)
+        .await
+        .expect("base backup");
+    (engine, conn, server, primary_dir, target)
+}
+
+async fn exec(engine: &Arc<SqlEngine>, sqls: &'static [&'static str]) {
+    let engine = engine.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut ctx = SessionContext::default();
+        for sql in sqls {
+            engine.execute_sql(sql, &mut ctx).expect(sql);
+        }
+    })
+    .await
+    .expect("exec");
+}
+
+async fn query(engine: &Arc<SqlEngine>, sql: &'static str) -> Vec<Vec<String>> {
+    let engine = engine.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut rows = rows(
+            engine
+                .execute_sql(sql, &mut SessionContext::default())
+                .expect(sql),
+        );
+        rows.sort();
+        rows
+    })
+    .await
+    .expect("query")
+}
+
+async fn eventually(what: &str, mut check: impl AsyncFnMut() -> bool) {
+    for _ in 0..200 {
+        if check().await {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("timed out waiting for {what}");
+}
+
+/// Position the apply loop resumes after.
+fn recorded_lsn(target: &std::path::Path) -> u64 {
+    std::fs::read_to_string(target.join(".rustdb/replica_lsn"))
+        .expect("lsn file")
+        .parse()
+        .expect("lsn")
+}
+
+/// Starts the apply loop for `target` and returns the replica engine, its status and the task.
+async fn follow(
+    conn: quinn::Connection,
+    target: PathBuf,
+    config: ReplicaConfig,
+    status: Arc<ReplicaStatus>,
+) -> (
+    Arc<SqlEngine>,
+    tokio::task::JoinHandle<Result<(), ReplicationError>>,
+) {
+    let dir = target.clone();
+    let replica =
+        tokio::task::spawn_blocking(move || Arc::new(SqlEngine::open(dir).expect("open replica")))
+            .await
+            .expect("open replica");
+    let engine: Arc<dyn EngineHandle> = replica.clone();
+    let task =
+        tokio::spawn(async move { run_replica(&conn, engine, &target, &config, &status).await });
+    (replica, task)
+}
+
+/// Stops the apply loop and drops the engines off the async workers (WAL flush blocks).
+async fn shut_down(
+    task: tokio::task::JoinHandle<Result<(), ReplicationError>>,
+    server: tokio::task::JoinHandle<()>,
+    engines: Vec<Arc<SqlEngine>>,
+) {
+    task.abort();
+    let _ = task.await;
+    server.abort();
+    let _ = server.await;
+    tokio::task::spawn_blocking(move || drop(engines))
+        .await
+        .expect("drop engines");
+}
+
+#[cfg_attr(miri, ignore = "QUIC / tokio I/O not supported under Miri")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn replica_applies_committed_changes_in_order() {
+    let (primary, conn, server, _dir, target) = primary_and_replica(&[
+        "CREATE TABLE items (id INT PRIMARY KEY, name TEXT)",
+        "INSERT INTO items (id, name) VALUES (1, 'one'), (2, 'two')",
+    ])
+    .await;
+    let status = Arc::new(ReplicaStatus::default());
+    let (replica, task) = follow(
+        conn,
+        target.clone(),
+        ReplicaConfig::default(),
+        status.clone(),
+    )
+    .await;
+
+    exec(
+        &primary,
+        &[
+            "INSERT INTO items (id, name) VALUES (3, 'three')",
+            "UPDATE items SET name = 'uno' WHERE id = 1",
+            "DELETE FROM items WHERE id = 2",
+            "BEGIN TRANSACTION",
+            "INSERT INTO items (id, name) VALUES (4, 'four')",
+            "ROLLBACK",
+            "CREATE TABLE tags (tag TEXT)",
+            "INSERT INTO tags (tag) VALUES ('red')",
+        ],
+    )
+    .await;
+    eventually("the last commit on the replica", async || {
+        !query(&replica, "SELECT tag FROM tags").await.is_empty()
+    })
+    .await;
+    assert_eq!(
+        query(&replica, "SELECT id, name FROM items").await,
+        vec![
+            vec!["Integer(1)", "Varchar(\"'uno'\")"],
+            vec!["Integer(3)", "Varchar(\"'three'\")"],
+        ]
+    );
+    assert_eq!(
+        query(&replica, "SELECT tag FROM tags").await,
+        vec![vec!["Varchar(\"'red'\")"]]
+    );
+    assert_eq!(status.applied_lsn(), status.received_lsn());
+    assert!(status.applied_commit_time() > 0);
+
+    // A restarted apply loop resumes after the recorded position instead of replaying.
+    assert_eq!(recorded_lsn(&target), status.applied_lsn());
+    shut_down(task, server, vec![primary, replica]).await;
+}
+
+#[cfg_attr(miri, ignore = "QUIC / tokio I/O not supported under Miri")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn delayed_replica_holds_commits_back() {
+    let (primary, conn, server, _dir, target) =
+        primary_and_replica(&["CREATE TABLE items (id INT, name TEXT)"]).await;
+    let status = Arc::new(ReplicaStatus::default());
+    let config = ReplicaConfig {
+        apply_delay: Duration::from_secs(3600),
+        label: "delayed".to_string(),
+        ..Default::default()
+    };
+    let start = recorded_lsn(&target);
+    let (replica, task) = follow(conn, target, config, status.clone()).await;
+
+    exec(
+        &primary,
+        &["INSERT INTO items (id, name) VALUES (1, 'one')"],
+    )
+    .await;
+    eventually("the commit to reach the replica", async || {
+        status.received_lsn() > start
+    })
+    .await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(status.applied_lsn(), start, "an hour has not passed");
+    assert!(query(&replica, "SELECT id FROM items").await.is_empty());
+    shut_down(task, server, vec![primary, replica]).await;
+}
+
+#[cfg_attr(miri, ignore = "QUIC / tokio I/O not supported under Miri")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn paused_replica_applies_after_resume() {
+    let (primary, conn, server, _dir, target) = primary_and_replica(&[
+        "CREATE TABLE items (id INT, name TEXT)",
+        "INSERT INTO items (id, name) VALUES (1, 'one')",
+    ])
+    .await;
+    let status = Arc::new(ReplicaStatus::default());
+    status.pause();
+    let start = recorded_lsn(&target);
+    let (replica, task) = follow(conn, target, ReplicaConfig::default(), status.clone()).await;
+
+    exec(&primary, &["DELETE FROM items WHERE id = 1"]).await;
+    eventually("the delete to reach the replica", async || {
+        status.received_lsn() > start
+    })
+    .await;
+    // The row the primary lost can still be read back from the paused replica.
+    assert_eq!(
+        query(&replica, "SELECT id FROM items").await,
+        vec![vec!["Integer(1)"]]
+    );
+
+    status.resume();
+    eventually("the delete to apply", async || {
+        status.applied_lsn() == status.received_lsn()
+    })
+    .await;
+    assert!(query(&replica, "SELECT id FROM items").await.is_empty());
+    shut_down(task, server, vec![primary, replica]).await;
+}
+
+#[cfg_attr(miri, ignore = "QUIC / tokio I/O not supported under Miri")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn replica_reads_wait_for_commit_lsn_token() {
+    let (primary, conn, server, _dir, target) =
+        primary_and_replica(&["CREATE TABLE items (id INT, name TEXT)"]).await;
+    let status = Arc::new(ReplicaStatus::default());
+    status.pause();
+    let (replica, task) = follow(conn, target, ReplicaConfig::default(), status.clone()).await;
+    replica.follow_replica_status(status.clone());
+
+    // The token of the session's commit, from the primary.
+    let eng = primary.clone();
+    let token = tokio::task::spawn_blocking(move || {
+        let mut ctx = SessionContext::default();
+        eng.execute_sql("INSERT INTO items (id, name) VALUES (1, 'one')", &mut ctx)
+            .expect("insert");
+        match eng.execute_sql("SHOW commit_lsn", &mut ctx).expect("show") {
+            EngineOutput::ResultSet { columns, rows } => {
+                let setting = columns
+                    .iter()
+                    .position(|c| c == "setting")
+                    .expect("setting");
+                rows[0][setting]
+                    .trim_start_matches("Varchar(\"'")
+                    .trim_end_matches("'\")")
+                    .parse::<u64>()
+                    .expect("lsn")
+            }
+            other => panic!("expected ResultSet, got {other:?}"),
+        }
+    })
+    .await
+    .expect("primary session");
+    assert!(token > status.applied_lsn());
+
+    let read = |timeout_ms: u64| {
+        let eng = replica.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut ctx = SessionContext::default();
+            for sql in [
+                format!("SET read_after_lsn = {token}"),
+                format!("SET read_after_lsn_timeout = {timeout_ms}"),
+            ] {
+                eng.execute_sql(&sql, &mut ctx).expect("set");
+            }
+            eng.execute_sql("SELECT id FROM items", &mut ctx).map(rows)
+        })
+    };
+    // Paused, the replica cannot reach the token.
+    let err = read(100)
+        .await
+        .expect("read")
+        .expect_err("replica is behind");
+    assert_eq!(err.code, engine_error_code::REPLICA_LAG_TIMEOUT);
+
+    // The read blocks until the commit is applied, then sees the write.
+    let pending = read(10_000);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!pending.is_finished());
+    status.resume();
+    assert_eq!(
+        pending.await.expect("read").expect("caught up"),
+        vec![vec!["Integer(1)"]]
+    );
+    assert!(status.applied_lsn() >= token);
+    shut_down(task, server, vec![primary, replica]).await;
+}
+
+#[cfg_attr(miri, ignore = "QUIC / tokio I/O not supported under Miri")]
+#[tokio::test]
+async fn replication_reports_unsupported_engine() {
+    let (conn, server) = serve(Arc::new(StubEngine::default())).await;
+    let target = TempDir::new().expect("tempdir");
+    std::fs::create_dir_all(target.path().join(".rustdb")).expect("mkdir");
+    std::fs::write(target.path().join(".rustdb/replica_lsn"), "0").expect("lsn file");
+    let status = ReplicaStatus::default();
+    match run_replica(
+        &conn,
+        Arc::new(StubEngine::default()),
+        target.path(),
+        &ReplicaConfig::default(),
+        &status,
+    )
+    .await
+    {
+        Err(ReplicationError::Server { code, .. }) => {
+            assert_eq!(code, crate::network::engine::engine_error_code::PROTOCOL)
+        }
+        other => panic!("expected server error, got {other:?}"),
+    }
+    server.abort();
+}
+
+#[test]
+fn synchronous_standby_names_parse() {
+    let parse = |s: &str| s.parse::<SynchronousStandbys>();
+    assert_eq!(
+        parse("ANY 2 (r1, r2, r3)").unwrap(),
+        SynchronousStandbys {
+            quorum: 2,
+            names: vec!["r1".into(), "r2".into(), "r3".into()],
+        }
+    );
+    assert_eq!(parse("1 (\"a\")").unwrap().names, vec!["a".to_string()]);
+    assert_eq!(parse("r1, r2").unwrap().quorum, 1);
+    assert_eq!(parse("ANY 3 (*)").unwrap().quorum, 3);
+    assert!(parse("ANY 3 (r1, r2)").is_err());
+    assert!(parse("ANY 0 (r1)").is_err());
+    assert!(parse("ANY 1 (r1").is_err());
+    assert!(parse("()").is_err());
+    assert_eq!(
+        "error".parse::<SyncTimeoutPolicy>(),
+        Ok(SyncTimeoutPolicy::Error)
+    );
+    assert!("later".parse::<SyncTimeoutPolicy>().is_err());
+}
+
+fn sync_config(
+    standbys: &str,
+    timeout: Duration,
+    on_timeout: SyncTimeoutPolicy,
+) -> SynchronousCommitConfig {
+    SynchronousCommitConfig {
+        standbys: standbys.parse().expect("standbys"),
+        timeout,
+        on_timeout,
+    }
+}
+
+#[test]
+fn commit_waits_for_quorum_of_distinct_standbys() {
+    let tracker = Arc::new(ReplicationTracker::default());
+    tracker.set_synchronous_commit(Some(sync_config(
+        "ANY 2 (a, b, c)",
+        Duration::from_secs(30),
+        SyncTimeoutPolicy::Error,
+    )));
+    let a = tracker.register("a", 0);
+    let a_again = tracker.register("a", 0);
+    let b = tracker.register("b", 0);
+    let async_replica = tracker.register("x", 0);
+    tracker.commit_logged(10);
+
+    let waiter = std::thread::spawn({
+        let tracker = tracker.clone();
+        move || tracker.wait_for_quorum(10)
+    });
+    a.ack(10);
+    a_again.ack(10);
+    async_replica.ack(10);
+    std::thread::sleep(Duration::from_millis(100));
+    assert!(
+        !waiter.is_finished(),
+        "one standby name is not a quorum of two"
+    );
+    b.ack(12);
+    assert_eq!(waiter.join().expect("waiter"), Ok(()));
+
+    let stats = tracker.statistics();
+    assert_eq!(stats.last_commit_lsn, 10);
+    assert_eq!(stats.synchronous_commits, 1);
+    assert_eq!(stats.synchronous_timeouts, 0);
+    let sync: Vec<_> = stats
+        .replicas
+        .iter()
+        .map(|r| (r.name.as_str(), r.synchronous))
+        .collect();
+    assert_eq!(
+        sync,
+        vec![("a", true), ("a", true), ("b", true), ("x", false)]
+    );
+    assert!(stats
+        .replicas
+        .iter()
+        .all(|r| r.lag_lsn == 0 && r.ack_lag.is_zero()));
+
+    drop(b);
+    tracker.commit_logged(20);
+    std::thread::sleep(Duration::from_millis(20));
+    let lagging = &tracker.statistics().replicas[0];
+    assert_eq!(lagging.lag_lsn, 10);
+    assert!(lagging.ack_lag >= Duration::from_millis(20));
+}
+
+#[test]
+fn quorum_timeout_follows_policy() {
+    let tracker = Arc::new(ReplicationTracker::default());
+    assert_eq!(tracker.wait_for_quorum(1), Ok(()), "asynchronous");
+    let _a = tracker.register("a", 0);
+    tracker.set_synchronous_commit(Some(sync_config(
+        "a",
+        Duration::from_millis(50),
+        SyncTimeoutPolicy::CommitLocally,
+    )));
+    assert_eq!(tracker.wait_for_quorum(1), Ok(()));
+    tracker.set_synchronous_commit(Some(sync_config(
+        "a",
+        Duration::from_millis(50),
+        SyncTimeoutPolicy::Error,
+    )));
+    let err = tracker.wait_for_quorum(1).expect_err("no ack");
+    assert_eq!((err.commit_lsn, err.acked, err.quorum), (1, 0, 1));
+    assert_eq!(tracker.statistics().synchronous_timeouts, 2);
+}
+
+#[cfg_attr(miri, ignore = "QUIC / tokio I/O not supported under Miri")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn synchronous_commit_returns_after_replica_applied_it() {
+    let (primary, conn, server, _dir, target) =
+        primary_and_replica(&["CREATE TABLE items (id INT, name TEXT)"]).await;
+    let tracker = primary.replication().clone();
+    tracker.set_synchronous_commit(Some(sync_config(
+        "ANY 1 (sync)",
+        Duration::from_secs(60),
+        SyncTimeoutPolicy::Error,
+    )));
+    let status = Arc::new(ReplicaStatus::default());
+    let config = ReplicaConfig {
+        label: "sync".to_string(),
+        ..Default::default()
+    };
+    let (replica, task) = follow(conn, target, config, status.clone()).await;
+    eventually("the replica to connect", async || {
+        tracker.statistics().replicas.len() == 1
+    })
+    .await;
+
+    exec(
+        &primary,
+        &["INSERT INTO items (id, name) VALUES (1, 'one')"],
+    )
+    .await;
+    // No waiting: the commit returned only once the replica had applied it.
+    assert_eq!(
+        query(&replica, "SELECT id FROM items").await,
+        vec![vec!["Integer(1)"]]
+    );
+    let stats = tracker.statistics();
+    assert_eq!(stats.synchronous_commits, 1);
+    assert_eq!(stats.synchronous_timeouts, 0);
+    assert_eq!(stats.replicas[0].name, "sync");
+    assert!(stats.replicas[0].synchronous);
+    assert!(stats.replicas[0].acked_lsn >= stats.last_commit_lsn);
+
+    // Without the replica the quorum cannot be met.
+    task.abort();
+    let _ = task.await;
+    eventually("the replica to disconnect", async || {
+        tracker.statistics().replicas.is_empty()
+    })
+    .await;
+    tracker.set_synchronous_commit(Some(sync_config(
+        "sync",
+        Duration::from_millis(100),
+        SyncTimeoutPolicy::Error,
+    )));
+    let eng = primary.clone();
+    let err = tokio::task::spawn_blocking(move || {
+        eng.execute_sql(
+            "INSERT INTO items (id, name) VALUES (2, 'two')",
+            &mut SessionContext::default(),
+        )
+    })
+    .await
+    .expect("insert")
+    .expect_err("quorum timeout");
+    assert_eq!(err.code, engine_error_code::SYNC_REPLICATION_TIMEOUT);
+    // Still committed on the primary.
+    assert_eq!(
+        query(&primary, "SELECT id FROM items").await,
+        vec![vec!["Integer(1)"], vec!["Integer(2)"]]
+    );
+    assert_eq!(tracker.statistics().synchronous_timeouts, 1);
+    server.abort();
+    let _ = server.await;
+    tokio::task::spawn_blocking(move || drop((primary, replica)))
+        .await
+        .expect("drop engines");
+}
+
+#[test]
+fn last_writer_wins_agrees_on_both_nodes() {
+    let row = |name: &str| {
+        Some(vec![
+            ReplicatedColumn {
+                name: "id".into(),
+                literal: "1".into(),
+            },
+            ReplicatedColumn {
+                name: "name".into(),
+                literal: name.into(),
+            },
+        ])
+    };
+    let conflict = |local: Option<Vec<ReplicatedColumn>>, lt, remote, rt| RowConflict {
+        table: "items".into(),
+        key: row("x").unwrap()[..1].to_vec(),
+        local,
+        local_commit_time: lt,
+        remote,
+        remote_commit_time: rt,
+    };
+    let lww = LastWriterWins;
+    assert_eq!(
+        lww.resolve(&conflict(row("'a'"), 10, row("'b'"), 11)),
+        Resolution::ApplyRemote
+    );
+    assert_eq!(
+        lww.resolve(&conflict(row("'b'"), 11, row("'a'"), 10)),
+        Resolution::KeepLocal
+    );
+    // Same second: both nodes keep the greater image.
+    assert_eq!(
+        lww.resolve(&conflict(row("'a'"), 10, row("'b'"), 10)),
+        Resolution::ApplyRemote
+    );
+    assert_eq!(
+        lww.resolve(&conflict(row("'b'"), 10, row("'a'"), 10)),
+        Resolution::KeepLocal
+    );
+    assert_eq!(
+        lww.resolve(&conflict(row("'b'"), 10, None, 10)),
+        Resolution::KeepLocal
+    );
+}
+
+#[cfg_attr(miri, ignore = "QUIC / tokio I/O not supported under Miri")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn bidirectional_nodes_converge_on_conflicting_updates() {
+    let (node_a, conn_a, server_a, dir_a, target) = primary_and_replica(&[
+        "CREATE TABLE items (id INT PRIMARY KEY, name TEXT)",
+        "INSERT INTO items (id, name) VALUES (1, 'one')",
+    ])
+    .await;
+    let bidirectional = |label: &str| ReplicaConfig {
+        label: label.to_string(),
+        conflict_resolver: Some(Arc::new(LastWriterWins)),
+        ..Default::default()
+    };
+    // B is provisioned from A; A follows B from the copy's position.
+    write_replica_lsn(dir_a.path(), recorded_lsn(&target))
+        .await
+        .expect("start lsn");
+    let status_b = Arc::new(ReplicaStatus::default());
+    let (node_b, task_b) = follow(conn_a, target, bidirectional("b"), status_b.clone()).await;
+    let (conn_b, server_b) = serve(node_b.clone()).await;
+    let status_a = Arc::new(ReplicaStatus::default());
+    let task_a = tokio::spawn({
+        let engine: Arc<dyn EngineHandle> = node_a.clone();
+        let dir = dir_a.path().to_path_buf();
+        let status = status_a.clone();
+        async move { run_replica(&conn_b, engine, &dir, &bidirectional("a"), &status).await }
+    });
+
+    exec(&node_a, &["INSERT INTO items (id, name) VALUES (2, 'two')"]).await;
+    exec(
+        &node_b,
+        &["INSERT INTO items (id, name) VALUES (3, 'three')"],
+    )
+    .await;
+    let all = vec![vec!["Integer(1)"], vec!["Integer(2)"], vec!["Integer(3)"]];
+    for node in [&node_a, &node_b] {
+        eventually("both inserts on both nodes", async || {
+            query(node, "SELECT id FROM items").await == all
+        })
+        .await;
+    }
+
+    // Both nodes change row 1 before seeing the other's change.
+    status_a.pause();
+    status_b.pause();
+    let (seen_a, seen_b) = (status_a.received_lsn(), status_b.received_lsn());
+    exec(&node_a, &["UPDATE items SET name = 'a' WHERE id = 1"]).await;
+    exec(&node_b, &["UPDATE items SET name = 'b' WHERE id = 1"]).await;
+    eventually("the updates to cross", async || {
+        status_a.received_lsn() > seen_a && status_b.received_lsn() > seen_b
+    })
+    .await;
+    status_a.resume();
+    status_b.resume();
+    eventually("both conflicts to resolve", async || {
+        status_a.conflicts() == 1 && status_b.conflicts() == 1
+    })
+    .await;
+    eventually("the nodes to converge", async || {
+        query(&node_a, "SELECT id, name FROM items").await
+            == query(&node_b, "SELECT id, name FROM items").await
+    })
+    .await;
+    let winner = query(&node_a, "SELECT id, name FROM items").await[0][1].clone();
+    assert!(
+        winner == "Varchar(\"'a'\")" || winner == "Varchar(\"'b'\")",
+        "{winner}"
+    );
+    // Replayed transactions are not echoed back and forth.
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(status_a.conflicts(), 1);
+    assert_eq!(status_b.conflicts(), 1);
+    assert_eq!(query(&node_b, "SELECT id FROM items").await, all);
+
+    task_a.abort();
+    let _ = task_a.await;
+    server_b.abort();
+    let _ = server_b.await;
+    shut_down(task_b, server_a, vec![node_a, node_b]).await;
+}
@@ -1,7 +1,8 @@
 //! End-to-end SQL through [`crate::network::SqlEngine`]: WHERE, ORDER BY, LIMIT, OFFSET.
 //! Written in a TDD style: behavior is asserted at the engine boundary.
 
-use crate::network::engine::{EngineHandle, EngineOutput, SessionContext};
+use crate::common::types::{ColumnValue, DataType};
+use crate::network::engine::{engine_error_code, EngineHandle, EngineOutput, SessionContext};
 use crate::network::SqlEngine;
 use tempfile::TempDir;
 
@@ -117,6 +118,52 @@ fn engine_select_order_by_sorts_strings() {
     }
 }
 
+#[test]
+fn engine_order_by_nulls_first_last_spills_past_work_mem() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut ctx = SessionContext::default();
+    eng.execute_sql(
+        "CREATE TABLE m (id INTEGER PRIMARY KEY, v INTEGER)",
+        &mut ctx,
+    )
+    .expect("create");
+    let values: Vec<String> = (0..300)
+        .map(|i| match i % 10 {
+            0 => format!("({i}, NULL)"),
+            _ => format!("({i}, {})", (i * 37) % 101),
+        })
+        .collect();
+    eng.execute_sql(
+        &format!("INSERT INTO m (id, v) VALUES {}", values.join(", ")),
+        &mut ctx,
+    )
+    .expect("insert");
+    eng.execute_sql("SET execution.work_mem = 2048", &mut ctx)
+        .expect("set work_mem");
+
+    let sorted = |sql: &str| match eng.execute_sql(sql, &mut SessionContext::default()) {
+        Ok(EngineOutput::ResultSet { rows, .. }) => rows
+            .into_iter()
+            .map(|r| match r[0].as_str() {
+                "Null" => None,
+                v => Some(v.to_string()),
+            })
+            .collect::<Vec<_>>(),
+        other => panic!("{other:?}"),
+    };
+    let first = sorted("SELECT v FROM m ORDER BY v ASC NULLS FIRST, id");
+    assert_eq!(first.len(), 300);
+    assert!(first[..30].iter().all(Option::is_none), "{first:?}");
+    assert_eq!(first[30].as_deref(), Some("Integer(0)"));
+    let last = sorted("SELECT v FROM m ORDER BY v DESC NULLS LAST");
+    assert_eq!(last[0].as_deref(), Some("Integer(100)"));
+    assert!(last[270..].iter().all(Option::is_none), "{last:?}");
+    // Runs are removed once the query is done.
+    let temp = dir.path().join("tmp");
+    assert!(!temp.exists() || std::fs::read_dir(&temp).unwrap().count() == 0);
+}
+
 #[test]
 fn engine_select_limit_and_limit_offset() {
     let dir = TempDir::new().expect("tempdir");
@@ -250,6 +297,181 @@ fn engine_create_drop_table_roundtrip() {
     eng.execute_sql("DROP TABLE users", &mut ctx).expect("drop");
 }
 
+#[test]
+fn engine_lsm_table_dml_survives_reopen() {
+    let dir = TempDir::new().expect("tempdir");
+    {
+        let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+        let mut ctx = SessionContext::default();
+        eng.execute_sql(
+            "CREATE TABLE events (id INT PRIMARY KEY, v INT) ENGINE lsm",
+            &mut ctx,
+        )
+        .expect("create");
+        for i in 1..=50 {
+            eng.execute_sql(
+                &format!("INSERT INTO events (id, v) VALUES ({i}, {})", i * 10),
+                &mut ctx,
+            )
+            .expect("insert");
+        }
+        eng.execute_sql("UPDATE events SET v = 0 WHERE id = 7", &mut ctx)
+            .expect("update");
+        for i in 41..=50 {
+            eng.execute_sql(&format!("DELETE FROM events WHERE id = {i}"), &mut ctx)
+                .expect("delete");
+        }
+        assert!(eng
+            .execute_sql("INSERT INTO events (id, v) VALUES (1, 1)", &mut ctx)
+            .is_err());
+    }
+    assert!(dir.path().join("events.lsm").is_dir());
+    assert!(!dir.path().join("events.tbl").exists());
+
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("reopen");
+    let mut ctx = SessionContext::default();
+    match eng
+        .execute_sql(
+            "SELECT v FROM events WHERE id = 7 OR id = 8 ORDER BY id",
+            &mut ctx,
+        )
+        .expect("select")
+    {
+        EngineOutput::ResultSet { rows, .. } => {
+            assert_eq!(rows, vec![vec!["Integer(0)"], vec!["Integer(80)"]]);
+        }
+        _ => panic!("expected ResultSet"),
+    }
+    match eng
+        .execute_sql("SELECT id FROM events", &mut ctx)
+        .expect("scan")
+    {
+        EngineOutput::ResultSet { rows, .. } => assert_eq!(rows.len(), 40),
+        _ => panic!("expected ResultSet"),
+    }
+    assert!(eng
+        .execute_sql("CREATE TABLE bad (a INT) ENGINE columnar", &mut ctx)
+        .is_err());
+    eng.execute_sql("DROP TABLE events", &mut ctx)
+        .expect("drop");
+    assert!(!dir.path().join("events.lsm").exists());
+}
+
+#[test]
+fn engine_foreign_csv_table_joins_native_table() {
+    let dir = TempDir::new().expect("tempdir");
+    std::fs::write(
+        dir.path().join("customers.csv"),
+        "cust_id,name,city\n1,Ada,London\n2,\"Lin, Wei\",Oslo\n3,Bo,\n",
+    )
+    .expect("write csv");
+    {
+        let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+        let mut ctx = SessionContext::default();
+        eng.execute_sql(
+            "CREATE FOREIGN TABLE customers (cust_id INT, name TEXT, city TEXT) \
+             USING csv OPTIONS (path 'customers.csv', header 'true')",
+            &mut ctx,
+        )
+        .expect("create foreign");
+        eng.execute_sql(
+            "CREATE TABLE orders (order_id INT PRIMARY KEY, customer INT, amount INT)",
+            &mut ctx,
+        )
+        .expect("create orders");
+        for (id, customer, amount) in [(10, 2, 5), (11, 1, 7), (12, 2, 9)] {
+            eng.execute_sql(
+                &format!(
+                    "INSERT INTO orders (order_id, customer, amount) \
+                     VALUES ({id}, {customer}, {amount})"
+                ),
+                &mut ctx,
+            )
+            .expect("insert");
+        }
+        match eng
+            .execute_sql(
+                "SELECT o.order_id, c.name FROM orders o JOIN customers c \
+                 ON o.customer = c.cust_id ORDER BY order_id",
+                &mut ctx,
+            )
+            .expect("join")
+        {
+            EngineOutput::ResultSet { columns, rows } => {
+                assert_eq!(columns, vec!["name", "order_id"]);
+                assert_eq!(
+                    rows,
+                    vec![
+                        vec!["Varchar(\"Lin, Wei\")", "Integer(10)"],
+                        vec!["Varchar(\"Ada\")", "Integer(11)"],
+                        vec!["Varchar(\"Lin, Wei\")", "Integer(12)"],
+                    ]
+                );
+            }
+            _ => panic!("expected ResultSet"),
+        }
+        assert!(eng
+            .execute_sql("INSERT INTO customers (cust_id) VALUES (4)", &mut ctx)
+            .is_err());
+        assert!(eng
+            .execute_sql("CREATE INDEX idx_city ON customers (city)", &mut ctx)
+            .is_err());
+        assert!(eng
+            .execute_sql(
+                "CREATE FOREIGN TABLE missing (a INT) USING csv OPTIONS (path 'nope.csv')",
+                &mut ctx,
+            )
+            .is_err());
+    }
+    assert!(!dir.path().join("customers.tbl").exists());
+
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("reopen");
+    let mut ctx = SessionContext::default();
+    match eng
+        .execute_sql("SELECT name FROM customers LIMIT 2", &mut ctx)
+        .expect("limit")
+    {
+        EngineOutput::ResultSet { rows, .. } => {
+            assert_eq!(
+                rows,
+                vec![vec!["Varchar(\"Ada\")"], vec!["Varchar(\"Lin, Wei\")"]]
+            );
+        }
+        _ => panic!("expected ResultSet"),
+    }
+    match eng
+        .execute_sql("SELECT city FROM customers WHERE cust_id = 3", &mut ctx)
+        .expect("null city")
+    {
+        EngineOutput::ResultSet { rows, .. } => assert_eq!(rows, vec![vec!["Null"]]),
+        _ => panic!("expected ResultSet"),
+    }
+    match eng
+        .execute_sql("EXPLAIN SELECT name FROM customers LIMIT 2", &mut ctx)
+        .expect("explain")
+    {
+        EngineOutput::ResultSet { rows, .. } => {
+            let plan: Vec<String> = rows.into_iter().flatten().collect();
+            let plan = plan.join("\n");
+            assert!(
+                plan.contains("Foreign Scan on customers using csv") && plan.contains("limit=2"),
+                "{plan}"
+            );
+            assert!(plan.contains("Columns: name"), "{plan}");
+        }
+        _ => panic!("expected ResultSet"),
+    }
+    eng.execute_sql("DROP TABLE customers", &mut ctx)
+        .expect("drop");
+    assert!(dir.path().join("customers.csv").exists());
+    eng.execute_sql(
+        "CREATE FOREIGN TABLE customers (cust_id INT, name TEXT, city TEXT) \
+         USING csv OPTIONS (path 'customers.csv', header 'true')",
+        &mut ctx,
+    )
+    .expect("recreate after drop");
+}
+
 #[test]
 fn engine_enforces_not_null_default_and_check() {
     let dir = TempDir::new().expect("tempdir");
@@ -400,442 +622,3468 @@ fn engine_reopen_constraint_rebuild_insert_values_without_column_list() {
 }
 
 #[test]
-fn engine_transaction_insert_commit_keeps_row() {
+fn engine_prepared_transaction_finishes_from_another_session() {
+    use crate::network::engine::engine_error_code;
+
     let dir = TempDir::new().expect("tempdir");
     let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
     let mut ctx = SessionContext::default();
-    eng.execute_sql("CREATE TABLE txc (k INT PRIMARY KEY)", &mut ctx)
-        .unwrap();
-    eng.execute_sql("BEGIN TRANSACTION", &mut ctx).unwrap();
-    eng.execute_sql("INSERT INTO txc (k) VALUES (7)", &mut ctx)
+    eng.execute_sql("CREATE TABLE txp (k INT PRIMARY KEY)", &mut ctx)
         .unwrap();
-    eng.execute_sql("COMMIT", &mut ctx).unwrap();
-    let out = eng
-        .execute_sql("SELECT k FROM txc", &mut ctx)
-        .expect("select");
-    match out {
-        EngineOutput::ResultSet { rows, .. } => assert_eq!(rows.len(), 1),
-        _ => panic!("expected ResultSet"),
-    }
-}
-
-#[test]
-fn engine_transaction_commit_then_rollback_does_not_corrupt_pk_on_reopen() {
-    let dir = TempDir::new().expect("tempdir");
-    {
-        let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
-        let mut ctx = SessionContext::default();
-        eng.execute_sql("CREATE TABLE ss92_tc (k INT PRIMARY KEY)", &mut ctx)
-            .unwrap();
+    for (k, gid) in [(1, "commit-me"), (2, "roll-me-back")] {
         eng.execute_sql("BEGIN TRANSACTION", &mut ctx).unwrap();
-        eng.execute_sql("INSERT INTO ss92_tc (k) VALUES (701)", &mut ctx)
+        eng.execute_sql(&format!("INSERT INTO txp (k) VALUES ({k})"), &mut ctx)
             .unwrap();
-        eng.execute_sql("COMMIT", &mut ctx).unwrap();
-
-        eng.execute_sql("BEGIN TRANSACTION", &mut ctx).unwrap();
-        eng.execute_sql("INSERT INTO ss92_tc (k) VALUES (702)", &mut ctx)
+        eng.execute_sql(&format!("PREPARE TRANSACTION '{gid}'"), &mut ctx)
             .unwrap();
-        eng.execute_sql("ROLLBACK", &mut ctx).unwrap();
-    }
-
-    // Reopen should not fail constraint rebuild due to duplicate PKs.
-    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("reopen");
-    let mut ctx = SessionContext::default();
-
-    let out_701 = eng
-        .execute_sql("SELECT k FROM ss92_tc WHERE k = 701", &mut ctx)
-        .expect("select 701");
-    match out_701 {
-        EngineOutput::ResultSet { rows, .. } => assert_eq!(rows.len(), 1),
-        _ => panic!("expected ResultSet"),
+        // The session is free again once its transaction is prepared.
+        assert_eq!(
+            eng.execute_sql("COMMIT", &mut ctx).unwrap_err().code,
+            engine_error_code::NO_ACTIVE_TRANSACTION
+        );
     }
+    eng.execute_sql("BEGIN TRANSACTION", &mut ctx).unwrap();
+    assert_eq!(
+        eng.execute_sql("PREPARE TRANSACTION 'commit-me'", &mut ctx)
+            .unwrap_err()
+            .code,
+        engine_error_code::PREPARED_TRANSACTION
+    );
+    eng.execute_sql("ROLLBACK", &mut ctx).unwrap();
 
-    let out_702 = eng
-        .execute_sql("SELECT k FROM ss92_tc WHERE k = 702", &mut ctx)
-        .expect("select 702");
-    match out_702 {
-        EngineOutput::ResultSet { rows, .. } => assert!(rows.is_empty()),
+    let mut other = SessionContext::default();
+    eng.execute_sql("COMMIT PREPARED 'commit-me'", &mut other)
+        .unwrap();
+    eng.execute_sql("ROLLBACK PREPARED 'roll-me-back'", &mut other)
+        .unwrap();
+    assert_eq!(
+        eng.execute_sql("COMMIT PREPARED 'commit-me'", &mut other)
+            .unwrap_err()
+            .code,
+        engine_error_code::PREPARED_TRANSACTION
+    );
+    match eng
+        .execute_sql("SELECT k FROM txp", &mut other)
+        .expect("select")
+    {
+        EngineOutput::ResultSet { rows, .. } => assert_eq!(rows, vec![vec!["Integer(1)"]]),
         _ => panic!("expected ResultSet"),
     }
 }
 
 #[test]
-fn engine_ddl_rejected_inside_transaction() {
+fn engine_exported_snapshot_is_shared_by_importing_sessions() {
     use crate::network::engine::engine_error_code;
 
     let dir = TempDir::new().expect("tempdir");
     let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
-    let mut ctx = SessionContext::default();
-    eng.execute_sql("BEGIN TRANSACTION", &mut ctx).unwrap();
+    let keys = |out: EngineOutput| match out {
+        EngineOutput::ResultSet { mut rows, .. } => {
+            rows.sort();
+            rows.into_iter().map(|r| r[0].clone()).collect::<Vec<_>>()
+        }
+        _ => panic!("expected ResultSet"),
+    };
+    let mut writer = SessionContext::default();
+    eng.execute_sql("CREATE TABLE snap (k INT PRIMARY KEY)", &mut writer)
+        .unwrap();
+    eng.execute_sql("INSERT INTO snap (k) VALUES (1)", &mut writer)
+        .unwrap();
+    // Uncommitted at export time: not part of the snapshot.
+    let mut in_flight = SessionContext::default();
+    eng.execute_sql("BEGIN TRANSACTION", &mut in_flight)
+        .unwrap();
+    eng.execute_sql("INSERT INTO snap (k) VALUES (2)", &mut in_flight)
+        .unwrap();
+
+    let mut exporter = SessionContext::default();
     assert_eq!(
-        eng.execute_sql("CREATE TABLE n (x INT)", &mut ctx)
+        eng.execute_sql("EXPORT SNAPSHOT", &mut exporter)
             .unwrap_err()
             .code,
-        engine_error_code::DDL_IN_TRANSACTION
+        engine_error_code::INVALID_SNAPSHOT
+    );
+    eng.execute_sql("BEGIN TRANSACTION", &mut exporter).unwrap();
+    let id = keys(eng.execute_sql("EXPORT SNAPSHOT", &mut exporter).unwrap()).remove(0);
+    assert_eq!(
+        keys(eng.execute_sql("EXPORT SNAPSHOT", &mut exporter).unwrap()),
+        vec![id.clone()]
+    );
+    eng.execute_sql("COMMIT", &mut in_flight).unwrap();
+    eng.execute_sql("INSERT INTO snap (k) VALUES (3)", &mut writer)
+        .unwrap();
+
+    let mut importer = SessionContext::default();
+    eng.execute_sql("BEGIN TRANSACTION", &mut importer).unwrap();
+    eng.execute_sql(&format!("SET TRANSACTION SNAPSHOT '{id}'"), &mut importer)
+        .unwrap();
+    for ctx in [&mut exporter, &mut importer] {
+        assert_eq!(
+            keys(eng.execute_sql("SELECT k FROM snap", ctx).unwrap()),
+            vec!["Integer(1)"]
+        );
+        assert_eq!(
+            eng.execute_sql("INSERT INTO snap (k) VALUES (4)", ctx)
+                .unwrap_err()
+                .code,
+            engine_error_code::INVALID_SNAPSHOT
+        );
+    }
+    assert_eq!(
+        keys(eng.execute_sql("SELECT k FROM snap", &mut writer).unwrap()).len(),
+        3
+    );
+
+    // The id is importable only while the exporting transaction is open; importers keep it.
+    eng.execute_sql("COMMIT", &mut exporter).unwrap();
+    let mut late = SessionContext::default();
+    eng.execute_sql("BEGIN TRANSACTION", &mut late).unwrap();
+    assert_eq!(
+        eng.execute_sql(&format!("SET TRANSACTION SNAPSHOT '{id}'"), &mut late)
+            .unwrap_err()
+            .code,
+        engine_error_code::INVALID_SNAPSHOT
+    );
+    assert_eq!(
+        keys(
+            eng.execute_sql("SELECT k FROM snap", &mut importer)
+                .unwrap()
+        ),
+        vec!["Integer(1)"]
+    );
+    eng.execute_sql("COMMIT", &mut importer).unwrap();
+    assert_eq!(
+        keys(
+            eng.execute_sql("SELECT k FROM snap", &mut importer)
+                .unwrap()
+        )
+        .len(),
+        3
     );
-    eng.execute_sql("ROLLBACK", &mut ctx).unwrap();
 }
 
-/// Eight threads contend on `INSERT … VALUES (1)`; exactly one succeeds.
-#[cfg(not(rustdb_loom))]
 #[test]
-fn engine_concurrent_inserts_only_one_wins_same_pk() {
-    use std::sync::atomic::{AtomicU32, Ordering};
-    use std::sync::Arc;
-    use std::thread;
-
+fn engine_create_index_builds_in_parallel_and_reports_progress() {
     let dir = TempDir::new().expect("tempdir");
-    let eng = Arc::new(SqlEngine::open(dir.path().to_path_buf()).expect("open"));
-    let mut setup = SessionContext::default();
-    eng.execute_sql("CREATE TABLE conc (id INT PRIMARY KEY)", &mut setup)
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut ctx = SessionContext::default();
+    eng.execute_sql(
+        "CREATE TABLE big (id INT PRIMARY KEY, grp INT, pad VARCHAR(64))",
+        &mut ctx,
+    )
+    .unwrap();
+    let values: Vec<String> = (0..3000)
+        .map(|i| format!("({i}, {}, 'padding-padding-padding-{i}')", i % 7))
+        .collect();
+    eng.execute_sql(
+        &format!(
+            "INSERT INTO big (id, grp, pad) VALUES {}",
+            values.join(", ")
+        ),
+        &mut ctx,
+    )
+    .unwrap();
+    // The workers' entries do not fit in the work memory: they are sorted in spilled runs.
+    eng.execute_sql("SET execution.work_mem = 4096", &mut ctx)
+        .expect("set work_mem");
+    eng.execute_sql("CREATE INDEX idx_grp ON big (grp)", &mut ctx)
         .unwrap();
 
-    let barrier = Arc::new(std::sync::Barrier::new(8));
-    let ok_count = Arc::new(AtomicU32::new(0));
-    let mut handles = vec![];
-    for _ in 0..8 {
-        let eng = eng.clone();
-        let barrier = barrier.clone();
-        let ok_count = ok_count.clone();
-        handles.push(thread::spawn(move || {
-            let mut ctx = SessionContext::default();
-            barrier.wait();
-            if eng
-                .execute_sql("INSERT INTO conc (id) VALUES (1)", &mut ctx)
-                .is_ok()
-            {
-                ok_count.fetch_add(1, Ordering::SeqCst);
-            }
-        }));
+    match eng
+        .execute_sql("SELECT id FROM big WHERE grp = 3", &mut ctx)
+        .unwrap()
+    {
+        EngineOutput::ResultSet { rows, .. } => assert_eq!(rows.len(), 429),
+        _ => panic!("expected ResultSet"),
     }
-    for h in handles {
-        h.join().expect("join");
+    match eng
+        .execute_sql(
+            "SELECT phase, pages_done, pages_total, tuples_loaded FROM \
+             rustdb_stat_progress_create_index WHERE index_name = 'idx_grp'",
+            &mut ctx,
+        )
+        .unwrap()
+    {
+        EngineOutput::ResultSet { columns, rows } => {
+            assert_eq!(
+                columns,
+                vec!["pages_done", "pages_total", "phase", "tuples_loaded"]
+            );
+            assert_eq!(rows.len(), 1);
+            assert_eq!(rows[0][0], rows[0][1]);
+            assert_ne!(rows[0][1], "BigInt(0)");
+            assert_eq!(rows[0][2], "Varchar(\"'done'\")");
+            assert_eq!(rows[0][3], "BigInt(3000)");
+        }
+        _ => panic!("expected ResultSet"),
+    }
+    drop(eng);
+
+    // An index whose file is gone is rebuilt on open through the same path.
+    std::fs::remove_file(dir.path().join("big.idx_grp.bpt")).expect("remove index file");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("reopen");
+    match eng
+        .execute_sql("SELECT id FROM big WHERE grp = 6", &mut ctx)
+        .unwrap()
+    {
+        EngineOutput::ResultSet { rows, .. } => assert_eq!(rows.len(), 428),
+        _ => panic!("expected ResultSet"),
+    }
+    match eng
+        .execute_sql("SELECT * FROM rustdb_stat_progress_create_index", &mut ctx)
+        .unwrap()
+    {
+        EngineOutput::ResultSet { rows, .. } => assert_eq!(rows.len(), 1),
+        _ => panic!("expected ResultSet"),
     }
-    assert_eq!(ok_count.load(Ordering::SeqCst), 1);
 }
 
-/// Same property under [loom](https://github.com/tokio-rs/loom) permutation scheduling (`RUSTFLAGS="--cfg rustdb_loom"`).
-#[cfg(rustdb_loom)]
 #[test]
-fn engine_concurrent_inserts_only_one_wins_same_pk() {
-    use loom::sync::atomic::AtomicU32;
-    use loom::sync::atomic::Ordering::SeqCst;
-    use loom::sync::Arc;
+fn engine_create_index_concurrently_keeps_up_with_concurrent_writes() {
+    use std::sync::atomic::{AtomicBool, Ordering};
 
-    loom::model(|| {
-        let dir = TempDir::new().expect("tempdir");
-        let eng = Arc::new(SqlEngine::open(dir.path().to_path_buf()).expect("open"));
-        let mut setup = SessionContext::default();
-        eng.execute_sql("CREATE TABLE conc (id INT PRIMARY KEY)", &mut setup)
-            .unwrap();
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut ctx = SessionContext::default();
+    eng.execute_sql(
+        "CREATE TABLE live (id INT, grp INT, pad VARCHAR(64))",
+        &mut ctx,
+    )
+    .unwrap();
+    let values: Vec<String> = (0..4000)
+        .map(|i| format!("({i}, {}, 'padding-padding-padding-{i}')", i % 7))
+        .collect();
+    eng.execute_sql(
+        &format!(
+            "INSERT INTO live (id, grp, pad) VALUES {}",
+            values.join(", ")
+        ),
+        &mut ctx,
+    )
+    .unwrap();
 
-        let ok_count = Arc::new(AtomicU32::new(0));
-        let ea = eng.clone();
-        let eb = eng.clone();
-        let oa = ok_count.clone();
-        let ob = ok_count.clone();
-        let h1 = loom::thread::spawn(move || {
-            let mut ctx = SessionContext::default();
-            if ea
-                .execute_sql("INSERT INTO conc (id) VALUES (1)", &mut ctx)
-                .is_ok()
-            {
-                oa.fetch_add(1, SeqCst);
-            }
-        });
-        let h2 = loom::thread::spawn(move || {
-            let mut ctx = SessionContext::default();
-            if eb
-                .execute_sql("INSERT INTO conc (id) VALUES (1)", &mut ctx)
-                .is_ok()
-            {
-                ob.fetch_add(1, SeqCst);
+    let built = AtomicBool::new(false);
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            let mut w = SessionContext::default();
+            let mut n = 0;
+            // Keeps writing until the index is registered, plus a few statements after.
+            while !built.load(Ordering::Acquire) || n % 50 != 49 {
+                let sql = match n % 3 {
+                    0 => format!(
+                        "INSERT INTO live (id, grp, pad) VALUES ({}, {}, 'w')",
+                        10_000 + n,
+                        n % 7
+                    ),
+                    1 => format!(
+                        "UPDATE live SET grp = {} WHERE id = {}",
+                        (n + 3) % 7,
+                        n * 5 % 4000
+                    ),
+                    _ => format!("DELETE FROM live WHERE id = {}", n * 11 % 4000),
+                };
+                eng.execute_sql(&sql, &mut w).unwrap();
+                n += 1;
             }
         });
-        h1.join().unwrap();
-        h2.join().unwrap();
-        assert_eq!(ok_count.load(SeqCst), 1);
+        eng.execute_sql(
+            "CREATE INDEX CONCURRENTLY idx_live_grp ON live (grp)",
+            &mut ctx,
+        )
+        .unwrap();
+        built.store(true, Ordering::Release);
     });
-}
 
-#[test]
-fn engine_select_named_table_after_engine_reopen() {
-    let dir = TempDir::new().expect("tempdir");
-    {
-        let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
-        let mut ctx = SessionContext::default();
-        eng.execute_sql("INSERT INTO stateful_r (a) VALUES (100)", &mut ctx)
-            .expect("insert");
-    }
-    let heap = dir.path().join("stateful_r.tbl");
-    assert!(
-        heap.exists() && heap.metadata().expect("meta").len() > 0,
-        "expected persisted heap file with data"
-    );
-    {
-        let eng = SqlEngine::open(dir.path().to_path_buf()).expect("reopen");
-        let mut ctx = SessionContext::default();
-        let out = eng
-            .execute_sql("SELECT a FROM stateful_r", &mut ctx)
-            .expect("select");
-        match out {
-            EngineOutput::ResultSet { columns, rows } => {
-                assert_eq!(columns, vec!["a"]);
-                assert_eq!(rows.len(), 1);
-                assert_eq!(rows[0], vec!["Integer(100)"]);
+    let mut scanned = [0usize; 7];
+    match eng.execute_sql("SELECT grp FROM live", &mut ctx).unwrap() {
+        EngineOutput::ResultSet { rows, .. } => {
+            for r in rows {
+                let g: usize = r[0]
+                    .trim_start_matches("Integer(")
+                    .trim_end_matches(')')
+                    .parse()
+                    .unwrap();
+                scanned[g] += 1;
             }
+        }
+        _ => panic!("expected ResultSet"),
+    }
+    for (g, expected) in scanned.iter().enumerate() {
+        match eng
+            .execute_sql(&format!("SELECT id FROM live WHERE grp = {g}"), &mut ctx)
+            .unwrap()
+        {
+            EngineOutput::ResultSet { rows, .. } => assert_eq!(rows.len(), *expected, "grp {g}"),
             _ => panic!("expected ResultSet"),
         }
     }
-}
-
-#[test]
-fn engine_enforces_primary_key_and_unique() {
-    let dir = TempDir::new().expect("tempdir");
-    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
-    let mut ctx = SessionContext::default();
-    eng.execute_sql(
-        "CREATE TABLE pk (id INT PRIMARY KEY, a INT UNIQUE)",
-        &mut ctx,
-    )
-    .expect("create");
-    eng.execute_sql("INSERT INTO pk (id, a) VALUES (1, 10)", &mut ctx)
-        .expect("i1");
+    match eng
+        .execute_sql(
+            "SELECT phase FROM rustdb_stat_progress_create_index \
+             WHERE index_name = 'idx_live_grp'",
+            &mut ctx,
+        )
+        .unwrap()
+    {
+        EngineOutput::ResultSet { rows, .. } => {
+            assert_eq!(rows, vec![vec!["Varchar(\"'done'\")".to_string()]])
+        }
+        _ => panic!("expected ResultSet"),
+    }
     assert!(eng
-        .execute_sql("INSERT INTO pk (id, a) VALUES (1, 20)", &mut ctx)
+        .execute_sql(
+            "CREATE INDEX CONCURRENTLY idx_live_grp ON live (grp)",
+            &mut ctx
+        )
         .is_err());
+    eng.execute_sql("BEGIN TRANSACTION", &mut ctx).unwrap();
     assert!(eng
-        .execute_sql("INSERT INTO pk (id, a) VALUES (2, 10)", &mut ctx)
+        .execute_sql(
+            "CREATE INDEX CONCURRENTLY idx_live_pad ON live (pad)",
+            &mut ctx
+        )
         .is_err());
-    eng.execute_sql("INSERT INTO pk (id, a) VALUES (2, 20)", &mut ctx)
-        .expect("i2");
-}
+    eng.execute_sql("ROLLBACK", &mut ctx).unwrap();
+    drop(eng);
 
-#[test]
-fn engine_enforces_foreign_key_and_parent_delete() {
-    let dir = TempDir::new().expect("tempdir");
-    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
-    let mut ctx = SessionContext::default();
-    eng.execute_sql("CREATE TABLE parent (id INT PRIMARY KEY)", &mut ctx)
-        .expect("p");
-    eng.execute_sql(
-        "CREATE TABLE child (pid INT REFERENCES parent(id))",
-        &mut ctx,
-    )
-    .expect("c");
-    assert!(eng
-        .execute_sql("INSERT INTO child (pid) VALUES (5)", &mut ctx)
-        .is_err());
-    eng.execute_sql("INSERT INTO parent (id) VALUES (5)", &mut ctx)
-        .expect("ip");
-    eng.execute_sql("INSERT INTO child (pid) VALUES (5)", &mut ctx)
-        .expect("ic");
-    assert!(eng
-        .execute_sql("DELETE FROM parent WHERE id = 5", &mut ctx)
-        .is_err());
-    eng.execute_sql("DELETE FROM child WHERE pid = 5", &mut ctx)
-        .expect("del child");
-    eng.execute_sql("DELETE FROM parent WHERE id = 5", &mut ctx)
-        .expect("del parent");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("reopen");
+    match eng
+        .execute_sql("SELECT id FROM live WHERE grp = 2", &mut ctx)
+        .unwrap()
+    {
+        EngineOutput::ResultSet { rows, .. } => assert_eq!(rows.len(), scanned[2]),
+        _ => panic!("expected ResultSet"),
+    }
 }
 
 #[test]
-fn engine_drop_table_restrict_and_cascade() {
+fn engine_background_jobs_view_reports_triggered_checkpoint() {
     let dir = TempDir::new().expect("tempdir");
     let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
     let mut ctx = SessionContext::default();
-    eng.execute_sql("CREATE TABLE p (id INT PRIMARY KEY)", &mut ctx)
-        .expect("p");
-    eng.execute_sql("CREATE TABLE c (pid INT REFERENCES p(id))", &mut ctx)
-        .expect("c");
-    assert!(eng.execute_sql("DROP TABLE p", &mut ctx).is_err());
-    eng.execute_sql("DROP TABLE p CASCADE", &mut ctx)
-        .expect("drop cascade");
-    eng.execute_sql("CREATE TABLE c (x INT)", &mut ctx)
-        .expect("recreate c after cascade drop");
+    let jobs = |ctx: &mut SessionContext| match eng
+        .execute_sql(
+            "SELECT job_name, state, runs, failures FROM rustdb_stat_background_jobs",
+            ctx,
+        )
+        .unwrap()
+    {
+        EngineOutput::ResultSet { columns, rows } => {
+            assert_eq!(columns, vec!["failures", "job_name", "runs", "state"]);
+            rows
+        }
+        _ => panic!("expected ResultSet"),
+    };
+    let rows = jobs(&mut ctx);
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0][1], "Varchar(\"'checkpoint'\")");
+    assert_eq!(rows[0][2], "BigInt(0)");
+    assert_eq!(rows[0][3], "Varchar(\"'paused'\")");
+
+    eng.execute_sql("CREATE TABLE jobs_t (k INT)", &mut ctx)
+        .unwrap();
+    eng.background_jobs().run_now("checkpoint").unwrap();
+    let mut rows = jobs(&mut ctx);
+    for _ in 0..200 {
+        if rows[0][2] == "BigInt(1)" {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        rows = jobs(&mut ctx);
+    }
+    assert_eq!(rows[0][0], "BigInt(0)");
+    assert_eq!(rows[0][2], "BigInt(1)");
+    assert_eq!(
+        eng.checkpoint_statistics()
+            .expect("checkpoint stats")
+            .total_checkpoints,
+        1
+    );
 }
 
 #[test]
-fn engine_alter_add_unique_constraint() {
+fn engine_show_and_set_server_parameters() {
     let dir = TempDir::new().expect("tempdir");
     let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
     let mut ctx = SessionContext::default();
-    eng.execute_sql("CREATE TABLE ac (a INT)", &mut ctx)
-        .expect("c");
-    eng.execute_sql("INSERT INTO ac (a) VALUES (1)", &mut ctx)
-        .expect("i1");
-    eng.execute_sql("INSERT INTO ac (a) VALUES (2)", &mut ctx)
-        .expect("i2");
-    eng.execute_sql("ALTER TABLE ac ADD CONSTRAINT uq UNIQUE (a)", &mut ctx)
-        .expect("alter");
-    assert!(eng
-        .execute_sql("INSERT INTO ac (a) VALUES (1)", &mut ctx)
-        .is_err());
+    let show = |ctx: &mut SessionContext, sql: &str| match eng.execute_sql(sql, ctx).unwrap() {
+        EngineOutput::ResultSet { columns, rows } => {
+            assert_eq!(
+                columns,
+                vec!["description", "name", "runtime", "setting", "source"]
+            );
+            rows
+        }
+        _ => panic!("expected ResultSet"),
+    };
+
+    let rows = show(&mut ctx, "SHOW replication.synchronous_commit_timeout_ms");
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0][2], "Boolean(true)");
+    assert_eq!(rows[0][3], "Varchar(\"'10000'\")");
+    assert_eq!(rows[0][4], "Varchar(\"'default'\")");
+
+    eng.execute_sql(
+        "SET replication.synchronous_commit_timeout_ms = 250",
+        &mut ctx,
+    )
+    .unwrap();
+    let rows = show(&mut ctx, "SHOW replication.synchronous_commit_timeout_ms");
+    assert_eq!(rows[0][3], "Varchar(\"'250'\")");
+    assert_eq!(rows[0][4], "Varchar(\"'runtime'\")");
+    assert_eq!(
+        eng.settings()
+            .unwrap()
+            .config()
+            .replication
+            .synchronous_commit_timeout_ms,
+        250
+    );
+
+    let err = eng
+        .execute_sql("SET network.port = 1", &mut ctx)
+        .unwrap_err();
+    assert_eq!(err.code, engine_error_code::INVALID_PARAMETER);
+    assert!(err.message.starts_with("network.port:"));
+    let err = eng
+        .execute_sql(
+            "SET replication.synchronous_commit_timeout_policy = 'retry'",
+            &mut ctx,
+        )
+        .unwrap_err();
+    assert!(err
+        .message
+        .starts_with("replication.synchronous_commit_timeout_policy:"));
+    assert!(eng.execute_sql("SHOW no_such_setting", &mut ctx).is_err());
+
+    let all = show(&mut ctx, "SHOW ALL");
+    assert_eq!(all.len(), crate::common::config::PARAMETERS.len());
+    let restart_only = show(
+        &mut ctx,
+        "SELECT * FROM rustdb_settings WHERE runtime = FALSE",
+    );
+    assert!(restart_only
+        .iter()
+        .any(|r| r[1] == "Varchar(\"'network.port'\")"));
+    assert!(restart_only.len() < all.len());
 }
 
 #[test]
-fn engine_transaction_rollback_rebuilds_fk_runtime() {
+fn create_database_template_clones_a_database() {
     let dir = TempDir::new().expect("tempdir");
-    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let prod = SqlEngine::open(dir.path().join("prod")).expect("open prod");
     let mut ctx = SessionContext::default();
-
-    eng.execute_sql("CREATE TABLE p (id INT PRIMARY KEY)", &mut ctx)
-        .expect("p");
-    eng.execute_sql("CREATE TABLE c (pid INT REFERENCES p(id))", &mut ctx)
-        .expect("c");
-
-    eng.execute_sql("BEGIN TRANSACTION", &mut ctx).unwrap();
-    eng.execute_sql("INSERT INTO p (id) VALUES (10)", &mut ctx)
-        .unwrap();
-    eng.execute_sql("INSERT INTO c (pid) VALUES (10)", &mut ctx)
+    for sql in [
+        "CREATE TABLE items (id INT PRIMARY KEY, name TEXT)",
+        "INSERT INTO items (id, name) VALUES (1, 'one'), (2, 'two')",
+    ] {
+        prod.execute_sql(sql, &mut ctx).expect(sql);
+    }
+    let count = |eng: &SqlEngine, ctx: &mut SessionContext| match eng
+        .execute_sql("SELECT id FROM items", ctx)
+        .unwrap()
+    {
+        EngineOutput::ResultSet { rows, .. } => rows.len(),
+        other => panic!("expected rows, got {other:?}"),
+    };
+
+    // An open transaction of another session is not part of the clone.
+    let mut writer = SessionContext::default();
+    prod.execute_sql("BEGIN TRANSACTION", &mut writer).unwrap();
+    prod.execute_sql(
+        "INSERT INTO items (id, name) VALUES (3, 'three')",
+        &mut writer,
+    )
+    .unwrap();
+    prod.execute_sql("CREATE DATABASE staging TEMPLATE prod", &mut ctx)
         .unwrap();
-    eng.execute_sql("ROLLBACK", &mut ctx).unwrap();
+    prod.execute_sql("COMMIT", &mut writer).unwrap();
 
-    // After rollback, FK runtime should be rebuilt: parent row is gone, so child insert must fail.
-    assert!(eng
-        .execute_sql("INSERT INTO c (pid) VALUES (10)", &mut ctx)
-        .is_err());
+    let staging = SqlEngine::open(dir.path().join("staging")).expect("open clone");
+    let mut staging_ctx = SessionContext::default();
+    assert_eq!(count(&staging, &mut staging_ctx), 2);
+    staging
+        .execute_sql("DELETE FROM items WHERE id = 1", &mut staging_ctx)
+        .unwrap();
+    assert_eq!(count(&staging, &mut staging_ctx), 1);
+    assert_eq!(count(&prod, &mut ctx), 3);
+    drop(staging);
 
-    // Parent delete should not be blocked (no child refcount).
-    eng.execute_sql("INSERT INTO p (id) VALUES (10)", &mut ctx)
+    // A closed database clones from its files; a database without template starts empty.
+    prod.execute_sql("CREATE DATABASE staging2 TEMPLATE staging", &mut ctx)
         .unwrap();
-    eng.execute_sql("DELETE FROM p WHERE id = 10", &mut ctx)
+    let staging2 = SqlEngine::open(dir.path().join("staging2")).expect("open clone of clone");
+    assert_eq!(count(&staging2, &mut SessionContext::default()), 1);
+    prod.execute_sql("CREATE DATABASE scratch", &mut ctx)
         .unwrap();
+    let scratch = SqlEngine::open(dir.path().join("scratch")).expect("open empty");
+    scratch
+        .execute_sql(
+            "CREATE TABLE items (id INT)",
+            &mut SessionContext::default(),
+        )
+        .expect("no items table in an empty database");
+
+    let code = |sql: &str, ctx: &mut SessionContext| prod.execute_sql(sql, ctx).unwrap_err().code;
+    assert_eq!(
+        code("CREATE DATABASE staging TEMPLATE prod", &mut ctx),
+        engine_error_code::INVALID_PARAMETER
+    );
+    assert_eq!(
+        code("CREATE DATABASE other TEMPLATE missing", &mut ctx),
+        engine_error_code::INVALID_PARAMETER
+    );
+    assert!(!dir.path().join("other").exists());
+    prod.execute_sql("BEGIN TRANSACTION", &mut ctx).unwrap();
+    assert_eq!(
+        code("CREATE DATABASE other TEMPLATE prod", &mut ctx),
+        engine_error_code::DDL_IN_TRANSACTION
+    );
 }
 
 #[test]
-fn engine_alter_drop_constraint_allows_duplicates_again() {
+fn closed_wal_segments_are_archived_after_flush_logs() {
+    use crate::network::sql_engine::SqlEngineConfig;
+    use crate::storage::remote::{LocalStorage, RemoteStorage};
+
     let dir = TempDir::new().expect("tempdir");
-    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let archive = dir.path().join("archive");
+    let eng = SqlEngine::open_with_config(
+        dir.path().join("data"),
+        SqlEngineConfig {
+            wal_archive_url: Some(archive.to_string_lossy().into_owned()),
+            ..SqlEngineConfig::default()
+        },
+    )
+    .expect("open");
     let mut ctx = SessionContext::default();
+    eng.execute_sql("CREATE TABLE arch (a INT)", &mut ctx)
+        .unwrap();
+    eng.execute_sql("INSERT INTO arch (a) VALUES (1)", &mut ctx)
+        .unwrap();
+    eng.execute_sql("FLUSH LOGS", &mut ctx).unwrap();
+
+    let remote = LocalStorage::new(&archive);
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    let archived = loop {
+        let keys = remote.list("wal/").expect("list");
+        if !keys.is_empty() || std::time::Instant::now() > deadline {
+            break keys;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    };
+    assert!(!archived.is_empty(), "no segment archived");
+    let wal_dir = dir.path().join("data/.rustdb/wal");
+    for key in &archived {
+        let mut bytes = Vec::new();
+        remote.get(key, &mut bytes).expect("get");
+        let local = std::fs::read(wal_dir.join(key.trim_start_matches("wal/"))).expect("segment");
+        assert_eq!(bytes, local, "{key}");
+    }
 
-    eng.execute_sql("CREATE TABLE ad (a INT)", &mut ctx)
-        .expect("c");
-    eng.execute_sql("ALTER TABLE ad ADD CONSTRAINT uq UNIQUE (a)", &mut ctx)
-        .expect("add uq");
-    eng.execute_sql("INSERT INTO ad (a) VALUES (1)", &mut ctx)
-        .expect("i1");
-    assert!(eng
-        .execute_sql("INSERT INTO ad (a) VALUES (1)", &mut ctx)
-        .is_err());
-
-    eng.execute_sql("ALTER TABLE ad DROP CONSTRAINT uq", &mut ctx)
-        .expect("drop uq");
-    eng.execute_sql("INSERT INTO ad (a) VALUES (1)", &mut ctx)
-        .expect("dup ok after drop");
+    // The next write opens a new segment, which stays local until it is closed.
+    eng.execute_sql("INSERT INTO arch (a) VALUES (2)", &mut ctx)
+        .unwrap();
+    let local_segments = std::fs::read_dir(&wal_dir)
+        .expect("wal dir")
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().ends_with(".log"))
+        .count();
+    assert_eq!(local_segments, archived.len() + 1);
+    assert_eq!(remote.list("wal/").expect("list"), archived);
 }
 
 #[test]
-fn engine_drop_table_if_exists_is_ok() {
+fn engine_admin_commands_checkpoint_flush_logs_and_show_engine_status() {
     let dir = TempDir::new().expect("tempdir");
     let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
     let mut ctx = SessionContext::default();
-    eng.execute_sql("DROP TABLE IF EXISTS missing", &mut ctx)
-        .expect("if exists");
-}
-
-#[cfg(not(rustdb_loom))]
-#[test]
-fn engine_alter_fk_many_inserts_under_contention() {
-    use std::sync::{Arc, Mutex};
+    eng.execute_sql("INSERT INTO adm (a) VALUES (1)", &mut ctx)
+        .unwrap();
 
-    let dir = TempDir::new().expect("tempdir");
-    let eng = Arc::new(Mutex::new(
-        SqlEngine::open(dir.path().to_path_buf()).expect("open"),
+    assert_eq!(
+        eng.execute_sql("CHECKPOINT", &mut ctx).unwrap(),
+        EngineOutput::ExecutionOk { rows_affected: 0 }
+    );
+    assert_eq!(
+        eng.execute_sql("FLUSH LOGS", &mut ctx).unwrap(),
+        EngineOutput::ExecutionOk { rows_affected: 0 }
+    );
+    eng.execute_sql(
+        "SET GLOBAL replication.synchronous_commit_timeout_ms = 300",
+        &mut ctx,
+    )
+    .unwrap();
+    assert_eq!(
+        eng.settings()
+            .unwrap()
+            .config()
+            .replication
+            .synchronous_commit_timeout_ms,
+        300
+    );
+
+    let rows = match eng.execute_sql("SHOW ENGINE STATUS", &mut ctx).unwrap() {
+        EngineOutput::ResultSet { columns, rows } => {
+            assert_eq!(columns, vec!["name", "section", "value"]);
+            rows
+        }
+        _ => panic!("expected ResultSet"),
+    };
+    let value = |section: &str, name: &str| {
+        rows.iter()
+            .find(|r| {
+                r[0] == format!("Varchar(\"'{name}'\")")
+                    && r[1] == format!("Varchar(\"'{section}'\")")
+            })
+            .map(|r| r[2].clone())
+    };
+    assert_eq!(
+        value("engine", "wal_enabled").as_deref(),
+        Some("Varchar(\"'true'\")")
+    );
+    assert_eq!(
+        value("checkpoint", "forced").as_deref(),
+        Some("Varchar(\"'1'\")")
+    );
+    assert_eq!(
+        value("wal", "file_rotations").as_deref(),
+        Some("Varchar(\"'1'\")")
+    );
+}
+
+#[test]
+fn engine_stat_statements_groups_by_fingerprint_and_resets() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut ctx = SessionContext::default();
+    eng.execute_sql("CREATE TABLE ss (k INT PRIMARY KEY, v TEXT)", &mut ctx)
+        .unwrap();
+    for k in 0..3 {
+        eng.execute_sql(
+            &format!("INSERT INTO ss (k, v) VALUES ({k}, 'row {k}')"),
+            &mut ctx,
+        )
+        .unwrap();
+    }
+    eng.execute_sql("SELECT k FROM ss WHERE k >= 1", &mut ctx)
+        .unwrap();
+    // Failed statements are not recorded.
+    assert!(eng
+        .execute_sql("INSERT INTO ss (k, v) VALUES (0, 'dup')", &mut ctx)
+        .is_err());
+
+    let sql = "SELECT query, calls, rows FROM rustdb_stat_statements WHERE calls > 0";
+    let rows = match eng.execute_sql(sql, &mut ctx).unwrap() {
+        EngineOutput::ResultSet { columns, rows } => {
+            assert_eq!(columns, vec!["calls", "query", "rows"]);
+            rows
+        }
+        _ => panic!("expected ResultSet"),
+    };
+    let stat = |query: &str| {
+        rows.iter()
+            .find(|r| r[1] == format!("Varchar(\"'{query}'\")"))
+            .map(|r| (r[0].clone(), r[2].clone()))
+    };
+    assert_eq!(
+        stat("INSERT INTO ss (k, v) VALUES (?, ?)"),
+        Some(("BigInt(3)".to_string(), "BigInt(3)".to_string()))
+    );
+    assert_eq!(
+        stat("SELECT k FROM ss WHERE k >= ?"),
+        Some(("BigInt(1)".to_string(), "BigInt(2)".to_string()))
+    );
+
+    eng.execute_sql("SELECT rustdb_stat_statements_reset()", &mut ctx)
+        .unwrap();
+    match eng
+        .execute_sql("SELECT query FROM rustdb_stat_statements", &mut ctx)
+        .unwrap()
+    {
+        EngineOutput::ResultSet { rows, .. } => assert_eq!(
+            rows,
+            vec![vec![
+                "Varchar(\"'SELECT rustdb_stat_statements_reset()'\")".to_string()
+            ]]
+        ),
+        _ => panic!("expected ResultSet"),
+    }
+}
+
+#[test]
+fn engine_index_advisor_proposes_indexes_from_the_workload() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut ctx = SessionContext::default();
+    eng.execute_sql("CREATE TABLE adv (id INT, grp INT, v TEXT)", &mut ctx)
+        .unwrap();
+    for id in 0..20 {
+        eng.execute_sql(
+            &format!(
+                "INSERT INTO adv (id, grp, v) VALUES ({id}, {}, 'r')",
+                id % 4
+            ),
+            &mut ctx,
+        )
+        .unwrap();
+    }
+    for grp in 0..3 {
+        eng.execute_sql(&format!("SELECT v FROM adv WHERE grp = {grp}"), &mut ctx)
+            .unwrap();
+    }
+    eng.execute_sql("DELETE FROM adv WHERE id = 100", &mut ctx)
+        .unwrap();
+    // Range predicates give no candidate.
+    eng.execute_sql("SELECT v FROM adv WHERE id > 3", &mut ctx)
+        .unwrap();
+
+    let sql = "SELECT index_columns, calls, est_rows_per_call, est_rows_saved \
+               FROM index_advisor() WHERE table_name = 'adv'";
+    let advise =
+        |eng: &SqlEngine, ctx: &mut SessionContext| match eng.execute_sql(sql, ctx).unwrap() {
+            EngineOutput::ResultSet { rows, .. } => rows,
+            _ => panic!("expected ResultSet"),
+        };
+    // Columns by name: calls, est_rows_per_call, est_rows_saved, index_columns.
+    assert_eq!(
+        advise(&eng, &mut ctx),
+        vec![
+            vec![
+                "BigInt(3)".to_string(),
+                "BigInt(5)".to_string(),
+                "BigInt(45)".to_string(),
+                "Varchar(\"'grp'\")".to_string(),
+            ],
+            vec![
+                "BigInt(1)".to_string(),
+                "BigInt(1)".to_string(),
+                "BigInt(19)".to_string(),
+                "Varchar(\"'id'\")".to_string(),
+            ],
+        ]
+    );
+
+    // An existing index on the column retires the proposal.
+    eng.execute_sql("CREATE INDEX adv_grp ON adv (grp)", &mut ctx)
+        .unwrap();
+    let rows = advise(&eng, &mut ctx);
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0][3], "Varchar(\"'id'\")");
+}
+
+#[test]
+fn engine_stat_memory_lists_every_subsystem() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut ctx = SessionContext::default();
+    let rows = match eng
+        .execute_sql(
+            "SELECT subsystem, live_bytes FROM rustdb_stat_memory",
+            &mut ctx,
+        )
+        .unwrap()
+    {
+        EngineOutput::ResultSet { columns, rows } => {
+            assert_eq!(columns, vec!["live_bytes", "subsystem"]);
+            rows
+        }
+        _ => panic!("expected ResultSet"),
+    };
+    let subsystems: Vec<&str> = rows.iter().map(|r| r[1].as_str()).collect();
+    assert_eq!(
+        subsystems,
+        vec![
+            "Varchar(\"'other'\")",
+            "Varchar(\"'buffer_pool'\")",
+            "Varchar(\"'plan_cache'\")",
+            "Varchar(\"'lock_tables'\")",
+            "Varchar(\"'execution'\")",
+        ]
+    );
+
+    match eng.execute_sql("SHOW ENGINE STATUS", &mut ctx).unwrap() {
+        EngineOutput::ResultSet { rows, .. } => {
+            assert!(rows
+                .iter()
+                .any(|r| r[0] == "Varchar(\"'tracking'\")" && r[1] == "Varchar(\"'memory'\")"));
+        }
+        _ => panic!("expected ResultSet"),
+    }
+}
+
+#[test]
+fn engine_profile_cpu_writes_profile_or_needs_feature() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut ctx = SessionContext::default();
+    let out = eng.execute_sql("PROFILE CPU FOR 1 SECONDS FORMAT PPROF", &mut ctx);
+    if !cfg!(feature = "cpu-profiling") {
+        assert_eq!(out.unwrap_err().code, engine_error_code::UNSUPPORTED_SQL);
+        return;
+    }
+    match out.unwrap() {
+        EngineOutput::ResultSet { columns, rows } => {
+            assert_eq!(
+                columns,
+                vec!["bytes", "file", "format", "samples", "seconds"]
+            );
+            assert_eq!(rows[0][2], "Varchar(\"'pb'\")");
+        }
+        _ => panic!("expected ResultSet"),
+    }
+    let written: Vec<_> = std::fs::read_dir(dir.path().join("profiles"))
+        .unwrap()
+        .collect();
+    assert_eq!(written.len(), 1);
+    assert_eq!(
+        eng.execute_sql("PROFILE CPU FOR 0 SECONDS", &mut ctx)
+            .unwrap_err()
+            .code,
+        engine_error_code::INVALID_PARAMETER
+    );
+}
+
+#[test]
+fn engine_session_trace_writes_json_trace_per_statement() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut ctx = SessionContext::default();
+    let mut other = SessionContext::default();
+    eng.execute_sql("CREATE TABLE tr (k INT PRIMARY KEY)", &mut ctx)
+        .unwrap();
+    eng.execute_sql("SET trace = on", &mut ctx).unwrap();
+    let file = ctx.trace_file().expect("trace file").to_path_buf();
+    assert!(file.starts_with(dir.path().join("traces")));
+
+    eng.execute_sql("INSERT INTO tr (k) VALUES (1)", &mut ctx)
+        .unwrap();
+    eng.execute_sql("SELECT k FROM tr WHERE k = 1", &mut ctx)
+        .unwrap();
+    assert!(eng
+        .execute_sql("INSERT INTO tr (k) VALUES (1)", &mut ctx)
+        .is_err());
+    // Other sessions are not traced.
+    eng.execute_sql("SELECT k FROM tr", &mut other).unwrap();
+    assert!(other.trace_file().is_none());
+
+    match eng.execute_sql("SHOW trace", &mut ctx).unwrap() {
+        EngineOutput::ResultSet { columns, rows } => {
+            assert_eq!(columns, vec!["name", "setting", "source", "trace_file"]);
+            assert_eq!(rows[0][1], "Varchar(\"'on'\")");
+        }
+        _ => panic!("expected ResultSet"),
+    }
+    eng.execute_sql("SET trace = off", &mut ctx).unwrap();
+    eng.execute_sql("SELECT k FROM tr", &mut ctx).unwrap();
+    assert!(ctx.trace_file().is_none());
+
+    let traces: Vec<serde_json::Value> = std::fs::read_to_string(&file)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    let queries: Vec<&str> = traces
+        .iter()
+        .map(|t| t["sql_query"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        queries,
+        vec![
+            "INSERT INTO tr (k) VALUES (1)",
+            "SELECT k FROM tr WHERE k = 1",
+            "INSERT INTO tr (k) VALUES (1)",
+            "SHOW trace",
+            "SET trace = off",
+        ]
+    );
+    let select = &traces[1];
+    assert_eq!(select["status"], "Completed");
+    assert_eq!(select["rows_returned"], 1);
+    let stages: Vec<&str> = select["events"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["stage"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        stages,
+        vec!["Received", "Parsing", "Planning", "Execution", "Completed"]
+    );
+    assert!(select["events"][2]["data"]["plan"].is_object());
+    assert!(select["events"][3]["duration_us"].is_u64());
+    assert_eq!(traces[2]["status"], "Failed");
+    assert!(traces[2]["error"].is_string());
+}
+
+#[test]
+fn engine_transaction_insert_commit_keeps_row() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut ctx = SessionContext::default();
+    eng.execute_sql("CREATE TABLE txc (k INT PRIMARY KEY)", &mut ctx)
+        .unwrap();
+    eng.execute_sql("BEGIN TRANSACTION", &mut ctx).unwrap();
+    eng.execute_sql("INSERT INTO txc (k) VALUES (7)", &mut ctx)
+        .unwrap();
+    eng.execute_sql("COMMIT", &mut ctx).unwrap();
+    let out = eng
+        .execute_sql("SELECT k FROM txc", &mut ctx)
+        .expect("select");
+    match out {
+        EngineOutput::ResultSet { rows, .. } => assert_eq!(rows.len(), 1),
+        _ => panic!("expected ResultSet"),
+    }
+}
+
+#[test]
+fn engine_transaction_commit_then_rollback_does_not_corrupt_pk_on_reopen() {
+    let dir = TempDir::new().expect("tempdir");
+    {
+        let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+        let mut ctx = SessionContext::default();
+        eng.execute_sql("CREATE TABLE ss92_tc (k INT PRIMARY KEY)", &mut ctx)
+            .unwrap();
+        eng.execute_sql("BEGIN TRANSACTION", &mut ctx).unwrap();
+        eng.execute_sql("INSERT INTO ss92_tc (k) VALUES (701)", &mut ctx)
+            .unwrap();
+        eng.execute_sql("COMMIT", &mut ctx).unwrap();
+
+        eng.execute_sql("BEGIN TRANSACTION", &mut ctx).unwrap();
+        eng.execute_sql("INSERT INTO ss92_tc (k) VALUES (702)", &mut ctx)
+            .unwrap();
+        eng.execute_sql("ROLLBACK", &mut ctx).unwrap();
+    }
+
+    // Reopen should not fail constraint rebuild due to duplicate PKs.
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("reopen");
+    let mut ctx = SessionContext::default();
+
+    let out_701 = eng
+        .execute_sql("SELECT k FROM ss92_tc WHERE k = 701", &mut ctx)
+        .expect("select 701");
+    match out_701 {
+        EngineOutput::ResultSet { rows, .. } => assert_eq!(rows.len(), 1),
+        _ => panic!("expected ResultSet"),
+    }
+
+    let out_702 = eng
+        .execute_sql("SELECT k FROM ss92_tc WHERE k = 702", &mut ctx)
+        .expect("select 702");
+    match out_702 {
+        EngineOutput::ResultSet { rows, .. } => assert!(rows.is_empty()),
+        _ => panic!("expected ResultSet"),
+    }
+}
+
+#[test]
+fn engine_ddl_rejected_inside_transaction() {
+    use crate::network::engine::engine_error_code;
+
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut ctx = SessionContext::default();
+    eng.execute_sql("BEGIN TRANSACTION", &mut ctx).unwrap();
+    assert_eq!(
+        eng.execute_sql("CREATE TABLE n (x INT)", &mut ctx)
+            .unwrap_err()
+            .code,
+        engine_error_code::DDL_IN_TRANSACTION
+    );
+    eng.execute_sql("ROLLBACK", &mut ctx).unwrap();
+}
+
+/// Eight threads contend on `INSERT … VALUES (1)`; exactly one succeeds.
+#[cfg(not(rustdb_loom))]
+#[test]
+fn engine_concurrent_inserts_only_one_wins_same_pk() {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    let dir = TempDir::new().expect("tempdir");
+    let eng = Arc::new(SqlEngine::open(dir.path().to_path_buf()).expect("open"));
+    let mut setup = SessionContext::default();
+    eng.execute_sql("CREATE TABLE conc (id INT PRIMARY KEY)", &mut setup)
+        .unwrap();
+
+    let barrier = Arc::new(std::sync::Barrier::new(8));
+    let ok_count = Arc::new(AtomicU32::new(0));
+    let mut handles = vec![];
+    for _ in 0..8 {
+        let eng = eng.clone();
+        let barrier = barrier.clone();
+        let ok_count = ok_count.clone();
+        handles.push(thread::spawn(move || {
+            let mut ctx = SessionContext::default();
+            barrier.wait();
+            if eng
+                .execute_sql("INSERT INTO conc (id) VALUES (1)", &mut ctx)
+                .is_ok()
+            {
+                ok_count.fetch_add(1, Ordering::SeqCst);
+            }
+        }));
+    }
+    for h in handles {
+        h.join().expect("join");
+    }
+    assert_eq!(ok_count.load(Ordering::SeqCst), 1);
+}
+
+/// Same property under [loom](https://github.com/tokio-rs/loom) permutation scheduling (`RUSTFLAGS="--cfg rustdb_loom"`).
+#[cfg(rustdb_loom)]
+#[test]
+fn engine_concurrent_inserts_only_one_wins_same_pk() {
+    use loom::sync::atomic::AtomicU32;
+    use loom::sync::atomic::Ordering::SeqCst;
+    use loom::sync::Arc;
+
+    loom::model(|| {
+        let dir = TempDir::new().expect("tempdir");
+        let eng = Arc::new(SqlEngine::open(dir.path().to_path_buf()).expect("open"));
+        let mut setup = SessionContext::default();
+        eng.execute_sql("CREATE TABLE conc (id INT PRIMARY KEY)", &mut setup)
+            .unwrap();
+
+        let ok_count = Arc::new(AtomicU32::new(0));
+        let ea = eng.clone();
+        let eb = eng.clone();
+        let oa = ok_count.clone();
+        let ob = ok_count.clone();
+        let h1 = loom::thread::spawn(move || {
+            let mut ctx = SessionContext::default();
+            if ea
+                .execute_sql("INSERT INTO conc (id) VALUES (1)", &mut ctx)
+                .is_ok()
+            {
+                oa.fetch_add(1, SeqCst);
+            }
+        });
+        let h2 = loom::thread::spawn(move || {
+            let mut ctx = SessionContext::default();
+            if eb
+                .execute_sql("INSERT INTO conc (id) VALUES (1)", &mut ctx)
+                .is_ok()
+            {
+                ob.fetch_add(1, SeqCst);
+            }
+        });
+        h1.join().unwrap();
+        h2.join().unwrap();
+        assert_eq!(ok_count.load(SeqCst), 1);
+    });
+}
+
+#[test]
+fn engine_select_named_table_after_engine_reopen() {
+    let dir = TempDir::new().expect("tempdir");
+    {
+        let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+        let mut ctx = SessionContext::default();
+        eng.execute_sql("INSERT INTO stateful_r (a) VALUES (100)", &mut ctx)
+            .expect("insert");
+    }
+    let heap = dir.path().join("stateful_r.tbl");
+    assert!(
+        heap.exists() && heap.metadata().expect("meta").len() > 0,
+        "expected persisted heap file with data"
+    );
+    {
+        let eng = SqlEngine::open(dir.path().to_path_buf()).expect("reopen");
+        let mut ctx = SessionContext::default();
+        let out = eng
+            .execute_sql("SELECT a FROM stateful_r", &mut ctx)
+            .expect("select");
+        match out {
+            EngineOutput::ResultSet { columns, rows } => {
+                assert_eq!(columns, vec!["a"]);
+                assert_eq!(rows.len(), 1);
+                assert_eq!(rows[0], vec!["Integer(100)"]);
+            }
+            _ => panic!("expected ResultSet"),
+        }
+    }
+}
+
+#[test]
+fn engine_enforces_primary_key_and_unique() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut ctx = SessionContext::default();
+    eng.execute_sql(
+        "CREATE TABLE pk (id INT PRIMARY KEY, a INT UNIQUE)",
+        &mut ctx,
+    )
+    .expect("create");
+    eng.execute_sql("INSERT INTO pk (id, a) VALUES (1, 10)", &mut ctx)
+        .expect("i1");
+    assert!(eng
+        .execute_sql("INSERT INTO pk (id, a) VALUES (1, 20)", &mut ctx)
+        .is_err());
+    assert!(eng
+        .execute_sql("INSERT INTO pk (id, a) VALUES (2, 10)", &mut ctx)
+        .is_err());
+    eng.execute_sql("INSERT INTO pk (id, a) VALUES (2, 20)", &mut ctx)
+        .expect("i2");
+}
+
+#[test]
+fn engine_enforces_foreign_key_and_parent_delete() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut ctx = SessionContext::default();
+    eng.execute_sql("CREATE TABLE parent (id INT PRIMARY KEY)", &mut ctx)
+        .expect("p");
+    eng.execute_sql(
+        "CREATE TABLE child (pid INT REFERENCES parent(id))",
+        &mut ctx,
+    )
+    .expect("c");
+    assert!(eng
+        .execute_sql("INSERT INTO child (pid) VALUES (5)", &mut ctx)
+        .is_err());
+    eng.execute_sql("INSERT INTO parent (id) VALUES (5)", &mut ctx)
+        .expect("ip");
+    eng.execute_sql("INSERT INTO child (pid) VALUES (5)", &mut ctx)
+        .expect("ic");
+    assert!(eng
+        .execute_sql("DELETE FROM parent WHERE id = 5", &mut ctx)
+        .is_err());
+    eng.execute_sql("DELETE FROM child WHERE pid = 5", &mut ctx)
+        .expect("del child");
+    eng.execute_sql("DELETE FROM parent WHERE id = 5", &mut ctx)
+        .expect("del parent");
+}
+
+#[test]
+fn engine_drop_table_restrict_and_cascade() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut ctx = SessionContext::default();
+    eng.execute_sql("CREATE TABLE p (id INT PRIMARY KEY)", &mut ctx)
+        .expect("p");
+    eng.execute_sql("CREATE TABLE c (pid INT REFERENCES p(id))", &mut ctx)
+        .expect("c");
+    assert!(eng.execute_sql("DROP TABLE p", &mut ctx).is_err());
+    eng.execute_sql("DROP TABLE p CASCADE", &mut ctx)
+        .expect("drop cascade");
+    eng.execute_sql("CREATE TABLE c (x INT)", &mut ctx)
+        .expect("recreate c after cascade drop");
+}
+
+#[test]
+fn engine_alter_add_unique_constraint() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut ctx = SessionContext::default();
+    eng.execute_sql("CREATE TABLE ac (a INT)", &mut ctx)
+        .expect("c");
+    eng.execute_sql("INSERT INTO ac (a) VALUES (1)", &mut ctx)
+        .expect("i1");
+    eng.execute_sql("INSERT INTO ac (a) VALUES (2)", &mut ctx)
+        .expect("i2");
+    eng.execute_sql("ALTER TABLE ac ADD CONSTRAINT uq UNIQUE (a)", &mut ctx)
+        .expect("alter");
+    assert!(eng
+        .execute_sql("INSERT INTO ac (a) VALUES (1)", &mut ctx)
+        .is_err());
+}
+
+#[test]
+fn engine_transaction_rollback_rebuilds_fk_runtime() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut ctx = SessionContext::default();
+
+    eng.execute_sql("CREATE TABLE p (id INT PRIMARY KEY)", &mut ctx)
+        .expect("p");
+    eng.execute_sql("CREATE TABLE c (pid INT REFERENCES p(id))", &mut ctx)
+        .expect("c");
+
+    eng.execute_sql("BEGIN TRANSACTION", &mut ctx).unwrap();
+    eng.execute_sql("INSERT INTO p (id) VALUES (10)", &mut ctx)
+        .unwrap();
+    eng.execute_sql("INSERT INTO c (pid) VALUES (10)", &mut ctx)
+        .unwrap();
+    eng.execute_sql("ROLLBACK", &mut ctx).unwrap();
+
+    // After rollback, FK runtime should be rebuilt: parent row is gone, so child insert must fail.
+    assert!(eng
+        .execute_sql("INSERT INTO c (pid) VALUES (10)", &mut ctx)
+        .is_err());
+
+    // Parent delete should not be blocked (no child refcount).
+    eng.execute_sql("INSERT INTO p (id) VALUES (10)", &mut ctx)
+        .unwrap();
+    eng.execute_sql("DELETE FROM p WHERE id = 10", &mut ctx)
+        .unwrap();
+}
+
+#[test]
+fn engine_alter_drop_constraint_allows_duplicates_again() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut ctx = SessionContext::default();
+
+    eng.execute_sql("CREATE TABLE ad (a INT)", &mut ctx)
+        .expect("c");
+    eng.execute_sql("ALTER TABLE ad ADD CONSTRAINT uq UNIQUE (a)", &mut ctx)
+        .expect("add uq");
+    eng.execute_sql("INSERT INTO ad (a) VALUES (1)", &mut ctx)
+        .expect("i1");
+    assert!(eng
+        .execute_sql("INSERT INTO ad (a) VALUES (1)", &mut ctx)
+        .is_err());
+
+    eng.execute_sql("ALTER TABLE ad DROP CONSTRAINT uq", &mut ctx)
+        .expect("drop uq");
+    eng.execute_sql("INSERT INTO ad (a) VALUES (1)", &mut ctx)
+        .expect("dup ok after drop");
+}
+
+#[test]
+fn engine_drop_table_if_exists_is_ok() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut ctx = SessionContext::default();
+    eng.execute_sql("DROP TABLE IF EXISTS missing", &mut ctx)
+        .expect("if exists");
+}
+
+#[cfg(not(rustdb_loom))]
+#[test]
+fn engine_alter_fk_many_inserts_under_contention() {
+    use std::sync::{Arc, Mutex};
+
+    let dir = TempDir::new().expect("tempdir");
+    let eng = Arc::new(Mutex::new(
+        SqlEngine::open(dir.path().to_path_buf()).expect("open"),
     ));
     {
-        let g = eng.lock().expect("lock");
-        let mut ctx = SessionContext::default();
-        g.execute_sql("CREATE TABLE pp (id INT PRIMARY KEY)", &mut ctx)
-            .expect("p");
-        for i in 1..=64 {
-            g.execute_sql(&format!("INSERT INTO pp (id) VALUES ({i})"), &mut ctx)
-                .expect("ip");
+        let g = eng.lock().expect("lock");
+        let mut ctx = SessionContext::default();
+        g.execute_sql("CREATE TABLE pp (id INT PRIMARY KEY)", &mut ctx)
+            .expect("p");
+        for i in 1..=64 {
+            g.execute_sql(&format!("INSERT INTO pp (id) VALUES ({i})"), &mut ctx)
+                .expect("ip");
+        }
+        g.execute_sql(
+            "CREATE TABLE cc (k INT PRIMARY KEY, pid INT REFERENCES pp(id))",
+            &mut ctx,
+        )
+        .expect("c");
+    }
+    std::thread::scope(|s| {
+        for t in 0..4 {
+            let e = eng.clone();
+            s.spawn(move || {
+                let mut ctx = SessionContext::default();
+                for n in 0..32 {
+                    let id = t * 10_000 + n;
+                    let pid = (n % 64) + 1;
+                    let sql = format!("INSERT INTO cc (k, pid) VALUES ({id}, {pid})");
+                    e.lock().expect("lock").execute_sql(&sql, &mut ctx).unwrap();
+                }
+            });
+        }
+    });
+    let g = eng.lock().expect("lock");
+    let mut ctx = SessionContext::default();
+    let out = g
+        .execute_sql("SELECT k FROM cc", &mut ctx)
+        .expect("all child rows");
+    match out {
+        EngineOutput::ResultSet { rows, .. } => assert_eq!(rows.len(), 4 * 32),
+        _ => panic!("expected result set"),
+    }
+}
+
+#[cfg(rustdb_loom)]
+#[test]
+fn engine_alter_fk_many_inserts_under_contention() {
+    use loom::sync::{Arc, Mutex};
+
+    loom::model(|| {
+        let dir = TempDir::new().expect("tempdir");
+        let eng = Arc::new(Mutex::new(
+            SqlEngine::open(dir.path().to_path_buf()).expect("open"),
+        ));
+        {
+            let g = eng.lock().unwrap();
+            let mut ctx = SessionContext::default();
+            g.execute_sql("CREATE TABLE pp (id INT PRIMARY KEY)", &mut ctx)
+                .expect("p");
+            for i in 1..=8 {
+                g.execute_sql(&format!("INSERT INTO pp (id) VALUES ({i})"), &mut ctx)
+                    .expect("ip");
+            }
+            g.execute_sql(
+                "CREATE TABLE cc (k INT PRIMARY KEY, pid INT REFERENCES pp(id))",
+                &mut ctx,
+            )
+            .expect("c");
+        }
+        let e0 = eng.clone();
+        let e1 = eng.clone();
+        let h0 = loom::thread::spawn(move || {
+            let mut ctx = SessionContext::default();
+            for n in 0..4 {
+                let id = n;
+                let pid = (n % 8) + 1;
+                let sql = format!("INSERT INTO cc (k, pid) VALUES ({id}, {pid})");
+                e0.lock().unwrap().execute_sql(&sql, &mut ctx).unwrap();
+            }
+        });
+        let h1 = loom::thread::spawn(move || {
+            let mut ctx = SessionContext::default();
+            for n in 0..4 {
+                let id = 100 + n;
+                let pid = (n % 8) + 1;
+                let sql = format!("INSERT INTO cc (k, pid) VALUES ({id}, {pid})");
+                e1.lock().unwrap().execute_sql(&sql, &mut ctx).unwrap();
+            }
+        });
+        h0.join().unwrap();
+        h1.join().unwrap();
+        let g = eng.lock().unwrap();
+        let mut ctx = SessionContext::default();
+        let out = g.execute_sql("SELECT k FROM cc", &mut ctx).expect("rows");
+        match out {
+            EngineOutput::ResultSet { rows, .. } => assert_eq!(rows.len(), 8),
+            _ => panic!("expected result set"),
+        }
+    });
+}
+
+#[test]
+fn engine_imports_mysqldump_and_pg_dump_scripts() {
+    use crate::network::dump_import::import_dump;
+    use crate::parser::DumpDialect;
+
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut ctx = SessionContext::default();
+
+    let mysql = "/*!40101 SET NAMES utf8mb4 */;\n\
+                 CREATE TABLE `users` (\n\
+                   `id` int(11) unsigned NOT NULL AUTO_INCREMENT,\n\
+                   `name` varchar(64) NOT NULL,\n\
+                   PRIMARY KEY (`id`)\n\
+                 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;\n\
+                 LOCK TABLES `users` WRITE;\n\
+                 INSERT INTO `users` VALUES (1,'ann'),(2,'bob');\n\
+                 UNLOCK TABLES;\n";
+    let report = import_dump(&eng, &mut ctx, mysql, DumpDialect::Auto).expect("mysqldump");
+    assert_eq!(report.dialect, DumpDialect::MySql);
+    assert_eq!(report.statements_executed, 2);
+    assert_eq!(report.rows_affected, 2);
+    assert!(!report.warnings.is_empty());
+
+    let pg = "SET client_encoding = 'UTF8';\n\
+              CREATE TABLE public.items (id integer NOT NULL, qty integer);\n\
+              COPY public.items (id, qty) FROM stdin;\n\
+              1\t10\n\
+              2\t\\N\n\
+              3\t30\n\
+              \\.\n";
+    let report = import_dump(&eng, &mut ctx, pg, DumpDialect::Auto).expect("pg_dump");
+    assert_eq!(report.dialect, DumpDialect::Postgres);
+    assert_eq!(report.rows_affected, 3);
+
+    match eng
+        .execute_sql("SELECT id FROM items WHERE qty = 30", &mut ctx)
+        .expect("select")
+    {
+        EngineOutput::ResultSet { rows, .. } => assert_eq!(rows, vec![vec!["Integer(3)"]]),
+        _ => panic!("expected ResultSet"),
+    }
+    match eng
+        .execute_sql("SELECT id FROM users ORDER BY id", &mut ctx)
+        .expect("select")
+    {
+        EngineOutput::ResultSet { rows, .. } => assert_eq!(rows.len(), 2),
+        _ => panic!("expected ResultSet"),
+    }
+
+    // Failures name the dump line of the offending statement.
+    let err = import_dump(
+        &eng,
+        &mut ctx,
+        "SET x = 1;\nINSERT INTO items (id, qty) VALUES (NULL, 1);",
+        DumpDialect::MySql,
+    )
+    .expect_err("NOT NULL id");
+    assert!(err.message.starts_with("line 2: "), "{}", err.message);
+}
+
+#[test]
+fn engine_validate_checks_statements_without_executing() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut ctx = SessionContext::default();
+    eng.execute_sql(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, email VARCHAR(50) NOT NULL, age INTEGER)",
+        &mut ctx,
+    )
+    .expect("create");
+    eng.execute_sql("CREATE INDEX users_email_idx ON users (email)", &mut ctx)
+        .expect("index");
+
+    let report = eng
+        .validate(
+            "CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER REFERENCES users(id));\
+             INSERT INTO orders (id, user_id) VALUES (1, 'one');\
+             SELECT u.email FROM users u JOIN orders o ON o.user_id = u.idd;\
+             SELECT * FROM users WHERE email = 'a@b';\
+             DROP TABLE users",
+        )
+        .expect("validate");
+    assert!(!report.is_valid());
+    let errors: Vec<_> = report.statements.iter().map(|s| s.errors.clone()).collect();
+    assert!(errors[0].is_empty(), "{report}");
+    assert_eq!(
+        errors[1],
+        vec!["orders.user_id: 'one' is not a valid INTEGER"]
+    );
+    assert_eq!(errors[2], vec!["unknown column users.idd"]);
+    assert!(errors[3].is_empty(), "{report}");
+    assert!(
+        report.statements[3]
+            .plan
+            .iter()
+            .any(|l| l.contains("users_email_idx")),
+        "{report}"
+    );
+    assert!(
+        errors[4][0].starts_with("cannot DROP TABLE users"),
+        "{report}"
+    );
+
+    // Nothing ran: orders was only created in the validator's copy of the catalog.
+    let err = eng
+        .execute_sql("DROP TABLE orders", &mut ctx)
+        .expect_err("orders was not created");
+    assert!(err.message.contains("does not exist"), "{}", err.message);
+
+    // The same checks work from the data directory, without opening the engine.
+    drop(eng);
+    let db = crate::Database::open(dir.path().to_str().expect("utf-8")).expect("db");
+    let report = db
+        .validate("INSERT INTO users (id, age) VALUES (1, 2)")
+        .expect("validate");
+    assert_eq!(
+        report.statements[0].errors,
+        vec!["column users.email is NOT NULL without a default and is not set"]
+    );
+    assert!(db
+        .validate("SELEC 1")
+        .expect("validate")
+        .parse_error
+        .is_some());
+}
+
+#[test]
+fn set_role_enforces_per_role_connection_caps() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut admin = SessionContext::default();
+    eng.execute_sql("SET network.max_connections_per_role = 'etl=1'", &mut admin)
+        .expect("set caps");
+
+    let mut first = SessionContext::default();
+    eng.execute_sql("SET role = 'ETL'", &mut first)
+        .expect("first etl session");
+    let mut second = SessionContext::default();
+    let err = eng
+        .execute_sql("SET role = etl", &mut second)
+        .expect_err("cap reached");
+    assert_eq!(err.code, engine_error_code::TOO_MANY_CONNECTIONS);
+    eng.execute_sql("SET role = reporting", &mut second)
+        .expect("unlisted roles are not capped");
+
+    let stats = |ctx: &mut SessionContext| match eng
+        .execute_sql("SELECT * FROM rustdb_stat_roles WHERE role = 'etl'", ctx)
+        .expect("stat roles")
+    {
+        EngineOutput::ResultSet { columns, rows } => {
+            assert_eq!(
+                columns,
+                vec!["max_connections", "refused", "role", "sessions"]
+            );
+            rows
+        }
+        other => panic!("expected ResultSet, got {other:?}"),
+    };
+    assert_eq!(
+        stats(&mut admin),
+        vec![vec![
+            "BigInt(1)",
+            "BigInt(1)",
+            "Varchar(\"'etl'\")",
+            "BigInt(1)"
+        ]]
+    );
+
+    // The slot goes with the session.
+    drop(first);
+    eng.execute_sql("SET role = etl", &mut second)
+        .expect("slot freed");
+    match eng.execute_sql("SHOW role", &mut second).expect("show") {
+        EngineOutput::ResultSet { rows, .. } => {
+            assert!(rows[0].contains(&"Varchar(\"'etl'\")".to_string()))
+        }
+        other => panic!("expected ResultSet, got {other:?}"),
+    }
+    assert_eq!(stats(&mut admin)[0][3], "BigInt(1)");
+}
+
+#[test]
+fn role_quotas_throttle_statements_and_stop_large_reads() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut admin = SessionContext::default();
+    for sql in [
+        "CREATE TABLE items (id INTEGER, name TEXT)",
+        "INSERT INTO items VALUES (1, 'a'), (2, 'b'), (3, 'c'), (4, 'd'), (5, 'e')",
+        "SET quotas.queries_per_second = 'batch=2'",
+        "SET quotas.max_rows_read = 'reporting=3'",
+        "SET quotas.max_temp_bytes = 'analyst=20'",
+    ] {
+        eng.execute_sql(sql, &mut admin).expect(sql);
+    }
+
+    // The bucket holds a second of statements: two go through, the third waits for a refill.
+    let mut batch = SessionContext::default();
+    eng.execute_sql("SET role = batch", &mut batch)
+        .expect("role");
+    for _ in 0..2 {
+        eng.execute_sql("SELECT id FROM items", &mut batch)
+            .expect("within rate");
+    }
+    let err = eng
+        .execute_sql("SELECT id FROM items", &mut batch)
+        .expect_err("throttled");
+    assert_eq!(err.code, engine_error_code::QUOTA_EXCEEDED);
+    assert!(err.message.contains("quotas.queries_per_second"), "{err:?}");
+
+    let mut reporting = SessionContext::default();
+    eng.execute_sql("SET role = reporting", &mut reporting)
+        .expect("role");
+    let err = eng
+        .execute_sql("SELECT id FROM items", &mut reporting)
+        .expect_err("five rows read");
+    assert_eq!(err.code, engine_error_code::QUOTA_EXCEEDED);
+    assert!(
+        err.message
+            .starts_with("statement read more than 3 rows, the limit of role \"reporting\""),
+        "{err:?}"
+    );
+    eng.execute_sql("SELECT 1", &mut reporting)
+        .expect("no table read");
+
+    // Scans are within the analyst's means, sorting all rows is not.
+    let mut analyst = SessionContext::default();
+    eng.execute_sql("SET role = analyst", &mut analyst)
+        .expect("role");
+    eng.execute_sql("SELECT id FROM items", &mut analyst)
+        .expect("no temp space");
+    let err = eng
+        .execute_sql("SELECT id, name FROM items ORDER BY name", &mut analyst)
+        .expect_err("sort buffers every row");
+    assert_eq!(err.code, engine_error_code::QUOTA_EXCEEDED);
+    assert!(err.message.contains("quotas.max_temp_bytes"), "{err:?}");
+
+    // Sessions without a quota are not limited.
+    eng.execute_sql("SELECT id, name FROM items ORDER BY name", &mut admin)
+        .expect("admin");
+
+    match eng
+        .execute_sql("SELECT * FROM rustdb_stat_quotas", &mut admin)
+        .expect("stat quotas")
+    {
+        EngineOutput::ResultSet { columns, rows } => {
+            assert_eq!(
+                columns,
+                vec![
+                    "max_rows_read",
+                    "max_temp_bytes",
+                    "queries_per_second",
+                    "role",
+                    "rows_read_exceeded",
+                    "temp_bytes_exceeded",
+                    "throttled"
+                ]
+            );
+            assert_eq!(
+                rows,
+                vec![
+                    vec![
+                        "Null",
+                        "BigInt(20)",
+                        "Null",
+                        "Varchar(\"'analyst'\")",
+                        "BigInt(0)",
+                        "BigInt(1)",
+                        "BigInt(0)"
+                    ],
+                    vec![
+                        "Null",
+                        "Null",
+                        "BigInt(2)",
+                        "Varchar(\"'batch'\")",
+                        "BigInt(0)",
+                        "BigInt(0)",
+                        "BigInt(1)"
+                    ],
+                    vec![
+                        "BigInt(3)",
+                        "Null",
+                        "Null",
+                        "Varchar(\"'reporting'\")",
+                        "BigInt(1)",
+                        "BigInt(0)",
+                        "BigInt(0)"
+                    ],
+                ]
+            );
+        }
+        other => panic!("expected ResultSet, got {other:?}"),
+    }
+}
+
+#[test]
+fn select_audit_samples_exempts_and_caps_reads_of_audited_tables() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut admin = SessionContext::default();
+    for sql in [
+        "CREATE TABLE customers (id INTEGER, card VARCHAR(20))",
+        "CREATE TABLE payments (id INTEGER, amount INTEGER)",
+        "CREATE TABLE notes (id INTEGER)",
+        "INSERT INTO customers VALUES (1, '4111111111111111')",
+        "SET audit.select_tables = 'customers, payments=0.0001'",
+        "SET audit.exempt_roles = 'etl'",
+    ] {
+        eng.execute_sql(sql, &mut admin).expect(sql);
+    }
+    let mut analyst = SessionContext::default();
+    let mut etl = SessionContext::default();
+    eng.execute_sql("SET role = analyst", &mut analyst)
+        .expect("role");
+    eng.execute_sql("SET role = etl", &mut etl).expect("role");
+
+    eng.execute_sql("SELECT card FROM customers", &mut analyst)
+        .expect("audited read");
+    eng.execute_sql("SELECT * FROM notes", &mut analyst)
+        .expect("unaudited table");
+    eng.execute_sql("SELECT * FROM customers", &mut etl)
+        .expect("exempt role");
+    for _ in 0..10 {
+        eng.execute_sql("SELECT * FROM payments", &mut analyst)
+            .expect("sampled table");
+    }
+    eng.execute_sql("SET audit.max_events_per_second = 1", &mut admin)
+        .expect("cap");
+    eng.execute_sql("SELECT id FROM customers", &mut analyst)
+        .expect("read");
+    eng.execute_sql("SELECT id FROM customers", &mut analyst)
+        .expect("read");
+
+    let log = std::fs::read_to_string(dir.path().join("audit/select.jsonl")).expect("audit log");
+    let events: Vec<serde_json::Value> = log
+        .lines()
+        .map(|l| serde_json::from_str(l).expect("json"))
+        .collect();
+    assert_eq!(events[0]["role"], "analyst");
+    assert_eq!(events[0]["tables"], serde_json::json!(["customers"]));
+    assert_eq!(events[0]["sql"], "SELECT card FROM customers");
+    assert_eq!(events[0]["rows"], 1);
+    assert!(events.iter().all(|e| e["role"] == "analyst"));
+    // The cap of one event per second lets at most one of the last two reads through (none when
+    // they share the first event's second).
+    assert!((1..=2).contains(&events.len()), "{log}");
+
+    let stats = match eng
+        .execute_sql("SELECT * FROM rustdb_stat_audit", &mut admin)
+        .expect("stat audit")
+    {
+        EngineOutput::ResultSet { columns, rows } => {
+            assert_eq!(
+                columns,
+                vec![
+                    "exempted",
+                    "logged",
+                    "sample_rate",
+                    "sampled_out",
+                    "suppressed",
+                    "table_name"
+                ]
+            );
+            rows
+        }
+        other => panic!("expected ResultSet, got {other:?}"),
+    };
+    assert_eq!(stats.len(), 2);
+    let (customers, payments) = (&stats[0], &stats[1]);
+    assert_eq!(customers[0], "BigInt(1)");
+    assert_eq!(customers[5], "Varchar(\"'customers'\")");
+    assert_eq!(payments[3], "BigInt(10)");
+    let logged = |row: &Vec<String>| {
+        row[1]
+            .trim_start_matches("BigInt(")
+            .trim_end_matches(')')
+            .parse::<usize>()
+            .unwrap()
+    };
+    assert_eq!(logged(customers) + logged(payments), events.len());
+}
+
+#[test]
+fn masked_columns_are_masked_for_roles_without_unmask() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut admin = SessionContext::default();
+    for sql in [
+        "CREATE TABLE customers (id INTEGER, card VARCHAR(40), email VARCHAR(40), ssn INTEGER)",
+        "CREATE TABLE copies (card VARCHAR(40))",
+        "INSERT INTO customers (id, card, email, ssn) \
+         VALUES (1, '4111-1111-1111-1111', 'alice@example.com', 123456789)",
+        "SET masking.columns = 'customers.card=last4, customers.email=email, customers.ssn=null'",
+        "SET masking.unmask_roles = 'fraud'",
+    ] {
+        eng.execute_sql(sql, &mut admin).expect(sql);
+    }
+    let mut analyst = SessionContext::default();
+    let mut fraud = SessionContext::default();
+    eng.execute_sql("SET role = analyst", &mut analyst)
+        .expect("role");
+    eng.execute_sql("SET role = fraud", &mut fraud)
+        .expect("role");
+    let rows = |ctx: &mut SessionContext, sql: &str| match eng.execute_sql(sql, ctx).expect(sql) {
+        EngineOutput::ResultSet { columns, rows } => (columns, rows),
+        other => panic!("expected ResultSet, got {other:?}"),
+    };
+
+    let (columns, masked) = rows(&mut analyst, "SELECT id, card, email, ssn FROM customers");
+    assert_eq!(columns, vec!["card", "email", "id", "ssn"]);
+    assert_eq!(masked[0][0], "Varchar(\"'****-****-****-1111'\")");
+    assert_eq!(masked[0][1], "Varchar(\"'a****@example.com'\")");
+    assert_eq!(masked[0][3], "Null");
+    // Filters see stored values; aliases and expressions over masked columns stay masked.
+    let (columns, masked) = rows(
+        &mut analyst,
+        "SELECT card AS c, ssn * 2 AS total FROM customers WHERE card = '4111-1111-1111-1111'",
+    );
+    assert_eq!(columns, vec!["c", "total"]);
+    assert_eq!(
+        masked,
+        vec![vec!["Varchar(\"'****-****-****-1111'\")", "Null"]]
+    );
+
+    let (_, raw) = rows(&mut fraud, "SELECT card, ssn FROM customers");
+    assert_eq!(raw[0][0], "Varchar(\"'4111-1111-1111-1111'\")");
+    assert!(raw[0][1].contains("123456789"), "{raw:?}");
+
+    eng.execute_sql(
+        "INSERT INTO copies (card) SELECT card FROM customers",
+        &mut analyst,
+    )
+    .expect("copy");
+    let (_, copied) = rows(&mut fraud, "SELECT card FROM copies");
+    assert_eq!(copied[0][0], "Varchar(\"'****-****-****-1111'\")");
+
+    let err = eng
+        .execute_sql(
+            "SELECT card FROM copies UNION SELECT * FROM customers",
+            &mut analyst,
+        )
+        .expect_err("wildcard over masked columns");
+    assert_eq!(err.code, engine_error_code::UNSUPPORTED_SQL);
+}
+
+#[test]
+fn forget_deletes_a_subjects_encrypted_rows_and_destroys_its_key() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut ctx = SessionContext::default();
+    for sql in [
+        "CREATE TABLE orders (id INTEGER, customer VARCHAR(40), item VARCHAR(40)) \
+         ENCRYPT BY customer",
+        "INSERT INTO orders (id, customer, item) VALUES (1, 'alice', 'hiking-boots')",
+        "INSERT INTO orders (id, customer, item) VALUES (2, 'bob', 'desk-lamp')",
+        "INSERT INTO orders (id, customer, item) VALUES (3, 'alice', 'fountain-pen')",
+    ] {
+        eng.execute_sql(sql, &mut ctx).expect(sql);
+    }
+    let err = eng
+        .execute_sql(
+            "CREATE TABLE notes (id INTEGER, body TEXT) ENCRYPT BY owner",
+            &mut ctx,
+        )
+        .expect_err("unknown ENCRYPT BY column");
+    assert_eq!(err.code, engine_error_code::UNSUPPORTED_SQL);
+    let orders = |eng: &SqlEngine, ctx: &mut SessionContext| match eng
+        .execute_sql("SELECT id, item FROM orders ORDER BY id", ctx)
+    {
+        Ok(EngineOutput::ResultSet { rows, .. }) => rows,
+        other => panic!("expected ResultSet, got {other:?}"),
+    };
+    let rows = orders(&eng, &mut ctx);
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0][1], "Varchar(\"'hiking-boots'\")");
+    let stored = std::fs::read(dir.path().join("orders.tbl")).expect("table file");
+    assert!(!stored.windows(12).any(|w| w == b"hiking-boots"));
+
+    match eng.execute_sql("FORGET 'alice'", &mut ctx).expect("forget") {
+        EngineOutput::ExecutionOk { rows_affected } => assert_eq!(rows_affected, 2),
+        other => panic!("expected ExecutionOk, got {other:?}"),
+    }
+    let rows = orders(&eng, &mut ctx);
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0][0], "Integer(2)");
+    eng.execute_sql("VACUUM", &mut ctx).expect("vacuum");
+
+    let keys = std::fs::read_to_string(dir.path().join("keys/subjects.json")).expect("keys");
+    assert!(!keys.contains("alice"));
+    let evidence =
+        std::fs::read_to_string(dir.path().join("audit/forget.jsonl")).expect("evidence");
+    let events: Vec<serde_json::Value> = evidence
+        .lines()
+        .map(|l| serde_json::from_str(l).expect("json"))
+        .collect();
+    assert_eq!(events[0]["event"], "forget");
+    assert_eq!(events[0]["rows"], 2);
+    assert_eq!(events[0]["tables"], serde_json::json!(["orders"]));
+    assert!(events[0]["key_id"].is_string());
+    assert!(!evidence.contains("alice"));
+    assert_eq!(events[1]["event"], "vacuum");
+    assert_eq!(events[1]["table"], "orders");
+
+    eng.flush_wal_buffer().expect("flush");
+    drop(eng);
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("reopen");
+    let rows = orders(&eng, &mut ctx);
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0][1], "Varchar(\"'desk-lamp'\")");
+}
+
+#[test]
+fn forget_destroys_keys_of_its_own_engine_only() {
+    let dir = TempDir::new().expect("tempdir");
+    let setup = [
+        "CREATE TABLE orders (id INTEGER, customer VARCHAR(40), item VARCHAR(40)) \
+         ENCRYPT BY customer",
+        "INSERT INTO orders (id, customer, item) VALUES (1, 'alice', 'hiking-boots')",
+        "CREATE TENANT acme",
+    ];
+    let prod = SqlEngine::open(dir.path().join("prod")).expect("open prod");
+    let other = SqlEngine::open(dir.path().join("other")).expect("open other");
+    let mut ctx = SessionContext::default();
+    for sql in setup {
+        prod.execute_sql(sql, &mut ctx).expect(sql);
+        other.execute_sql(sql, &mut ctx).expect(sql);
+    }
+    let mut acme = SessionContext::default();
+    for sql in [
+        "SET tenant = acme",
+        "CREATE TABLE notes (id INTEGER, body VARCHAR(40))",
+        "INSERT INTO notes (id, body) VALUES (1, 'call-bob')",
+    ] {
+        prod.execute_sql(sql, &mut acme).expect(sql);
+    }
+    prod.execute_sql("CREATE DATABASE staging TEMPLATE prod", &mut ctx)
+        .expect("clone");
+    let staging = SqlEngine::open(dir.path().join("staging")).expect("open clone");
+    let rows =
+        |eng: &SqlEngine, sql: &str, ctx: &mut SessionContext| match eng.execute_sql(sql, ctx) {
+            Ok(EngineOutput::ResultSet { rows, .. }) => rows.len(),
+            other => panic!("expected ResultSet, got {other:?}"),
+        };
+    let orders = "SELECT item FROM orders";
+    let key_ids = |db: &str| -> Vec<String> {
+        let keys = std::fs::read_to_string(dir.path().join(db).join("keys/subjects.json"))
+            .expect("subject keys");
+        let tenants = std::fs::read_to_string(dir.path().join(db).join("keys/tenants.json"))
+            .expect("tenant keys");
+        let keys: serde_json::Value = serde_json::from_str(&keys).expect("json");
+        assert_eq!(keys["inherited"], serde_json::json!([]));
+        let tenants: serde_json::Value = serde_json::from_str(&tenants).expect("json");
+        let subject = keys["subjects"].as_object().expect("subjects").values();
+        let tenant = tenants["tenants"]["acme"]["key"].clone();
+        subject
+            .chain([&tenant])
+            .map(|k| k["id"].as_str().expect("key id").to_string())
+            .collect()
+    };
+    // The clone resealed its rows under keys of its own.
+    let (prod_keys, staging_keys) = (key_ids("prod"), key_ids("staging"));
+    assert_eq!(staging_keys.len(), 2);
+    assert!(staging_keys.iter().all(|id| !prod_keys.contains(id)));
+    let mut staging_acme = SessionContext::default();
+    staging
+        .execute_sql("SET tenant = acme", &mut staging_acme)
+        .expect("tenant");
+    assert_eq!(
+        rows(&staging, "SELECT body FROM notes", &mut staging_acme),
+        1
+    );
+
+    prod.execute_sql("FORGET 'alice'", &mut ctx)
+        .expect("forget");
+    assert_eq!(rows(&prod, orders, &mut ctx), 0);
+    assert_eq!(rows(&other, orders, &mut ctx), 1);
+    assert_eq!(rows(&staging, orders, &mut ctx), 1);
+
+    drop(staging);
+    let staging = SqlEngine::open(dir.path().join("staging")).expect("reopen clone");
+    assert_eq!(rows(&staging, orders, &mut ctx), 1);
+}
+
+#[test]
+fn tenants_own_sealed_tables_have_quotas_and_drop_with_their_files() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut admin = SessionContext::default();
+    for sql in [
+        "CREATE TENANT acme WITH (max_rows_read = 3)",
+        "CREATE TENANT globex",
+        "CREATE TABLE shared (id INTEGER)",
+    ] {
+        eng.execute_sql(sql, &mut admin).expect(sql);
+    }
+    let mut acme = SessionContext::default();
+    for sql in [
+        "SET tenant = acme",
+        "CREATE TABLE orders (id INTEGER, item VARCHAR(40))",
+        "INSERT INTO orders (id, item) VALUES (1, 'hiking-boots'), (2, 'desk-lamp')",
+    ] {
+        eng.execute_sql(sql, &mut acme).expect(sql);
+    }
+    let stored = std::fs::read(dir.path().join("orders.tbl")).expect("table file");
+    assert!(!stored.windows(12).any(|w| w == b"hiking-boots"));
+    match eng
+        .execute_sql("SELECT item FROM orders WHERE id = 1", &mut acme)
+        .expect("select")
+    {
+        EngineOutput::ResultSet { rows, .. } => {
+            assert_eq!(rows, vec![vec!["Varchar(\"'hiking-boots'\")"]]);
+        }
+        other => panic!("expected ResultSet, got {other:?}"),
+    }
+
+    // Other tenants, and tenants' sessions outside their own tables, see nothing.
+    let mut globex = SessionContext::default();
+    eng.execute_sql("SET tenant = globex", &mut globex)
+        .expect("tenant");
+    let err = eng
+        .execute_sql("SELECT * FROM orders", &mut globex)
+        .expect_err("acme's table");
+    assert_eq!(err.message, "table orders does not exist");
+    let err = eng
+        .execute_sql("CREATE TABLE orders (id INTEGER)", &mut globex)
+        .expect_err("name taken");
+    assert_eq!(err.message, "table name orders is taken");
+    let err = eng
+        .execute_sql("SELECT * FROM shared", &mut acme)
+        .expect_err("not a tenant table");
+    assert_eq!(err.code, engine_error_code::CONSTRAINT_VIOLATION);
+    let err = eng
+        .execute_sql("DROP TENANT globex", &mut acme)
+        .expect_err("tenant session");
+    assert_eq!(err.code, engine_error_code::UNSUPPORTED_SQL);
+
+    eng.execute_sql(
+        "INSERT INTO orders (id, item) VALUES (3, 'a'), (4, 'b')",
+        &mut acme,
+    )
+    .expect("insert");
+    let err = eng
+        .execute_sql("SELECT id FROM orders", &mut acme)
+        .expect_err("four rows read");
+    assert_eq!(err.code, engine_error_code::QUOTA_EXCEEDED);
+    assert!(
+        err.message
+            .starts_with("statement read more than 3 rows, the limit of tenant \"acme\""),
+        "{err:?}"
+    );
+    eng.execute_sql("ALTER TENANT acme SET (max_rows_read = NONE)", &mut admin)
+        .expect("alter");
+    eng.execute_sql("SELECT id FROM orders", &mut acme)
+        .expect("no limit");
+
+    match eng
+        .execute_sql(
+            "SELECT tenant, tables, rows_read_exceeded FROM rustdb_stat_tenants",
+            &mut admin,
+        )
+        .expect("stat tenants")
+    {
+        EngineOutput::ResultSet { columns, rows } => {
+            assert_eq!(columns, vec!["rows_read_exceeded", "tables", "tenant"]);
+            assert_eq!(
+                rows,
+                vec![
+                    vec!["BigInt(1)", "BigInt(1)", "Varchar(\"'acme'\")"],
+                    vec!["BigInt(0)", "BigInt(0)", "Varchar(\"'globex'\")"],
+                ]
+            );
+        }
+        other => panic!("expected ResultSet, got {other:?}"),
+    }
+
+    match eng
+        .execute_sql("DROP TENANT acme", &mut admin)
+        .expect("drop tenant")
+    {
+        EngineOutput::ExecutionOk { rows_affected } => assert_eq!(rows_affected, 1),
+        other => panic!("expected ExecutionOk, got {other:?}"),
+    }
+    assert!(!dir.path().join("orders.tbl").exists());
+    let keys = std::fs::read_to_string(dir.path().join("keys/tenants.json")).expect("keys");
+    assert!(!keys.contains("acme"));
+    let err = eng
+        .execute_sql("SELECT 1", &mut acme)
+        .expect_err("tenant dropped");
+    assert_eq!(err.code, engine_error_code::INVALID_PARAMETER);
+    eng.execute_sql("DROP TENANT IF EXISTS acme", &mut admin)
+        .expect("if exists");
+
+    eng.flush_wal_buffer().expect("flush");
+    drop(eng);
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("reopen");
+    assert!(!dir.path().join("orders.tbl").exists());
+    eng.execute_sql("SET tenant = globex", &mut globex)
+        .expect("tenant survives reopen");
+}
+
+/// `YYYY-MM-DD HH:MM:SS` (UTC) of `secs` since the Unix epoch.
+fn utc_timestamp(secs: u64) -> String {
+    let (days, rem) = ((secs / 86_400) as i64, secs % 86_400);
+    let z = days + 719_468;
+    let (era, doe) = (z.div_euclid(146_097), z.rem_euclid(146_097));
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+#[test]
+fn select_as_of_timestamp_reads_past_rows() {
+    let now = || {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    };
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut ctx = SessionContext::default();
+    for sql in [
+        "CREATE TABLE accounts (id INT PRIMARY KEY, owner TEXT, balance INT)",
+        "INSERT INTO accounts (id, owner, balance) VALUES (1, 'ann', 100), (2, 'bob', 50)",
+    ] {
+        eng.execute_sql(sql, &mut ctx).expect(sql);
+    }
+    // Commit times have whole-second resolution.
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    let before = utc_timestamp(now());
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    for sql in [
+        "UPDATE accounts SET balance = 80 WHERE id = 1",
+        "DELETE FROM accounts WHERE id = 2",
+        "INSERT INTO accounts (id, owner, balance) VALUES (3, 'cy', 10)",
+    ] {
+        eng.execute_sql(sql, &mut ctx).expect(sql);
+    }
+    let mut writer = SessionContext::default();
+    eng.execute_sql("BEGIN TRANSACTION", &mut writer).unwrap();
+    eng.execute_sql(
+        "INSERT INTO accounts (id, owner, balance) VALUES (4, 'dee', 1)",
+        &mut writer,
+    )
+    .unwrap();
+
+    let rows = |sql: &str, ctx: &mut SessionContext| match eng.execute_sql(sql, ctx).expect(sql) {
+        EngineOutput::ResultSet { rows, .. } => {
+            let mut rows: Vec<String> = rows.into_iter().map(|r| r.join(" ")).collect();
+            rows.sort();
+            rows
+        }
+        other => panic!("expected rows, got {other:?}"),
+    };
+    let code = |sql: &str, ctx: &mut SessionContext| eng.execute_sql(sql, ctx).unwrap_err().code;
+    let as_of = |at: &str| format!("SELECT id, balance FROM accounts AS OF TIMESTAMP '{at}'");
+
+    assert_eq!(
+        code(&as_of(&before), &mut ctx),
+        engine_error_code::UNSUPPORTED_SQL
+    );
+    eng.execute_sql("SET history.retention_secs = 3600", &mut ctx)
+        .unwrap();
+    assert_eq!(
+        rows(&as_of(&before), &mut ctx),
+        vec!["Integer(100) Integer(1)", "Integer(50) Integer(2)"]
+    );
+    assert_eq!(
+        rows(
+            &format!("{} a WHERE a.balance < 100 LIMIT 5", as_of(&before)),
+            &mut ctx
+        ),
+        vec!["Integer(50) Integer(2)"]
+    );
+    // Committed changes up to now; not the open transaction's insert.
+    assert_eq!(
+        rows(&as_of(&utc_timestamp(now())), &mut ctx),
+        vec!["Integer(10) Integer(3)", "Integer(80) Integer(1)"]
+    );
+    eng.execute_sql("ROLLBACK", &mut writer).unwrap();
+
+    for at in [
+        "2000-01-01 00:00:00".to_string(),
+        utc_timestamp(now() + 3600),
+        "yesterday".to_string(),
+    ] {
+        assert_eq!(
+            code(&as_of(&at), &mut ctx),
+            engine_error_code::INVALID_SNAPSHOT,
+            "{at}"
+        );
+    }
+    assert_eq!(
+        code(
+            &format!(
+                "SELECT * FROM accounts AS OF TIMESTAMP '{before}' a JOIN accounts b ON a.id = b.id"
+            ),
+            &mut ctx
+        ),
+        engine_error_code::UNSUPPORTED_SQL
+    );
+    eng.execute_sql("ALTER TABLE accounts ADD COLUMN note TEXT", &mut ctx)
+        .unwrap();
+    assert_eq!(
+        code(&as_of(&before), &mut ctx),
+        engine_error_code::INVALID_SNAPSHOT
+    );
+}
+
+#[test]
+fn flashback_table_restores_past_rows() {
+    let now = || {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    };
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut ctx = SessionContext::default();
+    for sql in [
+        "CREATE TABLE accounts (id INT PRIMARY KEY, owner TEXT, balance INT)",
+        "INSERT INTO accounts (id, owner, balance) VALUES (1, 'ann', 100), (2, 'bob', 50)",
+        "CREATE TABLE audit (id INT PRIMARY KEY, note TEXT)",
+        "INSERT INTO audit (id, note) VALUES (1, 'kept')",
+    ] {
+        eng.execute_sql(sql, &mut ctx).expect(sql);
+    }
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    let before = utc_timestamp(now());
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    for sql in [
+        "UPDATE accounts SET balance = 0",
+        "DELETE FROM accounts WHERE id = 2",
+        "INSERT INTO accounts (id, owner, balance) VALUES (3, 'cy', 10)",
+        "INSERT INTO audit (id, note) VALUES (2, 'also kept')",
+    ] {
+        eng.execute_sql(sql, &mut ctx).expect(sql);
+    }
+
+    let rows = |sql: &str, ctx: &mut SessionContext| match eng.execute_sql(sql, ctx).expect(sql) {
+        EngineOutput::ResultSet { rows, .. } => {
+            let mut rows: Vec<String> = rows.into_iter().map(|r| r.join(" ")).collect();
+            rows.sort();
+            rows
+        }
+        other => panic!("expected rows, got {other:?}"),
+    };
+    let code = |sql: &str, ctx: &mut SessionContext| eng.execute_sql(sql, ctx).unwrap_err().code;
+    let flashback = |table: &str, at: &str| format!("FLASHBACK TABLE {table} TO TIMESTAMP '{at}'");
+
+    assert_eq!(
+        code(&flashback("accounts", &before), &mut ctx),
+        engine_error_code::UNSUPPORTED_SQL
+    );
+    eng.execute_sql("SET history.retention_secs = 3600", &mut ctx)
+        .unwrap();
+    assert_eq!(
+        code(&flashback("missing", &before), &mut ctx),
+        engine_error_code::UNSUPPORTED_SQL
+    );
+    assert_eq!(
+        code(&flashback("accounts", "yesterday"), &mut ctx),
+        engine_error_code::INVALID_SNAPSHOT
+    );
+    eng.execute_sql("BEGIN TRANSACTION", &mut ctx).unwrap();
+    assert!(eng
+        .execute_sql(&flashback("accounts", &before), &mut ctx)
+        .is_err());
+    eng.execute_sql("ROLLBACK", &mut ctx).unwrap();
+
+    match eng
+        .execute_sql(&flashback("accounts", &before), &mut ctx)
+        .unwrap()
+    {
+        EngineOutput::ExecutionOk { rows_affected } => assert_eq!(rows_affected, 2),
+        other => panic!("expected ExecutionOk, got {other:?}"),
+    }
+    assert_eq!(
+        rows("SELECT id, owner, balance FROM accounts", &mut ctx),
+        vec![
+            "Integer(100) Integer(1) Varchar(\"'ann'\")",
+            "Integer(50) Integer(2) Varchar(\"'bob'\")"
+        ]
+    );
+    // Other tables are untouched, and the primary key still holds.
+    assert_eq!(rows("SELECT id FROM audit", &mut ctx).len(), 2);
+    assert!(eng
+        .execute_sql(
+            "INSERT INTO accounts (id, owner, balance) VALUES (2, 'dup', 1)",
+            &mut ctx
+        )
+        .is_err());
+
+    // The flashback is itself history.
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    let restored = utc_timestamp(now());
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    eng.execute_sql("DELETE FROM accounts", &mut ctx).unwrap();
+    eng.execute_sql(&flashback("accounts", &restored), &mut ctx)
+        .unwrap();
+    assert_eq!(rows("SELECT id FROM accounts", &mut ctx).len(), 2);
+}
+
+#[test]
+fn change_tracking_lists_rows_changed_since_an_lsn() {
+    let dir = TempDir::new().expect("tempdir");
+    let changed = |eng: &SqlEngine, after: u64| {
+        let sql = format!(
+            "SELECT pk FROM rustdb_change_tracking WHERE table_name = 'orders' AND commit_lsn > {after}"
+        );
+        match eng
+            .execute_sql(&sql, &mut SessionContext::default())
+            .expect(&sql)
+        {
+            EngineOutput::ResultSet { rows, .. } => {
+                rows.into_iter().map(|r| r.join(" ")).collect::<Vec<_>>()
+            }
+            other => panic!("expected rows, got {other:?}"),
+        }
+    };
+    let keys = |keys: &[&str]| -> Vec<String> {
+        keys.iter().map(|k| format!("Varchar(\"'{k}'\")")).collect()
+    };
+    {
+        let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+        let mut ctx = SessionContext::default();
+        for sql in [
+            "CREATE TABLE orders (id INT PRIMARY KEY, item TEXT)",
+            "CREATE TABLE notes (id INT PRIMARY KEY, body TEXT)",
+            "INSERT INTO orders (id, item) VALUES (1, 'pen')",
+            "SET change_tracking.tables = 'Orders'",
+            "INSERT INTO orders (id, item) VALUES (2, 'ink'), (3, 'pad')",
+        ] {
+            eng.execute_sql(sql, &mut ctx).expect(sql);
+        }
+    }
+    // The log outlives the engine.
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("reopen");
+    assert_eq!(changed(&eng, 0), keys(&["2", "3"]));
+
+    let mut ctx = SessionContext::default();
+    eng.execute_sql("SET change_tracking.tables = 'orders'", &mut ctx)
+        .unwrap();
+    eng.execute_sql("INSERT INTO orders (id, item) VALUES (4, 'pad')", &mut ctx)
+        .unwrap();
+    let since = ctx.last_commit_lsn;
+    for sql in [
+        "UPDATE orders SET item = 'pencil' WHERE id = 1",
+        "DELETE FROM orders WHERE id = 2",
+        "INSERT INTO notes (id, body) VALUES (1, 'untracked')",
+    ] {
+        eng.execute_sql(sql, &mut ctx).expect(sql);
+    }
+    eng.execute_sql("BEGIN TRANSACTION", &mut ctx).unwrap();
+    eng.execute_sql(
+        "INSERT INTO orders (id, item) VALUES (5, 'quill')",
+        &mut ctx,
+    )
+    .unwrap();
+    eng.execute_sql("ROLLBACK", &mut ctx).unwrap();
+
+    assert_eq!(changed(&eng, 0), keys(&["2", "3", "4", "1", "2"]));
+    assert_eq!(changed(&eng, since), keys(&["1", "2"]));
+}
+
+#[test]
+fn check_table_and_analyze_report_on_tables() {
+    let dir = TempDir::new().expect("tempdir");
+    let rows = |eng: &SqlEngine, sql: &str| match eng
+        .execute_sql(sql, &mut SessionContext::default())
+        .expect(sql)
+    {
+        EngineOutput::ResultSet { rows, .. } => rows,
+        other => panic!("expected rows, got {other:?}"),
+    };
+    {
+        let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+        let mut ctx = SessionContext::default();
+        for sql in [
+            "CREATE TABLE items (id INT PRIMARY KEY, tag TEXT)",
+            "CREATE INDEX items_tag ON items (tag)",
+            "CREATE TABLE notes (id INT PRIMARY KEY)",
+            "INSERT INTO items (id, tag) VALUES (1, 'a'), (2, 'a'), (3, NULL)",
+        ] {
+            eng.execute_sql(sql, &mut ctx).expect(sql);
+        }
+        // Columns by name: message, problems, rows, status, table_name.
+        assert_eq!(
+            rows(&eng, "CHECK TABLE"),
+            vec![
+                vec![
+                    "Varchar(\"''\")",
+                    "BigInt(0)",
+                    "BigInt(3)",
+                    "Varchar(\"'ok'\")",
+                    "Varchar(\"'items'\")"
+                ],
+                vec![
+                    "Varchar(\"''\")",
+                    "BigInt(0)",
+                    "BigInt(0)",
+                    "Varchar(\"'ok'\")",
+                    "Varchar(\"'notes'\")"
+                ],
+            ]
+        );
+        assert!(eng.execute_sql("CHECK TABLE missing", &mut ctx).is_err());
+        // Columns: columns, pages, rows, table_name.
+        let analyzed = rows(&eng, "ANALYZE items");
+        assert_eq!(analyzed.len(), 1);
+        assert_eq!(analyzed[0][0], "BigInt(2)");
+        assert_eq!(analyzed[0][2], "BigInt(3)");
+
+        eng.execute_sql("BEGIN TRANSACTION", &mut ctx).unwrap();
+        assert!(eng.execute_sql("ANALYZE", &mut ctx).is_err());
+        eng.execute_sql("ROLLBACK", &mut ctx).unwrap();
+    }
+    // Statistics outlive the engine; columns by name again.
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("reopen");
+    assert_eq!(
+        rows(&eng, "SELECT table_name, rows FROM rustdb_stat_tables"),
+        vec![vec!["BigInt(3)", "Varchar(\"'items'\")"]]
+    );
+    assert_eq!(
+        rows(
+            &eng,
+            "SELECT column_name, nulls, distinct_values FROM rustdb_stat_columns WHERE table_name = 'items'"
+        ),
+        vec![
+            vec!["Varchar(\"'id'\")", "BigInt(3)", "BigInt(0)"],
+            vec!["Varchar(\"'tag'\")", "BigInt(1)", "BigInt(1)"],
+        ]
+    );
+}
+
+#[test]
+fn lazy_catalog_loads_tables_on_first_use_or_preload() {
+    let dir = TempDir::new().expect("tempdir");
+    {
+        let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+        let mut ctx = SessionContext::default();
+        for sql in [
+            "CREATE TABLE customers (id INTEGER PRIMARY KEY, name VARCHAR(20))",
+            "CREATE TABLE orders (id INTEGER PRIMARY KEY, customer_id INTEGER REFERENCES customers(id))",
+            "CREATE TABLE items (sku INTEGER PRIMARY KEY)",
+            "CREATE TABLE notes (body VARCHAR(20))",
+            "INSERT INTO customers (id, name) VALUES (1, 'ada'), (2, 'bob')",
+            "INSERT INTO orders (id, customer_id) VALUES (10, 1)",
+            "INSERT INTO items (sku) VALUES (7)",
+        ] {
+            eng.execute_sql(sql, &mut ctx).expect(sql);
+        }
+        eng.flush_wal_buffer().expect("flush");
+    }
+    let stats = |eng: &SqlEngine, ctx: &mut SessionContext| match eng
+        .execute_sql(
+            "SELECT cold_tables, stalls, stalled_tables, preloaded_tables \
+             FROM rustdb_stat_schema_cache",
+            ctx,
+        )
+        .expect("stat schema cache")
+    {
+        EngineOutput::ResultSet { rows, .. } => rows,
+        other => panic!("expected ResultSet, got {other:?}"),
+    };
+
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("reopen");
+    let mut ctx = SessionContext::default();
+    // Columns by name: cold_tables, preloaded_tables, stalled_tables, stalls.
+    assert_eq!(
+        stats(&eng, &mut ctx),
+        vec![vec!["BigInt(3)", "BigInt(0)", "BigInt(0)", "BigInt(0)"]]
+    );
+
+    // Naming orders loads customers with it, so both keys are enforced.
+    let err = eng
+        .execute_sql(
+            "INSERT INTO orders (id, customer_id) VALUES (10, 2)",
+            &mut ctx,
+        )
+        .expect_err("duplicate key");
+    assert_eq!(err.code, engine_error_code::CONSTRAINT_VIOLATION);
+    let err = eng
+        .execute_sql("DELETE FROM customers WHERE id = 1", &mut ctx)
+        .expect_err("referenced row");
+    assert_eq!(err.code, engine_error_code::CONSTRAINT_VIOLATION);
+    assert_eq!(
+        stats(&eng, &mut ctx),
+        vec![vec!["BigInt(1)", "BigInt(0)", "BigInt(2)", "BigInt(1)"]]
+    );
+
+    assert_eq!(eng.preload_schema(&["items", "notes"]).expect("preload"), 1);
+    assert_eq!(
+        stats(&eng, &mut ctx),
+        vec![vec!["BigInt(0)", "BigInt(1)", "BigInt(2)", "BigInt(1)"]]
+    );
+    let err = eng
+        .execute_sql("INSERT INTO items (sku) VALUES (7)", &mut ctx)
+        .expect_err("duplicate key");
+    assert_eq!(err.code, engine_error_code::CONSTRAINT_VIOLATION);
+}
+
+#[test]
+fn count_star_is_answered_from_committed_row_counts() {
+    let dir = TempDir::new().expect("tempdir");
+    let count = |eng: &SqlEngine, ctx: &mut SessionContext, table: &str| match eng
+        .execute_sql(&format!("SELECT COUNT(*) FROM {table}"), ctx)
+        .expect("count")
+    {
+        EngineOutput::ResultSet { columns, rows } => {
+            assert_eq!(columns, vec!["COUNT(*)"]);
+            rows
+        }
+        other => panic!("expected ResultSet, got {other:?}"),
+    };
+    let big_int = |n: i64| vec![vec![format!("BigInt({n})")]];
+    {
+        let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+        let mut ctx = SessionContext::default();
+        for sql in [
+            "CREATE TABLE plain (id INTEGER)",
+            "CREATE TABLE keyed (id INTEGER)",
+            "CREATE INDEX idx_keyed_id ON keyed (id)",
+            "CREATE TABLE hashed (id INTEGER)",
+            "CREATE INDEX idx_hashed_id ON hashed USING HASH (id)",
+            "INSERT INTO plain (id) VALUES (1), (2), (3)",
+            "INSERT INTO keyed (id) VALUES (1), (1), (2)",
+            "INSERT INTO hashed (id) VALUES (1), (2), (2), (3)",
+        ] {
+            eng.execute_sql(sql, &mut ctx).expect(sql);
+        }
+        // Counted from the heap and from a B-tree index on first use, then kept up to date.
+        assert_eq!(count(&eng, &mut ctx, "plain"), big_int(3));
+        assert_eq!(count(&eng, &mut ctx, "keyed"), big_int(3));
+        assert_eq!(count(&eng, &mut ctx, "hashed"), big_int(4));
+        eng.execute_sql("DELETE FROM plain WHERE id = 1", &mut ctx)
+            .expect("delete");
+        assert_eq!(count(&eng, &mut ctx, "plain"), big_int(2));
+        match eng
+            .execute_sql("SELECT COUNT(*) AS n FROM keyed", &mut ctx)
+            .expect("alias")
+        {
+            EngineOutput::ResultSet { columns, rows } => {
+                assert_eq!(columns, vec!["n"]);
+                assert_eq!(rows, big_int(3));
+            }
+            other => panic!("expected ResultSet, got {other:?}"),
+        }
+
+        // Uncommitted rows count for their own session only.
+        let mut writer = SessionContext::default();
+        eng.execute_sql("BEGIN TRANSACTION", &mut writer)
+            .expect("begin");
+        eng.execute_sql("INSERT INTO plain (id) VALUES (4), (5)", &mut writer)
+            .expect("insert");
+        assert_eq!(count(&eng, &mut writer, "plain"), big_int(4));
+        assert_eq!(count(&eng, &mut ctx, "plain"), big_int(2));
+        eng.execute_sql("ROLLBACK", &mut writer).expect("rollback");
+        assert_eq!(count(&eng, &mut ctx, "plain"), big_int(2));
+        eng.execute_sql("BEGIN TRANSACTION", &mut writer)
+            .expect("begin");
+        eng.execute_sql("INSERT INTO plain (id) VALUES (6)", &mut writer)
+            .expect("insert");
+        eng.execute_sql("COMMIT", &mut writer).expect("commit");
+        assert_eq!(count(&eng, &mut ctx, "plain"), big_int(3));
+
+        eng.flush_wal_buffer().expect("flush");
+    }
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("reopen");
+    let mut ctx = SessionContext::default();
+    assert_eq!(count(&eng, &mut ctx, "plain"), big_int(3));
+    assert_eq!(count(&eng, &mut ctx, "keyed"), big_int(3));
+}
+
+#[test]
+fn partial_index_holds_matching_rows_and_serves_implied_queries() {
+    let dir = TempDir::new().expect("tempdir");
+    let rows = |eng: &SqlEngine, sql: &str| match eng
+        .execute_sql(sql, &mut SessionContext::default())
+        .expect(sql)
+    {
+        EngineOutput::ResultSet { rows, .. } => rows,
+        other => panic!("expected rows, got {other:?}"),
+    };
+    let ids = |eng: &SqlEngine, sql: &str| {
+        let mut ids: Vec<String> = rows(eng, sql).into_iter().flatten().collect();
+        ids.sort();
+        ids
+    };
+    let uses_index = |eng: &SqlEngine, filter: &str| {
+        let plan = rows(
+            eng,
+            &format!("EXPLAIN SELECT id FROM orders WHERE {filter}"),
+        );
+        plan.into_iter()
+            .flatten()
+            .any(|line| line.contains("using idx_open"))
+    };
+    // Columns by name: message, problems, rows, status, table_name.
+    let problems = |eng: &SqlEngine| rows(eng, "CHECK TABLE orders")[0][1].clone();
+    const OPEN_OF_7: &str = "SELECT id FROM orders WHERE customer = 7 AND status = 'open'";
+    {
+        let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+        let mut ctx = SessionContext::default();
+        for sql in [
+            "CREATE TABLE orders (id INTEGER PRIMARY KEY, customer INTEGER, status VARCHAR(10))",
+            "INSERT INTO orders (id, customer, status) VALUES (1, 7, 'open'), (2, 7, 'closed')",
+            "CREATE INDEX idx_open ON orders (customer) WHERE status = 'open'",
+            "INSERT INTO orders (id, customer, status) VALUES (3, 8, 'open'), (4, 7, 'closed')",
+        ] {
+            eng.execute_sql(sql, &mut ctx).expect(sql);
+        }
+        assert!(uses_index(&eng, "customer = 7 AND status = 'open'"));
+        assert!(!uses_index(&eng, "customer = 7"));
+        assert!(!uses_index(&eng, "customer = 7 AND status = 'closed'"));
+        assert_eq!(ids(&eng, OPEN_OF_7), vec!["Integer(1)"]);
+        assert_eq!(
+            ids(&eng, "SELECT id FROM orders WHERE customer = 7"),
+            vec!["Integer(1)", "Integer(2)", "Integer(4)"]
+        );
+        assert_eq!(problems(&eng), "BigInt(0)");
+        eng.flush_wal_buffer().expect("flush");
+    }
+    // The predicate is kept in the catalog and the index rebuilt with it.
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("reopen");
+    let mut ctx = SessionContext::default();
+    assert!(uses_index(&eng, "status = 'open' AND customer = 7"));
+    assert_eq!(ids(&eng, OPEN_OF_7), vec!["Integer(1)"]);
+    assert_eq!(problems(&eng), "BigInt(0)");
+
+    // Rows move in and out of the index as they start or stop matching its predicate.
+    for sql in [
+        "UPDATE orders SET status = 'closed' WHERE id = 1",
+        "UPDATE orders SET status = 'open' WHERE id = 2",
+        "DELETE FROM orders WHERE id = 3",
+    ] {
+        eng.execute_sql(sql, &mut ctx).expect(sql);
+    }
+    assert_eq!(ids(&eng, OPEN_OF_7), vec!["Integer(2)"]);
+    assert_eq!(
+        rows(&eng, "SELECT COUNT(*) FROM orders"),
+        vec![vec!["BigInt(3)"]]
+    );
+    assert_eq!(problems(&eng), "BigInt(0)");
+
+    eng.execute_sql(
+        "CREATE INDEX CONCURRENTLY idx_closed ON orders (customer) WHERE status = 'closed'",
+        &mut ctx,
+    )
+    .expect("concurrent partial index");
+    assert_eq!(
+        ids(
+            &eng,
+            "SELECT id FROM orders WHERE customer = 7 AND status = 'closed'"
+        ),
+        vec!["Integer(1)", "Integer(4)"]
+    );
+    assert_eq!(problems(&eng), "BigInt(0)");
+}
+
+#[test]
+fn expression_index_is_maintained_and_matched_by_the_planner() {
+    let dir = TempDir::new().expect("tempdir");
+    let rows = |eng: &SqlEngine, sql: &str| match eng
+        .execute_sql(sql, &mut SessionContext::default())
+        .expect(sql)
+    {
+        EngineOutput::ResultSet { rows, .. } => rows,
+        other => panic!("expected rows, got {other:?}"),
+    };
+    let ids = |eng: &SqlEngine, sql: &str| {
+        let mut ids: Vec<String> = rows(eng, sql).into_iter().flatten().collect();
+        ids.sort();
+        ids
+    };
+    let uses_index = |eng: &SqlEngine, filter: &str| {
+        rows(eng, &format!("EXPLAIN SELECT id FROM users WHERE {filter}"))
+            .into_iter()
+            .flatten()
+            .any(|line| line.contains("using users_lower_email_idx"))
+    };
+    // Columns by name: message, problems, rows, status, table_name.
+    let problems = |eng: &SqlEngine| rows(eng, "CHECK TABLE users")[0][1].clone();
+    const ANN: &str = "SELECT id FROM users WHERE lower(email) = 'ann@x.io'";
+    {
+        let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+        let mut ctx = SessionContext::default();
+        for sql in [
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, email VARCHAR(20))",
+            "INSERT INTO users (id, email) VALUES (1, 'Ann@X.io'), (2, 'bob@x.io')",
+            "CREATE INDEX ON users (LOWER(email))",
+            "INSERT INTO users (id, email) VALUES (3, 'ANN@x.IO')",
+        ] {
+            eng.execute_sql(sql, &mut ctx).expect(sql);
+        }
+        assert!(uses_index(&eng, "lower(email) = 'ann@x.io'"));
+        assert!(uses_index(&eng, "'ann@x.io' = LOWER(users.email)"));
+        assert!(!uses_index(&eng, "upper(email) = 'ANN@X.IO'"));
+        assert!(!uses_index(&eng, "email = 'ann@x.io'"));
+        assert_eq!(ids(&eng, ANN), vec!["Integer(1)", "Integer(3)"]);
+        assert_eq!(problems(&eng), "BigInt(0)");
+        eng.flush_wal_buffer().expect("flush");
+    }
+    // The expression is kept in the catalog and evaluated again when the index is rebuilt.
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("reopen");
+    let mut ctx = SessionContext::default();
+    assert!(uses_index(&eng, "lower(email) = 'ann@x.io'"));
+    assert_eq!(ids(&eng, ANN), vec!["Integer(1)", "Integer(3)"]);
+    assert_eq!(problems(&eng), "BigInt(0)");
+
+    for sql in [
+        "UPDATE users SET email = 'ann@y.io' WHERE id = 1",
+        "UPDATE users SET email = 'Ann@x.io' WHERE id = 2",
+        "DELETE FROM users WHERE id = 3",
+    ] {
+        eng.execute_sql(sql, &mut ctx).expect(sql);
+    }
+    assert_eq!(ids(&eng, ANN), vec!["Integer(2)"]);
+    assert_eq!(problems(&eng), "BigInt(0)");
+
+    let err = eng
+        .execute_sql("CREATE INDEX ON users (lower(name))", &mut ctx)
+        .expect_err("unknown column in key expression");
+    assert!(err.message.contains("users.name"), "{}", err.message);
+}
+
+#[test]
+fn hash_index_serves_full_key_equalities_and_survives_reopen() {
+    let dir = TempDir::new().expect("tempdir");
+    let rows = |eng: &SqlEngine, sql: &str| match eng
+        .execute_sql(sql, &mut SessionContext::default())
+        .expect(sql)
+    {
+        EngineOutput::ResultSet { rows, .. } => rows,
+        other => panic!("expected rows, got {other:?}"),
+    };
+    let ids = |eng: &SqlEngine, sql: &str| {
+        let mut ids: Vec<String> = rows(eng, sql).into_iter().flatten().collect();
+        ids.sort();
+        ids
+    };
+    let uses_index = |eng: &SqlEngine, filter: &str| {
+        rows(eng, &format!("EXPLAIN SELECT id FROM items WHERE {filter}"))
+            .into_iter()
+            .flatten()
+            .any(|line| line.contains("using idx_grp_tag"))
+    };
+    // Columns by name: message, problems, rows, status, table_name.
+    let problems = |eng: &SqlEngine| rows(eng, "CHECK TABLE items")[0][1].clone();
+    let insert = |eng: &SqlEngine, ids: std::ops::RangeInclusive<i64>| {
+        for id in ids {
+            let sql = format!(
+                "INSERT INTO items (id, grp, tag) VALUES ({id}, {}, 't{}')",
+                id % 10,
+                id % 3
+            );
+            eng.execute_sql(&sql, &mut SessionContext::default())
+                .expect(&sql);
+        }
+    };
+    let mut live: Vec<i64> = Vec::new();
+    let expected = |live: &[i64]| {
+        let mut ids: Vec<String> = live
+            .iter()
+            .filter(|id| *id % 10 == 4 && *id % 3 == 1)
+            .map(|id| format!("Integer({id})"))
+            .collect();
+        ids.sort();
+        ids
+    };
+    const GRP_4_T1: &str = "SELECT id FROM items WHERE grp = 4 AND tag = 't1'";
+    {
+        let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+        let mut ctx = SessionContext::default();
+        eng.execute_sql(
+            "CREATE TABLE items (id INTEGER PRIMARY KEY, grp INTEGER, tag VARCHAR(10))",
+            &mut ctx,
+        )
+        .expect("create table");
+        insert(&eng, 1..=300);
+        live.extend(1..=300);
+        eng.execute_sql(
+            "CREATE INDEX idx_grp_tag ON items USING HASH (grp, tag)",
+            &mut ctx,
+        )
+        .expect("create hash index");
+        assert!(uses_index(&eng, "grp = 4 AND tag = 't1'"));
+        // A hash index only answers equalities on its whole key.
+        assert!(!uses_index(&eng, "grp = 4"));
+        assert_eq!(ids(&eng, GRP_4_T1), expected(&live));
+        assert!(eng
+            .execute_sql(
+                "CREATE INDEX CONCURRENTLY idx_tag ON items USING HASH (tag)",
+                &mut ctx,
+            )
+            .is_err());
+
+        // Changes after the checkpoint are redone onto the index on the next open.
+        eng.execute_sql("CHECKPOINT", &mut ctx).expect("checkpoint");
+        insert(&eng, 301..=330);
+        live.extend(301..=330);
+        for sql in [
+            "DELETE FROM items WHERE id = 4",
+            "DELETE FROM items WHERE id = 304",
+        ] {
+            eng.execute_sql(sql, &mut ctx).expect(sql);
+        }
+        live.retain(|id| *id != 4 && *id != 304);
+        assert_eq!(ids(&eng, GRP_4_T1), expected(&live));
+        eng.flush_wal_buffer().expect("flush");
+    }
+    assert!(dir.path().join("items.idx_grp_tag.lhx").exists());
+    {
+        let eng = SqlEngine::open(dir.path().to_path_buf()).expect("reopen");
+        assert!(uses_index(&eng, "tag = 't1' AND grp = 4"));
+        assert_eq!(ids(&eng, GRP_4_T1), expected(&live));
+        assert_eq!(problems(&eng), "BigInt(0)");
+        eng.execute_sql(
+            "DELETE FROM items WHERE id = 34",
+            &mut SessionContext::default(),
+        )
+        .expect("delete");
+        live.retain(|id| *id != 34);
+        insert(&eng, 331..=340);
+        live.extend(331..=340);
+        eng.flush_wal_buffer().expect("flush");
+    }
+    // Closing the engine checkpointed the index.
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("reopen");
+    assert_eq!(ids(&eng, GRP_4_T1), expected(&live));
+    assert_eq!(problems(&eng), "BigInt(0)");
+    eng.execute_sql("DROP TABLE items", &mut SessionContext::default())
+        .expect("drop table");
+    assert!(!dir.path().join("items.idx_grp_tag.lhx").exists());
+}
+
+#[test]
+fn btree_indexes_are_stored_in_pages_and_restored_on_reopen() {
+    let dir = TempDir::new().expect("tempdir");
+    let rows = |eng: &SqlEngine, sql: &str| match eng
+        .execute_sql(sql, &mut SessionContext::default())
+        .expect(sql)
+    {
+        EngineOutput::ResultSet { rows, .. } => rows,
+        other => panic!("expected rows, got {other:?}"),
+    };
+    let run = |eng: &SqlEngine, sql: &str| {
+        eng.execute_sql(sql, &mut SessionContext::default())
+            .expect(sql);
+    };
+    const GRP_3_TO_4: &str = "SELECT id FROM items WHERE grp >= 3 AND grp <= 4";
+    const GRP_4: &str = "SELECT id FROM items WHERE grp = 4";
+    // Rebuilds on open are reported like any index build.
+    const BUILDS: &str = "SELECT * FROM rustdb_stat_progress_create_index";
+    let insert = |eng: &SqlEngine, ids: std::ops::RangeInclusive<i64>| {
+        for id in ids {
+            run(
+                eng,
+                &format!("INSERT INTO items (id, grp) VALUES ({id}, {})", id % 10),
+            );
         }
-        g.execute_sql(
-            "CREATE TABLE cc (k INT PRIMARY KEY, pid INT REFERENCES pp(id))",
-            &mut ctx,
+    };
+    {
+        let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+        run(
+            &eng,
+            "CREATE TABLE items (id INTEGER PRIMARY KEY, grp INTEGER)",
+        );
+        insert(&eng, 1..=200);
+        run(&eng, "CREATE INDEX idx_grp ON items (grp)");
+        run(
+            &eng,
+            "CREATE INDEX CONCURRENTLY idx_id_grp ON items (id, grp)",
+        );
+        run(&eng, "CHECKPOINT");
+        // Changes after the checkpoint are redone from the WAL on the next open.
+        insert(&eng, 201..=230);
+        for id in (104..=194).step_by(10) {
+            run(&eng, &format!("DELETE FROM items WHERE id = {id}"));
+        }
+        assert_eq!(rows(&eng, GRP_3_TO_4).len(), 36);
+        eng.flush_wal_buffer().expect("flush");
+    }
+    assert!(dir.path().join("items.idx_grp.bpt").exists());
+    assert!(dir.path().join("items.idx_id_grp.bpt").exists());
+    {
+        let eng = SqlEngine::open(dir.path().to_path_buf()).expect("reopen");
+        assert_eq!(rows(&eng, GRP_3_TO_4).len(), 36);
+        assert!(rows(&eng, BUILDS).is_empty());
+        assert_eq!(rows(&eng, GRP_4).len(), 13);
+        assert!(rows(&eng, &format!("EXPLAIN {GRP_4}"))
+            .iter()
+            .any(|line| line[0].contains("using idx_grp")));
+        assert_eq!(rows(&eng, "CHECK TABLE items")[0][1], "BigInt(0)");
+        run(&eng, "DELETE FROM items WHERE id = 3");
+        eng.flush_wal_buffer().expect("flush");
+    }
+    // Closing the engine checkpointed the indexes.
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("reopen");
+    assert_eq!(rows(&eng, GRP_3_TO_4).len(), 35);
+    assert!(rows(&eng, BUILDS).is_empty());
+    run(&eng, "DROP TABLE items");
+    assert!(!dir.path().join("items.idx_grp.bpt").exists());
+    assert!(!dir.path().join("items.idx_id_grp.bpt").exists());
+}
+
+#[test]
+fn drop_index_removes_the_index_and_its_files() {
+    let dir = TempDir::new().expect("tempdir");
+    let rows = |eng: &SqlEngine, sql: &str| match eng
+        .execute_sql(sql, &mut SessionContext::default())
+        .expect(sql)
+    {
+        EngineOutput::ResultSet { rows, .. } => rows,
+        other => panic!("expected rows, got {other:?}"),
+    };
+    let run = |eng: &SqlEngine, sql: &str| {
+        eng.execute_sql(sql, &mut SessionContext::default())
+            .expect(sql);
+    };
+    let uses_index = |eng: &SqlEngine, sql: &str| {
+        rows(eng, &format!("EXPLAIN {sql}"))
+            .iter()
+            .any(|line| line[0].contains("using idx_"))
+    };
+    const GRP_4: &str = "SELECT id FROM items WHERE grp = 4";
+    {
+        let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+        run(
+            &eng,
+            "CREATE TABLE items (id INTEGER PRIMARY KEY, grp INTEGER, name VARCHAR(32))",
+        );
+        for id in 1..=50 {
+            run(
+                &eng,
+                &format!(
+                    "INSERT INTO items (id, grp, name) VALUES ({id}, {}, 'n{id}')",
+                    id % 10
+                ),
+            );
+        }
+        run(&eng, "CREATE INDEX idx_grp ON items (grp)");
+        run(&eng, "CREATE INDEX idx_name ON items (name)");
+        assert!(uses_index(&eng, GRP_4));
+        assert!(dir.path().join("items.idx_grp.bpt").exists());
+
+        run(&eng, "DROP INDEX idx_grp ON items");
+        assert!(!dir.path().join("items.idx_grp.bpt").exists());
+        assert!(!uses_index(&eng, GRP_4));
+        assert_eq!(rows(&eng, GRP_4).len(), 5);
+
+        let err = eng
+            .execute_sql("DROP INDEX idx_grp", &mut SessionContext::default())
+            .expect_err("index is gone");
+        assert!(err.message.contains("does not exist"), "{err:?}");
+        run(&eng, "DROP INDEX IF EXISTS idx_grp");
+        // Without ON the index is looked up by name.
+        run(&eng, "DROP INDEX idx_name");
+        assert!(!dir.path().join("items.idx_name.bpt").exists());
+        run(&eng, "CREATE INDEX idx_grp ON items (grp)");
+        run(&eng, "DROP INDEX idx_grp");
+        eng.flush_wal_buffer().expect("flush");
+    }
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("reopen");
+    assert!(!uses_index(&eng, GRP_4));
+    assert_eq!(rows(&eng, GRP_4).len(), 5);
+    assert!(!dir.path().join("items.idx_grp.bpt").exists());
+}
+
+#[test]
+fn size_functions_and_table_status_report_space_per_object() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let rows = |sql: &str| match eng
+        .execute_sql(sql, &mut SessionContext::default())
+        .expect(sql)
+    {
+        EngineOutput::ResultSet { columns, rows } => (columns, rows),
+        other => panic!("expected rows, got {other:?}"),
+    };
+    let run = |sql: &str| {
+        eng.execute_sql(sql, &mut SessionContext::default())
+            .expect(sql);
+    };
+    let bytes = |cell: &str| -> u64 {
+        cell.strip_prefix("BigInt(")
+            .and_then(|c| c.strip_suffix(')'))
+            .and_then(|c| c.parse().ok())
+            .unwrap_or_else(|| panic!("not a size: {cell}"))
+    };
+    run("CREATE TABLE items (id INTEGER PRIMARY KEY, name VARCHAR(64))");
+    for id in 1..=300 {
+        run(&format!(
+            "INSERT INTO items (id, name) VALUES ({id}, 'item number {id}')"
+        ));
+    }
+    run("CREATE INDEX idx_name ON items (name)");
+    run("CHECKPOINT");
+
+    let (columns, sizes) = rows(
+        "SELECT table_size('items') AS heap, index_size('idx_name'), database_size() AS total",
+    );
+    assert_eq!(columns, vec!["heap", "index_size", "total"]);
+    let (heap, index, total) = (
+        bytes(&sizes[0][0]),
+        bytes(&sizes[0][1]),
+        bytes(&sizes[0][2]),
+    );
+    assert!(heap >= 4096, "heap {heap}");
+    assert!(index >= 4096, "index {index}");
+    assert_eq!(total, heap + index);
+
+    let err = eng
+        .execute_sql(
+            "SELECT table_size('missing')",
+            &mut SessionContext::default(),
         )
-        .expect("c");
+        .unwrap_err();
+    assert!(err.message.contains("does not exist"), "{}", err.message);
+    assert!(eng
+        .execute_sql("SELECT index_size(1)", &mut SessionContext::default())
+        .is_err());
+
+    let status = |name: &str| {
+        let (columns, rows) = rows("SHOW TABLE STATUS");
+        assert_eq!(
+            columns,
+            vec![
+                "fragmentation",
+                "free_bytes",
+                "kind",
+                "name",
+                "pages",
+                "size_bytes",
+                "table_name"
+            ]
+        );
+        rows.into_iter()
+            .find(|r| r[3] == format!("Varchar(\"'{name}'\")"))
+            .unwrap_or_else(|| panic!("no status row for {name}"))
+    };
+    let table = status("items");
+    assert_eq!(table[2], "Varchar(\"'table'\")");
+    assert_eq!(bytes(&table[5]), heap);
+    let index_row = status("idx_name");
+    assert_eq!(index_row[2], "Varchar(\"'index'\")");
+    assert_eq!(index_row[6], "Varchar(\"'items'\")");
+    assert_eq!(bytes(&index_row[5]), index);
+    assert!(bytes(&index_row[4]) >= 2);
+
+    // Deleting every other row leaves gaps between the remaining ones.
+    let free_before = bytes(&table[1]);
+    for id in (2..=300).step_by(2) {
+        run(&format!("DELETE FROM items WHERE id = {id}"));
+    }
+    let table = status("items");
+    assert!(bytes(&table[1]) > free_before);
+    assert_ne!(table[0], "Double(0.0)");
+}
+
+#[test]
+fn unique_keys_stay_locked_until_the_writing_transaction_ends() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut a = SessionContext::default();
+    let mut b = SessionContext::default();
+    let run = |ctx: &mut SessionContext, sql: &str| eng.execute_sql(sql, ctx).map(drop);
+    let code = |ctx: &mut SessionContext, sql: &str| eng.execute_sql(sql, ctx).expect_err(sql).code;
+    let count = |ctx: &mut SessionContext| match eng
+        .execute_sql("SELECT COUNT(*) FROM u", ctx)
+        .expect("count")
+    {
+        EngineOutput::ResultSet { rows, .. } => rows[0][0].clone(),
+        other => panic!("expected rows, got {other:?}"),
+    };
+    for sql in [
+        "CREATE TABLE u (id INTEGER PRIMARY KEY, email VARCHAR(20) UNIQUE)",
+        "INSERT INTO u (id, email) VALUES (1, 'a')",
+    ] {
+        run(&mut a, sql).expect(sql);
     }
+
+    // A deleted key stays taken until the delete commits: a rollback brings the row back.
+    run(&mut a, "BEGIN TRANSACTION").unwrap();
+    run(&mut a, "DELETE FROM u WHERE id = 1").unwrap();
+    assert_eq!(
+        code(&mut b, "INSERT INTO u (id, email) VALUES (1, 'b')"),
+        engine_error_code::SERIALIZATION_FAILURE
+    );
+    run(&mut a, "ROLLBACK").unwrap();
+    assert_eq!(
+        code(&mut b, "INSERT INTO u (id, email) VALUES (1, 'b')"),
+        engine_error_code::CONSTRAINT_VIOLATION
+    );
+    assert_eq!(count(&mut b), "BigInt(1)");
+
+    // So is the old value of an updated UNIQUE column.
+    run(&mut a, "BEGIN TRANSACTION").unwrap();
+    run(&mut a, "UPDATE u SET email = 'z' WHERE id = 1").unwrap();
+    assert_eq!(
+        code(&mut b, "INSERT INTO u (id, email) VALUES (2, 'a')"),
+        engine_error_code::SERIALIZATION_FAILURE
+    );
+    run(&mut a, "COMMIT").unwrap();
+    run(&mut b, "INSERT INTO u (id, email) VALUES (2, 'a')").expect("key freed by commit");
+
+    // A key inserted by an open transaction is neither free nor (yet) a violation.
+    run(&mut a, "BEGIN TRANSACTION").unwrap();
+    run(&mut a, "INSERT INTO u (id, email) VALUES (3, 'c')").unwrap();
+    run(&mut b, "BEGIN TRANSACTION").unwrap();
+    assert_eq!(
+        code(&mut b, "INSERT INTO u (id, email) VALUES (4, 'c')"),
+        engine_error_code::SERIALIZATION_FAILURE
+    );
+    run(&mut b, "ROLLBACK").unwrap();
+    run(&mut a, "ROLLBACK").unwrap();
+    run(&mut b, "INSERT INTO u (id, email) VALUES (4, 'c')").expect("key freed by rollback");
+
+    // Of concurrent transactions inserting one key, at most one commits it.
+    let committed = std::sync::atomic::AtomicUsize::new(0);
     std::thread::scope(|s| {
-        for t in 0..4 {
-            let e = eng.clone();
+        for i in 0..8 {
+            let (eng, committed) = (&eng, &committed);
             s.spawn(move || {
                 let mut ctx = SessionContext::default();
-                for n in 0..32 {
-                    let id = t * 10_000 + n;
-                    let pid = (n % 64) + 1;
-                    let sql = format!("INSERT INTO cc (k, pid) VALUES ({id}, {pid})");
-                    e.lock().expect("lock").execute_sql(&sql, &mut ctx).unwrap();
+                eng.execute_sql("BEGIN TRANSACTION", &mut ctx).unwrap();
+                let sql = format!("INSERT INTO u (id, email) VALUES (100, 'x{i}')");
+                let inserted = eng.execute_sql(&sql, &mut ctx).is_ok();
+                std::thread::sleep(std::time::Duration::from_millis(20));
+                let end = if inserted { "COMMIT" } else { "ROLLBACK" };
+                if eng.execute_sql(end, &mut ctx).is_ok() && inserted {
+                    committed.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
                 }
             });
         }
     });
-    let g = eng.lock().expect("lock");
+    assert_eq!(committed.into_inner(), 1);
+    assert_eq!(count(&mut a), "BigInt(4)");
+}
+
+#[test]
+fn table_io_view_and_prometheus_break_io_down_by_table() {
+    let dir = TempDir::new().expect("tempdir");
+    {
+        let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+        let mut ctx = SessionContext::default();
+        for sql in [
+            "CREATE TABLE hot (id INTEGER)",
+            "CREATE TABLE cold (id INTEGER)",
+            "INSERT INTO hot (id) VALUES (1), (2), (3)",
+            "INSERT INTO cold (id) VALUES (1)",
+        ] {
+            eng.execute_sql(sql, &mut ctx).expect(sql);
+        }
+        eng.checkpoint().expect("checkpoint");
+        let stats = eng.table_io_statistics().expect("stats");
+        let tables: Vec<&str> = stats.iter().map(|t| t.table.as_str()).collect();
+        assert_eq!(tables, ["cold", "hot"]);
+        assert!(stats.iter().all(|t| t.io.writes > 0 && t.io.pages > 0));
+    }
+
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("reopen");
     let mut ctx = SessionContext::default();
-    let out = g
-        .execute_sql("SELECT k FROM cc", &mut ctx)
-        .expect("all child rows");
-    match out {
-        EngineOutput::ResultSet { rows, .. } => assert_eq!(rows.len(), 4 * 32),
-        _ => panic!("expected result set"),
+    for _ in 0..3 {
+        eng.execute_sql("SELECT id FROM hot", &mut ctx)
+            .expect("select");
+    }
+    let stats = eng.table_io_statistics().expect("stats");
+    let hot = stats.iter().find(|t| t.table == "hot").expect("hot");
+    assert!(hot.io.reads > 0);
+    assert!(hot.io.cache_hits > 0);
+    assert!(hot.io.hit_ratio() > 0.0 && hot.io.hit_ratio() <= 1.0);
+
+    match eng
+        .execute_sql(
+            "SELECT table_name, reads, cache_hits FROM rustdb_stat_table_io WHERE table_name = 'hot'",
+            &mut ctx,
+        )
+        .expect("view")
+    {
+        EngineOutput::ResultSet { columns, rows } => {
+            assert_eq!(rows.len(), 1);
+            let at = |name: &str| columns.iter().position(|c| c == name).expect(name);
+            assert_eq!(rows[0][at("table_name")], "Varchar(\"'hot'\")");
+            assert_eq!(
+                rows[0][at("cache_hits")],
+                format!("BigInt({})", hot.io.cache_hits)
+            );
+        }
+        other => panic!("expected rows, got {other:?}"),
     }
+
+    let exported = eng.table_io_prometheus().expect("prometheus");
+    assert!(exported.contains("# TYPE rustdb_table_page_reads_total counter"));
+    assert!(exported.contains(&format!(
+        "rustdb_table_buffer_hits_total{{table=\"hot\"}} {}",
+        hot.io.cache_hits
+    )));
 }
 
-#[cfg(rustdb_loom)]
 #[test]
-fn engine_alter_fk_many_inserts_under_contention() {
-    use loom::sync::{Arc, Mutex};
+fn tiering_moves_idle_extents_to_the_cold_tablespace_and_back_out_of_sight() {
+    use crate::common::config::LayeredConfig;
+    use crate::network::sql_engine_wal::TIERING_JOB;
 
-    loom::model(|| {
-        let dir = TempDir::new().expect("tempdir");
-        let eng = Arc::new(Mutex::new(
-            SqlEngine::open(dir.path().to_path_buf()).expect("open"),
-        ));
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    assert!(eng.background_jobs().job_status(TIERING_JOB).is_none());
+    let settings = LayeredConfig::load(
+        None,
+        vec![
+            ("tiering.cold_tablespace".to_string(), "cold".to_string()),
+            ("tiering.cold_after_windows".to_string(), "1".to_string()),
+        ],
+    )
+    .expect("settings");
+    eng.configure(settings).expect("configure");
+    assert!(eng.background_jobs().job_status(TIERING_JOB).is_some());
+
+    let mut ctx = SessionContext::default();
+    eng.execute_sql(
+        "CREATE TABLE events (id INTEGER PRIMARY KEY, payload VARCHAR(1000))",
+        &mut ctx,
+    )
+    .expect("create");
+    let payload = "p".repeat(900);
+    for batch in 0..40 {
+        let values: Vec<String> = (0..20)
+            .map(|i| format!("({}, '{payload}')", batch * 20 + i))
+            .collect();
+        let sql = format!(
+            "INSERT INTO events (id, payload) VALUES {}",
+            values.join(", ")
+        );
+        eng.execute_sql(&sql, &mut ctx).expect("insert");
+    }
+    eng.checkpoint().expect("checkpoint");
+
+    eng.run_tiering().expect("start the access window");
+    eng.run_tiering().expect("demote");
+    let stats = eng.tiering_statistics().expect("stats");
+    let events = stats.iter().find(|t| t.table == "events").expect("events");
+    assert!(events.tiering.cold_extents > 0, "{events:?}");
+    assert!(dir.path().join("cold/events.tbl.cold").is_file());
+
+    match eng
+        .execute_sql("SELECT COUNT(*) FROM events", &mut ctx)
+        .expect("count")
+    {
+        EngineOutput::ResultSet { rows, .. } => assert_eq!(rows[0][0], "BigInt(800)"),
+        other => panic!("expected rows, got {other:?}"),
+    }
+    match eng
+        .execute_sql(
+            "SELECT table_name, cold_extents, extents_demoted FROM rustdb_stat_tiering",
+            &mut ctx,
+        )
+        .expect("view")
+    {
+        EngineOutput::ResultSet { columns, rows } => {
+            let at = |name: &str| columns.iter().position(|c| c == name).expect(name);
+            let row = rows
+                .iter()
+                .find(|r| r[at("table_name")] == "Varchar(\"'events'\")")
+                .expect("events row");
+            assert_eq!(
+                row[at("extents_demoted")],
+                format!("BigInt({})", events.tiering.extents_demoted)
+            );
+        }
+        other => panic!("expected rows, got {other:?}"),
+    }
+
+    eng.execute_sql("DROP TABLE events", &mut ctx)
+        .expect("drop");
+    assert!(!dir.path().join("cold/events.tbl.cold").exists());
+    assert!(!dir.path().join("events.tbl.tiers").exists());
+}
+
+#[test]
+fn auto_analyze_refreshes_statistics_of_tables_with_many_changed_rows() {
+    use crate::common::config::LayeredConfig;
+    use crate::network::sql_engine_wal::AUTO_ANALYZE_JOB;
+
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let settings = LayeredConfig::load(
+        None,
+        vec![
+            ("auto_analyze.threshold".to_string(), "10".to_string()),
+            ("auto_analyze.scale_factor".to_string(), "0.1".to_string()),
+        ],
+    )
+    .expect("settings");
+    eng.configure(settings).expect("configure");
+    assert!(eng.background_jobs().job_status(AUTO_ANALYZE_JOB).is_some());
+
+    let mut ctx = SessionContext::default();
+    eng.execute_sql("CREATE TABLE items (id INTEGER PRIMARY KEY)", &mut ctx)
+        .expect("create");
+    eng.execute_sql("CREATE TABLE quiet (id INTEGER PRIMARY KEY)", &mut ctx)
+        .expect("create");
+    let values: Vec<String> = (0..50).map(|i| format!("({i})")).collect();
+    eng.execute_sql(
+        &format!("INSERT INTO items (id) VALUES {}", values.join(", ")),
+        &mut ctx,
+    )
+    .expect("insert");
+    eng.execute_sql("INSERT INTO quiet (id) VALUES (1)", &mut ctx)
+        .expect("insert");
+    assert_eq!(eng.run_auto_analyze().expect("check"), vec!["items"]);
+
+    let stat = |eng: &SqlEngine, ctx: &mut SessionContext| match eng
+        .execute_sql(
+            "SELECT table_name, rows, auto_analyzed, modified_rows FROM rustdb_stat_tables",
+            ctx,
+        )
+        .expect("view")
+    {
+        EngineOutput::ResultSet { columns, rows } => {
+            let at = |name: &str| columns.iter().position(|c| c == name).expect(name);
+            rows.iter()
+                .find(|r| r[at("table_name")] == "Varchar(\"'items'\")")
+                .map(|r| {
+                    (
+                        r[at("rows")].clone(),
+                        r[at("auto_analyzed")].clone(),
+                        r[at("modified_rows")].clone(),
+                    )
+                })
+                .expect("items row")
+        }
+        other => panic!("expected rows, got {other:?}"),
+    };
+    assert_eq!(
+        stat(&eng, &mut ctx),
+        (
+            "BigInt(50)".to_string(),
+            "Boolean(true)".to_string(),
+            "BigInt(0)".to_string()
+        )
+    );
+
+    // 12 changes stay under 10 + 0.1 * 50.
+    let more = |from: i32, to: i32| {
+        let values: Vec<String> = (from..to).map(|i| format!("({i})")).collect();
+        format!("INSERT INTO items (id) VALUES {}", values.join(", "))
+    };
+    eng.execute_sql(&more(100, 112), &mut ctx).expect("insert");
+    assert!(eng.run_auto_analyze().expect("check").is_empty());
+    assert_eq!(stat(&eng, &mut ctx).2, "BigInt(12)");
+    eng.execute_sql(&more(112, 120), &mut ctx).expect("insert");
+    assert_eq!(eng.run_auto_analyze().expect("check"), vec!["items"]);
+    assert_eq!(stat(&eng, &mut ctx).0, "BigInt(70)");
+
+    // A manual ANALYZE is not marked automatic.
+    eng.execute_sql("ANALYZE items", &mut ctx).expect("analyze");
+    assert_eq!(stat(&eng, &mut ctx).1, "Boolean(false)");
+}
+
+#[test]
+fn analyze_keeps_null_fraction_and_most_common_values() {
+    let dir = TempDir::new().expect("tempdir");
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
+    let mut ctx = SessionContext::default();
+    eng.execute_sql(
+        "CREATE TABLE events (id INTEGER PRIMARY KEY, kind VARCHAR(10))",
+        &mut ctx,
+    )
+    .expect("create");
+    // 6 clicks, 2 views, one each of 'a'/'b' and 10 rows without a kind.
+    let kinds = ["'click'"; 6]
+        .into_iter()
+        .chain(["'view'"; 2])
+        .chain(["'a'", "'b'"])
+        .chain(["NULL"; 10]);
+    let values: Vec<String> = kinds
+        .enumerate()
+        .map(|(i, kind)| format!("({i}, {kind})"))
+        .collect();
+    eng.execute_sql(
+        &format!("INSERT INTO events (id, kind) VALUES {}", values.join(", ")),
+        &mut ctx,
+    )
+    .expect("insert");
+    eng.execute_sql("ANALYZE events", &mut ctx)
+        .expect("analyze");
+
+    match eng
+        .execute_sql(
+            "SELECT column_name, null_frac, most_common_values FROM rustdb_stat_columns \
+             WHERE table_name = 'events'",
+            &mut ctx,
+        )
+        .expect("view")
+    {
+        EngineOutput::ResultSet { columns, rows } => {
+            let at = |name: &str| columns.iter().position(|c| c == name).expect(name);
+            let kind = rows
+                .iter()
+                .find(|r| r[at("column_name")] == "Varchar(\"'kind'\")")
+                .expect("kind row");
+            assert_eq!(kind[at("null_frac")], "Double(0.5)");
+            // Only values above the average of 2.5 rows per value.
+            assert_eq!(
+                kind[at("most_common_values")],
+                "Varchar(\"''click' (0.300)'\")"
+            );
+        }
+        other => panic!("expected rows, got {other:?}"),
+    }
+}
+
+#[test]
+fn cardinality_feedback_corrects_estimates_of_scans_and_filters() {
+    let dir = TempDir::new().expect("tempdir");
+    let explain = |eng: &SqlEngine| {
+        let mut ctx = SessionContext::default();
+        match eng
+            .execute_sql("EXPLAIN SELECT id FROM items WHERE tag > 6", &mut ctx)
+            .expect("explain")
         {
-            let g = eng.lock().unwrap();
-            let mut ctx = SessionContext::default();
-            g.execute_sql("CREATE TABLE pp (id INT PRIMARY KEY)", &mut ctx)
-                .expect("p");
-            for i in 1..=8 {
-                g.execute_sql(&format!("INSERT INTO pp (id) VALUES ({i})"), &mut ctx)
-                    .expect("ip");
+            EngineOutput::ResultSet { rows, .. } => {
+                rows.into_iter().map(|r| r[0].clone()).collect::<Vec<_>>()
             }
-            g.execute_sql(
-                "CREATE TABLE cc (k INT PRIMARY KEY, pid INT REFERENCES pp(id))",
-                &mut ctx,
-            )
-            .expect("c");
+            other => panic!("expected rows, got {other:?}"),
         }
-        let e0 = eng.clone();
-        let e1 = eng.clone();
-        let h0 = loom::thread::spawn(move || {
-            let mut ctx = SessionContext::default();
-            for n in 0..4 {
-                let id = n;
-                let pid = (n % 8) + 1;
-                let sql = format!("INSERT INTO cc (k, pid) VALUES ({id}, {pid})");
-                e0.lock().unwrap().execute_sql(&sql, &mut ctx).unwrap();
-            }
-        });
-        let h1 = loom::thread::spawn(move || {
-            let mut ctx = SessionContext::default();
-            for n in 0..4 {
-                let id = 100 + n;
-                let pid = (n % 8) + 1;
-                let sql = format!("INSERT INTO cc (k, pid) VALUES ({id}, {pid})");
-                e1.lock().unwrap().execute_sql(&sql, &mut ctx).unwrap();
-            }
-        });
-        h0.join().unwrap();
-        h1.join().unwrap();
-        let g = eng.lock().unwrap();
+    };
+    {
+        let eng = SqlEngine::open(dir.path().to_path_buf()).expect("open");
         let mut ctx = SessionContext::default();
-        let out = g.execute_sql("SELECT k FROM cc", &mut ctx).expect("rows");
-        match out {
-            EngineOutput::ResultSet { rows, .. } => assert_eq!(rows.len(), 8),
-            _ => panic!("expected result set"),
-        }
-    });
+        eng.execute_sql("CREATE TABLE items (id INTEGER, tag INTEGER)", &mut ctx)
+            .expect("create");
+        let values: Vec<String> = (0..30).map(|i| format!("({i}, {})", i % 10)).collect();
+        eng.execute_sql(
+            &format!("INSERT INTO items (id, tag) VALUES {}", values.join(", ")),
+            &mut ctx,
+        )
+        .expect("insert");
+        let before = explain(&eng);
+        assert!(before
+            .iter()
+            .any(|l| l.contains("Table Scan on items (cost=1.00 rows=1000)")));
+
+        eng.execute_sql("SELECT id FROM items WHERE tag > 6", &mut ctx)
+            .expect("select");
+        let after = explain(&eng);
+        assert!(after
+            .iter()
+            .any(|l| l.contains("Table Scan on items (cost=1.00 rows=30)")));
+        assert!(after
+            .iter()
+            .any(|l| l.starts_with("Planning:") && l.contains(" rows=9 ")));
+    }
+    // The counts outlive the engine.
+    let eng = SqlEngine::open(dir.path().to_path_buf()).expect("reopen");
+    assert!(dir
+        .path()
+        .join("statistics/cardinality_feedback.json")
+        .is_file());
+    let reopened = explain(&eng);
+    assert!(reopened
+        .iter()
+        .any(|l| l.starts_with("Planning:") && l.contains(" rows=9 ")));
+}
+
+#[test]
+fn database_execute_returns_the_values_behind_result_cells() {
+    let dir = TempDir::new().expect("tempdir");
+    let mut db = crate::Database::open(dir.path().to_str().expect("utf-8")).expect("db");
+    db.execute("CREATE TABLE notes (id INT PRIMARY KEY, body VARCHAR(20), score INT)")
+        .expect("create");
+    db.execute("INSERT INTO notes (id, body, score) VALUES (1, 'NULL', NULL), (2, '42', 7)")
+        .expect("insert");
+
+    let result = db
+        .execute("SELECT id, body, score FROM notes ORDER BY id")
+        .expect("select");
+    assert_eq!(result.columns, vec!["body", "id", "score"]);
+    assert_eq!(
+        result.rows,
+        vec![
+            vec![
+                DataType::Varchar("NULL".to_string()),
+                DataType::Integer(1),
+                DataType::Null,
+            ],
+            vec![
+                DataType::Varchar("42".to_string()),
+                DataType::Integer(2),
+                DataType::Integer(7),
+            ],
+        ]
+    );
+
+    // Literals without FROM, answered from a cache the second time.
+    for _ in 0..2 {
+        let result = db.execute("SELECT 'NULL', '42', 42").expect("literals");
+        assert_eq!(
+            result.rows,
+            vec![vec![
+                DataType::Varchar("NULL".to_string()),
+                DataType::Varchar("42".to_string()),
+                DataType::Integer(42),
+            ]]
+        );
+    }
+
+    let mut stmt = db
+        .prepare("SELECT body FROM notes WHERE id = $1")
+        .expect("prepare");
+    let result = stmt
+        .execute_with_params(&[ColumnValue::new(DataType::Integer(1))])
+        .expect("execute");
+    assert_eq!(
+        result.rows,
+        vec![vec![DataType::Varchar("NULL".to_string())]]
+    );
 }
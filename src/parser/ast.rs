@@ -18,18 +18,79 @@ pub enum SqlStatement {
     Delete(DeleteStatement),
     /// CREATE TABLE operation
     CreateTable(CreateTableStatement),
+    /// CREATE FOREIGN TABLE operation
+    CreateForeignTable(CreateForeignTableStatement),
     /// CREATE INDEX operation
     CreateIndex(CreateIndexStatement),
+    /// CREATE DATABASE <name> [TEMPLATE <database>] (a sibling data directory, optionally a copy
+    /// of another database)
+    CreateDatabase {
+        name: String,
+        template: Option<String>,
+    },
+    /// CREATE TENANT <name> [WITH (<quota> = <n>, ...)] (an owner of tables with its own keys
+    /// and quotas)
+    CreateTenant {
+        name: String,
+        quotas: Vec<TenantQuota>,
+    },
+    /// ALTER TENANT <name> SET (<quota> = { <n> | NONE }, ...)
+    AlterTenant {
+        name: String,
+        quotas: Vec<TenantQuota>,
+    },
+    /// DROP TENANT [IF EXISTS] <name> (the tenant's tables, files and keys)
+    DropTenant { name: String, if_exists: bool },
     /// ALTER TABLE operation
     AlterTable(AlterTableStatement),
     /// DROP TABLE operation
     DropTable(DropTableStatement),
+    /// DROP INDEX operation
+    DropIndex(DropIndexStatement),
     /// BEGIN TRANSACTION
     BeginTransaction,
     /// COMMIT TRANSACTION
     CommitTransaction,
     /// ROLLBACK TRANSACTION
     RollbackTransaction,
+    /// PREPARE TRANSACTION 'gid' (first phase of a two-phase commit)
+    PrepareTransaction(String),
+    /// COMMIT PREPARED 'gid'
+    CommitPrepared(String),
+    /// ROLLBACK PREPARED 'gid'
+    RollbackPrepared(String),
+    /// EXPORT SNAPSHOT (returns an id other sessions can import)
+    ExportSnapshot,
+    /// SET TRANSACTION SNAPSHOT 'id'
+    SetTransactionSnapshot(String),
+    /// SET <parameter> { = | TO } <value> (server configuration, see [`crate::common::config`])
+    SetParameter { name: String, value: String },
+    /// SHOW <parameter>, or SHOW ALL (`None`)
+    ShowParameter(Option<String>),
+    /// CHECKPOINT (flush dirty pages and write a WAL checkpoint)
+    Checkpoint,
+    /// FLUSH LOGS (flush the WAL and start a new log segment)
+    FlushLogs,
+    /// SHOW ENGINE STATUS (durability, WAL and checkpoint counters)
+    ShowEngineStatus,
+    /// SHOW TABLE STATUS (size, pages, free space and fragmentation of every table and index)
+    ShowTableStatus,
+    /// FORGET <subject literal> (delete the subject's rows of `ENCRYPT BY` tables and destroy
+    /// its key)
+    Forget(Literal),
+    /// VACUUM [table] (overwrite what deleted rows left in table files; all tables with
+    /// forgotten rows when no table is named)
+    Vacuum(Option<String>),
+    /// CHECK TABLE [table] (verify that rows decode and match the table's indexes; all tables
+    /// when no table is named)
+    CheckTable(Option<String>),
+    /// ANALYZE [table] (collect row and column statistics; all tables when no table is named)
+    Analyze(Option<String>),
+    /// FLASHBACK TABLE <table> TO TIMESTAMP '<timestamp>' (rewrite the table's rows to those
+    /// committed at that instant)
+    FlashbackTable { table: String, timestamp: String },
+    /// PROFILE CPU FOR <n> SECONDS [FORMAT { FLAMEGRAPH | PPROF }] (sample the server's stacks)
+    ProfileCpu { seconds: u64, format: ProfileFormat },
     /// PREPARE statement
     Prepare(PrepareStatement),
     /// EXECUTE prepared statement
@@ -40,10 +101,20 @@ pub enum SqlStatement {
     /// Note: currently represented as a single binary operation at the AST level.
     SetOperation(Box<SetOperationStatement>),
 
-    /// `EXPLAIN [ANALYZE] [VERBOSE] <statement>` — show (and optionally run) query plan.
+    /// `EXPLAIN [ANALYZE] [VERBOSE] [FORMAT TEXT|JSON|DOT] <statement>` — show (and optionally
+    /// run) query plan.
     Explain(ExplainStatement),
 }
 
+/// `<quota> = { <n> | NONE }` of `CREATE TENANT ... WITH` and `ALTER TENANT ... SET`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TenantQuota {
+    /// Quota name, lowercase (`queries_per_second`, `max_rows_read`, `max_temp_bytes`)
+    pub name: String,
+    /// Limit; `None` (`NONE`) removes it
+    pub limit: Option<u64>,
+}
+
 /// `EXPLAIN` wrapper around a single explainable statement.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ExplainStatement {
@@ -51,9 +122,33 @@ pub struct ExplainStatement {
     pub analyze: bool,
     /// When true, include optimizer messages in the plan output.
     pub verbose: bool,
+    /// Output format (`FORMAT ...`).
+    #[serde(default)]
+    pub format: ExplainFormat,
     pub statement: Box<SqlStatement>,
 }
 
+/// Output of `EXPLAIN`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExplainFormat {
+    /// Indented plan lines (the default)
+    #[default]
+    Text,
+    /// The plan tree as one JSON document
+    Json,
+    /// The plan tree as one Graphviz DOT graph
+    Dot,
+}
+
+/// Output of `PROFILE CPU`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProfileFormat {
+    /// Flamegraph SVG (the default)
+    Flamegraph,
+    /// pprof protobuf
+    Pprof,
+}
+
 /// Set operation statement: `<left> (UNION|INTERSECT|EXCEPT) [ALL] <right>`.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SetOperationStatement {
@@ -122,12 +217,24 @@ pub struct FromClause {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TableReference {
     /// Simple table
-    Table { name: String, alias: Option<String> },
+    Table {
+        name: String,
+        alias: Option<String>,
+        /// `AS OF TIMESTAMP '<timestamp>'`: the table as it was committed at that instant
+        #[serde(default)]
+        as_of: Option<String>,
+    },
     /// Subquery
     Subquery {
         query: Box<SelectStatement>,
         alias: String,
     },
+    /// Set-returning function, e.g. `index_advisor()`
+    Function {
+        name: String,
+        args: Vec<Expression>,
+        alias: Option<String>,
+    },
 }
 
 /// JOIN operation
@@ -156,6 +263,10 @@ pub enum JoinType {
 pub struct OrderByItem {
     pub expr: Expression,
     pub direction: OrderDirection,
+    /// `NULLS FIRST` / `NULLS LAST`; by default NULLs sort as the largest values (last
+    /// ascending, first descending)
+    #[serde(default)]
+    pub nulls: Option<NullsOrder>,
 }
 
 /// Sort direction
@@ -165,6 +276,13 @@ pub enum OrderDirection {
     Desc,
 }
 
+/// Place of NULLs in a sort order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NullsOrder {
+    First,
+    Last,
+}
+
 /// INSERT operation
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct InsertStatement {
@@ -211,14 +329,85 @@ pub struct CreateTableStatement {
     pub columns: Vec<ColumnDefinition>,
     pub constraints: Vec<TableConstraint>,
     pub if_not_exists: bool,
+    /// `ENGINE <name>` clause (`heap` when absent)
+    #[serde(default)]
+    pub engine: Option<String>,
+    /// `ENCRYPT BY <column>` clause: rows are sealed with the key of their subject (the value of
+    /// `column`)
+    #[serde(default)]
+    pub encrypt_by: Option<String>,
 }
 
-/// CREATE INDEX operation: CREATE INDEX index_name ON table_name (col1, col2, ...)
+/// CREATE FOREIGN TABLE operation:
+/// CREATE FOREIGN TABLE name (col type, ...) USING adapter [OPTIONS (key 'value', ...)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateForeignTableStatement {
+    pub table_name: String,
+    pub columns: Vec<ColumnDefinition>,
+    /// Adapter name (`csv`, `parquet`)
+    pub adapter: String,
+    /// `OPTIONS` pairs in declaration order (keys lowercased, values unquoted)
+    pub options: Vec<(String, String)>,
+}
+
+/// Access method of an index (`USING BTREE` / `USING HASH`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IndexMethod {
+    /// Ordered B+ tree: equality, prefix and range lookups
+    #[default]
+    BTree,
+    /// Persistent linear hash index: equality on the whole key only
+    Hash,
+}
+
+/// CREATE INDEX operation: CREATE INDEX [CONCURRENTLY] [index_name] ON table_name
+/// [USING BTREE | HASH] (col1, expr, ...) [WHERE predicate]
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CreateIndexStatement {
     pub index_name: String,
     pub table_name: String,
+    /// Access method (`USING ...`, B+ tree by default)
+    #[serde(default)]
+    pub method: IndexMethod,
+    /// Key parts: column names, and [`Expression::index_key`] for computed parts
     pub columns: Vec<String>,
+    /// Computed key parts (`lower(email)`) by their name in `columns`
+    #[serde(default)]
+    pub expressions: Vec<(String, Expression)>,
+    /// `CREATE INDEX CONCURRENTLY`: build without blocking writes to the table
+    #[serde(default)]
+    pub concurrently: bool,
+    /// `WHERE` predicate of a partial index (only matching rows are indexed)
+    #[serde(default)]
+    pub where_clause: Option<Expression>,
+}
+
+impl CreateIndexStatement {
+    /// Table columns the index key reads: the plain key columns and those in key expressions
+    pub fn key_table_columns(&self) -> Vec<&str> {
+        fn collect<'a>(expr: &'a Expression, out: &mut Vec<&'a str>) {
+            match expr {
+                Expression::Identifier(c) | Expression::QualifiedIdentifier { column: c, .. } => {
+                    out.push(c)
+                }
+                Expression::BinaryOp { left, right, .. } => {
+                    collect(left, out);
+                    collect(right, out);
+                }
+                Expression::UnaryOp { expr, .. } => collect(expr, out),
+                Expression::Function { args, .. } => args.iter().for_each(|a| collect(a, out)),
+                _ => {}
+            }
+        }
+        let mut out = Vec::new();
+        for col in &self.columns {
+            match self.expressions.iter().find(|(key, _)| key == col) {
+                Some((_, expr)) => collect(expr, &mut out),
+                None => out.push(col),
+            }
+        }
+        out
+    }
 }
 
 /// Column definition
@@ -312,6 +501,15 @@ pub struct DropTableStatement {
     pub cascade: bool,
 }
 
+/// `DROP INDEX [IF EXISTS] <index> [ON <table>]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DropIndexStatement {
+    pub index_name: String,
+    /// Table of the index; looked up by index name when omitted
+    pub table_name: Option<String>,
+    pub if_exists: bool,
+}
+
 /// SQL expression
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Expression {
@@ -361,6 +559,62 @@ pub enum Expression {
         pattern: Box<Expression>,
         negated: bool,
     },
+    /// Parameter placeholder of a prepared statement (`$n`, or the n-th `?`), 1-based
+    Parameter(usize),
+}
+
+impl Expression {
+    /// Canonical text of an indexed expression, e.g. `lower(email)`: function names lowercase,
+    /// column qualifiers dropped. It names the key part of an expression index and is how the
+    /// planner matches query expressions to it.
+    pub fn index_key(&self) -> String {
+        match self {
+            Expression::Literal(Literal::Null) => "NULL".to_string(),
+            Expression::Literal(Literal::Boolean(b)) => b.to_string(),
+            Expression::Literal(Literal::Integer(n)) => n.to_string(),
+            Expression::Literal(Literal::Float(f)) => format!("{f:?}"),
+            Expression::Literal(Literal::String(s)) => s.clone(),
+            Expression::Identifier(column) | Expression::QualifiedIdentifier { column, .. } => {
+                column.clone()
+            }
+            Expression::BinaryOp { left, op, right } => {
+                let op = match op {
+                    BinaryOperator::Add => "+",
+                    BinaryOperator::Subtract => "-",
+                    BinaryOperator::Multiply => "*",
+                    BinaryOperator::Divide => "/",
+                    BinaryOperator::Modulo => "%",
+                    BinaryOperator::Equal => "=",
+                    BinaryOperator::NotEqual => "<>",
+                    BinaryOperator::LessThan => "<",
+                    BinaryOperator::LessThanOrEqual => "<=",
+                    BinaryOperator::GreaterThan => ">",
+                    BinaryOperator::GreaterThanOrEqual => ">=",
+                    BinaryOperator::And => "AND",
+                    BinaryOperator::Or => "OR",
+                    BinaryOperator::Concat => "||",
+                };
+                format!("({} {op} {})", left.index_key(), right.index_key())
+            }
+            Expression::UnaryOp { op, expr } => {
+                let op = match op {
+                    UnaryOperator::Plus => "+",
+                    UnaryOperator::Minus => "-",
+                    UnaryOperator::Not => "NOT ",
+                };
+                format!("({op}{})", expr.index_key())
+            }
+            Expression::Function { name, args } => format!(
+                "{}({})",
+                name.to_lowercase(),
+                args.iter()
+                    .map(Expression::index_key)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            other => format!("{other:?}"),
+        }
+    }
 }
 
 /// Literal
@@ -460,6 +714,7 @@ impl AstBuilder {
             table: TableReference::Table {
                 name: table,
                 alias: None,
+                as_of: None,
             },
             joins: Vec::new(),
         });
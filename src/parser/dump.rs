@@ -0,0 +1,1420 @@
+//! Dump ingestion compatibility mode
+//!
+//! `mysqldump` and `pg_dump` output leans on syntax the strict [`crate::parser::SqlParser`]
+//! rejects: table options (`ENGINE=InnoDB`, `AUTO_INCREMENT=`, `DEFAULT CHARSET=`), column
+//! attributes (`AUTO_INCREMENT`, `COMMENT '..'`, `COLLATE`), session `SET` statements,
+//! ownership and sequence DDL, backtick or schema-qualified names, vendor type names and
+//! `COPY ... FROM stdin` data blocks. [`translate_dump`] rewrites such a script into
+//! statements the regular parser accepts, dropping or mapping each unsupported clause and
+//! recording a [`DumpWarning`] for it. Anything it does not recognise is passed through
+//! unchanged, so genuine errors still surface from the parser with the statement's line.
+
+use crate::common::{Error, Result};
+use crate::parser::lexer::Lexer;
+use crate::parser::quoting::identifier_text;
+use crate::parser::token::{Token, TokenType};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Rows folded into one `INSERT` when translating a `COPY` block
+const COPY_BATCH_ROWS: usize = 500;
+
+/// Source of a dump script
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DumpDialect {
+    /// Pick from the script: backticks or `/*!` version comments mean MySQL
+    #[default]
+    Auto,
+    /// `mysqldump` / MariaDB: backslash escapes in strings, `#` comments
+    MySql,
+    /// `pg_dump`: standard strings, `E'..'` escapes, dollar quoting, `COPY` blocks
+    Postgres,
+}
+
+impl DumpDialect {
+    /// Parses `auto`, `mysql` (`mariadb`) or `postgres` (`postgresql`, `pg`)
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "mysql" | "mariadb" => Ok(Self::MySql),
+            "postgres" | "postgresql" | "pg" => Ok(Self::Postgres),
+            other => Err(Error::validation(format!(
+                "Unknown dump dialect '{}' (expected auto, mysql or postgres)",
+                other
+            ))),
+        }
+    }
+
+    /// Resolves [`Self::Auto`] against the script text
+    pub fn resolve(self, script: &str) -> Self {
+        match self {
+            Self::Auto if script.contains('`') || script.contains("/*!") => Self::MySql,
+            Self::Auto => Self::Postgres,
+            other => other,
+        }
+    }
+}
+
+/// A clause or statement that was ignored or mapped to something the engine supports
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DumpWarning {
+    /// 1-based line of the statement in the dump
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for DumpWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// One translated statement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DumpStatement {
+    /// 1-based line of the source statement in the dump
+    pub line: usize,
+    /// SQL accepted by the strict parser
+    pub sql: String,
+}
+
+/// Result of [`translate_dump`]
+#[derive(Debug, Clone, Default)]
+pub struct DumpScript {
+    /// Dialect the script was read as (never [`DumpDialect::Auto`])
+    pub dialect: DumpDialect,
+    pub statements: Vec<DumpStatement>,
+    pub warnings: Vec<DumpWarning>,
+}
+
+/// Rewrites a `mysqldump` / `pg_dump` script into statements the strict parser accepts
+pub fn translate_dump(script: &str, dialect: DumpDialect) -> Result<DumpScript> {
+    let dialect = dialect.resolve(script);
+    let mut translator = Translator {
+        out: DumpScript {
+            dialect,
+            ..Default::default()
+        },
+        table_columns: HashMap::new(),
+        dropped_schemas: HashSet::new(),
+    };
+    let raw = split_script(script, dialect, &mut translator.out.warnings);
+    for statement in raw {
+        translator.statement(statement)?;
+    }
+    Ok(translator.out)
+}
+
+/// Statement text after comment removal and string/identifier normalization
+#[derive(Debug)]
+struct RawStatement {
+    line: usize,
+    text: String,
+    /// `COPY ... FROM stdin` data lines with their line numbers
+    copy_rows: Option<Vec<(usize, String)>>,
+}
+
+/// Splits a script on `;`, dropping comments and psql meta-commands.
+///
+/// String literals are re-emitted in the lexer's backslash-escape form (`''` becomes `\'`)
+/// and quoted identifiers (`"name"`, `` `name` ``) lose their quotes when they are plain
+/// non-keyword names.
+fn split_script(
+    script: &str,
+    dialect: DumpDialect,
+    warnings: &mut Vec<DumpWarning>,
+) -> Vec<RawStatement> {
+    let chars: Vec<char> = script.chars().collect();
+    let mut statements = Vec::new();
+    let mut text = String::new();
+    let mut start_line = 1;
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        if c == '\n' {
+            line += 1;
+            text.push(c);
+            i += 1;
+            continue;
+        }
+        let at_start = text.trim().is_empty();
+        if at_start && c == '\\' {
+            // psql meta-command (`\connect db`, `\restrict key`) runs to the end of the line
+            let end = line_end(&chars, i);
+            let command: String = chars[i..end].iter().collect();
+            warnings.push(DumpWarning {
+                line,
+                message: format!(
+                    "skipped psql meta-command {}",
+                    command.split_whitespace().next().unwrap_or("\\")
+                ),
+            });
+            i = end;
+            continue;
+        }
+        if (c == '-' && next == Some('-')) || (c == '#' && dialect == DumpDialect::MySql) {
+            i = line_end(&chars, i);
+            text.push(' ');
+            continue;
+        }
+        if c == '/' && next == Some('*') {
+            // Includes MySQL `/*!40101 SET ... */` version comments, which only carry
+            // session settings and `DISABLE KEYS` hints.
+            let mut j = i + 2;
+            while j < chars.len() && !(chars[j] == '*' && chars.get(j + 1) == Some(&'/')) {
+                if chars[j] == '\n' {
+                    line += 1;
+                }
+                j += 1;
+            }
+            i = (j + 2).min(chars.len());
+            text.push(' ');
+            continue;
+        }
+        if at_start && !c.is_whitespace() {
+            start_line = line;
+        }
+        match c {
+            '\'' => {
+                let escapes = dialect == DumpDialect::MySql || take_escape_prefix(&mut text);
+                i = read_string(&chars, i, escapes, &mut text, &mut line);
+            }
+            '"' | '`' => {
+                let (name, end) = read_delimited(&chars, i, &mut line);
+                text.push_str(&identifier_text(&name));
+                i = end;
+            }
+            '$' if dialect == DumpDialect::Postgres => {
+                i = read_dollar_quoted(&chars, i, &mut text, &mut line);
+            }
+            ';' => {
+                i += 1;
+                let body = text.trim();
+                if !body.is_empty() {
+                    let mut statement = RawStatement {
+                        line: start_line,
+                        text: body.to_string(),
+                        copy_rows: None,
+                    };
+                    if is_copy_from_stdin(body) {
+                        let (rows, end) = read_copy_rows(&chars, i, &mut line);
+                        statement.copy_rows = Some(rows);
+                        i = end;
+                    }
+                    statements.push(statement);
+                }
+                text.clear();
+            }
+            _ => {
+                text.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    let body = text.trim();
+    if !body.is_empty() {
+        statements.push(RawStatement {
+            line: start_line,
+            text: body.to_string(),
+            copy_rows: None,
+        });
+    }
+    statements
+}
+
+/// Index of the `\n` ending the line that contains `from` (or the end of input)
+fn line_end(chars: &[char], from: usize) -> usize {
+    chars[from..]
+        .iter()
+        .position(|c| *c == '\n')
+        .map_or(chars.len(), |p| from + p)
+}
+
+/// Strips a PostgreSQL `E` prefix (`E'a\nb'`) from `text`; returns whether one was present
+fn take_escape_prefix(text: &mut String) -> bool {
+    let mut rev = text.chars().rev();
+    let is_prefix = matches!(rev.next(), Some('E' | 'e'))
+        && !rev.next().is_some_and(|c| c.is_alphanumeric() || c == '_');
+    if is_prefix {
+        text.pop();
+    }
+    is_prefix
+}
+
+/// Copies the string literal at `start` into `out`; returns the index after it
+fn read_string(
+    chars: &[char],
+    start: usize,
+    escapes: bool,
+    out: &mut String,
+    line: &mut usize,
+) -> usize {
+    out.push('\'');
+    let mut j = start + 1;
+    while j < chars.len() {
+        let ch = chars[j];
+        if ch == '\n' {
+            *line += 1;
+        }
+        if ch == '\\' {
+            if escapes {
+                out.push('\\');
+                if let Some(&escaped) = chars.get(j + 1) {
+                    if escaped == '\n' {
+                        *line += 1;
+                    }
+                    out.push(escaped);
+                }
+                j += 2;
+            } else {
+                out.push_str("\\\\");
+                j += 1;
+            }
+            continue;
+        }
+        if ch == '\'' {
+            if chars.get(j + 1) == Some(&'\'') {
+                out.push_str("\\'");
+                j += 2;
+                continue;
+            }
+            out.push('\'');
+            return j + 1;
+        }
+        out.push(ch);
+        j += 1;
+    }
+    j
+}
+
+/// Reads a `"..."` or `` `...` `` identifier (doubled delimiters escape themselves)
+fn read_delimited(chars: &[char], start: usize, line: &mut usize) -> (String, usize) {
+    let quote = chars[start];
+    let mut name = String::new();
+    let mut j = start + 1;
+    while j < chars.len() {
+        let ch = chars[j];
+        if ch == quote {
+            if chars.get(j + 1) == Some(&quote) {
+                name.push(quote);
+                j += 2;
+                continue;
+            }
+            return (name, j + 1);
+        }
+        if ch == '\n' {
+            *line += 1;
+        }
+        name.push(ch);
+        j += 1;
+    }
+    (name, j)
+}
+
+/// Copies a `$tag$ ... $tag$` body verbatim (function bodies hide `;` this way)
+fn read_dollar_quoted(chars: &[char], start: usize, out: &mut String, line: &mut usize) -> usize {
+    let mut j = start + 1;
+    while j < chars.len() && (chars[j].is_alphanumeric() || chars[j] == '_') {
+        j += 1;
+    }
+    if chars.get(j) != Some(&'$') || chars.get(start + 1).is_some_and(|c| c.is_ascii_digit()) {
+        out.push('$');
+        return start + 1;
+    }
+    let tag = &chars[start..=j];
+    let mut k = j + 1;
+    while k < chars.len() && !chars[k..].starts_with(tag) {
+        k += 1;
+    }
+    let end = (k + tag.len()).min(chars.len());
+    for ch in &chars[start..end] {
+        if *ch == '\n' {
+            *line += 1;
+        }
+        out.push(*ch);
+    }
+    end
+}
+
+fn is_copy_from_stdin(body: &str) -> bool {
+    let words: Vec<String> = body
+        .split_whitespace()
+        .map(str::to_ascii_uppercase)
+        .collect();
+    words.first().is_some_and(|w| w == "COPY")
+        && words.windows(2).any(|w| w[0] == "FROM" && w[1] == "STDIN")
+}
+
+/// Reads `COPY` data lines after the statement's `;` up to the `\.` terminator
+fn read_copy_rows(chars: &[char], from: usize, line: &mut usize) -> (Vec<(usize, String)>, usize) {
+    let mut rows = Vec::new();
+    // The rest of the COPY line itself is not data.
+    let mut i = line_end(chars, from);
+    while i < chars.len() {
+        i += 1;
+        *line += 1;
+        let end = line_end(chars, i);
+        let row: String = chars[i..end].iter().collect();
+        let row = row.trim_end_matches('\r');
+        i = end;
+        if row == "\\." {
+            break;
+        }
+        rows.push((*line, row.to_string()));
+    }
+    (rows, i)
+}
+
+/// How a `COPY` field is rendered as a SQL literal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValueKind {
+    Numeric,
+    Boolean,
+    Text,
+    /// Column type unknown (table created outside the dump): numbers stay numbers
+    Unknown,
+}
+
+/// A vendor type mapped onto a parser type
+struct MappedType {
+    sql: String,
+    kind: ValueKind,
+    /// Tokens the source type spans
+    consumed: usize,
+    /// Whether the mapping loses information (precision, enum domain, sequence)
+    lossy: bool,
+}
+
+struct Translator {
+    out: DumpScript,
+    /// Column names and kinds of tables created earlier in the dump (for `COPY`)
+    table_columns: HashMap<String, Vec<(String, ValueKind)>>,
+    dropped_schemas: HashSet<String>,
+}
+
+impl Translator {
+    fn statement(&mut self, raw: RawStatement) -> Result<()> {
+        let line = raw.line;
+        let mut tokens = Lexer::new(&raw.text)
+            .and_then(|mut lexer| lexer.tokenize())
+            .map_err(|e| Error::parser(format!("line {}: {}", line, e)))?;
+        tokens.retain(|t| t.token_type != TokenType::Eof);
+        if tokens.is_empty() {
+            return Ok(());
+        }
+        if let Some(what) = skipped_statement(&tokens) {
+            self.warn(line, format!("skipped {} statement", what));
+            return Ok(());
+        }
+        if !is_word(&tokens[0], "SELECT") {
+            self.strip_schema_qualifiers(line, &mut tokens);
+        }
+        strip_casts(&mut tokens);
+
+        match word(&tokens, 0).as_str() {
+            "COPY" => match raw.copy_rows {
+                Some(rows) => self.copy(line, &tokens, rows),
+                None => {
+                    self.emit(line, render(&pieces(&tokens)));
+                    Ok(())
+                }
+            },
+            "CREATE" => {
+                self.create(line, &tokens);
+                Ok(())
+            }
+            "ALTER" => {
+                self.alter(line, &tokens);
+                Ok(())
+            }
+            "START" | "BEGIN"
+                if tokens.len() == 1
+                    || (tokens.len() == 2
+                        && matches!(word(&tokens, 1).as_str(), "TRANSACTION" | "WORK")) =>
+            {
+                self.emit(line, "BEGIN TRANSACTION".to_string());
+                Ok(())
+            }
+            "INSERT" => {
+                self.insert(line, tokens);
+                Ok(())
+            }
+            _ => {
+                self.emit(line, render(&pieces(&tokens)));
+                Ok(())
+            }
+        }
+    }
+
+    fn emit(&mut self, line: usize, sql: String) {
+        self.out.statements.push(DumpStatement { line, sql });
+    }
+
+    fn warn(&mut self, line: usize, message: String) {
+        self.out.warnings.push(DumpWarning { line, message });
+    }
+
+    /// `public.users` -> `users` (RustDB has a single namespace)
+    fn strip_schema_qualifiers(&mut self, line: usize, tokens: &mut Vec<Token>) {
+        let mut i = 0;
+        while i + 2 < tokens.len() {
+            if tokens[i].token_type == TokenType::Identifier
+                && tokens[i + 1].token_type == TokenType::Dot
+                && is_name(&tokens[i + 2])
+            {
+                let schema = tokens[i].value.clone();
+                tokens.drain(i..i + 2);
+                if self.dropped_schemas.insert(schema.clone()) {
+                    self.warn(line, format!("dropped schema qualifier '{}'", schema));
+                }
+            }
+            i += 1;
+        }
+    }
+
+    fn create(&mut self, line: usize, tokens: &[Token]) {
+        match word(tokens, 1).as_str() {
+            "TABLE" | "TEMPORARY" | "TEMP" | "UNLOGGED" => self.create_table(line, tokens),
+            "INDEX" => self.create_index(line, tokens, false),
+            "UNIQUE" if word(tokens, 2) == "INDEX" => self.create_index(line, tokens, true),
+            _ => self.emit(line, render(&pieces(tokens))),
+        }
+    }
+
+    fn create_table(&mut self, line: usize, tokens: &[Token]) {
+        let mut ignored = Vec::new();
+        let mut mapped = Vec::new();
+        let mut i = 1;
+        if word(tokens, i) != "TABLE" {
+            ignored.push(word(tokens, i));
+            i += 1;
+        }
+        if word(tokens, i) != "TABLE" {
+            return self.emit(line, render(&pieces(tokens)));
+        }
+        i += 1;
+        if word(tokens, i) == "IF"
+            && word(tokens, i + 1) == "NOT"
+            && word(tokens, i + 2) == "EXISTS"
+        {
+            ignored.push("IF NOT EXISTS".to_string());
+            i += 3;
+        }
+        let (Some(name), Some(open)) = (tokens.get(i), tokens.get(i + 1)) else {
+            return self.emit(line, render(&pieces(tokens)));
+        };
+        let Some(close) = (open.token_type == TokenType::LeftParen)
+            .then(|| matching_paren(tokens, i + 1))
+            .flatten()
+        else {
+            // `CREATE TABLE t AS SELECT ...` and other shapes go to the parser as-is.
+            return self.emit(line, render(&pieces(tokens)));
+        };
+        let table = name.value.clone();
+
+        let mut definitions = Vec::new();
+        let mut indexes = Vec::new();
+        let mut columns = Vec::new();
+        for item in split_top_level(&tokens[i + 2..close]) {
+            if item.is_empty() {
+                continue;
+            }
+            match word(item, 0).as_str() {
+                "PRIMARY" | "UNIQUE" | "FOREIGN" | "CHECK" | "CONSTRAINT" => {
+                    definitions.push(constraint(item, &mut ignored));
+                }
+                "KEY" | "INDEX" | "FULLTEXT" | "SPATIAL" => {
+                    match inline_index(&table, item, &mut ignored) {
+                        Some((index, sql)) => {
+                            mapped.push(format!("{} as index {}", key_label(item), index));
+                            indexes.push(sql);
+                        }
+                        None => ignored.push(key_label(item)),
+                    }
+                }
+                _ => {
+                    let (sql, column) = column_definition(item, &mut ignored, &mut mapped);
+                    definitions.push(sql);
+                    columns.push(column);
+                }
+            }
+        }
+
+        let (engine, options) = table_options(&tokens[close + 1..]);
+        if !options.is_empty() {
+            ignored.push(options);
+        }
+        let engine = engine.map_or(String::new(), |e| format!(" ENGINE {}", e));
+        self.emit(
+            line,
+            format!(
+                "CREATE TABLE {} ({}){}",
+                table,
+                definitions.join(", "),
+                engine
+            ),
+        );
+        for sql in indexes {
+            self.emit(line, sql);
+        }
+
+        let mut notes = Vec::new();
+        if !ignored.is_empty() {
+            notes.push(format!("ignored {}", ignored.join(", ")));
+        }
+        if !mapped.is_empty() {
+            notes.push(format!("mapped {}", mapped.join(", ")));
+        }
+        if !notes.is_empty() {
+            self.warn(
+                line,
+                format!("CREATE TABLE {}: {}", table, notes.join("; ")),
+            );
+        }
+        self.table_columns.insert(table, columns);
+    }
+
+    /// `CREATE [UNIQUE] INDEX [CONCURRENTLY] [IF NOT EXISTS] name ON [ONLY] t [USING m] (cols) ...`
+    fn create_index(&mut self, line: usize, tokens: &[Token], unique: bool) {
+        let Some(on) = tokens.iter().position(|t| t.token_type == TokenType::On) else {
+            return self.emit(line, render(&pieces(tokens)));
+        };
+        let Some(name) = tokens[..on].last().filter(|t| is_name(t)) else {
+            return self.emit(line, render(&pieces(tokens)));
+        };
+        let mut t = on + 1;
+        if word(tokens, t) == "ONLY" {
+            t += 1;
+        }
+        let Some(table) = tokens.get(t).filter(|t| is_name(t)) else {
+            return self.emit(line, render(&pieces(tokens)));
+        };
+        let Some(open) = tokens[t..]
+            .iter()
+            .position(|t| t.token_type == TokenType::LeftParen)
+            .map(|p| t + p)
+        else {
+            return self.emit(line, render(&pieces(tokens)));
+        };
+        let Some(close) = matching_paren(tokens, open) else {
+            return self.emit(line, render(&pieces(tokens)));
+        };
+        let Some(columns) = index_columns(&tokens[open + 1..close]) else {
+            self.warn(line, format!("skipped expression index {}", name.value));
+            return;
+        };
+
+        let extras: Vec<&Token> = tokens[t + 1..open]
+            .iter()
+            .chain(&tokens[close + 1..])
+            .collect();
+        if !extras.is_empty() {
+            let extras: Vec<String> = extras.iter().map(|t| t.value.clone()).collect();
+            self.warn(
+                line,
+                format!("CREATE INDEX {}: ignored {}", name.value, render(&extras)),
+            );
+        }
+        if unique {
+            self.warn(
+                line,
+                format!(
+                    "CREATE UNIQUE INDEX {} mapped to a UNIQUE constraint",
+                    name.value
+                ),
+            );
+            self.emit(
+                line,
+                format!(
+                    "ALTER TABLE {} ADD CONSTRAINT {} UNIQUE ({})",
+                    table.value,
+                    name.value,
+                    columns.join(", ")
+                ),
+            );
+        } else {
+            self.emit(
+                line,
+                format!(
+                    "CREATE INDEX {} ON {} ({})",
+                    name.value,
+                    table.value,
+                    columns.join(", ")
+                ),
+            );
+        }
+    }
+
+    /// `ALTER TABLE [ONLY] [IF EXISTS] t ADD ...`: drops `ONLY` and foreign key actions
+    fn alter(&mut self, line: usize, tokens: &[Token]) {
+        let mut i = 2;
+        while matches!(word(tokens, i).as_str(), "ONLY" | "IF" | "EXISTS") {
+            i += 1;
+        }
+        let Some(table) = tokens.get(i).filter(|_| word(tokens, 1) == "TABLE") else {
+            return self.emit(line, render(&pieces(tokens)));
+        };
+        if word(tokens, i + 1) != "ADD" || !is_constraint_start(&word(tokens, i + 2)) {
+            let mut out = vec!["ALTER".to_string(), "TABLE".to_string()];
+            out.extend(pieces(&tokens[i..]));
+            return self.emit(line, render(&out));
+        }
+        let mut ignored = Vec::new();
+        let definition = constraint(&tokens[i + 2..], &mut ignored);
+        self.emit(
+            line,
+            format!("ALTER TABLE {} ADD {}", table.value, definition),
+        );
+        if !ignored.is_empty() {
+            self.warn(
+                line,
+                format!(
+                    "ALTER TABLE {}: ignored {}",
+                    table.value,
+                    ignored.join(", ")
+                ),
+            );
+        }
+    }
+
+    /// Drops `INSERT IGNORE` / `OVERRIDING ... VALUE` modifiers and spells out the column
+    /// list of positional inserts into tables created earlier in the dump
+    fn insert(&mut self, line: usize, mut tokens: Vec<Token>) {
+        if word(&tokens, 1) == "IGNORE" {
+            tokens.remove(1);
+            self.warn(line, "INSERT IGNORE loaded as plain INSERT".to_string());
+        }
+        if let Some(p) = tokens.iter().position(|t| is_word(t, "OVERRIDING")) {
+            tokens.drain(p..(p + 3).min(tokens.len()));
+        }
+        let mut out = pieces(&tokens);
+        let known = (word(&tokens, 1) == "INTO")
+            .then(|| tokens.get(2))
+            .flatten()
+            .filter(|t| is_name(t))
+            .and_then(|t| self.table_columns.get(&t.value));
+        if let Some(columns) = known.filter(|_| word(&tokens, 3) == "VALUES") {
+            let mut list = vec!["(".to_string()];
+            for (n, (name, _)) in columns.iter().enumerate() {
+                if n > 0 {
+                    list.push(",".to_string());
+                }
+                list.push(name.clone());
+            }
+            list.push(")".to_string());
+            out.splice(3..3, list);
+        }
+        self.emit(line, render(&out));
+    }
+
+    /// `COPY t [(cols)] FROM stdin` + tab-separated rows -> batched `INSERT`s
+    fn copy(&mut self, line: usize, tokens: &[Token], rows: Vec<(usize, String)>) -> Result<()> {
+        let Some(table) = tokens
+            .get(1)
+            .filter(|t| is_name(t))
+            .map(|t| t.value.clone())
+        else {
+            return Err(Error::parser(format!(
+                "line {}: COPY without a table name",
+                line
+            )));
+        };
+        let listed = (tokens.get(2).map(|t| t.token_type) == Some(TokenType::LeftParen))
+            .then(|| matching_paren(tokens, 2))
+            .flatten()
+            .map(|close| {
+                tokens[3..close]
+                    .iter()
+                    .filter(|t| is_name(t))
+                    .map(|t| t.value.clone())
+                    .collect::<Vec<_>>()
+            });
+        let known = self.table_columns.get(&table);
+        let columns =
+            listed.or_else(|| known.map(|cols| cols.iter().map(|(n, _)| n.clone()).collect()));
+        let kinds: Vec<ValueKind> = match &columns {
+            Some(cols) => cols
+                .iter()
+                .map(|c| {
+                    known
+                        .and_then(|k| k.iter().find(|(n, _)| n == c))
+                        .map_or(ValueKind::Unknown, |(_, kind)| *kind)
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        let column_list = columns
+            .as_ref()
+            .map_or(String::new(), |cols| format!(" ({})", cols.join(", ")));
+
+        for chunk in rows.chunks(COPY_BATCH_ROWS) {
+            let mut values = Vec::with_capacity(chunk.len());
+            for (row_line, row) in chunk {
+                let fields: Vec<&str> = row.split('\t').collect();
+                if columns.is_some() && fields.len() != kinds.len() {
+                    return Err(Error::parser(format!(
+                        "line {}: COPY row has {} fields, expected {}",
+                        row_line,
+                        fields.len(),
+                        kinds.len()
+                    )));
+                }
+                let literals: Vec<String> = fields
+                    .iter()
+                    .enumerate()
+                    .map(|(n, f)| {
+                        copy_literal(f, kinds.get(n).copied().unwrap_or(ValueKind::Unknown))
+                    })
+                    .collect();
+                values.push(format!("({})", literals.join(", ")));
+            }
+            self.emit(
+                line,
+                format!(
+                    "INSERT INTO {}{} VALUES {}",
+                    table,
+                    column_list,
+                    values.join(", ")
+                ),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Statements with no RustDB equivalent: session settings, locks, privileges, ownership,
+/// sequences, views and routines. Returns a label for the warning.
+fn skipped_statement(tokens: &[Token]) -> Option<String> {
+    let head = word(tokens, 0);
+    match head.as_str() {
+        "SET" | "LOCK" | "UNLOCK" | "USE" | "GRANT" | "REVOKE" | "COMMENT" | "ANALYZE"
+        | "VACUUM" | "FLUSH" => Some(head),
+        // `SELECT pg_catalog.set_config(..)`, `SELECT setval(..)`: session/sequence calls
+        "SELECT"
+            if tokens
+                .get(1)
+                .is_some_and(|t| t.token_type == TokenType::Identifier)
+                && tokens.get(2).is_some_and(|t| {
+                    matches!(t.token_type, TokenType::Dot | TokenType::LeftParen)
+                })
+                && !tokens.iter().any(|t| t.token_type == TokenType::From) =>
+        {
+            let function = match tokens.get(2).map(|t| t.token_type) {
+                Some(TokenType::Dot) => format!("{}.{}", tokens[1].value, word_raw(tokens, 3)),
+                _ => tokens[1].value.clone(),
+            };
+            Some(format!("SELECT {}", function))
+        }
+        "CREATE" => {
+            let object = word(tokens, 1);
+            match object.as_str() {
+                "TABLE" | "TEMPORARY" | "TEMP" | "UNLOGGED" | "INDEX" | "UNIQUE" => None,
+                _ => Some(format!("CREATE {}", object)),
+            }
+        }
+        "DROP" => {
+            let object = word(tokens, 1);
+            (object != "TABLE").then(|| format!("DROP {}", object))
+        }
+        "ALTER" => {
+            let object = word(tokens, 1);
+            if object != "TABLE" {
+                return Some(format!("ALTER {}", object));
+            }
+            let mut i = 2;
+            while matches!(word(tokens, i).as_str(), "ONLY" | "IF" | "EXISTS") {
+                i += 1;
+            }
+            // table name, possibly schema-qualified
+            i += 1;
+            if tokens
+                .get(i)
+                .is_some_and(|t| t.token_type == TokenType::Dot)
+            {
+                i += 2;
+            }
+            let operation = word(tokens, i);
+            match operation.as_str() {
+                "OWNER" | "ALTER" | "DISABLE" | "ENABLE" | "REPLICA" | "SET" | "CLUSTER"
+                | "INHERIT" | "ATTACH" | "DETACH" | "FORCE" | "RESET" | "NO" => {
+                    Some(format!("ALTER TABLE {}", operation))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Normalizes a table constraint; unsupported tails (`ON DELETE CASCADE`, `USING BTREE`)
+/// are appended to `ignored`
+fn constraint(item: &[Token], ignored: &mut Vec<String>) -> String {
+    let mut prefix = Vec::new();
+    let mut i = 0;
+    if word(item, 0) == "CONSTRAINT" && item.len() > 1 {
+        prefix.push(format!("CONSTRAINT {}", item[1].value));
+        i = 2;
+    }
+    let body = &item[i..];
+    let first_group = body
+        .iter()
+        .position(|t| t.token_type == TokenType::LeftParen)
+        .and_then(|open| matching_paren(body, open).map(|close| (open, close)));
+    let columns = first_group.and_then(|(open, close)| index_columns(&body[open + 1..close]));
+
+    let normalized = match (word(body, 0).as_str(), first_group, columns) {
+        ("PRIMARY", Some((_, close)), Some(cols)) => {
+            note_tail(&body[close + 1..], ignored);
+            Some(format!("PRIMARY KEY ({})", cols.join(", ")))
+        }
+        ("UNIQUE", Some((_, close)), Some(cols)) => {
+            note_tail(&body[close + 1..], ignored);
+            Some(format!("UNIQUE ({})", cols.join(", ")))
+        }
+        ("FOREIGN", Some((_, close)), Some(cols)) => foreign_key(&body[close + 1..], ignored)
+            .map(|reference| format!("FOREIGN KEY ({}) {}", cols.join(", "), reference)),
+        _ => None,
+    };
+    let normalized = normalized.unwrap_or_else(|| render(&pieces(body)));
+    prefix.push(normalized);
+    prefix.join(" ")
+}
+
+/// `REFERENCES t [(cols)] [actions]` -> `REFERENCES t (cols)`
+fn foreign_key(tail: &[Token], ignored: &mut Vec<String>) -> Option<String> {
+    if word(tail, 0) != "REFERENCES" {
+        return None;
+    }
+    let table = tail.get(1).filter(|t| is_name(t))?;
+    let (reference, rest) = match tail.get(2).map(|t| t.token_type) {
+        Some(TokenType::LeftParen) => {
+            let close = matching_paren(tail, 2)?;
+            let cols = index_columns(&tail[3..close])?;
+            (
+                format!("REFERENCES {} ({})", table.value, cols.join(", ")),
+                &tail[close + 1..],
+            )
+        }
+        _ => (format!("REFERENCES {}", table.value), &tail[2..]),
+    };
+    note_tail(rest, ignored);
+    Some(reference)
+}
+
+fn note_tail(tail: &[Token], ignored: &mut Vec<String>) {
+    if !tail.is_empty() {
+        ignored.push(render(&pieces(tail)));
+    }
+}
+
+/// MySQL inline `[UNIQUE|FULLTEXT|SPATIAL] KEY name (cols)` -> `CREATE INDEX t_name ON t (cols)`
+fn inline_index(
+    table: &str,
+    item: &[Token],
+    ignored: &mut Vec<String>,
+) -> Option<(String, String)> {
+    let open = item
+        .iter()
+        .position(|t| t.token_type == TokenType::LeftParen)?;
+    let close = matching_paren(item, open)?;
+    let columns = index_columns(&item[open + 1..close])?;
+    note_tail(&item[close + 1..], ignored);
+    let name = item[..open]
+        .iter()
+        .rev()
+        .find(|t| t.token_type == TokenType::Identifier)
+        .map_or_else(|| columns.join("_"), |t| t.value.clone());
+    let index = format!("{}_{}", table, name);
+    let sql = format!(
+        "CREATE INDEX {} ON {} ({})",
+        index,
+        table,
+        columns.join(", ")
+    );
+    Some((index, sql))
+}
+
+fn key_label(item: &[Token]) -> String {
+    let end = item
+        .iter()
+        .position(|t| t.token_type == TokenType::LeftParen)
+        .unwrap_or(item.len());
+    render(&pieces(&item[..end]))
+}
+
+/// Plain column names of an index or key list; `None` for expressions.
+///
+/// Accepts MySQL prefix lengths (`name(10)`), sort order and PostgreSQL operator classes,
+/// none of which RustDB indexes use.
+fn index_columns(tokens: &[Token]) -> Option<Vec<String>> {
+    let mut columns = Vec::new();
+    for part in split_top_level(tokens) {
+        let name = part
+            .first()
+            .filter(|t| t.token_type == TokenType::Identifier)?;
+        if part
+            .get(1)
+            .is_some_and(|t| t.token_type == TokenType::LeftParen)
+            && !part
+                .get(2)
+                .is_some_and(|t| t.token_type == TokenType::IntegerLiteral)
+        {
+            return None;
+        }
+        columns.push(name.value.clone());
+    }
+    (!columns.is_empty()).then_some(columns)
+}
+
+/// Translates one column definition; returns its SQL and `(name, kind)` for `COPY`
+fn column_definition(
+    item: &[Token],
+    ignored: &mut Vec<String>,
+    mapped: &mut Vec<String>,
+) -> (String, (String, ValueKind)) {
+    let name = item[0].value.clone();
+    let Some(ty) = map_type(&item[1..]) else {
+        return (render(&pieces(item)), (name, ValueKind::Unknown));
+    };
+    if ty.lossy {
+        mapped.push(format!(
+            "{} {} to {}",
+            name,
+            render(&pieces(&item[1..1 + ty.consumed])).replace(" (", "("),
+            ty.sql
+        ));
+    }
+
+    let mut out = vec![name.clone(), ty.sql.clone()];
+    let rest = &item[1 + ty.consumed..];
+    let mut j = 0;
+    while j < rest.len() {
+        let start = j;
+        match word(rest, j).as_str() {
+            "AUTO_INCREMENT" | "UNSIGNED" | "SIGNED" | "ZEROFILL" => j += 1,
+            "COMMENT" | "CHARSET" => j += 2,
+            "CHARACTER" if word(rest, j + 1) == "SET" => j += 3,
+            "COLLATE" => {
+                j += 2;
+                if rest.get(j).is_some_and(|t| t.token_type == TokenType::Dot) {
+                    j += 2;
+                }
+            }
+            "NULL" => {
+                // Nullable is the default; only `DEFAULT NULL` carries meaning.
+                j += 1;
+                continue;
+            }
+            "ON" if matches!(word(rest, j + 1).as_str(), "DELETE" | "UPDATE") => {
+                j += 2;
+                match word(rest, j).as_str() {
+                    "NO" | "SET" => j += 2,
+                    "CASCADE" | "RESTRICT" => j += 1,
+                    // MySQL `ON UPDATE CURRENT_TIMESTAMP[(n)]`
+                    _ => j = skip_operand(rest, j),
+                }
+            }
+            "GENERATED" => {
+                j += 1;
+                while j < rest.len() && !is_column_constraint_start(&rest[j]) {
+                    j = skip_operand(rest, j);
+                }
+            }
+            "DEFAULT" if word(rest, j + 1) == "NEXTVAL" => {
+                j = skip_operand(rest, j + 1);
+                ignored.push(format!("sequence DEFAULT on {}", name));
+                continue;
+            }
+            "DEFAULT" => {
+                out.push(rest[j].value.clone());
+                j += 1;
+                if rest
+                    .get(j)
+                    .is_some_and(|t| t.token_type == TokenType::Minus)
+                {
+                    out.push(rest[j].value.clone());
+                    j += 1;
+                }
+                let end = skip_operand(rest, j);
+                out.extend(pieces(&rest[j..end]));
+                j = end;
+                continue;
+            }
+            _ => {
+                out.push(rest[j].value.clone());
+                j += 1;
+                continue;
+            }
+        }
+        let clause = match word(rest, start).as_str() {
+            "COMMENT" => "COMMENT".to_string(),
+            _ => render(&pieces(&rest[start..j.min(rest.len())])),
+        };
+        ignored.push(format!("{} on {}", clause, name));
+    }
+    (render(&out), (name, ty.kind))
+}
+
+/// Index after one operand: a token plus its argument list, if any
+fn skip_operand(tokens: &[Token], at: usize) -> usize {
+    if at >= tokens.len() {
+        return at;
+    }
+    if tokens
+        .get(at + 1)
+        .is_some_and(|t| t.token_type == TokenType::LeftParen)
+    {
+        if let Some(close) = matching_paren(tokens, at + 1) {
+            return close + 1;
+        }
+    }
+    if tokens[at].token_type == TokenType::LeftParen {
+        if let Some(close) = matching_paren(tokens, at) {
+            return close + 1;
+        }
+    }
+    at + 1
+}
+
+fn is_column_constraint_start(token: &Token) -> bool {
+    matches!(
+        token.token_type,
+        TokenType::NotNull
+            | TokenType::Null
+            | TokenType::Primary
+            | TokenType::Unique
+            | TokenType::Default
+            | TokenType::Check
+            | TokenType::References
+    )
+}
+
+fn is_constraint_start(word: &str) -> bool {
+    matches!(
+        word,
+        "CONSTRAINT" | "PRIMARY" | "UNIQUE" | "FOREIGN" | "CHECK"
+    )
+}
+
+/// Maps MySQL / PostgreSQL type names onto the parser's types
+fn map_type(tokens: &[Token]) -> Option<MappedType> {
+    let first = word(tokens, 0);
+    if first.is_empty() {
+        return None;
+    }
+    let mut used = 1;
+    let base = match (first.as_str(), word(tokens, 1).as_str()) {
+        ("CHARACTER" | "CHAR", "VARYING") => {
+            used = 2;
+            "VARCHAR".to_string()
+        }
+        ("DOUBLE", "PRECISION") => {
+            used = 2;
+            "DOUBLE".to_string()
+        }
+        ("BIT", "VARYING") => {
+            used = 2;
+            "VARBIT".to_string()
+        }
+        _ => first,
+    };
+    let mut length = None;
+    if tokens
+        .get(used)
+        .is_some_and(|t| t.token_type == TokenType::LeftParen)
+    {
+        let close = matching_paren(tokens, used)?;
+        length = tokens
+            .get(used + 1)
+            .filter(|t| t.token_type == TokenType::IntegerLiteral)
+            .and_then(|t| t.value.parse::<u64>().ok());
+        used = close + 1;
+    }
+    if matches!(base.as_str(), "TIMESTAMP" | "TIME")
+        && matches!(word(tokens, used).as_str(), "WITH" | "WITHOUT")
+        && word(tokens, used + 1) == "TIME"
+        && word(tokens, used + 2) == "ZONE"
+    {
+        used += 3;
+    }
+    let mut array = false;
+    while tokens
+        .get(used)
+        .is_some_and(|t| t.token_type == TokenType::LeftBracket)
+        && tokens
+            .get(used + 1)
+            .is_some_and(|t| t.token_type == TokenType::RightBracket)
+    {
+        used += 2;
+        array = true;
+    }
+
+    let (sql, kind, lossy) = match base.as_str() {
+        "INT" | "INTEGER" | "INT4" | "MEDIUMINT" | "SMALLINT" | "INT2" | "TINYINT" | "YEAR" => {
+            ("INTEGER".to_string(), ValueKind::Numeric, false)
+        }
+        "SERIAL" | "SERIAL4" | "SMALLSERIAL" | "SERIAL2" => {
+            ("INTEGER".to_string(), ValueKind::Numeric, true)
+        }
+        "BIGINT" | "INT8" => ("BIGINT".to_string(), ValueKind::Numeric, false),
+        "BIGSERIAL" | "SERIAL8" => ("BIGINT".to_string(), ValueKind::Numeric, true),
+        "REAL" | "FLOAT" | "FLOAT4" | "FLOAT8" | "DOUBLE" => {
+            ("REAL".to_string(), ValueKind::Numeric, false)
+        }
+        "DECIMAL" | "NUMERIC" | "DEC" | "FIXED" | "MONEY" => {
+            ("REAL".to_string(), ValueKind::Numeric, true)
+        }
+        "VARCHAR" | "CHAR" | "CHARACTER" | "NCHAR" | "NVARCHAR" | "BPCHAR" => {
+            let sql = match length {
+                Some(n) if n <= u16::MAX as u64 => format!("VARCHAR({})", n),
+                Some(_) => "TEXT".to_string(),
+                None => "VARCHAR".to_string(),
+            };
+            (sql, ValueKind::Text, false)
+        }
+        "TEXT" | "TINYTEXT" | "MEDIUMTEXT" | "LONGTEXT" | "CITEXT" | "JSON" | "JSONB" | "UUID"
+        | "XML" | "INET" | "CIDR" | "MACADDR" | "INTERVAL" => {
+            ("TEXT".to_string(), ValueKind::Text, false)
+        }
+        "ENUM" | "SET" | "BLOB" | "TINYBLOB" | "MEDIUMBLOB" | "LONGBLOB" | "BYTEA" | "BINARY"
+        | "VARBINARY" | "VARBIT" => ("TEXT".to_string(), ValueKind::Text, true),
+        "BOOLEAN" | "BOOL" | "BIT" => ("BOOLEAN".to_string(), ValueKind::Boolean, false),
+        "DATE" => ("DATE".to_string(), ValueKind::Text, false),
+        "TIME" | "TIMETZ" => ("TIME".to_string(), ValueKind::Text, false),
+        "DATETIME" | "TIMESTAMP" | "TIMESTAMPTZ" => {
+            ("TIMESTAMP".to_string(), ValueKind::Text, false)
+        }
+        _ => return None,
+    };
+    if array {
+        return Some(MappedType {
+            sql: "TEXT".to_string(),
+            kind: ValueKind::Text,
+            consumed: used,
+            lossy: true,
+        });
+    }
+    Some(MappedType {
+        sql,
+        kind,
+        consumed: used,
+        lossy,
+    })
+}
+
+/// Splits `ENGINE=...` (kept when it names a RustDB engine) from the other table options
+fn table_options(tokens: &[Token]) -> (Option<String>, String) {
+    let mut engine = None;
+    let mut dropped = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        if is_word(&tokens[i], "ENGINE") {
+            let mut v = i + 1;
+            if tokens
+                .get(v)
+                .is_some_and(|t| t.token_type == TokenType::Equal)
+            {
+                v += 1;
+            }
+            if let Some(value) = tokens.get(v) {
+                if matches!(value.value.to_ascii_lowercase().as_str(), "heap" | "lsm") {
+                    engine = Some(value.value.to_ascii_lowercase());
+                } else {
+                    dropped.push(format!("ENGINE={}", value.value));
+                }
+                i = v + 1;
+                continue;
+            }
+        }
+        dropped.push(tokens[i].value.clone());
+        i += 1;
+    }
+    (engine, dropped.join(" ").replace(" = ", "="))
+}
+
+/// Renders one `COPY` text-format field as a SQL literal
+fn copy_literal(field: &str, kind: ValueKind) -> String {
+    if field == "\\N" {
+        return "NULL".to_string();
+    }
+    let value = unescape_copy(field);
+    match kind {
+        ValueKind::Numeric | ValueKind::Unknown if is_plain_number(&value) => value,
+        ValueKind::Boolean => match value.as_str() {
+            "t" | "true" | "1" => "TRUE".to_string(),
+            "f" | "false" | "0" => "FALSE".to_string(),
+            _ => quote(&value),
+        },
+        _ => quote(&value),
+    }
+}
+
+fn unescape_copy(field: &str) -> String {
+    let mut out = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('b') => out.push('\u{8}'),
+            Some('f') => out.push('\u{c}'),
+            Some('v') => out.push('\u{b}'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// `-12`, `3.5`; no exponents or leading zeros (zip codes and ids stay strings)
+fn is_plain_number(value: &str) -> bool {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    let (int, frac) = digits.split_once('.').unwrap_or((digits, ""));
+    !int.is_empty()
+        && int.chars().all(|c| c.is_ascii_digit())
+        && (int == "0" || !int.starts_with('0'))
+        && frac.chars().all(|c| c.is_ascii_digit())
+        && !(digits.contains('.') && frac.is_empty())
+}
+
+/// Quotes `value` in the lexer's backslash-escape form
+fn quote(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('\'');
+    for c in value.chars() {
+        if c == '\\' || c == '\'' {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('\'');
+    out
+}
+
+/// Drops `::type` casts (`'x'::character varying`, `'{}'::text[]`)
+fn strip_casts(tokens: &mut Vec<Token>) {
+    let mut i = 0;
+    while i < tokens.len() {
+        if tokens[i].token_type != TokenType::DoubleColon {
+            i += 1;
+            continue;
+        }
+        let mut end = i + 2;
+        while end < tokens.len()
+            && matches!(
+                word(tokens, end).as_str(),
+                "VARYING" | "PRECISION" | "WITH" | "WITHOUT" | "TIME" | "ZONE"
+            )
+        {
+            end += 1;
+        }
+        if tokens
+            .get(end)
+            .is_some_and(|t| t.token_type == TokenType::LeftParen)
+        {
+            end = matching_paren(tokens, end).map_or(end, |close| close + 1);
+        }
+        while tokens
+            .get(end)
+            .is_some_and(|t| t.token_type == TokenType::LeftBracket)
+            && tokens
+                .get(end + 1)
+                .is_some_and(|t| t.token_type == TokenType::RightBracket)
+        {
+            end += 2;
+        }
+        tokens.drain(i..end.min(tokens.len()));
+    }
+}
+
+fn matching_paren(tokens: &[Token], open: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate().skip(open) {
+        match token.token_type {
+            TokenType::LeftParen => depth += 1,
+            TokenType::RightParen => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn split_top_level(tokens: &[Token]) -> Vec<&[Token]> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut start = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token.token_type {
+            TokenType::LeftParen => depth += 1,
+            TokenType::RightParen => depth -= 1,
+            TokenType::Comma if depth == 0 => {
+                parts.push(&tokens[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&tokens[start..]);
+    parts
+}
+
+/// Upper-cased value of `tokens[i]` (empty past the end)
+fn word(tokens: &[Token], i: usize) -> String {
+    tokens
+        .get(i)
+        .map(|t| t.value.to_ascii_uppercase())
+        .unwrap_or_default()
+}
+
+fn word_raw(tokens: &[Token], i: usize) -> String {
+    tokens.get(i).map(|t| t.value.clone()).unwrap_or_default()
+}
+
+fn is_word(token: &Token, keyword: &str) -> bool {
+    token.value.eq_ignore_ascii_case(keyword)
+}
+
+/// Identifier or keyword usable as a name (`public.user`, `pg_catalog.text`)
+fn is_name(token: &Token) -> bool {
+    token.token_type == TokenType::Identifier
+        || token
+            .value
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && !matches!(
+                token.token_type,
+                TokenType::StringLiteral | TokenType::Comment
+            )
+}
+
+fn pieces(tokens: &[Token]) -> Vec<String> {
+    tokens.iter().map(|t| t.value.clone()).collect()
+}
+
+/// Joins token texts with single spaces, tight around `,`, `.` and parentheses
+fn render(pieces: &[String]) -> String {
+    let mut out = String::new();
+    for piece in pieces {
+        let tight = matches!(piece.as_str(), "," | ")" | ".") || out.ends_with(['(', '.']);
+        if !out.is_empty() && !tight {
+            out.push(' ');
+        }
+        out.push_str(piece);
+    }
+    out
+}
@@ -66,6 +66,7 @@ impl Lexer {
             ';' => self.read_single_char_token(TokenType::Semicolon),
             '.' => self.read_single_char_token(TokenType::Dot),
             '?' => self.read_single_char_token(TokenType::Question),
+            '$' if self.position + 1 < self.input.len() && self.input[self.position + 1].is_ascii_digit() => self.read_parameter()?,
             
             // Unknown character
             _ => {
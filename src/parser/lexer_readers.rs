@@ -50,6 +50,22 @@ impl Lexer {
         Ok(Token::new(TokenType::Comment, value, start_position))
     }
     
+    /// Reads a numbered parameter placeholder (`$1`)
+    pub(crate) fn read_parameter(&mut self) -> Result<Token> {
+        let start_position = self.current_position.clone();
+        let mut value = String::new();
+        value.push(self.advance()); // $
+        
+        while let Some(ch) = self.peek() {
+            if !ch.is_ascii_digit() {
+                break;
+            }
+            value.push(self.advance());
+        }
+        
+        Ok(Token::new(TokenType::Parameter, value, start_position))
+    }
+    
     /// Reads string literal
     pub(crate) fn read_string_literal(&mut self) -> Result<Token> {
         let start_position = self.current_position.clone();
@@ -1,9 +1,11 @@
 //! SQL parser for rustdb
 
 pub mod ast;
+pub mod dump;
 pub mod lexer;
 pub mod parser;
 pub mod prepared;
+pub mod quoting;
 pub mod token;
 
 #[cfg(test)]
@@ -11,6 +13,8 @@ pub mod tests;
 
 // Re-export main types
 pub use ast::*;
+pub use dump::{translate_dump, DumpDialect, DumpScript, DumpStatement, DumpWarning};
 pub use lexer::Lexer;
-pub use parser::{ParserSettings, SqlParser};
+pub use parser::{parse_unchecked, ParserSettings, SqlParser};
+pub use quoting::{bind_params, placeholder_count, quote_identifier, quote_literal};
 pub use token::{Position, Token, TokenType};
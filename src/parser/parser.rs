@@ -15,6 +15,12 @@ pub struct SqlParser {
     parse_cache: HashMap<String, SqlStatement>,
     /// Parser settings
     settings: ParserSettings,
+    /// Current nesting depth (expressions, NOT chains, subqueries)
+    depth: usize,
+    /// `?` placeholders read so far; the next one is `Parameter(positional_parameters + 1)`
+    positional_parameters: usize,
+    /// Whether a `$n` placeholder was read (a statement uses one style or the other)
+    numbered_parameters: bool,
 }
 
 /// Parser settings
@@ -51,6 +57,9 @@ impl SqlParser {
             peek_token,
             parse_cache: HashMap::new(),
             settings: ParserSettings::default(),
+            depth: 0,
+            positional_parameters: 0,
+            numbered_parameters: false,
         })
     }
 
@@ -71,6 +80,9 @@ impl SqlParser {
         let mut statements = Vec::new();
 
         while self.current_token.is_some() && !self.match_token(&TokenType::Eof) {
+            // Placeholders are numbered per statement.
+            self.positional_parameters = 0;
+            self.numbered_parameters = false;
             let stmt = self.parse_statement()?;
             statements.push(stmt);
 
@@ -107,6 +119,10 @@ impl SqlParser {
                 }
                 TokenType::Commit => {
                     self.advance();
+                    if self.match_keyword("PREPARED") {
+                        self.advance();
+                        return Ok(SqlStatement::CommitPrepared(self.parse_transaction_gid()?));
+                    }
                     if self.match_keyword("TRANSACTION") {
                         self.advance();
                     }
@@ -114,6 +130,12 @@ impl SqlParser {
                 }
                 TokenType::Rollback => {
                     self.advance();
+                    if self.match_keyword("PREPARED") {
+                        self.advance();
+                        return Ok(SqlStatement::RollbackPrepared(
+                            self.parse_transaction_gid()?,
+                        ));
+                    }
                     if self.match_keyword("TRANSACTION") {
                         self.advance();
                     }
@@ -121,6 +143,92 @@ impl SqlParser {
                 }
                 TokenType::Prepare => self.parse_prepare(),
                 TokenType::Execute => self.parse_execute(),
+                TokenType::Set => {
+                    self.advance();
+                    if self.match_keyword("GLOBAL") {
+                        // Every SET is server-wide; GLOBAL is accepted for MySQL scripts.
+                        self.advance();
+                        return self.parse_set_parameter();
+                    }
+                    if !self.match_keyword("TRANSACTION") {
+                        return self.parse_set_parameter();
+                    }
+                    self.advance();
+                    self.expect_keyword("SNAPSHOT")?;
+                    Ok(SqlStatement::SetTransactionSnapshot(
+                        self.parse_snapshot_id()?,
+                    ))
+                }
+                TokenType::Identifier if self.match_keyword("SHOW") => {
+                    self.advance();
+                    if self.match_token(&TokenType::All) {
+                        self.advance();
+                        return Ok(SqlStatement::ShowParameter(None));
+                    }
+                    if self.match_keyword("ENGINE") {
+                        self.advance();
+                        self.expect_keyword("STATUS")?;
+                        return Ok(SqlStatement::ShowEngineStatus);
+                    }
+                    if self.match_keyword("TABLE") {
+                        self.advance();
+                        self.expect_keyword("STATUS")?;
+                        return Ok(SqlStatement::ShowTableStatus);
+                    }
+                    Ok(SqlStatement::ShowParameter(Some(
+                        self.parse_parameter_name()?,
+                    )))
+                }
+                TokenType::Identifier if self.match_keyword("CHECKPOINT") => {
+                    self.advance();
+                    Ok(SqlStatement::Checkpoint)
+                }
+                TokenType::Identifier if self.match_keyword("FLUSH") => {
+                    self.advance();
+                    self.expect_keyword("LOGS")?;
+                    Ok(SqlStatement::FlushLogs)
+                }
+                TokenType::Identifier if self.match_keyword("FORGET") => {
+                    self.advance();
+                    match self.parse_primary_expression()? {
+                        Expression::Literal(
+                            subject @ (Literal::String(_) | Literal::Integer(_)),
+                        ) => Ok(SqlStatement::Forget(subject)),
+                        _ => Err(Error::parser(
+                            "FORGET expects a string or integer subject".to_string(),
+                        )),
+                    }
+                }
+                TokenType::Identifier if self.match_keyword("VACUUM") => {
+                    self.advance();
+                    Ok(SqlStatement::Vacuum(self.parse_optional_table_name()?))
+                }
+                TokenType::Check => {
+                    self.advance();
+                    self.expect_keyword("TABLE")?;
+                    Ok(SqlStatement::CheckTable(self.parse_optional_table_name()?))
+                }
+                TokenType::Identifier if self.match_keyword("ANALYZE") => {
+                    self.advance();
+                    Ok(SqlStatement::Analyze(self.parse_optional_table_name()?))
+                }
+                TokenType::Identifier if self.match_keyword("FLASHBACK") => {
+                    self.advance();
+                    self.expect_keyword("TABLE")?;
+                    let table = self.parse_identifier()?;
+                    self.expect_keyword("TO")?;
+                    let timestamp = self.parse_timestamp_literal()?;
+                    Ok(SqlStatement::FlashbackTable { table, timestamp })
+                }
+                TokenType::Identifier if self.match_keyword("PROFILE") => {
+                    self.advance();
+                    self.parse_profile_cpu()
+                }
+                TokenType::Identifier if self.match_keyword("EXPORT") => {
+                    self.advance();
+                    self.expect_keyword("SNAPSHOT")?;
+                    Ok(SqlStatement::ExportSnapshot)
+                }
                 _ => Err(Error::parser(format!(
                     "Unexpected token: {:?}",
                     token.token_type
@@ -209,6 +317,7 @@ impl SqlParser {
                         "SET" => token.token_type == TokenType::Set,
                         "EXISTS" => token.token_type == TokenType::Exists,
                         "CONSTRAINT" => token.token_type == TokenType::Constraint,
+                        "DATABASE" => token.token_type == TokenType::Database,
                         _ => false,
                     }
                 }
@@ -311,7 +420,22 @@ impl SqlParser {
     }
 
     fn parse_expression(&mut self) -> Result<Expression> {
-        self.parse_or_expression()
+        self.nested(Self::parse_or_expression)
+    }
+
+    /// Runs `f` one nesting level deeper, failing once `max_recursion_depth` is reached
+    /// so hostile input (e.g. thousands of `(`) errors out instead of overflowing the stack.
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth >= self.settings.max_recursion_depth {
+            return Err(Error::parser(format!(
+                "Maximum nesting depth of {} exceeded",
+                self.settings.max_recursion_depth
+            )));
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
     }
 
     fn parse_or_expression(&mut self) -> Result<Expression> {
@@ -345,7 +469,7 @@ impl SqlParser {
     fn parse_not_expression(&mut self) -> Result<Expression> {
         if self.match_token(&TokenType::Not) {
             self.advance();
-            let expr = self.parse_not_expression()?;
+            let expr = self.nested(Self::parse_not_expression)?;
             return Ok(Expression::UnaryOp {
                 op: UnaryOperator::Not,
                 expr: Box::new(expr),
@@ -531,9 +655,40 @@ impl SqlParser {
             self.expect_token(&TokenType::RightParen)?;
             return Ok(expr);
         }
+        // Signed numeric literal (`-1`, `-2.5`), as written by dump tools in VALUES lists
+        if self.match_token(&TokenType::Minus)
+            && matches!(
+                self.peek_token.as_ref().map(|t| t.token_type),
+                Some(TokenType::IntegerLiteral | TokenType::FloatLiteral)
+            )
+        {
+            self.advance();
+            return match self.parse_simple_expression()? {
+                Expression::Literal(Literal::Integer(n)) => {
+                    Ok(Expression::Literal(Literal::Integer(-n)))
+                }
+                Expression::Literal(Literal::Float(f)) => {
+                    Ok(Expression::Literal(Literal::Float(-f)))
+                }
+                other => Err(Error::parser(format!(
+                    "Expected numeric literal after '-', got {:?}",
+                    other
+                ))),
+            };
+        }
         self.parse_simple_expression()
     }
 
+    /// A statement numbers its placeholders (`$1`) or lists them in order (`?`), not both.
+    fn check_parameter_style(&self) -> Result<()> {
+        if self.numbered_parameters && self.positional_parameters > 0 {
+            return Err(Error::parser(
+                "Cannot mix '?' and '$n' parameter placeholders".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
     /// Parses simple expression (literal or identifier)
     fn parse_simple_expression(&mut self) -> Result<Expression> {
         match &self.current_token {
@@ -611,6 +766,25 @@ impl SqlParser {
                     self.advance();
                     Ok(Expression::Literal(Literal::Null))
                 }
+                TokenType::Question => {
+                    self.advance();
+                    self.positional_parameters += 1;
+                    self.check_parameter_style()?;
+                    Ok(Expression::Parameter(self.positional_parameters))
+                }
+                TokenType::Parameter => {
+                    let index = token.value[1..]
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|&n| n > 0)
+                        .ok_or_else(|| {
+                            Error::parser(format!("Invalid parameter: {}", token.value))
+                        })?;
+                    self.advance();
+                    self.numbered_parameters = true;
+                    self.check_parameter_style()?;
+                    Ok(Expression::Parameter(index))
+                }
                 TokenType::Identifier
                 | TokenType::Count
                 | TokenType::Sum
@@ -670,6 +844,10 @@ impl SqlParser {
 
     // Main parsing methods
     fn parse_select(&mut self) -> Result<SqlStatement> {
+        self.nested(Self::parse_select_body)
+    }
+
+    fn parse_select_body(&mut self) -> Result<SqlStatement> {
         self.expect_keyword("SELECT")?;
 
         let distinct = if self.match_token(&TokenType::Distinct) {
@@ -726,10 +904,37 @@ impl SqlParser {
                         alias,
                     })
                 } else {
-                    // Simple table reference with optional alias.
+                    // Simple table reference (or table function call) with optional alias.
                     let name = p.parse_identifier()?;
+                    let args = if p.match_token(&TokenType::LeftParen) {
+                        p.advance();
+                        let args = if p.match_token(&TokenType::RightParen) {
+                            Vec::new()
+                        } else {
+                            p.parse_expression_list()?
+                        };
+                        p.expect_token(&TokenType::RightParen)?;
+                        Some(args)
+                    } else {
+                        None
+                    };
+                    let mut as_of = None;
                     if p.match_keyword("AS") {
                         p.advance();
+                        // `AS OF TIMESTAMP '...'`, not an alias named `of`.
+                        if args.is_none()
+                            && p.match_keyword("OF")
+                            && matches!(
+                                p.peek_token.as_ref().map(|t| t.token_type),
+                                Some(TokenType::Timestamp)
+                            )
+                        {
+                            p.advance();
+                            as_of = Some(p.parse_timestamp_literal()?);
+                            if p.match_keyword("AS") {
+                                p.advance();
+                            }
+                        }
                     }
                     let alias = if matches!(
                         p.current_token.as_ref().map(|t| t.token_type),
@@ -739,7 +944,10 @@ impl SqlParser {
                     } else {
                         None
                     };
-                    Ok(TableReference::Table { name, alias })
+                    Ok(match args {
+                        Some(args) => TableReference::Function { name, args, alias },
+                        None => TableReference::Table { name, alias, as_of },
+                    })
                 }
             }
 
@@ -850,7 +1058,23 @@ impl SqlParser {
                 } else {
                     OrderDirection::Asc
                 };
-                order_by.push(OrderByItem { expr, direction });
+                let nulls = if self.match_keyword("NULLS") {
+                    self.advance();
+                    if self.match_keyword("FIRST") {
+                        self.advance();
+                        Some(NullsOrder::First)
+                    } else {
+                        self.expect_keyword("LAST")?;
+                        Some(NullsOrder::Last)
+                    }
+                } else {
+                    None
+                };
+                order_by.push(OrderByItem {
+                    expr,
+                    direction,
+                    nulls,
+                });
                 if !self.match_token(&TokenType::Comma) {
                     break;
                 }
@@ -1053,6 +1277,7 @@ impl SqlParser {
         self.expect_keyword("EXPLAIN")?;
         let mut analyze = false;
         let mut verbose = false;
+        let mut format = ExplainFormat::Text;
         loop {
             if self.match_keyword("ANALYZE") {
                 self.advance();
@@ -1060,6 +1285,25 @@ impl SqlParser {
             } else if self.match_keyword("VERBOSE") {
                 self.advance();
                 verbose = true;
+            } else if self.match_keyword("FORMAT") {
+                self.advance();
+                // TEXT is a type keyword, so the name is read from the token itself.
+                let name = self
+                    .current_token
+                    .as_ref()
+                    .map(|t| t.value.to_ascii_uppercase())
+                    .unwrap_or_default();
+                format = match name.as_str() {
+                    "TEXT" => ExplainFormat::Text,
+                    "JSON" => ExplainFormat::Json,
+                    "DOT" => ExplainFormat::Dot,
+                    other => {
+                        return Err(Error::parser(format!(
+                            "unknown EXPLAIN format {other} (expected TEXT, JSON or DOT)"
+                        )))
+                    }
+                };
+                self.advance();
             } else {
                 break;
             }
@@ -1069,6 +1313,7 @@ impl SqlParser {
         Ok(SqlStatement::Explain(ExplainStatement {
             analyze,
             verbose,
+            format,
             statement: Box::new(inner),
         }))
     }
@@ -1092,6 +1337,12 @@ impl SqlParser {
 
     fn parse_prepare(&mut self) -> Result<SqlStatement> {
         self.expect_keyword("PREPARE")?;
+        if self.match_keyword("TRANSACTION") {
+            self.advance();
+            return Ok(SqlStatement::PrepareTransaction(
+                self.parse_transaction_gid()?,
+            ));
+        }
         let name = self.parse_identifier()?;
         self.expect_keyword("AS")?;
 
@@ -1116,6 +1367,143 @@ impl SqlParser {
         }))
     }
 
+    /// `TIMESTAMP '<timestamp>'` of `AS OF` and `FLASHBACK TABLE`
+    fn parse_timestamp_literal(&mut self) -> Result<String> {
+        self.expect_token(&TokenType::Timestamp)?;
+        let timestamp = match &self.current_token {
+            Some(token) if token.token_type == TokenType::StringLiteral => {
+                unquote_string_literal(&token.value)
+            }
+            _ => {
+                return Err(Error::parser(
+                    "Expected timestamp string after TIMESTAMP".to_string(),
+                ))
+            }
+        };
+        self.advance();
+        Ok(timestamp)
+    }
+
+    /// Global transaction id of `PREPARE TRANSACTION` / `COMMIT PREPARED` / `ROLLBACK PREPARED`
+    fn parse_transaction_gid(&mut self) -> Result<String> {
+        let gid = match &self.current_token {
+            Some(token) if token.token_type == TokenType::StringLiteral => {
+                unquote_string_literal(&token.value)
+            }
+            _ => {
+                return Err(Error::parser(
+                    "Expected transaction identifier string".to_string(),
+                ))
+            }
+        };
+        if gid.is_empty() {
+            return Err(Error::parser(
+                "Transaction identifier must not be empty".to_string(),
+            ));
+        }
+        self.advance();
+        Ok(gid)
+    }
+
+    fn parse_snapshot_id(&mut self) -> Result<String> {
+        let id = match &self.current_token {
+            Some(token) if token.token_type == TokenType::StringLiteral => {
+                unquote_string_literal(&token.value)
+            }
+            _ => {
+                return Err(Error::parser(
+                    "Expected snapshot identifier string".to_string(),
+                ))
+            }
+        };
+        self.advance();
+        Ok(id)
+    }
+
+    /// The table of `VACUUM`, `CHECK TABLE` or `ANALYZE`, if one is named.
+    fn parse_optional_table_name(&mut self) -> Result<Option<String>> {
+        match self.current_token {
+            Some(ref t) if t.token_type == TokenType::Identifier => {
+                Ok(Some(self.parse_identifier()?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// `CPU FOR <n> SECONDS [FORMAT { FLAMEGRAPH | PPROF }]` after `PROFILE`
+    fn parse_profile_cpu(&mut self) -> Result<SqlStatement> {
+        self.expect_keyword("CPU")?;
+        self.expect_keyword("FOR")?;
+        let seconds = self.parse_integer()?;
+        let seconds = u64::try_from(seconds)
+            .map_err(|_| Error::parser("Profile duration must not be negative".to_string()))?;
+        self.expect_keyword("SECONDS")?;
+        let format = if self.match_keyword("FORMAT") {
+            self.advance();
+            let format = if self.match_keyword("FLAMEGRAPH") {
+                ProfileFormat::Flamegraph
+            } else if self.match_keyword("PPROF") {
+                ProfileFormat::Pprof
+            } else {
+                return Err(Error::parser(
+                    "Expected FLAMEGRAPH or PPROF after FORMAT".to_string(),
+                ));
+            };
+            self.advance();
+            format
+        } else {
+            ProfileFormat::Flamegraph
+        };
+        Ok(SqlStatement::ProfileCpu { seconds, format })
+    }
+
+    /// `SET <parameter> { = | TO } <value>` after `SET`
+    fn parse_set_parameter(&mut self) -> Result<SqlStatement> {
+        let name = self.parse_parameter_name()?;
+        if self.match_token(&TokenType::Equal) {
+            self.advance();
+        } else {
+            self.expect_keyword("TO")?;
+        }
+        let value = match &self.current_token {
+            Some(token) if token.token_type == TokenType::StringLiteral => {
+                unquote_string_literal(&token.value)
+            }
+            Some(token)
+                if token.token_type.is_literal()
+                    || token.token_type.is_keyword()
+                    || token.token_type == TokenType::Identifier =>
+            {
+                token.value.clone()
+            }
+            _ => return Err(Error::parser("Expected parameter value".to_string())),
+        };
+        self.advance();
+        Ok(SqlStatement::SetParameter { name, value })
+    }
+
+    /// Configuration parameter name: words joined by dots (`network.port`)
+    fn parse_parameter_name(&mut self) -> Result<String> {
+        let mut name = String::new();
+        loop {
+            match &self.current_token {
+                Some(token)
+                    if token.token_type == TokenType::Identifier
+                        || token.token_type.is_keyword() =>
+                {
+                    name.push_str(&token.value);
+                    self.advance();
+                }
+                _ => return Err(Error::parser("Expected parameter name".to_string())),
+            }
+            if !self.match_token(&TokenType::Dot) {
+                return Ok(name);
+            }
+            name.push('.');
+            self.advance();
+        }
+    }
+
     fn parse_execute(&mut self) -> Result<SqlStatement> {
         self.expect_keyword("EXECUTE")?;
         let name = self.parse_identifier()?;
@@ -1138,36 +1526,219 @@ impl SqlParser {
         if self.match_keyword("TABLE") {
             self.advance();
             self.parse_create_table()
+        } else if self.match_keyword("FOREIGN") {
+            self.advance();
+            self.expect_keyword("TABLE")?;
+            self.parse_create_foreign_table()
         } else if self.match_keyword("INDEX") {
             self.advance();
             self.parse_create_index()
+        } else if self.match_keyword("DATABASE") {
+            self.advance();
+            let name = self.parse_identifier()?;
+            let template = if self.match_keyword("TEMPLATE") {
+                self.advance();
+                Some(self.parse_identifier()?)
+            } else {
+                None
+            };
+            Ok(SqlStatement::CreateDatabase { name, template })
+        } else if self.match_keyword("TENANT") {
+            self.advance();
+            let name = self.parse_identifier()?;
+            let quotas = if self.match_keyword("WITH") {
+                self.advance();
+                self.parse_tenant_quotas()?
+            } else {
+                Vec::new()
+            };
+            Ok(SqlStatement::CreateTenant { name, quotas })
         } else {
             Err(Error::parser(
-                "Only CREATE TABLE and CREATE INDEX are supported".to_string(),
+                "Only CREATE TABLE, CREATE FOREIGN TABLE, CREATE INDEX, CREATE DATABASE and \
+                 CREATE TENANT are supported"
+                    .to_string(),
             ))
         }
     }
 
+    /// `(<quota> = { <n> | NONE }, ...)` of `CREATE TENANT ... WITH` and `ALTER TENANT ... SET`
+    fn parse_tenant_quotas(&mut self) -> Result<Vec<TenantQuota>> {
+        self.expect_token(&TokenType::LeftParen)?;
+        let mut quotas = Vec::new();
+        loop {
+            let name = self.parse_identifier()?.to_ascii_lowercase();
+            self.expect_token(&TokenType::Equal)?;
+            let limit = if self.match_keyword("NONE") {
+                self.advance();
+                None
+            } else {
+                let n = self.parse_integer()?;
+                Some(u64::try_from(n).map_err(|_| {
+                    Error::parser(format!("Tenant quota {name} must not be negative"))
+                })?)
+            };
+            quotas.push(TenantQuota { name, limit });
+            if !self.match_token(&TokenType::Comma) {
+                break;
+            }
+            self.advance();
+        }
+        self.expect_token(&TokenType::RightParen)?;
+        Ok(quotas)
+    }
+
+    fn parse_create_foreign_table(&mut self) -> Result<SqlStatement> {
+        let table_name = self.parse_identifier()?;
+        self.expect_token(&TokenType::LeftParen)?;
+
+        // Columns only: constraints cannot be enforced on data owned by an external file.
+        let mut columns = Vec::new();
+        loop {
+            let name = self.parse_identifier()?;
+            let data_type = self.parse_data_type()?;
+            if !self.parse_column_constraints_tail()?.is_empty() {
+                return Err(Error::parser(format!(
+                    "Column constraints are not supported on foreign table columns ({})",
+                    name
+                )));
+            }
+            columns.push(ColumnDefinition {
+                name,
+                data_type,
+                constraints: Vec::new(),
+            });
+            if !self.match_token(&TokenType::Comma) {
+                break;
+            }
+            self.advance();
+        }
+        self.expect_token(&TokenType::RightParen)?;
+
+        self.expect_token(&TokenType::Using)?;
+        let adapter = self.parse_identifier()?.to_lowercase();
+
+        // OPTIONS (key 'value', ...)
+        let mut options = Vec::new();
+        if self.match_keyword("OPTIONS") {
+            self.advance();
+            self.expect_token(&TokenType::LeftParen)?;
+            loop {
+                let key = match &self.current_token {
+                    Some(token) if token.token_type != TokenType::StringLiteral => {
+                        token.value.to_lowercase()
+                    }
+                    _ => return Err(Error::parser("Expected option name".to_string())),
+                };
+                self.advance();
+                let value = match &self.current_token {
+                    Some(token) if token.token_type == TokenType::StringLiteral => {
+                        unquote_string_literal(&token.value)
+                    }
+                    _ => {
+                        return Err(Error::parser(format!(
+                            "Expected string value for option {}",
+                            key
+                        )))
+                    }
+                };
+                self.advance();
+                options.push((key, value));
+                if !self.match_token(&TokenType::Comma) {
+                    break;
+                }
+                self.advance();
+            }
+            self.expect_token(&TokenType::RightParen)?;
+        }
+
+        Ok(SqlStatement::CreateForeignTable(
+            CreateForeignTableStatement {
+                table_name,
+                columns,
+                adapter,
+                options,
+            },
+        ))
+    }
+
     fn parse_create_index(&mut self) -> Result<SqlStatement> {
-        let index_name = self.parse_identifier()?;
+        let concurrently = self.match_keyword("CONCURRENTLY");
+        if concurrently {
+            self.advance();
+        }
+        let index_name = if self.match_keyword("ON") {
+            None
+        } else {
+            Some(self.parse_identifier()?)
+        };
         self.expect_keyword("ON")?;
         let table_name = self.parse_identifier()?;
+        let method = if self.match_token(&TokenType::Using) {
+            self.advance();
+            let method = self.parse_identifier()?;
+            match method.to_uppercase().as_str() {
+                "BTREE" => IndexMethod::BTree,
+                "HASH" => IndexMethod::Hash,
+                _ => {
+                    return Err(Error::parser(format!(
+                        "Unknown index method {method}: expected BTREE or HASH"
+                    )))
+                }
+            }
+        } else {
+            IndexMethod::BTree
+        };
         self.expect_token(&TokenType::LeftParen)?;
 
+        // Key parts: a column name, or an expression computed from the row.
         let mut columns = Vec::new();
+        let mut expressions = Vec::new();
         loop {
-            columns.push(self.parse_identifier()?);
+            let column_only = self
+                .peek_token
+                .as_ref()
+                .is_some_and(|t| matches!(t.token_type, TokenType::Comma | TokenType::RightParen));
+            if column_only {
+                columns.push(self.parse_identifier()?);
+            } else {
+                let expr = self.parse_expression()?;
+                let key = expr.index_key();
+                columns.push(key.clone());
+                expressions.push((key, expr));
+            }
             if !self.match_token(&TokenType::Comma) {
                 break;
             }
             self.advance();
         }
         self.expect_token(&TokenType::RightParen)?;
+        // Unnamed indexes are named after their table and key, as `users_lower_email_idx`.
+        let index_name = index_name.unwrap_or_else(|| {
+            let key: String = columns
+                .join("_")
+                .chars()
+                .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                .collect();
+            let key: Vec<&str> = key.split('_').filter(|p| !p.is_empty()).collect();
+            format!("{table_name}_{}_idx", key.join("_"))
+        });
+
+        let where_clause = if self.match_keyword("WHERE") {
+            self.advance();
+            Some(self.parse_where_expression()?)
+        } else {
+            None
+        };
 
         Ok(SqlStatement::CreateIndex(CreateIndexStatement {
             index_name,
             table_name,
+            method,
             columns,
+            expressions,
+            concurrently,
+            where_clause,
         }))
     }
 
@@ -1262,11 +1833,33 @@ impl SqlParser {
 
         self.expect_token(&TokenType::RightParen)?;
 
+        // Optional storage engine: ENGINE lsm | ENGINE = heap
+        let engine = if self.match_keyword("ENGINE") {
+            self.advance();
+            if self.match_token(&TokenType::Equal) {
+                self.advance();
+            }
+            Some(self.parse_identifier()?)
+        } else {
+            None
+        };
+
+        // Optional crypto-shredding subject: ENCRYPT BY <column>
+        let encrypt_by = if self.match_keyword("ENCRYPT") {
+            self.advance();
+            self.expect_keyword("BY")?;
+            Some(self.parse_identifier()?)
+        } else {
+            None
+        };
+
         Ok(SqlStatement::CreateTable(CreateTableStatement {
             table_name,
             columns,
             constraints,
             if_not_exists: false,
+            engine,
+            encrypt_by,
         }))
     }
 
@@ -1449,6 +2042,13 @@ impl SqlParser {
 
     fn parse_alter(&mut self) -> Result<SqlStatement> {
         self.expect_keyword("ALTER")?;
+        if self.match_keyword("TENANT") {
+            self.advance();
+            let name = self.parse_identifier()?;
+            self.expect_keyword("SET")?;
+            let quotas = self.parse_tenant_quotas()?;
+            return Ok(SqlStatement::AlterTenant { name, quotas });
+        }
         self.expect_keyword("TABLE")?;
         let table_name = self.parse_identifier()?;
 
@@ -1544,7 +2144,16 @@ impl SqlParser {
 
     fn parse_drop(&mut self) -> Result<SqlStatement> {
         self.expect_keyword("DROP")?;
-        self.expect_keyword("TABLE")?;
+        if self.match_keyword("INDEX") {
+            self.advance();
+            return self.parse_drop_index();
+        }
+        let tenant = self.match_keyword("TENANT");
+        if tenant {
+            self.advance();
+        } else {
+            self.expect_keyword("TABLE")?;
+        }
         let if_exists = if self.match_keyword("IF") {
             self.advance();
             self.expect_keyword("EXISTS")?;
@@ -1552,6 +2161,10 @@ impl SqlParser {
         } else {
             false
         };
+        if tenant {
+            let name = self.parse_identifier()?;
+            return Ok(SqlStatement::DropTenant { name, if_exists });
+        }
         let table_name = self.parse_identifier()?;
         let cascade = if self.match_keyword("CASCADE") {
             self.advance();
@@ -1568,4 +2181,53 @@ impl SqlParser {
             cascade,
         }))
     }
+
+    /// `DROP INDEX [IF EXISTS] <index> [ON <table>]` after `DROP INDEX`
+    fn parse_drop_index(&mut self) -> Result<SqlStatement> {
+        let if_exists = if self.match_keyword("IF") {
+            self.advance();
+            self.expect_keyword("EXISTS")?;
+            true
+        } else {
+            false
+        };
+        let index_name = self.parse_identifier()?;
+        let table_name = if self.match_keyword("ON") {
+            self.advance();
+            Some(self.parse_identifier()?)
+        } else {
+            None
+        };
+        Ok(SqlStatement::DropIndex(DropIndexStatement {
+            index_name,
+            table_name,
+            if_exists,
+        }))
+    }
+}
+
+/// Parses arbitrary bytes as a SQL script, never panicking.
+///
+/// Intended as the cargo-fuzz entry point for the lexer and parser (see `fuzz/`): input need
+/// not be valid UTF-8 (invalid sequences are replaced), and every malformed script is reported
+/// as an [`Error`]. Statements are separated by `;` as in [`SqlParser::parse_multiple`].
+pub fn parse_unchecked(bytes: &[u8]) -> Result<Vec<SqlStatement>> {
+    let input = String::from_utf8_lossy(bytes);
+    let mut parser = SqlParser::with_settings(
+        &input,
+        ParserSettings {
+            enable_caching: false,
+            ..ParserSettings::default()
+        },
+    )?;
+    parser.parse_multiple()
+}
+
+/// Strips the quotes the lexer keeps on string literals (`'a\'b'` -> `a'b`)
+fn unquote_string_literal(s: &str) -> String {
+    if s.len() >= 2 && s.starts_with('\'') && s.ends_with('\'') {
+        s[1..s.len() - 1].replace("\\'", "'")
+    } else {
+        s.to_string()
+    }
 }
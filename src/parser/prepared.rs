@@ -1,9 +1,17 @@
 //! Prepared statement cache for RustDB
 //!
 //! Stores prepared statements (PREPARE name AS ...) and resolves EXECUTE name (params).
+//!
+//! [`parameter_count`] and [`bind_parameters`] work on any parsed statement with `$n` or `?`
+//! placeholders ([`Expression::Parameter`]): binding replaces each placeholder of the cached
+//! AST with its value, so a statement is parsed once however often it runs. [`bind_expression`]
+//! does the same for one expression, such as a predicate of a plan.
 
 use crate::common::{Error, Result};
-use crate::parser::ast::{ExecuteStatement, Expression, PrepareStatement, SqlStatement};
+use crate::parser::ast::{
+    ExecuteStatement, Expression, FromClause, InList, InsertValues, PrepareStatement, SelectItem,
+    SelectStatement, SqlStatement, TableReference,
+};
 use std::collections::HashMap;
 use std::sync::RwLock;
 
@@ -12,7 +20,7 @@ use std::sync::RwLock;
 pub struct CachedPreparedStatement {
     /// The parsed statement (SELECT, INSERT, UPDATE, DELETE)
     pub statement: SqlStatement,
-    /// Number of parameter placeholders ($1, $2, ...)
+    /// Number of parameter placeholders ($1, $2, ...): the highest one
     pub param_count: usize,
 }
 
@@ -86,24 +94,11 @@ impl PreparedStatementCache {
     }
 
     fn count_params(stmt: &SqlStatement) -> usize {
-        match stmt {
-            SqlStatement::Select(s) => Self::count_params_in_select(s),
-            SqlStatement::Insert(_) | SqlStatement::Update(_) | SqlStatement::Delete(_) => 0,
-            _ => 0,
-        }
-    }
-
-    fn count_params_in_select(_stmt: &crate::parser::ast::SelectStatement) -> usize {
-        // Simplified: no $1, $2 placeholders in parser yet - use 0
-        0
+        parameter_count(stmt)
     }
 
     fn bind_params(stmt: &SqlStatement, params: &[Expression]) -> Result<SqlStatement> {
-        if params.is_empty() {
-            return Ok(stmt.clone());
-        }
-        // Simplified: no placeholder substitution yet - return as-is
-        Ok(stmt.clone())
+        bind_parameters(stmt, params)
     }
 }
 
@@ -112,3 +107,147 @@ impl Default for PreparedStatementCache {
         Self::new()
     }
 }
+
+/// Parameters `stmt` takes: its highest placeholder (`$3` alone takes three). Placeholders are
+/// found in `SELECT`, set operations, `INSERT`, `UPDATE`, `DELETE` and the statement of an
+/// `EXPLAIN`.
+pub fn parameter_count(stmt: &SqlStatement) -> usize {
+    let mut count = 0;
+    visit_statement(&mut stmt.clone(), &mut |expr| {
+        if let Expression::Parameter(n) = expr {
+            count = count.max(*n);
+        }
+    });
+    count
+}
+
+/// `stmt` with each placeholder `$n` replaced by `params[n - 1]`. `params` must supply exactly
+/// [`parameter_count`] values.
+pub fn bind_parameters(stmt: &SqlStatement, params: &[Expression]) -> Result<SqlStatement> {
+    let expected = parameter_count(stmt);
+    if params.len() != expected {
+        return Err(Error::validation(format!(
+            "statement takes {expected} parameters but {} were given",
+            params.len()
+        )));
+    }
+    let mut bound = stmt.clone();
+    if !params.is_empty() {
+        visit_statement(&mut bound, &mut |expr| {
+            if let Expression::Parameter(n) = expr {
+                *expr = params[*n - 1].clone();
+            }
+        });
+    }
+    Ok(bound)
+}
+
+/// `expr` with each placeholder `$n` replaced by `params[n - 1]`; placeholders without a value
+/// stay.
+pub fn bind_expression(expr: &Expression, params: &[Expression]) -> Expression {
+    let mut bound = expr.clone();
+    visit(&mut bound, &mut |e| {
+        if let Expression::Parameter(n) = e {
+            if let Some(value) = params.get(*n - 1) {
+                *e = value.clone();
+            }
+        }
+    });
+    bound
+}
+
+/// Calls `f` on every expression of `stmt`, outer expressions before the ones inside them.
+fn visit_statement(stmt: &mut SqlStatement, f: &mut dyn FnMut(&mut Expression)) {
+    match stmt {
+        SqlStatement::Select(sel) => visit_select(sel, f),
+        SqlStatement::SetOperation(op) => {
+            visit_select(&mut op.left, f);
+            visit_select(&mut op.right, f);
+        }
+        SqlStatement::Insert(ins) => match &mut ins.values {
+            InsertValues::Values(rows) => rows.iter_mut().flatten().for_each(|e| visit(e, f)),
+            InsertValues::Select(sel) => visit_select(sel, f),
+        },
+        SqlStatement::Update(upd) => {
+            for assignment in &mut upd.assignments {
+                visit(&mut assignment.value, f);
+            }
+            upd.where_clause.iter_mut().for_each(|e| visit(e, f));
+        }
+        SqlStatement::Delete(del) => del.where_clause.iter_mut().for_each(|e| visit(e, f)),
+        SqlStatement::Explain(ex) => visit_statement(&mut ex.statement, f),
+        _ => {}
+    }
+}
+
+fn visit_select(sel: &mut SelectStatement, f: &mut dyn FnMut(&mut Expression)) {
+    for item in &mut sel.select_list {
+        if let SelectItem::Expression { expr, .. } = item {
+            visit(expr, f);
+        }
+    }
+    if let Some(FromClause { table, joins }) = &mut sel.from {
+        visit_table(table, f);
+        for join in joins {
+            visit_table(&mut join.table, f);
+            join.condition.iter_mut().for_each(|e| visit(e, f));
+        }
+    }
+    sel.where_clause.iter_mut().for_each(|e| visit(e, f));
+    sel.group_by.iter_mut().for_each(|e| visit(e, f));
+    sel.having.iter_mut().for_each(|e| visit(e, f));
+    sel.order_by.iter_mut().for_each(|o| visit(&mut o.expr, f));
+}
+
+fn visit_table(table: &mut TableReference, f: &mut dyn FnMut(&mut Expression)) {
+    match table {
+        TableReference::Table { .. } => {}
+        TableReference::Subquery { query, .. } => visit_select(query, f),
+        TableReference::Function { args, .. } => args.iter_mut().for_each(|e| visit(e, f)),
+    }
+}
+
+fn visit(expr: &mut Expression, f: &mut dyn FnMut(&mut Expression)) {
+    f(expr);
+    match expr {
+        Expression::Literal(_)
+        | Expression::Identifier(_)
+        | Expression::QualifiedIdentifier { .. }
+        | Expression::Parameter(_) => {}
+        Expression::BinaryOp { left, right, .. } => {
+            visit(left, f);
+            visit(right, f);
+        }
+        Expression::UnaryOp { expr, .. } | Expression::IsNull { expr, .. } => visit(expr, f),
+        Expression::Function { args, .. } => args.iter_mut().for_each(|e| visit(e, f)),
+        Expression::Case {
+            expr,
+            when_clauses,
+            else_clause,
+        } => {
+            expr.iter_mut().for_each(|e| visit(e, f));
+            for clause in when_clauses {
+                visit(&mut clause.condition, f);
+                visit(&mut clause.result, f);
+            }
+            else_clause.iter_mut().for_each(|e| visit(e, f));
+        }
+        Expression::Exists(sel) => visit_select(sel, f),
+        Expression::In { expr, list } => {
+            visit(expr, f);
+            match list {
+                InList::Values(values) => values.iter_mut().for_each(|e| visit(e, f)),
+                InList::Subquery(sel) => visit_select(sel, f),
+            }
+        }
+        Expression::Between { expr, low, high } => {
+            visit(expr, f);
+            visit(low, f);
+            visit(high, f);
+        }
+        Expression::Like { expr, pattern, .. } => {
+            visit(expr, f);
+            visit(pattern, f);
+        }
+    }
+}
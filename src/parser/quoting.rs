@@ -0,0 +1,172 @@
+//! Quoting helpers for code that has to build SQL text.
+//!
+//! They follow the lexer's rules: string literals are backslash-escaped, identifiers that are
+//! not plain (letters, digits, `_`) or that are keywords go in double quotes, which the parser
+//! keeps as part of the name. [`bind_params`] substitutes `?` and `$n` placeholders with quoted
+//! values and refuses SQL with more than one statement, so a value or a stray `;` cannot add a
+//! statement.
+
+use crate::common::row_mapping::ToValue;
+use crate::common::{Error, Result};
+use crate::parser::lexer::Lexer;
+use crate::parser::token::{keyword_map, TokenType};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// `value` as an SQL literal the lexer reads back as the same value.
+pub fn quote_literal(value: impl ToValue) -> String {
+    value.to_sql()
+}
+
+/// `name` as it must be written to reach the object called `name`: unchanged when it is a plain
+/// identifier, else in double quotes. Fails for names containing `"`, which cannot be quoted.
+pub fn quote_identifier(name: &str) -> Result<String> {
+    if name.is_empty() || name.contains('"') {
+        return Err(Error::validation(format!(
+            "{name:?} cannot be written as an identifier"
+        )));
+    }
+    Ok(identifier_text(name))
+}
+
+/// [`quote_identifier`] for names already known to be free of `"`.
+pub(crate) fn identifier_text(name: &str) -> String {
+    static KEYWORDS: OnceLock<HashMap<&'static str, TokenType>> = OnceLock::new();
+    let plain = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let keyword = KEYWORDS
+        .get_or_init(keyword_map)
+        .contains_key(name.to_ascii_uppercase().as_str());
+    if plain && !keyword {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name)
+    }
+}
+
+/// Replaces each placeholder of `sql` (outside literals and comments), `$n` or the n-th `?`,
+/// with `params[n - 1]` as a [`quote_literal`]. The placeholder count must match, and `sql` must
+/// be a single statement (a trailing `;` is allowed).
+pub fn bind_params(sql: &str, params: &[&dyn ToValue]) -> Result<String> {
+    let found = placeholders(sql)?;
+    let count = found.iter().map(|&(_, _, n)| n).max().unwrap_or(0);
+    if count != params.len() {
+        return Err(Error::validation(format!(
+            "SQL has {count} placeholders but {} parameters were given",
+            params.len()
+        )));
+    }
+    let chars: Vec<char> = sql.chars().collect();
+    let mut out = String::with_capacity(sql.len());
+    let mut copied = 0;
+    for (start, end, n) in found {
+        out.extend(&chars[copied..start]);
+        out.push_str(&params[n - 1].to_sql());
+        copied = end;
+    }
+    out.extend(&chars[copied..]);
+    Ok(out)
+}
+
+/// Number of parameters [`bind_params`] expects for `sql` (its highest placeholder), with the
+/// same single-statement check.
+pub fn placeholder_count(sql: &str) -> Result<usize> {
+    Ok(placeholders(sql)?
+        .into_iter()
+        .map(|(_, _, n)| n)
+        .max()
+        .unwrap_or(0))
+}
+
+/// Placeholders of `sql` outside literals and comments as `(start, end, n)`: the character range
+/// of each and the parameter it stands for, `$n` or the n-th `?`. Fails when `sql` is more than
+/// one statement (a trailing `;` is allowed).
+pub(crate) fn placeholders(sql: &str) -> Result<Vec<(usize, usize, usize)>> {
+    let mut lexer = Lexer::new(sql)?;
+    let mut out = Vec::new();
+    let mut positional = 0;
+    let mut statement_ended = false;
+    loop {
+        let token = lexer.next_token()?;
+        let start = token.position.offset;
+        match token.token_type {
+            TokenType::Eof => return Ok(out),
+            TokenType::Comment | TokenType::Whitespace | TokenType::Newline => continue,
+            _ if statement_ended => {
+                return Err(Error::validation(
+                    "parameterized SQL must be a single statement",
+                ))
+            }
+            TokenType::Semicolon => statement_ended = true,
+            TokenType::Question => {
+                positional += 1;
+                out.push((start, start + 1, positional));
+            }
+            TokenType::Parameter => {
+                let n = token.value[1..]
+                    .parse()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| {
+                        Error::validation(format!("invalid parameter {}", token.value))
+                    })?;
+                out.push((start, start + token.value.chars().count(), n));
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::SqlParser;
+
+    #[test]
+    fn identifiers_are_quoted_when_not_plain() {
+        assert_eq!(quote_identifier("users").unwrap(), "users");
+        assert_eq!(quote_identifier("order").unwrap(), "\"order\"");
+        assert_eq!(quote_identifier("my table").unwrap(), "\"my table\"");
+        assert!(quote_identifier("a\"b").is_err());
+        assert!(quote_identifier("").is_err());
+    }
+
+    #[test]
+    fn params_become_literals_of_a_single_statement() {
+        let name = "x'; DROP TABLE t; --";
+        let sql = bind_params(
+            "SELECT a FROM t WHERE b = ? AND c > ? AND d = '?';",
+            &[&name, &-3],
+        )
+        .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT a FROM t WHERE b = 'x\\'; DROP TABLE t; --' AND c > -3 AND d = '?';"
+        );
+        assert_eq!(
+            SqlParser::new(&sql)
+                .unwrap()
+                .parse_multiple()
+                .unwrap()
+                .len(),
+            1
+        );
+
+        assert_eq!(bind_params("SELECT 1 -- ?", &[]).unwrap(), "SELECT 1 -- ?");
+        assert!(bind_params("SELECT ?; DROP TABLE t", &[&1]).is_err());
+        assert!(bind_params("SELECT a FROM t WHERE b = ?", &[]).is_err());
+        assert!(bind_params("SELECT a FROM t", &[&1]).is_err());
+        assert_eq!(
+            placeholder_count("INSERT INTO t VALUES (?, '?', ?)").unwrap(),
+            2
+        );
+        assert!(placeholder_count("SELECT ?; SELECT ?").is_err());
+        assert_eq!(
+            bind_params("SELECT a FROM t WHERE b = $2 OR c = $1", &[&1, &"x"]).unwrap(),
+            "SELECT a FROM t WHERE b = 'x' OR c = 1"
+        );
+    }
+}
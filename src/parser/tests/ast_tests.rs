@@ -65,6 +65,7 @@ fn test_sql_statement_variants_construct() {
             table: TableReference::Table {
                 name: "x".to_string(),
                 alias: None,
+                as_of: None,
             },
             joins: vec![],
         }),
@@ -84,6 +85,8 @@ fn test_sql_statement_variants_construct() {
         columns: vec![],
         constraints: vec![],
         if_not_exists: true,
+        engine: None,
+        encrypt_by: None,
     });
     let _ = Expression::BinaryOp {
         left: Box::new(Expression::Literal(Literal::Integer(1))),
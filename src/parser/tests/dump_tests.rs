@@ -0,0 +1,220 @@
+//! Dump compatibility mode: mysqldump / pg_dump scripts translated for the strict parser
+
+use crate::common::Result;
+use crate::parser::ast::{
+    ColumnConstraint, DataType, Expression, InsertValues, Literal, SqlStatement,
+};
+use crate::parser::dump::{translate_dump, DumpDialect};
+use crate::parser::SqlParser;
+
+const MYSQLDUMP: &str = r#"-- MySQL dump 10.13  Distrib 8.0.36
+/*!40101 SET @OLD_CHARACTER_SET_CLIENT=@@CHARACTER_SET_CLIENT */;
+/*!50503 SET NAMES utf8mb4 */;
+SET @saved_cs_client     = @@character_set_client;
+
+DROP TABLE IF EXISTS `users`;
+CREATE TABLE `users` (
+  `id` int(11) unsigned NOT NULL AUTO_INCREMENT,
+  `name` varchar(64) COLLATE utf8mb4_unicode_ci NOT NULL COMMENT 'display name',
+  `score` decimal(10,2) DEFAULT NULL,
+  `active` tinyint(1) NOT NULL DEFAULT '1',
+  PRIMARY KEY (`id`),
+  UNIQUE KEY `uniq_name` (`name`),
+  KEY `idx_score` (`score`) USING BTREE
+) ENGINE=InnoDB AUTO_INCREMENT=3 DEFAULT CHARSET=utf8mb4 COMMENT='people';
+
+LOCK TABLES `users` WRITE;
+/*!40000 ALTER TABLE `users` DISABLE KEYS */;
+INSERT INTO `users` VALUES (1,'O\'Brien',-1.50,1),(2,'Zoë',NULL,0);
+/*!40000 ALTER TABLE `users` ENABLE KEYS */;
+UNLOCK TABLES;
+"#;
+
+const PG_DUMP: &str = r#"--
+-- PostgreSQL database dump
+--
+\restrict abc123
+
+SET statement_timeout = 0;
+SELECT pg_catalog.set_config('search_path', '', false);
+
+CREATE TABLE public.items (
+    id integer NOT NULL,
+    label character varying(40) DEFAULT 'n/a'::character varying,
+    price numeric(8,2),
+    created timestamp without time zone
+);
+
+ALTER TABLE public.items OWNER TO postgres;
+
+CREATE SEQUENCE public.items_id_seq
+    AS integer
+    START WITH 1;
+
+ALTER TABLE ONLY public.items ALTER COLUMN id SET DEFAULT nextval('public.items_id_seq'::regclass);
+
+COPY public.items (id, label, price, created) FROM stdin;
+1	it's	9.50	2024-01-02 03:04:05
+2	tab\there	\N	\N
+\.
+
+SELECT pg_catalog.setval('public.items_id_seq', 2, true);
+
+ALTER TABLE ONLY public.items
+    ADD CONSTRAINT items_pkey PRIMARY KEY (id);
+
+CREATE INDEX items_label_idx ON public.items USING btree (label);
+"#;
+
+fn parse_all(sqls: &[String]) -> Result<Vec<SqlStatement>> {
+    sqls.iter()
+        .map(|sql| SqlParser::new(sql)?.parse())
+        .collect()
+}
+
+#[test]
+fn mysqldump_translates_to_parseable_statements() -> Result<()> {
+    let script = translate_dump(MYSQLDUMP, DumpDialect::Auto)?;
+    assert_eq!(script.dialect, DumpDialect::MySql);
+    let sqls: Vec<String> = script.statements.iter().map(|s| s.sql.clone()).collect();
+    assert_eq!(
+        sqls,
+        vec![
+            "DROP TABLE IF EXISTS users",
+            "CREATE TABLE users (id INTEGER NOT NULL, name VARCHAR(64) NOT NULL, \
+             score REAL DEFAULT NULL, active INTEGER NOT NULL DEFAULT '1', PRIMARY KEY (id), \
+             UNIQUE (name))",
+            "CREATE INDEX users_idx_score ON users (score)",
+            "INSERT INTO users (id, name, score, active) VALUES (1, 'O\\'Brien', - 1.50, 1), \
+             (2, 'Zoë', NULL, 0)",
+        ]
+    );
+
+    let statements = parse_all(&sqls)?;
+    let SqlStatement::CreateTable(create) = &statements[1] else {
+        panic!("expected CREATE TABLE");
+    };
+    assert_eq!(create.engine, None);
+    assert_eq!(create.columns[2].data_type, DataType::Real);
+    let SqlStatement::Insert(insert) = &statements[3] else {
+        panic!("expected INSERT");
+    };
+    let InsertValues::Values(rows) = &insert.values else {
+        panic!("expected VALUES");
+    };
+    assert_eq!(rows[0][2], Expression::Literal(Literal::Float(-1.5)));
+
+    let messages: Vec<&str> = script.warnings.iter().map(|w| w.message.as_str()).collect();
+    assert!(messages.contains(&"skipped SET statement"));
+    assert!(messages.contains(&"skipped LOCK statement"));
+    let create_warning = messages
+        .iter()
+        .find(|m| m.starts_with("CREATE TABLE users"))
+        .expect("CREATE TABLE warning");
+    for clause in [
+        "unsigned on id",
+        "AUTO_INCREMENT on id",
+        "COMMENT on name",
+        "USING BTREE",
+        "ENGINE=InnoDB AUTO_INCREMENT=3 DEFAULT CHARSET=utf8mb4 COMMENT='people'",
+        "score DECIMAL(10, 2) to REAL",
+        "KEY idx_score as index users_idx_score",
+    ] {
+        assert!(
+            create_warning.contains(clause),
+            "{create_warning} lacks {clause}"
+        );
+    }
+    let create_line = script
+        .warnings
+        .iter()
+        .find(|w| w.message == *create_warning);
+    assert_eq!(create_line.map(|w| w.line), Some(7));
+    Ok(())
+}
+
+#[test]
+fn pg_dump_copy_block_becomes_typed_inserts() -> Result<()> {
+    let script = translate_dump(PG_DUMP, DumpDialect::Auto)?;
+    assert_eq!(script.dialect, DumpDialect::Postgres);
+    let sqls: Vec<String> = script.statements.iter().map(|s| s.sql.clone()).collect();
+    assert_eq!(
+        sqls,
+        vec![
+            "CREATE TABLE items (id INTEGER NOT NULL, label VARCHAR(40) DEFAULT 'n/a', \
+             price REAL, created TIMESTAMP)",
+            "INSERT INTO items (id, label, price, created) VALUES \
+             (1, 'it\\'s', 9.50, '2024-01-02 03:04:05'), (2, 'tab\there', NULL, NULL)",
+            "ALTER TABLE items ADD CONSTRAINT items_pkey PRIMARY KEY (id)",
+            "CREATE INDEX items_label_idx ON items (label)",
+        ]
+    );
+    parse_all(&sqls)?;
+
+    let messages: Vec<String> = script.warnings.iter().map(|w| w.to_string()).collect();
+    for expected in [
+        "line 4: skipped psql meta-command \\restrict",
+        "line 6: skipped SET statement",
+        "line 7: skipped SELECT pg_catalog.set_config statement",
+        "line 9: dropped schema qualifier 'public'",
+        "line 9: CREATE TABLE items: mapped price numeric(8, 2) to REAL",
+        "line 16: skipped ALTER TABLE OWNER statement",
+        "line 18: skipped CREATE SEQUENCE statement",
+        "line 22: skipped ALTER TABLE ALTER statement",
+        "line 29: skipped SELECT pg_catalog.setval statement",
+        "line 34: CREATE INDEX items_label_idx: ignored USING btree",
+    ] {
+        assert!(
+            messages.iter().any(|m| m == expected),
+            "missing {expected:?} in {messages:#?}"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn dump_mode_keeps_rustdb_engines_and_maps_unique_indexes() -> Result<()> {
+    let script = translate_dump(
+        "CREATE TABLE `log` (`id` bigint, `body` longtext) ENGINE=lsm;\n\
+         CREATE UNIQUE INDEX log_id ON log (id);\n\
+         START TRANSACTION;\nCOMMIT;",
+        DumpDialect::MySql,
+    )?;
+    let sqls: Vec<&str> = script.statements.iter().map(|s| s.sql.as_str()).collect();
+    assert_eq!(
+        sqls,
+        vec![
+            "CREATE TABLE log (id BIGINT, body TEXT) ENGINE lsm",
+            "ALTER TABLE log ADD CONSTRAINT log_id UNIQUE (id)",
+            "BEGIN TRANSACTION",
+            "COMMIT",
+        ]
+    );
+    let SqlStatement::CreateTable(create) = SqlParser::new(sqls[0])?.parse()? else {
+        panic!("expected CREATE TABLE");
+    };
+    assert_eq!(create.engine.as_deref(), Some("lsm"));
+    assert_eq!(
+        create.columns[0].constraints,
+        Vec::<ColumnConstraint>::new()
+    );
+    Ok(())
+}
+
+#[test]
+fn dump_dialect_names_and_unknown_syntax_passthrough() -> Result<()> {
+    assert_eq!(DumpDialect::from_name("PostgreSQL")?, DumpDialect::Postgres);
+    assert_eq!(DumpDialect::from_name("mariadb")?, DumpDialect::MySql);
+    assert!(DumpDialect::from_name("oracle").is_err());
+
+    // Unsupported statements are not silently dropped: the parser reports them.
+    let script = translate_dump(
+        "INSERT INTO t VALUES (1) ON CONFLICT DO NOTHING;",
+        DumpDialect::Postgres,
+    )?;
+    assert_eq!(script.statements.len(), 1);
+    assert!(SqlParser::new(&script.statements[0].sql)?
+        .parse_multiple()
+        .is_err());
+    Ok(())
+}
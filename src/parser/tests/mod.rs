@@ -1,6 +1,7 @@
 //! SQL parser tests for rustdb
 
 pub mod ast_tests;
+pub mod dump_tests;
 pub mod lexer_tests;
 pub mod parser_tests;
 pub mod prepared_tests;
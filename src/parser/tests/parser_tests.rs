@@ -1,10 +1,13 @@
 //! SQL parser tests
 
 use crate::common::Result;
-use crate::parser::ast::AlterTableOperation;
+use crate::parser::ast::{
+    AlterTableOperation, BinaryOperator, DropIndexStatement, ExplainFormat, IndexMethod, Literal,
+    TableReference,
+};
 use crate::parser::{
-    ColumnDefinition, CreateIndexStatement, CreateTableStatement, DataType, Expression, SelectItem,
-    SelectStatement, SqlParser, SqlStatement,
+    ColumnDefinition, CreateIndexStatement, CreateTableStatement, DataType, Expression,
+    ProfileFormat, SelectItem, SelectStatement, SqlParser, SqlStatement,
 };
 
 #[test]
@@ -60,6 +63,60 @@ fn test_parse_select_columns() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_parse_select_as_of_timestamp() -> Result<()> {
+    let table_of = |sql: &str| -> Result<TableReference> {
+        match SqlParser::new(sql)?.parse()? {
+            SqlStatement::Select(sel) => Ok(sel.from.expect("FROM").table),
+            other => panic!("Expected SELECT, got {other:?}"),
+        }
+    };
+    assert_eq!(
+        table_of("SELECT * FROM orders AS OF TIMESTAMP '2026-10-17 08:00:00' o WHERE o.id = 1")?,
+        TableReference::Table {
+            name: "orders".to_string(),
+            alias: Some("o".to_string()),
+            as_of: Some("2026-10-17 08:00:00".to_string()),
+        }
+    );
+    assert_eq!(
+        table_of("SELECT * FROM orders AS OF TIMESTAMP '2026-10-17T08:00:00' AS o")?,
+        TableReference::Table {
+            name: "orders".to_string(),
+            alias: Some("o".to_string()),
+            as_of: Some("2026-10-17T08:00:00".to_string()),
+        }
+    );
+    // `of` is still usable as an alias.
+    assert_eq!(
+        table_of("SELECT * FROM orders AS of")?,
+        TableReference::Table {
+            name: "orders".to_string(),
+            alias: Some("of".to_string()),
+            as_of: None,
+        }
+    );
+    assert!(SqlParser::new("SELECT * FROM orders AS OF TIMESTAMP 5")?
+        .parse()
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_parse_flashback_table() -> Result<()> {
+    assert_eq!(
+        SqlParser::new("FLASHBACK TABLE orders TO TIMESTAMP '2026-10-17 08:00:00'")?.parse()?,
+        SqlStatement::FlashbackTable {
+            table: "orders".to_string(),
+            timestamp: "2026-10-17 08:00:00".to_string(),
+        }
+    );
+    assert!(SqlParser::new("FLASHBACK TABLE orders TO '2026-10-17'")?
+        .parse()
+        .is_err());
+    Ok(())
+}
+
 #[test]
 fn test_parse_select_without_from() -> Result<()> {
     let mut parser = SqlParser::new("SELECT 1")?;
@@ -114,6 +171,7 @@ fn test_parse_create_index() -> Result<()> {
             assert_eq!(create_idx.index_name, "idx_users_email");
             assert_eq!(create_idx.table_name, "users");
             assert_eq!(create_idx.columns, vec!["email"]);
+            assert!(!create_idx.concurrently);
         }
         _ => panic!("Expected CREATE INDEX statement"),
     }
@@ -121,6 +179,29 @@ fn test_parse_create_index() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_parse_drop_index() -> Result<()> {
+    assert_eq!(
+        SqlParser::new("DROP INDEX idx_users_email ON users")?.parse()?,
+        SqlStatement::DropIndex(DropIndexStatement {
+            index_name: "idx_users_email".to_string(),
+            table_name: Some("users".to_string()),
+            if_exists: false,
+        })
+    );
+    assert_eq!(
+        SqlParser::new("DROP INDEX IF EXISTS idx_users_email")?.parse()?,
+        SqlStatement::DropIndex(DropIndexStatement {
+            index_name: "idx_users_email".to_string(),
+            table_name: None,
+            if_exists: true,
+        })
+    );
+    assert!(SqlParser::new("DROP INDEX")?.parse().is_err());
+
+    Ok(())
+}
+
 #[test]
 fn test_parse_create_index_multiple_columns() -> Result<()> {
     let mut parser =
@@ -139,6 +220,79 @@ fn test_parse_create_index_multiple_columns() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_parse_create_index_concurrently() -> Result<()> {
+    match SqlParser::new("CREATE INDEX CONCURRENTLY idx_email ON users (email)")?.parse()? {
+        SqlStatement::CreateIndex(create_idx) => {
+            assert!(create_idx.concurrently);
+            assert_eq!(create_idx.index_name, "idx_email");
+            assert_eq!(create_idx.table_name, "users");
+        }
+        _ => panic!("Expected CREATE INDEX statement"),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_parse_create_partial_index() -> Result<()> {
+    match SqlParser::new("CREATE INDEX idx_open ON orders (user_id) WHERE status = 'open'")?
+        .parse()?
+    {
+        SqlStatement::CreateIndex(create_idx) => {
+            assert_eq!(create_idx.columns, vec!["user_id"]);
+            assert!(matches!(
+                create_idx.where_clause,
+                Some(Expression::BinaryOp {
+                    op: BinaryOperator::Equal,
+                    ..
+                })
+            ));
+        }
+        _ => panic!("Expected CREATE INDEX statement"),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_parse_create_expression_index() -> Result<()> {
+    match SqlParser::new("CREATE INDEX ON users (LOWER(email), id)")?.parse()? {
+        SqlStatement::CreateIndex(create_idx) => {
+            assert_eq!(create_idx.index_name, "users_lower_email_id_idx");
+            assert_eq!(create_idx.columns, vec!["lower(email)", "id"]);
+            assert_eq!(create_idx.expressions.len(), 1);
+            assert_eq!(create_idx.expressions[0].0, "lower(email)");
+            assert!(matches!(
+                create_idx.expressions[0].1,
+                Expression::Function { .. }
+            ));
+            assert_eq!(create_idx.key_table_columns(), vec!["email", "id"]);
+        }
+        _ => panic!("Expected CREATE INDEX statement"),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_parse_create_hash_index() -> Result<()> {
+    match SqlParser::new("CREATE INDEX idx_email ON users USING HASH (email)")?.parse()? {
+        SqlStatement::CreateIndex(create_idx) => {
+            assert_eq!(create_idx.method, IndexMethod::Hash);
+            assert_eq!(create_idx.columns, vec!["email"]);
+        }
+        _ => panic!("Expected CREATE INDEX statement"),
+    }
+    match SqlParser::new("CREATE INDEX idx_email ON users (email)")?.parse()? {
+        SqlStatement::CreateIndex(create_idx) => assert_eq!(create_idx.method, IndexMethod::BTree),
+        _ => panic!("Expected CREATE INDEX statement"),
+    }
+    assert!(
+        SqlParser::new("CREATE INDEX idx_email ON users USING GIST (email)")?
+            .parse()
+            .is_err()
+    );
+    Ok(())
+}
+
 #[test]
 fn test_parse_create_table_with_different_types() -> Result<()> {
     let mut parser =
@@ -170,6 +324,40 @@ fn test_parse_create_table_with_different_types() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_parse_create_foreign_table() -> Result<()> {
+    let mut parser = SqlParser::new(
+        "CREATE FOREIGN TABLE trips (id INT, city VARCHAR(40)) USING CSV \
+         OPTIONS (path 'data/trips.csv', Header 'true', delimiter ';')",
+    )?;
+    match parser.parse()? {
+        SqlStatement::CreateForeignTable(create_stmt) => {
+            assert_eq!(create_stmt.table_name, "trips");
+            assert_eq!(create_stmt.columns.len(), 2);
+            assert_eq!(create_stmt.adapter, "csv");
+            assert_eq!(
+                create_stmt.options,
+                vec![
+                    ("path".to_string(), "data/trips.csv".to_string()),
+                    ("header".to_string(), "true".to_string()),
+                    ("delimiter".to_string(), ";".to_string()),
+                ]
+            );
+        }
+        _ => panic!("Expected CREATE FOREIGN TABLE statement"),
+    }
+
+    assert!(
+        SqlParser::new("CREATE FOREIGN TABLE t (id INT PRIMARY KEY) USING csv")?
+            .parse()
+            .is_err()
+    );
+    assert!(SqlParser::new("CREATE FOREIGN TABLE t (id INT)")?
+        .parse()
+        .is_err());
+    Ok(())
+}
+
 #[test]
 fn test_parse_transaction_statements() -> Result<()> {
     let mut parser = SqlParser::new("BEGIN TRANSACTION")?;
@@ -193,6 +381,159 @@ fn test_parse_transaction_statements() -> Result<()> {
         _ => panic!("Expected ROLLBACK"),
     }
 
+    assert_eq!(
+        SqlParser::new("PREPARE TRANSACTION 'tx-1'")?.parse()?,
+        SqlStatement::PrepareTransaction("tx-1".to_string())
+    );
+    assert_eq!(
+        SqlParser::new("COMMIT PREPARED 'tx-1'")?.parse()?,
+        SqlStatement::CommitPrepared("tx-1".to_string())
+    );
+    assert_eq!(
+        SqlParser::new("ROLLBACK PREPARED 'tx-1'")?.parse()?,
+        SqlStatement::RollbackPrepared("tx-1".to_string())
+    );
+    assert!(SqlParser::new("COMMIT PREPARED tx")?.parse().is_err());
+    assert!(SqlParser::new("PREPARE TRANSACTION ''")?.parse().is_err());
+    assert_eq!(
+        SqlParser::new("EXPORT SNAPSHOT")?.parse()?,
+        SqlStatement::ExportSnapshot
+    );
+    assert_eq!(
+        SqlParser::new("SET TRANSACTION SNAPSHOT '0000002A-0000000000000007'")?.parse()?,
+        SqlStatement::SetTransactionSnapshot("0000002A-0000000000000007".to_string())
+    );
+    assert!(SqlParser::new("SET TRANSACTION SNAPSHOT snap")?
+        .parse()
+        .is_err());
+    assert_eq!(
+        SqlParser::new("SET replication.synchronous_commit_timeout_ms = 250")?.parse()?,
+        SqlStatement::SetParameter {
+            name: "replication.synchronous_commit_timeout_ms".to_string(),
+            value: "250".to_string(),
+        }
+    );
+    assert_eq!(
+        SqlParser::new("SET language TO 'en'")?.parse()?,
+        SqlStatement::SetParameter {
+            name: "language".to_string(),
+            value: "en".to_string(),
+        }
+    );
+    assert_eq!(
+        SqlParser::new("SHOW network.port")?.parse()?,
+        SqlStatement::ShowParameter(Some("network.port".to_string()))
+    );
+    assert_eq!(
+        SqlParser::new("SHOW ALL")?.parse()?,
+        SqlStatement::ShowParameter(None)
+    );
+    assert!(SqlParser::new("SET network. = 1")?.parse().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_admin_commands() -> Result<()> {
+    assert_eq!(
+        SqlParser::new("CHECKPOINT")?.parse()?,
+        SqlStatement::Checkpoint
+    );
+    assert_eq!(
+        SqlParser::new("FLUSH LOGS")?.parse()?,
+        SqlStatement::FlushLogs
+    );
+    assert!(SqlParser::new("FLUSH TABLES")?.parse().is_err());
+    assert_eq!(
+        SqlParser::new("SHOW ENGINE STATUS")?.parse()?,
+        SqlStatement::ShowEngineStatus
+    );
+    assert_eq!(
+        SqlParser::new("SHOW TABLE STATUS")?.parse()?,
+        SqlStatement::ShowTableStatus
+    );
+    assert_eq!(
+        SqlParser::new("SET GLOBAL language = 'en'")?.parse()?,
+        SqlStatement::SetParameter {
+            name: "language".to_string(),
+            value: "en".to_string(),
+        }
+    );
+    assert_eq!(
+        SqlParser::new("PROFILE CPU FOR 30 SECONDS")?.parse()?,
+        SqlStatement::ProfileCpu {
+            seconds: 30,
+            format: ProfileFormat::Flamegraph,
+        }
+    );
+    assert_eq!(
+        SqlParser::new("profile cpu for 5 seconds format pprof")?.parse()?,
+        SqlStatement::ProfileCpu {
+            seconds: 5,
+            format: ProfileFormat::Pprof,
+        }
+    );
+    assert!(SqlParser::new("PROFILE CPU FOR 5 SECONDS FORMAT PNG")?
+        .parse()
+        .is_err());
+    assert_eq!(
+        SqlParser::new("FORGET 'alice'")?.parse()?,
+        SqlStatement::Forget(Literal::String("'alice'".to_string()))
+    );
+    assert_eq!(
+        SqlParser::new("FORGET 42")?.parse()?,
+        SqlStatement::Forget(Literal::Integer(42))
+    );
+    assert!(SqlParser::new("FORGET name")?.parse().is_err());
+    assert_eq!(
+        SqlParser::new("VACUUM")?.parse()?,
+        SqlStatement::Vacuum(None)
+    );
+    assert_eq!(
+        SqlParser::new("VACUUM orders")?.parse()?,
+        SqlStatement::Vacuum(Some("orders".to_string()))
+    );
+    assert_eq!(
+        SqlParser::new("CHECK TABLE")?.parse()?,
+        SqlStatement::CheckTable(None)
+    );
+    assert_eq!(
+        SqlParser::new("CHECK TABLE orders")?.parse()?,
+        SqlStatement::CheckTable(Some("orders".to_string()))
+    );
+    assert!(SqlParser::new("CHECK orders")?.parse().is_err());
+    assert_eq!(
+        SqlParser::new("ANALYZE")?.parse()?,
+        SqlStatement::Analyze(None)
+    );
+    assert_eq!(
+        SqlParser::new("ANALYZE orders")?.parse()?,
+        SqlStatement::Analyze(Some("orders".to_string()))
+    );
+    assert_eq!(
+        SqlParser::new("CREATE DATABASE staging TEMPLATE prod")?.parse()?,
+        SqlStatement::CreateDatabase {
+            name: "staging".to_string(),
+            template: Some("prod".to_string()),
+        }
+    );
+    assert_eq!(
+        SqlParser::new("create database scratch")?.parse()?,
+        SqlStatement::CreateDatabase {
+            name: "scratch".to_string(),
+            template: None,
+        }
+    );
+    assert!(SqlParser::new("CREATE DATABASE staging TEMPLATE")?
+        .parse()
+        .is_err());
+    match SqlParser::new("CREATE TABLE orders (id INTEGER, customer TEXT) ENCRYPT BY customer")?
+        .parse()?
+    {
+        SqlStatement::CreateTable(ct) => assert_eq!(ct.encrypt_by.as_deref(), Some("customer")),
+        other => panic!("expected CREATE TABLE, got {other:?}"),
+    }
+
     Ok(())
 }
 
@@ -566,6 +907,25 @@ fn test_parse_explain_analyze_insert() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_parse_explain_format() -> Result<()> {
+    for (sql, format) in [
+        ("EXPLAIN SELECT 1", ExplainFormat::Text),
+        ("EXPLAIN FORMAT json SELECT 1", ExplainFormat::Json),
+        ("EXPLAIN ANALYZE FORMAT DOT SELECT 1", ExplainFormat::Dot),
+        ("EXPLAIN FORMAT TEXT VERBOSE SELECT 1", ExplainFormat::Text),
+    ] {
+        match SqlParser::new(sql)?.parse()? {
+            SqlStatement::Explain(ex) => assert_eq!(ex.format, format, "{sql}"),
+            other => panic!("expected EXPLAIN, got {other:?}"),
+        }
+    }
+    assert!(SqlParser::new("EXPLAIN FORMAT XML SELECT 1")?
+        .parse()
+        .is_err());
+    Ok(())
+}
+
 #[test]
 fn test_parse_explain_nested_error() {
     let mut parser = SqlParser::new("EXPLAIN EXPLAIN SELECT 1").unwrap();
@@ -577,3 +937,46 @@ fn test_parse_explain_create_table_error() {
     let mut parser = SqlParser::new("EXPLAIN CREATE TABLE t (id INT)").unwrap();
     assert!(parser.parse().is_err());
 }
+
+#[test]
+fn test_recursion_depth_limit() {
+    let deep_parens = format!("SELECT {}1{}", "(".repeat(10_000), ")".repeat(10_000));
+    let err = SqlParser::new(&deep_parens).unwrap().parse().unwrap_err();
+    assert!(err.to_string().contains("nesting depth"));
+
+    let deep_not = format!("SELECT * FROM t WHERE {} a = 1", "NOT ".repeat(10_000));
+    assert!(SqlParser::new(&deep_not).unwrap().parse().is_err());
+
+    let mut deep_subquery = "SELECT 1".to_string();
+    for _ in 0..500 {
+        deep_subquery = format!("SELECT * FROM ({}) AS s", deep_subquery);
+    }
+    assert!(SqlParser::new(&deep_subquery).unwrap().parse().is_err());
+
+    // Nesting below the limit still parses.
+    let shallow = format!("SELECT {}1{}", "(".repeat(20), ")".repeat(20));
+    assert!(SqlParser::new(&shallow).unwrap().parse().is_ok());
+}
+
+#[test]
+fn test_parse_unchecked_never_panics() {
+    let inputs: &[&[u8]] = &[
+        b"",
+        b";;;",
+        b"SELECT 1; SELECT 2",
+        b"\xff\xfe\x00SELECT",
+        b"SELECT 'unterminated",
+        b"SELECT \"unterminated",
+        b"/* unterminated comment",
+        b"SELECT 99999999999999999999999",
+        b"SELECT 1e",
+        b"INSERT INTO t VALUES (",
+        b"CREATE TABLE t (a INT,",
+        b"SELECT CASE WHEN",
+    ];
+    for input in inputs {
+        let _ = crate::parser::parse_unchecked(input);
+    }
+    let stmts = crate::parser::parse_unchecked(b"SELECT 1; SELECT 2").unwrap();
+    assert_eq!(stmts.len(), 2);
+}
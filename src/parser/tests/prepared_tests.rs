@@ -15,6 +15,7 @@ fn minimal_select_stmt() -> SqlStatement {
             table: TableReference::Table {
                 name: "t".to_string(),
                 alias: None,
+                as_of: None,
             },
             joins: vec![],
         }),
@@ -79,3 +80,53 @@ fn test_prepared_param_mismatch() -> Result<()> {
     assert!(r.is_err());
     Ok(())
 }
+
+fn parse(sql: &str) -> Result<SqlStatement> {
+    crate::parser::SqlParser::new(sql)?.parse()
+}
+
+#[test]
+fn test_placeholders_parse_as_numbered_parameters() -> Result<()> {
+    use crate::parser::prepared::parameter_count;
+    let positional = parse("SELECT * FROM t WHERE a = ? AND b IN (?, ?)")?;
+    assert_eq!(parameter_count(&positional), 3);
+    let numbered = parse("UPDATE t SET a = $2 WHERE b = $1 OR c = $2")?;
+    assert_eq!(parameter_count(&numbered), 2);
+    assert_eq!(
+        parameter_count(&parse("INSERT INTO t (a, b) VALUES ($1, 'x')")?),
+        1
+    );
+    assert_eq!(parameter_count(&parse("SELECT '?', '$1' FROM t")?), 0);
+    assert!(parse("SELECT * FROM t WHERE a = ? AND b = $1").is_err());
+    assert!(parse("SELECT * FROM t WHERE a = $0").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_bind_parameters_replaces_placeholders() -> Result<()> {
+    use crate::parser::ast::Literal;
+    use crate::parser::prepared::bind_parameters;
+    let template = parse("SELECT a FROM t WHERE a = $1 AND EXISTS (SELECT b FROM u WHERE b = $2)")?;
+    let values = [
+        Expression::Literal(Literal::Integer(7)),
+        Expression::Literal(Literal::String("'x'".to_string())),
+    ];
+    let bound = bind_parameters(&template, &values)?;
+    assert_eq!(
+        bound,
+        parse("SELECT a FROM t WHERE a = 7 AND EXISTS (SELECT b FROM u WHERE b = 'x')")?
+    );
+    assert!(bind_parameters(&template, &values[..1]).is_err());
+
+    let c = PreparedStatementCache::new();
+    let SqlStatement::Prepare(prepare) = parse("PREPARE q AS DELETE FROM t WHERE a = $1")? else {
+        panic!("expected PREPARE");
+    };
+    c.prepare(prepare)?;
+    let out = c.execute(ExecuteStatement {
+        name: "q".to_string(),
+        params: vec![Expression::Literal(Literal::Integer(3))],
+    })?;
+    assert_eq!(out, parse("DELETE FROM t WHERE a = 3")?);
+    Ok(())
+}
@@ -1,7 +1,7 @@
 //! TDD-style parser coverage for SELECT with WHERE, ORDER BY, LIMIT, OFFSET.
 
 use crate::parser::ast::{
-    BinaryOperator, Expression, Literal, OrderDirection, SelectStatement, SqlStatement,
+    BinaryOperator, Expression, Literal, NullsOrder, OrderDirection, SelectStatement, SqlStatement,
 };
 use crate::parser::SqlParser;
 
@@ -56,6 +56,15 @@ fn select_parses_order_by_asc_desc() {
     assert_eq!(s.order_by[1].direction, OrderDirection::Desc);
 }
 
+#[test]
+fn select_parses_order_by_nulls_first_last() {
+    let s = assert_select("SELECT x FROM t ORDER BY x NULLS FIRST, y DESC NULLS LAST, z");
+    assert_eq!(s.order_by[0].nulls, Some(NullsOrder::First));
+    assert_eq!(s.order_by[1].direction, OrderDirection::Desc);
+    assert_eq!(s.order_by[1].nulls, Some(NullsOrder::Last));
+    assert_eq!(s.order_by[2].nulls, None);
+}
+
 #[test]
 fn select_parses_limit_offset() {
     let s = assert_select("SELECT * FROM t LIMIT 10 OFFSET 3");
@@ -144,6 +144,35 @@ fn from_parses_subquery_and_join_using() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn from_parses_table_function() -> Result<()> {
+    let mut p = SqlParser::new("SELECT * FROM index_advisor() AS a WHERE a.calls > 1")?;
+    let stmt = p.parse()?;
+    let SqlStatement::Select(s) = stmt else {
+        panic!("expected SELECT");
+    };
+    let from = s.from.expect("from");
+    assert_eq!(
+        from.table,
+        TableReference::Function {
+            name: "index_advisor".into(),
+            args: vec![],
+            alias: Some("a".into()),
+        }
+    );
+
+    let mut p = SqlParser::new("SELECT * FROM f(1, 'x')")?;
+    let SqlStatement::Select(s) = p.parse()? else {
+        panic!("expected SELECT");
+    };
+    let Some(TableReference::Function { args, alias, .. }) = s.from.map(|f| f.table) else {
+        panic!("expected table function");
+    };
+    assert_eq!(args.len(), 2);
+    assert_eq!(alias, None);
+    Ok(())
+}
+
 #[test]
 fn create_table_parses_constraints() -> Result<()> {
     let sql = "CREATE TABLE t (\
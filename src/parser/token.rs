@@ -228,6 +228,8 @@ pub enum TokenType {
     Colon,        // :
     DoubleColon,  // ::
     Question,     // ?
+    /// Numbered parameter placeholder (`$1`)
+    Parameter,
 
     // === Special tokens ===
     /// Comment (single-line or multi-line)
@@ -543,6 +545,7 @@ impl fmt::Display for TokenType {
             TokenType::Colon => ":",
             TokenType::DoubleColon => "::",
             TokenType::Question => "?",
+            TokenType::Parameter => "PARAMETER",
             TokenType::Comment => "COMMENT",
             TokenType::Whitespace => "WHITESPACE",
             TokenType::Newline => "NEWLINE",
@@ -1,12 +1,25 @@
 //! Advanced query optimizer for rustdb
-
-use crate::catalog::statistics::{ColumnStatistics, StatisticsManager, TableStatistics};
+//!
+//! Join enumeration: each region of inner and cross joins is flattened into its relations and
+//! `a=b` conditions, and a dynamic program over relation subsets builds the cheapest left-deep
+//! join tree (for up to `max_join_relations` relations; larger regions keep their order). Row
+//! counts and distinct values come from the [`StatisticsManager`]; every join of the tree gets
+//! the cheapest of nested loop, hash (build on the right input) and merge (both inputs ordered
+//! on the join keys) for its estimated input sizes. Outer joins keep their place and order.
+//!
+//! Query rewriting sets the selectivity of `column = literal` filters from the column's most
+//! common values and NULL fraction ([`ColumnStatistics::equality_selectivity`]).
+
+use crate::catalog::statistics::{
+    ColumnStatistics, ColumnValue, StatisticsManager, TableStatistics,
+};
 use crate::common::{Error, Result};
 use crate::parser::ast::{
-    BinaryOperator, Expression, SelectStatement, SqlStatement, UnaryOperator,
+    BinaryOperator, Expression, Literal, SelectStatement, SqlStatement, UnaryOperator,
 };
 use crate::planner::planner::{
-    ExecutionPlan, FilterNode, IndexScanNode, JoinNode, PlanNode, TableScanNode,
+    ExecutionPlan, FilterNode, IndexScanNode, JoinAlgorithm, JoinNode, JoinType, PlanNode,
+    SortDirection, TableScanNode,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -32,6 +45,10 @@ pub struct AdvancedOptimizerSettings {
     pub enable_expression_simplification: bool,
     /// Enable subquery extraction
     pub enable_subquery_extraction: bool,
+    /// Enable cost-based join order and algorithm selection
+    pub enable_join_reordering: bool,
+    /// Largest join region (in relations) whose order is enumerated
+    pub max_join_relations: usize,
     /// Enable debug logging
     pub enable_debug_logging: bool,
     /// Cost threshold for applying optimization
@@ -45,6 +62,8 @@ impl Default for AdvancedOptimizerSettings {
             enable_query_rewriting: true,
             enable_expression_simplification: true,
             enable_subquery_extraction: true,
+            enable_join_reordering: true,
+            max_join_relations: 10,
             enable_debug_logging: false,
             cost_threshold: 1000.0,
         }
@@ -66,10 +85,48 @@ pub struct AdvancedOptimizationStatistics {
     pub expression_simplifications: usize,
     /// Subquery extractions executed
     pub subquery_extractions: usize,
+    /// Join regions whose order was changed
+    pub join_reorders: usize,
     /// Statistics usage count
     pub statistics_usage_count: usize,
 }
 
+/// An input of a join region: a plan that is not itself an inner or cross join
+struct JoinRelation {
+    plan: PlanNode,
+    /// Base tables the plan reads, with their aliases
+    tables: Vec<(String, Option<String>)>,
+    rows: f64,
+    cost: f64,
+}
+
+/// An `a=b` condition of a join region
+struct JoinPredicate {
+    /// Column of each side and the relations (bit set) it comes from
+    left: (String, u32),
+    right: (String, u32),
+    selectivity: f64,
+}
+
+/// Cheapest join tree found for a set of relations
+#[derive(Clone)]
+struct JoinCandidate {
+    plan: PlanNode,
+    rows: f64,
+    cost: f64,
+    /// Relation indexes in join order
+    order: Vec<usize>,
+}
+
+/// Selectivity of a join condition without distinct value counts for its columns
+const DEFAULT_JOIN_SELECTIVITY: f64 = 0.1;
+/// Cost of comparing a pair of rows (nested loop) or of probing or merging one row
+const ROW_COMPARE_COST: f64 = 0.01;
+/// Cost of inserting one row into a hash table
+const HASH_BUILD_COST: f64 = 0.02;
+/// Fixed cost of setting up a hash table
+const HASH_SETUP_COST: f64 = 1.0;
+
 /// Advanced optimization result
 #[derive(Debug, Clone)]
 pub struct AdvancedOptimizationResult {
@@ -131,6 +188,19 @@ impl AdvancedQueryOptimizer {
             }
         }
 
+        // Choose join order and algorithms
+        let mut join_reorders = 0;
+        if self.settings.enable_join_reordering {
+            if let Some((new_plan, reorders)) = self.enumerate_joins(&optimized_plan)? {
+                optimized_plan = new_plan;
+                join_reorders = reorders;
+                messages.push(format!(
+                    "Chose join algorithms, reordering {reorders} join region(s)"
+                ));
+                optimizations_applied += 1;
+            }
+        }
+
         // Apply expression simplification
         if self.settings.enable_expression_simplification {
             if let Some((new_plan, msg)) = self.simplify_expressions(&optimized_plan)? {
@@ -176,6 +246,7 @@ impl AdvancedQueryOptimizer {
             } else {
                 0
             },
+            join_reorders,
             statistics_usage_count: if self.settings.enable_statistics_usage {
                 1
             } else {
@@ -265,13 +336,16 @@ impl AdvancedQueryOptimizer {
                 // Simplify filter predicate
                 let simplified_condition = self.simplify_condition(&filter.condition)?;
                 let optimized_input = self.rewrite_node_recursive(&filter.input)?;
+                let selectivity = self
+                    .equality_selectivity(filter, &optimized_input)
+                    .unwrap_or(filter.selectivity);
 
                 Ok(PlanNode::Filter(FilterNode {
                     condition: simplified_condition,
                     predicate: filter.predicate.clone(),
                     equality: filter.equality.clone(),
                     input: Box::new(optimized_input),
-                    selectivity: filter.selectivity,
+                    selectivity,
                     cost: filter.cost,
                 }))
             }
@@ -297,6 +371,21 @@ impl AdvancedQueryOptimizer {
         }
     }
 
+    /// Selectivity of a `column = literal` filter over `input` from the column statistics
+    fn equality_selectivity(&self, filter: &FilterNode, input: &PlanNode) -> Option<f64> {
+        let equality = filter.equality.as_ref()?;
+        let mut tables = Vec::new();
+        self.collect_tables(input, &mut tables);
+        let value = literal_value(&equality.literal);
+        tables.iter().find_map(|(table, _)| {
+            self.statistics_manager
+                .get_table_statistics(table)?
+                .column_statistics
+                .get(&equality.column)
+                .map(|c| c.equality_selectivity(&value))
+        })
+    }
+
     /// Simplify a filter predicate
     pub fn simplify_condition(&self, condition: &str) -> Result<String> {
         // Simplified implementation—real system would parse and simplify
@@ -323,6 +412,7 @@ impl AdvancedQueryOptimizer {
                 left: Box::new(right.clone()),
                 right: Box::new(left.clone()),
                 cost: join.cost,
+                algorithm: join.algorithm,
             })
         } else {
             Ok(JoinNode {
@@ -331,6 +421,7 @@ impl AdvancedQueryOptimizer {
                 left: Box::new(left.clone()),
                 right: Box::new(right.clone()),
                 cost: join.cost,
+                algorithm: join.algorithm,
             })
         }
     }
@@ -353,6 +444,306 @@ impl AdvancedQueryOptimizer {
         }
     }
 
+    /// Chooses the order and algorithms of the plan's joins; returns the new plan and the number
+    /// of join regions whose order changed, or `None` if nothing changed.
+    fn enumerate_joins(&self, plan: &ExecutionPlan) -> Result<Option<(ExecutionPlan, usize)>> {
+        let mut reorders = 0;
+        let root = self.enumerate_joins_recursive(&plan.root, &mut reorders)?;
+        if root == plan.root {
+            return Ok(None);
+        }
+        let mut new_plan = plan.clone();
+        new_plan.root = root;
+        Ok(Some((new_plan, reorders)))
+    }
+
+    fn enumerate_joins_recursive(&self, node: &PlanNode, reorders: &mut usize) -> Result<PlanNode> {
+        if !is_region_join(node) {
+            let mut node = node.clone();
+            for child in node.inputs_mut() {
+                *child = self.enumerate_joins_recursive(child, reorders)?;
+            }
+            return Ok(node);
+        }
+
+        // Flatten the region, attributing each condition's columns to relations.
+        let mut relations = Vec::new();
+        let mut conditions = Vec::new();
+        self.flatten_join_region(node, reorders, &mut relations, &mut conditions)?;
+        let predicates: Vec<JoinPredicate> = conditions
+            .iter()
+            .flat_map(|(condition, left, right)| {
+                condition
+                    .split(" AND ")
+                    .filter_map(|conjunct| self.join_predicate(conjunct, *left, *right, &relations))
+            })
+            .collect();
+
+        let n = relations.len();
+        let best = if n <= self.settings.max_join_relations && n < 32 {
+            self.best_left_deep_join(&relations, &predicates)
+        } else {
+            // Too many relations to enumerate: keep the order, choose the algorithms.
+            let mut candidate = relation_candidate(&relations, 0);
+            for r in 1..n {
+                candidate = self.join_step(&candidate, r, &relations, &predicates);
+            }
+            Some(candidate)
+        };
+        let Some(best) = best else {
+            return Err(Error::internal("join enumeration found no plan"));
+        };
+        if best.order.iter().enumerate().any(|(i, &r)| i != r) {
+            *reorders += 1;
+        }
+        Ok(best.plan)
+    }
+
+    /// Collects the relations of the region rooted at `node` in their current order, and each
+    /// join's condition with the relations (bit sets) of its left and right inputs.
+    fn flatten_join_region(
+        &self,
+        node: &PlanNode,
+        reorders: &mut usize,
+        relations: &mut Vec<JoinRelation>,
+        conditions: &mut Vec<(String, u32, u32)>,
+    ) -> Result<()> {
+        match node {
+            PlanNode::Join(join) if is_region_join(node) => {
+                let first = relations.len();
+                self.flatten_join_region(&join.left, reorders, relations, conditions)?;
+                let middle = relations.len();
+                self.flatten_join_region(&join.right, reorders, relations, conditions)?;
+                if !join.condition.is_empty() {
+                    conditions.push((
+                        join.condition.clone(),
+                        relation_range(first, middle),
+                        relation_range(middle, relations.len()),
+                    ));
+                }
+            }
+            _ => {
+                let plan = self.enumerate_joins_recursive(node, reorders)?;
+                let mut tables = Vec::new();
+                self.collect_tables(&plan, &mut tables);
+                relations.push(JoinRelation {
+                    rows: self.estimate_rows(&plan),
+                    cost: self.estimate_node_cost_with_statistics(&plan)?,
+                    plan,
+                    tables,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses `a=b` of a join whose inputs are the relations `left` and `right`, narrowing
+    /// each column to the one relation that has it when the statistics tell.
+    fn join_predicate(
+        &self,
+        conjunct: &str,
+        left: u32,
+        right: u32,
+        relations: &[JoinRelation],
+    ) -> Option<JoinPredicate> {
+        let (a, b) = conjunct.split_once('=')?;
+        let (a, b) = (a.trim().to_string(), b.trim().to_string());
+        let owners = |column: &str, within: u32| -> Option<u32> {
+            let matching: Vec<usize> = (0..relations.len())
+                .filter(|&i| within & (1 << i) != 0)
+                .filter(|&i| self.relation_has_column(&relations[i], column))
+                .collect();
+            match matching.as_slice() {
+                [i] => Some(1 << i),
+                _ => None,
+            }
+        };
+        // The condition may name the right input's column first.
+        let written = (owners(&a, left), owners(&b, right));
+        let swapped = (owners(&b, left), owners(&a, right));
+        let found = |o: &(Option<u32>, Option<u32>)| o.0.is_some() as u8 + o.1.is_some() as u8;
+        let (left_column, right_column, owners) = if found(&swapped) > found(&written) {
+            (b, a, swapped)
+        } else {
+            (a, b, written)
+        };
+        let left_set = owners.0.unwrap_or(left);
+        let right_set = owners.1.unwrap_or(right);
+        let distinct = |column: &str, set: u32| -> Option<f64> {
+            let i = set.trailing_zeros() as usize;
+            if set.count_ones() != 1 {
+                return None;
+            }
+            self.column_distinct_values(&relations[i], column)
+                .map(|d| d.min(relations[i].rows))
+        };
+        let selectivity = match (
+            distinct(&left_column, left_set),
+            distinct(&right_column, right_set),
+        ) {
+            (Some(l), Some(r)) => 1.0 / l.max(r).max(1.0),
+            (Some(d), None) | (None, Some(d)) => 1.0 / d.max(1.0),
+            (None, None) => DEFAULT_JOIN_SELECTIVITY,
+        };
+        Some(JoinPredicate {
+            left: (left_column, left_set),
+            right: (right_column, right_set),
+            selectivity,
+        })
+    }
+
+    /// Dynamic program over relation subsets: the cheapest left-deep tree joining all
+    /// `relations`, ties going to the current order.
+    fn best_left_deep_join(
+        &self,
+        relations: &[JoinRelation],
+        predicates: &[JoinPredicate],
+    ) -> Option<JoinCandidate> {
+        let n = relations.len();
+        let full = (1usize << n) - 1;
+        let mut best: Vec<Option<JoinCandidate>> = vec![None; full + 1];
+        for i in 0..n {
+            best[1 << i] = Some(relation_candidate(relations, i));
+        }
+        for mask in 1..=full {
+            if mask.count_ones() < 2 {
+                continue;
+            }
+            for r in (0..n).rev().filter(|r| mask & (1 << r) != 0) {
+                let Some(left) = &best[mask & !(1 << r)] else {
+                    continue;
+                };
+                let candidate = self.join_step(left, r, relations, predicates);
+                if best[mask]
+                    .as_ref()
+                    .is_none_or(|current| candidate.cost < current.cost)
+                {
+                    best[mask] = Some(candidate);
+                }
+            }
+        }
+        best[full].take()
+    }
+
+    /// Joins relation `r` as the right input of `left`.
+    fn join_step(
+        &self,
+        left: &JoinCandidate,
+        r: usize,
+        relations: &[JoinRelation],
+        predicates: &[JoinPredicate],
+    ) -> JoinCandidate {
+        let joined: u32 = left.order.iter().map(|&i| 1u32 << i).sum();
+        let all = joined | (1 << r);
+        let right = &relations[r];
+        let mut rows = left.rows * right.rows;
+        let mut keys = Vec::new();
+        for p in predicates {
+            let uses = p.left.1 | p.right.1;
+            if uses & !all != 0 || uses & !joined == 0 {
+                continue;
+            }
+            rows *= p.selectivity;
+            keys.push(if p.left.1 & (1 << r) == 0 {
+                (p.left.0.clone(), p.right.0.clone())
+            } else {
+                (p.right.0.clone(), p.left.0.clone())
+            });
+        }
+        let ordered = keys
+            .first()
+            .is_some_and(|(l, r)| ordered_on(&left.plan, l) && ordered_on(&right.plan, r));
+        let (algorithm, join_cost) =
+            join_algorithm_cost(left.rows, right.rows, !keys.is_empty(), ordered);
+        let cost = left.cost + right.cost + join_cost;
+        let condition = keys
+            .iter()
+            .map(|(l, r)| format!("{l}={r}"))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        let mut order = left.order.clone();
+        order.push(r);
+        JoinCandidate {
+            plan: PlanNode::Join(JoinNode {
+                join_type: if keys.is_empty() {
+                    JoinType::Cross
+                } else {
+                    JoinType::Inner
+                },
+                condition,
+                left: Box::new(left.plan.clone()),
+                right: Box::new(right.plan.clone()),
+                cost,
+                algorithm,
+            }),
+            rows: rows.max(1.0),
+            cost,
+            order,
+        }
+    }
+
+    /// Estimated output rows of `node`, from table statistics when available
+    fn estimate_rows(&self, node: &PlanNode) -> f64 {
+        let rows = match node {
+            PlanNode::TableScan(scan) => self
+                .statistics_manager
+                .get_table_statistics(&scan.table_name)
+                .map_or(scan.estimated_rows as f64, |t| t.total_rows as f64),
+            PlanNode::IndexScan(scan) => scan.estimated_rows as f64,
+            PlanNode::ForeignScan(scan) => scan.estimated_rows as f64,
+            PlanNode::Filter(filter) => self.estimate_rows(&filter.input) * filter.selectivity,
+            PlanNode::Limit(limit) => self.estimate_rows(&limit.input).min(limit.limit as f64),
+            PlanNode::Join(join) => self
+                .estimate_rows(&join.left)
+                .max(self.estimate_rows(&join.right)),
+            _ => self
+                .get_child_nodes(node)
+                .first()
+                .map_or(1.0, |child| self.estimate_rows(child)),
+        };
+        rows.max(1.0)
+    }
+
+    /// Base tables read under `node`, with their aliases
+    fn collect_tables(&self, node: &PlanNode, tables: &mut Vec<(String, Option<String>)>) {
+        match node {
+            PlanNode::TableScan(scan) => tables.push((scan.table_name.clone(), scan.alias.clone())),
+            PlanNode::IndexScan(scan) => tables.push((scan.table_name.clone(), None)),
+            PlanNode::ForeignScan(scan) => {
+                tables.push((scan.table_name.clone(), scan.alias.clone()))
+            }
+            _ => {
+                for child in self.get_child_nodes(node) {
+                    self.collect_tables(child, tables);
+                }
+            }
+        }
+    }
+
+    fn relation_has_column(&self, relation: &JoinRelation, column: &str) -> bool {
+        self.column_distinct_values(relation, column).is_some()
+    }
+
+    /// Distinct values of `column` (`c` or `t.c`) in the tables of `relation`
+    fn column_distinct_values(&self, relation: &JoinRelation, column: &str) -> Option<f64> {
+        let (qualifier, name) = match column.split_once('.') {
+            Some((q, c)) => (Some(q), c),
+            None => (None, column),
+        };
+        relation
+            .tables
+            .iter()
+            .filter(|(table, alias)| {
+                qualifier.is_none_or(|q| q == table || alias.as_deref() == Some(q))
+            })
+            .find_map(|(table, _)| {
+                self.statistics_manager
+                    .get_table_statistics(table)
+                    .and_then(|t| t.column_statistics.get(name))
+                    .map(|c| c.distinct_values as f64)
+            })
+    }
+
     /// Simplify expressions in the plan
     fn simplify_expressions(
         &self,
@@ -431,6 +822,7 @@ impl AdvancedQueryOptimizer {
         match node {
             PlanNode::TableScan(node) => node.cost,
             PlanNode::IndexScan(node) => node.cost,
+            PlanNode::ForeignScan(node) => node.cost,
             PlanNode::Filter(node) => node.cost,
             PlanNode::Projection(node) => node.cost,
             PlanNode::Join(node) => node.cost,
@@ -487,6 +879,95 @@ impl AdvancedQueryOptimizer {
     }
 }
 
+/// Whether `node` is an inner or cross join whose inputs may be reordered: every conjunct of
+/// its condition is an `a=b` the enumeration can move.
+fn is_region_join(node: &PlanNode) -> bool {
+    match node {
+        PlanNode::Join(join) => match join.join_type {
+            JoinType::Cross => true,
+            JoinType::Inner => {
+                !join.condition.is_empty() && join.condition.split(" AND ").all(|c| c.contains('='))
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Bit set of relations `from..to`
+fn literal_value(literal: &Literal) -> ColumnValue {
+    match literal {
+        Literal::Null => ColumnValue::Null,
+        Literal::Boolean(b) => ColumnValue::Boolean(*b),
+        Literal::Integer(i) => ColumnValue::Integer(*i),
+        Literal::Float(f) => ColumnValue::Float(*f),
+        Literal::String(s) => ColumnValue::String(s.clone()),
+    }
+}
+
+fn relation_range(from: usize, to: usize) -> u32 {
+    (from..to.min(32)).map(|i| 1u32 << i).sum()
+}
+
+fn relation_candidate(relations: &[JoinRelation], i: usize) -> JoinCandidate {
+    JoinCandidate {
+        plan: relations[i].plan.clone(),
+        rows: relations[i].rows,
+        cost: relations[i].cost,
+        order: vec![i],
+    }
+}
+
+/// Cheapest algorithm joining `left_rows` with `right_rows` rows and its cost: nested loop
+/// always works, hash and merge need an equality key, merge also inputs ordered on it.
+fn join_algorithm_cost(
+    left_rows: f64,
+    right_rows: f64,
+    equi: bool,
+    ordered: bool,
+) -> (JoinAlgorithm, f64) {
+    let mut best = (
+        JoinAlgorithm::NestedLoop,
+        left_rows * right_rows * ROW_COMPARE_COST,
+    );
+    if equi {
+        let hash = HASH_SETUP_COST + right_rows * HASH_BUILD_COST + left_rows * ROW_COMPARE_COST;
+        if hash < best.1 {
+            best = (JoinAlgorithm::Hash, hash);
+        }
+        let merge = (left_rows + right_rows) * ROW_COMPARE_COST;
+        if ordered && merge < best.1 {
+            best = (JoinAlgorithm::Merge, merge);
+        }
+    }
+    best
+}
+
+/// Whether the rows of `node` come out ordered on `column`
+fn ordered_on(node: &PlanNode, column: &str) -> bool {
+    let bare = |c: &str| c.rsplit('.').next().unwrap_or(c).to_string();
+    match node {
+        PlanNode::Sort(sort) => sort.sort_columns.first().is_some_and(|s| {
+            // The merge join reads keys ascending with NULLs last.
+            bare(&s.column) == bare(column) && s.direction == SortDirection::Asc && !s.nulls_first()
+        }),
+        PlanNode::IndexScan(scan) => scan
+            .conditions
+            .first()
+            .is_some_and(|c| bare(&c.column) == bare(column)),
+        PlanNode::Join(join) if join.algorithm == JoinAlgorithm::Merge => join
+            .condition
+            .split(" AND ")
+            .next()
+            .and_then(|c| c.split_once('='))
+            .is_some_and(|(l, r)| bare(l) == bare(column) || bare(r) == bare(column)),
+        PlanNode::Filter(filter) => ordered_on(&filter.input, column),
+        PlanNode::Projection(projection) => ordered_on(&projection.input, column),
+        PlanNode::Limit(limit) => ordered_on(&limit.input, column),
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,302 @@
+//! Cardinality feedback: the rows plan nodes actually returned, kept by node fingerprint so that
+//! later plans of the same scans and predicates start from them instead of fixed guesses.
+//!
+//! A [`fingerprint`] hashes what decides the rows of a node — tables, index conditions, filter
+//! and join conditions, limits — for the node and its inputs, but no costs or estimates, so a
+//! query planned again finds what its nodes returned last time. The executor reports the rows of
+//! each scan and filter it ran to the end (see [`crate::executor::cardinality`]), and
+//! [`CardinalityFeedback::correct`] replaces the row estimates of scans and the selectivity of
+//! filters with the rows seen on their last run.
+
+use crate::common::{Error, Result};
+use crate::planner::planner::{ExecutionPlan, PlanNode};
+use crate::storage::atomic_file::write_atomic;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+/// Fingerprints kept; nodes first seen once the store is full are not recorded.
+pub const MAX_FEEDBACK_ENTRIES: usize = 10_000;
+
+/// What the executor saw of one plan node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CardinalityObservation {
+    /// Rows the plan estimated when the node last ran
+    pub estimated_rows: u64,
+    /// Rows the node returned when it last ran
+    pub actual_rows: u64,
+    /// Runs seen
+    pub executions: u64,
+}
+
+/// Observed row counts by node fingerprint
+#[derive(Debug, Default)]
+pub struct CardinalityFeedback {
+    entries: RwLock<HashMap<u64, CardinalityObservation>>,
+    /// Changed since loaded or saved
+    dirty: AtomicBool,
+}
+
+impl CardinalityFeedback {
+    /// Empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store saved at `path` by [`Self::save`]; empty when the file does not exist
+    pub fn load(path: &Path) -> Result<Self> {
+        let entries: BTreeMap<u64, CardinalityObservation> = match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| Error::internal(format!("{}: {e}", path.display())))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(Error::internal(format!("{}: {e}", path.display()))),
+        };
+        Ok(Self {
+            entries: RwLock::new(entries.into_iter().collect()),
+            dirty: AtomicBool::new(false),
+        })
+    }
+
+    /// Writes the store to `path` if it changed since loaded or last saved.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if !self.dirty.swap(false, Ordering::AcqRel) {
+            return Ok(());
+        }
+        let json = {
+            let entries = self.read()?;
+            let sorted: BTreeMap<_, _> = entries.iter().map(|(k, v)| (*k, *v)).collect();
+            serde_json::to_vec_pretty(&sorted).map_err(|e| Error::internal(e.to_string()))?
+        };
+        let written = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .map_err(|e| Error::internal(format!("{}: {e}", path.display())))
+            .and_then(|()| write_atomic(path, &json));
+        if written.is_err() {
+            self.dirty.store(true, Ordering::Release);
+        }
+        written
+    }
+
+    /// The node with `fingerprint`, estimated at `estimated_rows`, returned `actual_rows`.
+    pub fn record(&self, fingerprint: u64, estimated_rows: u64, actual_rows: u64) {
+        let Ok(mut entries) = self.entries.write() else {
+            return;
+        };
+        let full = entries.len() >= MAX_FEEDBACK_ENTRIES;
+        match entries.get_mut(&fingerprint) {
+            Some(seen) => {
+                seen.estimated_rows = estimated_rows;
+                seen.actual_rows = actual_rows;
+                seen.executions += 1;
+            }
+            None if full => return,
+            None => {
+                entries.insert(
+                    fingerprint,
+                    CardinalityObservation {
+                        estimated_rows,
+                        actual_rows,
+                        executions: 1,
+                    },
+                );
+            }
+        }
+        self.dirty.store(true, Ordering::Release);
+    }
+
+    /// Last observation of the node with `fingerprint`
+    pub fn observation(&self, fingerprint: u64) -> Option<CardinalityObservation> {
+        self.read().ok()?.get(&fingerprint).copied()
+    }
+
+    /// Fingerprints with an observation
+    pub fn len(&self) -> usize {
+        self.read().map_or(0, |e| e.len())
+    }
+
+    /// Whether nothing was observed yet
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Replaces the estimates of the plan's observed scans and filters with the rows they
+    /// returned last time; returns how many nodes changed.
+    pub fn correct(&self, plan: &mut ExecutionPlan) -> usize {
+        let Ok(entries) = self.read() else {
+            return 0;
+        };
+        if entries.is_empty() {
+            return 0;
+        }
+        let corrected = correct_node(&entries, &mut plan.root);
+        if corrected > 0 {
+            plan.metadata.estimated_rows = plan.root.estimated_rows();
+        }
+        corrected
+    }
+
+    fn read(&self) -> Result<std::sync::RwLockReadGuard<'_, HashMap<u64, CardinalityObservation>>> {
+        self.entries
+            .read()
+            .map_err(|_| Error::internal("cardinality feedback lock poisoned"))
+    }
+}
+
+/// Whether the executor reports the rows of `node`: scans and filters, whose estimates
+/// [`CardinalityFeedback::correct`] can replace
+pub fn is_observed(node: &PlanNode) -> bool {
+    matches!(
+        node,
+        PlanNode::TableScan(_)
+            | PlanNode::IndexScan(_)
+            | PlanNode::ForeignScan(_)
+            | PlanNode::Filter(_)
+    )
+}
+
+/// Hash of what decides the rows `node` returns, stable across runs and restarts
+pub fn fingerprint(node: &PlanNode) -> u64 {
+    let mut hasher = Fnv1a::default();
+    hash_node(node, &mut hasher);
+    hasher.0
+}
+
+fn correct_node(entries: &HashMap<u64, CardinalityObservation>, node: &mut PlanNode) -> usize {
+    let mut corrected: usize = node
+        .inputs_mut()
+        .into_iter()
+        .map(|input| correct_node(entries, input))
+        .sum();
+    if !is_observed(node) {
+        return corrected;
+    }
+    let Some(seen) = entries.get(&fingerprint(node)) else {
+        return corrected;
+    };
+    let actual = seen.actual_rows as usize;
+    let changed = match node {
+        PlanNode::TableScan(n) => std::mem::replace(&mut n.estimated_rows, actual) != actual,
+        PlanNode::IndexScan(n) => std::mem::replace(&mut n.estimated_rows, actual) != actual,
+        PlanNode::ForeignScan(n) => std::mem::replace(&mut n.estimated_rows, actual) != actual,
+        PlanNode::Filter(n) => {
+            let input = n.input.estimated_rows();
+            // Half a row up, so the truncating estimate of the filter comes out at `actual`.
+            let selectivity = if input == 0 {
+                1.0
+            } else {
+                ((actual as f64 + 0.5) / input as f64).min(1.0)
+            };
+            std::mem::replace(&mut n.selectivity, selectivity) != selectivity
+        }
+        _ => false,
+    };
+    if changed {
+        corrected += 1;
+    }
+    corrected
+}
+
+fn hash_node(node: &PlanNode, h: &mut Fnv1a) {
+    match node {
+        PlanNode::TableScan(n) => {
+            h.write("scan");
+            h.write(&n.table_name);
+            h.write(n.alias.as_deref().unwrap_or(""));
+            h.write(n.filter.as_deref().unwrap_or(""));
+        }
+        PlanNode::IndexScan(n) => {
+            h.write("index");
+            h.write(&n.table_name);
+            h.write(&n.index_name);
+            for c in &n.conditions {
+                h.write(&c.column);
+                h.write(&c.operator);
+                h.write(&c.value);
+            }
+        }
+        PlanNode::ForeignScan(n) => {
+            h.write("foreign");
+            h.write(&n.table_name);
+            h.write(n.alias.as_deref().unwrap_or(""));
+            h.write(n.filter.as_deref().unwrap_or(""));
+            h.write(&format!("{:?}", n.limit));
+        }
+        PlanNode::Filter(n) => {
+            h.write("filter");
+            h.write(&n.condition);
+        }
+        PlanNode::Projection(_) => h.write("projection"),
+        PlanNode::Join(n) => {
+            h.write("join");
+            h.write(&format!("{:?}", n.join_type));
+            h.write(&n.condition);
+        }
+        PlanNode::GroupBy(n) => {
+            h.write("group");
+            for c in &n.group_columns {
+                h.write(c);
+            }
+        }
+        PlanNode::Sort(_) => h.write("sort"),
+        PlanNode::Limit(n) => {
+            h.write("limit");
+            h.write(&n.limit.to_string());
+        }
+        PlanNode::Offset(n) => {
+            h.write("offset");
+            h.write(&n.offset.to_string());
+        }
+        PlanNode::Aggregate(_) => h.write("aggregate"),
+        PlanNode::SetOp(n) => {
+            h.write("setop");
+            h.write(&format!("{:?} {}", n.op, n.all));
+        }
+        PlanNode::SemiJoin(n) => {
+            h.write("semi");
+            h.write(&n.condition);
+        }
+        PlanNode::AntiJoin(n) => {
+            h.write("anti");
+            h.write(&n.condition);
+        }
+        PlanNode::Distinct(_) => h.write("distinct"),
+        PlanNode::Insert(n) => {
+            h.write("insert");
+            h.write(&n.table_name);
+        }
+        PlanNode::Update(n) => {
+            h.write("update");
+            h.write(&n.table_name);
+        }
+        PlanNode::Delete(n) => {
+            h.write("delete");
+            h.write(&n.table_name);
+        }
+    }
+    for input in node.inputs() {
+        hash_node(input, h);
+    }
+    h.write(")");
+}
+
+/// 64-bit FNV-1a: fingerprints are persisted, so they must not depend on the std hasher.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv1a {
+    /// Hashes `s` and a terminator, so adjacent fields cannot run into each other.
+    fn write(&mut self, s: &str) {
+        for b in s.bytes().chain([0xff]) {
+            self.0 ^= u64::from(b);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
@@ -1,7 +1,9 @@
 //! Text formatting for `EXPLAIN` query plans.
 
+use crate::planner::cardinality_feedback::fingerprint;
 use crate::planner::optimizer::OptimizationResult;
 use crate::planner::planner::{ExecutionPlan, PlanNode, SetOpType};
+use std::collections::HashMap;
 
 /// Options controlling plan text output.
 #[derive(Debug, Clone, Copy, Default)]
@@ -13,14 +15,40 @@ pub struct ExplainFormatOptions {
     pub rows_affected: Option<u64>,
 }
 
+/// What one plan node did when `EXPLAIN ANALYZE` ran it (see [`crate::executor::analyze`])
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NodeActuals {
+    /// Rows the node returned
+    pub rows: u64,
+    /// Time spent producing them, its inputs included, in milliseconds
+    pub time_ms: f64,
+    /// Most memory the node held at once, in bytes
+    pub peak_memory_bytes: usize,
+    /// Times the node wrote rows to temporary files
+    pub spill_count: usize,
+    /// Bytes it wrote to temporary files
+    pub spilled_bytes: u64,
+}
+
 /// Format a full EXPLAIN result as lines suitable for a single-column result set.
 pub fn format_explain_output(
     plan: &ExecutionPlan,
     opt: &OptimizationResult,
     opts: ExplainFormatOptions,
+) -> Vec<String> {
+    format_explain_analyze_output(plan, opt, opts, &HashMap::new())
+}
+
+/// [`format_explain_output`] with an `Actual:` line under each node found in `actuals`, keyed
+/// by the node's [`fingerprint`]
+pub fn format_explain_analyze_output(
+    plan: &ExecutionPlan,
+    opt: &OptimizationResult,
+    opts: ExplainFormatOptions,
+    actuals: &HashMap<u64, NodeActuals>,
 ) -> Vec<String> {
     let mut lines = Vec::new();
-    format_plan_node(&plan.root, 0, &mut lines);
+    format_plan_node(&plan.root, 0, &mut lines, actuals);
     lines.push(String::new());
     lines.push(format!(
         "Planning: cost={:.2} rows={} ops={} depth={} tables={} joins={}",
@@ -55,8 +83,28 @@ fn indent(depth: usize) -> String {
     "  ".repeat(depth)
 }
 
-fn format_plan_node(node: &PlanNode, depth: usize, lines: &mut Vec<String>) {
+fn format_actuals(actual: &NodeActuals) -> String {
+    let mut line = format!("rows={} time={:.3} ms", actual.rows, actual.time_ms);
+    if actual.peak_memory_bytes > 0 {
+        line.push_str(&format!(" memory={} bytes", actual.peak_memory_bytes));
+    }
+    if actual.spill_count > 0 {
+        line.push_str(&format!(
+            " spills={} spilled={} bytes",
+            actual.spill_count, actual.spilled_bytes
+        ));
+    }
+    line
+}
+
+fn format_plan_node(
+    node: &PlanNode,
+    depth: usize,
+    lines: &mut Vec<String>,
+    actuals: &HashMap<u64, NodeActuals>,
+) {
     let pad = indent(depth);
+    let header = lines.len();
     match node {
         PlanNode::TableScan(n) => {
             let alias = n
@@ -74,6 +122,24 @@ fn format_plan_node(node: &PlanNode, depth: usize, lines: &mut Vec<String>) {
                 n.table_name, alias, n.cost, n.estimated_rows, filt
             ));
         }
+        PlanNode::ForeignScan(n) => {
+            let alias = n
+                .alias
+                .as_ref()
+                .map(|a| format!(" AS {}", a))
+                .unwrap_or_default();
+            let limit = n.limit.map(|l| format!(" limit={}", l)).unwrap_or_default();
+            let filt = n
+                .filter
+                .as_ref()
+                .map(|f| format!(" filter={}", f))
+                .unwrap_or_default();
+            lines.push(format!(
+                "{pad}Foreign Scan on {}{} using {} (cost={:.2} rows={}){}{}",
+                n.table_name, alias, n.table.adapter, n.cost, n.estimated_rows, limit, filt
+            ));
+            lines.push(format!("{pad}  Columns: {}", n.projection.join(", ")));
+        }
         PlanNode::IndexScan(n) => {
             lines.push(format!(
                 "{pad}Index Scan on {} using {} (cost={:.2} rows={})",
@@ -91,7 +157,7 @@ fn format_plan_node(node: &PlanNode, depth: usize, lines: &mut Vec<String>) {
                 "{pad}Filter: {} (cost={:.2} selectivity={:.4})",
                 n.condition, n.cost, n.selectivity
             ));
-            format_plan_node(&n.input, depth + 1, lines);
+            format_plan_node(&n.input, depth + 1, lines, actuals);
         }
         PlanNode::Projection(n) => {
             let cols = if n.columns.is_empty() {
@@ -104,7 +170,7 @@ fn format_plan_node(node: &PlanNode, depth: usize, lines: &mut Vec<String>) {
                     .join(", ")
             };
             lines.push(format!("{pad}Projection: {} (cost={:.2})", cols, n.cost));
-            format_plan_node(&n.input, depth + 1, lines);
+            format_plan_node(&n.input, depth + 1, lines, actuals);
         }
         PlanNode::Join(n) => {
             lines.push(format!(
@@ -112,38 +178,38 @@ fn format_plan_node(node: &PlanNode, depth: usize, lines: &mut Vec<String>) {
                 n.join_type, n.condition, n.cost
             ));
             lines.push(format!("{pad}  -> Left:"));
-            format_plan_node(&n.left, depth + 2, lines);
+            format_plan_node(&n.left, depth + 2, lines, actuals);
             lines.push(format!("{pad}  -> Right:"));
-            format_plan_node(&n.right, depth + 2, lines);
+            format_plan_node(&n.right, depth + 2, lines, actuals);
         }
         PlanNode::GroupBy(n) => {
             lines.push(format!(
                 "{pad}Group By: {:?} aggregates={:?} (cost={:.2})",
                 n.group_columns, n.aggregates, n.cost
             ));
-            format_plan_node(&n.input, depth + 1, lines);
+            format_plan_node(&n.input, depth + 1, lines, actuals);
         }
         PlanNode::Sort(n) => {
             lines.push(format!(
                 "{pad}Sort: {:?} (cost={:.2})",
                 n.sort_columns, n.cost
             ));
-            format_plan_node(&n.input, depth + 1, lines);
+            format_plan_node(&n.input, depth + 1, lines, actuals);
         }
         PlanNode::Limit(n) => {
             lines.push(format!("{pad}Limit: {} (cost={:.2})", n.limit, n.cost));
-            format_plan_node(&n.input, depth + 1, lines);
+            format_plan_node(&n.input, depth + 1, lines, actuals);
         }
         PlanNode::Offset(n) => {
             lines.push(format!("{pad}Offset: {} (cost={:.2})", n.offset, n.cost));
-            format_plan_node(&n.input, depth + 1, lines);
+            format_plan_node(&n.input, depth + 1, lines, actuals);
         }
         PlanNode::Aggregate(n) => {
             lines.push(format!(
                 "{pad}Aggregate: {:?} (cost={:.2})",
                 n.aggregates, n.cost
             ));
-            format_plan_node(&n.input, depth + 1, lines);
+            format_plan_node(&n.input, depth + 1, lines, actuals);
         }
         PlanNode::Insert(n) => {
             lines.push(format!(
@@ -152,7 +218,7 @@ fn format_plan_node(node: &PlanNode, depth: usize, lines: &mut Vec<String>) {
             ));
             if let Some(sub) = &n.insert_subplan {
                 lines.push(format!("{pad}  -> Subplan:"));
-                format_plan_node(sub, depth + 2, lines);
+                format_plan_node(sub, depth + 2, lines, actuals);
             }
         }
         PlanNode::Update(n) => {
@@ -179,7 +245,7 @@ fn format_plan_node(node: &PlanNode, depth: usize, lines: &mut Vec<String>) {
         }
         PlanNode::Distinct(n) => {
             lines.push(format!("{pad}Distinct (cost={:.2})", n.cost));
-            format_plan_node(&n.input, depth + 1, lines);
+            format_plan_node(&n.input, depth + 1, lines, actuals);
         }
         PlanNode::SetOp(n) => {
             let op = match n.op {
@@ -190,27 +256,33 @@ fn format_plan_node(node: &PlanNode, depth: usize, lines: &mut Vec<String>) {
             let all = if n.all { " ALL" } else { "" };
             lines.push(format!("{pad}{op}{all} (cost={:.2})", n.cost));
             lines.push(format!("{pad}  -> Left:"));
-            format_plan_node(&n.left, depth + 2, lines);
+            format_plan_node(&n.left, depth + 2, lines, actuals);
             lines.push(format!("{pad}  -> Right:"));
-            format_plan_node(&n.right, depth + 2, lines);
+            format_plan_node(&n.right, depth + 2, lines, actuals);
         }
         PlanNode::SemiJoin(n) => {
             lines.push(format!(
                 "{pad}Semi Join: {} (cost={:.2})",
                 n.condition, n.cost
             ));
-            format_plan_node(&n.left, depth + 1, lines);
-            format_plan_node(&n.right, depth + 1, lines);
+            format_plan_node(&n.left, depth + 1, lines, actuals);
+            format_plan_node(&n.right, depth + 1, lines, actuals);
         }
         PlanNode::AntiJoin(n) => {
             lines.push(format!(
                 "{pad}Anti Join: {} (cost={:.2})",
                 n.condition, n.cost
             ));
-            format_plan_node(&n.left, depth + 1, lines);
-            format_plan_node(&n.right, depth + 1, lines);
+            format_plan_node(&n.left, depth + 1, lines, actuals);
+            format_plan_node(&n.right, depth + 1, lines, actuals);
         }
     }
+    if let Some(actual) = actuals.get(&fingerprint(node)) {
+        lines.insert(
+            header + 1,
+            format!("{pad}  Actual: {}", format_actuals(actual)),
+        );
+    }
 }
 
 #[cfg(test)]
@@ -235,6 +307,7 @@ mod tests {
                 estimated_cost: 1.5,
                 estimated_rows: 10,
                 created_at: SystemTime::UNIX_EPOCH,
+                actual_rows: None,
                 statistics: PlanStatistics {
                     operator_count: 1,
                     max_depth: 1,
@@ -1,8 +1,10 @@
 //! Query planner for rustdb
 
 pub mod advanced_optimizer;
+pub mod cardinality_feedback;
 pub mod explain_format;
 pub mod optimizer;
+pub mod plan_graph;
 pub mod planner;
 
 #[cfg(test)]
@@ -12,8 +14,13 @@ pub use advanced_optimizer::{
     AdvancedOptimizationResult, AdvancedOptimizationStatistics, AdvancedOptimizerSettings,
     AdvancedQueryOptimizer,
 };
-pub use explain_format::{format_explain_output, ExplainFormatOptions};
+pub use cardinality_feedback::{CardinalityFeedback, CardinalityObservation};
+pub use explain_format::{
+    format_explain_analyze_output, format_explain_output, ExplainFormatOptions, NodeActuals,
+};
 pub use optimizer::{
     OptimizationResult, OptimizationStatistics, OptimizerSettings, QueryOptimizer,
 };
-pub use planner::{CacheStats, ExecutionPlan, PlanNode, PlannerSettings, QueryPlanner};
+pub use planner::{
+    CacheStats, ExecutionPlan, JoinAlgorithm, PlanNode, PlannerSettings, QueryPlanner,
+};
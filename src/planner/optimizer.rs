@@ -2,13 +2,16 @@
 
 use crate::analyzer::{AnalysisContext, SemanticAnalyzer};
 use crate::common::{Error, Result};
+use crate::parser::ast::{BinaryOperator, Expression, IndexMethod, Literal};
+use crate::planner::cardinality_feedback::CardinalityFeedback;
 use crate::planner::planner::{
-    estimate_selectivity, extract_equality_filters, extract_simple_equality, literal_to_string,
-    ExecutionPlan, FilterNode, IndexCondition, IndexScanNode, JoinNode, PlanNode, ProjectionNode,
-    SemiJoinNode, TableScanNode,
+    estimate_selectivity, extract_equality_filters, extract_expression_equality_filters,
+    extract_simple_equality, literal_to_string, ExecutionPlan, FilterNode, IndexCondition,
+    IndexScanNode, JoinNode, PlanNode, ProjectionNode, SemiJoinNode, TableScanNode,
 };
 use crate::storage::index_registry::IndexRegistry;
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -23,6 +26,8 @@ pub struct QueryOptimizer {
     statistics: Mutex<OptimizationStatistics>,
     /// Optional index registry for index selection
     index_registry: Option<Arc<IndexRegistry>>,
+    /// Optional row counts of earlier runs, correcting scan and filter estimates
+    cardinality_feedback: Option<Arc<CardinalityFeedback>>,
 }
 
 /// Optimizer settings
@@ -98,6 +103,127 @@ fn leading_index_conditions(
     }
 }
 
+/// Whether every row matching the query predicate `query` matches `index`, the predicate of a
+/// partial index. Each `AND` conjunct of `index` must be a conjunct of `query`, or follow from
+/// one comparing the same column with a literal (`x = 5` implies `x > 1` and `x IS NOT NULL`,
+/// `x > 10` implies `x >= 5`).
+pub(crate) fn predicate_implies(query: &Expression, index: &Expression) -> bool {
+    let mut have = Vec::new();
+    collect_conjuncts(query, &mut have);
+    let mut need = Vec::new();
+    collect_conjuncts(index, &mut need);
+    need.iter()
+        .all(|n| have.iter().any(|h| conjunct_implies(h, n)))
+}
+
+fn collect_conjuncts<'a>(expr: &'a Expression, out: &mut Vec<&'a Expression>) {
+    match expr {
+        Expression::BinaryOp {
+            left,
+            op: BinaryOperator::And,
+            right,
+        } => {
+            collect_conjuncts(left, out);
+            collect_conjuncts(right, out);
+        }
+        _ => out.push(expr),
+    }
+}
+
+fn conjunct_implies(have: &Expression, need: &Expression) -> bool {
+    if have == need {
+        return true;
+    }
+    let Some((column, op, value)) = column_comparison(have) else {
+        return false;
+    };
+    if let Expression::IsNull {
+        expr,
+        negated: true,
+    } = need
+    {
+        // Comparisons with a literal are never true for NULL.
+        return column_name(expr) == Some(column) && *value != Literal::Null;
+    }
+    let Some((need_column, need_op, need_value)) = column_comparison(need) else {
+        return false;
+    };
+    let Some(ord) = (column == need_column)
+        .then(|| compare_literals(value, need_value))
+        .flatten()
+    else {
+        return false;
+    };
+    use BinaryOperator::*;
+    match (op, need_op) {
+        (Equal, Equal) => ord == Ordering::Equal,
+        (Equal, NotEqual) => ord != Ordering::Equal,
+        (Equal, LessThan) => ord == Ordering::Less,
+        (Equal, LessThanOrEqual) => ord != Ordering::Greater,
+        (Equal, GreaterThan) => ord == Ordering::Greater,
+        (Equal, GreaterThanOrEqual) => ord != Ordering::Less,
+        (GreaterThan, NotEqual | GreaterThan | GreaterThanOrEqual) => ord != Ordering::Less,
+        (GreaterThanOrEqual, NotEqual | GreaterThan) => ord == Ordering::Greater,
+        (GreaterThanOrEqual, GreaterThanOrEqual) => ord != Ordering::Less,
+        (LessThan, NotEqual | LessThan | LessThanOrEqual) => ord != Ordering::Greater,
+        (LessThanOrEqual, NotEqual | LessThan) => ord == Ordering::Less,
+        (LessThanOrEqual, LessThanOrEqual) => ord != Ordering::Greater,
+        _ => false,
+    }
+}
+
+/// `column op literal` (or `literal op column`, flipped).
+fn column_comparison(expr: &Expression) -> Option<(&str, &BinaryOperator, &Literal)> {
+    let Expression::BinaryOp { left, op, right } = expr else {
+        return None;
+    };
+    if !matches!(
+        op,
+        BinaryOperator::Equal
+            | BinaryOperator::NotEqual
+            | BinaryOperator::LessThan
+            | BinaryOperator::LessThanOrEqual
+            | BinaryOperator::GreaterThan
+            | BinaryOperator::GreaterThanOrEqual
+    ) {
+        return None;
+    }
+    match (left.as_ref(), right.as_ref()) {
+        (column, Expression::Literal(value)) => Some((column_name(column)?, op, value)),
+        (Expression::Literal(value), column) => {
+            let flipped = match op {
+                BinaryOperator::LessThan => &BinaryOperator::GreaterThan,
+                BinaryOperator::LessThanOrEqual => &BinaryOperator::GreaterThanOrEqual,
+                BinaryOperator::GreaterThan => &BinaryOperator::LessThan,
+                BinaryOperator::GreaterThanOrEqual => &BinaryOperator::LessThanOrEqual,
+                other => other,
+            };
+            Some((column_name(column)?, flipped, value))
+        }
+        _ => None,
+    }
+}
+
+fn column_name(expr: &Expression) -> Option<&str> {
+    match expr {
+        Expression::Identifier(name) => Some(name),
+        Expression::QualifiedIdentifier { column, .. } => Some(column),
+        _ => None,
+    }
+}
+
+fn compare_literals(a: &Literal, b: &Literal) -> Option<Ordering> {
+    match (a, b) {
+        (Literal::Integer(x), Literal::Integer(y)) => Some(x.cmp(y)),
+        (Literal::Integer(x), Literal::Float(y)) => (*x as f64).partial_cmp(y),
+        (Literal::Float(x), Literal::Integer(y)) => x.partial_cmp(&(*y as f64)),
+        (Literal::Float(x), Literal::Float(y)) => x.partial_cmp(y),
+        (Literal::String(x), Literal::String(y)) => Some(x.cmp(y)),
+        (Literal::Boolean(x), Literal::Boolean(y)) => Some(x.cmp(y)),
+        _ => None,
+    }
+}
+
 /// Optimization result
 #[derive(Debug, Clone)]
 pub struct OptimizationResult {
@@ -118,6 +244,7 @@ impl QueryOptimizer {
             settings: OptimizerSettings::default(),
             statistics: Mutex::new(OptimizationStatistics::default()),
             index_registry: None,
+            cardinality_feedback: None,
         })
     }
 
@@ -129,6 +256,7 @@ impl QueryOptimizer {
             settings,
             statistics: Mutex::new(OptimizationStatistics::default()),
             index_registry: None,
+            cardinality_feedback: None,
         })
     }
 
@@ -138,6 +266,12 @@ impl QueryOptimizer {
         self
     }
 
+    /// Set the cardinality feedback store consulted for row estimates
+    pub fn with_cardinality_feedback(mut self, feedback: Arc<CardinalityFeedback>) -> Self {
+        self.cardinality_feedback = Some(feedback);
+        self
+    }
+
     /// Optimize execution plan
     pub fn optimize(&self, plan: ExecutionPlan) -> Result<OptimizationResult> {
         let start_time = std::time::Instant::now();
@@ -186,6 +320,17 @@ impl QueryOptimizer {
             optimizations_applied += 1;
         }
 
+        // Row counts seen when the same scans and filters ran before.
+        if let Some(feedback) = &self.cardinality_feedback {
+            let corrected = feedback.correct(&mut optimized_plan);
+            if corrected > 0 {
+                messages.push(format!(
+                    "Cardinality feedback corrected {corrected} row estimate(s)"
+                ));
+                optimizations_applied += 1;
+            }
+        }
+
         // Update statistics
         let optimization_time = start_time.elapsed().as_millis() as u64;
         let cost_improvement = if original_cost > 0.0 {
@@ -252,7 +397,7 @@ impl QueryOptimizer {
                     if let Some(from) = &sel.from {
                         // Right side: plan the subquery FROM table only (naive baseline).
                         let mut right = match &from.table {
-                            crate::parser::ast::TableReference::Table { name, alias } => {
+                            crate::parser::ast::TableReference::Table { name, alias, .. } => {
                                 PlanNode::TableScan(crate::planner::planner::TableScanNode {
                                     table_name: name.clone(),
                                     alias: alias.clone(),
@@ -306,6 +451,7 @@ impl QueryOptimizer {
                 left: Box::new(self.rewrite_exists_in_recursive(&j.left)?),
                 right: Box::new(self.rewrite_exists_in_recursive(&j.right)?),
                 cost: j.cost,
+                algorithm: j.algorithm,
             })),
             PlanNode::Projection(p) => Ok(PlanNode::Projection(
                 crate::planner::planner::ProjectionNode {
@@ -441,6 +587,7 @@ impl QueryOptimizer {
                         left: Box::new(left),
                         right: Box::new(right),
                         cost: join.cost,
+                        algorithm: join.algorithm,
                     });
 
                     // Keep the original filter unless we can prove it was fully pushed down.
@@ -475,6 +622,7 @@ impl QueryOptimizer {
                     left: Box::new(left),
                     right: Box::new(right),
                     cost: join.cost,
+                    algorithm: join.algorithm,
                 }))
             }
             PlanNode::Projection(p) => {
@@ -575,6 +723,7 @@ impl QueryOptimizer {
                         left: Box::new(right),
                         right: Box::new(left),
                         cost: join.cost,
+                        algorithm: join.algorithm,
                     }))
                 } else {
                     Ok(PlanNode::Join(JoinNode {
@@ -583,6 +732,7 @@ impl QueryOptimizer {
                         left: Box::new(left),
                         right: Box::new(right),
                         cost: join.cost,
+                        algorithm: join.algorithm,
                     }))
                 }
             }
@@ -625,7 +775,7 @@ impl QueryOptimizer {
         match node {
             PlanNode::TableScan(table_scan) => {
                 let eq = self.equality_hints_for_table_scan(table_scan, &HashMap::new());
-                if let Some(index_scan) = self.find_best_index(table_scan, &eq)? {
+                if let Some(index_scan) = self.find_best_index(table_scan, &eq, None)? {
                     Ok(PlanNode::IndexScan(index_scan))
                 } else {
                     Ok(node.clone())
@@ -638,7 +788,7 @@ impl QueryOptimizer {
                         table_scan,
                         &self.eq_filters_from_filter(filter),
                     );
-                    self.find_best_index(table_scan, &eq)?
+                    self.find_best_index(table_scan, &eq, filter.predicate.as_ref())?
                         .map(PlanNode::IndexScan)
                         .unwrap_or(optimized_input)
                 } else {
@@ -670,6 +820,7 @@ impl QueryOptimizer {
                     left: Box::new(left),
                     right: Box::new(right),
                     cost: join.cost,
+                    algorithm: join.algorithm,
                 }))
             }
             PlanNode::GroupBy(g) => {
@@ -727,11 +878,11 @@ impl QueryOptimizer {
     }
 
     fn eq_filters_from_filter(&self, filter: &FilterNode) -> HashMap<String, String> {
-        let mut map = filter
-            .predicate
-            .as_ref()
-            .map(extract_equality_filters)
-            .unwrap_or_default();
+        let mut map = HashMap::new();
+        if let Some(predicate) = &filter.predicate {
+            map.extend(extract_equality_filters(predicate));
+            map.extend(extract_expression_equality_filters(predicate));
+        }
         if let Some(eq) = &filter.equality {
             map.insert(eq.column.clone(), literal_to_string(&eq.literal));
         }
@@ -761,11 +912,13 @@ impl QueryOptimizer {
         eq
     }
 
-    /// Find the best index for a table using leading-prefix equality match.
+    /// Find the best index for a table using leading-prefix equality match. A partial index is
+    /// only a candidate when the filter `predicate` implies its own.
     fn find_best_index(
         &self,
         table_scan: &TableScanNode,
         eq_filters: &HashMap<String, String>,
+        predicate: Option<&Expression>,
     ) -> Result<Option<IndexScanNode>> {
         let registry = match &self.index_registry {
             Some(r) => r,
@@ -782,19 +935,32 @@ impl QueryOptimizer {
             return Ok(None);
         }
 
-        let mut best: Option<(String, Vec<IndexCondition>)> = None;
+        // (index, conditions, is a hash index)
+        let mut best: Option<(String, Vec<IndexCondition>, bool)> = None;
         for (index_name, columns) in indexes {
+            let entry = registry.get_index_entry(&table_scan.table_name, &index_name);
+            let partial = entry.and_then(|e| e.options.predicate.as_ref());
+            if partial.is_some_and(|p| !predicate.is_some_and(|q| predicate_implies(q, p))) {
+                continue;
+            }
+            let hash = entry.is_some_and(|e| e.options.method == IndexMethod::Hash);
             if let Some(conditions) = leading_index_conditions(&columns, &eq) {
-                let better = best
-                    .as_ref()
-                    .is_none_or(|(_, prev)| conditions.len() > prev.len());
+                // A hash index only answers equality on its whole key; on a tie its single
+                // probe beats a tree descent.
+                if hash && conditions.len() < columns.len() {
+                    continue;
+                }
+                let better = best.as_ref().is_none_or(|(_, prev, prev_hash)| {
+                    conditions.len() > prev.len()
+                        || (conditions.len() == prev.len() && hash && !prev_hash)
+                });
                 if better {
-                    best = Some((index_name, conditions));
+                    best = Some((index_name, conditions, hash));
                 }
             }
         }
 
-        let Some((index_name, conditions)) = best else {
+        let Some((index_name, conditions, _)) = best else {
             return Ok(None);
         };
 
@@ -863,6 +1029,7 @@ impl QueryOptimizer {
         match node {
             PlanNode::TableScan(node) => node.cost,
             PlanNode::IndexScan(node) => node.cost,
+            PlanNode::ForeignScan(node) => node.cost,
             PlanNode::Filter(node) => node.cost,
             PlanNode::Projection(node) => node.cost,
             PlanNode::Join(node) => node.cost,
@@ -0,0 +1,350 @@
+//! Query plans as graphs for visualization: Graphviz DOT ([`ExecutionPlan::to_dot`]) and JSON
+//! ([`ExecutionPlan::to_json`]) for web tools.
+//!
+//! Each operator is a node with its own cost and estimated rows, linked to its inputs. A plan
+//! run by `EXPLAIN ANALYZE` also carries the rows it actually returned (or changed), shown at
+//! the root: operators are not instrumented one by one.
+
+use crate::planner::planner::{ExecutionPlan, PlanNode, SetOpType};
+use serde_json::{json, Value};
+
+/// One operator of the plan graph.
+struct GraphNode<'a> {
+    operator: String,
+    detail: String,
+    cost: f64,
+    estimated_rows: usize,
+    /// Inputs, with the edge label for operators with several (`left` / `right`).
+    inputs: Vec<(Option<&'static str>, &'a PlanNode)>,
+}
+
+fn graph_node(node: &PlanNode) -> GraphNode<'_> {
+    let alias = |a: &Option<String>| a.as_ref().map(|a| format!(" AS {a}")).unwrap_or_default();
+    let filter = |f: &Option<String>| {
+        f.as_ref()
+            .map(|f| format!(" filter={f}"))
+            .unwrap_or_default()
+    };
+    let two = |l, r| vec![(Some("left"), l), (Some("right"), r)];
+    let (operator, detail, cost, inputs): (String, String, f64, Vec<_>) = match node {
+        PlanNode::TableScan(n) => (
+            "Table Scan".into(),
+            format!("{}{}{}", n.table_name, alias(&n.alias), filter(&n.filter)),
+            n.cost,
+            vec![],
+        ),
+        PlanNode::ForeignScan(n) => (
+            "Foreign Scan".into(),
+            format!(
+                "{}{} using {}{}",
+                n.table_name,
+                alias(&n.alias),
+                n.table.adapter,
+                filter(&n.filter)
+            ),
+            n.cost,
+            vec![],
+        ),
+        PlanNode::IndexScan(n) => {
+            let conditions: Vec<String> = n
+                .conditions
+                .iter()
+                .map(|c| format!("{} {} {}", c.column, c.operator, c.value))
+                .collect();
+            (
+                "Index Scan".into(),
+                format!(
+                    "{} using {} ({})",
+                    n.table_name,
+                    n.index_name,
+                    conditions.join(" AND ")
+                ),
+                n.cost,
+                vec![],
+            )
+        }
+        PlanNode::Filter(n) => (
+            "Filter".into(),
+            format!("{} selectivity={:.4}", n.condition, n.selectivity),
+            n.cost,
+            vec![(None, n.input.as_ref())],
+        ),
+        PlanNode::Projection(n) => {
+            let columns: Vec<&str> = n.columns.iter().map(|c| c.name.as_str()).collect();
+            let columns = if columns.is_empty() {
+                "*".to_string()
+            } else {
+                columns.join(", ")
+            };
+            (
+                "Projection".into(),
+                columns,
+                n.cost,
+                vec![(None, n.input.as_ref())],
+            )
+        }
+        PlanNode::Join(n) => (
+            format!("{:?} Join", n.join_type),
+            n.condition.clone(),
+            n.cost,
+            two(n.left.as_ref(), n.right.as_ref()),
+        ),
+        PlanNode::GroupBy(n) => (
+            "Group By".into(),
+            format!("{:?} aggregates={:?}", n.group_columns, n.aggregates),
+            n.cost,
+            vec![(None, n.input.as_ref())],
+        ),
+        PlanNode::Sort(n) => (
+            "Sort".into(),
+            format!("{:?}", n.sort_columns),
+            n.cost,
+            vec![(None, n.input.as_ref())],
+        ),
+        PlanNode::Limit(n) => (
+            "Limit".into(),
+            n.limit.to_string(),
+            n.cost,
+            vec![(None, n.input.as_ref())],
+        ),
+        PlanNode::Offset(n) => (
+            "Offset".into(),
+            n.offset.to_string(),
+            n.cost,
+            vec![(None, n.input.as_ref())],
+        ),
+        PlanNode::Aggregate(n) => (
+            "Aggregate".into(),
+            format!("{:?}", n.aggregates),
+            n.cost,
+            vec![(None, n.input.as_ref())],
+        ),
+        PlanNode::Insert(n) => (
+            "Insert".into(),
+            n.table_name.clone(),
+            n.cost,
+            n.insert_subplan
+                .iter()
+                .map(|s| (None, s.as_ref()))
+                .collect(),
+        ),
+        PlanNode::Update(n) => (
+            "Update".into(),
+            match &n.where_condition {
+                Some(w) => format!("{} WHERE {w}", n.table_name),
+                None => n.table_name.clone(),
+            },
+            n.cost,
+            vec![],
+        ),
+        PlanNode::Delete(n) => (
+            "Delete".into(),
+            match &n.where_condition {
+                Some(w) => format!("{} WHERE {w}", n.table_name),
+                None => n.table_name.clone(),
+            },
+            n.cost,
+            vec![],
+        ),
+        PlanNode::Distinct(n) => (
+            "Distinct".into(),
+            String::new(),
+            n.cost,
+            vec![(None, n.input.as_ref())],
+        ),
+        PlanNode::SetOp(n) => {
+            let op = match n.op {
+                SetOpType::Union => "Union",
+                SetOpType::Intersect => "Intersect",
+                SetOpType::Except => "Except",
+            };
+            let all = if n.all { " All" } else { "" };
+            (
+                format!("{op}{all}"),
+                String::new(),
+                n.cost,
+                two(n.left.as_ref(), n.right.as_ref()),
+            )
+        }
+        PlanNode::SemiJoin(n) => (
+            "Semi Join".into(),
+            n.condition.clone(),
+            n.cost,
+            two(n.left.as_ref(), n.right.as_ref()),
+        ),
+        PlanNode::AntiJoin(n) => (
+            "Anti Join".into(),
+            n.condition.clone(),
+            n.cost,
+            two(n.left.as_ref(), n.right.as_ref()),
+        ),
+    };
+    GraphNode {
+        operator,
+        detail,
+        cost,
+        estimated_rows: node.estimated_rows(),
+        inputs,
+    }
+}
+
+impl ExecutionPlan {
+    /// The plan as a Graphviz `digraph`: one box per operator (name, detail, cost, estimated
+    /// and, after `EXPLAIN ANALYZE`, actual rows) with edges from each operator to its inputs.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from(
+            "digraph plan {\n  rankdir=TB;\n  node [shape=box, fontname=\"monospace\"];\n",
+        );
+        let mut next_id = 0;
+        dot_node(
+            &self.root,
+            self.metadata.actual_rows,
+            &mut next_id,
+            &mut out,
+        );
+        out.push_str("}\n");
+        out
+    }
+
+    /// The plan as a JSON document: plan-wide estimates and the operator tree under `plan`,
+    /// each operator with its `inputs`.
+    pub fn to_json(&self) -> String {
+        let doc = json!({
+            "estimated_cost": self.metadata.estimated_cost,
+            "estimated_rows": self.metadata.estimated_rows,
+            "actual_rows": self.metadata.actual_rows,
+            "plan": json_node(&self.root, self.metadata.actual_rows, None),
+        });
+        serde_json::to_string_pretty(&doc).unwrap_or_default()
+    }
+}
+
+/// Writes `node` and its inputs; returns the node's id.
+fn dot_node(
+    node: &PlanNode,
+    actual_rows: Option<usize>,
+    next_id: &mut usize,
+    out: &mut String,
+) -> usize {
+    let g = graph_node(node);
+    let id = *next_id;
+    *next_id += 1;
+    let mut label = g.operator.clone();
+    if !g.detail.is_empty() {
+        label.push('\n');
+        label.push_str(&g.detail);
+    }
+    label.push_str(&format!("\ncost={:.2} rows={}", g.cost, g.estimated_rows));
+    if let Some(actual) = actual_rows {
+        label.push_str(&format!(" actual={actual}"));
+    }
+    out.push_str(&format!("  n{id} [label=\"{}\"];\n", dot_escape(&label)));
+    for (edge, input) in g.inputs {
+        let child = dot_node(input, None, next_id, out);
+        match edge {
+            Some(edge) => out.push_str(&format!("  n{id} -> n{child} [label=\"{edge}\"];\n")),
+            None => out.push_str(&format!("  n{id} -> n{child};\n")),
+        }
+    }
+    id
+}
+
+fn dot_escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn json_node(node: &PlanNode, actual_rows: Option<usize>, edge: Option<&str>) -> Value {
+    let g = graph_node(node);
+    let mut v = json!({
+        "operator": g.operator,
+        "detail": g.detail,
+        "cost": g.cost,
+        "estimated_rows": g.estimated_rows,
+        "inputs": g
+            .inputs
+            .into_iter()
+            .map(|(edge, input)| json_node(input, None, edge))
+            .collect::<Vec<_>>(),
+    });
+    if let Some(actual) = actual_rows {
+        v["actual_rows"] = json!(actual);
+    }
+    if let Some(edge) = edge {
+        v["edge"] = json!(edge);
+    }
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::planner::planner::{
+        ExecutionPlan, FilterNode, PlanMetadata, PlanNode, PlanStatistics, TableScanNode,
+    };
+    use std::time::SystemTime;
+
+    fn plan(actual_rows: Option<usize>) -> ExecutionPlan {
+        ExecutionPlan {
+            root: PlanNode::Filter(FilterNode {
+                condition: "name = \"ann\"".to_string(),
+                predicate: None,
+                equality: None,
+                input: Box::new(PlanNode::TableScan(TableScanNode {
+                    table_name: "users".to_string(),
+                    alias: None,
+                    columns: vec!["name".to_string()],
+                    filter: None,
+                    cost: 4.0,
+                    estimated_rows: 100,
+                })),
+                selectivity: 0.1,
+                cost: 1.0,
+            }),
+            metadata: PlanMetadata {
+                estimated_cost: 5.0,
+                estimated_rows: 10,
+                created_at: SystemTime::UNIX_EPOCH,
+                actual_rows,
+                statistics: PlanStatistics {
+                    operator_count: 2,
+                    max_depth: 2,
+                    table_count: 1,
+                    join_count: 0,
+                },
+            },
+        }
+    }
+
+    #[test]
+    fn dot_graph_links_operators_to_their_inputs() {
+        let dot = plan(Some(3)).to_dot();
+        assert!(dot.starts_with("digraph plan {"), "{dot}");
+        assert!(
+            dot.contains(
+                r#"n0 [label="Filter\nname = \"ann\" selectivity=0.1000\ncost=1.00 rows=10 actual=3"];"#
+            ),
+            "{dot}"
+        );
+        assert!(
+            dot.contains(r#"n1 [label="Table Scan\nusers\ncost=4.00 rows=100"];"#),
+            "{dot}"
+        );
+        assert!(dot.contains("n0 -> n1;"), "{dot}");
+    }
+
+    #[test]
+    fn json_graph_nests_inputs() {
+        let doc: serde_json::Value = serde_json::from_str(&plan(None).to_json()).unwrap();
+        assert_eq!(doc["estimated_rows"], 10);
+        assert!(doc["actual_rows"].is_null());
+        assert_eq!(doc["plan"]["operator"], "Filter");
+        assert_eq!(doc["plan"]["inputs"][0]["operator"], "Table Scan");
+        assert_eq!(doc["plan"]["inputs"][0]["estimated_rows"], 100);
+        assert!(doc["plan"].get("actual_rows").is_none());
+
+        let doc: serde_json::Value = serde_json::from_str(&plan(Some(3)).to_json()).unwrap();
+        assert_eq!(doc["plan"]["actual_rows"], 3);
+    }
+}
@@ -1,14 +1,18 @@
 //! Query planner for rustdb
 
 use crate::analyzer::{AnalysisContext, SemanticAnalyzer};
+use crate::catalog::schema::{ForeignTableDef, TableSchema};
+use crate::common::types::DataType;
 use crate::common::{Error, Result};
 use crate::parser::ast::{
     BinaryOperator, DeleteStatement, Expression, InsertStatement, InsertValues, Literal,
-    SelectItem, SelectStatement, SetOperationStatement, SetOperator, SqlStatement, UpdateStatement,
+    NullsOrder, SelectItem, SelectStatement, SetOperationStatement, SetOperator, SqlStatement,
+    UpdateStatement,
 };
+use crate::parser::prepared::bind_expression;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::Mutex;
+use std::sync::{Mutex, RwLock};
 
 /// Heuristic predicate selectivity for cost estimation (0.001–0.95).
 pub(crate) fn estimate_selectivity(condition: &str) -> f64 {
@@ -80,6 +84,11 @@ fn column_names_from_select(select: &SelectStatement) -> Vec<String> {
     if let Some(h) = &select.having {
         collect_identifier_columns(h, &mut out, &mut push_unique, &mut seen);
     }
+    for join in select.from.iter().flat_map(|f| &f.joins) {
+        if let Some(cond) = &join.condition {
+            collect_identifier_columns(cond, &mut out, &mut push_unique, &mut seen);
+        }
+    }
     if out.is_empty() {
         out.push("*".to_string());
     }
@@ -133,11 +142,231 @@ fn collect_identifier_columns(
                 }
             }
         }
+        Expression::In { expr, list } => {
+            collect_identifier_columns(expr, out, push_unique, seen);
+            if let crate::parser::ast::InList::Values(values) = list {
+                for v in values {
+                    collect_identifier_columns(v, out, push_unique, seen);
+                }
+            }
+        }
+        Expression::Between { expr, low, high } => {
+            collect_identifier_columns(expr, out, push_unique, seen);
+            collect_identifier_columns(low, out, push_unique, seen);
+            collect_identifier_columns(high, out, push_unique, seen);
+        }
+        Expression::IsNull { expr, .. } => collect_identifier_columns(expr, out, push_unique, seen),
+        Expression::Like { expr, pattern, .. } => {
+            collect_identifier_columns(expr, out, push_unique, seen);
+            collect_identifier_columns(pattern, out, push_unique, seen);
+        }
         _ => {}
     }
 }
 
-fn expr_to_short_name(expr: &Expression) -> String {
+/// What a SELECT lets a foreign scan push down to its adapter
+struct ForeignPushdown {
+    /// Lowercased identifiers the query references anywhere; `None` when it reads every column
+    referenced: Option<std::collections::HashSet<String>>,
+    /// `LIMIT + OFFSET` when only the `WHERE` filter may sit between the scan and the limit
+    /// (no grouping, sort, DISTINCT or join)
+    limit_after_where: Option<usize>,
+    /// `WHERE` split on `AND`
+    conjuncts: Vec<Expression>,
+    /// `FROM` names one table, so unqualified columns are its own
+    single_table: bool,
+}
+
+impl ForeignPushdown {
+    fn from_select(select: &SelectStatement) -> Self {
+        let mut names = Vec::new();
+        let mut seen = std::collections::HashSet::new();
+        let mut push_unique =
+            |s: String, out: &mut Vec<String>, seen: &mut std::collections::HashSet<String>| {
+                if seen.insert(s.clone()) {
+                    out.push(s);
+                }
+            };
+        let mut wildcard = false;
+        for item in &select.select_list {
+            match item {
+                SelectItem::Wildcard => wildcard = true,
+                SelectItem::Expression { expr, .. } => {
+                    collect_identifier_columns(expr, &mut names, &mut push_unique, &mut seen)
+                }
+            }
+        }
+        let joins = select
+            .from
+            .as_ref()
+            .map(|f| f.joins.as_slice())
+            .unwrap_or(&[]);
+        let clauses = select
+            .where_clause
+            .iter()
+            .chain(select.having.iter())
+            .chain(select.group_by.iter())
+            .chain(select.order_by.iter().map(|o| &o.expr))
+            .chain(joins.iter().filter_map(|j| j.condition.as_ref()));
+        for expr in clauses {
+            collect_identifier_columns(expr, &mut names, &mut push_unique, &mut seen);
+        }
+
+        let mut conjuncts = Vec::new();
+        if let Some(w) = &select.where_clause {
+            split_conjuncts(w, &mut conjuncts);
+        }
+        let streams_into_limit = joins.is_empty()
+            && select.group_by.is_empty()
+            && select.having.is_none()
+            && select.order_by.is_empty()
+            && !select.distinct
+            && extract_aggregates_from_select(select).is_empty();
+        Self {
+            referenced: (!wildcard).then(|| names.iter().map(|n| n.to_ascii_lowercase()).collect()),
+            limit_after_where: select
+                .limit
+                .filter(|_| streams_into_limit)
+                .map(|limit| limit as usize + select.offset.unwrap_or(0) as usize),
+            conjuncts,
+            single_table: joins.is_empty(),
+        }
+    }
+
+    /// `WHERE` conjuncts that only read the foreign table `name` / `alias`, as SQL over its bare
+    /// column names; the second value is whether every conjunct was pushed
+    fn filter_for(
+        &self,
+        name: &str,
+        alias: Option<&str>,
+        columns: &[(String, DataType)],
+    ) -> (Option<String>, bool) {
+        let owns = |table: Option<&str>, column: &str| {
+            let table_ok = match table {
+                Some(t) => {
+                    t.eq_ignore_ascii_case(name) || alias.is_some_and(|a| t.eq_ignore_ascii_case(a))
+                }
+                None => self.single_table,
+            };
+            table_ok && columns.iter().any(|(c, _)| c.eq_ignore_ascii_case(column))
+        };
+        let pushed: Vec<String> = self
+            .conjuncts
+            .iter()
+            .filter_map(|c| expression_to_sql(c, &owns))
+            .collect();
+        let all = pushed.len() == self.conjuncts.len();
+        ((!pushed.is_empty()).then(|| pushed.join(" AND ")), all)
+    }
+
+    /// Row limit for a scan whose `WHERE` is fully evaluated by the adapter (or absent)
+    fn limit(&self, where_pushed: bool) -> Option<usize> {
+        self.limit_after_where
+            .filter(|_| self.conjuncts.is_empty() || where_pushed)
+    }
+}
+
+fn split_conjuncts(expr: &Expression, out: &mut Vec<Expression>) {
+    match expr {
+        Expression::BinaryOp {
+            left,
+            op: BinaryOperator::And,
+            right,
+        } => {
+            split_conjuncts(left, out);
+            split_conjuncts(right, out);
+        }
+        other => out.push(other.clone()),
+    }
+}
+
+/// Renders a predicate as SQL for a remote server; `None` when it uses a column `owns` rejects
+/// (`(table, column)`) or a construct that is not pushed down (functions, CASE, subqueries)
+pub(crate) fn expression_to_sql(
+    expr: &Expression,
+    owns: &dyn Fn(Option<&str>, &str) -> bool,
+) -> Option<String> {
+    Some(match expr {
+        Expression::Literal(Literal::Null) => "NULL".to_string(),
+        Expression::Literal(Literal::Boolean(b)) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+        Expression::Literal(Literal::Integer(i)) => i.to_string(),
+        Expression::Literal(Literal::Float(f)) => format!("{:?}", f),
+        // String literals keep their source quoting.
+        Expression::Literal(Literal::String(s)) => s.clone(),
+        Expression::Identifier(column) => owns(None, column).then(|| column.clone())?,
+        Expression::QualifiedIdentifier { table, column } => {
+            owns(Some(table), column).then(|| column.clone())?
+        }
+        Expression::BinaryOp { left, op, right } => {
+            let op = match op {
+                BinaryOperator::Add => "+",
+                BinaryOperator::Subtract => "-",
+                BinaryOperator::Multiply => "*",
+                BinaryOperator::Divide => "/",
+                BinaryOperator::Modulo => "%",
+                BinaryOperator::Equal => "=",
+                BinaryOperator::NotEqual => "<>",
+                BinaryOperator::LessThan => "<",
+                BinaryOperator::LessThanOrEqual => "<=",
+                BinaryOperator::GreaterThan => ">",
+                BinaryOperator::GreaterThanOrEqual => ">=",
+                BinaryOperator::And => "AND",
+                BinaryOperator::Or => "OR",
+                BinaryOperator::Concat => "||",
+            };
+            format!(
+                "({} {} {})",
+                expression_to_sql(left, owns)?,
+                op,
+                expression_to_sql(right, owns)?
+            )
+        }
+        Expression::UnaryOp { op, expr } => {
+            let op = match op {
+                crate::parser::ast::UnaryOperator::Plus => "+",
+                crate::parser::ast::UnaryOperator::Minus => "-",
+                crate::parser::ast::UnaryOperator::Not => "NOT ",
+            };
+            format!("({}{})", op, expression_to_sql(expr, owns)?)
+        }
+        Expression::In {
+            expr,
+            list: crate::parser::ast::InList::Values(values),
+        } => format!(
+            "({} IN ({}))",
+            expression_to_sql(expr, owns)?,
+            values
+                .iter()
+                .map(|v| expression_to_sql(v, owns))
+                .collect::<Option<Vec<_>>>()?
+                .join(", ")
+        ),
+        Expression::Between { expr, low, high } => format!(
+            "({} BETWEEN {} AND {})",
+            expression_to_sql(expr, owns)?,
+            expression_to_sql(low, owns)?,
+            expression_to_sql(high, owns)?
+        ),
+        Expression::IsNull { expr, negated } => format!(
+            "({} IS {}NULL)",
+            expression_to_sql(expr, owns)?,
+            if *negated { "NOT " } else { "" }
+        ),
+        Expression::Like {
+            expr,
+            pattern,
+            negated,
+        } => format!(
+            "({} {}LIKE {})",
+            expression_to_sql(expr, owns)?,
+            if *negated { "NOT " } else { "" },
+            expression_to_sql(pattern, owns)?
+        ),
+        _ => return None,
+    })
+}
+
+pub(crate) fn expr_to_short_name(expr: &Expression) -> String {
     match expr {
         Expression::Identifier(s) => s.clone(),
         Expression::QualifiedIdentifier { column, .. } => column.clone(),
@@ -192,6 +421,7 @@ fn rough_rows(node: &PlanNode) -> usize {
     match node {
         PlanNode::TableScan(t) => t.estimated_rows.max(1),
         PlanNode::IndexScan(i) => i.estimated_rows.max(1),
+        PlanNode::ForeignScan(f) => f.estimated_rows.max(1),
         PlanNode::Filter(f) => {
             let r = rough_rows(&f.input);
             ((r as f64) * f.selectivity).max(1.0) as usize
@@ -224,6 +454,7 @@ fn rough_subtree_cost(node: &PlanNode) -> f64 {
     match node {
         PlanNode::TableScan(t) => t.cost,
         PlanNode::IndexScan(i) => i.cost,
+        PlanNode::ForeignScan(f) => f.cost,
         PlanNode::Filter(f) => rough_subtree_cost(&f.input) + f.cost + 0.05,
         PlanNode::Projection(p) => rough_subtree_cost(&p.input) + p.cost + 0.02,
         PlanNode::Join(j) => rough_subtree_cost(&j.left) + rough_subtree_cost(&j.right) + j.cost,
@@ -268,6 +499,57 @@ pub struct ExecutionPlan {
     pub metadata: PlanMetadata,
 }
 
+impl ExecutionPlan {
+    /// The plan of a statement with `$n` placeholders, planned once, with `params[n - 1]` in
+    /// place of each placeholder: in predicates and projections, and in the index conditions
+    /// equality on a placeholder chose.
+    pub fn bind_parameters(&self, params: &[Literal]) -> ExecutionPlan {
+        let values: Vec<Expression> = params.iter().cloned().map(Expression::Literal).collect();
+        let mut bound = self.clone();
+        bind_node(&mut bound.root, params, &values);
+        bound
+    }
+}
+
+fn bind_node(node: &mut PlanNode, params: &[Literal], values: &[Expression]) {
+    match node {
+        PlanNode::Filter(filter) => {
+            if let Some(predicate) = &mut filter.predicate {
+                *predicate = bind_expression(predicate, values);
+                filter.condition = format!("{predicate:?}");
+                filter.equality = extract_simple_equality(predicate);
+            }
+        }
+        PlanNode::Projection(projection) => {
+            for column in &mut projection.columns {
+                if let Some(expr) = &mut column.expression {
+                    let bound = bind_expression(expr, values);
+                    if column.name == expr_to_short_name(expr) {
+                        column.name = expr_to_short_name(&bound);
+                    }
+                    *expr = bound;
+                }
+            }
+        }
+        PlanNode::IndexScan(scan) => {
+            for condition in &mut scan.conditions {
+                let param = condition
+                    .value
+                    .strip_prefix('$')
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .and_then(|n| params.get(n.checked_sub(1)?));
+                if let Some(value) = param {
+                    condition.value = literal_to_string(value);
+                }
+            }
+        }
+        _ => {}
+    }
+    for input in node.inputs_mut() {
+        bind_node(input, params, values);
+    }
+}
+
 /// Execution plan metadata
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PlanMetadata {
@@ -277,6 +559,9 @@ pub struct PlanMetadata {
     pub estimated_rows: usize,
     /// Plan creation time
     pub created_at: std::time::SystemTime,
+    /// Rows the plan returned (or changed) when run by `EXPLAIN ANALYZE`
+    #[serde(default)]
+    pub actual_rows: Option<usize>,
     /// Plan statistics
     pub statistics: PlanStatistics,
 }
@@ -331,6 +616,98 @@ pub enum PlanNode {
     SemiJoin(SemiJoinNode),
     /// Anti-join (used by rewrites like NOT EXISTS/NOT IN).
     AntiJoin(AntiJoinNode),
+    /// Scan of a foreign table through its adapter
+    ForeignScan(ForeignScanNode),
+}
+
+impl PlanNode {
+    /// Estimated number of rows the operator returns
+    pub fn estimated_rows(&self) -> usize {
+        match self {
+            PlanNode::TableScan(node) => node.estimated_rows,
+            PlanNode::IndexScan(node) => node.estimated_rows,
+            PlanNode::ForeignScan(node) => node.estimated_rows,
+            PlanNode::Filter(node) => {
+                (node.input.estimated_rows() as f64 * node.selectivity) as usize
+            }
+            PlanNode::Projection(node) => node.input.estimated_rows(),
+            PlanNode::Join(node) => {
+                let left_rows = node.left.estimated_rows();
+                let right_rows = node.right.estimated_rows();
+                left_rows * right_rows / 1000 // Simplified estimate
+            }
+            PlanNode::GroupBy(node) => node.input.estimated_rows() / 10, // Simplified estimate
+            PlanNode::Sort(node) => node.input.estimated_rows(),
+            PlanNode::Limit(node) => node.limit.min(node.input.estimated_rows()),
+            PlanNode::Offset(node) => {
+                let input_rows = node.input.estimated_rows();
+                if node.offset >= input_rows {
+                    0
+                } else {
+                    input_rows - node.offset
+                }
+            }
+            PlanNode::Aggregate(node) => node.input.estimated_rows() / 10,
+            PlanNode::SetOp(node) => {
+                // Conservative: UNION ALL adds, others at most left size.
+                let l = node.left.estimated_rows();
+                let r = node.right.estimated_rows();
+                match node.op {
+                    SetOpType::Union if node.all => l.saturating_add(r),
+                    _ => l.max(1),
+                }
+            }
+            PlanNode::SemiJoin(node) => node.left.estimated_rows(),
+            PlanNode::AntiJoin(node) => node.left.estimated_rows(),
+            PlanNode::Distinct(node) => node.input.estimated_rows().max(1),
+            PlanNode::Insert(node) => node
+                .insert_subplan
+                .as_ref()
+                .map(|s| s.estimated_rows())
+                .unwrap_or(1)
+                .max(1),
+            PlanNode::Update(_) => 1,
+            PlanNode::Delete(_) => 1,
+        }
+    }
+
+    /// Input operators of the node
+    pub fn inputs(&self) -> Vec<&PlanNode> {
+        match self {
+            PlanNode::Filter(node) => vec![&node.input],
+            PlanNode::Projection(node) => vec![&node.input],
+            PlanNode::Join(node) => vec![&node.left, &node.right],
+            PlanNode::GroupBy(node) => vec![&node.input],
+            PlanNode::Sort(node) => vec![&node.input],
+            PlanNode::Limit(node) => vec![&node.input],
+            PlanNode::Offset(node) => vec![&node.input],
+            PlanNode::Aggregate(node) => vec![&node.input],
+            PlanNode::SetOp(node) => vec![&node.left, &node.right],
+            PlanNode::SemiJoin(node) => vec![&node.left, &node.right],
+            PlanNode::AntiJoin(node) => vec![&node.left, &node.right],
+            PlanNode::Distinct(node) => vec![&node.input],
+            _ => vec![],
+        }
+    }
+
+    /// Input operators of the node, for rewriting them in place
+    pub fn inputs_mut(&mut self) -> Vec<&mut PlanNode> {
+        match self {
+            PlanNode::Filter(node) => vec![&mut node.input],
+            PlanNode::Projection(node) => vec![&mut node.input],
+            PlanNode::Join(node) => vec![&mut node.left, &mut node.right],
+            PlanNode::GroupBy(node) => vec![&mut node.input],
+            PlanNode::Sort(node) => vec![&mut node.input],
+            PlanNode::Limit(node) => vec![&mut node.input],
+            PlanNode::Offset(node) => vec![&mut node.input],
+            PlanNode::Aggregate(node) => vec![&mut node.input],
+            PlanNode::SetOp(node) => vec![&mut node.left, &mut node.right],
+            PlanNode::SemiJoin(node) => vec![&mut node.left, &mut node.right],
+            PlanNode::AntiJoin(node) => vec![&mut node.left, &mut node.right],
+            PlanNode::Distinct(node) => vec![&mut node.input],
+            _ => vec![],
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -391,6 +768,30 @@ pub struct TableScanNode {
     pub estimated_rows: usize,
 }
 
+/// Foreign table scan node: reads external data through the table's adapter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForeignScanNode {
+    /// Table name
+    pub table_name: String,
+    /// Table alias
+    pub alias: Option<String>,
+    /// Adapter and options from the catalog
+    pub table: ForeignTableDef,
+    /// Declared columns as `(name, type template)`
+    pub columns: Vec<(String, DataType)>,
+    /// Columns the query reads (pushed down to the adapter), in declared order
+    pub projection: Vec<String>,
+    /// Row limit pushed down to the adapter (`LIMIT + OFFSET`)
+    pub limit: Option<usize>,
+    /// `WHERE` conjuncts pushed down as SQL (adapters that filter remotely only); the query's
+    /// own filter still runs on the returned rows
+    pub filter: Option<String>,
+    /// Cost estimate
+    pub cost: f64,
+    /// Estimated number of rows
+    pub estimated_rows: usize,
+}
+
 /// Index scan node
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct IndexScanNode {
@@ -474,7 +875,8 @@ pub(crate) fn extract_simple_equality(expr: &Expression) -> Option<SimpleEqualit
     }
 }
 
-/// Collects `column = literal` predicates from an expression tree (`AND` chains).
+/// Collects `column = literal` predicates from an expression tree (`AND` chains); `column = $n`
+/// gives the placeholder's value in a plan with parameters.
 pub(crate) fn extract_equality_filters(expr: &Expression) -> HashMap<String, String> {
     let mut out = HashMap::new();
     collect_equality_filters(expr, &mut out);
@@ -494,8 +896,83 @@ fn collect_equality_filters(expr: &Expression, out: &mut HashMap<String, String>
         _ => {
             if let Some(eq) = extract_simple_equality(expr) {
                 out.insert(eq.column, literal_to_string(&eq.literal));
+            } else if let Some((column, n)) = parameter_equality(expr) {
+                out.insert(column, parameter_value(n));
+            }
+        }
+    }
+}
+
+/// `column = $n` (either way round)
+fn parameter_equality(expr: &Expression) -> Option<(String, usize)> {
+    let Expression::BinaryOp {
+        left,
+        op: BinaryOperator::Equal,
+        right,
+    } = expr
+    else {
+        return None;
+    };
+    match (left.as_ref(), right.as_ref()) {
+        (Expression::Identifier(c), Expression::Parameter(n))
+        | (Expression::Parameter(n), Expression::Identifier(c))
+        | (Expression::QualifiedIdentifier { column: c, .. }, Expression::Parameter(n))
+        | (Expression::Parameter(n), Expression::QualifiedIdentifier { column: c, .. }) => {
+            Some((c.clone(), *n))
+        }
+        _ => None,
+    }
+}
+
+/// Index condition value standing for placeholder `$n` until [`ExecutionPlan::bind_parameters`]
+/// replaces it. A literal never reads like this: string values keep their quotes.
+fn parameter_value(n: usize) -> String {
+    format!("${n}")
+}
+
+/// Collects `expression = literal` predicates on computed expressions (`lower(email) = 'x'`)
+/// from an expression tree (`AND` chains), keyed by [`Expression::index_key`] so they match the
+/// key parts of expression indexes.
+pub(crate) fn extract_expression_equality_filters(expr: &Expression) -> HashMap<String, String> {
+    let mut out = HashMap::new();
+    collect_expression_equality_filters(expr, &mut out);
+    out
+}
+
+fn collect_expression_equality_filters(expr: &Expression, out: &mut HashMap<String, String>) {
+    match expr {
+        Expression::BinaryOp {
+            left,
+            op: BinaryOperator::And,
+            right,
+        } => {
+            collect_expression_equality_filters(left, out);
+            collect_expression_equality_filters(right, out);
+        }
+        Expression::BinaryOp {
+            left,
+            op: BinaryOperator::Equal,
+            right,
+        } => {
+            let computed = |e: &Expression| {
+                matches!(
+                    e,
+                    Expression::Function { .. }
+                        | Expression::BinaryOp { .. }
+                        | Expression::UnaryOp { .. }
+                )
+            };
+            match (left.as_ref(), right.as_ref()) {
+                (e, Expression::Literal(l)) | (Expression::Literal(l), e) if computed(e) => {
+                    out.insert(e.index_key(), literal_to_string(l));
+                }
+                (e, Expression::Parameter(n)) | (Expression::Parameter(n), e) if computed(e) => {
+                    out.insert(e.index_key(), parameter_value(*n));
+                }
+                _ => {}
             }
         }
+        _ => {}
     }
 }
 
@@ -552,6 +1029,21 @@ pub struct JoinNode {
     pub right: Box<PlanNode>,
     /// Cost estimate
     pub cost: f64,
+    /// How the join is executed (chosen by the advanced optimizer's join enumeration)
+    #[serde(default)]
+    pub algorithm: JoinAlgorithm,
+}
+
+/// Join algorithm
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JoinAlgorithm {
+    /// Compare every left row with every right row
+    #[default]
+    NestedLoop,
+    /// Build a hash table on the join key of the right input and probe it with the left
+    Hash,
+    /// Merge two inputs ordered on their join keys
+    Merge,
 }
 
 /// Join type
@@ -606,6 +1098,19 @@ pub struct SortColumn {
     pub column: String,
     /// Sort direction
     pub direction: SortDirection,
+    /// Place of NULLs; `None`: NULLs sort as the largest values
+    #[serde(default)]
+    pub nulls: Option<NullsOrder>,
+}
+
+impl SortColumn {
+    /// Whether NULLs come before the other values
+    pub fn nulls_first(&self) -> bool {
+        match self.nulls {
+            Some(order) => order == NullsOrder::First,
+            None => self.direction == SortDirection::Desc,
+        }
+    }
 }
 
 /// Sort direction
@@ -705,6 +1210,15 @@ pub struct QueryPlanner {
     settings: PlannerSettings,
     /// Plan cache
     plan_cache: Mutex<HashMap<String, ExecutionPlan>>,
+    /// Foreign tables by name: scans of these plan as [`PlanNode::ForeignScan`]
+    foreign_tables: RwLock<HashMap<String, ForeignScanTable>>,
+}
+
+/// Catalog entry of a foreign table as the planner needs it
+#[derive(Debug, Clone)]
+struct ForeignScanTable {
+    table: ForeignTableDef,
+    columns: Vec<(String, DataType)>,
 }
 
 /// Planner settings
@@ -742,6 +1256,7 @@ impl QueryPlanner {
             semantic_analyzer: Mutex::new(semantic_analyzer),
             settings: PlannerSettings::default(),
             plan_cache: Mutex::new(HashMap::new()),
+            foreign_tables: RwLock::new(HashMap::new()),
         })
     }
 
@@ -752,9 +1267,48 @@ impl QueryPlanner {
             semantic_analyzer: Mutex::new(semantic_analyzer),
             settings,
             plan_cache: Mutex::new(HashMap::new()),
+            foreign_tables: RwLock::new(HashMap::new()),
         })
     }
 
+    /// Plans scans of `schema` as foreign scans (no-op for regular tables)
+    pub fn register_foreign_table(&self, schema: &TableSchema) -> Result<()> {
+        let Some(table) = schema.foreign.clone() else {
+            return Ok(());
+        };
+        let columns = schema
+            .columns
+            .iter()
+            .map(|c| (c.name.clone(), c.data_type.clone()))
+            .collect();
+        self.foreign_tables
+            .write()
+            .map_err(|_| Error::lock("planner foreign tables lock poisoned"))?
+            .insert(
+                schema.table_name.clone(),
+                ForeignScanTable { table, columns },
+            );
+        Ok(())
+    }
+
+    /// Forgets a foreign table (after `DROP TABLE`)
+    pub fn unregister_foreign_table(&self, table_name: &str) -> Result<()> {
+        self.foreign_tables
+            .write()
+            .map_err(|_| Error::lock("planner foreign tables lock poisoned"))?
+            .remove(table_name);
+        Ok(())
+    }
+
+    fn foreign_table(&self, table_name: &str) -> Result<Option<ForeignScanTable>> {
+        Ok(self
+            .foreign_tables
+            .read()
+            .map_err(|_| Error::lock("planner foreign tables lock poisoned"))?
+            .get(table_name)
+            .cloned())
+    }
+
     /// Creates execution plan for SQL query
     pub fn create_plan(&self, sql_statement: &SqlStatement) -> Result<ExecutionPlan> {
         // First perform semantic analysis
@@ -809,10 +1363,11 @@ impl QueryPlanner {
     /// Creates plan for SELECT query
     fn create_select_plan(&self, select: &SelectStatement) -> Result<PlanNode> {
         let scan_columns = column_names_from_select(select);
+        let pushdown = ForeignPushdown::from_select(select);
 
         // Create base table scan plan
         let mut current_plan = if let Some(from) = &select.from {
-            self.create_table_scan_plan(&from.table, &scan_columns)?
+            self.create_table_scan_plan(&from.table, &scan_columns, &pushdown)?
         } else {
             // If no FROM, create empty plan
             PlanNode::TableScan(TableScanNode {
@@ -828,7 +1383,8 @@ impl QueryPlanner {
         // Add JOIN operations
         if let Some(from) = &select.from {
             for join in &from.joins {
-                let join_plan = self.create_table_scan_plan(&join.table, &scan_columns)?;
+                let join_plan =
+                    self.create_table_scan_plan(&join.table, &scan_columns, &pushdown)?;
                 let join_cost = estimate_binary_join_cost(&current_plan, &join_plan);
                 current_plan = PlanNode::Join(JoinNode {
                     join_type: match join.join_type {
@@ -857,6 +1413,7 @@ impl QueryPlanner {
                     left: Box::new(current_plan),
                     right: Box::new(join_plan),
                     cost: join_cost,
+                    algorithm: JoinAlgorithm::NestedLoop,
                 });
             }
         }
@@ -875,12 +1432,13 @@ impl QueryPlanner {
             });
         }
 
-        // Add GROUP BY
-        if !select.group_by.is_empty() {
+        // Add GROUP BY (aggregates without one make a single group)
+        let aggregates = extract_aggregates_from_select(select);
+        if !select.group_by.is_empty() || !aggregates.is_empty() {
             let gb_cost = rough_subtree_cost(&current_plan) * 0.02 + 2.0;
             current_plan = PlanNode::GroupBy(GroupByNode {
                 group_columns: select.group_by.iter().map(expr_to_short_name).collect(),
-                aggregates: extract_aggregates_from_select(select),
+                aggregates,
                 input: Box::new(current_plan),
                 cost: gb_cost,
             });
@@ -913,6 +1471,7 @@ impl QueryPlanner {
                             crate::parser::ast::OrderDirection::Asc => SortDirection::Asc,
                             crate::parser::ast::OrderDirection::Desc => SortDirection::Desc,
                         },
+                        nulls: item.nulls,
                     })
                     .collect(),
                 input: Box::new(current_plan),
@@ -976,6 +1535,7 @@ impl QueryPlanner {
         &self,
         table_ref: &crate::parser::ast::TableReference,
         output_columns: &[String],
+        pushdown: &ForeignPushdown,
     ) -> Result<PlanNode> {
         let cols = if output_columns.is_empty() {
             vec!["*".to_string()]
@@ -983,7 +1543,43 @@ impl QueryPlanner {
             output_columns.to_vec()
         };
         match table_ref {
-            crate::parser::ast::TableReference::Table { name, alias } => {
+            crate::parser::ast::TableReference::Table {
+                name,
+                as_of: Some(_),
+                ..
+            } => Err(Error::unsupported(format!(
+                "{name} AS OF TIMESTAMP must be the only table of a SELECT"
+            ))),
+            crate::parser::ast::TableReference::Table { name, alias, .. } => {
+                if let Some(foreign) = self.foreign_table(name)? {
+                    let (filter, where_pushed) = if foreign.table.pushes_down_filters() {
+                        pushdown.filter_for(name, alias.as_deref(), &foreign.columns)
+                    } else {
+                        (None, false)
+                    };
+                    let limit = pushdown.limit(where_pushed);
+                    return Ok(PlanNode::ForeignScan(ForeignScanNode {
+                        table_name: name.clone(),
+                        alias: alias.clone(),
+                        projection: foreign
+                            .columns
+                            .iter()
+                            .filter(|(c, _)| {
+                                pushdown
+                                    .referenced
+                                    .as_ref()
+                                    .is_none_or(|r| r.contains(&c.to_ascii_lowercase()))
+                            })
+                            .map(|(c, _)| c.clone())
+                            .collect(),
+                        table: foreign.table,
+                        columns: foreign.columns,
+                        limit,
+                        filter,
+                        cost: 2.0,
+                        estimated_rows: limit.unwrap_or(1000),
+                    }));
+                }
                 Ok(PlanNode::TableScan(TableScanNode {
                     table_name: name.clone(),
                     alias: alias.clone(),
@@ -1006,6 +1602,9 @@ impl QueryPlanner {
                     cost: 0.05,
                 }))
             }
+            crate::parser::ast::TableReference::Function { name, .. } => Err(Error::unsupported(
+                format!("table function {name}() cannot be used in a planned query"),
+            )),
         }
     }
 
@@ -1068,8 +1667,9 @@ impl QueryPlanner {
 
         Ok(PlanMetadata {
             estimated_cost: self.estimate_plan_cost(root),
-            estimated_rows: self.estimate_plan_rows(root),
-            created_at: std::time::SystemTime::now(),
+            estimated_rows: root.estimated_rows(),
+            created_at: crate::common::clock::system_now(),
+            actual_rows: None,
             statistics: PlanStatistics {
                 operator_count,
                 max_depth,
@@ -1092,7 +1692,7 @@ impl QueryPlanner {
 
         // Count specific operators
         match node {
-            PlanNode::TableScan(_) => table_count += 1,
+            PlanNode::TableScan(_) | PlanNode::ForeignScan(_) => table_count += 1,
             PlanNode::Join(_) => join_count += 1,
             _ => {}
         }
@@ -1135,6 +1735,7 @@ impl QueryPlanner {
         match node {
             PlanNode::TableScan(node) => node.cost,
             PlanNode::IndexScan(node) => node.cost,
+            PlanNode::ForeignScan(node) => node.cost,
             PlanNode::Filter(node) => node.cost + self.estimate_plan_cost(&node.input),
             PlanNode::Projection(node) => node.cost + self.estimate_plan_cost(&node.input),
             PlanNode::Join(node) => {
@@ -1176,55 +1777,6 @@ impl QueryPlanner {
         }
     }
 
-    /// Estimates number of rows in result
-    fn estimate_plan_rows(&self, node: &PlanNode) -> usize {
-        match node {
-            PlanNode::TableScan(node) => node.estimated_rows,
-            PlanNode::IndexScan(node) => node.estimated_rows,
-            PlanNode::Filter(node) => {
-                (self.estimate_plan_rows(&node.input) as f64 * node.selectivity) as usize
-            }
-            PlanNode::Projection(node) => self.estimate_plan_rows(&node.input),
-            PlanNode::Join(node) => {
-                let left_rows = self.estimate_plan_rows(&node.left);
-                let right_rows = self.estimate_plan_rows(&node.right);
-                left_rows * right_rows / 1000 // Simplified estimate
-            }
-            PlanNode::GroupBy(node) => self.estimate_plan_rows(&node.input) / 10, // Simplified estimate
-            PlanNode::Sort(node) => self.estimate_plan_rows(&node.input),
-            PlanNode::Limit(node) => node.limit.min(self.estimate_plan_rows(&node.input)),
-            PlanNode::Offset(node) => {
-                let input_rows = self.estimate_plan_rows(&node.input);
-                if node.offset >= input_rows {
-                    0
-                } else {
-                    input_rows - node.offset
-                }
-            }
-            PlanNode::Aggregate(node) => self.estimate_plan_rows(&node.input) / 10,
-            PlanNode::SetOp(node) => {
-                // Conservative: UNION ALL adds, others at most left size.
-                let l = self.estimate_plan_rows(&node.left);
-                let r = self.estimate_plan_rows(&node.right);
-                match node.op {
-                    SetOpType::Union if node.all => l.saturating_add(r),
-                    _ => l.max(1),
-                }
-            }
-            PlanNode::SemiJoin(node) => self.estimate_plan_rows(&node.left),
-            PlanNode::AntiJoin(node) => self.estimate_plan_rows(&node.left),
-            PlanNode::Distinct(node) => self.estimate_plan_rows(&node.input).max(1),
-            PlanNode::Insert(node) => node
-                .insert_subplan
-                .as_ref()
-                .map(|s| self.estimate_plan_rows(s))
-                .unwrap_or(1)
-                .max(1),
-            PlanNode::Update(_) => 1,
-            PlanNode::Delete(_) => 1,
-        }
-    }
-
     /// Gets planner settings
     pub fn settings(&self) -> &PlannerSettings {
         &self.settings
@@ -1,12 +1,16 @@
 //! Advanced query optimizer tests
 
 use crate::catalog::statistics::StatisticsManager;
+use crate::catalog::statistics::TableStatistics;
+use crate::catalog::statistics::{ColumnValue, MostCommonValue};
+use crate::parser::ast::Literal;
 use crate::planner::advanced_optimizer::{
     AdvancedOptimizationResult, AdvancedOptimizationStatistics, AdvancedOptimizerSettings,
     AdvancedQueryOptimizer,
 };
 use crate::planner::planner::{
-    ExecutionPlan, FilterNode, PlanMetadata, PlanNode, PlanStatistics, TableScanNode,
+    ExecutionPlan, FilterNode, JoinAlgorithm, JoinNode, JoinType, PlanMetadata, PlanNode,
+    PlanStatistics, SimpleEqualityFilter, SortColumn, SortDirection, SortNode, TableScanNode,
 };
 
 #[test]
@@ -22,6 +26,8 @@ fn test_advanced_optimizer_with_settings() {
         enable_query_rewriting: true,
         enable_expression_simplification: false,
         enable_subquery_extraction: true,
+        enable_join_reordering: true,
+        max_join_relations: 10,
         enable_debug_logging: true,
         cost_threshold: 2000.0,
     };
@@ -63,6 +69,8 @@ fn test_advanced_optimizer_settings_update() {
         enable_query_rewriting: false,
         enable_expression_simplification: false,
         enable_subquery_extraction: false,
+        enable_join_reordering: false,
+        max_join_relations: 10,
         enable_debug_logging: true,
         cost_threshold: 5000.0,
     };
@@ -261,6 +269,7 @@ fn create_test_execution_plan() -> Result<ExecutionPlan, Box<dyn std::error::Err
         estimated_cost: 1300.0,
         estimated_rows: 7000,
         created_at: std::time::SystemTime::now(),
+        actual_rows: None,
         statistics: PlanStatistics {
             operator_count: 2,
             max_depth: 2,
@@ -317,6 +326,7 @@ fn test_advanced_optimizer_join_order_optimization() {
         left: Box::new(left_node.clone()),
         right: Box::new(right_node.clone()),
         cost: 1500.0,
+        algorithm: crate::planner::planner::JoinAlgorithm::NestedLoop,
     };
 
     // Optimize join order
@@ -348,3 +358,187 @@ fn test_advanced_optimizer_statistics_integration() {
     assert_eq!(table_stats.table_name, "test_table");
     assert_eq!(table_stats.total_rows, 10000);
 }
+
+fn join_test_scan(table: &str) -> PlanNode {
+    PlanNode::TableScan(TableScanNode {
+        table_name: table.to_string(),
+        alias: None,
+        columns: vec!["*".to_string()],
+        filter: None,
+        cost: 1.0,
+        estimated_rows: 1,
+    })
+}
+
+fn join_test_stats(table: &str, rows: usize, columns: &[(&str, usize)]) -> TableStatistics {
+    let manager = StatisticsManager::new().unwrap();
+    TableStatistics {
+        table_name: table.to_string(),
+        total_rows: rows,
+        total_size_bytes: rows * 100,
+        last_updated: std::time::SystemTime::now(),
+        column_statistics: columns
+            .iter()
+            .map(|&(column, distinct)| {
+                (
+                    column.to_string(),
+                    manager.create_column_statistics(column, distinct, 0, 0, distinct as i64),
+                )
+            })
+            .collect(),
+    }
+}
+
+fn join_test_node(left: PlanNode, right: PlanNode, condition: &str) -> PlanNode {
+    PlanNode::Join(JoinNode {
+        join_type: JoinType::Inner,
+        condition: condition.to_string(),
+        left: Box::new(left),
+        right: Box::new(right),
+        cost: 1.0,
+        algorithm: JoinAlgorithm::NestedLoop,
+    })
+}
+
+fn join_test_plan(root: PlanNode) -> ExecutionPlan {
+    ExecutionPlan {
+        root,
+        metadata: PlanMetadata {
+            estimated_cost: 3.0,
+            estimated_rows: 1,
+            created_at: std::time::SystemTime::now(),
+            actual_rows: None,
+            statistics: PlanStatistics {
+                operator_count: 5,
+                max_depth: 3,
+                table_count: 3,
+                join_count: 2,
+            },
+        },
+    }
+}
+
+#[test]
+fn test_advanced_optimizer_reorders_joins_by_statistics() {
+    let mut optimizer = AdvancedQueryOptimizer::new().unwrap();
+    let manager = optimizer.statistics_manager_mut();
+    manager.set_table_statistics(join_test_stats("small", 10, &[("id", 10)]));
+    manager.set_table_statistics(join_test_stats(
+        "big",
+        100_000,
+        &[("small_id", 10), ("mid_id", 1000)],
+    ));
+    manager.set_table_statistics(join_test_stats("mid", 1000, &[("id", 1000)]));
+
+    // FROM small JOIN big ON small.id = big.small_id JOIN mid ON big.mid_id = mid.id
+    let plan = join_test_plan(join_test_node(
+        join_test_node(
+            join_test_scan("small"),
+            join_test_scan("big"),
+            "id=small_id",
+        ),
+        join_test_scan("mid"),
+        "mid_id=id",
+    ));
+    let result = optimizer.optimize_with_statistics(plan).unwrap();
+    assert_eq!(result.statistics.join_reorders, 1);
+
+    // The big table is probed; the small ones are hashed.
+    let PlanNode::Join(top) = &result.optimized_plan.root else {
+        panic!("expected a join: {:?}", result.optimized_plan.root);
+    };
+    assert_eq!(*top.right, join_test_scan("mid"));
+    assert_eq!(top.condition, "mid_id=id");
+    assert_eq!(top.algorithm, JoinAlgorithm::Hash);
+    let PlanNode::Join(first) = &*top.left else {
+        panic!("expected a join: {:?}", top.left);
+    };
+    assert_eq!(*first.left, join_test_scan("big"));
+    assert_eq!(*first.right, join_test_scan("small"));
+    assert_eq!(first.condition, "small_id=id");
+    assert_eq!(first.algorithm, JoinAlgorithm::Hash);
+}
+
+#[test]
+fn test_advanced_optimizer_picks_merge_join_for_ordered_inputs() {
+    let mut optimizer = AdvancedQueryOptimizer::new().unwrap();
+    let manager = optimizer.statistics_manager_mut();
+    manager.set_table_statistics(join_test_stats("a", 10_000, &[("id", 10_000)]));
+    manager.set_table_statistics(join_test_stats("b", 10_000, &[("a_id", 10_000)]));
+    let sorted = |table: &str, column: &str| {
+        PlanNode::Sort(SortNode {
+            sort_columns: vec![SortColumn {
+                column: column.to_string(),
+                direction: SortDirection::Asc,
+                nulls: None,
+            }],
+            input: Box::new(join_test_scan(table)),
+            cost: 1.0,
+        })
+    };
+
+    let plan = join_test_plan(join_test_node(
+        sorted("a", "id"),
+        sorted("b", "a_id"),
+        "id=a_id",
+    ));
+    let result = optimizer.optimize_with_statistics(plan).unwrap();
+    let PlanNode::Join(join) = &result.optimized_plan.root else {
+        panic!("expected a join: {:?}", result.optimized_plan.root);
+    };
+    assert_eq!(join.algorithm, JoinAlgorithm::Merge);
+
+    // Outer joins keep their order and algorithm.
+    let mut plan = join_test_plan(join_test_node(
+        join_test_scan("b"),
+        join_test_scan("a"),
+        "a_id=id",
+    ));
+    if let PlanNode::Join(join) = &mut plan.root {
+        join.join_type = JoinType::Left;
+    }
+    let result = optimizer.optimize_with_statistics(plan.clone()).unwrap();
+    assert_eq!(result.optimized_plan.root, plan.root);
+}
+
+#[test]
+fn test_advanced_optimizer_uses_most_common_values_for_equality_filters() {
+    let mut optimizer = AdvancedQueryOptimizer::new().unwrap();
+    let mut stats = join_test_stats("events", 10_000, &[("kind", 100)]);
+    let kind = stats.column_statistics.get_mut("kind").unwrap();
+    kind.null_frac = 0.1;
+    kind.most_common_values = vec![MostCommonValue {
+        value: ColumnValue::String("click".to_string()),
+        frequency: 0.6,
+    }];
+    optimizer
+        .statistics_manager_mut()
+        .set_table_statistics(stats);
+
+    let filter = |value: &str| {
+        join_test_plan(PlanNode::Filter(FilterNode {
+            condition: format!("kind = '{value}'"),
+            predicate: None,
+            equality: Some(SimpleEqualityFilter {
+                column: "kind".to_string(),
+                literal: Literal::String(value.to_string()),
+            }),
+            input: Box::new(join_test_scan("events")),
+            selectivity: 0.1,
+            cost: 1.0,
+        }))
+    };
+    let mut selectivity = |plan: ExecutionPlan| {
+        let result = optimizer.optimize_with_statistics(plan).unwrap();
+        match result.optimized_plan.root {
+            PlanNode::Filter(filter) => filter.selectivity,
+            other => panic!("expected a filter: {other:?}"),
+        }
+    };
+
+    // The hot value keeps its own frequency.
+    assert_eq!(selectivity(filter("click")), 0.6);
+    // The 30% left by NULLs and the hot value spread over the 99 other values.
+    let rare = selectivity(filter("scroll"));
+    assert!((rare - 0.3 / 99.0).abs() < 1e-9, "{rare}");
+}
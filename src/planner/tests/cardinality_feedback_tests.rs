@@ -0,0 +1,89 @@
+//! Tests for the cardinality feedback store (`cardinality_feedback.rs`).
+
+use crate::planner::cardinality_feedback::{fingerprint, CardinalityFeedback};
+use crate::planner::planner::{
+    ExecutionPlan, FilterNode, PlanMetadata, PlanNode, PlanStatistics, TableScanNode,
+};
+
+fn scan(cost: f64, estimated_rows: usize) -> PlanNode {
+    PlanNode::TableScan(TableScanNode {
+        table_name: "items".into(),
+        alias: None,
+        columns: vec!["id".into()],
+        filter: None,
+        cost,
+        estimated_rows,
+    })
+}
+
+fn filtered_plan(scan_rows: usize) -> ExecutionPlan {
+    ExecutionPlan {
+        root: PlanNode::Filter(FilterNode {
+            condition: "tag > 6".into(),
+            predicate: None,
+            equality: None,
+            input: Box::new(scan(1.0, scan_rows)),
+            selectivity: 0.33,
+            cost: 1.0,
+        }),
+        metadata: PlanMetadata {
+            estimated_cost: 2.0,
+            estimated_rows: scan_rows / 3,
+            created_at: std::time::SystemTime::UNIX_EPOCH,
+            actual_rows: None,
+            statistics: PlanStatistics {
+                operator_count: 2,
+                max_depth: 2,
+                table_count: 1,
+                join_count: 0,
+            },
+        },
+    }
+}
+
+#[test]
+fn test_fingerprint_ignores_costs_and_estimates() {
+    assert_eq!(fingerprint(&scan(1.0, 1000)), fingerprint(&scan(7.5, 30)));
+    let mut other = scan(1.0, 1000);
+    if let PlanNode::TableScan(n) = &mut other {
+        n.table_name = "orders".into();
+    }
+    assert_ne!(fingerprint(&scan(1.0, 1000)), fingerprint(&other));
+}
+
+#[test]
+fn test_correct_replaces_scan_rows_and_filter_selectivity() {
+    let feedback = CardinalityFeedback::new();
+    let mut plan = filtered_plan(1000);
+    assert_eq!(feedback.correct(&mut plan), 0);
+
+    let PlanNode::Filter(filter) = &plan.root else {
+        unreachable!()
+    };
+    feedback.record(fingerprint(&filter.input), 1000, 30);
+    feedback.record(fingerprint(&plan.root), 330, 9);
+    assert_eq!(feedback.len(), 2);
+
+    assert_eq!(feedback.correct(&mut plan), 2);
+    assert_eq!(plan.root.estimated_rows(), 9);
+    assert_eq!(plan.metadata.estimated_rows, 9);
+    // Already corrected: nothing changes a second time.
+    assert_eq!(feedback.correct(&mut plan), 0);
+}
+
+#[test]
+fn test_feedback_survives_save_and_load() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("statistics").join("feedback.json");
+    let feedback = CardinalityFeedback::new();
+    feedback.record(42, 1000, 30);
+    feedback.record(42, 1000, 31);
+    feedback.save(&path).unwrap();
+
+    let loaded = CardinalityFeedback::load(&path).unwrap();
+    let seen = loaded.observation(42).unwrap();
+    assert_eq!((seen.actual_rows, seen.executions), (31, 2));
+    assert!(CardinalityFeedback::load(&dir.path().join("missing.json"))
+        .unwrap()
+        .is_empty());
+}
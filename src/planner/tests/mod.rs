@@ -1,6 +1,7 @@
 //! Query planner tests for rustdb
 
 pub mod advanced_optimizer_tests;
+pub mod cardinality_feedback_tests;
 pub mod optimizer_coverage_tests;
 pub mod optimizer_tests;
 pub mod planner_coverage_tests;
@@ -2,13 +2,13 @@
 
 use crate::common::Result;
 use crate::parser::ast::{BinaryOperator, Expression, Literal};
-use crate::parser::SqlParser;
+use crate::parser::{SqlParser, SqlStatement};
 use crate::planner::planner::{
-    ExecutionPlan, FilterNode, JoinNode, JoinType, PlanMetadata, PlanNode, PlanStatistics,
-    TableScanNode,
+    ExecutionPlan, FilterNode, JoinAlgorithm, JoinNode, JoinType, PlanMetadata, PlanNode,
+    PlanStatistics, TableScanNode,
 };
 use crate::planner::{OptimizerSettings, QueryOptimizer, QueryPlanner};
-use crate::storage::index_registry::IndexRegistry;
+use crate::storage::index_registry::{IndexOptions, IndexRegistry};
 use std::sync::Arc;
 
 fn plan_meta(cost: f64) -> PlanMetadata {
@@ -16,6 +16,7 @@ fn plan_meta(cost: f64) -> PlanMetadata {
         estimated_cost: cost,
         estimated_rows: 10,
         created_at: std::time::SystemTime::UNIX_EPOCH,
+        actual_rows: None,
         statistics: PlanStatistics {
             operator_count: 3,
             max_depth: 2,
@@ -46,6 +47,7 @@ fn test_optimizer_join_reorder_swaps_heavy_left() -> Result<()> {
         left: Box::new(table_scan("heavy", 200.0, None)),
         right: Box::new(table_scan("light", 5.0, None)),
         cost: 10.0,
+        algorithm: JoinAlgorithm::NestedLoop,
     });
     let plan = ExecutionPlan {
         root: join,
@@ -211,6 +213,7 @@ fn test_optimizer_filter_over_join_pushdown_path() -> Result<()> {
         left: Box::new(table_scan("t1", 10.0, None)),
         right: Box::new(table_scan("t2", 10.0, None)),
         cost: 2.0,
+        algorithm: JoinAlgorithm::NestedLoop,
     });
     let root = PlanNode::Filter(FilterNode {
         condition: "a=1".into(),
@@ -271,6 +274,7 @@ fn test_optimizer_rewrites_exists_to_semi_join() -> Result<()> {
             table: TableReference::Table {
                 name: "t".to_string(),
                 alias: None,
+                as_of: None,
             },
             joins: vec![],
         }),
@@ -284,6 +288,7 @@ fn test_optimizer_rewrites_exists_to_semi_join() -> Result<()> {
                 table: TableReference::Table {
                     name: "u".to_string(),
                     alias: None,
+                    as_of: None,
                 },
                 joins: vec![],
             }),
@@ -323,3 +328,48 @@ fn test_optimizer_rewrites_exists_to_semi_join() -> Result<()> {
     );
     Ok(())
 }
+
+#[test]
+fn test_optimizer_uses_partial_index_only_when_predicate_implied() -> Result<()> {
+    let SqlStatement::CreateIndex(ci) = SqlParser::new(
+        "CREATE INDEX idx_open ON orders (customer_id) WHERE status = 'open' AND total > 100",
+    )?
+    .parse()?
+    else {
+        panic!("expected CREATE INDEX");
+    };
+    let mut reg = IndexRegistry::new();
+    let options = IndexOptions {
+        method: ci.method,
+        predicate: ci.where_clause,
+        expressions: ci.expressions,
+    };
+    reg.create_index_with_options("orders", "idx_open", ci.columns, options)?;
+    let mut opt = QueryOptimizer::new()?.with_index_registry(Arc::new(reg));
+    let planner = QueryPlanner::new()?;
+
+    for (filter, uses_index) in [
+        ("customer_id = 1 AND status = 'open' AND total > 100", true),
+        ("status = 'open' AND customer_id = 1 AND total = 250", true),
+        (
+            "customer_id = 1 AND orders.status = 'open' AND 500 <= total",
+            true,
+        ),
+        ("customer_id = 1", false),
+        ("customer_id = 1 AND status = 'open' AND total > 50", false),
+        (
+            "customer_id = 1 AND status = 'closed' AND total > 500",
+            false,
+        ),
+    ] {
+        let stmt = SqlParser::new(&format!("SELECT * FROM orders WHERE {filter}"))?.parse()?;
+        let res = opt.optimize(planner.create_plan(&stmt)?)?;
+        assert_eq!(
+            plan_contains_index_scan_on(&res.optimized_plan.root, "orders", "idx_open"),
+            uses_index,
+            "{filter}: {:?}",
+            res.optimized_plan.root
+        );
+    }
+    Ok(())
+}
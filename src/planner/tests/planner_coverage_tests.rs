@@ -53,6 +53,7 @@ fn test_execution_plan_clone() {
         estimated_cost: 1.0,
         estimated_rows: 1,
         created_at: std::time::SystemTime::now(),
+        actual_rows: None,
         statistics: PlanStatistics {
             operator_count: 1,
             max_depth: 1,
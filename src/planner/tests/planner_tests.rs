@@ -94,6 +94,7 @@ fn test_create_select_plan_columns_and_groupby_aggregates() -> Result<()> {
             table: TableReference::Table {
                 name: "employees".to_string(),
                 alias: None,
+                as_of: None,
             },
             joins: vec![],
         }),
@@ -0,0 +1,652 @@
+//! In-memory SQL playground engine
+//!
+//! [`MemoryEngine`] runs SQL from the regular parser against
+//! [`MemoryStorage`](crate::storage::memory::MemoryStorage): no threads, files or async
+//! runtime. It is available in every build and is the whole engine when the crate is
+//! built with `--no-default-features` (e.g. for `wasm32-unknown-unknown`, a browser
+//! playground or an edge runtime). On wasm32 the module also exports a small C-ABI bridge
+//! (`rustdb_playground_*`, JSON results) so a page can drive it without generated bindings.
+//!
+//! Queries (`SELECT`, set operations) are planned by [`QueryPlanner`] and run by the
+//! executor's operators like the native engine's, with table scans over the in-memory rows;
+//! `INSERT`, `UPDATE` and `DELETE` evaluate their expressions with the executor's
+//! [`eval_scalar_expression`] and [`eval_predicate_expression`]. Also supported:
+//! `CREATE TABLE` / `DROP TABLE` and `BEGIN` / `COMMIT` / `ROLLBACK`. Indexes and durability
+//! belong to the native engine.
+
+use crate::common::row_mapping::result_value;
+use crate::common::types::{ColumnValue, DataType, Row, RowSchema};
+use crate::common::{Error, Result};
+use crate::executor::operators::{
+    eval_predicate_expression, eval_scalar_expression, Operator, OperatorStatistics, ScanSource,
+};
+use crate::executor::QueryExecutor;
+use crate::parser::ast::*;
+use crate::parser::SqlParser;
+use crate::planner::planner::SimpleEqualityFilter;
+use crate::planner::QueryPlanner;
+use crate::storage::memory::{MemoryStorage, MemoryTable};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Result of one statement
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct QueryResult {
+    /// Output column names (empty for DML / DDL and for queries that return no rows)
+    pub columns: Vec<String>,
+    /// Output rows
+    pub rows: Vec<Vec<Literal>>,
+    /// Rows inserted, updated or deleted
+    pub rows_affected: u64,
+}
+
+/// Single-session SQL engine over in-memory tables
+#[derive(Debug, Default)]
+pub struct MemoryEngine {
+    storage: MemoryStorage,
+    /// Copy of `storage` taken at `BEGIN`; restored on `ROLLBACK`
+    snapshot: Option<MemoryStorage>,
+}
+
+impl MemoryEngine {
+    /// Creates an engine with no tables
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Underlying table store
+    pub fn storage(&self) -> &MemoryStorage {
+        &self.storage
+    }
+
+    /// Whether a transaction is open
+    pub fn in_transaction(&self) -> bool {
+        self.snapshot.is_some()
+    }
+
+    /// Executes exactly one statement
+    pub fn execute(&mut self, sql: &str) -> Result<QueryResult> {
+        let mut results = self.execute_script(sql)?;
+        match results.len() {
+            1 => Ok(results.remove(0)),
+            0 => Err(Error::parser("Empty statement".to_string())),
+            n => Err(Error::parser(format!(
+                "Expected one statement, got {}; use execute_script",
+                n
+            ))),
+        }
+    }
+
+    /// Executes `;`-separated statements, stopping at the first error
+    pub fn execute_script(&mut self, sql: &str) -> Result<Vec<QueryResult>> {
+        let statements = SqlParser::new(sql)?.parse_multiple()?;
+        statements
+            .iter()
+            .map(|stmt| self.execute_statement(stmt))
+            .collect()
+    }
+
+    /// Executes a script and renders the outcome as JSON:
+    /// `{"results":[{"columns":[..],"rows":[[..]],"rows_affected":n}]}` or `{"error":".."}`.
+    pub fn execute_json(&mut self, sql: &str) -> String {
+        let value = match self.execute_script(sql) {
+            Ok(results) => serde_json::json!({
+                "results": results.iter().map(result_to_json).collect::<Vec<_>>()
+            }),
+            Err(e) => serde_json::json!({ "error": e.to_string() }),
+        };
+        value.to_string()
+    }
+
+    fn execute_statement(&mut self, stmt: &SqlStatement) -> Result<QueryResult> {
+        match stmt {
+            SqlStatement::CreateTable(create) => {
+                if create.if_not_exists && self.storage.contains(&create.table_name) {
+                    return Ok(QueryResult::default());
+                }
+                self.storage
+                    .create_table(MemoryTable::from_create(create)?)?;
+                Ok(QueryResult::default())
+            }
+            SqlStatement::DropTable(drop) => {
+                if !self.storage.drop_table(&drop.table_name) && !drop.if_exists {
+                    return Err(Error::validation(format!(
+                        "Table '{}' does not exist",
+                        drop.table_name
+                    )));
+                }
+                Ok(QueryResult::default())
+            }
+            SqlStatement::Insert(insert) => self.insert(insert),
+            SqlStatement::Select(_) | SqlStatement::SetOperation(_) => self.query(stmt),
+            SqlStatement::Update(update) => self.update(update),
+            SqlStatement::Delete(delete) => self.delete(delete),
+            SqlStatement::BeginTransaction => {
+                if self.snapshot.is_some() {
+                    return Err(Error::validation("Transaction already open".to_string()));
+                }
+                self.snapshot = Some(self.storage.clone());
+                Ok(QueryResult::default())
+            }
+            SqlStatement::CommitTransaction => {
+                self.snapshot
+                    .take()
+                    .ok_or_else(|| Error::validation("No active transaction".to_string()))?;
+                Ok(QueryResult::default())
+            }
+            SqlStatement::RollbackTransaction => {
+                self.storage = self
+                    .snapshot
+                    .take()
+                    .ok_or_else(|| Error::validation("No active transaction".to_string()))?;
+                Ok(QueryResult::default())
+            }
+            other => Err(Error::unsupported(format!(
+                "Statement not supported by the in-memory engine: {}",
+                statement_kind(other)
+            ))),
+        }
+    }
+
+    fn insert(&mut self, insert: &InsertStatement) -> Result<QueryResult> {
+        let InsertValues::Values(value_rows) = &insert.values else {
+            return Err(Error::unsupported(
+                "INSERT ... SELECT is not supported by the in-memory engine".to_string(),
+            ));
+        };
+        let table = self.storage.table_mut(&insert.table)?;
+        let targets: Vec<usize> = match &insert.columns {
+            Some(cols) => cols
+                .iter()
+                .map(|c| {
+                    table.column_index(c).ok_or_else(|| {
+                        Error::validation(format!("Column '{}' not found in '{}'", c, table.name))
+                    })
+                })
+                .collect::<Result<_>>()?,
+            None => (0..table.columns.len()).collect(),
+        };
+
+        // A failing row removes the rows this statement already added.
+        let start = table.rows.len();
+        let no_columns = Row::new();
+        for values in value_rows {
+            if values.len() != targets.len() {
+                table.rows.truncate(start);
+                return Err(Error::validation(format!(
+                    "INSERT has {} values for {} columns",
+                    values.len(),
+                    targets.len()
+                )));
+            }
+            let mut row = vec![ColumnValue::null(); table.columns.len()];
+            for (&pos, expr) in targets.iter().zip(values) {
+                row[pos] = eval_scalar_expression(&no_columns, expr);
+            }
+            if let Err(e) = table.check_row(&row, None) {
+                table.rows.truncate(start);
+                return Err(e);
+            }
+            table.rows.push(row);
+        }
+        Ok(QueryResult {
+            rows_affected: (table.rows.len() - start) as u64,
+            ..QueryResult::default()
+        })
+    }
+
+    fn update(&mut self, update: &UpdateStatement) -> Result<QueryResult> {
+        let table = self.storage.table_mut(&update.table)?;
+        let assignments: Vec<(usize, &Expression)> = update
+            .assignments
+            .iter()
+            .map(|a| {
+                table
+                    .column_index(&a.column)
+                    .map(|i| (i, &a.value))
+                    .ok_or_else(|| Error::validation(format!("Column '{}' not found", a.column)))
+            })
+            .collect::<Result<_>>()?;
+
+        let schema = table.row_schema();
+        let mut new_rows = table.rows.clone();
+        let mut affected = 0u64;
+        for (i, values) in table.rows.iter().enumerate() {
+            let row = Row::from_values(schema.clone(), values.clone());
+            if !matches_where(update.where_clause.as_ref(), &row) {
+                continue;
+            }
+            for (pos, expr) in &assignments {
+                new_rows[i][*pos] = eval_scalar_expression(&row, expr);
+            }
+            affected += 1;
+        }
+        let before = std::mem::replace(&mut table.rows, new_rows);
+        for i in 0..table.rows.len() {
+            if let Err(e) = table.check_row(&table.rows[i], Some(i)) {
+                table.rows = before;
+                return Err(e);
+            }
+        }
+        Ok(QueryResult {
+            rows_affected: affected,
+            ..QueryResult::default()
+        })
+    }
+
+    fn delete(&mut self, delete: &DeleteStatement) -> Result<QueryResult> {
+        let table = self.storage.table_mut(&delete.table)?;
+        let schema = table.row_schema();
+        let before = table.rows.len();
+        table.rows.retain(|values| {
+            let row = Row::from_values(schema.clone(), values.clone());
+            !matches_where(delete.where_clause.as_ref(), &row)
+        });
+        Ok(QueryResult {
+            rows_affected: (before - table.rows.len()) as u64,
+            ..QueryResult::default()
+        })
+    }
+
+    /// Plans the query with [`QueryPlanner`] and runs it on a [`QueryExecutor`] whose table
+    /// scans read this engine's tables.
+    fn query(&self, stmt: &SqlStatement) -> Result<QueryResult> {
+        if let SqlStatement::Select(select) = stmt {
+            if select.from.is_none() {
+                return select_without_from(select);
+            }
+        }
+        let plan = QueryPlanner::new()?.create_plan(stmt)?;
+        let executor = QueryExecutor::new(Arc::new(TableScans::new(&self.storage)))?;
+        let rows = executor.execute(&plan)?;
+        let columns: Vec<String> = rows
+            .first()
+            .map(|r| r.column_names().map(str::to_string).collect())
+            .unwrap_or_default();
+        let rows = rows
+            .iter()
+            .map(|row| {
+                columns
+                    .iter()
+                    .map(|c| row.get_value(c).map_or(Literal::Null, result_literal))
+                    .collect()
+            })
+            .collect();
+        Ok(QueryResult {
+            columns,
+            rows,
+            rows_affected: 0,
+        })
+    }
+}
+
+/// The one-row result of a `SELECT` without `FROM`; columns are named like the engine's
+/// (`col1`, `col2`, ... unless aliased).
+fn select_without_from(select: &SelectStatement) -> Result<QueryResult> {
+    let no_columns = Row::new();
+    let mut columns = Vec::new();
+    let mut values = Vec::new();
+    for (i, item) in select.select_list.iter().enumerate() {
+        let SelectItem::Expression { expr, alias } = item else {
+            return Err(Error::validation(
+                "SELECT * requires a FROM clause".to_string(),
+            ));
+        };
+        columns.push(alias.clone().unwrap_or_else(|| format!("col{}", i + 1)));
+        values.push(result_literal(&eval_scalar_expression(&no_columns, expr)));
+    }
+    Ok(QueryResult {
+        columns,
+        rows: vec![values],
+        rows_affected: 0,
+    })
+}
+
+fn matches_where(predicate: Option<&Expression>, row: &Row) -> bool {
+    predicate.is_none_or(|p| eval_predicate_expression(row, p))
+}
+
+/// `value` as a result cell: strings without the SQL quotes rows keep around them
+fn result_literal(value: &ColumnValue) -> Literal {
+    if value.is_null() {
+        return Literal::Null;
+    }
+    match result_value(value.data_type.clone()) {
+        DataType::Null => Literal::Null,
+        DataType::Boolean(b) => Literal::Boolean(b),
+        DataType::TinyInt(n) => Literal::Integer(n.into()),
+        DataType::SmallInt(n) => Literal::Integer(n.into()),
+        DataType::Integer(n) => Literal::Integer(n.into()),
+        DataType::BigInt(n) => Literal::Integer(n),
+        DataType::Float(f) => Literal::Float(f.into()),
+        DataType::Double(f) => Literal::Float(f),
+        DataType::Char(s)
+        | DataType::Varchar(s)
+        | DataType::Text(s)
+        | DataType::Date(s)
+        | DataType::Time(s)
+        | DataType::Timestamp(s) => Literal::String(s),
+        DataType::Blob(b) => Literal::String(format!("{:?}", b)),
+    }
+}
+
+/// Table scans over a copy of the engine's tables, for one query
+struct TableScans {
+    tables: HashMap<String, (Arc<RowSchema>, Arc<Vec<Vec<ColumnValue>>>)>,
+}
+
+impl TableScans {
+    fn new(storage: &MemoryStorage) -> Self {
+        let tables = storage
+            .table_names()
+            .into_iter()
+            .filter_map(|name| {
+                let table = storage.table(&name).ok()?;
+                let entry = (table.row_schema(), Arc::new(table.rows.clone()));
+                Some((name.to_ascii_lowercase(), entry))
+            })
+            .collect();
+        Self { tables }
+    }
+}
+
+impl ScanSource for TableScans {
+    fn create_table_scan(
+        &self,
+        table_name: String,
+        _filter: Option<String>,
+        pushdown_equality: Option<SimpleEqualityFilter>,
+        _schema: Vec<String>,
+    ) -> Result<Box<dyn Operator>> {
+        let (schema, rows) = self
+            .tables
+            .get(&table_name.to_ascii_lowercase())
+            .ok_or_else(|| Error::validation(format!("Table '{}' does not exist", table_name)))?;
+        // The executor leaves a simple `column = literal` filter to the scan.
+        let predicate = pushdown_equality.map(|eq| Expression::BinaryOp {
+            left: Box::new(Expression::Identifier(eq.column)),
+            op: BinaryOperator::Equal,
+            right: Box::new(Expression::Literal(eq.literal)),
+        });
+        Ok(Box::new(MemoryScan {
+            schema: schema.clone(),
+            rows: rows.clone(),
+            predicate,
+            position: 0,
+            statistics: OperatorStatistics::default(),
+        }))
+    }
+}
+
+/// Every column of every row of a table, in insertion order
+struct MemoryScan {
+    schema: Arc<RowSchema>,
+    rows: Arc<Vec<Vec<ColumnValue>>>,
+    predicate: Option<Expression>,
+    position: usize,
+    statistics: OperatorStatistics,
+}
+
+impl Operator for MemoryScan {
+    fn next(&mut self) -> Result<Option<Row>> {
+        while let Some(values) = self.rows.get(self.position) {
+            self.position += 1;
+            self.statistics.rows_processed += 1;
+            let row = Row::from_values(self.schema.clone(), values.clone());
+            if matches_where(self.predicate.as_ref(), &row) {
+                self.statistics.rows_returned += 1;
+                return Ok(Some(row));
+            }
+        }
+        Ok(None)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.position = 0;
+        self.statistics = OperatorStatistics::default();
+        Ok(())
+    }
+
+    fn get_schema(&self) -> Result<Vec<String>> {
+        Ok(self.schema.columns().to_vec())
+    }
+
+    fn get_statistics(&self) -> OperatorStatistics {
+        self.statistics.clone()
+    }
+}
+
+fn statement_kind(stmt: &SqlStatement) -> &'static str {
+    match stmt {
+        SqlStatement::CreateIndex(_) => "CREATE INDEX",
+        SqlStatement::DropIndex(_) => "DROP INDEX",
+        SqlStatement::AlterTable(_) => "ALTER TABLE",
+        SqlStatement::Prepare(_) => "PREPARE",
+        SqlStatement::Execute(_) => "EXECUTE",
+        SqlStatement::Explain(_) => "EXPLAIN",
+        _ => "statement",
+    }
+}
+
+fn literal_to_json(v: &Literal) -> serde_json::Value {
+    match v {
+        Literal::Null => serde_json::Value::Null,
+        Literal::Boolean(b) => (*b).into(),
+        Literal::Integer(n) => (*n).into(),
+        Literal::Float(f) => (*f).into(),
+        Literal::String(s) => s.clone().into(),
+    }
+}
+
+fn result_to_json(result: &QueryResult) -> serde_json::Value {
+    serde_json::json!({
+        "columns": result.columns,
+        "rows": result
+            .rows
+            .iter()
+            .map(|r| r.iter().map(literal_to_json).collect::<Vec<_>>())
+            .collect::<Vec<_>>(),
+        "rows_affected": result.rows_affected,
+    })
+}
+
+/// Raw wasm exports for a JavaScript host (no generated bindings needed).
+///
+/// Protocol: `rustdb_playground_alloc(len)` returns a buffer the host fills with UTF-8 SQL;
+/// `rustdb_playground_execute(ptr, len)` runs it against a per-instance engine and returns a
+/// pointer to the JSON result, whose length is `rustdb_playground_result_len()`. The result
+/// buffer stays valid until the next execute call. The host frees its input buffer with
+/// `rustdb_playground_free(ptr, len)`.
+#[cfg(target_arch = "wasm32")]
+mod wasm_exports {
+    use super::MemoryEngine;
+    use std::cell::RefCell;
+
+    thread_local! {
+        static ENGINE: RefCell<MemoryEngine> = RefCell::new(MemoryEngine::new());
+        static RESULT: RefCell<String> = const { RefCell::new(String::new()) };
+    }
+
+    #[no_mangle]
+    pub extern "C" fn rustdb_playground_alloc(len: usize) -> *mut u8 {
+        let mut buf = Vec::<u8>::with_capacity(len);
+        let ptr = buf.as_mut_ptr();
+        std::mem::forget(buf);
+        ptr
+    }
+
+    /// # Safety
+    /// `ptr`/`len` must come from [`rustdb_playground_alloc`].
+    #[no_mangle]
+    pub unsafe extern "C" fn rustdb_playground_free(ptr: *mut u8, len: usize) {
+        drop(Vec::from_raw_parts(ptr, 0, len));
+    }
+
+    /// # Safety
+    /// `ptr` must point to `len` initialized bytes.
+    #[no_mangle]
+    pub unsafe extern "C" fn rustdb_playground_execute(ptr: *const u8, len: usize) -> *const u8 {
+        let bytes = std::slice::from_raw_parts(ptr, len);
+        let json = match std::str::from_utf8(bytes) {
+            Ok(sql) => ENGINE.with(|e| e.borrow_mut().execute_json(sql)),
+            Err(_) => r#"{"error":"SQL is not valid UTF-8"}"#.to_string(),
+        };
+        RESULT.with(|r| {
+            *r.borrow_mut() = json;
+            r.borrow().as_ptr()
+        })
+    }
+
+    #[no_mangle]
+    pub extern "C" fn rustdb_playground_result_len() -> usize {
+        RESULT.with(|r| r.borrow().len())
+    }
+
+    /// Drops all tables (fresh playground session).
+    #[no_mangle]
+    pub extern "C" fn rustdb_playground_reset() {
+        ENGINE.with(|e| *e.borrow_mut() = MemoryEngine::new());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(engine: &mut MemoryEngine, sql: &str) -> Vec<Vec<Literal>> {
+        engine.execute(sql).unwrap().rows
+    }
+
+    fn seeded() -> MemoryEngine {
+        let mut engine = MemoryEngine::new();
+        engine
+            .execute_script(
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, age INTEGER);
+                 INSERT INTO users (id, name, age) VALUES (1, 'ann', 30), (2, 'bob', 25), (3, 'cy', 30);",
+            )
+            .unwrap();
+        engine
+    }
+
+    #[test]
+    fn test_select_filter_order_limit() {
+        let mut engine = seeded();
+        let r = engine
+            .execute("SELECT name FROM users WHERE age >= 26 ORDER BY name DESC LIMIT 1")
+            .unwrap();
+        assert_eq!(r.columns, vec!["name"]);
+        assert_eq!(r.rows, vec![vec![Literal::String("cy".into())]]);
+
+        let all = rows(&mut engine, "SELECT * FROM users ORDER BY id");
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[1][1], Literal::String("bob".into()));
+        assert_eq!(
+            rows(&mut engine, "SELECT 1 + 2 * 3"),
+            vec![vec![Literal::Integer(7)]]
+        );
+    }
+
+    #[test]
+    fn test_group_by_aggregates() {
+        let mut engine = seeded();
+        let r = rows(
+            &mut engine,
+            "SELECT age, COUNT(*) AS n FROM users GROUP BY age HAVING COUNT(*) > 1 ORDER BY age",
+        );
+        assert_eq!(r, vec![vec![Literal::Integer(30), Literal::Integer(2)]]);
+        let r = rows(
+            &mut engine,
+            "SELECT MAX(age), SUM(age), COUNT(*) FROM users",
+        );
+        assert_eq!(
+            r,
+            vec![vec![
+                Literal::Integer(30),
+                Literal::Integer(85),
+                Literal::Integer(3)
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_join_runs_on_the_shared_executor() {
+        let mut engine = seeded();
+        engine
+            .execute_script(
+                "CREATE TABLE pets (owner_id INTEGER, pet TEXT);
+                 INSERT INTO pets (owner_id, pet) VALUES (1, 'cat'), (3, 'dog'), (3, 'fish');",
+            )
+            .unwrap();
+        let r = engine
+            .execute(
+                "SELECT users.name, pets.pet FROM users JOIN pets ON users.id = pets.owner_id \
+                 WHERE users.age = 30 ORDER BY pet",
+            )
+            .unwrap();
+        assert_eq!(r.columns, vec!["name", "pet"]);
+        assert_eq!(
+            r.rows,
+            vec![
+                vec![Literal::String("ann".into()), Literal::String("cat".into())],
+                vec![Literal::String("cy".into()), Literal::String("dog".into())],
+                vec![Literal::String("cy".into()), Literal::String("fish".into())],
+            ]
+        );
+        assert_eq!(
+            rows(&mut engine, "SELECT COUNT(*) FROM users WHERE age > 100"),
+            vec![vec![Literal::Integer(0)]]
+        );
+    }
+
+    #[test]
+    fn test_dml_and_constraints() {
+        let mut engine = seeded();
+        let r = engine
+            .execute("UPDATE users SET age = age + 1 WHERE name = 'bob'")
+            .unwrap();
+        assert_eq!(r.rows_affected, 1);
+        assert!(engine
+            .execute("INSERT INTO users (id, name) VALUES (1, 'dup')")
+            .is_err());
+        assert!(engine
+            .execute("INSERT INTO users (id, name) VALUES (9, NULL)")
+            .is_err());
+        assert!(engine.execute("UPDATE users SET id = 1").is_err());
+        assert_eq!(
+            rows(&mut engine, "SELECT COUNT(*) FROM users"),
+            vec![vec![Literal::Integer(3)]]
+        );
+        let r = engine.execute("DELETE FROM users WHERE age = 30").unwrap();
+        assert_eq!(r.rows_affected, 2);
+        assert!(engine.execute("SELECT * FROM missing").is_err());
+    }
+
+    #[test]
+    fn test_transactions_roll_back() {
+        let mut engine = seeded();
+        engine.execute("BEGIN TRANSACTION").unwrap();
+        engine.execute("DELETE FROM users").unwrap();
+        assert!(engine.in_transaction());
+        engine.execute("ROLLBACK").unwrap();
+        assert_eq!(
+            rows(&mut engine, "SELECT COUNT(*) FROM users"),
+            vec![vec![Literal::Integer(3)]]
+        );
+        assert!(engine.execute("COMMIT").is_err());
+    }
+
+    #[test]
+    fn test_execute_json() {
+        let mut engine = seeded();
+        let json: serde_json::Value = serde_json::from_str(
+            &engine.execute_json("SELECT id, name FROM users WHERE name LIKE 'a%'"),
+        )
+        .unwrap();
+        assert_eq!(json["results"][0]["rows"][0][0], 1);
+        assert_eq!(json["results"][0]["rows"][0][1], "ann");
+        let err: serde_json::Value =
+            serde_json::from_str(&engine.execute_json("SELECT * FROM nope")).unwrap();
+        assert!(err["error"].is_string());
+    }
+}
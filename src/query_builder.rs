@@ -0,0 +1,419 @@
+//! Typed query builder: `table("users").filter(col("age").gt(30)).select(&["name"])` builds the
+//! [`SqlStatement`] the parser would produce for the same query, without assembling SQL text.
+//!
+//! Values are always bound as literals ([`ToValue`]), so they cannot change the statement; table
+//! and column names must be plain identifiers (letters, digits, `_`) and are checked when the
+//! statement is built. The embedded API runs a query with [`crate::Connection::fetch`], which
+//! renders it to SQL ([`TableQuery::to_sql`]) so it is logged and audited like any statement.
+
+use crate::common::row_mapping::{sql_literal, ToValue};
+use crate::common::types::DataType;
+use crate::common::{Error, Result};
+use crate::parser::ast::{
+    BinaryOperator, Expression, FromClause, InList, Literal, NullsOrder, OrderByItem,
+    OrderDirection, SelectItem, SelectStatement, SqlStatement, TableReference, UnaryOperator,
+};
+use crate::planner::planner::expression_to_sql;
+
+/// Column reference.
+pub fn col(name: &str) -> Expr {
+    Expr(Expression::Identifier(name.to_string()))
+}
+
+/// Bound value.
+pub fn val(value: impl ToValue) -> Expr {
+    Expr(value_expression(&value.to_value()))
+}
+
+/// Starts a `SELECT * FROM table` query.
+pub fn table(name: &str) -> TableQuery {
+    TableQuery {
+        table: name.to_string(),
+        columns: Vec::new(),
+        filter: None,
+        order_by: Vec::new(),
+        limit: None,
+        offset: None,
+    }
+}
+
+/// Scalar expression for [`TableQuery::filter`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr(Expression);
+
+/// Right-hand side of a comparison: another [`Expr`] or any [`ToValue`] (bound as a literal).
+pub trait IntoExpr {
+    fn into_expr(self) -> Expr;
+}
+
+impl IntoExpr for Expr {
+    fn into_expr(self) -> Expr {
+        self
+    }
+}
+
+impl<T: ToValue> IntoExpr for T {
+    fn into_expr(self) -> Expr {
+        val(self)
+    }
+}
+
+impl Expr {
+    fn binary(self, op: BinaryOperator, right: impl IntoExpr) -> Expr {
+        Expr(Expression::BinaryOp {
+            left: Box::new(self.0),
+            op,
+            right: Box::new(right.into_expr().0),
+        })
+    }
+
+    pub fn eq(self, right: impl IntoExpr) -> Expr {
+        self.binary(BinaryOperator::Equal, right)
+    }
+
+    pub fn ne(self, right: impl IntoExpr) -> Expr {
+        self.binary(BinaryOperator::NotEqual, right)
+    }
+
+    pub fn lt(self, right: impl IntoExpr) -> Expr {
+        self.binary(BinaryOperator::LessThan, right)
+    }
+
+    pub fn le(self, right: impl IntoExpr) -> Expr {
+        self.binary(BinaryOperator::LessThanOrEqual, right)
+    }
+
+    pub fn gt(self, right: impl IntoExpr) -> Expr {
+        self.binary(BinaryOperator::GreaterThan, right)
+    }
+
+    pub fn ge(self, right: impl IntoExpr) -> Expr {
+        self.binary(BinaryOperator::GreaterThanOrEqual, right)
+    }
+
+    pub fn and(self, right: Expr) -> Expr {
+        self.binary(BinaryOperator::And, right)
+    }
+
+    pub fn or(self, right: Expr) -> Expr {
+        self.binary(BinaryOperator::Or, right)
+    }
+
+    pub fn not(self) -> Expr {
+        Expr(Expression::UnaryOp {
+            op: UnaryOperator::Not,
+            expr: Box::new(self.0),
+        })
+    }
+
+    pub fn is_null(self) -> Expr {
+        Expr(Expression::IsNull {
+            expr: Box::new(self.0),
+            negated: false,
+        })
+    }
+
+    pub fn is_not_null(self) -> Expr {
+        Expr(Expression::IsNull {
+            expr: Box::new(self.0),
+            negated: true,
+        })
+    }
+
+    /// `LIKE pattern` (`%` and `_` wildcards).
+    pub fn like(self, pattern: &str) -> Expr {
+        Expr(Expression::Like {
+            expr: Box::new(self.0),
+            pattern: Box::new(val(pattern).0),
+            negated: false,
+        })
+    }
+
+    pub fn between(self, low: impl IntoExpr, high: impl IntoExpr) -> Expr {
+        Expr(Expression::Between {
+            expr: Box::new(self.0),
+            low: Box::new(low.into_expr().0),
+            high: Box::new(high.into_expr().0),
+        })
+    }
+
+    pub fn in_list<T: IntoExpr>(self, values: impl IntoIterator<Item = T>) -> Expr {
+        Expr(Expression::In {
+            expr: Box::new(self.0),
+            list: InList::Values(values.into_iter().map(|v| v.into_expr().0).collect()),
+        })
+    }
+
+    pub fn into_expression(self) -> Expression {
+        self.0
+    }
+}
+
+/// The literal the parser reads for `value`; strings keep their quotes (see [`sql_literal`]).
+fn value_expression(value: &DataType) -> Expression {
+    Expression::Literal(match value {
+        DataType::Null => Literal::Null,
+        DataType::Boolean(b) => Literal::Boolean(*b),
+        DataType::TinyInt(n) => Literal::Integer(i64::from(*n)),
+        DataType::SmallInt(n) => Literal::Integer(i64::from(*n)),
+        DataType::Integer(n) => Literal::Integer(i64::from(*n)),
+        DataType::BigInt(n) => Literal::Integer(*n),
+        DataType::Float(f) => Literal::Float(f64::from(*f)),
+        DataType::Double(f) => Literal::Float(*f),
+        other => Literal::String(sql_literal(other)),
+    })
+}
+
+/// `SELECT` over one table; see the module docs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableQuery {
+    table: String,
+    columns: Vec<String>,
+    filter: Option<Expression>,
+    order_by: Vec<OrderByItem>,
+    limit: Option<u64>,
+    offset: Option<u64>,
+}
+
+impl TableQuery {
+    /// Adds a `WHERE` condition; several are combined with `AND`.
+    pub fn filter(mut self, condition: Expr) -> Self {
+        self.filter = Some(match self.filter.take() {
+            Some(previous) => Expr(previous).and(condition).0,
+            None => condition.0,
+        });
+        self
+    }
+
+    /// Selects these columns instead of `*`.
+    pub fn select(mut self, columns: &[&str]) -> Self {
+        self.columns = columns.iter().map(|c| c.to_string()).collect();
+        self
+    }
+
+    pub fn order_by(mut self, column: &str) -> Self {
+        self.order_by.push(OrderByItem {
+            expr: col(column).0,
+            direction: OrderDirection::Asc,
+            nulls: None,
+        });
+        self
+    }
+
+    pub fn order_by_desc(mut self, column: &str) -> Self {
+        self.order_by.push(OrderByItem {
+            expr: col(column).0,
+            direction: OrderDirection::Desc,
+            nulls: None,
+        });
+        self
+    }
+
+    pub fn limit(mut self, rows: u64) -> Self {
+        self.limit = Some(rows);
+        self
+    }
+
+    pub fn offset(mut self, rows: u64) -> Self {
+        self.offset = Some(rows);
+        self
+    }
+
+    /// The statement, as [`crate::parser::SqlParser`] produces it for [`Self::to_sql`].
+    pub fn statement(&self) -> Result<SqlStatement> {
+        check_identifier(&self.table)?;
+        for column in &self.columns {
+            check_identifier(column)?;
+        }
+        if let Some(filter) = &self.filter {
+            check_expression(filter)?;
+        }
+        for item in &self.order_by {
+            check_expression(&item.expr)?;
+        }
+        let select_list = if self.columns.is_empty() {
+            vec![SelectItem::Wildcard]
+        } else {
+            self.columns
+                .iter()
+                .map(|c| SelectItem::Expression {
+                    expr: col(c).0,
+                    alias: None,
+                })
+                .collect()
+        };
+        Ok(SqlStatement::Select(SelectStatement {
+            distinct: false,
+            select_list,
+            from: Some(FromClause {
+                table: TableReference::Table {
+                    name: self.table.clone(),
+                    alias: None,
+                    as_of: None,
+                },
+                joins: Vec::new(),
+            }),
+            where_clause: self.filter.clone(),
+            group_by: Vec::new(),
+            having: None,
+            order_by: self.order_by.clone(),
+            limit: self.limit,
+            offset: self.offset,
+        }))
+    }
+
+    /// The query as SQL text.
+    pub fn to_sql(&self) -> Result<String> {
+        let SqlStatement::Select(select) = self.statement()? else {
+            unreachable!("TableQuery builds a SELECT");
+        };
+        let render = |expr: &Expression| {
+            expression_to_sql(expr, &|_, _| true)
+                .ok_or_else(|| Error::internal(format!("cannot render {expr:?}")))
+        };
+        let columns = select
+            .select_list
+            .iter()
+            .map(|item| match item {
+                SelectItem::Wildcard => Ok("*".to_string()),
+                SelectItem::Expression { expr, .. } => render(expr),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut sql = format!("SELECT {} FROM {}", columns.join(", "), self.table);
+        if let Some(filter) = &select.where_clause {
+            sql.push_str(&format!(" WHERE {}", render(filter)?));
+        }
+        if !select.order_by.is_empty() {
+            let items = select
+                .order_by
+                .iter()
+                .map(|item| {
+                    let direction = match item.direction {
+                        OrderDirection::Asc => "ASC",
+                        OrderDirection::Desc => "DESC",
+                    };
+                    let nulls = match item.nulls {
+                        Some(NullsOrder::First) => " NULLS FIRST",
+                        Some(NullsOrder::Last) => " NULLS LAST",
+                        None => "",
+                    };
+                    render(&item.expr).map(|e| format!("{e} {direction}{nulls}"))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            sql.push_str(&format!(" ORDER BY {}", items.join(", ")));
+        }
+        if let Some(limit) = self.limit {
+            sql.push_str(&format!(" LIMIT {limit}"));
+        }
+        if let Some(offset) = self.offset {
+            sql.push_str(&format!(" OFFSET {offset}"));
+        }
+        Ok(sql)
+    }
+}
+
+fn check_identifier(name: &str) -> Result<()> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::validation(format!("invalid identifier {name:?}")))
+    }
+}
+
+/// Checks the column names of an expression built by [`Expr`].
+fn check_expression(expr: &Expression) -> Result<()> {
+    match expr {
+        Expression::Identifier(name) => check_identifier(name),
+        Expression::BinaryOp { left, right, .. } => {
+            check_expression(left)?;
+            check_expression(right)
+        }
+        Expression::UnaryOp { expr, .. } | Expression::IsNull { expr, .. } => {
+            check_expression(expr)
+        }
+        Expression::Like { expr, pattern, .. } => {
+            check_expression(expr)?;
+            check_expression(pattern)
+        }
+        Expression::Between { expr, low, high } => {
+            check_expression(expr)?;
+            check_expression(low)?;
+            check_expression(high)
+        }
+        Expression::In {
+            expr,
+            list: InList::Values(values),
+        } => {
+            check_expression(expr)?;
+            values.iter().try_for_each(check_expression)
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::SqlParser;
+
+    fn parsed(sql: &str) -> SqlStatement {
+        SqlParser::new(sql).unwrap().parse().unwrap()
+    }
+
+    #[test]
+    fn built_statement_matches_the_parsed_sql() {
+        let query = table("users")
+            .filter(col("age").gt(30))
+            .filter(col("name").like("a%").or(col("score").between(-1.5, 2)))
+            .filter(
+                col("id")
+                    .in_list([1, -2, 3])
+                    .and(col("email").is_not_null()),
+            )
+            .select(&["name", "age"])
+            .order_by_desc("age")
+            .order_by("name")
+            .limit(10)
+            .offset(5);
+        let sql = query.to_sql().unwrap();
+        assert_eq!(query.statement().unwrap(), parsed(&sql), "{sql}");
+        assert!(
+            sql.starts_with("SELECT name, age FROM users WHERE "),
+            "{sql}"
+        );
+        assert_eq!(table("t").to_sql().unwrap(), "SELECT * FROM t");
+    }
+
+    #[test]
+    fn values_cannot_change_the_statement() {
+        let query = table("users").filter(col("name").eq("x' OR '1'='1\\"));
+        let sql = query.to_sql().unwrap();
+        assert_eq!(query.statement().unwrap(), parsed(&sql), "{sql}");
+        let SqlStatement::Select(select) = parsed(&sql) else {
+            panic!("{sql}");
+        };
+        assert!(matches!(
+            select.where_clause,
+            Some(Expression::BinaryOp {
+                op: BinaryOperator::Equal,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn names_must_be_plain_identifiers() {
+        assert!(table("users; DROP TABLE users").to_sql().is_err());
+        assert!(table("users").select(&["a b"]).statement().is_err());
+        assert!(table("users")
+            .filter(col("x=1 OR 1").eq(1))
+            .statement()
+            .is_err());
+        assert!(table("users").order_by("1").statement().is_err());
+    }
+}
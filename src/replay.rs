@@ -0,0 +1,562 @@
+//! Workload replay behind `rustdb replay`: re-runs a captured statement stream against another
+//! instance, e.g. to check an upgrade or a configuration change against production traffic.
+//!
+//! A capture (`SET workload_capture = on`, one [`CapturedStatement`] per line) keeps each
+//! statement's session, start offset and outcome. [`replay`] opens one [`ReplaySession`] per
+//! captured session and issues its statements in order, each no earlier than its offset divided
+//! by [`ReplayConfig::speed`] (`0` replays as fast as possible). Sessions run on plain OS threads
+//! like the `bench` clients; a thread takes the next session once its current one is done, with
+//! as many threads as sessions overlapped in the capture. Statements that start late (the target
+//! cannot keep up) show as schedule lag.
+//!
+//! The [`ReplayReport`] compares the replay with the capture: latency percentiles of both,
+//! statements that now fail or now succeed, and row counts that differ.
+
+use crate::bench::LatencyStats;
+use crate::network::engine::{EngineHandle, EngineOutput, SessionContext};
+use crate::network::framing::{
+    decode_server_frame_v1, encode_client_message_v1, ClientMessage, QueryPayload, ServerMessage,
+    MAX_FRAME_PAYLOAD_BYTES,
+};
+use crate::network::query_stream::{read_application_frame, MAX_FRAMES_PER_STREAM};
+use quinn::{Connection, RecvStream, SendStream};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::BufRead;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Divergences listed in a [`ReplayReport`] (all are counted).
+pub const MAX_REPORTED_DIVERGENCES: usize = 20;
+
+/// One line of a workload capture.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapturedStatement {
+    /// Capturing session; a session's statements replay in order on one session.
+    pub session: u64,
+    /// Start, in microseconds after the capture started.
+    pub offset_us: u64,
+    pub duration_us: u64,
+    pub sql: String,
+    /// Rows returned, or affected for DML.
+    #[serde(default)]
+    pub rows: u64,
+    /// Error message, when the statement failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Reads a capture file (blank lines skipped).
+pub fn read_capture(path: &Path) -> Result<Vec<CapturedStatement>, String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let mut statements = Vec::new();
+    for (n, line) in std::io::BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| format!("{}: {e}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        statements.push(
+            serde_json::from_str(&line)
+                .map_err(|e| format!("{}:{}: {e}", path.display(), n + 1))?,
+        );
+    }
+    Ok(statements)
+}
+
+/// Target session of a replay.
+pub trait ReplaySession {
+    /// Runs `sql`, returning the rows returned or affected, or the error message.
+    fn execute(&mut self, sql: &str) -> Result<u64, String>;
+}
+
+/// Session on an in-process engine.
+pub struct EngineSession<'a, E: EngineHandle> {
+    engine: &'a E,
+    ctx: SessionContext,
+}
+
+impl<'a, E: EngineHandle> EngineSession<'a, E> {
+    pub fn new(engine: &'a E) -> Self {
+        Self {
+            engine,
+            ctx: SessionContext::default(),
+        }
+    }
+}
+
+impl<E: EngineHandle> ReplaySession for EngineSession<'_, E> {
+    fn execute(&mut self, sql: &str) -> Result<u64, String> {
+        match self.engine.execute_sql(sql, &mut self.ctx) {
+            Ok(EngineOutput::ResultSet { rows, .. }) => Ok(rows.len() as u64),
+            Ok(EngineOutput::ExecutionOk { rows_affected }) => Ok(rows_affected),
+            Err(e) => Err(e.message),
+        }
+    }
+}
+
+/// Session on a QUIC server: one bidirectional stream, driven from a replay thread through
+/// `runtime`.
+///
+/// The server finishes a stream after [`MAX_FRAMES_PER_STREAM`] queries; the session then
+/// continues on a new stream, which is a new server session.
+pub struct QuicSession {
+    runtime: tokio::runtime::Handle,
+    connection: Connection,
+    stream: Option<(SendStream, RecvStream)>,
+    queries_on_stream: usize,
+}
+
+impl QuicSession {
+    pub fn new(runtime: tokio::runtime::Handle, connection: Connection) -> Self {
+        Self {
+            runtime,
+            connection,
+            stream: None,
+            queries_on_stream: 0,
+        }
+    }
+
+    async fn query(&mut self, sql: &str) -> Result<ServerMessage, String> {
+        if self.stream.is_none() || self.queries_on_stream == MAX_FRAMES_PER_STREAM {
+            self.stream = Some(self.connection.open_bi().await.map_err(|e| e.to_string())?);
+            self.queries_on_stream = 0;
+        }
+        let Some((send, recv)) = self.stream.as_mut() else {
+            unreachable!("stream opened above");
+        };
+        self.queries_on_stream += 1;
+        let frame = encode_client_message_v1(&ClientMessage::Query(QueryPayload {
+            sql: sql.to_string(),
+        }))
+        .map_err(|e| e.to_string())?;
+        send.write_all(&frame).await.map_err(|e| e.to_string())?;
+        let response = read_application_frame(recv, MAX_FRAME_PAYLOAD_BYTES)
+            .await
+            .map_err(|e| e.to_string())?;
+        decode_server_frame_v1(&response).map_err(|e| e.to_string())
+    }
+}
+
+impl ReplaySession for QuicSession {
+    fn execute(&mut self, sql: &str) -> Result<u64, String> {
+        let runtime = self.runtime.clone();
+        let reply = runtime.block_on(self.query(sql));
+        if reply.is_err() {
+            // The stream is unusable after a transport error.
+            self.stream = None;
+        }
+        match reply? {
+            ServerMessage::ResultSet(rs) => Ok(rs.rows.len() as u64),
+            ServerMessage::ExecutionOk(ok) => Ok(ok.rows_affected),
+            ServerMessage::Error(e) => Err(e.message),
+            other => Err(format!("unexpected reply: {other:?}")),
+        }
+    }
+}
+
+/// Options of `rustdb replay`.
+#[derive(Debug, Clone)]
+pub struct ReplayConfig {
+    /// Time scale: `1` keeps the captured pacing, `2` replays twice as fast, `0` does not wait.
+    pub speed: f64,
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self { speed: 1.0 }
+    }
+}
+
+/// A statement whose replay outcome differs from the captured one.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Divergence {
+    pub session: u64,
+    pub offset_us: u64,
+    pub sql: String,
+    pub captured: String,
+    pub replayed: String,
+}
+
+/// Result of one replay (serialized by `rustdb replay --json`).
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayReport {
+    pub statements: usize,
+    pub sessions: usize,
+    /// Replay threads (peak concurrent sessions of the capture).
+    pub threads: usize,
+    pub speed: f64,
+    /// Span of the capture, first statement start to last statement end.
+    pub captured_s: f64,
+    pub elapsed_s: f64,
+    /// Statements that failed in the replay.
+    pub errors: u64,
+    /// Statements that succeeded in the capture and failed in the replay.
+    pub new_errors: u64,
+    /// Statements that failed in the capture and succeeded in the replay.
+    pub resolved_errors: u64,
+    /// Statements that succeeded both times with a different row count.
+    pub row_mismatches: u64,
+    /// Largest delay of a statement start behind its schedule (0 when not paced).
+    pub max_lag_ms: f64,
+    pub captured_latency: LatencyStats,
+    pub replay_latency: LatencyStats,
+    /// First [`MAX_REPORTED_DIVERGENCES`] new errors, resolved errors and row mismatches, by offset.
+    pub divergences: Vec<Divergence>,
+}
+
+impl ReplayReport {
+    /// Human-readable multi-line summary printed by the CLI.
+    pub fn summary(&self) -> String {
+        let latency = |l: &LatencyStats| {
+            format!(
+                "mean {:.3}, p50 {:.3}, p95 {:.3}, p99 {:.3}, max {:.3}",
+                l.mean_ms, l.p50_ms, l.p95_ms, l.p99_ms, l.max_ms
+            )
+        };
+        let mut out = format!(
+            "statements: {} in {} sessions ({} threads)\nspeed: {}\n\
+             captured span: {:.2}s\nreplay time: {:.2}s (max lag {:.1} ms)\n\
+             errors: {} ({} new, {} resolved)\nrow count mismatches: {}\n\
+             captured latency ms: {}\nreplay latency ms: {}",
+            self.statements,
+            self.sessions,
+            self.threads,
+            if self.speed > 0.0 {
+                format!("{}x", self.speed)
+            } else {
+                "unpaced".to_string()
+            },
+            self.captured_s,
+            self.elapsed_s,
+            self.max_lag_ms,
+            self.errors,
+            self.new_errors,
+            self.resolved_errors,
+            self.row_mismatches,
+            latency(&self.captured_latency),
+            latency(&self.replay_latency),
+        );
+        for d in &self.divergences {
+            out.push_str(&format!(
+                "\n  session {} at {:.3}s: {}\n    captured: {}\n    replayed: {}",
+                d.session,
+                d.offset_us as f64 / 1e6,
+                d.sql,
+                d.captured,
+                d.replayed
+            ));
+        }
+        out
+    }
+}
+
+/// Outcome of one replayed statement.
+struct Replayed {
+    /// Index in the capture
+    index: usize,
+    lag: Duration,
+    duration: Duration,
+    result: Result<u64, String>,
+}
+
+/// Replays `capture` on sessions from `open_session` and compares the outcomes.
+pub fn replay<S, F>(
+    capture: &[CapturedStatement],
+    config: &ReplayConfig,
+    open_session: F,
+) -> Result<ReplayReport, String>
+where
+    S: ReplaySession,
+    F: Fn() -> Result<S, String> + Sync,
+{
+    // Statement indexes per session, in start order; sessions by their first statement.
+    let mut by_session: BTreeMap<u64, Vec<usize>> = BTreeMap::new();
+    let mut order: Vec<usize> = (0..capture.len()).collect();
+    order.sort_by_key(|&i| capture[i].offset_us);
+    for i in order {
+        by_session.entry(capture[i].session).or_default().push(i);
+    }
+    let mut sessions: Vec<Vec<usize>> = by_session.into_values().collect();
+    sessions.sort_by_key(|s| capture[s[0]].offset_us);
+
+    let end_us = |i: usize| capture[i].offset_us + capture[i].duration_us;
+    let first_us = capture.iter().map(|s| s.offset_us).min().unwrap_or(0);
+    let last_us = (0..capture.len()).map(end_us).max().unwrap_or(0);
+    let threads = peak_overlap(sessions.iter().map(|s| {
+        (
+            capture[s[0]].offset_us,
+            s.iter().copied().map(end_us).max().unwrap_or(0),
+        )
+    }));
+
+    let next_session = AtomicUsize::new(0);
+    let start = Instant::now();
+    let per_thread: Vec<Result<Vec<Replayed>, String>> = std::thread::scope(|s| {
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                let (sessions, next_session, open_session) =
+                    (&sessions, &next_session, &open_session);
+                s.spawn(move || {
+                    let mut out = Vec::new();
+                    loop {
+                        let Some(statements) =
+                            sessions.get(next_session.fetch_add(1, Ordering::Relaxed))
+                        else {
+                            return Ok(out);
+                        };
+                        let mut session = open_session()?;
+                        for &index in statements {
+                            let stmt = &capture[index];
+                            let mut lag = Duration::ZERO;
+                            if config.speed > 0.0 {
+                                let due = start
+                                    + Duration::from_micros(stmt.offset_us - first_us)
+                                        .div_f64(config.speed);
+                                let now = Instant::now();
+                                if now < due {
+                                    std::thread::sleep(due - now);
+                                } else {
+                                    lag = now - due;
+                                }
+                            }
+                            let t0 = Instant::now();
+                            let result = session.execute(&stmt.sql);
+                            out.push(Replayed {
+                                index,
+                                lag,
+                                duration: t0.elapsed(),
+                                result,
+                            });
+                        }
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| {
+                h.join()
+                    .unwrap_or_else(|_| Err("replay thread panicked".to_string()))
+            })
+            .collect()
+    });
+    let elapsed_s = start.elapsed().as_secs_f64();
+
+    let mut replayed = Vec::with_capacity(capture.len());
+    for r in per_thread {
+        replayed.extend(r?);
+    }
+    replayed.sort_by_key(|r| (capture[r.index].offset_us, r.index));
+
+    let mut report = ReplayReport {
+        statements: capture.len(),
+        sessions: sessions.len(),
+        threads,
+        speed: config.speed,
+        captured_s: (last_us - first_us) as f64 / 1e6,
+        elapsed_s,
+        errors: 0,
+        new_errors: 0,
+        resolved_errors: 0,
+        row_mismatches: 0,
+        max_lag_ms: 0.0,
+        captured_latency: LatencyStats::from_micros(
+            &mut capture
+                .iter()
+                .map(|s| s.duration_us as u128)
+                .collect::<Vec<_>>(),
+        ),
+        replay_latency: LatencyStats::from_durations(
+            &replayed.iter().map(|r| r.duration).collect::<Vec<_>>(),
+        ),
+        divergences: Vec::new(),
+    };
+    for r in &replayed {
+        let stmt = &capture[r.index];
+        report.max_lag_ms = report.max_lag_ms.max(r.lag.as_secs_f64() * 1000.0);
+        let diverged = match (&stmt.error, &r.result) {
+            (None, Ok(rows)) => {
+                let mismatch = *rows != stmt.rows;
+                report.row_mismatches += u64::from(mismatch);
+                mismatch
+            }
+            (Some(_), Ok(_)) => {
+                report.resolved_errors += 1;
+                true
+            }
+            (None, Err(_)) => {
+                report.errors += 1;
+                report.new_errors += 1;
+                true
+            }
+            (Some(_), Err(_)) => {
+                report.errors += 1;
+                false
+            }
+        };
+        if diverged && report.divergences.len() < MAX_REPORTED_DIVERGENCES {
+            let captured = match &stmt.error {
+                Some(e) => Err(e.clone()),
+                None => Ok(stmt.rows),
+            };
+            report.divergences.push(Divergence {
+                session: stmt.session,
+                offset_us: stmt.offset_us,
+                sql: stmt.sql.clone(),
+                captured: describe(&captured),
+                replayed: describe(&r.result),
+            });
+        }
+    }
+    Ok(report)
+}
+
+fn describe(result: &Result<u64, String>) -> String {
+    match result {
+        Ok(rows) => format!("ok, {rows} rows"),
+        Err(e) => format!("error: {e}"),
+    }
+}
+
+/// Largest number of `[start, end]` spans that overlap (at least 1).
+fn peak_overlap(spans: impl Iterator<Item = (u64, u64)>) -> usize {
+    // At equal times a span ends before the next one starts.
+    let mut events: Vec<(u64, i64)> = spans.flat_map(|(a, b)| [(a, 1), (b, -1)]).collect();
+    events.sort_unstable();
+    let (mut open, mut peak) = (0i64, 1i64);
+    for (_, delta) in events {
+        open += delta;
+        peak = peak.max(open);
+    }
+    peak as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::SqlEngine;
+    use tempfile::TempDir;
+
+    #[test]
+    fn peak_overlap_of_sessions() {
+        assert_eq!(peak_overlap(std::iter::empty()), 1);
+        assert_eq!(peak_overlap([(0, 10), (10, 20), (20, 30)].into_iter()), 1);
+        assert_eq!(
+            peak_overlap([(0, 10), (5, 20), (6, 7), (15, 30)].into_iter()),
+            3
+        );
+    }
+
+    #[test]
+    fn capture_replays_onto_another_engine() {
+        let source_dir = TempDir::new().unwrap();
+        let source = SqlEngine::open(source_dir.path().to_path_buf()).unwrap();
+        let mut admin = SessionContext::default();
+        let mut a = SessionContext::default();
+        let mut b = SessionContext::default();
+        source
+            .execute_sql("CREATE TABLE t (id INTEGER, v INTEGER)", &mut admin)
+            .unwrap();
+        source
+            .execute_sql("CREATE TABLE u (id INTEGER)", &mut admin)
+            .unwrap();
+        source
+            .execute_sql("SET workload_capture = on", &mut admin)
+            .unwrap();
+        // Pauses keep the sessions' order through a paced replay.
+        let pause = || std::thread::sleep(Duration::from_millis(50));
+        source.execute_sql("SELECT * FROM t", &mut b).unwrap();
+        pause();
+        for sql in [
+            "DROP TABLE u",
+            "BEGIN TRANSACTION",
+            "INSERT INTO t (id, v) VALUES (1, 10)",
+            "INSERT INTO t (id, v) VALUES (2, 20)",
+            "COMMIT",
+        ] {
+            source.execute_sql(sql, &mut a).unwrap();
+        }
+        pause();
+        source.execute_sql("SELECT * FROM t", &mut b).unwrap();
+        assert!(source.execute_sql("SELEC 1", &mut b).is_err());
+        let shown = source
+            .execute_sql("SHOW workload_capture", &mut admin)
+            .unwrap();
+        source
+            .execute_sql("SET workload_capture = off", &mut admin)
+            .unwrap();
+        let files: Vec<_> = std::fs::read_dir(source_dir.path().join("captures"))
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 1);
+        assert!(
+            format!("{shown:?}").contains(&*files[0].to_string_lossy()),
+            "{shown:?}"
+        );
+
+        let capture = read_capture(&files[0]).unwrap();
+        assert_eq!(capture.len(), 9, "{capture:#?}");
+        assert!(capture.iter().all(|s| !s.sql.starts_with("SET")));
+        let rows: Vec<u64> = capture
+            .iter()
+            .filter(|s| s.sql == "SELECT * FROM t")
+            .map(|s| s.rows)
+            .collect();
+        assert_eq!(rows, vec![0, 2]);
+        let captured = |sql: &str| capture.iter().find(|s| s.sql == sql).unwrap();
+        assert_eq!(
+            captured("BEGIN TRANSACTION").session,
+            captured("COMMIT").session
+        );
+        assert_ne!(
+            captured("BEGIN TRANSACTION").session,
+            captured("SELEC 1").session
+        );
+        assert!(captured("SELEC 1").error.is_some());
+
+        let target_dir = TempDir::new().unwrap();
+        let target = SqlEngine::open(target_dir.path().to_path_buf()).unwrap();
+        target
+            .execute_sql(
+                "CREATE TABLE t (id INTEGER, v INTEGER)",
+                &mut SessionContext::default(),
+            )
+            .unwrap();
+        // The target lacks `u`.
+        let report = replay(&capture, &ReplayConfig::default(), || {
+            Ok(EngineSession::new(&target))
+        })
+        .unwrap();
+        assert_eq!(report.statements, 9);
+        assert_eq!(report.sessions, 3);
+        assert_eq!(report.threads, 2);
+        assert_eq!(report.replay_latency.count, 9);
+        assert_eq!(report.errors, 2, "{}", report.summary());
+        assert_eq!(report.new_errors, 1);
+        assert_eq!(report.row_mismatches, 0);
+        assert_eq!(report.divergences.len(), 1);
+        assert_eq!(report.divergences[0].sql, "DROP TABLE u");
+        assert_eq!(report.divergences[0].captured, "ok, 0 rows");
+        assert!(report.divergences[0].replayed.starts_with("error: "));
+        assert!(report.elapsed_s >= 0.04);
+
+        // Again on the same target: `t` already has the rows.
+        let report = replay(&capture, &ReplayConfig { speed: 2.0 }, || {
+            Ok(EngineSession::new(&target))
+        })
+        .unwrap();
+        assert_eq!(report.row_mismatches, 2, "{}", report.summary());
+        let selects: Vec<(&str, &str)> = report
+            .divergences
+            .iter()
+            .filter(|d| d.sql == "SELECT * FROM t")
+            .map(|d| (d.captured.as_str(), d.replayed.as_str()))
+            .collect();
+        assert_eq!(
+            selects,
+            vec![("ok, 0 rows", "ok, 2 rows"), ("ok, 2 rows", "ok, 4 rows")]
+        );
+    }
+}
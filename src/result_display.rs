@@ -0,0 +1,322 @@
+//! Result rendering for `rustdb query`: aligned Unicode tables or, for wide rows, psql-style
+//! expanded records, with a NULL placeholder, a column width cap and a pager for long output.
+//!
+//! Widths count `char`s, so double-width (CJK, emoji) text may misalign columns. Cells with line
+//! breaks (such as `EXPLAIN FORMAT JSON`) span several table lines.
+
+use std::io::{IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+/// Marks a value cut at [`DisplayOptions::max_width`].
+const TRUNCATION_MARKER: char = '…';
+
+/// When records are shown one field per line (`\x`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExpandedMode {
+    #[default]
+    Off,
+    On,
+    /// Expanded when the table would be wider than the terminal.
+    Auto,
+}
+
+/// How `rustdb query` prints result sets.
+#[derive(Debug, Clone)]
+pub struct DisplayOptions {
+    /// Shown for NULL values.
+    pub null_display: String,
+    /// Longer values are cut to this many characters, ending in `…`.
+    pub max_width: Option<usize>,
+    pub expanded: ExpandedMode,
+    /// Pipe output longer than the terminal through `$PAGER` (default `less -FRSX`).
+    pub pager: bool,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        Self {
+            null_display: "NULL".to_string(),
+            max_width: None,
+            expanded: ExpandedMode::Off,
+            pager: true,
+        }
+    }
+}
+
+impl DisplayOptions {
+    /// Applies a `\x [on|off|auto]` meta-command (no argument toggles) and returns the message
+    /// to print, or `None` if `line` is not one.
+    pub fn apply_meta_command(&mut self, line: &str) -> Option<Result<String, String>> {
+        let mut words = line.split_whitespace();
+        if words.next() != Some("\\x") {
+            return None;
+        }
+        let mode = match words.next().map(str::to_ascii_lowercase).as_deref() {
+            None if self.expanded == ExpandedMode::On => ExpandedMode::Off,
+            None => ExpandedMode::On,
+            Some("on") => ExpandedMode::On,
+            Some("off") => ExpandedMode::Off,
+            Some("auto") => ExpandedMode::Auto,
+            Some(other) => {
+                return Some(Err(format!(
+                    "\\x: unknown value {other} (expected on, off or auto)"
+                )))
+            }
+        };
+        self.expanded = mode;
+        Some(Ok(match mode {
+            ExpandedMode::On => "Expanded display is on.",
+            ExpandedMode::Off => "Expanded display is off.",
+            ExpandedMode::Auto => "Expanded display is used automatically.",
+        }
+        .to_string()))
+    }
+
+    /// Renders a result set for a terminal `terminal_width` columns wide.
+    pub fn render(
+        &self,
+        columns: &[String],
+        rows: &[Vec<String>],
+        terminal_width: usize,
+    ) -> String {
+        let header: Vec<Vec<String>> = columns.iter().map(|c| self.cell_lines(c)).collect();
+        let body: Vec<Vec<Vec<String>>> = rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| {
+                        if is_null(cell) {
+                            vec![self.null_display.clone()]
+                        } else {
+                            self.cell_lines(cell)
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        if columns.is_empty() {
+            return format!("({} rows)\n", rows.len());
+        }
+        let table = render_table(&header, &body);
+        let expanded = match self.expanded {
+            ExpandedMode::On => true,
+            ExpandedMode::Off => false,
+            ExpandedMode::Auto => table
+                .lines()
+                .next()
+                .is_some_and(|l| l.chars().count() > terminal_width),
+        };
+        let mut out = if expanded {
+            render_expanded(&header, &body)
+        } else {
+            table
+        };
+        out.push_str(&match rows.len() {
+            1 => "(1 row)\n".to_string(),
+            n => format!("({n} rows)\n"),
+        });
+        out
+    }
+
+    fn cell_lines(&self, text: &str) -> Vec<String> {
+        text.split('\n')
+            .map(|line| match self.max_width {
+                Some(max) if line.chars().count() > max => {
+                    let mut cut: String = line.chars().take(max.saturating_sub(1)).collect();
+                    cut.push(TRUNCATION_MARKER);
+                    cut
+                }
+                _ => line.to_string(),
+            })
+            .collect()
+    }
+
+    /// Prints `text`, through the pager when enabled, stdout is a terminal and `text` is
+    /// taller than it; falls back to stdout if the pager cannot be started.
+    pub fn print(&self, text: &str) {
+        if self.pager
+            && std::io::stdout().is_terminal()
+            && text.lines().count() + 1 > terminal_size("LINES", 24)
+            && page(text).is_ok()
+        {
+            return;
+        }
+        print!("{text}");
+    }
+}
+
+/// Cells the engine reports for NULL: a missing value or the `Null` type.
+fn is_null(cell: &str) -> bool {
+    cell == "NULL" || cell == "Null"
+}
+
+fn width(lines: &[String]) -> usize {
+    lines.iter().map(|l| l.chars().count()).max().unwrap_or(0)
+}
+
+fn render_table(header: &[Vec<String>], body: &[Vec<Vec<String>>]) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|c| width(c)).collect();
+    for row in body {
+        for (i, cell) in row.iter().enumerate() {
+            if let Some(w) = widths.get_mut(i) {
+                *w = (*w).max(width(cell));
+            }
+        }
+    }
+    let rule = |left: &str, mid: &str, right: &str| {
+        let parts: Vec<String> = widths.iter().map(|w| "─".repeat(w + 2)).collect();
+        format!("{left}{}{right}\n", parts.join(mid))
+    };
+    let line = |cells: &[Vec<String>], out: &mut String| {
+        let height = cells.iter().map(Vec::len).max().unwrap_or(1).max(1);
+        for k in 0..height {
+            out.push('│');
+            for (i, w) in widths.iter().enumerate() {
+                let text = cells
+                    .get(i)
+                    .and_then(|c| c.get(k))
+                    .map_or("", String::as_str);
+                let pad = w - text.chars().count();
+                out.push_str(&format!(" {text}{} │", " ".repeat(pad)));
+            }
+            out.push('\n');
+        }
+    };
+    let mut out = rule("┌", "┬", "┐");
+    line(header, &mut out);
+    out.push_str(&rule("├", "┼", "┤"));
+    for row in body {
+        line(row, &mut out);
+    }
+    out.push_str(&rule("└", "┴", "┘"));
+    out
+}
+
+fn render_expanded(header: &[Vec<String>], body: &[Vec<Vec<String>>]) -> String {
+    let names: Vec<String> = header.iter().map(|c| c.join(" ")).collect();
+    let name_width = names.iter().map(|n| n.chars().count()).max().unwrap_or(0);
+    let mut out = String::new();
+    for (r, row) in body.iter().enumerate() {
+        out.push_str(&format!("─[ RECORD {} ]─\n", r + 1));
+        for (name, cell) in names.iter().zip(row) {
+            for (k, text) in cell.iter().enumerate() {
+                let name = if k == 0 { name.as_str() } else { "" };
+                let pad = name_width - name.chars().count();
+                out.push_str(&format!("{name}{} │ {text}\n", " ".repeat(pad)));
+            }
+        }
+    }
+    out
+}
+
+/// The terminal size from `var` (`COLUMNS` / `LINES`, exported by most shells), else `default`.
+pub fn terminal_size(var: &str, default: usize) -> usize {
+    std::env::var(var)
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(default)
+}
+
+fn page(text: &str) -> std::io::Result<()> {
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| "less -FRSX".to_string());
+    let mut words = pager.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| std::io::Error::other("PAGER is empty"))?;
+    let mut child = Command::new(program)
+        .args(words)
+        .stdin(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The user may quit the pager before reading everything.
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    child.wait()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result() -> (Vec<String>, Vec<Vec<String>>) {
+        (
+            vec!["id".to_string(), "name".to_string()],
+            vec![
+                vec!["Integer(1)".to_string(), "Varchar(\"'ann'\")".to_string()],
+                vec!["Integer(2)".to_string(), "NULL".to_string()],
+            ],
+        )
+    }
+
+    #[test]
+    fn table_aligns_columns_and_shows_nulls() {
+        let (columns, rows) = result();
+        let options = DisplayOptions {
+            null_display: "∅".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            options.render(&columns, &rows, 80),
+            "┌────────────┬──────────────────┐\n\
+             │ id         │ name             │\n\
+             ├────────────┼──────────────────┤\n\
+             │ Integer(1) │ Varchar(\"'ann'\") │\n\
+             │ Integer(2) │ ∅                │\n\
+             └────────────┴──────────────────┘\n\
+             (2 rows)\n"
+        );
+    }
+
+    #[test]
+    fn long_values_are_truncated_with_a_marker() {
+        let options = DisplayOptions {
+            max_width: Some(6),
+            ..Default::default()
+        };
+        let out = options.render(
+            &["v".to_string()],
+            &[vec!["Varchar(\"'abcdef'\")".to_string()]],
+            80,
+        );
+        assert!(out.contains("│ Varch… │"), "{out}");
+    }
+
+    #[test]
+    fn expanded_display_lists_one_field_per_line() {
+        let (columns, rows) = result();
+        let mut options = DisplayOptions::default();
+        assert_eq!(
+            options.apply_meta_command("\\x"),
+            Some(Ok("Expanded display is on.".to_string()))
+        );
+        let out = options.render(&columns, &rows, 80);
+        assert!(
+            out.starts_with("─[ RECORD 1 ]─\nid   │ Integer(1)\nname │ Varchar(\"'ann'\")\n"),
+            "{out}"
+        );
+        assert!(out.ends_with("name │ NULL\n(2 rows)\n"), "{out}");
+
+        options.apply_meta_command("\\x auto").unwrap().unwrap();
+        assert!(options
+            .render(&columns, &rows, 20)
+            .starts_with("─[ RECORD 1 ]─"));
+        assert!(options.render(&columns, &rows, 80).starts_with('┌'));
+        assert!(options.apply_meta_command("\\x wide").unwrap().is_err());
+        assert_eq!(options.apply_meta_command("SELECT 1"), None);
+    }
+
+    #[test]
+    fn multiline_cells_span_table_lines() {
+        let out = DisplayOptions::default().render(
+            &["plan".to_string()],
+            &[vec!["{\n  \"a\": 1\n}".to_string()]],
+            80,
+        );
+        assert!(
+            out.contains("│ {        │\n│   \"a\": 1 │\n│ }        │\n"),
+            "{out}"
+        );
+    }
+}
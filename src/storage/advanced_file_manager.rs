@@ -3,18 +3,21 @@
 //! This module combines the basic file manager with extended database structures:
 //! - Integration with DatabaseFileHeader and FreePageMap
 //! - Automatic file extension management
-//! - Optimized page allocation
+//! - Optimized page allocation, including extents that keep each owner's pages contiguous
 //! - Usage monitoring and statistics
+//! - Hot/cold tiering: pages of extents moved to a cold file are read and written there (see
+//!   [`crate::storage::tiering`])
 
 use crate::common::{Error, Result};
 use crate::storage::database_file::{
     DatabaseFileHeader, DatabaseFileState, DatabaseFileType, ExtensionReason, ExtensionStrategy,
-    FileExtensionManager, FreePageMap, PageId,
+    Extent, ExtentOwner, FileExtensionManager, FreePageMap, PageId,
 };
 use crate::storage::file_manager::{DatabaseFile, FileManager};
+use crate::storage::tiering::{ColdTier, TieringStatistics, EXTENT_PAGES};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Advanced database file ID
 pub type AdvancedFileId = u32;
@@ -35,6 +38,8 @@ pub struct AdvancedDatabaseFile {
     pub free_map_dirty: bool,
     /// Statistics cache
     pub statistics: FileStatistics,
+    /// Extents moved to the cold tier
+    pub tiers: ColdTier,
 }
 
 /// File statistics
@@ -72,6 +77,9 @@ impl AdvancedDatabaseFile {
 
         let free_page_map = FreePageMap::new();
         let extension_manager = FileExtensionManager::new(extension_strategy);
+        // A new file never has cold extents; drop those of an earlier file at this path.
+        ColdTier::remove_files(&base_file.path)?;
+        let tiers = ColdTier::load(&base_file.path)?;
 
         Ok(Self {
             base_file,
@@ -81,6 +89,7 @@ impl AdvancedDatabaseFile {
             header_dirty: true,
             free_map_dirty: true,
             statistics: FileStatistics::default(),
+            tiers,
         })
     }
 
@@ -93,6 +102,7 @@ impl AdvancedDatabaseFile {
         header.total_pages = base_file.size_in_blocks() as u64;
         let free_page_map = FreePageMap::new();
         let extension_manager = FileExtensionManager::new(ExtensionStrategy::Adaptive);
+        let tiers = ColdTier::load(&base_file.path)?;
 
         Ok(Self {
             base_file,
@@ -102,6 +112,7 @@ impl AdvancedDatabaseFile {
             header_dirty: false,
             free_map_dirty: false,
             statistics: FileStatistics::default(),
+            tiers,
         })
     }
 
@@ -150,6 +161,88 @@ impl AdvancedDatabaseFile {
         Ok(if old_size == 0 { 1 } else { old_size })
     }
 
+    /// Allocates pages for `owner` from its extents, reserving a new extent of whole
+    /// [`FreePageMap::EXTENT_PAGES`] runs (extending the file if needed) when none has room.
+    /// Consecutive allocations of one owner thus get consecutive pages.
+    pub fn allocate_pages_for(&mut self, owner: ExtentOwner, page_count: u32) -> Result<PageId> {
+        if page_count == 0 {
+            return Err(Error::validation("Page count cannot be zero"));
+        }
+        if let Some(page_id) = self.free_page_map.allocate_from_extent(owner, page_count) {
+            self.statistics.allocated_pages += page_count as u64;
+            self.free_map_dirty = true;
+            return Ok(page_id);
+        }
+
+        let extent_pages =
+            page_count.div_ceil(FreePageMap::EXTENT_PAGES) * FreePageMap::EXTENT_PAGES;
+        if self
+            .free_page_map
+            .allocate_extent(owner, extent_pages)
+            .is_none()
+        {
+            self.extend_for_extent(extent_pages)?;
+            self.free_page_map
+                .allocate_extent(owner, extent_pages)
+                .ok_or_else(|| Error::internal("Extended file has no room for the extent"))?;
+        }
+        self.free_map_dirty = true;
+        self.update_utilization_ratio();
+
+        let page_id = self
+            .free_page_map
+            .allocate_from_extent(owner, page_count)
+            .ok_or_else(|| Error::internal("New extent has no room for the allocation"))?;
+        self.statistics.allocated_pages += page_count as u64;
+        Ok(page_id)
+    }
+
+    /// Extends the file so that its new free pages (past page 0) hold `extent_pages`.
+    fn extend_for_extent(&mut self, extent_pages: u32) -> Result<()> {
+        let old_size = self.header.total_pages;
+        let first_free = old_size.max(1);
+        let required = (first_free - old_size) as u32 + extent_pages;
+        let extension_size = self
+            .extension_manager
+            .calculate_extension_size(old_size, required)
+            .max(required);
+        let new_size = old_size + extension_size as u64;
+
+        self.base_file.extend_file(new_size as u32)?;
+        self.header.total_pages = new_size;
+        self.header.increment_write_count();
+        self.header_dirty = true;
+
+        self.free_page_map
+            .add_free_block(first_free, (new_size - first_free) as u32)?;
+        self.extension_manager
+            .record_extension(old_size, new_size, ExtensionReason::Preallocation);
+        self.statistics.file_extensions += 1;
+        self.update_extension_statistics();
+        self.free_map_dirty = true;
+        Ok(())
+    }
+
+    /// Owner of the extent holding the page
+    pub fn extent_owner(&self, page_id: PageId) -> Option<ExtentOwner> {
+        self.free_page_map.extent_owner(page_id)
+    }
+
+    /// Extents reserved for `owner`, by start page
+    pub fn owner_extents(&self, owner: ExtentOwner) -> Vec<Extent> {
+        self.free_page_map.owner_extents(owner)
+    }
+
+    /// Returns the extents of `owner` (e.g. a dropped table) to the free pages
+    pub fn release_extents(&mut self, owner: ExtentOwner) -> Result<u64> {
+        let freed = self.free_page_map.release_extents(owner)?;
+        self.statistics.freed_pages += freed;
+        self.free_map_dirty = true;
+        self.update_utilization_ratio();
+        self.update_fragmentation_ratio();
+        Ok(freed)
+    }
+
     /// Frees pages in the file
     pub fn free_pages(&mut self, start_page: PageId, page_count: u32) -> Result<()> {
         self.free_page_map.free_pages(start_page, page_count)?;
@@ -160,9 +253,15 @@ impl AdvancedDatabaseFile {
         Ok(())
     }
 
+    /// Reads a page from the file without counting the read, so that readers sharing the file
+    /// need no exclusive access; they count their reads themselves.
+    pub fn read_page_shared(&self, page_id: PageId) -> Result<Vec<u8>> {
+        self.tiers.read_page(&self.base_file, page_id)
+    }
+
     /// Reads a page from the file
     pub fn read_page(&mut self, page_id: PageId) -> Result<Vec<u8>> {
-        let data = self.base_file.read_block(page_id)?;
+        let data = self.tiers.read_page(&self.base_file, page_id)?;
         self.header.increment_read_count();
         self.statistics.read_operations += 1;
         self.header_dirty = true;
@@ -171,7 +270,7 @@ impl AdvancedDatabaseFile {
 
     /// Writes a page to the file
     pub fn write_page(&mut self, page_id: PageId, data: &[u8]) -> Result<()> {
-        self.base_file.write_block(page_id, data)?;
+        self.tiers.write_page(&mut self.base_file, page_id, data)?;
         self.header.increment_write_count();
         self.statistics.write_operations += 1;
         self.header_dirty = true;
@@ -246,11 +345,32 @@ impl AdvancedDatabaseFile {
         }
 
         // Synchronize base file
+        self.tiers.sync()?;
         self.base_file.sync()?;
 
         Ok(())
     }
 
+    /// Complete extents of the file, the ones tiering may move
+    pub fn complete_extents(&self) -> u64 {
+        u64::from(self.base_file.size_in_blocks()) / EXTENT_PAGES
+    }
+
+    /// Moves `extent` to the cold file in `cold_dir` (see [`ColdTier::demote`])
+    pub fn demote_extent(&mut self, extent: u64, cold_dir: &Path) -> Result<()> {
+        self.tiers.demote(&mut self.base_file, extent, cold_dir)
+    }
+
+    /// Moves cold `extent` back to this file (see [`ColdTier::promote`])
+    pub fn promote_extent(&mut self, extent: u64) -> Result<()> {
+        self.tiers.promote(&mut self.base_file, extent)
+    }
+
+    /// Returns the tiering counters of the file
+    pub fn tiering_statistics(&self) -> TieringStatistics {
+        self.tiers.statistics(self.complete_extents())
+    }
+
     /// Returns file statistics
     pub fn get_statistics(&self) -> &FileStatistics {
         &self.statistics
@@ -507,6 +627,36 @@ impl AdvancedFileManager {
         Ok(result)
     }
 
+    /// Allocates pages for `owner` from its extents in the file
+    /// (see [`AdvancedDatabaseFile::allocate_pages_for`])
+    pub fn allocate_pages_for(
+        &mut self,
+        file_id: AdvancedFileId,
+        owner: ExtentOwner,
+        page_count: u32,
+    ) -> Result<PageId> {
+        let file = self
+            .advanced_files
+            .get_mut(&file_id)
+            .ok_or_else(|| Error::database(format!("File {} not found", file_id)))?;
+
+        let result = file.allocate_pages_for(owner, page_count)?;
+        self.update_global_statistics();
+        Ok(result)
+    }
+
+    /// Returns the extents of `owner` in the file to its free pages
+    pub fn release_extents(&mut self, file_id: AdvancedFileId, owner: ExtentOwner) -> Result<u64> {
+        let file = self
+            .advanced_files
+            .get_mut(&file_id)
+            .ok_or_else(|| Error::database(format!("File {} not found", file_id)))?;
+
+        let freed = file.release_extents(owner)?;
+        self.update_global_statistics();
+        Ok(freed)
+    }
+
     /// Frees pages in the file
     pub fn free_pages(
         &mut self,
@@ -536,6 +686,14 @@ impl AdvancedFileManager {
         Ok(result)
     }
 
+    /// Reads a page without counting the read (see [`AdvancedDatabaseFile::read_page_shared`])
+    pub fn read_page_shared(&self, file_id: AdvancedFileId, page_id: PageId) -> Result<Vec<u8>> {
+        self.advanced_files
+            .get(&file_id)
+            .ok_or_else(|| Error::database(format!("File {} not found", file_id)))?
+            .read_page_shared(page_id)
+    }
+
     /// Writes a page to the file
     pub fn write_page(
         &mut self,
@@ -588,6 +746,25 @@ impl AdvancedFileManager {
             .map(|file| file.get_file_info())
     }
 
+    /// Returns an open file
+    pub fn get_file(&self, file_id: AdvancedFileId) -> Option<&AdvancedDatabaseFile> {
+        self.advanced_files.get(&file_id)
+    }
+
+    /// Returns an open file for changes the manager does not wrap (e.g. tiering moves)
+    pub fn get_file_mut(&mut self, file_id: AdvancedFileId) -> Result<&mut AdvancedDatabaseFile> {
+        self.advanced_files
+            .get_mut(&file_id)
+            .ok_or_else(|| Error::database(format!("File {} not found", file_id)))
+    }
+
+    /// Returns the statistics of one file
+    pub fn get_file_statistics(&self, file_id: AdvancedFileId) -> Option<&FileStatistics> {
+        self.advanced_files
+            .get(&file_id)
+            .map(|file| file.get_statistics())
+    }
+
     /// Returns global statistics
     pub fn get_global_statistics(&self) -> &GlobalStatistics {
         &self.global_statistics
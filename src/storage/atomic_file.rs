@@ -0,0 +1,82 @@
+//! Crash-consistent file operations for rustdb
+//!
+//! Small metadata files (the catalog, checkpoint metadata, backup manifests, LSM manifests) are
+//! replaced as a whole: the new contents are written to a temporary file next to the target,
+//! synced, renamed over the target, and the directory is synced so the rename itself survives a
+//! crash. A reader therefore sees either the old or the new file, never a truncated one.
+//!
+//! The temporary file is `<name>.tmp` in the target's directory; callers must not replace the
+//! same path concurrently.
+
+use crate::common::Result;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Atomically replaces `path` with `data`, syncing the file and its directory.
+pub fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    write_atomic_with_sync(path, data, true)
+}
+
+/// Atomically replaces `path` with `data`.
+///
+/// The rename is atomic either way; with `sync == false` the file and directory are not synced,
+/// so after a power loss the target may still hold its previous contents.
+pub fn write_atomic_with_sync(path: &Path, data: &[u8], sync: bool) -> Result<()> {
+    let tmp = temp_path(path);
+    let written = (|| -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .write(true)
+            .open(&tmp)?;
+        file.write_all(data)?;
+        if sync {
+            file.sync_all()?;
+        }
+        Ok(())
+    })();
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e);
+    }
+    std::fs::rename(&tmp, path)?;
+    if sync {
+        sync_parent_dir(path)?;
+    }
+    Ok(())
+}
+
+/// Renames `from` to `to` and syncs the directories of both.
+pub fn rename_durable(from: &Path, to: &Path) -> Result<()> {
+    std::fs::rename(from, to)?;
+    sync_parent_dir(to)?;
+    if from.parent() != to.parent() {
+        sync_parent_dir(from)?;
+    }
+    Ok(())
+}
+
+/// Syncs the directory containing `path`, making a create, rename or delete of `path` durable.
+pub fn sync_parent_dir(path: &Path) -> Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => sync_dir(dir),
+        _ => sync_dir(Path::new(".")),
+    }
+}
+
+/// Syncs the entries of `dir` (no-op where directories cannot be opened, e.g. Windows).
+pub fn sync_dir(dir: &Path) -> Result<()> {
+    #[cfg(unix)]
+    std::fs::File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+/// Temporary file [`write_atomic`] writes before renaming it to `path`.
+pub fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
@@ -5,6 +5,7 @@
 
 use crate::common::{Error, Result};
 use std::fs::{File, OpenOptions};
+#[cfg(not(unix))]
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::Path;
 use std::sync::Mutex;
@@ -15,8 +16,9 @@ use std::os::unix::io::AsRawFd;
 /// Block I/O backend trait.
 /// Implementations: StdFileBackend (all platforms), IoUringBackend (Linux).
 pub trait BlockIoBackend: Send + Sync {
-    /// Reads exactly `buf.len()` bytes at the given offset.
-    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()>;
+    /// Reads exactly `buf.len()` bytes at the given offset. Takes `&self` so that readers
+    /// sharing a file do not wait for each other.
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<()>;
 
     /// Writes `data` at the given offset.
     fn write_at(&mut self, offset: u64, data: &[u8]) -> Result<()>;
@@ -30,9 +32,12 @@ pub trait BlockIoBackend: Send + Sync {
 
 /// Standard library file backend (std::fs::File).
 /// Used on all platforms; fallback on Linux when io_uring is unavailable.
-/// Wrapped in Mutex for Sync (safe sharing across threads).
+/// On Unix reads and writes are positional (`pread`/`pwrite`) and never wait for each other;
+/// elsewhere a mutex keeps each seek together with its read or write.
 pub struct StdFileBackend {
-    file: Mutex<File>,
+    file: File,
+    #[cfg_attr(unix, allow(dead_code))]
+    cursor: Mutex<()>,
 }
 
 impl StdFileBackend {
@@ -45,7 +50,8 @@ impl StdFileBackend {
             .open(path)
             .map_err(|e| Error::database(format!("Failed to create file: {}", e)))?;
         Ok(Self {
-            file: Mutex::new(file),
+            file,
+            cursor: Mutex::new(()),
         })
     }
 
@@ -58,7 +64,8 @@ impl StdFileBackend {
         }
         .map_err(|e| Error::database(format!("Failed to open file: {}", e)))?;
         Ok(Self {
-            file: Mutex::new(file),
+            file,
+            cursor: Mutex::new(()),
         })
     }
 }
@@ -91,54 +98,61 @@ pub fn create_backend_for_file(
     }
 }
 
-impl BlockIoBackend for StdFileBackend {
-    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
-        let mut file = self
-            .file
+impl StdFileBackend {
+    /// Holds the seek position of the file while `f` seeks and reads or writes (non-Unix).
+    #[cfg(not(unix))]
+    fn at<T>(&self, offset: u64, f: impl FnOnce(&File) -> std::io::Result<T>) -> Result<T> {
+        let _cursor = self
+            .cursor
             .lock()
             .map_err(|e| Error::database(format!("Lock error: {}", e)))?;
-        file.seek(SeekFrom::Start(offset))
+        (&self.file)
+            .seek(SeekFrom::Start(offset))
             .map_err(|e| Error::database(format!("Seek error: {}", e)))?;
-        file.read_exact(buf)
-            .map_err(|e| Error::database(format!("Read error: {}", e)))?;
-        Ok(())
+        f(&self.file).map_err(|e| Error::database(format!("I/O error: {}", e)))
+    }
+}
+
+impl BlockIoBackend for StdFileBackend {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileExt;
+            self.file
+                .read_exact_at(buf, offset)
+                .map_err(|e| Error::database(format!("Read error: {}", e)))
+        }
+        #[cfg(not(unix))]
+        {
+            self.at(offset, |mut file| file.read_exact(buf))
+        }
     }
 
     fn write_at(&mut self, offset: u64, data: &[u8]) -> Result<()> {
-        let mut file = self
-            .file
-            .lock()
-            .map_err(|e| Error::database(format!("Lock error: {}", e)))?;
-        file.seek(SeekFrom::Start(offset))
-            .map_err(|e| Error::database(format!("Seek error: {}", e)))?;
-        file.write_all(data)
-            .map_err(|e| Error::database(format!("Write error: {}", e)))?;
-        Ok(())
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileExt;
+            self.file
+                .write_all_at(data, offset)
+                .map_err(|e| Error::database(format!("Write error: {}", e)))
+        }
+        #[cfg(not(unix))]
+        {
+            self.at(offset, |mut file| file.write_all(data))
+        }
     }
 
     fn sync(&mut self) -> Result<()> {
-        let mut file = self
-            .file
-            .lock()
-            .map_err(|e| Error::database(format!("Lock error: {}", e)))?;
-        file.sync_all()
-            .map_err(|e| Error::database(format!("Sync error: {}", e)))?;
-        Ok(())
+        self.file
+            .sync_all()
+            .map_err(|e| Error::database(format!("Sync error: {}", e)))
     }
 
     fn extend(&mut self, new_size: u64) -> Result<()> {
         if new_size == 0 {
             return Ok(());
         }
-        let mut file = self
-            .file
-            .lock()
-            .map_err(|e| Error::database(format!("Lock error: {}", e)))?;
-        file.seek(SeekFrom::Start(new_size - 1))
-            .map_err(|e| Error::database(format!("Seek error: {}", e)))?;
-        file.write_all(&[0])
-            .map_err(|e| Error::database(format!("Extend error: {}", e)))?;
-        Ok(())
+        self.write_at(new_size - 1, &[0])
     }
 }
 
@@ -190,7 +204,7 @@ impl IoUringBackend {
 
 #[cfg(all(target_os = "linux", feature = "io-uring"))]
 impl BlockIoBackend for IoUringBackend {
-    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<()> {
         let len = buf.len();
         if len == 0 {
             return Ok(());
@@ -1,29 +1,234 @@
 //! Cached file manager with sync page cache (buffer pool)
 //!
-//! Wraps AdvancedFileManager with an in-memory LRU page cache to reduce disk I/O.
+//! Wraps AdvancedFileManager with an in-memory LRU page cache to reduce disk I/O. The cache is a
+//! [`ShardedPageCache`]: pages are spread over shards by page hash, each a
+//! [`crate::core::buffer::BufferManager`] of page images with its own latch and LRU list, and
+//! [`CachedFileManager::cache_shard_stats`] reports per-shard contention.
+//!
+//! Readers that only need record bytes pin a page with [`CachedFileManager::pin_page`] and
+//! borrow records from the cached frame instead of copying the page; the frame keeps a pin in
+//! its shard until the guard is dropped. Pinning and
+//! [`CachedFileManager::read_page_shared`] take `&self`: cache misses are read with positional
+//! I/O, so readers sharing the manager load pages in parallel.
+//!
+//! A [`PagePrefetcher`] loads pages of one file into the buffer pool from another thread, through
+//! its own read-only handle, so a caller that knows which pages it needs next (WAL redo) finds
+//! them cached instead of waiting on random reads.
+//!
+//! Every page lookup and write is counted per extent for hot/cold tiering once
+//! [`CachedFileManager::run_tiering`] first ran (see [`crate::storage::tiering`]).
 
+use crate::common::memory_tracking::{memory_scope, MemoryTag};
 use crate::common::Result;
 use crate::storage::advanced_file_manager::{AdvancedFileId, AdvancedFileManager, FileInfo};
 use crate::storage::database_file::{DatabaseFileType, ExtensionStrategy, PageId};
-use crate::storage::io_optimization::PageCache;
-use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::Mutex;
+use crate::storage::file_manager::DatabaseFile;
+use crate::storage::io_optimization::{PageCacheShardStats, ShardedPageCache};
+use crate::storage::page::{Page, SlottedPageView};
+use crate::storage::tiering::{
+    extent_of, plan_moves, ExtentHeat, TieringPlan, TieringPolicy, TieringStatistics,
+};
+use dashmap::DashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A page pinned in the buffer pool for reading
+///
+/// Records are borrowed from the cached page bytes; the frame holds a pin in the buffer pool, so
+/// the page is not evicted until the guard is dropped. Pages that are not in the slotted format
+/// (or not backed by the buffer pool, e.g. uncommitted or LSM pages) hold a copy of their
+/// records instead.
+pub struct PinnedPage {
+    page_id: PageId,
+    data: PinnedData,
+    /// Buffer pool and file the pin is released to on drop (pages borrowed from the pool)
+    pin: Option<(Arc<ShardedPageCache>, AdvancedFileId)>,
+}
+
+enum PinnedData {
+    /// Slotted page bytes shared with the buffer pool
+    Cached(Arc<Vec<u8>>),
+    /// Records copied out of the page, as `(offset, bytes)`
+    Owned(Vec<(u32, Vec<u8>)>),
+}
+
+impl PinnedPage {
+    /// Guard of page bytes pinned in `pool` (the pin is released when the bytes are not slotted
+    /// and their records are copied instead)
+    fn from_cached(
+        page_id: PageId,
+        bytes: Arc<Vec<u8>>,
+        pool: Arc<ShardedPageCache>,
+        file_id: AdvancedFileId,
+    ) -> Result<Self> {
+        if SlottedPageView::new(&bytes).is_some() {
+            return Ok(Self {
+                page_id,
+                data: PinnedData::Cached(bytes),
+                pin: Some((pool, file_id)),
+            });
+        }
+        pool.unpin(file_id, page_id);
+        let records = Page::from_bytes(&bytes)?.scan_records()?;
+        Ok(Self::from_records(page_id, records))
+    }
+
+    /// Wraps records that were already copied out of a page
+    pub(crate) fn from_records(page_id: PageId, records: Vec<(u32, Vec<u8>)>) -> Self {
+        Self {
+            page_id,
+            data: PinnedData::Owned(records),
+            pin: None,
+        }
+    }
+
+    /// Page id
+    pub fn page_id(&self) -> PageId {
+        self.page_id
+    }
+
+    /// Number of record positions; some positions may be empty (deleted slots)
+    pub fn slot_count(&self) -> usize {
+        match &self.data {
+            PinnedData::Cached(bytes) => view(bytes).slot_count(),
+            PinnedData::Owned(records) => records.len(),
+        }
+    }
+
+    /// Returns `(offset, bytes)` of the record at position `index`, or `None` if the position is
+    /// empty or out of range
+    pub fn record(&self, index: usize) -> Option<(u32, &[u8])> {
+        match &self.data {
+            PinnedData::Cached(bytes) => view(bytes).record(index),
+            PinnedData::Owned(records) => records
+                .get(index)
+                .map(|(offset, data)| (*offset, data.as_slice())),
+        }
+    }
+
+    /// Returns the record stored at byte `offset`
+    pub fn record_at(&self, offset: u32) -> Option<&[u8]> {
+        match &self.data {
+            PinnedData::Cached(bytes) => view(bytes).record_at_offset(offset),
+            PinnedData::Owned(records) => records
+                .iter()
+                .find(|(record_offset, _)| *record_offset == offset)
+                .map(|(_, data)| data.as_slice()),
+        }
+    }
+
+    /// Live records as `(offset, bytes)`
+    pub fn records(&self) -> impl Iterator<Item = (u32, &[u8])> + '_ {
+        (0..self.slot_count()).filter_map(move |i| self.record(i))
+    }
+
+    /// Whether the records are borrowed from the buffer pool rather than copied
+    pub fn is_cached(&self) -> bool {
+        matches!(self.data, PinnedData::Cached(_))
+    }
+}
+
+impl Drop for PinnedPage {
+    fn drop(&mut self) {
+        if let Some((pool, file_id)) = &self.pin {
+            pool.unpin(*file_id, self.page_id);
+        }
+    }
+}
+
+fn view(bytes: &[u8]) -> SlottedPageView<'_> {
+    SlottedPageView::new(bytes).expect("cached pinned pages are checked to be slotted")
+}
 
 /// Cached file manager with sync buffer pool
 pub struct CachedFileManager {
     /// Base file manager
     inner: AdvancedFileManager,
-    /// LRU page cache (buffer pool)
-    cache: Mutex<PageCache>,
+    /// LRU page cache (buffer pool), latched and evicted per shard
+    cache: Arc<ShardedPageCache>,
+    /// Disk reads of shared readers per file, which the file's own counters do not see
+    shared_reads: DashMap<AdvancedFileId, u64>,
+    /// Page accesses per extent, for tiering (page managers cache one file each)
+    heat: ExtentHeat,
+}
+
+/// Disk I/O, buffer pool and growth counters of one file
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FileIoStatistics {
+    /// Pages read from disk
+    pub reads: u64,
+    /// Pages written to disk
+    pub writes: u64,
+    /// Times the file was extended
+    pub extensions: u64,
+    /// Pages in the file
+    pub pages: u64,
+    /// Page lookups served by the buffer pool
+    pub cache_hits: u64,
+    /// Page lookups that went to disk
+    pub cache_misses: u64,
+}
+
+impl FileIoStatistics {
+    /// Share of page lookups served by the buffer pool (`0.0` before the first lookup)
+    pub fn hit_ratio(&self) -> f64 {
+        let lookups = self.cache_hits + self.cache_misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.cache_hits as f64 / lookups as f64
+        }
+    }
+}
+
+/// Loads pages of one file into the buffer pool of a [`CachedFileManager`]
+///
+/// Pages already cached are left alone, since the cached copy may be newer than the file.
+pub struct PagePrefetcher {
+    file: DatabaseFile,
+    file_id: AdvancedFileId,
+    cache: Arc<ShardedPageCache>,
+    /// Extents in the cold tier, which the handle's primary file does not hold
+    cold_extents: BTreeSet<u64>,
+    loaded: usize,
+}
+
+impl PagePrefetcher {
+    /// Reads `page_id` into the buffer pool unless it is cached (pages past the end of the file
+    /// and pages in the cold tier are skipped)
+    pub fn prefetch(&mut self, page_id: PageId) {
+        if self.cache.contains(self.file_id, page_id)
+            || self.cold_extents.contains(&extent_of(page_id))
+        {
+            return;
+        }
+        let Ok(data) = self.file.read_block(page_id) else {
+            return;
+        };
+        let _memory = memory_scope(MemoryTag::BufferPool);
+        if self.cache.put_if_absent(self.file_id, page_id, data) {
+            self.loaded += 1;
+        }
+    }
+
+    /// Pages this prefetcher read into the buffer pool
+    pub fn pages_loaded(&self) -> usize {
+        self.loaded
+    }
 }
 
 impl CachedFileManager {
     /// Creates a new cached file manager with specified buffer pool size
     pub fn new(root_dir: impl AsRef<std::path::Path>, buffer_pool_size: usize) -> Result<Self> {
         let inner = AdvancedFileManager::new(root_dir)?;
-        let cache = Mutex::new(PageCache::new(buffer_pool_size));
-        Ok(Self { inner, cache })
+        let cache = Arc::new(ShardedPageCache::new(buffer_pool_size));
+        Ok(Self {
+            inner,
+            cache,
+            shared_reads: DashMap::new(),
+            heat: ExtentHeat::default(),
+        })
     }
 
     /// Creates a new database file
@@ -60,14 +265,53 @@ impl CachedFileManager {
 
     /// Reads a page (checks cache first, then disk)
     pub fn read_page(&mut self, file_id: AdvancedFileId, page_id: PageId) -> Result<Vec<u8>> {
-        if let Some(data) = self.cache.lock().unwrap().get(file_id, page_id) {
+        self.heat.record(page_id);
+        if let Some(data) = self.cache.get(file_id, page_id) {
             return Ok(data);
         }
         let data = self.inner.read_page(file_id, page_id)?;
-        self.cache
-            .lock()
-            .unwrap()
-            .put(file_id, page_id, data.clone());
+        let _memory = memory_scope(MemoryTag::BufferPool);
+        self.cache.put(file_id, page_id, data.clone());
+        Ok(data)
+    }
+
+    /// Reads a page through the cache like [`Self::read_page`], without exclusive access
+    pub fn read_page_shared(&self, file_id: AdvancedFileId, page_id: PageId) -> Result<Vec<u8>> {
+        self.heat.record(page_id);
+        if let Some(data) = self.cache.get(file_id, page_id) {
+            return Ok(data);
+        }
+        let data = self.read_from_disk(file_id, page_id)?;
+        let _memory = memory_scope(MemoryTag::BufferPool);
+        self.cache.put(file_id, page_id, data.clone());
+        Ok(data)
+    }
+
+    /// Pins a page in the cache (loading it from disk on a miss) and returns a guard that
+    /// borrows its records and releases the pin when dropped
+    pub fn pin_page(&self, file_id: AdvancedFileId, page_id: PageId) -> Result<PinnedPage> {
+        self.heat.record(page_id);
+        let cached = self.cache.pin(file_id, page_id);
+        let bytes = match cached {
+            Some(bytes) => bytes,
+            None => {
+                let _memory = memory_scope(MemoryTag::BufferPool);
+                let bytes = Arc::new(self.read_from_disk(file_id, page_id)?);
+                self.cache.put_pinned(file_id, page_id, bytes.clone());
+                bytes
+            }
+        };
+        PinnedPage::from_cached(page_id, bytes, self.cache.clone(), file_id)
+    }
+
+    /// Pins held on a cached page (by [`PinnedPage`] guards)
+    pub fn page_pin_count(&self, file_id: AdvancedFileId, page_id: PageId) -> u32 {
+        self.cache.pin_count(file_id, page_id)
+    }
+
+    fn read_from_disk(&self, file_id: AdvancedFileId, page_id: PageId) -> Result<Vec<u8>> {
+        let data = self.inner.read_page_shared(file_id, page_id)?;
+        *self.shared_reads.entry(file_id).or_default() += 1;
         Ok(data)
     }
 
@@ -78,11 +322,10 @@ impl CachedFileManager {
         page_id: PageId,
         data: &[u8],
     ) -> Result<()> {
+        self.heat.record(page_id);
         self.inner.write_page(file_id, page_id, data)?;
-        self.cache
-            .lock()
-            .unwrap()
-            .put(file_id, page_id, data.to_vec());
+        let _memory = memory_scope(MemoryTag::BufferPool);
+        self.cache.put(file_id, page_id, data.to_vec());
         Ok(())
     }
 
@@ -101,21 +344,98 @@ impl CachedFileManager {
         self.inner.close_file(file_id)
     }
 
+    /// Returns the number of cached pages currently pinned
+    pub fn pinned_pages(&self) -> usize {
+        self.cache.pinned_count()
+    }
+
     /// Returns cache statistics (hits, misses, hit_ratio)
     pub fn cache_stats(&self) -> (u64, u64, f64) {
-        self.cache.lock().unwrap().get_stats()
+        self.cache.get_stats()
     }
 
     /// Clears the page cache
     pub fn clear_cache(&self) {
-        self.cache.lock().unwrap().clear();
+        self.cache.clear();
     }
 
     /// Invalidates cached pages (e.g. after external flush)
     pub fn invalidate_pages(&self, file_id: AdvancedFileId, page_ids: &[PageId]) {
-        let mut cache = self.cache.lock().unwrap();
         for &page_id in page_ids {
-            cache.remove(file_id, page_id);
+            self.cache.remove(file_id, page_id);
+        }
+    }
+
+    /// Returns the I/O counters of `file_id`; the buffer pool counters cover every file this
+    /// manager caches (page managers cache one file each)
+    pub fn file_io_statistics(&self, file_id: AdvancedFileId) -> Option<FileIoStatistics> {
+        let stats = self.inner.get_file_statistics(file_id)?;
+        let pages = self.inner.get_file_info(file_id)?.total_pages;
+        let (cache_hits, cache_misses, _) = self.cache.get_stats();
+        Some(FileIoStatistics {
+            reads: stats.read_operations
+                + self.shared_reads.get(&file_id).map_or(0, |reads| *reads),
+            writes: stats.write_operations,
+            extensions: stats.file_extensions,
+            pages,
+            cache_hits,
+            cache_misses,
+        })
+    }
+
+    /// Opens a prefetcher filling the buffer pool with pages of `file_id`
+    pub fn page_prefetcher(&self, file_id: AdvancedFileId) -> Result<PagePrefetcher> {
+        let info = self
+            .inner
+            .get_file_info(file_id)
+            .ok_or_else(|| crate::common::Error::database(format!("File {} not found", file_id)))?;
+        Ok(PagePrefetcher {
+            file: DatabaseFile::open(file_id, info.path, true)?,
+            file_id,
+            cache: self.cache.clone(),
+            cold_extents: self
+                .inner
+                .get_file(file_id)
+                .map(|f| f.tiers.cold_extents())
+                .unwrap_or_default(),
+            loaded: 0,
+        })
+    }
+
+    /// Closes the access window of `file_id` and moves its extents between the tiers as
+    /// `policy` says, putting demoted extents in `cold_dir`; returns the moves made
+    pub fn run_tiering(
+        &mut self,
+        file_id: AdvancedFileId,
+        cold_dir: &Path,
+        policy: &TieringPolicy,
+    ) -> Result<TieringPlan> {
+        let file = self.inner.get_file_mut(file_id)?;
+        let extents = file.complete_extents();
+        let accesses = self.heat.close_window(extents);
+        let plan = plan_moves(
+            policy,
+            extents,
+            &self.heat,
+            &accesses,
+            &file.tiers.cold_extents(),
+        );
+        for &extent in &plan.promote {
+            file.promote_extent(extent)?;
         }
+        for &extent in &plan.demote {
+            file.demote_extent(extent, cold_dir)?;
+        }
+        Ok(plan)
+    }
+
+    /// Returns the tiering counters of `file_id`
+    pub fn tiering_statistics(&self, file_id: AdvancedFileId) -> Option<TieringStatistics> {
+        self.inner.get_file(file_id).map(|f| f.tiering_statistics())
+    }
+
+    /// Returns the state and latch contention counters of each buffer pool shard
+    pub fn cache_shard_stats(&self) -> Vec<PageCacheShardStats> {
+        self.cache.shard_stats()
     }
 }
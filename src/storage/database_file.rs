@@ -289,6 +289,40 @@ pub struct FreePageMapEntry {
     pub flags: u8,
 }
 
+/// Owner of an extent (a table or index sharing the file)
+pub type ExtentOwner = u32;
+
+/// Contiguous pages reserved for one owner and handed out to it front to back, so an owner's
+/// pages stay together on disk instead of interleaving with other owners' allocations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Extent {
+    /// First page of the extent
+    pub start_page: PageId,
+    /// Pages in the extent
+    pub page_count: u32,
+    /// Owner the pages are reserved for
+    pub owner: ExtentOwner,
+    /// Pages handed out so far, from `start_page` on
+    pub used_pages: u32,
+}
+
+impl Extent {
+    /// Page after the last page of the extent
+    pub fn end_page(&self) -> PageId {
+        self.start_page + self.page_count as u64
+    }
+
+    /// Checks if the page belongs to the extent
+    pub fn contains(&self, page_id: PageId) -> bool {
+        (self.start_page..self.end_page()).contains(&page_id)
+    }
+
+    /// Pages not handed out yet
+    pub fn remaining_pages(&self) -> u32 {
+        self.page_count - self.used_pages
+    }
+}
+
 /// Free page map
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FreePageMap {
@@ -298,6 +332,9 @@ pub struct FreePageMap {
     pub entries: Vec<FreePageMapEntry>,
     /// Bitmap for quick search (optional)
     pub bitmap: Option<Vec<u8>>,
+    /// Extents reserved for owners, by start page; their pages are not in `entries`
+    #[serde(default)]
+    pub extents: Vec<Extent>,
 }
 
 /// Free page map header
@@ -328,6 +365,9 @@ impl FreePageMap {
     /// Map format version
     pub const VERSION: u16 = 1;
 
+    /// Default extent size in pages (256KB)
+    pub const EXTENT_PAGES: u32 = 64;
+
     /// Creates a new free page map
     pub fn new() -> Self {
         let now = std::time::SystemTime::now()
@@ -348,6 +388,7 @@ impl FreePageMap {
             },
             entries: Vec::new(),
             bitmap: None,
+            extents: Vec::new(),
         }
     }
 
@@ -357,6 +398,15 @@ impl FreePageMap {
             return Err(Error::validation("Page count cannot be zero"));
         }
 
+        let new_end = start_page + page_count as u64;
+        if self
+            .extents
+            .iter()
+            .any(|extent| start_page < extent.end_page() && new_end > extent.start_page)
+        {
+            return Err(Error::validation("Free block intersects with an extent"));
+        }
+
         // Check for intersections with existing blocks
         for entry in &self.entries {
             let entry_end = entry.start_page + entry.page_count as u64;
@@ -446,6 +496,97 @@ impl FreePageMap {
         self.add_free_block(start_page, page_count)
     }
 
+    /// Reserves `page_count` contiguous free pages (never page 0) as an extent of `owner`.
+    /// Returns its first page, or `None` when no free block is large enough.
+    pub fn allocate_extent(&mut self, owner: ExtentOwner, page_count: u32) -> Option<PageId> {
+        if page_count == 0 {
+            return None;
+        }
+
+        // First fit; a block may lose pages at its front (page 0), its middle, or both ends.
+        let (i, start) = self.entries.iter().enumerate().find_map(|(i, entry)| {
+            let start = entry.start_page.max(1);
+            let end = entry.start_page + entry.page_count as u64;
+            (end >= start + page_count as u64).then_some((i, start))
+        })?;
+        let entry = self.entries.remove(i);
+        let entry_end = entry.start_page + entry.page_count as u64;
+        let extent_end = start + page_count as u64;
+        if start > entry.start_page {
+            self.entries.push(FreePageMapEntry {
+                page_count: (start - entry.start_page) as u32,
+                ..entry
+            });
+        }
+        if entry_end > extent_end {
+            self.entries.push(FreePageMapEntry {
+                start_page: extent_end,
+                page_count: (entry_end - extent_end) as u32,
+                ..entry
+            });
+        }
+        self.entries.sort_by_key(|entry| entry.start_page);
+
+        let pos = self.extents.partition_point(|e| e.start_page < start);
+        self.extents.insert(
+            pos,
+            Extent {
+                start_page: start,
+                page_count,
+                owner,
+                used_pages: 0,
+            },
+        );
+        self.update_statistics();
+        Some(start)
+    }
+
+    /// Hands out `page_count` consecutive pages from the first extent of `owner` with room
+    /// for them. Returns `None` when none has.
+    pub fn allocate_from_extent(&mut self, owner: ExtentOwner, page_count: u32) -> Option<PageId> {
+        if page_count == 0 {
+            return None;
+        }
+        let extent = self
+            .extents
+            .iter_mut()
+            .find(|e| e.owner == owner && e.remaining_pages() >= page_count)?;
+        let page_id = extent.start_page + extent.used_pages as u64;
+        extent.used_pages += page_count;
+        Some(page_id)
+    }
+
+    /// Owner of the extent holding the page
+    pub fn extent_owner(&self, page_id: PageId) -> Option<ExtentOwner> {
+        self.extents
+            .iter()
+            .find(|e| e.contains(page_id))
+            .map(|e| e.owner)
+    }
+
+    /// Extents of `owner`, by start page
+    pub fn owner_extents(&self, owner: ExtentOwner) -> Vec<Extent> {
+        self.extents
+            .iter()
+            .filter(|e| e.owner == owner)
+            .copied()
+            .collect()
+    }
+
+    /// Returns every extent of `owner` to the free blocks. Returns the number of pages freed.
+    pub fn release_extents(&mut self, owner: ExtentOwner) -> Result<u64> {
+        let (released, kept) = std::mem::take(&mut self.extents)
+            .into_iter()
+            .partition::<Vec<_>, _>(|e| e.owner == owner);
+        self.extents = kept;
+        let mut freed = 0;
+        for extent in released {
+            self.add_free_block(extent.start_page, extent.page_count)?;
+            freed += extent.page_count as u64;
+        }
+        Ok(freed)
+    }
+
     /// Finds the largest contiguous free page block
     pub fn find_largest_free_block(&self) -> u32 {
         self.entries
@@ -499,6 +640,25 @@ impl FreePageMap {
             }
         }
 
+        // Extents are sorted, disjoint, and hold no free block
+        for pair in self.extents.windows(2) {
+            if pair[0].end_page() > pair[1].start_page {
+                return Err(Error::validation("Overlapping extents detected in map"));
+            }
+        }
+        for extent in &self.extents {
+            if extent.used_pages > extent.page_count {
+                return Err(Error::validation("Extent uses more pages than it holds"));
+            }
+            let overlaps_free = self.entries.iter().any(|entry| {
+                entry.start_page < extent.end_page()
+                    && entry.start_page + entry.page_count as u64 > extent.start_page
+            });
+            if overlaps_free {
+                return Err(Error::validation("Extent overlaps a free block"));
+            }
+        }
+
         Ok(())
     }
 
@@ -790,6 +950,34 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_free_page_map_extents() -> Result<()> {
+        let mut map = FreePageMap::new();
+        map.add_free_block(0, 200)?;
+
+        // Page 0 is never handed out; the block splits around the extents.
+        assert_eq!(map.allocate_extent(1, 64), Some(1));
+        assert_eq!(map.allocate_extent(2, 64), Some(65));
+        assert_eq!(map.allocate_from_extent(1, 1), Some(1));
+        assert_eq!(map.allocate_from_extent(2, 1), Some(65));
+        assert_eq!(map.allocate_from_extent(1, 3), Some(2));
+        assert_eq!(map.allocate_from_extent(1, 61), None);
+        assert_eq!(map.extent_owner(10), Some(1));
+        assert_eq!(map.extent_owner(100), Some(2));
+        assert_eq!(map.extent_owner(150), None);
+        assert_eq!(map.total_free_pages(), 200 - 128);
+        assert!(map.add_free_block(70, 1).is_err());
+        map.validate()?;
+
+        assert_eq!(map.release_extents(1)?, 64);
+        assert!(map.owner_extents(1).is_empty());
+        assert_eq!(map.owner_extents(2).len(), 1);
+        assert_eq!(map.total_free_pages(), 200 - 64);
+        map.validate()?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_free_page_map_merge_blocks() -> Result<()> {
         let mut map = FreePageMap::new();
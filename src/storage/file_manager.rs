@@ -157,6 +157,8 @@ impl DatabaseFile {
         }
 
         backend.sync()?;
+        // Make the new directory entry durable too, not only the header.
+        crate::storage::atomic_file::sync_parent_dir(&path)?;
 
         Ok(Self {
             file_id,
@@ -208,7 +210,7 @@ impl DatabaseFile {
     }
 
     /// Reads a data block from the file
-    pub fn read_block(&mut self, block_id: BlockId) -> Result<Vec<u8>> {
+    pub fn read_block(&self, block_id: BlockId) -> Result<Vec<u8>> {
         if block_id >= self.header.total_blocks as u64 {
             return Err(Error::database(format!(
                 "Block {} does not exist",
@@ -0,0 +1,213 @@
+//! CSV adapter: one delimited file, fields matched to the declared columns by position
+//!
+//! Options: `path` (required), `header` (`'true'` skips the first record), `delimiter` and
+//! `quote` (single characters, `,` and `"` by default) and `null` (the unquoted text read as
+//! NULL, empty by default). Quoted fields may contain delimiters, doubled quotes and newlines.
+
+use super::{parse_value, resolve_path, value_error, ForeignReader, ForeignScanRequest};
+use crate::catalog::schema::ForeignTableDef;
+use crate::common::types::{ColumnValue, DataType};
+use crate::common::{Error, Result};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+/// Options accepted by `USING csv`
+pub(super) const OPTIONS: &[&str] = &["path", "header", "delimiter", "quote", "null"];
+
+/// Parsed `OPTIONS` of a CSV foreign table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvOptions {
+    pub path: PathBuf,
+    pub header: bool,
+    pub delimiter: char,
+    pub quote: char,
+    pub null: String,
+}
+
+impl CsvOptions {
+    /// Reads the options of `def`; relative paths resolve against `base_dir`
+    pub fn from_def(def: &ForeignTableDef, base_dir: Option<&Path>) -> Result<Self> {
+        let header = match def.option("header").map(str::to_ascii_lowercase).as_deref() {
+            None | Some("false") | Some("off") => false,
+            Some("true") | Some("on") => true,
+            Some(other) => {
+                return Err(Error::validation(format!(
+                    "csv option header must be 'true' or 'false', got '{}'",
+                    other
+                )))
+            }
+        };
+        Ok(Self {
+            path: resolve_path(def, base_dir)?,
+            header,
+            delimiter: single_char(def, "delimiter", ',')?,
+            quote: single_char(def, "quote", '"')?,
+            null: def.option("null").unwrap_or("").to_string(),
+        })
+    }
+}
+
+fn single_char(def: &ForeignTableDef, key: &str, default: char) -> Result<char> {
+    let Some(value) = def.option(key) else {
+        return Ok(default);
+    };
+    let value = if value == "\\t" { "\t" } else { value };
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c != '\n' && c != '\r' => Ok(c),
+        _ => Err(Error::validation(format!(
+            "csv option {} must be a single character, got '{}'",
+            key, value
+        ))),
+    }
+}
+
+/// One field of a record; quoted fields are never NULL
+#[derive(Debug)]
+struct Field {
+    text: String,
+    quoted: bool,
+}
+
+/// Streaming reader over a CSV file
+pub struct CsvReader {
+    input: BufReader<File>,
+    options: CsvOptions,
+    columns: Vec<(String, DataType)>,
+    projection: Vec<usize>,
+    limit: Option<usize>,
+    returned: usize,
+    /// Lines consumed so far (records may span lines)
+    line: usize,
+}
+
+impl CsvReader {
+    /// Opens the file and skips the header record when `header` is set
+    pub fn open(options: CsvOptions, request: ForeignScanRequest<'_>) -> Result<Self> {
+        let file = File::open(&options.path).map_err(|e| {
+            Error::database(format!("cannot open {}: {}", options.path.display(), e))
+        })?;
+        let mut reader = Self {
+            input: BufReader::new(file),
+            columns: request.columns.to_vec(),
+            projection: request.projection,
+            limit: request.limit,
+            options,
+            returned: 0,
+            line: 0,
+        };
+        if reader.options.header {
+            reader.read_record()?;
+        }
+        Ok(reader)
+    }
+
+    /// Next record as `(first line, fields)`; blank lines are skipped
+    fn read_record(&mut self) -> Result<Option<(usize, Vec<Field>)>> {
+        let (delimiter, quote) = (self.options.delimiter, self.options.quote);
+        let mut fields = Vec::new();
+        let mut field = Field {
+            text: String::new(),
+            quoted: false,
+        };
+        let mut in_quotes = false;
+        let mut start_line = 0;
+        let mut buf = String::new();
+        loop {
+            buf.clear();
+            let n = self.input.read_line(&mut buf).map_err(|e| {
+                Error::database(format!(
+                    "cannot read {}: {}",
+                    self.options.path.display(),
+                    e
+                ))
+            })?;
+            if n == 0 {
+                if in_quotes {
+                    return Err(Error::validation(format!(
+                        "{} line {}: unterminated quoted field",
+                        self.options.path.display(),
+                        start_line
+                    )));
+                }
+                return Ok(None);
+            }
+            self.line += 1;
+            if start_line == 0 {
+                if buf.trim_end_matches(['\r', '\n']).is_empty() {
+                    continue;
+                }
+                start_line = self.line;
+            }
+
+            let mut chars = buf.chars().peekable();
+            while let Some(c) = chars.next() {
+                if in_quotes {
+                    if c == quote {
+                        if chars.peek() == Some(&quote) {
+                            chars.next();
+                            field.text.push(quote);
+                        } else {
+                            in_quotes = false;
+                        }
+                    } else {
+                        field.text.push(c);
+                    }
+                } else if c == delimiter {
+                    fields.push(std::mem::replace(
+                        &mut field,
+                        Field {
+                            text: String::new(),
+                            quoted: false,
+                        },
+                    ));
+                } else if c == quote && field.text.is_empty() && !field.quoted {
+                    in_quotes = true;
+                    field.quoted = true;
+                } else if c != '\r' && c != '\n' {
+                    field.text.push(c);
+                }
+            }
+            if !in_quotes {
+                fields.push(field);
+                return Ok(Some((start_line, fields)));
+            }
+        }
+    }
+}
+
+impl ForeignReader for CsvReader {
+    fn next_row(&mut self) -> Result<Option<Vec<ColumnValue>>> {
+        if self.limit.is_some_and(|limit| self.returned >= limit) {
+            return Ok(None);
+        }
+        let Some((line, fields)) = self.read_record()? else {
+            return Ok(None);
+        };
+        let at = || format!("{} line {}", self.options.path.display(), line);
+        if fields.len() != self.columns.len() {
+            return Err(Error::validation(format!(
+                "{}: expected {} fields, found {}",
+                at(),
+                self.columns.len(),
+                fields.len()
+            )));
+        }
+        let mut values = Vec::with_capacity(self.projection.len());
+        for &i in &self.projection {
+            let field = &fields[i];
+            let (name, template) = &self.columns[i];
+            if !field.quoted && field.text == self.options.null {
+                values.push(ColumnValue::null());
+                continue;
+            }
+            values.push(
+                parse_value(&field.text, template)
+                    .ok_or_else(|| value_error(&at(), name, &field.text, template))?,
+            );
+        }
+        self.returned += 1;
+        Ok(Some(values))
+    }
+}
@@ -0,0 +1,199 @@
+//! Foreign tables: external files read through adapters
+//!
+//! `CREATE FOREIGN TABLE t (cols) USING csv|parquet OPTIONS (path '...')` stores a
+//! [`ForeignTableDef`] in the catalog instead of creating a heap file. Scans open the adapter
+//! with a [`ForeignScanRequest`] carrying the pushed-down projection and row limit, so an
+//! adapter only converts the columns the query reads and stops once the limit is reached.
+//! Values are typed by the declared column types, not by the file.
+//!
+//! `USING rustdb` proxies the table to another rustdb server (see [`remote`]); it also takes
+//! the pushed-down `WHERE` predicate, so filtering happens on the remote side.
+
+pub mod csv;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+pub mod remote;
+
+use crate::catalog::schema::ForeignTableDef;
+use crate::common::types::{ColumnValue, DataType};
+use crate::common::{Error, Result};
+use std::path::{Path, PathBuf};
+
+/// Built-in foreign table adapters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForeignAdapterKind {
+    /// One delimited text file
+    Csv,
+    /// A `.parquet` file or a directory of them
+    Parquet,
+    /// A table on another rustdb server, read over the QUIC protocol
+    Rustdb,
+}
+
+impl ForeignAdapterKind {
+    /// Parses a `USING <adapter>` name
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "parquet" => Ok(Self::Parquet),
+            "rustdb" => Ok(Self::Rustdb),
+            other => Err(Error::validation(format!(
+                "unknown foreign table adapter '{}' (expected csv, parquet or rustdb)",
+                other
+            ))),
+        }
+    }
+
+    /// Options the adapter accepts
+    fn known_options(self) -> &'static [&'static str] {
+        match self {
+            Self::Csv => csv::OPTIONS,
+            Self::Parquet => &["path"],
+            Self::Rustdb => remote::OPTIONS,
+        }
+    }
+}
+
+/// What a scan needs from the adapter
+#[derive(Debug, Clone)]
+pub struct ForeignScanRequest<'a> {
+    /// Local table name
+    pub table_name: &'a str,
+    /// Declared columns of the foreign table as `(name, type template)`
+    pub columns: &'a [(String, DataType)],
+    /// Indexes into `columns` to return, in output order
+    pub projection: Vec<usize>,
+    /// Stop after this many rows
+    pub limit: Option<usize>,
+    /// SQL predicate over the declared columns; only adapters that evaluate it remotely
+    /// receive one (see [`ForeignTableDef::pushes_down_filters`]), others always get `None`
+    pub filter: Option<&'a str>,
+}
+
+/// Row stream of one foreign scan
+pub trait ForeignReader {
+    /// Next row: one value per projected column, in projection order
+    fn next_row(&mut self) -> Result<Option<Vec<ColumnValue>>>;
+}
+
+/// Checks the adapter name, option names and that `path` (or the remote certificate) exists;
+/// runs at `CREATE FOREIGN TABLE`
+pub fn validate_foreign_table(def: &ForeignTableDef, base_dir: Option<&Path>) -> Result<()> {
+    let kind = ForeignAdapterKind::from_name(&def.adapter)?;
+    for (key, _) in &def.options {
+        if !kind.known_options().contains(&key.as_str()) {
+            return Err(Error::validation(format!(
+                "unknown option '{}' for {} foreign table (expected one of: {})",
+                key,
+                def.adapter,
+                kind.known_options().join(", ")
+            )));
+        }
+    }
+    if kind == ForeignAdapterKind::Rustdb {
+        return remote::RemoteOptions::from_def(def, "", base_dir).map(|_| ());
+    }
+    let path = resolve_path(def, base_dir)?;
+    if !path.exists() {
+        return Err(Error::validation(format!(
+            "foreign table path {} does not exist",
+            path.display()
+        )));
+    }
+    match kind {
+        ForeignAdapterKind::Csv => csv::CsvOptions::from_def(def, base_dir).map(|_| ()),
+        #[cfg(feature = "parquet")]
+        ForeignAdapterKind::Parquet => Ok(()),
+        #[cfg(not(feature = "parquet"))]
+        ForeignAdapterKind::Parquet => Err(parquet_disabled()),
+        ForeignAdapterKind::Rustdb => Ok(()),
+    }
+}
+
+/// Opens a scan over the external data of `def`
+pub fn open_foreign_scan(
+    def: &ForeignTableDef,
+    base_dir: Option<&Path>,
+    request: ForeignScanRequest<'_>,
+) -> Result<Box<dyn ForeignReader>> {
+    match ForeignAdapterKind::from_name(&def.adapter)? {
+        ForeignAdapterKind::Csv => Ok(Box::new(csv::CsvReader::open(
+            csv::CsvOptions::from_def(def, base_dir)?,
+            request,
+        )?)),
+        #[cfg(feature = "parquet")]
+        ForeignAdapterKind::Parquet => Ok(Box::new(parquet::ParquetReader::open(
+            &resolve_path(def, base_dir)?,
+            request,
+        )?)),
+        #[cfg(not(feature = "parquet"))]
+        ForeignAdapterKind::Parquet => Err(parquet_disabled()),
+        ForeignAdapterKind::Rustdb => Ok(Box::new(remote::RemoteReader::open(
+            remote::RemoteOptions::from_def(def, request.table_name, base_dir)?,
+            request,
+        )?)),
+    }
+}
+
+#[cfg(not(feature = "parquet"))]
+fn parquet_disabled() -> Error {
+    Error::unsupported("parquet foreign tables (rebuild with the `parquet` feature)")
+}
+
+/// The `path` option; relative paths are resolved against `base_dir` (the data directory)
+pub fn resolve_path(def: &ForeignTableDef, base_dir: Option<&Path>) -> Result<PathBuf> {
+    let path = def.option("path").ok_or_else(|| {
+        Error::validation(format!(
+            "{} foreign table requires OPTIONS (path '...')",
+            def.adapter
+        ))
+    })?;
+    let path = PathBuf::from(path);
+    Ok(match base_dir {
+        Some(base) if path.is_relative() => base.join(path),
+        _ => path,
+    })
+}
+
+/// Converts external text to a value of the declared column type (`template`); `None` when
+/// the text is not a valid value of that type
+pub fn parse_value(text: &str, template: &DataType) -> Option<ColumnValue> {
+    let trimmed = text.trim();
+    let value = match template {
+        DataType::Boolean(_) => match trimmed.to_ascii_lowercase().as_str() {
+            "true" | "t" | "yes" | "y" | "1" => DataType::Boolean(true),
+            "false" | "f" | "no" | "n" | "0" => DataType::Boolean(false),
+            _ => return None,
+        },
+        DataType::TinyInt(_) => DataType::TinyInt(trimmed.parse().ok()?),
+        DataType::SmallInt(_) => DataType::SmallInt(trimmed.parse().ok()?),
+        DataType::Integer(_) => DataType::Integer(trimmed.parse().ok()?),
+        DataType::BigInt(_) => DataType::BigInt(trimmed.parse().ok()?),
+        DataType::Float(_) => DataType::Float(trimmed.parse().ok()?),
+        DataType::Double(_) => DataType::Double(trimmed.parse().ok()?),
+        DataType::Char(_) => DataType::Char(text.to_string()),
+        DataType::Varchar(_) => DataType::Varchar(text.to_string()),
+        DataType::Date(_) => DataType::Date(trimmed.to_string()),
+        DataType::Time(_) => DataType::Time(trimmed.to_string()),
+        DataType::Timestamp(_) => DataType::Timestamp(trimmed.to_string()),
+        DataType::Blob(_) => DataType::Blob(text.as_bytes().to_vec()),
+        _ => DataType::Text(text.to_string()),
+    };
+    Some(ColumnValue::new(value))
+}
+
+/// Error for a field [`parse_value`] rejected; `at` locates it (`data.csv line 3`)
+fn value_error(at: &str, column: &str, text: &str, template: &DataType) -> Error {
+    let type_name = match template {
+        DataType::Boolean(_) => "BOOLEAN",
+        DataType::TinyInt(_) | DataType::SmallInt(_) | DataType::Integer(_) => "INTEGER",
+        DataType::BigInt(_) => "BIGINT",
+        DataType::Float(_) => "REAL",
+        DataType::Double(_) => "DOUBLE",
+        _ => "TEXT",
+    };
+    Error::validation(format!(
+        "{}: column {}: cannot read '{}' as {}",
+        at, column, text, type_name
+    ))
+}
@@ -0,0 +1,158 @@
+//! Parquet adapter: a `.parquet` file, or every `*.parquet` file of a directory in name order
+//!
+//! Declared columns are matched to top-level Parquet fields by name (case-insensitive). Only
+//! projected fields are decoded: each file is read with a projected schema through the
+//! `parquet` record API. Nested (group, list, map) fields are not supported.
+
+use super::{parse_value, value_error, ForeignReader, ForeignScanRequest};
+use crate::common::types::{ColumnValue, DataType};
+use crate::common::{Error, Result};
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::record::reader::RowIter;
+use parquet::record::Field;
+use parquet::schema::types::Type;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Streaming reader over one or more Parquet files
+pub struct ParquetReader {
+    files: VecDeque<PathBuf>,
+    current: Option<(PathBuf, RowIter<'static>)>,
+    /// Projected `(name, type template)` in output order
+    columns: Vec<(String, DataType)>,
+    limit: Option<usize>,
+    returned: usize,
+    /// Rows read from the current file (for error messages)
+    file_row: usize,
+}
+
+impl ParquetReader {
+    /// Lists the files under `path`; each is opened lazily when the previous one is exhausted
+    pub fn open(path: &Path, request: ForeignScanRequest<'_>) -> Result<Self> {
+        let files = if path.is_dir() {
+            let mut files: Vec<PathBuf> = std::fs::read_dir(path)
+                .map_err(|e| Error::database(format!("cannot list {}: {}", path.display(), e)))?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "parquet"))
+                .collect();
+            files.sort();
+            files
+        } else {
+            vec![path.to_path_buf()]
+        };
+        Ok(Self {
+            files: files.into(),
+            current: None,
+            columns: request
+                .projection
+                .iter()
+                .map(|&i| request.columns[i].clone())
+                .collect(),
+            limit: request.limit,
+            returned: 0,
+            file_row: 0,
+        })
+    }
+
+    fn open_file(&self, path: &Path) -> Result<RowIter<'static>> {
+        let file = File::open(path)
+            .map_err(|e| Error::database(format!("cannot open {}: {}", path.display(), e)))?;
+        let reader = SerializedFileReader::new(file).map_err(|e| parquet_error(path, e))?;
+        let schema = reader.metadata().file_metadata().schema();
+        let mut fields = Vec::with_capacity(self.columns.len().max(1));
+        for (name, _) in &self.columns {
+            let field = schema
+                .get_fields()
+                .iter()
+                .find(|f| f.name().eq_ignore_ascii_case(name))
+                .ok_or_else(|| {
+                    Error::validation(format!("{}: no column {}", path.display(), name))
+                })?;
+            if field.is_group() {
+                return Err(Error::unsupported(format!(
+                    "nested parquet column {} in {}",
+                    name,
+                    path.display()
+                )));
+            }
+            fields.push(Arc::new(field.as_ref().clone()));
+        }
+        // Counting rows (e.g. COUNT(*)) still needs one column to drive the row iterator.
+        if fields.is_empty() {
+            if let Some(first) = schema.get_fields().iter().find(|f| !f.is_group()) {
+                fields.push(first.clone());
+            }
+        }
+        let projection = Type::group_type_builder(schema.name())
+            .with_fields(fields)
+            .build()
+            .map_err(|e| parquet_error(path, e))?;
+        RowIter::from_file_into(Box::new(reader))
+            .project(Some(projection))
+            .map_err(|e| parquet_error(path, e))
+    }
+
+    fn convert(
+        &self,
+        path: &Path,
+        field: &Field,
+        column: &(String, DataType),
+    ) -> Result<ColumnValue> {
+        let (name, template) = column;
+        let text = match field {
+            Field::Null => return Ok(ColumnValue::null()),
+            Field::Str(s) => s.clone(),
+            Field::Bytes(b) => match template {
+                DataType::Blob(_) => {
+                    return Ok(ColumnValue::new(DataType::Blob(b.data().to_vec())))
+                }
+                _ => String::from_utf8_lossy(b.data()).into_owned(),
+            },
+            other => other.to_string(),
+        };
+        parse_value(&text, template).ok_or_else(|| {
+            let at = format!("{} row {}", path.display(), self.file_row);
+            value_error(&at, name, &text, template)
+        })
+    }
+}
+
+impl ForeignReader for ParquetReader {
+    fn next_row(&mut self) -> Result<Option<Vec<ColumnValue>>> {
+        if self.limit.is_some_and(|limit| self.returned >= limit) {
+            return Ok(None);
+        }
+        loop {
+            if self.current.is_none() {
+                let Some(path) = self.files.pop_front() else {
+                    return Ok(None);
+                };
+                let rows = self.open_file(&path)?;
+                self.current = Some((path, rows));
+                self.file_row = 0;
+            }
+            let Some((path, rows)) = self.current.as_mut() else {
+                continue;
+            };
+            let Some(row) = rows.next() else {
+                self.current = None;
+                continue;
+            };
+            let path = path.clone();
+            let row = row.map_err(|e| parquet_error(&path, e))?;
+            self.file_row += 1;
+            let mut values = Vec::with_capacity(self.columns.len());
+            for ((_, field), column) in row.get_column_iter().zip(&self.columns) {
+                values.push(self.convert(&path, field, column)?);
+            }
+            self.returned += 1;
+            return Ok(Some(values));
+        }
+    }
+}
+
+fn parquet_error(path: &Path, e: parquet::errors::ParquetError) -> Error {
+    Error::database(format!("{}: {}", path.display(), e))
+}
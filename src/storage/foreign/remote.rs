@@ -0,0 +1,350 @@
+//! Remote adapter: a table on another rustdb server, read over the QUIC protocol
+//!
+//! Options: `addr` (`host:port`, required), `cert` (the server's DER leaf certificate as written
+//! by `rustdb server --cert-out`, required), `server_name` (TLS name, defaults to the host of
+//! `addr`), `table` (remote table, defaults to the local name) and `fetch_size` (rows per
+//! request, 1000 by default).
+//!
+//! A scan sends `SELECT <projection> FROM <table> [WHERE <filter>] LIMIT n OFFSET m` pages from
+//! a background thread and hands rows over through a bounded channel, so the remote side is
+//! read as the consumer pulls rows and at most a page or so is buffered. Pages rely on the
+//! remote scan order being stable while the scan runs (rustdb heap scans are).
+
+use super::{parse_value, value_error, ForeignReader, ForeignScanRequest};
+use crate::catalog::schema::ForeignTableDef;
+use crate::common::types::{ColumnValue, DataType};
+use crate::common::{Error, Result};
+use crate::network::client::{
+    build_quinn_client_config, connect, make_client_endpoint, query_once,
+};
+use crate::network::framing::ServerMessage;
+use rustls::pki_types::CertificateDer;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+
+/// Options accepted by `USING rustdb`
+pub(super) const OPTIONS: &[&str] = &["addr", "cert", "server_name", "table", "fetch_size"];
+
+const DEFAULT_FETCH_SIZE: usize = 1000;
+
+/// Parsed `OPTIONS` of a remote foreign table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteOptions {
+    pub addr: SocketAddr,
+    pub cert: PathBuf,
+    pub server_name: String,
+    pub table: String,
+    pub fetch_size: usize,
+}
+
+impl RemoteOptions {
+    /// Reads the options of `def`; `table` defaults to `local_table` and a relative `cert`
+    /// resolves against `base_dir`
+    pub fn from_def(
+        def: &ForeignTableDef,
+        local_table: &str,
+        base_dir: Option<&Path>,
+    ) -> Result<Self> {
+        let addr_text = def.option("addr").ok_or_else(|| {
+            Error::validation("rustdb foreign table requires OPTIONS (addr 'host:port', ...)")
+        })?;
+        let addr = addr_text
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+            .ok_or_else(|| {
+                Error::validation(format!(
+                    "rustdb option addr '{}' does not resolve",
+                    addr_text
+                ))
+            })?;
+        let server_name = match def.option("server_name") {
+            Some(name) => name.to_string(),
+            None => addr_text
+                .rsplit_once(':')
+                .map_or(addr_text, |(host, _)| host)
+                .trim_matches(['[', ']'])
+                .to_string(),
+        };
+
+        let cert = def.option("cert").ok_or_else(|| {
+            Error::validation("rustdb foreign table requires OPTIONS (cert '<server cert .der>')")
+        })?;
+        let cert = PathBuf::from(cert);
+        let cert = match base_dir {
+            Some(base) if cert.is_relative() => base.join(cert),
+            _ => cert,
+        };
+        if !cert.is_file() {
+            return Err(Error::validation(format!(
+                "rustdb certificate {} does not exist",
+                cert.display()
+            )));
+        }
+
+        let fetch_size = match def.option("fetch_size") {
+            None => DEFAULT_FETCH_SIZE,
+            Some(text) => text
+                .parse()
+                .ok()
+                .filter(|&n: &usize| n > 0)
+                .ok_or_else(|| {
+                    Error::validation(format!(
+                        "rustdb option fetch_size must be a positive integer, got '{}'",
+                        text
+                    ))
+                })?,
+        };
+        Ok(Self {
+            addr,
+            cert,
+            server_name,
+            table: def.option("table").unwrap_or(local_table).to_string(),
+            fetch_size,
+        })
+    }
+}
+
+/// Streaming reader over a remote table
+pub struct RemoteReader {
+    rows: Receiver<Result<Vec<ColumnValue>>>,
+    /// No projected columns: the remote query selects one column only to count rows
+    count_only: bool,
+}
+
+impl RemoteReader {
+    /// Starts the fetch thread; connection errors surface on the first `next_row`
+    pub fn open(options: RemoteOptions, request: ForeignScanRequest<'_>) -> Result<Self> {
+        if request.columns.is_empty() {
+            return Err(Error::validation(format!(
+                "foreign table {} has no columns",
+                request.table_name
+            )));
+        }
+        let count_only = request.projection.is_empty();
+        let projection = if count_only {
+            vec![0]
+        } else {
+            request.projection
+        };
+        let columns: Vec<(String, DataType)> = projection
+            .iter()
+            .map(|&i| request.columns[i].clone())
+            .collect();
+        let mut sql = format!(
+            "SELECT {} FROM {}",
+            columns
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            options.table
+        );
+        if let Some(filter) = request.filter {
+            sql.push_str(" WHERE ");
+            sql.push_str(filter);
+        }
+        tracing::debug!(table = %request.table_name, remote = %options.addr, sql = %sql, "remote foreign scan");
+
+        let (tx, rx) = sync_channel(options.fetch_size);
+        let fetch = RemoteFetch {
+            options,
+            sql,
+            columns,
+            limit: request.limit,
+        };
+        std::thread::Builder::new()
+            .name(format!("rustdb-remote-{}", request.table_name))
+            .spawn(move || fetch.run(tx))
+            .map_err(|e| Error::database(format!("cannot start remote scan thread: {}", e)))?;
+        Ok(Self {
+            rows: rx,
+            count_only,
+        })
+    }
+}
+
+impl ForeignReader for RemoteReader {
+    fn next_row(&mut self) -> Result<Option<Vec<ColumnValue>>> {
+        // A closed channel means the fetch thread is done (errors are sent before it exits).
+        match self.rows.recv() {
+            Ok(Ok(_)) if self.count_only => Ok(Some(Vec::new())),
+            Ok(row) => row.map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+/// State moved into the fetch thread
+struct RemoteFetch {
+    options: RemoteOptions,
+    /// Query without `LIMIT` / `OFFSET`
+    sql: String,
+    columns: Vec<(String, DataType)>,
+    limit: Option<usize>,
+}
+
+impl RemoteFetch {
+    fn run(self, tx: SyncSender<Result<Vec<ColumnValue>>>) {
+        let runtime = match tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+        {
+            Ok(rt) => rt,
+            Err(e) => {
+                let _ = tx.send(Err(Error::database(format!("remote scan runtime: {}", e))));
+                return;
+            }
+        };
+        if let Err(e) = runtime.block_on(self.fetch_pages(&tx)) {
+            let _ = tx.send(Err(e));
+        }
+    }
+
+    async fn fetch_pages(&self, tx: &SyncSender<Result<Vec<ColumnValue>>>) -> Result<()> {
+        let remote_error = |e: &dyn std::fmt::Display| {
+            Error::database(format!(
+                "remote table {} at {}: {}",
+                self.options.table, self.options.addr, e
+            ))
+        };
+        let der = std::fs::read(&self.options.cert).map_err(|e| remote_error(&e))?;
+        let cert = CertificateDer::from(der);
+        let client =
+            build_quinn_client_config(std::slice::from_ref(&cert)).map_err(|e| remote_error(&e))?;
+        let endpoint = make_client_endpoint(client).map_err(|e| remote_error(&e))?;
+        let conn = connect(&endpoint, self.options.addr, &self.options.server_name)
+            .await
+            .map_err(|e| remote_error(&e))?;
+
+        let mut offset = 0;
+        loop {
+            let page = match self.limit {
+                Some(limit) => self.options.fetch_size.min(limit - offset),
+                None => self.options.fetch_size,
+            };
+            if page == 0 {
+                break;
+            }
+            let sql = format!("{} LIMIT {} OFFSET {}", self.sql, page, offset);
+            let result = match query_once(&conn, &sql)
+                .await
+                .map_err(|e| remote_error(&e))?
+            {
+                ServerMessage::ResultSet(result) => result,
+                ServerMessage::Error(e) => {
+                    return Err(remote_error(&format_args!("{}: {}", e.code, e.message)))
+                }
+                other => {
+                    return Err(remote_error(&format_args!(
+                        "unexpected response {:?}",
+                        other
+                    )))
+                }
+            };
+            let positions = self
+                .columns
+                .iter()
+                .map(|(name, _)| {
+                    result
+                        .columns
+                        .iter()
+                        .position(|c| c.eq_ignore_ascii_case(name))
+                })
+                .collect::<Vec<_>>();
+            let fetched = result.rows.len();
+            for (i, cells) in result.rows.into_iter().enumerate() {
+                let row = self.decode_row(&positions, &cells, offset + i + 1);
+                let failed = row.is_err();
+                if tx.send(row).is_err() || failed {
+                    // The consumer is gone (scan finished or dropped) or we reported an error.
+                    conn.close(0u32.into(), b"done");
+                    return Ok(());
+                }
+            }
+            offset += fetched;
+            if fetched < page {
+                break;
+            }
+        }
+        conn.close(0u32.into(), b"done");
+        Ok(())
+    }
+
+    fn decode_row(
+        &self,
+        positions: &[Option<usize>],
+        cells: &[String],
+        row_number: usize,
+    ) -> Result<Vec<ColumnValue>> {
+        let mut values = Vec::with_capacity(self.columns.len());
+        for ((name, template), position) in self.columns.iter().zip(positions) {
+            let at = format!("remote table {} row {}", self.options.table, row_number);
+            let Some(cell) = position.and_then(|p| cells.get(p)) else {
+                return Err(Error::validation(format!("{}: no column {}", at, name)));
+            };
+            values.push(
+                decode_cell(cell, template)
+                    .ok_or_else(|| value_error(&at, name, cell, template))?,
+            );
+        }
+        Ok(values)
+    }
+}
+
+/// Converts a result cell (the `Debug` form of the remote value, e.g. `Integer(5)`,
+/// `Varchar("a\"b")`, `Null`) to the declared column type
+fn decode_cell(cell: &str, template: &DataType) -> Option<ColumnValue> {
+    if cell == "Null" || cell == "NULL" {
+        return Some(ColumnValue::null());
+    }
+    let payload = cell
+        .split_once('(')
+        .and_then(|(_, rest)| rest.strip_suffix(')'))
+        .unwrap_or(cell);
+    if let (DataType::Blob(_), Some(list)) = (
+        template,
+        payload.strip_prefix('[').and_then(|p| p.strip_suffix(']')),
+    ) {
+        let bytes = list
+            .split(',')
+            .map(str::trim)
+            .filter(|b| !b.is_empty())
+            .map(|b| b.parse().ok())
+            .collect::<Option<Vec<u8>>>()?;
+        return Some(ColumnValue::new(DataType::Blob(bytes)));
+    }
+    match payload.strip_prefix('"').and_then(|p| p.strip_suffix('"')) {
+        Some(quoted) => parse_value(&unescape_debug(quoted)?, template),
+        None => parse_value(payload, template),
+    }
+}
+
+/// Reverses `str::escape_debug` as used by `{:?}` on strings
+fn unescape_debug(text: &str) -> Option<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => out.push('\n'),
+            'r' => out.push('\r'),
+            't' => out.push('\t'),
+            '0' => out.push('\0'),
+            'u' => {
+                let hex: String = chars
+                    .by_ref()
+                    .skip_while(|&c| c == '{')
+                    .take_while(|&c| c != '}')
+                    .collect();
+                out.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+            }
+            other => out.push(other),
+        }
+    }
+    Some(out)
+}
@@ -208,12 +208,10 @@ where
         new_node.keys = node.keys.split_off(mid);
         new_node.values = node.values.split_off(mid);
 
-        // Link leaf nodes (right sibling; parent also receives `new_box`)
-        new_node.next_leaf = node.next_leaf.take();
+        // The parent owns the new sibling; `next_leaf` is left unset because a boxed
+        // copy would go stale on the next write (range scans walk the tree instead).
         let separator_key = new_node.keys[0].clone();
-        let new_box = Box::new(new_node);
-        node.next_leaf = Some(new_box.clone());
-        Ok((separator_key, new_box))
+        Ok((separator_key, Box::new(new_node)))
     }
 
     /// Splits internal node
@@ -284,8 +282,9 @@ where
             node.keys.insert(pos, separator_key);
             node.children.insert(pos + 1, new_child);
 
-            // Check if current node needs splitting
-            if node.is_full(self.degree) {
+            // Internal nodes hold up to `degree` children (`degree - 1` keys); split only
+            // past that so both halves keep at least one separator.
+            if node.keys.len() >= self.degree {
                 let (separator_key, new_node) = self.split_internal(node)?;
                 Ok(Some((separator_key, new_node)))
             } else {
@@ -315,30 +314,23 @@ where
         }
     }
 
-    /// Collects all key-values in range from leaf nodes
-    fn collect_range_from_leaf(
-        &self,
-        node: &BTreeNode<K, V>,
-        start: &K,
-        end: &K,
-        result: &mut Vec<(K, V)>,
-    ) {
-        if !node.is_leaf {
-            return;
-        }
-
-        for (i, key) in node.keys.iter().enumerate() {
-            if key >= start && key <= end {
-                result.push((key.clone(), node.values[i].clone()));
-            } else if key > end {
-                break;
+    /// Removes `key` from the leaf it lives in (no rebalancing: leaves may become
+    /// empty, which search, insert and range scans all tolerate)
+    fn delete_from_node(node: &mut BTreeNode<K, V>, key: &K) -> bool {
+        if node.is_leaf {
+            match node.search_key(key) {
+                Some(pos) => {
+                    node.keys.remove(pos);
+                    node.values.remove(pos);
+                    true
+                }
+                None => false,
             }
-        }
-
-        // Move to next leaf node
-        if let Some(ref next) = node.next_leaf {
-            if !next.keys.is_empty() && next.keys[0] <= *end {
-                self.collect_range_from_leaf(next, start, end, result);
+        } else {
+            let pos = node.descend_child_index(key);
+            match node.children.get_mut(pos) {
+                Some(child) => Self::delete_from_node(child, key),
+                None => false,
             }
         }
     }
@@ -425,39 +417,18 @@ where
         }
 
         let root = self.root.as_mut().unwrap();
-        if root.is_leaf {
-            if let Some(pos) = root.search_key(key) {
-                root.keys.remove(pos);
-                root.values.remove(pos);
-                {
-                    let mut s = self.statistics.borrow_mut();
-                    s.total_elements = s.total_elements.saturating_sub(1);
-                }
-                if root.keys.is_empty() {
-                    self.root = None;
-                }
-                self.update_statistics();
-                return Ok(true);
-            }
-        } else {
-            let pos = root.descend_child_index(key);
-            if pos < root.children.len() {
-                let child = &mut root.children[pos];
-                if child.is_leaf {
-                    if let Some(idx) = child.search_key(key) {
-                        child.keys.remove(idx);
-                        child.values.remove(idx);
-                        {
-                            let mut s = self.statistics.borrow_mut();
-                            s.total_elements = s.total_elements.saturating_sub(1);
-                        }
-                        self.update_statistics();
-                        return Ok(true);
-                    }
-                }
-            }
+        if !Self::delete_from_node(root, key) {
+            return Ok(false);
         }
-        Ok(false)
+        if root.is_leaf && root.keys.is_empty() {
+            self.root = None;
+        }
+        {
+            let mut s = self.statistics.borrow_mut();
+            s.total_elements = s.total_elements.saturating_sub(1);
+        }
+        self.update_statistics();
+        Ok(true)
     }
 
     fn range_search(
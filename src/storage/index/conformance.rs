@@ -0,0 +1,306 @@
+//! Conformance suite for [`Index`] implementations
+//!
+//! Drives an index and a [`BTreeMap`] model through the same operation sequence and
+//! compares every observable result (`search`, `delete`, `range_search`, `size`).
+//! Sequences are generated from seeds, so a failure reports the seed and step needed
+//! to reproduce it. New index types (R-tree, full-text, ...) plug in by passing a
+//! constructor closure to [`run_conformance_suite`].
+//!
+//! Compiled for unit tests and, for downstream crates, behind the `test-utils` feature.
+
+use crate::storage::index::Index;
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug};
+
+/// What the index under test promises beyond point lookups
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexCapabilities {
+    /// `range_search` returns every key in `[start, end]` in ascending order.
+    /// Hash indexes leave this off (their `range_search` returns nothing).
+    pub ordered_range: bool,
+}
+
+impl IndexCapabilities {
+    /// Ordered index (B+ tree and friends)
+    pub fn ordered() -> Self {
+        Self {
+            ordered_range: true,
+        }
+    }
+
+    /// Point-lookup only index (hash indexes)
+    pub fn point_only() -> Self {
+        Self {
+            ordered_range: false,
+        }
+    }
+}
+
+/// One step of a conformance sequence
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexOp<K, V> {
+    /// `insert(key, value)`; inserting an existing key replaces its value
+    Insert(K, V),
+    /// `delete(key)`; returns whether the key was present
+    Delete(K),
+    /// `search(key)`
+    Search(K),
+    /// `range_search(start, end)`
+    Range(K, K),
+}
+
+/// Describes the first divergence between the index and the model
+#[derive(Debug, Clone)]
+pub struct ConformanceFailure {
+    /// Seed of the generated sequence (`None` for hand-written sequences)
+    pub seed: Option<u64>,
+    /// Index of the failing step
+    pub step: usize,
+    /// Human-readable description (operation, expected and actual results)
+    pub message: String,
+}
+
+impl fmt::Display for ConformanceFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.seed {
+            Some(seed) => write!(f, "seed {} step {}: {}", seed, self.step, self.message),
+            None => write!(f, "step {}: {}", self.step, self.message),
+        }
+    }
+}
+
+impl std::error::Error for ConformanceFailure {}
+
+/// Applies `ops` to `index` and to a [`BTreeMap`] model, checking results after every step.
+///
+/// `size()` is compared after each mutation; when `caps.ordered_range` is set a full
+/// scan is also compared at the end to catch entries lost in unvisited nodes.
+pub fn check_ops<I>(
+    index: &mut I,
+    ops: &[IndexOp<I::Key, I::Value>],
+    caps: IndexCapabilities,
+) -> Result<(), ConformanceFailure>
+where
+    I: Index,
+    I::Key: Debug,
+    I::Value: PartialEq + Debug,
+{
+    let mut model: BTreeMap<I::Key, I::Value> = BTreeMap::new();
+    let fail = |step: usize, message: String| ConformanceFailure {
+        seed: None,
+        step,
+        message,
+    };
+
+    for (step, op) in ops.iter().enumerate() {
+        match op {
+            IndexOp::Insert(k, v) => {
+                index
+                    .insert(k.clone(), v.clone())
+                    .map_err(|e| fail(step, format!("insert({:?}) failed: {}", k, e)))?;
+                model.insert(k.clone(), v.clone());
+            }
+            IndexOp::Delete(k) => {
+                let got = index
+                    .delete(k)
+                    .map_err(|e| fail(step, format!("delete({:?}) failed: {}", k, e)))?;
+                let expected = model.remove(k).is_some();
+                if got != expected {
+                    return Err(fail(
+                        step,
+                        format!("delete({:?}) returned {}, expected {}", k, got, expected),
+                    ));
+                }
+            }
+            IndexOp::Search(k) => {
+                let got = index
+                    .search(k)
+                    .map_err(|e| fail(step, format!("search({:?}) failed: {}", k, e)))?;
+                let expected = model.get(k);
+                if got.as_ref() != expected {
+                    return Err(fail(
+                        step,
+                        format!(
+                            "search({:?}) returned {:?}, expected {:?}",
+                            k, got, expected
+                        ),
+                    ));
+                }
+            }
+            IndexOp::Range(start, end) => {
+                let got = index.range_search(start, end).map_err(|e| {
+                    fail(
+                        step,
+                        format!("range_search({:?}, {:?}) failed: {}", start, end, e),
+                    )
+                })?;
+                if caps.ordered_range {
+                    let expected = model_range(&model, start, end);
+                    if got != expected {
+                        return Err(fail(
+                            step,
+                            format!(
+                                "range_search({:?}, {:?}) returned {:?}, expected {:?}",
+                                start, end, got, expected
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+
+        if index.size() != model.len() {
+            return Err(fail(
+                step,
+                format!(
+                    "size() is {} after {:?}, expected {}",
+                    index.size(),
+                    op,
+                    model.len()
+                ),
+            ));
+        }
+        if index.is_empty() != model.is_empty() {
+            return Err(fail(
+                step,
+                format!("is_empty() disagrees with size() after {:?}", op),
+            ));
+        }
+    }
+
+    if caps.ordered_range {
+        if let (Some((lo, _)), Some((hi, _))) = (model.first_key_value(), model.last_key_value()) {
+            let got = index
+                .range_search(lo, hi)
+                .map_err(|e| fail(ops.len(), format!("final full scan failed: {}", e)))?;
+            let expected = model_range(&model, lo, hi);
+            if got != expected {
+                return Err(fail(
+                    ops.len(),
+                    format!(
+                        "final full scan returned {} entries, expected {}",
+                        got.len(),
+                        expected.len()
+                    ),
+                ));
+            }
+        }
+    } else {
+        for (k, v) in &model {
+            let got = index
+                .search(k)
+                .map_err(|e| fail(ops.len(), format!("final search({:?}) failed: {}", k, e)))?;
+            if got.as_ref() != Some(v) {
+                return Err(fail(
+                    ops.len(),
+                    format!("final search({:?}) returned {:?}, expected {:?}", k, got, v),
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn model_range<K: Ord + Clone, V: Clone>(
+    model: &BTreeMap<K, V>,
+    start: &K,
+    end: &K,
+) -> Vec<(K, V)> {
+    if start > end {
+        return Vec::new();
+    }
+    model
+        .range(start.clone()..=end.clone())
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect()
+}
+
+/// Generates a random operation sequence over `i64` keys in `0..key_space`.
+///
+/// The mix is insert-heavy so trees grow deep enough to split internal nodes, with
+/// deletes, overwrites, point lookups (hits and misses) and ranges interleaved.
+pub fn generate_ops(seed: u64, len: usize, key_space: i64) -> Vec<IndexOp<i64, u64>> {
+    let mut state = seed ^ 0x9E37_79B9_7F4A_7C15;
+    let mut next = move || {
+        // xorshift64*
+        state ^= state >> 12;
+        state ^= state << 25;
+        state ^= state >> 27;
+        state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    };
+    let key_space = key_space.max(1) as u64;
+
+    (0..len)
+        .map(|_| {
+            let key = (next() % key_space) as i64;
+            match next() % 10 {
+                0..=4 => IndexOp::Insert(key, next()),
+                5 | 6 => IndexOp::Delete(key),
+                7 | 8 => IndexOp::Search(key),
+                _ => {
+                    let width = (next() % (key_space / 4 + 1)) as i64;
+                    IndexOp::Range(key, key + width)
+                }
+            }
+        })
+        .collect()
+}
+
+/// Hand-written sequences covering edge cases random generation hits rarely
+fn edge_case_sequences() -> Vec<Vec<IndexOp<i64, u64>>> {
+    use IndexOp::*;
+
+    let ascending: Vec<_> = (0..2_000).map(|k| Insert(k, k as u64)).collect();
+    let descending: Vec<_> = (0..2_000).rev().map(|k| Insert(k, k as u64)).collect();
+    let mut drain = ascending.clone();
+    drain.extend((0..2_000).map(Delete));
+    drain.push(Search(0));
+    drain.extend((0..100).map(|k| Insert(k, 1)));
+    let mut overwrite = Vec::new();
+    for round in 0..5u64 {
+        overwrite.extend((0..500).map(|k| Insert(k, round)));
+    }
+    overwrite.extend((0..500).map(Search));
+
+    vec![
+        vec![Search(1), Delete(1), Range(0, 100), Range(5, 1)],
+        vec![Insert(1, 1), Insert(1, 2), Search(1), Delete(1), Delete(1)],
+        vec![
+            Insert(i64::MIN, 1),
+            Insert(i64::MAX, 2),
+            Range(i64::MIN, i64::MAX),
+        ],
+        ascending,
+        descending,
+        drain,
+        overwrite,
+    ]
+}
+
+/// Runs the edge-case sequences plus `seeds` random sequences, each against a fresh index.
+///
+/// Random sequences alternate between a small key space (many overwrites and deletes of
+/// present keys) and a large one (deep trees, mostly misses).
+pub fn run_conformance_suite<I, F>(
+    mut make: F,
+    caps: IndexCapabilities,
+    seeds: u64,
+) -> Result<(), ConformanceFailure>
+where
+    I: Index<Key = i64, Value = u64>,
+    F: FnMut() -> I,
+{
+    for ops in edge_case_sequences() {
+        check_ops(&mut make(), &ops, caps)?;
+    }
+    for seed in 0..seeds {
+        let key_space = if seed % 2 == 0 { 64 } else { 10_000 };
+        let ops = generate_ops(seed, 1_500, key_space);
+        check_ops(&mut make(), &ops, caps).map_err(|mut f| {
+            f.seed = Some(seed);
+            f
+        })?;
+    }
+    Ok(())
+}
@@ -261,32 +261,45 @@ where
     /// Insert using open addressing
     fn insert_open_addressing(&mut self, key: K, value: V) -> Result<()> {
         let cap = self.capacity;
+        // A tombstone may be reused, but only once the probe sequence proves the key is
+        // not stored further along (otherwise the key would end up in the table twice).
+        let mut first_deleted = None;
+        let mut target = None;
         for attempt in 0..cap {
             let index = self.hash_key_double(&key, attempt);
-            let table = self.open_table.as_mut().unwrap();
+            let table = self.open_table.as_ref().unwrap();
 
             match &table[index] {
-                HashEntry::Empty | HashEntry::Deleted => {
-                    if matches!(table[index], HashEntry::Deleted) {
-                        self.deleted_count -= 1;
-                    }
-                    table[index] = HashEntry::Occupied { key, value };
-                    self.size += 1;
-                    self.statistics.borrow_mut().total_elements += 1;
-                    return Ok(());
+                HashEntry::Empty => {
+                    target = Some(first_deleted.unwrap_or(index));
+                    break;
+                }
+                HashEntry::Deleted => {
+                    first_deleted.get_or_insert(index);
                 }
                 HashEntry::Occupied {
                     key: existing_key, ..
                 } => {
                     if *existing_key == key {
-                        table[index] = HashEntry::Occupied { key, value };
+                        self.open_table.as_mut().unwrap()[index] =
+                            HashEntry::Occupied { key, value };
                         return Ok(());
                     }
                 }
             }
         }
 
-        Err(Error::database("Hash table is full"))
+        let Some(index) = target.or(first_deleted) else {
+            return Err(Error::database("Hash table is full"));
+        };
+        let table = self.open_table.as_mut().unwrap();
+        if matches!(table[index], HashEntry::Deleted) {
+            self.deleted_count -= 1;
+        }
+        table[index] = HashEntry::Occupied { key, value };
+        self.size += 1;
+        self.statistics.borrow_mut().total_elements += 1;
+        Ok(())
     }
 
     /// Search using chaining
@@ -0,0 +1,848 @@
+//! Persistent hash index with linear hashing
+//!
+//! Maps string keys to record ids in bucket pages on disk. The index file holds a header page
+//! and one primary page per bucket, in bucket order; a bucket whose entries do not fit on its
+//! primary page chains overflow pages kept in a second file (`<path>.ovf`). The index grows by
+//! linear hashing: once buckets hold more than [`SPLIT_LOAD`] entries on average, the bucket at
+//! the split pointer is split in two (its entries rehashed with one more bit, the new bucket
+//! appended to the file), so growing costs one bucket rewrite at a time instead of a rehash of
+//! the whole index. Buckets are never merged; freed overflow pages are reused.
+//!
+//! Changed pages stay in memory until [`LinearHashIndex::checkpoint`], which writes them through
+//! a page-image log (`<path>.wal`, see [`super::page_log`]), so the files always hold the index
+//! as of a checkpoint. Each checkpoint is tagged with the caller's log sequence number, letting
+//! the caller redo later changes from its own log, or rebuild the index when it cannot.
+//!
+//! Hash indexes answer point lookups only: [`Index::range_search`] scans every bucket.
+
+use super::page_log::{companion, file_sizes, open_page_file, ImageLoc, PageLog};
+use crate::common::types::RecordId;
+use crate::common::{Error, Result};
+use crate::storage::index::{Index, IndexStatistics, PersistentIndex};
+use crate::storage::page::SpaceUsage;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use twox_hash::XxHash64;
+
+/// Size of header, bucket and overflow pages
+pub const PAGE_SIZE: usize = super::page_log::PAGE_SIZE;
+/// Average entries per bucket above which the next bucket is split
+pub const SPLIT_LOAD: u64 = 64;
+/// Longest key an entry can hold (one entry must fit on a page)
+pub const MAX_KEY_LEN: usize = PAGE_SIZE - PAGE_HEADER - ENTRY_OVERHEAD;
+
+/// Buckets of an empty index
+const INITIAL_BUCKETS: u32 = 4;
+/// `next: u32`, `count: u16`
+const PAGE_HEADER: usize = 6;
+/// `key_len: u16` and `record_id: u64` around each key
+const ENTRY_OVERHEAD: usize = 10;
+const MAGIC: &[u8; 8] = b"RDBLHX01";
+/// Clean pages are dropped from memory once this many pages are cached
+const CACHED_PAGES: usize = 1024;
+const OVERFLOW_SUFFIX: &str = ".ovf";
+const LOG_SUFFIX: &str = ".wal";
+
+/// Where a page lives
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum PageLoc {
+    Header,
+    /// Primary page of a bucket
+    Bucket(u32),
+    /// Overflow page, numbered from 1
+    Overflow(u32),
+}
+
+impl PageLoc {
+    fn encode(self) -> ImageLoc {
+        match self {
+            Self::Header => (0, 0),
+            Self::Bucket(b) => (1, b),
+            Self::Overflow(n) => (2, n),
+        }
+    }
+
+    fn decode(kind: u8, n: u32) -> Option<Self> {
+        match kind {
+            0 => Some(Self::Header),
+            1 => Some(Self::Bucket(n)),
+            2 if n > 0 => Some(Self::Overflow(n)),
+            _ => None,
+        }
+    }
+}
+
+/// Bucket or overflow page: one entry per `(key, record id)` pair
+#[derive(Debug, Clone, Default)]
+struct Page {
+    /// Overflow page continuing the chain (`0`: none)
+    next: u32,
+    entries: Vec<(String, RecordId)>,
+}
+
+impl Page {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(PAGE_SIZE);
+        buf.extend_from_slice(&self.next.to_le_bytes());
+        buf.extend_from_slice(&(self.entries.len() as u16).to_le_bytes());
+        for (key, record_id) in &self.entries {
+            buf.extend_from_slice(&(key.len() as u16).to_le_bytes());
+            buf.extend_from_slice(key.as_bytes());
+            buf.extend_from_slice(&record_id.to_le_bytes());
+        }
+        buf.resize(PAGE_SIZE, 0);
+        buf
+    }
+
+    /// Encoded bytes (the rest of the page is unused)
+    fn size(&self) -> usize {
+        PAGE_HEADER
+            + self
+                .entries
+                .iter()
+                .map(|(key, _)| ENTRY_OVERHEAD + key.len())
+                .sum::<usize>()
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let corrupt = || Error::database("corrupt hash index page");
+        let next = u32::from_le_bytes(bytes[0..4].try_into().expect("4 bytes"));
+        let count = u16::from_le_bytes(bytes[4..6].try_into().expect("2 bytes"));
+        let mut pos = PAGE_HEADER;
+        let mut entries = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let len = bytes.get(pos..pos + 2).ok_or_else(corrupt)?;
+            let len = u16::from_le_bytes(len.try_into().expect("2 bytes")) as usize;
+            let key = bytes.get(pos + 2..pos + 2 + len).ok_or_else(corrupt)?;
+            let key = std::str::from_utf8(key).map_err(|_| corrupt())?.to_string();
+            let record_id = bytes
+                .get(pos + 2 + len..pos + ENTRY_OVERHEAD + len)
+                .ok_or_else(corrupt)?;
+            entries.push((
+                key,
+                u64::from_le_bytes(record_id.try_into().expect("8 bytes")),
+            ));
+            pos += ENTRY_OVERHEAD + len;
+        }
+        Ok(Self { next, entries })
+    }
+}
+
+/// Index header (page 0)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Header {
+    /// Splitting round: buckets below the split pointer use one more hash bit
+    level: u32,
+    /// Next bucket to split
+    next_split: u32,
+    /// Overflow pages allocated (free or in use)
+    overflow_pages: u32,
+    /// First page of the free overflow page list (`0`: none)
+    free_overflow: u32,
+    /// `(key, record id)` pairs
+    entries: u64,
+    /// Distinct keys
+    keys: u64,
+    /// Caller's log sequence number of the last checkpoint
+    checkpoint_lsn: Option<u64>,
+    /// Changed since that checkpoint (the changes are only in memory)
+    modified: bool,
+}
+
+impl Header {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(PAGE_SIZE);
+        buf.extend_from_slice(MAGIC);
+        buf.extend_from_slice(&INITIAL_BUCKETS.to_le_bytes());
+        buf.extend_from_slice(&self.level.to_le_bytes());
+        buf.extend_from_slice(&self.next_split.to_le_bytes());
+        buf.extend_from_slice(&self.overflow_pages.to_le_bytes());
+        buf.extend_from_slice(&self.free_overflow.to_le_bytes());
+        buf.extend_from_slice(&self.entries.to_le_bytes());
+        buf.extend_from_slice(&self.keys.to_le_bytes());
+        buf.extend_from_slice(&self.checkpoint_lsn.unwrap_or(0).to_le_bytes());
+        buf.push(u8::from(self.checkpoint_lsn.is_some()) | (u8::from(self.modified) << 1));
+        buf.resize(PAGE_SIZE, 0);
+        buf
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        if &bytes[0..8] != MAGIC {
+            return Err(Error::database("not a hash index file"));
+        }
+        let u32_at = |pos: usize| u32::from_le_bytes(bytes[pos..pos + 4].try_into().expect("4"));
+        let u64_at = |pos: usize| u64::from_le_bytes(bytes[pos..pos + 8].try_into().expect("8"));
+        if u32_at(8) != INITIAL_BUCKETS {
+            return Err(Error::database("unsupported hash index layout"));
+        }
+        let flags = bytes[52];
+        Ok(Self {
+            level: u32_at(12),
+            next_split: u32_at(16),
+            overflow_pages: u32_at(20),
+            free_overflow: u32_at(24),
+            entries: u64_at(28),
+            keys: u64_at(36),
+            checkpoint_lsn: (flags & 1 != 0).then(|| u64_at(44)),
+            modified: flags & 2 != 0,
+        })
+    }
+}
+
+/// Files of a persistent index
+#[derive(Debug)]
+struct Files {
+    index: File,
+    overflow: File,
+    log: PageLog,
+}
+
+impl Files {
+    fn open(path: &Path) -> Result<Self> {
+        Ok(Self {
+            index: open_page_file(path)?,
+            overflow: open_page_file(&companion(path, OVERFLOW_SUFFIX))?,
+            log: PageLog::open(&companion(path, LOG_SUFFIX))?,
+        })
+    }
+
+    fn locate(&self, loc: PageLoc) -> (&File, u64) {
+        let page = PAGE_SIZE as u64;
+        match loc {
+            PageLoc::Header => (&self.index, 0),
+            PageLoc::Bucket(b) => (&self.index, (u64::from(b) + 1) * page),
+            PageLoc::Overflow(n) => (&self.overflow, (u64::from(n) - 1) * page),
+        }
+    }
+
+    /// Reads the page at `loc` into `buf`; `false` if the file does not reach it
+    fn read_page(&self, loc: PageLoc, buf: &mut [u8]) -> Result<bool> {
+        let (mut file, offset) = self.locate(loc);
+        file.seek(SeekFrom::Start(offset))?;
+        match file.read_exact(buf) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write_page(&self, loc: PageLoc, image: &[u8]) -> Result<()> {
+        let (mut file, offset) = self.locate(loc);
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(image)?;
+        Ok(())
+    }
+
+    fn sync(&self) -> Result<()> {
+        self.index.sync_data()?;
+        self.overflow.sync_data()?;
+        Ok(())
+    }
+
+    /// Replaces the log with one batch of page images and fsyncs it
+    fn log_images(&self, images: &[(PageLoc, Vec<u8>)]) -> Result<()> {
+        let images: Vec<(ImageLoc, &[u8])> = images
+            .iter()
+            .map(|(loc, image)| (loc.encode(), image.as_slice()))
+            .collect();
+        self.log.write_batch(&images)
+    }
+
+    fn clear_log(&self) -> Result<()> {
+        self.log.clear()
+    }
+
+    /// Finishes an interrupted checkpoint: writes the pages of a complete logged batch in place
+    /// and empties the log. Returns whether a batch was applied.
+    fn recover(&self) -> Result<bool> {
+        self.log.recover(|images| {
+            let Some(images) = images
+                .into_iter()
+                .map(|((kind, n), image)| Some((PageLoc::decode(kind, n)?, image)))
+                .collect::<Option<Vec<_>>>()
+            else {
+                return Ok(false);
+            };
+            for (loc, image) in images {
+                self.write_page(loc, image)?;
+            }
+            self.sync()?;
+            Ok(true)
+        })
+    }
+}
+
+fn hash_key(key: &str) -> u64 {
+    XxHash64::oneshot(0, key.as_bytes())
+}
+
+/// Bucket layout of a [`LinearHashIndex`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashIndexShape {
+    /// Buckets (primary pages)
+    pub buckets: u32,
+    /// Splitting round
+    pub level: u32,
+    /// Next bucket to split
+    pub next_split: u32,
+    /// Overflow pages allocated, free ones included
+    pub overflow_pages: u32,
+    /// `(key, record id)` pairs
+    pub entries: u64,
+}
+
+/// Hash index from string keys to record ids, on disk or in memory
+pub struct LinearHashIndex {
+    path: Option<PathBuf>,
+    /// `None` for an in-memory index
+    files: Option<Files>,
+    header: Header,
+    /// Header as written to disk
+    disk_header: Header,
+    /// Cached pages; dirty ones are only written by checkpoints
+    pages: RefCell<HashMap<PageLoc, Page>>,
+    dirty: HashSet<PageLoc>,
+    statistics: RefCell<IndexStatistics>,
+}
+
+impl LinearHashIndex {
+    /// Creates an index that lives in memory only
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            files: None,
+            header: Header::default(),
+            disk_header: Header::default(),
+            pages: RefCell::new(HashMap::new()),
+            dirty: HashSet::new(),
+            statistics: RefCell::new(IndexStatistics::default()),
+        }
+    }
+
+    /// Opens the index stored at `path`, creating an empty one if there is none, and finishes
+    /// an interrupted checkpoint
+    pub fn open(path: &Path) -> Result<Self> {
+        let files = Files::open(path)?;
+        if files.recover()? {
+            tracing::info!(path = %path.display(), "hash index checkpoint finished from its log");
+        }
+        let mut buf = vec![0; PAGE_SIZE];
+        let header = if files.read_page(PageLoc::Header, &mut buf)? {
+            Header::decode(&buf)?
+        } else {
+            let header = Header::default();
+            files.write_page(PageLoc::Header, &header.encode())?;
+            files.sync()?;
+            header
+        };
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            files: Some(files),
+            header,
+            disk_header: header,
+            ..Self::in_memory()
+        })
+    }
+
+    /// Files of the index at `path`
+    fn files(path: &Path) -> [PathBuf; 3] {
+        [
+            path.to_path_buf(),
+            companion(path, OVERFLOW_SUFFIX),
+            companion(path, LOG_SUFFIX),
+        ]
+    }
+
+    /// Deletes the files of the index at `path` (those that exist)
+    pub fn remove_files(path: &Path) -> Result<()> {
+        for file in Self::files(path) {
+            match std::fs::remove_file(&file) {
+                Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Location of the index file (`None` in memory)
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Log sequence number of the checkpoint the files hold (`None`: never checkpointed)
+    pub fn checkpoint_lsn(&self) -> Option<u64> {
+        self.disk_header.checkpoint_lsn
+    }
+
+    /// Whether the index changed after its last checkpoint, in this process or before a crash
+    pub fn modified_since_checkpoint(&self) -> bool {
+        self.disk_header.modified
+    }
+
+    /// Writes the changed pages durably and records `lsn` as the checkpoint's sequence number
+    pub fn checkpoint(&mut self, lsn: u64) -> Result<()> {
+        self.header.checkpoint_lsn = Some(lsn);
+        self.header.modified = false;
+        let Some(files) = &self.files else {
+            self.disk_header = self.header;
+            return Ok(());
+        };
+        let mut dirty: Vec<PageLoc> = self.dirty.iter().copied().collect();
+        dirty.sort_unstable();
+        let images: Vec<(PageLoc, Vec<u8>)> = {
+            let pages = self.pages.borrow();
+            std::iter::once((PageLoc::Header, self.header.encode()))
+                .chain(dirty.into_iter().map(|loc| (loc, pages[&loc].encode())))
+                .collect()
+        };
+        files.log_images(&images)?;
+        for (loc, image) in &images {
+            files.write_page(*loc, image)?;
+        }
+        files.sync()?;
+        files.clear_log()?;
+        self.dirty.clear();
+        self.disk_header = self.header;
+        Ok(())
+    }
+
+    /// Removes every entry and shrinks the files back to an empty, never checkpointed index
+    pub fn clear(&mut self) -> Result<()> {
+        self.header = Header::default();
+        self.pages.get_mut().clear();
+        self.dirty.clear();
+        if let Some(files) = &self.files {
+            files.clear_log()?;
+            files.overflow.set_len(0)?;
+            files.index.set_len(0)?;
+            files.write_page(PageLoc::Header, &self.header.encode())?;
+            files.sync()?;
+        }
+        self.disk_header = self.header;
+        Ok(())
+    }
+
+    /// Current bucket layout
+    pub fn shape(&self) -> HashIndexShape {
+        HashIndexShape {
+            buckets: self.bucket_count(),
+            level: self.header.level,
+            next_split: self.header.next_split,
+            overflow_pages: self.header.overflow_pages,
+            entries: self.header.entries,
+        }
+    }
+
+    /// Bytes of the index files on disk (`0` in memory)
+    pub fn file_size(&self) -> Result<u64> {
+        let Some(path) = &self.path else {
+            return Ok(0);
+        };
+        file_sizes(&Self::files(path))
+    }
+
+    /// Pages of the index and the free space on them: free overflow pages are free as a whole,
+    /// the unused bytes of bucket chains are fragmented
+    pub fn space_usage(&self) -> Result<SpaceUsage> {
+        let mut free_pages = HashSet::new();
+        let mut n = self.header.free_overflow;
+        while n != 0 && free_pages.insert(n) {
+            n = self.with_page(PageLoc::Overflow(n), |p| p.next)?;
+        }
+        let in_use = (0..self.bucket_count()).map(PageLoc::Bucket).chain(
+            (1..=self.header.overflow_pages)
+                .filter(|n| !free_pages.contains(n))
+                .map(PageLoc::Overflow),
+        );
+        let mut usage = SpaceUsage {
+            pages: 1 + u64::from(self.bucket_count()) + u64::from(self.header.overflow_pages),
+            free_bytes: (free_pages.len() * PAGE_SIZE) as u64,
+            fragmented_bytes: 0,
+        };
+        for loc in in_use {
+            let unused = self.with_page(loc, |p| PAGE_SIZE - p.size())? as u64;
+            usage.free_bytes += unused;
+            usage.fragmented_bytes += unused;
+        }
+        Ok(usage)
+    }
+
+    /// Operation counters and fill factor (entries per bucket over [`SPLIT_LOAD`])
+    pub fn statistics(&self) -> IndexStatistics {
+        let mut s = self.statistics.borrow().clone();
+        s.total_elements = self.header.keys;
+        s.depth = 1;
+        s.fill_factor =
+            self.header.entries as f64 / (f64::from(self.bucket_count()) * SPLIT_LOAD as f64);
+        s
+    }
+
+    fn bucket_count(&self) -> u32 {
+        (INITIAL_BUCKETS << self.header.level) + self.header.next_split
+    }
+
+    fn bucket_for(&self, key: &str) -> u32 {
+        let hash = hash_key(key);
+        let low = u64::from(INITIAL_BUCKETS) << self.header.level;
+        let bucket = hash % low;
+        if bucket < u64::from(self.header.next_split) {
+            (hash % (low * 2)) as u32
+        } else {
+            bucket as u32
+        }
+    }
+
+    /// Runs `f` on the page at `loc`, reading it into the cache first if needed
+    fn with_page<R>(&self, loc: PageLoc, f: impl FnOnce(&Page) -> R) -> Result<R> {
+        if let Some(page) = self.pages.borrow().get(&loc) {
+            return Ok(f(page));
+        }
+        let page = match &self.files {
+            Some(files) => {
+                let mut buf = vec![0; PAGE_SIZE];
+                if files.read_page(loc, &mut buf)? {
+                    Page::decode(&buf)?
+                } else {
+                    Page::default()
+                }
+            }
+            None => Page::default(),
+        };
+        let out = f(&page);
+        let mut pages = self.pages.borrow_mut();
+        if self.files.is_some() && pages.len() >= CACHED_PAGES {
+            pages.retain(|loc, _| self.dirty.contains(loc));
+        }
+        pages.insert(loc, page);
+        Ok(out)
+    }
+
+    fn put_page(&mut self, loc: PageLoc, page: Page) {
+        self.pages.get_mut().insert(loc, page);
+        self.dirty.insert(loc);
+    }
+
+    /// Entries of `bucket` in chain order, with the overflow pages holding them
+    fn read_chain(&self, bucket: u32) -> Result<(Vec<(String, RecordId)>, Vec<u32>)> {
+        let mut entries = Vec::new();
+        let mut overflow = Vec::new();
+        let mut loc = PageLoc::Bucket(bucket);
+        loop {
+            let next = self.with_page(loc, |page| {
+                entries.extend(page.entries.iter().cloned());
+                page.next
+            })?;
+            if next == 0 {
+                return Ok((entries, overflow));
+            }
+            overflow.push(next);
+            loc = PageLoc::Overflow(next);
+        }
+    }
+
+    /// Rewrites `bucket` with `entries`, reusing its `overflow` pages and freeing those left over
+    fn write_chain(
+        &mut self,
+        bucket: u32,
+        entries: Vec<(String, RecordId)>,
+        overflow: Vec<u32>,
+    ) -> Result<()> {
+        let mut pages: Vec<Vec<(String, RecordId)>> = vec![Vec::new()];
+        let mut used = PAGE_HEADER;
+        for entry in entries {
+            let size = ENTRY_OVERHEAD + entry.0.len();
+            if used + size > PAGE_SIZE {
+                pages.push(Vec::new());
+                used = PAGE_HEADER;
+            }
+            used += size;
+            pages
+                .last_mut()
+                .expect("chain has a primary page")
+                .push(entry);
+        }
+
+        let mut spare = overflow.into_iter();
+        let mut chain = Vec::with_capacity(pages.len() - 1);
+        for _ in 1..pages.len() {
+            chain.push(match spare.next() {
+                Some(n) => n,
+                None => self.allocate_overflow()?,
+            });
+        }
+        for n in spare {
+            self.free_overflow(n);
+        }
+        let mut loc = PageLoc::Bucket(bucket);
+        for (i, entries) in pages.into_iter().enumerate() {
+            let next = chain.get(i).copied().unwrap_or(0);
+            self.put_page(loc, Page { next, entries });
+            loc = PageLoc::Overflow(next);
+        }
+        Ok(())
+    }
+
+    fn allocate_overflow(&mut self) -> Result<u32> {
+        let head = self.header.free_overflow;
+        if head != 0 {
+            self.header.free_overflow = self.with_page(PageLoc::Overflow(head), |p| p.next)?;
+            return Ok(head);
+        }
+        self.header.overflow_pages += 1;
+        Ok(self.header.overflow_pages)
+    }
+
+    fn free_overflow(&mut self, n: u32) {
+        let next = self.header.free_overflow;
+        self.put_page(
+            PageLoc::Overflow(n),
+            Page {
+                next,
+                entries: Vec::new(),
+            },
+        );
+        self.header.free_overflow = n;
+    }
+
+    /// Splits buckets until the average load is back under [`SPLIT_LOAD`]
+    fn split_while_overloaded(&mut self) -> Result<()> {
+        while self.header.entries > u64::from(self.bucket_count()) * SPLIT_LOAD {
+            self.split_next()?;
+        }
+        Ok(())
+    }
+
+    /// Splits the bucket at the split pointer, moving the entries that hash to its new sibling
+    fn split_next(&mut self) -> Result<()> {
+        let low = INITIAL_BUCKETS << self.header.level;
+        let old = self.header.next_split;
+        let (entries, overflow) = self.read_chain(old)?;
+        let (kept, moved): (Vec<_>, Vec<_>) = entries
+            .into_iter()
+            .partition(|(key, _)| hash_key(key) % (u64::from(low) * 2) == u64::from(old));
+        self.header.next_split += 1;
+        if self.header.next_split == low {
+            self.header.level += 1;
+            self.header.next_split = 0;
+        }
+        self.write_chain(old, kept, overflow)?;
+        self.write_chain(old + low, moved, Vec::new())
+    }
+
+    /// Records on disk that the index changed after its checkpoint, before the first change
+    fn mark_modified(&mut self) -> Result<()> {
+        if self.disk_header.modified {
+            return Ok(());
+        }
+        let mut header = self.disk_header;
+        header.modified = true;
+        if let Some(files) = &self.files {
+            files.write_page(PageLoc::Header, &header.encode())?;
+            files.sync()?;
+        }
+        self.disk_header = header;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for LinearHashIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LinearHashIndex")
+            .field("path", &self.path)
+            .field("shape", &self.shape())
+            .field("dirty_pages", &self.dirty.len())
+            .finish()
+    }
+}
+
+impl Index for LinearHashIndex {
+    type Key = String;
+    type Value = Vec<RecordId>;
+
+    /// Replaces the record ids of `key`; an empty list removes the key
+    fn insert(&mut self, key: String, value: Vec<RecordId>) -> Result<()> {
+        self.statistics.get_mut().insert_operations += 1;
+        if key.len() > MAX_KEY_LEN {
+            return Err(Error::validation(format!(
+                "hash index key of {} bytes exceeds the {MAX_KEY_LEN}-byte limit",
+                key.len()
+            )));
+        }
+        self.mark_modified()?;
+        let bucket = self.bucket_for(&key);
+        let (mut entries, overflow) = self.read_chain(bucket)?;
+        let before = entries.len();
+        entries.retain(|(k, _)| *k != key);
+        let removed = before - entries.len();
+        if removed > 0 {
+            self.header.keys -= 1;
+        }
+        if !value.is_empty() {
+            self.header.keys += 1;
+        }
+        self.header.entries = self.header.entries - removed as u64 + value.len() as u64;
+        entries.extend(value.into_iter().map(|id| (key.clone(), id)));
+        self.write_chain(bucket, entries, overflow)?;
+        self.split_while_overloaded()
+    }
+
+    fn search(&self, key: &String) -> Result<Option<Vec<RecordId>>> {
+        self.statistics.borrow_mut().search_operations += 1;
+        let mut ids = Vec::new();
+        let mut loc = PageLoc::Bucket(self.bucket_for(key));
+        loop {
+            let next = self.with_page(loc, |page| {
+                ids.extend(
+                    page.entries
+                        .iter()
+                        .filter(|(k, _)| k == key)
+                        .map(|(_, id)| *id),
+                );
+                page.next
+            })?;
+            if next == 0 {
+                break;
+            }
+            loc = PageLoc::Overflow(next);
+        }
+        Ok((!ids.is_empty()).then_some(ids))
+    }
+
+    fn delete(&mut self, key: &String) -> Result<bool> {
+        self.statistics.get_mut().delete_operations += 1;
+        let bucket = self.bucket_for(key);
+        let (mut entries, overflow) = self.read_chain(bucket)?;
+        let before = entries.len();
+        entries.retain(|(k, _)| k != key);
+        let removed = before - entries.len();
+        if removed == 0 {
+            return Ok(false);
+        }
+        self.mark_modified()?;
+        self.header.entries -= removed as u64;
+        self.header.keys -= 1;
+        self.write_chain(bucket, entries, overflow)?;
+        Ok(true)
+    }
+
+    /// Scans every bucket: hash indexes keep no key order
+    fn range_search(&self, start: &String, end: &String) -> Result<Vec<(String, Vec<RecordId>)>> {
+        self.statistics.borrow_mut().range_search_operations += 1;
+        let mut found: BTreeMap<String, Vec<RecordId>> = BTreeMap::new();
+        if start > end {
+            return Ok(Vec::new());
+        }
+        for bucket in 0..self.bucket_count() {
+            let (entries, _) = self.read_chain(bucket)?;
+            for (key, id) in entries {
+                if key >= *start && key <= *end {
+                    found.entry(key).or_default().push(id);
+                }
+            }
+        }
+        Ok(found.into_iter().collect())
+    }
+
+    fn size(&self) -> usize {
+        self.header.keys as usize
+    }
+}
+
+impl PersistentIndex for LinearHashIndex {
+    fn checkpoint_lsn(&self) -> Option<u64> {
+        LinearHashIndex::checkpoint_lsn(self)
+    }
+
+    fn modified_since_checkpoint(&self) -> bool {
+        LinearHashIndex::modified_since_checkpoint(self)
+    }
+
+    fn checkpoint(&mut self, lsn: u64) -> Result<()> {
+        LinearHashIndex::checkpoint(self, lsn)
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        LinearHashIndex::clear(self)
+    }
+
+    fn file_size(&self) -> Result<u64> {
+        LinearHashIndex::file_size(self)
+    }
+
+    fn space_usage(&self) -> Result<SpaceUsage> {
+        LinearHashIndex::space_usage(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn key(i: u64) -> String {
+        format!("key-{i}")
+    }
+
+    #[test]
+    fn test_logged_batch_is_applied_on_open() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("t.idx.lhx");
+        {
+            let mut index = LinearHashIndex::open(&path).unwrap();
+            index.insert(key(1), vec![10]).unwrap();
+            index.checkpoint(5).unwrap();
+            index.insert(key(2), vec![20, 21]).unwrap();
+            // Crash after logging the second checkpoint, before any page is written in place.
+            index.header.checkpoint_lsn = Some(9);
+            index.header.modified = false;
+            let pages = index.pages.borrow();
+            let images: Vec<_> = std::iter::once((PageLoc::Header, index.header.encode()))
+                .chain(index.dirty.iter().map(|loc| (*loc, pages[loc].encode())))
+                .collect();
+            index.files.as_ref().unwrap().log_images(&images).unwrap();
+        }
+        let index = LinearHashIndex::open(&path).unwrap();
+        assert_eq!(index.checkpoint_lsn(), Some(9));
+        assert_eq!(index.search(&key(2)).unwrap(), Some(vec![20, 21]));
+        assert_eq!(index.size(), 2);
+        assert_eq!(
+            std::fs::metadata(companion(&path, LOG_SUFFIX))
+                .unwrap()
+                .len(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_torn_batch_is_discarded_on_open() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("t.idx.lhx");
+        {
+            let mut index = LinearHashIndex::open(&path).unwrap();
+            index.insert(key(1), vec![10]).unwrap();
+            index.checkpoint(5).unwrap();
+            index.insert(key(2), vec![20]).unwrap();
+            let pages = index.pages.borrow();
+            let images: Vec<_> = index
+                .dirty
+                .iter()
+                .map(|loc| (*loc, pages[loc].encode()))
+                .collect();
+            let files = index.files.as_ref().unwrap();
+            files.log_images(&images).unwrap();
+            // Lose the end of the batch.
+            let len = files.log.file.metadata().unwrap().len();
+            files.log.file.set_len(len - 3).unwrap();
+        }
+        let index = LinearHashIndex::open(&path).unwrap();
+        assert_eq!(index.checkpoint_lsn(), Some(5));
+        assert!(index.modified_since_checkpoint());
+        assert_eq!(index.search(&key(1)).unwrap(), Some(vec![10]));
+        assert_eq!(index.search(&key(2)).unwrap(), None);
+        assert_eq!(index.size(), 1);
+    }
+}
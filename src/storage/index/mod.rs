@@ -1,20 +1,29 @@
 //! Index module for rustdb
 //!
 //! This module provides implementations of various index types,
-//! including B+ trees and hash indexes.
+//! including B+ trees (in memory, or persistent in node pages) and hash indexes (in memory, or
+//! persistent with linear hashing).
 
 pub mod btree;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod conformance;
 pub mod hash_index;
+pub mod linear_hash;
+mod page_log;
+pub mod paged_btree;
 pub mod simple_hash_index;
 
 pub use btree::BPlusTree;
 pub use hash_index::{CollisionResolution, HashIndex};
+pub use linear_hash::{HashIndexShape, LinearHashIndex};
+pub use paged_btree::PagedBPlusTree;
 pub use simple_hash_index::SimpleHashIndex;
 
 use crate::common::{
     types::{PageId, RecordId},
     Result,
 };
+use crate::storage::page::SpaceUsage;
 use serde::{Deserialize, Serialize};
 
 /// Trait for all index types
@@ -47,6 +56,30 @@ pub trait Index {
     }
 }
 
+/// Index whose files hold it as of its last checkpoint, tagged with the caller's log sequence
+/// number, so the caller redoes later changes from its own log on reopen
+pub trait PersistentIndex: Index {
+    /// Log sequence number of the checkpoint the files hold (`None`: never checkpointed)
+    fn checkpoint_lsn(&self) -> Option<u64>;
+
+    /// Whether the index changed after its last checkpoint, in this process or before a crash
+    fn modified_since_checkpoint(&self) -> bool;
+
+    /// Writes the changed pages durably and records `lsn` as the checkpoint's sequence number
+    fn checkpoint(&mut self, lsn: u64) -> Result<()>;
+
+    /// Removes every entry, leaving an empty, never checkpointed index
+    fn clear(&mut self) -> Result<()>;
+
+    /// Bytes of the index files on disk (`0` in memory)
+    fn file_size(&self) -> Result<u64>;
+
+    /// Pages of the index, changes since the checkpoint included, and the free space on them.
+    /// Free bytes inside pages that hold entries count as fragmented: only merges or a rebuild
+    /// give them back as whole pages.
+    fn space_usage(&self) -> Result<SpaceUsage>;
+}
+
 /// Index statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexStatistics {
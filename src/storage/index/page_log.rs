@@ -0,0 +1,145 @@
+//! Page-image log of the persistent indexes
+//!
+//! A persistent index keeps its changed pages in memory until a checkpoint, which writes them
+//! all to this log as one batch and fsyncs it before writing them in place. Opening the index
+//! applies a complete logged batch again (the checkpoint was interrupted while writing pages in
+//! place) and discards a torn one, so its files always hold the index as of a checkpoint.
+//!
+//! A batch is a sequence of `kind: u8`, `page: u32`, page image records, then `count: u32`,
+//! `xxh64: u64` of the records and a marker. Kind and page number locate the page in the
+//! index's files; their meaning is up to the index.
+
+use crate::common::Result;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use twox_hash::XxHash64;
+
+/// Size of the logged page images
+pub(super) const PAGE_SIZE: usize = 4096;
+
+/// Ends a complete batch of page images in the log: `count: u32`, `xxh64: u64`, marker
+const BATCH_MARKER: &[u8; 8] = b"LHXCOMMT";
+const BATCH_TRAILER: usize = 4 + 8 + BATCH_MARKER.len();
+/// `kind: u8`, `page: u32` before each logged image
+const IMAGE_HEADER: usize = 5;
+
+/// Location of a page image: `(kind, page number)`
+pub(super) type ImageLoc = (u8, u32);
+
+/// Page-image log file of an index
+#[derive(Debug)]
+pub(super) struct PageLog {
+    pub(super) file: File,
+}
+
+impl PageLog {
+    /// Opens the log at `path`, creating an empty one if there is none
+    pub(super) fn open(path: &Path) -> Result<Self> {
+        Ok(Self {
+            file: open_page_file(path)?,
+        })
+    }
+
+    /// Replaces the log with one batch of page images and fsyncs it
+    pub(super) fn write_batch(&self, images: &[(ImageLoc, &[u8])]) -> Result<()> {
+        let mut batch =
+            Vec::with_capacity(images.len() * (IMAGE_HEADER + PAGE_SIZE) + BATCH_TRAILER);
+        for ((kind, n), image) in images {
+            batch.push(*kind);
+            batch.extend_from_slice(&n.to_le_bytes());
+            batch.extend_from_slice(image);
+        }
+        let checksum = XxHash64::oneshot(0, &batch);
+        batch.extend_from_slice(&(images.len() as u32).to_le_bytes());
+        batch.extend_from_slice(&checksum.to_le_bytes());
+        batch.extend_from_slice(BATCH_MARKER);
+
+        let mut log = &self.file;
+        log.set_len(0)?;
+        log.seek(SeekFrom::Start(0))?;
+        log.write_all(&batch)?;
+        log.sync_data()?;
+        Ok(())
+    }
+
+    pub(super) fn clear(&self) -> Result<()> {
+        self.file.set_len(0)?;
+        self.file.sync_data()?;
+        Ok(())
+    }
+
+    /// Finishes an interrupted checkpoint: passes the pages of a complete logged batch to
+    /// `apply` (which writes them in place and syncs, returning `false` when it does not know
+    /// their locations), then empties the log. Returns whether a batch was applied.
+    pub(super) fn recover(
+        &self,
+        apply: impl FnOnce(Vec<(ImageLoc, &[u8])>) -> Result<bool>,
+    ) -> Result<bool> {
+        let mut bytes = Vec::new();
+        let mut log = &self.file;
+        log.seek(SeekFrom::Start(0))?;
+        log.read_to_end(&mut bytes)?;
+        if bytes.is_empty() {
+            return Ok(false);
+        }
+        let applied = match decode_batch(&bytes) {
+            Some(images) => apply(images)?,
+            None => false,
+        };
+        self.clear()?;
+        Ok(applied)
+    }
+}
+
+/// Page images of a complete, checksum-valid batch; `None` for a torn or corrupt one
+fn decode_batch(bytes: &[u8]) -> Option<Vec<(ImageLoc, &[u8])>> {
+    let body_len = bytes.len().checked_sub(BATCH_TRAILER)?;
+    let (body, trailer) = bytes.split_at(body_len);
+    if &trailer[12..] != BATCH_MARKER {
+        return None;
+    }
+    let count = u32::from_le_bytes(trailer[0..4].try_into().expect("4 bytes")) as usize;
+    let checksum = u64::from_le_bytes(trailer[4..12].try_into().expect("8 bytes"));
+    if body.len() != count * (IMAGE_HEADER + PAGE_SIZE) || XxHash64::oneshot(0, body) != checksum {
+        return None;
+    }
+    Some(
+        body.chunks_exact(IMAGE_HEADER + PAGE_SIZE)
+            .map(|record| {
+                let n = u32::from_le_bytes(record[1..5].try_into().expect("4 bytes"));
+                ((record[0], n), &record[IMAGE_HEADER..])
+            })
+            .collect(),
+    )
+}
+
+/// Opens `path` for reading and writing, creating it if needed
+pub(super) fn open_page_file(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+}
+
+/// Total bytes of those of `files` that exist
+pub(super) fn file_sizes(files: &[PathBuf]) -> Result<u64> {
+    let mut size = 0;
+    for file in files {
+        match std::fs::metadata(file) {
+            Ok(meta) => size += meta.len(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(size)
+}
+
+/// `path` with `suffix` appended to its file name
+pub(super) fn companion(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    name.into()
+}
@@ -0,0 +1,1022 @@
+//! Persistent B+ tree index
+//!
+//! Maps string keys to record ids in node pages on disk, in key order. The index file holds a
+//! header page and one node per page. Leaves hold one `(key, record id)` entry per pair, sorted,
+//! and link to their right sibling, so the ids of a key may continue on the next leaf; internal
+//! nodes hold separator entries and child page numbers. An insert that overflows a page splits
+//! the node in two and adds a separator to its parent, growing a new root when the root splits.
+//! A delete that leaves a node under a quarter full merges it with a sibling, or moves entries
+//! over from the sibling when the two do not fit on one page; a root left with a single child
+//! is replaced by it. Freed pages are reused.
+//!
+//! Changed pages stay in memory until [`PagedBPlusTree::checkpoint`], which writes them through
+//! a page-image log (`<path>.wal`, see [`super::page_log`]), so the file always holds the tree as
+//! of a checkpoint. Like [`super::LinearHashIndex`], each checkpoint is tagged with the caller's
+//! log sequence number, letting the caller redo later changes from its own log, or rebuild the
+//! index when it cannot.
+
+use super::page_log::{companion, file_sizes, open_page_file, ImageLoc, PageLog, PAGE_SIZE};
+use crate::common::types::RecordId;
+use crate::common::{Error, Result};
+use crate::storage::index::{Index, IndexStatistics, PersistentIndex};
+use crate::storage::page::SpaceUsage;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Longest key an entry can hold (an internal node must fit four separators on a page)
+pub const MAX_KEY_LEN: usize = (PAGE_SIZE - NODE_HEADER - 5 * CHILD_SIZE) / 4 - ENTRY_OVERHEAD;
+
+/// `kind: u8`, `count: u16`, `next: u32`
+const NODE_HEADER: usize = 7;
+/// `key_len: u16` and `record_id: u64` around each key
+const ENTRY_OVERHEAD: usize = 10;
+/// Child page number of an internal node
+const CHILD_SIZE: usize = 4;
+/// Nodes smaller than this after a delete are merged with or refilled from a sibling
+const MIN_FILL: usize = PAGE_SIZE / 4;
+const MAGIC: &[u8; 8] = b"RDBBPT01";
+/// Clean pages are dropped from memory once this many pages are cached
+const CACHED_PAGES: usize = 1024;
+const LOG_SUFFIX: &str = ".wal";
+/// Page kinds in the page-image log
+const LOG_HEADER: u8 = 0;
+const LOG_NODE: u8 = 1;
+
+/// Leaf entry or separator: a key and one of its record ids
+type Entry = (String, RecordId);
+
+fn entry_size(entry: &Entry) -> usize {
+    ENTRY_OVERHEAD + entry.0.len()
+}
+
+/// Number of leading entries holding about half of their bytes, leaving both sides non-empty
+fn byte_midpoint(entries: &[Entry]) -> usize {
+    let total: usize = entries.iter().map(entry_size).sum();
+    let mut acc = 0;
+    let mut mid = entries.len();
+    for (i, entry) in entries.iter().enumerate() {
+        if acc >= total / 2 {
+            mid = i;
+            break;
+        }
+        acc += entry_size(entry);
+    }
+    mid.clamp(1, entries.len().saturating_sub(1).max(1))
+}
+
+/// Tree node, one per page
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Leaf {
+        entries: Vec<Entry>,
+        /// Right sibling (`0`: none)
+        next: u32,
+    },
+    Internal {
+        /// Separators: child `i` holds the entries below `keys[i]`, at or above `keys[i - 1]`
+        keys: Vec<Entry>,
+        children: Vec<u32>,
+    },
+    /// Page on the free list
+    Free { next: u32 },
+}
+
+/// Result of rebalancing two sibling nodes
+enum Rebalanced {
+    Merged(Node),
+    Split(Node, Entry, Node),
+}
+
+impl Node {
+    fn empty_leaf() -> Self {
+        Self::Leaf {
+            entries: Vec::new(),
+            next: 0,
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Self::Leaf { entries, .. } => {
+                NODE_HEADER + entries.iter().map(entry_size).sum::<usize>()
+            }
+            Self::Internal { keys, children } => {
+                NODE_HEADER
+                    + keys.iter().map(entry_size).sum::<usize>()
+                    + children.len() * CHILD_SIZE
+            }
+            Self::Free { .. } => NODE_HEADER,
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(PAGE_SIZE);
+        let (kind, entries, next) = match self {
+            Self::Leaf { entries, next } => (1u8, entries.as_slice(), *next),
+            Self::Internal { keys, .. } => (2, keys.as_slice(), 0),
+            Self::Free { next } => (0, [].as_slice(), *next),
+        };
+        buf.push(kind);
+        buf.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        buf.extend_from_slice(&next.to_le_bytes());
+        for (key, record_id) in entries {
+            buf.extend_from_slice(&(key.len() as u16).to_le_bytes());
+            buf.extend_from_slice(key.as_bytes());
+            buf.extend_from_slice(&record_id.to_le_bytes());
+        }
+        if let Self::Internal { children, .. } = self {
+            for child in children {
+                buf.extend_from_slice(&child.to_le_bytes());
+            }
+        }
+        buf.resize(PAGE_SIZE, 0);
+        buf
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let corrupt = || Error::database("corrupt B+ tree index page");
+        let count = u16::from_le_bytes(bytes[1..3].try_into().expect("2 bytes")) as usize;
+        let next = u32::from_le_bytes(bytes[3..7].try_into().expect("4 bytes"));
+        let mut pos = NODE_HEADER;
+        let mut entries = Vec::with_capacity(count);
+        for _ in 0..count {
+            let len = bytes.get(pos..pos + 2).ok_or_else(corrupt)?;
+            let len = u16::from_le_bytes(len.try_into().expect("2 bytes")) as usize;
+            let key = bytes.get(pos + 2..pos + 2 + len).ok_or_else(corrupt)?;
+            let key = std::str::from_utf8(key).map_err(|_| corrupt())?.to_string();
+            let record_id = bytes
+                .get(pos + 2 + len..pos + ENTRY_OVERHEAD + len)
+                .ok_or_else(corrupt)?;
+            entries.push((
+                key,
+                u64::from_le_bytes(record_id.try_into().expect("8 bytes")),
+            ));
+            pos += ENTRY_OVERHEAD + len;
+        }
+        match bytes[0] {
+            0 => Ok(Self::Free { next }),
+            1 => Ok(Self::Leaf { entries, next }),
+            2 => {
+                let children = (0..=count)
+                    .map(|i| {
+                        let at = pos + i * CHILD_SIZE;
+                        let child = bytes.get(at..at + CHILD_SIZE).ok_or_else(corrupt)?;
+                        Ok(u32::from_le_bytes(child.try_into().expect("4 bytes")))
+                    })
+                    .collect::<Result<_>>()?;
+                Ok(Self::Internal {
+                    keys: entries,
+                    children,
+                })
+            }
+            _ => Err(corrupt()),
+        }
+    }
+
+    /// Splits an overfull node in two of about equal size; the left one keeps `next` unset
+    fn split(self) -> (Node, Entry, Node) {
+        match self {
+            Self::Leaf { mut entries, next } => {
+                let right = entries.split_off(byte_midpoint(&entries));
+                let separator = right[0].clone();
+                (
+                    Self::Leaf { entries, next: 0 },
+                    separator,
+                    Self::Leaf {
+                        entries: right,
+                        next,
+                    },
+                )
+            }
+            Self::Internal {
+                mut keys,
+                mut children,
+            } => {
+                let mid = byte_midpoint(&keys).min(keys.len().saturating_sub(2).max(1));
+                let right_keys = keys.split_off(mid + 1);
+                let separator = keys.pop().expect("separator at the midpoint");
+                let right_children = children.split_off(mid + 1);
+                (
+                    Self::Internal { keys, children },
+                    separator,
+                    Self::Internal {
+                        keys: right_keys,
+                        children: right_children,
+                    },
+                )
+            }
+            Self::Free { .. } => unreachable!("free pages are never split"),
+        }
+    }
+
+    /// Merges siblings `left` and `right` (separated by `separator` in their parent) into one
+    /// node when they fit on a page, and otherwise spreads their entries evenly over both
+    fn rebalance(left: Node, separator: Entry, right: Node) -> Result<Rebalanced> {
+        match (left, right) {
+            (
+                Self::Leaf {
+                    entries: mut left,
+                    next: left_next,
+                },
+                Self::Leaf {
+                    entries: right,
+                    next,
+                },
+            ) => {
+                left.extend(right);
+                let merged = Self::Leaf {
+                    entries: left,
+                    next,
+                };
+                if merged.size() <= PAGE_SIZE {
+                    return Ok(Rebalanced::Merged(merged));
+                }
+                let Self::Leaf {
+                    entries: mut left, ..
+                } = merged
+                else {
+                    unreachable!("built as a leaf")
+                };
+                let right = left.split_off(byte_midpoint(&left));
+                let separator = right[0].clone();
+                Ok(Rebalanced::Split(
+                    Self::Leaf {
+                        entries: left,
+                        next: left_next,
+                    },
+                    separator,
+                    Self::Leaf {
+                        entries: right,
+                        next,
+                    },
+                ))
+            }
+            (
+                Self::Internal {
+                    keys: mut left_keys,
+                    children: mut left_children,
+                },
+                Self::Internal { keys, children },
+            ) => {
+                left_keys.push(separator);
+                left_keys.extend(keys);
+                left_children.extend(children);
+                let merged = Self::Internal {
+                    keys: left_keys,
+                    children: left_children,
+                };
+                if merged.size() <= PAGE_SIZE {
+                    return Ok(Rebalanced::Merged(merged));
+                }
+                let (left, separator, right) = merged.split();
+                Ok(Rebalanced::Split(left, separator, right))
+            }
+            _ => Err(Error::database(
+                "corrupt B+ tree index: unbalanced siblings",
+            )),
+        }
+    }
+}
+
+/// Index header (page 0)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Header {
+    /// Page of the root node
+    root: u32,
+    /// Levels of nodes, leaves included
+    height: u32,
+    /// Pages allocated, the header and free pages included
+    pages: u32,
+    /// First page of the free page list (`0`: none)
+    free_list: u32,
+    /// `(key, record id)` pairs
+    entries: u64,
+    /// Distinct keys
+    keys: u64,
+    /// Caller's log sequence number of the last checkpoint
+    checkpoint_lsn: Option<u64>,
+    /// Changed since that checkpoint (the changes are only in memory)
+    modified: bool,
+}
+
+impl Default for Header {
+    /// An empty tree: an empty root leaf on page 1
+    fn default() -> Self {
+        Self {
+            root: 1,
+            height: 1,
+            pages: 2,
+            free_list: 0,
+            entries: 0,
+            keys: 0,
+            checkpoint_lsn: None,
+            modified: false,
+        }
+    }
+}
+
+impl Header {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(PAGE_SIZE);
+        buf.extend_from_slice(MAGIC);
+        buf.extend_from_slice(&self.root.to_le_bytes());
+        buf.extend_from_slice(&self.height.to_le_bytes());
+        buf.extend_from_slice(&self.pages.to_le_bytes());
+        buf.extend_from_slice(&self.free_list.to_le_bytes());
+        buf.extend_from_slice(&self.entries.to_le_bytes());
+        buf.extend_from_slice(&self.keys.to_le_bytes());
+        buf.extend_from_slice(&self.checkpoint_lsn.unwrap_or(0).to_le_bytes());
+        buf.push(u8::from(self.checkpoint_lsn.is_some()) | (u8::from(self.modified) << 1));
+        buf.resize(PAGE_SIZE, 0);
+        buf
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        if &bytes[0..8] != MAGIC {
+            return Err(Error::database("not a B+ tree index file"));
+        }
+        let u32_at = |pos: usize| u32::from_le_bytes(bytes[pos..pos + 4].try_into().expect("4"));
+        let u64_at = |pos: usize| u64::from_le_bytes(bytes[pos..pos + 8].try_into().expect("8"));
+        let flags = bytes[48];
+        Ok(Self {
+            root: u32_at(8),
+            height: u32_at(12),
+            pages: u32_at(16),
+            free_list: u32_at(20),
+            entries: u64_at(24),
+            keys: u64_at(32),
+            checkpoint_lsn: (flags & 1 != 0).then(|| u64_at(40)),
+            modified: flags & 2 != 0,
+        })
+    }
+}
+
+/// Files of a persistent tree
+#[derive(Debug)]
+struct Files {
+    index: File,
+    log: PageLog,
+}
+
+impl Files {
+    fn open(path: &Path) -> Result<Self> {
+        Ok(Self {
+            index: open_page_file(path)?,
+            log: PageLog::open(&companion(path, LOG_SUFFIX))?,
+        })
+    }
+
+    /// Reads page `n` (`0`: the header) into `buf`; `false` if the file does not reach it
+    fn read_page(&self, n: u32, buf: &mut [u8]) -> Result<bool> {
+        let mut file = &self.index;
+        file.seek(SeekFrom::Start(u64::from(n) * PAGE_SIZE as u64))?;
+        match file.read_exact(buf) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write_page(&self, n: u32, image: &[u8]) -> Result<()> {
+        let mut file = &self.index;
+        file.seek(SeekFrom::Start(u64::from(n) * PAGE_SIZE as u64))?;
+        file.write_all(image)?;
+        Ok(())
+    }
+
+    /// Finishes an interrupted checkpoint: writes the pages of a complete logged batch in place
+    /// and empties the log. Returns whether a batch was applied.
+    fn recover(&self) -> Result<bool> {
+        self.log.recover(|images| {
+            for ((_, n), image) in images {
+                self.write_page(n, image)?;
+            }
+            self.index.sync_data()?;
+            Ok(true)
+        })
+    }
+}
+
+/// B+ tree from string keys to record ids, on disk or in memory
+pub struct PagedBPlusTree {
+    path: Option<PathBuf>,
+    /// `None` for an in-memory tree
+    files: Option<Files>,
+    header: Header,
+    /// Header as written to disk
+    disk_header: Header,
+    /// Cached nodes by page; dirty ones are only written by checkpoints
+    pages: RefCell<HashMap<u32, Node>>,
+    dirty: HashSet<u32>,
+    statistics: RefCell<IndexStatistics>,
+}
+
+impl PagedBPlusTree {
+    /// Creates a tree that lives in memory only
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            files: None,
+            header: Header::default(),
+            disk_header: Header::default(),
+            pages: RefCell::new(HashMap::new()),
+            dirty: HashSet::new(),
+            statistics: RefCell::new(IndexStatistics::default()),
+        }
+    }
+
+    /// Opens the tree stored at `path`, creating an empty one if there is none, and finishes
+    /// an interrupted checkpoint
+    pub fn open(path: &Path) -> Result<Self> {
+        let files = Files::open(path)?;
+        if files.recover()? {
+            tracing::info!(path = %path.display(), "B+ tree index checkpoint finished from its log");
+        }
+        let mut buf = vec![0; PAGE_SIZE];
+        let header = if files.read_page(0, &mut buf)? {
+            Header::decode(&buf)?
+        } else {
+            let header = Header::default();
+            files.write_page(0, &header.encode())?;
+            files.index.sync_data()?;
+            header
+        };
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            files: Some(files),
+            header,
+            disk_header: header,
+            ..Self::in_memory()
+        })
+    }
+
+    /// Files of the tree at `path`
+    fn files(path: &Path) -> [PathBuf; 2] {
+        [path.to_path_buf(), companion(path, LOG_SUFFIX)]
+    }
+
+    /// Deletes the files of the tree at `path` (those that exist)
+    pub fn remove_files(path: &Path) -> Result<()> {
+        for file in Self::files(path) {
+            match std::fs::remove_file(&file) {
+                Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Location of the index file (`None` in memory)
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Log sequence number of the checkpoint the file holds (`None`: never checkpointed)
+    pub fn checkpoint_lsn(&self) -> Option<u64> {
+        self.disk_header.checkpoint_lsn
+    }
+
+    /// Whether the tree changed after its last checkpoint, in this process or before a crash
+    pub fn modified_since_checkpoint(&self) -> bool {
+        self.disk_header.modified
+    }
+
+    /// Writes the changed pages durably and records `lsn` as the checkpoint's sequence number
+    pub fn checkpoint(&mut self, lsn: u64) -> Result<()> {
+        self.header.checkpoint_lsn = Some(lsn);
+        self.header.modified = false;
+        let Some(files) = &self.files else {
+            self.disk_header = self.header;
+            return Ok(());
+        };
+        let mut dirty: Vec<u32> = self.dirty.iter().copied().collect();
+        dirty.sort_unstable();
+        let images: Vec<(ImageLoc, Vec<u8>)> = {
+            let pages = self.pages.borrow();
+            std::iter::once(((LOG_HEADER, 0), self.header.encode()))
+                .chain(
+                    dirty
+                        .into_iter()
+                        .map(|n| ((LOG_NODE, n), pages[&n].encode())),
+                )
+                .collect()
+        };
+        let logged: Vec<(ImageLoc, &[u8])> = images
+            .iter()
+            .map(|(loc, image)| (*loc, image.as_slice()))
+            .collect();
+        files.log.write_batch(&logged)?;
+        for ((_, n), image) in &images {
+            files.write_page(*n, image)?;
+        }
+        files.index.sync_data()?;
+        files.log.clear()?;
+        self.dirty.clear();
+        self.disk_header = self.header;
+        Ok(())
+    }
+
+    /// Removes every entry and shrinks the file back to an empty, never checkpointed tree
+    pub fn clear(&mut self) -> Result<()> {
+        self.header = Header::default();
+        self.pages.get_mut().clear();
+        self.dirty.clear();
+        if let Some(files) = &self.files {
+            files.log.clear()?;
+            files.index.set_len(0)?;
+            files.write_page(0, &self.header.encode())?;
+            files.index.sync_data()?;
+        }
+        self.disk_header = self.header;
+        Ok(())
+    }
+
+    /// Levels of nodes, leaves included
+    pub fn height(&self) -> u32 {
+        self.header.height
+    }
+
+    /// Pages of the file, the header and free pages included
+    pub fn page_count(&self) -> u32 {
+        self.header.pages
+    }
+
+    /// Bytes of the tree's files on disk (`0` in memory)
+    pub fn file_size(&self) -> Result<u64> {
+        let Some(path) = &self.path else {
+            return Ok(0);
+        };
+        file_sizes(&Self::files(path))
+    }
+
+    /// Pages of the tree and the free space on them: free-list pages are free as a whole,
+    /// the unused bytes of nodes are fragmented
+    pub fn space_usage(&self) -> Result<SpaceUsage> {
+        let mut usage = SpaceUsage {
+            pages: u64::from(self.header.pages),
+            ..SpaceUsage::default()
+        };
+        for n in 1..self.header.pages {
+            let unused = self.with_node(n, |node| match node {
+                Node::Free { .. } => None,
+                node => Some(PAGE_SIZE - node.size()),
+            })?;
+            usage.free_bytes += unused.unwrap_or(PAGE_SIZE) as u64;
+            usage.fragmented_bytes += unused.unwrap_or(0) as u64;
+        }
+        Ok(usage)
+    }
+
+    /// Operation counters, key count and height
+    pub fn statistics(&self) -> IndexStatistics {
+        let mut s = self.statistics.borrow().clone();
+        s.total_elements = self.header.keys;
+        s.depth = self.header.height;
+        s
+    }
+
+    /// Runs `f` on the node of page `n`, reading it into the cache first if needed
+    fn with_node<R>(&self, n: u32, f: impl FnOnce(&Node) -> R) -> Result<R> {
+        if let Some(node) = self.pages.borrow().get(&n) {
+            return Ok(f(node));
+        }
+        let node = match &self.files {
+            Some(files) => {
+                let mut buf = vec![0; PAGE_SIZE];
+                if files.read_page(n, &mut buf)? {
+                    Node::decode(&buf)?
+                } else {
+                    Node::empty_leaf()
+                }
+            }
+            None => Node::empty_leaf(),
+        };
+        let out = f(&node);
+        let mut pages = self.pages.borrow_mut();
+        if self.files.is_some() && pages.len() >= CACHED_PAGES {
+            pages.retain(|n, _| self.dirty.contains(n));
+        }
+        pages.insert(n, node);
+        Ok(out)
+    }
+
+    fn node(&self, n: u32) -> Result<Node> {
+        self.with_node(n, Node::clone)
+    }
+
+    fn put_node(&mut self, n: u32, node: Node) {
+        self.pages.get_mut().insert(n, node);
+        self.dirty.insert(n);
+    }
+
+    fn allocate_page(&mut self) -> Result<u32> {
+        let head = self.header.free_list;
+        if head != 0 {
+            self.header.free_list = self.with_node(head, |node| match node {
+                Node::Free { next } => Ok(*next),
+                _ => Err(Error::database("corrupt B+ tree index: free list")),
+            })??;
+            return Ok(head);
+        }
+        self.header.pages += 1;
+        Ok(self.header.pages - 1)
+    }
+
+    fn free_page(&mut self, n: u32) {
+        let next = self.header.free_list;
+        self.put_node(n, Node::Free { next });
+        self.header.free_list = n;
+    }
+
+    /// Leaf where the entries at or after `target` start
+    fn find_leaf(&self, target: &Entry) -> Result<u32> {
+        let mut n = self.header.root;
+        loop {
+            let child = self.with_node(n, |node| match node {
+                Node::Internal { keys, children } => {
+                    Ok(Some(children[keys.partition_point(|k| k <= target)]))
+                }
+                Node::Leaf { .. } => Ok(None),
+                Node::Free { .. } => Err(Error::database("corrupt B+ tree index: free node")),
+            })??;
+            match child {
+                Some(child) => n = child,
+                None => return Ok(n),
+            }
+        }
+    }
+
+    /// Passes the entries from `target` on, in order, to `visit` until it returns `false`
+    fn scan_from(&self, target: &Entry, mut visit: impl FnMut(&Entry) -> bool) -> Result<()> {
+        let mut n = self.find_leaf(target)?;
+        loop {
+            let next = self.with_node(n, |node| {
+                let Node::Leaf { entries, next } = node else {
+                    return Err(Error::database("corrupt B+ tree index: leaf chain"));
+                };
+                let start = entries.partition_point(|e| e < target);
+                for entry in &entries[start..] {
+                    if !visit(entry) {
+                        return Ok(0);
+                    }
+                }
+                Ok(*next)
+            })??;
+            if next == 0 {
+                return Ok(());
+            }
+            n = next;
+        }
+    }
+
+    /// Record ids of `key`, ascending
+    fn ids_of(&self, key: &str) -> Result<Vec<RecordId>> {
+        let mut ids = Vec::new();
+        self.scan_from(&(key.to_string(), 0), |(k, id)| {
+            let matches = k == key;
+            if matches {
+                ids.push(*id);
+            }
+            matches
+        })?;
+        Ok(ids)
+    }
+
+    fn insert_entry(&mut self, entry: Entry) -> Result<()> {
+        let root = self.header.root;
+        if let Some((separator, right)) = self.insert_into(root, entry)? {
+            let new_root = self.allocate_page()?;
+            self.put_node(
+                new_root,
+                Node::Internal {
+                    keys: vec![separator],
+                    children: vec![root, right],
+                },
+            );
+            self.header.root = new_root;
+            self.header.height += 1;
+        }
+        Ok(())
+    }
+
+    /// Inserts `entry` under page `n`; returns the separator and page of the new right sibling
+    /// when the node split
+    fn insert_into(&mut self, n: u32, entry: Entry) -> Result<Option<(Entry, u32)>> {
+        let mut node = self.node(n)?;
+        match &mut node {
+            Node::Leaf { entries, .. } => match entries.binary_search(&entry) {
+                Ok(_) => return Ok(None),
+                Err(pos) => entries.insert(pos, entry),
+            },
+            Node::Internal { keys, children } => {
+                let i = keys.partition_point(|k| k <= &entry);
+                let Some((separator, right)) = self.insert_into(children[i], entry)? else {
+                    return Ok(None);
+                };
+                keys.insert(i, separator);
+                children.insert(i + 1, right);
+            }
+            Node::Free { .. } => return Err(Error::database("corrupt B+ tree index: free node")),
+        }
+        if node.size() <= PAGE_SIZE {
+            self.put_node(n, node);
+            return Ok(None);
+        }
+        let (mut left, separator, right) = node.split();
+        let right_page = self.allocate_page()?;
+        if let Node::Leaf { next, .. } = &mut left {
+            *next = right_page;
+        }
+        self.put_node(n, left);
+        self.put_node(right_page, right);
+        Ok(Some((separator, right_page)))
+    }
+
+    fn delete_entry(&mut self, entry: &Entry) -> Result<bool> {
+        let removed = self.delete_from(self.header.root, entry)?;
+        // A root left with a single child gives way to it.
+        while let Node::Internal { keys, children } = self.node(self.header.root)? {
+            if !keys.is_empty() {
+                break;
+            }
+            let old_root = self.header.root;
+            self.header.root = children[0];
+            self.header.height -= 1;
+            self.free_page(old_root);
+        }
+        Ok(removed)
+    }
+
+    /// Deletes `entry` under page `n`, rebalancing the child it was deleted from when that
+    /// child is left under [`MIN_FILL`]
+    fn delete_from(&mut self, n: u32, entry: &Entry) -> Result<bool> {
+        let mut node = self.node(n)?;
+        match &mut node {
+            Node::Leaf { entries, .. } => {
+                let Ok(pos) = entries.binary_search(entry) else {
+                    return Ok(false);
+                };
+                entries.remove(pos);
+                self.put_node(n, node);
+                Ok(true)
+            }
+            Node::Internal { keys, children } => {
+                let i = keys.partition_point(|k| k <= entry);
+                if !self.delete_from(children[i], entry)? {
+                    return Ok(false);
+                }
+                if children.len() > 1 && self.with_node(children[i], Node::size)? < MIN_FILL {
+                    let left = i.saturating_sub(1);
+                    let right = left + 1;
+                    let separator = keys[left].clone();
+                    let rebalanced = Node::rebalance(
+                        self.node(children[left])?,
+                        separator,
+                        self.node(children[right])?,
+                    )?;
+                    match rebalanced {
+                        Rebalanced::Merged(merged) => {
+                            self.put_node(children[left], merged);
+                            self.free_page(children[right]);
+                            keys.remove(left);
+                            children.remove(right);
+                        }
+                        Rebalanced::Split(left_node, separator, right_node) => {
+                            self.put_node(children[left], left_node);
+                            self.put_node(children[right], right_node);
+                            keys[left] = separator;
+                        }
+                    }
+                    self.put_node(n, node);
+                }
+                Ok(true)
+            }
+            Node::Free { .. } => Err(Error::database("corrupt B+ tree index: free node")),
+        }
+    }
+
+    /// Records on disk that the tree changed after its checkpoint, before the first change
+    fn mark_modified(&mut self) -> Result<()> {
+        if self.disk_header.modified {
+            return Ok(());
+        }
+        let mut header = self.disk_header;
+        header.modified = true;
+        if let Some(files) = &self.files {
+            files.write_page(0, &header.encode())?;
+            files.index.sync_data()?;
+        }
+        self.disk_header = header;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for PagedBPlusTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PagedBPlusTree")
+            .field("path", &self.path)
+            .field("height", &self.header.height)
+            .field("entries", &self.header.entries)
+            .field("dirty_pages", &self.dirty.len())
+            .finish()
+    }
+}
+
+impl Index for PagedBPlusTree {
+    type Key = String;
+    type Value = Vec<RecordId>;
+
+    /// Replaces the record ids of `key`; an empty list removes the key
+    fn insert(&mut self, key: String, value: Vec<RecordId>) -> Result<()> {
+        self.statistics.get_mut().insert_operations += 1;
+        if key.len() > MAX_KEY_LEN {
+            return Err(Error::validation(format!(
+                "B+ tree index key of {} bytes exceeds the {MAX_KEY_LEN}-byte limit",
+                key.len()
+            )));
+        }
+        let old = self.ids_of(&key)?;
+        let mut new = value;
+        new.sort_unstable();
+        new.dedup();
+        if old == new {
+            return Ok(());
+        }
+        self.mark_modified()?;
+        for id in old.iter().filter(|id| new.binary_search(id).is_err()) {
+            self.delete_entry(&(key.clone(), *id))?;
+        }
+        for id in new.iter().filter(|id| old.binary_search(id).is_err()) {
+            self.insert_entry((key.clone(), *id))?;
+        }
+        if old.is_empty() {
+            self.header.keys += 1;
+        } else if new.is_empty() {
+            self.header.keys -= 1;
+        }
+        self.header.entries = self.header.entries - old.len() as u64 + new.len() as u64;
+        Ok(())
+    }
+
+    fn search(&self, key: &String) -> Result<Option<Vec<RecordId>>> {
+        self.statistics.borrow_mut().search_operations += 1;
+        let ids = self.ids_of(key)?;
+        Ok((!ids.is_empty()).then_some(ids))
+    }
+
+    fn delete(&mut self, key: &String) -> Result<bool> {
+        self.statistics.get_mut().delete_operations += 1;
+        let ids = self.ids_of(key)?;
+        if ids.is_empty() {
+            return Ok(false);
+        }
+        self.mark_modified()?;
+        for id in &ids {
+            self.delete_entry(&(key.clone(), *id))?;
+        }
+        self.header.entries -= ids.len() as u64;
+        self.header.keys -= 1;
+        Ok(true)
+    }
+
+    fn range_search(&self, start: &String, end: &String) -> Result<Vec<(String, Vec<RecordId>)>> {
+        self.statistics.borrow_mut().range_search_operations += 1;
+        let mut found: Vec<(String, Vec<RecordId>)> = Vec::new();
+        if start > end {
+            return Ok(found);
+        }
+        self.scan_from(&(start.clone(), 0), |(key, id)| {
+            if key > end {
+                return false;
+            }
+            match found.last_mut() {
+                Some((last, ids)) if last == key => ids.push(*id),
+                _ => found.push((key.clone(), vec![*id])),
+            }
+            true
+        })?;
+        Ok(found)
+    }
+
+    fn size(&self) -> usize {
+        self.header.keys as usize
+    }
+}
+
+impl PersistentIndex for PagedBPlusTree {
+    fn checkpoint_lsn(&self) -> Option<u64> {
+        PagedBPlusTree::checkpoint_lsn(self)
+    }
+
+    fn modified_since_checkpoint(&self) -> bool {
+        PagedBPlusTree::modified_since_checkpoint(self)
+    }
+
+    fn checkpoint(&mut self, lsn: u64) -> Result<()> {
+        PagedBPlusTree::checkpoint(self, lsn)
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        PagedBPlusTree::clear(self)
+    }
+
+    fn file_size(&self) -> Result<u64> {
+        PagedBPlusTree::file_size(self)
+    }
+
+    fn space_usage(&self) -> Result<SpaceUsage> {
+        PagedBPlusTree::space_usage(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn key(i: u64) -> String {
+        format!("key-{i:05}")
+    }
+
+    #[test]
+    fn test_splits_and_merges_keep_the_tree_balanced() {
+        // Long keys make for small fanouts and a deep tree.
+        let key = |i: u64| format!("{}{:>40}", key(i), "");
+        let mut tree = PagedBPlusTree::in_memory();
+        for i in 0..3_000 {
+            tree.insert(key(i), vec![i, i + 10_000]).unwrap();
+        }
+        assert!(tree.height() >= 3, "height {}", tree.height());
+        let pages = tree.page_count();
+
+        for i in 0..2_990 {
+            assert!(tree.delete(&key(i)).unwrap());
+        }
+        assert_eq!(tree.height(), 1);
+        assert_eq!(tree.size(), 10);
+        assert_eq!(tree.search(&key(2_995)).unwrap(), Some(vec![2_995, 12_995]));
+
+        // Freed pages are reused before the file grows.
+        for i in 0..3_000 {
+            tree.insert(key(i), vec![i]).unwrap();
+        }
+        assert_eq!(tree.page_count(), pages);
+    }
+
+    #[test]
+    fn test_logged_batch_is_applied_on_open() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("t.idx.bpt");
+        {
+            let mut tree = PagedBPlusTree::open(&path).unwrap();
+            tree.insert(key(1), vec![10]).unwrap();
+            tree.checkpoint(5).unwrap();
+            tree.insert(key(2), vec![20, 21]).unwrap();
+            // Crash after logging the second checkpoint, before any page is written in place.
+            tree.header.checkpoint_lsn = Some(9);
+            tree.header.modified = false;
+            let pages = tree.pages.borrow();
+            let images: Vec<(ImageLoc, Vec<u8>)> =
+                std::iter::once(((LOG_HEADER, 0), tree.header.encode()))
+                    .chain(
+                        tree.dirty
+                            .iter()
+                            .map(|n| ((LOG_NODE, *n), pages[n].encode())),
+                    )
+                    .collect();
+            let logged: Vec<(ImageLoc, &[u8])> =
+                images.iter().map(|(l, i)| (*l, i.as_slice())).collect();
+            tree.files
+                .as_ref()
+                .unwrap()
+                .log
+                .write_batch(&logged)
+                .unwrap();
+        }
+        let tree = PagedBPlusTree::open(&path).unwrap();
+        assert_eq!(tree.checkpoint_lsn(), Some(9));
+        assert_eq!(tree.search(&key(2)).unwrap(), Some(vec![20, 21]));
+        assert_eq!(tree.size(), 2);
+    }
+
+    #[test]
+    fn test_changes_after_the_checkpoint_are_lost_on_reopen() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("t.idx.bpt");
+        {
+            let mut tree = PagedBPlusTree::open(&path).unwrap();
+            tree.insert(key(1), vec![10]).unwrap();
+            tree.checkpoint(5).unwrap();
+            tree.insert(key(2), vec![20]).unwrap();
+        }
+        let tree = PagedBPlusTree::open(&path).unwrap();
+        assert_eq!(tree.checkpoint_lsn(), Some(5));
+        assert!(tree.modified_since_checkpoint());
+        assert_eq!(tree.search(&key(1)).unwrap(), Some(vec![10]));
+        assert_eq!(tree.search(&key(2)).unwrap(), None);
+    }
+}
@@ -2,20 +2,185 @@
 //!
 //! Manages B+ tree indexes for tables. Indexes map column values to RecordIds
 //! for efficient lookups during SELECT.
+//!
+//! A *partial* index (`CREATE INDEX ... WHERE predicate`) only has entries for the rows matching
+//! its predicate. The registry does not evaluate predicates: callers mark the partial indexes a
+//! row matches in its column values under [`IndexRegistry::partial_index_key`], and maintenance
+//! skips the partial indexes a row's values do not mark.
+//!
+//! An *expression* index (`CREATE INDEX ... (lower(email))`) has computed key parts. Each is
+//! named in the key columns by its [`Expression::index_key`], and callers put its value for a
+//! row in the row's column values under that name, like a stored column.
+//!
+//! With a storage directory ([`IndexRegistry::with_storage_dir`]) indexes live in files there and
+//! survive restarts once checkpointed (see [`PersistentIndex`]): a B+ tree index is a
+//! [`PagedBPlusTree`] in `<table>.<index>.bpt`, a *hash* index (`CREATE INDEX ... USING HASH`) a
+//! [`LinearHashIndex`] in `<table>.<index>.lhx`. Without one they are kept in memory. A hash index
+//! only answers equalities on its whole key.
+//!
+//! Keys are built from the text of each column value (see [`index_key`]): the typed value it
+//! stands for, in the order-preserving encoding of [`crate::common::key_encoding`], so the keys
+//! of an index sort like its values (`9` before `10`) and a key of a prefix of the columns is a
+//! prefix of the full keys.
 
-use crate::common::types::RecordId;
+use crate::common::key_encoding;
+use crate::common::types::{ColumnValue, DataType, RecordId};
 use crate::common::{Error, Result};
-use crate::storage::index::{BPlusTree, Index};
+use crate::parser::ast::{Expression, IndexMethod};
+use crate::storage::index::{BPlusTree, Index, LinearHashIndex, PagedBPlusTree, PersistentIndex};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
+/// Row change to an index that is being built (see [`IndexRegistry::begin_index_build`])
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexChange {
+    Insert { key: String, record_id: RecordId },
+    Delete { key: String, record_id: RecordId },
+}
+
+impl IndexChange {
+    /// Applies the change to `index`; inserting an entry that is already present, or deleting
+    /// one that is not, leaves the index unchanged
+    pub fn apply_to<I>(&self, index: &mut I) -> Result<()>
+    where
+        I: Index<Key = String, Value = Vec<RecordId>> + ?Sized,
+    {
+        match self {
+            Self::Insert { key, record_id } => {
+                let mut ids = index.search(key)?.unwrap_or_default();
+                if !ids.contains(record_id) {
+                    ids.push(*record_id);
+                    index.insert(key.clone(), ids)?;
+                }
+            }
+            Self::Delete { key, record_id } => {
+                if let Some(mut ids) = index.search(key)? {
+                    ids.retain(|id| id != record_id);
+                    if ids.is_empty() {
+                        index.delete(key)?;
+                    } else {
+                        index.insert(key.clone(), ids)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Changes recorded for an index build, oldest first
+pub type IndexChangeLog = Arc<Mutex<Vec<IndexChange>>>;
+
+/// How an index is stored and what it computes from a row besides its stored column values
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IndexOptions {
+    /// Access method: B+ tree or hash
+    pub method: IndexMethod,
+    /// `WHERE` predicate of a partial index: only rows matching it have entries
+    pub predicate: Option<Expression>,
+    /// Computed key parts (expression index) by their name in the index columns
+    pub expressions: Vec<(String, Expression)>,
+}
+
+/// Storage of a secondary index: key = serialized column values, value = record ids
+#[derive(Debug)]
+pub enum SecondaryIndex {
+    BTree(BPlusTree<String, Vec<RecordId>>),
+    PagedBTree(Box<PagedBPlusTree>),
+    Hash(Box<LinearHashIndex>),
+}
+
+impl SecondaryIndex {
+    /// The index as one kept in files, if it is
+    pub fn as_persistent_mut(
+        &mut self,
+    ) -> Option<&mut dyn PersistentIndex<Key = String, Value = Vec<RecordId>>> {
+        match self {
+            Self::PagedBTree(index) => Some(index.as_mut()),
+            Self::Hash(index) => Some(index.as_mut()),
+            Self::BTree(_) => None,
+        }
+    }
+
+    /// Location of the index's file (`None` in memory)
+    fn path(&self) -> Option<&std::path::Path> {
+        match self {
+            Self::PagedBTree(index) => index.path(),
+            Self::Hash(index) => index.path(),
+            Self::BTree(_) => None,
+        }
+    }
+}
+
+impl From<BPlusTree<String, Vec<RecordId>>> for SecondaryIndex {
+    fn from(tree: BPlusTree<String, Vec<RecordId>>) -> Self {
+        Self::BTree(tree)
+    }
+}
+
+impl Index for SecondaryIndex {
+    type Key = String;
+    type Value = Vec<RecordId>;
+
+    fn insert(&mut self, key: String, value: Vec<RecordId>) -> Result<()> {
+        match self {
+            Self::BTree(index) => index.insert(key, value),
+            Self::PagedBTree(index) => index.insert(key, value),
+            Self::Hash(index) => index.insert(key, value),
+        }
+    }
+
+    fn search(&self, key: &String) -> Result<Option<Vec<RecordId>>> {
+        match self {
+            Self::BTree(index) => index.search(key),
+            Self::PagedBTree(index) => index.search(key),
+            Self::Hash(index) => index.search(key),
+        }
+    }
+
+    fn delete(&mut self, key: &String) -> Result<bool> {
+        match self {
+            Self::BTree(index) => index.delete(key),
+            Self::PagedBTree(index) => index.delete(key),
+            Self::Hash(index) => index.delete(key),
+        }
+    }
+
+    fn range_search(&self, start: &String, end: &String) -> Result<Vec<(String, Vec<RecordId>)>> {
+        match self {
+            Self::BTree(index) => index.range_search(start, end),
+            Self::PagedBTree(index) => index.range_search(start, end),
+            Self::Hash(index) => index.range_search(start, end),
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Self::BTree(index) => index.size(),
+            Self::PagedBTree(index) => index.size(),
+            Self::Hash(index) => index.size(),
+        }
+    }
+}
+
+/// Index being built outside the registry; row changes to its table are logged for it
+#[derive(Debug, Clone)]
+struct IndexBuild {
+    columns: Vec<String>,
+    options: IndexOptions,
+    changes: IndexChangeLog,
+}
+
 /// Entry for a single index
 #[derive(Debug, Clone)]
 pub struct IndexEntry {
-    /// Indexed column names
+    /// Indexed column names (and names of computed key parts)
     pub columns: Vec<String>,
-    /// B+ tree: key = serialized column value, value = list of record IDs
-    pub index: Arc<Mutex<BPlusTree<String, Vec<RecordId>>>>,
+    /// Partial index predicate and key expressions
+    pub options: IndexOptions,
+    /// B+ tree or hash index: key = serialized column value, value = list of record IDs
+    pub index: Arc<Mutex<SecondaryIndex>>,
 }
 
 /// Registry of indexes per table
@@ -23,6 +188,10 @@ pub struct IndexEntry {
 pub struct IndexRegistry {
     /// (table_name, index_name) -> IndexEntry
     indexes: HashMap<(String, String), IndexEntry>,
+    /// (table_name, index_name) -> index build in progress
+    builds: HashMap<(String, String), IndexBuild>,
+    /// Directory of the index files (`None`: indexes live in memory)
+    storage_dir: Option<PathBuf>,
 }
 
 impl IndexRegistry {
@@ -30,6 +199,67 @@ impl IndexRegistry {
     pub fn new() -> Self {
         Self {
             indexes: HashMap::new(),
+            builds: HashMap::new(),
+            storage_dir: None,
+        }
+    }
+
+    /// Creates a registry keeping indexes in files under `dir`.
+    pub fn with_storage_dir(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            storage_dir: Some(dir.into()),
+            ..Self::new()
+        }
+    }
+
+    /// File of the index `index_name` on `table_name`, when indexes are stored
+    fn index_path(
+        &self,
+        table_name: &str,
+        index_name: &str,
+        method: IndexMethod,
+    ) -> Option<PathBuf> {
+        let extension = match method {
+            IndexMethod::BTree => "bpt",
+            IndexMethod::Hash => "lhx",
+        };
+        self.storage_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{table_name}.{index_name}.{extension}")))
+    }
+
+    /// Opens the stored index at `path`, deleting earlier files there first unless `reopen`
+    fn open_stored_index(method: IndexMethod, path: &Path, reopen: bool) -> Result<SecondaryIndex> {
+        Ok(match method {
+            IndexMethod::BTree => {
+                if !reopen {
+                    PagedBPlusTree::remove_files(path)?;
+                }
+                SecondaryIndex::PagedBTree(Box::new(PagedBPlusTree::open(path)?))
+            }
+            IndexMethod::Hash => {
+                if !reopen {
+                    LinearHashIndex::remove_files(path)?;
+                }
+                SecondaryIndex::Hash(Box::new(LinearHashIndex::open(path)?))
+            }
+        })
+    }
+
+    /// Deletes the files of a dropped index
+    fn remove_index_files(entry: &IndexEntry) {
+        let Ok(index) = entry.index.lock() else {
+            return;
+        };
+        let Some(path) = index.path() else {
+            return;
+        };
+        let removed = match &*index {
+            SecondaryIndex::PagedBTree(_) => PagedBPlusTree::remove_files(path),
+            _ => LinearHashIndex::remove_files(path),
+        };
+        if let Err(e) = removed {
+            tracing::warn!(path = %path.display(), error = %e, "cannot remove index files");
         }
     }
 
@@ -40,7 +270,14 @@ impl IndexRegistry {
 
     /// Removes every index defined on `table_name` (e.g. after `DROP TABLE`).
     pub fn remove_all_indexes_for_table(&mut self, table_name: &str) {
-        self.indexes.retain(|(t, _), _| t.as_str() != table_name);
+        self.indexes.retain(|(t, _), entry| {
+            let keep = t.as_str() != table_name;
+            if !keep {
+                Self::remove_index_files(entry);
+            }
+            keep
+        });
+        self.builds.retain(|(t, _), _| t.as_str() != table_name);
     }
 
     /// Creates and registers a new index
@@ -49,20 +286,63 @@ impl IndexRegistry {
         table_name: &str,
         index_name: &str,
         columns: Vec<String>,
+    ) -> Result<()> {
+        self.create_index_with_options(table_name, index_name, columns, IndexOptions::default())
+    }
+
+    /// Creates and registers a new hash, partial and/or expression index; a stored index starts
+    /// empty even if files of an earlier index with its name remain
+    pub fn create_index_with_options(
+        &mut self,
+        table_name: &str,
+        index_name: &str,
+        columns: Vec<String>,
+        options: IndexOptions,
+    ) -> Result<()> {
+        self.register_index(table_name, index_name, columns, options, false)
+    }
+
+    /// Registers an index defined earlier (after a restart): a stored index opens its files as
+    /// left by its last checkpoint (see [`PersistentIndex::checkpoint_lsn`]), which the caller
+    /// brings up to date or clears and rebuilds. Indexes kept in memory start empty.
+    pub fn open_index_with_options(
+        &mut self,
+        table_name: &str,
+        index_name: &str,
+        columns: Vec<String>,
+        options: IndexOptions,
+    ) -> Result<()> {
+        self.register_index(table_name, index_name, columns, options, true)
+    }
+
+    fn register_index(
+        &mut self,
+        table_name: &str,
+        index_name: &str,
+        columns: Vec<String>,
+        options: IndexOptions,
+        reopen: bool,
     ) -> Result<()> {
         let key = (table_name.to_string(), index_name.to_string());
-        if self.indexes.contains_key(&key) {
+        if self.indexes.contains_key(&key) || self.builds.contains_key(&key) {
             return Err(Error::validation(format!(
                 "Index {} already exists on table {}",
                 index_name, table_name
             )));
         }
 
-        let index = BPlusTree::new_default();
+        let index = match self.index_path(table_name, index_name, options.method) {
+            Some(path) => Self::open_stored_index(options.method, &path, reopen)?,
+            None => match options.method {
+                IndexMethod::BTree => SecondaryIndex::BTree(BPlusTree::new_default()),
+                IndexMethod::Hash => SecondaryIndex::Hash(Box::new(LinearHashIndex::in_memory())),
+            },
+        };
         self.indexes.insert(
             key,
             IndexEntry {
                 columns: columns.clone(),
+                options,
                 index: Arc::new(Mutex::new(index)),
             },
         );
@@ -72,11 +352,127 @@ impl IndexRegistry {
     /// Drops an index
     pub fn drop_index(&mut self, table_name: &str, index_name: &str) -> Result<()> {
         let key = (table_name.to_string(), index_name.to_string());
-        if self.indexes.remove(&key).is_none() {
+        let Some(entry) = self.indexes.remove(&key) else {
             return Err(Error::validation(format!(
                 "Index {} not found on table {}",
                 index_name, table_name
             )));
+        };
+        Self::remove_index_files(&entry);
+        Ok(())
+    }
+
+    /// Reserves `index_name` for an index built outside the registry: until
+    /// [`Self::finish_index_build`], row changes to `table_name` are appended to the returned
+    /// log instead of being applied to an index. Only changes to rows matching its predicate are
+    /// logged for a partial index.
+    pub fn begin_index_build(
+        &mut self,
+        table_name: &str,
+        index_name: &str,
+        columns: Vec<String>,
+        options: IndexOptions,
+    ) -> Result<IndexChangeLog> {
+        let key = (table_name.to_string(), index_name.to_string());
+        if self.indexes.contains_key(&key) || self.builds.contains_key(&key) {
+            return Err(Error::validation(format!(
+                "Index {} already exists on table {}",
+                index_name, table_name
+            )));
+        }
+        if options.method == IndexMethod::Hash {
+            return Err(Error::validation(
+                "hash indexes cannot be built concurrently",
+            ));
+        }
+        let changes = IndexChangeLog::default();
+        self.builds.insert(
+            key,
+            IndexBuild {
+                columns,
+                options,
+                changes: changes.clone(),
+            },
+        );
+        Ok(changes)
+    }
+
+    /// Empty index for the build of `index_name` on `table_name` to fill: a new stored B+ tree
+    /// when indexes are stored, an in-memory one otherwise
+    pub fn new_build_index(&self, table_name: &str, index_name: &str) -> Result<SecondaryIndex> {
+        match self.index_path(table_name, index_name, IndexMethod::BTree) {
+            Some(path) => Self::open_stored_index(IndexMethod::BTree, &path, false),
+            None => Ok(BPlusTree::new_default().into()),
+        }
+    }
+
+    /// Registers the built `index`, applying the changes still in its log first
+    pub fn finish_index_build(
+        &mut self,
+        table_name: &str,
+        index_name: &str,
+        index: impl Into<SecondaryIndex>,
+    ) -> Result<()> {
+        let mut index = index.into();
+        let key = (table_name.to_string(), index_name.to_string());
+        let build = self.builds.remove(&key).ok_or_else(|| {
+            Error::validation(format!(
+                "Index {} is not being built on table {}",
+                index_name, table_name
+            ))
+        })?;
+        let changes = std::mem::take(
+            &mut *build
+                .changes
+                .lock()
+                .map_err(|_| Error::internal("Lock poisoned"))?,
+        );
+        for change in &changes {
+            change.apply_to(&mut index)?;
+        }
+        self.indexes.insert(
+            key,
+            IndexEntry {
+                columns: build.columns,
+                options: build.options,
+                index: Arc::new(Mutex::new(index)),
+            },
+        );
+        Ok(())
+    }
+
+    /// Drops an index build started by [`Self::begin_index_build`]
+    pub fn abort_index_build(&mut self, table_name: &str, index_name: &str) {
+        self.builds
+            .remove(&(table_name.to_string(), index_name.to_string()));
+    }
+
+    /// Columns of the indexes being built on `table_name`
+    pub fn list_index_builds_for_table(&self, table_name: &str) -> Vec<(String, Vec<String>)> {
+        self.builds
+            .iter()
+            .filter(|((t, _), _)| t == table_name)
+            .map(|((_, idx_name), build)| (idx_name.clone(), build.columns.clone()))
+            .collect()
+    }
+
+    /// Appends the change `change(key)` to the log of every index build on `table_name`
+    fn log_index_build_changes(
+        &self,
+        table_name: &str,
+        column_values: &HashMap<String, String>,
+        change: impl Fn(String) -> IndexChange,
+    ) -> Result<()> {
+        for ((_, name), build) in self.builds.iter().filter(|((t, _), _)| t == table_name) {
+            if !Self::covers(name, &build.options.predicate, column_values) {
+                continue;
+            }
+            let key = Self::build_index_key(&build.columns, column_values)?;
+            build
+                .changes
+                .lock()
+                .map_err(|_| Error::internal("Lock poisoned"))?
+                .push(change(key));
         }
         Ok(())
     }
@@ -86,7 +482,7 @@ impl IndexRegistry {
         &self,
         table_name: &str,
         index_name: &str,
-    ) -> Option<Arc<Mutex<BPlusTree<String, Vec<RecordId>>>>> {
+    ) -> Option<Arc<Mutex<SecondaryIndex>>> {
         let key = (table_name.to_string(), index_name.to_string());
         self.indexes.get(&key).map(|e| e.index.clone())
     }
@@ -97,6 +493,83 @@ impl IndexRegistry {
         self.indexes.get(&key)
     }
 
+    /// Key under which a row's column values mark that the row matches the predicate of the
+    /// partial index `index_name`
+    pub fn partial_index_key(index_name: &str) -> String {
+        format!("\0where:{index_name}")
+    }
+
+    /// Whether an index with `predicate` has an entry for the row with `column_values`
+    fn covers(
+        index_name: &str,
+        predicate: &Option<Expression>,
+        column_values: &HashMap<String, String>,
+    ) -> bool {
+        predicate.is_none() || column_values.contains_key(&Self::partial_index_key(index_name))
+    }
+
+    /// Whether index `index_name` on `table_name` has (or should have) an entry for the row with
+    /// `column_values`: always for a full index, only when marked for a partial one
+    pub fn covers_row(
+        &self,
+        table_name: &str,
+        index_name: &str,
+        column_values: &HashMap<String, String>,
+    ) -> bool {
+        self.get_index_entry(table_name, index_name)
+            .is_some_and(|e| Self::covers(index_name, &e.options.predicate, column_values))
+    }
+
+    /// Options of the indexes on `table_name`, including those being built, by index name
+    fn table_options<'a>(
+        &'a self,
+        table_name: &'a str,
+    ) -> impl Iterator<Item = (&'a str, &'a IndexOptions)> + 'a {
+        let built = self
+            .indexes
+            .iter()
+            .map(|(key, entry)| (key, &entry.options));
+        let building = self.builds.iter().map(|(key, build)| (key, &build.options));
+        built
+            .chain(building)
+            .filter(move |((t, _), _)| t == table_name)
+            .map(|((_, name), options)| (name.as_str(), options))
+    }
+
+    /// Names and predicates of the partial indexes on `table_name`, including those being built
+    pub fn partial_index_predicates<'a>(
+        &'a self,
+        table_name: &'a str,
+    ) -> impl Iterator<Item = (&'a str, &'a Expression)> + 'a {
+        self.table_options(table_name)
+            .filter_map(|(name, options)| Some((name, options.predicate.as_ref()?)))
+    }
+
+    /// Names and expressions of the computed key parts of the indexes on `table_name`,
+    /// including those being built (a part shared by several indexes is listed for each)
+    pub fn index_expressions<'a>(
+        &'a self,
+        table_name: &'a str,
+    ) -> impl Iterator<Item = (&'a str, &'a Expression)> + 'a {
+        self.table_options(table_name).flat_map(|(_, options)| {
+            options
+                .expressions
+                .iter()
+                .map(|(key, expr)| (key.as_str(), expr))
+        })
+    }
+
+    /// Indexes of every table kept in files, as `((table, index), index)`
+    pub fn persistent_indexes(&self) -> Vec<((String, String), Arc<Mutex<SecondaryIndex>>)> {
+        if self.storage_dir.is_none() {
+            return Vec::new();
+        }
+        self.indexes
+            .iter()
+            .map(|(key, entry)| (key.clone(), entry.index.clone()))
+            .collect()
+    }
+
     /// Lists indexes for a table
     pub fn list_indexes_for_table(&self, table_name: &str) -> Vec<(String, Vec<String>)> {
         self.indexes
@@ -135,6 +608,35 @@ impl IndexRegistry {
         Ok(())
     }
 
+    /// Loads `(key, record ids)` entries into a single named index, one tree insert per key
+    /// (index builds). Entries for a key already in the index are appended to it.
+    pub fn load_into_named_index(
+        &self,
+        table_name: &str,
+        index_name: &str,
+        entries: impl IntoIterator<Item = (String, Vec<RecordId>)>,
+    ) -> Result<()> {
+        let key = (table_name.to_string(), index_name.to_string());
+        let entry = self.indexes.get(&key).ok_or_else(|| {
+            Error::validation(format!(
+                "Index {} not found on table {}",
+                index_name, table_name
+            ))
+        })?;
+        let mut index = entry
+            .index
+            .lock()
+            .map_err(|_| Error::internal("Lock poisoned"))?;
+        for (index_key, mut record_ids) in entries {
+            if let Some(mut ids) = index.search(&index_key)? {
+                ids.append(&mut record_ids);
+                record_ids = ids;
+            }
+            index.insert(index_key, record_ids)?;
+        }
+        Ok(())
+    }
+
     /// Inserts a record into all indexes of a table
     /// column_values: map from column name to serialized value
     pub fn insert_into_indexes(
@@ -143,7 +645,10 @@ impl IndexRegistry {
         record_id: RecordId,
         column_values: &HashMap<String, String>,
     ) -> Result<()> {
-        for ((t, _), entry) in self.indexes.iter().filter(|((t, _), _)| t == table_name) {
+        for ((_, name), entry) in self.indexes.iter().filter(|((t, _), _)| t == table_name) {
+            if !Self::covers(name, &entry.options.predicate, column_values) {
+                continue;
+            }
             let key = Self::build_index_key(&entry.columns, column_values)?;
             let mut index = entry
                 .index
@@ -156,7 +661,10 @@ impl IndexRegistry {
                 index.insert(key, vec![record_id])?;
             }
         }
-        Ok(())
+        self.log_index_build_changes(table_name, column_values, |key| IndexChange::Insert {
+            key,
+            record_id,
+        })
     }
 
     /// Removes a record from all indexes of a table
@@ -166,7 +674,10 @@ impl IndexRegistry {
         record_id: RecordId,
         column_values: &HashMap<String, String>,
     ) -> Result<()> {
-        for ((t, _), entry) in self.indexes.iter().filter(|((t, _), _)| t == table_name) {
+        for ((_, name), entry) in self.indexes.iter().filter(|((t, _), _)| t == table_name) {
+            if !Self::covers(name, &entry.options.predicate, column_values) {
+                continue;
+            }
             let key = Self::build_index_key(&entry.columns, column_values)?;
             let mut index = entry
                 .index
@@ -181,17 +692,28 @@ impl IndexRegistry {
                 }
             }
         }
-        Ok(())
+        self.log_index_build_changes(table_name, column_values, |key| IndexChange::Delete {
+            key,
+            record_id,
+        })
     }
 
-    /// True when every index key on `table_name` is identical in `old_values` and `new_values`.
+    /// True when every index key on `table_name`, including those of indexes being built, is
+    /// identical in `old_values` and `new_values`.
     pub fn index_keys_unchanged(
         &self,
         table_name: &str,
         old_values: &HashMap<String, String>,
         new_values: &HashMap<String, String>,
     ) -> bool {
-        for ((t, _), entry) in self.indexes.iter().filter(|((t, _), _)| t == table_name) {
+        for ((_, name), entry) in self.indexes.iter().filter(|((t, _), _)| t == table_name) {
+            let old_in = Self::covers(name, &entry.options.predicate, old_values);
+            if old_in != Self::covers(name, &entry.options.predicate, new_values) {
+                return false;
+            }
+            if !old_in {
+                continue;
+            }
             let old_key = match Self::build_index_key(&entry.columns, old_values) {
                 Ok(k) => k,
                 Err(_) => return false,
@@ -204,7 +726,23 @@ impl IndexRegistry {
                 return false;
             }
         }
-        true
+        self.builds
+            .iter()
+            .filter(|((t, _), _)| t == table_name)
+            .all(|((_, name), build)| {
+                let old_in = Self::covers(name, &build.options.predicate, old_values);
+                if old_in != Self::covers(name, &build.options.predicate, new_values) {
+                    return false;
+                }
+                !old_in
+                    || match (
+                        Self::build_index_key(&build.columns, old_values),
+                        Self::build_index_key(&build.columns, new_values),
+                    ) {
+                        (Ok(old_key), Ok(new_key)) => old_key == new_key,
+                        _ => false,
+                    }
+            })
     }
 
     /// Updates indexes when a record changes (remove old, add new)
@@ -233,15 +771,12 @@ impl IndexRegistry {
 
     /// Resolves `column = literal` predicates via the best matching index on `table_name`.
     ///
-    /// `equalities` maps column names to index-key strings (same encoding as
-    /// [`crate::network::sql_engine`] index maintenance). Returns `None` when no registered
-    /// index has a leading prefix covered by `equalities`. An empty vector means the index
-    /// matched but no rows were found.
+    /// `equalities` maps column names to value texts (as [`index_key`] takes them). Returns
+    /// `None` when no registered index has a leading prefix covered by `equalities`. An empty
+    /// vector means the index matched but no rows were found.
     ///
-    /// **Limits:** only equality on a leading prefix of the index column list is supported.
-    /// Partial-prefix lookups use B+tree range search and may include false positives when
-    /// index-key strings share prefixes (e.g. integer `1` vs `10`); callers must re-check the
-    /// full `WHERE` clause on heap tuples.
+    /// **Limits:** only equality on a leading prefix of the index column list is supported;
+    /// partial-prefix lookups are B+tree range searches over the keys starting with the prefix.
     pub fn lookup_record_ids_by_equalities(
         &self,
         table_name: &str,
@@ -264,7 +799,7 @@ impl IndexRegistry {
         let rids = if exact_key {
             index.search(&key)?.unwrap_or_default()
         } else {
-            let end = format!("{key}\0\u{10FFFF}");
+            let end = format!("{key}{INDEX_KEY_MAX}");
             index
                 .range_search(&key, &end)?
                 .into_iter()
@@ -274,7 +809,9 @@ impl IndexRegistry {
         Ok(Some((rids, exact_key)))
     }
 
-    /// Picks the index with the longest leading column prefix present in `equalities`.
+    /// Picks the index with the longest leading column prefix present in `equalities`; partial
+    /// indexes are skipped, since equalities alone do not tell whether they cover the rows, and
+    /// hash indexes need the whole key.
     fn best_index_for_equalities(
         &self,
         table_name: &str,
@@ -282,7 +819,7 @@ impl IndexRegistry {
     ) -> Option<(&IndexEntry, usize)> {
         let mut best: Option<(&IndexEntry, usize)> = None;
         for ((t, _), entry) in self.indexes.iter() {
-            if t != table_name {
+            if t != table_name || entry.options.predicate.is_some() {
                 continue;
             }
             let mut prefix = 0usize;
@@ -293,7 +830,8 @@ impl IndexRegistry {
                     break;
                 }
             }
-            if prefix == 0 {
+            let whole_key = prefix == entry.columns.len();
+            if prefix == 0 || (entry.options.method == IndexMethod::Hash && !whole_key) {
                 continue;
             }
             if best.map(|(_, p)| prefix > p).unwrap_or(true) {
@@ -304,10 +842,51 @@ impl IndexRegistry {
     }
 
     fn build_index_key(columns: &[String], values: &HashMap<String, String>) -> Result<String> {
-        let parts: Vec<String> = columns
-            .iter()
-            .map(|col| values.get(col).cloned().unwrap_or_else(|| "".to_string()))
-            .collect();
-        Ok(parts.join("\0"))
+        Ok(index_key(
+            columns
+                .iter()
+                .map(|col| values.get(col).map_or("", String::as_str)),
+        ))
+    }
+}
+
+/// Sorts after every index key: the end of a range search over all keys with a given prefix
+pub const INDEX_KEY_MAX: &str = "\u{10FFFF}";
+
+/// Index key of the value texts `parts`, one per key column: the concatenated
+/// [`key_encoding::encode_value`] of the values they stand for (see [`index_key_value`]), one
+/// character per byte, so keys compare like the values.
+pub fn index_key<'a>(parts: impl IntoIterator<Item = &'a str>) -> String {
+    let mut bytes = Vec::new();
+    for part in parts {
+        key_encoding::encode_value(&index_key_value(part), &mut bytes);
     }
+    bytes.into_iter().map(char::from).collect()
+}
+
+/// Value a column value text stands for: empty for NULL, a quoted string literal, `true` or
+/// `false`, a number, or else the text itself as a string.
+fn index_key_value(text: &str) -> ColumnValue {
+    if text.is_empty() {
+        return ColumnValue::null();
+    }
+    if let Some(inner) = text
+        .strip_prefix('\'')
+        .and_then(|rest| rest.strip_suffix('\''))
+    {
+        return ColumnValue::new(DataType::Varchar(inner.to_string()));
+    }
+    let value = match text {
+        "true" => DataType::Boolean(true),
+        "false" => DataType::Boolean(false),
+        _ => match text.parse::<i64>() {
+            Ok(n) => DataType::BigInt(n),
+            // `inf` and `NaN` parse as floats but name no number here.
+            Err(_) => match text.parse::<f64>() {
+                Ok(f) if text.bytes().any(|b| b.is_ascii_digit()) => DataType::Double(f),
+                _ => DataType::Varchar(text.to_string()),
+            },
+        },
+    };
+    ColumnValue::new(value)
 }
@@ -7,10 +7,13 @@
 //! - Batch processing of operations to improve performance
 
 use crate::common::{Error, Result};
+use crate::core::buffer::{BufferManager, EvictionStrategy};
 use crate::storage::database_file::{PageId, BLOCK_SIZE};
 use serde::{Deserialize, Serialize};
+use std::cell::Cell;
 use std::collections::{HashMap, VecDeque};
-use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, TryLockError};
 use std::time::{Duration, Instant};
 use tokio::sync::{mpsc, oneshot, Semaphore};
 use tokio::task::JoinHandle;
@@ -154,10 +157,36 @@ impl Default for IoBufferConfig {
     }
 }
 
+thread_local! {
+    static THREAD_CACHE_LOOKUPS: Cell<(u64, u64)> = const { Cell::new((0, 0)) };
+}
+
+/// Page cache (hits, misses) of lookups made on the calling thread, over all caches
+///
+/// The counters only grow, so the difference of two readings is the cache traffic of the work
+/// done in between on this thread (e.g. one SQL statement).
+pub fn thread_page_cache_lookups() -> (u64, u64) {
+    THREAD_CACHE_LOOKUPS.with(Cell::get)
+}
+
+fn count_thread_lookup(hit: bool) {
+    THREAD_CACHE_LOOKUPS.with(|c| {
+        let (hits, misses) = c.get();
+        c.set(if hit {
+            (hits + 1, misses)
+        } else {
+            (hits, misses + 1)
+        });
+    });
+}
+
 /// Page cache with LRU policy
+///
+/// Pages are shared: [`PageCache::get_shared`] hands out a reference to the cached bytes instead
+/// of a copy, and a page is pinned (never evicted) while such a reference is alive.
 pub struct PageCache {
     /// Cache data
-    data: HashMap<(u32, PageId), (Vec<u8>, Instant)>,
+    data: HashMap<(u32, PageId), (Arc<Vec<u8>>, Instant)>,
     /// Access order (LRU)
     access_order: VecDeque<(u32, PageId)>,
     /// Maximum cache size
@@ -180,28 +209,48 @@ impl PageCache {
         }
     }
 
-    /// Gets a page from the cache
+    /// Gets a copy of a page from the cache
     pub fn get(&mut self, file_id: u32, page_id: PageId) -> Option<Vec<u8>> {
+        self.get_shared(file_id, page_id).map(|data| data.to_vec())
+    }
+
+    /// Gets a page from the cache without copying it; the page stays pinned while the returned
+    /// reference is alive
+    pub fn get_shared(&mut self, file_id: u32, page_id: PageId) -> Option<Arc<Vec<u8>>> {
         let key = (file_id, page_id);
 
-        if let Some((data, _)) = self.data.get(&key).cloned() {
+        if let Some((data, _)) = self.data.get(&key) {
+            let data = data.clone();
             // Update access order
             self.update_access_order(&key);
             self.hits += 1;
+            count_thread_lookup(true);
             Some(data)
         } else {
             self.misses += 1;
+            count_thread_lookup(false);
             None
         }
     }
 
     /// Adds a page to the cache
     pub fn put(&mut self, file_id: u32, page_id: PageId, data: Vec<u8>) {
+        self.put_shared(file_id, page_id, Arc::new(data));
+    }
+
+    /// Adds a shared page to the cache
+    pub fn put_shared(&mut self, file_id: u32, page_id: PageId, data: Arc<Vec<u8>>) {
         let key = (file_id, page_id);
 
-        // If cache is full, remove the oldest entry
+        // If cache is full, remove the least recently used page that is not pinned. When every
+        // page is pinned the cache grows past `max_size` until pins are released.
         if self.data.len() >= self.max_size && !self.data.contains_key(&key) {
-            if let Some(lru_key) = self.access_order.pop_front() {
+            let victim = self.access_order.iter().position(|k| {
+                self.data
+                    .get(k)
+                    .is_none_or(|(data, _)| Arc::strong_count(data) == 1)
+            });
+            if let Some(lru_key) = victim.and_then(|i| self.access_order.remove(i)) {
                 self.data.remove(&lru_key);
             }
         }
@@ -249,6 +298,288 @@ impl PageCache {
     pub fn size(&self) -> usize {
         self.data.len()
     }
+
+    /// Returns the number of cached pages currently pinned
+    pub fn pinned_count(&self) -> usize {
+        self.data
+            .values()
+            .filter(|(data, _)| Arc::strong_count(data) > 1)
+            .count()
+    }
+
+    /// Returns whether a page is cached, without counting an access
+    pub fn contains(&self, file_id: u32, page_id: PageId) -> bool {
+        self.data.contains_key(&(file_id, page_id))
+    }
+}
+
+/// Smallest number of pages per shard of a [`ShardedPageCache`] sized by [`ShardedPageCache::new`]
+const MIN_PAGES_PER_SHARD: usize = 64;
+
+/// Upper bound of the shard count chosen by [`ShardedPageCache::new`]
+const MAX_PAGE_CACHE_SHARDS: usize = 64;
+
+/// Page images of a shard, keyed by file and page
+type ShardPool = BufferManager<Arc<Vec<u8>>, (u32, PageId)>;
+
+/// One partition of a [`ShardedPageCache`] with its latch contention counters
+struct PageCacheShard {
+    cache: Mutex<ShardPool>,
+    /// Latch acquisitions
+    acquisitions: AtomicU64,
+    /// Acquisitions that found the latch held and had to wait
+    contentions: AtomicU64,
+    /// Total time spent waiting for the latch, in microseconds
+    wait_us: AtomicU64,
+}
+
+/// Per-shard state and contention metrics of a [`ShardedPageCache`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PageCacheShardStats {
+    /// Shard index
+    pub shard: usize,
+    /// Cached pages
+    pub pages: usize,
+    /// Cached pages currently pinned
+    pub pinned: usize,
+    /// Maximum pages before the shard evicts
+    pub capacity: usize,
+    pub hits: u64,
+    pub misses: u64,
+    /// Latch acquisitions
+    pub lock_acquisitions: u64,
+    /// Acquisitions that had to wait for another thread
+    pub lock_contentions: u64,
+    /// Total time spent waiting for the latch, in microseconds
+    pub lock_wait_us: u64,
+}
+
+/// Page cache partitioned by page hash
+///
+/// Every shard is a [`BufferManager`] of page images behind its own latch with its own LRU list,
+/// so threads touching different pages rarely wait for each other and eviction only scans one
+/// partition. A page always maps to the same shard; the shard capacity is the total capacity
+/// divided evenly. Pages are written through before they are cached, so frames are never dirty;
+/// a page pinned with [`Self::pin`] is not evicted until [`Self::unpin`] (when every page of a
+/// shard is pinned, the shard grows past its capacity instead).
+pub struct ShardedPageCache {
+    shards: Box<[PageCacheShard]>,
+    /// Total capacity in pages
+    max_size: usize,
+}
+
+impl ShardedPageCache {
+    /// Creates a cache of `max_size` pages; the shard count grows with the capacity (at least
+    /// [`MIN_PAGES_PER_SHARD`] pages per shard) and the number of cores.
+    pub fn new(max_size: usize) -> Self {
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let by_size = (max_size / MIN_PAGES_PER_SHARD).max(1);
+        let by_cores = (cores * 4).next_power_of_two();
+        let shards = by_size.min(by_cores).min(MAX_PAGE_CACHE_SHARDS);
+        Self::with_shards(max_size, shards)
+    }
+
+    /// Creates a cache of `max_size` pages split into `shards` partitions (rounded down to a
+    /// power of two)
+    pub fn with_shards(max_size: usize, shards: usize) -> Self {
+        let shards = shards.clamp(1, max_size.max(1));
+        let shards = if shards.is_power_of_two() {
+            shards
+        } else {
+            shards.next_power_of_two() / 2
+        };
+        let per_shard = max_size.div_ceil(shards).max(1);
+        let shards = (0..shards)
+            .map(|_| PageCacheShard {
+                cache: Mutex::new(ShardPool::with_frames(per_shard, EvictionStrategy::LRU)),
+                acquisitions: AtomicU64::new(0),
+                contentions: AtomicU64::new(0),
+                wait_us: AtomicU64::new(0),
+            })
+            .collect();
+        Self { shards, max_size }
+    }
+
+    /// Number of shards
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Total capacity in pages
+    pub fn capacity(&self) -> usize {
+        self.max_size
+    }
+
+    /// Shard index of a page
+    pub fn shard_of(&self, file_id: u32, page_id: PageId) -> usize {
+        let key = page_id ^ (u64::from(file_id)).rotate_left(32);
+        let hash = key.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        (hash >> 32) as usize & (self.shards.len() - 1)
+    }
+
+    /// Locks the shard of a page, recording whether the latch was contended
+    fn lock(&self, file_id: u32, page_id: PageId) -> MutexGuard<'_, ShardPool> {
+        self.lock_shard(self.shard_of(file_id, page_id))
+    }
+
+    fn lock_shard(&self, index: usize) -> MutexGuard<'_, ShardPool> {
+        let shard = &self.shards[index];
+        shard.acquisitions.fetch_add(1, Ordering::Relaxed);
+        match shard.cache.try_lock() {
+            Ok(guard) => guard,
+            Err(TryLockError::WouldBlock) => {
+                let started = Instant::now();
+                let guard = shard.cache.lock().unwrap();
+                shard.contentions.fetch_add(1, Ordering::Relaxed);
+                shard
+                    .wait_us
+                    .fetch_add(started.elapsed().as_micros() as u64, Ordering::Relaxed);
+                guard
+            }
+            Err(TryLockError::Poisoned(e)) => panic!("page cache shard poisoned: {e}"),
+        }
+    }
+
+    /// Gets a copy of a page from the cache
+    pub fn get(&self, file_id: u32, page_id: PageId) -> Option<Vec<u8>> {
+        self.get_shared(file_id, page_id).map(|data| data.to_vec())
+    }
+
+    /// Gets a page from the cache without copying it
+    pub fn get_shared(&self, file_id: u32, page_id: PageId) -> Option<Arc<Vec<u8>>> {
+        let data = self
+            .lock(file_id, page_id)
+            .get_page((file_id, page_id))
+            .cloned();
+        count_thread_lookup(data.is_some());
+        data
+    }
+
+    /// Gets a page from the cache without copying it and pins it until [`Self::unpin`]
+    pub fn pin(&self, file_id: u32, page_id: PageId) -> Option<Arc<Vec<u8>>> {
+        let mut shard = self.lock(file_id, page_id);
+        let data = shard.get_page((file_id, page_id)).cloned();
+        count_thread_lookup(data.is_some());
+        if data.is_some() {
+            let _ = shard.pin_page((file_id, page_id));
+        }
+        data
+    }
+
+    /// Releases one pin of a page taken by [`Self::pin`] or [`Self::put_pinned`]
+    pub fn unpin(&self, file_id: u32, page_id: PageId) {
+        let _ = self.lock(file_id, page_id).unpin_page((file_id, page_id));
+    }
+
+    /// Adds a page to the cache
+    pub fn put(&self, file_id: u32, page_id: PageId, data: Vec<u8>) {
+        self.put_shared(file_id, page_id, Arc::new(data));
+    }
+
+    /// Adds a shared page to the cache
+    pub fn put_shared(&self, file_id: u32, page_id: PageId, data: Arc<Vec<u8>>) {
+        // Frames are never dirty, so making room cannot fail.
+        let _ = self
+            .lock(file_id, page_id)
+            .put_frame((file_id, page_id), data);
+    }
+
+    /// Adds a shared page to the cache and pins it until [`Self::unpin`]
+    pub fn put_pinned(&self, file_id: u32, page_id: PageId, data: Arc<Vec<u8>>) {
+        let mut shard = self.lock(file_id, page_id);
+        let _ = shard.put_frame((file_id, page_id), data);
+        let _ = shard.pin_page((file_id, page_id));
+    }
+
+    /// Adds a page unless it is already cached (a newer copy may be); returns whether it was
+    /// added
+    pub fn put_if_absent(&self, file_id: u32, page_id: PageId, data: Vec<u8>) -> bool {
+        let mut shard = self.lock(file_id, page_id);
+        if shard.contains_page((file_id, page_id)) {
+            return false;
+        }
+        let _ = shard.put_frame((file_id, page_id), Arc::new(data));
+        true
+    }
+
+    /// Removes a page from the cache
+    pub fn remove(&self, file_id: u32, page_id: PageId) {
+        self.lock(file_id, page_id).remove_page((file_id, page_id));
+    }
+
+    /// Returns whether a page is cached, without counting an access
+    pub fn contains(&self, file_id: u32, page_id: PageId) -> bool {
+        self.lock(file_id, page_id)
+            .contains_page((file_id, page_id))
+    }
+
+    /// Number of pins held on a page (0 when it is not cached)
+    pub fn pin_count(&self, file_id: u32, page_id: PageId) -> u32 {
+        self.lock(file_id, page_id).pin_count((file_id, page_id))
+    }
+
+    /// Clears every shard and its hit/miss counters
+    pub fn clear(&self) {
+        for index in 0..self.shards.len() {
+            let mut shard = self.lock_shard(index);
+            shard.clear();
+            shard.reset_stats();
+        }
+    }
+
+    /// Returns cache statistics summed over all shards (hits, misses, hit_ratio)
+    pub fn get_stats(&self) -> (u64, u64, f64) {
+        let (hits, misses) = self
+            .shard_stats()
+            .iter()
+            .fold((0, 0), |(h, m), s| (h + s.hits, m + s.misses));
+        let total = hits + misses;
+        let hit_ratio = if total > 0 {
+            hits as f64 / total as f64
+        } else {
+            0.0
+        };
+        (hits, misses, hit_ratio)
+    }
+
+    /// Returns the number of cached pages
+    pub fn size(&self) -> usize {
+        (0..self.shards.len())
+            .map(|i| self.lock_shard(i).page_count())
+            .sum()
+    }
+
+    /// Returns the number of cached pages currently pinned
+    pub fn pinned_count(&self) -> usize {
+        (0..self.shards.len())
+            .map(|i| self.lock_shard(i).pinned_page_count())
+            .sum()
+    }
+
+    /// Returns the state and latch contention counters of every shard
+    pub fn shard_stats(&self) -> Vec<PageCacheShardStats> {
+        self.shards
+            .iter()
+            .enumerate()
+            .map(|(index, shard)| {
+                // Read the counters without going through `lock_shard`, so taking the snapshot
+                // does not count as an acquisition.
+                let cache = shard.cache.lock().unwrap();
+                let stats = cache.get_stats();
+                PageCacheShardStats {
+                    shard: index,
+                    pages: cache.page_count(),
+                    pinned: cache.pinned_page_count(),
+                    capacity: cache.capacity(),
+                    hits: stats.cache_hits,
+                    misses: stats.cache_misses,
+                    lock_acquisitions: shard.acquisitions.load(Ordering::Relaxed),
+                    lock_contentions: shard.contentions.load(Ordering::Relaxed),
+                    lock_wait_us: shard.wait_us.load(Ordering::Relaxed),
+                }
+            })
+            .collect()
+    }
 }
 
 /// Buffered I/O operation manager
@@ -258,7 +589,7 @@ pub struct BufferedIoManager {
     /// Write operation buffer
     write_buffer: Arc<Mutex<Vec<BufferedWrite>>>,
     /// Page cache
-    page_cache: Arc<RwLock<PageCache>>,
+    page_cache: Arc<ShardedPageCache>,
     /// Operation statistics
     statistics: Arc<RwLock<IoStatistics>>,
     /// Channel for sending requests
@@ -280,7 +611,7 @@ impl BufferedIoManager {
         let semaphore = Arc::new(Semaphore::new(config.max_concurrent_operations));
 
         let write_buffer = Arc::new(Mutex::new(Vec::new()));
-        let page_cache = Arc::new(RwLock::new(PageCache::new(config.page_cache_size)));
+        let page_cache = Arc::new(ShardedPageCache::new(config.page_cache_size));
         let statistics = Arc::new(RwLock::new(IoStatistics::default()));
         let request_counter = Arc::new(Mutex::new(0));
 
@@ -315,7 +646,7 @@ impl BufferedIoManager {
         &mut self,
         mut request_rx: mpsc::UnboundedReceiver<IoRequest>,
         write_buffer: Arc<Mutex<Vec<BufferedWrite>>>,
-        page_cache: Arc<RwLock<PageCache>>,
+        page_cache: Arc<ShardedPageCache>,
         statistics: Arc<RwLock<IoStatistics>>,
         _semaphore: Arc<Semaphore>,
     ) {
@@ -354,10 +685,7 @@ impl BufferedIoManager {
     /// Asynchronously reads a page
     pub async fn read_page_async(&self, file_id: u32, page_id: PageId) -> Result<Vec<u8>> {
         // Check cache
-        if let Some(data) = {
-            let mut cache = self.page_cache.write().unwrap();
-            cache.get(file_id, page_id)
-        } {
+        if let Some(data) = self.page_cache.get(file_id, page_id) {
             self.update_statistics(IoOperationType::Read, 0, true).await;
             return Ok(data);
         }
@@ -390,10 +718,7 @@ impl BufferedIoManager {
         match result {
             Some(data) => {
                 // Add to cache
-                {
-                    let mut cache = self.page_cache.write().unwrap();
-                    cache.put(file_id, page_id, data.clone());
-                }
+                self.page_cache.put(file_id, page_id, data.clone());
 
                 // Trigger prefetch if enabled
                 if self.config.enable_prefetch {
@@ -424,10 +749,7 @@ impl BufferedIoManager {
         }
 
         // Update cache
-        {
-            let mut cache = self.page_cache.write().unwrap();
-            cache.put(file_id, page_id, data.clone());
-        }
+        self.page_cache.put(file_id, page_id, data.clone());
 
         // Add to write buffer
         let buffered_write = BufferedWrite {
@@ -465,6 +787,11 @@ impl BufferedIoManager {
         }
     }
 
+    /// Returns the cached copy of a page without queuing a read on a miss
+    pub fn cached_page(&self, file_id: u32, page_id: PageId) -> Option<Vec<u8>> {
+        self.page_cache.get(file_id, page_id)
+    }
+
     /// Synchronous wrapper for reading a page (for use in benchmarks)
     pub fn read_page_sync(&self, file_id: u32, page_id: PageId) -> Result<Vec<u8>> {
         if let Ok(handle) = tokio::runtime::Handle::try_current() {
@@ -489,11 +816,8 @@ impl BufferedIoManager {
             let prefetch_page_id = base_page_id + i as u64;
 
             // Check if already in cache
-            {
-                let cache = self.page_cache.read().unwrap();
-                if cache.data.contains_key(&(file_id, prefetch_page_id)) {
-                    continue;
-                }
+            if self.page_cache.contains(file_id, prefetch_page_id) {
+                continue;
             }
 
             // Create prefetch request
@@ -520,7 +844,7 @@ impl BufferedIoManager {
     async fn handle_io_request(
         request: IoRequest,
         statistics: Arc<RwLock<IoStatistics>>,
-        page_cache: Arc<RwLock<PageCache>>,
+        page_cache: Arc<ShardedPageCache>,
     ) {
         let start_time = Instant::now();
 
@@ -576,8 +900,7 @@ impl BufferedIoManager {
                         stats.bytes_read += data.len() as u64;
 
                         // Add prefetch result to cache
-                        let mut cache = page_cache.write().unwrap();
-                        cache.put(request.file_id, request.page_id, data.clone());
+                        page_cache.put(request.file_id, request.page_id, data.clone());
                     }
                 }
             }
@@ -664,7 +987,7 @@ impl BufferedIoManager {
     /// Returns current statistics
     pub fn get_statistics(&self) -> IoStatistics {
         let stats = self.statistics.read().unwrap();
-        let cache_stats = self.page_cache.read().unwrap().get_stats();
+        let cache_stats = self.page_cache.get_stats();
 
         let mut result = stats.clone();
         result.cache_hits = cache_stats.0;
@@ -676,8 +999,7 @@ impl BufferedIoManager {
 
     /// Clears cache and resets statistics
     pub async fn clear_cache(&self) {
-        let mut cache = self.page_cache.write().unwrap();
-        cache.clear();
+        self.page_cache.clear();
 
         let mut stats = self.statistics.write().unwrap();
         *stats = IoStatistics::default();
@@ -686,12 +1008,11 @@ impl BufferedIoManager {
     /// Gets buffer state information
     pub fn get_buffer_info(&self) -> (usize, usize, usize) {
         let buffer = self.write_buffer.lock().unwrap();
-        let cache = self.page_cache.read().unwrap();
 
         (
             buffer.len(),
             self.config.max_write_buffer_size,
-            cache.size(),
+            self.page_cache.size(),
         )
     }
 }
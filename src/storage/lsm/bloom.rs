@@ -0,0 +1,86 @@
+//! Bloom filter stored in every SSTable
+//!
+//! Point lookups consult the filter before touching the table's data blocks, so a key
+//! that is absent from most tables costs one hash per table instead of one block read.
+
+use crate::common::{Error, Result};
+use twox_hash::XxHash64;
+
+/// Fixed-size bloom filter using double hashing (`h1 + i * h2`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BloomFilter {
+    bits: Vec<u8>,
+    num_hashes: u32,
+}
+
+impl BloomFilter {
+    /// Sizes a filter for `keys` entries at `bits_per_key` bits each
+    /// (10 bits per key gives roughly a 1% false-positive rate).
+    pub fn with_capacity(keys: usize, bits_per_key: usize) -> Self {
+        let bits_per_key = bits_per_key.max(1);
+        let num_bits = (keys * bits_per_key).max(64);
+        // ln(2) * bits_per_key minimizes the false-positive rate.
+        let num_hashes = ((bits_per_key as f64 * 0.69) as u32).clamp(1, 30);
+        Self {
+            bits: vec![0; num_bits.div_ceil(8)],
+            num_hashes,
+        }
+    }
+
+    /// Adds `key`
+    pub fn insert(&mut self, key: &[u8]) {
+        let num_bits = self.num_bits();
+        let (h1, h2) = hash_pair(key);
+        for i in 0..self.num_hashes as u64 {
+            let bit = h1.wrapping_add(i.wrapping_mul(h2)) % num_bits;
+            self.bits[(bit / 8) as usize] |= 1 << (bit % 8);
+        }
+    }
+
+    /// `false` means `key` was definitely never inserted
+    pub fn may_contain(&self, key: &[u8]) -> bool {
+        let num_bits = self.num_bits();
+        let (h1, h2) = hash_pair(key);
+        (0..self.num_hashes as u64).all(|i| {
+            let bit = h1.wrapping_add(i.wrapping_mul(h2)) % num_bits;
+            self.bits[(bit / 8) as usize] & (1 << (bit % 8)) != 0
+        })
+    }
+
+    fn num_bits(&self) -> u64 {
+        self.bits.len() as u64 * 8
+    }
+
+    /// Serialized form: `num_hashes` (u32 LE) followed by the bit array
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(4 + self.bits.len());
+        out.extend_from_slice(&self.num_hashes.to_le_bytes());
+        out.extend_from_slice(&self.bits);
+        out
+    }
+
+    /// Parses [`Self::to_bytes`] output
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 5 {
+            return Err(Error::database("bloom filter block is truncated"));
+        }
+        let num_hashes = u32::from_le_bytes(bytes[..4].try_into().expect("4 bytes"));
+        if num_hashes == 0 || num_hashes > 30 {
+            return Err(Error::database(format!(
+                "bloom filter has invalid hash count {}",
+                num_hashes
+            )));
+        }
+        Ok(Self {
+            bits: bytes[4..].to_vec(),
+            num_hashes,
+        })
+    }
+}
+
+fn hash_pair(key: &[u8]) -> (u64, u64) {
+    // Seeds are part of the on-disk format; do not change them.
+    let h1 = XxHash64::oneshot(0, key);
+    let h2 = XxHash64::oneshot(0x9E37_79B9, key) | 1;
+    (h1, h2)
+}
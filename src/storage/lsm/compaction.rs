@@ -0,0 +1,128 @@
+//! Leveled compaction
+//!
+//! L0 holds memtable flushes whose key ranges may overlap. Once it has
+//! `l0_compaction_trigger` tables they are merged, together with every overlapping L1
+//! table, into L1. Each deeper level `n >= 1` is a sorted run of non-overlapping tables
+//! with a byte budget of `level_base_bytes * level_size_multiplier^(n-1)`; when a level
+//! exceeds it, one table (chosen round-robin by key) is merged into the overlapping
+//! tables of level `n + 1`. Tombstones are dropped once no deeper level holds data.
+
+use super::memtable::LsmValue;
+use super::sstable::{encoded_entry_len, SsTable};
+use super::LsmConfig;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// One merge step chosen by [`pick_compaction`]
+#[derive(Debug)]
+pub struct CompactionTask {
+    /// Source level
+    pub level: usize,
+    /// Tables taken from `level`, oldest first
+    pub inputs: Vec<Arc<SsTable>>,
+    /// Overlapping tables from `level + 1`
+    pub overlaps: Vec<Arc<SsTable>>,
+}
+
+/// Byte budget for `level` (>= 1)
+pub fn max_bytes_for_level(config: &LsmConfig, level: usize) -> u64 {
+    let mut budget = config.level_base_bytes;
+    for _ in 1..level {
+        budget = budget.saturating_mul(config.level_size_multiplier);
+    }
+    budget
+}
+
+/// Picks the next compaction, if any level is over its trigger.
+///
+/// `cursors[level]` remembers the largest key compacted out of each level so successive
+/// compactions walk the key space instead of always picking the first table.
+pub fn pick_compaction(
+    levels: &[Vec<Arc<SsTable>>],
+    config: &LsmConfig,
+    cursors: &mut [Vec<u8>],
+) -> Option<CompactionTask> {
+    if levels[0].len() >= config.l0_compaction_trigger.max(1) {
+        let inputs = levels[0].clone();
+        let smallest = inputs.iter().map(|t| &t.meta().smallest).min()?.clone();
+        let largest = inputs.iter().map(|t| &t.meta().largest).max()?.clone();
+        return Some(CompactionTask {
+            level: 0,
+            overlaps: overlapping(&levels[1], &smallest, &largest),
+            inputs,
+        });
+    }
+
+    // The last level has nowhere to compact into.
+    for level in 1..levels.len() - 1 {
+        let total: u64 = levels[level].iter().map(|t| t.meta().size).sum();
+        if total <= max_bytes_for_level(config, level) {
+            continue;
+        }
+        let cursor = &cursors[level];
+        let input = levels[level]
+            .iter()
+            .find(|t| t.meta().smallest.as_slice() > cursor.as_slice())
+            .or_else(|| levels[level].first())?
+            .clone();
+        cursors[level] = input.meta().largest.clone();
+        let overlaps = overlapping(
+            &levels[level + 1],
+            &input.meta().smallest,
+            &input.meta().largest,
+        );
+        return Some(CompactionTask {
+            level,
+            inputs: vec![input],
+            overlaps,
+        });
+    }
+    None
+}
+
+fn overlapping(tables: &[Arc<SsTable>], smallest: &[u8], largest: &[u8]) -> Vec<Arc<SsTable>> {
+    tables
+        .iter()
+        .filter(|t| t.meta().overlaps(smallest, largest))
+        .cloned()
+        .collect()
+}
+
+/// Merges sources given oldest first (later sources win on equal keys).
+///
+/// With `drop_tombstones` the output contains live entries only.
+pub fn merge_sources(
+    sources: Vec<Vec<(Vec<u8>, LsmValue)>>,
+    drop_tombstones: bool,
+) -> Vec<(Vec<u8>, LsmValue)> {
+    let mut merged: BTreeMap<Vec<u8>, LsmValue> = BTreeMap::new();
+    for source in sources {
+        merged.extend(source);
+    }
+    merged
+        .into_iter()
+        .filter(|(_, v)| !drop_tombstones || v.is_some())
+        .collect()
+}
+
+/// Splits merged output into runs of roughly `target_bytes` each
+pub fn split_into_tables(
+    entries: Vec<(Vec<u8>, LsmValue)>,
+    target_bytes: u64,
+) -> Vec<Vec<(Vec<u8>, LsmValue)>> {
+    let mut tables = Vec::new();
+    let mut current = Vec::new();
+    let mut current_bytes = 0u64;
+    for (key, value) in entries {
+        current_bytes += encoded_entry_len(&key, &value) as u64;
+        current.push((key, value));
+        if current_bytes >= target_bytes.max(1) {
+            tables.push(std::mem::take(&mut current));
+            current_bytes = 0;
+        }
+    }
+    if !current.is_empty() {
+        tables.push(current);
+    }
+    tables
+}
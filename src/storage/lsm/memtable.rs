@@ -0,0 +1,223 @@
+//! Memtable and its append-only log
+//!
+//! Writes land in a sorted in-memory map and are appended to `memtable.log`. The log is
+//! written on [`MemtableLog::write_pending`] (the heap equivalent of flushing dirty pages)
+//! and replayed on open, so the memtable survives a restart without being turned into an
+//! SSTable on every commit. Once the memtable is flushed to an SSTable the log is reset.
+
+use crate::common::{Error, Result};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use twox_hash::XxHash32;
+
+/// Value slot in the memtable and SSTables: `None` is a tombstone
+pub type LsmValue = Option<Vec<u8>>;
+
+const TAG_PUT: u8 = 0;
+const TAG_DELETE: u8 = 1;
+/// Per-entry bookkeeping counted against the memtable budget
+const ENTRY_OVERHEAD: usize = 32;
+
+/// Sorted in-memory write buffer
+#[derive(Debug, Default)]
+pub struct Memtable {
+    entries: BTreeMap<Vec<u8>, LsmValue>,
+    approximate_bytes: usize,
+}
+
+impl Memtable {
+    /// Creates an empty memtable
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a put (`Some`) or tombstone (`None`) for `key`
+    pub fn insert(&mut self, key: Vec<u8>, value: LsmValue) {
+        let added = key.len() + value.as_ref().map_or(0, Vec::len) + ENTRY_OVERHEAD;
+        if let Some(old) = self.entries.insert(key.clone(), value) {
+            let removed = key.len() + old.map_or(0, |v| v.len()) + ENTRY_OVERHEAD;
+            self.approximate_bytes = self.approximate_bytes.saturating_sub(removed);
+        }
+        self.approximate_bytes += added;
+    }
+
+    /// Latest entry for `key`: `Some(None)` is a tombstone
+    pub fn get(&self, key: &[u8]) -> Option<&LsmValue> {
+        self.entries.get(key)
+    }
+
+    /// Entries in `[start, end]` order, tombstones included
+    pub fn range<'a>(
+        &'a self,
+        start: Bound<&'a [u8]>,
+        end: Bound<&'a [u8]>,
+    ) -> impl Iterator<Item = (&'a Vec<u8>, &'a LsmValue)> + 'a {
+        self.entries.range::<[u8], _>((start, end))
+    }
+
+    /// Largest key (live or tombstone)
+    pub fn last_key(&self) -> Option<&[u8]> {
+        self.entries.keys().next_back().map(Vec::as_slice)
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the memtable is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Approximate memory footprint used for the flush threshold
+    pub fn approximate_bytes(&self) -> usize {
+        self.approximate_bytes
+    }
+
+    /// Empties the memtable, returning its entries in key order
+    pub fn take(&mut self) -> Vec<(Vec<u8>, LsmValue)> {
+        self.approximate_bytes = 0;
+        std::mem::take(&mut self.entries).into_iter().collect()
+    }
+}
+
+/// Append-only log of memtable writes
+///
+/// Record layout: `len: u32 LE`, `xxh32(payload): u32 LE`, then the payload
+/// (`tag: u8`, `key_len: u32 LE`, key, `value_len: u32 LE`, value). A torn or corrupt tail
+/// ends replay and is truncated away.
+#[derive(Debug)]
+pub struct MemtableLog {
+    path: PathBuf,
+    file: File,
+    pending: Vec<u8>,
+    pending_records: usize,
+}
+
+impl MemtableLog {
+    /// Opens (or creates) the log at `path` and returns the entries it holds, oldest first
+    pub fn open(path: &Path) -> Result<(Self, Vec<(Vec<u8>, LsmValue)>)> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        let (entries, valid_len) = decode_log(&bytes);
+        if valid_len < bytes.len() {
+            file.set_len(valid_len as u64)?;
+        }
+        file.seek(SeekFrom::End(0))?;
+        Ok((
+            Self {
+                path: path.to_path_buf(),
+                file,
+                pending: Vec::new(),
+                pending_records: 0,
+            },
+            entries,
+        ))
+    }
+
+    /// Buffers one write; nothing reaches the file until [`Self::write_pending`]
+    pub fn append(&mut self, key: &[u8], value: Option<&[u8]>) {
+        let mut payload = Vec::with_capacity(key.len() + value.map_or(0, <[u8]>::len) + 9);
+        payload.push(if value.is_some() { TAG_PUT } else { TAG_DELETE });
+        payload.extend_from_slice(&(key.len() as u32).to_le_bytes());
+        payload.extend_from_slice(key);
+        let value = value.unwrap_or(&[]);
+        payload.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        payload.extend_from_slice(value);
+
+        self.pending
+            .extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        self.pending
+            .extend_from_slice(&XxHash32::oneshot(0, &payload).to_le_bytes());
+        self.pending.extend_from_slice(&payload);
+        self.pending_records += 1;
+    }
+
+    /// Number of buffered records not yet written
+    pub fn pending_records(&self) -> usize {
+        self.pending_records
+    }
+
+    /// Writes buffered records without fsync; returns how many were written
+    pub fn write_pending(&mut self) -> Result<usize> {
+        if self.pending.is_empty() {
+            return Ok(0);
+        }
+        self.file.write_all(&self.pending)?;
+        self.pending.clear();
+        Ok(std::mem::take(&mut self.pending_records))
+    }
+
+    /// Fsyncs the log file
+    pub fn sync(&mut self) -> Result<()> {
+        self.file.sync_data()?;
+        Ok(())
+    }
+
+    /// Discards the log after its entries reached an SSTable
+    pub fn reset(&mut self) -> Result<()> {
+        self.pending.clear();
+        self.pending_records = 0;
+        self.file.set_len(0)?;
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.sync_all()?;
+        Ok(())
+    }
+
+    /// Log file location
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Decodes complete, checksum-valid records; returns them with the byte length they cover
+fn decode_log(bytes: &[u8]) -> (Vec<(Vec<u8>, LsmValue)>, usize) {
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    while pos + 8 <= bytes.len() {
+        let len = u32::from_le_bytes(bytes[pos..pos + 4].try_into().expect("4 bytes")) as usize;
+        let checksum = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().expect("4 bytes"));
+        let Some(payload) = bytes.get(pos + 8..pos + 8 + len) else {
+            break;
+        };
+        if XxHash32::oneshot(0, payload) != checksum {
+            break;
+        }
+        match decode_payload(payload) {
+            Ok(entry) => entries.push(entry),
+            Err(_) => break,
+        }
+        pos += 8 + len;
+    }
+    (entries, pos)
+}
+
+fn decode_payload(payload: &[u8]) -> Result<(Vec<u8>, LsmValue)> {
+    let truncated = || Error::database("memtable log record is truncated");
+    let tag = *payload.first().ok_or_else(truncated)?;
+    let key_len =
+        u32::from_le_bytes(payload.get(1..5).ok_or_else(truncated)?.try_into().unwrap()) as usize;
+    let key = payload.get(5..5 + key_len).ok_or_else(truncated)?.to_vec();
+    let rest = &payload[5 + key_len..];
+    let value_len =
+        u32::from_le_bytes(rest.get(..4).ok_or_else(truncated)?.try_into().unwrap()) as usize;
+    let value = rest.get(4..4 + value_len).ok_or_else(truncated)?.to_vec();
+    match tag {
+        TAG_PUT => Ok((key, Some(value))),
+        TAG_DELETE => Ok((key, None)),
+        other => Err(Error::database(format!(
+            "memtable log record has unknown tag {}",
+            other
+        ))),
+    }
+}
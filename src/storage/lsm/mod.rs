@@ -0,0 +1,429 @@
+//! LSM-tree storage engine for rustdb
+//!
+//! Write-optimized alternative to the slotted-page heap: writes go to a memtable (plus an
+//! append-only log), full memtables are flushed to immutable SSTables in L0, and
+//! [leveled compaction](compaction) merges them down into non-overlapping levels. Every
+//! SSTable carries a bloom filter so point lookups skip tables that cannot hold the key.
+//!
+//! [`LsmTree`] is a generic ordered byte-key store. [`LsmRowStore`] adapts it to the
+//! record-id API of [`crate::storage::page_manager::PageManager`], which is how tables
+//! created with `CREATE TABLE ... ENGINE lsm` plug into the SQL engine (scans, index
+//! lookups, WAL redo/undo) unchanged.
+//!
+//! On-disk layout of a tree directory:
+//!
+//! ```text
+//! MANIFEST       JSON: level -> SSTable list, next file number
+//! memtable.log   writes not yet in an SSTable
+//! 000001.sst ... SSTables (see `sstable`)
+//! ```
+
+pub mod bloom;
+pub mod compaction;
+pub mod memtable;
+pub mod row_store;
+pub mod sstable;
+
+pub use bloom::BloomFilter;
+pub use memtable::{LsmValue, Memtable, MemtableLog};
+pub use row_store::LsmRowStore;
+pub use sstable::{SsTable, SsTableMeta};
+
+use crate::common::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+const MANIFEST_FILE: &str = "MANIFEST";
+const MEMTABLE_LOG_FILE: &str = "memtable.log";
+
+/// LSM tuning knobs
+#[derive(Debug, Clone)]
+pub struct LsmConfig {
+    /// Memtable size that triggers a flush to L0
+    pub memtable_bytes: usize,
+    /// Number of L0 tables that triggers an L0 -> L1 compaction
+    pub l0_compaction_trigger: usize,
+    /// Byte budget of L1
+    pub level_base_bytes: u64,
+    /// Growth factor between consecutive level budgets
+    pub level_size_multiplier: u64,
+    /// Target size of compaction output tables
+    pub target_file_bytes: u64,
+    /// Number of levels including L0
+    pub max_levels: usize,
+    /// Bloom filter bits per key (0 disables the filters' benefit, not the filters)
+    pub bloom_bits_per_key: usize,
+}
+
+impl Default for LsmConfig {
+    fn default() -> Self {
+        Self {
+            memtable_bytes: 4 * 1024 * 1024,
+            l0_compaction_trigger: 4,
+            level_base_bytes: 10 * 1024 * 1024,
+            level_size_multiplier: 10,
+            target_file_bytes: 2 * 1024 * 1024,
+            max_levels: 7,
+            bloom_bits_per_key: 10,
+        }
+    }
+}
+
+/// LSM operation counters
+#[derive(Debug, Default, Clone)]
+pub struct LsmStatistics {
+    /// Put operations
+    pub puts: u64,
+    /// Delete operations (tombstones written)
+    pub deletes: u64,
+    /// Point lookups
+    pub gets: u64,
+    /// SSTable probes skipped because the bloom filter excluded the key
+    pub bloom_skips: u64,
+    /// Memtable flushes to L0
+    pub memtable_flushes: u64,
+    /// Compactions run
+    pub compactions: u64,
+    /// Bytes written by compactions
+    pub compaction_bytes_written: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    next_file_number: u64,
+    levels: Vec<Vec<SsTableMeta>>,
+}
+
+/// Log-structured merge tree over byte keys
+#[derive(Debug)]
+pub struct LsmTree {
+    dir: PathBuf,
+    config: LsmConfig,
+    memtable: Memtable,
+    log: MemtableLog,
+    /// `levels[0]` oldest first; deeper levels sorted by smallest key, non-overlapping
+    levels: Vec<Vec<Arc<SsTable>>>,
+    compaction_cursors: Vec<Vec<u8>>,
+    next_file_number: u64,
+    statistics: LsmStatistics,
+    /// Lookup counters, kept apart so that concurrent readers can count through `&self`
+    gets: AtomicU64,
+    bloom_skips: AtomicU64,
+}
+
+impl LsmTree {
+    /// Opens the tree in `dir`, creating it when missing
+    pub fn open(dir: impl AsRef<Path>, config: LsmConfig) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+        let max_levels = config.max_levels.max(2);
+
+        let manifest_path = dir.join(MANIFEST_FILE);
+        let manifest: Manifest = if manifest_path.exists() {
+            serde_json::from_slice(&std::fs::read(&manifest_path)?).map_err(|e| {
+                Error::database(format!("LSM manifest {}: {}", manifest_path.display(), e))
+            })?
+        } else {
+            Manifest {
+                next_file_number: 1,
+                levels: Vec::new(),
+            }
+        };
+
+        let mut levels: Vec<Vec<Arc<SsTable>>> = vec![Vec::new(); max_levels];
+        let mut live = HashSet::new();
+        for (level, metas) in manifest.levels.into_iter().enumerate() {
+            if level >= max_levels && !metas.is_empty() {
+                return Err(Error::database(format!(
+                    "LSM tree {} has data in level {} but max_levels is {}",
+                    dir.display(),
+                    level,
+                    max_levels
+                )));
+            }
+            for meta in metas {
+                live.insert(sstable::sstable_file_name(meta.number));
+                levels[level].push(Arc::new(SsTable::open(&dir, meta)?));
+            }
+        }
+        // Tables from an interrupted flush / compaction never made it into the manifest.
+        for entry in std::fs::read_dir(&dir)? {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            if (name.ends_with(".sst") && !live.contains(&name)) || name.ends_with(".tmp") {
+                let _ = std::fs::remove_file(dir.join(&name));
+            }
+        }
+
+        let (log, replay) = MemtableLog::open(&dir.join(MEMTABLE_LOG_FILE))?;
+        let mut memtable = Memtable::new();
+        for (key, value) in replay {
+            memtable.insert(key, value);
+        }
+
+        let mut tree = Self {
+            dir,
+            config,
+            memtable,
+            log,
+            levels,
+            compaction_cursors: vec![Vec::new(); max_levels],
+            next_file_number: manifest.next_file_number.max(1),
+            statistics: LsmStatistics::default(),
+            gets: AtomicU64::new(0),
+            bloom_skips: AtomicU64::new(0),
+        };
+        if !manifest_path.exists() {
+            tree.save_manifest()?;
+        }
+        Ok(tree)
+    }
+
+    /// Whether `dir` holds an LSM tree
+    pub fn exists(dir: impl AsRef<Path>) -> bool {
+        dir.as_ref().join(MANIFEST_FILE).is_file()
+    }
+
+    /// Tree directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Inserts or replaces `key`
+    pub fn put(&mut self, key: Vec<u8>, value: Vec<u8>) -> Result<()> {
+        self.statistics.puts += 1;
+        self.write(key, Some(value))
+    }
+
+    /// Deletes `key` (writes a tombstone)
+    pub fn delete(&mut self, key: Vec<u8>) -> Result<()> {
+        self.statistics.deletes += 1;
+        self.write(key, None)
+    }
+
+    fn write(&mut self, key: Vec<u8>, value: LsmValue) -> Result<()> {
+        self.log.append(&key, value.as_deref());
+        self.memtable.insert(key, value);
+        if self.memtable.approximate_bytes() >= self.config.memtable_bytes {
+            self.flush_memtable()?;
+        }
+        Ok(())
+    }
+
+    /// Latest live value for `key`
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.gets.fetch_add(1, Ordering::Relaxed);
+        if let Some(value) = self.memtable.get(key) {
+            return Ok(value.clone());
+        }
+        for table in self.levels[0].iter().rev() {
+            if table.bloom_excludes(key) {
+                self.bloom_skips.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+            if let Some(value) = table.get(key)? {
+                return Ok(value);
+            }
+        }
+        for level in &self.levels[1..] {
+            let i = level.partition_point(|t| t.meta().largest.as_slice() < key);
+            let Some(table) = level.get(i) else {
+                continue;
+            };
+            if table.meta().smallest.as_slice() > key {
+                continue;
+            }
+            if table.bloom_excludes(key) {
+                self.bloom_skips.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+            if let Some(value) = table.get(key)? {
+                return Ok(value);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Live entries with keys in `(start, end)`, in key order
+    pub fn range(&self, start: Bound<&[u8]>, end: Bound<&[u8]>) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let mut merged: BTreeMap<Vec<u8>, LsmValue> = BTreeMap::new();
+        // Oldest data first so newer entries overwrite it.
+        for level in self.levels[1..].iter().rev() {
+            for table in level {
+                if range_may_overlap(table.meta(), start, end) {
+                    merged.extend(table.range(start, end)?);
+                }
+            }
+        }
+        for table in &self.levels[0] {
+            if range_may_overlap(table.meta(), start, end) {
+                merged.extend(table.range(start, end)?);
+            }
+        }
+        merged.extend(
+            self.memtable
+                .range(start, end)
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+        Ok(merged
+            .into_iter()
+            .filter_map(|(k, v)| v.map(|v| (k, v)))
+            .collect())
+    }
+
+    /// Every live entry in key order
+    pub fn scan(&self) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.range(Bound::Unbounded, Bound::Unbounded)
+    }
+
+    /// Largest key ever written and not yet compacted away (live or tombstone)
+    pub fn max_key(&self) -> Option<Vec<u8>> {
+        self.levels
+            .iter()
+            .flatten()
+            .map(|t| t.meta().largest.as_slice())
+            .chain(self.memtable.last_key())
+            .max()
+            .map(<[u8]>::to_vec)
+    }
+
+    /// Writes buffered memtable-log records (no fsync); returns how many were written
+    pub fn write_log(&mut self) -> Result<usize> {
+        self.log.write_pending()
+    }
+
+    /// Number of writes not yet in the memtable log
+    pub fn pending_log_records(&self) -> usize {
+        self.log.pending_records()
+    }
+
+    /// Fsyncs the memtable log
+    pub fn sync(&mut self) -> Result<()> {
+        self.log.sync()
+    }
+
+    /// Flushes the memtable to a new L0 table and runs any compaction that became due
+    pub fn flush_memtable(&mut self) -> Result<()> {
+        if self.memtable.is_empty() {
+            return Ok(());
+        }
+        let entries = self.memtable.take();
+        let number = self.allocate_file_number();
+        let meta =
+            sstable::write_sstable(&self.dir, number, &entries, self.config.bloom_bits_per_key)?;
+        self.levels[0].push(Arc::new(SsTable::open(&self.dir, meta)?));
+        self.save_manifest()?;
+        // The table is durable and referenced by the manifest; the log is now redundant.
+        self.log.reset()?;
+        self.statistics.memtable_flushes += 1;
+        self.compact()
+    }
+
+    /// Runs compactions until every level is within its budget
+    pub fn compact(&mut self) -> Result<()> {
+        while let Some(task) =
+            compaction::pick_compaction(&self.levels, &self.config, &mut self.compaction_cursors)
+        {
+            self.run_compaction(task)?;
+        }
+        Ok(())
+    }
+
+    fn run_compaction(&mut self, task: compaction::CompactionTask) -> Result<()> {
+        let target = task.level + 1;
+        let mut sources = Vec::with_capacity(task.overlaps.len() + task.inputs.len());
+        for table in task.overlaps.iter().chain(&task.inputs) {
+            sources.push(table.entries()?);
+        }
+        let bottom = self.levels[target + 1..].iter().all(Vec::is_empty);
+        let merged = compaction::merge_sources(sources, bottom);
+
+        let mut outputs = Vec::new();
+        for run in compaction::split_into_tables(merged, self.config.target_file_bytes) {
+            let number = self.allocate_file_number();
+            let meta =
+                sstable::write_sstable(&self.dir, number, &run, self.config.bloom_bits_per_key)?;
+            self.statistics.compaction_bytes_written += meta.size;
+            outputs.push(Arc::new(SsTable::open(&self.dir, meta)?));
+        }
+
+        let removed: HashSet<u64> = task
+            .inputs
+            .iter()
+            .chain(&task.overlaps)
+            .map(|t| t.meta().number)
+            .collect();
+        self.levels[task.level].retain(|t| !removed.contains(&t.meta().number));
+        self.levels[target].retain(|t| !removed.contains(&t.meta().number));
+        self.levels[target].extend(outputs);
+        self.levels[target].sort_by(|a, b| a.meta().smallest.cmp(&b.meta().smallest));
+        self.save_manifest()?;
+
+        for table in task.inputs.iter().chain(&task.overlaps) {
+            let _ = std::fs::remove_file(table.path());
+        }
+        self.statistics.compactions += 1;
+        Ok(())
+    }
+
+    fn allocate_file_number(&mut self) -> u64 {
+        let n = self.next_file_number;
+        self.next_file_number += 1;
+        n
+    }
+
+    fn save_manifest(&self) -> Result<()> {
+        let manifest = Manifest {
+            next_file_number: self.next_file_number,
+            levels: self
+                .levels
+                .iter()
+                .map(|level| level.iter().map(|t| t.meta().clone()).collect())
+                .collect(),
+        };
+        let bytes = serde_json::to_vec(&manifest)
+            .map_err(|e| Error::internal(format!("LSM manifest encode: {}", e)))?;
+        crate::storage::atomic_file::write_atomic(&self.dir.join(MANIFEST_FILE), &bytes)
+    }
+
+    /// SSTable count per level (L0 first)
+    pub fn level_table_counts(&self) -> Vec<usize> {
+        self.levels.iter().map(Vec::len).collect()
+    }
+
+    /// Entries currently buffered in the memtable
+    pub fn memtable_len(&self) -> usize {
+        self.memtable.len()
+    }
+
+    /// Operation counters
+    pub fn statistics(&self) -> LsmStatistics {
+        LsmStatistics {
+            gets: self.gets.load(Ordering::Relaxed),
+            bloom_skips: self.bloom_skips.load(Ordering::Relaxed),
+            ..self.statistics.clone()
+        }
+    }
+
+    /// Configuration in use
+    pub fn config(&self) -> &LsmConfig {
+        &self.config
+    }
+}
+
+fn range_may_overlap(meta: &SsTableMeta, start: Bound<&[u8]>, end: Bound<&[u8]>) -> bool {
+    let after_start = match start {
+        Bound::Included(s) => meta.largest.as_slice() >= s,
+        Bound::Excluded(s) => meta.largest.as_slice() > s,
+        Bound::Unbounded => true,
+    };
+    let before_end = match end {
+        Bound::Included(e) => meta.smallest.as_slice() <= e,
+        Bound::Excluded(e) => meta.smallest.as_slice() < e,
+        Bound::Unbounded => true,
+    };
+    after_start && before_end
+}
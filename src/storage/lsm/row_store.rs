@@ -0,0 +1,206 @@
+//! Record-id row store on top of [`LsmTree`]
+//!
+//! Presents the heap API used by [`crate::storage::page_manager::PageManager`]: rows are
+//! addressed by [`RecordId`] (`page_id << 32 | slot`), and the tree key is the record id
+//! in big-endian so key order is record-id order. Record ids are allocated sequentially
+//! after the largest key on disk, [`LSM_SLOTS_PER_PAGE`] to a virtual page, which keeps WAL
+//! records (`page_id`, `record_offset`) meaningful for redo/undo exactly as for the heap.
+
+use super::{LsmConfig, LsmTree};
+use crate::common::types::{PageId, RecordId};
+use crate::common::{Error, Result};
+use crate::logging::log_record::{LogRecordType, RecordOperation};
+use std::collections::BTreeSet;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+
+/// Rows per virtual page (scan granularity for `TableScanOperator`; WAL slot offsets are `u16`)
+pub const LSM_SLOTS_PER_PAGE: u32 = 256;
+
+/// Directory holding the LSM tree of `table_name`
+pub fn lsm_table_dir(data_dir: &Path, table_name: &str) -> PathBuf {
+    data_dir.join(format!("{}.lsm", table_name))
+}
+
+/// LSM-backed table rows
+#[derive(Debug)]
+pub struct LsmRowStore {
+    tree: LsmTree,
+    file_id: u32,
+    next_record_id: RecordId,
+}
+
+impl LsmRowStore {
+    /// Opens (or creates) the row store for `table_name` under `data_dir`
+    pub fn open(data_dir: &Path, table_name: &str, config: LsmConfig) -> Result<Self> {
+        let tree = LsmTree::open(lsm_table_dir(data_dir, table_name), config)?;
+        let next_record_id = tree
+            .max_key()
+            .map(|k| decode_key(&k).and_then(next_after))
+            .transpose()?
+            .unwrap_or(0);
+        Ok(Self {
+            tree,
+            file_id: stable_file_id(&format!("{}.lsm", table_name)),
+            next_record_id,
+        })
+    }
+
+    /// Whether `table_name` has an LSM tree under `data_dir`
+    pub fn exists(data_dir: &Path, table_name: &str) -> bool {
+        LsmTree::exists(lsm_table_dir(data_dir, table_name))
+    }
+
+    /// Underlying tree
+    pub fn tree(&self) -> &LsmTree {
+        &self.tree
+    }
+
+    /// Underlying tree (mutable, e.g. to force a flush or compaction)
+    pub fn tree_mut(&mut self) -> &mut LsmTree {
+        &mut self.tree
+    }
+
+    /// WAL file id (stable hash of the directory name, like heap file ids)
+    pub fn file_id(&self) -> u32 {
+        self.file_id
+    }
+
+    /// Appends a row and returns its record id
+    pub fn insert(&mut self, data: &[u8]) -> Result<RecordId> {
+        let record_id = self.next_record_id;
+        self.next_record_id = next_after(record_id)?;
+        self.tree
+            .put(encode_key(record_id).to_vec(), data.to_vec())?;
+        Ok(record_id)
+    }
+
+    /// Replaces the row at `record_id`
+    pub fn update(&mut self, record_id: RecordId, data: &[u8]) -> Result<()> {
+        if self.get(record_id)?.is_none() {
+            return Err(record_not_found(record_id));
+        }
+        self.tree.put(encode_key(record_id).to_vec(), data.to_vec())
+    }
+
+    /// Deletes the row at `record_id`
+    pub fn delete(&mut self, record_id: RecordId) -> Result<()> {
+        if self.get(record_id)?.is_none() {
+            return Err(record_not_found(record_id));
+        }
+        self.tree.delete(encode_key(record_id).to_vec())
+    }
+
+    /// Row at `record_id`
+    pub fn get(&self, record_id: RecordId) -> Result<Option<Vec<u8>>> {
+        self.tree.get(&encode_key(record_id))
+    }
+
+    /// All rows in record-id order
+    pub fn scan(&self) -> Result<Vec<(RecordId, Vec<u8>)>> {
+        self.tree
+            .scan()?
+            .into_iter()
+            .map(|(k, v)| Ok((decode_key(&k)?, v)))
+            .collect()
+    }
+
+    /// Virtual pages that currently hold rows
+    pub fn page_ids(&self) -> Result<Vec<PageId>> {
+        let pages: BTreeSet<PageId> = self.scan()?.into_iter().map(|(rid, _)| rid >> 32).collect();
+        Ok(pages.into_iter().collect())
+    }
+
+    /// Rows of one virtual page as `(slot, bytes)`
+    pub fn records_from_page(&self, page_id: PageId) -> Result<Vec<(u32, Vec<u8>)>> {
+        let start = encode_key(page_id << 32);
+        let end = encode_key((page_id << 32) | u32::MAX as u64);
+        self.tree
+            .range(Bound::Included(&start[..]), Bound::Included(&end[..]))?
+            .into_iter()
+            .map(|(k, v)| Ok((decode_key(&k)? as u32, v)))
+            .collect()
+    }
+
+    /// Replays one WAL record operation (REDO when `redo`, UNDO otherwise).
+    ///
+    /// Puts and tombstones are idempotent, so replaying a record twice is harmless.
+    pub fn apply_recovery(
+        &mut self,
+        record_type: LogRecordType,
+        op: &RecordOperation,
+        redo: bool,
+    ) -> Result<()> {
+        let record_id = (op.page_id << 32) | (op.record_offset as u64);
+        let key = encode_key(record_id);
+        let missing = |what: &str| Error::internal(format!("LSM recovery: missing {}", what));
+        match (record_type, redo) {
+            (LogRecordType::DataInsert, true) | (LogRecordType::DataUpdate, true) => {
+                let data = op.new_data.as_ref().ok_or_else(|| missing("new_data"))?;
+                self.tree.put(key.to_vec(), data.clone())?;
+                self.next_record_id = self.next_record_id.max(next_after(record_id)?);
+            }
+            (LogRecordType::DataUpdate, false) | (LogRecordType::DataDelete, false) => {
+                let data = op.old_data.as_ref().ok_or_else(|| missing("old_data"))?;
+                self.tree.put(key.to_vec(), data.clone())?;
+            }
+            (LogRecordType::DataInsert, false) | (LogRecordType::DataDelete, true) => {
+                self.tree.delete(key.to_vec())?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Writes buffered log records (no fsync); returns how many were written
+    pub fn flush(&mut self) -> Result<usize> {
+        self.tree.write_log()
+    }
+
+    /// Fsyncs the memtable log
+    pub fn sync(&mut self) -> Result<()> {
+        self.tree.sync()
+    }
+
+    /// Writes not yet in the memtable log (the heap's "dirty pages")
+    pub fn pending_writes(&self) -> usize {
+        self.tree.pending_log_records()
+    }
+}
+
+fn encode_key(record_id: RecordId) -> [u8; 8] {
+    record_id.to_be_bytes()
+}
+
+fn decode_key(key: &[u8]) -> Result<RecordId> {
+    let bytes: [u8; 8] = key
+        .try_into()
+        .map_err(|_| Error::database(format!("LSM row key has {} bytes, expected 8", key.len())))?;
+    Ok(RecordId::from_be_bytes(bytes))
+}
+
+fn next_after(record_id: RecordId) -> Result<RecordId> {
+    let page = record_id >> 32;
+    let slot = (record_id & 0xFFFF_FFFF) as u32;
+    let next = if slot + 1 >= LSM_SLOTS_PER_PAGE {
+        page.checked_add(1).map(|p| p << 32)
+    } else {
+        Some(record_id + 1)
+    };
+    next.filter(|n| n >> 32 < u32::MAX as u64)
+        .ok_or_else(|| Error::database("LSM table record ids exhausted"))
+}
+
+fn record_not_found(record_id: RecordId) -> Error {
+    Error::validation(format!("Record not found: {}", record_id))
+}
+
+/// FNV-1a, matching the stable file ids of heap files
+fn stable_file_id(name: &str) -> u32 {
+    let mut h: u32 = 2166136261;
+    for b in name.as_bytes() {
+        h ^= *b as u32;
+        h = h.wrapping_mul(16777619);
+    }
+    h | 1
+}
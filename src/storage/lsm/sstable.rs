@@ -0,0 +1,352 @@
+//! Immutable sorted string tables
+//!
+//! File layout (all integers little-endian):
+//!
+//! ```text
+//! data   : entry*            entry = tag u8 | key_len u32 | key | value_len u32 | value
+//! index  : count u32 | (key_len u32 | key | offset u64)*   one entry per INDEX_INTERVAL entries
+//! bloom  : BloomFilter::to_bytes
+//! footer : index_offset u64 | bloom_offset u64 | entry_count u64 | magic u64
+//! ```
+//!
+//! The sparse index and bloom filter are loaded on open; a point lookup reads one
+//! index interval of the data section.
+
+use super::bloom::BloomFilter;
+use super::memtable::LsmValue;
+use crate::common::{Error, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+
+const SSTABLE_MAGIC: u64 = 0x5253_4442_4c53_4d31; // "RSDBLSM1"
+const FOOTER_LEN: u64 = 32;
+/// Data entries per sparse index entry
+const INDEX_INTERVAL: usize = 16;
+const TAG_PUT: u8 = 0;
+const TAG_DELETE: u8 = 1;
+
+/// Manifest entry describing one SSTable
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SsTableMeta {
+    /// File number (`<number>.sst`)
+    pub number: u64,
+    /// Smallest key in the table
+    pub smallest: Vec<u8>,
+    /// Largest key in the table
+    pub largest: Vec<u8>,
+    /// File size in bytes
+    pub size: u64,
+    /// Number of entries (tombstones included)
+    pub entries: u64,
+}
+
+impl SsTableMeta {
+    /// Whether the table's key range intersects `[smallest, largest]`
+    pub fn overlaps(&self, smallest: &[u8], largest: &[u8]) -> bool {
+        self.smallest.as_slice() <= largest && self.largest.as_slice() >= smallest
+    }
+}
+
+/// File name for table `number`
+pub fn sstable_file_name(number: u64) -> String {
+    format!("{:06}.sst", number)
+}
+
+/// Encoded size of one data entry
+pub fn encoded_entry_len(key: &[u8], value: &LsmValue) -> usize {
+    9 + key.len() + value.as_ref().map_or(0, Vec::len)
+}
+
+/// Writes `entries` (sorted, unique keys, non-empty) as table `number` under `dir`.
+///
+/// The file is written to a temporary name, fsynced and renamed into place, so a crash
+/// never leaves a partial table under its final name.
+pub fn write_sstable(
+    dir: &Path,
+    number: u64,
+    entries: &[(Vec<u8>, LsmValue)],
+    bloom_bits_per_key: usize,
+) -> Result<SsTableMeta> {
+    let (first, last) = match (entries.first(), entries.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Err(Error::internal("cannot write an empty SSTable")),
+    };
+    let path = dir.join(sstable_file_name(number));
+    let tmp = path.with_extension("sst.tmp");
+    let file = File::create(&tmp)?;
+    let mut out = BufWriter::new(file);
+
+    let mut bloom = BloomFilter::with_capacity(entries.len(), bloom_bits_per_key);
+    let mut index: Vec<(&[u8], u64)> = Vec::with_capacity(entries.len() / INDEX_INTERVAL + 1);
+    let mut offset = 0u64;
+    for (i, (key, value)) in entries.iter().enumerate() {
+        if i % INDEX_INTERVAL == 0 {
+            index.push((key, offset));
+        }
+        bloom.insert(key);
+        out.write_all(&[if value.is_some() { TAG_PUT } else { TAG_DELETE }])?;
+        out.write_all(&(key.len() as u32).to_le_bytes())?;
+        out.write_all(key)?;
+        let value = value.as_deref().unwrap_or(&[]);
+        out.write_all(&(value.len() as u32).to_le_bytes())?;
+        out.write_all(value)?;
+        offset += (9 + key.len() + value.len()) as u64;
+    }
+
+    let index_offset = offset;
+    out.write_all(&(index.len() as u32).to_le_bytes())?;
+    offset += 4;
+    for (key, data_offset) in &index {
+        out.write_all(&(key.len() as u32).to_le_bytes())?;
+        out.write_all(key)?;
+        out.write_all(&data_offset.to_le_bytes())?;
+        offset += 12 + key.len() as u64;
+    }
+
+    let bloom_offset = offset;
+    let bloom_bytes = bloom.to_bytes();
+    out.write_all(&bloom_bytes)?;
+    offset += bloom_bytes.len() as u64;
+
+    out.write_all(&index_offset.to_le_bytes())?;
+    out.write_all(&bloom_offset.to_le_bytes())?;
+    out.write_all(&(entries.len() as u64).to_le_bytes())?;
+    out.write_all(&SSTABLE_MAGIC.to_le_bytes())?;
+    offset += FOOTER_LEN;
+
+    let file = out
+        .into_inner()
+        .map_err(|e| Error::database(format!("SSTable write failed: {}", e)))?;
+    file.sync_all()?;
+    drop(file);
+    crate::storage::atomic_file::rename_durable(&tmp, &path)?;
+
+    Ok(SsTableMeta {
+        number,
+        smallest: first.0.clone(),
+        largest: last.0.clone(),
+        size: offset,
+        entries: entries.len() as u64,
+    })
+}
+
+/// Open SSTable: sparse index and bloom filter in memory, data read on demand
+#[derive(Debug)]
+pub struct SsTable {
+    meta: SsTableMeta,
+    path: PathBuf,
+    index: Vec<(Vec<u8>, u64)>,
+    data_end: u64,
+    bloom: BloomFilter,
+    file: Mutex<File>,
+}
+
+impl SsTable {
+    /// Opens table `meta.number` under `dir`
+    pub fn open(dir: &Path, meta: SsTableMeta) -> Result<Self> {
+        let path = dir.join(sstable_file_name(meta.number));
+        let mut file = OpenOptions::new().read(true).open(&path)?;
+        let len = file.metadata()?.len();
+        if len < FOOTER_LEN {
+            return Err(corrupt(&path, "file shorter than footer"));
+        }
+        file.seek(SeekFrom::Start(len - FOOTER_LEN))?;
+        let mut footer = [0u8; FOOTER_LEN as usize];
+        file.read_exact(&mut footer)?;
+        let word = |i: usize| u64::from_le_bytes(footer[i * 8..i * 8 + 8].try_into().unwrap());
+        let (index_offset, bloom_offset, entry_count, magic) = (word(0), word(1), word(2), word(3));
+        if magic != SSTABLE_MAGIC {
+            return Err(corrupt(&path, "bad magic"));
+        }
+        if index_offset > bloom_offset || bloom_offset > len - FOOTER_LEN {
+            return Err(corrupt(&path, "bad section offsets"));
+        }
+
+        let mut meta_block = vec![0u8; (len - FOOTER_LEN - index_offset) as usize];
+        file.seek(SeekFrom::Start(index_offset))?;
+        file.read_exact(&mut meta_block)?;
+        let split = (bloom_offset - index_offset) as usize;
+        let index = decode_index(&meta_block[..split]).map_err(|m| corrupt(&path, m))?;
+        let bloom = BloomFilter::from_bytes(&meta_block[split..])?;
+        if entry_count != meta.entries {
+            return Err(corrupt(&path, "entry count disagrees with manifest"));
+        }
+
+        Ok(Self {
+            meta,
+            path,
+            index,
+            data_end: index_offset,
+            bloom,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Manifest entry for this table
+    pub fn meta(&self) -> &SsTableMeta {
+        &self.meta
+    }
+
+    /// File location
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the bloom filter rules `key` out
+    pub fn bloom_excludes(&self, key: &[u8]) -> bool {
+        !self.bloom.may_contain(key)
+    }
+
+    /// Looks up `key`: `None` when absent, `Some(None)` for a tombstone
+    pub fn get(&self, key: &[u8]) -> Result<Option<LsmValue>> {
+        if key < self.meta.smallest.as_slice()
+            || key > self.meta.largest.as_slice()
+            || self.bloom_excludes(key)
+        {
+            return Ok(None);
+        }
+        let block = self.index.partition_point(|(k, _)| k.as_slice() <= key);
+        if block == 0 {
+            return Ok(None);
+        }
+        let start = self.index[block - 1].1;
+        let end = self.index.get(block).map_or(self.data_end, |(_, off)| *off);
+        for (k, v) in decode_entries(&self.read_range(start, end)?, &self.path)? {
+            match k.as_slice().cmp(key) {
+                std::cmp::Ordering::Less => continue,
+                std::cmp::Ordering::Equal => return Ok(Some(v)),
+                std::cmp::Ordering::Greater => break,
+            }
+        }
+        Ok(None)
+    }
+
+    /// Entries with keys inside `(start, end)`, tombstones included
+    pub fn range(
+        &self,
+        start: Bound<&[u8]>,
+        end: Bound<&[u8]>,
+    ) -> Result<Vec<(Vec<u8>, LsmValue)>> {
+        let first_block = match start {
+            Bound::Included(k) | Bound::Excluded(k) => self
+                .index
+                .partition_point(|(ik, _)| ik.as_slice() <= k)
+                .saturating_sub(1),
+            Bound::Unbounded => 0,
+        };
+        let Some(&(_, from)) = self.index.get(first_block) else {
+            return Ok(Vec::new());
+        };
+        let bytes = self.read_range(from, self.data_end)?;
+        let mut out = Vec::new();
+        for (k, v) in decode_entries(&bytes, &self.path)? {
+            let after_start = match start {
+                Bound::Included(s) => k.as_slice() >= s,
+                Bound::Excluded(s) => k.as_slice() > s,
+                Bound::Unbounded => true,
+            };
+            if !after_start {
+                continue;
+            }
+            let before_end = match end {
+                Bound::Included(e) => k.as_slice() <= e,
+                Bound::Excluded(e) => k.as_slice() < e,
+                Bound::Unbounded => true,
+            };
+            if !before_end {
+                break;
+            }
+            out.push((k, v));
+        }
+        Ok(out)
+    }
+
+    /// Every entry in key order (compaction input)
+    pub fn entries(&self) -> Result<Vec<(Vec<u8>, LsmValue)>> {
+        self.range(Bound::Unbounded, Bound::Unbounded)
+    }
+
+    fn read_range(&self, start: u64, end: u64) -> Result<Vec<u8>> {
+        let mut buf = vec![0u8; (end - start) as usize];
+        let mut file = self.file.lock();
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+fn corrupt(path: &Path, message: &str) -> Error {
+    Error::database(format!("corrupt SSTable {}: {}", path.display(), message))
+}
+
+fn decode_index(bytes: &[u8]) -> std::result::Result<Vec<(Vec<u8>, u64)>, &'static str> {
+    let count =
+        u32::from_le_bytes(bytes.get(..4).ok_or("index truncated")?.try_into().unwrap()) as usize;
+    let mut pos = 4;
+    let mut index = Vec::with_capacity(count);
+    for _ in 0..count {
+        let key_len = u32::from_le_bytes(
+            bytes
+                .get(pos..pos + 4)
+                .ok_or("index truncated")?
+                .try_into()
+                .unwrap(),
+        ) as usize;
+        pos += 4;
+        let key = bytes.get(pos..pos + key_len).ok_or("index truncated")?;
+        pos += key_len;
+        let offset = u64::from_le_bytes(
+            bytes
+                .get(pos..pos + 8)
+                .ok_or("index truncated")?
+                .try_into()
+                .unwrap(),
+        );
+        pos += 8;
+        index.push((key.to_vec(), offset));
+    }
+    Ok(index)
+}
+
+fn decode_entries(bytes: &[u8], path: &Path) -> Result<Vec<(Vec<u8>, LsmValue)>> {
+    let truncated = || corrupt(path, "data entry truncated");
+    let read_u32 = |pos: usize| -> Result<usize> {
+        Ok(u32::from_le_bytes(
+            bytes
+                .get(pos..pos + 4)
+                .ok_or_else(truncated)?
+                .try_into()
+                .unwrap(),
+        ) as usize)
+    };
+    let mut out = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let tag = bytes[pos];
+        let key_len = read_u32(pos + 1)?;
+        let key_start = pos + 5;
+        let key = bytes
+            .get(key_start..key_start + key_len)
+            .ok_or_else(truncated)?
+            .to_vec();
+        let value_len = read_u32(key_start + key_len)?;
+        let value_start = key_start + key_len + 4;
+        let value = bytes
+            .get(value_start..value_start + value_len)
+            .ok_or_else(truncated)?;
+        out.push((
+            key,
+            match tag {
+                TAG_PUT => Some(value.to_vec()),
+                TAG_DELETE => None,
+                _ => return Err(corrupt(path, "unknown entry tag")),
+            },
+        ));
+        pos = value_start + value_len;
+    }
+    Ok(out)
+}
@@ -0,0 +1,217 @@
+//! In-memory table store for rustdb
+//!
+//! Heap-only tables (no pages, files or WAL) backing [`crate::playground`]. Rows are
+//! vectors of [`ColumnValue`] in column order, the values the engine's heap tuples hold; the
+//! primary key, `UNIQUE` and `NOT NULL` constraints are checked on every write. Everything here
+//! builds for `wasm32-unknown-unknown`.
+
+use crate::common::types::{ColumnValue, RowSchema};
+use crate::common::{Error, Result};
+use crate::parser::ast::{ColumnConstraint, CreateTableStatement, DataType, TableConstraint};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Column of an in-memory table
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryColumn {
+    /// Column name (as declared)
+    pub name: String,
+    /// Declared type
+    pub data_type: DataType,
+    /// NOT NULL (implied by PRIMARY KEY)
+    pub not_null: bool,
+    /// Part of a UNIQUE constraint on this column alone
+    pub unique: bool,
+}
+
+/// Heap-only table
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryTable {
+    /// Table name (as declared)
+    pub name: String,
+    /// Columns in declaration order
+    pub columns: Vec<MemoryColumn>,
+    /// Primary key column positions (empty when the table has none)
+    pub primary_key: Vec<usize>,
+    /// Rows in insertion order
+    pub rows: Vec<Vec<ColumnValue>>,
+}
+
+impl MemoryTable {
+    /// Builds an empty table from a parsed `CREATE TABLE`
+    pub fn from_create(stmt: &CreateTableStatement) -> Result<Self> {
+        let mut columns: Vec<MemoryColumn> = Vec::with_capacity(stmt.columns.len());
+        let mut primary_key = Vec::new();
+        for (i, def) in stmt.columns.iter().enumerate() {
+            if columns
+                .iter()
+                .any(|c| c.name.eq_ignore_ascii_case(&def.name))
+            {
+                return Err(Error::validation(format!(
+                    "Duplicate column '{}' in table '{}'",
+                    def.name, stmt.table_name
+                )));
+            }
+            let is_pk = def
+                .constraints
+                .iter()
+                .any(|c| matches!(c, ColumnConstraint::PrimaryKey));
+            if is_pk {
+                primary_key.push(i);
+            }
+            columns.push(MemoryColumn {
+                name: def.name.clone(),
+                data_type: def.data_type.clone(),
+                not_null: is_pk
+                    || def
+                        .constraints
+                        .iter()
+                        .any(|c| matches!(c, ColumnConstraint::NotNull)),
+                unique: def
+                    .constraints
+                    .iter()
+                    .any(|c| matches!(c, ColumnConstraint::Unique)),
+            });
+        }
+        let mut table = Self {
+            name: stmt.table_name.clone(),
+            columns,
+            primary_key,
+            rows: Vec::new(),
+        };
+        for constraint in &stmt.constraints {
+            match constraint {
+                TableConstraint::PrimaryKey(cols) => {
+                    table.primary_key = cols
+                        .iter()
+                        .map(|c| table.require_column(c))
+                        .collect::<Result<_>>()?;
+                    for &i in &table.primary_key {
+                        table.columns[i].not_null = true;
+                    }
+                }
+                TableConstraint::Unique(cols) if cols.len() == 1 => {
+                    let i = table.require_column(&cols[0])?;
+                    table.columns[i].unique = true;
+                }
+                _ => {}
+            }
+        }
+        Ok(table)
+    }
+
+    /// Position of `name` (case-insensitive)
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns
+            .iter()
+            .position(|c| c.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Row layout of the columns, for [`Row`](crate::common::types::Row)s of this table
+    pub fn row_schema(&self) -> Arc<RowSchema> {
+        Arc::new(RowSchema::new(self.columns.iter().map(|c| c.name.as_str())))
+    }
+
+    fn require_column(&self, name: &str) -> Result<usize> {
+        self.column_index(name).ok_or_else(|| {
+            Error::validation(format!("Column '{}' not found in '{}'", name, self.name))
+        })
+    }
+
+    /// Checks NOT NULL / PRIMARY KEY / UNIQUE for `row`, ignoring the row at `skip`
+    /// (the row being updated).
+    pub fn check_row(&self, row: &[ColumnValue], skip: Option<usize>) -> Result<()> {
+        for (col, value) in self.columns.iter().zip(row) {
+            if col.not_null && value.is_null() {
+                return Err(Error::validation(format!(
+                    "NOT NULL constraint failed: {}.{}",
+                    self.name, col.name
+                )));
+            }
+        }
+        let others = self
+            .rows
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| Some(*i) != skip)
+            .map(|(_, r)| r);
+        if !self.primary_key.is_empty() {
+            let key: Vec<&ColumnValue> = self.primary_key.iter().map(|&i| &row[i]).collect();
+            if others.clone().any(|r| {
+                self.primary_key
+                    .iter()
+                    .map(|&i| &r[i])
+                    .eq(key.iter().copied())
+            }) {
+                return Err(Error::validation(format!(
+                    "PRIMARY KEY constraint failed: {}",
+                    self.name
+                )));
+            }
+        }
+        for (i, col) in self.columns.iter().enumerate() {
+            if col.unique && !row[i].is_null() && others.clone().any(|r| r[i] == row[i]) {
+                return Err(Error::validation(format!(
+                    "UNIQUE constraint failed: {}.{}",
+                    self.name, col.name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Catalog of in-memory tables (names are case-insensitive)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemoryStorage {
+    tables: BTreeMap<String, MemoryTable>,
+}
+
+impl MemoryStorage {
+    /// Creates an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a table; errors if the name is taken
+    pub fn create_table(&mut self, table: MemoryTable) -> Result<()> {
+        let key = table.name.to_ascii_lowercase();
+        if self.tables.contains_key(&key) {
+            return Err(Error::validation(format!(
+                "Table '{}' already exists",
+                table.name
+            )));
+        }
+        self.tables.insert(key, table);
+        Ok(())
+    }
+
+    /// Removes a table, returning whether it existed
+    pub fn drop_table(&mut self, name: &str) -> bool {
+        self.tables.remove(&name.to_ascii_lowercase()).is_some()
+    }
+
+    /// Looks up a table
+    pub fn table(&self, name: &str) -> Result<&MemoryTable> {
+        self.tables
+            .get(&name.to_ascii_lowercase())
+            .ok_or_else(|| Error::validation(format!("Table '{}' does not exist", name)))
+    }
+
+    /// Looks up a table for writing
+    pub fn table_mut(&mut self, name: &str) -> Result<&mut MemoryTable> {
+        self.tables
+            .get_mut(&name.to_ascii_lowercase())
+            .ok_or_else(|| Error::validation(format!("Table '{}' does not exist", name)))
+    }
+
+    /// Whether `name` exists
+    pub fn contains(&self, name: &str) -> bool {
+        self.tables.contains_key(&name.to_ascii_lowercase())
+    }
+
+    /// Table names in sorted order
+    pub fn table_names(&self) -> Vec<String> {
+        self.tables.values().map(|t| t.name.clone()).collect()
+    }
+}
@@ -1,23 +1,43 @@
 //! Data storage for rustdb
 
+#[cfg(feature = "native")]
 pub mod advanced_file_manager;
+pub mod atomic_file;
 pub mod block;
+#[cfg(feature = "native")]
 pub mod block_io;
+#[cfg(feature = "native")]
 pub mod cached_file_manager;
 pub mod database_file;
+#[cfg(feature = "native")]
 pub mod file_manager;
+#[cfg(feature = "native")]
+pub mod foreign;
 pub mod index;
 pub mod index_registry;
+#[cfg(feature = "native")]
 pub mod io_optimization;
+#[cfg(feature = "native")]
+pub mod lsm;
+pub mod memory;
+#[cfg(feature = "native")]
 pub mod optimized_file_manager;
 pub mod page;
+#[cfg(feature = "native")]
 pub mod page_manager;
+#[cfg(feature = "native")]
+pub mod remote;
 pub mod row;
 pub mod row_locks;
 pub mod schema_manager;
+#[cfg(feature = "native")]
+pub mod shred;
+#[cfg(feature = "native")]
+pub mod tiering;
 pub mod tuple;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "native"))]
 pub mod tests;
 
-// Page store, file managers, indexes, and tuple layer — modules above.
+// Page store, LSM engine, file managers, indexes, and tuple layer — modules above. File-backed modules
+// are `native`-only; `memory` is the in-memory table store used by `crate::playground`.
@@ -5,15 +5,112 @@
 //! - Asynchronous read/write operations
 //! - Intelligent page caching
 //! - Data prefetching
+//! - Optional memory-mapped reads ([`ReadPath::Mmap`])
+//!
+//! With the mmap read path, pages missing from the page cache are copied straight out of a
+//! read-only mapping of the data file, without a request to the I/O manager, the file manager lock
+//! or a `pread` per page. A file that cannot be mapped, or a page beyond the mapped length after
+//! one remap, is read through the file manager instead.
 
 use crate::common::Result;
 use crate::storage::advanced_file_manager::{AdvancedFileId, AdvancedFileManager, FileInfo};
-use crate::storage::database_file::{DatabaseFileType, ExtensionStrategy, PageId};
+use crate::storage::database_file::{DatabaseFileType, ExtensionStrategy, PageId, BLOCK_SIZE};
 use crate::storage::io_optimization::{BufferedIoManager, IoBufferConfig, IoStatistics};
+use memmap2::Mmap;
 use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+/// How pages missing from the page cache are read from data files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReadPath {
+    /// Through the I/O manager's request queue
+    #[default]
+    Buffered,
+    /// From a read-only memory mapping of the file, with the given access hint
+    Mmap(MmapAdvice),
+}
+
+/// Access pattern hint passed to `madvise` for mapped data files (ignored off Unix)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MmapAdvice {
+    /// No special treatment
+    #[default]
+    Normal,
+    /// Pages are read in order; the kernel reads ahead aggressively
+    Sequential,
+    /// Pages are read in random order; read-ahead is disabled
+    Random,
+    /// The whole file is read soon; the kernel starts reading it in
+    WillNeed,
+}
+
+/// Counters of the mmap read path
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MmapStatistics {
+    /// Files currently mapped
+    pub mapped_files: usize,
+    /// Pages copied out of a mapping
+    pub mapped_reads: u64,
+    /// Page reads that fell back to the buffered path
+    pub fallback_reads: u64,
+    /// Mappings recreated because the file grew
+    pub remaps: u64,
+}
+
+/// Mapping of one data file; `None` when the file could not be mapped.
+struct MappedFile {
+    map: Option<Mmap>,
+}
+
+impl MappedFile {
+    fn open(path: &Path, advice: MmapAdvice) -> Self {
+        let map = std::fs::File::open(path).ok().and_then(|file| {
+            // SAFETY: data files are only ever extended, never truncated, while they are open, so
+            // every mapped byte stays backed by the file. Concurrent writes through the file
+            // manager only make the copied page contents racy, as with a buffered read.
+            unsafe { Mmap::map(&file) }.ok()
+        });
+        #[cfg(unix)]
+        if let Some(map) = &map {
+            let advice = match advice {
+                MmapAdvice::Normal => memmap2::Advice::Normal,
+                MmapAdvice::Sequential => memmap2::Advice::Sequential,
+                MmapAdvice::Random => memmap2::Advice::Random,
+                MmapAdvice::WillNeed => memmap2::Advice::WillNeed,
+            };
+            // Only a hint: a kernel that rejects it still serves the mapping.
+            let _ = map.advise(advice);
+        }
+        #[cfg(not(unix))]
+        let _ = advice;
+        Self { map }
+    }
+
+    /// Copy of `page_id`, or `None` when the page lies beyond the mapping
+    fn page(&self, page_id: PageId) -> Option<Vec<u8>> {
+        // Data starts after the one-block file header (see `DatabaseFile::read_block`).
+        let start = usize::try_from(page_id.checked_add(1)?)
+            .ok()?
+            .checked_mul(BLOCK_SIZE)?;
+        let page = self
+            .map
+            .as_ref()?
+            .get(start..start.checked_add(BLOCK_SIZE)?)?;
+        Some(page.to_vec())
+    }
+}
+
+#[derive(Default)]
+struct MmapReader {
+    files: std::sync::RwLock<HashMap<AdvancedFileId, Arc<MappedFile>>>,
+    mapped_reads: AtomicU64,
+    fallback_reads: AtomicU64,
+    remaps: AtomicU64,
+}
+
 /// Optimized file manager with I/O optimizations
 pub struct OptimizedFileManager {
     /// Base advanced file manager
@@ -22,11 +119,23 @@ pub struct OptimizedFileManager {
     io_manager: Arc<BufferedIoManager>,
     /// File mapping to their I/O handlers
     file_mapping: Arc<RwLock<HashMap<AdvancedFileId, u32>>>,
+    /// Read path for page cache misses
+    read_path: ReadPath,
+    /// Memory mappings of data files (used with [`ReadPath::Mmap`])
+    mmaps: MmapReader,
 }
 
 impl OptimizedFileManager {
     /// Creates a new optimized file manager
     pub fn new(root_dir: impl AsRef<std::path::Path>) -> Result<Self> {
+        Self::with_read_path(root_dir, ReadPath::Buffered)
+    }
+
+    /// Creates a new optimized file manager reading cache misses through `read_path`
+    pub fn with_read_path(
+        root_dir: impl AsRef<std::path::Path>,
+        read_path: ReadPath,
+    ) -> Result<Self> {
         let advanced_manager = Arc::new(RwLock::new(AdvancedFileManager::new(root_dir)?));
 
         let mut io_config = IoBufferConfig::default();
@@ -41,9 +150,16 @@ impl OptimizedFileManager {
             advanced_manager,
             io_manager,
             file_mapping,
+            read_path,
+            mmaps: MmapReader::default(),
         })
     }
 
+    /// Read path used for page cache misses
+    pub fn read_path(&self) -> ReadPath {
+        self.read_path
+    }
+
     /// Creates a new database file with optimizations
     pub async fn create_database_file(
         &self,
@@ -94,6 +210,19 @@ impl OptimizedFileManager {
 
     /// Asynchronously reads a page using cache and prefetching
     pub async fn read_page(&self, file_id: AdvancedFileId, page_id: PageId) -> Result<Vec<u8>> {
+        if let ReadPath::Mmap(advice) = self.read_path {
+            if let Some(data) = self.io_manager.cached_page(file_id, page_id) {
+                return Ok(data);
+            }
+            if let Some(data) = self.read_page_mapped(file_id, page_id, advice).await {
+                self.mmaps.mapped_reads.fetch_add(1, Ordering::Relaxed);
+                return Ok(data);
+            }
+            self.mmaps.fallback_reads.fetch_add(1, Ordering::Relaxed);
+            let mut manager = self.advanced_manager.write().await;
+            return manager.read_page(file_id, page_id);
+        }
+
         // First try to read from I/O manager cache
         match self.io_manager.read_page_async(file_id, page_id).await {
             Ok(data) => Ok(data),
@@ -113,6 +242,39 @@ impl OptimizedFileManager {
         }
     }
 
+    /// Copies `page_id` out of the file's mapping, mapping the file on first use and remapping it
+    /// once if the page lies beyond the mapped length (the file grew).
+    async fn read_page_mapped(
+        &self,
+        file_id: AdvancedFileId,
+        page_id: PageId,
+        advice: MmapAdvice,
+    ) -> Option<Vec<u8>> {
+        let mapped = self.mmaps.files.read().unwrap().get(&file_id).cloned();
+        if let Some(data) = mapped.as_ref().and_then(|m| m.page(page_id)) {
+            return Some(data);
+        }
+        let path = self.get_file_info(file_id).await?.path;
+        let remapped = Arc::new(MappedFile::open(&path, advice));
+        if mapped.is_some() {
+            self.mmaps.remaps.fetch_add(1, Ordering::Relaxed);
+        }
+        let data = remapped.page(page_id);
+        self.mmaps.files.write().unwrap().insert(file_id, remapped);
+        data
+    }
+
+    /// Returns counters of the mmap read path
+    pub fn mmap_statistics(&self) -> MmapStatistics {
+        let files = self.mmaps.files.read().unwrap();
+        MmapStatistics {
+            mapped_files: files.values().filter(|f| f.map.is_some()).count(),
+            mapped_reads: self.mmaps.mapped_reads.load(Ordering::Relaxed),
+            fallback_reads: self.mmaps.fallback_reads.load(Ordering::Relaxed),
+            remaps: self.mmaps.remaps.load(Ordering::Relaxed),
+        }
+    }
+
     /// Asynchronously writes a page with buffering
     pub async fn write_page(
         &self,
@@ -159,6 +321,7 @@ impl OptimizedFileManager {
         // Remove from mapping
         let mut mapping = self.file_mapping.write().await;
         mapping.remove(&file_id);
+        self.mmaps.files.write().unwrap().remove(&file_id);
 
         // Close in base manager
         let mut manager = self.advanced_manager.write().await;
@@ -196,6 +359,8 @@ impl OptimizedFileManager {
     pub async fn defragment_all(&self) {
         let mut manager = self.advanced_manager.write().await;
         manager.defragment_all();
+        // Layouts may have changed; map again on the next read.
+        self.mmaps.files.write().unwrap().clear();
     }
 
     /// Validates integrity of all files
@@ -480,4 +645,86 @@ mod tests {
 
         Ok(())
     }
+
+    /// Writes pages `1..=count` (page `p` filled with `p`) through a plain advanced manager.
+    fn write_test_file(dir: &std::path::Path, name: &str, count: u32) -> Result<()> {
+        let mut manager = AdvancedFileManager::new(dir)?;
+        let file_id = manager.create_database_file(
+            name,
+            DatabaseFileType::Data,
+            1,
+            ExtensionStrategy::Fixed,
+        )?;
+        let first = manager.allocate_pages(file_id, count)?;
+        for page_id in first..first + count as PageId {
+            manager.write_page(file_id, page_id, &vec![page_id as u8; BLOCK_SIZE])?;
+        }
+        manager.close_file(file_id)
+    }
+
+    #[cfg_attr(miri, ignore)]
+    #[tokio::test]
+    async fn test_mmap_read_path_reads_file_pages() -> Result<()> {
+        let temp_dir = TempDir::new().unwrap();
+        write_test_file(temp_dir.path(), "mmap.db", 8)?;
+
+        let mapped = OptimizedFileManager::with_read_path(
+            temp_dir.path(),
+            ReadPath::Mmap(MmapAdvice::Sequential),
+        )?;
+        assert_eq!(mapped.read_path(), ReadPath::Mmap(MmapAdvice::Sequential));
+        let mapped_id = mapped.open_database_file("mmap.db").await?;
+
+        for page_id in 1..=8 {
+            let data = mapped.read_page(mapped_id, page_id).await?;
+            assert_eq!(data, vec![page_id as u8; BLOCK_SIZE]);
+        }
+        let stats = mapped.mmap_statistics();
+        assert_eq!(stats.mapped_files, 1);
+        assert_eq!(stats.mapped_reads, 8);
+        assert_eq!(stats.fallback_reads, 0);
+        let buffered = OptimizedFileManager::new(temp_dir.path())?;
+        assert_eq!(buffered.read_path(), ReadPath::Buffered);
+        assert_eq!(buffered.mmap_statistics(), MmapStatistics::default());
+
+        // Buffered writes are served from the page cache, not the stale mapping.
+        let data = vec![0xAB; BLOCK_SIZE];
+        mapped.write_page(mapped_id, 3, &data).await?;
+        assert_eq!(mapped.read_page(mapped_id, 3).await?, data);
+        assert_eq!(mapped.mmap_statistics().mapped_reads, 8);
+
+        Ok(())
+    }
+
+    #[cfg_attr(miri, ignore)]
+    #[tokio::test]
+    async fn test_mmap_read_path_remaps_grown_file_and_falls_back() -> Result<()> {
+        let temp_dir = TempDir::new().unwrap();
+        write_test_file(temp_dir.path(), "grow.db", 2)?;
+        let manager = OptimizedFileManager::with_read_path(
+            temp_dir.path(),
+            ReadPath::Mmap(MmapAdvice::Random),
+        )?;
+        let file_id = manager.open_database_file("grow.db").await?;
+        assert_eq!(manager.read_page(file_id, 1).await?, vec![1u8; BLOCK_SIZE]);
+
+        // Pages past the original end of the file need a new mapping.
+        let first = manager.allocate_pages(file_id, 64).await?;
+        let last = first + 63;
+        assert_eq!(
+            manager.read_page(file_id, last).await?,
+            vec![0u8; BLOCK_SIZE]
+        );
+        assert_eq!(manager.mmap_statistics().remaps, 1);
+
+        // Beyond the file even after remapping: the buffered path reports the error.
+        assert!(manager.read_page(file_id, 1_000_000).await.is_err());
+        let stats = manager.mmap_statistics();
+        assert_eq!(stats.fallback_reads, 1);
+        assert_eq!(stats.mapped_reads, 2);
+
+        manager.close_file(file_id).await?;
+        assert_eq!(manager.mmap_statistics().mapped_files, 0);
+        Ok(())
+    }
 }
@@ -112,6 +112,29 @@ pub enum PageType {
     Log,
 }
 
+/// Pages of a stored object (table heap or index) and the free space inside them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpaceUsage {
+    /// Pages in use or free
+    pub pages: u64,
+    /// Bytes of those pages that hold no data
+    pub free_bytes: u64,
+    /// Free bytes scattered in gaps too small for the largest entry the pages could still take
+    /// (the object must be reorganized to use them for one)
+    pub fragmented_bytes: u64,
+}
+
+impl SpaceUsage {
+    /// Share of the free bytes that are fragmented (`0.0` without free space)
+    pub fn fragmentation(&self) -> f64 {
+        if self.free_bytes == 0 {
+            0.0
+        } else {
+            self.fragmented_bytes as f64 / self.free_bytes as f64
+        }
+    }
+}
+
 /// Record slot on a page
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RecordSlot {
@@ -678,6 +701,17 @@ impl Page {
         self.slots.iter().any(|slot| slot.is_deleted)
     }
 
+    /// Longest run of free data bytes (at most the page's free space)
+    pub fn largest_free_run(&self) -> u32 {
+        let mut longest = 0;
+        let mut run = 0;
+        for &free in &self.free_space_map {
+            run = if free { run + 1 } else { 0 };
+            longest = longest.max(run);
+        }
+        (longest as u32).min(self.header.free_space)
+    }
+
     /// Scans all records on the page
     pub fn scan_records(&self) -> Result<Vec<(u32, Vec<u8>)>> {
         let mut records = Vec::new();
@@ -740,6 +774,28 @@ impl Page {
         Ok(())
     }
 
+    /// Zeroes the data bytes no live record occupies (left behind by deleted, moved or shrunk
+    /// records) without moving records. Returns how many bytes were not already zero.
+    pub fn scrub_free_space(&mut self) -> usize {
+        let mut live = vec![false; self.data.len()];
+        for slot in self.slots.iter().filter(|s| !s.is_deleted) {
+            let start = (slot.offset as usize).min(live.len());
+            let end = (slot.offset as usize + slot.size as usize).min(live.len());
+            live[start..end].fill(true);
+        }
+        let mut scrubbed = 0;
+        for (byte, live) in self.data.iter_mut().zip(live).skip(PAGE_HEADER_SIZE) {
+            if !live && *byte != 0 {
+                *byte = 0;
+                scrubbed += 1;
+            }
+        }
+        if scrubbed > 0 {
+            self.header.mark_dirty();
+        }
+        scrubbed
+    }
+
     /// Updates a record by offset
     pub fn update_record_by_offset(&mut self, offset: u32, new_data: &[u8]) -> Result<()> {
         // Find slot by offset
@@ -801,6 +857,74 @@ impl Page {
     }
 }
 
+/// Borrowed view of a serialized slotted page
+///
+/// Reads the slot directory in place, so records can be visited without deserializing the page
+/// into a [`Page`] or copying their bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct SlottedPageView<'a> {
+    bytes: &'a [u8],
+    slot_start: usize,
+    slot_count: usize,
+}
+
+impl<'a> SlottedPageView<'a> {
+    /// Returns a view of `bytes`, or `None` if they are not a valid slotted page (e.g. the
+    /// legacy bincode format)
+    pub fn new(bytes: &'a [u8]) -> Option<Self> {
+        if bytes.len() < PAGE_SIZE || bytes[0..2] != SLOTTED_PAGE_MAGIC {
+            return None;
+        }
+        let slot_count = u16::from_le_bytes(bytes[PAGE_SIZE - 2..PAGE_SIZE].try_into().unwrap());
+        let slot_count = slot_count as usize;
+        if slot_count * SLOT_SIZE + 2 > PAGE_SIZE - SLOTTED_DATA_OFFSET {
+            return None;
+        }
+        Some(Self {
+            bytes,
+            slot_start: PAGE_SIZE - 2 - slot_count * SLOT_SIZE,
+            slot_count,
+        })
+    }
+
+    /// Number of slots, including deleted ones
+    pub fn slot_count(&self) -> usize {
+        self.slot_count
+    }
+
+    /// Returns `(offset, bytes)` of the record in slot `index`, or `None` if the slot is
+    /// deleted, out of range or points outside the page
+    pub fn record(&self, index: usize) -> Option<(u32, &'a [u8])> {
+        if index >= self.slot_count {
+            return None;
+        }
+        let base = self.slot_start + index * SLOT_SIZE;
+        let slot = &self.bytes[base..base + SLOT_SIZE];
+        if slot[8] & 1 != 0 {
+            return None;
+        }
+        let offset = u16::from_le_bytes([slot[0], slot[1]]) as usize;
+        let size = u16::from_le_bytes([slot[2], slot[3]]) as usize;
+        if offset + size > PAGE_SIZE {
+            return None;
+        }
+        Some((offset as u32, &self.bytes[offset..offset + size]))
+    }
+
+    /// Returns the live record stored at byte `offset`
+    pub fn record_at_offset(&self, offset: u32) -> Option<&'a [u8]> {
+        self.records()
+            .find(|(record_offset, _)| *record_offset == offset)
+            .map(|(_, data)| data)
+    }
+
+    /// Live records in slot order, as `(offset, bytes)` (same as [`Page::scan_records`])
+    pub fn records(&self) -> impl Iterator<Item = (u32, &'a [u8])> + 'a {
+        let view = *self;
+        (0..view.slot_count).filter_map(move |i| view.record(i))
+    }
+}
+
 /// Page manager
 pub struct PageManager {
     /// Page cache
@@ -4,7 +4,7 @@
 //! including CRUD operations, page splitting/merging, and optimizations.
 
 use crate::common::{
-    types::{PageId, RecordId},
+    types::{PageId, RecordId, MAX_RECORD_SIZE, PAGE_SIZE},
     Error, Result,
 };
 use crate::logging::log_record::{LogOperationData, LogRecord, LogRecordType, RecordOperation};
@@ -14,19 +14,28 @@ use crate::logging::log_record::{LogOperationData, LogRecord, LogRecordType, Rec
 /// For PAGE_SIZE=4096: n ≤ 136. Use 100 for safety with variable record sizes.
 const MAX_RECORDS_PER_PAGE: u32 = 100;
 use crate::storage::{
-    cached_file_manager::CachedFileManager,
+    cached_file_manager::{CachedFileManager, FileIoStatistics, PagePrefetcher, PinnedPage},
     database_file::{DatabaseFileType, ExtensionStrategy},
-    page::Page,
+    io_optimization::PageCacheShardStats,
+    lsm::{LsmConfig, LsmRowStore},
+    page::{Page, SpaceUsage},
+    tiering::{TieringPlan, TieringPolicy, TieringStatistics},
 };
 use dashmap::DashMap;
 use parking_lot::RwLock;
 use std::cmp::Reverse;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-/// Hot-path mutex for [`PageManager`] (used by the SQL engine and WAL flush).
-pub type PageManagerMutex = parking_lot::Mutex<PageManager>;
+/// Hot-path lock for [`PageManager`] (used by the SQL engine and WAL flush).
+///
+/// Page reads ([`PageManager::pin_page`], [`PageManager::records_from_page`],
+/// [`PageManager::get_record`], [`PageManager::all_page_ids`]) take `&self`, so scans and index
+/// lookups of several queries hold the read lock together and load pages in parallel; inserts,
+/// updates, deletes and flushes take the write lock, which covers the whole table (the page
+/// manager has no finer-grained locks of its own).
+pub type PageManagerLock = parking_lot::RwLock<PageManager>;
 
 /// Page manager configuration
 #[derive(Debug, Clone)]
@@ -140,6 +149,38 @@ pub struct PageManagerStatistics {
     pub defragmentation_operations: u64,
 }
 
+/// Physical layout of a table (`CREATE TABLE ... ENGINE <name>`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StorageEngineKind {
+    /// Slotted-page heap file (`<table>.tbl`)
+    #[default]
+    Heap,
+    /// LSM tree (`<table>.lsm/`), see [`crate::storage::lsm`]
+    Lsm,
+}
+
+impl StorageEngineKind {
+    /// Parses an `ENGINE` clause value (case-insensitive)
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "heap" => Ok(Self::Heap),
+            "lsm" => Ok(Self::Lsm),
+            other => Err(Error::validation(format!(
+                "unknown storage engine '{}' (expected heap or lsm)",
+                other
+            ))),
+        }
+    }
+
+    /// Engine name as written in SQL
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Heap => "heap",
+            Self::Lsm => "lsm",
+        }
+    }
+}
+
 /// Page information for the manager
 #[derive(Debug, Clone)]
 pub struct PageInfo {
@@ -176,12 +217,18 @@ pub struct PageManager {
     preallocated_pages: Vec<PageId>,
     /// Dirty pages (modified in current transaction, not yet flushed)
     dirty_pages: HashMap<PageId, Page>,
+    /// Dirty pages of committed transactions whose flush was deferred (see
+    /// [`Self::queue_commit_group`]), in file order.
+    commit_groups: BTreeSet<PageId>,
     /// Per-page latches for fine-grained locking (lock ordering: ascending page_id)
     page_latches: DashMap<PageId, PageLatch>,
     /// Last page that accepted an insert (append-heavy tables try this first).
     last_insert_page: Option<PageId>,
     /// Operation statistics
     statistics: PageManagerStatistics,
+    /// Set for `ENGINE lsm` tables: every record operation goes to the LSM tree instead of
+    /// heap pages (the heap fields above stay empty).
+    lsm: Option<LsmRowStore>,
 }
 
 impl PageManager {
@@ -208,9 +255,11 @@ impl PageManager {
             page_cache: HashMap::new(),
             preallocated_pages: Vec::new(),
             dirty_pages: HashMap::new(),
+            commit_groups: BTreeSet::new(),
             page_latches: DashMap::new(),
             last_insert_page: None,
             statistics: PageManagerStatistics::default(),
+            lsm: None,
         };
 
         // Preallocate initial pages
@@ -219,8 +268,11 @@ impl PageManager {
         Ok(manager)
     }
 
-    /// Opens an existing page manager
+    /// Opens an existing page manager (an LSM table when `<table>.lsm/` exists)
     pub fn open(data_dir: PathBuf, table_name: &str, config: PageManagerConfig) -> Result<Self> {
+        if LsmRowStore::exists(&data_dir, table_name) {
+            return Self::open_lsm(data_dir, table_name, config, LsmConfig::default());
+        }
         let buffer_size = config.buffer_pool_size.max(1);
         let data_dir_clone = data_dir.clone();
         let mut file_manager = CachedFileManager::new(data_dir, buffer_size)?;
@@ -237,9 +289,11 @@ impl PageManager {
             page_cache: HashMap::new(),
             preallocated_pages: Vec::new(),
             dirty_pages: HashMap::new(),
+            commit_groups: BTreeSet::new(),
             page_latches: DashMap::new(),
             last_insert_page: None,
             statistics: PageManagerStatistics::default(),
+            lsm: None,
         };
 
         // Load information about existing pages
@@ -248,9 +302,59 @@ impl PageManager {
         Ok(manager)
     }
 
+    /// Creates or opens the LSM-backed manager for `table_name` (`<table>.lsm/`).
+    ///
+    /// The record-id API is unchanged, so scans, index lookups and WAL recovery work the
+    /// same as for heap tables.
+    pub fn open_lsm(
+        data_dir: PathBuf,
+        table_name: &str,
+        config: PageManagerConfig,
+        lsm_config: LsmConfig,
+    ) -> Result<Self> {
+        let store = LsmRowStore::open(&data_dir, table_name, lsm_config)?;
+        Ok(Self {
+            file_manager: CachedFileManager::new(data_dir.clone(), 1)?,
+            file_id: store.file_id(),
+            data_dir,
+            table_name: table_name.to_string(),
+            config,
+            page_cache: HashMap::new(),
+            preallocated_pages: Vec::new(),
+            dirty_pages: HashMap::new(),
+            commit_groups: BTreeSet::new(),
+            page_latches: DashMap::new(),
+            last_insert_page: None,
+            statistics: PageManagerStatistics::default(),
+            lsm: Some(store),
+        })
+    }
+
+    /// Storage engine backing this table
+    pub fn storage_engine(&self) -> StorageEngineKind {
+        if self.lsm.is_some() {
+            StorageEngineKind::Lsm
+        } else {
+            StorageEngineKind::Heap
+        }
+    }
+
+    /// LSM row store for `ENGINE lsm` tables
+    pub fn lsm_store(&mut self) -> Option<&mut LsmRowStore> {
+        self.lsm.as_mut()
+    }
+
     /// Inserts a new record
     pub fn insert(&mut self, data: &[u8]) -> Result<InsertResult> {
         self.statistics.insert_operations += 1;
+        if let Some(lsm) = self.lsm.as_mut() {
+            let record_id = lsm.insert(data)?;
+            return Ok(InsertResult {
+                record_id,
+                page_id: record_id >> 32,
+                page_split: false,
+            });
+        }
 
         let required = data.len();
 
@@ -301,6 +405,13 @@ impl PageManager {
         condition: Option<Box<dyn Fn(&[u8]) -> bool>>,
     ) -> Result<Vec<(RecordId, Vec<u8>)>> {
         self.statistics.select_operations += 1;
+        if let Some(lsm) = self.lsm.as_ref() {
+            let mut rows = lsm.scan()?;
+            if let Some(cond) = condition {
+                rows.retain(|(_, data)| cond(data));
+            }
+            return Ok(rows);
+        }
 
         let mut results = Vec::new();
 
@@ -317,18 +428,13 @@ impl PageManager {
             // Fine-grained lock: read latch per page
             let latch = self.get_page_latch(page_id);
             let _guard = latch.read();
-            let records = self.get_records_from_page(page_id)?;
+            let page = self.read_pinned_page(page_id)?;
 
-            for (offset, record_data) in records {
-                let record_id = self.generate_record_id(page_id, offset);
-
-                // Apply filter condition
-                if let Some(ref cond) = condition {
-                    if cond(&record_data) {
-                        results.push((record_id, record_data));
-                    }
-                } else {
-                    results.push((record_id, record_data));
+            for (offset, record_data) in page.records() {
+                // Apply filter condition (only matching records are copied)
+                if condition.as_ref().is_none_or(|cond| cond(record_data)) {
+                    let record_id = self.generate_record_id(page_id, offset);
+                    results.push((record_id, record_data.to_vec()));
                 }
             }
         }
@@ -341,20 +447,48 @@ impl PageManager {
     ///
     /// This is a streaming-scan building block for executors that want to avoid materializing
     /// all records at once (see `TableScanOperator`).
-    pub fn all_page_ids(&mut self) -> Result<Vec<PageId>> {
+    pub fn all_page_ids(&self) -> Result<Vec<PageId>> {
+        if let Some(lsm) = self.lsm.as_ref() {
+            return lsm.page_ids();
+        }
         self.get_all_page_ids()
     }
 
     /// Returns raw record payloads from a single page (offset + bytes).
     ///
     /// Uses dirty pages when present; otherwise reads from disk without polluting `dirty_pages`.
-    pub fn records_from_page(&mut self, page_id: PageId) -> Result<Vec<(u32, Vec<u8>)>> {
+    pub fn records_from_page(&self, page_id: PageId) -> Result<Vec<(u32, Vec<u8>)>> {
+        if let Some(lsm) = self.lsm.as_ref() {
+            return lsm.records_from_page(page_id);
+        }
         self.get_records_from_page(page_id)
     }
 
+    /// Pins a single page for reading and returns a guard that borrows its records.
+    ///
+    /// Committed heap pages are borrowed from the buffer pool without copying; dirty and LSM
+    /// pages yield a copy of their records.
+    pub fn pin_page(&self, page_id: PageId) -> Result<PinnedPage> {
+        if let Some(lsm) = self.lsm.as_ref() {
+            return Ok(PinnedPage::from_records(
+                page_id,
+                lsm.records_from_page(page_id)?,
+            ));
+        }
+        self.read_pinned_page(page_id)
+    }
+
     /// Updates a record
     pub fn update(&mut self, record_id: RecordId, new_data: &[u8]) -> Result<UpdateResult> {
         self.statistics.update_operations += 1;
+        if let Some(lsm) = self.lsm.as_mut() {
+            lsm.update(record_id, new_data)?;
+            return Ok(UpdateResult {
+                in_place: true,
+                new_page_id: None,
+                page_split: false,
+            });
+        }
 
         let (page_id, offset) = self.parse_record_id(record_id);
 
@@ -392,13 +526,23 @@ impl PageManager {
     /// Deletes a record
     pub fn delete(&mut self, record_id: RecordId) -> Result<DeleteResult> {
         self.statistics.delete_operations += 1;
+        if let Some(lsm) = self.lsm.as_mut() {
+            lsm.delete(record_id)?;
+            return Ok(DeleteResult {
+                physical_delete: false,
+                page_merge: false,
+            });
+        }
 
         let (page_id, offset) = self.parse_record_id(record_id);
         self.delete_record_internal(page_id, offset)
     }
 
     /// Gets a record by ID
-    pub fn get_record(&mut self, record_id: RecordId) -> Result<Option<Vec<u8>>> {
+    pub fn get_record(&self, record_id: RecordId) -> Result<Option<Vec<u8>>> {
+        if let Some(lsm) = self.lsm.as_ref() {
+            return lsm.get(record_id);
+        }
         let (page_id, _) = self.parse_record_id(record_id);
         // Fine-grained lock: read latch for this page
         let latch = self.get_page_latch(page_id);
@@ -411,6 +555,123 @@ impl PageManager {
         &self.statistics
     }
 
+    /// Per-shard state and latch contention of the buffer pool
+    pub fn buffer_pool_shard_stats(&self) -> Vec<PageCacheShardStats> {
+        self.file_manager.cache_shard_stats()
+    }
+
+    /// Pins held on `page_id` in the buffer pool by readers such as table scans (zero for LSM
+    /// tables and uncached pages)
+    pub fn page_pin_count(&self, page_id: PageId) -> u32 {
+        if self.lsm.is_some() {
+            return 0;
+        }
+        self.file_manager.page_pin_count(self.file_id, page_id)
+    }
+
+    /// Disk I/O, buffer pool and growth counters of this table's heap file (zero for LSM
+    /// tables)
+    pub fn io_statistics(&self) -> FileIoStatistics {
+        if self.lsm.is_some() {
+            return FileIoStatistics::default();
+        }
+        self.file_manager
+            .file_io_statistics(self.file_id)
+            .unwrap_or_default()
+    }
+
+    /// Moves extents of the heap file between the primary file and the cold tier in `cold_dir`
+    /// as `policy` says (see [`crate::storage::tiering`]). Pages keep their ids, so dirty pages
+    /// and record ids stay valid; LSM tables are not tiered.
+    pub fn run_tiering(&mut self, cold_dir: &Path, policy: &TieringPolicy) -> Result<TieringPlan> {
+        if self.lsm.is_some() {
+            return Ok(TieringPlan::default());
+        }
+        self.file_manager
+            .run_tiering(self.file_id, cold_dir, policy)
+    }
+
+    /// Hot and cold extents of the heap file and the moves between them (zero for LSM tables)
+    pub fn tiering_statistics(&self) -> TieringStatistics {
+        if self.lsm.is_some() {
+            return TieringStatistics::default();
+        }
+        self.file_manager
+            .tiering_statistics(self.file_id)
+            .unwrap_or_default()
+    }
+
+    /// Bytes of the table's files on disk (the heap file, or every file of an LSM table's
+    /// directory)
+    pub fn file_size(&self) -> Result<u64> {
+        if self.lsm.is_some() {
+            let dir =
+                crate::storage::lsm::row_store::lsm_table_dir(&self.data_dir, &self.table_name);
+            let mut size = 0;
+            for entry in std::fs::read_dir(dir)? {
+                let meta = entry?.metadata()?;
+                if meta.is_file() {
+                    size += meta.len();
+                }
+            }
+            return Ok(size);
+        }
+        let info = self
+            .file_manager
+            .get_file_info(self.file_id)
+            .ok_or_else(|| Error::database("File info not found"))?;
+        Ok(std::fs::metadata(info.path)?.len())
+    }
+
+    /// Pages of the heap file and the free space on them, including changes not yet flushed.
+    ///
+    /// Free bytes of a page outside its longest free run count as fragmented. LSM tables have
+    /// no pages to fill: they report their size in pages and no free space.
+    pub fn space_usage(&mut self) -> Result<SpaceUsage> {
+        if self.lsm.is_some() {
+            return Ok(SpaceUsage {
+                pages: self.file_size()?.div_ceil(PAGE_SIZE as u64),
+                ..SpaceUsage::default()
+            });
+        }
+        let total_pages = self
+            .file_manager
+            .get_file_info(self.file_id)
+            .ok_or_else(|| Error::database("File info not found"))?
+            .total_pages;
+        let page_ids = self.get_all_page_ids()?;
+        let mut usage = SpaceUsage {
+            pages: total_pages.max(page_ids.len() as u64),
+            ..SpaceUsage::default()
+        };
+        // Pages never written hold no records.
+        usage.free_bytes = (usage.pages - page_ids.len() as u64) * MAX_RECORD_SIZE as u64;
+        for page_id in page_ids {
+            let loaded;
+            let page = match self.dirty_pages.get(&page_id) {
+                Some(page) => page,
+                None => {
+                    loaded =
+                        Page::from_bytes(&self.file_manager.read_page(self.file_id, page_id)?)?;
+                    &loaded
+                }
+            };
+            let free = page.free_space();
+            usage.free_bytes += u64::from(free);
+            usage.fragmented_bytes += u64::from(free - page.largest_free_run());
+        }
+        Ok(usage)
+    }
+
+    /// Opens a prefetcher loading heap pages of this table into its buffer pool (`None` for
+    /// LSM tables)
+    pub(crate) fn page_prefetcher(&self) -> Option<PagePrefetcher> {
+        if self.lsm.is_some() {
+            return None;
+        }
+        self.file_manager.page_prefetcher(self.file_id).ok()
+    }
+
     /// Record id encoding used by this manager (`page_id` high bits, slot byte offset low bits).
     pub fn record_id_for_slot(page_id: PageId, slot_offset: u32) -> RecordId {
         ((page_id as u64) << 32) | (slot_offset as u64)
@@ -426,6 +687,9 @@ impl PageManager {
         if op.file_id != self.file_id() {
             return Ok(());
         }
+        if let Some(lsm) = self.lsm.as_mut() {
+            return lsm.apply_recovery(record_type, op, redo);
+        }
         let page_id = op.page_id;
         let slot_off = op.record_offset as u32;
         let rid = Self::record_id_for_slot(page_id, slot_off);
@@ -528,23 +792,67 @@ impl PageManager {
 
     /// Number of heap pages dirty in memory (not yet flushed).
     pub(crate) fn dirty_page_count(&self) -> usize {
+        if let Some(lsm) = self.lsm.as_ref() {
+            return usize::from(lsm.pending_writes() > 0);
+        }
         self.dirty_pages.len()
     }
 
-    /// Writes all dirty heap pages without calling `sync_file`.
+    /// Writes all dirty heap pages without calling `sync_file`, in file order.
     ///
     /// Callers that flush multiple page managers (e.g. `COMMIT` over several tables) should
     /// invoke this per manager, then [`Self::sync_heap_file`] once per distinct `file_id`.
     pub fn flush_dirty_pages_no_sync(&mut self) -> Result<usize> {
+        if let Some(lsm) = self.lsm.as_mut() {
+            return lsm.flush();
+        }
+        self.commit_groups.clear();
+        let mut page_ids: Vec<PageId> = self.dirty_pages.keys().copied().collect();
+        page_ids.sort_unstable();
+        self.write_dirty_pages(&page_ids)
+    }
+
+    /// Marks every page dirty now as written by a transaction that committed without flushing
+    /// it, so a later [`Self::flush_commit_groups_no_sync`] writes them together. Pages dirtied
+    /// afterwards (by transactions still running) wait for their own commit or a checkpoint.
+    /// Returns the pages queued in all.
+    pub fn queue_commit_group(&mut self) -> usize {
+        if self.lsm.is_none() {
+            self.commit_groups.extend(self.dirty_pages.keys().copied());
+        }
+        self.queued_commit_page_count()
+    }
+
+    /// Pages queued by [`Self::queue_commit_group`] and not yet written.
+    pub fn queued_commit_page_count(&self) -> usize {
+        if let Some(lsm) = self.lsm.as_ref() {
+            return usize::from(lsm.pending_writes() > 0);
+        }
+        self.commit_groups.len()
+    }
+
+    /// Writes the pages queued by [`Self::queue_commit_group`] that are still dirty, in file
+    /// order, without calling `sync_file` (LSM tables flush their memtable).
+    pub fn flush_commit_groups_no_sync(&mut self) -> Result<usize> {
+        if let Some(lsm) = self.lsm.as_mut() {
+            return lsm.flush();
+        }
+        let page_ids: Vec<PageId> = std::mem::take(&mut self.commit_groups)
+            .into_iter()
+            .filter(|id| self.dirty_pages.contains_key(id))
+            .collect();
+        self.write_dirty_pages(&page_ids)
+    }
+
+    /// Writes the dirty pages `page_ids` in that order, `batch_flush_size` at a time.
+    fn write_dirty_pages(&mut self, page_ids: &[PageId]) -> Result<usize> {
         let batch_size = self.config.batch_flush_size.max(1);
         let mut flushed = 0;
 
-        while !self.dirty_pages.is_empty() {
-            let page_ids: Vec<PageId> = self.dirty_pages.keys().take(batch_size).copied().collect();
-
+        for page_ids in page_ids.chunks(batch_size) {
             let to_flush: Vec<(PageId, Vec<u8>)> = {
                 let mut batch = Vec::with_capacity(page_ids.len());
-                for page_id in page_ids {
+                for &page_id in page_ids {
                     if let Some(page) = self.dirty_pages.remove(&page_id) {
                         let serialized = page.to_bytes()?;
                         self.update_page_cache(page_id, &page);
@@ -554,9 +862,8 @@ impl PageManager {
                 batch
             };
 
-            let count = to_flush.len();
-            if count == 0 {
-                break;
+            if to_flush.is_empty() {
+                continue;
             }
 
             let file_id = self.file_id;
@@ -586,6 +893,9 @@ impl PageManager {
 
     /// Fsyncs the heap file for this page manager.
     pub fn sync_heap_file(&mut self) -> Result<()> {
+        if let Some(lsm) = self.lsm.as_mut() {
+            return lsm.sync();
+        }
         self.file_manager.sync_file(self.file_id)
     }
 
@@ -603,6 +913,12 @@ impl PageManager {
     /// Performs page defragmentation
     pub fn defragment(&mut self) -> Result<u32> {
         self.statistics.defragmentation_operations += 1;
+        if let Some(lsm) = self.lsm.as_mut() {
+            // Compaction is the LSM equivalent of defragmentation.
+            let before = lsm.tree().statistics().compactions;
+            lsm.tree_mut().compact()?;
+            return Ok((lsm.tree().statistics().compactions - before) as u32);
+        }
 
         let mut defragmented_count = 0;
 
@@ -628,6 +944,24 @@ impl PageManager {
         Ok(defragmented_count)
     }
 
+    /// Overwrites what deleted records left in the table file: every heap page is rewritten with
+    /// its free space zeroed (LSM tables compact instead). Returns the pages rewritten.
+    pub fn scrub_free_space(&mut self) -> Result<u32> {
+        if self.lsm.is_some() {
+            return self.defragment();
+        }
+        let page_ids = self.get_all_page_ids()?;
+        for &page_id in &page_ids {
+            let latch = self.get_page_latch(page_id);
+            let _guard = latch.write();
+            // Pages loaded from disk come back without deleted records' bytes; pages already in
+            // memory may still hold them. Either way the flush below rewrites the whole page.
+            self.get_or_load_page(page_id)?.scrub_free_space();
+        }
+        self.flush_dirty_pages()?;
+        Ok(page_ids.len() as u32)
+    }
+
     /// Performs batch insert operations
     pub fn batch_insert(&mut self, records: Vec<Vec<u8>>) -> Result<Vec<InsertResult>> {
         let mut results = Vec::with_capacity(records.len());
@@ -664,20 +998,31 @@ impl PageManager {
         }
     }
 
-    /// Gets records from page for read-only access. Uses dirty_pages if present, else reads
-    /// from disk without adding to dirty_pages (avoids polluting dirty_pages and infinite loop).
-    fn get_records_from_page(&mut self, page_id: PageId) -> Result<Vec<(u32, Vec<u8>)>> {
+    /// Pins a heap page for read-only access. Uses dirty_pages if present, else reads through
+    /// the buffer pool without adding to dirty_pages (avoids polluting dirty_pages and infinite
+    /// loop).
+    fn read_pinned_page(&self, page_id: PageId) -> Result<PinnedPage> {
+        if let Some(page) = self.dirty_pages.get(&page_id) {
+            return Ok(PinnedPage::from_records(page_id, page.scan_records()?));
+        }
+        self.file_manager.pin_page(self.file_id, page_id)
+    }
+
+    /// Gets records from page for read-only access.
+    fn get_records_from_page(&self, page_id: PageId) -> Result<Vec<(u32, Vec<u8>)>> {
         if let Some(page) = self.dirty_pages.get(&page_id) {
             return page.scan_records();
         }
-        let page_data = self.file_manager.read_page(self.file_id, page_id)?;
-        let page = Page::from_bytes(&page_data)?;
-        page.scan_records()
+        let page = self.file_manager.pin_page(self.file_id, page_id)?;
+        Ok(page
+            .records()
+            .map(|(offset, data)| (offset, data.to_vec()))
+            .collect())
     }
 
     /// Gets a single record for read-only access without adding page to dirty_pages.
     fn get_record_from_page(
-        &mut self,
+        &self,
         page_id: PageId,
         record_id: RecordId,
     ) -> Result<Option<Vec<u8>>> {
@@ -685,9 +1030,8 @@ impl PageManager {
         if let Some(page) = self.dirty_pages.get(&page_id) {
             return Ok(page.get_record_at_offset(offset).map(|s| s.to_vec()));
         }
-        let page_data = self.file_manager.read_page(self.file_id, page_id)?;
-        let page = Page::from_bytes(&page_data)?;
-        Ok(page.get_record_at_offset(offset).map(|s| s.to_vec()))
+        let page = self.file_manager.pin_page(self.file_id, page_id)?;
+        Ok(page.record_at(offset).map(|s| s.to_vec()))
     }
 
     /// Preallocates pages
@@ -954,7 +1298,7 @@ impl PageManager {
     }
 
     /// Gets all page IDs
-    fn get_all_page_ids(&mut self) -> Result<Vec<PageId>> {
+    fn get_all_page_ids(&self) -> Result<Vec<PageId>> {
         // Get file information and count pages
         let file_info = self
             .file_manager
@@ -968,7 +1312,8 @@ impl PageManager {
             // Include if in dirty_pages (not yet flushed) or has data on disk
             if self.dirty_pages.contains_key(&page_id) {
                 page_ids.push(page_id);
-            } else if let Ok(page_data) = self.file_manager.read_page(self.file_id, page_id) {
+            } else if let Ok(page_data) = self.file_manager.read_page_shared(self.file_id, page_id)
+            {
                 if !page_data.iter().all(|&b| b == 0) {
                     page_ids.push(page_id);
                 }
@@ -1126,7 +1471,7 @@ impl PageManager {
 /// Spawns a background task that flushes dirty pages every `interval_ms` ms.
 /// Use when `defer_data_flush` is true. Returns a handle to abort the task when done.
 pub fn spawn_deferred_flush_task(
-    pm: Arc<PageManagerMutex>,
+    pm: Arc<PageManagerLock>,
     interval_ms: u64,
 ) -> tokio::task::JoinHandle<()> {
     tokio::spawn(async move {
@@ -1134,7 +1479,7 @@ pub fn spawn_deferred_flush_task(
             tokio::time::interval(tokio::time::Duration::from_millis(interval_ms.max(1)));
         loop {
             interval.tick().await;
-            let mut guard = pm.lock();
+            let mut guard = pm.write();
             let _ = guard.flush_dirty_pages();
         }
     })
@@ -1144,6 +1489,9 @@ impl Drop for PageManager {
     fn drop(&mut self) {
         // Flush any dirty pages before closing
         let _ = self.flush_dirty_pages();
+        if self.lsm.is_some() {
+            return;
+        }
         // Synchronize and close file when manager is destroyed
         let _ = self.file_manager.sync_file(self.file_id);
         let _ = self.file_manager.close_file(self.file_id);
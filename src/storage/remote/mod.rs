@@ -0,0 +1,171 @@
+//! Remote object storage for backups and archived WAL segments.
+//!
+//! [`RemoteStorage`] is a flat key/value store of files: [`LocalStorage`] keeps them in a
+//! directory (a mounted volume, or tests), [`s3::S3Storage`] (feature `s3`) in an S3-compatible
+//! bucket. [`open_remote`] picks one from a URL: `s3://bucket/prefix`, `file:///path` or a plain
+//! path. Backups are pushed and pulled with
+//! [`crate::network::replication::push_backup`] / [`crate::network::replication::pull_backup`];
+//! the engine archives closed WAL segments with `SqlEngineConfig::wal_archive_url`.
+
+#[cfg(feature = "s3")]
+pub mod s3;
+
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Failure of a [`RemoteStorage`] operation.
+#[derive(Debug, Error)]
+pub enum RemoteStorageError {
+    #[error("I/O: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("object {0} not found")]
+    NotFound(String),
+    /// The server answered with an error status (after any retries).
+    #[error("HTTP {status}: {message}")]
+    Http { status: u16, message: String },
+    /// The server could not be reached (after any retries).
+    #[error("transport: {0}")]
+    Transport(String),
+    #[error("{0}")]
+    Invalid(String),
+}
+
+/// Flat store of named objects; keys are `/`-separated relative paths.
+pub trait RemoteStorage: Send + Sync + std::fmt::Debug {
+    /// Stores the `len` bytes read from `data` under `key`, replacing any existing object.
+    fn put(&self, key: &str, data: &mut dyn Read, len: u64) -> Result<(), RemoteStorageError>;
+
+    /// Writes the object `key` into `out`; returns its length.
+    fn get(&self, key: &str, out: &mut dyn Write) -> Result<u64, RemoteStorageError>;
+
+    /// Keys starting with `prefix`, sorted.
+    fn list(&self, prefix: &str) -> Result<Vec<String>, RemoteStorageError>;
+
+    /// Removes `key`; removing a missing object is not an error.
+    fn delete(&self, key: &str) -> Result<(), RemoteStorageError>;
+}
+
+/// [`RemoteStorage`] in a local directory.
+#[derive(Debug, Clone)]
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, RemoteStorageError> {
+        let rel = Path::new(key);
+        if key.is_empty() || !rel.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(RemoteStorageError::Invalid(format!(
+                "object key {key:?} is not a relative path"
+            )));
+        }
+        Ok(self.root.join(rel))
+    }
+}
+
+impl RemoteStorage for LocalStorage {
+    fn put(&self, key: &str, data: &mut dyn Read, len: u64) -> Result<(), RemoteStorageError> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".part");
+        let tmp = PathBuf::from(tmp);
+        let mut file = std::fs::File::create(&tmp)?;
+        let copied = std::io::copy(&mut data.take(len), &mut file)?;
+        if copied != len {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(RemoteStorageError::Invalid(format!(
+                "{key}: source ended after {copied} of {len} bytes"
+            )));
+        }
+        file.sync_all()?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    fn get(&self, key: &str, out: &mut dyn Write) -> Result<u64, RemoteStorageError> {
+        let mut file = match std::fs::File::open(self.path(key)?) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(RemoteStorageError::NotFound(key.to_string()))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        Ok(std::io::copy(&mut file, out)?)
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<String>, RemoteStorageError> {
+        let mut out = Vec::new();
+        if self.root.is_dir() {
+            list_dir(&self.root, "", &mut out)?;
+        }
+        out.retain(|k| k.starts_with(prefix) && !k.ends_with(".part"));
+        out.sort();
+        Ok(out)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), RemoteStorageError> {
+        match std::fs::remove_file(self.path(key)?) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn list_dir(root: &Path, rel: &str, out: &mut Vec<String>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(root.join(rel))? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let child = if rel.is_empty() {
+            name
+        } else {
+            format!("{rel}/{name}")
+        };
+        if entry.file_type()?.is_dir() {
+            list_dir(root, &child, out)?;
+        } else {
+            out.push(child);
+        }
+    }
+    Ok(())
+}
+
+/// Opens the remote storage `url` names: `s3://bucket[/prefix]` (credentials and endpoint from
+/// the environment, see [`s3::S3Config::from_env`]), `file:///path` or a plain directory path.
+pub fn open_remote(url: &str) -> Result<Arc<dyn RemoteStorage>, RemoteStorageError> {
+    if let Some(rest) = url.strip_prefix("s3://") {
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            return Err(RemoteStorageError::Invalid(format!(
+                "{url}: missing bucket"
+            )));
+        }
+        #[cfg(feature = "s3")]
+        {
+            let config = s3::S3Config::from_env(bucket, prefix)?;
+            return Ok(Arc::new(s3::S3Storage::new(config)?));
+        }
+        #[cfg(not(feature = "s3"))]
+        {
+            let _ = prefix;
+            return Err(RemoteStorageError::Invalid(format!(
+                "{url}: built without the `s3` feature"
+            )));
+        }
+    }
+    let path = url.strip_prefix("file://").unwrap_or(url);
+    if path.is_empty() || (url.contains("://") && !url.starts_with("file://")) {
+        return Err(RemoteStorageError::Invalid(format!(
+            "unsupported remote storage URL {url:?}"
+        )));
+    }
+    Ok(Arc::new(LocalStorage::new(path)))
+}
@@ -0,0 +1,497 @@
+//! S3-compatible [`RemoteStorage`] (AWS S3, MinIO, Ceph RGW, ...) over `ureq`.
+//!
+//! Requests are signed with AWS Signature Version 4. Objects larger than
+//! [`S3Config::part_size`] go up as multipart uploads (aborted when a part fails), and every
+//! request is retried with exponential backoff on transport errors, `429` and `5xx`.
+
+use super::{RemoteStorage, RemoteStorageError};
+use ring::{digest, hmac};
+use std::io::{Read, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Smallest part S3 accepts in a multipart upload (except the last one).
+pub const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+
+/// Where and how [`S3Storage`] talks to the bucket.
+#[derive(Clone)]
+pub struct S3Config {
+    /// Service endpoint, e.g. `https://s3.eu-west-1.amazonaws.com` or `http://127.0.0.1:9000`.
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    /// Prepended to every key (`""`, or ending in `/`).
+    pub prefix: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+    /// Address the bucket as `endpoint/bucket/key` instead of `bucket.endpoint/key`.
+    pub path_style: bool,
+    /// Objects above this size are uploaded in parts of this size.
+    pub part_size: usize,
+    /// Attempts per request, the first included.
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubles on each further one.
+    pub retry_backoff: Duration,
+}
+
+impl std::fmt::Debug for S3Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("S3Config")
+            .field("endpoint", &self.endpoint)
+            .field("region", &self.region)
+            .field("bucket", &self.bucket)
+            .field("prefix", &self.prefix)
+            .field("access_key_id", &self.access_key_id)
+            .field("path_style", &self.path_style)
+            .field("part_size", &self.part_size)
+            .field("max_attempts", &self.max_attempts)
+            .finish_non_exhaustive()
+    }
+}
+
+impl S3Config {
+    /// Config for `bucket` under `prefix` from the usual AWS environment: `AWS_ACCESS_KEY_ID`,
+    /// `AWS_SECRET_ACCESS_KEY`, optional `AWS_SESSION_TOKEN`, `AWS_REGION` (or
+    /// `AWS_DEFAULT_REGION`, else `us-east-1`) and `AWS_ENDPOINT_URL` for an S3-compatible
+    /// server, which is then addressed path-style.
+    pub fn from_env(bucket: &str, prefix: &str) -> Result<Self, RemoteStorageError> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let required = |name: &str| {
+            var(name).ok_or_else(|| RemoteStorageError::Invalid(format!("{name} is not set")))
+        };
+        let region = var("AWS_REGION")
+            .or_else(|| var("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|| "us-east-1".to_string());
+        let custom_endpoint = var("AWS_ENDPOINT_URL");
+        let mut prefix = prefix.trim_matches('/').to_string();
+        if !prefix.is_empty() {
+            prefix.push('/');
+        }
+        Ok(Self {
+            path_style: custom_endpoint.is_some(),
+            endpoint: custom_endpoint
+                .unwrap_or_else(|| format!("https://s3.{region}.amazonaws.com")),
+            region,
+            bucket: bucket.to_string(),
+            prefix,
+            access_key_id: required("AWS_ACCESS_KEY_ID")?,
+            secret_access_key: required("AWS_SECRET_ACCESS_KEY")?,
+            session_token: var("AWS_SESSION_TOKEN"),
+            part_size: 16 * 1024 * 1024,
+            max_attempts: 5,
+            retry_backoff: Duration::from_millis(200),
+        })
+    }
+}
+
+/// [`RemoteStorage`] in an S3 bucket (see the module docs).
+#[derive(Debug)]
+pub struct S3Storage {
+    config: S3Config,
+    agent: ureq::Agent,
+}
+
+impl S3Storage {
+    pub fn new(config: S3Config) -> Result<Self, RemoteStorageError> {
+        if config.part_size < MIN_PART_SIZE {
+            return Err(RemoteStorageError::Invalid(format!(
+                "S3 part size must be at least {MIN_PART_SIZE} bytes"
+            )));
+        }
+        if !config.endpoint.starts_with("http://") && !config.endpoint.starts_with("https://") {
+            return Err(RemoteStorageError::Invalid(format!(
+                "S3 endpoint {:?} is not an http(s) URL",
+                config.endpoint
+            )));
+        }
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(Duration::from_secs(10))
+            .timeout_read(Duration::from_secs(120))
+            .timeout_write(Duration::from_secs(120))
+            .build();
+        Ok(Self { config, agent })
+    }
+
+    /// Sends one request, signing each attempt and retrying transient failures.
+    fn send(
+        &self,
+        method: &str,
+        key: Option<&str>,
+        query: &[(&str, &str)],
+        body: &[u8],
+    ) -> Result<ureq::Response, RemoteStorageError> {
+        let (scheme, endpoint_host) = self
+            .config
+            .endpoint
+            .trim_end_matches('/')
+            .split_once("://")
+            .expect("checked in S3Storage::new");
+        let (host, mut path) = if self.config.path_style {
+            (
+                endpoint_host.to_string(),
+                format!("/{}", self.config.bucket),
+            )
+        } else {
+            (
+                format!("{}.{endpoint_host}", self.config.bucket),
+                String::new(),
+            )
+        };
+        path.push('/');
+        if let Some(key) = key {
+            path.push_str(&uri_encode(&format!("{}{key}", self.config.prefix), false));
+        }
+        let mut query: Vec<(String, String)> = query
+            .iter()
+            .map(|(k, v)| (uri_encode(k, true), uri_encode(v, true)))
+            .collect();
+        query.sort();
+        let query_string = query
+            .iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect::<Vec<_>>()
+            .join("&");
+        let url = if query_string.is_empty() {
+            format!("{scheme}://{host}{path}")
+        } else {
+            format!("{scheme}://{host}{path}?{query_string}")
+        };
+        let payload_sha256 = to_hex(digest::digest(&digest::SHA256, body).as_ref());
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let amz_date = amz_date(SystemTime::now());
+            let mut headers = vec![
+                ("host".to_string(), host.clone()),
+                ("x-amz-content-sha256".to_string(), payload_sha256.clone()),
+                ("x-amz-date".to_string(), amz_date.clone()),
+            ];
+            if let Some(token) = &self.config.session_token {
+                headers.push(("x-amz-security-token".to_string(), token.clone()));
+            }
+            let authorization = sign_v4(&SigningRequest {
+                method,
+                path: &path,
+                query: &query,
+                headers: &headers,
+                payload_sha256: &payload_sha256,
+                amz_date: &amz_date,
+                region: &self.config.region,
+                access_key_id: &self.config.access_key_id,
+                secret_access_key: &self.config.secret_access_key,
+            });
+            let mut request = self
+                .agent
+                .request(method, &url)
+                .set("authorization", &authorization);
+            for (name, value) in headers.iter().filter(|(n, _)| n != "host") {
+                request = request.set(name, value);
+            }
+            let err = match request.send_bytes(body) {
+                Ok(response) => return Ok(response),
+                Err(ureq::Error::Status(status, response)) => {
+                    let retry = status == 429 || status >= 500;
+                    let message = error_message(response);
+                    if !retry || attempt >= self.config.max_attempts {
+                        return Err(match status {
+                            404 => RemoteStorageError::NotFound(key.unwrap_or("").to_string()),
+                            _ => RemoteStorageError::Http { status, message },
+                        });
+                    }
+                    RemoteStorageError::Http { status, message }
+                }
+                Err(ureq::Error::Transport(t)) => {
+                    let err = RemoteStorageError::Transport(t.to_string());
+                    if attempt >= self.config.max_attempts {
+                        return Err(err);
+                    }
+                    err
+                }
+            };
+            let delay = self.config.retry_backoff * 2u32.saturating_pow(attempt - 1);
+            tracing::warn!(%method, %path, attempt, error = %err, "S3 request failed, retrying");
+            std::thread::sleep(delay);
+        }
+    }
+
+    fn put_multipart(
+        &self,
+        key: &str,
+        data: &mut dyn Read,
+        len: u64,
+    ) -> Result<(), RemoteStorageError> {
+        let created = self.send("POST", Some(key), &[("uploads", "")], &[])?;
+        let body = response_text(created)?;
+        let upload_id = xml_values(&body, "UploadId")
+            .pop()
+            .ok_or_else(|| RemoteStorageError::Invalid(format!("no UploadId in {body:?}")))?;
+        let uploaded = self.upload_parts(key, &upload_id, data, len);
+        let completed = uploaded.and_then(|etags| {
+            let mut xml = String::from("<CompleteMultipartUpload>");
+            for (i, etag) in etags.iter().enumerate() {
+                xml.push_str(&format!(
+                    "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>",
+                    i + 1,
+                    xml_escape(etag)
+                ));
+            }
+            xml.push_str("</CompleteMultipartUpload>");
+            let response = self.send(
+                "POST",
+                Some(key),
+                &[("uploadId", &upload_id)],
+                xml.as_bytes(),
+            )?;
+            // S3 reports some completion failures in a 200 response.
+            let body = response_text(response)?;
+            if body.contains("<Error>") {
+                return Err(RemoteStorageError::Http {
+                    status: 200,
+                    message: error_text(&body),
+                });
+            }
+            Ok(())
+        });
+        if completed.is_err() {
+            let _ = self.send("DELETE", Some(key), &[("uploadId", &upload_id)], &[]);
+        }
+        completed
+    }
+
+    /// Uploads `len` bytes of `data` as parts of `upload_id`; returns their ETags in order.
+    fn upload_parts(
+        &self,
+        key: &str,
+        upload_id: &str,
+        data: &mut dyn Read,
+        len: u64,
+    ) -> Result<Vec<String>, RemoteStorageError> {
+        let mut etags = Vec::new();
+        let mut remaining = len;
+        let mut buf = vec![0u8; self.config.part_size];
+        while remaining > 0 {
+            let n = remaining.min(buf.len() as u64) as usize;
+            data.read_exact(&mut buf[..n])?;
+            remaining -= n as u64;
+            let part = (etags.len() + 1).to_string();
+            let response = self.send(
+                "PUT",
+                Some(key),
+                &[("partNumber", &part), ("uploadId", upload_id)],
+                &buf[..n],
+            )?;
+            let etag = response.header("etag").ok_or_else(|| {
+                RemoteStorageError::Invalid(format!("part {part} of {key} has no ETag"))
+            })?;
+            etags.push(etag.to_string());
+        }
+        Ok(etags)
+    }
+}
+
+impl RemoteStorage for S3Storage {
+    fn put(&self, key: &str, data: &mut dyn Read, len: u64) -> Result<(), RemoteStorageError> {
+        if len > self.config.part_size as u64 {
+            return self.put_multipart(key, data, len);
+        }
+        let mut body = vec![0u8; len as usize];
+        data.read_exact(&mut body)?;
+        self.send("PUT", Some(key), &[], &body)?;
+        Ok(())
+    }
+
+    fn get(&self, key: &str, out: &mut dyn Write) -> Result<u64, RemoteStorageError> {
+        let response = self.send("GET", Some(key), &[], &[])?;
+        Ok(std::io::copy(&mut response.into_reader(), out)?)
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<String>, RemoteStorageError> {
+        let full_prefix = format!("{}{prefix}", self.config.prefix);
+        let mut keys = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let mut query = vec![("list-type", "2"), ("prefix", full_prefix.as_str())];
+            if let Some(t) = &token {
+                query.push(("continuation-token", t));
+            }
+            let body = response_text(self.send("GET", None, &query, &[])?)?;
+            keys.extend(
+                xml_values(&body, "Key")
+                    .into_iter()
+                    .filter_map(|k| k.strip_prefix(&self.config.prefix).map(str::to_string)),
+            );
+            let truncated =
+                xml_values(&body, "IsTruncated").first().map(String::as_str) == Some("true");
+            token = xml_values(&body, "NextContinuationToken").pop();
+            if !truncated || token.is_none() {
+                break;
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), RemoteStorageError> {
+        match self.send("DELETE", Some(key), &[], &[]) {
+            Ok(_) | Err(RemoteStorageError::NotFound(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Canonical request of one Signature V4 signature. `headers` are the signed headers with
+/// lowercase names; they include `host`, `x-amz-date` and `x-amz-content-sha256`.
+pub(crate) struct SigningRequest<'a> {
+    pub method: &'a str,
+    /// URI-encoded path.
+    pub path: &'a str,
+    /// URI-encoded query parameters, sorted.
+    pub query: &'a [(String, String)],
+    pub headers: &'a [(String, String)],
+    pub payload_sha256: &'a str,
+    /// `YYYYMMDD'T'HHMMSS'Z'`
+    pub amz_date: &'a str,
+    pub region: &'a str,
+    pub access_key_id: &'a str,
+    pub secret_access_key: &'a str,
+}
+
+/// `Authorization` header value for `req`.
+pub(crate) fn sign_v4(req: &SigningRequest<'_>) -> String {
+    let mut headers: Vec<(&str, String)> = req
+        .headers
+        .iter()
+        .map(|(n, v)| (n.as_str(), v.trim().to_string()))
+        .collect();
+    headers.sort();
+    let signed_headers = headers
+        .iter()
+        .map(|(n, _)| *n)
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_headers: String = headers.iter().map(|(n, v)| format!("{n}:{v}\n")).collect();
+    let canonical_query = req
+        .query
+        .iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join("&");
+    let canonical_request = format!(
+        "{}\n{}\n{canonical_query}\n{canonical_headers}\n{signed_headers}\n{}",
+        req.method, req.path, req.payload_sha256
+    );
+    let date = &req.amz_date[..8];
+    let scope = format!("{date}/{}/s3/aws4_request", req.region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{scope}\n{}",
+        req.amz_date,
+        to_hex(digest::digest(&digest::SHA256, canonical_request.as_bytes()).as_ref())
+    );
+    let mut key = format!("AWS4{}", req.secret_access_key).into_bytes();
+    for part in [date, req.region, "s3", "aws4_request"] {
+        key = hmac_sha256(&key, part.as_bytes());
+    }
+    let signature = to_hex(&hmac_sha256(&key, string_to_sign.as_bytes()));
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{scope},SignedHeaders={signed_headers},Signature={signature}",
+        req.access_key_id
+    )
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data)
+        .as_ref()
+        .to_vec()
+}
+
+/// Percent-encodes all but RFC 3986 unreserved characters (and `/` unless `slash`).
+pub(crate) fn uri_encode(s: &str, slash: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            b'/' if !slash => out.push('/'),
+            _ => out.push_str(&format!("%{b:02X}")),
+        }
+    }
+    out
+}
+
+/// `time` as `YYYYMMDD'T'HHMMSS'Z'` (UTC).
+pub(crate) fn amz_date(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}{month:02}{day:02}T{:02}{:02}{:02}Z",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn response_text(response: ureq::Response) -> Result<String, RemoteStorageError> {
+    response.into_string().map_err(RemoteStorageError::Io)
+}
+
+fn error_message(response: ureq::Response) -> String {
+    let status = response.status_text().to_string();
+    match response.into_string() {
+        Ok(body) if body.contains("<Error>") => error_text(&body),
+        _ => status,
+    }
+}
+
+fn error_text(body: &str) -> String {
+    let code = xml_values(body, "Code").pop().unwrap_or_default();
+    let message = xml_values(body, "Message").pop().unwrap_or_default();
+    format!("{code}: {message}")
+}
+
+/// Text of every `<tag>...</tag>` element in `xml`, unescaped.
+pub(crate) fn xml_values(xml: &str, tag: &str) -> Vec<String> {
+    let open = format!("<{tag}>");
+    let close = format!("</{tag}>");
+    let mut out = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        let Some(end) = rest.find(&close) else { break };
+        out.push(
+            rest[..end]
+                .replace("&quot;", "\"")
+                .replace("&apos;", "'")
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&amp;", "&"),
+        );
+        rest = &rest[end + close.len()..];
+    }
+    out
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -2,6 +2,7 @@
 //!
 //! Table-level locks remain for DDL, heap scan fallback, and `INSERT`.
 
+use crate::common::memory_tracking::{memory_scope, MemoryTag};
 use crate::common::types::RecordId;
 use dashmap::DashMap;
 use parking_lot::RwLock;
@@ -41,6 +42,7 @@ impl RowLockManager {
             return f();
         }
         let wait_clock = row_lock_phase_log_enabled().then(Instant::now);
+        let memory = memory_scope(MemoryTag::LockTables);
         let table_owned = table.to_string();
         let mut arcs: Vec<Arc<RwLock<()>>> = Vec::with_capacity(rids.len());
         for rid in rids {
@@ -51,6 +53,7 @@ impl RowLockManager {
                 .clone();
             arcs.push(lock);
         }
+        drop(memory);
         let guards: Vec<_> = arcs.iter().map(|l| l.write()).collect();
         if let Some(t0) = wait_clock {
             info!(
@@ -0,0 +1,141 @@
+//! Sealed tuples for crypto-shredding (`CREATE TABLE ... ENCRYPT BY <column>`).
+//!
+//! A sealed tuple keeps its id, versions and deletion flag in the clear and carries its column
+//! values as one AES-256-GCM ciphertext under [`SEALED_COLUMN`], encrypted with the key of the
+//! row's subject. Each engine keeps the keys of its data directory in a [`Keyring`], which
+//! [`Keyring::decode`] opens stored tuples with; once a subject's key is forgotten, tuples sealed
+//! with it read as deleted wherever their bytes survive (heap pages, WAL segments, copies of the
+//! table files).
+
+use crate::common::types::{ColumnValue, DataType};
+use crate::common::{bincode_io, Error, Result};
+use crate::storage::tuple::Tuple;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::collections::HashMap;
+use std::sync::{PoisonError, RwLock};
+
+/// Column holding a sealed tuple's ciphertext (the NUL keeps it clear of SQL column names).
+pub const SEALED_COLUMN: &str = "\u{0}sealed";
+
+/// Key length in bytes (AES-256).
+pub const KEY_LEN: usize = 32;
+
+/// Identifies a subject key inside sealed tuples.
+pub type KeyId = [u8; 16];
+
+/// Generates a key id and key.
+pub fn generate_key() -> Result<(KeyId, [u8; KEY_LEN])> {
+    let rng = SystemRandom::new();
+    let mut id = KeyId::default();
+    let mut key = [0u8; KEY_LEN];
+    rng.fill(&mut id)
+        .and_then(|()| rng.fill(&mut key))
+        .map_err(|_| Error::internal("system random generator failed"))?;
+    Ok((id, key))
+}
+
+/// Subject keys of one engine, by id.
+#[derive(Default)]
+pub struct Keyring {
+    keys: RwLock<HashMap<KeyId, LessSafeKey>>,
+}
+
+impl Keyring {
+    /// Makes `key` available to [`Keyring::seal`] and [`Keyring::unseal`] under `id`.
+    pub fn register(&self, id: KeyId, key: &[u8]) -> Result<()> {
+        let key = UnboundKey::new(&AES_256_GCM, key)
+            .map_err(|_| Error::validation("subject keys must be 32 bytes"))?;
+        self.keys
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(id, LessSafeKey::new(key));
+        Ok(())
+    }
+
+    /// Drops the key `id`; returns whether it was loaded.
+    pub fn forget(&self, id: &KeyId) -> bool {
+        self.keys
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(id)
+            .is_some()
+    }
+
+    /// `tuple` with its values sealed under the key `id`.
+    pub fn seal(&self, tuple: &Tuple, id: &KeyId) -> Result<Tuple> {
+        let mut payload = bincode_io::serialize(&tuple.values).map_err(Error::from)?;
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| Error::internal("system random generator failed"))?;
+        {
+            let keys = self.keys.read().unwrap_or_else(PoisonError::into_inner);
+            let key = keys
+                .get(id)
+                .ok_or_else(|| Error::validation("subject key is not loaded"))?;
+            key.seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(id),
+                &mut payload,
+            )
+            .map_err(|_| Error::internal("tuple encryption failed"))?;
+        }
+        let mut envelope = Vec::with_capacity(id.len() + NONCE_LEN + payload.len());
+        envelope.extend_from_slice(id);
+        envelope.extend_from_slice(&nonce);
+        envelope.extend_from_slice(&payload);
+        let mut sealed = tuple.clone();
+        sealed.values = HashMap::from([(
+            SEALED_COLUMN.to_string(),
+            ColumnValue::new(DataType::Blob(envelope)),
+        )]);
+        Ok(sealed)
+    }
+
+    /// Opens a sealed `tuple` in place. Without its key (forgotten, or never loaded) the tuple
+    /// reads as deleted and keeps no values; tuples that are not sealed are left as they are.
+    pub fn unseal(&self, tuple: &mut Tuple) {
+        if tuple.values.len() != 1 {
+            return;
+        }
+        let Some(ColumnValue {
+            data_type: DataType::Blob(envelope),
+            ..
+        }) = tuple.values.get(SEALED_COLUMN)
+        else {
+            return;
+        };
+        match self.open(envelope) {
+            Some(values) => tuple.values = values,
+            None => {
+                tuple.values.clear();
+                tuple.is_deleted = true;
+            }
+        }
+    }
+
+    /// Deserializes a stored tuple and opens it when it is sealed.
+    pub fn decode(&self, bytes: &[u8]) -> Result<Tuple> {
+        let mut tuple = Tuple::from_bytes(bytes)?;
+        self.unseal(&mut tuple);
+        Ok(tuple)
+    }
+
+    fn open(&self, envelope: &[u8]) -> Option<HashMap<String, ColumnValue>> {
+        let (id, rest) = envelope.split_at_checked(16)?;
+        let (nonce, ciphertext) = rest.split_at_checked(NONCE_LEN)?;
+        let id: KeyId = id.try_into().ok()?;
+        let mut buf = ciphertext.to_vec();
+        let keys = self.keys.read().unwrap_or_else(PoisonError::into_inner);
+        let plain = keys
+            .get(&id)?
+            .open_in_place(
+                Nonce::try_assume_unique_for_key(nonce).ok()?,
+                Aad::from(&id),
+                &mut buf,
+            )
+            .ok()?;
+        bincode_io::deserialize(plain).ok()
+    }
+}
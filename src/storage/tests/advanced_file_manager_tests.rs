@@ -74,6 +74,46 @@ fn test_allocate_pages() {
     let _ = start_page; // Use variable
 }
 
+#[test]
+fn test_allocate_pages_for_keeps_each_owner_contiguous() {
+    let (mut manager, _temp_dir) = create_test_advanced_file_manager();
+
+    let file_id = manager
+        .create_database_file(
+            "extent_test.dat",
+            DatabaseFileType::Data,
+            1,
+            ExtensionStrategy::Fixed,
+        )
+        .unwrap();
+
+    // Interleaved single-page allocations of two tables
+    let mut orders: Vec<PageId> = Vec::new();
+    let mut users: Vec<PageId> = Vec::new();
+    for _ in 0..10 {
+        orders.push(manager.allocate_pages_for(file_id, 1, 1).unwrap());
+        users.push(manager.allocate_pages_for(file_id, 2, 1).unwrap());
+    }
+    assert!(orders.windows(2).all(|w| w[1] == w[0] + 1));
+    assert!(users.windows(2).all(|w| w[1] == w[0] + 1));
+    assert!(orders[0] >= 1);
+
+    let free_before = manager.get_file_info(file_id).unwrap().free_pages;
+    assert_eq!(manager.release_extents(file_id, 1).unwrap(), 64);
+    assert_eq!(
+        manager.get_file_info(file_id).unwrap().free_pages,
+        free_before + 64
+    );
+
+    // A request larger than one extent gets a run of whole extents
+    let start = manager.allocate_pages_for(file_id, 3, 100).unwrap();
+    assert!(start >= 1);
+    assert_eq!(
+        manager.allocate_pages_for(file_id, 3, 28).unwrap(),
+        start + 100
+    );
+}
+
 #[test]
 fn test_free_pages() {
     let (mut manager, _temp_dir) = create_test_advanced_file_manager();
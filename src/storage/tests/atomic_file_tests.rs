@@ -0,0 +1,60 @@
+use crate::storage::atomic_file::{
+    rename_durable, temp_path, write_atomic, write_atomic_with_sync,
+};
+use tempfile::TempDir;
+
+#[test]
+fn test_write_atomic_replaces_whole_file() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("meta.json");
+    write_atomic(&path, b"first version, longer than the second").unwrap();
+    write_atomic(&path, b"second").unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"second");
+    assert!(!temp_path(&path).exists());
+}
+
+#[test]
+fn test_write_atomic_ignores_stale_temp_file() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("meta.json");
+    write_atomic(&path, b"committed").unwrap();
+    // Left behind by a crash between the temp write and the rename.
+    std::fs::write(temp_path(&path), b"half-writ").unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"committed");
+
+    write_atomic_with_sync(&path, b"next", false).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"next");
+    assert!(!temp_path(&path).exists());
+}
+
+#[test]
+fn test_write_atomic_failure_keeps_previous_contents() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("meta.json");
+    write_atomic(&path, b"old").unwrap();
+    // The temp path is a directory, so the write fails before the rename.
+    std::fs::create_dir(temp_path(&path)).unwrap();
+    assert!(write_atomic(&path, b"new").is_err());
+    assert_eq!(std::fs::read(&path).unwrap(), b"old");
+}
+
+#[test]
+fn test_rename_durable_across_directories() {
+    let dir = TempDir::new().unwrap();
+    let from = dir.path().join("a.tmp");
+    std::fs::create_dir(dir.path().join("sub")).unwrap();
+    let to = dir.path().join("sub").join("a");
+    std::fs::write(&from, b"data").unwrap();
+    rename_durable(&from, &to).unwrap();
+    assert!(!from.exists());
+    assert_eq!(std::fs::read(&to).unwrap(), b"data");
+}
+
+#[test]
+fn test_temp_path_is_next_to_target() {
+    let path = std::path::Path::new("/data/.rustdb/catalog.json");
+    assert_eq!(
+        temp_path(path),
+        std::path::Path::new("/data/.rustdb/catalog.json.tmp")
+    );
+}
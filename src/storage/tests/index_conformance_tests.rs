@@ -0,0 +1,250 @@
+//! Index trait conformance: every index implementation against the `BTreeMap` model
+
+use crate::storage::index::conformance::{
+    check_ops, run_conformance_suite, IndexCapabilities, IndexOp,
+};
+use crate::storage::index::{
+    BPlusTree, CollisionResolution, HashIndex, Index, LinearHashIndex, PagedBPlusTree,
+    SimpleHashIndex,
+};
+use std::cell::Cell;
+use tempfile::TempDir;
+
+const SEEDS: u64 = 24;
+
+fn assert_conforms<I, F>(make: F, caps: IndexCapabilities)
+where
+    I: Index<Key = i64, Value = u64>,
+    F: FnMut() -> I,
+{
+    if let Err(failure) = run_conformance_suite(make, caps, SEEDS) {
+        panic!("index conformance failure: {}", failure);
+    }
+}
+
+#[test]
+fn test_btree_conformance_default_degree() {
+    assert_conforms(BPlusTree::new_default, IndexCapabilities::ordered());
+}
+
+#[test]
+fn test_btree_conformance_min_degree() {
+    // Tiny nodes force deep trees and frequent internal splits.
+    assert_conforms(|| BPlusTree::new(3), IndexCapabilities::ordered());
+    assert_conforms(|| BPlusTree::new(4), IndexCapabilities::ordered());
+}
+
+#[test]
+fn test_simple_hash_index_conformance() {
+    assert_conforms(SimpleHashIndex::new, IndexCapabilities::point_only());
+}
+
+#[test]
+fn test_hash_index_conformance() {
+    assert_conforms(
+        || HashIndex::new(16, CollisionResolution::Chaining, 0.75),
+        IndexCapabilities::point_only(),
+    );
+    assert_conforms(
+        || HashIndex::new(16, CollisionResolution::OpenAddressing, 0.75),
+        IndexCapabilities::point_only(),
+    );
+}
+
+/// A [`LinearHashIndex`] seen as a map from integers to single ids, as the suite drives it.
+struct LinearHashAdapter(LinearHashIndex);
+
+impl Index for LinearHashAdapter {
+    type Key = i64;
+    type Value = u64;
+    fn insert(&mut self, key: i64, value: u64) -> crate::common::Result<()> {
+        self.0.insert(key.to_string(), vec![value])
+    }
+    fn search(&self, key: &i64) -> crate::common::Result<Option<u64>> {
+        Ok(self
+            .0
+            .search(&key.to_string())?
+            .and_then(|ids| ids.first().copied()))
+    }
+    fn delete(&mut self, key: &i64) -> crate::common::Result<bool> {
+        self.0.delete(&key.to_string())
+    }
+    fn range_search(&self, _: &i64, _: &i64) -> crate::common::Result<Vec<(i64, u64)>> {
+        // String keys do not sort like integers; the point-only suite ignores the result.
+        Ok(Vec::new())
+    }
+    fn size(&self) -> usize {
+        self.0.size()
+    }
+}
+
+#[test]
+fn test_linear_hash_index_conformance() {
+    assert_conforms(
+        || LinearHashAdapter(LinearHashIndex::in_memory()),
+        IndexCapabilities::point_only(),
+    );
+    let dir = TempDir::new().expect("tempdir");
+    let n = Cell::new(0);
+    assert_conforms(
+        || {
+            n.set(n.get() + 1);
+            let path = dir.path().join(format!("ix{}.lhx", n.get()));
+            LinearHashAdapter(LinearHashIndex::open(&path).expect("open"))
+        },
+        IndexCapabilities::point_only(),
+    );
+}
+
+#[test]
+fn test_linear_hash_index_grows_and_reopens() -> crate::common::Result<()> {
+    let dir = TempDir::new().expect("tempdir");
+    let path = dir.path().join("t.ix.lhx");
+    let shape = {
+        let mut index = LinearHashIndex::open(&path)?;
+        for i in 0..5_000u64 {
+            index.insert(format!("key{i}"), vec![i, i + 1])?;
+        }
+        for i in (0..5_000u64).step_by(5) {
+            assert!(index.delete(&format!("key{i}"))?);
+        }
+        index.checkpoint(42)?;
+        index.shape()
+    };
+    assert!(shape.buckets > 4, "buckets did not split: {shape:?}");
+    assert_eq!(shape.entries, 8_000);
+
+    let index = LinearHashIndex::open(&path)?;
+    assert_eq!(index.shape(), shape);
+    assert_eq!(index.checkpoint_lsn(), Some(42));
+    assert!(!index.modified_since_checkpoint());
+    assert_eq!(index.size(), 4_000);
+    assert_eq!(index.search(&"key7".to_string())?, Some(vec![7, 8]));
+    assert_eq!(index.search(&"key10".to_string())?, None);
+    Ok(())
+}
+
+/// A [`PagedBPlusTree`] seen as an ordered map from integers to single ids: keys are offset
+/// to be non-negative and zero-padded, so they sort like the integers.
+struct PagedBTreeAdapter(PagedBPlusTree);
+
+fn ordered_key(key: i64) -> String {
+    format!("{:020}", (i128::from(key) - i128::from(i64::MIN)) as u64)
+}
+
+impl Index for PagedBTreeAdapter {
+    type Key = i64;
+    type Value = u64;
+    fn insert(&mut self, key: i64, value: u64) -> crate::common::Result<()> {
+        self.0.insert(ordered_key(key), vec![value])
+    }
+    fn search(&self, key: &i64) -> crate::common::Result<Option<u64>> {
+        Ok(self
+            .0
+            .search(&ordered_key(*key))?
+            .and_then(|ids| ids.first().copied()))
+    }
+    fn delete(&mut self, key: &i64) -> crate::common::Result<bool> {
+        self.0.delete(&ordered_key(*key))
+    }
+    fn range_search(&self, start: &i64, end: &i64) -> crate::common::Result<Vec<(i64, u64)>> {
+        Ok(self
+            .0
+            .range_search(&ordered_key(*start), &ordered_key(*end))?
+            .into_iter()
+            .map(|(key, ids)| {
+                let offset: u64 = key.parse().expect("ordered key");
+                ((i128::from(offset) + i128::from(i64::MIN)) as i64, ids[0])
+            })
+            .collect())
+    }
+    fn size(&self) -> usize {
+        self.0.size()
+    }
+}
+
+#[test]
+fn test_paged_btree_conformance() {
+    assert_conforms(
+        || PagedBTreeAdapter(PagedBPlusTree::in_memory()),
+        IndexCapabilities::ordered(),
+    );
+    let dir = TempDir::new().expect("tempdir");
+    let n = Cell::new(0);
+    assert_conforms(
+        || {
+            n.set(n.get() + 1);
+            let path = dir.path().join(format!("ix{}.bpt", n.get()));
+            PagedBTreeAdapter(PagedBPlusTree::open(&path).expect("open"))
+        },
+        IndexCapabilities::ordered(),
+    );
+}
+
+#[test]
+fn test_paged_btree_grows_and_reopens() -> crate::common::Result<()> {
+    let dir = TempDir::new().expect("tempdir");
+    let path = dir.path().join("t.ix.bpt");
+    let height = {
+        let mut tree = PagedBPlusTree::open(&path)?;
+        for i in 0..5_000u64 {
+            tree.insert(format!("key{i:04}"), vec![i, i + 1])?;
+        }
+        for i in (0..5_000u64).step_by(5) {
+            assert!(tree.delete(&format!("key{i:04}"))?);
+        }
+        tree.checkpoint(42)?;
+        tree.height()
+    };
+    assert!(height > 1, "tree did not split");
+
+    let tree = PagedBPlusTree::open(&path)?;
+    assert_eq!(tree.height(), height);
+    assert_eq!(tree.checkpoint_lsn(), Some(42));
+    assert!(!tree.modified_since_checkpoint());
+    assert_eq!(tree.size(), 4_000);
+    assert_eq!(tree.search(&"key0007".to_string())?, Some(vec![7, 8]));
+    assert_eq!(tree.search(&"key0010".to_string())?, None);
+    let range = tree.range_search(&"key0009".to_string(), &"key0012".to_string())?;
+    assert_eq!(
+        range,
+        vec![
+            ("key0009".to_string(), vec![9, 10]),
+            ("key0011".to_string(), vec![11, 12]),
+            ("key0012".to_string(), vec![12, 13]),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_check_ops_reports_divergence() {
+    // An index that forgets everything must be caught on the first lookup.
+    struct Forgetful(usize);
+    impl Index for Forgetful {
+        type Key = i64;
+        type Value = u64;
+        fn insert(&mut self, _: i64, _: u64) -> crate::common::Result<()> {
+            self.0 += 1;
+            Ok(())
+        }
+        fn search(&self, _: &i64) -> crate::common::Result<Option<u64>> {
+            Ok(None)
+        }
+        fn delete(&mut self, _: &i64) -> crate::common::Result<bool> {
+            Ok(false)
+        }
+        fn range_search(&self, _: &i64, _: &i64) -> crate::common::Result<Vec<(i64, u64)>> {
+            Ok(Vec::new())
+        }
+        fn size(&self) -> usize {
+            self.0
+        }
+    }
+
+    let ops = [IndexOp::Insert(1, 10), IndexOp::Search(1)];
+    let failure = check_ops(&mut Forgetful(0), &ops, IndexCapabilities::point_only())
+        .expect_err("divergence not detected");
+    assert_eq!(failure.step, 1);
+    assert!(failure.to_string().contains("search(1)"));
+}
@@ -1,7 +1,8 @@
 //! Tests for IndexRegistry
 
 use crate::common::Result;
-use crate::storage::index_registry::IndexRegistry;
+use crate::storage::index::{BPlusTree, Index};
+use crate::storage::index_registry::{index_key, IndexOptions, IndexRegistry};
 use std::collections::HashMap;
 
 #[test]
@@ -111,6 +112,72 @@ fn test_index_registry_named_index_insert() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_index_registry_load_into_named_index_appends_to_existing_keys() -> Result<()> {
+    let mut registry = IndexRegistry::new();
+    registry.create_index("t", "idx_k", vec!["k".to_string()])?;
+    let mut m = HashMap::new();
+    m.insert("k".to_string(), "1".to_string());
+    registry.insert_into_named_index("t", "idx_k", 7, &m)?;
+    registry.load_into_named_index(
+        "t",
+        "idx_k",
+        [(index_key(["1"]), vec![8, 9]), (index_key(["2"]), vec![10])],
+    )?;
+    let (ids, _) = registry
+        .lookup_record_ids_by_equalities("t", &m)?
+        .expect("lookup");
+    assert_eq!(ids, vec![7, 8, 9]);
+    m.insert("k".to_string(), "2".to_string());
+    let (ids, _) = registry
+        .lookup_record_ids_by_equalities("t", &m)?
+        .expect("lookup");
+    assert_eq!(ids, vec![10]);
+    assert!(registry
+        .load_into_named_index("t", "missing", Vec::new())
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_index_registry_index_build_catches_up_on_logged_changes() -> Result<()> {
+    let mut registry = IndexRegistry::new();
+    let changes =
+        registry.begin_index_build("t", "idx_k", vec!["k".to_string()], IndexOptions::default())?;
+    assert!(registry
+        .create_index("t", "idx_k", vec!["k".to_string()])
+        .is_err());
+    let row = |k: &str| HashMap::from([("k".to_string(), k.to_string())]);
+    registry.insert_into_indexes("t", 1, &row("1"))?;
+    registry.insert_into_indexes("t", 2, &row("1"))?;
+    registry.update_indexes("t", 2, &row("1"), &row("2"))?;
+    registry.delete_from_indexes("t", 3, &row("3"))?;
+    assert_eq!(changes.lock().unwrap().len(), 5);
+    assert!(registry
+        .lookup_record_ids_by_equalities("t", &row("1"))?
+        .is_none());
+
+    // The scan already saw record 1 and record 3, which was deleted after the build began.
+    let mut tree = BPlusTree::new_default();
+    tree.insert("1".to_string(), vec![1])?;
+    tree.insert("3".to_string(), vec![3])?;
+    registry.finish_index_build("t", "idx_k", tree)?;
+    assert!(changes.lock().unwrap().is_empty());
+    assert!(registry.list_index_builds_for_table("t").is_empty());
+    let ids = |k: &str| {
+        registry
+            .lookup_record_ids_by_equalities("t", &row(k))
+            .map(|r| r.map(|(ids, _)| ids))
+    };
+    assert_eq!(ids("1")?, Some(vec![1]));
+    assert_eq!(ids("2")?, Some(vec![2]));
+    assert_eq!(ids("3")?, Some(vec![]));
+    assert!(registry
+        .finish_index_build("t", "idx_k", BPlusTree::new_default())
+        .is_err());
+    Ok(())
+}
+
 #[test]
 fn test_index_registry_lookup_miss_no_index() -> Result<()> {
     let registry = IndexRegistry::new();
@@ -1,6 +1,9 @@
 //! Simplified tests for the I/O optimization subsystem
 
-use crate::storage::io_optimization::{BufferedIoManager, IoBufferConfig, IoStatistics, PageCache};
+use crate::storage::io_optimization::{
+    BufferedIoManager, IoBufferConfig, IoStatistics, PageCache, ShardedPageCache,
+};
+use std::sync::Arc;
 use std::time::Duration;
 use tempfile::TempDir;
 
@@ -47,6 +50,104 @@ async fn test_page_cache_operations() {
     assert!(missing_data.is_none());
 }
 
+#[test]
+fn test_page_cache_does_not_evict_pinned_pages() {
+    let mut cache = PageCache::new(2);
+    cache.put(1, 1, vec![1; 4096]);
+    cache.put(1, 2, vec![2; 4096]);
+
+    // Page 1 is the LRU entry but pinned, so page 2 is evicted instead
+    let pinned = cache.get_shared(1, 1).unwrap();
+    cache.get(1, 2);
+    cache.get_shared(1, 1);
+    cache.put(1, 3, vec![3; 4096]);
+    assert_eq!(cache.pinned_count(), 1);
+    assert!(cache.get(1, 2).is_none());
+
+    // With every page pinned the cache grows instead of dropping one
+    let _pinned_3 = cache.get_shared(1, 3).unwrap();
+    cache.put(1, 4, vec![4; 4096]);
+    assert_eq!(cache.size(), 3);
+    assert_eq!(pinned[0], 1);
+
+    drop(pinned);
+    cache.put(1, 5, vec![5; 4096]);
+    assert!(cache.get(1, 1).is_none());
+    assert!(cache.get(1, 3).is_some());
+}
+
+#[test]
+fn test_sharded_page_cache_evicts_per_shard() {
+    let cache = ShardedPageCache::with_shards(8, 4);
+    assert_eq!(cache.shard_count(), 4);
+    assert_eq!(ShardedPageCache::with_shards(8, 3).shard_count(), 2);
+    assert_eq!(ShardedPageCache::new(10).shard_count(), 1);
+
+    // Pages spread over every shard
+    let pages: Vec<u64> = (0..64).collect();
+    let mut used = [false; 4];
+    for &page in &pages {
+        used[cache.shard_of(1, page)] = true;
+    }
+    assert!(used.iter().all(|&u| u));
+
+    // Each shard holds two pages; a third page of one shard evicts that shard's LRU page only
+    let same_shard: Vec<u64> = pages
+        .iter()
+        .copied()
+        .filter(|&p| cache.shard_of(1, p) == 0)
+        .take(3)
+        .collect();
+    let other = pages
+        .iter()
+        .copied()
+        .find(|&p| cache.shard_of(1, p) == 1)
+        .unwrap();
+    cache.put(1, other, vec![9; 4096]);
+    for &page in &same_shard {
+        cache.put(1, page, vec![page as u8; 4096]);
+    }
+    assert!(cache.get(1, same_shard[0]).is_none());
+    assert!(cache.get(1, same_shard[2]).is_some());
+    assert!(cache.get(1, other).is_some());
+    assert_eq!(cache.size(), 3);
+
+    let stats = cache.shard_stats();
+    assert_eq!(stats.len(), 4);
+    assert_eq!(stats[0].pages, 2);
+    assert_eq!(stats[0].capacity, 2);
+    assert_eq!((stats[0].hits, stats[0].misses), (1, 1));
+    assert_eq!(cache.get_stats().0, 2);
+}
+
+#[test]
+fn test_sharded_page_cache_counts_latch_acquisitions_under_concurrency() {
+    let cache = Arc::new(ShardedPageCache::with_shards(1024, 8));
+    let threads: Vec<_> = (0..4u64)
+        .map(|t| {
+            let cache = cache.clone();
+            std::thread::spawn(move || {
+                for page in 0..200u64 {
+                    cache.put(1, t * 1000 + page, vec![0; 64]);
+                    assert!(cache.get_shared(1, t * 1000 + page).is_some());
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let stats = cache.shard_stats();
+    let acquisitions: u64 = stats.iter().map(|s| s.lock_acquisitions).sum();
+    assert_eq!(acquisitions, 4 * 200 * 2);
+    assert!(stats
+        .iter()
+        .all(|s| s.lock_contentions <= s.lock_acquisitions));
+    assert_eq!(stats.iter().map(|s| s.pages).sum::<usize>(), 800);
+    assert_eq!(cache.pinned_count(), 0);
+}
+
 #[cfg_attr(miri, ignore)]
 #[tokio::test]
 async fn test_page_cache_lru_eviction() {
@@ -0,0 +1,178 @@
+//! Tests for the LSM storage engine
+
+use crate::storage::lsm::{LsmConfig, LsmTree};
+use crate::storage::page_manager::{PageManager, PageManagerConfig, StorageEngineKind};
+use std::collections::BTreeMap;
+use std::ops::Bound;
+use tempfile::TempDir;
+
+/// Tiny budgets so a few hundred writes exercise flushes and multi-level compaction
+fn small_config() -> LsmConfig {
+    LsmConfig {
+        memtable_bytes: 2 * 1024,
+        l0_compaction_trigger: 2,
+        level_base_bytes: 4 * 1024,
+        level_size_multiplier: 2,
+        target_file_bytes: 1024,
+        max_levels: 4,
+        bloom_bits_per_key: 10,
+    }
+}
+
+fn key(i: u32) -> Vec<u8> {
+    format!("key{:05}", i).into_bytes()
+}
+
+#[test]
+fn test_lsm_matches_model_across_flushes_and_compaction() {
+    let dir = TempDir::new().unwrap();
+    let mut tree = LsmTree::open(dir.path(), small_config()).unwrap();
+    let mut model = BTreeMap::new();
+
+    for round in 0..4u32 {
+        for i in 0..300u32 {
+            let k = key((i * 7 + round) % 500);
+            if (i + round) % 5 == 0 {
+                tree.delete(k.clone()).unwrap();
+                model.remove(&k);
+            } else {
+                let v = format!("v{}-{}", round, i).into_bytes();
+                tree.put(k.clone(), v.clone()).unwrap();
+                model.insert(k, v);
+            }
+        }
+    }
+
+    let stats = tree.statistics().clone();
+    assert!(stats.memtable_flushes > 0);
+    assert!(stats.compactions > 0);
+    assert!(
+        tree.level_table_counts()[0] < small_config().l0_compaction_trigger,
+        "L0 should be compacted below its trigger"
+    );
+
+    for i in 0..500 {
+        assert_eq!(tree.get(&key(i)).unwrap(), model.get(&key(i)).cloned());
+    }
+    let scanned = tree.scan().unwrap();
+    assert_eq!(scanned, model.clone().into_iter().collect::<Vec<_>>());
+
+    let lo = key(100);
+    let hi = key(200);
+    let expected: Vec<_> = model
+        .range(lo.clone()..hi.clone())
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    assert_eq!(
+        tree.range(Bound::Included(&lo), Bound::Excluded(&hi))
+            .unwrap(),
+        expected
+    );
+}
+
+#[test]
+fn test_lsm_reopen_recovers_memtable_log_and_sstables() {
+    let dir = TempDir::new().unwrap();
+    {
+        let mut tree = LsmTree::open(dir.path(), small_config()).unwrap();
+        for i in 0..200 {
+            tree.put(key(i), vec![i as u8; 16]).unwrap();
+        }
+        tree.delete(key(3)).unwrap();
+        // Entries still in the memtable survive only through the log.
+        tree.write_log().unwrap();
+        tree.sync().unwrap();
+        assert!(tree.memtable_len() > 0);
+    }
+
+    let mut tree = LsmTree::open(dir.path(), small_config()).unwrap();
+    assert_eq!(tree.get(&key(3)).unwrap(), None);
+    for i in (0..200).filter(|i| *i != 3) {
+        assert_eq!(tree.get(&key(i)).unwrap(), Some(vec![i as u8; 16]));
+    }
+    assert_eq!(tree.scan().unwrap().len(), 199);
+}
+
+#[test]
+fn test_lsm_torn_memtable_log_tail_is_ignored() {
+    let dir = TempDir::new().unwrap();
+    {
+        let mut tree = LsmTree::open(dir.path(), LsmConfig::default()).unwrap();
+        tree.put(b"a".to_vec(), b"1".to_vec()).unwrap();
+        tree.put(b"b".to_vec(), b"2".to_vec()).unwrap();
+        tree.write_log().unwrap();
+    }
+    let log = dir.path().join("memtable.log");
+    let bytes = std::fs::read(&log).unwrap();
+    std::fs::write(&log, &bytes[..bytes.len() - 1]).unwrap();
+
+    let mut tree = LsmTree::open(dir.path(), LsmConfig::default()).unwrap();
+    assert_eq!(tree.get(b"a").unwrap(), Some(b"1".to_vec()));
+    assert_eq!(tree.get(b"b").unwrap(), None);
+    // New writes append after the truncated tail.
+    tree.put(b"c".to_vec(), b"3".to_vec()).unwrap();
+    tree.write_log().unwrap();
+    drop(tree);
+    let mut tree = LsmTree::open(dir.path(), LsmConfig::default()).unwrap();
+    assert_eq!(tree.get(b"c").unwrap(), Some(b"3".to_vec()));
+}
+
+#[test]
+fn test_lsm_bloom_filters_skip_tables_for_missing_keys() {
+    let dir = TempDir::new().unwrap();
+    let config = LsmConfig {
+        l0_compaction_trigger: 100,
+        ..small_config()
+    };
+    let mut tree = LsmTree::open(dir.path(), config).unwrap();
+    for i in 0..400 {
+        tree.put(key(i * 2), vec![0; 8]).unwrap();
+    }
+    tree.flush_memtable().unwrap();
+    assert!(tree.level_table_counts()[0] > 1);
+
+    for i in 0..400 {
+        assert_eq!(tree.get(&key(i * 2 + 1)).unwrap(), None);
+    }
+    assert!(tree.statistics().bloom_skips > 0);
+}
+
+#[test]
+fn test_page_manager_lsm_backend_round_trip() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().to_path_buf();
+    let (rid_a, rid_b) = {
+        let mut pm = PageManager::open_lsm(
+            path.clone(),
+            "events",
+            PageManagerConfig::default(),
+            small_config(),
+        )
+        .unwrap();
+        assert_eq!(pm.storage_engine(), StorageEngineKind::Lsm);
+        let a = pm.insert(b"alpha").unwrap().record_id;
+        let b = pm.insert(b"beta").unwrap().record_id;
+        let c = pm.insert(b"gamma").unwrap().record_id;
+        pm.update(b, b"BETA").unwrap();
+        pm.delete(c).unwrap();
+        assert!(pm.delete(c).is_err());
+        pm.flush_dirty_pages().unwrap();
+        (a, b)
+    };
+
+    // `open` picks the LSM backend from the directory on disk.
+    let mut pm = PageManager::open(path, "events", PageManagerConfig::default()).unwrap();
+    assert_eq!(pm.storage_engine(), StorageEngineKind::Lsm);
+    assert_eq!(pm.get_record(rid_a).unwrap(), Some(b"alpha".to_vec()));
+    assert_eq!(pm.get_record(rid_b).unwrap(), Some(b"BETA".to_vec()));
+
+    let mut streamed = Vec::new();
+    for page in pm.all_page_ids().unwrap() {
+        streamed.extend(pm.records_from_page(page).unwrap());
+    }
+    assert_eq!(streamed.len(), 2);
+    let selected = pm.select(None).unwrap();
+    assert_eq!(selected.len(), 2);
+    let next = pm.insert(b"delta").unwrap().record_id;
+    assert!(next > rid_b, "record ids must not be reused after reopen");
+}
@@ -8,14 +8,21 @@
 // pub mod tuple_tests;
 // pub mod row_tests;
 pub mod advanced_file_manager_tests;
+pub mod atomic_file_tests;
 pub mod file_manager_tests;
+pub mod foreign_tests;
+pub mod index_conformance_tests;
 pub mod index_integration_tests;
 pub mod index_performance_tests;
 pub mod index_registry_tests;
 pub mod index_tests;
+pub mod lsm_tests;
 pub mod page_manager_tests;
 pub mod page_manager_tests_simple;
+pub mod remote_storage_tests;
 pub mod row_lock_tests;
+pub mod shred_tests;
+pub mod tiering_tests;
 // pub mod database_file_tests;
 pub mod io_optimization_tests_simple;
 // pub mod optimized_file_manager_tests;
@@ -1,6 +1,7 @@
 //! Page manager tests
 
-use crate::storage::page_manager::{PageManager, PageManagerConfig};
+use crate::storage::page_manager::{PageManager, PageManagerConfig, PageManagerLock};
+use std::sync::Arc;
 use tempfile::TempDir;
 
 /// Creates a test PageManager with a temporary directory
@@ -513,3 +514,135 @@ fn test_compression_with_different_data_types() {
     }
     assert!(true);
 }
+
+#[test]
+fn test_scrub_free_space_overwrites_deleted_records() {
+    let (mut manager, temp_dir) = create_test_page_manager().expect("page manager");
+    let file = temp_dir.path().join("test_table.tbl");
+    let contains = |needle: &[u8]| {
+        let bytes = std::fs::read(&file).expect("table file");
+        bytes.windows(needle.len()).any(|w| w == needle)
+    };
+    manager.insert(b"Record 1").expect("insert");
+    let secret = manager.insert(b"SECRET-PAYLOAD").expect("insert");
+    manager.insert(b"Record 3").expect("insert");
+    manager.flush_dirty_pages().expect("flush");
+    manager.delete(secret.record_id).expect("delete");
+    manager.flush_dirty_pages().expect("flush");
+    // Deleting only frees the slot; the record's bytes stay in the page.
+    assert!(contains(b"SECRET-PAYLOAD"));
+
+    assert!(manager.scrub_free_space().expect("scrub") >= 1);
+    assert!(!contains(b"SECRET-PAYLOAD"));
+    let records = manager.select(None).expect("select");
+    assert_eq!(records.len(), 2);
+    assert!(records.iter().any(|(_, data)| data == b"Record 3"));
+}
+
+#[test]
+fn test_commit_groups_flush_only_queued_pages() {
+    let (mut manager, temp_dir) = create_test_page_manager().expect("page manager");
+    let file = temp_dir.path().join("test_table.tbl");
+    let contains = |needle: &[u8]| {
+        let bytes = std::fs::read(&file).expect("table file");
+        bytes.windows(needle.len()).any(|w| w == needle)
+    };
+    manager.insert(b"COMMITTED-ROW").expect("insert");
+    assert_eq!(manager.queue_commit_group(), 1);
+    assert_eq!(manager.flush_commit_groups_no_sync().expect("flush"), 1);
+    assert_eq!(manager.queued_commit_page_count(), 0);
+    assert_eq!(manager.dirty_page_count(), 0);
+    assert!(contains(b"COMMITTED-ROW"));
+
+    // Pages dirtied after the last queued commit wait for their own.
+    manager.insert(b"IN-FLIGHT-ROW").expect("insert");
+    assert_eq!(manager.flush_commit_groups_no_sync().expect("flush"), 0);
+    assert_eq!(manager.dirty_page_count(), 1);
+    assert_eq!(manager.flush_dirty_pages().expect("flush"), 1);
+    assert!(contains(b"IN-FLIGHT-ROW"));
+}
+
+#[test]
+fn test_page_prefetcher_loads_pages_into_the_buffer_pool_once() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let dir = temp_dir.path().to_path_buf();
+    let mut pages = Vec::new();
+    {
+        let mut manager =
+            PageManager::new(dir.clone(), "prefetched", PageManagerConfig::default()).unwrap();
+        let record = [7u8; 500];
+        for _ in 0..100 {
+            pages.push(manager.insert(&record).expect("insert").page_id);
+        }
+        manager.flush_dirty_pages().expect("flush");
+    }
+    pages.dedup();
+    assert!(pages.len() > 4);
+
+    // Opening reads every page; with a pool of 4 pages only the last ones stay cached.
+    let config = PageManagerConfig {
+        buffer_pool_size: 4,
+        ..PageManagerConfig::default()
+    };
+    let mut manager = PageManager::open(dir, "prefetched", config).unwrap();
+    let mut prefetcher = manager.page_prefetcher().expect("heap table");
+    let evicted = &pages[..3];
+    for &page_id in evicted {
+        prefetcher.prefetch(page_id);
+    }
+    assert_eq!(prefetcher.pages_loaded(), evicted.len());
+    // Cached pages are not read again, and pages past the end of the file are skipped.
+    for &page_id in evicted {
+        prefetcher.prefetch(page_id);
+    }
+    prefetcher.prefetch(1_000_000);
+    assert_eq!(prefetcher.pages_loaded(), evicted.len());
+
+    assert_eq!(manager.select(None).expect("select").len(), 100);
+}
+
+#[test]
+fn test_shared_page_manager_serves_parallel_readers() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let mut pages = Vec::new();
+    {
+        let mut manager = PageManager::new(
+            temp_dir.path().to_path_buf(),
+            "shared",
+            PageManagerConfig::default(),
+        )
+        .unwrap();
+        for i in 0..200u32 {
+            pages.push(manager.insert(&[i as u8; 300]).expect("insert").page_id);
+        }
+        manager.flush_dirty_pages().expect("flush");
+    }
+    pages.dedup();
+    // A small pool makes readers go to disk.
+    let config = PageManagerConfig {
+        buffer_pool_size: 2,
+        ..PageManagerConfig::default()
+    };
+    let pm = Arc::new(PageManagerLock::new(
+        PageManager::open(temp_dir.path().to_path_buf(), "shared", config).unwrap(),
+    ));
+
+    // Readers share the lock: every reader holds it while the others read.
+    let guards: Vec<_> = (0..4).map(|_| pm.read()).collect();
+    let barrier = std::sync::Barrier::new(4);
+    std::thread::scope(|scope| {
+        for guard in &guards {
+            let (barrier, pages) = (&barrier, &pages);
+            scope.spawn(move || {
+                barrier.wait();
+                let records: usize = pages
+                    .iter()
+                    .map(|&page_id| guard.records_from_page(page_id).expect("read").len())
+                    .sum();
+                assert_eq!(records, 200);
+            });
+        }
+    });
+    drop(guards);
+    assert!(pm.read().io_statistics().reads >= pages.len() as u64);
+}
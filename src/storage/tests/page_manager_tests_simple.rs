@@ -1,5 +1,6 @@
 //! Simplified tests for the page manager
 
+use crate::storage::page::{Page, SlottedPageView};
 use crate::storage::page_manager::{PageManager, PageManagerConfig};
 use tempfile::TempDir;
 
@@ -410,3 +411,72 @@ fn insert_flush_open_sees_records() {
         "after flush, reopen should read persisted pages"
     );
 }
+
+#[test]
+fn pinned_pages_borrow_records_from_the_buffer_pool() {
+    let dir = TempDir::new().expect("tempdir");
+    let mut pm = PageManager::new(
+        dir.path().to_path_buf(),
+        "t_pin",
+        PageManagerConfig::default(),
+    )
+    .expect("new");
+    let mut ids = Vec::new();
+    for i in 0..40 {
+        ids.push(
+            pm.insert(format!("pinned-{i:03}").as_bytes())
+                .expect("insert"),
+        );
+    }
+    pm.delete(ids[3].record_id).expect("delete");
+    pm.flush_dirty_pages().expect("flush");
+
+    let mut expected = pm.select(None).expect("select");
+    let mut pinned = Vec::new();
+    for page_id in pm.all_page_ids().expect("page ids") {
+        let page = pm.pin_page(page_id).expect("pin");
+        assert!(
+            page.is_cached(),
+            "flushed page {page_id} should be borrowed"
+        );
+        for (offset, data) in page.records() {
+            pinned.push((
+                PageManager::record_id_for_slot(page_id, offset),
+                data.to_vec(),
+            ));
+            assert_eq!(page.record_at(offset), Some(data));
+        }
+    }
+    expected.sort();
+    pinned.sort();
+    assert_eq!(pinned.len(), 39);
+    assert_eq!(pinned, expected);
+    assert_eq!(
+        pm.get_record(ids[5].record_id).expect("get"),
+        Some(b"pinned-005".to_vec())
+    );
+    assert_eq!(pm.get_record(ids[3].record_id).expect("get"), None);
+}
+
+#[test]
+fn slotted_page_view_matches_deserialized_page() {
+    let mut page = Page::new(7);
+    let mut offsets = Vec::new();
+    for i in 0..10u64 {
+        offsets.push(page.add_record(format!("rec-{i}").as_bytes(), i).unwrap());
+    }
+    page.delete_record_by_offset(offsets[4]).unwrap();
+    let bytes = page.to_bytes().unwrap();
+
+    let view = SlottedPageView::new(&bytes).expect("slotted page");
+    let borrowed: Vec<(u32, Vec<u8>)> = view.records().map(|(o, d)| (o, d.to_vec())).collect();
+    assert_eq!(
+        borrowed,
+        Page::from_bytes(&bytes).unwrap().scan_records().unwrap()
+    );
+    assert_eq!(view.record_at_offset(offsets[2]), Some(&b"rec-2"[..]));
+    assert_eq!(view.record_at_offset(offsets[4]), None);
+
+    assert!(SlottedPageView::new(&bytes[..100]).is_none());
+    assert!(SlottedPageView::new(&vec![0u8; bytes.len()]).is_none());
+}
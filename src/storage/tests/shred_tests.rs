@@ -0,0 +1,36 @@
+//! Sealed tuple tests
+
+use crate::common::types::{ColumnValue, DataType};
+use crate::storage::shred::{self, Keyring};
+use crate::storage::tuple::Tuple;
+
+#[test]
+fn sealed_tuples_open_until_their_key_is_forgotten() {
+    let (id, key) = shred::generate_key().expect("key");
+    let keyring = Keyring::default();
+    keyring.register(id, &key).expect("register");
+    let mut tuple = Tuple::new(7);
+    tuple.set_value(
+        "email",
+        ColumnValue::new(DataType::Varchar("'alice@example.com'".to_string())),
+    );
+    let bytes = keyring
+        .seal(&tuple, &id)
+        .and_then(|sealed| sealed.to_bytes())
+        .expect("seal");
+    assert!(!bytes.windows(5).any(|w| w == b"alice"));
+
+    let opened = keyring.decode(&bytes).expect("decode");
+    assert!(!opened.is_deleted);
+    assert_eq!(opened.get_value("email"), tuple.get_value("email"));
+
+    // Another keyring holding the same key is not affected by this one forgetting it.
+    let other = Keyring::default();
+    other.register(id, &key).expect("register");
+    assert!(keyring.forget(&id));
+    let forgotten = keyring.decode(&bytes).expect("decode");
+    assert_eq!(forgotten.id, 7);
+    assert!(forgotten.is_deleted);
+    assert!(forgotten.values.is_empty());
+    assert!(!other.decode(&bytes).expect("decode").is_deleted);
+}
@@ -0,0 +1,161 @@
+//! Hot/cold tiering of heap file extents
+
+use crate::common::types::RecordId;
+use crate::storage::page_manager::{PageManager, PageManagerConfig};
+use crate::storage::tiering::{extent_of, plan_moves, ExtentHeat, TieringPolicy, EXTENT_PAGES};
+use std::collections::{BTreeSet, HashMap};
+use tempfile::TempDir;
+
+const EAGER: TieringPolicy = TieringPolicy {
+    cold_after_windows: 1,
+    promote_accesses: 1,
+    max_moves_per_run: 16,
+};
+
+fn record(i: usize) -> Vec<u8> {
+    let mut data = format!("row-{i:05}-").into_bytes();
+    data.resize(1000, b'x');
+    data
+}
+
+/// A table spanning several extents; returns its rows, sorted.
+fn fill(manager: &mut PageManager) -> Vec<Vec<u8>> {
+    let rows: Vec<Vec<u8>> = (0..800).map(record).collect();
+    for row in &rows {
+        manager.insert(row).expect("insert");
+    }
+    manager.flush_dirty_pages().expect("flush");
+    rows
+}
+
+fn rows(manager: &mut PageManager) -> Vec<Vec<u8>> {
+    let mut rows: Vec<Vec<u8>> = manager
+        .select(None)
+        .expect("scan")
+        .into_iter()
+        .map(|(_, row)| row)
+        .collect();
+    rows.sort();
+    rows
+}
+
+/// Page of `record_id` (see [`PageManager::record_id_for_slot`])
+fn page_of(record_id: RecordId) -> u64 {
+    record_id >> 32
+}
+
+#[test]
+fn test_plan_demotes_idle_extents_and_promotes_read_ones() {
+    let mut heat = ExtentHeat::default();
+    assert!(
+        heat.close_window(4).is_empty(),
+        "first window only starts tracking"
+    );
+    heat.record(0);
+    heat.record(3 * EXTENT_PAGES);
+    heat.record(3 * EXTENT_PAGES + 1);
+    let accesses = heat.close_window(4);
+    assert_eq!(accesses.get(&3), Some(&2));
+
+    let policy = TieringPolicy {
+        promote_accesses: 2,
+        ..EAGER
+    };
+    let cold = BTreeSet::from([2, 3]);
+    let plan = plan_moves(&policy, 4, &heat, &accesses, &cold);
+    assert_eq!(plan.promote, vec![3]);
+    assert_eq!(plan.demote, vec![1]);
+
+    let limited = TieringPolicy {
+        max_moves_per_run: 1,
+        ..policy
+    };
+    let plan = plan_moves(&limited, 4, &heat, &accesses, &cold);
+    assert_eq!((plan.promote, plan.demote), (vec![3], vec![]));
+
+    let plan = plan_moves(&policy, 4, &heat, &HashMap::new(), &BTreeSet::new());
+    assert_eq!(
+        plan.demote,
+        vec![1, 2],
+        "extents 0 and 3 were read in the last window"
+    );
+}
+
+#[test]
+fn test_cold_extents_stay_readable_and_move_back() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let dir = temp_dir.path().to_path_buf();
+    let cold_dir = temp_dir.path().join("cold");
+    let config = PageManagerConfig::default;
+    let mut manager = PageManager::new(dir.clone(), "orders", config()).expect("create");
+    let mut expected = fill(&mut manager);
+
+    assert!(manager
+        .run_tiering(&cold_dir, &EAGER)
+        .expect("run")
+        .demote
+        .is_empty());
+    manager
+        .get_record(PageManager::record_id_for_slot(0, 0))
+        .expect("read");
+    #[cfg(target_os = "linux")]
+    let allocated = || {
+        use std::os::unix::fs::MetadataExt;
+        std::fs::metadata(dir.join("orders.tbl"))
+            .expect("heap file")
+            .blocks()
+    };
+    #[cfg(target_os = "linux")]
+    let hot_blocks = allocated();
+    let plan = manager.run_tiering(&cold_dir, &EAGER).expect("run");
+    assert!(plan.demote.len() >= 2, "{plan:?}");
+    #[cfg(target_os = "linux")]
+    assert!(
+        allocated() < hot_blocks,
+        "demoted extents release their blocks"
+    );
+    assert!(!plan.demote.contains(&0), "extent 0 was read");
+    let stats = manager.tiering_statistics();
+    assert_eq!(stats.cold_extents, plan.demote.len() as u64);
+    assert_eq!(stats.extents_demoted, plan.demote.len() as u64);
+    assert_eq!(stats.pages_moved, plan.demote.len() as u64 * EXTENT_PAGES);
+    assert!(cold_dir.join("orders.tbl.cold").is_file());
+    assert_eq!(rows(&mut manager), expected);
+
+    // Writes to a cold page go to the cold file and survive a reopen.
+    let (cold_id, old) = manager
+        .select(None)
+        .expect("scan")
+        .into_iter()
+        .find(|(id, _)| plan.demote.contains(&extent_of(page_of(*id))))
+        .expect("a row in a cold extent");
+    let new = record(expected.len());
+    manager.update(cold_id, &new).expect("update");
+    manager.flush_dirty_pages().expect("flush");
+    assert!(manager.tiering_statistics().cold_writes >= 1);
+    drop(manager);
+    expected.retain(|row| *row != old);
+    expected.push(new);
+    expected.sort();
+
+    let mut manager = PageManager::open(dir.clone(), "orders", config()).expect("open");
+    assert_eq!(
+        manager.tiering_statistics().cold_extents,
+        plan.demote.len() as u64
+    );
+    assert_eq!(rows(&mut manager), expected);
+    assert!(manager.tiering_statistics().cold_reads > 0);
+
+    // Read again: every cold extent moves back and the cold file goes away.
+    manager.run_tiering(&cold_dir, &EAGER).expect("run");
+    rows(&mut manager);
+    let plan_back = manager.run_tiering(&cold_dir, &EAGER).expect("run");
+    assert_eq!(plan_back.promote.len(), plan.demote.len());
+    assert_eq!(manager.tiering_statistics().cold_extents, 0);
+    assert!(!cold_dir.join("orders.tbl.cold").exists());
+    assert!(!dir.join("orders.tbl.tiers").exists());
+    drop(manager);
+
+    let mut manager = PageManager::open(dir, "orders", config()).expect("reopen");
+    assert_eq!(rows(&mut manager), expected);
+}
@@ -0,0 +1,494 @@
+//! Hot/cold tiering of heap file extents
+//!
+//! A heap file is divided into extents of [`EXTENT_PAGES`] pages. [`ExtentHeat`] counts the
+//! page accesses of each extent (buffer pool hits included) during a window; every tiering run
+//! closes the window and, following a [`TieringPolicy`], moves extents between the primary file
+//! and a cold file in a secondary tablespace (e.g. a slower disk):
+//! - an extent not accessed for `cold_after_windows` runs is copied to the cold file (demoted);
+//! - a cold extent accessed at least `promote_accesses` times in one window is copied back
+//!   (promoted).
+//!
+//! [`ColdTier`] records which extents live in the cold file in `<file>.tiers`, replaced
+//! atomically. Reads and writes of a page in a cold extent go to the cold file, so queries do not
+//! see where a page lives. A move copies the pages and syncs the destination before the map
+//! changes, so after a crash either the old or the new copy is authoritative, never a half-moved
+//! extent. On Linux the primary file's blocks of a demoted extent are released
+//! (`FALLOC_FL_PUNCH_HOLE`); the file keeps its size.
+
+use crate::common::{Error, Result};
+use crate::storage::atomic_file::write_atomic;
+use crate::storage::block_io::{create_backend_for_file, BlockIoBackend};
+use crate::storage::database_file::{FreePageMap, PageId};
+use crate::storage::file_manager::{DatabaseFile, BLOCK_SIZE};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// Pages per extent, the unit of access tracking and migration
+pub const EXTENT_PAGES: u64 = FreePageMap::EXTENT_PAGES as u64;
+
+const EXTENT_BYTES: u64 = EXTENT_PAGES * BLOCK_SIZE as u64;
+
+/// Extent holding `page_id`
+pub fn extent_of(page_id: PageId) -> u64 {
+    page_id / EXTENT_PAGES
+}
+
+/// When extents move between the tiers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TieringPolicy {
+    /// Runs in a row without an access after which an extent is demoted
+    pub cold_after_windows: u32,
+    /// Accesses within one run's window after which a cold extent is promoted
+    pub promote_accesses: u64,
+    /// Extents moved per run and file, promotions first
+    pub max_moves_per_run: usize,
+}
+
+impl Default for TieringPolicy {
+    fn default() -> Self {
+        Self {
+            cold_after_windows: 10,
+            promote_accesses: 8,
+            max_moves_per_run: 16,
+        }
+    }
+}
+
+/// Page accesses per extent of one file since the last tiering run
+///
+/// Tracking starts with the first run, so files that are never tiered pay nothing per access.
+#[derive(Debug, Default)]
+pub struct ExtentHeat {
+    tracking: AtomicBool,
+    window: DashMap<u64, u64>,
+    idle_windows: HashMap<u64, u32>,
+}
+
+impl ExtentHeat {
+    /// Counts an access to `page_id`
+    pub fn record(&self, page_id: PageId) {
+        if self.tracking.load(Ordering::Relaxed) {
+            *self.window.entry(extent_of(page_id)).or_default() += 1;
+        }
+    }
+
+    /// Closes the current window over the first `extents` extents and returns its accesses per
+    /// extent. The first call only starts tracking.
+    pub fn close_window(&mut self, extents: u64) -> HashMap<u64, u64> {
+        if !self.tracking.swap(true, Ordering::Relaxed) {
+            return HashMap::new();
+        }
+        let accesses: HashMap<u64, u64> = self.window.iter().map(|e| (*e.key(), *e)).collect();
+        self.window.clear();
+        for extent in 0..extents {
+            let idle = self.idle_windows.entry(extent).or_default();
+            *idle = if accesses.contains_key(&extent) {
+                0
+            } else {
+                idle.saturating_add(1)
+            };
+        }
+        accesses
+    }
+
+    /// Closed windows in a row without an access to `extent`
+    pub fn idle_windows(&self, extent: u64) -> u32 {
+        self.idle_windows.get(&extent).copied().unwrap_or(0)
+    }
+}
+
+/// Extents to move in one tiering run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TieringPlan {
+    /// Cold extents to copy back to the primary file, hottest first
+    pub promote: Vec<u64>,
+    /// Hot extents to copy to the cold file, idle longest first
+    pub demote: Vec<u64>,
+}
+
+/// Applies `policy` to the first `extents` extents, given the window just closed in `heat` and
+/// its `accesses`, and the extents already `cold`.
+pub fn plan_moves(
+    policy: &TieringPolicy,
+    extents: u64,
+    heat: &ExtentHeat,
+    accesses: &HashMap<u64, u64>,
+    cold: &BTreeSet<u64>,
+) -> TieringPlan {
+    let mut promote: Vec<(u64, u64)> = cold
+        .iter()
+        .filter_map(|e| accesses.get(e).map(|n| (*e, *n)))
+        .filter(|(_, n)| *n >= policy.promote_accesses.max(1))
+        .collect();
+    promote.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    promote.truncate(policy.max_moves_per_run);
+
+    let mut demote: Vec<(u64, u32)> = (0..extents)
+        .filter(|e| !cold.contains(e))
+        .map(|e| (e, heat.idle_windows(e)))
+        .filter(|(_, idle)| *idle >= policy.cold_after_windows.max(1))
+        .collect();
+    demote.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    demote.truncate(policy.max_moves_per_run - promote.len());
+
+    TieringPlan {
+        promote: promote.into_iter().map(|(e, _)| e).collect(),
+        demote: demote.into_iter().map(|(e, _)| e).collect(),
+    }
+}
+
+/// Tiering counters of one file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TieringStatistics {
+    /// Complete extents in the primary file
+    pub hot_extents: u64,
+    /// Extents in the cold file
+    pub cold_extents: u64,
+    /// Extents moved to the cold file
+    pub extents_demoted: u64,
+    /// Extents moved back to the primary file
+    pub extents_promoted: u64,
+    /// Pages copied between the tiers, both ways
+    pub pages_moved: u64,
+    /// Pages read from the cold file
+    pub cold_reads: u64,
+    /// Pages written to the cold file (moves excluded)
+    pub cold_writes: u64,
+}
+
+/// Contents of `<file>.tiers`
+#[derive(Debug, Serialize, Deserialize)]
+struct TierMap {
+    cold_file: PathBuf,
+    /// `(extent, slot)`: the extent's pages are at `slot * EXTENT_BYTES` in the cold file
+    extents: Vec<(u64, u64)>,
+}
+
+struct ColdFile {
+    path: PathBuf,
+    backend: Box<dyn BlockIoBackend>,
+    slots: BTreeMap<u64, u64>,
+}
+
+impl ColdFile {
+    fn offset(&self, page_id: PageId) -> Option<u64> {
+        let slot = self.slots.get(&extent_of(page_id))?;
+        Some(slot * EXTENT_BYTES + (page_id % EXTENT_PAGES) * BLOCK_SIZE as u64)
+    }
+
+    fn free_slot(&self) -> u64 {
+        let used: BTreeSet<u64> = self.slots.values().copied().collect();
+        (0..).find(|s| !used.contains(s)).unwrap_or_default()
+    }
+}
+
+/// Cold extents of one heap file and the file holding them
+pub struct ColdTier {
+    map_path: PathBuf,
+    cold: Option<ColdFile>,
+    extents_demoted: u64,
+    extents_promoted: u64,
+    pages_moved: u64,
+    cold_reads: AtomicU64,
+    cold_writes: u64,
+}
+
+impl ColdTier {
+    /// Map of the cold extents of the heap file at `primary`
+    pub fn map_path(primary: &Path) -> PathBuf {
+        let mut name = primary.as_os_str().to_owned();
+        name.push(".tiers");
+        PathBuf::from(name)
+    }
+
+    /// Loads the cold extents of the heap file at `primary` (none without a map)
+    pub fn load(primary: &Path) -> Result<Self> {
+        let map_path = Self::map_path(primary);
+        let cold = match std::fs::read(&map_path) {
+            Ok(bytes) => {
+                let map: TierMap = serde_json::from_slice(&bytes).map_err(|e| {
+                    Error::database(format!("tier map {}: {e}", map_path.display()))
+                })?;
+                Some(ColdFile {
+                    backend: create_backend_for_file(&map.cold_file, false, false)?,
+                    path: map.cold_file,
+                    slots: map.extents.into_iter().collect(),
+                })
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            map_path,
+            cold,
+            extents_demoted: 0,
+            extents_promoted: 0,
+            pages_moved: 0,
+            cold_reads: AtomicU64::new(0),
+            cold_writes: 0,
+        })
+    }
+
+    /// Removes the map and cold file of the heap file at `primary` (on `DROP TABLE`, or before
+    /// a new file is created there)
+    pub fn remove_files(primary: &Path) -> Result<()> {
+        let map_path = Self::map_path(primary);
+        if let Ok(bytes) = std::fs::read(&map_path) {
+            if let Ok(map) = serde_json::from_slice::<TierMap>(&bytes) {
+                let _ = std::fs::remove_file(map.cold_file);
+            }
+        }
+        match std::fs::remove_file(&map_path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Moves the map and cold file of the heap file at `from` to those of `to`, after the heap
+    /// file itself was renamed; the cold file stays in its tablespace
+    pub fn rename_files(from: &Path, to: &Path) -> Result<()> {
+        let from_map = Self::map_path(from);
+        let bytes = match std::fs::read(&from_map) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut map: TierMap = serde_json::from_slice(&bytes)
+            .map_err(|e| Error::database(format!("tier map {}: {e}", from_map.display())))?;
+        let cold_file = cold_file_path(map.cold_file.parent().unwrap_or(Path::new(".")), to);
+        std::fs::rename(&map.cold_file, &cold_file)?;
+        map.cold_file = cold_file;
+        store_map(&Self::map_path(to), &map)?;
+        std::fs::remove_file(from_map)?;
+        Ok(())
+    }
+
+    /// Whether `page_id` lives in the cold file
+    pub fn is_cold(&self, page_id: PageId) -> bool {
+        self.cold
+            .as_ref()
+            .is_some_and(|c| c.slots.contains_key(&extent_of(page_id)))
+    }
+
+    /// Cold extents, in order
+    pub fn cold_extents(&self) -> BTreeSet<u64> {
+        self.cold
+            .as_ref()
+            .map(|c| c.slots.keys().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Reads `page_id` from whichever tier holds it
+    pub fn read_page(&self, base: &DatabaseFile, page_id: PageId) -> Result<Vec<u8>> {
+        match self
+            .cold
+            .as_ref()
+            .and_then(|c| Some((c, c.offset(page_id)?)))
+        {
+            Some((cold, offset)) => {
+                let mut data = vec![0u8; BLOCK_SIZE];
+                cold.backend.read_at(offset, &mut data)?;
+                self.cold_reads.fetch_add(1, Ordering::Relaxed);
+                Ok(data)
+            }
+            None => base.read_block(page_id),
+        }
+    }
+
+    /// Writes `page_id` to whichever tier holds it
+    pub fn write_page(
+        &mut self,
+        base: &mut DatabaseFile,
+        page_id: PageId,
+        data: &[u8],
+    ) -> Result<()> {
+        match self
+            .cold
+            .as_mut()
+            .and_then(|c| Some((c.offset(page_id)?, c)))
+        {
+            Some((offset, cold)) => {
+                if data.len() != BLOCK_SIZE {
+                    return Err(Error::database(format!(
+                        "Invalid data size: {} (expected {})",
+                        data.len(),
+                        BLOCK_SIZE
+                    )));
+                }
+                cold.backend.write_at(offset, data)?;
+                self.cold_writes += 1;
+                Ok(())
+            }
+            None => base.write_block(page_id, data),
+        }
+    }
+
+    /// Syncs the cold file
+    pub fn sync(&mut self) -> Result<()> {
+        match self.cold.as_mut() {
+            Some(cold) => cold.backend.sync(),
+            None => Ok(()),
+        }
+    }
+
+    /// Copies `extent` of `base` to the cold file in `cold_dir` (the file's existing cold file,
+    /// if it has one) and releases its primary blocks
+    pub fn demote(&mut self, base: &mut DatabaseFile, extent: u64, cold_dir: &Path) -> Result<()> {
+        if self.cold.is_none() {
+            std::fs::create_dir_all(cold_dir)?;
+            let path = cold_file_path(cold_dir, &base.path);
+            let backend = create_backend_for_file(&path, !path.exists(), false)?;
+            self.cold = Some(ColdFile {
+                path,
+                backend,
+                slots: BTreeMap::new(),
+            });
+        }
+        let cold = self.cold.as_mut().expect("cold file opened above");
+        let slot = cold.free_slot();
+        let first = extent * EXTENT_PAGES;
+        for i in 0..EXTENT_PAGES {
+            let data = base.read_block(first + i)?;
+            cold.backend
+                .write_at(slot * EXTENT_BYTES + i * BLOCK_SIZE as u64, &data)?;
+        }
+        cold.backend.sync()?;
+        cold.slots.insert(extent, slot);
+        if let Err(e) = self.store() {
+            if let Some(cold) = self.cold.as_mut() {
+                cold.slots.remove(&extent);
+            }
+            return Err(e);
+        }
+        release_blocks(&base.path, BLOCK_SIZE as u64 * (first + 1), EXTENT_BYTES)?;
+        self.extents_demoted += 1;
+        self.pages_moved += EXTENT_PAGES;
+        Ok(())
+    }
+
+    /// Copies cold `extent` back to `base`; the cold file is removed with its last extent
+    pub fn promote(&mut self, base: &mut DatabaseFile, extent: u64) -> Result<()> {
+        let Some(cold) = self.cold.as_mut() else {
+            return Ok(());
+        };
+        let Some(&slot) = cold.slots.get(&extent) else {
+            return Ok(());
+        };
+        let first = extent * EXTENT_PAGES;
+        let mut data = vec![0u8; BLOCK_SIZE];
+        for i in 0..EXTENT_PAGES {
+            cold.backend
+                .read_at(slot * EXTENT_BYTES + i * BLOCK_SIZE as u64, &mut data)?;
+            base.write_block(first + i, &data)?;
+        }
+        base.sync()?;
+        cold.slots.remove(&extent);
+        if cold.slots.is_empty() {
+            let path = cold.path.clone();
+            self.cold = None;
+            std::fs::remove_file(&self.map_path)?;
+            crate::storage::atomic_file::sync_parent_dir(&self.map_path)?;
+            let _ = std::fs::remove_file(path);
+        } else {
+            self.store()?;
+        }
+        self.extents_promoted += 1;
+        self.pages_moved += EXTENT_PAGES;
+        Ok(())
+    }
+
+    /// Writes the cold pages into `copy`, a copy of the primary file, so that it holds every
+    /// page without the cold file (base backups)
+    pub fn write_cold_pages_into(&self, copy: &Path) -> Result<()> {
+        let Some(cold) = self.cold.as_ref() else {
+            return Ok(());
+        };
+        let mut out = create_backend_for_file(copy, false, false)?;
+        let mut data = vec![0u8; BLOCK_SIZE];
+        for (extent, slot) in &cold.slots {
+            for i in 0..EXTENT_PAGES {
+                cold.backend
+                    .read_at(slot * EXTENT_BYTES + i * BLOCK_SIZE as u64, &mut data)?;
+                out.write_at(BLOCK_SIZE as u64 * (extent * EXTENT_PAGES + i + 1), &data)?;
+            }
+        }
+        out.sync()
+    }
+
+    /// Counters of this file; `extents` is the number of complete extents of the file
+    pub fn statistics(&self, extents: u64) -> TieringStatistics {
+        let cold_extents = self.cold.as_ref().map_or(0, |c| c.slots.len() as u64);
+        TieringStatistics {
+            hot_extents: extents.saturating_sub(cold_extents),
+            cold_extents,
+            extents_demoted: self.extents_demoted,
+            extents_promoted: self.extents_promoted,
+            pages_moved: self.pages_moved,
+            cold_reads: self.cold_reads.load(Ordering::Relaxed),
+            cold_writes: self.cold_writes,
+        }
+    }
+
+    fn store(&self) -> Result<()> {
+        let Some(cold) = self.cold.as_ref() else {
+            return Ok(());
+        };
+        store_map(
+            &self.map_path,
+            &TierMap {
+                cold_file: cold.path.clone(),
+                extents: cold.slots.iter().map(|(e, s)| (*e, *s)).collect(),
+            },
+        )
+    }
+}
+
+fn store_map(path: &Path, map: &TierMap) -> Result<()> {
+    let bytes = serde_json::to_vec(map).map_err(|e| Error::database(e.to_string()))?;
+    write_atomic(path, &bytes)
+}
+
+/// `<cold_dir>/<primary file name>.cold`
+fn cold_file_path(cold_dir: &Path, primary: &Path) -> PathBuf {
+    let mut name = primary.file_name().unwrap_or_default().to_owned();
+    name.push(".cold");
+    cold_dir.join(name)
+}
+
+/// Gives the `len` bytes at `offset` of the file at `path` back to the filesystem; they read as
+/// zeros afterwards
+#[cfg(target_os = "linux")]
+fn release_blocks(path: &Path, offset: u64, len: u64) -> Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let file = std::fs::OpenOptions::new().write(true).open(path)?;
+    // SAFETY: `fallocate` only reads its scalar arguments; the descriptor is owned by `file`,
+    // which outlives the call.
+    let ret = unsafe {
+        libc::fallocate(
+            file.as_raw_fd(),
+            libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE,
+            offset as libc::off_t,
+            len as libc::off_t,
+        )
+    };
+    if ret == 0 {
+        return Ok(());
+    }
+    let err = std::io::Error::last_os_error();
+    if err.raw_os_error() == Some(libc::EOPNOTSUPP) {
+        // Filesystem without hole punching: the stale copy keeps its space.
+        return Ok(());
+    }
+    Err(err.into())
+}
+
+/// Keeps the primary copy of a demoted extent where holes cannot be punched
+#[cfg(not(target_os = "linux"))]
+fn release_blocks(_path: &Path, _offset: u64, _len: u64) -> Result<()> {
+    Ok(())
+}
@@ -12,7 +12,9 @@ use std::collections::HashMap;
 pub struct Tuple {
     /// Tuple ID
     pub id: u64,
-    /// Column values
+    /// Column values, by column name. Unlike [`crate::common::types::Row`], a tuple is not
+    /// positional: this map is the stored row format, and a table created by its first `INSERT`
+    /// has no schema to place values against.
     pub values: HashMap<String, ColumnValue>,
     /// Tuple version (for MVCC)
     pub version: u64,
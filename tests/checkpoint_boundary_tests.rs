@@ -1,3 +1,4 @@
+use rustdb::logging::checkpoint::CheckpointManager;
 use rustdb::logging::log_record::{LogRecord, LogRecordType};
 use rustdb::network::engine::{EngineHandle, SessionContext};
 use rustdb::network::sql_engine::SqlEngine;
@@ -30,4 +31,10 @@ fn manual_checkpoint_writes_checkpoint_record() {
             .any(|r| r.record_type == LogRecordType::Checkpoint),
         "expected at least one Checkpoint record in WAL"
     );
+    let meta = CheckpointManager::load_metadata(&data_dir.join(".rustdb").join("checkpoint.json"))
+        .unwrap()
+        .expect("checkpoint metadata written");
+    assert!(recs
+        .iter()
+        .any(|r| r.lsn == meta.lsn && r.record_type == LogRecordType::Checkpoint));
 }
@@ -75,6 +75,83 @@ fn explain_analyze_insert_executes_and_reports_rows_affected() {
     }
 }
 
+#[test]
+fn explain_analyze_reports_rows_memory_and_spills_per_operator() {
+    let (_dir, eng) = open_engine();
+    let mut ctx = SessionContext::default();
+    eng.execute_sql("CREATE TABLE ex_s (id INTEGER, v INTEGER)", &mut ctx)
+        .expect("ddl");
+    let values: Vec<String> = (0..200).map(|i| format!("({i}, {})", i % 7)).collect();
+    eng.execute_sql(
+        &format!("INSERT INTO ex_s (id, v) VALUES {}", values.join(", ")),
+        &mut ctx,
+    )
+    .expect("insert");
+    eng.execute_sql("SET execution.work_mem = 2048", &mut ctx)
+        .expect("set work_mem");
+
+    let lines = plan_lines(
+        eng.execute_sql("EXPLAIN ANALYZE SELECT id FROM ex_s ORDER BY v", &mut ctx)
+            .expect("explain analyze"),
+    );
+    let text = lines.join("\n");
+    let sort = lines
+        .iter()
+        .position(|l| l.trim_start().starts_with("Sort:"))
+        .unwrap_or_else(|| panic!("{text}"));
+    let actual = lines[sort + 1].trim_start();
+    assert!(actual.starts_with("Actual: rows=200 time="), "{text}");
+    assert!(actual.contains(" memory="), "{text}");
+    assert!(actual.contains(" spills="), "{text}");
+    assert!(
+        lines
+            .iter()
+            .any(|l| l.trim_start().starts_with("Actual: rows=200") && !l.contains("spills=")),
+        "{text}"
+    );
+
+    // Plain EXPLAIN runs nothing.
+    let lines = plan_lines(
+        eng.execute_sql("EXPLAIN SELECT id FROM ex_s ORDER BY v", &mut ctx)
+            .expect("explain"),
+    );
+    assert!(!lines.iter().any(|l| l.contains("Actual:")), "{lines:?}");
+}
+
+#[test]
+fn explain_format_json_and_dot_return_plan_graphs() {
+    let (_dir, eng) = open_engine();
+    let mut ctx = SessionContext::default();
+    eng.execute_sql("CREATE TABLE ex_g (id INTEGER)", &mut ctx)
+        .expect("ddl");
+    eng.execute_sql("INSERT INTO ex_g (id) VALUES (1), (2), (3)", &mut ctx)
+        .expect("insert");
+
+    let lines = plan_lines(
+        eng.execute_sql("EXPLAIN FORMAT JSON SELECT id FROM ex_g", &mut ctx)
+            .expect("explain json"),
+    );
+    assert_eq!(lines.len(), 1);
+    let doc: serde_json::Value = serde_json::from_str(&lines[0]).expect("json plan");
+    assert!(doc["actual_rows"].is_null());
+    assert!(doc["plan"]["operator"].is_string());
+    assert!(lines[0].contains("Table Scan"), "{}", lines[0]);
+
+    let lines = plan_lines(
+        eng.execute_sql("EXPLAIN ANALYZE FORMAT DOT SELECT id FROM ex_g", &mut ctx)
+            .expect("explain analyze dot"),
+    );
+    assert_eq!(lines.len(), 1);
+    assert!(lines[0].starts_with("digraph plan {"), "{}", lines[0]);
+    assert!(lines[0].contains("actual=3"), "{}", lines[0]);
+    assert!(lines[0].contains("n0 -> n1"), "{}", lines[0]);
+
+    let err = eng
+        .execute_sql("EXPLAIN FORMAT YAML SELECT id FROM ex_g", &mut ctx)
+        .expect_err("unknown format");
+    assert!(err.message.contains("YAML"), "{}", err.message);
+}
+
 #[test]
 fn explain_create_table_rejected_at_parse() {
     let (_dir, eng) = open_engine();
@@ -332,94 +332,4 @@ pub mod test_data {
     }
 }
 
-// / Utilities for measuring performance
-pub mod performance {
-    use std::time::{Duration, Instant};
-
-    // / Measures the execution time of a function
-    #[allow(dead_code)]
-    pub fn measure_time<F, R>(f: F) -> (R, Duration)
-    where
-        F: FnOnce() -> R,
-    {
-        let start = Instant::now();
-        let result = f();
-        let duration = start.elapsed();
-        (result, duration)
-    }
-
-    // / Measures the execution time of an asynchronous function
-    #[allow(dead_code)]
-    pub async fn measure_time_async<F, Fut, R>(f: F) -> (R, Duration)
-    where
-        F: FnOnce() -> Fut,
-        Fut: std::future::Future<Output = R>,
-    {
-        let start = Instant::now();
-        let result = f().await;
-        let duration = start.elapsed();
-        (result, duration)
-    }
-
-    // / Performance Statistics
-    #[derive(Debug, Clone)]
-    #[allow(dead_code)]
-    pub struct PerformanceStats {
-        pub min_duration: Duration,
-        pub max_duration: Duration,
-        pub avg_duration: Duration,
-        pub total_duration: Duration,
-        pub operation_count: usize,
-        pub operations_per_second: f64,
-    }
-
-    impl PerformanceStats {
-        // / Creates statistics from a list of dimensions
-        #[allow(dead_code)]
-        pub fn from_measurements(measurements: &[Duration]) -> Self {
-            if measurements.is_empty() {
-                return Self {
-                    min_duration: Duration::ZERO,
-                    max_duration: Duration::ZERO,
-                    avg_duration: Duration::ZERO,
-                    total_duration: Duration::ZERO,
-                    operation_count: 0,
-                    operations_per_second: 0.0,
-                };
-            }
-
-            let min_duration = *measurements.iter().min().unwrap();
-            let max_duration = *measurements.iter().max().unwrap();
-            let total_duration: Duration = measurements.iter().sum();
-            let avg_duration = total_duration / measurements.len() as u32;
-            let operation_count = measurements.len();
-            let operations_per_second = if total_duration.as_secs_f64() > 0.0 {
-                operation_count as f64 / total_duration.as_secs_f64()
-            } else {
-                0.0
-            };
-
-            Self {
-                min_duration,
-                max_duration,
-                avg_duration,
-                total_duration,
-                operation_count,
-                operations_per_second,
-            }
-        }
-
-        // / Displays statistics in a convenient format
-        #[allow(dead_code)]
-        pub fn print_summary(&self, test_name: &str) {
-            println!("=== {} ===", test_name);
-            println!("Operations: {}", self.operation_count);
-            println!("Total time: {:?}", self.total_duration);
-            println!("Minimum time: {:?}", self.min_duration);
-            println!("Maximum time: {:?}", self.max_duration);
-            println!("Average time: {:?}", self.avg_duration);
-            println!("Operations per second: {:.2}", self.operations_per_second);
-            println!();
-        }
-    }
-}
+// Throughput / latency measurement lives in `rustdb::bench` (`LatencyStats`, `rustdb bench`).
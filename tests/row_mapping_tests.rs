@@ -0,0 +1,117 @@
+//! `Connection::query_as` with `#[derive(FromRow)]` (`rustdb-derive`) and queries built with
+//! `rustdb::query_builder`, against an embedded [`Db`].
+
+use rustdb::network::engine::{engine_error_code, EngineOutput};
+use rustdb::query_builder::col;
+use rustdb::{Config, Db, FromRow, ToValue};
+use tempfile::TempDir;
+
+#[derive(Debug, PartialEq, FromRow)]
+struct User {
+    id: i64,
+    #[rustdb(rename = "user_name")]
+    name: String,
+    email: Option<String>,
+}
+
+#[test]
+fn query_as_maps_rows_into_derived_structs() {
+    let dir = TempDir::new().unwrap();
+    let db = Db::open(dir.path(), Config::fast()).unwrap();
+    let mut conn = db.connect();
+    conn.execute("CREATE TABLE users (id INTEGER, user_name VARCHAR(32), email VARCHAR(64))")
+        .unwrap();
+    let name = "o'hara";
+    conn.execute(&format!(
+        "INSERT INTO users (id, user_name, email) VALUES (1, {}, 'o@example.com')",
+        name.to_sql()
+    ))
+    .unwrap();
+    conn.execute("INSERT INTO users (id, user_name, email) VALUES (2, 'bo', NULL)")
+        .unwrap();
+
+    let mut users: Vec<User> = conn
+        .query_as("SELECT id, user_name, email FROM users")
+        .unwrap();
+    users.sort_by_key(|u| u.id);
+    assert_eq!(
+        users,
+        vec![
+            User {
+                id: 1,
+                name: "o'hara".to_string(),
+                email: Some("o@example.com".to_string()),
+            },
+            User {
+                id: 2,
+                name: "bo".to_string(),
+                email: None,
+            },
+        ]
+    );
+
+    #[derive(Debug, FromRow)]
+    struct Wrong {
+        #[allow(dead_code)]
+        id: bool,
+    }
+    let err = db.query_as::<Wrong>("SELECT id FROM users").unwrap_err();
+    assert_eq!(err.code, engine_error_code::RESULT_MAPPING);
+    assert!(err.message.contains("column id"), "{}", err.message);
+    let err = conn
+        .query_as::<User>("DELETE FROM users WHERE id = 2")
+        .unwrap_err();
+    assert_eq!(err.code, engine_error_code::RESULT_MAPPING);
+}
+
+#[test]
+fn fetch_runs_queries_from_the_builder() {
+    let dir = TempDir::new().unwrap();
+    let db = Db::open(dir.path(), Config::fast()).unwrap();
+    let mut conn = db.connect();
+    conn.execute("CREATE TABLE users (id INTEGER, user_name VARCHAR(32), email VARCHAR(64))")
+        .unwrap();
+    for (id, name) in [(1, "ann"), (2, "bob"), (3, "o'hara")] {
+        conn.execute(&format!(
+            "INSERT INTO users (id, user_name, email) VALUES ({id}, {}, NULL)",
+            name.to_sql()
+        ))
+        .unwrap();
+    }
+
+    let users: Vec<User> = conn
+        .fetch_as(
+            &db.table("users")
+                .filter(col("id").gt(1))
+                .select(&["id", "user_name", "email"])
+                .order_by_desc("id"),
+        )
+        .unwrap();
+    let names: Vec<&str> = users.iter().map(|u| u.name.as_str()).collect();
+    assert_eq!(names, ["o'hara", "bob"]);
+
+    let injected = db
+        .table("users")
+        .filter(col("user_name").eq("x' OR 'a'='a"));
+    assert_eq!(conn.fetch_as::<User>(&injected).unwrap(), vec![]);
+    let err = conn
+        .fetch(&db.table("users; DROP TABLE users"))
+        .unwrap_err();
+    assert!(
+        err.message.contains("invalid identifier"),
+        "{}",
+        err.message
+    );
+
+    let out = conn
+        .execute_params(
+            "UPDATE users SET email = ? WHERE user_name = ?",
+            &[&"a@b.c", &"o'hara"],
+        )
+        .unwrap();
+    assert_eq!(out, EngineOutput::ExecutionOk { rows_affected: 1 });
+    let err = conn
+        .execute_params("SELECT id FROM users WHERE id = ?; DROP TABLE users", &[&1])
+        .unwrap_err();
+    assert_eq!(err.code, engine_error_code::PROTOCOL);
+}
@@ -180,3 +180,113 @@ fn wal_replay_undo_uncommitted_mixed_dml_on_reopen() {
         assert_eq!(n2, 1);
     }
 }
+
+#[test]
+fn recovery_report_lists_rolled_back_transactions_and_corrupt_files() {
+    let _guard = ENV_LOCK.lock().unwrap();
+    std::env::remove_var("RUSTDB_DISABLE_WAL");
+    std::env::set_var("RUSTDB_FSYNC_COMMIT", "1");
+    let dir = TempDir::new().unwrap();
+
+    {
+        let engine = SqlEngine::open(dir.path().to_path_buf()).unwrap();
+        let report = engine.recovery_report();
+        assert!(report.is_clean());
+        assert!(report.transactions_rolled_back.is_empty());
+        let mut ctx = SessionContext::default();
+        exec(&engine, &mut ctx, "CREATE TABLE t (a INTEGER)");
+        exec(&engine, &mut ctx, "BEGIN TRANSACTION");
+        exec(&engine, &mut ctx, "INSERT INTO t (a) VALUES (1)");
+        // Crash: the session transaction is lost without COMMIT/ROLLBACK.
+        drop(ctx);
+    }
+    std::fs::write(dir.path().join("broken.tbl"), b"not a rustdb file").unwrap();
+
+    let engine = SqlEngine::open(dir.path().to_path_buf()).unwrap();
+    let report = engine.recovery_report();
+    assert_eq!(report.transactions_rolled_back.len(), 1);
+    assert!(report.wal_records_undone >= 1);
+    assert!(report.pages_repaired >= 1);
+    assert_eq!(report.corrupt_files.len(), 1);
+    assert!(report.corrupt_files[0].starts_with("broken.tbl:"));
+    assert!(report.catalog_issues.is_empty());
+    assert!(!report.is_clean());
+    let phases: Vec<&str> = report.phases.iter().map(|p| p.name).collect();
+    assert_eq!(
+        phases,
+        [
+            "file_check",
+            "wal_replay",
+            "catalog_check",
+            "constraint_rebuild",
+            "index_rebuild",
+            "foreign_tables"
+        ]
+    );
+
+    let mut ctx = SessionContext::default();
+    match engine
+        .execute_sql(
+            "SELECT phase, status FROM rustdb_startup_report WHERE status = 'warning'",
+            &mut ctx,
+        )
+        .unwrap()
+    {
+        rustdb::network::engine::EngineOutput::ResultSet { rows, .. } => {
+            assert_eq!(rows.len(), 1);
+            assert_eq!(rows[0][0], "Varchar(\"'file_check'\")");
+        }
+        _ => panic!("expected ResultSet"),
+    }
+    assert_eq!(row_count(&engine, &mut ctx, "SELECT a FROM t"), 0);
+}
+
+#[test]
+fn recovery_report_redoes_heap_files_in_parallel_and_reports_its_rate() {
+    let _guard = ENV_LOCK.lock().unwrap();
+    std::env::remove_var("RUSTDB_DISABLE_WAL");
+    std::env::set_var("RUSTDB_FSYNC_COMMIT", "1");
+    let dir = TempDir::new().unwrap();
+
+    {
+        let engine = SqlEngine::open(dir.path().to_path_buf()).unwrap();
+        let mut ctx = SessionContext::default();
+        for table in ["a", "b", "c"] {
+            exec(
+                &engine,
+                &mut ctx,
+                &format!("CREATE TABLE {table} (n INTEGER)"),
+            );
+            for n in 0..50 {
+                exec(
+                    &engine,
+                    &mut ctx,
+                    &format!("INSERT INTO {table} (n) VALUES ({n})"),
+                );
+            }
+        }
+    }
+
+    let engine = SqlEngine::open(dir.path().to_path_buf()).unwrap();
+    let report = engine.recovery_report();
+    assert!(report.wal_records_redone >= 150);
+    assert!((1..=3).contains(&report.wal_redo_workers));
+    assert!(report.wal_redo_records_per_sec > 0.0);
+    let replay = report
+        .phases
+        .iter()
+        .find(|p| p.name == "wal_replay")
+        .unwrap();
+    assert!(replay.detail.contains("records/s on"), "{}", replay.detail);
+    assert!(
+        replay.detail.contains("pages prefetched"),
+        "{}",
+        replay.detail
+    );
+
+    let mut ctx = SessionContext::default();
+    for table in ["a", "b", "c"] {
+        let n = row_count(&engine, &mut ctx, &format!("SELECT n FROM {table}"));
+        assert_eq!(n, 50);
+    }
+}